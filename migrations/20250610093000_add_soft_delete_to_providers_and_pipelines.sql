@@ -0,0 +1,15 @@
+-- Soft deletion for providers and pipelines.
+-- A row with deleted_at set is hidden from the management API and the gateway config
+-- until it is restored, and can be purged with DELETE ...?hard=true.
+
+ALTER TABLE hub_llmgateway_providers ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE hub_llmgateway_pipelines ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Names only need to be unique among live rows, so a deleted name can be reused.
+ALTER TABLE hub_llmgateway_providers DROP CONSTRAINT IF EXISTS hub_llmgateway_providers_name_key;
+CREATE UNIQUE INDEX idx_hub_llmgateway_providers_active_name
+    ON hub_llmgateway_providers(name) WHERE deleted_at IS NULL;
+
+ALTER TABLE hub_llmgateway_pipelines DROP CONSTRAINT IF EXISTS hub_llmgateway_pipelines_name_key;
+CREATE UNIQUE INDEX idx_hub_llmgateway_pipelines_active_name
+    ON hub_llmgateway_pipelines(name) WHERE deleted_at IS NULL;
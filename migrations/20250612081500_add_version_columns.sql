@@ -0,0 +1,6 @@
+-- Optimistic concurrency control: every successful update bumps the row's version, and
+-- updates must name the version they were based on.
+
+ALTER TABLE hub_llmgateway_providers ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE hub_llmgateway_model_definitions ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE hub_llmgateway_pipelines ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,18 @@
+-- API keys for the management API. Only a SHA-256 digest of each key is stored; the
+-- plaintext is returned once, when the key is created or rotated.
+
+CREATE TABLE IF NOT EXISTS hub_llmgateway_api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL,
+    key_hash VARCHAR(64) NOT NULL UNIQUE,   -- hex-encoded SHA-256 of the key
+    key_prefix VARCHAR(16) NOT NULL,        -- first characters of the key, to tell keys apart
+    role VARCHAR(20) NOT NULL CHECK (role IN ('admin', 'read_only')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE TRIGGER update_hub_llmgateway_api_keys_modtime
+    BEFORE UPDATE ON hub_llmgateway_api_keys
+    FOR EACH ROW
+    EXECUTE FUNCTION update_modified_column();
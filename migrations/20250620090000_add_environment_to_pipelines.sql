@@ -0,0 +1,9 @@
+-- Environment tags for pipelines (e.g. "staging", "prod").
+-- A gateway started with HUB_ENVIRONMENT loads the pipelines tagged with that environment
+-- plus untagged ones, so the same pipeline name can exist once per environment.
+
+ALTER TABLE hub_llmgateway_pipelines ADD COLUMN environment VARCHAR(64);
+
+DROP INDEX IF EXISTS idx_hub_llmgateway_pipelines_active_name;
+CREATE UNIQUE INDEX idx_hub_llmgateway_pipelines_active_name
+    ON hub_llmgateway_pipelines(name, COALESCE(environment, '')) WHERE deleted_at IS NULL;
@@ -0,0 +1,13 @@
+-- Snapshots of the management state behind each config the gateway applied, for rollbacks.
+-- config_state holds the live providers, model definitions and pipelines as stored here,
+-- so secrets stay as references and are never written out resolved.
+
+CREATE TABLE IF NOT EXISTS hub_llmgateway_config_snapshots (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    config_hash VARCHAR(16) NOT NULL,      -- hash of the GatewayConfig built from config_state
+    config_state JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_hub_llmgateway_config_snapshots_created_at
+    ON hub_llmgateway_config_snapshots(created_at DESC);
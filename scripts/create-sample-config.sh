@@ -16,6 +16,11 @@ NC='\033[0m' # No Color
 GATEWAY_URL="http://localhost:3000"
 MANAGEMENT_URL="http://localhost:8080"
 API_BASE="${MANAGEMENT_URL}/api/v1/management"
+# Bearer token for the management API, needed once API keys are configured
+CURL_AUTH=()
+if [ -n "${MANAGEMENT_API_KEY}" ]; then
+    CURL_AUTH=(-H "Authorization: Bearer ${MANAGEMENT_API_KEY}")
+fi
 
 echo -e "${BLUE}🔧 Creating Sample Configuration for EE Gateway${NC}"
 echo "=============================================="
@@ -32,7 +37,7 @@ echo -e "${GREEN}   ✅ Gateway is running${NC}"
 
 # Create OpenAI Provider
 echo -e "${BLUE}2. Creating OpenAI provider...${NC}"
-OPENAI_PROVIDER_RESPONSE=$(curl -s -X POST "${API_BASE}/providers" \
+OPENAI_PROVIDER_RESPONSE=$(curl -s "${CURL_AUTH[@]}" -X POST "${API_BASE}/providers" \
   -H "Content-Type: application/json" \
   -d '{
     "name": "OpenAI Main",
@@ -57,7 +62,7 @@ echo -e "${GREEN}   ✅ OpenAI provider created (ID: $OPENAI_PROVIDER_ID)${NC}"
 
 # Create Azure Provider
 echo -e "${BLUE}3. Creating Azure provider...${NC}"
-AZURE_PROVIDER_RESPONSE=$(curl -s -X POST "${API_BASE}/providers" \
+AZURE_PROVIDER_RESPONSE=$(curl -s "${CURL_AUTH[@]}" -X POST "${API_BASE}/providers" \
   -H "Content-Type: application/json" \
   -d '{
     "name": "Azure OpenAI",
@@ -85,7 +90,7 @@ echo -e "${GREEN}   ✅ Azure provider created (ID: $AZURE_PROVIDER_ID)${NC}"
 echo -e "${BLUE}4. Creating model definitions...${NC}"
 
 # GPT-4 model
-GPT4_1_MODEL_RESPONSE=$(curl -s -X POST "${API_BASE}/model-definitions" \
+GPT4_1_MODEL_RESPONSE=$(curl -s "${CURL_AUTH[@]}" -X POST "${API_BASE}/model-definitions" \
   -H "Content-Type: application/json" \
   -d "{
     \"key\": \"gpt-4.1\",
@@ -103,7 +108,7 @@ fi
 echo -e "${GREEN}   ✅ GPT-4.1 model created${NC}"
 
 # GPT-3.5-turbo model
-GPT35_MODEL_RESPONSE=$(curl -s -X POST "${API_BASE}/model-definitions" \
+GPT35_MODEL_RESPONSE=$(curl -s "${CURL_AUTH[@]}" -X POST "${API_BASE}/model-definitions" \
   -H "Content-Type: application/json" \
   -d "{
     \"key\": \"gpt-3.5-turbo\",
@@ -121,7 +126,7 @@ fi
 echo -e "${GREEN}   ✅ GPT-3.5-turbo model created${NC}"
 
 # Azure GPT-4 model
-AZURE_GPT4_MODEL_RESPONSE=$(curl -s -X POST "${API_BASE}/model-definitions" \
+AZURE_GPT4_MODEL_RESPONSE=$(curl -s "${CURL_AUTH[@]}" -X POST "${API_BASE}/model-definitions" \
   -H "Content-Type: application/json" \
   -d "{
     \"key\": \"gpt-4o-azure\",
@@ -142,7 +147,7 @@ echo -e "${GREEN}   ✅ Azure GPT-4 model created${NC}"
 echo -e "${BLUE}5. Creating pipelines...${NC}"
 
 # Chat pipeline with multiple models
-CHAT_PIPELINE_RESPONSE=$(curl -s -X POST "${API_BASE}/pipelines" \
+CHAT_PIPELINE_RESPONSE=$(curl -s "${CURL_AUTH[@]}" -X POST "${API_BASE}/pipelines" \
   -H "Content-Type: application/json" \
   -d '{
     "name": "default",
@@ -195,7 +200,7 @@ fi
 echo -e "${GREEN}   ✅ Chat pipeline created${NC}"
 
 # Simple pipeline with single model
-SIMPLE_PIPELINE_RESPONSE=$(curl -s -X POST "${API_BASE}/pipelines" \
+SIMPLE_PIPELINE_RESPONSE=$(curl -s "${CURL_AUTH[@]}" -X POST "${API_BASE}/pipelines" \
   -H "Content-Type: application/json" \
   -d '{
     "name": "simple-pipeline",
@@ -265,16 +270,19 @@ echo ""
 echo -e "${BLUE}   # Option A: Literal secret (for testing)${NC}"
 echo -e "   ${BLUE}curl -X PUT ${API_BASE}/providers/$OPENAI_PROVIDER_ID \\${NC}"
 echo -e "   ${BLUE}     -H \"Content-Type: application/json\" \\${NC}"
+echo -e "   ${BLUE}     -H \"If-Match: 1\" \\${NC}"
 echo -e "   ${BLUE}     -d '{\"config\": {\"api_key\": {\"type\": \"literal\", \"value\": \"your-real-openai-key\"}}}'${NC}"
 echo ""
 echo -e "${BLUE}   # Option B: Environment variable (recommended for production)${NC}"
 echo -e "   ${BLUE}curl -X PUT ${API_BASE}/providers/$OPENAI_PROVIDER_ID \\${NC}"
 echo -e "   ${BLUE}     -H \"Content-Type: application/json\" \\${NC}"
+echo -e "   ${BLUE}     -H \"If-Match: 1\" \\${NC}"
 echo -e "   ${BLUE}     -d '{\"config\": {\"api_key\": {\"type\": \"environment\", \"variable_name\": \"OPENAI_API_KEY\"}}}'${NC}"
 echo ""
 echo -e "${BLUE}   # Option C: Kubernetes secret (for K8s deployments)${NC}"
 echo -e "   ${BLUE}curl -X PUT ${API_BASE}/providers/$OPENAI_PROVIDER_ID \\${NC}"
 echo -e "   ${BLUE}     -H \"Content-Type: application/json\" \\${NC}"
+echo -e "   ${BLUE}     -H \"If-Match: 1\" \\${NC}"
 echo -e "   ${BLUE}     -d '{\"config\": {\"api_key\": {\"type\": \"kubernetes\", \"secret_name\": \"openai-creds\", \"key\": \"api-key\"}}}'${NC}"
 echo ""
 echo "3. Test the configuration:"
@@ -17,6 +17,11 @@ NC='\033[0m' # No Color
 GATEWAY_URL="http://localhost:3000"
 MANAGEMENT_URL="http://localhost:8080"
 API_BASE="${MANAGEMENT_URL}/api/v1/management"
+# Bearer token for the management API, needed once API keys are configured
+CURL_AUTH=()
+if [ -n "${MANAGEMENT_API_KEY}" ]; then
+    CURL_AUTH=(-H "Authorization: Bearer ${MANAGEMENT_API_KEY}")
+fi
 
 echo -e "${BLUE}🚀 Adding GPT-4.1 Mini Model to EE Gateway${NC}"
 echo "=============================================="
@@ -33,7 +38,7 @@ echo -e "${GREEN}   ✅ Gateway is running${NC}"
 
 # Get existing OpenAI provider
 echo -e "${BLUE}2. Finding OpenAI provider...${NC}"
-PROVIDERS_RESPONSE=$(curl -s "${API_BASE}/providers")
+PROVIDERS_RESPONSE=$(curl -s "${CURL_AUTH[@]}" "${API_BASE}/providers")
 OPENAI_PROVIDER_ID=$(echo "$PROVIDERS_RESPONSE" | grep -o '"id":"[^"]*","name":"OpenAI Main"' | cut -d'"' -f4)
 
 if [ -z "$OPENAI_PROVIDER_ID" ]; then
@@ -45,7 +50,7 @@ echo -e "${GREEN}   ✅ OpenAI provider found (ID: $OPENAI_PROVIDER_ID)${NC}"
 
 # Check if GPT-4.1 mini model already exists
 echo -e "${BLUE}3. Checking if GPT-4.1 mini model already exists...${NC}"
-MODELS_RESPONSE=$(curl -s "${API_BASE}/model-definitions")
+MODELS_RESPONSE=$(curl -s "${CURL_AUTH[@]}" "${API_BASE}/model-definitions")
 if echo "$MODELS_RESPONSE" | grep -q '"key":"gpt-4.1-mini"'; then
     echo -e "${YELLOW}   ⚠️  GPT-4.1 mini model already exists, skipping creation${NC}"
     GPT4_MINI_EXISTS=true
@@ -56,7 +61,7 @@ fi
 # Create GPT-4.1 mini model if it doesn't exist
 if [ "$GPT4_MINI_EXISTS" = false ]; then
     echo -e "${BLUE}4. Creating GPT-4.1 mini model...${NC}"
-    GPT4_MINI_MODEL_RESPONSE=$(curl -s -X POST "${API_BASE}/model-definitions" \
+    GPT4_MINI_MODEL_RESPONSE=$(curl -s "${CURL_AUTH[@]}" -X POST "${API_BASE}/model-definitions" \
       -H "Content-Type: application/json" \
       -d "{
         \"key\": \"gpt-4.1-mini\",
@@ -78,7 +83,7 @@ fi
 
 # Get the default pipeline
 echo -e "${BLUE}5. Finding default pipeline...${NC}"
-PIPELINES_RESPONSE=$(curl -s "${API_BASE}/pipelines")
+PIPELINES_RESPONSE=$(curl -s "${CURL_AUTH[@]}" "${API_BASE}/pipelines")
 DEFAULT_PIPELINE_ID=$(echo "$PIPELINES_RESPONSE" | grep -o '"id":"[^"]*","name":"default"' | cut -d'"' -f4)
 
 if [ -z "$DEFAULT_PIPELINE_ID" ]; then
@@ -90,7 +95,8 @@ echo -e "${GREEN}   ✅ Default pipeline found (ID: $DEFAULT_PIPELINE_ID)${NC}"
 
 # Get current pipeline configuration
 echo -e "${BLUE}6. Getting current pipeline configuration...${NC}"
-CURRENT_PIPELINE=$(curl -s "${API_BASE}/pipelines/${DEFAULT_PIPELINE_ID}")
+CURRENT_PIPELINE=$(curl -s "${CURL_AUTH[@]}" "${API_BASE}/pipelines/${DEFAULT_PIPELINE_ID}")
+PIPELINE_VERSION=$(echo "$CURRENT_PIPELINE" | grep -o '"version":[0-9]*' | cut -d: -f2)
 
 # Check if GPT-4.1 mini is already in the pipeline
 if echo "$CURRENT_PIPELINE" | grep -q '"key":"gpt-4.1-mini"'; then
@@ -101,8 +107,9 @@ fi
 
 # Update pipeline to include GPT-4.1 mini
 echo -e "${BLUE}7. Updating default pipeline to include GPT-4.1 mini...${NC}"
-UPDATE_PIPELINE_RESPONSE=$(curl -s -X PUT "${API_BASE}/pipelines/${DEFAULT_PIPELINE_ID}" \
+UPDATE_PIPELINE_RESPONSE=$(curl -s "${CURL_AUTH[@]}" -X PUT "${API_BASE}/pipelines/${DEFAULT_PIPELINE_ID}" \
   -H "Content-Type: application/json" \
+  -H "If-Match: ${PIPELINE_VERSION}" \
   -d '{
     "name": "default",
     "pipeline_type": "Chat",
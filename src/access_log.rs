@@ -0,0 +1,178 @@
+//! Access logs and request spans for the gateway and management servers, each with its own
+//! level, so management traffic such as UI polling doesn't drown out the gateway's.
+
+use crate::types::{General, LogLevel};
+use axum::http::{Request, Response};
+use std::time::Duration;
+use tower_http::classify::{ServerErrorsAsFailures, ServerErrorsFailureClass, SharedClassifier};
+use tower_http::trace::{
+    DefaultOnBodyChunk, DefaultOnEos, MakeSpan, OnFailure, OnRequest, OnResponse, TraceLayer,
+};
+use tracing::{Level, Span};
+
+/// Parent target of every access log event and request span. The process's log filter
+/// lets it through at any level, since [`AccessLog`] applies the servers' own levels.
+pub const ACCESS_LOG_TARGET: &str = "hub::access";
+const GATEWAY_TARGET: &str = "hub::access::gateway";
+const MANAGEMENT_TARGET: &str = "hub::access::management";
+const HEALTH_CHECK_PATH: &str = "/health";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Server {
+    Gateway,
+    Management,
+}
+
+impl Server {
+    /// The `server` field of its spans.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Server::Gateway => "gateway",
+            Server::Management => "management",
+        }
+    }
+
+    fn level_env_var(self) -> &'static str {
+        match self {
+            Server::Gateway => "GATEWAY_LOG_LEVEL",
+            Server::Management => "MANAGEMENT_LOG_LEVEL",
+        }
+    }
+}
+
+/// Emits a `tracing` macro with `server`'s target, which has to be a constant.
+macro_rules! with_target {
+    ($server:expr, $macro:ident!($($arg:tt)*)) => {
+        match $server {
+            Server::Gateway => tracing::$macro!(target: GATEWAY_TARGET, $($arg)*),
+            Server::Management => tracing::$macro!(target: MANAGEMENT_TARGET, $($arg)*),
+        }
+    };
+}
+
+/// One server's access log settings, used as every hook of its [`TraceLayer`].
+#[derive(Debug, Clone, Copy)]
+pub struct AccessLog {
+    server: Server,
+    level: Level,
+    include_headers: bool,
+    exclude_health_checks: bool,
+}
+
+pub type AccessLogLayer = TraceLayer<
+    SharedClassifier<ServerErrorsAsFailures>,
+    AccessLog,
+    AccessLog,
+    AccessLog,
+    DefaultOnBodyChunk,
+    DefaultOnEos,
+    AccessLog,
+>;
+
+impl AccessLog {
+    /// `server`'s settings from `general.logging`, with `GATEWAY_LOG_LEVEL` or
+    /// `MANAGEMENT_LOG_LEVEL` overriding the level. Without either the level is
+    /// `default_level`.
+    pub fn new(server: Server, general: Option<&General>, default_level: Level) -> Self {
+        let config = general
+            .and_then(|g| g.logging.as_ref())
+            .map(|logging| match server {
+                Server::Gateway => logging.gateway.clone(),
+                Server::Management => logging.management.clone(),
+            })
+            .unwrap_or_default();
+        let level = std::env::var(server.level_env_var())
+            .ok()
+            .and_then(|level| level.parse::<Level>().ok())
+            .or(config.level.map(Level::from))
+            .unwrap_or(default_level);
+        Self {
+            server,
+            level,
+            include_headers: config.include_headers,
+            exclude_health_checks: config.exclude_health_checks,
+        }
+    }
+
+    pub fn layer(self) -> AccessLogLayer {
+        TraceLayer::new_for_http()
+            .make_span_with(self)
+            .on_request(self)
+            .on_response(self)
+            .on_failure(self)
+    }
+}
+
+impl From<LogLevel> for Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Level::ERROR,
+            LogLevel::Warn => Level::WARN,
+            LogLevel::Info => Level::INFO,
+            LogLevel::Debug => Level::DEBUG,
+            LogLevel::Trace => Level::TRACE,
+        }
+    }
+}
+
+impl<B> MakeSpan<B> for AccessLog {
+    /// Excluded health checks get no span, which the other hooks take as not to log them.
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        if self.exclude_health_checks && request.uri().path() == HEALTH_CHECK_PATH {
+            return Span::none();
+        }
+        let span = with_target!(
+            self.server,
+            info_span!(
+                "request",
+                server = self.server.as_str(),
+                method = %request.method(),
+                uri = %request.uri(),
+                version = ?request.version(),
+                headers = tracing::field::Empty,
+            )
+        );
+        if self.include_headers {
+            span.record("headers", tracing::field::debug(request.headers()));
+        }
+        span
+    }
+}
+
+impl<B> OnRequest<B> for AccessLog {
+    fn on_request(&mut self, _request: &Request<B>, span: &Span) {
+        if self.level >= Level::DEBUG && !span.is_none() {
+            with_target!(self.server, debug!("started processing request"));
+        }
+    }
+}
+
+impl<B> OnResponse<B> for AccessLog {
+    fn on_response(self, response: &Response<B>, latency: Duration, span: &Span) {
+        if self.level >= Level::INFO && !span.is_none() {
+            with_target!(
+                self.server,
+                info!(
+                    status = response.status().as_u16(),
+                    latency_ms = latency.as_millis() as u64,
+                    "finished processing request"
+                )
+            );
+        }
+    }
+}
+
+impl OnFailure<ServerErrorsFailureClass> for AccessLog {
+    fn on_failure(&mut self, failure: ServerErrorsFailureClass, latency: Duration, span: &Span) {
+        if !span.is_none() {
+            with_target!(
+                self.server,
+                error!(
+                    classification = %failure,
+                    latency_ms = latency.as_millis() as u64,
+                    "response failed"
+                )
+            );
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use crate::metrics::{counter, gauge};
+use crate::models::chat::PRIORITY_HEADER;
+use crate::state::AppState;
+use crate::types::{General, RequestPriority};
+use axum::Json;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+pub const QUEUE_DEPTH_METRIC: &str = "hub_admission_queue_depth";
+pub const IN_FLIGHT_METRIC: &str = "hub_admission_in_flight";
+pub const REJECTED_METRIC: &str = "hub_admission_rejected_total";
+/// Seconds a rejected client is told to wait before retrying.
+const RETRY_AFTER_SECONDS: &str = "1";
+
+/// Returned when both the in-flight slots and the waiting queue are full.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overloaded;
+
+impl IntoResponse for Overloaded {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "error": {
+                "type": "overloaded",
+                "message": "The gateway is at capacity, retry shortly",
+            }
+        });
+        let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response();
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from_static(RETRY_AFTER_SECONDS),
+        );
+        response
+    }
+}
+
+#[derive(Default)]
+struct Queues {
+    in_flight: usize,
+    high: VecDeque<oneshot::Sender<Permit>>,
+    normal: VecDeque<oneshot::Sender<Permit>>,
+}
+
+impl Queues {
+    fn depth(&self) -> usize {
+        self.high.len() + self.normal.len()
+    }
+}
+
+/// Global in-flight limit with a bounded two-level waiting queue.
+///
+/// High priority waiters are always admitted before normal ones; within a level
+/// requests are admitted in arrival order.
+pub struct AdmissionController {
+    max_in_flight: usize,
+    max_queued: usize,
+    queues: Mutex<Queues>,
+}
+
+/// An admitted request's slot, handed to the next waiter when dropped.
+pub struct Permit {
+    controller: Option<Arc<AdmissionController>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(controller) = self.controller.take() {
+            controller.release();
+        }
+    }
+}
+
+impl AdmissionController {
+    pub fn new(max_in_flight: usize, max_queued: usize) -> Arc<Self> {
+        Arc::new(Self {
+            max_in_flight,
+            max_queued,
+            queues: Mutex::new(Queues::default()),
+        })
+    }
+
+    /// Builds the controller for `general.max_in_flight_requests`, if set.
+    pub fn from_general(general: &General) -> Option<Arc<Self>> {
+        let max_in_flight = general.max_in_flight_requests? as usize;
+        let max_queued = general
+            .max_queued_requests
+            .map_or(max_in_flight, |queued| queued as usize);
+        Some(Self::new(max_in_flight, max_queued))
+    }
+
+    /// Waits for a slot, or fails straight away when the queue is full.
+    pub async fn acquire(self: &Arc<Self>, high_priority: bool) -> Result<Permit, Overloaded> {
+        let waiter = {
+            let mut queues = self.queues.lock().unwrap();
+            if queues.in_flight < self.max_in_flight && queues.depth() == 0 {
+                queues.in_flight += 1;
+                self.record(&queues);
+                return Ok(Permit {
+                    controller: Some(self.clone()),
+                });
+            }
+            if queues.depth() >= self.max_queued {
+                counter!(REJECTED_METRIC).increment(1);
+                return Err(Overloaded);
+            }
+            let (sender, receiver) = oneshot::channel();
+            if high_priority {
+                queues.high.push_back(sender);
+            } else {
+                queues.normal.push_back(sender);
+            }
+            self.record(&queues);
+            receiver
+        };
+        // A sender is only dropped without a permit if the controller goes away.
+        waiter.await.map_err(|_| Overloaded)
+    }
+
+    /// Hands the released slot to the next live waiter, or frees it.
+    fn release(self: Arc<Self>) {
+        let mut queues = self.queues.lock().unwrap();
+        while let Some(waiter) = queues
+            .high
+            .pop_front()
+            .or_else(|| queues.normal.pop_front())
+        {
+            let permit = Permit {
+                controller: Some(self.clone()),
+            };
+            match waiter.send(permit) {
+                Ok(()) => {
+                    self.record(&queues);
+                    return;
+                }
+                // The waiter gave up; disarm the permit so it doesn't release again.
+                Err(mut permit) => permit.controller = None,
+            }
+        }
+        queues.in_flight -= 1;
+        self.record(&queues);
+    }
+
+    fn record(&self, queues: &Queues) {
+        gauge!(QUEUE_DEPTH_METRIC).set(queues.depth() as f64);
+        gauge!(IN_FLIGHT_METRIC).set(queues.in_flight as f64);
+    }
+}
+
+/// State for [`admit`]: the controller plus the live config, for pipeline priorities.
+#[derive(Clone)]
+pub struct Admission {
+    pub controller: Arc<AdmissionController>,
+    pub state: Arc<AppState>,
+}
+
+/// Requests sending `x-hub-priority: high`, or routed to a pipeline whose `priority`
+/// plugin defaults to high, jump the queue.
+fn is_high_priority(request: &Request, state: &AppState) -> bool {
+    let priority = match request.headers().get(PRIORITY_HEADER) {
+        Some(value) => value.to_str().ok().and_then(|value| value.parse().ok()),
+        None => state.pipeline_default_priority(request.headers()),
+    };
+    priority == Some(RequestPriority::High)
+}
+
+/// Middleware holding each request until it is admitted, or rejecting it with 503.
+pub async fn admit(State(admission): State<Admission>, request: Request, next: Next) -> Response {
+    let high_priority = is_high_priority(&request, &admission.state);
+    let permit = match admission.controller.acquire(high_priority).await {
+        Ok(permit) => permit,
+        Err(overloaded) => return overloaded.into_response(),
+    };
+    let response = next.run(request).await;
+
+    let streaming = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"text/event-stream"));
+    if !streaming {
+        return response;
+    }
+    // Streams keep their slot until the last event is sent.
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _permit = &permit;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn queued(
+        controller: &Arc<AdmissionController>,
+        high_priority: bool,
+    ) -> tokio::task::JoinHandle<Permit> {
+        let controller = controller.clone();
+        let handle = tokio::spawn(async move { controller.acquire(high_priority).await.unwrap() });
+        // Let the task reach the queue before the next one is spawned.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        handle
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_waiters_are_admitted_first() {
+        let controller = AdmissionController::new(1, 4);
+        let running = controller.acquire(false).await.unwrap();
+        let normal = queued(&controller, false).await;
+        let high = queued(&controller, true).await;
+        assert_eq!(controller.queues.lock().unwrap().depth(), 2);
+
+        drop(running);
+        let high_permit = high.await.unwrap();
+        assert!(!normal.is_finished());
+
+        drop(high_permit);
+        normal.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_rejects_immediately() {
+        let controller = AdmissionController::new(1, 1);
+        let _running = controller.acquire(false).await.unwrap();
+        let _waiting = queued(&controller, false).await;
+
+        assert_eq!(controller.acquire(true).await.err(), Some(Overloaded));
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter_does_not_leak_slot() {
+        let controller = AdmissionController::new(1, 2);
+        let running = controller.acquire(false).await.unwrap();
+        let abandoned = queued(&controller, false).await;
+        abandoned.abort();
+        let _ = abandoned.await;
+
+        drop(running);
+        assert_eq!(controller.queues.lock().unwrap().in_flight, 0);
+        let _next = controller.acquire(false).await.unwrap();
+        assert_eq!(controller.queues.lock().unwrap().in_flight, 1);
+    }
+}
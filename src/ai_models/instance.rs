@@ -1,8 +1,16 @@
+use crate::ai_models::params::{
+    apply_chat_defaults, apply_completion_defaults, ignores_unsupported_params,
+    inlines_message_names,
+};
 use crate::config::models::ModelConfig;
 use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
 use crate::models::completion::{CompletionRequest, CompletionResponse};
+use crate::models::content::ChatCompletionMessage;
 use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
+use crate::providers::capabilities::Capabilities;
 use crate::providers::provider::Provider;
+use crate::providers::upstream::UpstreamRequest;
+use crate::types::ProviderType;
 use axum::http::StatusCode;
 use std::sync::Arc;
 
@@ -14,28 +22,177 @@ pub struct ModelInstance {
 }
 
 impl ModelInstance {
+    pub fn capabilities(&self) -> Capabilities {
+        self.provider.capabilities(&self.config)
+    }
+
+    /// Chat request fields the provider can't honour. Empty when the model is configured with
+    /// `ignore_unsupported_params`.
+    pub fn unsupported_chat_params(&self, payload: &ChatCompletionRequest) -> Vec<&'static str> {
+        if ignores_unsupported_params(&self.config.params) {
+            return Vec::new();
+        }
+        self.capabilities().unsupported_chat_params(payload)
+    }
+
+    /// Types of the chat request's built-in tools the provider can't run. Empty when the model
+    /// is configured with `ignore_unsupported_params`; they are then left out of the request.
+    pub fn unsupported_builtin_tools<'a>(
+        &self,
+        payload: &'a ChatCompletionRequest,
+    ) -> Vec<&'a str> {
+        if ignores_unsupported_params(&self.config.params) {
+            return Vec::new();
+        }
+        self.capabilities().unsupported_builtin_tools(payload)
+    }
+
+    /// Completion request fields the provider can't honour. Empty when the model is configured
+    /// with `ignore_unsupported_params`.
+    pub fn unsupported_completion_params(&self, payload: &CompletionRequest) -> Vec<&'static str> {
+        if ignores_unsupported_params(&self.config.params) {
+            return Vec::new();
+        }
+        self.capabilities().unsupported_completion_params(payload)
+    }
+
     pub async fn chat_completions(
         &self,
-        mut payload: ChatCompletionRequest,
+        payload: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse, StatusCode> {
-        payload.model = self.model_type.clone();
+        let payload = self.prepare_chat_payload(payload);
         self.provider.chat_completions(payload, &self.config).await
     }
 
     pub async fn completions(
         &self,
-        mut payload: CompletionRequest,
+        payload: CompletionRequest,
     ) -> Result<CompletionResponse, StatusCode> {
-        payload.model = self.model_type.clone();
-
+        let payload = self.prepare_completion_payload(payload)?;
         self.provider.completions(payload, &self.config).await
     }
 
     pub async fn embeddings(
         &self,
-        mut payload: EmbeddingsRequest,
+        payload: EmbeddingsRequest,
     ) -> Result<EmbeddingsResponse, StatusCode> {
+        let payload = self.prepare_embeddings_payload(payload)?;
+        let wants_base64 = payload.wants_base64();
+        let mut response = self.provider.embeddings(payload, &self.config).await?;
+        // Only OpenAI and Azure encode natively; keep base64-requesting SDKs working elsewhere.
+        if wants_base64 {
+            response.encode_base64();
+        }
+        Ok(response)
+    }
+
+    /// The upstream request `chat_completions` would send for `payload`.
+    pub async fn build_chat_request(
+        &self,
+        payload: ChatCompletionRequest,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let payload = self.prepare_chat_payload(payload);
+        self.provider
+            .build_chat_request(&payload, &self.config)
+            .await
+    }
+
+    /// Input tokens of `payload` as counted by the provider's tokenizer.
+    pub async fn count_tokens(&self, payload: ChatCompletionRequest) -> Result<u32, StatusCode> {
+        let payload = self.prepare_chat_payload(payload);
+        self.provider.count_tokens(&payload, &self.config).await
+    }
+
+    /// The upstream request `completions` would send for `payload`.
+    pub async fn build_completion_request(
+        &self,
+        payload: CompletionRequest,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let payload = self.prepare_completion_payload(payload)?;
+        self.provider
+            .build_completion_request(&payload, &self.config)
+            .await
+    }
+
+    /// The upstream request `embeddings` would send for `payload`.
+    pub async fn build_embeddings_request(
+        &self,
+        payload: EmbeddingsRequest,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let payload = self.prepare_embeddings_payload(payload)?;
+        self.provider
+            .build_embeddings_request(&payload, &self.config)
+            .await
+    }
+
+    fn prepare_chat_payload(&self, mut payload: ChatCompletionRequest) -> ChatCompletionRequest {
         payload.model = self.model_type.clone();
-        self.provider.embeddings(payload, &self.config).await
+
+        // Stored completions are an OpenAI feature; other providers reject unknown fields.
+        let supports_stored_completions = matches!(
+            self.provider.r#type(),
+            ProviderType::OpenAI | ProviderType::Azure
+        );
+        if !supports_stored_completions && (payload.store.is_some() || payload.metadata.is_some()) {
+            tracing::debug!(
+                "Dropping store/metadata for model '{}': not supported by provider type {}",
+                self.name,
+                self.provider.r#type()
+            );
+            payload.store = None;
+            payload.metadata = None;
+        }
+        if !self.capabilities().supports_builtin_tools {
+            payload.web_search_options = None;
+            if let Some(tools) = &mut payload.tools {
+                tools.retain(|tool| tool.function().is_some());
+                if tools.is_empty() {
+                    payload.tools = None;
+                }
+            }
+        }
+        // Only OpenAI-compatible APIs have a `name` field on messages.
+        let supports_message_names = matches!(
+            self.provider.r#type(),
+            ProviderType::OpenAI | ProviderType::Azure
+        );
+        if !supports_message_names && inlines_message_names(&self.config.params) {
+            payload
+                .messages
+                .iter_mut()
+                .for_each(ChatCompletionMessage::inline_name);
+        }
+        apply_chat_defaults(&self.config.params, &mut payload);
+        payload
+    }
+
+    fn prepare_completion_payload(
+        &self,
+        mut payload: CompletionRequest,
+    ) -> Result<CompletionRequest, StatusCode> {
+        if !self.capabilities().supports_completions {
+            tracing::error!("Model '{}' does not support completions", self.name);
+            return Err(StatusCode::NOT_IMPLEMENTED);
+        }
+        payload.model = self.model_type.clone();
+        apply_completion_defaults(&self.config.params, &mut payload);
+        Ok(payload)
+    }
+
+    fn prepare_embeddings_payload(
+        &self,
+        mut payload: EmbeddingsRequest,
+    ) -> Result<EmbeddingsRequest, StatusCode> {
+        if !self.capabilities().supports_embeddings {
+            tracing::error!("Model '{}' does not support embeddings", self.name);
+            return Err(StatusCode::NOT_IMPLEMENTED);
+        }
+        payload.model = self.model_type.clone();
+
+        if payload.dimensions == Some(0) {
+            tracing::error!("Invalid embeddings request: dimensions must be positive");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok(payload)
     }
 }
@@ -0,0 +1,268 @@
+use crate::models::chat::ChatCompletionRequest;
+use crate::models::completion::CompletionRequest;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Default `temperature` applied when a request doesn't set one.
+pub const TEMPERATURE_PARAM: &str = "temperature";
+/// Default `top_p` applied when a request doesn't set one.
+pub const TOP_P_PARAM: &str = "top_p";
+/// Default `max_tokens` applied when a request doesn't set one.
+pub const MAX_TOKENS_PARAM: &str = "max_tokens";
+/// `max_tokens` sent to providers that require one, such as Anthropic, when neither the
+/// request nor `max_tokens` sets it. Defaults to the model's maximum output.
+pub const DEFAULT_MAX_TOKENS_PARAM: &str = "default_max_tokens";
+/// When `true`, requests using features the provider lacks are sent anyway instead of
+/// being rejected.
+pub const IGNORE_UNSUPPORTED_PARAMS_PARAM: &str = "ignore_unsupported_params";
+/// When `false`, message `name`s are dropped instead of prefixed into the content for
+/// providers without the field.
+pub const INLINE_MESSAGE_NAMES_PARAM: &str = "inline_message_names";
+/// Size of the model's context window, in tokens.
+pub const CONTEXT_WINDOW_PARAM: &str = "context_window";
+/// When `true`, requests whose prompt plus `max_tokens` can't fit in `context_window` are
+/// rejected before reaching the provider.
+pub const CONTEXT_WINDOW_CHECK_PARAM: &str = "context_window_check";
+/// When `true`, JSON-mode chat responses are repaired: code fences and commentary around the
+/// JSON are stripped, and the result is checked against the request's schema.
+pub const JSON_REPAIR_PARAM: &str = "json_repair";
+/// Longest a realtime websocket session may stay open, in seconds.
+pub const REALTIME_MAX_SESSION_SECONDS_PARAM: &str = "realtime_max_session_seconds";
+/// OpenAI's own limit on realtime sessions.
+const DEFAULT_REALTIME_MAX_SESSION: Duration = Duration::from_secs(30 * 60);
+
+/// Model params with a meaning to the gateway or a provider. Other keys are passed through
+/// untouched.
+pub const RESERVED_MODEL_PARAMS: &[&str] = &[
+    "deployment",
+    "model_provider",
+    CONTEXT_WINDOW_PARAM,
+    "input_cost_per_1k_tokens",
+    "output_cost_per_1k_tokens",
+    "cached_input_cost_per_1k_tokens",
+    "cache_write_cost_per_1k_tokens",
+    TEMPERATURE_PARAM,
+    TOP_P_PARAM,
+    MAX_TOKENS_PARAM,
+    DEFAULT_MAX_TOKENS_PARAM,
+    IGNORE_UNSUPPORTED_PARAMS_PARAM,
+    INLINE_MESSAGE_NAMES_PARAM,
+    REALTIME_MAX_SESSION_SECONDS_PARAM,
+    CONTEXT_WINDOW_CHECK_PARAM,
+    JSON_REPAIR_PARAM,
+    "response",
+    "tool_arguments",
+];
+
+/// Checks that model `config_details` can be flattened into string params: a JSON object
+/// whose values are strings, numbers, booleans or null.
+pub fn validate_config_details(config_details: &Value) -> Result<(), String> {
+    let map = match config_details {
+        Value::Null => return Ok(()),
+        Value::Object(map) => map,
+        _ => return Err("config_details must be a JSON object".to_string()),
+    };
+    for (key, value) in map {
+        if matches!(value, Value::Object(_) | Value::Array(_)) {
+            return Err(format!(
+                "config_details.{key} must be a string, number or boolean"
+            ));
+        }
+    }
+    let params: HashMap<String, String> = map
+        .iter()
+        .filter_map(|(key, value)| Some((key.clone(), config_value_to_param(value)?)))
+        .collect();
+    validate_default_params(&params)
+}
+
+/// Stringifies a scalar `config_details` value. Nulls and nested values have no param form.
+pub fn config_value_to_param(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+fn parse_param<T: FromStr>(params: &HashMap<String, String>, key: &str) -> Option<T> {
+    params.get(key).and_then(|value| value.trim().parse().ok())
+}
+
+/// Checks that default sampling params parse and are in range.
+pub fn validate_default_params(params: &HashMap<String, String>) -> Result<(), String> {
+    for (key, max) in [(TEMPERATURE_PARAM, 2.0), (TOP_P_PARAM, 1.0)] {
+        if params.contains_key(key) {
+            match parse_param::<f32>(params, key) {
+                Some(value) if (0.0..=max).contains(&value) => {}
+                _ => return Err(format!("{key} must be a number between 0 and {max}")),
+            }
+        }
+    }
+    for key in [
+        MAX_TOKENS_PARAM,
+        DEFAULT_MAX_TOKENS_PARAM,
+        REALTIME_MAX_SESSION_SECONDS_PARAM,
+    ] {
+        if params.contains_key(key) {
+            match parse_param::<u32>(params, key) {
+                Some(value) if value > 0 => {}
+                _ => return Err(format!("{key} must be a positive integer")),
+            }
+        }
+    }
+    if parse_param::<bool>(params, CONTEXT_WINDOW_CHECK_PARAM) == Some(true)
+        && !matches!(parse_param::<u32>(params, CONTEXT_WINDOW_PARAM), Some(value) if value > 0)
+    {
+        return Err(format!(
+            "{CONTEXT_WINDOW_CHECK_PARAM} requires {CONTEXT_WINDOW_PARAM} to be a positive integer"
+        ));
+    }
+    for key in [
+        IGNORE_UNSUPPORTED_PARAMS_PARAM,
+        INLINE_MESSAGE_NAMES_PARAM,
+        CONTEXT_WINDOW_CHECK_PARAM,
+        JSON_REPAIR_PARAM,
+    ] {
+        if params.contains_key(key) && parse_param::<bool>(params, key).is_none() {
+            return Err(format!("{key} must be true or false"));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the model sends requests with features its provider lacks instead of rejecting
+/// them.
+pub fn ignores_unsupported_params(params: &HashMap<String, String>) -> bool {
+    parse_param(params, IGNORE_UNSUPPORTED_PARAMS_PARAM).unwrap_or(false)
+}
+
+/// Whether message names are prefixed into the content for providers without the field.
+pub fn inlines_message_names(params: &HashMap<String, String>) -> bool {
+    parse_param(params, INLINE_MESSAGE_NAMES_PARAM).unwrap_or(true)
+}
+
+/// The context window requests are checked against, when the model opts into the check.
+pub fn checked_context_window(params: &HashMap<String, String>) -> Option<u32> {
+    if !parse_param(params, CONTEXT_WINDOW_CHECK_PARAM).unwrap_or(false) {
+        return None;
+    }
+    parse_param(params, CONTEXT_WINDOW_PARAM)
+}
+
+/// The model's `default_max_tokens`, for providers that require `max_tokens`.
+pub fn model_default_max_tokens(params: &HashMap<String, String>) -> Option<u32> {
+    parse_param(params, DEFAULT_MAX_TOKENS_PARAM)
+}
+
+/// Whether the model's JSON-mode chat responses are repaired.
+pub fn repairs_json(params: &HashMap<String, String>) -> bool {
+    parse_param(params, JSON_REPAIR_PARAM).unwrap_or(false)
+}
+
+/// How long the model's realtime sessions may stay open.
+pub fn realtime_max_session(params: &HashMap<String, String>) -> Duration {
+    parse_param(params, REALTIME_MAX_SESSION_SECONDS_PARAM)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_REALTIME_MAX_SESSION)
+}
+
+/// Fills sampling params the request left unset from the model's params.
+pub fn apply_chat_defaults(params: &HashMap<String, String>, request: &mut ChatCompletionRequest) {
+    if request.temperature.is_none() {
+        request.temperature = parse_param(params, TEMPERATURE_PARAM);
+    }
+    if request.top_p.is_none() {
+        request.top_p = parse_param(params, TOP_P_PARAM);
+    }
+    if request.max_tokens.is_none() && request.max_completion_tokens.is_none() {
+        request.max_tokens = parse_param(params, MAX_TOKENS_PARAM);
+    }
+}
+
+/// Fills sampling params the request left unset from the model's params.
+pub fn apply_completion_defaults(
+    params: &HashMap<String, String>,
+    request: &mut CompletionRequest,
+) {
+    if request.temperature.is_none() {
+        request.temperature = parse_param(params, TEMPERATURE_PARAM);
+    }
+    if request.top_p.is_none() {
+        request.top_p = parse_param(params, TOP_P_PARAM);
+    }
+    if request.max_tokens.is_none() {
+        request.max_tokens = parse_param(params, MAX_TOKENS_PARAM);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_config_details() {
+        assert!(validate_config_details(&Value::Null).is_ok());
+        assert!(
+            validate_config_details(&json!({"deployment": "d1", "temperature": 0.7, "x": true}))
+                .is_ok()
+        );
+        assert!(validate_config_details(&json!(["a"])).is_err());
+        let error = validate_config_details(&json!({"routing": {"region": "us"}})).unwrap_err();
+        assert!(error.contains("config_details.routing"));
+        assert!(validate_config_details(&json!({"temperature": 3})).is_err());
+        assert!(validate_config_details(&json!({"max_tokens": "lots"})).is_err());
+        assert!(validate_config_details(&json!({"ignore_unsupported_params": true})).is_ok());
+        assert!(validate_config_details(&json!({"ignore_unsupported_params": "yes"})).is_err());
+        assert!(
+            validate_config_details(&json!({"context_window": 8192, "context_window_check": true}))
+                .is_ok()
+        );
+        assert!(validate_config_details(&json!({"context_window_check": true})).is_err());
+        assert!(validate_config_details(&json!({"json_repair": true})).is_ok());
+        assert!(validate_config_details(&json!({"json_repair": "sometimes"})).is_err());
+    }
+
+    #[test]
+    fn test_chat_defaults_only_fill_unset_fields() {
+        let params = HashMap::from([
+            (TEMPERATURE_PARAM.to_string(), "0.5".to_string()),
+            (MAX_TOKENS_PARAM.to_string(), "256".to_string()),
+        ]);
+        let mut request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hi"}],
+            "max_tokens": 10
+        }))
+        .unwrap();
+
+        apply_chat_defaults(&params, &mut request);
+
+        assert_eq!(request.temperature, Some(0.5));
+        assert_eq!(request.top_p, None);
+        assert_eq!(request.max_tokens, Some(10));
+    }
+
+    #[test]
+    fn test_realtime_max_session() {
+        assert_eq!(
+            realtime_max_session(&HashMap::new()),
+            Duration::from_secs(1800)
+        );
+        let params = HashMap::from([(
+            REALTIME_MAX_SESSION_SECONDS_PARAM.to_string(),
+            "90".to_string(),
+        )]);
+        assert_eq!(realtime_max_session(&params), Duration::from_secs(90));
+        assert!(validate_default_params(&params).is_ok());
+        let params = HashMap::from([(
+            REALTIME_MAX_SESSION_SECONDS_PARAM.to_string(),
+            "0".to_string(),
+        )]);
+        assert!(validate_default_params(&params).is_err());
+    }
+}
@@ -1,15 +1,21 @@
 use anyhow::Result;
+use chrono::Utc;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use super::instance::ModelInstance;
 use crate::config::models::ModelConfig;
+use crate::config::names::lookup_matches;
 use crate::models::responses::{ModelInfoResponse, ModelListResponse};
+use crate::providers::maintenance::{InMaintenance, record_maintenance_skip};
 use crate::providers::registry::ProviderRegistry;
+use crate::types::ModelDeprecation;
 
 #[derive(Clone)]
 pub struct ModelRegistry {
     models: HashMap<String, Arc<ModelInstance>>,
+    provider_registry: Arc<ProviderRegistry>,
+    case_insensitive_lookups: bool,
 }
 
 impl ModelRegistry {
@@ -19,7 +25,7 @@ impl ModelRegistry {
     ) -> Result<Self> {
         let mut models = HashMap::new();
 
-        for config in model_configs {
+        for config in model_configs.iter().filter(|config| config.enabled) {
             if let Some(provider) = provider_registry.get(&config.provider) {
                 let model = Arc::new(ModelInstance {
                     name: config.key.clone(),
@@ -32,14 +38,207 @@ impl ModelRegistry {
             }
         }
 
-        Ok(Self { models })
+        Ok(Self {
+            models,
+            provider_registry,
+            case_insensitive_lookups: false,
+        })
+    }
+
+    /// Matches requested model types ignoring case, as `general.case_insensitive_lookups`
+    /// asks.
+    pub fn with_case_insensitive_lookups(mut self, case_insensitive_lookups: bool) -> Self {
+        self.case_insensitive_lookups = case_insensitive_lookups;
+        self
+    }
+
+    pub fn case_insensitive_lookups(&self) -> bool {
+        self.case_insensitive_lookups
+    }
+
+    /// Whether `requested` refers to the configured name or type `configured`.
+    pub fn lookup_matches(&self, configured: &str, requested: &str) -> bool {
+        lookup_matches(configured, requested, self.case_insensitive_lookups)
     }
 
     pub fn get(&self, name: &str) -> Option<Arc<ModelInstance>> {
         self.models.get(name).cloned()
     }
 
-    pub fn get_filtered_model_info(&self, allowed_models: &[String]) -> ModelListResponse {
+    /// The maintenance window `model`'s provider is in, if any, in which case routers skip
+    /// the model. Counts the skip.
+    pub fn in_maintenance(&self, model: &ModelInstance) -> Option<InMaintenance> {
+        let provider = &model.config.provider;
+        let until = self
+            .provider_registry
+            .maintenance_until(provider, Utc::now())?;
+        record_maintenance_skip(provider);
+        Some(InMaintenance {
+            provider: provider.clone(),
+            until,
+        })
+    }
+
+    /// Each of `model_keys` a router could consider for `requested`, with the reason it's
+    /// passed over, if it is: `disabled` for keys with no registered model and
+    /// `maintenance` for models whose provider is in a maintenance window. Models of other
+    /// types are left out. Doesn't count maintenance skips.
+    pub fn route_candidates(
+        &self,
+        requested: &str,
+        model_keys: &[String],
+    ) -> Vec<(String, Option<&'static str>)> {
+        let now = Utc::now();
+        model_keys
+            .iter()
+            .filter_map(|key| match self.get(key) {
+                None => Some((key.clone(), Some("disabled"))),
+                Some(model) if !self.lookup_matches(&model.model_type, requested) => None,
+                Some(model) => {
+                    let in_maintenance = self
+                        .provider_registry
+                        .maintenance_until(&model.config.provider, now)
+                        .is_some();
+                    Some((key.clone(), in_maintenance.then_some("maintenance")))
+                }
+            })
+            .collect()
+    }
+
+    /// The models among `model_keys` whose type is `requested`, in router order, leaving out
+    /// models whose provider is in maintenance.
+    pub fn candidates(&self, requested: &str, model_keys: &[String]) -> Vec<Arc<ModelInstance>> {
+        // Disabled models are not registered, so routers simply skip them.
+        model_keys
+            .iter()
+            .filter_map(|key| self.get(key))
+            .filter(|model| self.lookup_matches(&model.model_type, requested))
+            .filter(|model| self.in_maintenance(model).is_none())
+            .collect()
+    }
+
+    /// The model among `model_keys` whose type is `requested`. Failing that, and when
+    /// `allow_dynamic_models` is set, a `provider/model` name is served by an implicit model
+    /// on one of the providers behind `model_keys`. Models whose provider is in maintenance
+    /// are skipped.
+    pub fn route(
+        &self,
+        requested: &str,
+        model_keys: &[String],
+        allow_dynamic_models: bool,
+    ) -> Option<Arc<ModelInstance>> {
+        // Disabled models are not registered, so routers simply skip them.
+        let configured: Vec<Arc<ModelInstance>> =
+            model_keys.iter().filter_map(|key| self.get(key)).collect();
+        if let Some(model) = configured
+            .iter()
+            .filter(|model| self.lookup_matches(&model.model_type, requested))
+            .find(|model| self.in_maintenance(model).is_none())
+        {
+            return Some(model.clone());
+        }
+        if !allow_dynamic_models {
+            return None;
+        }
+
+        let (provider_key, model_type) = Self::dynamic_provider(&configured, requested)?;
+        if self
+            .provider_registry
+            .maintenance_until(provider_key, Utc::now())
+            .is_some()
+        {
+            record_maintenance_skip(provider_key);
+            return None;
+        }
+        let provider = self.provider_registry.get(provider_key)?;
+
+        let config = ModelConfig {
+            key: format!("{provider_key}/{model_type}"),
+            r#type: model_type.to_string(),
+            provider: provider_key.clone(),
+            deprecation: ModelDeprecation::default(),
+            params: HashMap::new(),
+            enabled: true,
+        };
+        Some(Arc::new(ModelInstance {
+            name: config.key.clone(),
+            model_type: config.r#type.clone(),
+            provider,
+            config,
+        }))
+    }
+
+    /// Key of the configured provider a `provider/model` name refers to, with the model.
+    fn dynamic_provider<'a, 'b>(
+        configured: &'a [Arc<ModelInstance>],
+        requested: &'b str,
+    ) -> Option<(&'a String, &'b str)> {
+        let (prefix, model_type) = requested.split_once('/')?;
+        if prefix.is_empty() || model_type.trim().is_empty() {
+            return None;
+        }
+        // Keys take precedence over types, so `azure-eu/gpt-4o` can pick one of several
+        // providers of the same type.
+        let provider_key = configured
+            .iter()
+            .map(|model| &model.config.provider)
+            .find(|provider| provider.as_str() == prefix)
+            .or_else(|| {
+                configured
+                    .iter()
+                    .find(|model| model.provider.r#type().to_string() == prefix)
+                    .map(|model| &model.config.provider)
+            })?;
+        Some((provider_key, model_type))
+    }
+
+    /// Why `route` found no model for `requested` even though models match it: their
+    /// providers are in maintenance. Reports the provider that comes back first.
+    pub fn maintenance(
+        &self,
+        requested: &str,
+        model_keys: &[String],
+        allow_dynamic_models: bool,
+    ) -> Option<InMaintenance> {
+        let configured: Vec<Arc<ModelInstance>> =
+            model_keys.iter().filter_map(|key| self.get(key)).collect();
+        let mut providers: Vec<&String> = configured
+            .iter()
+            .filter(|model| self.lookup_matches(&model.model_type, requested))
+            .map(|model| &model.config.provider)
+            .collect();
+        if providers.is_empty() && allow_dynamic_models {
+            providers.extend(Self::dynamic_provider(&configured, requested).map(|(key, _)| key));
+        }
+
+        let now = Utc::now();
+        providers
+            .into_iter()
+            .filter_map(|provider| {
+                Some(InMaintenance {
+                    provider: provider.clone(),
+                    until: self.provider_registry.maintenance_until(provider, now)?,
+                })
+            })
+            .min_by_key(|maintenance| maintenance.until)
+    }
+
+    /// The model names requests can use with `model_keys`, in router order.
+    pub fn served_models(&self, model_keys: &[String]) -> Vec<String> {
+        let mut served: Vec<String> = Vec::new();
+        for model in model_keys.iter().filter_map(|key| self.get(key)) {
+            if !served.contains(&model.model_type) {
+                served.push(model.model_type.clone());
+            }
+        }
+        served
+    }
+
+    pub fn get_filtered_model_info(
+        &self,
+        allowed_models: &[String],
+        include_capabilities: bool,
+    ) -> ModelListResponse {
         ModelListResponse {
             object: "list".to_string(),
             data: self
@@ -50,6 +249,8 @@ impl ModelRegistry {
                     id: model.name.clone(),
                     object: "model".to_string(),
                     owned_by: model.provider.key(),
+                    deprecation: model.config.deprecation.clone(),
+                    capabilities: include_capabilities.then(|| model.capabilities()),
                 })
                 .collect(),
         }
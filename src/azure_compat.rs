@@ -0,0 +1,201 @@
+//! Azure OpenAI's paths, `/openai/deployments/{deployment}/chat/completions?api-version=...`
+//! and its siblings, served by the pipelines so clients written against Azure can point at
+//! the hub unchanged. The deployment picks the model, and errors come back in Azure's
+//! envelope. Off unless `general.azure_compat_routes` is set.
+
+use crate::gateway::Gateway;
+use crate::pipelines::buffered_body::{buffer_response_body, read_request_body};
+use crate::state::AppState;
+use axum::Json;
+use axum::body::Body;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct AzureQuery {
+    #[serde(rename = "api-version")]
+    api_version: Option<String>,
+}
+
+/// An error in the envelope of Azure OpenAI.
+fn azure_error(status: StatusCode, code: &str, message: &str) -> Response {
+    let body = json!({"error": {"code": code, "message": message}});
+    (status, Json(body)).into_response()
+}
+
+/// Whether `version` has the shape of an Azure OpenAI API version, e.g. `2024-02-01` or
+/// `2024-05-01-preview`. The version itself doesn't change what the hub does.
+fn is_api_version(version: &str) -> bool {
+    let (Some(date), Some(suffix)) = (version.get(..10), version.get(10..)) else {
+        return false;
+    };
+    let is_date = date.bytes().enumerate().all(|(index, byte)| match index {
+        4 | 7 => byte == b'-',
+        _ => byte.is_ascii_digit(),
+    });
+    is_date && (suffix.is_empty() || suffix == "-preview")
+}
+
+/// Rewrites a pipeline's error response in Azure's envelope, keeping its status, headers
+/// and message. A 404 means the deployment's model isn't served by the pipeline, which Azure
+/// reports as a missing deployment. Error bodies larger than `max_body_bytes` aren't read,
+/// and their status stands in for the message.
+async fn to_azure_error(response: Response, max_body_bytes: usize) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let body = buffer_response_body(body, max_body_bytes)
+        .await
+        .unwrap_or_default();
+    let error = serde_json::from_slice::<Value>(&body)
+        .ok()
+        .and_then(|mut body| body.get_mut("error").map(Value::take));
+    let message = match &error {
+        Some(Value::String(message)) => message.clone(),
+        Some(error) => error["message"].as_str().unwrap_or_default().to_string(),
+        None => String::from_utf8_lossy(&body).into_owned(),
+    };
+    let message = if message.is_empty() {
+        parts.status.to_string()
+    } else {
+        message
+    };
+    let code = match (
+        parts.status,
+        error.as_ref().and_then(|error| error.get("code")),
+    ) {
+        (StatusCode::NOT_FOUND, _) => "DeploymentNotFound".to_string(),
+        (_, Some(Value::String(code))) => code.clone(),
+        (status, _) => status.as_u16().to_string(),
+    };
+    let envelope = azure_error(parts.status, &code, &message);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, envelope.into_body())
+}
+
+/// Sends a request made to a deployment's `path` to its pipeline, as a request to the
+/// deployment's model.
+async fn forward(
+    state: Arc<AppState>,
+    deployment: String,
+    query: AzureQuery,
+    request: Request,
+    path: &'static str,
+) -> Response {
+    if !state.azure_compat_routes() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let Some(api_version) = query.api_version else {
+        return azure_error(
+            StatusCode::BAD_REQUEST,
+            "MissingApiVersionParameter",
+            "The api-version query parameter (?api-version=) is required for all requests.",
+        );
+    };
+    if !is_api_version(&api_version) {
+        return azure_error(
+            StatusCode::BAD_REQUEST,
+            "UnsupportedApiVersion",
+            &format!("The api-version '{api_version}' is not supported."),
+        );
+    }
+    let Some(model) = state.model_for_deployment(&deployment) else {
+        return azure_error(
+            StatusCode::NOT_FOUND,
+            "DeploymentNotFound",
+            &format!("The API deployment '{deployment}' for this resource does not exist."),
+        );
+    };
+
+    let max_body_bytes = state.settings().max_buffered_body_bytes;
+    let (mut parts, body) = request.into_parts();
+    let body = match read_request_body(body, max_body_bytes).await {
+        Ok(body) => body,
+        Err(response) => return to_azure_error(response, max_body_bytes).await,
+    };
+    let Ok(Value::Object(mut body)) = serde_json::from_slice::<Value>(&body) else {
+        return azure_error(
+            StatusCode::BAD_REQUEST,
+            "BadRequest",
+            "The request body must be a JSON object.",
+        );
+    };
+    body.insert("model".to_string(), Value::String(model));
+    parts.uri = path.parse().expect("valid path");
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let request = Request::from_parts(parts, Body::from(Value::Object(body).to_string()));
+
+    let response = Gateway::from_state(state).handle(request).await;
+    if response.status().is_success() {
+        return response;
+    }
+    to_azure_error(response, max_body_bytes).await
+}
+
+pub async fn chat_completions(
+    State(state): State<Arc<AppState>>,
+    Path(deployment): Path<String>,
+    Query(query): Query<AzureQuery>,
+    request: Request,
+) -> Response {
+    forward(state, deployment, query, request, "/chat/completions").await
+}
+
+pub async fn completions(
+    State(state): State<Arc<AppState>>,
+    Path(deployment): Path<String>,
+    Query(query): Query<AzureQuery>,
+    request: Request,
+) -> Response {
+    forward(state, deployment, query, request, "/completions").await
+}
+
+pub async fn embeddings(
+    State(state): State<Arc<AppState>>,
+    Path(deployment): Path<String>,
+    Query(query): Query<AzureQuery>,
+    request: Request,
+) -> Response {
+    forward(state, deployment, query, request, "/embeddings").await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    #[test]
+    fn test_api_versions() {
+        assert!(is_api_version("2024-02-01"));
+        assert!(is_api_version("2024-05-01-preview"));
+        assert!(!is_api_version("2024-2-1"));
+        assert!(!is_api_version("2024-02-01-beta"));
+        assert!(!is_api_version("v1"));
+        assert!(!is_api_version("2024-02-0é"));
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_errors_get_the_azure_envelope() {
+        let response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, "3")],
+            Json(json!({"error": {"type": "rate_limit_error", "message": "Budget spent"}})),
+        )
+            .into_response();
+        let response = to_azure_error(response, 1024).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "3");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"error": {"code": "429", "message": "Budget spent"}})
+        );
+    }
+}
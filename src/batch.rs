@@ -0,0 +1,377 @@
+//! `POST /api/v1/batch-inference`: chat completions for offline workloads, sent as one JSON
+//! Lines body with a request per line. Each line goes through its pipeline like a request of
+//! its own, so budgets, routing and circuit breakers apply to it, and its result is streamed
+//! back as a JSON line as soon as it completes.
+
+use crate::gateway::Gateway;
+use crate::pipelines::buffered_body::buffer_response_body;
+use crate::pipelines::idempotency::IDEMPOTENCY_KEY_HEADER;
+use crate::pipelines::resumable_streams::STREAM_ID_HEADER;
+use crate::state::{AppState, PIPELINE_HEADER};
+use crate::types::BatchInferenceConfig;
+use axum::Json;
+use axum::body::{Body, Bytes};
+use axum::extract::{Query, Request, State};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+/// Field of a line identifying it in the results; not sent on to the pipeline.
+pub const CUSTOM_ID_FIELD: &str = "custom_id";
+pub const BATCH_TOO_LARGE_CODE: &str = "batch_too_large";
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Request headers that belong to the batch as a whole, so aren't copied onto its lines.
+const BATCH_ONLY_HEADERS: [header::HeaderName; 6] = [
+    header::CONTENT_LENGTH,
+    header::CONTENT_TYPE,
+    header::CONTENT_ENCODING,
+    header::ACCEPT_ENCODING,
+    IDEMPOTENCY_KEY_HEADER,
+    STREAM_ID_HEADER,
+];
+
+#[derive(Debug, Deserialize)]
+pub struct BatchQuery {
+    /// Pipeline serving the batch, as the `x-traceloop-pipeline` header would pick it.
+    pipeline: Option<String>,
+    /// Lines sent at once. Defaults to, and can't exceed, `max_concurrency`.
+    concurrency: Option<usize>,
+    /// Returns results in the order of the lines rather than as they complete.
+    #[serde(default)]
+    ordered: bool,
+}
+
+/// Why a whole batch was rejected. Failures of single lines are reported in their results.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchError {
+    BodyTooLarge { max_bytes: usize },
+    TooManyLines { max_lines: usize },
+    InvalidConcurrency { max_concurrency: usize },
+    InvalidPipeline(String),
+    UnreadableBody,
+}
+
+impl IntoResponse for BatchError {
+    fn into_response(self) -> Response {
+        let (status, code, message) = match self {
+            BatchError::BodyTooLarge { max_bytes } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Some(BATCH_TOO_LARGE_CODE),
+                format!("Batches are limited to {max_bytes} bytes"),
+            ),
+            BatchError::TooManyLines { max_lines } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Some(BATCH_TOO_LARGE_CODE),
+                format!("Batches are limited to {max_lines} lines"),
+            ),
+            BatchError::InvalidConcurrency { max_concurrency } => (
+                StatusCode::BAD_REQUEST,
+                None,
+                format!("concurrency must be between 1 and {max_concurrency}"),
+            ),
+            BatchError::InvalidPipeline(name) => (
+                StatusCode::BAD_REQUEST,
+                None,
+                format!("Invalid pipeline name '{name}'"),
+            ),
+            BatchError::UnreadableBody => (
+                StatusCode::BAD_REQUEST,
+                None,
+                "The batch must be UTF-8 JSON Lines".to_string(),
+            ),
+        };
+        let body = json!({
+            "error": {
+                "type": "invalid_request_error",
+                "message": message,
+                "param": null,
+                "code": code,
+            }
+        });
+        (status, Json(body)).into_response()
+    }
+}
+
+/// The outcome of one line, sent back as a JSON line. `line` counts from 1, blank lines
+/// included, so results can be matched to lines without a `custom_id`.
+#[derive(Debug, Serialize)]
+struct BatchResult {
+    line: usize,
+    custom_id: Option<String>,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Value>,
+}
+
+impl BatchResult {
+    fn invalid(line: usize, custom_id: Option<String>, message: String) -> Self {
+        Self {
+            line,
+            custom_id,
+            status: StatusCode::BAD_REQUEST.as_u16(),
+            response: None,
+            error: Some(json!({"type": "invalid_request_error", "message": message})),
+        }
+    }
+}
+
+/// A line's `custom_id` and the chat completion request it holds.
+fn parse_line(line: &str) -> (Option<String>, Result<Value, String>) {
+    let mut request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return (None, Err(format!("Invalid JSON: {e}"))),
+    };
+    let Some(object) = request.as_object_mut() else {
+        return (None, Err("Each line must be a JSON object".to_string()));
+    };
+    let custom_id = match object.remove(CUSTOM_ID_FIELD) {
+        None | Some(Value::Null) => None,
+        Some(Value::String(custom_id)) => Some(custom_id),
+        Some(_) => return (None, Err(format!("{CUSTOM_ID_FIELD} must be a string"))),
+    };
+    if object.get("stream").and_then(Value::as_bool) == Some(true) {
+        return (
+            custom_id,
+            Err("Streaming isn't supported in batches".to_string()),
+        );
+    }
+    (custom_id, Ok(request))
+}
+
+/// The non-blank lines of a batch with their line numbers, checked against the caps.
+fn split_lines(
+    body: &Bytes,
+    config: &BatchInferenceConfig,
+) -> Result<Vec<(usize, String)>, BatchError> {
+    let body = std::str::from_utf8(body).map_err(|_| BatchError::UnreadableBody)?;
+    let lines: Vec<(usize, String)> = body
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| (index + 1, line.to_string()))
+        .collect();
+    if lines.len() > config.max_lines {
+        return Err(BatchError::TooManyLines {
+            max_lines: config.max_lines,
+        });
+    }
+    Ok(lines)
+}
+
+/// Reads a batch body, giving up as soon as it's larger than `max_bytes`.
+async fn read_body(body: Body, max_bytes: usize) -> Result<Bytes, BatchError> {
+    let mut data = body.into_data_stream();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = data.next().await {
+        let chunk = chunk.map_err(|_| BatchError::UnreadableBody)?;
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(BatchError::BodyTooLarge { max_bytes });
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(bytes))
+}
+
+/// Sends one line to its pipeline as a chat completion request with the batch's headers.
+/// A response larger than `max_body_bytes` fails the line.
+async fn run_line(
+    gateway: &Gateway,
+    headers: &HeaderMap,
+    line: usize,
+    text: &str,
+    max_body_bytes: usize,
+) -> BatchResult {
+    let (custom_id, request) = parse_line(text);
+    let request = match request {
+        Ok(request) => request,
+        Err(message) => return BatchResult::invalid(line, custom_id, message),
+    };
+    let mut http_request = Request::new(Body::from(request.to_string()));
+    *http_request.method_mut() = Method::POST;
+    *http_request.uri_mut() = "/chat/completions".parse().expect("valid path");
+    *http_request.headers_mut() = headers.clone();
+    let response = gateway.handle(http_request).await;
+
+    let status = response.status();
+    let body = match buffer_response_body(response.into_body(), max_body_bytes).await {
+        Ok(body) => serde_json::from_slice(&body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned())),
+        Err(_) => {
+            return BatchResult {
+                line,
+                custom_id,
+                status: StatusCode::BAD_GATEWAY.as_u16(),
+                response: None,
+                error: Some(json!({
+                    "type": "api_error",
+                    "message": format!("The response failed or was larger than {max_body_bytes} bytes"),
+                })),
+            };
+        }
+    };
+    if status.is_success() {
+        return BatchResult {
+            line,
+            custom_id,
+            status: status.as_u16(),
+            response: Some(body),
+            error: None,
+        };
+    }
+    let error = match body {
+        Value::Object(mut object) if object.contains_key("error") => object.remove("error"),
+        Value::String(message) if message.is_empty() => None,
+        Value::String(message) => Some(json!({"message": message})),
+        body => Some(body),
+    };
+    BatchResult {
+        line,
+        custom_id,
+        status: status.as_u16(),
+        response: None,
+        error: Some(error.unwrap_or_else(|| json!({"message": status.to_string()}))),
+    }
+}
+
+/// The headers each line is sent with: the batch's own, minus those describing its body,
+/// with the pipeline picked by the `pipeline` query param if set.
+fn line_headers(headers: &HeaderMap, pipeline: Option<&str>) -> Result<HeaderMap, BatchError> {
+    let mut headers = headers.clone();
+    for name in BATCH_ONLY_HEADERS {
+        headers.remove(name);
+    }
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    if let Some(pipeline) = pipeline {
+        let value = HeaderValue::from_str(pipeline)
+            .map_err(|_| BatchError::InvalidPipeline(pipeline.to_string()))?;
+        headers.insert(PIPELINE_HEADER, value);
+    }
+    Ok(headers)
+}
+
+/// Handles `POST /api/v1/batch-inference`. Lines that fail, including ones that aren't
+/// valid requests, are reported in their results without stopping the others. A client
+/// disconnecting cancels the lines still running.
+pub async fn batch_inference(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<BatchQuery>,
+    request: Request,
+) -> Result<Response, BatchError> {
+    let config = state.batch_inference_config();
+    let concurrency = query.concurrency.unwrap_or(config.max_concurrency);
+    if concurrency == 0 || concurrency > config.max_concurrency {
+        return Err(BatchError::InvalidConcurrency {
+            max_concurrency: config.max_concurrency,
+        });
+    }
+    let (parts, body) = request.into_parts();
+    let headers = Arc::new(line_headers(&parts.headers, query.pipeline.as_deref())?);
+    let body = read_body(body, config.max_body_bytes).await?;
+    let lines = split_lines(&body, &config)?;
+
+    let max_body_bytes = state.settings().max_buffered_body_bytes;
+    let gateway = Gateway::from_state(state);
+    let results = futures::stream::iter(lines).map(move |(line, text)| {
+        let gateway = gateway.clone();
+        let headers = headers.clone();
+        async move { run_line(&gateway, &headers, line, &text, max_body_bytes).await }
+    });
+    let results: BoxStream<'static, BatchResult> = if query.ordered {
+        results.buffered(concurrency).boxed()
+    } else {
+        results.buffer_unordered(concurrency).boxed()
+    };
+    let body = results.map(|result| {
+        let mut line = serde_json::to_vec(&result).expect("results serialize");
+        line.push(b'\n');
+        Ok::<_, Infallible>(Bytes::from(line))
+    });
+    Ok((
+        [(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_id_is_taken_out_of_the_request() {
+        let (custom_id, request) =
+            parse_line(r#"{"custom_id": "a-1", "model": "gpt-4o", "messages": []}"#);
+        assert_eq!(custom_id.as_deref(), Some("a-1"));
+        assert_eq!(request.unwrap(), json!({"model": "gpt-4o", "messages": []}));
+
+        let (custom_id, request) = parse_line(r#"{"model": "gpt-4o", "messages": []}"#);
+        assert_eq!(custom_id, None);
+        assert!(request.is_ok());
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        assert!(parse_line("{not json").1.is_err());
+        assert!(parse_line("[1, 2]").1.is_err());
+        assert!(
+            parse_line(r#"{"custom_id": 7, "model": "gpt-4o"}"#)
+                .1
+                .is_err()
+        );
+        let (custom_id, request) = parse_line(r#"{"custom_id": "s", "stream": true}"#);
+        assert_eq!(custom_id.as_deref(), Some("s"));
+        assert!(request.is_err());
+    }
+
+    #[test]
+    fn test_blank_lines_are_skipped_but_counted() {
+        let config = BatchInferenceConfig {
+            max_lines: 2,
+            ..Default::default()
+        };
+        let body = Bytes::from("{\"a\":1}\n\n  \n{\"b\":2}\n");
+        let lines = split_lines(&body, &config).unwrap();
+        assert_eq!(
+            lines,
+            vec![(1, "{\"a\":1}".to_string()), (4, "{\"b\":2}".to_string())]
+        );
+
+        let body = Bytes::from("{}\n{}\n{}");
+        assert_eq!(
+            split_lines(&body, &config),
+            Err(BatchError::TooManyLines { max_lines: 2 })
+        );
+    }
+
+    #[test]
+    fn test_batch_headers_are_not_copied_to_lines() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer sk"));
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(NDJSON_CONTENT_TYPE),
+        );
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static("batch-1"));
+        headers.insert(PIPELINE_HEADER, HeaderValue::from_static("from-header"));
+
+        let lines = line_headers(&headers, None).unwrap();
+        assert_eq!(lines[header::AUTHORIZATION], "Bearer sk");
+        assert_eq!(lines[header::CONTENT_TYPE], "application/json");
+        assert!(!lines.contains_key(IDEMPOTENCY_KEY_HEADER));
+        assert_eq!(lines[PIPELINE_HEADER], "from-header");
+
+        let lines = line_headers(&headers, Some("batch")).unwrap();
+        assert_eq!(lines[PIPELINE_HEADER], "batch");
+    }
+}
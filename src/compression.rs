@@ -0,0 +1,32 @@
+use crate::types::{CompressionAlgorithm, CompressionConfig};
+use tower_http::compression::CompressionLayer;
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::decompression::RequestDecompressionLayer;
+
+/// Compresses responses with the configured algorithms. SSE streams are left alone
+/// so events aren't held back in the encoder's buffer.
+pub fn compression_layer(config: &CompressionConfig) -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(config.min_size_bytes)
+        .and(NotForContentType::SSE)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES);
+    CompressionLayer::new()
+        .gzip(config.algorithms.contains(&CompressionAlgorithm::Gzip))
+        .br(config.algorithms.contains(&CompressionAlgorithm::Br))
+        .compress_when(predicate)
+}
+
+/// Accepts request bodies sent with the configured `Content-Encoding`s.
+pub fn request_decompression_layer(config: &CompressionConfig) -> RequestDecompressionLayer {
+    RequestDecompressionLayer::new()
+        .gzip(config.algorithms.contains(&CompressionAlgorithm::Gzip))
+        .br(config.algorithms.contains(&CompressionAlgorithm::Br))
+}
+
+/// Returns the problems with a `general.compression` section; empty when it is usable.
+pub fn validate_compression(config: &CompressionConfig) -> Vec<String> {
+    if config.algorithms.is_empty() {
+        return vec!["general.compression.algorithms must not be empty.".to_string()];
+    }
+    Vec::new()
+}
@@ -28,3 +28,27 @@ pub fn default_embedding_normalize() -> bool {
         .parse()
         .unwrap_or(true)
 }
+
+/// Minimum seconds between two log lines for the same rate-limited error.
+pub fn error_log_interval_seconds() -> u64 {
+    env::var("ERROR_LOG_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .unwrap_or(60)
+}
+
+/// Environment whose pipelines the gateway loads in database mode, besides untagged ones.
+pub fn hub_environment() -> Option<String> {
+    env::var("HUB_ENVIRONMENT")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Number of applied-config snapshots kept in database mode.
+pub fn config_snapshot_retention() -> i64 {
+    env::var("CONFIG_SNAPSHOT_RETENTION")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .unwrap_or(20)
+}
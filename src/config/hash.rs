@@ -9,6 +9,11 @@ pub fn calculate_config_hash(config: &GatewayConfig) -> u64 {
     hasher.finish()
 }
 
+/// Format a config hash the way it is reported by `/health` and stored in snapshots
+pub fn format_config_hash(hash: u64) -> String {
+    format!("{hash:016x}")
+}
+
 /// Check if two configurations are equal by comparing their hashes
 /// This is more efficient than deep comparison for large configs
 pub fn configs_are_equal(config1: &GatewayConfig, config2: &GatewayConfig) -> bool {
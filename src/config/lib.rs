@@ -1,9 +1,23 @@
-use crate::types::{GatewayConfig, ModelConfig, Pipeline, PipelineType, PluginConfig, Provider};
+use crate::config::names::normalize_names;
+use crate::providers::api_keys::API_KEY_SECRET_PARAM;
+use crate::types::{
+    GatewayConfig, General, ModelConfig, PassthroughHeaders, Pipeline, PipelineType, PluginConfig,
+    Provider, SafetyBlockBehavior, SlowStreamConfig, TraceContentPolicy,
+};
 use serde::Deserialize;
-use std::sync::OnceLock;
-// std::collections::HashMap is used by serde_yaml for flatten, but not directly here otherwise.
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
 
-pub static TRACE_CONTENT_ENABLED: OnceLock<bool> = OnceLock::new();
+const DEFAULT_IDEMPOTENCY_TTL_SECONDS: u64 = 3600;
+const DEFAULT_RESUMABLE_STREAM_TTL_SECONDS: u64 = 300;
+const DEFAULT_STREAM_BUFFER_CHUNKS: usize = 64;
+const DEFAULT_STREAM_BUFFER_MAX_BYTES: usize = 4 * 1024 * 1024;
+const DEFAULT_MAX_BUFFERED_BODY_BYTES: usize = 32 * 1024 * 1024;
+/// Stands in for the file name in errors about a document given to `parse_config`.
+const PARSED_CONFIG_SOURCE: &str = "<inline>";
 // Intermediate struct for deserializing pipelines from YAML
 #[derive(Deserialize, Debug)]
 struct YamlCompatiblePipeline {
@@ -14,6 +28,8 @@ struct YamlCompatiblePipeline {
     #[serde(default = "default_enabled_true_lib")]
     #[allow(dead_code)]
     enabled: bool, // Keep for YAML parsing, but won't be mapped to core Pipeline
+    #[serde(default)]
+    store_artifacts: bool,
 }
 
 fn default_enabled_true_lib() -> bool {
@@ -25,17 +41,247 @@ fn default_enabled_true_lib() -> bool {
 // For now, assuming only pipelines might need this special handling for `singleton_map_recursive`.
 #[derive(Deserialize, Debug)]
 struct YamlRoot {
+    /// Additional config files to merge, resolved relative to the including file.
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    general: Option<General>,
     #[serde(default)]
     providers: Vec<Provider>,
     #[serde(default)]
     models: Vec<ModelConfig>,
     #[serde(default)]
     pipelines: Vec<YamlCompatiblePipeline>,
+    #[serde(default)]
+    prompt_templates: BTreeMap<String, String>,
+}
+
+/// Accumulates the contents of several config files, remembering which file
+/// defined each provider, model and pipeline so duplicates can be reported.
+#[derive(Default)]
+struct MergedConfig {
+    config: GatewayConfig,
+    general_source: Option<PathBuf>,
+    provider_sources: HashMap<String, PathBuf>,
+    model_sources: HashMap<String, PathBuf>,
+    pipeline_sources: HashMap<String, PathBuf>,
+    prompt_template_sources: HashMap<String, PathBuf>,
+    loaded_files: HashSet<PathBuf>,
+}
+
+impl MergedConfig {
+    fn merge(&mut self, root: YamlRoot, source: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(general) = root.general {
+            if let Some(existing) = &self.general_source {
+                return Err(format!(
+                    "Duplicate general section defined in '{}' and '{}'",
+                    existing.display(),
+                    source.display()
+                )
+                .into());
+            }
+            self.general_source = Some(source.to_path_buf());
+            self.config.general = Some(general);
+        }
+
+        for provider in root.providers {
+            check_duplicate(
+                &mut self.provider_sources,
+                "provider key",
+                &provider.key,
+                source,
+            )?;
+            self.config.providers.push(provider);
+        }
+
+        for model in root.models {
+            check_duplicate(&mut self.model_sources, "model key", &model.key, source)?;
+            self.config.models.push(model);
+        }
+
+        for p_yaml in root.pipelines {
+            check_duplicate(
+                &mut self.pipeline_sources,
+                "pipeline name",
+                &p_yaml.name,
+                source,
+            )?;
+            // Map to core_types::Pipeline. ee_id and enabled are no longer fields here.
+            self.config.pipelines.push(Pipeline {
+                name: p_yaml.name,
+                r#type: p_yaml.r#type,
+                plugins: p_yaml.plugins,
+                // p_yaml.enabled is parsed from YAML but not stored in core Pipeline struct
+                store_artifacts: p_yaml.store_artifacts,
+            });
+        }
+
+        for (name, template) in root.prompt_templates {
+            check_duplicate(
+                &mut self.prompt_template_sources,
+                "prompt template",
+                &name,
+                source,
+            )?;
+            self.config.prompt_templates.insert(name, template);
+        }
+
+        Ok(())
+    }
+}
+
+fn check_duplicate(
+    sources: &mut HashMap<String, PathBuf>,
+    kind: &str,
+    key: &str,
+    source: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(existing) = sources.get(key) {
+        return Err(format!(
+            "Duplicate {kind} '{key}' defined in '{}' and '{}'",
+            existing.display(),
+            source.display()
+        )
+        .into());
+    }
+    sources.insert(key.to_string(), source.to_path_buf());
+    Ok(())
+}
+
+/// Matches `name` against a shell-style pattern supporting `*` and `?`.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
-fn substitute_env_vars(content: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// Expands a config path into concrete files. Wildcards are only supported in the
+/// file name component; matches are returned in lexicographic order so merging is
+/// deterministic regardless of directory listing order.
+fn expand_config_path(pattern: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let file_pattern = pattern
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+
+    if !file_pattern.contains(['*', '?']) {
+        return Ok(vec![pattern.to_path_buf()]);
+    }
+
+    let dir = match pattern.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut matches: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read config directory '{}': {e}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| wildcard_match(file_pattern, name))
+        })
+        .collect();
+    matches.sort();
+
+    if matches.is_empty() {
+        warn!(
+            "Config pattern '{}' did not match any files",
+            pattern.display()
+        );
+    }
+
+    Ok(matches)
+}
+
+fn load_config_file(
+    path: &Path,
+    merged: &mut MergedConfig,
+    env: EnvSubstitution,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if !merged.loaded_files.insert(canonical) {
+        return Err(format!(
+            "Config file '{}' is included more than once",
+            path.display()
+        )
+        .into());
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file '{}': {e}", path.display()))?;
+    let mut yaml_root = parse_config_document(&contents, path, env)?;
+
+    let includes = std::mem::take(&mut yaml_root.include);
+    merged.merge(yaml_root, path)?;
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for include in includes {
+        for included_path in expand_config_path(&base_dir.join(include))? {
+            load_config_file(&included_path, merged, env)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the contents of the config file at `path`, handling `${VAR_NAME}` references
+/// as `env` says.
+fn parse_config_document(
+    contents: &str,
+    path: &Path,
+    env: EnvSubstitution,
+) -> Result<YamlRoot, Box<dyn std::error::Error>> {
+    let (contents_with_env, mut missing_vars) = match env {
+        EnvSubstitution::Resolve => substitute_env_vars(&contents),
+        EnvSubstitution::CheckSyntax => {
+            check_env_references(contents)
+                .map_err(|e| format!("Invalid config file '{}': {e}", path.display()))?;
+            (contents.to_string(), BTreeMap::new())
+        }
+    };
+    let parsed: Result<YamlRoot, _> = serde_yaml::from_str(&contents_with_env);
+    if let Ok(yaml_root) = &parsed {
+        defer_missing_api_keys(&yaml_root.providers, &mut missing_vars);
+    }
+    if let Some(var_name) = missing_vars.keys().next() {
+        return Err(format!("Environment variable '{var_name}' not found").into());
+    }
+    let mut yaml_root =
+        parsed.map_err(|e| format!("Failed to parse config file '{}': {e}", path.display()))?;
+    for provider in &mut yaml_root.providers {
+        defer_api_key_env_var(provider, env);
+    }
+    Ok(yaml_root)
+}
+
+/// Replaces each `${VAR_NAME}` with the value of the environment variable. Unset
+/// variables are left in place and counted, keyed by name.
+fn substitute_env_vars(content: &str) -> (String, BTreeMap<String, usize>) {
     use std::env;
 
+    let mut missing = BTreeMap::new();
     let mut result = content.to_string();
 
     // Use a regex-like approach to find ${VAR_NAME} patterns
@@ -54,7 +300,8 @@ fn substitute_env_vars(content: &str) -> Result<String, Box<dyn std::error::Erro
                     start_pos = actual_start + value.len();
                 }
                 Err(_) => {
-                    return Err(format!("Environment variable '{var_name}' not found").into());
+                    *missing.entry(var_name.to_string()).or_default() += 1;
+                    start_pos = actual_end + 1;
                 }
             }
         } else {
@@ -63,38 +310,150 @@ fn substitute_env_vars(content: &str) -> Result<String, Box<dyn std::error::Erro
         }
     }
 
-    Ok(result)
+    (result, missing)
 }
 
-pub fn load_config(path: &str) -> Result<GatewayConfig, Box<dyn std::error::Error>> {
-    let contents = std::fs::read_to_string(path)?;
-    let contents_with_env = substitute_env_vars(&contents)?;
-    let yaml_root: YamlRoot = serde_yaml::from_str(&contents_with_env)?;
-
-    let gateway_config = GatewayConfig {
-        providers: yaml_root.providers,
-        models: yaml_root.models,
-        pipelines: yaml_root
-            .pipelines
-            .into_iter()
-            .map(|p_yaml| {
-                // Map to core_types::Pipeline. ee_id and enabled are no longer fields here.
-                Pipeline {
-                    name: p_yaml.name,
-                    r#type: p_yaml.r#type,
-                    plugins: p_yaml.plugins,
-                    // p_yaml.enabled is parsed from YAML but not stored in core Pipeline struct
-                }
-            })
-            .collect(),
-        general: None,
+/// Checks that every `${` opens a reference to a valid variable name, closed by `}`.
+fn check_env_references(content: &str) -> Result<(), String> {
+    let mut rest = content;
+    while let Some(start) = rest.find("${") {
+        let reference = &rest[start + 2..];
+        let Some(end) = reference.find('}') else {
+            return Err("unclosed '${' in environment variable reference".to_string());
+        };
+        let var_name = &reference[..end];
+        let valid = var_name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && var_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!(
+                "'${{{var_name}}}' is not a valid environment variable reference"
+            ));
+        }
+        rest = &reference[end + 1..];
+    }
+    Ok(())
+}
+
+/// The variable a provider's `api_key` consists of, if it's just `${VAR_NAME}`.
+fn api_key_env_var(provider: &Provider) -> Option<&str> {
+    provider.api_key.strip_prefix("${")?.strip_suffix('}')
+}
+
+/// The unset variable a provider's `api_key` consists of, if it's just `${VAR_NAME}`.
+fn unset_api_key_env_var(provider: &Provider) -> Option<&str> {
+    let var_name = api_key_env_var(provider)?;
+    std::env::var(var_name).is_err().then_some(var_name)
+}
+
+/// Takes the occurrences of unset variables that are whole provider API keys out of
+/// `missing`. Those are resolved by the provider on first use, so one missing key fails
+/// only that provider's requests instead of the whole config.
+fn defer_missing_api_keys(providers: &[Provider], missing: &mut BTreeMap<String, usize>) {
+    for provider in providers {
+        let Some(var_name) = unset_api_key_env_var(provider) else {
+            continue;
+        };
+        if let Some(count) = missing.get_mut(var_name) {
+            *count -= 1;
+            if *count == 0 {
+                missing.remove(var_name);
+            }
+        }
+    }
+}
+
+/// Turns an API key that is an unset `${VAR_NAME}` into an `api_key_secret` reference to
+/// the variable. References that are left in place are all turned into one.
+fn defer_api_key_env_var(provider: &mut Provider, env: EnvSubstitution) {
+    let var_name = match env {
+        EnvSubstitution::Resolve => {
+            let Some(var_name) = unset_api_key_env_var(provider) else {
+                return;
+            };
+            warn!(
+                "Environment variable '{var_name}' with the API key of provider '{}' is not set. \
+                 It will be resolved when the provider is first used.",
+                provider.key
+            );
+            var_name
+        }
+        EnvSubstitution::CheckSyntax => match api_key_env_var(provider) {
+            Some(var_name) => var_name,
+            None => return,
+        },
     };
-    let _ = TRACE_CONTENT_ENABLED.set(
-        gateway_config
-            .general
-            .as_ref()
-            .is_none_or(|g| g.trace_content_enabled),
-    );
+    let secret = json!({"type": "environment", "variable_name": var_name});
+    provider
+        .params
+        .insert(API_KEY_SECRET_PARAM.to_string(), secret.to_string());
+    provider.api_key.clear();
+}
+
+/// How `${VAR_NAME}` references in config files are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnvSubstitution {
+    /// Replaced with the variables' values. Unset variables are an error, except for
+    /// provider API keys, which are resolved on first use.
+    #[default]
+    Resolve,
+    /// Left in place, only checking that each reference is well-formed. For validating a
+    /// config without the environment it will run in.
+    CheckSyntax,
+}
+
+/// Parses a configuration document the way `load_config_with` parses each file.
+/// `include` isn't supported, as there's no file to resolve it against.
+pub fn parse_config(
+    contents: &str,
+    env: EnvSubstitution,
+) -> Result<GatewayConfig, Box<dyn std::error::Error>> {
+    let source = Path::new(PARSED_CONFIG_SOURCE);
+    let yaml_root = parse_config_document(contents, source, env)?;
+    if !yaml_root.include.is_empty() {
+        return Err("include isn't supported in a configuration sent inline".into());
+    }
+    let mut merged = MergedConfig::default();
+    merged.merge(yaml_root, source)?;
+    let mut gateway_config = merged.config;
+    normalize_names(&mut gateway_config);
+    Ok(gateway_config)
+}
+
+/// Loads the gateway configuration.
+///
+/// `path` may be a single file, a comma-separated list of files, or a glob such as
+/// `config/*.yaml`. Each file may also pull in further files with a top-level
+/// `include:` list. Files are merged in order; defining the same provider key,
+/// model key, pipeline name or prompt template twice, or `general` in two files, is an
+/// error naming both files.
+pub fn load_config(path: &str) -> Result<GatewayConfig, Box<dyn std::error::Error>> {
+    load_config_with(path, EnvSubstitution::Resolve)
+}
+
+/// Like `load_config`, handling `${VAR_NAME}` references as `env` says.
+pub fn load_config_with(
+    path: &str,
+    env: EnvSubstitution,
+) -> Result<GatewayConfig, Box<dyn std::error::Error>> {
+    let mut merged = MergedConfig::default();
+
+    for entry in path.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        for file in expand_config_path(Path::new(entry))? {
+            load_config_file(&file, &mut merged, env)?;
+        }
+    }
+
+    if merged.loaded_files.is_empty() {
+        return Err(format!("No configuration files found for '{path}'").into());
+    }
+
+    let mut gateway_config = merged.config;
+    normalize_names(&mut gateway_config);
 
     Ok(gateway_config)
 }
@@ -107,13 +466,190 @@ fn parse_env_var_bool(var: &str) -> Option<bool> {
     }
 }
 
-pub fn get_trace_content_enabled() -> bool {
-    // Always check environment variable first (useful for database mode)
-    if let Ok(env_value) = std::env::var("TRACE_CONTENT_ENABLED") {
+/// The `general` settings read while serving requests, with their defaults filled in and
+/// the environment variables overriding them applied.
+///
+/// Each `AppState` holds those of the configuration it's serving and swaps them when a new
+/// one is applied, so they follow config reloads and two hubs in one process don't share
+/// them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeSettings {
+    pub trace_content_enabled: bool,
+    pub trace_content: TraceContentPolicy,
+    pub timing_headers: bool,
+    pub allow_debug_headers: bool,
+    pub safety_block_behavior: SafetyBlockBehavior,
+    pub prefix_routing: bool,
+    pub expose_available_models: bool,
+    pub case_insensitive_lookups: bool,
+    pub reuse_port: bool,
+    pub forward_traceloop_headers: bool,
+    pub attribution_headers: bool,
+    pub idempotency_ttl: Duration,
+    pub resumable_stream_ttl: Duration,
+    pub passthrough_response_headers: PassthroughHeaders,
+    pub stream_buffer_chunks: usize,
+    pub stream_buffer_max_bytes: usize,
+    pub max_buffered_body_bytes: usize,
+    pub slow_stream: Option<SlowStreamConfig>,
+}
+
+impl RuntimeSettings {
+    /// The settings `general` sets, overridden by the environment. Without a `general`
+    /// section, the defaults overridden by the environment.
+    pub fn from_general(general: Option<&General>) -> Self {
+        let mut settings = match general {
+            Some(general) => Self::from_config(general),
+            None => Self::default(),
+        };
+        settings.apply_env();
+        settings
+    }
+
+    fn from_config(general: &General) -> Self {
+        Self {
+            trace_content_enabled: general.trace_content_enabled,
+            trace_content: general.trace_content.unwrap_or_default(),
+            timing_headers: general.timing_headers,
+            allow_debug_headers: general.allow_debug_headers,
+            safety_block_behavior: general.safety_block_behavior,
+            prefix_routing: general.prefix_routing,
+            expose_available_models: general.expose_available_models,
+            case_insensitive_lookups: general.case_insensitive_lookups,
+            reuse_port: general.reuse_port,
+            forward_traceloop_headers: general.forward_traceloop_headers,
+            attribution_headers: general.attribution_headers,
+            idempotency_ttl: Duration::from_secs(
+                general
+                    .idempotency_ttl_seconds
+                    .unwrap_or(DEFAULT_IDEMPOTENCY_TTL_SECONDS),
+            ),
+            resumable_stream_ttl: Duration::from_secs(
+                general
+                    .resumable_stream_ttl_seconds
+                    .unwrap_or(DEFAULT_RESUMABLE_STREAM_TTL_SECONDS),
+            ),
+            passthrough_response_headers: general
+                .passthrough_response_headers
+                .clone()
+                .unwrap_or_default(),
+            stream_buffer_chunks: general
+                .stream_buffer_chunks
+                .unwrap_or(DEFAULT_STREAM_BUFFER_CHUNKS),
+            stream_buffer_max_bytes: general
+                .stream_buffer_max_bytes
+                .unwrap_or(DEFAULT_STREAM_BUFFER_MAX_BYTES),
+            max_buffered_body_bytes: general
+                .max_buffered_body_bytes
+                .unwrap_or(DEFAULT_MAX_BUFFERED_BODY_BYTES),
+            slow_stream: general.slow_stream,
+        }
+    }
+
+    // The environment variables are useful in database mode, where there's no `general`
+    // section to set these in.
+    fn apply_env(&mut self) {
+        override_bool("TRACE_CONTENT_ENABLED", &mut self.trace_content_enabled);
+        override_bool("TIMING_HEADERS_ENABLED", &mut self.timing_headers);
+        override_bool("ALLOW_DEBUG_HEADERS", &mut self.allow_debug_headers);
+        override_parsed("SAFETY_BLOCK_BEHAVIOR", &mut self.safety_block_behavior);
+        override_bool("PREFIX_ROUTING", &mut self.prefix_routing);
+        override_bool("EXPOSE_AVAILABLE_MODELS", &mut self.expose_available_models);
+        override_bool(
+            "CASE_INSENSITIVE_LOOKUPS",
+            &mut self.case_insensitive_lookups,
+        );
+        override_bool("REUSE_PORT", &mut self.reuse_port);
+        override_bool(
+            "FORWARD_TRACELOOP_HEADERS",
+            &mut self.forward_traceloop_headers,
+        );
+        override_bool("ATTRIBUTION_HEADERS", &mut self.attribution_headers);
+        override_seconds("IDEMPOTENCY_TTL_SECONDS", &mut self.idempotency_ttl);
+        override_seconds(
+            "RESUMABLE_STREAM_TTL_SECONDS",
+            &mut self.resumable_stream_ttl,
+        );
+        override_parsed("STREAM_BUFFER_CHUNKS", &mut self.stream_buffer_chunks);
+        override_parsed("STREAM_BUFFER_MAX_BYTES", &mut self.stream_buffer_max_bytes);
+        override_parsed("MAX_BUFFERED_BODY_BYTES", &mut self.max_buffered_body_bytes);
+        // A comma-separated list.
+        if let Ok(env_value) = std::env::var("PASSTHROUGH_RESPONSE_HEADERS") {
+            self.passthrough_response_headers.headers = env_value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        override_bool(
+            "PASSTHROUGH_HEADER_PREFIX",
+            &mut self.passthrough_response_headers.prefix,
+        );
+    }
+
+    /// The rules traces and artifacts apply to content. Turning `trace_content_enabled`
+    /// off, in the config or through its env var, excludes everything.
+    pub fn trace_content_policy(&self) -> TraceContentPolicy {
+        if !self.trace_content_enabled {
+            return TraceContentPolicy::EXCLUDE_ALL;
+        }
+        self.trace_content
+    }
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            trace_content_enabled: true,
+            trace_content: TraceContentPolicy::default(),
+            timing_headers: false,
+            allow_debug_headers: false,
+            safety_block_behavior: SafetyBlockBehavior::default(),
+            prefix_routing: false,
+            expose_available_models: false,
+            case_insensitive_lookups: false,
+            reuse_port: false,
+            forward_traceloop_headers: false,
+            attribution_headers: false,
+            idempotency_ttl: Duration::from_secs(DEFAULT_IDEMPOTENCY_TTL_SECONDS),
+            resumable_stream_ttl: Duration::from_secs(DEFAULT_RESUMABLE_STREAM_TTL_SECONDS),
+            passthrough_response_headers: PassthroughHeaders::default(),
+            stream_buffer_chunks: DEFAULT_STREAM_BUFFER_CHUNKS,
+            stream_buffer_max_bytes: DEFAULT_STREAM_BUFFER_MAX_BYTES,
+            max_buffered_body_bytes: DEFAULT_MAX_BUFFERED_BODY_BYTES,
+            slow_stream: None,
+        }
+    }
+}
+
+impl GatewayConfig {
+    /// The runtime settings this configuration's `general` section sets.
+    pub fn settings(&self) -> RuntimeSettings {
+        RuntimeSettings::from_general(self.general.as_ref())
+    }
+}
+
+fn override_bool(var: &str, setting: &mut bool) {
+    if let Ok(env_value) = std::env::var(var) {
         if let Some(val) = parse_env_var_bool(&env_value) {
-            return val;
+            *setting = val;
+        }
+    }
+}
+
+fn override_parsed<T: std::str::FromStr>(var: &str, setting: &mut T) {
+    if let Ok(env_value) = std::env::var(var) {
+        if let Ok(val) = env_value.parse() {
+            *setting = val;
+        }
+    }
+}
+
+fn override_seconds(var: &str, setting: &mut Duration) {
+    if let Ok(env_value) = std::env::var(var) {
+        if let Ok(seconds) = env_value.parse() {
+            *setting = Duration::from_secs(seconds);
         }
     }
-    // Fall back to config value or default true
-    *TRACE_CONTENT_ENABLED.get_or_init(|| true)
 }
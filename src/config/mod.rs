@@ -2,6 +2,8 @@ pub mod constants;
 pub mod hash;
 pub mod lib;
 pub mod models;
+pub mod names;
+pub mod redaction;
 pub mod validation;
 
 pub use lib::load_config;
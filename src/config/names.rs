@@ -0,0 +1,130 @@
+//! Rules for pipeline names and model and provider keys. YAML validation and the management
+//! API apply them when names are created; request routing applies [`lookup_matches`].
+
+use crate::types::{GatewayConfig, PluginConfig};
+
+/// Longest pipeline name or model or provider key.
+pub const MAX_NAME_LENGTH: usize = 128;
+
+/// `name` as it is stored: without surrounding whitespace.
+pub fn normalize_name(name: &str) -> String {
+    name.trim().to_string()
+}
+
+/// Checks that a normalized name is 1-128 letters, digits, `.`, `_` or `-`. `kind` says what
+/// the name is in the error, e.g. `Pipeline name`.
+pub fn validate_name(kind: &str, name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err(format!("{kind} must not be empty"));
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "{kind} '{name}' is longer than {MAX_NAME_LENGTH} characters"
+        ));
+    }
+    match name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+    {
+        Some(c) => Err(format!(
+            "{kind} '{name}' contains {c:?}; use letters, digits, '.', '_' or '-'"
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Whether two names clash. Names must be unique ignoring case, so they stay unambiguous when
+/// lookups ignore it.
+pub fn same_name(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+/// Whether `requested`, a name from a request's header or body, refers to `configured`.
+/// Case only counts unless `case_insensitive` is set, as by
+/// `general.case_insensitive_lookups`.
+pub fn lookup_matches(configured: &str, requested: &str, case_insensitive: bool) -> bool {
+    let requested = requested.trim();
+    if case_insensitive {
+        configured.eq_ignore_ascii_case(requested)
+    } else {
+        configured == requested
+    }
+}
+
+/// Trims the names and keys declared in `config`, and the references to them that would
+/// otherwise stop matching.
+pub fn normalize_names(config: &mut GatewayConfig) {
+    for provider in &mut config.providers {
+        provider.key = normalize_name(&provider.key);
+    }
+    for model in &mut config.models {
+        model.key = normalize_name(&model.key);
+        model.provider = normalize_name(&model.provider);
+    }
+    for pipeline in &mut config.pipelines {
+        pipeline.name = normalize_name(&pipeline.name);
+        for plugin in &mut pipeline.plugins {
+            if let PluginConfig::ModelRouter { models, .. } = plugin {
+                for model in models {
+                    *model = normalize_name(model);
+                }
+            }
+        }
+    }
+}
+
+/// Errors for each name in `names` that is invalid or clashes with an earlier one.
+pub fn check_names<'a>(
+    kind: &str,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Vec<(&'a str, String)> {
+    let mut seen: Vec<&str> = Vec::new();
+    let mut errors = Vec::new();
+    for name in names {
+        if let Err(e) = validate_name(kind, name) {
+            errors.push((name, e));
+        }
+        match seen.iter().copied().find(|other| same_name(other, name)) {
+            Some(other) if other == name => {
+                errors.push((name, format!("{kind} '{name}' is used more than once")));
+            }
+            Some(other) => errors.push((
+                name,
+                format!("{kind} '{name}' differs from '{other}' only by case"),
+            )),
+            None => seen.push(name),
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("Model key", "gpt-4o_mini.v2").is_ok());
+        assert_eq!(
+            validate_name("Model key", "gpt 4o").unwrap_err(),
+            "Model key 'gpt 4o' contains ' '; use letters, digits, '.', '_' or '-'"
+        );
+        assert!(validate_name("Model key", "openai/gpt-4o").is_err());
+        assert!(validate_name("Model key", "").is_err());
+        assert!(validate_name("Model key", &"a".repeat(MAX_NAME_LENGTH)).is_ok());
+        assert!(validate_name("Model key", &"a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_check_names_ignores_case() {
+        let errors = check_names("Pipeline name", ["default", "Default", "default", "ok"]);
+        let messages: Vec<&str> = errors.iter().map(|(_, e)| e.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "Pipeline name 'Default' differs from 'default' only by case",
+                "Pipeline name 'default' is used more than once",
+            ]
+        );
+    }
+}
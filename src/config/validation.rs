@@ -1,18 +1,171 @@
-use crate::types::GatewayConfig;
+use crate::ai_models::params::validate_default_params;
+use crate::artifacts::validate_artifact_store;
+use crate::compression::validate_compression;
+use crate::config::names::check_names;
+use crate::cors::validate_cors;
+use crate::metrics::validate_otlp_metrics;
+use crate::models::chat::validate_metadata;
+use crate::notifications::validate_notifications;
+use crate::pipelines::adaptive_routing::validate_adaptive_routing;
+use crate::pipelines::cost::{COST_PARAMS, parse_price};
+use crate::pipelines::degraded_mode::{degraded_mode_settings, validate_degraded_mode};
+use crate::pipelines::deprecation::validate_model_deprecation;
+use crate::pipelines::parameter_policy::validate_parameter_policy;
+use crate::pipelines::race::validate_race_routing;
+use crate::pipelines::system_prompt::{PromptTemplate, validate_system_prompt};
+use crate::pipelines::tool_limits::validate_tool_limits;
+use crate::providers::allowed_models::{
+    ALLOWED_MODEL_PREFIXES_PARAM, ANY_MODEL_PREFIX, check_model_type, parse_model_prefixes,
+    provider_model_prefixes,
+};
+use crate::providers::api_keys::{
+    API_KEY_FILE_PARAM, API_KEY_SECRET_PARAM, UNRESOLVED_SECRETS_PARAM, api_key_file_refresh,
+    api_key_secret, api_key_secret_retry,
+};
+use crate::providers::azure::entra::validate_auth_params;
+use crate::providers::failover::{provider_group, validate_failover_groups};
+use crate::providers::http_client::{
+    PROXY_URL_PARAM, build_http_client, has_tls_params, validate_proxy_url,
+};
+use crate::providers::maintenance::validate_maintenance_window;
+use crate::providers::mock::{MODE_PARAM, RESPONSE_PARAM, validate_mock_params};
+use crate::providers::registry::has_factory;
+use crate::types::{GatewayConfig, PipelineType, ProviderType};
+use crate::upstream_headers::validate_passthrough_headers;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::fmt;
+use utoipa::ToSchema;
+
+/// Exit code of the gateway when it refuses to start because of an invalid configuration.
+pub const CONFIG_ERROR_EXIT_CODE: i32 = 64;
+
+/// Whether a validation finding stops a configuration from being applied.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a configuration, in a form tooling can parse.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct ValidationError {
+    /// Identifies the check that failed, e.g. `unknown_provider`.
+    #[schema(example = "unknown_provider")]
+    pub code: String,
+    /// Where in the configuration the problem is, e.g. `models[gpt-4o].provider`.
+    #[schema(example = "models[gpt-4o].provider")]
+    pub path: String,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl ValidationError {
+    pub fn error(code: &str, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            path: path.into(),
+            message: message.into(),
+            severity: Severity::Error,
+        }
+    }
+
+    pub fn warning(code: &str, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, path, message)
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{} at {}]", self.message, self.code, self.path)
+    }
+}
+
+/// Returned when a configuration fails validation, with everything that was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidConfig(pub Vec<ValidationError>);
+
+impl InvalidConfig {
+    /// A configuration that couldn't be read at all, e.g. malformed YAML.
+    pub fn unreadable(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self(vec![ValidationError::error(
+            "unreadable_config",
+            path,
+            message,
+        )])
+    }
+
+    /// The findings as a JSON array, as printed on startup failures.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.0).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration: ")?;
+        for (index, error) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidConfig {}
+
+fn provider_path(key: &str) -> String {
+    format!("providers[{key}]")
+}
+
+fn model_path(key: &str) -> String {
+    format!("models[{key}]")
+}
+
+fn plugin_path(pipeline: &str, plugin: &str) -> String {
+    format!("pipelines[{pipeline}].plugins.{plugin}")
+}
 
 /// Validates the logical consistency of a GatewayConfig.
-/// Returns Ok(()) if valid, or Err(Vec<String>) with a list of error messages if invalid.
-pub fn validate_gateway_config(config: &GatewayConfig) -> Result<(), Vec<String>> {
+/// Returns Ok(()) if valid, or Err with every finding, warnings included, if any of them is
+/// an error. Warnings alone are logged and don't fail validation.
+pub fn validate_gateway_config(config: &GatewayConfig) -> Result<(), Vec<ValidationError>> {
+    let findings = check_gateway_config(config);
+    if findings.iter().any(|f| f.severity == Severity::Error) {
+        return Err(findings);
+    }
+    for warning in findings {
+        tracing::warn!("{warning}");
+    }
+    Ok(())
+}
+
+/// Runs every check on `config` and returns what they found.
+pub fn check_gateway_config(config: &GatewayConfig) -> Vec<ValidationError> {
     let mut errors = Vec::new();
 
-    // Check 1: Provider references in Models must exist
-    let provider_keys: HashSet<&String> = config.providers.iter().map(|p| &p.key).collect();
+    // Check 1: Provider references in Models must exist, as a provider or a failover group
+    let provider_keys: HashSet<&str> = config
+        .providers
+        .iter()
+        .map(|p| p.key.as_str())
+        .chain(config.providers.iter().filter_map(provider_group))
+        .collect();
     for model in &config.models {
-        if !provider_keys.contains(&model.provider) {
-            errors.push(format!(
-                "Model '{}' references non-existent provider '{}'.",
-                model.key, model.provider
+        if !provider_keys.contains(model.provider.as_str()) {
+            errors.push(ValidationError::error(
+                "unknown_provider",
+                format!("{}.provider", model_path(&model.key)),
+                format!(
+                    "Model '{}' references non-existent provider '{}'.",
+                    model.key, model.provider
+                ),
             ));
         }
     }
@@ -23,13 +176,30 @@ pub fn validate_gateway_config(config: &GatewayConfig) -> Result<(), Vec<String>
         for plugin in &pipeline.plugins {
             if let crate::types::PluginConfig::ModelRouter {
                 models: router_models,
+                allow_dynamic_models,
+                ..
             } = plugin
             {
+                let path = plugin_path(&pipeline.name, "model-router");
+                if *allow_dynamic_models && router_models.is_empty() {
+                    errors.push(ValidationError::error(
+                        "dynamic_models_without_models",
+                        format!("{path}.models"),
+                        format!(
+                            "Pipeline '{}'s ModelRouter allows dynamic models but lists no models to take providers from.",
+                            pipeline.name
+                        ),
+                    ));
+                }
                 for model_key in router_models {
                     if !model_keys.contains(model_key) {
-                        errors.push(format!(
-                            "Pipeline '{}'s ModelRouter references non-existent model '{}'.",
-                            pipeline.name, model_key
+                        errors.push(ValidationError::error(
+                            "unknown_model",
+                            format!("{path}.models"),
+                            format!(
+                                "Pipeline '{}'s ModelRouter references non-existent model '{}'.",
+                                pipeline.name, model_key
+                            ),
                         ));
                     }
                 }
@@ -37,103 +207,2238 @@ pub fn validate_gateway_config(config: &GatewayConfig) -> Result<(), Vec<String>
         }
     }
 
-    // Add more validation checks as needed:
-    // - Duplicate keys for providers, models, pipelines?
-    // - Empty names/keys?
-    // - Specific validation for provider params based on type (more complex, might be out of scope for basic validation)
+    // Check 3: Proxy URLs must be well-formed
+    if let Some(default_proxy_url) = config
+        .general
+        .as_ref()
+        .and_then(|g| g.default_proxy_url.as_ref())
+    {
+        if let Err(e) = validate_proxy_url(default_proxy_url) {
+            errors.push(ValidationError::error(
+                "invalid_proxy_url",
+                "general.default_proxy_url",
+                format!("general.default_proxy_url is invalid: {e}."),
+            ));
+        }
+    }
+    for provider in &config.providers {
+        if let Some(proxy_url) = provider.params.get(PROXY_URL_PARAM) {
+            if let Err(e) = validate_proxy_url(proxy_url) {
+                errors.push(ValidationError::error(
+                    "invalid_proxy_url",
+                    format!("{}.params.{PROXY_URL_PARAM}", provider_path(&provider.key)),
+                    format!("Provider '{}' has an invalid proxy_url: {e}.", provider.key),
+                ));
+            }
+        }
+    }
+
+    // Check 4: Custom TLS material must load
+    for provider in &config.providers {
+        if has_tls_params(provider) {
+            if let Err(e) = build_http_client(provider) {
+                errors.push(ValidationError::error(
+                    "invalid_tls",
+                    format!("{}.params", provider_path(&provider.key)),
+                    format!(
+                        "Provider '{}' has invalid TLS settings: {e:#}.",
+                        provider.key
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Check 5: Model prices must be non-negative numbers
+    for model in &config.models {
+        for param in COST_PARAMS {
+            if let Some(value) = model.params.get(param) {
+                if let Err(e) = parse_price(value) {
+                    errors.push(ValidationError::error(
+                        "invalid_price",
+                        format!("{}.params.{param}", model_path(&model.key)),
+                        format!("Model '{}' has an invalid {param}: {e}.", model.key),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Check 6: Budget limits must be positive with a sane warning threshold
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            if let crate::types::PluginConfig::Budget {
+                limit_usd,
+                warn_at_percent,
+                ..
+            } = plugin
+            {
+                let path = plugin_path(&pipeline.name, "budget");
+                if !(limit_usd.0.is_finite() && limit_usd.0 > 0.0) {
+                    errors.push(ValidationError::error(
+                        "invalid_budget",
+                        format!("{path}.limit_usd"),
+                        format!(
+                            "Pipeline '{}' budget limit_usd must be a positive number.",
+                            pipeline.name
+                        ),
+                    ));
+                }
+                if !(1..=100).contains(warn_at_percent) {
+                    errors.push(ValidationError::error(
+                        "invalid_budget",
+                        format!("{path}.warn_at_percent"),
+                        format!(
+                            "Pipeline '{}' budget warn_at_percent must be between 1 and 100.",
+                            pipeline.name
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Check 7: Pipeline metadata must fit OpenAI's metadata limits
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            if let crate::types::PluginConfig::Metadata { values } = plugin {
+                if let Err(e) = validate_metadata(values.iter()) {
+                    errors.push(ValidationError::error(
+                        "invalid_metadata",
+                        format!("{}.values", plugin_path(&pipeline.name, "metadata")),
+                        format!("Pipeline '{}' has invalid metadata: {e}.", pipeline.name),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Check 8: Logging sample rates must be fractions
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            if let crate::types::PluginConfig::Logging { sample_rate, .. } = plugin {
+                if !(0.0..=1.0).contains(&sample_rate.0) {
+                    errors.push(ValidationError::error(
+                        "invalid_sample_rate",
+                        format!("{}.sample_rate", plugin_path(&pipeline.name, "logging")),
+                        format!(
+                            "Pipeline '{}' has logging sample_rate {} outside 0.0-1.0.",
+                            pipeline.name, sample_rate.0
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Check 9: Pipelines must keep at least one enabled model
+    let disabled_models: HashSet<&String> = config
+        .models
+        .iter()
+        .filter(|m| !m.enabled)
+        .map(|m| &m.key)
+        .collect();
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            if let crate::types::PluginConfig::ModelRouter {
+                models: router_models,
+                ..
+            } = plugin
+            {
+                let disabled: Vec<&String> = router_models
+                    .iter()
+                    .filter(|key| disabled_models.contains(key))
+                    .collect();
+                if disabled.is_empty() {
+                    continue;
+                }
+                let path = format!("{}.models", plugin_path(&pipeline.name, "model-router"));
+                if disabled.len() == router_models.len() {
+                    errors.push(ValidationError::error(
+                        "all_models_disabled",
+                        path,
+                        format!(
+                            "Pipeline '{}'s ModelRouter only references disabled models.",
+                            pipeline.name
+                        ),
+                    ));
+                } else {
+                    errors.push(ValidationError::warning(
+                        "disabled_models",
+                        path,
+                        format!(
+                            "Pipeline '{}'s ModelRouter references disabled models: {:?}",
+                            pipeline.name, disabled
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Check 10: Default sampling params on models must be in range
+    for model in &config.models {
+        if let Err(e) = validate_default_params(&model.params) {
+            errors.push(ValidationError::error(
+                "invalid_default_param",
+                format!("{}.params", model_path(&model.key)),
+                format!("Model '{}' has an invalid default: {e}.", model.key),
+            ));
+        }
+    }
+
+    // Check 11: CORS settings must be usable by browsers
+    if let Some(cors) = config.general.as_ref().and_then(|g| g.cors.as_ref()) {
+        errors.extend(
+            validate_cors(cors)
+                .into_iter()
+                .map(|e| ValidationError::error("invalid_cors", "general.cors", e)),
+        );
+    }
+
+    // Check 12: Compression needs at least one algorithm
+    if let Some(compression) = config.general.as_ref().and_then(|g| g.compression.as_ref()) {
+        errors.extend(
+            validate_compression(compression)
+                .into_iter()
+                .map(|e| ValidationError::error("invalid_compression", "general.compression", e)),
+        );
+    }
+
+    // Check 13: Parameter policies must have usable rules and extra_body settings
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            if let crate::types::PluginConfig::ParameterPolicy {
+                rules,
+                allow_extra_body,
+                extra_body_keys,
+                ..
+            } = plugin
+            {
+                if let Err(e) = validate_parameter_policy(rules, *allow_extra_body, extra_body_keys)
+                {
+                    errors.push(ValidationError::error(
+                        "invalid_parameter_policy",
+                        plugin_path(&pipeline.name, "parameter-policy"),
+                        format!("Pipeline '{}': {e}.", pipeline.name),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Check 14: Admission control needs at least one in-flight slot
+    if config
+        .general
+        .as_ref()
+        .and_then(|g| g.max_in_flight_requests)
+        == Some(0)
+    {
+        errors.push(ValidationError::error(
+            "invalid_admission_limit",
+            "general.max_in_flight_requests",
+            "general.max_in_flight_requests must be greater than 0.",
+        ));
+    }
+
+    // Check 15: Deprecated models must point at a usable replacement
+    for model in &config.models {
+        if let Err(e) = validate_model_deprecation(model, &config.models) {
+            errors.push(ValidationError::error(
+                "invalid_deprecation",
+                format!("{}.deprecation", model_path(&model.key)),
+                format!("Model '{}': {e}.", model.key),
+            ));
+        }
+        let deprecation = &model.deprecation;
+        let (Some(replacement), true) = (&deprecation.replacement, deprecation.auto_replace) else {
+            continue;
+        };
+        for pipeline in &config.pipelines {
+            for plugin in &pipeline.plugins {
+                if let crate::types::PluginConfig::ModelRouter { models, .. } = plugin {
+                    if models.contains(&model.key) && !models.contains(replacement) {
+                        errors.push(ValidationError::error(
+                            "replacement_not_routed",
+                            format!("{}.models", plugin_path(&pipeline.name, "model-router")),
+                            format!(
+                                "Pipeline '{}' routes '{}' but not its auto_replace replacement '{}'.",
+                                pipeline.name, model.key, replacement
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    // Check 16: Notification webhooks and alert thresholds must be usable
+    if let Some(notifications) = config
+        .general
+        .as_ref()
+        .and_then(|g| g.notifications.as_ref())
+    {
+        errors.extend(
+            validate_notifications(notifications).into_iter().map(|e| {
+                ValidationError::error("invalid_notifications", "general.notifications", e)
+            }),
+        );
+    }
+
+    // Check 17: Azure providers need a complete set of auth settings. A provider whose
+    // secrets didn't resolve is left out; it fails its own requests instead.
+    for provider in &config.providers {
+        if provider.r#type == ProviderType::Azure
+            && !provider.params.contains_key(UNRESOLVED_SECRETS_PARAM)
+        {
+            if let Err(e) = validate_auth_params(&provider.params) {
+                errors.push(ValidationError::error(
+                    "invalid_auth",
+                    format!("{}.params", provider_path(&provider.key)),
+                    format!(
+                        "Provider '{}' has invalid auth settings: {e}.",
+                        provider.key
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Check 18: Adaptive routing settings must be in range
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            if let crate::types::PluginConfig::ModelRouter {
+                adaptive: Some(adaptive),
+                ..
+            } = plugin
+            {
+                if let Err(e) = validate_adaptive_routing(adaptive) {
+                    errors.push(ValidationError::error(
+                        "invalid_adaptive_routing",
+                        format!("{}.adaptive", plugin_path(&pipeline.name, "model-router")),
+                        format!(
+                            "Pipeline '{}'s ModelRouter has invalid adaptive settings: {e}.",
+                            pipeline.name
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Check 19: The artifact store must be usable, and configured wherever it is used
+    let artifact_store = config
+        .general
+        .as_ref()
+        .and_then(|g| g.artifact_store.as_ref());
+    if let Some(artifact_store) = artifact_store {
+        errors.extend(
+            validate_artifact_store(artifact_store)
+                .into_iter()
+                .map(|e| {
+                    ValidationError::error("invalid_artifact_store", "general.artifact_store", e)
+                }),
+        );
+    }
+    for pipeline in &config.pipelines {
+        if pipeline.store_artifacts && artifact_store.is_none() {
+            errors.push(ValidationError::error(
+                "missing_artifact_store",
+                format!("pipelines[{}].store_artifacts", pipeline.name),
+                format!(
+                    "Pipeline '{}' sets store_artifacts but general.artifact_store is not configured.",
+                    pipeline.name
+                ),
+            ));
+        }
+    }
+
+    // Check 20: Failover groups must have consistent members and a name of their own
+    errors.extend(
+        validate_failover_groups(&config.providers)
+            .into_iter()
+            .map(|e| ValidationError::error("invalid_failover_group", "providers", e)),
+    );
+
+    // Check 21: Idempotent responses and resumable streams must be kept for some time
+    if config
+        .general
+        .as_ref()
+        .and_then(|g| g.idempotency_ttl_seconds)
+        == Some(0)
+    {
+        errors.push(ValidationError::error(
+            "invalid_idempotency_ttl",
+            "general.idempotency_ttl_seconds",
+            "general.idempotency_ttl_seconds must be greater than 0.",
+        ));
+    }
+
+    if config
+        .general
+        .as_ref()
+        .and_then(|g| g.resumable_stream_ttl_seconds)
+        == Some(0)
+    {
+        errors.push(ValidationError::error(
+            "invalid_resumable_stream_ttl",
+            "general.resumable_stream_ttl_seconds",
+            "general.resumable_stream_ttl_seconds must be greater than 0.",
+        ));
+    }
+
+    // Check 22: An API key comes either from the config, a file or a secret reference,
+    // never more than one
+    for provider in &config.providers {
+        let params_path = format!("{}.params", provider_path(&provider.key));
+        if let Some(secret) = api_key_secret(provider) {
+            if let Err(e) = secret {
+                errors.push(ValidationError::error(
+                    "invalid_api_key_secret",
+                    format!("{params_path}.{API_KEY_SECRET_PARAM}"),
+                    format!(
+                        "Provider '{}' has an invalid {API_KEY_SECRET_PARAM}: {e}.",
+                        provider.key
+                    ),
+                ));
+            }
+            if !provider.api_key.is_empty() || provider.params.contains_key(API_KEY_FILE_PARAM) {
+                errors.push(ValidationError::error(
+                    "invalid_api_key_secret",
+                    format!("{params_path}.{API_KEY_SECRET_PARAM}"),
+                    format!(
+                        "Provider '{}' sets {API_KEY_SECRET_PARAM} along with another API key.",
+                        provider.key
+                    ),
+                ));
+            }
+            if let Err(e) = api_key_secret_retry(provider) {
+                errors.push(ValidationError::error(
+                    "invalid_api_key_secret",
+                    params_path.clone(),
+                    format!(
+                        "Provider '{}' has an invalid API key secret retry interval: {e}.",
+                        provider.key
+                    ),
+                ));
+            }
+        }
+
+        let Some(path) = provider.params.get(API_KEY_FILE_PARAM) else {
+            continue;
+        };
+        if path.trim().is_empty() {
+            errors.push(ValidationError::error(
+                "invalid_api_key_file",
+                format!("{params_path}.{API_KEY_FILE_PARAM}"),
+                format!(
+                    "Provider '{}' has an empty {API_KEY_FILE_PARAM}.",
+                    provider.key
+                ),
+            ));
+        }
+        if !provider.api_key.is_empty() {
+            errors.push(ValidationError::error(
+                "invalid_api_key_file",
+                format!("{}.api_key", provider_path(&provider.key)),
+                format!(
+                    "Provider '{}' sets both api_key and {API_KEY_FILE_PARAM}.",
+                    provider.key
+                ),
+            ));
+        }
+        if let Err(e) = api_key_file_refresh(provider) {
+            errors.push(ValidationError::error(
+                "invalid_api_key_file",
+                params_path,
+                format!(
+                    "Provider '{}' has an invalid API key file refresh interval: {e}.",
+                    provider.key
+                ),
+            ));
+        }
+    }
+
+    // Check 23: Maintenance windows must be valid one-off or recurring windows
+    for provider in &config.providers {
+        for (index, window) in provider.maintenance_windows.iter().enumerate() {
+            if let Err(e) = validate_maintenance_window(window) {
+                errors.push(ValidationError::error(
+                    "invalid_maintenance_window",
+                    format!(
+                        "{}.maintenance_windows[{index}]",
+                        provider_path(&provider.key)
+                    ),
+                    format!(
+                        "Provider '{}' has an invalid maintenance window #{}: {e}.",
+                        provider.key,
+                        index + 1
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Check 24: Degraded mode needs usable thresholds and an existing substitute model
+    for pipeline in &config.pipelines {
+        let Some(degraded_mode) = degraded_mode_settings(pipeline) else {
+            continue;
+        };
+        let path = plugin_path(&pipeline.name, "degraded-mode");
+        if let Err(e) = validate_degraded_mode(degraded_mode) {
+            errors.push(ValidationError::error(
+                "invalid_degraded_mode",
+                path.clone(),
+                format!(
+                    "Pipeline '{}' has invalid degraded-mode settings: {e}.",
+                    pipeline.name
+                ),
+            ));
+        }
+        if !config.models.iter().any(|m| m.key == degraded_mode.model) {
+            errors.push(ValidationError::error(
+                "unknown_model",
+                format!("{path}.model"),
+                format!(
+                    "Pipeline '{}' degrades to non-existent model '{}'.",
+                    pipeline.name, degraded_mode.model
+                ),
+            ));
+        }
+        if pipeline.r#type != PipelineType::Chat {
+            errors.push(ValidationError::error(
+                "invalid_degraded_mode",
+                path,
+                format!(
+                    "Pipeline '{}' uses degraded-mode, which only applies to chat pipelines.",
+                    pipeline.name
+                ),
+            ));
+        }
+    }
+
+    // Check 25: Passed-through upstream headers must be valid and not replace the hub's own
+    if let Some(passthrough) = config
+        .general
+        .as_ref()
+        .and_then(|g| g.passthrough_response_headers.as_ref())
+    {
+        if let Err(e) = validate_passthrough_headers(passthrough) {
+            errors.push(ValidationError::error(
+                "invalid_passthrough_headers",
+                "general.passthrough_response_headers",
+                format!("general.passthrough_response_headers is invalid: {e}."),
+            ));
+        }
+    }
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            if let crate::types::PluginConfig::PassthroughHeaders(passthrough) = plugin {
+                if let Err(e) = validate_passthrough_headers(passthrough) {
+                    errors.push(ValidationError::error(
+                        "invalid_passthrough_headers",
+                        plugin_path(&pipeline.name, "passthrough-headers"),
+                        format!(
+                            "Pipeline '{}' has invalid passthrough headers: {e}.",
+                            pipeline.name
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Check 26: Mock providers need valid settings, and a reply for each model in fixed mode
+    for provider in &config.providers {
+        if provider.r#type != ProviderType::Mock {
+            continue;
+        }
+        if let Err(e) = validate_mock_params(&provider.params) {
+            errors.push(ValidationError::error(
+                "invalid_mock_provider",
+                format!("{}.params", provider_path(&provider.key)),
+                format!("Mock provider '{}' is invalid: {e}.", provider.key),
+            ));
+            continue;
+        }
+        if provider.params.get(MODE_PARAM).map(|mode| mode.trim()) != Some("fixed")
+            || provider.params.contains_key(RESPONSE_PARAM)
+        {
+            continue;
+        }
+        for model in &config.models {
+            if model.provider == provider.key && !model.params.contains_key(RESPONSE_PARAM) {
+                errors.push(ValidationError::warning(
+                    "missing_mock_response",
+                    format!("{}.params", model_path(&model.key)),
+                    format!(
+                        "Model '{}' has no response for fixed mock provider '{}' and will reply with empty text.",
+                        model.key, provider.key
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Check 27: trace_content rules only apply while trace content is enabled
+    if let Some(general) = &config.general {
+        if general.trace_content.is_some() && !general.trace_content_enabled {
+            errors.push(ValidationError::warning(
+                "ignored_trace_content",
+                "general.trace_content",
+                "general.trace_content is ignored because trace_content_enabled is false; all content is excluded.",
+            ));
+        }
+    }
+
+    // Check 28: Race candidates must be router models, and racing excludes adaptive routing
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            let crate::types::PluginConfig::ModelRouter {
+                models,
+                adaptive,
+                race: Some(race),
+                ..
+            } = plugin
+            else {
+                continue;
+            };
+            let path = format!("{}.race", plugin_path(&pipeline.name, "model-router"));
+            if adaptive.is_some() {
+                errors.push(ValidationError::error(
+                    "invalid_race_routing",
+                    path.clone(),
+                    format!(
+                        "Pipeline '{}'s ModelRouter can't use both the adaptive and race strategies.",
+                        pipeline.name
+                    ),
+                ));
+            }
+            if let Err(e) = validate_race_routing(race, models) {
+                errors.push(ValidationError::error(
+                    "invalid_race_routing",
+                    path,
+                    format!(
+                        "Pipeline '{}'s ModelRouter has invalid race settings: {e}.",
+                        pipeline.name
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Check 29: Tool limits need at least one usable setting and only apply to chat pipelines
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            let crate::types::PluginConfig::ToolLimits(tool_limits) = plugin else {
+                continue;
+            };
+            let path = plugin_path(&pipeline.name, "tool-limits");
+            if let Err(e) = validate_tool_limits(tool_limits) {
+                errors.push(ValidationError::error(
+                    "invalid_tool_limits",
+                    path.clone(),
+                    format!(
+                        "Pipeline '{}' has invalid tool-limits settings: {e}.",
+                        pipeline.name
+                    ),
+                ));
+            }
+            if pipeline.r#type != PipelineType::Chat {
+                errors.push(ValidationError::error(
+                    "invalid_tool_limits",
+                    path,
+                    format!(
+                        "Pipeline '{}' uses tool-limits, which only applies to chat pipelines.",
+                        pipeline.name
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Check 30: Prompt templates parse, and system prompts name one and set its variables
+    for (name, template) in &config.prompt_templates {
+        if let Err(e) = PromptTemplate::parse(template) {
+            errors.push(ValidationError::error(
+                "invalid_prompt_template",
+                format!("prompt_templates[{name}]"),
+                format!("Prompt template '{name}' is invalid: {e}."),
+            ));
+        }
+    }
+    for pipeline in &config.pipelines {
+        for plugin in &pipeline.plugins {
+            let crate::types::PluginConfig::SystemPrompt(system_prompt) = plugin else {
+                continue;
+            };
+            let path = plugin_path(&pipeline.name, "system-prompt");
+            // Broken templates are reported on their own above.
+            let template_parses = config
+                .prompt_templates
+                .get(&system_prompt.system_prompt_template)
+                .is_none_or(|template| PromptTemplate::parse(template).is_ok());
+            if template_parses {
+                if let Err(e) = validate_system_prompt(system_prompt, &config.prompt_templates) {
+                    errors.push(ValidationError::error(
+                        "invalid_system_prompt",
+                        path.clone(),
+                        format!(
+                            "Pipeline '{}' has invalid system-prompt settings: {e}.",
+                            pipeline.name
+                        ),
+                    ));
+                }
+            }
+            if pipeline.r#type != PipelineType::Chat {
+                errors.push(ValidationError::error(
+                    "invalid_system_prompt",
+                    path,
+                    format!(
+                        "Pipeline '{}' uses system-prompt, which only applies to chat pipelines.",
+                        pipeline.name
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Check 31: OTLP metrics export needs a collector URL and a usable interval
+    if let Some(otlp_metrics) = config
+        .general
+        .as_ref()
+        .and_then(|g| g.otlp_metrics.as_ref())
+    {
+        errors.extend(
+            validate_otlp_metrics(otlp_metrics)
+                .into_iter()
+                .map(|e| ValidationError::error("invalid_otlp_metrics", "general.otlp_metrics", e)),
+        );
+    }
+
+    // Check 32: Names and keys use a safe charset and are unique ignoring case
+    for (key, e) in check_names(
+        "Provider key",
+        config.providers.iter().map(|p| p.key.as_str()),
+    ) {
+        errors.push(ValidationError::error(
+            "invalid_name",
+            format!("providers[{key}].key"),
+            format!("{e}."),
+        ));
+    }
+    for (key, e) in check_names("Model key", config.models.iter().map(|m| m.key.as_str())) {
+        errors.push(ValidationError::error(
+            "invalid_name",
+            format!("models[{key}].key"),
+            format!("{e}."),
+        ));
+    }
+    for (name, e) in check_names(
+        "Pipeline name",
+        config.pipelines.iter().map(|p| p.name.as_str()),
+    ) {
+        errors.push(ValidationError::error(
+            "invalid_name",
+            format!("pipelines[{name}].name"),
+            format!("{e}."),
+        ));
+    }
+
+    // Check 33: Streamed responses need room for at least one chunk
+    if let Some(general) = &config.general {
+        for (path, value) in [
+            ("general.stream_buffer_chunks", general.stream_buffer_chunks),
+            (
+                "general.stream_buffer_max_bytes",
+                general.stream_buffer_max_bytes,
+            ),
+        ] {
+            if value == Some(0) {
+                errors.push(ValidationError::error(
+                    "invalid_stream_buffer",
+                    path,
+                    format!("{path} must be greater than 0."),
+                ));
+            }
+        }
+    }
+
+    // Check 34: Stream caps need room for at least one stream
+    if let Some(general) = &config.general {
+        for (path, value) in [
+            (
+                "general.max_concurrent_streams_global",
+                general.max_concurrent_streams_global,
+            ),
+            (
+                "general.max_concurrent_streams_per_key",
+                general.max_concurrent_streams_per_key,
+            ),
+        ] {
+            if value == Some(0) {
+                errors.push(ValidationError::error(
+                    "invalid_stream_limit",
+                    path,
+                    format!("{path} must be greater than 0."),
+                ));
+            }
+        }
+    }
+
+    // Check 35: Models must have a type their provider allows
+    for provider in &config.providers {
+        if provider
+            .params
+            .get(ALLOWED_MODEL_PREFIXES_PARAM)
+            .is_some_and(|param| parse_model_prefixes(param).is_empty())
+        {
+            errors.push(ValidationError::error(
+                "invalid_allowed_model_prefixes",
+                format!(
+                    "{}.{ALLOWED_MODEL_PREFIXES_PARAM}",
+                    provider_path(&provider.key)
+                ),
+                format!(
+                    "Provider '{}' must list at least one allowed model prefix, or '{ANY_MODEL_PREFIX}' to allow any model.",
+                    provider.key
+                ),
+            ));
+            continue;
+        }
+        let prefixes = provider_model_prefixes(provider);
+        for model in config.models.iter().filter(|m| m.provider == provider.key) {
+            if let Err(e) = check_model_type(
+                &model.key,
+                &model.r#type,
+                &provider.key,
+                prefixes.as_deref(),
+            ) {
+                errors.push(ValidationError::error(
+                    "disallowed_model_type",
+                    format!("{}.type", model_path(&model.key)),
+                    format!("{e}."),
+                ));
+            }
+        }
+    }
+
+    // Check 36: Slow-stream detection needs a positive rate over a window of some length
+    if let Some(slow_stream) = config.general.as_ref().and_then(|g| g.slow_stream) {
+        if !(slow_stream.min_tokens_per_second.is_finite()
+            && slow_stream.min_tokens_per_second > 0.0)
+        {
+            errors.push(ValidationError::error(
+                "invalid_slow_stream",
+                "general.slow_stream.min_tokens_per_second",
+                "general.slow_stream.min_tokens_per_second must be a number greater than 0.",
+            ));
+        }
+        if slow_stream.window_seconds == 0 {
+            errors.push(ValidationError::error(
+                "invalid_slow_stream",
+                "general.slow_stream.window_seconds",
+                "general.slow_stream.window_seconds must be greater than 0.",
+            ));
+        }
+    }
+
+    // Check 37: Batch caps of 0 would reject every batch
+    if let Some(batch) = config.general.as_ref().and_then(|g| g.batch_inference) {
+        for (field, value) in [
+            ("max_lines", batch.max_lines),
+            ("max_body_bytes", batch.max_body_bytes),
+            ("max_concurrency", batch.max_concurrency),
+        ] {
+            if value == 0 {
+                errors.push(ValidationError::error(
+                    "invalid_batch_inference",
+                    format!("general.batch_inference.{field}"),
+                    format!("general.batch_inference.{field} must be greater than 0."),
+                ));
+            }
+        }
+    }
+
+    // Check 38: Providers of custom types need a factory registered for their type
+    for provider in &config.providers {
+        if has_factory(provider.r#type) {
+            continue;
+        }
+        let built_in = ProviderType::BUILT_IN
+            .map(|provider_type| provider_type.name())
+            .join(", ");
+        errors.push(ValidationError::error(
+            "unknown_provider_type",
+            format!("{}.type", provider_path(&provider.key)),
+            format!(
+                "Provider '{}' has type '{}', which is neither built in ({built_in}) nor \
+                 registered with ProviderRegistry::register_factory.",
+                provider.key, provider.r#type
+            ),
+        ));
+    }
+
+    // Check 39: A body cap of 0 would reject every request read whole
+    if config
+        .general
+        .as_ref()
+        .is_some_and(|g| g.max_buffered_body_bytes == Some(0))
+    {
+        errors.push(ValidationError::error(
+            "invalid_max_buffered_body_bytes",
+            "general.max_buffered_body_bytes",
+            "general.max_buffered_body_bytes must be greater than 0.",
+        ));
+    }
+
+    // Add more validation checks as needed:
+    // - Specific validation for provider params based on type (more complex, might be out of scope for basic validation)
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*; // To import validate_gateway_config
+    use crate::types::{DegradedMode, MaintenanceWindow};
+    use crate::types::{ModelConfig, Pipeline, PipelineType, PluginConfig, Provider, ProviderType}; // For test data
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_valid_config() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "p1".to_string(),
+                r#type: ProviderType::OpenAI,
+                api_key: "key1".to_string(),
+                maintenance_windows: vec![],
+                params: Default::default(),
+            }],
+            models: vec![ModelConfig {
+                key: "m1".to_string(),
+                r#type: "gpt-4".to_string(),
+                provider: "p1".to_string(),
+                params: Default::default(),
+                enabled: true,
+                deprecation: Default::default(),
+            }],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![PluginConfig::ModelRouter {
+                    models: vec!["m1".to_string()],
+                    allow_dynamic_models: false,
+                    adaptive: None,
+                    race: None,
+                }],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        assert!(validate_gateway_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_model_provider_ref() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "p1".to_string(),
+                r#type: ProviderType::OpenAI,
+                api_key: "key1".to_string(),
+                maintenance_windows: vec![],
+                params: Default::default(),
+            }],
+            models: vec![ModelConfig {
+                key: "m1".to_string(),
+                r#type: "gpt-4".to_string(),
+                provider: "p2_non_existent".to_string(), // Invalid provider ref
+                params: Default::default(),
+                enabled: true,
+                deprecation: Default::default(),
+            }],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let result = validate_gateway_config(&config);
+        assert!(result.is_err());
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("references non-existent provider 'p2_non_existent'")
+        );
+    }
+
+    #[test]
+    fn test_invalid_pipeline_model_ref() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "p1".to_string(),
+                r#type: ProviderType::OpenAI,
+                api_key: "key1".to_string(),
+                maintenance_windows: vec![],
+                params: Default::default(),
+            }],
+            models: vec![ModelConfig {
+                key: "m1".to_string(),
+                r#type: "gpt-4".to_string(),
+                provider: "p1".to_string(),
+                params: Default::default(),
+                enabled: true,
+                deprecation: Default::default(),
+            }],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![PluginConfig::ModelRouter {
+                    models: vec!["m2_non_existent".to_string()],
+                    allow_dynamic_models: false,
+                    adaptive: None,
+                    race: None,
+                }], // Invalid model ref
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let result = validate_gateway_config(&config);
+        assert!(result.is_err());
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("references non-existent model 'm2_non_existent'")
+        );
+    }
+
+    #[test]
+    fn test_invalid_tls_settings_name_provider() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "internal-llm".to_string(),
+                r#type: ProviderType::OpenAI,
+                api_key: "key1".to_string(),
+                maintenance_windows: vec![],
+                params: HashMap::from([(
+                    "ca_cert_path".to_string(),
+                    "/nonexistent/ca.pem".to_string(),
+                )]),
+            }],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("Provider 'internal-llm' has invalid TLS settings")
+        );
+        assert!(errors[0].message.contains("/nonexistent/ca.pem"));
+    }
+
+    #[test]
+    fn test_invalid_budget_and_prices() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "p1".to_string(),
+                r#type: ProviderType::OpenAI,
+                api_key: "key1".to_string(),
+                maintenance_windows: vec![],
+                params: Default::default(),
+            }],
+            models: vec![ModelConfig {
+                key: "m1".to_string(),
+                r#type: "gpt-4".to_string(),
+                provider: "p1".to_string(),
+                params: HashMap::from([(
+                    "input_cost_per_1k_tokens".to_string(),
+                    "free".to_string(),
+                )]),
+                enabled: true,
+                deprecation: Default::default(),
+            }],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![
+                    PluginConfig::Budget {
+                        limit_usd: crate::types::UsdAmount(0.0),
+                        window: Default::default(),
+                        warn_at_percent: 80,
+                    },
+                    PluginConfig::ModelRouter {
+                        models: vec!["m1".to_string()],
+                        allow_dynamic_models: false,
+                        adaptive: None,
+                        race: None,
+                    },
+                ],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(
+            errors[0]
+                .message
+                .contains("Model 'm1' has an invalid input_cost_per_1k_tokens")
+        );
+        assert!(
+            errors[1]
+                .message
+                .contains("Pipeline 'pipe1' budget limit_usd")
+        );
+    }
+
+    #[test]
+    fn test_pipeline_metadata_limits() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![PluginConfig::Metadata {
+                    values: std::collections::BTreeMap::from([(
+                        "k".repeat(65),
+                        "value".to_string(),
+                    )]),
+                }],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("Pipeline 'pipe1' has invalid metadata")
+        );
+    }
+
+    #[test]
+    fn test_invalid_parameter_policy() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![PluginConfig::ParameterPolicy {
+                    mode: crate::types::ParameterPolicyMode::Sanitize,
+                    rules: std::collections::BTreeMap::from([(
+                        "temperature".to_string(),
+                        crate::types::ParameterRule::Clamp {
+                            min: Some(1.5),
+                            max: Some(1.0),
+                        },
+                    )]),
+                    allow_extra_body: false,
+                    extra_body_keys: vec![],
+                }],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("Pipeline 'pipe1': parameter-policy clamp for 'temperature'")
+        );
+    }
+
+    #[test]
+    fn test_auto_replace_needs_replacement_in_pipeline() {
+        let model = |key: &str, deprecation: crate::types::ModelDeprecation| ModelConfig {
+            key: key.to_string(),
+            r#type: key.to_string(),
+            provider: "p1".to_string(),
+            params: Default::default(),
+            enabled: true,
+            deprecation,
+        };
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "p1".to_string(),
+                r#type: ProviderType::OpenAI,
+                api_key: "key1".to_string(),
+                maintenance_windows: vec![],
+                params: Default::default(),
+            }],
+            models: vec![
+                model("gpt-4o", Default::default()),
+                model(
+                    "gpt-4-0314",
+                    crate::types::ModelDeprecation {
+                        deprecated: true,
+                        replacement: Some("gpt-4o".to_string()),
+                        auto_replace: true,
+                        sunset: None,
+                    },
+                ),
+            ],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![PluginConfig::ModelRouter {
+                    models: vec!["gpt-4-0314".to_string()],
+                    allow_dynamic_models: false,
+                    adaptive: None,
+                    race: None,
+                }],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("auto_replace replacement 'gpt-4o'")
+        );
+    }
+
+    #[test]
+    fn test_zero_max_in_flight_requests() {
+        let config = GatewayConfig {
+            general: Some(crate::types::General {
+                max_in_flight_requests: Some(0),
+                ..Default::default()
+            }),
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "general.max_in_flight_requests must be greater than 0."
+        );
+    }
+
+    #[test]
+    fn test_zero_stream_limits() {
+        let config = GatewayConfig {
+            general: Some(crate::types::General {
+                max_concurrent_streams_global: Some(0),
+                max_concurrent_streams_per_key: Some(0),
+                ..Default::default()
+            }),
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[1].message,
+            "general.max_concurrent_streams_per_key must be greater than 0."
+        );
+    }
+
+    #[test]
+    fn test_invalid_slow_stream() {
+        let config = GatewayConfig {
+            general: Some(crate::types::General {
+                slow_stream: Some(crate::types::SlowStreamConfig {
+                    min_tokens_per_second: f64::NAN,
+                    window_seconds: 0,
+                }),
+                ..Default::default()
+            }),
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].path, "general.slow_stream.min_tokens_per_second");
+        assert_eq!(errors[1].path, "general.slow_stream.window_seconds");
+    }
+
+    #[test]
+    fn test_zero_batch_inference_caps() {
+        let config = GatewayConfig {
+            general: Some(crate::types::General {
+                batch_inference: Some(crate::types::BatchInferenceConfig {
+                    max_concurrency: 0,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "general.batch_inference.max_concurrency");
+    }
+
+    #[test]
+    fn test_custom_provider_types_need_a_factory() {
+        let config = |type_name: &str| GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "custom".to_string(),
+                r#type: ProviderType::from_name(type_name),
+                api_key: String::new(),
+                maintenance_windows: vec![],
+                params: HashMap::new(),
+            }],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config("validation-unregistered")).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, "unknown_provider_type");
+        assert_eq!(errors[0].path, "providers[custom].type");
+
+        crate::providers::registry::ProviderRegistry::register_factory(
+            "validation-registered",
+            |_| Err(anyhow::anyhow!("validation doesn't build providers")),
+        );
+        assert!(validate_gateway_config(&config("validation-registered")).is_ok());
+    }
 
-    if errors.is_empty() {
-        Ok(())
-    } else {
-        Err(errors)
+    #[test]
+    fn test_zero_idempotency_ttl() {
+        let config = GatewayConfig {
+            general: Some(crate::types::General {
+                idempotency_ttl_seconds: Some(0),
+                ..Default::default()
+            }),
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "general.idempotency_ttl_seconds must be greater than 0."
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*; // To import validate_gateway_config
-    use crate::types::{ModelConfig, Pipeline, PipelineType, PluginConfig, Provider, ProviderType}; // For test data
+    #[test]
+    fn test_zero_stream_buffer() {
+        let config = GatewayConfig {
+            general: Some(crate::types::General {
+                stream_buffer_chunks: Some(0),
+                stream_buffer_max_bytes: Some(1024),
+                ..Default::default()
+            }),
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "general.stream_buffer_chunks must be greater than 0."
+        );
+    }
 
     #[test]
-    fn test_valid_config() {
+    fn test_logging_sample_rate_out_of_range() {
         let config = GatewayConfig {
+            general: None,
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![PluginConfig::Logging {
+                    level: "info".to_string(),
+                    sample_rate: crate::types::SampleRate(1.5),
+                }],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("logging sample_rate 1.5"));
+    }
+
+    #[test]
+    fn test_disabled_models_in_pipeline() {
+        let model = |key: &str, enabled: bool| ModelConfig {
+            key: key.to_string(),
+            r#type: "gpt-4".to_string(),
+            provider: "p1".to_string(),
+            params: Default::default(),
+            enabled,
+            deprecation: Default::default(),
+        };
+        let pipeline = |models: &[&str]| Pipeline {
+            name: "pipe1".to_string(),
+            r#type: PipelineType::Chat,
+            plugins: vec![PluginConfig::ModelRouter {
+                models: models.iter().map(|m| m.to_string()).collect(),
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: None,
+            }],
+            store_artifacts: false,
+        };
+        let mut config = GatewayConfig {
             general: None,
             providers: vec![Provider {
                 key: "p1".to_string(),
                 r#type: ProviderType::OpenAI,
                 api_key: "key1".to_string(),
+                maintenance_windows: vec![],
                 params: Default::default(),
             }],
-            models: vec![ModelConfig {
-                key: "m1".to_string(),
-                r#type: "gpt-4".to_string(),
-                provider: "p1".to_string(),
-                params: Default::default(),
-            }],
+            models: vec![model("m1", true), model("m2", false)],
+            pipelines: vec![pipeline(&["m1", "m2"])],
+            prompt_templates: Default::default(),
+        };
+        assert!(validate_gateway_config(&config).is_ok());
+
+        config.pipelines = vec![pipeline(&["m2"])];
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("only references disabled models")
+        );
+    }
+
+    #[test]
+    fn test_dynamic_models_need_router_models() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![],
+            models: vec![],
             pipelines: vec![Pipeline {
                 name: "pipe1".to_string(),
                 r#type: PipelineType::Chat,
                 plugins: vec![PluginConfig::ModelRouter {
-                    models: vec!["m1".to_string()],
+                    models: vec![],
+                    allow_dynamic_models: true,
+                    adaptive: None,
+                    race: None,
                 }],
+                store_artifacts: false,
             }],
+            prompt_templates: Default::default(),
         };
-        assert!(validate_gateway_config(&config).is_ok());
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("allows dynamic models but lists no models")
+        );
     }
 
     #[test]
-    fn test_invalid_model_provider_ref() {
+    fn test_store_artifacts_needs_artifact_store() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![],
+                store_artifacts: true,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("general.artifact_store is not configured")
+        );
+    }
+
+    #[test]
+    fn test_names_are_checked_and_unique_ignoring_case() {
+        let provider = |key: &str| Provider {
+            key: key.to_string(),
+            r#type: ProviderType::OpenAI,
+            api_key: "k".to_string(),
+            maintenance_windows: vec![],
+            params: HashMap::new(),
+        };
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![provider("openai"), provider("OpenAI")],
+            models: vec![],
+            pipelines: vec![Pipeline {
+                name: "default ".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        let errors: Vec<(&str, &str)> = errors
+            .iter()
+            .map(|e| (e.path.as_str(), e.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (
+                    "providers[OpenAI].key",
+                    "Provider key 'OpenAI' differs from 'openai' only by case."
+                ),
+                (
+                    "pipelines[default ].name",
+                    "Pipeline name 'default ' contains ' '; use letters, digits, '.', '_' or '-'."
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_otlp_metrics_needs_http_endpoint_and_interval() {
         let config = GatewayConfig {
+            general: Some(crate::types::General {
+                otlp_metrics: Some(crate::types::OtlpMetricsConfig {
+                    endpoint: "collector:4318".to_string(),
+                    headers: Default::default(),
+                    export_interval_seconds: 0,
+                    service_name: "traceloop-hub".to_string(),
+                    environment: None,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.code == "invalid_otlp_metrics"));
+    }
+
+    #[test]
+    fn test_failover_groups() {
+        let member = |key: &str, r#type: ProviderType, group: &str| Provider {
+            key: key.to_string(),
+            r#type,
+            api_key: "k".to_string(),
+            maintenance_windows: vec![],
+            params: HashMap::from([("group".to_string(), group.to_string())]),
+        };
+        let mut config = GatewayConfig {
+            general: None,
+            providers: vec![
+                member("azure-eastus", ProviderType::Azure, "azure-prod"),
+                member("azure-westeu", ProviderType::Azure, "azure-prod"),
+            ],
+            models: vec![ModelConfig {
+                key: "gpt-4o".to_string(),
+                r#type: "gpt-4o".to_string(),
+                provider: "azure-prod".to_string(),
+                params: HashMap::new(),
+                enabled: true,
+                deprecation: Default::default(),
+            }],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        assert!(validate_gateway_config(&config).is_ok());
+
+        config.providers[1].r#type = ProviderType::OpenAI;
+        config
+            .providers
+            .push(member("azure-prod", ProviderType::Azure, ""));
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(
+            errors[0]
+                .message
+                .contains("'azure-westeu' is of type openai")
+        );
+        assert!(
+            errors[1]
+                .message
+                .contains("'azure-prod' has the same name as a provider")
+        );
+    }
+
+    #[test]
+    fn test_api_key_file() {
+        let mut config = GatewayConfig {
             general: None,
             providers: vec![Provider {
-                key: "p1".to_string(),
+                key: "openai".to_string(),
                 r#type: ProviderType::OpenAI,
-                api_key: "key1".to_string(),
-                params: Default::default(),
+                api_key: String::new(),
+                maintenance_windows: vec![],
+                params: HashMap::from([
+                    (
+                        "api_key_file".to_string(),
+                        "/var/run/secrets/openai/api-key".to_string(),
+                    ),
+                    ("api_key_file_refresh_seconds".to_string(), "30".to_string()),
+                ]),
+            }],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        assert!(validate_gateway_config(&config).is_ok());
+
+        config.providers[0].api_key = "sk-inline".to_string();
+        config.providers[0].params.insert(
+            "api_key_file_refresh_seconds".to_string(),
+            "soon".to_string(),
+        );
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(
+            errors[0]
+                .message
+                .contains("sets both api_key and api_key_file")
+        );
+        assert!(
+            errors[1]
+                .message
+                .contains("'soon' is not a whole number of seconds")
+        );
+    }
+
+    #[test]
+    fn test_api_key_secret() {
+        let mut config = GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "openai".to_string(),
+                r#type: ProviderType::OpenAI,
+                api_key: String::new(),
+                maintenance_windows: vec![],
+                params: HashMap::from([
+                    (
+                        "api_key_secret".to_string(),
+                        r#"{"type": "environment", "variable_name": "OPENAI_KEY"}"#.to_string(),
+                    ),
+                    ("api_key_secret_retry_seconds".to_string(), "5".to_string()),
+                ]),
+            }],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        assert!(validate_gateway_config(&config).is_ok());
+
+        config.providers[0].api_key = "sk-inline".to_string();
+        config.providers[0].params.insert(
+            "api_key_secret".to_string(),
+            r#"{"type": "vault"}"#.to_string(),
+        );
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors[0].message.contains("has an invalid api_key_secret"));
+        assert!(
+            errors[1]
+                .message
+                .contains("sets api_key_secret along with another API key")
+        );
+    }
+
+    #[test]
+    fn test_invalid_maintenance_window() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "azure".to_string(),
+                r#type: ProviderType::Azure,
+                api_key: "k".to_string(),
+                maintenance_windows: vec![
+                    MaintenanceWindow {
+                        schedule: Some("0 22 * * SAT".to_string()),
+                        duration_minutes: Some(240),
+                        timezone: Some("+01:00".to_string()),
+                        ..Default::default()
+                    },
+                    MaintenanceWindow {
+                        start: Some("2025-03-01T22:00:00Z".to_string()),
+                        ..Default::default()
+                    },
+                ],
+                params: HashMap::new(),
+            }],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(
+            errors[0]
+                .message
+                .contains("'azure' has an invalid maintenance window #2")
+        );
+    }
+
+    #[test]
+    fn test_degraded_mode_needs_existing_model() {
+        let degraded_mode: DegradedMode = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o-mini",
+            "max_error_rate_percent": 20
+        }))
+        .unwrap();
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![Pipeline {
+                name: "embed".to_string(),
+                r#type: PipelineType::Embeddings,
+                plugins: vec![PluginConfig::DegradedMode(degraded_mode)],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(
+            errors[0]
+                .message
+                .contains("degrades to non-existent model 'gpt-4o-mini'")
+        );
+        assert!(errors[1].message.contains("only applies to chat pipelines"));
+    }
+
+    #[test]
+    fn test_tool_limits_need_a_setting_and_a_chat_pipeline() {
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![Pipeline {
+                name: "embed".to_string(),
+                r#type: PipelineType::Embeddings,
+                plugins: vec![PluginConfig::ToolLimits(Default::default())],
+                store_artifacts: false,
             }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert_eq!(errors[0].path, "pipelines[embed].plugins.tool-limits");
+        assert!(errors[0].message.contains("set max_tools"));
+        assert!(errors[1].message.contains("only applies to chat pipelines"));
+    }
+
+    #[test]
+    fn test_system_prompt_templates() {
+        let system_prompt = |template: &str| {
+            PluginConfig::SystemPrompt(crate::types::SystemPrompt {
+                system_prompt_template: template.to_string(),
+                variables: std::collections::BTreeMap::from([(
+                    "brand".to_string(),
+                    "Acme".to_string(),
+                )]),
+                allow_header_variables: false,
+            })
+        };
+        let pipeline = |name: &str, r#type, template: &str| Pipeline {
+            name: name.to_string(),
+            r#type,
+            plugins: vec![system_prompt(template)],
+            store_artifacts: false,
+        };
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![
+                pipeline("ok", PipelineType::Chat, "support"),
+                pipeline("missing-var", PipelineType::Chat, "sales"),
+                pipeline("unknown", PipelineType::Chat, "billing"),
+                pipeline("broken", PipelineType::Chat, "broken"),
+                pipeline("embed", PipelineType::Embeddings, "support"),
+            ],
+            prompt_templates: std::collections::BTreeMap::from([
+                (
+                    "support".to_string(),
+                    "You help {{brand}} users.".to_string(),
+                ),
+                (
+                    "sales".to_string(),
+                    "You sell {{brand}} {{product}}.".to_string(),
+                ),
+                ("broken".to_string(), "You help {{brand".to_string()),
+            ]),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "prompt_templates[broken]",
+                "pipelines[missing-var].plugins.system-prompt",
+                "pipelines[unknown].plugins.system-prompt",
+                "pipelines[embed].plugins.system-prompt",
+            ],
+            "{errors:?}"
+        );
+        assert!(errors[0].message.contains("never closed"));
+        assert!(errors[1].message.contains("no value for product"));
+        assert!(errors[2].message.contains("'billing' is not defined"));
+        assert!(errors[3].message.contains("only applies to chat pipelines"));
+    }
+
+    #[test]
+    fn test_errors_serialize_with_code_path_and_severity() {
+        let config = GatewayConfig {
+            general: Some(crate::types::General {
+                max_in_flight_requests: Some(0),
+                ..Default::default()
+            }),
+            providers: vec![],
             models: vec![ModelConfig {
                 key: "m1".to_string(),
                 r#type: "gpt-4".to_string(),
-                provider: "p2_non_existent".to_string(), // Invalid provider ref
+                provider: "missing".to_string(),
                 params: Default::default(),
+                enabled: true,
+                deprecation: Default::default(),
             }],
             pipelines: vec![],
+            prompt_templates: Default::default(),
         };
-        let result = validate_gateway_config(&config);
-        assert!(result.is_err());
-        let errors = result.unwrap_err();
-        assert_eq!(errors.len(), 1);
-        assert!(errors[0].contains("references non-existent provider 'p2_non_existent'"));
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(
+            serde_json::to_value(&errors).unwrap(),
+            serde_json::json!([
+                {
+                    "code": "unknown_provider",
+                    "path": "models[m1].provider",
+                    "message": "Model 'm1' references non-existent provider 'missing'.",
+                    "severity": "error"
+                },
+                {
+                    "code": "invalid_admission_limit",
+                    "path": "general.max_in_flight_requests",
+                    "message": "general.max_in_flight_requests must be greater than 0.",
+                    "severity": "error"
+                }
+            ])
+        );
     }
 
     #[test]
-    fn test_invalid_pipeline_model_ref() {
+    fn test_plugin_errors_point_at_the_plugin() {
         let config = GatewayConfig {
+            general: None,
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![
+                    PluginConfig::ModelRouter {
+                        models: vec!["m2".to_string()],
+                        allow_dynamic_models: false,
+                        adaptive: None,
+                        race: None,
+                    },
+                    PluginConfig::Logging {
+                        level: "info".to_string(),
+                        sample_rate: crate::types::SampleRate(-1.0),
+                    },
+                ],
+                store_artifacts: true,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        let json = serde_json::to_value(&errors).unwrap();
+        let findings: Vec<(&str, &str)> = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| (e["code"].as_str().unwrap(), e["path"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    "unknown_model",
+                    "pipelines[pipe1].plugins.model-router.models"
+                ),
+                (
+                    "invalid_sample_rate",
+                    "pipelines[pipe1].plugins.logging.sample_rate"
+                ),
+                ("missing_artifact_store", "pipelines[pipe1].store_artifacts"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_warnings_come_with_errors_only() {
+        let model = |key: &str, enabled: bool| ModelConfig {
+            key: key.to_string(),
+            r#type: "gpt-4".to_string(),
+            provider: "p1".to_string(),
+            params: Default::default(),
+            enabled,
+            deprecation: Default::default(),
+        };
+        let mut config = GatewayConfig {
             general: None,
             providers: vec![Provider {
                 key: "p1".to_string(),
                 r#type: ProviderType::OpenAI,
                 api_key: "key1".to_string(),
+                maintenance_windows: vec![],
                 params: Default::default(),
             }],
-            models: vec![ModelConfig {
-                key: "m1".to_string(),
-                r#type: "gpt-4".to_string(),
-                provider: "p1".to_string(),
+            models: vec![model("m1", true), model("m2", false)],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![PluginConfig::ModelRouter {
+                    models: vec!["m1".to_string(), "m2".to_string()],
+                    allow_dynamic_models: false,
+                    adaptive: None,
+                    race: None,
+                }],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let findings = check_gateway_config(&config);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(
+            serde_json::to_value(&findings[0]).unwrap()["severity"],
+            "warning"
+        );
+        assert!(validate_gateway_config(&config).is_ok());
+
+        config.general = Some(crate::types::General {
+            idempotency_ttl_seconds: Some(0),
+            ..Default::default()
+        });
+        let errors = validate_gateway_config(&config).unwrap_err();
+        let codes: Vec<_> = errors
+            .iter()
+            .map(|e| (e.code.as_str(), e.severity))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                ("disabled_models", Severity::Warning),
+                ("invalid_idempotency_ttl", Severity::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_passthrough_headers() {
+        let config = GatewayConfig {
+            general: Some(crate::types::General {
+                passthrough_response_headers: Some(crate::types::PassthroughHeaders {
+                    headers: vec!["content-type".to_string()],
+                    prefix: false,
+                }),
+                ..Default::default()
+            }),
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![Pipeline {
+                name: "pipe1".to_string(),
+                r#type: PipelineType::Chat,
+                plugins: vec![PluginConfig::PassthroughHeaders(
+                    crate::types::PassthroughHeaders {
+                        headers: vec!["not a header".to_string()],
+                        prefix: true,
+                    },
+                )],
+                store_artifacts: false,
+            }],
+            prompt_templates: Default::default(),
+        };
+        let errors = validate_gateway_config(&config).unwrap_err();
+        let paths: Vec<_> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "general.passthrough_response_headers",
+                "pipelines[pipe1].plugins.passthrough-headers"
+            ]
+        );
+        assert!(
+            errors[0]
+                .message
+                .contains("'content-type' can't be passed through")
+        );
+    }
+
+    #[test]
+    fn test_mock_provider_settings() {
+        let mock = |key: &str, params: &[(&str, &str)]| Provider {
+            key: key.to_string(),
+            r#type: ProviderType::Mock,
+            api_key: String::new(),
+            maintenance_windows: vec![],
+            params: params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        };
+        let model = |key: &str, provider: &str| ModelConfig {
+            key: key.to_string(),
+            r#type: key.to_string(),
+            provider: provider.to_string(),
+            params: HashMap::new(),
+            enabled: true,
+            deprecation: Default::default(),
+        };
+        let config = GatewayConfig {
+            general: None,
+            providers: vec![
+                mock("broken", &[("fail_status", "200")]),
+                mock("fixed", &[("mode", "fixed")]),
+            ],
+            models: vec![model("m1", "broken"), model("m2", "fixed")],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+
+        let findings = check_gateway_config(&config);
+        let codes: Vec<_> = findings
+            .iter()
+            .map(|e| (e.code.as_str(), e.severity))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                ("invalid_mock_provider", Severity::Error),
+                ("missing_mock_response", Severity::Warning)
+            ]
+        );
+        assert_eq!(findings[1].path, "models[m2].params");
+    }
+
+    #[test]
+    fn test_trace_content_ignored_when_disabled() {
+        let mut config = GatewayConfig {
+            general: Some(crate::types::General {
+                trace_content_enabled: false,
+                trace_content: Some(Default::default()),
+                ..Default::default()
+            }),
+            providers: vec![],
+            models: vec![],
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        };
+
+        let findings = check_gateway_config(&config);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].code, "ignored_trace_content");
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert!(validate_gateway_config(&config).is_ok());
+
+        config.general.as_mut().unwrap().trace_content_enabled = true;
+        assert!(check_gateway_config(&config).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_race_routing() {
+        let model = |key: &str| ModelConfig {
+            key: key.to_string(),
+            r#type: "gpt-4".to_string(),
+            provider: "p1".to_string(),
+            params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
+        };
+        let router = |candidates: &[&str], adaptive: bool| PluginConfig::ModelRouter {
+            models: vec!["m1".to_string(), "m2".to_string()],
+            allow_dynamic_models: false,
+            adaptive: adaptive.then(Default::default),
+            race: Some(crate::types::RaceRouting {
+                candidates: candidates.iter().map(|c| c.to_string()).collect(),
+                stagger_ms: 0,
+            }),
+        };
+        let mut config = GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "p1".to_string(),
+                r#type: ProviderType::OpenAI,
+                api_key: "key1".to_string(),
+                maintenance_windows: vec![],
                 params: Default::default(),
             }],
+            models: vec![model("m1"), model("m2")],
             pipelines: vec![Pipeline {
                 name: "pipe1".to_string(),
                 r#type: PipelineType::Chat,
-                plugins: vec![PluginConfig::ModelRouter {
-                    models: vec!["m2_non_existent".to_string()],
-                }], // Invalid model ref
+                plugins: vec![router(&["m2", "m1"], false)],
+                store_artifacts: false,
             }],
+            prompt_templates: Default::default(),
         };
-        let result = validate_gateway_config(&config);
-        assert!(result.is_err());
-        let errors = result.unwrap_err();
+        assert!(validate_gateway_config(&config).is_ok());
+
+        config.pipelines[0].plugins = vec![router(&["m1", "m3"], false)];
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, "invalid_race_routing");
+        assert_eq!(errors[0].path, "pipelines[pipe1].plugins.model-router.race");
+        assert!(errors[0].message.contains("'m3'"));
+
+        config.pipelines[0].plugins = vec![router(&[], true)];
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("can't use both the adaptive and race strategies")
+        );
+    }
+
+    fn allowed_models_config(
+        provider_type: ProviderType,
+        params: &[(&str, &str)],
+        model_types: &[&str],
+    ) -> GatewayConfig {
+        GatewayConfig {
+            general: None,
+            providers: vec![Provider {
+                key: "p1".to_string(),
+                r#type: provider_type,
+                api_key: "key1".to_string(),
+                maintenance_windows: vec![],
+                params: params
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            }],
+            models: model_types
+                .iter()
+                .map(|model_type| ModelConfig {
+                    key: format!("m-{model_type}"),
+                    r#type: model_type.to_string(),
+                    provider: "p1".to_string(),
+                    params: Default::default(),
+                    enabled: true,
+                    deprecation: Default::default(),
+                })
+                .collect(),
+            pipelines: vec![],
+            prompt_templates: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_default_allowed_model_prefixes() {
+        let config =
+            allowed_models_config(ProviderType::Anthropic, &[], &["claude-sonnet-4", "gpt-4o"]);
+        let errors = validate_gateway_config(&config).unwrap_err();
         assert_eq!(errors.len(), 1);
-        assert!(errors[0].contains("references non-existent model 'm2_non_existent'"));
+        assert_eq!(errors[0].code, "disallowed_model_type");
+        assert_eq!(errors[0].path, "models[m-gpt-4o].type");
+        assert!(
+            errors[0].message.starts_with(
+                "Model 'm-gpt-4o' has type 'gpt-4o', which provider 'p1' doesn't allow"
+            )
+        );
+
+        let config = allowed_models_config(
+            ProviderType::OpenAI,
+            &[],
+            &[
+                "gpt-4o",
+                "o3-mini",
+                "text-embedding-3-small",
+                "claude-sonnet-4",
+            ],
+        );
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "models[m-claude-sonnet-4].type");
+
+        // OpenAI-compatible servers and deployment-named providers accept any model type.
+        let config = allowed_models_config(
+            ProviderType::OpenAI,
+            &[("base_url", "http://localhost:8000/v1")],
+            &["llama-3.1-8b"],
+        );
+        assert!(validate_gateway_config(&config).is_ok());
+        let config = allowed_models_config(ProviderType::Azure, &[], &["my-deployment"]);
+        assert!(validate_gateway_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_overridden_allowed_model_prefixes() {
+        let config = allowed_models_config(
+            ProviderType::OpenAI,
+            &[("allowed_model_prefixes", "gpt-4o, o3")],
+            &["gpt-4o-mini", "o3", "gpt-4"],
+        );
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "models[m-gpt-4].type");
+        assert!(errors[0].message.contains("start with 'gpt-4o', 'o3'"));
+
+        let config = allowed_models_config(
+            ProviderType::Azure,
+            &[("allowed_model_prefixes", "gpt-")],
+            &["my-deployment"],
+        );
+        assert_eq!(validate_gateway_config(&config).unwrap_err().len(), 1);
+
+        let config = allowed_models_config(
+            ProviderType::OpenAI,
+            &[("allowed_model_prefixes", " , ")],
+            &["gpt-4o"],
+        );
+        let errors = validate_gateway_config(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, "invalid_allowed_model_prefixes");
+        assert_eq!(errors[0].path, "providers[p1].allowed_model_prefixes");
+    }
+
+    #[test]
+    fn test_wildcard_allows_any_model_type() {
+        let config = allowed_models_config(
+            ProviderType::Anthropic,
+            &[("allowed_model_prefixes", "*")],
+            &["claude-sonnet-4", "gpt-4o"],
+        );
+        assert!(validate_gateway_config(&config).is_ok());
     }
 }
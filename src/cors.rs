@@ -0,0 +1,166 @@
+use crate::pipelines::pipeline::HEADER_PROVIDER;
+use crate::timing::{OVERHEAD_HEADER, UPSTREAM_TTFB_HEADER};
+use crate::types::CorsConfig;
+use axum::http::request::Parts;
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+
+const WILDCARD: &str = "*";
+
+/// Builds the layer for a validated `general.cors` section.
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let allow_origin = if config.allowed_origins.iter().any(|o| o == WILDCARD) {
+        AllowOrigin::any()
+    } else {
+        let patterns = config.allowed_origins.clone();
+        AllowOrigin::predicate(move |origin: &HeaderValue, _: &Parts| {
+            let origin = origin.to_str().unwrap_or_default();
+            patterns
+                .iter()
+                .any(|pattern| origin_matches(pattern, origin))
+        })
+    };
+
+    let allow_headers = if config.allowed_headers.is_empty() {
+        AllowHeaders::mirror_request()
+    } else if config.allowed_headers.iter().any(|h| h == WILDCARD) {
+        AllowHeaders::any()
+    } else {
+        AllowHeaders::list(
+            config
+                .allowed_headers
+                .iter()
+                .filter_map(|h| h.parse::<HeaderName>().ok()),
+        )
+    };
+
+    let layer = CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers(allow_headers)
+        .allow_credentials(config.allow_credentials)
+        .expose_headers([HEADER_PROVIDER, UPSTREAM_TTFB_HEADER, OVERHEAD_HEADER]);
+    match config.max_age {
+        Some(seconds) => layer.max_age(Duration::from_secs(seconds)),
+        None => layer,
+    }
+}
+
+/// Matches an origin against an exact origin or a `scheme://*.domain` pattern.
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    match pattern.split_once("*.") {
+        Some((scheme, domain)) => origin
+            .strip_prefix(scheme)
+            .and_then(|host| host.strip_suffix(domain))
+            .and_then(|subdomain| subdomain.strip_suffix('.'))
+            .is_some_and(|subdomain| !subdomain.is_empty() && !subdomain.contains(['/', ':'])),
+        None => pattern.eq_ignore_ascii_case(origin),
+    }
+}
+
+/// Returns the problems with a `general.cors` section; empty when it is usable.
+pub fn validate_cors(config: &CorsConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    if config.allowed_origins.is_empty() {
+        errors.push("general.cors.allowed_origins must not be empty.".to_string());
+    }
+    for origin in &config.allowed_origins {
+        let valid = match origin.split_once("*.") {
+            _ if origin == WILDCARD => true,
+            Some((scheme, domain)) => scheme.ends_with("://") && !domain.contains('*'),
+            None => !origin.contains('*'),
+        };
+        if !valid {
+            errors.push(format!(
+                "general.cors.allowed_origins entry '{origin}' must be '*', an origin or 'scheme://*.domain'."
+            ));
+        }
+    }
+    for header in &config.allowed_headers {
+        if header != WILDCARD && header.parse::<HeaderName>().is_err() {
+            errors.push(format!(
+                "general.cors.allowed_headers entry '{header}' is not a valid header name."
+            ));
+        }
+    }
+    if config.allow_credentials {
+        if config.allowed_origins.iter().any(|o| o == WILDCARD) {
+            errors.push(
+                "general.cors.allow_credentials can't be combined with a '*' origin.".to_string(),
+            );
+        }
+        if config.allowed_headers.iter().any(|h| h == WILDCARD) {
+            errors.push(
+                "general.cors.allow_credentials can't be combined with '*' allowed_headers."
+                    .to_string(),
+            );
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cors(origins: &[&str], allow_credentials: bool) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            allow_credentials,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_origin_matching() {
+        assert!(origin_matches(
+            "https://app.example.com",
+            "https://app.example.com"
+        ));
+        assert!(!origin_matches(
+            "https://app.example.com",
+            "http://app.example.com"
+        ));
+        assert!(origin_matches(
+            "https://*.example.com",
+            "https://app.example.com"
+        ));
+        assert!(origin_matches(
+            "https://*.example.com",
+            "https://a.b.example.com"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.com",
+            "https://example.com"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.com",
+            "https://evil-example.com"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.com",
+            "http://app.example.com"
+        ));
+    }
+
+    #[test]
+    fn test_credentials_with_wildcard_origin_is_rejected() {
+        assert!(validate_cors(&cors(&["*"], false)).is_empty());
+        assert!(validate_cors(&cors(&["https://*.example.com"], true)).is_empty());
+        let errors = validate_cors(&cors(&["*"], true));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("allow_credentials"));
+    }
+
+    #[test]
+    fn test_malformed_entries_are_rejected() {
+        assert_eq!(validate_cors(&cors(&[], false)).len(), 1);
+        assert_eq!(validate_cors(&cors(&["https://app*.com"], false)).len(), 1);
+        let config = CorsConfig {
+            allowed_headers: vec!["bad header".to_string()],
+            ..cors(&["https://app.example.com"], false)
+        };
+        assert_eq!(validate_cors(&config).len(), 1);
+    }
+}
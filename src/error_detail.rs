@@ -0,0 +1,544 @@
+//! Error responses as clients see them, per `general.error_detail`. Provider errors can
+//! carry upstream URLs, IP addresses, account and resource identifiers: `sanitized` redacts
+//! those and `minimal` keeps only the error type. The messages of error responses are cut
+//! to `MAX_ERROR_MESSAGE_CHARS` in every mode, and an error that was changed is logged in full
+//! with the request id sent back in `x-hub-request-id`. Only the first `MAX_ERROR_BODY_BYTES`
+//! of an error body are read; a longer one is returned as a message cut from them.
+
+use crate::artifacts::HEADER_REQUEST_ID;
+use crate::types::ErrorDetail;
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use regex::Regex;
+use serde_json::{Map, Value, json};
+use std::sync::LazyLock;
+use tracing::info;
+use uuid::Uuid;
+
+pub const MAX_ERROR_MESSAGE_CHARS: usize = 1024;
+pub const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+const MINIMAL_MESSAGE: &str = "The request failed. Quote the request id when reporting it.";
+
+/// Patterns redacted by `sanitized`, in order: URLs go first as they contain hosts and
+/// paths the other patterns would only partly match.
+static REDACTIONS: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    [
+        (r#"\b[a-zA-Z][a-zA-Z0-9+.-]*://[^\s"'<>)]+"#, "[url]"),
+        (r#"\barn:aws[a-z-]*:[^\s"']+"#, "[resource]"),
+        (r#"/subscriptions/[^\s"']+"#, "[resource]"),
+        (r#"\bprojects/[^\s"']+"#, "[resource]"),
+        (r"\b[\w.+-]+@[\w-]+\.[\w.-]+\b", "[email]"),
+        (
+            r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b",
+            "[id]",
+        ),
+        (r"\b(?:\d{1,3}\.){3}\d{1,3}(?::\d{1,5})?\b", "[ip]"),
+        (r"\b(?:[0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}\b", "[ip]"),
+        (r"\b(?:org|proj|req|sk|acct|file)[-_][\w-]*\d[\w-]*", "[id]"),
+        (r"\b[0-9a-fA-F]{16,}\b", "[id]"),
+        (r"\b\d{10,}\b", "[id]"),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| (Regex::new(pattern).expect("valid pattern"), replacement))
+    .collect()
+});
+
+/// `message` cut to `MAX_ERROR_MESSAGE_CHARS`, marking the cut with an ellipsis.
+fn bound(message: &str) -> String {
+    match message.char_indices().nth(MAX_ERROR_MESSAGE_CHARS) {
+        Some((end, _)) => format!("{}…", &message[..end]),
+        None => message.to_string(),
+    }
+}
+
+/// `message` with its URLs, IP addresses and resource identifiers redacted, then bounded.
+pub fn sanitize_message(message: &str) -> String {
+    let sanitized = REDACTIONS
+        .iter()
+        .fold(message.to_string(), |message, (pattern, replacement)| {
+            pattern.replace_all(&message, *replacement).into_owned()
+        });
+    bound(&sanitized)
+}
+
+/// The OpenAI error type of a response with `status`, for errors that don't name one.
+fn error_type(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::NOT_FOUND => "not_found_error",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        status if status.is_client_error() => "invalid_request_error",
+        _ => "api_error",
+    }
+}
+
+fn sanitize_value(value: Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(sanitize_message(&text)),
+        Value::Array(items) => Value::Array(items.into_iter().map(sanitize_value).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, sanitize_value(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// The `error` of a response with `status` as `detail` returns it. `error` is either an
+/// object with a `message`, as OpenAI and the pipelines send them, or a bare message.
+pub fn redact_error(
+    detail: ErrorDetail,
+    status: StatusCode,
+    error: Value,
+    request_id: &str,
+) -> Value {
+    match detail {
+        ErrorDetail::Full => match error {
+            Value::String(message) => Value::String(bound(&message)),
+            Value::Object(mut fields) => {
+                if let Some(Value::String(message)) = fields.get_mut("message") {
+                    *message = bound(message);
+                }
+                Value::Object(fields)
+            }
+            error => error,
+        },
+        ErrorDetail::Sanitized => sanitize_value(error),
+        ErrorDetail::Minimal => {
+            let r#type = error
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or(error_type(status));
+            json!({
+                "type": r#type,
+                "message": MINIMAL_MESSAGE,
+                "request_id": request_id,
+            })
+        }
+    }
+}
+
+/// The id of the request in `headers`, added to them if the pipeline didn't set one.
+fn ensure_request_id(headers: &mut HeaderMap) -> String {
+    if let Some(id) = headers
+        .get(HEADER_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+    {
+        return id.to_string();
+    }
+    let id = Uuid::new_v4().to_string();
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        headers.insert(HEADER_REQUEST_ID, value);
+    }
+    id
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"text/event-stream"))
+}
+
+/// The body of an error response with `status` as `detail` returns it. `None` keeps the
+/// body as it was: `full` only rewrites the errors of JSON bodies, and `sanitized` leaves
+/// empty bodies alone.
+fn redact_body(
+    detail: ErrorDetail,
+    status: StatusCode,
+    body: &[u8],
+    request_id: &str,
+) -> Option<Value> {
+    let parsed = serde_json::from_slice::<Value>(body).ok();
+    let (mut envelope, error) = match parsed {
+        Some(Value::Object(mut fields)) if fields.contains_key("error") => {
+            let error = fields.remove("error").unwrap_or_default();
+            (fields, error)
+        }
+        _ if detail == ErrorDetail::Full => return None,
+        _ if body.is_empty() && detail == ErrorDetail::Sanitized => return None,
+        _ => (
+            Map::new(),
+            json!({"type": error_type(status), "message": String::from_utf8_lossy(body)}),
+        ),
+    };
+    if detail == ErrorDetail::Minimal {
+        envelope.clear();
+    }
+    envelope.insert(
+        "error".to_string(),
+        redact_error(detail, status, error, request_id),
+    );
+    Some(Value::Object(envelope))
+}
+
+/// The first `MAX_ERROR_BODY_BYTES` of an error body, and whether there was more.
+async fn read_error_body(body: Body) -> Result<(Bytes, bool), axum::Error> {
+    let mut data = body.into_data_stream();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = data.next().await {
+        let chunk = chunk?;
+        let room = MAX_ERROR_BODY_BYTES - bytes.len();
+        if chunk.len() > room {
+            bytes.extend_from_slice(&chunk[..room]);
+            return Ok((Bytes::from(bytes), true));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok((Bytes::from(bytes), false))
+}
+
+/// An SSE event as `detail` returns it, rewriting the `data` of error events. Events are
+/// written whole, so an error event never spans two chunks.
+fn redact_event(detail: ErrorDetail, request_id: &str, event: Bytes) -> Bytes {
+    let Ok(text) = std::str::from_utf8(&event) else {
+        return event;
+    };
+    if !text.contains("\"error\"") {
+        return event;
+    }
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            let Some(data) = line.strip_prefix("data:") else {
+                return line.to_string();
+            };
+            let Ok(Value::Object(mut fields)) = serde_json::from_str::<Value>(data.trim_start())
+            else {
+                return line.to_string();
+            };
+            let Some(error) = fields.remove("error") else {
+                return line.to_string();
+            };
+            info!(request_id = %request_id, error = %error, "Redacted a streamed error");
+            let error = redact_error(detail, StatusCode::BAD_GATEWAY, error, request_id);
+            fields.insert("error".to_string(), error);
+            format!("data: {}", Value::Object(fields))
+        })
+        .collect();
+    Bytes::from(lines.join("\n"))
+}
+
+/// Middleware returning the errors of a config's pipelines as its `error_detail` says,
+/// including those that end a stream.
+pub async fn redact_errors(
+    State(detail): State<ErrorDetail>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    let (mut parts, body) = response.into_parts();
+
+    if is_event_stream(&parts.headers) {
+        if detail == ErrorDetail::Full {
+            return Response::from_parts(parts, body);
+        }
+        let request_id = ensure_request_id(&mut parts.headers);
+        let events = body
+            .into_data_stream()
+            .map(move |event| event.map(|event| redact_event(detail, &request_id, event)));
+        return Response::from_parts(parts, Body::from_stream(events));
+    }
+    if !parts.status.is_client_error() && !parts.status.is_server_error() {
+        return Response::from_parts(parts, body);
+    }
+
+    let Ok((body, truncated)) = read_error_body(body).await else {
+        return parts.status.into_response();
+    };
+    // `full` doesn't send the request id, so only needs one to log a rewritten error.
+    let request_id = match detail {
+        ErrorDetail::Full => None,
+        _ => Some(ensure_request_id(&mut parts.headers)),
+    };
+    let redacted = if truncated {
+        // What was read can't be passed on as it was, so even `full` gets the message.
+        let error = json!({
+            "type": error_type(parts.status),
+            "message": String::from_utf8_lossy(&body),
+        });
+        let error = redact_error(
+            detail,
+            parts.status,
+            error,
+            request_id.as_deref().unwrap_or_default(),
+        );
+        Some(json!({ "error": error }))
+    } else {
+        redact_body(
+            detail,
+            parts.status,
+            &body,
+            request_id.as_deref().unwrap_or_default(),
+        )
+    };
+    let Some(redacted) = redacted
+        .filter(|redacted| serde_json::from_slice::<Value>(&body).ok().as_ref() != Some(redacted))
+    else {
+        return Response::from_parts(parts, Body::from(body));
+    };
+    let request_id = request_id.unwrap_or_else(|| ensure_request_id(&mut parts.headers));
+    info!(
+        request_id = %request_id,
+        status = %parts.status,
+        error = %String::from_utf8_lossy(&body),
+        "Redacted an error response"
+    );
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(redacted.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::to_bytes;
+    use axum::middleware;
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    const OPENAI_RATE_LIMIT: &str = "{\"error\":{\"message\":\"Rate limit reached for gpt-4o in \
+        organization org-AbC123xyz on tokens per min (TPM). Visit \
+        https://platform.openai.com/account/rate-limits\",\"type\":\"rate_limit_error\",\
+        \"param\":null,\"code\":\"rate_limit_exceeded\"}}";
+    const CONNECTION_REFUSED: &str = "error sending request for url \
+        (http://10.0.3.17:8080/v1/chat/completions): connection refused by 192.168.1.20";
+
+    /// `body` returned with `status` by a route behind `redact_errors`.
+    async fn through(
+        detail: ErrorDetail,
+        status: StatusCode,
+        content_type: &'static str,
+        body: &'static str,
+    ) -> Response {
+        let app = Router::new()
+            .route(
+                "/",
+                post(move || async move { (status, [(header::CONTENT_TYPE, content_type)], body) }),
+            )
+            .layer(middleware::from_fn_with_state(detail, redact_errors));
+        let request = axum::http::Request::post("/").body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap()
+    }
+
+    async fn text(response: Response) -> String {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_sanitized_provider_errors() {
+        let cases = [
+            (
+                "The resource /subscriptions/0b1f6471-1bf0-4dda-aec3-cb9272f09590/resourceGroups/\
+                prod-rg/providers/Microsoft.CognitiveServices/accounts/acme-openai was not found",
+                "The resource [resource] was not found",
+            ),
+            (
+                "User: arn:aws:iam::123456789012:user/hub is not authorized to perform: \
+                bedrock:InvokeModel on resource: \
+                arn:aws:bedrock:us-east-1::foundation-model/anthropic.claude-v2",
+                "User: [resource] is not authorized to perform: bedrock:InvokeModel on resource: \
+                [resource]",
+            ),
+            (
+                "Permission denied on resource projects/acme-prod-4411/locations/us-central1/\
+                publishers/google/models/gemini-pro (or it may not exist).",
+                "Permission denied on resource [resource] (or it may not exist).",
+            ),
+            (
+                CONNECTION_REFUSED,
+                "error sending request for url ([url]): connection refused by [ip]",
+            ),
+            (
+                "Request req_8f3a2b1c9d rejected for ops@acme.example, account 123456789012",
+                "Request [id] rejected for [email], account [id]",
+            ),
+            (
+                "Invalid value for 'temperature': must be at most 2.",
+                "Invalid value for 'temperature': must be at most 2.",
+            ),
+        ];
+        for (message, sanitized) in cases {
+            assert_eq!(sanitize_message(message), sanitized);
+        }
+    }
+
+    #[test]
+    fn test_messages_are_bounded() {
+        let long = "é".repeat(MAX_ERROR_MESSAGE_CHARS * 2);
+        for detail in [ErrorDetail::Full, ErrorDetail::Sanitized] {
+            let error = json!({"type": "api_error", "message": long});
+            let error = redact_error(detail, StatusCode::BAD_GATEWAY, error, "");
+            let message = error["message"].as_str().unwrap();
+            assert_eq!(message.chars().count(), MAX_ERROR_MESSAGE_CHARS + 1);
+            assert!(message.ends_with('…'));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_errors_in_each_mode() {
+        let status = StatusCode::TOO_MANY_REQUESTS;
+        let content_type = "application/json";
+
+        let response = through(ErrorDetail::Full, status, content_type, OPENAI_RATE_LIMIT).await;
+        assert!(response.headers().get(HEADER_REQUEST_ID).is_none());
+        assert_eq!(text(response).await, OPENAI_RATE_LIMIT);
+
+        let response = through(
+            ErrorDetail::Sanitized,
+            status,
+            content_type,
+            OPENAI_RATE_LIMIT,
+        )
+        .await;
+        assert_eq!(response.status(), status);
+        assert!(response.headers().contains_key(HEADER_REQUEST_ID));
+        let body: Value = serde_json::from_str(&text(response).await).unwrap();
+        assert_eq!(
+            body["error"],
+            json!({
+                "message": "Rate limit reached for gpt-4o in organization [id] on tokens per min \
+                    (TPM). Visit [url]",
+                "type": "rate_limit_error",
+                "param": null,
+                "code": "rate_limit_exceeded"
+            })
+        );
+
+        let response = through(
+            ErrorDetail::Minimal,
+            status,
+            content_type,
+            OPENAI_RATE_LIMIT,
+        )
+        .await;
+        let request_id = response.headers()[HEADER_REQUEST_ID]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: Value = serde_json::from_str(&text(response).await).unwrap();
+        assert_eq!(
+            body,
+            json!({"error": {
+                "type": "rate_limit_error",
+                "message": MINIMAL_MESSAGE,
+                "request_id": request_id
+            }})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_text_and_empty_errors() {
+        let status = StatusCode::BAD_GATEWAY;
+
+        let response = through(ErrorDetail::Full, status, "text/plain", CONNECTION_REFUSED).await;
+        assert_eq!(text(response).await, CONNECTION_REFUSED);
+
+        let response = through(
+            ErrorDetail::Sanitized,
+            status,
+            "text/plain",
+            CONNECTION_REFUSED,
+        )
+        .await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body: Value = serde_json::from_str(&text(response).await).unwrap();
+        assert_eq!(
+            body["error"]["message"],
+            "error sending request for url ([url]): connection refused by [ip]"
+        );
+        assert_eq!(body["error"]["type"], "api_error");
+
+        let response = through(ErrorDetail::Sanitized, status, "text/plain", "").await;
+        assert_eq!(text(response).await, "");
+
+        let response = through(
+            ErrorDetail::Minimal,
+            StatusCode::NOT_FOUND,
+            "text/plain",
+            "",
+        )
+        .await;
+        let body: Value = serde_json::from_str(&text(response).await).unwrap();
+        assert_eq!(body["error"]["type"], "not_found_error");
+        assert_eq!(body["error"]["message"], MINIMAL_MESSAGE);
+    }
+
+    #[tokio::test]
+    async fn test_error_bodies_are_read_up_to_the_cap() {
+        let body: &'static str = "x".repeat(MAX_ERROR_BODY_BYTES + 1).leak();
+        let response = through(
+            ErrorDetail::Full,
+            StatusCode::BAD_GATEWAY,
+            "text/plain",
+            body,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let body: Value = serde_json::from_str(&text(response).await).unwrap();
+        assert_eq!(body["error"]["type"], "api_error");
+        let message = body["error"]["message"].as_str().unwrap();
+        assert_eq!(message.chars().count(), MAX_ERROR_MESSAGE_CHARS + 1);
+        assert!(message.ends_with('…'));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_errors_are_redacted() {
+        const EVENTS: &str = "data: {\"choices\":[]}\n\n\
+            data: {\"error\":{\"type\":\"api_error\",\
+            \"message\":\"upstream 10.0.0.7:443 reset the stream\"}}\n\n";
+
+        let response = through(
+            ErrorDetail::Full,
+            StatusCode::OK,
+            "text/event-stream",
+            EVENTS,
+        )
+        .await;
+        assert_eq!(text(response).await, EVENTS);
+
+        let response = through(
+            ErrorDetail::Sanitized,
+            StatusCode::OK,
+            "text/event-stream",
+            EVENTS,
+        )
+        .await;
+        let events = text(response).await;
+        let (first, second) = events.split_once("\n\n").unwrap();
+        assert_eq!(first, "data: {\"choices\":[]}");
+        let second: Value =
+            serde_json::from_str(second.trim().trim_start_matches("data: ")).unwrap();
+        assert_eq!(
+            second["error"],
+            json!({"type": "api_error", "message": "upstream [ip] reset the stream"})
+        );
+
+        let response = through(
+            ErrorDetail::Minimal,
+            StatusCode::OK,
+            "text/event-stream",
+            EVENTS,
+        )
+        .await;
+        let request_id = response.headers()[HEADER_REQUEST_ID]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let events = text(response).await;
+        assert!(events.contains(MINIMAL_MESSAGE));
+        assert!(events.contains(&request_id));
+        assert!(!events.contains("10.0.0.7"));
+    }
+}
@@ -0,0 +1,281 @@
+//! Embeds the hub in another service. A [`Gateway`] sends requests through a pipeline in
+//! process, without an HTTP server or JSON encoding: it calls the same pipelines that serve
+//! the HTTP API, so requests go through the same plugins, routing and providers either way.
+//! What only applies to HTTP requests is skipped: request logging, artifacts, error
+//! redaction, and the features clients ask for with headers, such as idempotency keys or
+//! resumable streams.
+//!
+//! Each gateway has its own settings, budgets, usage and other state, so several can run
+//! in one process without affecting each other.
+//!
+//! ```
+//! use hub_lib::gateway::Gateway;
+//! use hub_lib::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+//! use hub_lib::models::content::ChatMessageContent;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = serde_yaml::from_str(
+//!     r#"
+//! providers:
+//!   - key: mock
+//!     type: mock
+//! models:
+//!   - key: echo
+//!     type: echo
+//!     provider: mock
+//! pipelines:
+//!   - name: default
+//!     type: chat
+//!     plugins:
+//!       - model-router:
+//!           models: [echo]
+//! "#,
+//! )?;
+//! let gateway = Gateway::new(config)?;
+//!
+//! let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+//!     "model": "echo",
+//!     "messages": [{"role": "user", "content": "ping"}],
+//! }))?;
+//! let ChatCompletionResponse::NonStream(completion) =
+//!     gateway.chat_completions("default", request).await?
+//! else {
+//!     unreachable!("the request didn't ask for a stream");
+//! };
+//! let content = completion.choices[0].message.content.as_ref();
+//! assert!(matches!(content, Some(ChatMessageContent::String(text)) if text == "ping"));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::config::models::PipelineType;
+use crate::config::validation::{InvalidConfig, validate_gateway_config};
+use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::completion::{CompletionRequest, CompletionResponse};
+use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
+use crate::outcome::Outcome;
+use crate::pipelines::normalization::{Normalized, ResponseNormalizer};
+use crate::pipelines::pipeline::{ChatOutcome, PipelineRunner};
+use crate::pipelines::stream_buffer::ChunkStream;
+use crate::pipelines::tool_call_aggregation::aggregate_tool_call_stream;
+use crate::routes::create_dynamic_pipeline_router;
+use crate::services::HubServices;
+use crate::state::AppState;
+use crate::types::GatewayConfig;
+use axum::body::to_bytes;
+use axum::extract::Request;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use futures::StreamExt;
+use reqwest_streams::error::{StreamBodyError, StreamBodyKind};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// Why a [`Gateway`] request failed.
+#[derive(Debug)]
+pub enum GatewayError {
+    /// The pipeline answered with an error status, e.g. 400 for an invalid request or 429
+    /// once a budget is spent. `body` is the error response the HTTP API would send.
+    Status { status: StatusCode, body: String },
+    /// The response couldn't be converted to the OpenAI schema.
+    Json(serde_json::Error),
+    /// The error response couldn't be read.
+    Body(axum::Error),
+}
+
+impl fmt::Display for GatewayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GatewayError::Status { status, body } if body.is_empty() => {
+                write!(f, "Pipeline answered {status}")
+            }
+            GatewayError::Status { status, body } => {
+                write!(f, "Pipeline answered {status}: {body}")
+            }
+            GatewayError::Json(e) => write!(f, "Invalid JSON: {e}"),
+            GatewayError::Body(e) => write!(f, "Failed to read the error response: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for GatewayError {}
+
+impl From<serde_json::Error> for GatewayError {
+    fn from(e: serde_json::Error) -> Self {
+        GatewayError::Json(e)
+    }
+}
+
+/// The hub as a library. Cloning is cheap and clones share the live config and state.
+#[derive(Clone)]
+pub struct Gateway {
+    state: Arc<AppState>,
+}
+
+impl Gateway {
+    /// A gateway serving `config`, which is validated as on startup.
+    pub fn new(config: GatewayConfig) -> anyhow::Result<Self> {
+        let services = HubServices::for_config(&config)?;
+        Self::with_services(config, services)
+    }
+
+    /// Like [`Gateway::new`], keeping budgets, usage and the like in `services`.
+    pub fn with_services(config: GatewayConfig, services: HubServices) -> anyhow::Result<Self> {
+        validate_gateway_config(&config).map_err(InvalidConfig)?;
+        Ok(Self::from_state(Arc::new(AppState::with_services(
+            config, services,
+        )?)))
+    }
+
+    /// A gateway serving whatever config `state` holds, including later updates.
+    pub fn from_state(state: Arc<AppState>) -> Self {
+        Self { state }
+    }
+
+    pub fn state(&self) -> &Arc<AppState> {
+        &self.state
+    }
+
+    /// Routes `request` to its pipeline, picked by the `x-traceloop-pipeline` header, and
+    /// returns the response the HTTP API would send. Paths are relative to `/api/v1`, e.g.
+    /// `/chat/completions`.
+    pub async fn handle(&self, request: Request) -> Response {
+        create_dynamic_pipeline_router(self.state.clone())
+            .oneshot(request)
+            .await
+            .unwrap_or_else(|never| match never {})
+    }
+
+    /// A chat completion from `pipeline`, streamed when the request sets `stream`. Unknown
+    /// pipelines fall back to the default one, as over HTTP.
+    pub async fn chat_completions(
+        &self,
+        pipeline: &str,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, GatewayError> {
+        let runner = self.runner(pipeline, PipelineType::Chat)?;
+        let request = match runner.admit(request) {
+            Ok(request) => request,
+            Err(rejection) => return Err(failed(&runner, rejection).await),
+        };
+        let outcome = match runner.run_chat(&HeaderMap::new(), request).await {
+            Ok(outcome) => outcome,
+            Err(status) => {
+                runner.record_outcome(Outcome::from_status(status), status);
+                return Err(GatewayError::Status {
+                    status,
+                    body: String::new(),
+                });
+            }
+        };
+        let normalizer = runner.normalizer();
+        let response = match outcome {
+            ChatOutcome::Response(response) => return Err(failed(&runner, response).await),
+            ChatOutcome::Completion { completion, .. } => {
+                ChatCompletionResponse::NonStream(typed(normalizer.completion(completion))?)
+            }
+            ChatOutcome::Stream { chunks, .. } => {
+                let chunks = if runner.aggregates_tool_calls() {
+                    aggregate_tool_call_stream(chunks)
+                } else {
+                    chunks
+                };
+                ChatCompletionResponse::Stream(normalized_chunks(chunks, normalizer))
+            }
+        };
+        runner.record_outcome(Outcome::Success, StatusCode::OK);
+        Ok(response)
+    }
+
+    pub async fn completions(
+        &self,
+        pipeline: &str,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, GatewayError> {
+        let runner = self.runner(pipeline, PipelineType::Completion)?;
+        let served = match runner.admit(request) {
+            Ok(request) => runner.run_completion(&HeaderMap::new(), request).await,
+            Err(rejection) => Err(rejection),
+        };
+        match served {
+            Ok(served) => {
+                runner.record_outcome(Outcome::Success, StatusCode::OK);
+                Ok(served.into_body())
+            }
+            Err(response) => Err(failed(&runner, response).await),
+        }
+    }
+
+    pub async fn embeddings(
+        &self,
+        pipeline: &str,
+        request: EmbeddingsRequest,
+    ) -> Result<EmbeddingsResponse, GatewayError> {
+        let runner = self.runner(pipeline, PipelineType::Embeddings)?;
+        let served = match runner.admit(request) {
+            Ok(request) => runner.run_embeddings(&HeaderMap::new(), request).await,
+            Err(rejection) => Err(rejection),
+        };
+        match served {
+            Ok(served) => {
+                runner.record_outcome(Outcome::Success, StatusCode::OK);
+                Ok(served.into_body())
+            }
+            Err(response) => Err(failed(&runner, response).await),
+        }
+    }
+
+    /// The pipeline `pipeline` names, or the default one, if it serves `r#type`. Fails with
+    /// 404 otherwise, as the HTTP API does.
+    fn runner(
+        &self,
+        pipeline: &str,
+        r#type: PipelineType,
+    ) -> Result<Arc<PipelineRunner>, GatewayError> {
+        self.state
+            .pipeline_runner(Some(pipeline))
+            .filter(|runner| runner.serves(r#type))
+            .ok_or(GatewayError::Status {
+                status: StatusCode::NOT_FOUND,
+                body: String::new(),
+            })
+    }
+}
+
+/// Counts a response the pipeline answered instead of a completion, and reads its body
+/// into the error.
+async fn failed(runner: &PipelineRunner, response: Response) -> GatewayError {
+    let status = response.status();
+    runner.record_outcome(Outcome::of(&response), status);
+    let max_body_bytes = runner.scope().settings.max_buffered_body_bytes;
+    match to_bytes(response.into_body(), max_body_bytes).await {
+        Ok(body) => GatewayError::Status {
+            status,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        },
+        Err(e) => GatewayError::Body(e),
+    }
+}
+
+/// A normalized response in the OpenAI schema. Provider fields the pipeline passes through
+/// have no place in it, so they're left out.
+fn typed<T: DeserializeOwned>(normalized: Normalized<T>) -> Result<T, serde_json::Error> {
+    match normalized {
+        Normalized::OpenAi(response) => Ok(response),
+        Normalized::WithMetadata(value) => serde_json::from_value(value),
+    }
+}
+
+/// The chunks of a streamed completion, normalized as the pipeline's SSE events are.
+fn normalized_chunks(chunks: ChunkStream, normalizer: ResponseNormalizer) -> ChunkStream {
+    chunks
+        .map(move |chunk| {
+            typed(normalizer.chunk(chunk?)).map_err(|e| {
+                StreamBodyError::new(StreamBodyKind::CodecError, Some(Box::new(e)), None)
+            })
+        })
+        .boxed()
+}
@@ -1,13 +1,36 @@
+pub mod access_log;
+pub mod admission;
 pub mod ai_models;
+pub mod artifacts;
+pub mod azure_compat;
+pub mod batch;
+pub mod compression;
 pub mod config;
+pub mod cors;
+pub mod error_detail;
+pub mod gateway;
+pub mod listener;
+pub mod logging;
 pub mod management;
+pub mod metrics;
 pub mod models;
+pub mod notifications;
 pub mod openapi;
+pub mod outcome;
 pub mod pipelines;
 pub mod providers;
 pub mod routes;
+pub mod services;
+pub mod startup;
 pub mod state;
+pub mod state_store;
+pub mod stream_limits;
+pub mod timing;
+pub mod trace_content;
+pub mod trace_context;
+pub mod traceloop_headers;
 pub mod types;
+pub mod upstream_headers;
 
 pub use axum;
 pub use reqwest;
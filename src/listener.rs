@@ -0,0 +1,234 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::SocketAddr;
+use std::os::fd::{FromRawFd, RawFd};
+use tokio::net::TcpListener;
+use tracing::info;
+
+/// The first file descriptor systemd passes to an activated service.
+pub const SD_LISTEN_FDS_START: RawFd = 3;
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// The server a listener is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerRole {
+    Gateway,
+    Management,
+}
+
+impl ListenerRole {
+    /// The `FileDescriptorName=` that hands a socket to this server.
+    pub fn fd_name(self) -> &'static str {
+        match self {
+            ListenerRole::Gateway => "gateway",
+            ListenerRole::Management => "management",
+        }
+    }
+
+    /// Position of the socket for this server when the sockets are unnamed.
+    fn position(self) -> usize {
+        match self {
+            ListenerRole::Gateway => 0,
+            ListenerRole::Management => 1,
+        }
+    }
+}
+
+/// Listening sockets passed by systemd socket activation (the `LISTEN_FDS` protocol).
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct InheritedSockets {
+    /// Each fd with its name from `LISTEN_FDNAMES`; taken fds are `None`.
+    fds: Vec<Option<(RawFd, String)>>,
+}
+
+impl InheritedSockets {
+    /// The sockets systemd passed to this process, if any.
+    pub fn from_env() -> Self {
+        Self::parse(
+            std::env::var("LISTEN_PID").ok().as_deref(),
+            std::env::var("LISTEN_FDS").ok().as_deref(),
+            std::env::var("LISTEN_FDNAMES").ok().as_deref(),
+            std::process::id(),
+        )
+    }
+
+    /// Reads the protocol's variables. They're ignored unless `LISTEN_PID` is `pid`, since
+    /// a process inherits its parent's environment but not its sockets.
+    pub fn parse(
+        listen_pid: Option<&str>,
+        listen_fds: Option<&str>,
+        listen_fdnames: Option<&str>,
+        pid: u32,
+    ) -> Self {
+        if listen_pid.and_then(|p| p.trim().parse::<u32>().ok()) != Some(pid) {
+            return Self::default();
+        }
+        let count = listen_fds
+            .and_then(|n| n.trim().parse::<RawFd>().ok())
+            .unwrap_or(0);
+        let names: Vec<&str> = listen_fdnames
+            .map(|n| n.split(':').collect())
+            .unwrap_or_default();
+        let fds = (0..count.max(0))
+            .map(|i| {
+                let name = names.get(i as usize).copied().unwrap_or_default();
+                Some((SD_LISTEN_FDS_START + i, name.to_string()))
+            })
+            .collect();
+        Self { fds }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fds.iter().all(Option::is_none)
+    }
+
+    /// Takes the fd for `role`: the one named after it, or when no fd is named after a
+    /// role, the first fd for the gateway and the second for the management API.
+    pub fn take(&mut self, role: ListenerRole) -> Option<RawFd> {
+        let named = self.fds.iter().flatten().any(|(_, name)| {
+            name == ListenerRole::Gateway.fd_name() || name == ListenerRole::Management.fd_name()
+        });
+        let slot = if named {
+            self.fds
+                .iter_mut()
+                .find(|fd| matches!(fd, Some((_, name)) if name == role.fd_name()))?
+        } else {
+            self.fds.get_mut(role.position())?
+        };
+        slot.take().map(|(fd, _)| fd)
+    }
+}
+
+/// A listener for `role`, on the socket systemd passed for it if there is one. Otherwise a
+/// new socket is bound to `address`, with `SO_REUSEPORT` when `reuse_port` is set so
+/// another instance can listen on the same port during a rolling restart.
+pub fn listen(
+    role: ListenerRole,
+    address: SocketAddr,
+    inherited: &mut InheritedSockets,
+    reuse_port: bool,
+) -> io::Result<TcpListener> {
+    let listener = match inherited.take(role) {
+        Some(fd) => {
+            info!("Using the socket passed by systemd as fd {fd} for the {role:?} listener");
+            // SAFETY: systemd hands the fds from SD_LISTEN_FDS_START on to this process,
+            // and `take` gives each of them out once.
+            let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+            // Fails if the fd isn't a socket.
+            listener.local_addr()?;
+            listener
+        }
+        None => bind(address, reuse_port)?,
+    };
+    listener.set_nonblocking(true)?;
+    TcpListener::from_std(listener)
+}
+
+fn bind(address: SocketAddr, reuse_port: bool) -> io::Result<std::net::TcpListener> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    // Like `TcpListener::bind`, so restarts don't wait out connections in TIME_WAIT.
+    socket.set_reuse_address(true)?;
+    if reuse_port {
+        socket.set_reuse_port(true)?;
+    }
+    socket.bind(&address.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    Ok(socket.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::fd::IntoRawFd;
+
+    const PID: u32 = 4242;
+
+    fn loopback() -> SocketAddr {
+        "127.0.0.1:0".parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_listen_fds() {
+        let mut sockets = InheritedSockets::parse(Some("4242"), Some("2"), None, PID);
+        assert_eq!(sockets.take(ListenerRole::Management), Some(4));
+        assert_eq!(sockets.take(ListenerRole::Gateway), Some(3));
+        assert_eq!(sockets.take(ListenerRole::Gateway), None);
+        assert!(sockets.is_empty());
+
+        // Names pick the socket regardless of order.
+        let mut sockets =
+            InheritedSockets::parse(Some("4242"), Some("2"), Some("management:gateway"), PID);
+        assert_eq!(sockets.take(ListenerRole::Gateway), Some(4));
+        assert_eq!(sockets.take(ListenerRole::Management), Some(3));
+
+        // Only the management API is activated; the gateway binds its own socket.
+        let mut sockets = InheritedSockets::parse(Some("4242"), Some("1"), Some("management"), PID);
+        assert_eq!(sockets.take(ListenerRole::Gateway), None);
+        assert_eq!(sockets.take(ListenerRole::Management), Some(3));
+
+        // Variables meant for another process, or missing, are ignored.
+        for (pid, fds) in [
+            (Some("1"), Some("2")),
+            (None, Some("2")),
+            (Some("4242"), None),
+            (Some("4242"), Some("0")),
+            (Some("4242"), Some("-1")),
+            (Some("not-a-pid"), Some("2")),
+        ] {
+            assert!(InheritedSockets::parse(pid, fds, None, PID).is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_listen_on_inherited_socket() {
+        let prebound = std::net::TcpListener::bind(loopback()).unwrap();
+        let address = prebound.local_addr().unwrap();
+        let mut inherited = InheritedSockets {
+            fds: vec![Some((prebound.into_raw_fd(), "gateway".to_string()))],
+        };
+
+        let listener = listen(ListenerRole::Gateway, loopback(), &mut inherited, false).unwrap();
+        assert_eq!(listener.local_addr().unwrap(), address);
+        assert!(inherited.is_empty());
+
+        let client = tokio::net::TcpStream::connect(address);
+        let (accepted, connected) = tokio::join!(listener.accept(), client);
+        assert_eq!(
+            accepted.unwrap().1,
+            connected.unwrap().local_addr().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_listen_binds_without_inherited_socket() {
+        let mut inherited = InheritedSockets::default();
+        let listener = listen(ListenerRole::Gateway, loopback(), &mut inherited, false).unwrap();
+        let address = listener.local_addr().unwrap();
+        assert!(address.ip().is_loopback());
+        assert_ne!(address.port(), 0);
+
+        // Without SO_REUSEPORT the port can't be shared.
+        let err = listen(ListenerRole::Gateway, address, &mut inherited, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+    }
+
+    #[tokio::test]
+    async fn test_reuse_port_lets_instances_overlap() {
+        let mut inherited = InheritedSockets::default();
+        let first = listen(ListenerRole::Gateway, loopback(), &mut inherited, true).unwrap();
+        let address = first.local_addr().unwrap();
+        let second = listen(ListenerRole::Gateway, address, &mut inherited, true).unwrap();
+        assert_eq!(second.local_addr().unwrap(), address);
+
+        // Connections keep being accepted once the old instance is gone.
+        drop(first);
+        let client = tokio::net::TcpStream::connect(address);
+        let (accepted, connected) = tokio::join!(second.accept(), client);
+        accepted.unwrap();
+        connected.unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use crate::config::constants::error_log_interval_seconds;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::error;
+
+#[derive(Debug)]
+struct SignatureState {
+    last_logged: Instant,
+    suppressed: u64,
+}
+
+/// Error logger that emits each signature at most once per interval and
+/// reports how many repeats were dropped in between.
+#[derive(Debug)]
+pub struct RateLimitedLogger {
+    interval: Duration,
+    signatures: Mutex<HashMap<String, SignatureState>>,
+}
+
+impl RateLimitedLogger {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            signatures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Logger shared by the whole process, configured by `ERROR_LOG_INTERVAL_SECONDS`.
+    pub fn global() -> &'static RateLimitedLogger {
+        static LOGGER: OnceLock<RateLimitedLogger> = OnceLock::new();
+        LOGGER.get_or_init(|| Self::new(Duration::from_secs(error_log_interval_seconds())))
+    }
+
+    /// Logs `message` at error level unless `signature` was logged within the interval.
+    pub fn error(&self, signature: &str, message: impl Display) {
+        match self.should_log_at(signature, Instant::now()) {
+            Some(0) => error!("{message}"),
+            Some(suppressed) => {
+                error!("{message} ({suppressed} similar errors suppressed)")
+            }
+            None => {}
+        }
+    }
+
+    /// Returns the number of suppressed repeats if `signature` may be logged at `now`.
+    fn should_log_at(&self, signature: &str, now: Instant) -> Option<u64> {
+        let mut signatures = self.signatures.lock().unwrap();
+        match signatures.get_mut(signature) {
+            Some(state) if now.saturating_duration_since(state.last_logged) < self.interval => {
+                state.suppressed += 1;
+                None
+            }
+            Some(state) => {
+                let suppressed = state.suppressed;
+                state.last_logged = now;
+                state.suppressed = 0;
+                Some(suppressed)
+            }
+            None => {
+                signatures.insert(
+                    signature.to_string(),
+                    SignatureState {
+                        last_logged: now,
+                        suppressed: 0,
+                    },
+                );
+                Some(0)
+            }
+        }
+    }
+}
+
+/// Logs an error through the global [`RateLimitedLogger`].
+pub fn error_rate_limited(signature: &str, message: impl Display) {
+    RateLimitedLogger::global().error(signature, message);
+}
+
+/// Decides which requests get logged when a pipeline sets a `sample_rate` below 1.
+#[derive(Debug)]
+pub struct LogSampler {
+    sample_rate: f64,
+    state: AtomicU64,
+}
+
+impl LogSampler {
+    pub fn new(sample_rate: f64) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Self::with_seed(sample_rate, seed)
+    }
+
+    /// Sampler with a fixed seed, so the sequence of decisions is reproducible.
+    pub fn with_seed(sample_rate: f64, seed: u64) -> Self {
+        Self {
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            state: AtomicU64::new(seed),
+        }
+    }
+
+    /// Returns true if the next request should be logged.
+    pub fn sample(&self) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        if self.sample_rate <= 0.0 {
+            return false;
+        }
+        self.next_f64() < self.sample_rate
+    }
+
+    /// Uniform value in `[0, 1)` from a splitmix64 sequence.
+    fn next_f64(&self) -> f64 {
+        let mut z = self
+            .state
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limited_logger_suppresses_repeats_within_interval() {
+        let logger = RateLimitedLogger::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(logger.should_log_at("openai.request", start), Some(0));
+        assert_eq!(
+            logger.should_log_at("openai.request", start + Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(
+            logger.should_log_at("openai.request", start + Duration::from_secs(59)),
+            None
+        );
+        // A different signature is tracked independently.
+        assert_eq!(
+            logger.should_log_at("anthropic.request", start + Duration::from_secs(2)),
+            Some(0)
+        );
+
+        assert_eq!(
+            logger.should_log_at("openai.request", start + Duration::from_secs(60)),
+            Some(2)
+        );
+        assert_eq!(
+            logger.should_log_at("openai.request", start + Duration::from_secs(121)),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_log_sampler_is_deterministic_with_seed() {
+        let first = LogSampler::with_seed(0.5, 42);
+        let second = LogSampler::with_seed(0.5, 42);
+        let decisions: Vec<bool> = (0..32).map(|_| first.sample()).collect();
+        let replayed: Vec<bool> = (0..32).map(|_| second.sample()).collect();
+        assert_eq!(decisions, replayed);
+    }
+
+    #[test]
+    fn test_log_sampler_approximates_rate() {
+        let sampler = LogSampler::with_seed(0.1, 7);
+        let sampled = (0..10_000).filter(|_| sampler.sample()).count();
+        assert!((800..1200).contains(&sampled), "sampled {sampled}");
+    }
+
+    #[test]
+    fn test_log_sampler_edge_rates() {
+        let always = LogSampler::with_seed(1.0, 1);
+        let never = LogSampler::with_seed(0.0, 1);
+        assert!((0..100).all(|_| always.sample()));
+        assert!((0..100).all(|_| !never.sample()));
+    }
+}
@@ -1,80 +1,84 @@
+use clap::{Args, Parser, Subcommand};
+use hub_lib::access_log::{ACCESS_LOG_TARGET, AccessLog, Server};
+use hub_lib::config::lib::EnvSubstitution;
+use hub_lib::config::validation::{CONFIG_ERROR_EXIT_CODE, InvalidConfig, Severity};
+use hub_lib::listener::{InheritedSockets, ListenerRole, listen};
+use hub_lib::logging::error_rate_limited;
+use hub_lib::management::poller::{
+    ConfigPoller, DEFAULT_POLL_JITTER_PERCENT, PollOutcome, PollSchedule, random_unit,
+};
+use hub_lib::management::services::api_key_service::{
+    ApiKeyService, StaticApiKey, auth_disabled_from_env,
+};
+use hub_lib::management::{DbBasedConfigIntegration, admin_router, db_based_config_integration};
+use hub_lib::startup::{
+    ConfigMode, DEFAULT_CONFIG_PATH, connect_database, determine_config_mode, read_yaml_config,
+    validate_config,
+};
 use hub_lib::types::GatewayConfig;
-use hub_lib::{config, routes, state::AppState};
+use hub_lib::{
+    config, routes,
+    state::{AppState, ConfigSource},
+};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::trace::{DefaultMakeSpan, TraceLayer};
-use tracing::{Level, debug, error, info};
+use std::time::Duration;
+use tracing::{Level, debug, error, info, warn};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
 
-// Always import database components - mode detection happens at runtime
-use {hub_lib::management::db_based_config_integration, sqlx::PgPool, std::time::Duration};
-
-#[allow(dead_code)]
-const DEFAULT_CONFIG_PATH: &str = "config.yaml";
 const DEFAULT_PORT: &str = "3000";
 const DEFAULT_MANAGEMENT_PORT: &str = "8080";
+/// Set to `true` to serve the admin endpoints in YAML mode, which has no management port
+/// otherwise.
+const MANAGEMENT_API_ENABLED_ENV: &str = "MANAGEMENT_API_ENABLED";
 const DEFAULT_DB_POLL_INTERVAL_SECONDS: u64 = 30;
 
 // Error handling constants
 const MAX_CONSECUTIVE_FAILURES: u32 = 5;
 const MAX_BACKOFF_SECONDS: u64 = 300; // 5 minutes
 
-#[derive(Debug, Clone)]
-pub enum ConfigMode {
-    Yaml { path: String },
-    Database { pool: PgPool },
-}
-
 type ConfigProvider =
     Arc<hub_lib::management::services::config_provider_service::ConfigProviderService>;
 
-async fn determine_config_mode() -> anyhow::Result<ConfigMode> {
-    // Check HUB_MODE environment variable first
-    match std::env::var("HUB_MODE").as_deref() {
-        Ok("database") => {
-            debug!("HUB_MODE=database detected. Initializing database mode.");
-            let database_url = std::env::var("DATABASE_URL")
-                .map_err(|e| anyhow::anyhow!("DATABASE_URL not set for database mode: {e}"))?;
-
-            debug!("Connecting to database: {}", database_url);
+#[derive(Parser)]
+#[command(name = "hub", version, about = "Traceloop Hub LLM gateway")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
 
-            // Use connection pool with optimized settings
-            let pool = PgPool::connect(&database_url).await.map_err(|e| {
-                anyhow::anyhow!("Failed to connect to database at {database_url}: {e}")
-            })?;
+#[derive(Subcommand)]
+enum Command {
+    /// Checks a configuration without starting the gateway. Prints the findings as a JSON
+    /// array and exits with 64 when any of them is an error.
+    Validate(ValidateArgs),
+}
 
-            info!("Database connection established successfully.");
-            Ok(ConfigMode::Database { pool })
-        }
-        Ok("yaml") => {
-            debug!("HUB_MODE=yaml detected. Using YAML configuration mode.");
-            let config_path = std::env::var("CONFIG_FILE_PATH")
-                .unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
-            Ok(ConfigMode::Yaml { path: config_path })
-        }
-        Ok(invalid_mode) => {
-            error!(
-                "Invalid HUB_MODE '{}'. Valid options: 'yaml', 'database'",
-                invalid_mode
-            );
-            Err(anyhow::anyhow!("Invalid HUB_MODE: {invalid_mode}"))
-        }
-        Err(_) => {
-            // HUB_MODE not set, fallback to yaml mode
-            debug!("HUB_MODE not set. Defaulting to YAML configuration mode.");
-            let config_path = std::env::var("CONFIG_FILE_PATH")
-                .unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
-            Ok(ConfigMode::Yaml { path: config_path })
-        }
-    }
+#[derive(Args)]
+struct ValidateArgs {
+    /// Config file, comma-separated list of files or glob to validate.
+    #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+    config: String,
+    /// Leave `${VAR}` references unresolved, only checking that they're well-formed.
+    #[arg(long, conflicts_with = "database")]
+    no_env: bool,
+    /// Also build the providers and read their API key files and secrets.
+    #[arg(long, conflicts_with = "no_env")]
+    resolve_secrets: bool,
+    /// Validate the configuration in the database at `DATABASE_URL` instead of a file.
+    #[arg(long)]
+    database: bool,
 }
 
 async fn get_initial_config_and_services(
     mode: ConfigMode,
-) -> anyhow::Result<(GatewayConfig, Option<axum::Router>, Option<ConfigProvider>)> {
+) -> anyhow::Result<(GatewayConfig, Option<DbBasedConfigIntegration>)> {
     match mode {
-        ConfigMode::Database { pool } => {
+        ConfigMode::Database { pools } => {
             debug!("Initializing database-based configuration.");
 
-            let db_integration = db_based_config_integration(pool).await?;
+            let db_integration = db_based_config_integration(pools).await?;
             debug!("Database integration initialized successfully.");
 
             match db_integration.config_provider.fetch_live_config().await {
@@ -89,9 +93,7 @@ async fn get_initial_config_and_services(
                             "Initial database configuration is invalid: {:?}. Application cannot start.",
                             val_errors
                         );
-                        return Err(anyhow::anyhow!(
-                            "Invalid initial database configuration: {val_errors:?}"
-                        ));
+                        return Err(InvalidConfig(val_errors).into());
                     }
 
                     info!(
@@ -101,11 +103,7 @@ async fn get_initial_config_and_services(
                         initial_db_config.pipelines.len()
                     );
 
-                    Ok((
-                        initial_db_config,
-                        Some(db_integration.router.clone()),
-                        Some(db_integration.config_provider.clone()),
-                    ))
+                    Ok((initial_db_config, Some(db_integration)))
                 }
                 Err(e) => {
                     error!(
@@ -119,51 +117,187 @@ async fn get_initial_config_and_services(
             }
         }
         ConfigMode::Yaml { path } => {
-            debug!("Loading configuration from YAML file: {}", path);
-            let yaml_config = config::load_config(&path).map_err(|e| {
-                anyhow::anyhow!("Failed to load YAML configuration from {path}: {e}")
-            })?;
+            let yaml_config = read_yaml_config(&path, EnvSubstitution::Resolve)?;
 
             if let Err(val_errors) = config::validation::validate_gateway_config(&yaml_config) {
                 error!(
                     "YAML configuration from {} is invalid: {:?}. Halting.",
                     path, val_errors
                 );
-                return Err(anyhow::anyhow!("Invalid YAML config: {val_errors:?}"));
+                return Err(InvalidConfig(val_errors).into());
             }
             debug!("YAML configuration validated successfully.");
-            Ok((yaml_config, None, None))
+            Ok((yaml_config, None))
         }
     }
 }
 
+/// Snapshots the database state behind the live config so it can be rolled back to.
+async fn record_config_snapshot(config_provider: &ConfigProvider, app_state: &AppState) {
+    let config_hash = app_state.config_version().config_hash;
+    match config_provider.record_applied_config(&config_hash).await {
+        Ok(true) => info!("Stored config snapshot for hash {}.", config_hash),
+        Ok(false) => debug!("No new config snapshot stored for hash {}.", config_hash),
+        Err(e) => error_rate_limited(
+            "db_poller.snapshot",
+            format!("Failed to store config snapshot: {e:?}"),
+        ),
+    }
+}
+
+/// Authenticates the admin endpoints in YAML mode, where only `MANAGEMENT_API_KEYS` exist.
+fn static_api_key_service() -> anyhow::Result<Arc<ApiKeyService>> {
+    let api_key_service = ApiKeyService::with_static_keys(StaticApiKey::from_env()?)
+        .with_auth_disabled(auth_disabled_from_env());
+    if api_key_service.is_auth_disabled() {
+        warn!(
+            "Management API authentication is disabled; the admin endpoints are served without an API key. Don't use this outside local development."
+        );
+    } else if !api_key_service.has_static_keys() {
+        warn!("MANAGEMENT_API_KEYS is not set; the admin endpoints reject every request.");
+    }
+    Ok(Arc::new(api_key_service))
+}
+
+/// Whether `MANAGEMENT_API_ENABLED` is set to `true`.
+fn management_enabled_from_env() -> bool {
+    std::env::var(MANAGEMENT_API_ENABLED_ENV).is_ok_and(|value| value.eq_ignore_ascii_case("true"))
+}
+
+/// Listens on the socket systemd passed for `role`, or else binds `port` on all interfaces.
+fn bind_listener(
+    server: &str,
+    role: ListenerRole,
+    port: &str,
+    inherited_sockets: &mut InheritedSockets,
+    reuse_port: bool,
+) -> anyhow::Result<tokio::net::TcpListener> {
+    let bind_address: SocketAddr = format!("0.0.0.0:{port}")
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid port '{port}' for {server}: {e}"))?;
+    let listener = listen(role, bind_address, inherited_sockets, reuse_port)
+        .map_err(|e| anyhow::anyhow!("Failed to bind {server} to {bind_address}: {e}"))?;
+    info!("Starting {} server on {}", server, listener.local_addr()?);
+    Ok(listener)
+}
+
+/// Exits with `CONFIG_ERROR_EXIT_CODE` and the validation errors as a JSON array on stderr
+/// when the configuration is invalid, so deploy tooling can tell it apart from other
+/// startup failures.
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() {
+    let result = match Cli::parse().command {
+        Some(Command::Validate(args)) => validate(args).await,
+        None => run().await,
+    };
+    if let Err(e) = result {
+        if let Some(invalid) = e.downcast_ref::<InvalidConfig>() {
+            eprintln!("{}", invalid.to_json());
+            std::process::exit(CONFIG_ERROR_EXIT_CODE);
+        }
+        eprintln!("Error: {e:?}");
+        std::process::exit(1);
+    }
+}
+
+/// `hub validate`: prints every finding, warnings included, to stdout.
+async fn validate(args: ValidateArgs) -> anyhow::Result<()> {
+    let mode = if args.database {
+        ConfigMode::Database {
+            pools: connect_database().await?,
+        }
+    } else {
+        ConfigMode::Yaml { path: args.config }
+    };
+    let env = if args.no_env {
+        EnvSubstitution::CheckSyntax
+    } else {
+        EnvSubstitution::Resolve
+    };
+    let findings = validate_config(mode, env, args.resolve_secrets).await?;
+    println!("{}", serde_json::to_string(&findings)?);
+    if findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error)
+    {
+        std::process::exit(CONFIG_ERROR_EXIT_CODE);
+    }
+    Ok(())
+}
+
+async fn run() -> anyhow::Result<()> {
     let log_level = std::env::var("RUST_LOG")
         .ok()
         .and_then(|level| level.parse::<Level>().ok())
         .unwrap_or(Level::WARN);
 
-    tracing_subscriber::fmt().with_max_level(log_level).init();
+    // Access logs apply each server's own level instead.
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .with(
+            Targets::new()
+                .with_default(log_level)
+                .with_target(ACCESS_LOG_TARGET, Level::TRACE),
+        )
+        .init();
 
     info!("Starting Traceloop Hub Gateway...");
 
     let config_mode = determine_config_mode().await?;
     info!("Configuration mode determined: {:?}", config_mode);
 
-    let (initial_config, management_router_opt, config_provider_opt) =
+    let (initial_config, db_integration) =
         get_initial_config_and_services(config_mode.clone()).await?;
+    let general = initial_config.general.clone();
+
+    let config_source = match &config_mode {
+        ConfigMode::Yaml { path } => ConfigSource::Yaml { path: path.clone() },
+        ConfigMode::Database { .. } => ConfigSource::Database,
+    };
 
     let app_state = Arc::new(
         AppState::new(initial_config)
-            .map_err(|e| anyhow::anyhow!("Failed to create app state: {e}"))?,
+            .map_err(|e| anyhow::anyhow!("Failed to create app state: {e}"))?
+            .with_config_source(config_source)
+            .with_metrics_export(),
     );
 
     // Create LLM Gateway router
     let gateway_router = routes::create_router(app_state.clone());
+    app_state.services().usage.clone().spawn_flush_task();
+
+    // The admin endpoints are served by the management server, which YAML mode only starts
+    // when asked to
+    let (management_router, config_provider_opt) = match db_integration {
+        Some(db_integration) => {
+            let admin = admin_router(
+                routes::admin_routes(app_state.clone()),
+                db_integration.api_key_service,
+            );
+            (
+                Some(db_integration.router.merge(admin)),
+                Some(db_integration.config_provider),
+            )
+        }
+        None if management_enabled_from_env() => (
+            Some(admin_router(
+                routes::admin_routes(app_state.clone()),
+                static_api_key_service()?,
+            )),
+            None,
+        ),
+        None => {
+            info!(
+                "Management API disabled in YAML mode; set {MANAGEMENT_API_ENABLED_ENV}=true to serve the admin endpoints."
+            );
+            (None, None)
+        }
+    };
 
     // Start configuration polling only in database mode
     if let Some(config_provider) = config_provider_opt {
+        record_config_snapshot(&config_provider, &app_state).await;
+
         // Clone Arcs for the poller task
         let poller_app_state = app_state.clone();
         let poller_config_provider = config_provider.clone();
@@ -172,40 +306,37 @@ async fn main() -> anyhow::Result<()> {
             .ok()
             .and_then(|s| s.parse().ok())
             .unwrap_or(DEFAULT_DB_POLL_INTERVAL_SECONDS);
-        let poll_duration = Duration::from_secs(poll_interval_seconds);
+        let poll_jitter_percent = std::env::var("DB_POLL_JITTER_PERCENT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_POLL_JITTER_PERCENT);
+        let schedule = PollSchedule::new(
+            Duration::from_secs(poll_interval_seconds),
+            poll_jitter_percent,
+        );
+        let poll_duration = schedule.interval();
 
         info!(
             "Starting database configuration poller with interval: {:?}.",
             poll_duration
         );
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(poll_duration);
+            let mut poller =
+                ConfigPoller::new(poller_config_provider.clone(), poller_app_state.clone());
             let mut consecutive_failures = 0u32;
 
+            // Replicas started together shouldn't poll together.
+            tokio::time::sleep(schedule.initial_delay(random_unit())).await;
             loop {
-                interval.tick().await;
                 debug!("Polling database for configuration updates...");
 
-                match poller_config_provider.fetch_live_config().await {
-                    Ok(new_config) => {
+                match poller.poll().await {
+                    Ok(outcome) => {
                         consecutive_failures = 0; // Reset failure counter on success
-
-                        debug!("Successfully fetched configuration from database.");
-                        debug!(
-                            "Config has {} providers, {} models, {} pipelines",
-                            new_config.providers.len(),
-                            new_config.models.len(),
-                            new_config.pipelines.len()
-                        );
-
-                        // Use AppState's efficient change detection - it handles validation internally
-                        match poller_app_state.update_config(new_config) {
-                            Ok(()) => {
-                                debug!("Configuration update completed successfully.");
-                            }
-                            Err(update_err) => {
-                                error!("Failed to apply updated configuration: {:?}", update_err);
-                            }
+                        debug!("Config poll completed: {:?}.", outcome);
+                        if outcome == PollOutcome::Applied {
+                            record_config_snapshot(&poller_config_provider, &poller_app_state)
+                                .await;
                         }
                     }
                     Err(e) => {
@@ -213,13 +344,15 @@ async fn main() -> anyhow::Result<()> {
 
                         if consecutive_failures <= MAX_CONSECUTIVE_FAILURES {
                             error!(
-                                "Failed to fetch configuration from DB (attempt {}/{}): {:?}",
+                                "Failed to poll configuration from DB (attempt {}/{}): {:?}",
                                 consecutive_failures, MAX_CONSECUTIVE_FAILURES, e
                             );
                         } else {
-                            error!(
-                                "Failed to fetch configuration from DB {} consecutive times. Will keep retrying but reducing log verbosity.",
-                                consecutive_failures
+                            error_rate_limited(
+                                "db_poller.fetch",
+                                format!(
+                                    "Failed to poll configuration from DB {consecutive_failures} consecutive times. Will keep retrying but reducing log verbosity."
+                                ),
                             );
                         }
 
@@ -233,72 +366,66 @@ async fn main() -> anyhow::Result<()> {
                         }
                     }
                 }
+                tokio::time::sleep(schedule.next_delay(random_unit())).await;
             }
         });
     }
 
-    // Apply tracing layer to gateway router
-    let gateway_app = gateway_router.layer(
-        TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::default().include_headers(true)),
-    );
+    // Apply tracing layer to gateway router. Client addresses key the per-client stream caps.
+    let gateway_app = gateway_router
+        .layer(AccessLog::new(Server::Gateway, general.as_ref(), log_level).layer())
+        .into_make_service_with_connect_info::<SocketAddr>();
+
+    // Sockets passed by systemd socket activation take the place of the ports
+    let mut inherited_sockets = InheritedSockets::from_env();
+    let reuse_port = app_state.settings().reuse_port;
 
     // Get port configurations
     let gateway_port = std::env::var("PORT").unwrap_or_else(|_| DEFAULT_PORT.to_string());
-    let gateway_bind_address = format!("0.0.0.0:{gateway_port}");
-
-    info!("Starting LLM Gateway server on {}", gateway_bind_address);
-    let gateway_listener = tokio::net::TcpListener::bind(&gateway_bind_address)
-        .await
-        .map_err(|e| {
-            anyhow::anyhow!("Failed to bind LLM Gateway to {gateway_bind_address}: {e}")
-        })?;
-
-    // Start servers based on mode
-    match management_router_opt {
+    let gateway_listener = bind_listener(
+        "LLM Gateway",
+        ListenerRole::Gateway,
+        &gateway_port,
+        &mut inherited_sockets,
+        reuse_port,
+    )?;
+
+    let management_server = match management_router {
         Some(management_router) => {
-            // Database mode - start both servers
             let management_port = std::env::var("MANAGEMENT_PORT")
                 .unwrap_or_else(|_| DEFAULT_MANAGEMENT_PORT.to_string());
-            let management_bind_address = format!("0.0.0.0:{management_port}");
-
-            info!(
-                "Starting Management API server on {}",
-                management_bind_address
-            );
-            let management_listener = tokio::net::TcpListener::bind(&management_bind_address)
-                .await
-                .map_err(|e| {
-                    anyhow::anyhow!(
-                        "Failed to bind Management API to {management_bind_address}: {e}"
-                    )
-                })?;
+            let management_listener = bind_listener(
+                "Management API",
+                ListenerRole::Management,
+                &management_port,
+                &mut inherited_sockets,
+                reuse_port,
+            )?;
 
             // Apply tracing layer to management router
-            let management_app = management_router.layer(
-                TraceLayer::new_for_http()
-                    .make_span_with(DefaultMakeSpan::default().include_headers(true)),
-            );
+            let management_app = management_router
+                .layer(AccessLog::new(Server::Management, general.as_ref(), log_level).layer());
+            Some(axum::serve(management_listener, management_app))
+        }
+        None => None,
+    };
 
-            tokio::select! {
-                res = axum::serve(gateway_listener, gateway_app) => {
-                    if let Err(e) = res {
-                        error!("LLM Gateway server failed: {}", e);
-                    }
-                },
-                res = axum::serve(management_listener, management_app) => {
-                    if let Err(e) = res {
-                        error!("Management API server failed: {}", e);
-                    }
-                },
+    tokio::select! {
+        res = axum::serve(gateway_listener, gateway_app) => {
+            if let Err(e) = res {
+                error!("LLM Gateway server failed: {}", e);
             }
-        }
-        None => {
-            // YAML mode - only start the gateway server
-            let app = gateway_app;
-            axum::serve(gateway_listener, app)
-                .await
-                .map_err(|e| anyhow::anyhow!("LLM Gateway server failed: {e}"))?;
-        }
+        },
+        res = async {
+            match management_server {
+                Some(management_server) => management_server.await,
+                None => std::future::pending().await,
+            }
+        } => {
+            if let Err(e) = res {
+                error!("Management API server failed: {}", e);
+            }
+        },
     }
 
     Ok(())
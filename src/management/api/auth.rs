@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, Method, header},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::management::{
+    dto::ApiKeyRole, errors::ApiError, services::api_key_service::ApiKeyService,
+};
+
+/// Authenticates a management API request by its bearer token and enforces the key's role.
+/// The resolved [`ApiKeyRole`] is stored in the request extensions.
+pub async fn require_api_key(
+    State(api_key_service): State<Arc<ApiKeyService>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let role = api_key_service
+        .authenticate(bearer_token(request.headers())?)
+        .await?;
+    if role == ApiKeyRole::ReadOnly && !matches!(*request.method(), Method::GET | Method::HEAD) {
+        return Err(ApiError::Forbidden(
+            "Read-only API keys can only perform GET requests.".to_string(),
+        ));
+    }
+    request.extensions_mut().insert(role);
+    Ok(next.run(request).await)
+}
+
+/// Restricts routes to admin keys. Must be layered inside [`require_api_key`].
+pub async fn require_admin(request: Request, next: Next) -> Result<Response, ApiError> {
+    match request.extensions().get::<ApiKeyRole>() {
+        Some(ApiKeyRole::Admin) => Ok(next.run(request).await),
+        _ => Err(ApiError::Forbidden(
+            "This endpoint requires an admin API key.".to_string(),
+        )),
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Result<Option<&str>, ApiError> {
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|v| v.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| Some(token.trim()))
+        .ok_or_else(|| {
+            ApiError::Unauthorized("Authorization header must be 'Bearer <key>'.".to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_bearer_token_parsing() {
+        assert_eq!(bearer_token(&HeaderMap::new()).unwrap(), None);
+        assert_eq!(bearer_token(&headers("Bearer abc")).unwrap(), Some("abc"));
+        assert_eq!(bearer_token(&headers("bearer abc")).unwrap(), Some("abc"));
+        assert!(matches!(
+            bearer_token(&headers("Basic abc")),
+            Err(ApiError::Unauthorized(_))
+        ));
+    }
+}
@@ -1 +1,3 @@
+pub mod auth;
 pub mod routes;
+pub mod versioning;
@@ -0,0 +1,119 @@
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::StatusCode,
+    middleware,
+    response::Json,
+    routing::{delete, post},
+};
+use uuid::Uuid;
+
+use crate::management::{
+    AppState,
+    api::auth::require_admin,
+    dto::{ApiKeyResponse, ApiKeySecretResponse, CreateApiKeyRequest},
+    errors::ApiError,
+};
+
+/// Creates the Axum router for API key management. Every route requires an admin key.
+pub fn api_key_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", post(create_api_key_handler).get(list_api_keys_handler))
+        .route("/{id}", delete(revoke_api_key_handler))
+        .route("/{id}/rotate", post(rotate_api_key_handler))
+        .route_layer(middleware::from_fn(require_admin))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/management/api-keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "API key created; the key is only shown in this response", body = ApiKeySecretResponse),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "Admin API key required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "API Keys"
+)]
+#[axum::debug_handler]
+async fn create_api_key_handler(
+    State(app_state): State<AppState>,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<ApiKeySecretResponse>), ApiError> {
+    let service = &app_state.api_key_service;
+    let api_key = service.create_key(payload).await?;
+    Ok((StatusCode::CREATED, Json(api_key)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/management/api-keys",
+    responses(
+        (status = 200, description = "List of active API keys", body = Vec<ApiKeyResponse>),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "Admin API key required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "API Keys"
+)]
+#[axum::debug_handler]
+async fn list_api_keys_handler(
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<ApiKeyResponse>>, ApiError> {
+    let service = &app_state.api_key_service;
+    let api_keys = service.list_keys().await?;
+    Ok(Json(api_keys))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/management/api-keys/{id}/rotate",
+    params(
+        ("id" = Uuid, Path, description = "API key ID")
+    ),
+    responses(
+        (status = 200, description = "New key issued; the old one no longer works", body = ApiKeySecretResponse),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "Admin API key required", body = ApiError),
+        (status = 404, description = "API key not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "API Keys"
+)]
+#[axum::debug_handler]
+async fn rotate_api_key_handler(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiKeySecretResponse>, ApiError> {
+    let service = &app_state.api_key_service;
+    let api_key = service.rotate_key(id).await?;
+    Ok(Json(api_key))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/management/api-keys/{id}",
+    params(
+        ("id" = Uuid, Path, description = "API key ID")
+    ),
+    responses(
+        (status = 204, description = "API key revoked"),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "Admin API key required", body = ApiError),
+        (status = 404, description = "API key not found", body = ApiError),
+        (status = 409, description = "Conflict - the last admin key can't be revoked", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "API Keys"
+)]
+#[axum::debug_handler]
+async fn revoke_api_key_handler(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let service = &app_state.api_key_service;
+    service.revoke_key(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,100 @@
+use axum::{
+    Router,
+    extract::{Path, State},
+    response::Json,
+    routing::{get, post},
+};
+use uuid::Uuid;
+
+use crate::config::validation::validate_gateway_config;
+use crate::management::{
+    AppState,
+    dto::{ConfigDiffResponse, ConfigSnapshotResponse},
+    errors::ApiError,
+};
+
+/// Creates the Axum router for config snapshots, rollbacks and diffs.
+pub fn config_routes() -> Router<AppState> {
+    Router::new()
+        .route("/snapshots", get(list_config_snapshots_handler))
+        .route("/rollback/{snapshot_id}", post(rollback_config_handler))
+        .route("/diff", post(diff_config_handler))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/management/config/snapshots",
+    responses(
+        (status = 200, description = "Snapshots of applied configs, newest first, with their differences from the live state", body = Vec<ConfigSnapshotResponse>),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "Config"
+)]
+#[axum::debug_handler]
+async fn list_config_snapshots_handler(
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<ConfigSnapshotResponse>>, ApiError> {
+    let snapshots = app_state.config_snapshot_service.list_snapshots().await?;
+    Ok(Json(snapshots))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/management/config/rollback/{snapshot_id}",
+    params(
+        ("snapshot_id" = Uuid, Path, description = "Config snapshot ID")
+    ),
+    responses(
+        (status = 200, description = "Providers, model definitions and pipelines restored; the diff lists what changed", body = ConfigSnapshotResponse),
+        (status = 404, description = "Config snapshot not found", body = ApiError),
+        (status = 422, description = "The restored config fails validation; nothing was changed. `validation_errors` lists the findings", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "Config"
+)]
+#[axum::debug_handler]
+async fn rollback_config_handler(
+    State(app_state): State<AppState>,
+    Path(snapshot_id): Path<Uuid>,
+) -> Result<Json<ConfigSnapshotResponse>, ApiError> {
+    let previous = app_state.config_snapshot_service.capture_state().await?;
+    let snapshot = app_state
+        .config_snapshot_service
+        .rollback(snapshot_id)
+        .await?;
+
+    // Snapshots predate any checks added since, so the restored config is validated the
+    // way the gateway would and undone if the gateway would refuse to load it.
+    let config = app_state
+        .config_provider_service
+        .fetch_live_config()
+        .await
+        .map_err(|e| {
+            ApiError::InternalServerError(format!("Failed to load restored config: {e}"))
+        })?;
+    if let Err(errors) = validate_gateway_config(&config) {
+        app_state.config_snapshot_service.restore(&previous).await?;
+        return Err(ApiError::InvalidConfig(errors));
+    }
+    Ok(Json(snapshot))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/management/config/diff",
+    request_body(content = String, description = "A configuration in the schema of config.yaml", content_type = "application/yaml"),
+    responses(
+        (status = 200, description = "How the config built from the database differs from the submitted YAML. Secrets are masked", body = ConfigDiffResponse),
+        (status = 400, description = "The YAML can't be parsed as a configuration", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "Config"
+)]
+#[axum::debug_handler]
+async fn diff_config_handler(
+    State(app_state): State<AppState>,
+    yaml: String,
+) -> Result<Json<ConfigDiffResponse>, ApiError> {
+    let diff = app_state.config_diff_service.diff_yaml(&yaml).await?;
+    Ok(Json(diff))
+}
@@ -1,4 +1,6 @@
 // Placeholder for route modules
+pub mod api_key_routes;
+pub mod config_routes;
 pub mod model_definition_routes;
 pub mod pipeline_routes;
 pub mod provider_routes;
@@ -9,6 +9,7 @@ use uuid::Uuid;
 
 use crate::management::{
     AppState,
+    api::versioning::IfMatch,
     dto::{CreateModelDefinitionRequest, ModelDefinitionResponse, UpdateModelDefinitionRequest},
     errors::ApiError,
 };
@@ -127,13 +128,15 @@ async fn get_model_definition_by_key_handler(
     path = "/api/v1/management/model-definitions/{id}",
     request_body = UpdateModelDefinitionRequest,
     params(
-        ("id" = Uuid, Path, description = "Model Definition ID")
+        ("id" = Uuid, Path, description = "Model Definition ID"),
+        ("If-Match" = Option<i32>, Header, description = "Version the update is based on")
     ),
     responses(
         (status = 200, description = "Model definition updated successfully", body = ModelDefinitionResponse),
         (status = 400, description = "Invalid request", body = ApiError),
         (status = 404, description = "Model definition not found or provider not found", body = ApiError),
-        (status = 409, description = "Conflict - key already exists", body = ApiError),
+        (status = 409, description = "Conflict - key already exists or version is stale", body = ApiError),
+        (status = 428, description = "Missing If-Match header or expected_version", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "Model Definitions"
@@ -142,11 +145,13 @@ async fn get_model_definition_by_key_handler(
 async fn update_model_definition_handler(
     State(app_state): State<AppState>,
     Path(id): Path<Uuid>,
+    if_match: IfMatch,
     Json(payload): Json<UpdateModelDefinitionRequest>,
 ) -> Result<Json<ModelDefinitionResponse>, ApiError> {
+    let expected_version = if_match.expected_version(payload.expected_version)?;
     let response = app_state
         .model_definition_service
-        .update_model_definition(id, payload)
+        .update_model_definition(id, payload, expected_version)
         .await?;
     Ok(Json(response))
 }
@@ -1,15 +1,19 @@
 use axum::{
     Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::Json,
-    routing::{get, post},
+    routing::{get, patch, post},
 };
 use uuid::Uuid;
 
 use crate::management::{
     AppState,
-    dto::{CreatePipelineRequestDto, PipelineResponseDto, UpdatePipelineRequestDto},
+    api::versioning::IfMatch,
+    dto::{
+        CreatePipelineRequestDto, DeleteQuery, PatchPipelinePluginRequestDto, PipelineNameQuery,
+        PipelineResponseDto, PromotePipelineRequestDto, UpdatePipelineRequestDto,
+    },
     errors::ApiError,
 };
 
@@ -79,7 +83,8 @@ async fn get_pipeline_handler(
     get,
     path = "/api/v1/management/pipelines/name/{name}",
     params(
-        ("name" = String, Path, description = "Pipeline Name")
+        ("name" = String, Path, description = "Pipeline Name"),
+        PipelineNameQuery
     ),
     responses(
         (status = 200, description = "Pipeline found by name", body = PipelineResponseDto),
@@ -92,10 +97,11 @@ async fn get_pipeline_handler(
 async fn get_pipeline_by_name_handler(
     State(app_state): State<AppState>,
     Path(name): Path<String>,
+    Query(query): Query<PipelineNameQuery>,
 ) -> Result<Json<PipelineResponseDto>, ApiError> {
     let result = app_state
         .pipeline_service
-        .get_pipeline_by_name(&name)
+        .get_pipeline_by_name(&name, query.environment.as_deref())
         .await?;
     Ok(Json(result))
 }
@@ -105,13 +111,15 @@ async fn get_pipeline_by_name_handler(
     path = "/api/v1/management/pipelines/{id}",
     request_body = UpdatePipelineRequestDto,
     params(
-        ("id" = Uuid, Path, description = "Pipeline ID")
+        ("id" = Uuid, Path, description = "Pipeline ID"),
+        ("If-Match" = Option<i32>, Header, description = "Version the update is based on")
     ),
     responses(
         (status = 200, description = "Pipeline updated successfully", body = PipelineResponseDto),
         (status = 400, description = "Invalid request", body = ApiError),
         (status = 404, description = "Pipeline not found", body = ApiError),
-        (status = 409, description = "Conflict - pipeline name already exists", body = ApiError),
+        (status = 409, description = "Conflict - pipeline name already exists or version is stale", body = ApiError),
+        (status = 428, description = "Missing If-Match header or expected_version", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "Pipelines"
@@ -120,11 +128,13 @@ async fn get_pipeline_by_name_handler(
 async fn update_pipeline_handler(
     State(app_state): State<AppState>,
     Path(id): Path<Uuid>,
+    if_match: IfMatch,
     Json(payload): Json<UpdatePipelineRequestDto>,
 ) -> Result<Json<PipelineResponseDto>, ApiError> {
+    let expected_version = if_match.expected_version(payload.expected_version)?;
     let result = app_state
         .pipeline_service
-        .update_pipeline(id, payload)
+        .update_pipeline(id, payload, expected_version)
         .await?;
     Ok(Json(result))
 }
@@ -133,7 +143,8 @@ async fn update_pipeline_handler(
     delete,
     path = "/api/v1/management/pipelines/{id}",
     params(
-        ("id" = Uuid, Path, description = "Pipeline ID")
+        ("id" = Uuid, Path, description = "Pipeline ID"),
+        DeleteQuery
     ),
     responses(
         (status = 204, description = "Pipeline deleted successfully"),
@@ -146,11 +157,100 @@ async fn update_pipeline_handler(
 async fn delete_pipeline_handler(
     State(app_state): State<AppState>,
     Path(id): Path<Uuid>,
+    Query(query): Query<DeleteQuery>,
 ) -> Result<StatusCode, ApiError> {
-    app_state.pipeline_service.delete_pipeline(id).await?;
+    app_state
+        .pipeline_service
+        .delete_pipeline(id, query.hard)
+        .await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/management/pipelines/{id}/restore",
+    params(
+        ("id" = Uuid, Path, description = "Pipeline ID")
+    ),
+    responses(
+        (status = 200, description = "Pipeline restored successfully", body = PipelineResponseDto),
+        (status = 404, description = "Deleted pipeline not found", body = ApiError),
+        (status = 409, description = "Conflict - pipeline name is taken", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "Pipelines"
+)]
+#[axum::debug_handler]
+async fn restore_pipeline_handler(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PipelineResponseDto>, ApiError> {
+    let result = app_state.pipeline_service.restore_pipeline(id).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/management/pipelines/{id}/promote",
+    request_body = PromotePipelineRequestDto,
+    params(
+        ("id" = Uuid, Path, description = "ID of the pipeline to promote")
+    ),
+    responses(
+        (status = 200, description = "Pipeline promoted; returns the pipeline in the target environment", body = PipelineResponseDto),
+        (status = 400, description = "Invalid target environment", body = ApiError),
+        (status = 404, description = "Pipeline not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "Pipelines"
+)]
+#[axum::debug_handler]
+async fn promote_pipeline_handler(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<PromotePipelineRequestDto>,
+) -> Result<Json<PipelineResponseDto>, ApiError> {
+    let result = app_state
+        .pipeline_service
+        .promote_pipeline(id, payload)
+        .await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/management/pipelines/{id}/plugins/{plugin_id}",
+    request_body = PatchPipelinePluginRequestDto,
+    params(
+        ("id" = Uuid, Path, description = "Pipeline ID"),
+        ("plugin_id" = Uuid, Path, description = "Plugin configuration ID"),
+        ("If-Match" = Option<i32>, Header, description = "Pipeline version the update is based on")
+    ),
+    responses(
+        (status = 200, description = "Plugin updated successfully", body = PipelineResponseDto),
+        (status = 400, description = "Invalid request or resulting plugin config", body = ApiError),
+        (status = 404, description = "Pipeline or plugin not found", body = ApiError),
+        (status = 409, description = "Conflict - pipeline version is stale", body = ApiError),
+        (status = 428, description = "Missing If-Match header or expected_version", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "Pipelines"
+)]
+#[axum::debug_handler]
+async fn patch_pipeline_plugin_handler(
+    State(app_state): State<AppState>,
+    Path((id, plugin_id)): Path<(Uuid, Uuid)>,
+    if_match: IfMatch,
+    Json(payload): Json<PatchPipelinePluginRequestDto>,
+) -> Result<Json<PipelineResponseDto>, ApiError> {
+    let expected_version = if_match.expected_version(payload.expected_version)?;
+    let result = app_state
+        .pipeline_service
+        .patch_pipeline_plugin(id, plugin_id, payload, expected_version)
+        .await?;
+    Ok(Json(result))
+}
+
 // --- Router Definition ---
 
 pub fn pipeline_routes() -> Router<AppState> {
@@ -165,5 +265,11 @@ pub fn pipeline_routes() -> Router<AppState> {
                 .put(update_pipeline_handler)
                 .delete(delete_pipeline_handler),
         )
+        .route("/{id}/restore", post(restore_pipeline_handler))
+        .route("/{id}/promote", post(promote_pipeline_handler))
+        .route(
+            "/{id}/plugins/{plugin_id}",
+            patch(patch_pipeline_plugin_handler),
+        )
         .route("/name/{name}", get(get_pipeline_by_name_handler))
 }
@@ -1,6 +1,6 @@
 use axum::{
     Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::Json,
     routing::{get, post},
@@ -9,7 +9,11 @@ use uuid::Uuid;
 
 use crate::management::{
     AppState,
-    dto::{CreateProviderRequest, ProviderResponse, UpdateProviderRequest},
+    api::versioning::IfMatch,
+    dto::{
+        CreateProviderRequest, DeleteQuery, ForceQuery, ProviderDependentsResponse,
+        ProviderResponse, UpdateProviderRequest,
+    },
     errors::ApiError,
 };
 
@@ -27,6 +31,8 @@ pub fn provider_routes() -> Router<AppState> {
                 .put(update_provider_handler)
                 .delete(delete_provider_handler),
         )
+        .route("/{id}/restore", post(restore_provider_handler))
+        .route("/{id}/dependents", get(get_provider_dependents_handler))
 }
 
 #[utoipa::path(
@@ -97,13 +103,16 @@ async fn get_provider_handler(
     path = "/api/v1/management/providers/{id}",
     request_body = UpdateProviderRequest,
     params(
-        ("id" = Uuid, Path, description = "Provider ID")
+        ("id" = Uuid, Path, description = "Provider ID"),
+        ("If-Match" = Option<i32>, Header, description = "Version the update is based on"),
+        ForceQuery
     ),
     responses(
         (status = 200, description = "Provider updated successfully", body = ProviderResponse),
         (status = 400, description = "Invalid request", body = ApiError),
         (status = 404, description = "Provider not found", body = ApiError),
-        (status = 409, description = "Conflict - provider name already exists", body = ApiError),
+        (status = 409, description = "Conflict - provider name already exists, version is stale, or disabling would break enabled pipelines", body = ApiError),
+        (status = 428, description = "Missing If-Match header or expected_version", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "Providers"
@@ -112,10 +121,15 @@ async fn get_provider_handler(
 async fn update_provider_handler(
     State(app_state): State<AppState>,
     Path(id): Path<Uuid>,
+    Query(force): Query<ForceQuery>,
+    if_match: IfMatch,
     Json(payload): Json<UpdateProviderRequest>,
 ) -> Result<Json<ProviderResponse>, ApiError> {
+    let expected_version = if_match.expected_version(payload.expected_version)?;
     let service = &app_state.provider_service;
-    let provider_response = service.update_provider(id, payload).await?;
+    let provider_response = service
+        .update_provider(id, payload, expected_version, force.force)
+        .await?;
     Ok(Json(provider_response))
 }
 
@@ -123,11 +137,14 @@ async fn update_provider_handler(
     delete,
     path = "/api/v1/management/providers/{id}",
     params(
-        ("id" = Uuid, Path, description = "Provider ID")
+        ("id" = Uuid, Path, description = "Provider ID"),
+        DeleteQuery,
+        ForceQuery
     ),
     responses(
         (status = 204, description = "Provider deleted successfully"),
         (status = 404, description = "Provider not found", body = ApiError),
+        (status = 409, description = "Conflict - provider is used by enabled pipelines", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "Providers"
@@ -136,8 +153,57 @@ async fn update_provider_handler(
 async fn delete_provider_handler(
     State(app_state): State<AppState>,
     Path(id): Path<Uuid>,
+    Query(query): Query<DeleteQuery>,
+    Query(force): Query<ForceQuery>,
 ) -> Result<StatusCode, ApiError> {
     let service = &app_state.provider_service;
-    service.delete_provider(id).await?;
+    service.delete_provider(id, query.hard, force.force).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/management/providers/{id}/dependents",
+    params(
+        ("id" = Uuid, Path, description = "Provider ID")
+    ),
+    responses(
+        (status = 200, description = "Model definitions on the provider and the pipelines routing to them", body = ProviderDependentsResponse),
+        (status = 404, description = "Provider not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "Providers"
+)]
+#[axum::debug_handler]
+async fn get_provider_dependents_handler(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ProviderDependentsResponse>, ApiError> {
+    let service = &app_state.provider_service;
+    let dependents = service.find_dependents(id).await?;
+    Ok(Json(dependents))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/management/providers/{id}/restore",
+    params(
+        ("id" = Uuid, Path, description = "Provider ID")
+    ),
+    responses(
+        (status = 200, description = "Provider restored successfully", body = ProviderResponse),
+        (status = 404, description = "Deleted provider not found", body = ApiError),
+        (status = 409, description = "Conflict - provider name is taken", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "Providers"
+)]
+#[axum::debug_handler]
+async fn restore_provider_handler(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ProviderResponse>, ApiError> {
+    let service = &app_state.provider_service;
+    let provider_response = service.restore_provider(id).await?;
+    Ok(Json(provider_response))
+}
@@ -0,0 +1,91 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+
+use crate::management::errors::ApiError;
+
+/// The resource version named by an `If-Match` header, e.g. `If-Match: 3` or `If-Match: "3"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IfMatch(pub Option<i32>);
+
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(header::IF_MATCH) else {
+            return Ok(Self(None));
+        };
+        value
+            .to_str()
+            .ok()
+            .map(|v| v.trim().trim_start_matches("W/").trim_matches('"'))
+            .and_then(|v| v.parse().ok())
+            .map(|version| Self(Some(version)))
+            .ok_or_else(|| {
+                ApiError::ValidationError(
+                    "If-Match must be a resource version, e.g. If-Match: 3".to_string(),
+                )
+            })
+    }
+}
+
+impl IfMatch {
+    /// The version an update is based on, from this header or the body's `expected_version`.
+    pub fn expected_version(self, body_version: Option<i32>) -> Result<i32, ApiError> {
+        match (self.0, body_version) {
+            (Some(header), Some(body)) if header != body => Err(ApiError::ValidationError(
+                format!("If-Match ({header}) and expected_version ({body}) disagree"),
+            )),
+            (Some(version), _) | (None, Some(version)) => Ok(version),
+            (None, None) => Err(ApiError::PreconditionRequired(
+                "Updates must send the current version in an If-Match header or expected_version field"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn parse(value: &str) -> Result<IfMatch, ApiError> {
+        let (mut parts, _) = Request::builder()
+            .header(header::IF_MATCH, value)
+            .body(())
+            .unwrap()
+            .into_parts();
+        IfMatch::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_if_match_parsing() {
+        assert_eq!(parse("3").await.unwrap(), IfMatch(Some(3)));
+        assert_eq!(parse("\"4\"").await.unwrap(), IfMatch(Some(4)));
+        assert_eq!(parse("W/\"5\"").await.unwrap(), IfMatch(Some(5)));
+        assert!(matches!(
+            parse("*").await,
+            Err(ApiError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_expected_version_sources() {
+        assert_eq!(IfMatch(Some(2)).expected_version(None).unwrap(), 2);
+        assert_eq!(IfMatch(None).expected_version(Some(7)).unwrap(), 7);
+        assert_eq!(IfMatch(Some(2)).expected_version(Some(2)).unwrap(), 2);
+        assert!(matches!(
+            IfMatch(Some(2)).expected_version(Some(3)),
+            Err(ApiError::ValidationError(_))
+        ));
+        assert!(matches!(
+            IfMatch(None).expected_version(None),
+            Err(ApiError::PreconditionRequired(_))
+        ));
+    }
+}
@@ -1,2 +1,5 @@
 pub mod models;
+pub mod pools;
 pub mod repositories;
+
+pub use pools::DbPools;
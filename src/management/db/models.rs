@@ -18,6 +18,7 @@ pub struct Provider {
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i32,
 }
 
 #[derive(Debug, FromRow, Clone)] // Added Clone here for potential use in services
@@ -30,6 +31,7 @@ pub struct ModelDefinition {
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i32,
 }
 
 /// Represents a pipeline record in the database.
@@ -40,8 +42,10 @@ pub struct Pipeline {
     pub pipeline_type: String,
     pub description: Option<String>,
     pub enabled: bool,
+    pub environment: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i32,
 }
 
 /// Represents a pipeline plugin configuration record in the database.
@@ -57,6 +61,19 @@ pub struct PipelinePluginConfig {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Represents a management API key record in the database.
+#[derive(Debug, FromRow, Clone)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub key_hash: String,
+    pub key_prefix: String,
+    pub role: String, // 'admin' or 'read_only'
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
 /// Helper struct to combine a Pipeline with its associated plugins.
 /// This is not a direct database model but used for service/repository logic.
 #[derive(Debug, Clone)]
@@ -66,7 +83,18 @@ pub struct PipelineWithPlugins {
     pub pipeline_type: String,
     pub description: Option<String>,
     pub enabled: bool,
+    pub environment: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i32,
     pub plugins: Vec<PipelinePluginConfig>,
 }
+
+/// Represents a snapshot of the configuration the gateway applied.
+#[derive(Debug, FromRow, Clone)]
+pub struct ConfigSnapshot {
+    pub id: Uuid,
+    pub config_hash: String,
+    pub config_state: JsonValue, // Serialized ConfigState
+    pub created_at: DateTime<Utc>,
+}
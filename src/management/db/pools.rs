@@ -0,0 +1,153 @@
+use sqlx::PgPool;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How long a read waits for a replica connection before falling back to the primary.
+const READ_POOL_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long reads skip a replica that was found unavailable.
+const READ_POOL_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// SQLSTATE Postgres reports when `statement_timeout` cancels a statement.
+const QUERY_CANCELED: &str = "57014";
+
+tokio::task_local! {
+    static READ_FROM_PRIMARY: ();
+}
+
+/// The management database pools: the primary, used for every write, and an optional read
+/// replica, used for SELECTs. Reads fall back to the primary while the replica can't be
+/// reached, and may lag writes by the replica's replication delay.
+#[derive(Debug, Clone)]
+pub struct DbPools {
+    primary: PgPool,
+    replica: Option<PgPool>,
+    replica_down_until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl DbPools {
+    pub fn new(primary: PgPool) -> Self {
+        Self {
+            primary,
+            replica: None,
+            replica_down_until: Arc::default(),
+        }
+    }
+
+    /// Sends reads to `replica` instead of the primary.
+    pub fn with_read_pool(mut self, replica: PgPool) -> Self {
+        self.replica = Some(replica);
+        self
+    }
+
+    /// Connects to the primary at `database_url` and, if given, lazily to the replica at
+    /// `read_url`, so a replica that is down at startup only sends reads to the primary.
+    /// Statements on either pool are cancelled after `statement_timeout`, if set.
+    pub async fn connect(
+        database_url: &str,
+        read_url: Option<&str>,
+        statement_timeout: Option<Duration>,
+    ) -> Result<Self, sqlx::Error> {
+        let primary = PgPoolOptions::new()
+            .connect_with(connect_options(database_url, statement_timeout)?)
+            .await?;
+        let mut pools = Self::new(primary);
+        if let Some(read_url) = read_url {
+            let replica = PgPoolOptions::new()
+                .acquire_timeout(READ_POOL_ACQUIRE_TIMEOUT)
+                .connect_lazy_with(connect_options(read_url, statement_timeout)?);
+            pools = pools.with_read_pool(replica);
+        }
+        Ok(pools)
+    }
+
+    /// The pool for writes, and for reads that must see them.
+    pub fn primary(&self) -> &PgPool {
+        &self.primary
+    }
+
+    /// Runs the read `query` on the replica, or on the primary when there is no replica or
+    /// it is unavailable. `query` may be called twice, so it must not have side effects.
+    pub async fn read<T, F, Fut>(&self, query: F) -> Result<T, sqlx::Error>
+    where
+        F: Fn(PgPool) -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        if let Some(replica) = self.available_replica() {
+            match query(replica.clone()).await {
+                Err(e) if is_unavailable(&e) => {
+                    warn!("Read replica unavailable, reading from the primary: {e}");
+                    *self.replica_down_until.lock().unwrap() =
+                        Some(Instant::now() + READ_POOL_RETRY_AFTER);
+                }
+                result => return result,
+            }
+        }
+        query(self.primary.clone()).await
+    }
+
+    fn available_replica(&self) -> Option<&PgPool> {
+        if READ_FROM_PRIMARY.try_with(|_| ()).is_ok() {
+            return None;
+        }
+        let replica = self.replica.as_ref()?;
+        let mut down_until = self.replica_down_until.lock().unwrap();
+        match *down_until {
+            Some(until) if Instant::now() < until => None,
+            _ => {
+                *down_until = None;
+                Some(replica)
+            }
+        }
+    }
+}
+
+impl From<PgPool> for DbPools {
+    fn from(primary: PgPool) -> Self {
+        Self::new(primary)
+    }
+}
+
+/// Runs `reads` with every [`DbPools::read`] going to the primary, for reads that must see
+/// writes made just before them.
+pub async fn read_from_primary<F: Future>(reads: F) -> F::Output {
+    READ_FROM_PRIMARY.scope((), reads).await
+}
+
+fn connect_options(
+    url: &str,
+    statement_timeout: Option<Duration>,
+) -> Result<PgConnectOptions, sqlx::Error> {
+    let options = PgConnectOptions::from_str(url)?;
+    Ok(match statement_timeout {
+        Some(timeout) => {
+            options.options([("statement_timeout", format!("{}ms", timeout.as_millis()))])
+        }
+        None => options,
+    })
+}
+
+/// Whether `error` means the database couldn't be reached, rather than that the query failed.
+pub fn is_unavailable(error: &sqlx::Error) -> bool {
+    matches!(
+        error,
+        sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed
+    )
+}
+
+/// Whether `error` is a timeout: no connection was free in time, or the statement ran past
+/// the statement timeout.
+pub fn is_timeout(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(e) => e.code().as_deref() == Some(QUERY_CANCELED),
+        _ => false,
+    }
+}
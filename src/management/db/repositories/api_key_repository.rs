@@ -0,0 +1,147 @@
+use sqlx::{Result, types::Uuid};
+
+use crate::management::db::{DbPools, models::ApiKey};
+
+const API_KEY_COLUMNS: &str =
+    "id, name, key_hash, key_prefix, role, created_at, updated_at, revoked_at";
+
+#[derive(Debug, Clone)]
+pub struct ApiKeyRepository {
+    pools: DbPools,
+}
+
+impl ApiKeyRepository {
+    pub fn new(pools: impl Into<DbPools>) -> Self {
+        Self {
+            pools: pools.into(),
+        }
+    }
+
+    pub async fn create(
+        &self,
+        name: &str,
+        role: &str,
+        key_hash: &str,
+        key_prefix: &str,
+    ) -> Result<ApiKey> {
+        sqlx::query_as::<_, ApiKey>(&format!(
+            r#"
+            INSERT INTO hub_llmgateway_api_keys (id, name, role, key_hash, key_prefix)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING {API_KEY_COLUMNS}
+            "#
+        ))
+        .bind(Uuid::new_v4())
+        .bind(name)
+        .bind(role)
+        .bind(key_hash)
+        .bind(key_prefix)
+        .fetch_one(self.pools.primary())
+        .await
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiKey>> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_as::<_, ApiKey>(&format!(
+                    r#"
+                    SELECT {API_KEY_COLUMNS}
+                    FROM hub_llmgateway_api_keys
+                    WHERE revoked_at IS NULL
+                    ORDER BY created_at
+                    "#
+                ))
+                .fetch_all(&pool)
+                .await
+            })
+            .await
+    }
+
+    pub async fn find_active_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_as::<_, ApiKey>(&format!(
+                    r#"
+                    SELECT {API_KEY_COLUMNS}
+                    FROM hub_llmgateway_api_keys
+                    WHERE key_hash = $1 AND revoked_at IS NULL
+                    "#
+                ))
+                .bind(key_hash)
+                .fetch_optional(&pool)
+                .await
+            })
+            .await
+    }
+
+    /// Counts unrevoked keys, optionally only those with the given role.
+    pub async fn count_active(&self, role: Option<&str>) -> Result<i64> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_scalar(
+                    r#"
+                    SELECT COUNT(*)
+                    FROM hub_llmgateway_api_keys
+                    WHERE revoked_at IS NULL AND ($1::VARCHAR IS NULL OR role = $1)
+                    "#,
+                )
+                .bind(role)
+                .fetch_one(&pool)
+                .await
+            })
+            .await
+    }
+
+    /// Replaces the stored digest of an unrevoked key.
+    pub async fn rotate(
+        &self,
+        id: Uuid,
+        key_hash: &str,
+        key_prefix: &str,
+    ) -> Result<Option<ApiKey>> {
+        sqlx::query_as::<_, ApiKey>(&format!(
+            r#"
+            UPDATE hub_llmgateway_api_keys
+            SET key_hash = $2, key_prefix = $3
+            WHERE id = $1 AND revoked_at IS NULL
+            RETURNING {API_KEY_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(key_hash)
+        .bind(key_prefix)
+        .fetch_optional(self.pools.primary())
+        .await
+    }
+
+    pub async fn revoke(&self, id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE hub_llmgateway_api_keys
+            SET revoked_at = now()
+            WHERE id = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .execute(self.pools.primary())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn find_active_by_id(&self, id: Uuid) -> Result<Option<ApiKey>> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_as::<_, ApiKey>(&format!(
+                    r#"
+                    SELECT {API_KEY_COLUMNS}
+                    FROM hub_llmgateway_api_keys
+                    WHERE id = $1 AND revoked_at IS NULL
+                    "#
+                ))
+                .bind(id)
+                .fetch_optional(&pool)
+                .await
+            })
+            .await
+    }
+}
@@ -0,0 +1,436 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::{FromRow, PgPool, Postgres, Result, Transaction, types::Uuid};
+use std::collections::HashMap;
+
+use crate::management::db::{DbPools, models::ConfigSnapshot};
+
+const CONFIG_SNAPSHOT_COLUMNS: &str = "id, config_hash, config_state, created_at";
+
+/// The live providers, model definitions and pipelines, as stored in the database.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfigState {
+    pub providers: Vec<SnapshotProvider>,
+    pub model_definitions: Vec<SnapshotModelDefinition>,
+    pub pipelines: Vec<SnapshotPipeline>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SnapshotProvider {
+    pub id: Uuid,
+    pub name: String,
+    pub provider_type: String,
+    pub config_details: JsonValue,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SnapshotModelDefinition {
+    pub id: Uuid,
+    pub key: String,
+    pub model_type: String,
+    pub provider_id: Uuid,
+    pub config_details: Option<JsonValue>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SnapshotPipeline {
+    pub id: Uuid,
+    pub name: String,
+    pub pipeline_type: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub environment: Option<String>,
+    #[sqlx(skip)]
+    pub plugins: Vec<SnapshotPipelinePlugin>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SnapshotPipelinePlugin {
+    pub pipeline_id: Uuid,
+    pub plugin_type: String,
+    pub config_data: JsonValue,
+    pub enabled: bool,
+    pub order_in_pipeline: i32,
+}
+
+/// Newest change and row count across the config tables. Any insert, update or delete
+/// moves at least one of them, so the config only needs re-reading when it differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromRow)]
+pub struct ConfigWatermark {
+    pub updated_at: Option<DateTime<Utc>>,
+    pub row_count: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigSnapshotRepository {
+    pools: DbPools,
+}
+
+impl ConfigSnapshotRepository {
+    pub fn new(pools: impl Into<DbPools>) -> Self {
+        Self {
+            pools: pools.into(),
+        }
+    }
+
+    /// Reads the live configuration rows in one consistent view.
+    pub async fn capture_state(&self) -> Result<ConfigState> {
+        self.pools
+            .read(|pool| async move { Self::select_state(&pool).await })
+            .await
+    }
+
+    /// Reads the watermark of the config tables, far cheaper than [`Self::capture_state`].
+    pub async fn watermark(&self) -> Result<ConfigWatermark> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_as::<_, ConfigWatermark>(
+                    r#"
+                    SELECT max(updated_at) AS updated_at, count(*) AS row_count FROM (
+                        SELECT updated_at FROM hub_llmgateway_providers
+                        UNION ALL SELECT updated_at FROM hub_llmgateway_model_definitions
+                        UNION ALL SELECT updated_at FROM hub_llmgateway_pipelines
+                        UNION ALL SELECT updated_at FROM hub_llmgateway_pipeline_plugin_configs
+                    ) AS config
+                    "#,
+                )
+                .fetch_one(&pool)
+                .await
+            })
+            .await
+    }
+
+    async fn select_state(pool: &PgPool) -> Result<ConfigState> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+
+        let providers = sqlx::query_as::<_, SnapshotProvider>(
+            r#"
+            SELECT id, name, provider_type, config_details, enabled
+            FROM hub_llmgateway_providers
+            WHERE deleted_at IS NULL
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // Models of soft-deleted providers are hidden with them, so they aren't part of the state.
+        let model_definitions = sqlx::query_as::<_, SnapshotModelDefinition>(
+            r#"
+            SELECT m.id, m.key, m.model_type, m.provider_id, m.config_details, m.enabled
+            FROM hub_llmgateway_model_definitions m
+            JOIN hub_llmgateway_providers p ON p.id = m.provider_id
+            WHERE p.deleted_at IS NULL
+            ORDER BY m.id
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut pipelines = sqlx::query_as::<_, SnapshotPipeline>(
+            r#"
+            SELECT id, name, pipeline_type, description, enabled, environment
+            FROM hub_llmgateway_pipelines
+            WHERE deleted_at IS NULL
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let plugins = sqlx::query_as::<_, SnapshotPipelinePlugin>(
+            r#"
+            SELECT c.pipeline_id, c.plugin_type, c.config_data, c.enabled, c.order_in_pipeline
+            FROM hub_llmgateway_pipeline_plugin_configs c
+            JOIN hub_llmgateway_pipelines p ON p.id = c.pipeline_id
+            WHERE p.deleted_at IS NULL
+            ORDER BY c.pipeline_id, c.order_in_pipeline, c.plugin_type
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let mut plugins_map: HashMap<Uuid, Vec<SnapshotPipelinePlugin>> = HashMap::new();
+        for plugin in plugins {
+            plugins_map
+                .entry(plugin.pipeline_id)
+                .or_default()
+                .push(plugin);
+        }
+        for pipeline in &mut pipelines {
+            pipeline.plugins = plugins_map.remove(&pipeline.id).unwrap_or_default();
+        }
+
+        Ok(ConfigState {
+            providers,
+            model_definitions,
+            pipelines,
+        })
+    }
+
+    /// Stores a snapshot unless the newest one already has this hash, then keeps only the
+    /// `retention` newest snapshots. Returns `None` if nothing was stored.
+    pub async fn create(
+        &self,
+        config_hash: &str,
+        config_state: JsonValue,
+        retention: i64,
+    ) -> Result<Option<ConfigSnapshot>> {
+        let mut tx = self.pools.primary().begin().await?;
+
+        let snapshot = sqlx::query_as::<_, ConfigSnapshot>(&format!(
+            r#"
+            INSERT INTO hub_llmgateway_config_snapshots (id, config_hash, config_state)
+            SELECT $1, $2, $3
+            WHERE NOT EXISTS (
+                SELECT 1 FROM (
+                    SELECT config_hash FROM hub_llmgateway_config_snapshots
+                    ORDER BY created_at DESC
+                    LIMIT 1
+                ) latest
+                WHERE latest.config_hash = $2
+            )
+            RETURNING {CONFIG_SNAPSHOT_COLUMNS}
+            "#
+        ))
+        .bind(Uuid::new_v4())
+        .bind(config_hash)
+        .bind(config_state)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if snapshot.is_some() {
+            sqlx::query(
+                r#"
+                DELETE FROM hub_llmgateway_config_snapshots
+                WHERE id NOT IN (
+                    SELECT id FROM hub_llmgateway_config_snapshots
+                    ORDER BY created_at DESC
+                    LIMIT $1
+                )
+                "#,
+            )
+            .bind(retention)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(snapshot)
+    }
+
+    /// Lists snapshots, newest first.
+    pub async fn list(&self) -> Result<Vec<ConfigSnapshot>> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_as::<_, ConfigSnapshot>(&format!(
+                    r#"
+                    SELECT {CONFIG_SNAPSHOT_COLUMNS}
+                    FROM hub_llmgateway_config_snapshots
+                    ORDER BY created_at DESC
+                    "#
+                ))
+                .fetch_all(&pool)
+                .await
+            })
+            .await
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<ConfigSnapshot>> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_as::<_, ConfigSnapshot>(&format!(
+                    r#"
+                    SELECT {CONFIG_SNAPSHOT_COLUMNS}
+                    FROM hub_llmgateway_config_snapshots
+                    WHERE id = $1
+                    "#
+                ))
+                .bind(id)
+                .fetch_optional(&pool)
+                .await
+            })
+            .await
+    }
+
+    /// Makes the live providers, model definitions and pipelines match `state`, in one
+    /// transaction. Providers and pipelines missing from `state` are soft-deleted; restored
+    /// rows keep their IDs and get a new version.
+    pub async fn restore_state(&self, state: &ConfigState) -> Result<()> {
+        let mut tx = self.pools.primary().begin().await?;
+
+        let provider_ids: Vec<Uuid> = state.providers.iter().map(|p| p.id).collect();
+        let model_ids: Vec<Uuid> = state.model_definitions.iter().map(|m| m.id).collect();
+        let model_keys: Vec<String> = state
+            .model_definitions
+            .iter()
+            .map(|m| m.key.clone())
+            .collect();
+        let pipeline_ids: Vec<Uuid> = state.pipelines.iter().map(|p| p.id).collect();
+
+        // Model keys are unique across all rows, including models hidden with their provider.
+        sqlx::query(
+            r#"
+            DELETE FROM hub_llmgateway_model_definitions
+            WHERE id <> ALL($1)
+                AND (key = ANY($2) OR provider_id IN (
+                    SELECT id FROM hub_llmgateway_providers WHERE deleted_at IS NULL
+                ))
+            "#,
+        )
+        .bind(&model_ids)
+        .bind(&model_keys)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE hub_llmgateway_providers
+            SET deleted_at = now()
+            WHERE id <> ALL($1) AND deleted_at IS NULL
+            "#,
+        )
+        .bind(&provider_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE hub_llmgateway_pipelines
+            SET deleted_at = now()
+            WHERE id <> ALL($1) AND deleted_at IS NULL
+            "#,
+        )
+        .bind(&pipeline_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        for provider in &state.providers {
+            Self::upsert_provider(&mut tx, provider).await?;
+        }
+        for model in &state.model_definitions {
+            Self::upsert_model_definition(&mut tx, model).await?;
+        }
+        for pipeline in &state.pipelines {
+            Self::upsert_pipeline(&mut tx, pipeline).await?;
+        }
+
+        tx.commit().await
+    }
+
+    async fn upsert_provider(
+        tx: &mut Transaction<'_, Postgres>,
+        provider: &SnapshotProvider,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO hub_llmgateway_providers (id, name, provider_type, config_details, enabled)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (id) DO UPDATE SET
+                name = EXCLUDED.name,
+                provider_type = EXCLUDED.provider_type,
+                config_details = EXCLUDED.config_details,
+                enabled = EXCLUDED.enabled,
+                deleted_at = NULL,
+                version = hub_llmgateway_providers.version + 1
+            "#,
+        )
+        .bind(provider.id)
+        .bind(&provider.name)
+        .bind(&provider.provider_type)
+        .bind(&provider.config_details)
+        .bind(provider.enabled)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn upsert_model_definition(
+        tx: &mut Transaction<'_, Postgres>,
+        model: &SnapshotModelDefinition,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO hub_llmgateway_model_definitions
+                (id, key, model_type, provider_id, config_details, enabled)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (id) DO UPDATE SET
+                key = EXCLUDED.key,
+                model_type = EXCLUDED.model_type,
+                provider_id = EXCLUDED.provider_id,
+                config_details = EXCLUDED.config_details,
+                enabled = EXCLUDED.enabled,
+                version = hub_llmgateway_model_definitions.version + 1
+            "#,
+        )
+        .bind(model.id)
+        .bind(&model.key)
+        .bind(&model.model_type)
+        .bind(model.provider_id)
+        .bind(&model.config_details)
+        .bind(model.enabled)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn upsert_pipeline(
+        tx: &mut Transaction<'_, Postgres>,
+        pipeline: &SnapshotPipeline,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO hub_llmgateway_pipelines
+                (id, name, pipeline_type, description, enabled, environment)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (id) DO UPDATE SET
+                name = EXCLUDED.name,
+                pipeline_type = EXCLUDED.pipeline_type,
+                description = EXCLUDED.description,
+                enabled = EXCLUDED.enabled,
+                environment = EXCLUDED.environment,
+                deleted_at = NULL,
+                version = hub_llmgateway_pipelines.version + 1
+            "#,
+        )
+        .bind(pipeline.id)
+        .bind(&pipeline.name)
+        .bind(&pipeline.pipeline_type)
+        .bind(&pipeline.description)
+        .bind(pipeline.enabled)
+        .bind(&pipeline.environment)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query("DELETE FROM hub_llmgateway_pipeline_plugin_configs WHERE pipeline_id = $1")
+            .bind(pipeline.id)
+            .execute(&mut **tx)
+            .await?;
+
+        for plugin in &pipeline.plugins {
+            sqlx::query(
+                r#"
+                INSERT INTO hub_llmgateway_pipeline_plugin_configs
+                    (pipeline_id, plugin_type, config_data, enabled, order_in_pipeline)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(pipeline.id)
+            .bind(&plugin.plugin_type)
+            .bind(&plugin.config_data)
+            .bind(plugin.enabled)
+            .bind(plugin.order_in_pipeline)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 // Placeholder for repository modules
+pub mod api_key_repository;
+pub mod config_snapshot_repository;
 pub mod model_definition_repository;
 pub mod pipeline_repository;
 pub mod provider_repository;
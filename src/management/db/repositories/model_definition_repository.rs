@@ -1,17 +1,19 @@
 use crate::management::{
-    db::models::ModelDefinition,
+    db::{DbPools, models::ModelDefinition},
     dto::{CreateModelDefinitionRequest, UpdateModelDefinitionRequest},
 };
 use sqlx::{PgPool, Result, query, query_as, types::Uuid};
 
 #[derive(Debug, Clone)]
 pub struct ModelDefinitionRepository {
-    pool: PgPool,
+    pools: DbPools,
 }
 
 impl ModelDefinitionRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pools: impl Into<DbPools>) -> Self {
+        Self {
+            pools: pools.into(),
+        }
     }
 
     pub async fn create(&self, data: &CreateModelDefinitionRequest) -> Result<ModelDefinition> {
@@ -20,7 +22,7 @@ impl ModelDefinitionRepository {
             r#"
             INSERT INTO hub_llmgateway_model_definitions (key, model_type, provider_id, config_details, enabled)
             VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, key, model_type, provider_id, config_details, enabled, created_at, updated_at
+            RETURNING id, key, model_type, provider_id, config_details, enabled, created_at, updated_at, version
             "#,
             data.key,
             data.model_type,
@@ -28,40 +30,73 @@ impl ModelDefinitionRepository {
             data.config_details.as_ref().map(|val| val.clone()), // Option<Value> -> Option<Value>
             default_enabled
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.pools.primary())
         .await?;
         Ok(model_def)
     }
 
     pub async fn find_by_id(&self, id: Uuid) -> Result<Option<ModelDefinition>> {
+        self.pools
+            .read(|pool| async move { Self::select_by_id(&pool, id).await })
+            .await
+    }
+
+    async fn select_by_id(pool: &PgPool, id: Uuid) -> Result<Option<ModelDefinition>> {
         query_as!(ModelDefinition,
-            "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at FROM hub_llmgateway_model_definitions WHERE id = $1",
+            "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at, version FROM hub_llmgateway_model_definitions WHERE id = $1",
             id
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(pool)
         .await
     }
 
     pub async fn find_by_key(&self, key: &str) -> Result<Option<ModelDefinition>> {
+        self.pools
+            .read(|pool| async move { Self::select_by_key(&pool, key).await })
+            .await
+    }
+
+    async fn select_by_key(pool: &PgPool, key: &str) -> Result<Option<ModelDefinition>> {
         query_as!(ModelDefinition,
-            "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at FROM hub_llmgateway_model_definitions WHERE key = $1",
+            "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at, version FROM hub_llmgateway_model_definitions WHERE key = $1",
             key
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(pool)
         .await
     }
 
     pub async fn list(&self) -> Result<Vec<ModelDefinition>> {
-        query_as!(ModelDefinition, "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at FROM hub_llmgateway_model_definitions ORDER BY key ASC")
-            .fetch_all(&self.pool)
+        self.pools
+            .read(|pool| async move { Self::select_all(&pool).await })
             .await
     }
 
+    async fn select_all(pool: &PgPool) -> Result<Vec<ModelDefinition>> {
+        query_as!(ModelDefinition, "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at, version FROM hub_llmgateway_model_definitions WHERE provider_id IN (SELECT id FROM hub_llmgateway_providers WHERE deleted_at IS NULL) ORDER BY key ASC")
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn list_by_provider(&self, provider_id: Uuid) -> Result<Vec<ModelDefinition>> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_as::<_, ModelDefinition>(
+                    "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at, version FROM hub_llmgateway_model_definitions WHERE provider_id = $1 ORDER BY key ASC",
+                )
+                .bind(provider_id)
+                .fetch_all(&pool)
+                .await
+            })
+            .await
+    }
+
+    /// Returns `None` if the model definition's version is no longer `expected_version`.
     pub async fn update(
         &self,
         id: Uuid,
         data: &UpdateModelDefinitionRequest,
-    ) -> Result<ModelDefinition> {
+        expected_version: i32,
+    ) -> Result<Option<ModelDefinition>> {
         // Fetch current to handle Option fields correctly
         let current_model = self
             .find_by_id(id)
@@ -86,18 +121,19 @@ impl ModelDefinitionRepository {
         let model_def = query_as!(ModelDefinition,
             r#"
             UPDATE hub_llmgateway_model_definitions
-            SET key = $1, model_type = $2, provider_id = $3, config_details = $4, enabled = $5, updated_at = NOW()
-            WHERE id = $6
-            RETURNING id, key, model_type, provider_id, config_details, enabled, created_at, updated_at
+            SET key = $1, model_type = $2, provider_id = $3, config_details = $4, enabled = $5, version = version + 1, updated_at = NOW()
+            WHERE id = $6 AND version = $7
+            RETURNING id, key, model_type, provider_id, config_details, enabled, created_at, updated_at, version
             "#,
             key,
             model_type,
             provider_id,
             config_details_to_update,
             enabled,
-            id
+            id,
+            expected_version
         )
-        .fetch_one(&self.pool)
+        .fetch_optional(self.pools.primary())
         .await?;
         Ok(model_def)
     }
@@ -107,7 +143,7 @@ impl ModelDefinitionRepository {
             "DELETE FROM hub_llmgateway_model_definitions WHERE id = $1",
             id
         )
-        .execute(&self.pool)
+        .execute(self.pools.primary())
         .await?;
         Ok(result.rows_affected())
     }
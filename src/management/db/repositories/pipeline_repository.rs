@@ -2,7 +2,10 @@ use sqlx::{PgPool, Result, Row, query_as, types::Uuid};
 use std::collections::HashMap;
 
 use crate::management::{
-    db::models::{Pipeline, PipelinePluginConfig, PipelineWithPlugins},
+    db::{
+        DbPools,
+        models::{Pipeline, PipelinePluginConfig, PipelineWithPlugins},
+    },
     dto::{CreatePipelineRequestDto, UpdatePipelineRequestDto},
     errors::ApiError,
 };
@@ -12,31 +15,34 @@ use crate::management::{
 
 #[derive(Debug)]
 pub struct PipelineRepository {
-    pool: PgPool,
+    pools: DbPools,
 }
 
 impl PipelineRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pools: impl Into<DbPools>) -> Self {
+        Self {
+            pools: pools.into(),
+        }
     }
 
     pub async fn create_pipeline_with_plugins(
         &self,
         pipeline_data: &CreatePipelineRequestDto,
     ) -> Result<PipelineWithPlugins, ApiError> {
-        let mut tx = self.pool.begin().await.map_err(ApiError::from)?;
+        let mut tx = self.pools.primary().begin().await.map_err(ApiError::from)?;
 
         let pipeline = query_as!(
             Pipeline,
             r#"
-            INSERT INTO hub_llmgateway_pipelines (name, pipeline_type, description, enabled)
-            VALUES ($1, $2, $3, $4)
-            RETURNING id, name, pipeline_type, description, enabled, created_at, updated_at
+            INSERT INTO hub_llmgateway_pipelines (name, pipeline_type, description, enabled, environment)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version
             "#,
             pipeline_data.name,
             pipeline_data.pipeline_type,
             pipeline_data.description,
-            pipeline_data.enabled
+            pipeline_data.enabled,
+            pipeline_data.environment
         )
         .fetch_one(&mut *tx) // Use &mut *tx for transaction
         .await
@@ -73,8 +79,10 @@ impl PipelineRepository {
             pipeline_type: pipeline.pipeline_type,
             description: pipeline.description,
             enabled: pipeline.enabled,
+            environment: pipeline.environment,
             created_at: pipeline.created_at,
             updated_at: pipeline.updated_at,
+            version: pipeline.version,
             plugins: created_plugins,
         })
     }
@@ -83,17 +91,23 @@ impl PipelineRepository {
         &self,
         id: Uuid,
     ) -> Result<Option<PipelineWithPlugins>, ApiError> {
+        self.pools
+            .read(|pool| async move { Self::select_pipeline_by_id(&pool, id).await })
+            .await
+            .map_err(ApiError::from)
+    }
+
+    async fn select_pipeline_by_id(pool: &PgPool, id: Uuid) -> Result<Option<PipelineWithPlugins>> {
         let pipeline_row = sqlx::query!(
             r#"
-            SELECT id, name, pipeline_type, description, enabled, created_at, updated_at
+            SELECT id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version
             FROM hub_llmgateway_pipelines
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
             "#,
             id
         )
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(ApiError::from)?;
+        .fetch_optional(pool)
+        .await?;
 
         if let Some(row) = pipeline_row {
             let plugins = sqlx::query_as!(
@@ -106,9 +120,8 @@ impl PipelineRepository {
                 "#,
                 row.id
             )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(ApiError::from)?;
+            .fetch_all(pool)
+            .await?;
 
             Ok(Some(PipelineWithPlugins {
                 id: row.id,
@@ -116,8 +129,10 @@ impl PipelineRepository {
                 pipeline_type: row.pipeline_type,
                 description: row.description,
                 enabled: row.enabled,
+                environment: row.environment,
                 created_at: row.created_at,
                 updated_at: row.updated_at,
+                version: row.version,
                 plugins,
             }))
         } else {
@@ -125,21 +140,36 @@ impl PipelineRepository {
         }
     }
 
+    /// Finds the live pipeline with this name in the given environment (`None` = untagged).
     pub async fn find_pipeline_by_name(
         &self,
         name: &str,
+        environment: Option<&str>,
     ) -> Result<Option<PipelineWithPlugins>, ApiError> {
+        self.pools
+            .read(
+                |pool| async move { Self::select_pipeline_by_name(&pool, name, environment).await },
+            )
+            .await
+            .map_err(ApiError::from)
+    }
+
+    async fn select_pipeline_by_name(
+        pool: &PgPool,
+        name: &str,
+        environment: Option<&str>,
+    ) -> Result<Option<PipelineWithPlugins>> {
         let pipeline_row = sqlx::query!(
             r#"
-            SELECT id, name, pipeline_type, description, enabled, created_at, updated_at
+            SELECT id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version
             FROM hub_llmgateway_pipelines
-            WHERE name = $1
+            WHERE name = $1 AND environment IS NOT DISTINCT FROM $2 AND deleted_at IS NULL
             "#,
-            name
+            name,
+            environment
         )
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(ApiError::from)?;
+        .fetch_optional(pool)
+        .await?;
 
         if let Some(row) = pipeline_row {
             let plugins = sqlx::query_as!(
@@ -152,9 +182,8 @@ impl PipelineRepository {
                 "#,
                 row.id
             )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(ApiError::from)?;
+            .fetch_all(pool)
+            .await?;
 
             Ok(Some(PipelineWithPlugins {
                 id: row.id,
@@ -162,8 +191,10 @@ impl PipelineRepository {
                 pipeline_type: row.pipeline_type,
                 description: row.description,
                 enabled: row.enabled,
+                environment: row.environment,
                 created_at: row.created_at,
                 updated_at: row.updated_at,
+                version: row.version,
                 plugins,
             }))
         } else {
@@ -172,17 +203,24 @@ impl PipelineRepository {
     }
 
     pub async fn list_pipelines(&self) -> Result<Vec<PipelineWithPlugins>, ApiError> {
+        self.pools
+            .read(|pool| async move { Self::select_pipelines(&pool).await })
+            .await
+            .map_err(ApiError::from)
+    }
+
+    async fn select_pipelines(pool: &PgPool) -> Result<Vec<PipelineWithPlugins>> {
         let pipelines = query_as!(
             Pipeline,
             r#"
-            SELECT id, name, pipeline_type, description, enabled, created_at, updated_at
+            SELECT id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version
             FROM hub_llmgateway_pipelines
+            WHERE deleted_at IS NULL
             ORDER BY created_at DESC
             "#
         )
-        .fetch_all(&self.pool)
-        .await
-        .map_err(ApiError::from)?;
+        .fetch_all(pool)
+        .await?;
 
         if pipelines.is_empty() {
             return Ok(Vec::new());
@@ -200,9 +238,8 @@ impl PipelineRepository {
             "#,
             &pipeline_ids // Pass as slice
         )
-        .fetch_all(&self.pool)
-        .await
-        .map_err(ApiError::from)?;
+        .fetch_all(pool)
+        .await?;
 
         let mut plugins_map: HashMap<Uuid, Vec<PipelinePluginConfig>> = HashMap::new();
         for plugin in all_plugins {
@@ -220,8 +257,10 @@ impl PipelineRepository {
                 pipeline_type: p.pipeline_type.clone(),
                 description: p.description.clone(),
                 enabled: p.enabled,
+                environment: p.environment.clone(),
                 created_at: p.created_at,
                 updated_at: p.updated_at,
+                version: p.version,
                 plugins: plugins_map.remove(&p.id).unwrap_or_default(),
             })
             .collect();
@@ -233,19 +272,27 @@ impl PipelineRepository {
         &self,
         id: Uuid,
         data: &UpdatePipelineRequestDto,
+        expected_version: i32,
     ) -> Result<PipelineWithPlugins, ApiError> {
-        let mut tx = self.pool.begin().await.map_err(ApiError::from)?;
+        let mut tx = self.pools.primary().begin().await.map_err(ApiError::from)?;
 
         // Fetch current pipeline to check existence and for returning non-updated fields
         let current_pipeline = sqlx::query_as!(
             Pipeline,
-            "SELECT * FROM hub_llmgateway_pipelines WHERE id = $1",
+            "SELECT id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version FROM hub_llmgateway_pipelines WHERE id = $1 AND deleted_at IS NULL",
             id
         )
         .fetch_optional(&mut *tx)
         .await
         .map_err(ApiError::from)?
         .ok_or(ApiError::NotFound("Pipeline not found".to_string()))?;
+        if current_pipeline.version != expected_version {
+            return Err(ApiError::version_conflict(
+                "Pipeline",
+                id,
+                current_pipeline.version,
+            ));
+        }
 
         let updated_pipeline = query_as!(
             Pipeline,
@@ -256,9 +303,10 @@ impl PipelineRepository {
                 pipeline_type = COALESCE($2, pipeline_type),
                 description = COALESCE($3, description),
                 enabled = COALESCE($4, enabled),
+                version = version + 1,
                 updated_at = NOW()
-            WHERE id = $5
-            RETURNING id, name, pipeline_type, description, enabled, created_at, updated_at
+            WHERE id = $5 AND version = $6
+            RETURNING id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version
             "#,
             data.name.as_ref().unwrap_or(&current_pipeline.name),
             data.pipeline_type
@@ -268,11 +316,22 @@ impl PipelineRepository {
                 .as_ref()
                 .or(current_pipeline.description.as_ref()), // Handles Option<String>
             data.enabled.unwrap_or(current_pipeline.enabled),
-            id
+            id,
+            expected_version
         )
-        .fetch_one(&mut *tx)
+        .fetch_optional(&mut *tx)
         .await
         .map_err(ApiError::from)?;
+        // A concurrent update committed between the read above and this write.
+        let Some(updated_pipeline) = updated_pipeline else {
+            let current_version: i32 =
+                sqlx::query_scalar("SELECT version FROM hub_llmgateway_pipelines WHERE id = $1")
+                    .bind(id)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(ApiError::from)?;
+            return Err(ApiError::version_conflict("Pipeline", id, current_version));
+        };
 
         let mut updated_plugins_list: Vec<PipelinePluginConfig> = Vec::new();
 
@@ -332,17 +391,237 @@ impl PipelineRepository {
             pipeline_type: updated_pipeline.pipeline_type,
             description: updated_pipeline.description,
             enabled: updated_pipeline.enabled,
+            environment: updated_pipeline.environment,
             created_at: updated_pipeline.created_at, // This should be original creation time
             updated_at: updated_pipeline.updated_at,
+            version: updated_pipeline.version,
             plugins: updated_plugins_list,
         })
     }
 
+    /// Updates one plugin of a pipeline and bumps the pipeline's version.
+    pub async fn update_pipeline_plugin(
+        &self,
+        pipeline_id: Uuid,
+        plugin_id: Uuid,
+        config_data: &serde_json::Value,
+        enabled: bool,
+        expected_version: i32,
+    ) -> Result<PipelineWithPlugins, ApiError> {
+        let mut tx = self.pools.primary().begin().await.map_err(ApiError::from)?;
+
+        let updated_pipeline = sqlx::query_as::<_, Pipeline>(
+            r#"
+            UPDATE hub_llmgateway_pipelines
+            SET version = version + 1, updated_at = NOW()
+            WHERE id = $1 AND version = $2 AND deleted_at IS NULL
+            RETURNING id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version
+            "#,
+        )
+        .bind(pipeline_id)
+        .bind(expected_version)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ApiError::from)?;
+        let Some(updated_pipeline) = updated_pipeline else {
+            let current_version: Option<i32> = sqlx::query_scalar(
+                "SELECT version FROM hub_llmgateway_pipelines WHERE id = $1 AND deleted_at IS NULL",
+            )
+            .bind(pipeline_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(ApiError::from)?;
+            return Err(match current_version {
+                Some(current) => ApiError::version_conflict("Pipeline", pipeline_id, current),
+                None => ApiError::NotFound("Pipeline not found".to_string()),
+            });
+        };
+
+        let updated_plugin = sqlx::query(
+            r#"
+            UPDATE hub_llmgateway_pipeline_plugin_configs
+            SET config_data = $1, enabled = $2
+            WHERE id = $3 AND pipeline_id = $4
+            "#,
+        )
+        .bind(config_data)
+        .bind(enabled)
+        .bind(plugin_id)
+        .bind(pipeline_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(ApiError::from)?;
+        if updated_plugin.rows_affected() == 0 {
+            return Err(ApiError::NotFound("Pipeline plugin not found".to_string()));
+        }
+
+        let plugins = sqlx::query_as::<_, PipelinePluginConfig>(
+            r#"
+            SELECT id, pipeline_id, plugin_type, config_data, enabled, order_in_pipeline, created_at, updated_at
+            FROM hub_llmgateway_pipeline_plugin_configs
+            WHERE pipeline_id = $1
+            ORDER BY order_in_pipeline ASC
+            "#,
+        )
+        .bind(pipeline_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(ApiError::from)?;
+
+        tx.commit().await.map_err(ApiError::from)?;
+
+        Ok(PipelineWithPlugins {
+            id: updated_pipeline.id,
+            name: updated_pipeline.name,
+            pipeline_type: updated_pipeline.pipeline_type,
+            description: updated_pipeline.description,
+            enabled: updated_pipeline.enabled,
+            environment: updated_pipeline.environment,
+            created_at: updated_pipeline.created_at,
+            updated_at: updated_pipeline.updated_at,
+            version: updated_pipeline.version,
+            plugins,
+        })
+    }
+
+    /// Copies a pipeline's type, description, enabled flag and plugins onto the pipeline with
+    /// the same name in `target_environment`, creating it if absent.
+    pub async fn promote_pipeline(
+        &self,
+        id: Uuid,
+        target_environment: &str,
+    ) -> Result<PipelineWithPlugins, ApiError> {
+        let mut tx = self.pools.primary().begin().await.map_err(ApiError::from)?;
+
+        let source = sqlx::query_as::<_, Pipeline>(
+            r#"
+            SELECT id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version
+            FROM hub_llmgateway_pipelines
+            WHERE id = $1 AND deleted_at IS NULL
+            FOR SHARE
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ApiError::from)?
+        .ok_or(ApiError::NotFound("Pipeline not found".to_string()))?;
+        let source_plugins = sqlx::query_as::<_, PipelinePluginConfig>(
+            r#"
+            SELECT id, pipeline_id, plugin_type, config_data, enabled, order_in_pipeline, created_at, updated_at
+            FROM hub_llmgateway_pipeline_plugin_configs
+            WHERE pipeline_id = $1
+            ORDER BY order_in_pipeline ASC
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(ApiError::from)?;
+
+        let existing_target: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM hub_llmgateway_pipelines
+            WHERE name = $1 AND environment = $2 AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+        )
+        .bind(&source.name)
+        .bind(target_environment)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(ApiError::from)?;
+
+        let target = match existing_target {
+            Some(target_id) => {
+                let target = sqlx::query_as::<_, Pipeline>(
+                    r#"
+                    UPDATE hub_llmgateway_pipelines
+                    SET
+                        pipeline_type = $1,
+                        description = $2,
+                        enabled = $3,
+                        version = version + 1,
+                        updated_at = NOW()
+                    WHERE id = $4
+                    RETURNING id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version
+                    "#,
+                )
+                .bind(&source.pipeline_type)
+                .bind(&source.description)
+                .bind(source.enabled)
+                .bind(target_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(ApiError::from)?;
+                sqlx::query(
+                    "DELETE FROM hub_llmgateway_pipeline_plugin_configs WHERE pipeline_id = $1",
+                )
+                .bind(target_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(ApiError::from)?;
+                target
+            }
+            None => sqlx::query_as::<_, Pipeline>(
+                r#"
+                INSERT INTO hub_llmgateway_pipelines (name, pipeline_type, description, enabled, environment)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id, name, pipeline_type, description, enabled, environment, created_at, updated_at, version
+                "#,
+            )
+            .bind(&source.name)
+            .bind(&source.pipeline_type)
+            .bind(&source.description)
+            .bind(source.enabled)
+            .bind(target_environment)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(ApiError::from)?,
+        };
+
+        let mut plugins = Vec::with_capacity(source_plugins.len());
+        for plugin in source_plugins {
+            let copied_plugin = sqlx::query_as::<_, PipelinePluginConfig>(
+                r#"
+                INSERT INTO hub_llmgateway_pipeline_plugin_configs
+                    (pipeline_id, plugin_type, config_data, enabled, order_in_pipeline)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id, pipeline_id, plugin_type, config_data, enabled, order_in_pipeline, created_at, updated_at
+                "#,
+            )
+            .bind(target.id)
+            .bind(&plugin.plugin_type)
+            .bind(&plugin.config_data)
+            .bind(plugin.enabled)
+            .bind(plugin.order_in_pipeline)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(ApiError::from)?;
+            plugins.push(copied_plugin);
+        }
+
+        tx.commit().await.map_err(ApiError::from)?;
+
+        Ok(PipelineWithPlugins {
+            id: target.id,
+            name: target.name,
+            pipeline_type: target.pipeline_type,
+            description: target.description,
+            enabled: target.enabled,
+            environment: target.environment,
+            created_at: target.created_at,
+            updated_at: target.updated_at,
+            version: target.version,
+            plugins,
+        })
+    }
+
     pub async fn delete_pipeline(&self, id: Uuid) -> Result<u64, ApiError> {
         // The `ON DELETE CASCADE` constraint on `pipeline_plugin_configs.pipeline_id`
         // should handle deleting associated plugins automatically.
         let result = sqlx::query!("DELETE FROM hub_llmgateway_pipelines WHERE id = $1", id)
-            .execute(&self.pool)
+            .execute(self.pools.primary())
             .await
             .map_err(ApiError::from)?;
 
@@ -352,6 +631,47 @@ impl PipelineRepository {
         Ok(result.rows_affected())
     }
 
+    /// Marks a pipeline as deleted, hiding it from lists and the gateway config.
+    pub async fn soft_delete_pipeline(&self, id: Uuid) -> Result<u64, ApiError> {
+        let result = sqlx::query(
+            "UPDATE hub_llmgateway_pipelines SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .execute(self.pools.primary())
+        .await
+        .map_err(ApiError::from)?;
+        Ok(result.rows_affected())
+    }
+
+    /// Returns the name and environment of a soft-deleted pipeline.
+    pub async fn find_deleted_pipeline_name(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<(String, Option<String>)>, ApiError> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_as(
+                    "SELECT name, environment FROM hub_llmgateway_pipelines WHERE id = $1 AND deleted_at IS NOT NULL",
+                )
+                .bind(id)
+                .fetch_optional(&pool)
+                .await
+            })
+            .await
+            .map_err(ApiError::from)
+    }
+
+    pub async fn restore_pipeline(&self, id: Uuid) -> Result<u64, ApiError> {
+        let result = sqlx::query(
+            "UPDATE hub_llmgateway_pipelines SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .execute(self.pools.primary())
+        .await
+        .map_err(ApiError::from)?;
+        Ok(result.rows_affected())
+    }
+
     /// Checks if all provided model definition keys exist in the database.
     /// Returns Ok(true) if all exist, Ok(false) if any do not exist, or an ApiError.
     pub async fn check_model_definition_keys_exist(
@@ -364,15 +684,19 @@ impl PipelineRepository {
 
         // Construct a query like: SELECT key FROM hub_llmgateway_model_definitions WHERE key = ANY($1)
         // This is more efficient than querying one by one.
-        let existing_keys: Vec<String> =
-            sqlx::query("SELECT key FROM hub_llmgateway_model_definitions WHERE key = ANY($1)")
-                .bind(keys)
-                .fetch_all(&self.pool)
-                .await
-                .map_err(ApiError::from)?
-                .into_iter()
-                .map(|row| row.get::<&str, _>("key").to_string())
-                .collect();
+        let existing_keys: Vec<String> = self
+            .pools
+            .read(|pool| async move {
+                sqlx::query("SELECT key FROM hub_llmgateway_model_definitions WHERE key = ANY($1)")
+                    .bind(keys)
+                    .fetch_all(&pool)
+                    .await
+            })
+            .await
+            .map_err(ApiError::from)?
+            .into_iter()
+            .map(|row| row.get::<&str, _>("key").to_string())
+            .collect();
 
         // Check if the count of found keys matches the count of input keys
         Ok(existing_keys.len() == keys.len())
@@ -2,7 +2,7 @@ use serde_json::Value as JsonValue;
 use sqlx::{PgPool, Result, query, query_as, types::Uuid};
 
 use crate::management::{
-    db::models::Provider,
+    db::{DbPools, models::Provider},
     dto::{CreateProviderRequest, UpdateProviderRequest},
 };
 
@@ -28,12 +28,14 @@ pub struct UpdateProviderData {
 
 #[derive(Debug, Clone)] // Added Clone
 pub struct ProviderRepository {
-    pool: PgPool,
+    pools: DbPools,
 }
 
 impl ProviderRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pools: impl Into<DbPools>) -> Self {
+        Self {
+            pools: pools.into(),
+        }
     }
 
     pub async fn create(
@@ -49,7 +51,7 @@ impl ProviderRepository {
             r#"
             INSERT INTO hub_llmgateway_providers (id, name, provider_type, config_details, enabled)
             VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, name, provider_type, config_details, enabled, created_at, updated_at
+            RETURNING id, name, provider_type, config_details, enabled, created_at, updated_at, version
             "#,
             new_id,
             data.name,
@@ -57,56 +59,77 @@ impl ProviderRepository {
             config_json_value,
             enabled
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.pools.primary())
         .await
     }
 
     pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Provider>> {
+        self.pools
+            .read(|pool| async move { Self::select_by_id(&pool, id).await })
+            .await
+    }
+
+    async fn select_by_id(pool: &PgPool, id: Uuid) -> Result<Option<Provider>> {
         query_as!(
             Provider,
             r#"
-            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at
+            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at, version
             FROM hub_llmgateway_providers
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
             "#,
             id
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(pool)
         .await
     }
 
     pub async fn find_by_name(&self, name: &str) -> Result<Option<Provider>> {
+        self.pools
+            .read(|pool| async move { Self::select_by_name(&pool, name).await })
+            .await
+    }
+
+    async fn select_by_name(pool: &PgPool, name: &str) -> Result<Option<Provider>> {
         query_as!(
             Provider,
             r#"
-            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at
+            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at, version
             FROM hub_llmgateway_providers
-            WHERE name = $1
+            WHERE name = $1 AND deleted_at IS NULL
             "#,
             name
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(pool)
         .await
     }
 
     pub async fn list(&self) -> Result<Vec<Provider>> {
+        self.pools
+            .read(|pool| async move { Self::select_all(&pool).await })
+            .await
+    }
+
+    async fn select_all(pool: &PgPool) -> Result<Vec<Provider>> {
         query_as!(
             Provider,
             r#"
-            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at
+            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at, version
             FROM hub_llmgateway_providers
+            WHERE deleted_at IS NULL
             ORDER BY name
             "#
         )
-        .fetch_all(&self.pool)
+        .fetch_all(pool)
         .await
     }
 
+    /// Returns `None` if the provider's version is no longer `expected_version`.
     pub async fn update(
         &self,
         id: Uuid,
         data: &UpdateProviderRequest,
         config_json_value_opt: Option<JsonValue>,
+        expected_version: i32,
     ) -> Result<Option<Provider>> {
         // Fetch current and merge, or use COALESCE intelligently
         // For simplicity, this query relies on COALESCE for all fields in data.
@@ -143,16 +166,18 @@ impl ProviderRepository {
                 name = $1,
                 config_details = $2,
                 enabled = $3,
+                version = version + 1,
                 updated_at = now()
-            WHERE id = $4
-            RETURNING id, name, provider_type, config_details, enabled, created_at, updated_at
+            WHERE id = $4 AND version = $5
+            RETURNING id, name, provider_type, config_details, enabled, created_at, updated_at, version
             "#,
             name_to_update,
             final_config_details, // This is Option<JsonValue>
             enabled_to_update,
-            id
+            id,
+            expected_version
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(self.pools.primary())
         .await
     }
 
@@ -164,8 +189,54 @@ impl ProviderRepository {
             "#,
             id
         )
-        .execute(&self.pool)
+        .execute(self.pools.primary())
         .await?;
         Ok(result.rows_affected())
     }
+
+    /// Marks a provider as deleted, hiding it from lists and the gateway config.
+    pub async fn soft_delete(&self, id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE hub_llmgateway_providers
+            SET deleted_at = now()
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .execute(self.pools.primary())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn find_deleted_by_id(&self, id: Uuid) -> Result<Option<Provider>> {
+        self.pools
+            .read(|pool| async move {
+                sqlx::query_as::<_, Provider>(
+                    r#"
+                    SELECT id, name, provider_type, config_details, enabled, created_at, updated_at, version
+                    FROM hub_llmgateway_providers
+                    WHERE id = $1 AND deleted_at IS NOT NULL
+                    "#,
+                )
+                .bind(id)
+                .fetch_optional(&pool)
+                .await
+            })
+            .await
+    }
+
+    pub async fn restore(&self, id: Uuid) -> Result<Option<Provider>> {
+        sqlx::query_as::<_, Provider>(
+            r#"
+            UPDATE hub_llmgateway_providers
+            SET deleted_at = NULL
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            RETURNING id, name, provider_type, config_details, enabled, created_at, updated_at, version
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pools.primary())
+        .await
+    }
 }
@@ -1,12 +1,22 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::types::Uuid;
-use utoipa::ToSchema;
+use std::collections::{BTreeMap, HashMap};
+use utoipa::{IntoParams, ToSchema};
 
-pub use crate::types::ProviderType;
+use crate::providers::mock::{
+    CHUNK_DELAY_MS_PARAM, EMBEDDING_DIMENSIONS_PARAM, FAIL_EVERY_PARAM, FAIL_STATUS_PARAM,
+    LATENCY_MS_PARAM, MODE_PARAM, RESPONSE_PARAM, TOOL_ARGUMENTS_PARAM,
+};
+
+pub use crate::types::{
+    AdaptiveRouting, BudgetWindow, DegradedMode, DegradedOverrides, MaintenanceWindow,
+    ParameterPolicyMode, ParameterRule, PassthroughHeaders, ProviderType, RaceRouting,
+    RequestPriority, ToolLimits,
+};
 
 /// Represents different ways to store and retrieve secrets
-#[derive(Serialize, Deserialize, Debug, ToSchema, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone, PartialEq, Eq, Hash)]
 #[serde(tag = "type")]
 pub enum SecretObject {
     #[serde(rename = "literal")]
@@ -26,6 +36,11 @@ pub enum SecretObject {
 
     #[serde(rename = "environment")]
     Environment { variable_name: String },
+
+    /// A file such as a mounted Kubernetes secret. Provider API keys are re-read when the
+    /// file changes; other secrets are read when the config is loaded.
+    #[serde(rename = "file")]
+    File { path: String },
 }
 
 impl SecretObject {
@@ -50,29 +65,126 @@ impl SecretObject {
     pub fn environment(variable_name: String) -> Self {
         Self::Environment { variable_name }
     }
+
+    /// Create a file reference
+    pub fn file(path: String) -> Self {
+        Self::File { path }
+    }
+}
+
+/// Custom TLS settings used when connecting to a provider's upstream endpoint.
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderTlsConfig {
+    /// Extra root CA as inline PEM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<SecretObject>,
+    /// Path to a PEM file with an extra root CA.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert_path: Option<String>,
+    /// Path to the PEM client certificate for mTLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert_path: Option<String>,
+    /// Path to the PKCS#8 PEM private key for the client certificate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key_path: Option<String>,
+    /// Disables certificate verification. Dev environments only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub danger_accept_invalid_certs: Option<bool>,
 }
 
 /// Configuration specific to OpenAI providers.
 #[derive(Serialize, Deserialize, Debug, ToSchema, Clone, PartialEq, Eq)]
 pub struct OpenAIProviderConfig {
     pub api_key: SecretObject,
+    /// Tried once when the upstream rejects `api_key` with 401/403, for zero-downtime rotation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_secondary: Option<SecretObject>,
     pub organization_id: Option<String>,
     pub base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<SecretObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<ProviderTlsConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    /// Prefixes of the model types the provider accepts, replacing the defaults of its type.
+    /// `["*"]` accepts any model type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_model_prefixes: Option<Vec<String>>,
 }
 
 /// Configuration specific to Anthropic providers.
 #[derive(Serialize, Deserialize, Debug, ToSchema, Clone, PartialEq, Eq)]
 pub struct AnthropicProviderConfig {
     pub api_key: SecretObject,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<SecretObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<ProviderTlsConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_model_prefixes: Option<Vec<String>>,
+}
+
+/// How an Azure OpenAI provider authorizes its requests.
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AzureAuthType {
+    /// Sends `api_key` in the `api-key` header.
+    ApiKey,
+    /// Sends a Microsoft Entra ID (Azure AD) bearer token.
+    EntraId,
+}
+
+impl std::fmt::Display for AzureAuthType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AzureAuthType::ApiKey => write!(f, "api_key"),
+            AzureAuthType::EntraId => write!(f, "entra_id"),
+        }
+    }
 }
 
 /// Configuration specific to Azure OpenAI providers.
 #[derive(Serialize, Deserialize, Debug, ToSchema, Clone, PartialEq, Eq)]
 pub struct AzureProviderConfig {
-    pub api_key: SecretObject,
+    /// Required unless `auth_type` is `entra_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<SecretObject>,
     pub resource_name: String,
     pub api_version: String,
     pub base_url: Option<String>,
+    /// Defaults to `api_key`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_type: Option<AzureAuthType>,
+    /// Tenant of the app registration for client-credential tokens. Leave it and
+    /// `client_secret` unset to use the managed identity of the host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<SecretObject>,
+    /// App registration, or user-assigned managed identity, to request tokens for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<SecretObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<SecretObject>,
+    /// Overrides `https://login.microsoftonline.com`, e.g. for sovereign clouds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authority_host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<SecretObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<ProviderTlsConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_model_prefixes: Option<Vec<String>>,
 }
 
 /// Configuration specific to AWS Bedrock providers.
@@ -84,6 +196,16 @@ pub struct BedrockProviderConfig {
     pub region: String,
     pub use_iam_role: Option<bool>,
     pub inference_profile_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<SecretObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<ProviderTlsConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_model_prefixes: Option<Vec<String>>,
 }
 
 /// Configuration specific to Google VertexAI providers.
@@ -94,6 +216,90 @@ pub struct VertexAIProviderConfig {
     pub location: Option<String>,
     pub credentials_path: Option<String>,
     pub api_key: Option<SecretObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<SecretObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<ProviderTlsConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_model_prefixes: Option<Vec<String>>,
+}
+
+/// How a mock provider makes its replies.
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MockMode {
+    /// Returns the last user message.
+    Echo,
+    /// Returns `response`.
+    Fixed,
+}
+
+impl std::fmt::Display for MockMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MockMode::Echo => write!(f, "echo"),
+            MockMode::Fixed => write!(f, "fixed"),
+        }
+    }
+}
+
+/// Configuration of mock providers, which answer locally without calling any upstream.
+#[derive(Serialize, Deserialize, Debug, ToSchema, Clone, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MockProviderConfig {
+    /// Defaults to `echo`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<MockMode>,
+    /// Canned reply in `fixed` mode, unless the model sets its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// Arguments of the canned tool call. Defaults to `{}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_arguments: Option<String>,
+    /// Every Nth request fails with `fail_status`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fail_every: Option<u64>,
+    /// Defaults to 500.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fail_status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_delay_ms: Option<u64>,
+    /// Defaults to 8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_dimensions: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+}
+
+impl MockProviderConfig {
+    /// The provider params the mock provider reads these settings from.
+    pub fn params(&self) -> HashMap<String, String> {
+        [
+            (MODE_PARAM, self.mode.map(|mode| mode.to_string())),
+            (RESPONSE_PARAM, self.response.clone()),
+            (TOOL_ARGUMENTS_PARAM, self.tool_arguments.clone()),
+            (FAIL_EVERY_PARAM, self.fail_every.map(|n| n.to_string())),
+            (FAIL_STATUS_PARAM, self.fail_status.map(|n| n.to_string())),
+            (LATENCY_MS_PARAM, self.latency_ms.map(|n| n.to_string())),
+            (
+                CHUNK_DELAY_MS_PARAM,
+                self.chunk_delay_ms.map(|n| n.to_string()),
+            ),
+            (
+                EMBEDDING_DIMENSIONS_PARAM,
+                self.embedding_dimensions.map(|n| n.to_string()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(param, value)| Some((param.to_string(), value?)))
+        .collect()
+    }
 }
 
 /// Enum to hold the configuration for different provider types.
@@ -105,7 +311,66 @@ pub enum ProviderConfig {
     Azure(AzureProviderConfig),         // 3 fields
     Bedrock(BedrockProviderConfig),     // 4 fields but some optional
     OpenAI(OpenAIProviderConfig),       // 2 fields (1 optional) - must come before Anthropic
-    Anthropic(AnthropicProviderConfig), // 1 field - least specific, must be last
+    Anthropic(AnthropicProviderConfig), // 1 field - least specific of the real providers
+    Mock(MockProviderConfig),           // all optional, unknown fields denied - must be last
+}
+
+impl ProviderConfig {
+    /// Proxy settings shared by every provider config variant.
+    pub fn proxy_settings(&self) -> (Option<&SecretObject>, Option<&String>) {
+        match self {
+            ProviderConfig::OpenAI(c) => (c.proxy_url.as_ref(), c.no_proxy.as_ref()),
+            ProviderConfig::Anthropic(c) => (c.proxy_url.as_ref(), c.no_proxy.as_ref()),
+            ProviderConfig::Azure(c) => (c.proxy_url.as_ref(), c.no_proxy.as_ref()),
+            ProviderConfig::Bedrock(c) => (c.proxy_url.as_ref(), c.no_proxy.as_ref()),
+            ProviderConfig::VertexAI(c) => (c.proxy_url.as_ref(), c.no_proxy.as_ref()),
+            ProviderConfig::Mock(_) => (None, None),
+        }
+    }
+
+    /// TLS settings shared by every provider config variant.
+    pub fn tls_settings(&self) -> Option<&ProviderTlsConfig> {
+        match self {
+            ProviderConfig::OpenAI(c) => c.tls.as_ref(),
+            ProviderConfig::Anthropic(c) => c.tls.as_ref(),
+            ProviderConfig::Azure(c) => c.tls.as_ref(),
+            ProviderConfig::Bedrock(c) => c.tls.as_ref(),
+            ProviderConfig::VertexAI(c) => c.tls.as_ref(),
+            ProviderConfig::Mock(_) => None,
+        }
+    }
+
+    /// Model type prefixes configured on any provider config variant but the mock one.
+    pub fn allowed_model_prefixes(&self) -> Option<&[String]> {
+        match self {
+            ProviderConfig::OpenAI(c) => c.allowed_model_prefixes.as_deref(),
+            ProviderConfig::Anthropic(c) => c.allowed_model_prefixes.as_deref(),
+            ProviderConfig::Azure(c) => c.allowed_model_prefixes.as_deref(),
+            ProviderConfig::Bedrock(c) => c.allowed_model_prefixes.as_deref(),
+            ProviderConfig::VertexAI(c) => c.allowed_model_prefixes.as_deref(),
+            ProviderConfig::Mock(_) => None,
+        }
+    }
+
+    /// Maintenance windows shared by every provider config variant.
+    pub fn maintenance_windows(&self) -> &[MaintenanceWindow] {
+        match self {
+            ProviderConfig::OpenAI(c) => &c.maintenance_windows,
+            ProviderConfig::Anthropic(c) => &c.maintenance_windows,
+            ProviderConfig::Azure(c) => &c.maintenance_windows,
+            ProviderConfig::Bedrock(c) => &c.maintenance_windows,
+            ProviderConfig::VertexAI(c) => &c.maintenance_windows,
+            ProviderConfig::Mock(c) => &c.maintenance_windows,
+        }
+    }
+}
+
+/// Why the management API rejects a provider type that isn't built in.
+pub(crate) fn custom_provider_type_error(name: &str) -> String {
+    format!(
+        "Provider type '{name}' isn't built in. Custom provider types registered with \
+         ProviderRegistry::register_factory are only available in YAML mode."
+    )
 }
 
 // --- API Request DTOs ---
@@ -113,9 +378,11 @@ pub enum ProviderConfig {
 /// Request payload for creating a new provider configuration.
 #[derive(Serialize, Debug, ToSchema)]
 pub struct CreateProviderRequest {
-    /// A unique, user-friendly name for this provider configuration.
+    /// A unique, user-friendly name for this provider configuration: up to 128 letters,
+    /// digits, `.`, `_` or `-`, unique ignoring case.
     pub name: String,
-    /// The type of the LLM provider.
+    /// The type of the LLM provider. Only the built-in types are accepted; custom provider
+    /// types are only available in YAML mode.
     #[schema(value_type = String)] // Helps Utoipa represent the enum as a string
     pub provider_type: ProviderType,
     /// The specific configuration details for the provider type.
@@ -177,6 +444,16 @@ impl<'de> serde::Deserialize<'de> for CreateProviderRequest {
                     })?;
                 ProviderConfig::VertexAI(config)
             }
+            ProviderType::Mock => {
+                let config: MockProviderConfig =
+                    serde_json::from_value(helper.config).map_err(|e| {
+                        D::Error::custom(format!("Failed to deserialize mock config: {e}"))
+                    })?;
+                ProviderConfig::Mock(config)
+            }
+            ProviderType::Custom(name) => {
+                return Err(D::Error::custom(custom_provider_type_error(name)));
+            }
         };
 
         Ok(CreateProviderRequest {
@@ -191,13 +468,16 @@ impl<'de> serde::Deserialize<'de> for CreateProviderRequest {
 /// Request payload for updating an existing provider configuration.
 #[derive(Serialize, Debug, ToSchema)]
 pub struct UpdateProviderRequest {
-    /// A new unique, user-friendly name for this provider configuration.
+    /// A new unique, user-friendly name for this provider configuration, following the same
+    /// rules as on creation.
     pub name: Option<String>,
     /// The new specific configuration details. The 'type' within this config
     /// must match the existing provider's type.
     pub config: Option<ProviderConfig>,
     /// Whether this provider configuration should be enabled.
     pub enabled: Option<bool>,
+    /// The version this update is based on. Alternative to the `If-Match` header.
+    pub expected_version: Option<i32>,
 }
 
 impl<'de> serde::Deserialize<'de> for UpdateProviderRequest {
@@ -212,6 +492,7 @@ impl<'de> serde::Deserialize<'de> for UpdateProviderRequest {
             name: Option<String>,
             config: Option<serde_json::Value>,
             enabled: Option<bool>,
+            expected_version: Option<i32>,
         }
 
         let helper = UpdateProviderRequestHelper::deserialize(deserializer)?;
@@ -232,10 +513,29 @@ impl<'de> serde::Deserialize<'de> for UpdateProviderRequest {
             name: helper.name,
             config,
             enabled: helper.enabled,
+            expected_version: helper.expected_version,
         })
     }
 }
 
+/// Query parameters for DELETE endpoints.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteQuery {
+    /// Permanently purge the record instead of soft-deleting it.
+    #[serde(default)]
+    pub hard: bool,
+}
+
+/// Query parameters for disabling or deleting a provider that pipelines depend on.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ForceQuery {
+    /// Apply the change even though enabled pipelines route to the provider's models.
+    #[serde(default)]
+    pub force: bool,
+}
+
 // --- API Response DTO ---
 
 /// Response payload representing a provider configuration.
@@ -249,6 +549,7 @@ pub struct ProviderResponse {
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i32,
 }
 
 impl<'de> serde::Deserialize<'de> for ProviderResponse {
@@ -267,6 +568,7 @@ impl<'de> serde::Deserialize<'de> for ProviderResponse {
             enabled: bool,
             created_at: DateTime<Utc>,
             updated_at: DateTime<Utc>,
+            version: i32,
         }
 
         let helper = ProviderResponseHelper::deserialize(deserializer)?;
@@ -307,6 +609,16 @@ impl<'de> serde::Deserialize<'de> for ProviderResponse {
                     })?;
                 ProviderConfig::VertexAI(config)
             }
+            ProviderType::Mock => {
+                let config: MockProviderConfig =
+                    serde_json::from_value(helper.config).map_err(|e| {
+                        D::Error::custom(format!("Failed to deserialize mock config: {e}"))
+                    })?;
+                ProviderConfig::Mock(config)
+            }
+            ProviderType::Custom(name) => {
+                return Err(D::Error::custom(custom_provider_type_error(name)));
+            }
         };
 
         Ok(ProviderResponse {
@@ -317,14 +629,58 @@ impl<'de> serde::Deserialize<'de> for ProviderResponse {
             enabled: helper.enabled,
             created_at: helper.created_at,
             updated_at: helper.updated_at,
+            version: helper.version,
         })
     }
 }
 
+/// A pipeline whose model router uses a model definition.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Clone)]
+pub struct DependentPipelineDto {
+    pub id: Uuid,
+    pub name: String,
+    pub environment: Option<String>,
+    pub enabled: bool,
+}
+
+/// A model definition on a provider, with the pipelines routing to it.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Clone)]
+pub struct DependentModelDefinitionDto {
+    pub id: Uuid,
+    pub key: String,
+    pub model_type: String,
+    pub enabled: bool,
+    pub pipelines: Vec<DependentPipelineDto>,
+}
+
+/// Response payload listing what would break if a provider went away.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Clone)]
+pub struct ProviderDependentsResponse {
+    pub provider_id: Uuid,
+    pub model_definitions: Vec<DependentModelDefinitionDto>,
+}
+
+impl ProviderDependentsResponse {
+    /// Enabled pipelines routing to any of the provider's models, each listed once.
+    pub fn enabled_pipelines(&self) -> Vec<&DependentPipelineDto> {
+        let mut pipelines: Vec<&DependentPipelineDto> = self
+            .model_definitions
+            .iter()
+            .flat_map(|model| &model.pipelines)
+            .filter(|pipeline| pipeline.enabled)
+            .collect();
+        pipelines
+            .sort_by(|a, b| (&a.name, &a.environment, a.id).cmp(&(&b.name, &b.environment, b.id)));
+        pipelines.dedup_by_key(|pipeline| pipeline.id);
+        pipelines
+    }
+}
+
 // --- Model Definition DTOs ---
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
 pub struct CreateModelDefinitionRequest {
+    /// Up to 128 letters, digits, `.`, `_` or `-`, unique ignoring case.
     #[schema(example = "gpt-4o-openai")]
     pub key: String,
     #[schema(example = "gpt-4o")]
@@ -352,6 +708,9 @@ pub struct UpdateModelDefinitionRequest {
     pub config_details: Option<serde_json::Value>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enabled: Option<bool>,
+    /// The version this update is based on. Alternative to the `If-Match` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_version: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
@@ -366,6 +725,7 @@ pub struct ModelDefinitionResponse {
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i32,
 }
 
 // --- Pipeline & Model Routing DTOs ---
@@ -395,6 +755,10 @@ pub enum ModelRouterStrategyDto {
     OrderedFallback,
     /// Future: Randomly selects a model based on weights.
     WeightedRandom, // Add other strategies as needed
+    /// Picks the model with the best recent p95 latency and error rate, tuned by `adaptive`.
+    Adaptive,
+    /// Sends the request to the `race` candidates and returns the first response to arrive.
+    Race,
 }
 
 /// Configuration specific to the 'model-router' plugin.
@@ -404,12 +768,27 @@ pub struct ModelRouterConfigDto {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub strategy: Option<ModelRouterStrategyDto>,
     pub models: Vec<ModelRouterModelEntryDto>,
+    /// Serve `provider/model` names on the providers of `models` when the gateway has
+    /// `prefix_routing` enabled. Defaults to false.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = true)]
+    pub allow_dynamic_models: Option<bool>,
+    /// Settings of the `adaptive` strategy. Defaults apply when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptive: Option<AdaptiveRouting>,
+    /// Settings of the `race` strategy. Defaults apply when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub race: Option<RaceRouting>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
 pub struct LoggingConfigDto {
     #[schema(value_type = String, example = "debug")]
     pub level: String,
+    /// Fraction (0.0-1.0) of successful requests to log. Defaults to 1.0.
+    #[schema(example = 0.1)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
@@ -420,6 +799,83 @@ pub struct TracingConfigDto {
     pub api_key: SecretObject,
 }
 
+/// Configuration specific to the 'budget' plugin.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetConfigDto {
+    /// Maximum spend in USD per window.
+    #[schema(example = 100.0)]
+    pub limit_usd: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "monthly")]
+    pub window: Option<BudgetWindow>,
+    /// Percentage of the limit at which a warning is emitted. Defaults to 80.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = 80)]
+    pub warn_at_percent: Option<u8>,
+}
+
+/// Configuration specific to the 'metadata' plugin.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MetadataConfigDto {
+    #[schema(example = json!({"environment": "production"}))]
+    pub values: BTreeMap<String, String>,
+}
+
+/// Configuration specific to the 'priority' plugin.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PriorityConfigDto {
+    /// Priority applied when a request doesn't send `x-hub-priority`.
+    #[schema(value_type = String, example = "high")]
+    pub default: RequestPriority,
+}
+
+/// Configuration specific to the 'parameter-policy' plugin.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ParameterPolicyConfigDto {
+    /// `strict` rejects violations with 400, `sanitize` strips or clamps them. Defaults to strict.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "sanitize")]
+    pub mode: Option<ParameterPolicyMode>,
+    /// Rules keyed by request field: `deny`, `require` or `{"clamp": {"min", "max"}}`.
+    #[serde(default)]
+    #[schema(value_type = Object, example = json!({"user": "deny", "temperature": {"clamp": {"max": 1.0}}}))]
+    pub rules: BTreeMap<String, ParameterRule>,
+    /// Accept a request's `extra_body` and merge it into the provider's request body.
+    /// Defaults to false.
+    #[serde(default)]
+    pub allow_extra_body: bool,
+    /// The only `extra_body` keys accepted. Empty accepts any key the hub doesn't manage.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(example = json!(["safety_settings"]))]
+    pub extra_body_keys: Vec<String>,
+}
+
+/// Configuration specific to the 'response-normalization' plugin.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ResponseNormalizationConfigDto {
+    /// Nest provider-specific response fields under `provider_metadata` instead of dropping
+    /// them. Defaults to false.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = true)]
+    pub passthrough_extra_fields: Option<bool>,
+}
+
+/// Configuration specific to the 'stream-options' plugin.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StreamOptionsConfigDto {
+    /// Emit each streamed tool call as one complete chunk instead of argument fragments.
+    /// Defaults to false.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = true)]
+    pub aggregate_tool_calls: Option<bool>,
+}
+
 /// Supported plugin types for pipelines.
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq, Eq, Hash)]
 #[serde(rename_all = "kebab-case")]
@@ -430,6 +886,27 @@ pub enum PluginType {
     Logging,
     /// Tracing plugin for distributed tracing.
     Tracing,
+    /// Budget plugin enforcing a spend cap per window.
+    Budget,
+    /// Metadata plugin tagging chat requests with fixed metadata pairs.
+    Metadata,
+    /// Priority plugin pinning a default request priority.
+    Priority,
+    /// Parameter policy plugin denying, clamping or requiring request fields.
+    ParameterPolicy,
+    /// Response normalization plugin controlling provider-specific response fields.
+    ResponseNormalization,
+    /// Stream options plugin controlling how streamed chunks are emitted.
+    StreamOptions,
+    /// Degraded mode plugin switching to a substitute model while the pipeline is unhealthy.
+    /// Its `config_data` is a `DegradedMode`.
+    DegradedMode,
+    /// Passthrough headers plugin choosing the upstream response headers copied onto
+    /// responses. Its `config_data` is a `PassthroughHeaders`.
+    PassthroughHeaders,
+    /// Tool limits plugin capping and filtering the tools of chat requests. Its
+    /// `config_data` is a `ToolLimits`.
+    ToolLimits,
 }
 
 impl std::fmt::Display for PluginType {
@@ -438,6 +915,15 @@ impl std::fmt::Display for PluginType {
             PluginType::ModelRouter => write!(f, "model-router"),
             PluginType::Logging => write!(f, "logging"),
             PluginType::Tracing => write!(f, "tracing"),
+            PluginType::Budget => write!(f, "budget"),
+            PluginType::Metadata => write!(f, "metadata"),
+            PluginType::Priority => write!(f, "priority"),
+            PluginType::ParameterPolicy => write!(f, "parameter-policy"),
+            PluginType::ResponseNormalization => write!(f, "response-normalization"),
+            PluginType::StreamOptions => write!(f, "stream-options"),
+            PluginType::DegradedMode => write!(f, "degraded-mode"),
+            PluginType::PassthroughHeaders => write!(f, "passthrough-headers"),
+            PluginType::ToolLimits => write!(f, "tool-limits"),
         }
     }
 }
@@ -450,6 +936,15 @@ impl std::str::FromStr for PluginType {
             "model-router" => Ok(PluginType::ModelRouter),
             "logging" => Ok(PluginType::Logging),
             "tracing" => Ok(PluginType::Tracing),
+            "budget" => Ok(PluginType::Budget),
+            "metadata" => Ok(PluginType::Metadata),
+            "priority" => Ok(PluginType::Priority),
+            "parameter-policy" => Ok(PluginType::ParameterPolicy),
+            "response-normalization" => Ok(PluginType::ResponseNormalization),
+            "stream-options" => Ok(PluginType::StreamOptions),
+            "degraded-mode" => Ok(PluginType::DegradedMode),
+            "passthrough-headers" => Ok(PluginType::PassthroughHeaders),
+            "tool-limits" => Ok(PluginType::ToolLimits),
             _ => Err(format!("Unknown plugin type: {s}")),
         }
     }
@@ -459,6 +954,10 @@ impl std::str::FromStr for PluginType {
 /// The `config_data` field will be interpreted based on `plugin_type`.
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
 pub struct PipelinePluginConfigDto {
+    /// ID of the plugin configuration, assigned by the server. Ignored in requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(read_only)]
+    pub id: Option<Uuid>,
     /// Type of the plugin.
     #[schema(value_type = String, example = "model-router")]
     pub plugin_type: PluginType,
@@ -481,7 +980,8 @@ fn default_true() -> bool {
 /// Request payload for creating a new pipeline.
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
 pub struct CreatePipelineRequestDto {
-    /// A unique, user-friendly name for this pipeline.
+    /// A unique, user-friendly name for this pipeline: up to 128 letters, digits, `.`, `_` or
+    /// `-`, unique ignoring case within its environment.
     #[schema(example = "default_chat_pipeline")]
     pub name: String,
     /// Type of the pipeline (e.g., "chat", "completion").
@@ -495,6 +995,11 @@ pub struct CreatePipelineRequestDto {
     /// Whether this pipeline is enabled. Defaults to true.
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// Environment this pipeline belongs to (e.g. "staging", "prod"). Untagged pipelines
+    /// are loaded in every environment.
+    #[schema(example = "staging")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
 }
 
 /// Request payload for updating an existing pipeline.
@@ -513,6 +1018,24 @@ pub struct UpdatePipelineRequestDto {
     pub plugins: Option<Vec<PipelinePluginConfigDto>>,
     /// Whether this pipeline should be enabled.
     pub enabled: Option<bool>,
+    /// The version this update is based on. Alternative to the `If-Match` header.
+    pub expected_version: Option<i32>,
+}
+
+/// Request payload for updating a single plugin of a pipeline.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq, Default)]
+pub struct PatchPipelinePluginRequestDto {
+    /// Top-level keys to replace in the plugin's config_data. Keys not listed are kept;
+    /// a key set to null is removed.
+    #[schema(value_type = Option<Object>, example = json!({"api_key": {"type": "environment", "variable_name": "TRACE_API_KEY"}}))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_data: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Whether this plugin is enabled within the pipeline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// The pipeline version this update is based on. Alternative to the `If-Match` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_version: Option<i32>,
 }
 
 /// Response payload representing a pipeline.
@@ -524,19 +1047,193 @@ pub struct PipelineResponseDto {
     pub description: Option<String>,
     pub plugins: Vec<PipelinePluginConfigDto>, // For simplicity, returning the same DTO used in create/update. Could be a different one if needed.
     pub enabled: bool,
+    #[serde(default)]
+    pub environment: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+/// Request payload for promoting a pipeline to another environment.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PromotePipelineRequestDto {
+    /// Environment to copy the pipeline into.
+    #[schema(example = "prod")]
+    pub target_environment: String,
+}
+
+/// Query parameters for looking up a pipeline by name.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PipelineNameQuery {
+    /// Environment of the pipeline. Omit to find the untagged pipeline.
+    pub environment: Option<String>,
+}
+
+// --- API Key DTOs ---
+
+/// What a management API key is allowed to do.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyRole {
+    /// Full access, including API key management.
+    Admin,
+    /// GET requests only.
+    ReadOnly,
+}
+
+impl std::fmt::Display for ApiKeyRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiKeyRole::Admin => write!(f, "admin"),
+            ApiKeyRole::ReadOnly => write!(f, "read_only"),
+        }
+    }
+}
+
+impl std::str::FromStr for ApiKeyRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(ApiKeyRole::Admin),
+            "read_only" => Ok(ApiKeyRole::ReadOnly),
+            _ => Err(format!("Unknown API key role: {s}")),
+        }
+    }
+}
+
+/// Request payload for creating a management API key.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+pub struct CreateApiKeyRequest {
+    /// A label for the key, e.g. the service or person using it.
+    #[schema(example = "ci-deployer")]
+    pub name: String,
+    pub role: ApiKeyRole,
+}
+
+/// Response payload describing an API key. The key itself is never returned here.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub role: ApiKeyRole,
+    /// The first characters of the key, to tell keys apart.
+    pub key_prefix: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// Response payload for a newly created or rotated API key.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+pub struct ApiKeySecretResponse {
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+    /// The plaintext key. It is only shown once and cannot be retrieved later.
+    pub key: String,
+}
+
+// --- Config Snapshot DTOs ---
+
+/// Names of the resources of one kind that differ between a snapshot and the live state.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq, Default)]
+pub struct ResourceDiffDto {
+    /// In the snapshot but not live; a rollback recreates them.
+    pub added: Vec<String>,
+    /// Live but not in the snapshot; a rollback deletes them.
+    pub removed: Vec<String>,
+    /// In both, with different settings.
+    pub changed: Vec<String>,
+}
+
+/// What rolling back to a snapshot would change. Pipelines in an environment are
+/// listed as `name@environment`.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq, Default)]
+pub struct ConfigSnapshotDiffDto {
+    pub providers: ResourceDiffDto,
+    pub model_definitions: ResourceDiffDto,
+    pub pipelines: ResourceDiffDto,
+}
+
+/// Response payload describing a snapshot of a configuration the gateway applied.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+pub struct ConfigSnapshotResponse {
+    pub id: Uuid,
+    /// Hash of the gateway configuration, as reported by the gateway's `/health`.
+    #[schema(example = "4f1c2a9b8e7d6c5b")]
+    pub config_hash: String,
+    pub created_at: DateTime<Utc>,
+    /// Differences from the live state. After a rollback, the changes it made.
+    pub diff: ConfigSnapshotDiffDto,
+}
+
+// --- Config Diff DTOs ---
+
+/// A setting that differs between the database and the submitted YAML. Secrets are
+/// masked on both sides, so a changed secret shows as `***` on both.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+pub struct FieldChangeDto {
+    /// Dotted path within the resource. Plugins are keyed by their type.
+    #[schema(example = "plugins.model-router.models")]
+    pub path: String,
+    /// Value in the database; absent when only the YAML sets it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<serde_json::Value>,
+    /// Value in the YAML; absent when only the database sets it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yaml: Option<serde_json::Value>,
+}
+
+/// A resource in both configs with different settings.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+pub struct ResourceChangeDto {
+    pub name: String,
+    pub fields: Vec<FieldChangeDto>,
+}
+
+/// Resources of one kind that differ between the database and the YAML, by key.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq, Default)]
+pub struct ConfigResourceDiffDto {
+    /// In the database but not in the YAML.
+    pub added: Vec<String>,
+    /// In the YAML but not in the database.
+    pub removed: Vec<String>,
+    pub changed: Vec<ResourceChangeDto>,
+}
+
+/// How the config the gateway builds from the database differs from a YAML config.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq, Default)]
+pub struct ConfigDiffResponse {
+    /// Both produce the same providers, models, pipelines and prompt templates.
+    pub identical: bool,
+    pub providers: ConfigResourceDiffDto,
+    pub models: ConfigResourceDiffDto,
+    pub pipelines: ConfigResourceDiffDto,
+    pub prompt_templates: ConfigResourceDiffDto,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_create_provider_request_rejects_custom_types() {
+        let error = serde_json::from_value::<CreateProviderRequest>(json!({
+            "name": "acme",
+            "provider_type": "acme",
+            "config": {},
+        }))
+        .unwrap_err();
+        assert!(error.to_string().contains("only available in YAML mode"));
+    }
+
     #[test]
     fn test_logging_config_dto_serialization() {
         let config = LoggingConfigDto {
             level: "debug".to_string(),
+            sample_rate: None,
         };
 
         let serialized = serde_json::to_value(&config).unwrap();
@@ -613,6 +1310,7 @@ mod tests {
             config_data: json!({"level": "error"}),
             enabled: true,
             order_in_pipeline: 1,
+            id: None,
         };
 
         let serialized = serde_json::to_value(&plugin_config).unwrap();
@@ -637,6 +1335,7 @@ mod tests {
             }),
             enabled: true,
             order_in_pipeline: 2,
+            id: None,
         };
 
         let serialized = serde_json::to_value(&plugin_config).unwrap();
@@ -671,6 +1370,7 @@ mod tests {
                     config_data: json!({"level": "debug"}),
                     enabled: true,
                     order_in_pipeline: 1,
+                    id: None,
                 },
                 PipelinePluginConfigDto {
                     plugin_type: PluginType::Tracing,
@@ -683,9 +1383,11 @@ mod tests {
                     }),
                     enabled: true,
                     order_in_pipeline: 2,
+                    id: None,
                 },
             ],
             enabled: true,
+            environment: None,
         };
 
         let serialized = serde_json::to_value(&request).unwrap();
@@ -1,18 +1,42 @@
 use axum::{
     Json,
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use utoipa::ToSchema;
 
+use crate::config::validation::ValidationError;
+use crate::management::db::pools::is_timeout;
+use crate::management::dto::ProviderDependentsResponse;
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub enum ApiError {
     DatabaseError(String), // Changed from sqlx::Error to String for serialization
     NotFound(String),
     Conflict(String),        // For duplicate entries, etc.
     ValidationError(String), // For DTO validation issues
+    /// An update was based on a stale version of the resource.
+    VersionConflict {
+        message: String,
+        current_version: i32,
+    },
+    /// An update did not say which version it was based on.
+    PreconditionRequired(String),
+    /// The request carried no valid API key.
+    Unauthorized(String),
+    /// The API key's role doesn't allow the request.
+    Forbidden(String),
+    /// The change would break enabled pipelines routing to the provider's models.
+    DependentsConflict {
+        message: String,
+        dependents: ProviderDependentsResponse,
+    },
+    /// Applying the change would leave the gateway with a config it refuses to load.
+    InvalidConfig(Vec<ValidationError>),
+    /// The database timed out; the request can be retried.
+    ServiceUnavailable(String),
     // Add other specific error types as needed
     InternalServerError(String),
 }
@@ -24,6 +48,35 @@ impl IntoResponse for ApiError {
             ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
             ApiError::Conflict(message) => (StatusCode::CONFLICT, message),
             ApiError::ValidationError(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::VersionConflict {
+                message,
+                current_version,
+            } => {
+                let body = Json(json!({ "error": message, "current_version": current_version }));
+                return (StatusCode::CONFLICT, body).into_response();
+            }
+            ApiError::DependentsConflict {
+                message,
+                dependents,
+            } => {
+                let body = Json(json!({ "error": message, "dependents": dependents }));
+                return (StatusCode::CONFLICT, body).into_response();
+            }
+            ApiError::PreconditionRequired(message) => (StatusCode::PRECONDITION_REQUIRED, message),
+            ApiError::Unauthorized(message) => {
+                let body = Json(json!({ "error": message }));
+                let challenge = [(header::WWW_AUTHENTICATE, "Bearer")];
+                return (StatusCode::UNAUTHORIZED, challenge, body).into_response();
+            }
+            ApiError::Forbidden(message) => (StatusCode::FORBIDDEN, message),
+            ApiError::InvalidConfig(errors) => {
+                let body = Json(json!({
+                    "error": "The resulting configuration is invalid.",
+                    "validation_errors": errors,
+                }));
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+            ApiError::ServiceUnavailable(message) => (StatusCode::SERVICE_UNAVAILABLE, message),
             ApiError::InternalServerError(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
         };
 
@@ -32,11 +85,26 @@ impl IntoResponse for ApiError {
     }
 }
 
+impl ApiError {
+    pub fn version_conflict(resource: &str, id: impl std::fmt::Display, current: i32) -> Self {
+        ApiError::VersionConflict {
+            message: format!(
+                "{resource} with ID {id} has been modified; the current version is {current}."
+            ),
+            current_version: current,
+        }
+    }
+}
+
 // Convenience for converting sqlx::Error to ApiError
 impl From<sqlx::Error> for ApiError {
     fn from(err: sqlx::Error) -> Self {
         match err {
             sqlx::Error::RowNotFound => ApiError::NotFound("Resource not found".to_string()),
+            _ if is_timeout(&err) => {
+                tracing::warn!("Database timeout: {err}");
+                ApiError::ServiceUnavailable("The database timed out; try again later.".to_string())
+            }
             // Add more specific mappings if needed, e.g., for unique constraint violations
             _ => {
                 // Log the detailed database error to the console for debugging
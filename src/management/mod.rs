@@ -2,15 +2,25 @@ pub mod api;
 pub mod db;
 pub mod dto;
 pub mod errors;
+pub mod poller;
 pub mod services;
 pub mod state;
 
+pub use db::DbPools;
 pub use state::{DbBasedConfigIntegration, db_based_config_integration};
 
-use axum::Router;
+use axum::{
+    Router,
+    extract::Request,
+    http::Method,
+    middleware::{self, Next},
+    response::Response,
+};
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use self::db::pools::read_from_primary;
+
 // Repositories - some services might need direct Arc<Repo> access
 use self::db::repositories::{
     // ProviderRepository is created internally by ProviderService via pool
@@ -20,8 +30,12 @@ use self::db::repositories::{
 
 // Services
 use self::services::{
+    api_key_service::{ApiKeyService, StaticApiKey},
+    config_diff_service::ConfigDiffService,
     config_provider_service::ConfigProviderService,
-    model_definition_service::ModelDefinitionService, pipeline_service::PipelineService,
+    config_snapshot_service::ConfigSnapshotService,
+    model_definition_service::ModelDefinitionService,
+    pipeline_service::PipelineService,
     provider_service::ProviderService,
 };
 
@@ -33,39 +47,110 @@ pub struct AppState {
     pub model_definition_service: Arc<ModelDefinitionService>,
     pub pipeline_service: Arc<PipelineService>,
     pub config_provider_service: Arc<ConfigProviderService>,
+    pub api_key_service: Arc<ApiKeyService>,
+    pub config_snapshot_service: Arc<ConfigSnapshotService>,
+    pub config_diff_service: Arc<ConfigDiffService>,
 }
 
 /// Initializes and returns the Axum router for the DB based config Management API
 /// and the ConfigProviderService for gateway integration.
-pub fn management_api_bundle(pool: PgPool) -> (Router, Arc<ConfigProviderService>) {
+/// Only API keys stored in the database are accepted.
+pub fn management_api_bundle(pools: impl Into<DbPools>) -> (Router, Arc<ConfigProviderService>) {
+    management_api_bundle_with_api_keys(pools, Vec::new())
+}
+
+/// Like [`management_api_bundle`], serving every request, with or without a key, as an
+/// admin's. Only meant for local development.
+pub fn management_api_bundle_without_auth(
+    pools: impl Into<DbPools>,
+) -> (Router, Arc<ConfigProviderService>) {
+    let pools = pools.into();
+    let api_key_service = ApiKeyService::new(pools.clone(), Vec::new()).with_auth_disabled(true);
+    management_api_bundle_with_api_key_service(pools, Arc::new(api_key_service))
+}
+
+/// The ConfigProviderService alone, reading the config as the gateway's poller does, for
+/// tooling that doesn't serve the Management API.
+pub fn config_provider_service(pools: impl Into<DbPools>) -> Arc<ConfigProviderService> {
+    let pools = pools.into();
+    let pipeline_service = Arc::new(PipelineService::new(
+        Arc::new(PipelineRepository::new(pools.clone())),
+        Arc::new(ModelDefinitionRepository::new(pools.clone())),
+    ));
+    Arc::new(
+        ConfigProviderService::new(
+            Arc::new(ProviderService::new(pools.clone())),
+            Arc::new(ModelDefinitionService::new(pools.clone())),
+            pipeline_service,
+        )
+        .with_snapshots(Arc::new(ConfigSnapshotService::new(pools))),
+    )
+}
+
+/// Like [`management_api_bundle`], additionally accepting the given static API keys.
+pub fn management_api_bundle_with_api_keys(
+    pools: impl Into<DbPools>,
+    static_api_keys: Vec<StaticApiKey>,
+) -> (Router, Arc<ConfigProviderService>) {
+    let pools = pools.into();
+    let api_key_service = Arc::new(ApiKeyService::new(pools.clone(), static_api_keys));
+    management_api_bundle_with_api_key_service(pools, api_key_service)
+}
+
+/// Like [`management_api_bundle`], authenticating requests with `api_key_service`.
+pub fn management_api_bundle_with_api_key_service(
+    pools: impl Into<DbPools>,
+    api_key_service: Arc<ApiKeyService>,
+) -> (Router, Arc<ConfigProviderService>) {
+    let pools = pools.into();
     // Repositories that are direct dependencies for some services
     let model_definition_repo_for_pipeline_service =
-        Arc::new(ModelDefinitionRepository::new(pool.clone()));
-    let pipeline_repo_for_pipeline_service = Arc::new(PipelineRepository::new(pool.clone()));
+        Arc::new(ModelDefinitionRepository::new(pools.clone()));
+    let pipeline_repo_for_pipeline_service = Arc::new(PipelineRepository::new(pools.clone()));
 
     // Initialize services
     // ProviderService and ModelDefinitionService create their own repo instances internally using the pool.
-    let provider_service = Arc::new(ProviderService::new(pool.clone()));
-    let model_definition_service = Arc::new(ModelDefinitionService::new(pool.clone()));
+    let provider_service = Arc::new(ProviderService::new(pools.clone()));
+    let model_definition_service = Arc::new(ModelDefinitionService::new(pools.clone()));
     // PipelineService takes pre-initialized Arc<Repository> instances.
     let pipeline_service = Arc::new(PipelineService::new(
         pipeline_repo_for_pipeline_service,
         model_definition_repo_for_pipeline_service,
     ));
 
+    let config_snapshot_service = Arc::new(ConfigSnapshotService::new(pools.clone()));
+
     // Create ConfigProviderService using the other services
-    let config_provider_service = Arc::new(ConfigProviderService::new(
-        provider_service.clone(),
-        model_definition_service.clone(),
-        pipeline_service.clone(),
-    ));
+    let config_provider_service = Arc::new(
+        ConfigProviderService::new(
+            provider_service.clone(),
+            model_definition_service.clone(),
+            pipeline_service.clone(),
+        )
+        .with_snapshots(config_snapshot_service.clone()),
+    );
+
+    let config_diff_service = Arc::new(ConfigDiffService::new(config_provider_service.clone()));
+
+    if api_key_service.is_auth_disabled() {
+        tracing::warn!(
+            "Management API authentication is disabled; every request is served as an admin's. Don't use this outside local development."
+        );
+    } else if !api_key_service.has_static_keys() {
+        tracing::warn!(
+            "MANAGEMENT_API_KEYS is not set; the management API only accepts API keys stored in the database."
+        );
+    }
 
     let app_state = AppState {
-        db_pool: pool.clone(), // Store the pool in AppState as well
+        db_pool: pools.primary().clone(), // Store the pool in AppState as well
         provider_service,
         model_definition_service,
         pipeline_service,
         config_provider_service: config_provider_service.clone(),
+        api_key_service,
+        config_snapshot_service,
+        config_diff_service,
     };
 
     let router = Router::new()
@@ -81,11 +166,42 @@ pub fn management_api_bundle(pool: PgPool) -> (Router, Arc<ConfigProviderService
             "/api/v1/management/pipelines",
             api::routes::pipeline_routes::pipeline_routes(),
         )
+        .nest(
+            "/api/v1/management/config",
+            api::routes::config_routes::config_routes(),
+        )
+        .nest(
+            "/api/v1/management/api-keys",
+            api::routes::api_key_routes::api_key_routes(),
+        )
+        // Routes added after this layer, like /health, don't require an API key.
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            api::auth::require_api_key,
+        ))
         .route(
             "/health",
             axum::routing::get(|| async { "Management API is healthy" }),
         )
+        .layer(middleware::from_fn(mutations_read_from_primary))
         .with_state(app_state);
 
     (router, config_provider_service)
 }
+
+/// Puts `admin_routes`, the gateway's `/admin` endpoints, behind the management API keys.
+pub fn admin_router(admin_routes: Router, api_key_service: Arc<ApiKeyService>) -> Router {
+    admin_routes.route_layer(middleware::from_fn_with_state(
+        api_key_service,
+        api::auth::require_api_key,
+    ))
+}
+
+/// Sends every read of a request that changes something to the primary, so the checks
+/// before its writes and the response after them don't see a lagging read replica.
+async fn mutations_read_from_primary(request: Request, next: Next) -> Response {
+    if matches!(*request.method(), Method::GET | Method::HEAD) {
+        return next.run(request).await;
+    }
+    read_from_primary(next.run(request)).await
+}
@@ -0,0 +1,289 @@
+//! Polls the database for configuration changes. Replicas spread their polls out with a
+//! random initial delay and jitter on every interval, and skip re-reading the config while
+//! a cheap watermark query shows the config tables unchanged.
+
+use crate::management::db::repositories::config_snapshot_repository::ConfigWatermark;
+use crate::management::services::config_provider_service::ConfigProviderService;
+use crate::metrics::counter;
+use crate::state::AppState;
+use crate::types::GatewayConfig;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Counts config polls, labelled by `outcome`: `skipped`, `fetched`, `applied` or `failed`.
+pub const CONFIG_POLLS_METRIC: &str = "hub_config_polls_total";
+
+pub const DEFAULT_POLL_JITTER_PERCENT: u32 = 10;
+/// Polls that may be skipped in a row before the config is read anyway, so changes outside
+/// the database, such as rotated secrets, are still picked up.
+pub const MAX_SKIPPED_POLLS: u32 = 9;
+
+/// What a poll did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollOutcome {
+    /// The watermark was unchanged, so the config wasn't read.
+    Skipped,
+    /// The config was read but was the one already applied.
+    Fetched,
+    /// A changed config was applied.
+    Applied,
+}
+
+impl PollOutcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            PollOutcome::Skipped => "skipped",
+            PollOutcome::Fetched => "fetched",
+            PollOutcome::Applied => "applied",
+        }
+    }
+}
+
+/// Where the poller reads the config from.
+#[async_trait]
+pub trait PolledConfig: Send + Sync {
+    /// `None` when the source can't tell, so every poll reads the config.
+    async fn watermark(&self) -> Result<Option<ConfigWatermark>>;
+    async fn fetch(&self) -> Result<GatewayConfig>;
+}
+
+#[async_trait]
+impl PolledConfig for ConfigProviderService {
+    async fn watermark(&self) -> Result<Option<ConfigWatermark>> {
+        self.config_watermark().await
+    }
+
+    async fn fetch(&self) -> Result<GatewayConfig> {
+        self.fetch_live_config().await
+    }
+}
+
+/// When to poll: every `interval`, give or take `jitter_percent` of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PollSchedule {
+    interval: Duration,
+    jitter_percent: u32,
+}
+
+impl PollSchedule {
+    /// Jitter is capped at 100%.
+    pub fn new(interval: Duration, jitter_percent: u32) -> Self {
+        Self {
+            interval,
+            jitter_percent: jitter_percent.min(100),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Delay before the first poll: anywhere within one interval, for `random` in `[0, 1)`.
+    pub fn initial_delay(&self, random: f64) -> Duration {
+        self.interval.mul_f64(random.clamp(0.0, 1.0))
+    }
+
+    /// Delay before the next poll: the interval moved by up to `jitter_percent` either way,
+    /// for `random` in `[0, 1)`.
+    pub fn next_delay(&self, random: f64) -> Duration {
+        let jitter = f64::from(self.jitter_percent) / 100.0;
+        let factor = 1.0 + jitter * (2.0 * random.clamp(0.0, 1.0) - 1.0);
+        self.interval.mul_f64(factor)
+    }
+}
+
+/// A random number in `[0, 1)`.
+pub fn random_unit() -> f64 {
+    let (bits, _) = Uuid::new_v4().as_u64_pair();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Applies the config from `source` to `app_state` when it changes.
+pub struct ConfigPoller<S: ?Sized> {
+    source: Arc<S>,
+    app_state: Arc<AppState>,
+    last_watermark: Option<ConfigWatermark>,
+    skipped_in_a_row: u32,
+}
+
+impl<S: PolledConfig + ?Sized> ConfigPoller<S> {
+    pub fn new(source: Arc<S>, app_state: Arc<AppState>) -> Self {
+        Self {
+            source,
+            app_state,
+            last_watermark: None,
+            skipped_in_a_row: 0,
+        }
+    }
+
+    /// Polls once, counting the outcome in [`CONFIG_POLLS_METRIC`].
+    pub async fn poll(&mut self) -> Result<PollOutcome> {
+        let result = self.try_poll().await;
+        let outcome = result.as_ref().map_or("failed", |outcome| outcome.as_str());
+        counter!(CONFIG_POLLS_METRIC, "outcome" => outcome).increment(1);
+        result
+    }
+
+    async fn try_poll(&mut self) -> Result<PollOutcome> {
+        let watermark = self.source.watermark().await?;
+        if watermark.is_some()
+            && watermark == self.last_watermark
+            && self.skipped_in_a_row < MAX_SKIPPED_POLLS
+        {
+            self.skipped_in_a_row += 1;
+            debug!("Config tables unchanged; skipping the config fetch.");
+            return Ok(PollOutcome::Skipped);
+        }
+
+        let config = self.source.fetch().await?;
+        debug!(
+            "Config has {} providers, {} models, {} pipelines",
+            config.providers.len(),
+            config.models.len(),
+            config.pipelines.len()
+        );
+        let previous_hash = self.app_state.config_version().config_hash;
+        self.app_state.update_config(config)?;
+        // Only once applied, so a config that failed to apply is read again.
+        self.last_watermark = watermark;
+        self.skipped_in_a_row = 0;
+        Ok(
+            if self.app_state.config_version().config_hash != previous_hash {
+                PollOutcome::Applied
+            } else {
+                PollOutcome::Fetched
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ModelConfig, Provider, ProviderType};
+    use chrono::{TimeZone, Utc};
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_jitter_bounds() {
+        let schedule = PollSchedule::new(Duration::from_secs(30), 20);
+        assert_eq!(schedule.initial_delay(0.0), Duration::ZERO);
+        assert!(schedule.initial_delay(0.999) < Duration::from_secs(30));
+        assert_eq!(schedule.next_delay(0.0), Duration::from_secs(24));
+        assert_eq!(schedule.next_delay(0.5), Duration::from_secs(30));
+        assert!(schedule.next_delay(0.999) < Duration::from_secs(36));
+
+        for _ in 0..1000 {
+            let random = random_unit();
+            assert!((0.0..1.0).contains(&random));
+            let delay = schedule.next_delay(random);
+            assert!(delay >= Duration::from_secs(24) && delay < Duration::from_secs(36));
+        }
+
+        let unjittered = PollSchedule::new(Duration::from_secs(30), 0);
+        assert_eq!(unjittered.next_delay(0.9), Duration::from_secs(30));
+        let capped = PollSchedule::new(Duration::from_secs(30), 500);
+        assert_eq!(capped.next_delay(0.0), Duration::ZERO);
+    }
+
+    /// A config source whose watermark and config the test sets, counting fetches.
+    #[derive(Default)]
+    struct FakeSource {
+        watermark: Mutex<Option<ConfigWatermark>>,
+        config: Mutex<GatewayConfig>,
+        fetches: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl PolledConfig for FakeSource {
+        async fn watermark(&self) -> Result<Option<ConfigWatermark>> {
+            Ok(*self.watermark.lock().unwrap())
+        }
+
+        async fn fetch(&self) -> Result<GatewayConfig> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(self.config.lock().unwrap().clone())
+        }
+    }
+
+    fn watermark(second: u32, row_count: i64) -> Option<ConfigWatermark> {
+        Some(ConfigWatermark {
+            updated_at: Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, second).unwrap()),
+            row_count,
+        })
+    }
+
+    fn config_with_model(key: &str) -> GatewayConfig {
+        GatewayConfig {
+            providers: vec![Provider {
+                key: "mock".to_string(),
+                r#type: ProviderType::Mock,
+                api_key: String::new(),
+                maintenance_windows: vec![],
+                params: Default::default(),
+            }],
+            models: vec![ModelConfig {
+                key: key.to_string(),
+                r#type: key.to_string(),
+                provider: "mock".to_string(),
+                params: Default::default(),
+                enabled: true,
+                deprecation: Default::default(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_watermark_skips_the_fetch() {
+        let source = Arc::new(FakeSource::default());
+        *source.watermark.lock().unwrap() = watermark(0, 2);
+        *source.config.lock().unwrap() = config_with_model("gpt-4o");
+        let app_state = Arc::new(AppState::new(GatewayConfig::default()).unwrap());
+        let mut poller = ConfigPoller::new(source.clone(), app_state);
+
+        assert_eq!(poller.poll().await.unwrap(), PollOutcome::Applied);
+        assert_eq!(poller.poll().await.unwrap(), PollOutcome::Skipped);
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 1);
+
+        // A newer row, or a deleted one, means the config is read again.
+        *source.watermark.lock().unwrap() = watermark(1, 2);
+        assert_eq!(poller.poll().await.unwrap(), PollOutcome::Fetched);
+        *source.watermark.lock().unwrap() = watermark(1, 1);
+        *source.config.lock().unwrap() = config_with_model("gpt-4o-mini");
+        assert_eq!(poller.poll().await.unwrap(), PollOutcome::Applied);
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_config_is_read_after_too_many_skips() {
+        let source = Arc::new(FakeSource::default());
+        *source.watermark.lock().unwrap() = watermark(0, 0);
+        let app_state = Arc::new(AppState::new(GatewayConfig::default()).unwrap());
+        let mut poller = ConfigPoller::new(source.clone(), app_state);
+
+        assert_eq!(poller.poll().await.unwrap(), PollOutcome::Fetched);
+        for _ in 0..MAX_SKIPPED_POLLS {
+            assert_eq!(poller.poll().await.unwrap(), PollOutcome::Skipped);
+        }
+        assert_eq!(poller.poll().await.unwrap(), PollOutcome::Fetched);
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_every_poll_fetches_without_a_watermark() {
+        let source = Arc::new(FakeSource::default());
+        let app_state = Arc::new(AppState::new(GatewayConfig::default()).unwrap());
+        let mut poller = ConfigPoller::new(source.clone(), app_state);
+
+        for _ in 0..3 {
+            assert_eq!(poller.poll().await.unwrap(), PollOutcome::Fetched);
+        }
+        assert_eq!(source.fetches.load(Ordering::SeqCst), 3);
+    }
+}
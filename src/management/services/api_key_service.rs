@@ -0,0 +1,276 @@
+use sha2::{Digest, Sha256};
+use sqlx::types::Uuid;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+
+use crate::management::{
+    db::{DbPools, models::ApiKey, repositories::api_key_repository::ApiKeyRepository},
+    dto::{ApiKeyResponse, ApiKeyRole, ApiKeySecretResponse, CreateApiKeyRequest},
+    errors::ApiError,
+};
+
+/// Environment variable holding API keys that don't live in the database.
+pub const MANAGEMENT_API_KEYS_ENV: &str = "MANAGEMENT_API_KEYS";
+/// Environment variable that, set to `true`, opens the management API to every caller.
+pub const MANAGEMENT_AUTH_DISABLED_ENV: &str = "MANAGEMENT_AUTH_DISABLED";
+
+const KEY_PREFIX_LEN: usize = 12;
+
+/// An API key configured outside the database, e.g. via `MANAGEMENT_API_KEYS`.
+#[derive(Clone)]
+pub struct StaticApiKey {
+    digest: [u8; 32],
+    role: ApiKeyRole,
+}
+
+impl std::fmt::Debug for StaticApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StaticApiKey")
+            .field("role", &self.role)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StaticApiKey {
+    pub fn new(key: &str, role: ApiKeyRole) -> Self {
+        Self {
+            digest: Sha256::digest(key.as_bytes()).into(),
+            role,
+        }
+    }
+
+    /// Parses a comma-separated list of `key[:role]` entries. The role defaults to `admin`.
+    pub fn parse_list(value: &str) -> Result<Vec<Self>, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.rsplit_once(':') {
+                Some((key, role)) if !key.is_empty() => Ok(Self::new(key, role.parse()?)),
+                Some(_) => Err("API key entries must not be empty".to_string()),
+                None => Ok(Self::new(entry, ApiKeyRole::Admin)),
+            })
+            .collect()
+    }
+
+    /// Reads `MANAGEMENT_API_KEYS`; an unset variable means no static keys.
+    pub fn from_env() -> anyhow::Result<Vec<Self>> {
+        match std::env::var(MANAGEMENT_API_KEYS_ENV) {
+            Ok(value) => Self::parse_list(&value)
+                .map_err(|e| anyhow::anyhow!("Invalid {MANAGEMENT_API_KEYS_ENV}: {e}")),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Whether `MANAGEMENT_AUTH_DISABLED` is set to `true`.
+pub fn auth_disabled_from_env() -> bool {
+    std::env::var(MANAGEMENT_AUTH_DISABLED_ENV)
+        .is_ok_and(|value| value.eq_ignore_ascii_case("true"))
+}
+
+#[derive(Clone)]
+pub struct ApiKeyService {
+    /// `None` without a database, where only static keys are accepted.
+    repo: Option<Arc<ApiKeyRepository>>,
+    static_keys: Vec<StaticApiKey>,
+    auth_disabled: bool,
+}
+
+impl ApiKeyService {
+    pub fn new(pools: impl Into<DbPools>, static_keys: Vec<StaticApiKey>) -> Self {
+        Self {
+            repo: Some(Arc::new(ApiKeyRepository::new(pools))),
+            static_keys,
+            auth_disabled: false,
+        }
+    }
+
+    /// Accepts only `static_keys`, for servers without a database.
+    pub fn with_static_keys(static_keys: Vec<StaticApiKey>) -> Self {
+        Self {
+            repo: None,
+            static_keys,
+            auth_disabled: false,
+        }
+    }
+
+    /// Accepts requests without a valid key as an admin's. Only meant for local
+    /// development.
+    pub fn with_auth_disabled(mut self, auth_disabled: bool) -> Self {
+        self.auth_disabled = auth_disabled;
+        self
+    }
+
+    pub fn has_static_keys(&self) -> bool {
+        !self.static_keys.is_empty()
+    }
+
+    pub fn is_auth_disabled(&self) -> bool {
+        self.auth_disabled
+    }
+
+    /// Resolves the role of a bearer token. Requests without a valid key are rejected,
+    /// even while no keys are configured, unless authentication is disabled.
+    pub async fn authenticate(&self, token: Option<&str>) -> Result<ApiKeyRole, ApiError> {
+        if let Some(token) = token {
+            let digest: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+            // Check every static key so the time taken doesn't depend on which one matched.
+            let static_match = self.static_keys.iter().fold(None, |found, key| {
+                if bool::from(key.digest.ct_eq(&digest)) {
+                    Some(key.role)
+                } else {
+                    found
+                }
+            });
+            if let Some(role) = static_match {
+                return Ok(role);
+            }
+            let stored = match &self.repo {
+                Some(repo) => repo.find_active_by_hash(&hex::encode(digest)).await?,
+                None => None,
+            };
+            if let Some(key) = stored {
+                return Self::parse_role(&key.role);
+            }
+        }
+
+        if self.auth_disabled {
+            return Ok(ApiKeyRole::Admin);
+        }
+        Err(ApiError::Unauthorized(match token {
+            Some(_) => "Invalid API key.".to_string(),
+            None => "Missing API key. Send it as 'Authorization: Bearer <key>'.".to_string(),
+        }))
+    }
+
+    pub async fn create_key(
+        &self,
+        request: CreateApiKeyRequest,
+    ) -> Result<ApiKeySecretResponse, ApiError> {
+        if request.name.trim().is_empty() {
+            return Err(ApiError::ValidationError(
+                "API key name must not be empty.".to_string(),
+            ));
+        }
+        let (key, key_hash, key_prefix) = Self::generate_key();
+        let db_key = self
+            .repo()?
+            .create(
+                &request.name,
+                &request.role.to_string(),
+                &key_hash,
+                &key_prefix,
+            )
+            .await?;
+        Ok(ApiKeySecretResponse {
+            api_key: Self::map_db_key_to_response(db_key)?,
+            key,
+        })
+    }
+
+    pub async fn list_keys(&self) -> Result<Vec<ApiKeyResponse>, ApiError> {
+        let db_keys = self.repo()?.list().await?;
+        db_keys
+            .into_iter()
+            .map(Self::map_db_key_to_response)
+            .collect()
+    }
+
+    /// Issues a new secret for a key. The previous secret stops working immediately.
+    pub async fn rotate_key(&self, id: Uuid) -> Result<ApiKeySecretResponse, ApiError> {
+        let (key, key_hash, key_prefix) = Self::generate_key();
+        let db_key = self
+            .repo()?
+            .rotate(id, &key_hash, &key_prefix)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("API key with ID {id} not found.")))?;
+        Ok(ApiKeySecretResponse {
+            api_key: Self::map_db_key_to_response(db_key)?,
+            key,
+        })
+    }
+
+    pub async fn revoke_key(&self, id: Uuid) -> Result<(), ApiError> {
+        let db_key = self
+            .repo()?
+            .find_active_by_id(id)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("API key with ID {id} not found.")))?;
+
+        // Revoking the last admin key would lock admins out.
+        let static_admin = self.static_keys.iter().any(|k| k.role == ApiKeyRole::Admin);
+        if Self::parse_role(&db_key.role)? == ApiKeyRole::Admin && !static_admin {
+            let admins = self.repo()?.count_active(Some("admin")).await?;
+            if admins <= 1 {
+                return Err(ApiError::Conflict(
+                    "Cannot revoke the last admin API key.".to_string(),
+                ));
+            }
+        }
+
+        self.repo()?.revoke(id).await?;
+        Ok(())
+    }
+
+    fn repo(&self) -> Result<&ApiKeyRepository, ApiError> {
+        self.repo.as_deref().ok_or_else(|| {
+            ApiError::InternalServerError(
+                "API keys can only be managed with a database.".to_string(),
+            )
+        })
+    }
+
+    /// Returns a new key with its hex SHA-256 digest and display prefix.
+    fn generate_key() -> (String, String, String) {
+        let key = format!("hub_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let key_hash = hex::encode(Sha256::digest(key.as_bytes()));
+        let key_prefix = key[..KEY_PREFIX_LEN].to_string();
+        (key, key_hash, key_prefix)
+    }
+
+    fn parse_role(role: &str) -> Result<ApiKeyRole, ApiError> {
+        role.parse().map_err(ApiError::InternalServerError)
+    }
+
+    fn map_db_key_to_response(db_key: ApiKey) -> Result<ApiKeyResponse, ApiError> {
+        Ok(ApiKeyResponse {
+            id: db_key.id,
+            name: db_key.name,
+            role: Self::parse_role(&db_key.role)?,
+            key_prefix: db_key.key_prefix,
+            created_at: db_key.created_at,
+            updated_at: db_key.updated_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_static_keys() {
+        let keys = StaticApiKey::parse_list("first:admin, second:read_only,third,").unwrap();
+        let roles: Vec<_> = keys.iter().map(|k| k.role).collect();
+        assert_eq!(
+            roles,
+            vec![ApiKeyRole::Admin, ApiKeyRole::ReadOnly, ApiKeyRole::Admin]
+        );
+        assert_eq!(
+            keys[0].digest,
+            StaticApiKey::new("first", ApiKeyRole::Admin).digest
+        );
+
+        assert!(StaticApiKey::parse_list("key:owner").is_err());
+        assert!(StaticApiKey::parse_list(":admin").is_err());
+    }
+
+    #[test]
+    fn test_generated_keys_are_unique() {
+        let (key, key_hash, key_prefix) = ApiKeyService::generate_key();
+        assert!(key.starts_with(&key_prefix));
+        assert_eq!(key_hash, hex::encode(Sha256::digest(key.as_bytes())));
+        assert_ne!(key, ApiKeyService::generate_key().0);
+    }
+}
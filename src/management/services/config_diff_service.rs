@@ -0,0 +1,313 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use crate::config::lib::{EnvSubstitution, parse_config};
+use crate::config::redaction::RedactedGatewayConfig;
+use crate::management::{
+    dto::{ConfigDiffResponse, ConfigResourceDiffDto, FieldChangeDto, ResourceChangeDto},
+    errors::ApiError,
+    services::config_provider_service::ConfigProviderService,
+};
+use crate::types::GatewayConfig;
+
+/// Compares the config the gateway builds from the database with a YAML config, to check a
+/// migration from YAML to database mode before switching over.
+#[derive(Clone)]
+pub struct ConfigDiffService {
+    config_provider_service: Arc<ConfigProviderService>,
+}
+
+impl ConfigDiffService {
+    pub fn new(config_provider_service: Arc<ConfigProviderService>) -> Self {
+        Self {
+            config_provider_service,
+        }
+    }
+
+    /// Diffs the live database config against `yaml`, a document in the schema of
+    /// `config.yaml`. `${VAR_NAME}` references are compared as written, not resolved, so the
+    /// management server's environment never shows up in the response.
+    pub async fn diff_yaml(&self, yaml: &str) -> Result<ConfigDiffResponse, ApiError> {
+        let yaml_config = parse_config(yaml, EnvSubstitution::CheckSyntax)
+            .map_err(|e| ApiError::ValidationError(format!("Invalid YAML config: {e}")))?;
+        let database_config = self
+            .config_provider_service
+            .fetch_live_config()
+            .await
+            .map_err(|e| {
+                ApiError::InternalServerError(format!("Failed to load config from DB: {e}"))
+            })?;
+        diff_configs(&database_config, &yaml_config)
+    }
+}
+
+/// Compares two configs resource by resource, matched by key. `general` isn't compared, as
+/// database mode doesn't store it.
+pub fn diff_configs(
+    database: &GatewayConfig,
+    yaml: &GatewayConfig,
+) -> Result<ConfigDiffResponse, ApiError> {
+    let database = NormalizedConfig::new(database)?;
+    let yaml = NormalizedConfig::new(yaml)?;
+    let mut diff = ConfigDiffResponse {
+        identical: false,
+        providers: diff_resources(&database.providers, &yaml.providers),
+        models: diff_resources(&database.models, &yaml.models),
+        pipelines: diff_resources(&database.pipelines, &yaml.pipelines),
+        prompt_templates: diff_resources(&database.prompt_templates, &yaml.prompt_templates),
+    };
+    diff.identical = [
+        &diff.providers,
+        &diff.models,
+        &diff.pipelines,
+        &diff.prompt_templates,
+    ]
+    .iter()
+    .all(|resources| **resources == ConfigResourceDiffDto::default());
+    Ok(diff)
+}
+
+/// A resource as JSON, as configured and with its secrets masked. Changes are found on the
+/// former and reported from the latter, so a changed secret counts without being shown.
+struct Resource {
+    value: Value,
+    masked: Value,
+}
+
+type Resources = BTreeMap<String, Resource>;
+
+/// The resources of a config by key, in a form that compares equal whenever the gateway
+/// would behave the same: object keys are sorted and plugins are keyed by type, since a
+/// pipeline finds each of its plugins by type whatever their order.
+struct NormalizedConfig {
+    providers: Resources,
+    models: Resources,
+    pipelines: Resources,
+    prompt_templates: Resources,
+}
+
+impl NormalizedConfig {
+    fn new(config: &GatewayConfig) -> Result<Self, ApiError> {
+        let masked = RedactedGatewayConfig::from(config).into_inner();
+        let templates = |config: &GatewayConfig| {
+            config
+                .prompt_templates
+                .iter()
+                .map(|(name, template)| PromptTemplate { name, template })
+                .collect::<Vec<_>>()
+        };
+        Ok(Self {
+            providers: resources(&config.providers, &masked.providers, "key")?,
+            models: resources(&config.models, &masked.models, "key")?,
+            pipelines: resources(&config.pipelines, &masked.pipelines, "name")?,
+            prompt_templates: resources(&templates(config), &templates(&masked), "name")?,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct PromptTemplate<'a> {
+    name: &'a str,
+    template: &'a str,
+}
+
+fn resources<T: Serialize>(items: &[T], masked: &[T], key: &str) -> Result<Resources, ApiError> {
+    items
+        .iter()
+        .zip(masked)
+        .map(|(item, masked)| {
+            let value = normalize(serde_json::to_value(item)?);
+            let name = value[key].as_str().unwrap_or_default().to_string();
+            let masked = normalize(serde_json::to_value(masked)?);
+            Ok((name, Resource { value, masked }))
+        })
+        .collect()
+}
+
+/// Keys a pipeline's plugins by type. A type used more than once gets its position in the
+/// list of that type appended, e.g. `logging#2`.
+fn normalize(mut value: Value) -> Value {
+    if let Some(Value::Array(plugins)) = value.get_mut("plugins") {
+        let mut keyed = Map::new();
+        for plugin in std::mem::take(plugins) {
+            let plugin_type = match &plugin {
+                Value::Object(object) => object.keys().next().cloned().unwrap_or_default(),
+                Value::String(name) => name.clone(),
+                other => other.to_string(),
+            };
+            let mut key = plugin_type.clone();
+            let mut position = 1;
+            while keyed.contains_key(&key) {
+                position += 1;
+                key = format!("{plugin_type}#{position}");
+            }
+            keyed.insert(key, plugin);
+        }
+        value["plugins"] = Value::Object(keyed);
+    }
+    value
+}
+
+fn diff_resources(database: &Resources, yaml: &Resources) -> ConfigResourceDiffDto {
+    let mut diff = ConfigResourceDiffDto::default();
+    let names: BTreeSet<&String> = database.keys().chain(yaml.keys()).collect();
+    for name in names {
+        match (database.get(name), yaml.get(name)) {
+            (Some(_), None) => diff.added.push(name.clone()),
+            (None, Some(_)) => diff.removed.push(name.clone()),
+            (Some(database), Some(yaml)) => {
+                let mut fields = Vec::new();
+                diff_values(database, yaml, &mut Vec::new(), &mut fields);
+                if !fields.is_empty() {
+                    diff.changed.push(ResourceChangeDto {
+                        name: name.clone(),
+                        fields,
+                    });
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    diff
+}
+
+/// Collects the fields under `path` that differ. Objects are compared key by key, any
+/// other values as a whole.
+fn diff_values(
+    database: &Resource,
+    yaml: &Resource,
+    path: &mut Vec<String>,
+    fields: &mut Vec<FieldChangeDto>,
+) {
+    let (Some(database_value), Some(yaml_value)) =
+        (value_at(&database.value, path), value_at(&yaml.value, path))
+    else {
+        fields.push(field_change(database, yaml, path));
+        return;
+    };
+    if database_value == yaml_value {
+        return;
+    }
+    match (database_value, yaml_value) {
+        (Value::Object(database_object), Value::Object(yaml_object)) => {
+            let keys: BTreeSet<&String> =
+                database_object.keys().chain(yaml_object.keys()).collect();
+            for key in keys {
+                path.push(key.clone());
+                diff_values(database, yaml, path, fields);
+                path.pop();
+            }
+        }
+        _ => fields.push(field_change(database, yaml, path)),
+    }
+}
+
+fn value_at<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| value.get(key))
+}
+
+fn field_change(database: &Resource, yaml: &Resource, path: &[String]) -> FieldChangeDto {
+    FieldChangeDto {
+        path: path.join("."),
+        database: value_at(&database.masked, path).cloned(),
+        yaml: value_at(&yaml.masked, path).cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config(value: Value) -> GatewayConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn openai(api_key_secret: Option<&str>) -> Value {
+        let mut provider = json!({"key": "openai", "type": "openai", "api_key": ""});
+        if let Some(variable_name) = api_key_secret {
+            let secret = json!({"type": "environment", "variable_name": variable_name});
+            provider["api_key_secret"] = json!(secret.to_string());
+        }
+        provider
+    }
+
+    fn pipeline(plugins: Value) -> Value {
+        json!({"name": "default", "type": "chat", "plugins": plugins})
+    }
+
+    #[test]
+    fn test_identical_configs() {
+        let config = config(json!({
+            "providers": [openai(Some("OPENAI_API_KEY"))],
+            "models": [{"key": "gpt-4o", "type": "gpt-4o", "provider": "openai"}],
+            "pipelines": [pipeline(json!([{"model-router": {"models": ["gpt-4o"]}}]))],
+        }));
+        let diff = diff_configs(&config, &config.clone()).unwrap();
+        assert!(diff.identical);
+        assert_eq!(diff.pipelines, ConfigResourceDiffDto::default());
+    }
+
+    #[test]
+    fn test_plugin_order_is_ignored() {
+        let router = json!({"model-router": {"models": ["gpt-4o"]}});
+        let logging = json!({"logging": {"level": "info"}});
+        let database = config(json!({"pipelines": [pipeline(json!([router, logging]))]}));
+        let yaml = config(json!({"pipelines": [pipeline(json!([logging, router]))]}));
+
+        assert!(diff_configs(&database, &yaml).unwrap().identical);
+    }
+
+    #[test]
+    fn test_changed_secret_reference_is_masked() {
+        let database = config(json!({"providers": [openai(Some("OPENAI_API_KEY"))]}));
+        let yaml = config(json!({"providers": [openai(Some("OPENAI_KEY"))]}));
+
+        let diff = diff_configs(&database, &yaml).unwrap();
+        assert!(!diff.identical);
+        assert_eq!(
+            diff.providers.changed,
+            vec![ResourceChangeDto {
+                name: "openai".to_string(),
+                fields: vec![FieldChangeDto {
+                    path: "api_key_secret".to_string(),
+                    database: Some(json!("***")),
+                    yaml: Some(json!("***")),
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_field_changes_added_and_removed_resources() {
+        let database = config(json!({
+            "models": [
+                {"key": "gpt-4o", "type": "gpt-4o", "provider": "openai"},
+                {"key": "o3", "type": "o3", "provider": "openai"}
+            ],
+            "pipelines": [pipeline(json!([{"model-router": {"models": ["gpt-4o", "o3"]}}]))],
+        }));
+        let yaml = config(json!({
+            "models": [
+                {"key": "gpt-4o", "type": "gpt-4o-2024-08-06", "provider": "openai"},
+                {"key": "claude", "type": "claude-sonnet-4", "provider": "anthropic"}
+            ],
+            "pipelines": [pipeline(json!([{"model-router": {"models": ["gpt-4o"]}}]))],
+        }));
+
+        let diff = diff_configs(&database, &yaml).unwrap();
+        assert_eq!(diff.models.added, vec!["o3".to_string()]);
+        assert_eq!(diff.models.removed, vec!["claude".to_string()]);
+        assert_eq!(diff.models.changed[0].fields[0].path, "type");
+        assert_eq!(
+            diff.pipelines.changed[0].fields,
+            vec![FieldChangeDto {
+                path: "plugins.model-router.models".to_string(),
+                database: Some(json!(["gpt-4o", "o3"])),
+                yaml: Some(json!(["gpt-4o"])),
+            }]
+        );
+    }
+}
@@ -1,4 +1,23 @@
-use crate::types::{GatewayConfig, ModelConfig, Pipeline, PipelineType, PluginConfig, Provider};
+use crate::ai_models::params::config_value_to_param;
+use crate::config::constants::hub_environment;
+use crate::config::hash::{calculate_config_hash, format_config_hash};
+use crate::config::names::same_name;
+use crate::management::db::repositories::config_snapshot_repository::ConfigWatermark;
+use crate::providers::allowed_models::ALLOWED_MODEL_PREFIXES_PARAM;
+use crate::providers::api_keys::{
+    API_KEY_FILE_PARAM, API_KEY_SECONDARY_PARAM, API_KEY_SECRET_PARAM, UNRESOLVED_SECRETS_PARAM,
+};
+use crate::providers::azure::entra::{
+    AUTH_TYPE_PARAM, AUTHORITY_HOST_PARAM, CLIENT_ID_PARAM, CLIENT_SECRET_PARAM, TENANT_ID_PARAM,
+};
+use crate::providers::http_client::{
+    CA_CERT_PATH_PARAM, CA_CERT_PEM_PARAM, CLIENT_CERT_PATH_PARAM, CLIENT_KEY_PATH_PARAM,
+    DANGER_ACCEPT_INVALID_CERTS_PARAM, NO_PROXY_PARAM, PROXY_URL_PARAM,
+};
+use crate::types::{
+    GatewayConfig, ModelConfig, Pipeline, PipelineType, PluginConfig, Provider, SampleRate,
+    UsdAmount,
+};
 use anyhow::{Result, anyhow};
 use log::{error, warn};
 use serde_json::Value as JsonValue;
@@ -9,33 +28,21 @@ use uuid::Uuid;
 
 use super::{
     super::dto::{
-        LoggingConfigDto, ModelDefinitionResponse, ModelRouterConfigDto, PipelinePluginConfigDto,
-        PipelineResponseDto,
+        BudgetConfigDto, DegradedMode, LoggingConfigDto, MetadataConfigDto,
+        ModelDefinitionResponse, ModelRouterConfigDto, ModelRouterStrategyDto,
+        ParameterPolicyConfigDto, PassthroughHeaders, PipelinePluginConfigDto, PipelineResponseDto,
+        PriorityConfigDto,
         ProviderConfig, /*, OpenAIProviderConfig, AzureProviderConfig, BedrockProviderConfig*/
-        ProviderResponse, TracingConfigDto,
+        ProviderResponse, ResponseNormalizationConfigDto, SecretObject, StreamOptionsConfigDto,
+        ToolLimits, TracingConfigDto,
     },
+    config_snapshot_service::ConfigSnapshotService,
     model_definition_service::ModelDefinitionService,
     pipeline_service::PipelineService,
     provider_service::ProviderService,
     secret_resolver::SecretResolver,
 };
 
-// Helper function to convert serde_json::Value to String
-// Handles simple types and serializes complex types to a JSON string.
-fn convert_json_value_to_string(json_value: &JsonValue) -> String {
-    match json_value {
-        JsonValue::String(s) => s.clone(),
-        JsonValue::Number(n) => n.to_string(),
-        JsonValue::Bool(b) => b.to_string(),
-        JsonValue::Null => String::new(), // Or a specific string like "null"
-        JsonValue::Array(_) | JsonValue::Object(_) => serde_json::to_string(json_value)
-            .unwrap_or_else(|e| {
-                warn!("Failed to serialize complex JsonValue to string: {e}. Using empty string.");
-                String::new()
-            }),
-    }
-}
-
 // Helper function to get JsonValue type as a string for logging
 fn get_json_value_type_as_str(value: &JsonValue) -> &str {
     match value {
@@ -53,6 +60,8 @@ pub struct ConfigProviderService {
     model_definition_service: Arc<ModelDefinitionService>,
     pipeline_service: Arc<PipelineService>,
     secret_resolver: SecretResolver,
+    environment: Option<String>,
+    snapshots: Option<Arc<ConfigSnapshotService>>,
 }
 
 impl ConfigProviderService {
@@ -66,7 +75,63 @@ impl ConfigProviderService {
             model_definition_service,
             pipeline_service,
             secret_resolver: SecretResolver::new(),
+            environment: hub_environment(),
+            snapshots: None,
+        }
+    }
+
+    /// Overrides the environment read from `HUB_ENVIRONMENT`.
+    pub fn with_environment(mut self, environment: Option<String>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// Enables [`Self::record_applied_config`], storing snapshots through this service.
+    pub fn with_snapshots(mut self, snapshots: Arc<ConfigSnapshotService>) -> Self {
+        self.snapshots = Some(snapshots);
+        self
+    }
+
+    /// Stores a snapshot of the database state behind the config the gateway just applied.
+    /// The state is read before and after rebuilding the config, and nothing is stored if it
+    /// changed in between; the config applied next is snapshotted instead.
+    /// Returns whether a snapshot was stored.
+    pub async fn record_applied_config(&self, config_hash: &str) -> Result<bool> {
+        let Some(snapshots) = &self.snapshots else {
+            return Ok(false);
+        };
+        let state_before = snapshots
+            .capture_state()
+            .await
+            .map_err(|e| anyhow!("Failed to read config state from DB: {e:?}"))?;
+        let config = self.fetch_live_config().await?;
+        let state_after = snapshots
+            .capture_state()
+            .await
+            .map_err(|e| anyhow!("Failed to read config state from DB: {e:?}"))?;
+
+        let rebuilt_hash = format_config_hash(calculate_config_hash(&config));
+        if state_before != state_after || rebuilt_hash != config_hash {
+            debug!("Config changed since hash {config_hash} was applied; skipping its snapshot.");
+            return Ok(false);
         }
+        snapshots
+            .record(config_hash, &state_after)
+            .await
+            .map_err(|e| anyhow!("Failed to store config snapshot: {e:?}"))
+    }
+
+    /// The watermark of the config tables, to tell whether [`Self::fetch_live_config`] could
+    /// return anything new. `None` without a snapshot service to read it through.
+    pub async fn config_watermark(&self) -> Result<Option<ConfigWatermark>> {
+        let Some(snapshots) = &self.snapshots else {
+            return Ok(None);
+        };
+        snapshots
+            .watermark()
+            .await
+            .map(Some)
+            .map_err(|e| anyhow!("Failed to read config watermark from DB: {e:?}"))
     }
 
     pub async fn fetch_live_config(&self) -> Result<GatewayConfig> {
@@ -115,7 +180,8 @@ impl ConfigProviderService {
             .list_pipelines()
             .await
             .map_err(|e| anyhow!("Failed to fetch pipelines from DB: {e:?}"))?;
-        for pl_dto in db_pipelines.into_iter().filter(|pl| pl.enabled) {
+        let db_pipelines = db_pipelines.into_iter().filter(|pl| pl.enabled).collect();
+        for pl_dto in pipelines_for_environment(db_pipelines, self.environment.as_deref()) {
             match self.transform_pipeline_dto(pl_dto).await {
                 Ok(core_pipeline) => gateway_config.pipelines.push(core_pipeline),
                 Err(e) => error!("Failed to transform pipeline DTO: {e:?}. Skipping."),
@@ -126,8 +192,94 @@ impl ConfigProviderService {
         Ok(gateway_config)
     }
 
+    /// Resolves a provider's API key. Only literal keys are resolved here. Keys in files
+    /// are left to the provider, which reads them lazily and picks up rotated contents,
+    /// and other references are resolved by the provider on first use, so a secret that
+    /// can't be resolved fails only that provider's requests.
+    fn resolve_api_key(
+        &self,
+        secret: &SecretObject,
+        params: &mut HashMap<String, String>,
+    ) -> Result<Option<String>> {
+        match secret {
+            SecretObject::Literal { .. } => Ok(Some(self.secret_resolver.resolve(secret)?)),
+            SecretObject::File { path } => {
+                params.insert(API_KEY_FILE_PARAM.to_string(), path.clone());
+                Ok(None)
+            }
+            SecretObject::Environment { .. } | SecretObject::Kubernetes { .. } => {
+                params.insert(
+                    API_KEY_SECRET_PARAM.to_string(),
+                    serde_json::to_string(secret)?,
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Resolves a provider secret other than its API key. A failure is recorded in
+    /// `unresolved` rather than failing the provider, which then answers 503 until a later
+    /// poll resolves the secret.
+    async fn resolve_provider_secret(
+        &self,
+        name: &str,
+        secret: &SecretObject,
+        unresolved: &mut Vec<String>,
+    ) -> Option<String> {
+        match self.secret_resolver.resolve_secret(secret).await {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Failed to resolve provider secret '{name}': {e}");
+                unresolved.push(format!("{name}: {e}"));
+                None
+            }
+        }
+    }
+
     async fn transform_provider_dto(&self, dto: ProviderResponse) -> Result<Provider> {
         let mut params = HashMap::new();
+        let mut unresolved = Vec::new();
+        let maintenance_windows = dto.config.maintenance_windows().to_vec();
+        let (proxy_url, no_proxy) = dto.config.proxy_settings();
+        if let Some(proxy_url) = proxy_url {
+            if let Some(resolved_proxy_url) = self
+                .resolve_provider_secret(PROXY_URL_PARAM, proxy_url, &mut unresolved)
+                .await
+            {
+                params.insert(PROXY_URL_PARAM.to_string(), resolved_proxy_url);
+            }
+        }
+        if let Some(no_proxy) = no_proxy {
+            params.insert(NO_PROXY_PARAM.to_string(), no_proxy.clone());
+        }
+        if let Some(tls) = dto.config.tls_settings() {
+            if let Some(ca_cert) = &tls.ca_cert {
+                if let Some(resolved_ca_cert) = self
+                    .resolve_provider_secret(CA_CERT_PEM_PARAM, ca_cert, &mut unresolved)
+                    .await
+                {
+                    params.insert(CA_CERT_PEM_PARAM.to_string(), resolved_ca_cert);
+                }
+            }
+            if let Some(ca_cert_path) = &tls.ca_cert_path {
+                params.insert(CA_CERT_PATH_PARAM.to_string(), ca_cert_path.clone());
+            }
+            if let Some(client_cert_path) = &tls.client_cert_path {
+                params.insert(CLIENT_CERT_PATH_PARAM.to_string(), client_cert_path.clone());
+            }
+            if let Some(client_key_path) = &tls.client_key_path {
+                params.insert(CLIENT_KEY_PATH_PARAM.to_string(), client_key_path.clone());
+            }
+            if let Some(accept_invalid) = tls.danger_accept_invalid_certs {
+                params.insert(
+                    DANGER_ACCEPT_INVALID_CERTS_PARAM.to_string(),
+                    accept_invalid.to_string(),
+                );
+            }
+        }
+        if let Some(prefixes) = dto.config.allowed_model_prefixes() {
+            params.insert(ALLOWED_MODEL_PREFIXES_PARAM.to_string(), prefixes.join(","));
+        }
         let api_key_from_dto = match dto.config {
             ProviderConfig::OpenAI(c) => {
                 if let Some(org_id) = c.organization_id {
@@ -136,7 +288,19 @@ impl ConfigProviderService {
                 if let Some(base_url) = c.base_url {
                     params.insert("base_url".to_string(), base_url);
                 }
-                Some(self.secret_resolver.resolve_secret(&c.api_key).await?)
+                if let Some(secondary) = &c.api_key_secondary {
+                    if let Some(secondary) = self
+                        .resolve_provider_secret(
+                            API_KEY_SECONDARY_PARAM,
+                            secondary,
+                            &mut unresolved,
+                        )
+                        .await
+                    {
+                        params.insert(API_KEY_SECONDARY_PARAM.to_string(), secondary);
+                    }
+                }
+                self.resolve_api_key(&c.api_key, &mut params)?
             }
             ProviderConfig::Azure(c) => {
                 params.insert("resource_name".to_string(), c.resource_name);
@@ -144,24 +308,49 @@ impl ConfigProviderService {
                 if let Some(base_url) = c.base_url {
                     params.insert("base_url".to_string(), base_url);
                 }
-                Some(self.secret_resolver.resolve_secret(&c.api_key).await?)
-            }
-            ProviderConfig::Anthropic(c) => {
-                Some(self.secret_resolver.resolve_secret(&c.api_key).await?)
+                if let Some(auth_type) = c.auth_type {
+                    params.insert(AUTH_TYPE_PARAM.to_string(), auth_type.to_string());
+                }
+                for (param, secret) in [
+                    (TENANT_ID_PARAM, &c.tenant_id),
+                    (CLIENT_ID_PARAM, &c.client_id),
+                    (CLIENT_SECRET_PARAM, &c.client_secret),
+                ] {
+                    let Some(secret) = secret else {
+                        continue;
+                    };
+                    if let Some(value) = self
+                        .resolve_provider_secret(param, secret, &mut unresolved)
+                        .await
+                    {
+                        params.insert(param.to_string(), value);
+                    }
+                }
+                if let Some(authority_host) = c.authority_host {
+                    params.insert(AUTHORITY_HOST_PARAM.to_string(), authority_host);
+                }
+                match &c.api_key {
+                    Some(api_key) => self.resolve_api_key(api_key, &mut params)?,
+                    None => None,
+                }
             }
+            ProviderConfig::Anthropic(c) => self.resolve_api_key(&c.api_key, &mut params)?,
             ProviderConfig::Bedrock(c) => {
                 params.insert("region".to_string(), c.region.clone());
-                if let Some(access_key) = &c.aws_access_key_id {
-                    let resolved_key = self.secret_resolver.resolve_secret(access_key).await?;
-                    params.insert("AWS_ACCESS_KEY_ID".to_string(), resolved_key);
-                }
-                if let Some(secret) = &c.aws_secret_access_key {
-                    let resolved_secret = self.secret_resolver.resolve_secret(secret).await?;
-                    params.insert("AWS_SECRET_ACCESS_KEY".to_string(), resolved_secret);
-                }
-                if let Some(token) = &c.aws_session_token {
-                    let resolved_token = self.secret_resolver.resolve_secret(token).await?;
-                    params.insert("AWS_SESSION_TOKEN".to_string(), resolved_token);
+                for (param, secret) in [
+                    ("AWS_ACCESS_KEY_ID", &c.aws_access_key_id),
+                    ("AWS_SECRET_ACCESS_KEY", &c.aws_secret_access_key),
+                    ("AWS_SESSION_TOKEN", &c.aws_session_token),
+                ] {
+                    let Some(secret) = secret else {
+                        continue;
+                    };
+                    if let Some(value) = self
+                        .resolve_provider_secret(param, secret, &mut unresolved)
+                        .await
+                    {
+                        params.insert(param.to_string(), value);
+                    }
                 }
                 if let Some(use_iam_role) = c.use_iam_role {
                     params.insert("use_iam_role".to_string(), use_iam_role.to_string());
@@ -181,18 +370,25 @@ impl ConfigProviderService {
                 if let Some(credentials_path) = c.credentials_path {
                     params.insert("credentials_path".to_string(), credentials_path);
                 }
-                if let Some(api_key) = &c.api_key {
-                    Some(self.secret_resolver.resolve_secret(api_key).await?)
-                } else {
-                    None
+                match &c.api_key {
+                    Some(api_key) => self.resolve_api_key(api_key, &mut params)?,
+                    None => None,
                 }
             }
+            ProviderConfig::Mock(c) => {
+                params.extend(c.params());
+                None
+            }
         };
+        if !unresolved.is_empty() {
+            params.insert(UNRESOLVED_SECRETS_PARAM.to_string(), unresolved.join("; "));
+        }
 
         Ok(Provider {
             key: dto.name,
             r#type: dto.provider_type,
             api_key: api_key_from_dto.unwrap_or_default(),
+            maintenance_windows,
             params,
         })
     }
@@ -215,8 +411,18 @@ impl ConfigProviderService {
         let mut params = HashMap::new();
         match dto.config_details {
             JsonValue::Object(map) => {
+                // Scalars become string params; nested values are rejected at write time.
                 for (k, v) in map {
-                    params.insert(k, convert_json_value_to_string(&v));
+                    match config_value_to_param(&v) {
+                        Some(value) => {
+                            params.insert(k, value);
+                        }
+                        None if v.is_null() => {}
+                        None => warn!(
+                            "Model '{}' config_details.{} is not a scalar; skipping it.",
+                            dto.key, k
+                        ),
+                    }
                 }
             }
             JsonValue::Null => {}
@@ -234,6 +440,8 @@ impl ConfigProviderService {
             r#type: dto.model_type,
             provider: provider_key,
             params,
+            enabled: true,
+            deprecation: Default::default(),
         })
     }
 
@@ -260,6 +468,7 @@ impl ConfigProviderService {
             name: dto.name,
             r#type: core_pipeline_type,
             plugins: core_plugins,
+            store_artifacts: false,
         })
     }
 
@@ -275,7 +484,14 @@ impl ConfigProviderService {
                     })?;
 
                 let model_keys = mr_config.models.into_iter().map(|m| m.key).collect();
-                Ok(PluginConfig::ModelRouter { models: model_keys })
+                Ok(PluginConfig::ModelRouter {
+                    models: model_keys,
+                    allow_dynamic_models: mr_config.allow_dynamic_models.unwrap_or_default(),
+                    adaptive: (mr_config.strategy == Some(ModelRouterStrategyDto::Adaptive))
+                        .then(|| mr_config.adaptive.unwrap_or_default()),
+                    race: (mr_config.strategy == Some(ModelRouterStrategyDto::Race))
+                        .then(|| mr_config.race.unwrap_or_default()),
+                })
             }
             super::super::dto::PluginType::Logging => {
                 let logging_config: LoggingConfigDto = serde_json::from_value(dto.config_data)
@@ -288,6 +504,10 @@ impl ConfigProviderService {
 
                 Ok(PluginConfig::Logging {
                     level: logging_config.level,
+                    sample_rate: logging_config
+                        .sample_rate
+                        .map(SampleRate)
+                        .unwrap_or_default(),
                 })
             }
             super::super::dto::PluginType::Tracing => {
@@ -309,15 +529,161 @@ impl ConfigProviderService {
                     api_key: resolved_api_key,
                 })
             }
+            super::super::dto::PluginType::Metadata => {
+                let metadata_config: MetadataConfigDto = serde_json::from_value(dto.config_data)
+                    .map_err(|e| {
+                        anyhow!(
+                            "Failed to deserialize MetadataConfigDto for plugin type '{:?}': {e}",
+                            dto.plugin_type
+                        )
+                    })?;
+
+                Ok(PluginConfig::Metadata {
+                    values: metadata_config.values,
+                })
+            }
+            super::super::dto::PluginType::Budget => {
+                let budget_config: BudgetConfigDto = serde_json::from_value(dto.config_data)
+                    .map_err(|e| {
+                        anyhow!(
+                            "Failed to deserialize BudgetConfigDto for plugin type '{:?}': {e}",
+                            dto.plugin_type
+                        )
+                    })?;
+
+                Ok(PluginConfig::Budget {
+                    limit_usd: UsdAmount(budget_config.limit_usd),
+                    window: budget_config.window.unwrap_or_default(),
+                    warn_at_percent: budget_config.warn_at_percent.unwrap_or(80),
+                })
+            }
+            super::super::dto::PluginType::Priority => {
+                let priority_config: PriorityConfigDto = serde_json::from_value(dto.config_data)
+                    .map_err(|e| {
+                        anyhow!(
+                            "Failed to deserialize PriorityConfigDto for plugin type '{:?}': {e}",
+                            dto.plugin_type
+                        )
+                    })?;
+
+                Ok(PluginConfig::Priority {
+                    default: priority_config.default,
+                })
+            }
+            super::super::dto::PluginType::ParameterPolicy => {
+                let policy_config: ParameterPolicyConfigDto =
+                    serde_json::from_value(dto.config_data).map_err(|e| {
+                        anyhow!(
+                            "Failed to deserialize ParameterPolicyConfigDto for plugin type '{:?}': {e}",
+                            dto.plugin_type
+                        )
+                    })?;
+
+                Ok(PluginConfig::ParameterPolicy {
+                    mode: policy_config.mode.unwrap_or_default(),
+                    rules: policy_config.rules,
+                    allow_extra_body: policy_config.allow_extra_body,
+                    extra_body_keys: policy_config.extra_body_keys,
+                })
+            }
+            super::super::dto::PluginType::ResponseNormalization => {
+                let normalization_config: ResponseNormalizationConfigDto =
+                    serde_json::from_value(dto.config_data).map_err(|e| {
+                        anyhow!(
+                            "Failed to deserialize ResponseNormalizationConfigDto for plugin type '{:?}': {e}",
+                            dto.plugin_type
+                        )
+                    })?;
+
+                Ok(PluginConfig::ResponseNormalization {
+                    passthrough_extra_fields: normalization_config
+                        .passthrough_extra_fields
+                        .unwrap_or_default(),
+                })
+            }
+            super::super::dto::PluginType::StreamOptions => {
+                let stream_options_config: StreamOptionsConfigDto = serde_json::from_value(
+                    dto.config_data,
+                )
+                .map_err(|e| {
+                    anyhow!(
+                        "Failed to deserialize StreamOptionsConfigDto for plugin type '{:?}': {e}",
+                        dto.plugin_type
+                    )
+                })?;
+
+                Ok(PluginConfig::StreamOptions {
+                    aggregate_tool_calls: stream_options_config
+                        .aggregate_tool_calls
+                        .unwrap_or_default(),
+                })
+            }
+            super::super::dto::PluginType::DegradedMode => {
+                let degraded_mode: DegradedMode =
+                    serde_json::from_value(dto.config_data).map_err(|e| {
+                        anyhow!(
+                            "Failed to deserialize DegradedMode for plugin type '{:?}': {e}",
+                            dto.plugin_type
+                        )
+                    })?;
+
+                Ok(PluginConfig::DegradedMode(degraded_mode))
+            }
+            super::super::dto::PluginType::PassthroughHeaders => {
+                let passthrough: PassthroughHeaders = serde_json::from_value(dto.config_data)
+                    .map_err(|e| {
+                        anyhow!(
+                            "Failed to deserialize PassthroughHeaders for plugin type '{:?}': {e}",
+                            dto.plugin_type
+                        )
+                    })?;
+
+                Ok(PluginConfig::PassthroughHeaders(passthrough))
+            }
+            super::super::dto::PluginType::ToolLimits => {
+                let tool_limits: ToolLimits =
+                    serde_json::from_value(dto.config_data).map_err(|e| {
+                        anyhow!(
+                            "Failed to deserialize ToolLimits for plugin type '{:?}': {e}",
+                            dto.plugin_type
+                        )
+                    })?;
+
+                Ok(PluginConfig::ToolLimits(tool_limits))
+            }
+        }
+    }
+}
+
+/// Keeps the pipelines tagged with `environment` and the untagged ones. When both exist
+/// for a name, the tagged pipeline wins.
+fn pipelines_for_environment(
+    pipelines: Vec<PipelineResponseDto>,
+    environment: Option<&str>,
+) -> Vec<PipelineResponseDto> {
+    let mut selected: Vec<PipelineResponseDto> = Vec::new();
+    for pipeline in pipelines {
+        if pipeline.environment.is_some() && pipeline.environment.as_deref() != environment {
+            continue;
+        }
+        match selected
+            .iter_mut()
+            .find(|p| same_name(&p.name, &pipeline.name))
+        {
+            Some(untagged) if pipeline.environment.is_some() => *untagged = pipeline,
+            Some(_) => {}
+            None => selected.push(pipeline),
         }
     }
+    selected
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::management::dto::{
-        LoggingConfigDto, PipelinePluginConfigDto, PluginType, SecretObject, TracingConfigDto,
+        LoggingConfigDto, OpenAIProviderConfig, PipelinePluginConfigDto, PipelineResponseDto,
+        PluginType, ProviderTlsConfig, SecretObject, TracingConfigDto,
     };
     use serde_json::json;
 
@@ -367,6 +733,7 @@ mod tests {
             config_data: json!({"level": "debug"}),
             enabled: true,
             order_in_pipeline: 1,
+            id: None,
         };
 
         let logging_config: LoggingConfigDto =
@@ -385,6 +752,7 @@ mod tests {
             }),
             enabled: true,
             order_in_pipeline: 2,
+            id: None,
         };
 
         let tracing_config: TracingConfigDto =
@@ -398,4 +766,101 @@ mod tests {
             SecretObject::environment("TRACE_KEY".to_string())
         );
     }
+
+    #[test]
+    fn test_provider_tls_config_deserialization() {
+        let json_data = json!({
+            "api_key": {"type": "literal", "value": "sk-test"},
+            "tls": {
+                "ca_cert": {"type": "environment", "variable_name": "INTERNAL_CA_PEM"},
+                "client_cert_path": "/certs/client.pem",
+                "client_key_path": "/certs/client-key.pem",
+                "danger_accept_invalid_certs": false
+            }
+        });
+        let config: OpenAIProviderConfig = serde_json::from_value(json_data).unwrap();
+        assert_eq!(
+            config.tls,
+            Some(ProviderTlsConfig {
+                ca_cert: Some(SecretObject::environment("INTERNAL_CA_PEM".to_string())),
+                ca_cert_path: None,
+                client_cert_path: Some("/certs/client.pem".to_string()),
+                client_key_path: Some("/certs/client-key.pem".to_string()),
+                danger_accept_invalid_certs: Some(false),
+            })
+        );
+
+        let invalid: Result<ProviderTlsConfig, _> =
+            serde_json::from_value(json!({"client_cert": "/certs/client.pem"}));
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_provider_maintenance_windows_deserialization() {
+        let json_data = json!({
+            "api_key": {"type": "literal", "value": "sk-test"},
+            "maintenance_windows": [
+                {"start": "2025-03-01T22:00:00+01:00", "end": "2025-03-02T02:00:00+01:00"},
+                {"schedule": "0 22 * * SAT", "duration_minutes": 240, "timezone": "+01:00"}
+            ]
+        });
+        let config = ProviderConfig::OpenAI(serde_json::from_value(json_data).unwrap());
+        let windows = config.maintenance_windows();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].end.as_deref(), Some("2025-03-02T02:00:00+01:00"));
+        assert_eq!(windows[1].duration_minutes, Some(240));
+    }
+
+    fn pipeline(name: &str, environment: Option<&str>) -> PipelineResponseDto {
+        PipelineResponseDto {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            pipeline_type: "chat".to_string(),
+            description: None,
+            plugins: vec![],
+            enabled: true,
+            environment: environment.map(str::to_string),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_pipelines_are_filtered_by_environment() {
+        let pipelines = || {
+            vec![
+                pipeline("default", None),
+                pipeline("default", Some("prod")),
+                pipeline("canary", Some("staging")),
+                pipeline("shared", None),
+            ]
+        };
+        let loaded = |environment| {
+            pipelines_for_environment(pipelines(), environment)
+                .into_iter()
+                .map(|p| (p.name, p.environment))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            loaded(Some("prod")),
+            vec![
+                ("default".to_string(), Some("prod".to_string())),
+                ("shared".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            loaded(Some("staging")),
+            vec![
+                ("default".to_string(), None),
+                ("canary".to_string(), Some("staging".to_string())),
+                ("shared".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            loaded(None),
+            vec![("default".to_string(), None), ("shared".to_string(), None)]
+        );
+    }
 }
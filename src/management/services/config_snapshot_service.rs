@@ -0,0 +1,230 @@
+use sqlx::types::Uuid;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::config::constants::config_snapshot_retention;
+use crate::management::{
+    db::{
+        DbPools,
+        models::ConfigSnapshot,
+        repositories::config_snapshot_repository::{
+            ConfigSnapshotRepository, ConfigState, ConfigWatermark, SnapshotPipeline,
+        },
+    },
+    dto::{ConfigSnapshotDiffDto, ConfigSnapshotResponse, ResourceDiffDto},
+    errors::ApiError,
+};
+
+#[derive(Clone)]
+pub struct ConfigSnapshotService {
+    repo: Arc<ConfigSnapshotRepository>,
+    retention: i64,
+}
+
+impl ConfigSnapshotService {
+    pub fn new(pools: impl Into<DbPools>) -> Self {
+        Self {
+            repo: Arc::new(ConfigSnapshotRepository::new(pools)),
+            retention: config_snapshot_retention(),
+        }
+    }
+
+    /// Overrides the number of snapshots kept, read from `CONFIG_SNAPSHOT_RETENTION`.
+    pub fn with_retention(mut self, retention: i64) -> Self {
+        self.retention = retention.max(1);
+        self
+    }
+
+    pub async fn capture_state(&self) -> Result<ConfigState, ApiError> {
+        Ok(self.repo.capture_state().await?)
+    }
+
+    pub async fn watermark(&self) -> Result<ConfigWatermark, ApiError> {
+        Ok(self.repo.watermark().await?)
+    }
+
+    /// Puts the providers, model definitions and pipelines of `state` back in place.
+    pub async fn restore(&self, state: &ConfigState) -> Result<(), ApiError> {
+        Ok(self.repo.restore_state(state).await?)
+    }
+
+    /// Stores `state` as the source of the config with this hash. Returns `false` if the
+    /// newest snapshot already has the hash, e.g. because another replica stored it.
+    pub async fn record(&self, config_hash: &str, state: &ConfigState) -> Result<bool, ApiError> {
+        let snapshot = self
+            .repo
+            .create(config_hash, serde_json::to_value(state)?, self.retention)
+            .await?;
+        Ok(snapshot.is_some())
+    }
+
+    /// Lists snapshots, newest first, each with its differences from the live state.
+    pub async fn list_snapshots(&self) -> Result<Vec<ConfigSnapshotResponse>, ApiError> {
+        let live = self.capture_state().await?;
+        self.repo
+            .list()
+            .await?
+            .into_iter()
+            .map(|snapshot| Self::map_db_snapshot_to_response(snapshot, &live))
+            .collect()
+    }
+
+    /// Restores the providers, model definitions and pipelines of a snapshot. The gateway
+    /// picks the restored config up on its next poll.
+    pub async fn rollback(&self, id: Uuid) -> Result<ConfigSnapshotResponse, ApiError> {
+        let snapshot = self.repo.find_by_id(id).await?.ok_or_else(|| {
+            ApiError::NotFound(format!("Config snapshot with ID {id} not found."))
+        })?;
+        let state: ConfigState = serde_json::from_value(snapshot.config_state.clone())?;
+        let live = self.capture_state().await?;
+        let response = Self::map_db_snapshot_to_response(snapshot, &live)?;
+        self.repo.restore_state(&state).await?;
+        Ok(response)
+    }
+
+    fn map_db_snapshot_to_response(
+        snapshot: ConfigSnapshot,
+        live: &ConfigState,
+    ) -> Result<ConfigSnapshotResponse, ApiError> {
+        let state: ConfigState = serde_json::from_value(snapshot.config_state)?;
+        Ok(ConfigSnapshotResponse {
+            id: snapshot.id,
+            config_hash: snapshot.config_hash,
+            created_at: snapshot.created_at,
+            diff: diff_states(&state, live),
+        })
+    }
+}
+
+/// Compares a snapshot with the live state. Resources are matched by ID, so a renamed
+/// resource shows up as changed rather than as added and removed.
+pub fn diff_states(snapshot: &ConfigState, live: &ConfigState) -> ConfigSnapshotDiffDto {
+    ConfigSnapshotDiffDto {
+        providers: diff_resources(
+            &snapshot.providers,
+            &live.providers,
+            |p| p.id,
+            |p| p.name.clone(),
+        ),
+        model_definitions: diff_resources(
+            &snapshot.model_definitions,
+            &live.model_definitions,
+            |m| m.id,
+            |m| m.key.clone(),
+        ),
+        pipelines: diff_resources(
+            &snapshot.pipelines,
+            &live.pipelines,
+            |p| p.id,
+            pipeline_label,
+        ),
+    }
+}
+
+fn pipeline_label(pipeline: &SnapshotPipeline) -> String {
+    match &pipeline.environment {
+        Some(environment) => format!("{}@{environment}", pipeline.name),
+        None => pipeline.name.clone(),
+    }
+}
+
+fn diff_resources<T: PartialEq>(
+    snapshot: &[T],
+    live: &[T],
+    id: impl Fn(&T) -> Uuid,
+    label: impl Fn(&T) -> String,
+) -> ResourceDiffDto {
+    let live_by_id: HashMap<Uuid, &T> = live.iter().map(|item| (id(item), item)).collect();
+    let mut diff = ResourceDiffDto::default();
+    for item in snapshot {
+        match live_by_id.get(&id(item)) {
+            None => diff.added.push(label(item)),
+            Some(live_item) if *live_item != item => diff.changed.push(label(item)),
+            Some(_) => {}
+        }
+    }
+    let snapshot_ids: Vec<Uuid> = snapshot.iter().map(&id).collect();
+    diff.removed = live
+        .iter()
+        .filter(|item| !snapshot_ids.contains(&id(item)))
+        .map(label)
+        .collect();
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::management::db::repositories::config_snapshot_repository::SnapshotProvider;
+    use serde_json::json;
+
+    fn provider(id: Uuid, name: &str, api_key: &str) -> SnapshotProvider {
+        SnapshotProvider {
+            id,
+            name: name.to_string(),
+            provider_type: "openai".to_string(),
+            config_details: json!({ "api_key": { "type": "literal", "value": api_key } }),
+            enabled: true,
+        }
+    }
+
+    fn pipeline(name: &str, environment: Option<&str>) -> SnapshotPipeline {
+        SnapshotPipeline {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            pipeline_type: "chat".to_string(),
+            description: None,
+            enabled: true,
+            environment: environment.map(str::to_string),
+            plugins: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_diff_states() {
+        let kept = Uuid::new_v4();
+        let changed = Uuid::new_v4();
+        let staging = pipeline("default", Some("staging"));
+        let snapshot = ConfigState {
+            providers: vec![
+                provider(kept, "openai", "key"),
+                provider(changed, "azure", "old-key"),
+                provider(Uuid::new_v4(), "anthropic", "key"),
+            ],
+            pipelines: vec![staging.clone()],
+            ..Default::default()
+        };
+        let live = ConfigState {
+            providers: vec![
+                provider(kept, "openai", "key"),
+                provider(changed, "azure", "new-key"),
+                provider(Uuid::new_v4(), "bedrock", "key"),
+            ],
+            pipelines: vec![staging, pipeline("default", None)],
+            ..Default::default()
+        };
+
+        let diff = diff_states(&snapshot, &live);
+        assert_eq!(
+            diff.providers,
+            ResourceDiffDto {
+                added: vec!["anthropic".to_string()],
+                removed: vec!["bedrock".to_string()],
+                changed: vec!["azure".to_string()],
+            }
+        );
+        assert_eq!(diff.model_definitions, ResourceDiffDto::default());
+        assert_eq!(diff.pipelines.removed, vec!["default".to_string()]);
+        assert!(diff.pipelines.added.is_empty());
+        assert_eq!(diff_states(&live, &live), ConfigSnapshotDiffDto::default());
+    }
+
+    #[test]
+    fn test_pipeline_label_includes_environment() {
+        assert_eq!(pipeline_label(&pipeline("chat", Some("prod"))), "chat@prod");
+        assert_eq!(pipeline_label(&pipeline("chat", None)), "chat");
+    }
+}
@@ -1,7 +1,10 @@
 // Placeholder for service modules
 // pub mod config_management_service;
 
+pub mod api_key_service;
+pub mod config_diff_service;
 pub mod config_provider_service;
+pub mod config_snapshot_service;
 pub mod model_definition_service;
 pub mod pipeline_service;
 pub mod provider_service;
@@ -1,16 +1,21 @@
+use crate::ai_models::params::validate_config_details;
+use crate::config::names::{normalize_name, same_name, validate_name};
 use crate::management::{
-    db::models::ModelDefinition,
+    db::DbPools,
+    db::models::{ModelDefinition, Provider as DbProvider},
     db::repositories::{
         model_definition_repository::ModelDefinitionRepository,
         provider_repository::ProviderRepository,
     },
     dto::{
-        CreateModelDefinitionRequest, ModelDefinitionResponse, ProviderResponse, ProviderType,
-        UpdateModelDefinitionRequest,
+        CreateModelDefinitionRequest, ModelDefinitionResponse, ProviderConfig, ProviderResponse,
+        ProviderType, UpdateModelDefinitionRequest,
     },
     errors::ApiError,
+    services::provider_service::ProviderService,
 };
-use sqlx::{PgPool, types::Uuid};
+use crate::providers::allowed_models::{allowed_model_prefixes, check_model_type};
+use sqlx::types::Uuid;
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -20,10 +25,11 @@ pub struct ModelDefinitionService {
 }
 
 impl ModelDefinitionService {
-    pub fn new(pool: PgPool) -> Self {
+    pub fn new(pools: impl Into<DbPools>) -> Self {
+        let pools = pools.into();
         Self {
-            repo: Arc::new(ModelDefinitionRepository::new(pool.clone())),
-            provider_repo: Arc::new(ProviderRepository::new(pool)),
+            repo: Arc::new(ModelDefinitionRepository::new(pools.clone())),
+            provider_repo: Arc::new(ProviderRepository::new(pools)),
         }
     }
 
@@ -37,8 +43,9 @@ impl ModelDefinitionService {
             .find_by_id(db_model.provider_id)
             .await?
             .ok_or_else(|| {
-                ApiError::InternalServerError(format!(
-                    "Consistency error: Provider ID {} referenced by Model Definition {} not found",
+                // The provider was soft-deleted; its models are hidden with it.
+                ApiError::NotFound(format!(
+                    "Provider ID {} referenced by Model Definition {} not found",
                     db_model.provider_id, db_model.id
                 ))
             })?;
@@ -72,6 +79,7 @@ impl ModelDefinitionService {
             enabled: provider_db.enabled,
             created_at: provider_db.created_at,
             updated_at: provider_db.updated_at,
+            version: provider_db.version,
         };
 
         Ok(ModelDefinitionResponse {
@@ -83,31 +91,69 @@ impl ModelDefinitionService {
             enabled: db_model.enabled,
             created_at: db_model.created_at,
             updated_at: db_model.updated_at,
+            version: db_model.version,
         })
     }
 
+    /// The model definition whose key equals `key` ignoring case, if any.
+    async fn find_clashing_key(&self, key: &str) -> Result<Option<ModelDefinition>, ApiError> {
+        // Keys stay unique across deleted providers' models too, which `list` hides.
+        if let Some(existing) = self.repo.find_by_key(key).await? {
+            return Ok(Some(existing));
+        }
+        let models = self.repo.list().await?;
+        Ok(models.into_iter().find(|m| same_name(&m.key, key)))
+    }
+
+    /// Rejects a model whose type `provider` doesn't allow, going by its
+    /// `allowed_model_prefixes` or the defaults of its type.
+    fn check_provider_allows(
+        provider: &DbProvider,
+        model_key: &str,
+        model_type: &str,
+    ) -> Result<(), ApiError> {
+        let provider_type: ProviderType = provider.provider_type.parse().map_err(|e| {
+            ApiError::InternalServerError(format!(
+                "Failed to parse provider_type '{}' from DB for provider ID {}: {}",
+                provider.provider_type, provider.id, e
+            ))
+        })?;
+        let config =
+            ProviderService::deserialize_provider_config(&provider_type, &provider.config_details)?;
+        let has_base_url = matches!(&config, ProviderConfig::OpenAI(c) if c.base_url.is_some());
+        let prefixes = allowed_model_prefixes(
+            &provider_type,
+            config.allowed_model_prefixes(),
+            has_base_url,
+        );
+        check_model_type(model_key, model_type, &provider.name, prefixes.as_deref())
+            .map_err(|e| ApiError::ValidationError(format!("{e}.")))
+    }
+
     pub async fn create_model_definition(
         &self,
-        data: CreateModelDefinitionRequest,
+        mut data: CreateModelDefinitionRequest,
     ) -> Result<ModelDefinitionResponse, ApiError> {
+        data.key = normalize_name(&data.key);
+        validate_name("Model key", &data.key).map_err(ApiError::ValidationError)?;
+        if let Some(config_details) = &data.config_details {
+            validate_config_details(config_details).map_err(ApiError::ValidationError)?;
+        }
+
         // Check if provider_id exists
-        if self
-            .provider_repo
-            .find_by_id(data.provider_id)
-            .await?
-            .is_none()
-        {
+        let Some(provider) = self.provider_repo.find_by_id(data.provider_id).await? else {
             return Err(ApiError::ValidationError(format!(
                 "Provider with ID {} does not exist",
                 data.provider_id
             )));
-        }
+        };
+        Self::check_provider_allows(&provider, &data.key, &data.model_type)?;
 
         // Check if key is unique
-        if self.repo.find_by_key(&data.key).await?.is_some() {
+        if let Some(existing) = self.find_clashing_key(&data.key).await? {
             return Err(ApiError::Conflict(format!(
                 "Model Definition key '{}' already exists",
-                data.key
+                existing.key
             )));
         }
 
@@ -147,19 +193,34 @@ impl ModelDefinitionService {
     pub async fn update_model_definition(
         &self,
         id: Uuid,
-        data: UpdateModelDefinitionRequest,
+        mut data: UpdateModelDefinitionRequest,
+        expected_version: i32,
     ) -> Result<ModelDefinitionResponse, ApiError> {
         // Ensure the model definition to update exists
-        let _ = self.repo.find_by_id(id).await?.ok_or_else(|| {
+        let existing_model = self.repo.find_by_id(id).await?.ok_or_else(|| {
             ApiError::NotFound(format!("Model Definition with ID {id} not found"))
         })?;
+        if existing_model.version != expected_version {
+            return Err(ApiError::version_conflict(
+                "Model Definition",
+                id,
+                existing_model.version,
+            ));
+        }
+
+        if let Some(config_details) = &data.config_details {
+            validate_config_details(config_details).map_err(ApiError::ValidationError)?;
+        }
 
         // If key is being updated, check for uniqueness
-        if let Some(key) = &data.key {
-            if let Some(existing_by_key) = self.repo.find_by_key(key).await? {
+        if let Some(key) = &mut data.key {
+            *key = normalize_name(key);
+            validate_name("Model key", key).map_err(ApiError::ValidationError)?;
+            if let Some(existing_by_key) = self.find_clashing_key(key).await? {
                 if existing_by_key.id != id {
                     return Err(ApiError::Conflict(format!(
-                        "Model Definition key '{key}' already exists"
+                        "Model Definition key '{}' already exists",
+                        existing_by_key.key
                     )));
                 }
             }
@@ -167,14 +228,42 @@ impl ModelDefinitionService {
 
         // If provider_id is being updated, check if it exists
         if let Some(provider_id) = data.provider_id {
-            if self.provider_repo.find_by_id(provider_id).await?.is_none() {
+            let Some(provider) = self.provider_repo.find_by_id(provider_id).await? else {
                 return Err(ApiError::ValidationError(format!(
                     "Provider with ID {provider_id} does not exist"
                 )));
+            };
+            let model_type = data.model_type.as_deref();
+            Self::check_provider_allows(
+                &provider,
+                data.key.as_deref().unwrap_or(&existing_model.key),
+                model_type.unwrap_or(&existing_model.model_type),
+            )?;
+        } else if let Some(model_type) = &data.model_type {
+            if let Some(provider) = self
+                .provider_repo
+                .find_by_id(existing_model.provider_id)
+                .await?
+            {
+                Self::check_provider_allows(
+                    &provider,
+                    data.key.as_deref().unwrap_or(&existing_model.key),
+                    model_type,
+                )?;
             }
         }
 
-        let updated_db_model = self.repo.update(id, &data).await?;
+        let Some(updated_db_model) = self.repo.update(id, &data, expected_version).await? else {
+            // Another update won the race since the version check above.
+            let current = self.repo.find_by_id(id).await?.ok_or_else(|| {
+                ApiError::NotFound(format!("Model Definition with ID {id} not found"))
+            })?;
+            return Err(ApiError::version_conflict(
+                "Model Definition",
+                id,
+                current.version,
+            ));
+        };
         self.map_db_model_to_response(updated_db_model).await
     }
 
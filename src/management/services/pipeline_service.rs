@@ -1,17 +1,28 @@
 use sqlx::types::Uuid;
 use std::sync::Arc;
 
+use crate::config::names::{normalize_name, same_name, validate_name};
 use crate::management::{
     db::models::PipelineWithPlugins, // Internal struct from repository
     // We'll need ModelDefinitionRepository for validating model keys in model-router
     db::repositories::model_definition_repository::ModelDefinitionRepository,
     db::repositories::pipeline_repository::PipelineRepository,
     dto::{
-        CreatePipelineRequestDto, LoggingConfigDto, ModelRouterConfigDto, PipelinePluginConfigDto,
-        PipelineResponseDto, PluginType, TracingConfigDto, UpdatePipelineRequestDto,
+        BudgetConfigDto, CreatePipelineRequestDto, DegradedMode, LoggingConfigDto,
+        MetadataConfigDto, ModelRouterConfigDto, ParameterPolicyConfigDto, PassthroughHeaders,
+        PatchPipelinePluginRequestDto, PipelinePluginConfigDto, PipelineResponseDto, PluginType,
+        PriorityConfigDto, PromotePipelineRequestDto, ResponseNormalizationConfigDto,
+        StreamOptionsConfigDto, ToolLimits, TracingConfigDto, UpdatePipelineRequestDto,
     },
     errors::ApiError,
 };
+use crate::models::chat::validate_metadata;
+use crate::pipelines::adaptive_routing::validate_adaptive_routing;
+use crate::pipelines::degraded_mode::validate_degraded_mode;
+use crate::pipelines::parameter_policy::validate_parameter_policy;
+use crate::pipelines::race::validate_race_routing;
+use crate::pipelines::tool_limits::validate_tool_limits;
+use crate::upstream_headers::validate_passthrough_headers;
 
 #[derive(Debug)]
 pub struct PipelineService {
@@ -52,6 +63,7 @@ impl PipelineService {
                 config_data: plugin_config.config_data,
                 enabled: plugin_config.enabled,
                 order_in_pipeline: plugin_config.order_in_pipeline,
+                id: Some(plugin_config.id),
             });
         }
 
@@ -62,8 +74,10 @@ impl PipelineService {
             description: db_pipeline.description,
             plugins: plugin_dtos,
             enabled: db_pipeline.enabled,
+            environment: db_pipeline.environment,
             created_at: db_pipeline.created_at,
             updated_at: db_pipeline.updated_at,
+            version: db_pipeline.version,
         })
     }
 
@@ -71,18 +85,36 @@ impl PipelineService {
     async fn validate_pipeline_for_creation(
         &self,
         name: &str,
+        environment: Option<&str>,
         plugins: &[PipelinePluginConfigDto],
     ) -> Result<(), ApiError> {
+        if let Some(environment) = environment {
+            validate_environment(environment)?;
+        }
+        validate_name("Pipeline name", name).map_err(ApiError::ValidationError)?;
         // Validate pipeline name uniqueness for new pipelines
-        if self.repo.find_pipeline_by_name(name).await?.is_some() {
+        if let Some(existing) = self.find_clashing_name(name, environment).await? {
             return Err(ApiError::Conflict(format!(
-                "Pipeline name '{name}' already exists"
+                "Pipeline name '{}' already exists",
+                existing.name
             )));
         }
         // Validate plugin configurations
         self.validate_plugins_config(plugins).await
     }
 
+    /// The pipeline in `environment` whose name equals `name` ignoring case, if any.
+    async fn find_clashing_name(
+        &self,
+        name: &str,
+        environment: Option<&str>,
+    ) -> Result<Option<PipelineWithPlugins>, ApiError> {
+        let pipelines = self.repo.list_pipelines().await?;
+        Ok(pipelines
+            .into_iter()
+            .find(|p| p.environment.as_deref() == environment && same_name(&p.name, name)))
+    }
+
     // New method specifically for validating plugin configurations
     async fn validate_plugins_config(
         &self,
@@ -97,6 +129,21 @@ impl PipelineService {
                                 "Invalid model-router config_data: {e}"
                             ))
                         })?;
+                    if let Some(adaptive) = &model_router_config.adaptive {
+                        validate_adaptive_routing(adaptive).map_err(|e| {
+                            ApiError::ValidationError(format!("Invalid adaptive routing: {e}"))
+                        })?;
+                    }
+                    if let Some(race) = &model_router_config.race {
+                        let model_keys: Vec<String> = model_router_config
+                            .models
+                            .iter()
+                            .map(|m| m.key.clone())
+                            .collect();
+                        validate_race_routing(race, &model_keys).map_err(|e| {
+                            ApiError::ValidationError(format!("Invalid race routing: {e}"))
+                        })?;
+                    }
                     for model_entry in model_router_config.models {
                         if self
                             .model_definition_repo
@@ -112,11 +159,17 @@ impl PipelineService {
                     }
                 }
                 PluginType::Logging => {
-                    let _logging_config: LoggingConfigDto =
+                    let logging_config: LoggingConfigDto =
                         serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
                             ApiError::ValidationError(format!("Invalid logging config_data: {e}"))
                         })?;
-                    // Additional validation for logging config can be added here
+                    if let Some(sample_rate) = logging_config.sample_rate {
+                        if !(0.0..=1.0).contains(&sample_rate) {
+                            return Err(ApiError::ValidationError(format!(
+                                "Logging sample_rate must be between 0.0 and 1.0, got {sample_rate}"
+                            )));
+                        }
+                    }
                 }
                 PluginType::Tracing => {
                     let _tracing_config: TracingConfigDto =
@@ -125,6 +178,113 @@ impl PipelineService {
                         })?;
                     // Additional validation for tracing config can be added here
                 }
+                PluginType::Metadata => {
+                    let metadata_config: MetadataConfigDto =
+                        serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
+                            ApiError::ValidationError(format!("Invalid metadata config_data: {e}"))
+                        })?;
+                    validate_metadata(metadata_config.values.iter()).map_err(|e| {
+                        ApiError::ValidationError(format!("Invalid metadata config_data: {e}"))
+                    })?;
+                }
+                PluginType::Priority => {
+                    let _priority_config: PriorityConfigDto =
+                        serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
+                            ApiError::ValidationError(format!("Invalid priority config_data: {e}"))
+                        })?;
+                }
+                PluginType::ResponseNormalization => {
+                    let _normalization_config: ResponseNormalizationConfigDto =
+                        serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
+                            ApiError::ValidationError(format!(
+                                "Invalid response-normalization config_data: {e}"
+                            ))
+                        })?;
+                }
+                PluginType::StreamOptions => {
+                    let _stream_options_config: StreamOptionsConfigDto =
+                        serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
+                            ApiError::ValidationError(format!(
+                                "Invalid stream-options config_data: {e}"
+                            ))
+                        })?;
+                }
+                PluginType::DegradedMode => {
+                    let degraded_mode: DegradedMode =
+                        serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
+                            ApiError::ValidationError(format!(
+                                "Invalid degraded-mode config_data: {e}"
+                            ))
+                        })?;
+                    validate_degraded_mode(&degraded_mode).map_err(|e| {
+                        ApiError::ValidationError(format!("Invalid degraded mode: {e}"))
+                    })?;
+                    if self
+                        .model_definition_repo
+                        .find_by_key(&degraded_mode.model)
+                        .await?
+                        .is_none()
+                    {
+                        return Err(ApiError::ValidationError(format!(
+                            "ModelDefinition key '{}' not found for degraded-mode",
+                            degraded_mode.model
+                        )));
+                    }
+                }
+                PluginType::PassthroughHeaders => {
+                    let passthrough: PassthroughHeaders =
+                        serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
+                            ApiError::ValidationError(format!(
+                                "Invalid passthrough-headers config_data: {e}"
+                            ))
+                        })?;
+                    validate_passthrough_headers(&passthrough).map_err(|e| {
+                        ApiError::ValidationError(format!("Invalid passthrough headers: {e}"))
+                    })?;
+                }
+                PluginType::ToolLimits => {
+                    let tool_limits: ToolLimits =
+                        serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
+                            ApiError::ValidationError(format!(
+                                "Invalid tool-limits config_data: {e}"
+                            ))
+                        })?;
+                    validate_tool_limits(&tool_limits).map_err(|e| {
+                        ApiError::ValidationError(format!("Invalid tool limits: {e}"))
+                    })?;
+                }
+                PluginType::ParameterPolicy => {
+                    let policy_config: ParameterPolicyConfigDto =
+                        serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
+                            ApiError::ValidationError(format!(
+                                "Invalid parameter-policy config_data: {e}"
+                            ))
+                        })?;
+                    validate_parameter_policy(
+                        &policy_config.rules,
+                        policy_config.allow_extra_body,
+                        &policy_config.extra_body_keys,
+                    )
+                    .map_err(ApiError::ValidationError)?;
+                }
+                PluginType::Budget => {
+                    let budget_config: BudgetConfigDto =
+                        serde_json::from_value(plugin_dto.config_data.clone()).map_err(|e| {
+                            ApiError::ValidationError(format!("Invalid budget config_data: {e}"))
+                        })?;
+                    if !(budget_config.limit_usd.is_finite() && budget_config.limit_usd > 0.0) {
+                        return Err(ApiError::ValidationError(
+                            "Budget limit_usd must be a positive number".to_string(),
+                        ));
+                    }
+                    if let Some(percent) = budget_config.warn_at_percent {
+                        if !(1..=100).contains(&percent) {
+                            return Err(ApiError::ValidationError(
+                                "Budget warn_at_percent must be between 1 and 100".to_string(),
+                            ));
+                        }
+                    }
+                }
             }
         }
         Ok(())
@@ -132,11 +292,16 @@ impl PipelineService {
 
     pub async fn create_pipeline(
         &self,
-        request: CreatePipelineRequestDto,
+        mut request: CreatePipelineRequestDto,
     ) -> Result<PipelineResponseDto, ApiError> {
+        request.name = normalize_name(&request.name);
         // Use the more specific validation method for creation
-        self.validate_pipeline_for_creation(&request.name, &request.plugins)
-            .await?;
+        self.validate_pipeline_for_creation(
+            &request.name,
+            request.environment.as_deref(),
+            &request.plugins,
+        )
+        .await?;
         let created_db_pipeline = self.repo.create_pipeline_with_plugins(&request).await?;
         self.map_db_pipeline_to_response(created_db_pipeline)
     }
@@ -151,8 +316,12 @@ impl PipelineService {
         }
     }
 
-    pub async fn get_pipeline_by_name(&self, name: &str) -> Result<PipelineResponseDto, ApiError> {
-        let db_pipeline = self.repo.find_pipeline_by_name(name).await?;
+    pub async fn get_pipeline_by_name(
+        &self,
+        name: &str,
+        environment: Option<&str>,
+    ) -> Result<PipelineResponseDto, ApiError> {
+        let db_pipeline = self.repo.find_pipeline_by_name(name, environment).await?;
         match db_pipeline {
             Some(p) => self.map_db_pipeline_to_response(p),
             None => Err(ApiError::NotFound(format!(
@@ -172,23 +341,27 @@ impl PipelineService {
     pub async fn update_pipeline(
         &self,
         id: Uuid,
-        request: UpdatePipelineRequestDto,
+        mut request: UpdatePipelineRequestDto,
+        expected_version: i32,
     ) -> Result<PipelineResponseDto, ApiError> {
         // Ensure pipeline exists before update
-        let existing_pipeline_opt = self.repo.find_pipeline_by_id(id).await?;
-        if existing_pipeline_opt.is_none() {
-            return Err(ApiError::NotFound(format!(
-                "Pipeline with ID {id} not found for update"
-            )));
-        }
+        let existing_pipeline = self.repo.find_pipeline_by_id(id).await?.ok_or_else(|| {
+            ApiError::NotFound(format!("Pipeline with ID {id} not found for update"))
+        })?;
 
         // Validate new name uniqueness if name is being changed
-        if let Some(new_name) = &request.name {
-            if let Some(found_pipeline_by_name) = self.repo.find_pipeline_by_name(new_name).await? {
+        if let Some(new_name) = &mut request.name {
+            *new_name = normalize_name(new_name);
+            validate_name("Pipeline name", new_name).map_err(ApiError::ValidationError)?;
+            if let Some(found_pipeline_by_name) = self
+                .find_clashing_name(new_name, existing_pipeline.environment.as_deref())
+                .await?
+            {
                 if found_pipeline_by_name.id != id {
                     // It's a different pipeline with the same new name
                     return Err(ApiError::Conflict(format!(
-                        "Pipeline name '{new_name}' already exists"
+                        "Pipeline name '{}' already exists",
+                        found_pipeline_by_name.name
                     )));
                 }
             }
@@ -198,12 +371,93 @@ impl PipelineService {
         if let Some(plugins) = &request.plugins {
             self.validate_plugins_config(plugins).await?;
         }
-        let updated_db_pipeline = self.repo.update_pipeline(id, &request).await?;
+        let updated_db_pipeline = self
+            .repo
+            .update_pipeline(id, &request, expected_version)
+            .await?;
         self.map_db_pipeline_to_response(updated_db_pipeline)
     }
 
-    pub async fn delete_pipeline(&self, id: Uuid) -> Result<(), ApiError> {
-        let affected_rows = self.repo.delete_pipeline(id).await?;
+    /// Updates one plugin's config_data and enabled flag, leaving the other plugins as they are.
+    pub async fn patch_pipeline_plugin(
+        &self,
+        pipeline_id: Uuid,
+        plugin_id: Uuid,
+        request: PatchPipelinePluginRequestDto,
+        expected_version: i32,
+    ) -> Result<PipelineResponseDto, ApiError> {
+        let pipeline = self
+            .repo
+            .find_pipeline_by_id(pipeline_id)
+            .await?
+            .ok_or_else(|| {
+                ApiError::NotFound(format!(
+                    "Pipeline with ID {pipeline_id} not found for update"
+                ))
+            })?;
+        if pipeline.version != expected_version {
+            return Err(ApiError::version_conflict(
+                "Pipeline",
+                pipeline_id,
+                pipeline.version,
+            ));
+        }
+        let plugin = pipeline
+            .plugins
+            .into_iter()
+            .find(|p| p.id == plugin_id)
+            .ok_or_else(|| {
+                ApiError::NotFound(format!(
+                    "Plugin with ID {plugin_id} not found in pipeline {pipeline_id}"
+                ))
+            })?;
+
+        let mut config_data = plugin.config_data;
+        if let Some(changes) = request.config_data {
+            let config_object = config_data.as_object_mut().ok_or_else(|| {
+                ApiError::InternalServerError("Plugin config_data is not an object".to_string())
+            })?;
+            for (key, value) in changes {
+                if value.is_null() {
+                    config_object.remove(&key);
+                } else {
+                    config_object.insert(key, value);
+                }
+            }
+        }
+
+        let patched_plugin = PipelinePluginConfigDto {
+            plugin_type: plugin.plugin_type.parse::<PluginType>().map_err(|e| {
+                ApiError::InternalServerError(format!("Invalid plugin type in database: {e}"))
+            })?,
+            config_data,
+            enabled: request.enabled.unwrap_or(plugin.enabled),
+            order_in_pipeline: plugin.order_in_pipeline,
+            id: Some(plugin.id),
+        };
+        self.validate_plugins_config(std::slice::from_ref(&patched_plugin))
+            .await?;
+
+        let updated_db_pipeline = self
+            .repo
+            .update_pipeline_plugin(
+                pipeline_id,
+                plugin_id,
+                &patched_plugin.config_data,
+                patched_plugin.enabled,
+                expected_version,
+            )
+            .await?;
+        self.map_db_pipeline_to_response(updated_db_pipeline)
+    }
+
+    /// Soft-deletes a pipeline, or purges it when `hard` is set.
+    pub async fn delete_pipeline(&self, id: Uuid, hard: bool) -> Result<(), ApiError> {
+        let affected_rows = if hard {
+            self.repo.delete_pipeline(id).await?
+        } else {
+            self.repo.soft_delete_pipeline(id).await?
+        };
         if affected_rows == 0 {
             return Err(ApiError::NotFound(format!(
                 "Pipeline with ID {id} not found for deletion"
@@ -211,4 +465,80 @@ impl PipelineService {
         }
         Ok(())
     }
+
+    pub async fn restore_pipeline(&self, id: Uuid) -> Result<PipelineResponseDto, ApiError> {
+        let (name, environment) =
+            self.repo
+                .find_deleted_pipeline_name(id)
+                .await?
+                .ok_or_else(|| {
+                    ApiError::NotFound(format!("Deleted pipeline with ID {id} not found"))
+                })?;
+        if let Some(other) = self
+            .find_clashing_name(&name, environment.as_deref())
+            .await?
+        {
+            return Err(ApiError::Conflict(format!(
+                "Cannot restore pipeline: another pipeline named '{}' exists",
+                other.name
+            )));
+        }
+
+        self.repo.restore_pipeline(id).await?;
+        self.get_pipeline(id).await
+    }
+
+    /// Copies a pipeline onto the pipeline with the same name in another environment,
+    /// creating it there if needed.
+    pub async fn promote_pipeline(
+        &self,
+        id: Uuid,
+        request: PromotePipelineRequestDto,
+    ) -> Result<PipelineResponseDto, ApiError> {
+        validate_environment(&request.target_environment)?;
+        let source = self
+            .repo
+            .find_pipeline_by_id(id)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Pipeline with ID {id} not found")))?;
+        if source.environment.as_deref() == Some(request.target_environment.as_str()) {
+            return Err(ApiError::ValidationError(format!(
+                "Pipeline is already in environment '{}'",
+                request.target_environment
+            )));
+        }
+        if let Some(target) = self
+            .find_clashing_name(&source.name, Some(&request.target_environment))
+            .await?
+        {
+            if target.name != source.name {
+                return Err(ApiError::Conflict(format!(
+                    "Pipeline '{}' in environment '{}' differs from '{}' only by case",
+                    target.name, request.target_environment, source.name
+                )));
+            }
+        }
+
+        let promoted = self
+            .repo
+            .promote_pipeline(id, &request.target_environment)
+            .await?;
+        self.map_db_pipeline_to_response(promoted)
+    }
+}
+
+/// Environment tags are short identifiers such as `staging` or `prod-eu`.
+fn validate_environment(environment: &str) -> Result<(), ApiError> {
+    let valid = !environment.is_empty()
+        && environment.len() <= 64
+        && environment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ApiError::ValidationError(format!(
+            "Invalid environment '{environment}': use 1-64 letters, digits, '-' or '_'"
+        )))
+    }
 }
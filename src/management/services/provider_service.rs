@@ -1,40 +1,65 @@
 use serde_json;
-use sqlx::{PgPool, types::Uuid};
+use sqlx::types::Uuid;
 use std::sync::Arc;
 
+use crate::config::names::{normalize_name, same_name, validate_name};
 use crate::management::{
-    db::{models::Provider as DbProvider, repositories::provider_repository::ProviderRepository},
+    db::{
+        DbPools,
+        models::{PipelineWithPlugins, Provider as DbProvider},
+        repositories::{
+            model_definition_repository::ModelDefinitionRepository,
+            pipeline_repository::PipelineRepository, provider_repository::ProviderRepository,
+        },
+    },
     dto::{
-        AnthropicProviderConfig, AzureProviderConfig, BedrockProviderConfig, CreateProviderRequest,
-        OpenAIProviderConfig, ProviderConfig, ProviderResponse, ProviderType,
-        UpdateProviderRequest, VertexAIProviderConfig,
+        AnthropicProviderConfig, AzureAuthType, AzureProviderConfig, BedrockProviderConfig,
+        CreateProviderRequest, DependentModelDefinitionDto, DependentPipelineDto,
+        MockProviderConfig, OpenAIProviderConfig, PluginType, ProviderConfig,
+        ProviderDependentsResponse, ProviderResponse, ProviderType, SecretObject,
+        UpdateProviderRequest, VertexAIProviderConfig, custom_provider_type_error,
     },
     errors::ApiError,
 };
+use crate::providers::allowed_models::ANY_MODEL_PREFIX;
+use crate::providers::http_client::validate_proxy_url;
+use crate::providers::mock::validate_mock_params;
 
 #[derive(Clone)]
 pub struct ProviderService {
     repo: Arc<ProviderRepository>,
+    model_definition_repo: Arc<ModelDefinitionRepository>, // To find dependents
+    pipeline_repo: Arc<PipelineRepository>,
 }
 
 impl ProviderService {
-    pub fn new(pool: PgPool) -> Self {
+    pub fn new(pools: impl Into<DbPools>) -> Self {
+        let pools = pools.into();
         Self {
-            repo: Arc::new(ProviderRepository::new(pool)),
+            repo: Arc::new(ProviderRepository::new(pools.clone())),
+            model_definition_repo: Arc::new(ModelDefinitionRepository::new(pools.clone())),
+            pipeline_repo: Arc::new(PipelineRepository::new(pools)),
         }
     }
 
     pub async fn create_provider(
         &self,
-        request: CreateProviderRequest,
+        mut request: CreateProviderRequest,
     ) -> Result<ProviderResponse, ApiError> {
-        if self.repo.find_by_name(&request.name).await?.is_some() {
+        request.name = normalize_name(&request.name);
+        validate_name("Provider name", &request.name).map_err(ApiError::ValidationError)?;
+        if let Some(existing) = self.find_clashing_name(&request.name).await? {
             return Err(ApiError::Conflict(format!(
                 "Provider with name '{}' already exists.",
-                request.name
+                existing.name
             )));
         }
 
+        Self::validate_proxy_settings(&request.config)?;
+        Self::validate_azure_auth(&request.config)?;
+        Self::validate_mock_settings(&request.config)?;
+        Self::validate_allowed_model_prefixes(&request.config)?;
+
         let provider_type_string_for_db = request.provider_type.to_string();
 
         let config_json_value = serde_json::to_value(&request.config)?;
@@ -46,6 +71,12 @@ impl ProviderService {
         Self::map_db_provider_to_response(db_provider)
     }
 
+    /// The live provider whose name equals `name` ignoring case.
+    async fn find_clashing_name(&self, name: &str) -> Result<Option<DbProvider>, ApiError> {
+        let providers = self.repo.list().await?;
+        Ok(providers.into_iter().find(|p| same_name(&p.name, name)))
+    }
+
     pub async fn get_provider(&self, id: Uuid) -> Result<ProviderResponse, ApiError> {
         let db_provider = self
             .repo
@@ -66,22 +97,44 @@ impl ProviderService {
     pub async fn update_provider(
         &self,
         id: Uuid,
-        request: UpdateProviderRequest,
+        mut request: UpdateProviderRequest,
+        expected_version: i32,
+        force: bool,
     ) -> Result<ProviderResponse, ApiError> {
         let existing_provider = self.repo.find_by_id(id).await?.ok_or_else(|| {
             ApiError::NotFound(format!("Provider with ID {id} not found to update."))
         })?;
+        if existing_provider.version != expected_version {
+            return Err(ApiError::version_conflict(
+                "Provider",
+                id,
+                existing_provider.version,
+            ));
+        }
+        if existing_provider.enabled && request.enabled == Some(false) && !force {
+            self.check_no_enabled_dependents(id, "disable").await?;
+        }
 
-        if let Some(new_name) = &request.name {
-            if new_name != &existing_provider.name
-                && self.repo.find_by_name(new_name).await?.is_some()
-            {
-                return Err(ApiError::Conflict(format!(
-                    "Another provider with name '{new_name}' already exists."
-                )));
+        if let Some(new_name) = &mut request.name {
+            *new_name = normalize_name(new_name);
+            validate_name("Provider name", new_name).map_err(ApiError::ValidationError)?;
+            if let Some(other) = self.find_clashing_name(new_name).await? {
+                if other.id != id {
+                    return Err(ApiError::Conflict(format!(
+                        "Another provider with name '{}' already exists.",
+                        other.name
+                    )));
+                }
             }
         }
 
+        if let Some(config) = &request.config {
+            Self::validate_proxy_settings(config)?;
+            Self::validate_azure_auth(config)?;
+            Self::validate_mock_settings(config)?;
+            Self::validate_allowed_model_prefixes(config)?;
+        }
+
         let config_json_value_opt = match request.config.as_ref() {
             Some(provider_config_enum_instance) => {
                 Some(serde_json::to_value(provider_config_enum_instance)?)
@@ -89,21 +142,36 @@ impl ProviderService {
             None => None,
         };
 
-        let updated_db_provider = self
+        let Some(updated_db_provider) = self
             .repo
-            .update(id, &request, config_json_value_opt)
+            .update(id, &request, config_json_value_opt, expected_version)
             .await?
-            .ok_or_else(|| {
+        else {
+            // Another update won the race since the version check above.
+            let current = self.repo.find_by_id(id).await?.ok_or_else(|| {
                 ApiError::NotFound(format!(
                     "Provider with ID {id} not found after update attempt."
                 ))
             })?;
+            return Err(ApiError::version_conflict("Provider", id, current.version));
+        };
 
         Self::map_db_provider_to_response(updated_db_provider)
     }
 
-    pub async fn delete_provider(&self, id: Uuid) -> Result<(), ApiError> {
-        let affected_rows = self.repo.delete(id).await?;
+    /// Soft-deletes a provider, or purges it (and its model definitions) when `hard` is set.
+    /// Fails with a conflict while enabled pipelines route to any of its models, unless
+    /// `force` is set.
+    pub async fn delete_provider(&self, id: Uuid, hard: bool, force: bool) -> Result<(), ApiError> {
+        if !force {
+            self.check_no_enabled_dependents(id, "delete").await?;
+        }
+
+        let affected_rows = if hard {
+            self.repo.delete(id).await?
+        } else {
+            self.repo.soft_delete(id).await?
+        };
         if affected_rows == 0 {
             Err(ApiError::NotFound(format!(
                 "Provider with ID {id} not found, nothing deleted."
@@ -113,6 +181,161 @@ impl ProviderService {
         }
     }
 
+    pub async fn restore_provider(&self, id: Uuid) -> Result<ProviderResponse, ApiError> {
+        let deleted_provider = self.repo.find_deleted_by_id(id).await?.ok_or_else(|| {
+            ApiError::NotFound(format!("Deleted provider with ID {id} not found."))
+        })?;
+        if let Some(other) = self.find_clashing_name(&deleted_provider.name).await? {
+            return Err(ApiError::Conflict(format!(
+                "Cannot restore provider: another provider named '{}' exists.",
+                other.name
+            )));
+        }
+
+        let restored_provider = self.repo.restore(id).await?.ok_or_else(|| {
+            ApiError::NotFound(format!("Deleted provider with ID {id} not found."))
+        })?;
+        Self::map_db_provider_to_response(restored_provider)
+    }
+
+    /// The provider's model definitions and the live pipelines whose model router uses them.
+    pub async fn find_dependents(&self, id: Uuid) -> Result<ProviderDependentsResponse, ApiError> {
+        if self.repo.find_by_id(id).await?.is_none() {
+            return Err(ApiError::NotFound(format!(
+                "Provider with ID {id} not found."
+            )));
+        }
+        self.dependents(id).await
+    }
+
+    async fn dependents(&self, id: Uuid) -> Result<ProviderDependentsResponse, ApiError> {
+        let model_definitions = self.model_definition_repo.list_by_provider(id).await?;
+        let mut pipelines = self.pipeline_repo.list_pipelines().await?;
+        pipelines.sort_by(|a, b| (&a.name, &a.environment).cmp(&(&b.name, &b.environment)));
+
+        let model_definitions = model_definitions
+            .into_iter()
+            .map(|model| DependentModelDefinitionDto {
+                pipelines: pipelines
+                    .iter()
+                    .filter(|pipeline| Self::routes_to(pipeline, &model.key))
+                    .map(|pipeline| DependentPipelineDto {
+                        id: pipeline.id,
+                        name: pipeline.name.clone(),
+                        environment: pipeline.environment.clone(),
+                        enabled: pipeline.enabled,
+                    })
+                    .collect(),
+                id: model.id,
+                key: model.key,
+                model_type: model.model_type,
+                enabled: model.enabled,
+            })
+            .collect();
+        Ok(ProviderDependentsResponse {
+            provider_id: id,
+            model_definitions,
+        })
+    }
+
+    /// Whether a model-router plugin of the pipeline lists the model.
+    fn routes_to(pipeline: &PipelineWithPlugins, model_key: &str) -> bool {
+        let model_router = PluginType::ModelRouter.to_string();
+        pipeline
+            .plugins
+            .iter()
+            .filter(|plugin| plugin.plugin_type == model_router)
+            .filter_map(|plugin| plugin.config_data["models"].as_array())
+            .flatten()
+            .any(|model| model["key"] == model_key)
+    }
+
+    /// Fails with a conflict listing the dependents while enabled pipelines route to any of
+    /// the provider's models.
+    async fn check_no_enabled_dependents(&self, id: Uuid, action: &str) -> Result<(), ApiError> {
+        let dependents = self.dependents(id).await?;
+        let pipelines: Vec<String> = dependents
+            .enabled_pipelines()
+            .into_iter()
+            .map(|pipeline| match &pipeline.environment {
+                Some(environment) => format!("{}@{environment}", pipeline.name),
+                None => pipeline.name.clone(),
+            })
+            .collect();
+        if pipelines.is_empty() {
+            return Ok(());
+        }
+        Err(ApiError::DependentsConflict {
+            message: format!(
+                "Provider with ID {id} is used by enabled pipelines: {}. \
+                 Remove it from them first, or pass force=true to {action} it anyway.",
+                pipelines.join(", ")
+            ),
+            dependents,
+        })
+    }
+
+    /// Rejects malformed literal proxy URLs up front; secret references are
+    /// checked when the live config is validated.
+    fn validate_proxy_settings(config: &ProviderConfig) -> Result<(), ApiError> {
+        if let (Some(SecretObject::Literal { value, .. }), _) = config.proxy_settings() {
+            validate_proxy_url(value)
+                .map_err(|e| ApiError::ValidationError(format!("Invalid proxy_url: {e}")))?;
+        }
+        Ok(())
+    }
+
+    /// API-key auth needs `api_key`. Entra ID auth needs a full set of client
+    /// credentials, or none of them to use a managed identity.
+    fn validate_azure_auth(config: &ProviderConfig) -> Result<(), ApiError> {
+        let ProviderConfig::Azure(c) = config else {
+            return Ok(());
+        };
+        match c.auth_type.unwrap_or(AzureAuthType::ApiKey) {
+            AzureAuthType::ApiKey if c.api_key.is_none() => Err(ApiError::ValidationError(
+                "api_key is required unless auth_type is entra_id".to_string(),
+            )),
+            AzureAuthType::ApiKey => Ok(()),
+            AzureAuthType::EntraId => match (&c.tenant_id, &c.client_id, &c.client_secret) {
+                (Some(_), Some(_), Some(_)) | (None, _, None) => Ok(()),
+                _ => Err(ApiError::ValidationError(
+                    "entra_id auth needs tenant_id, client_id and client_secret, \
+                     or no tenant_id and client_secret to use a managed identity"
+                        .to_string(),
+                )),
+            },
+        }
+    }
+
+    fn validate_mock_settings(config: &ProviderConfig) -> Result<(), ApiError> {
+        let ProviderConfig::Mock(c) = config else {
+            return Ok(());
+        };
+        validate_mock_params(&c.params())
+            .map_err(|e| ApiError::ValidationError(format!("Invalid mock settings: {e}")))
+    }
+
+    /// Prefixes are stored as a comma-separated provider param, so they can't contain one.
+    fn validate_allowed_model_prefixes(config: &ProviderConfig) -> Result<(), ApiError> {
+        let Some(prefixes) = config.allowed_model_prefixes() else {
+            return Ok(());
+        };
+        if prefixes.is_empty() {
+            return Err(ApiError::ValidationError(format!(
+                "allowed_model_prefixes must list at least one prefix, or '{ANY_MODEL_PREFIX}' to allow any model"
+            )));
+        }
+        if let Some(prefix) = prefixes
+            .iter()
+            .find(|prefix| prefix.trim().is_empty() || prefix.contains(','))
+        {
+            return Err(ApiError::ValidationError(format!(
+                "Invalid allowed_model_prefixes entry '{prefix}': prefixes must be non-empty and can't contain ','"
+            )));
+        }
+        Ok(())
+    }
+
     pub fn deserialize_provider_config(
         provider_type: &ProviderType,
         config_details: &serde_json::Value,
@@ -140,6 +363,13 @@ impl ProviderService {
                     serde_json::from_value(config_details.clone())?;
                 ProviderConfig::VertexAI(config)
             }
+            ProviderType::Mock => {
+                let config: MockProviderConfig = serde_json::from_value(config_details.clone())?;
+                ProviderConfig::Mock(config)
+            }
+            ProviderType::Custom(name) => {
+                return Err(ApiError::ValidationError(custom_provider_type_error(name)));
+            }
         };
         Ok(config_enum)
     }
@@ -163,6 +393,7 @@ impl ProviderService {
             enabled: db_provider.enabled,
             created_at: db_provider.created_at,
             updated_at: db_provider.updated_at,
+            version: db_provider.version,
         })
     }
 }
@@ -13,6 +13,12 @@ impl SecretResolver {
 
     /// Resolve a SecretObject to its actual secret value
     pub async fn resolve_secret(&self, secret: &SecretObject) -> Result<String> {
+        self.resolve(secret)
+    }
+
+    /// Resolve a SecretObject outside an async context, such as a provider resolving its
+    /// API key on first use
+    pub fn resolve(&self, secret: &SecretObject) -> Result<String> {
         match secret {
             SecretObject::Literal { value, encrypted } => {
                 if encrypted.unwrap_or(false) {
@@ -43,6 +49,13 @@ impl SecretResolver {
                     "Kubernetes secret resolution not yet implemented for secret '{secret_name}' key '{key}'"
                 ))
             }
+
+            SecretObject::File { path } => {
+                debug!("Resolving secret file: {}", path);
+                let contents = std::fs::read_to_string(path)
+                    .map_err(|e| anyhow!("Failed to read secret file '{path}': {e}"))?;
+                Ok(contents.trim().to_string())
+            }
         }
     }
 
@@ -134,6 +147,20 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_resolve_file_secret() {
+        let resolver = SecretResolver::new();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "file-secret\n").unwrap();
+
+        let secret = SecretObject::file(file.path().to_string_lossy().into_owned());
+        let result = resolver.resolve_secret(&secret).await.unwrap();
+        assert_eq!(result, "file-secret");
+
+        let missing = SecretObject::file("/nonexistent/secret".to_string());
+        assert!(resolver.resolve_secret(&missing).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_resolve_optional_secret() {
         let resolver = SecretResolver::new();
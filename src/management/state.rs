@@ -1,17 +1,31 @@
-use crate::management::services::config_provider_service::ConfigProviderService;
+use crate::management::db::DbPools;
+use crate::management::services::{
+    api_key_service::{ApiKeyService, StaticApiKey, auth_disabled_from_env},
+    config_provider_service::ConfigProviderService,
+};
 use axum::Router;
-use sqlx::PgPool;
 use std::sync::Arc;
 
 pub struct DbBasedConfigIntegration {
     pub router: Router,
     pub config_provider: Arc<ConfigProviderService>,
+    /// Authenticates the management API's requests.
+    pub api_key_service: Arc<ApiKeyService>,
 }
 
-pub async fn db_based_config_integration(pool: PgPool) -> anyhow::Result<DbBasedConfigIntegration> {
-    let (router, config_provider) = super::management_api_bundle(pool);
+pub async fn db_based_config_integration(
+    pools: DbPools,
+) -> anyhow::Result<DbBasedConfigIntegration> {
+    let static_api_keys = StaticApiKey::from_env()?;
+    let api_key_service = Arc::new(
+        ApiKeyService::new(pools.clone(), static_api_keys)
+            .with_auth_disabled(auth_disabled_from_env()),
+    );
+    let (router, config_provider) =
+        super::management_api_bundle_with_api_key_service(pools, api_key_service.clone());
     Ok(DbBasedConfigIntegration {
         router,
         config_provider,
+        api_key_service,
     })
 }
@@ -0,0 +1,196 @@
+//! The gateway's metrics. Code records through the re-exported `counter!`, `gauge!` and
+//! `histogram!` macros; the process-wide recorder serves every value on `/metrics` and, once
+//! `general.otlp_metrics` is configured, also pushes it over OTLP.
+
+mod otlp;
+
+pub use axum_prometheus::metrics::{counter, gauge, histogram};
+pub use otlp::{OtlpMetrics, validate_otlp_metrics};
+
+use axum_prometheus::metrics::{
+    self as metrics_api, Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName,
+    Metadata, Recorder, SharedString, Unit,
+};
+use axum_prometheus::metrics_exporter_prometheus::{
+    Matcher, PrometheusBuilder, PrometheusHandle, PrometheusRecorder,
+};
+use axum_prometheus::utils::SECONDS_DURATION_BUCKETS;
+use axum_prometheus::{PrometheusMetricLayer, PrometheusMetricLayerBuilder};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// Prefix of the HTTP metrics recorded for every request.
+const HTTP_METRICS_PREFIX: &str = "traceloop_hub";
+const PROMETHEUS_UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The HTTP metrics layer and the handle rendering `/metrics`.
+///
+/// The recorder behind them is installed on first use, so every router in the process shares
+/// it.
+pub fn prometheus_layer() -> (PrometheusMetricLayer<'static>, PrometheusHandle) {
+    static LAYER: OnceLock<(PrometheusMetricLayer<'static>, PrometheusHandle)> = OnceLock::new();
+    LAYER
+        .get_or_init(|| {
+            // Installed first, so the builder's metric descriptions reach it.
+            let handle = install();
+            PrometheusMetricLayerBuilder::new()
+                .with_ignore_patterns(&["/metrics", "/health"])
+                .with_prefix(HTTP_METRICS_PREFIX)
+                .with_metrics_from_fn(|| handle)
+                .build_pair()
+        })
+        .clone()
+}
+
+/// Installs the global recorder, unless one is already installed.
+///
+/// Runs from [`prometheus_layer`]; call it directly to record metrics without a router.
+pub fn install() -> PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE
+        .get_or_init(|| {
+            let prometheus = PrometheusBuilder::new()
+                .set_buckets_for_metric(
+                    Matcher::Full(format!(
+                        "{HTTP_METRICS_PREFIX}_http_requests_duration_seconds"
+                    )),
+                    SECONDS_DURATION_BUCKETS,
+                )
+                .expect("duration buckets are not empty")
+                .build_recorder();
+            let handle = prometheus.handle();
+            let upkeep = handle.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(PROMETHEUS_UPKEEP_INTERVAL).await;
+                    upkeep.run_upkeep();
+                }
+            });
+            let recorder = FanoutRecorder {
+                prometheus,
+                gauges: Mutex::default(),
+            };
+            if metrics_api::set_global_recorder(recorder).is_err() {
+                tracing::warn!("A metrics recorder is already installed; /metrics stays empty");
+            }
+            handle
+        })
+        .clone()
+}
+
+/// Records every metric in Prometheus and forwards it to [`OtlpMetrics`].
+struct FanoutRecorder {
+    prometheus: PrometheusRecorder,
+    /// Current gauge values, since OTLP gauges take absolute values but `gauge!` also moves
+    /// them by a delta.
+    gauges: Mutex<HashMap<Key, Arc<AtomicU64>>>,
+}
+
+impl Recorder for FanoutRecorder {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.prometheus.describe_counter(key, unit, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.prometheus.describe_gauge(key, unit, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.prometheus.describe_histogram(key, unit, description);
+    }
+
+    fn register_counter(&self, key: &Key, metadata: &Metadata<'_>) -> Counter {
+        Counter::from_arc(Arc::new(FanoutCounter {
+            prometheus: self.prometheus.register_counter(key, metadata),
+            key: key.clone(),
+        }))
+    }
+
+    fn register_gauge(&self, key: &Key, metadata: &Metadata<'_>) -> Gauge {
+        let value = self
+            .gauges
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        Gauge::from_arc(Arc::new(FanoutGauge {
+            prometheus: self.prometheus.register_gauge(key, metadata),
+            key: key.clone(),
+            value,
+        }))
+    }
+
+    fn register_histogram(&self, key: &Key, metadata: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(Arc::new(FanoutHistogram {
+            prometheus: self.prometheus.register_histogram(key, metadata),
+            key: key.clone(),
+        }))
+    }
+}
+
+struct FanoutCounter {
+    prometheus: Counter,
+    key: Key,
+}
+
+impl CounterFn for FanoutCounter {
+    fn increment(&self, value: u64) {
+        self.prometheus.increment(value);
+        OtlpMetrics::global().add_to_counter(&self.key, value);
+    }
+
+    fn absolute(&self, value: u64) {
+        // Prometheus only: OTLP counters can only be added to.
+        self.prometheus.absolute(value);
+    }
+}
+
+struct FanoutGauge {
+    prometheus: Gauge,
+    key: Key,
+    value: Arc<AtomicU64>,
+}
+
+impl FanoutGauge {
+    fn update(&self, f: impl Fn(f64) -> f64) {
+        let previous = self
+            .value
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |bits| {
+                Some(f(f64::from_bits(bits)).to_bits())
+            })
+            .unwrap_or_else(|bits| bits);
+        OtlpMetrics::global().set_gauge(&self.key, f(f64::from_bits(previous)));
+    }
+}
+
+impl GaugeFn for FanoutGauge {
+    fn increment(&self, value: f64) {
+        self.prometheus.increment(value);
+        self.update(|current| current + value);
+    }
+
+    fn decrement(&self, value: f64) {
+        self.prometheus.decrement(value);
+        self.update(|current| current - value);
+    }
+
+    fn set(&self, value: f64) {
+        self.prometheus.set(value);
+        self.update(|_| value);
+    }
+}
+
+struct FanoutHistogram {
+    prometheus: Histogram,
+    key: Key,
+}
+
+impl HistogramFn for FanoutHistogram {
+    fn record(&self, value: f64) {
+        self.prometheus.record(value);
+        OtlpMetrics::global().record_in_histogram(&self.key, value);
+    }
+}
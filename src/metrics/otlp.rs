@@ -0,0 +1,201 @@
+use crate::management::services::secret_resolver::SecretResolver;
+use crate::types::OtlpMetricsConfig;
+use anyhow::Result;
+use axum_prometheus::metrics::Key;
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::{Counter, Gauge, Histogram, Meter, MeterProvider};
+use opentelemetry_otlp::{MetricExporter, WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::runtime;
+use reqwest::Url;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const METER_NAME: &str = "traceloop_hub";
+
+/// Process-wide OTLP export of the metrics recorded through [`crate::metrics`].
+///
+/// Nothing is exported until `general.otlp_metrics` is configured.
+#[derive(Default)]
+pub struct OtlpMetrics {
+    sink: RwLock<Option<Arc<Sink>>>,
+    /// Bumped on every `configure`, so a slow setup can't replace a newer one.
+    generation: AtomicU64,
+}
+
+impl OtlpMetrics {
+    /// Exporter shared by every pipeline in the process.
+    pub fn global() -> Arc<OtlpMetrics> {
+        static METRICS: OnceLock<Arc<OtlpMetrics>> = OnceLock::new();
+        METRICS.get_or_init(Default::default).clone()
+    }
+
+    /// Replaces the running export, stopping it when `config` is `None`. The new export
+    /// starts once its headers are resolved.
+    pub fn configure(self: &Arc<Self>, config: Option<&OtlpMetricsConfig>) {
+        let generation = self.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        let Some(config) = config.cloned() else {
+            self.replace(None);
+            return;
+        };
+        let metrics = self.clone();
+        tokio::spawn(async move {
+            let provider = match meter_provider(&config).await {
+                Ok(provider) => provider,
+                Err(e) => {
+                    warn!("OTLP metrics export disabled: {e:#}");
+                    return;
+                }
+            };
+            if metrics.generation.load(Ordering::Acquire) == generation {
+                debug!("Exporting metrics to {}", config.endpoint);
+                metrics.replace(Some(provider));
+            } else {
+                shutdown(provider);
+            }
+        });
+    }
+
+    /// Exports through `provider` until the next `configure`, for providers built with
+    /// their own readers, such as tests' in-memory exporters.
+    pub fn install(&self, provider: SdkMeterProvider) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.replace(Some(provider));
+    }
+
+    fn replace(&self, provider: Option<SdkMeterProvider>) {
+        let sink = provider.map(|provider| Arc::new(Sink::new(provider)));
+        let previous = std::mem::replace(&mut *self.sink.write().unwrap(), sink);
+        if let Some(previous) = previous {
+            shutdown(previous.provider.clone());
+        }
+    }
+
+    fn sink(&self) -> Option<Arc<Sink>> {
+        self.sink.read().unwrap().clone()
+    }
+
+    pub(super) fn add_to_counter(&self, key: &Key, value: u64) {
+        if let Some(sink) = self.sink() {
+            sink.counter(key.name()).add(value, &attributes(key));
+        }
+    }
+
+    pub(super) fn set_gauge(&self, key: &Key, value: f64) {
+        if let Some(sink) = self.sink() {
+            sink.gauge(key.name()).record(value, &attributes(key));
+        }
+    }
+
+    pub(super) fn record_in_histogram(&self, key: &Key, value: f64) {
+        if let Some(sink) = self.sink() {
+            sink.histogram(key.name()).record(value, &attributes(key));
+        }
+    }
+}
+
+/// A meter provider and the instruments created on it so far, by metric name.
+struct Sink {
+    provider: SdkMeterProvider,
+    meter: Meter,
+    counters: Mutex<HashMap<String, Counter<u64>>>,
+    gauges: Mutex<HashMap<String, Gauge<f64>>>,
+    histograms: Mutex<HashMap<String, Histogram<f64>>>,
+}
+
+impl Sink {
+    fn new(provider: SdkMeterProvider) -> Self {
+        Self {
+            meter: provider.meter(METER_NAME),
+            provider,
+            counters: Mutex::default(),
+            gauges: Mutex::default(),
+            histograms: Mutex::default(),
+        }
+    }
+
+    fn counter(&self, name: &str) -> Counter<u64> {
+        let mut counters = self.counters.lock().unwrap();
+        counters
+            .entry(name.to_string())
+            .or_insert_with(|| self.meter.u64_counter(name.to_string()).build())
+            .clone()
+    }
+
+    fn gauge(&self, name: &str) -> Gauge<f64> {
+        let mut gauges = self.gauges.lock().unwrap();
+        gauges
+            .entry(name.to_string())
+            .or_insert_with(|| self.meter.f64_gauge(name.to_string()).build())
+            .clone()
+    }
+
+    fn histogram(&self, name: &str) -> Histogram<f64> {
+        let mut histograms = self.histograms.lock().unwrap();
+        histograms
+            .entry(name.to_string())
+            .or_insert_with(|| self.meter.f64_histogram(name.to_string()).build())
+            .clone()
+    }
+}
+
+fn attributes(key: &Key) -> Vec<KeyValue> {
+    key.labels()
+        .map(|label| KeyValue::new(label.key().to_string(), label.value().to_string()))
+        .collect()
+}
+
+async fn meter_provider(config: &OtlpMetricsConfig) -> Result<SdkMeterProvider> {
+    let resolver = SecretResolver::new();
+    let mut headers = HashMap::with_capacity(config.headers.len());
+    for (name, value) in &config.headers {
+        headers.insert(name.clone(), resolver.resolve_secret(value).await?);
+    }
+    let exporter = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(config.endpoint.clone())
+        .with_headers(headers)
+        .build()?;
+    let reader = PeriodicReader::builder(exporter, runtime::Tokio)
+        .with_interval(Duration::from_secs(config.export_interval_seconds))
+        .build();
+    let mut resource = vec![KeyValue::new("service.name", config.service_name.clone())];
+    if let Some(environment) = &config.environment {
+        resource.push(KeyValue::new("deployment.environment", environment.clone()));
+    }
+    Ok(SdkMeterProvider::builder()
+        .with_reader(reader)
+        .with_resource(Resource::new(resource))
+        .build())
+}
+
+/// Flushes and stops `provider` off the runtime's worker threads, which it blocks on.
+fn shutdown(provider: SdkMeterProvider) {
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = provider.shutdown() {
+            warn!("Failed to stop OTLP metrics export: {e}");
+        }
+    });
+}
+
+pub fn validate_otlp_metrics(config: &OtlpMetricsConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    let valid =
+        Url::parse(&config.endpoint).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+    if !valid {
+        errors.push("general.otlp_metrics.endpoint must be an http(s) URL.".to_string());
+    }
+    if config.export_interval_seconds == 0 {
+        errors.push(
+            "general.otlp_metrics.export_interval_seconds must be greater than 0.".to_string(),
+        );
+    }
+    if config.service_name.trim().is_empty() {
+        errors.push("general.otlp_metrics.service_name must not be empty.".to_string());
+    }
+    errors
+}
@@ -5,12 +5,14 @@ use std::collections::HashMap;
 use utoipa::ToSchema;
 
 use super::content::ChatCompletionMessage;
-use super::logprob::LogProbs;
+use super::logprob::ChoiceLogprobs;
+use super::prediction::Prediction;
 use super::response_format::ResponseFormat;
 use super::streaming::ChatCompletionChunk;
 use super::tool_choice::ToolChoice;
 use super::tool_definition::ToolDefinition;
 use super::usage::Usage;
+use crate::types::RequestPriority;
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ReasoningConfig {
@@ -126,6 +128,58 @@ pub struct ChatCompletionRequest {
     pub reasoning: Option<ReasoningConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+    /// Predicted outputs. Only OpenAI and Azure honour it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prediction: Option<Prediction>,
+    /// Settings of the web search done by OpenAI's search models. Only OpenAI and Azure
+    /// honour it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_search_options: Option<serde_json::Value>,
+    /// Provider-specific parameters the hub doesn't map, merged into the provider's request
+    /// body after translation. Pipelines accept it only if their `parameter-policy` plugin
+    /// sets `allow_extra_body`.
+    #[serde(default, skip_serializing)]
+    pub extra_body: Option<serde_json::Value>,
+    /// Set by the gateway from `x-hub-priority`; providers map it to their own service tiers.
+    #[serde(skip)]
+    pub priority: Option<RequestPriority>,
+}
+
+/// Request header selecting a provider-neutral priority: `low`, `default` or `high`.
+pub const PRIORITY_HEADER: &str = "x-hub-priority";
+
+/// OpenAI limits for request `metadata`.
+pub const MAX_METADATA_PAIRS: usize = 16;
+pub const MAX_METADATA_KEY_CHARS: usize = 64;
+pub const MAX_METADATA_VALUE_CHARS: usize = 512;
+
+/// Checks metadata against OpenAI's limits on pair count and key/value length.
+pub fn validate_metadata<'a>(
+    metadata: impl ExactSizeIterator<Item = (&'a String, &'a String)>,
+) -> Result<(), String> {
+    if metadata.len() > MAX_METADATA_PAIRS {
+        return Err(format!(
+            "metadata may contain at most {MAX_METADATA_PAIRS} pairs, got {}",
+            metadata.len()
+        ));
+    }
+    for (key, value) in metadata {
+        if key.chars().count() > MAX_METADATA_KEY_CHARS {
+            return Err(format!(
+                "metadata key '{key}' exceeds {MAX_METADATA_KEY_CHARS} characters"
+            ));
+        }
+        if value.chars().count() > MAX_METADATA_VALUE_CHARS {
+            return Err(format!(
+                "metadata value for '{key}' exceeds {MAX_METADATA_VALUE_CHARS} characters"
+            ));
+        }
+    }
+    Ok(())
 }
 
 // Note: ChatCompletionResponse cannot derive ToSchema due to BoxStream
@@ -146,6 +200,9 @@ pub struct ChatCompletion {
     pub choices: Vec<ChatCompletionChoice>,
     pub usage: Usage,
     pub system_fingerprint: Option<String>,
+    /// Service tier the provider used to process the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone, ToSchema)]
@@ -155,5 +212,11 @@ pub struct ChatCompletionChoice {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub finish_reason: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub logprobs: Option<LogProbs>,
+    pub logprobs: Option<ChoiceLogprobs>,
+    /// Provider safety ratings, passed through as an extension field when reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety_ratings: Option<serde_json::Value>,
+    /// The stop sequence that ended the completion, an extension field set when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequence: Option<String>,
 }
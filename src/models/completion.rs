@@ -36,6 +36,11 @@ pub struct CompletionRequest {
     pub logit_bias: Option<HashMap<String, i32>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+    /// Provider-specific parameters the hub doesn't map, merged into the provider's request
+    /// body after translation. Pipelines accept it only if their `parameter-policy` plugin
+    /// sets `allow_extra_body`.
+    #[serde(default, skip_serializing)]
+    pub extra_body: Option<serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Clone, ToSchema)]
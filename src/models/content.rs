@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use utoipa::ToSchema;
 
 use super::tool_calls::ChatMessageToolCall;
@@ -30,4 +33,133 @@ pub struct ChatCompletionMessage {
     pub tool_call_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refusal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<Annotation>>,
+}
+
+/// A citation attached to a message, e.g. the sources of a web search. Fields of annotation
+/// types the gateway doesn't model are kept in `extra` so they reach the client unchanged.
+#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
+pub struct Annotation {
+    #[serde(rename = "type")]
+    pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url_citation: Option<UrlCitation>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
+pub struct UrlCitation {
+    pub start_index: u32,
+    pub end_index: u32,
+    pub url: String,
+    pub title: String,
+}
+
+/// Whether a message carries instructions rather than conversation. Newer OpenAI models use
+/// `developer` where older ones use `system`.
+pub fn is_instruction_role(role: &str) -> bool {
+    matches!(role, "system" | "developer")
+}
+
+impl ChatMessageContent {
+    /// The text of each text part, keeping part boundaries.
+    pub fn text_parts(&self) -> Vec<&str> {
+        match self {
+            ChatMessageContent::String(text) => vec![text.as_str()],
+            ChatMessageContent::Array(parts) => parts
+                .iter()
+                .filter(|part| part.r#type == "text")
+                .map(|part| part.text.as_str())
+                .collect(),
+        }
+    }
+}
+
+impl ChatCompletionMessage {
+    /// Moves a user or assistant message's `name` into its text as a `name: ` prefix, for
+    /// providers without the field.
+    pub fn inline_name(&mut self) {
+        if !matches!(self.role.as_str(), "user" | "assistant") {
+            return;
+        }
+        let Some(name) = self.name.take() else {
+            return;
+        };
+        let prefix = format!("{name}: ");
+        match &mut self.content {
+            Some(ChatMessageContent::String(text)) => text.insert_str(0, &prefix),
+            Some(ChatMessageContent::Array(parts)) => {
+                match parts.iter_mut().find(|part| part.r#type == "text") {
+                    Some(part) => part.text.insert_str(0, &prefix),
+                    None => parts.insert(
+                        0,
+                        ChatMessageContentPart {
+                            r#type: "text".to_string(),
+                            text: prefix,
+                        },
+                    ),
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(
+        role: &str,
+        content: ChatMessageContent,
+        name: Option<&str>,
+    ) -> ChatCompletionMessage {
+        ChatCompletionMessage {
+            role: role.to_string(),
+            content: Some(content),
+            name: name.map(str::to_string),
+            tool_calls: None,
+            tool_call_id: None,
+            refusal: None,
+            annotations: None,
+        }
+    }
+
+    fn text_part(text: &str) -> ChatMessageContentPart {
+        ChatMessageContentPart {
+            r#type: "text".to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_inline_name_prefixes_first_text_part() {
+        let mut named = message(
+            "user",
+            ChatMessageContent::Array(vec![text_part("Hi"), text_part("there")]),
+            Some("alice"),
+        );
+        named.inline_name();
+
+        assert!(named.name.is_none());
+        assert_eq!(
+            named.content.unwrap().text_parts(),
+            vec!["alice: Hi", "there"]
+        );
+    }
+
+    #[test]
+    fn test_inline_name_leaves_tool_results_alone() {
+        let mut tool = message(
+            "tool",
+            ChatMessageContent::String("72F".to_string()),
+            Some("get_weather"),
+        );
+        tool.inline_name();
+
+        assert_eq!(tool.name.as_deref(), Some("get_weather"));
+        assert_eq!(tool.content.unwrap().text_parts(), vec!["72F"]);
+    }
 }
@@ -12,6 +12,21 @@ pub struct EmbeddingsRequest {
     pub user: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub encoding_format: Option<String>,
+    /// Output vector size for models that support shortening (e.g. text-embedding-3).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+    /// Provider-specific parameters the hub doesn't map, merged into the provider's request
+    /// body after translation. Pipelines accept it only if their `parameter-policy` plugin
+    /// sets `allow_extra_body`.
+    #[serde(default, skip_serializing)]
+    pub extra_body: Option<Value>,
+}
+
+impl EmbeddingsRequest {
+    /// True if the caller asked for base64-encoded vectors.
+    pub fn wants_base64(&self) -> bool {
+        self.encoding_format.as_deref() == Some("base64")
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, ToSchema)]
@@ -31,6 +46,17 @@ pub struct EmbeddingsResponse {
     pub usage: EmbeddingUsage,
 }
 
+impl EmbeddingsResponse {
+    /// Converts float vectors to base64, for upstreams that ignore `encoding_format`.
+    pub fn encode_base64(&mut self) {
+        for item in &mut self.data {
+            if let Embedding::Float(vector) = &item.embedding {
+                item.embedding = Embedding::String(encode_floats_base64(vector));
+            }
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, ToSchema)]
 pub struct Embeddings {
     pub object: String,
@@ -45,3 +71,77 @@ pub enum Embedding {
     Float(Vec<f32>),
     Json(Value),
 }
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes a vector the way OpenAI does: little-endian f32 bytes, standard padded base64.
+pub fn encode_floats_base64(vector: &[f32]) -> String {
+    let bytes: Vec<u8> = vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = match *chunk {
+            [a, b, c] => u32::from_be_bytes([0, a, b, c]),
+            [a, b] => u32::from_be_bytes([0, a, b, 0]),
+            [a] => u32::from_be_bytes([0, a, 0, 0]),
+            _ => unreachable!(),
+        };
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (triple >> (18 - 6 * i)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_floats_base64() {
+        // 1.0f32 is 00 00 80 3f little-endian.
+        assert_eq!(encode_floats_base64(&[1.0]), "AACAPw==");
+        assert_eq!(encode_floats_base64(&[1.0, -2.0]), "AACAPwAAAMA=");
+        assert_eq!(encode_floats_base64(&[]), "");
+    }
+
+    #[test]
+    fn test_encode_base64_leaves_strings_untouched() {
+        let mut response = EmbeddingsResponse {
+            object: "list".to_string(),
+            data: vec![
+                Embeddings {
+                    object: "embedding".to_string(),
+                    embedding: Embedding::Float(vec![1.0]),
+                    index: 0,
+                },
+                Embeddings {
+                    object: "embedding".to_string(),
+                    embedding: Embedding::String("already-encoded".to_string()),
+                    index: 1,
+                },
+            ],
+            model: "text-embedding-3-small".to_string(),
+            usage: EmbeddingUsage::default(),
+        };
+        response.encode_base64();
+
+        let encoded: Vec<String> = response
+            .data
+            .iter()
+            .map(|item| match &item.embedding {
+                Embedding::String(value) => value.clone(),
+                _ => panic!("expected base64 string"),
+            })
+            .collect();
+        assert_eq!(encoded, ["AACAPw==", "already-encoded"]);
+    }
+}
@@ -1,19 +1,6 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
-pub struct LogProbs {
-    pub content: Vec<LogProbContent>,
-}
-
-#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
-pub struct LogProbContent {
-    pub token: String,
-    pub logprob: f32,
-    pub bytes: Vec<u8>,
-    pub top_logprobs: Vec<TopLogprob>,
-}
-
 #[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
 pub struct TopLogprob {
     pub token: String,
@@ -0,0 +1,366 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::chat::{ChatCompletion, ChatCompletionRequest};
+use super::content::{ChatCompletionMessage, ChatMessageContent};
+use super::tool_calls::{ChatMessageToolCall, FunctionCall};
+use super::tool_choice::{
+    ChatCompletionNamedToolChoice, Function, SimpleToolChoice, ToolChoice, ToolType,
+};
+use super::tool_definition::{FunctionDefinition, FunctionTool, ToolDefinition};
+use crate::providers::anthropic::models::{
+    ContentBlock, ToolChoice as AnthropicToolChoice, ToolParam, Usage,
+};
+
+/// Request body of the Anthropic-compatible `POST /messages` endpoint.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct MessagesRequest {
+    pub model: String,
+    pub max_tokens: u32,
+    pub messages: Vec<InputMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<ChatMessageContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolParam>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<AnthropicToolChoice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MessagesMetadata>,
+}
+
+/// Request body of the Anthropic-compatible `POST /messages/count_tokens` endpoint: the
+/// prompt fields of a messages request.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CountTokensRequest {
+    pub model: String,
+    pub messages: Vec<InputMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<ChatMessageContent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolParam>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<AnthropicToolChoice>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct MessagesMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct InputMessage {
+    pub role: String,
+    pub content: InputContent,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum InputContent {
+    Text(String),
+    Blocks(Vec<InputContentBlock>),
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<ChatMessageContent>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        is_error: Option<bool>,
+    },
+}
+
+/// Response body of `POST /messages`, in Anthropic's message format.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct MessagesResponse {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub role: String,
+    pub model: String,
+    pub content: Vec<ContentBlock>,
+    pub stop_reason: Option<String>,
+    pub stop_sequence: Option<String>,
+    pub usage: Usage,
+}
+
+fn content_text(content: &ChatMessageContent) -> String {
+    match content {
+        ChatMessageContent::String(text) => text.clone(),
+        ChatMessageContent::Array(parts) => parts
+            .iter()
+            .filter(|part| part.r#type == "text")
+            .map(|part| part.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn chat_message(role: &str, content: Option<String>) -> ChatCompletionMessage {
+    ChatCompletionMessage {
+        role: role.to_string(),
+        content: content.map(ChatMessageContent::String),
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+        refusal: None,
+        annotations: None,
+    }
+}
+
+/// Parses tool call arguments into the object Anthropic expects as `input`.
+pub(crate) fn tool_input(arguments: &str) -> Value {
+    if arguments.trim().is_empty() {
+        return Value::Object(Default::default());
+    }
+    serde_json::from_str(arguments).unwrap_or_else(|e| {
+        tracing::warn!("Tool call arguments are not valid JSON: {}", e);
+        Value::Object(Default::default())
+    })
+}
+
+/// Anthropic's `stop_reason` when generation ended on one of the request's stop sequences.
+pub const STOP_SEQUENCE_REASON: &str = "stop_sequence";
+
+/// Maps an OpenAI `finish_reason` onto Anthropic's `stop_reason`.
+pub fn stop_reason(finish_reason: &str) -> &'static str {
+    match finish_reason {
+        "length" => "max_tokens",
+        "tool_calls" | "function_call" => "tool_use",
+        "content_filter" => "refusal",
+        _ => "end_turn",
+    }
+}
+
+impl InputMessage {
+    /// Tool results become `tool` messages, placed before the rest of the turn so they
+    /// directly follow the assistant message that requested them.
+    fn into_chat_messages(self) -> Vec<ChatCompletionMessage> {
+        let blocks = match self.content {
+            InputContent::Text(text) => return vec![chat_message(&self.role, Some(text))],
+            InputContent::Blocks(blocks) => blocks,
+        };
+
+        let mut messages = Vec::new();
+        let mut text = Vec::new();
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block {
+                InputContentBlock::Text { text: part } => text.push(part),
+                InputContentBlock::ToolUse { id, name, input } => {
+                    tool_calls.push(ChatMessageToolCall {
+                        id,
+                        function: FunctionCall {
+                            name,
+                            arguments: input.to_string(),
+                        },
+                        r#type: "function".to_string(),
+                    });
+                }
+                InputContentBlock::ToolResult {
+                    tool_use_id,
+                    content,
+                    is_error,
+                } => {
+                    let output = content.as_ref().map(content_text).unwrap_or_default();
+                    let output = if is_error == Some(true) {
+                        format!("Error: {output}")
+                    } else {
+                        output
+                    };
+                    messages.push(ChatCompletionMessage {
+                        tool_call_id: Some(tool_use_id),
+                        ..chat_message("tool", Some(output))
+                    });
+                }
+            }
+        }
+
+        if !text.is_empty() || !tool_calls.is_empty() {
+            let content = (!text.is_empty()).then(|| text.join("\n"));
+            messages.push(ChatCompletionMessage {
+                tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                ..chat_message(&self.role, content)
+            });
+        }
+        messages
+    }
+}
+
+impl From<MessagesRequest> for ChatCompletionRequest {
+    fn from(request: MessagesRequest) -> Self {
+        let mut messages: Vec<ChatCompletionMessage> = request
+            .system
+            .as_ref()
+            .map(|system| chat_message("system", Some(content_text(system))))
+            .into_iter()
+            .collect();
+        for message in request.messages {
+            messages.extend(message.into_chat_messages());
+        }
+
+        let (tool_choice, disable_parallel_tool_use) = match request.tool_choice {
+            None => (None, false),
+            Some(AnthropicToolChoice::Auto {
+                disable_parallel_tool_use,
+            }) => (
+                Some(ToolChoice::Simple(SimpleToolChoice::Auto)),
+                disable_parallel_tool_use,
+            ),
+            Some(AnthropicToolChoice::Any {
+                disable_parallel_tool_use,
+            }) => (
+                Some(ToolChoice::Simple(SimpleToolChoice::Required)),
+                disable_parallel_tool_use,
+            ),
+            Some(AnthropicToolChoice::Tool {
+                name,
+                disable_parallel_tool_use,
+            }) => (
+                Some(ToolChoice::Named(ChatCompletionNamedToolChoice {
+                    tool_type: ToolType::Function,
+                    function: Function { name },
+                })),
+                disable_parallel_tool_use,
+            ),
+        };
+
+        let tools: Vec<ToolDefinition> = request
+            .tools
+            .into_iter()
+            .map(|tool| {
+                ToolDefinition::Function(FunctionTool {
+                    function: FunctionDefinition {
+                        name: tool.name,
+                        description: tool.description,
+                        parameters: match tool.input_schema {
+                            Value::Object(schema) => Some(schema.into_iter().collect()),
+                            _ => None,
+                        },
+                        strict: None,
+                    },
+                    tool_type: "function".to_string(),
+                })
+            })
+            .collect();
+
+        ChatCompletionRequest {
+            model: request.model,
+            messages,
+            temperature: request.temperature,
+            top_p: request.top_p,
+            n: None,
+            stream: request.stream,
+            stop: request.stop_sequences,
+            max_tokens: Some(request.max_tokens),
+            max_completion_tokens: None,
+            parallel_tool_calls: disable_parallel_tool_use.then_some(false),
+            presence_penalty: None,
+            frequency_penalty: None,
+            logit_bias: None,
+            tool_choice,
+            tools: (!tools.is_empty()).then_some(tools),
+            user: request.metadata.and_then(|metadata| metadata.user_id),
+            logprobs: None,
+            top_logprobs: None,
+            response_format: None,
+            reasoning: None,
+            reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
+        }
+    }
+}
+
+impl From<CountTokensRequest> for ChatCompletionRequest {
+    fn from(request: CountTokensRequest) -> Self {
+        let mut payload = ChatCompletionRequest::from(MessagesRequest {
+            model: request.model,
+            max_tokens: 0,
+            messages: request.messages,
+            system: request.system,
+            temperature: None,
+            top_p: None,
+            stop_sequences: None,
+            stream: None,
+            tools: request.tools,
+            tool_choice: request.tool_choice,
+            metadata: None,
+        });
+        // Counting doesn't generate anything, so there is no output budget to carry over.
+        payload.max_tokens = None;
+        payload
+    }
+}
+
+impl From<ChatCompletion> for MessagesResponse {
+    fn from(completion: ChatCompletion) -> Self {
+        let mut content = Vec::new();
+        let mut finish_reason = None;
+        let mut stop_sequence = None;
+        if let Some(choice) = completion.choices.into_iter().next() {
+            finish_reason = choice.finish_reason;
+            stop_sequence = choice.stop_sequence;
+            let message = choice.message;
+            let text = message.content.as_ref().map(content_text);
+            if let Some(text) = text.or(message.refusal).filter(|text| !text.is_empty()) {
+                content.push(ContentBlock::Text { text });
+            }
+            for tool_call in message.tool_calls.unwrap_or_default() {
+                content.push(ContentBlock::ToolUse {
+                    id: tool_call.id,
+                    input: tool_input(&tool_call.function.arguments),
+                    name: tool_call.function.name,
+                });
+            }
+        }
+
+        let prompt_details = completion.usage.prompt_tokens_details.as_ref();
+        MessagesResponse {
+            id: completion.id,
+            r#type: "message".to_string(),
+            role: "assistant".to_string(),
+            model: completion.model,
+            content,
+            stop_reason: match &stop_sequence {
+                Some(_) => Some(STOP_SEQUENCE_REASON.to_string()),
+                None => finish_reason.map(|reason| stop_reason(&reason).to_string()),
+            },
+            stop_sequence,
+            usage: Usage {
+                // Anthropic's `input_tokens` leaves out the cached ones.
+                input_tokens: completion.usage.prompt_tokens.saturating_sub(
+                    completion.usage.cached_tokens() + completion.usage.cache_creation_tokens(),
+                ),
+                output_tokens: completion.usage.completion_tokens,
+                cache_creation_input_tokens: prompt_details
+                    .and_then(|details| details.cache_creation_tokens),
+                cache_read_input_tokens: prompt_details.and_then(|details| details.cached_tokens),
+                service_tier: completion.service_tier,
+            },
+        }
+    }
+}
@@ -3,6 +3,8 @@ pub mod completion;
 pub mod content;
 pub mod embeddings;
 pub mod logprob;
+pub mod messages;
+pub mod prediction;
 pub mod response_format;
 pub mod responses;
 pub mod streaming;
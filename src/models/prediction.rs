@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::content::ChatMessageContent;
+
+/// OpenAI predicted outputs: known content of the response, such as the file being edited,
+/// that lets the model skip generating matching tokens.
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct Prediction {
+    /// Always `content` for now.
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub content: ChatMessageContent,
+}
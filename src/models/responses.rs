@@ -1,4 +1,7 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::providers::capabilities::Capabilities;
+use crate::types::ModelDeprecation;
 
 #[derive(Serialize)]
 pub struct ModelListResponse {
@@ -11,4 +14,15 @@ pub struct ModelInfoResponse {
     pub id: String,
     pub object: String, // always "model"
     pub owned_by: String,
+    #[serde(flatten)]
+    pub deprecation: ModelDeprecation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<Capabilities>,
+}
+
+/// Query parameters for `GET /models`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ModelListQuery {
+    #[serde(default)]
+    pub include_capabilities: bool,
 }
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use super::content::Annotation;
 use super::logprob::ChoiceLogprobs;
-use super::tool_calls::ChatMessageToolCall;
+use super::tool_calls::{ChatMessageToolCall, ChoiceDeltaToolCall};
 use super::usage::Usage;
 
 #[derive(Deserialize, Serialize, Clone, Debug, Default, ToSchema)]
@@ -20,9 +21,11 @@ pub struct ChoiceDelta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub role: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_calls: Option<Vec<ChatMessageToolCall>>,
+    pub tool_calls: Option<Vec<ChoiceDeltaToolCall>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<Annotation>>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
@@ -30,6 +33,9 @@ pub struct Choice {
     pub delta: ChoiceDelta,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub finish_reason: Option<String>,
+    /// The stop sequence that ended the stream, an extension field set when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequence: Option<String>,
     pub index: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logprobs: Option<ChoiceLogprobs>,
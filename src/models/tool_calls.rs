@@ -14,3 +14,75 @@ pub struct ChatMessageToolCall {
     #[serde(rename = "type")]
     pub r#type: String, // Using `function` as the only valid value
 }
+
+/// A fragment of a tool call in a streamed chunk. The first fragment of a call carries its
+/// id and name; later ones only append to `function.arguments`.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, ToSchema)]
+pub struct ChoiceDeltaToolCall {
+    /// Position of the call among the tool calls of its choice.
+    #[serde(default)]
+    pub index: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<FunctionCallDelta>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, ToSchema)]
+pub struct FunctionCallDelta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+}
+
+impl ChoiceDeltaToolCall {
+    /// A fragment holding the whole of `tool_call`.
+    pub fn complete(index: u32, tool_call: ChatMessageToolCall) -> Self {
+        Self {
+            index,
+            id: Some(tool_call.id),
+            r#type: Some(tool_call.r#type),
+            function: Some(FunctionCallDelta {
+                name: Some(tool_call.function.name),
+                arguments: Some(tool_call.function.arguments),
+            }),
+        }
+    }
+
+    /// Applies this fragment to `tool_call`, appending its arguments.
+    pub fn merge_into(&self, tool_call: &mut ChatMessageToolCall) {
+        if let Some(id) = self.id.as_ref().filter(|id| !id.is_empty()) {
+            tool_call.id.clone_from(id);
+        }
+        if let Some(r#type) = &self.r#type {
+            tool_call.r#type.clone_from(r#type);
+        }
+        if let Some(function) = &self.function {
+            if let Some(name) = function.name.as_ref().filter(|name| !name.is_empty()) {
+                tool_call.function.name.clone_from(name);
+            }
+            if let Some(arguments) = &function.arguments {
+                tool_call.function.arguments.push_str(arguments);
+            }
+        }
+    }
+
+    /// Applies this fragment to the call at its index, adding calls as needed.
+    pub fn merge_into_calls(&self, tool_calls: &mut Vec<ChatMessageToolCall>) {
+        let index = self.index as usize;
+        while tool_calls.len() <= index {
+            tool_calls.push(ChatMessageToolCall {
+                id: String::new(),
+                function: FunctionCall {
+                    arguments: String::new(),
+                    name: String::new(),
+                },
+                r#type: "function".to_string(),
+            });
+        }
+        self.merge_into(&mut tool_calls[index]);
+    }
+}
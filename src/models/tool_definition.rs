@@ -1,16 +1,103 @@
 use std::collections::HashMap;
+use std::fmt;
 
-use serde::{Deserialize, Serialize};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use utoipa::ToSchema;
 
+/// A tool the model may use: a function the client implements, or a tool built into the
+/// provider, such as OpenAI's `web_search_preview` or `file_search`.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum ToolDefinition {
+    Function(FunctionTool),
+    BuiltIn(BuiltInTool),
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
-pub struct ToolDefinition {
+pub struct FunctionTool {
     pub function: FunctionDefinition,
 
     #[serde(rename = "type")]
     pub tool_type: String, // Will only accept "function" value
 }
 
+/// A tool the provider runs itself. Only OpenAI and Azure have them; the settings of the
+/// tool type are passed through unchanged.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct BuiltInTool {
+    #[serde(rename = "type")]
+    pub tool_type: String,
+    #[serde(flatten)]
+    pub config: HashMap<String, serde_json::Value>,
+}
+
+impl ToolDefinition {
+    pub fn tool_type(&self) -> &str {
+        match self {
+            ToolDefinition::Function(tool) => &tool.tool_type,
+            ToolDefinition::BuiltIn(tool) => &tool.tool_type,
+        }
+    }
+
+    pub fn function(&self) -> Option<&FunctionDefinition> {
+        match self {
+            ToolDefinition::Function(tool) => Some(&tool.function),
+            ToolDefinition::BuiltIn(_) => None,
+        }
+    }
+
+    pub fn into_function(self) -> Option<FunctionDefinition> {
+        match self {
+            ToolDefinition::Function(tool) => Some(tool.function),
+            ToolDefinition::BuiltIn(_) => None,
+        }
+    }
+}
+
+/// Tools with a `function` are function tools, whatever their `type`; any other type is a
+/// built-in tool. Fields are read in place rather than buffered, so errors in a function
+/// definition keep their path.
+impl<'de> Deserialize<'de> for ToolDefinition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ToolVisitor;
+
+        impl<'de> Visitor<'de> for ToolVisitor {
+            type Value = ToolDefinition;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a tool definition")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<ToolDefinition, A::Error> {
+                let mut tool_type = None;
+                let mut function = None;
+                let mut config = HashMap::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "type" => tool_type = Some(map.next_value::<String>()?),
+                        "function" => function = Some(map.next_value::<FunctionDefinition>()?),
+                        _ => {
+                            config.insert(key, map.next_value::<serde_json::Value>()?);
+                        }
+                    }
+                }
+                let tool_type = tool_type.ok_or_else(|| de::Error::missing_field("type"))?;
+                match function {
+                    Some(function) => Ok(ToolDefinition::Function(FunctionTool {
+                        function,
+                        tool_type,
+                    })),
+                    None if tool_type == "function" => Err(de::Error::missing_field("function")),
+                    None => Ok(ToolDefinition::BuiltIn(BuiltInTool { tool_type, config })),
+                }
+            }
+        }
+
+        deserializer.deserialize_map(ToolVisitor)
+    }
+}
+
 /// A definition of a function that can be called.
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct FunctionDefinition {
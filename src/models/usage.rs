@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, ToSchema)]
 pub struct CompletionTokensDetails {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub accepted_prediction_tokens: Option<u32>,
@@ -13,12 +13,17 @@ pub struct CompletionTokensDetails {
     pub rejected_prediction_tokens: Option<u32>,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, ToSchema)]
 pub struct PromptTokensDetails {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub audio_tokens: Option<u32>,
+    /// Prompt tokens read from the provider's prompt cache.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cached_tokens: Option<u32>,
+    /// Prompt tokens written to the provider's prompt cache, which Anthropic bills above
+    /// the input price. OpenAI has no such field, so it's only used for pricing.
+    #[serde(skip)]
+    pub cache_creation_tokens: Option<u32>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, Default, ToSchema)]
@@ -32,6 +37,42 @@ pub struct Usage {
     pub prompt_tokens_details: Option<PromptTokensDetails>,
 }
 
+impl Usage {
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            completion_tokens_details: None,
+            prompt_tokens_details: None,
+        }
+    }
+
+    /// Part of `prompt_tokens` read from the prompt cache.
+    pub fn cached_tokens(&self) -> u32 {
+        self.prompt_tokens_details
+            .as_ref()
+            .and_then(|details| details.cached_tokens)
+            .unwrap_or(0)
+    }
+
+    /// Part of `prompt_tokens` written to the prompt cache.
+    pub fn cache_creation_tokens(&self) -> u32 {
+        self.prompt_tokens_details
+            .as_ref()
+            .and_then(|details| details.cache_creation_tokens)
+            .unwrap_or(0)
+    }
+
+    /// Part of `completion_tokens` spent on reasoning.
+    pub fn reasoning_tokens(&self) -> u32 {
+        self.completion_tokens_details
+            .as_ref()
+            .and_then(|details| details.reasoning_tokens)
+            .unwrap_or(0)
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, Default, ToSchema)]
 pub struct EmbeddingUsage {
     #[serde(skip_serializing_if = "Option::is_none")]
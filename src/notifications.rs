@@ -0,0 +1,409 @@
+use crate::management::dto::SecretObject;
+use crate::management::services::secret_resolver::SecretResolver;
+use crate::metrics::counter;
+use crate::pipelines::pipeline::PipelineScope;
+use crate::types::{
+    ErrorRateAlert, NotificationEventType, NotificationSeverity, NotificationsConfig,
+};
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::time::timeout_at;
+use tracing::warn;
+
+pub const DROPPED_METRIC: &str = "hub_notifications_dropped_total";
+pub const DELIVERY_FAILURES_METRIC: &str = "hub_notification_delivery_failures_total";
+/// Events waiting for delivery beyond this many are dropped rather than slowing requests.
+const QUEUE_CAPACITY: usize = 1024;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before the first retry; doubled for each further attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+
+impl NotificationEventType {
+    pub fn severity(self) -> NotificationSeverity {
+        match self {
+            NotificationEventType::BudgetWarning => NotificationSeverity::Warning,
+            NotificationEventType::BudgetExceeded | NotificationEventType::ErrorRate => {
+                NotificationSeverity::Critical
+            }
+        }
+    }
+}
+
+/// One event, as delivered to webhooks.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub event: NotificationEventType,
+    pub severity: NotificationSeverity,
+    pub pipeline: String,
+    pub message: String,
+    pub details: Value,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Notification {
+    pub fn new(
+        event: NotificationEventType,
+        pipeline: &str,
+        message: String,
+        details: Value,
+    ) -> Self {
+        Self {
+            event,
+            severity: event.severity(),
+            pipeline: pipeline.to_string(),
+            message,
+            details,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// The body posted to webhooks for one batch of events.
+pub fn webhook_payload(notifications: &[&Notification]) -> Value {
+    let text = notifications
+        .iter()
+        .map(|notification| notification.message.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    json!({
+        // Lets Slack incoming webhooks render the batch as is.
+        "text": text,
+        "notifications": notifications,
+    })
+}
+
+/// A webhook with its URL secret resolved.
+struct Webhook {
+    url: String,
+    events: Vec<NotificationEventType>,
+    min_severity: NotificationSeverity,
+}
+
+impl Webhook {
+    fn wants(&self, notification: &Notification) -> bool {
+        notification.severity >= self.min_severity
+            && (self.events.is_empty() || self.events.contains(&notification.event))
+    }
+}
+
+#[derive(Debug)]
+struct ErrorWindow {
+    started: Instant,
+    requests: u64,
+    errors: u64,
+    alerted: bool,
+}
+
+impl ErrorWindow {
+    fn starting(now: Instant) -> Self {
+        Self {
+            started: now,
+            requests: 0,
+            errors: 0,
+            alerted: false,
+        }
+    }
+}
+
+/// Tumbling per-pipeline windows of request outcomes, alerting at most once per window.
+#[derive(Debug)]
+struct ErrorRateMonitor {
+    alert: ErrorRateAlert,
+    windows: Mutex<HashMap<String, ErrorWindow>>,
+}
+
+impl ErrorRateMonitor {
+    fn new(alert: ErrorRateAlert) -> Self {
+        Self {
+            alert,
+            windows: Mutex::default(),
+        }
+    }
+
+    fn record_at(&self, now: Instant, pipeline: &str, failed: bool) -> Option<Notification> {
+        let window_length = Duration::from_secs(self.alert.window_seconds);
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows
+            .entry(pipeline.to_string())
+            .or_insert_with(|| ErrorWindow::starting(now));
+        if now.duration_since(window.started) >= window_length {
+            *window = ErrorWindow::starting(now);
+        }
+        window.requests += 1;
+        window.errors += u64::from(failed);
+
+        let crossed = window.requests >= u64::from(self.alert.min_requests)
+            && window.errors * 100 >= window.requests * u64::from(self.alert.threshold_percent);
+        if window.alerted || !crossed {
+            return None;
+        }
+        window.alerted = true;
+        let error_percent = window.errors as f64 * 100.0 / window.requests as f64;
+        Some(Notification::new(
+            NotificationEventType::ErrorRate,
+            pipeline,
+            format!(
+                "Pipeline '{pipeline}' failed {} of {} requests ({error_percent:.0}%) in {}s",
+                window.errors, window.requests, self.alert.window_seconds
+            ),
+            json!({
+                "errors": window.errors,
+                "requests": window.requests,
+                "error_percent": error_percent,
+                "threshold_percent": self.alert.threshold_percent,
+                "window_seconds": self.alert.window_seconds,
+            }),
+        ))
+    }
+}
+
+/// Handle to a running dispatcher. Dropping it lets the dispatcher flush its batch and exit.
+pub struct Notifier {
+    sender: mpsc::Sender<Notification>,
+    error_rate: Option<ErrorRateMonitor>,
+}
+
+impl Notifier {
+    /// Spawns the background task that batches and posts events. Needs a Tokio runtime.
+    pub fn start(config: &NotificationsConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(dispatch(config.clone(), receiver));
+        Self {
+            sender,
+            error_rate: config.error_rate.clone().map(ErrorRateMonitor::new),
+        }
+    }
+
+    /// Queues an event for delivery, dropping it if the queue is full.
+    pub fn publish(&self, notification: Notification) {
+        if self.sender.try_send(notification).is_err() {
+            counter!(DROPPED_METRIC).increment(1);
+        }
+    }
+
+    /// Counts a finished request towards its pipeline's error rate.
+    pub fn record_outcome(&self, pipeline: &str, failed: bool) {
+        let Some(monitor) = &self.error_rate else {
+            return;
+        };
+        if let Some(notification) = monitor.record_at(Instant::now(), pipeline, failed) {
+            self.publish(notification);
+        }
+    }
+}
+
+/// Entry point that a hub's budget and pipeline code publish through.
+///
+/// Publishing is a no-op until `general.notifications` is configured.
+#[derive(Default)]
+pub struct NotificationBus {
+    notifier: RwLock<Option<Arc<Notifier>>>,
+}
+
+impl NotificationBus {
+    /// Replaces the running notifier, stopping it when `config` is `None`.
+    pub fn configure(&self, config: Option<&NotificationsConfig>) {
+        let notifier = config.map(|config| Arc::new(Notifier::start(config)));
+        *self.notifier.write().unwrap() = notifier;
+    }
+
+    fn notifier(&self) -> Option<Arc<Notifier>> {
+        self.notifier.read().unwrap().clone()
+    }
+
+    pub fn publish(&self, notification: Notification) {
+        if let Some(notifier) = self.notifier() {
+            notifier.publish(notification);
+        }
+    }
+
+    pub fn record_outcome(&self, pipeline: &str, failed: bool) {
+        if let Some(notifier) = self.notifier() {
+            notifier.record_outcome(pipeline, failed);
+        }
+    }
+}
+
+/// Middleware counting each pipeline response towards the pipeline's error rate.
+pub async fn track_errors(
+    State(scope): State<Arc<PipelineScope>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    scope
+        .services
+        .notifications
+        .record_outcome(&scope.name, response.status().is_server_error());
+    response
+}
+
+async fn dispatch(config: NotificationsConfig, mut receiver: mpsc::Receiver<Notification>) {
+    let resolver = SecretResolver::new();
+    let mut webhooks = Vec::with_capacity(config.webhooks.len());
+    for webhook in config.webhooks {
+        match resolver.resolve_secret(&webhook.url).await {
+            Ok(url) => webhooks.push(Webhook {
+                url,
+                events: webhook.events,
+                min_severity: webhook.min_severity,
+            }),
+            Err(e) => warn!("Skipping notification webhook: {e}"),
+        }
+    }
+    let client = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    let batch_interval = Duration::from_millis(config.batch_interval_ms);
+
+    while let Some(first) = receiver.recv().await {
+        let mut batch = vec![first];
+        let deadline = tokio::time::Instant::now() + batch_interval;
+        // Stops at the deadline, or early when the notifier was dropped.
+        while let Ok(Some(notification)) = timeout_at(deadline, receiver.recv()).await {
+            batch.push(notification);
+        }
+
+        for webhook in &webhooks {
+            let wanted: Vec<_> = batch.iter().filter(|n| webhook.wants(n)).collect();
+            if !wanted.is_empty() {
+                let payload = webhook_payload(&wanted);
+                deliver(&client, &webhook.url, &payload, config.max_retries).await;
+            }
+        }
+    }
+}
+
+async fn deliver(client: &reqwest::Client, url: &str, payload: &Value, max_retries: u32) {
+    let mut delay = RETRY_BASE_DELAY;
+    for attempt in 0..=max_retries {
+        if attempt > 0 {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+        // Webhook URLs often embed credentials, so they are kept out of the logs.
+        match client.post(url).json(payload).send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => warn!("Notification webhook returned {}", response.status()),
+            Err(e) => warn!("Notification webhook request failed: {}", e.without_url()),
+        }
+    }
+    counter!(DELIVERY_FAILURES_METRIC).increment(1);
+}
+
+/// Checks that webhooks and the error-rate alert are usable.
+pub fn validate_notifications(config: &NotificationsConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    if config.webhooks.is_empty() {
+        errors.push("general.notifications.webhooks must not be empty.".to_string());
+    }
+    for (index, webhook) in config.webhooks.iter().enumerate() {
+        if let SecretObject::Literal { value, .. } = &webhook.url {
+            let valid = reqwest::Url::parse(value)
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+            if !valid {
+                errors.push(format!(
+                    "general.notifications.webhooks[{index}].url must be an http(s) URL."
+                ));
+            }
+        }
+    }
+    if let Some(error_rate) = &config.error_rate {
+        if !(1..=100).contains(&error_rate.threshold_percent) {
+            errors.push(
+                "general.notifications.error_rate.threshold_percent must be between 1 and 100."
+                    .to_string(),
+            );
+        }
+        if error_rate.window_seconds == 0 {
+            errors.push(
+                "general.notifications.error_rate.window_seconds must be greater than 0."
+                    .to_string(),
+            );
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor() -> ErrorRateMonitor {
+        ErrorRateMonitor::new(ErrorRateAlert {
+            threshold_percent: 50,
+            window_seconds: 60,
+            min_requests: 4,
+        })
+    }
+
+    #[test]
+    fn test_error_rate_alerts_once_per_window() {
+        let monitor = monitor();
+        let start = Instant::now();
+
+        for failed in [true, true, false] {
+            assert!(monitor.record_at(start, "default", failed).is_none());
+        }
+        let alert = monitor.record_at(start, "default", false).unwrap();
+        assert_eq!(alert.event, NotificationEventType::ErrorRate);
+        assert_eq!(alert.severity, NotificationSeverity::Critical);
+        assert_eq!(alert.details["errors"], 2);
+        assert_eq!(alert.details["requests"], 4);
+        assert!(monitor.record_at(start, "default", true).is_none());
+
+        let next_window = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(monitor.record_at(next_window, "default", true).is_none());
+        }
+        assert!(monitor.record_at(next_window, "default", true).is_some());
+    }
+
+    #[test]
+    fn test_error_rate_is_tracked_per_pipeline() {
+        let monitor = monitor();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            monitor.record_at(now, "chat", true);
+            monitor.record_at(now, "embeddings", false);
+        }
+        assert!(monitor.record_at(now, "embeddings", false).is_none());
+        assert!(monitor.record_at(now, "chat", false).is_some());
+    }
+
+    #[test]
+    fn test_validate_notifications() {
+        let config = NotificationsConfig {
+            webhooks: vec![crate::types::WebhookConfig {
+                url: SecretObject::literal("hooks.slack.com/services/T0".to_string()),
+                events: vec![],
+                min_severity: NotificationSeverity::Warning,
+            }],
+            error_rate: Some(ErrorRateAlert {
+                threshold_percent: 0,
+                window_seconds: 0,
+                min_requests: 1,
+            }),
+            batch_interval_ms: 1000,
+            max_retries: 3,
+        };
+        assert_eq!(
+            validate_notifications(&config),
+            vec![
+                "general.notifications.webhooks[0].url must be an http(s) URL.",
+                "general.notifications.error_rate.threshold_percent must be between 1 and 100.",
+                "general.notifications.error_rate.window_seconds must be greater than 0.",
+            ]
+        );
+    }
+}
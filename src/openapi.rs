@@ -8,16 +8,28 @@ use crate::models::{
     streaming::ChatCompletionChunk,
 };
 
+use crate::config::validation::{Severity, ValidationError};
+
 // Always import management API components
 use crate::management::{
-    api::routes::{model_definition_routes::*, pipeline_routes::*, provider_routes::*},
+    api::routes::{
+        api_key_routes::*, config_routes::*, model_definition_routes::*, pipeline_routes::*,
+        provider_routes::*,
+    },
     dto::{
-        AnthropicProviderConfig, AzureProviderConfig, BedrockProviderConfig,
+        AdaptiveRouting, AnthropicProviderConfig, ApiKeyResponse, ApiKeyRole, ApiKeySecretResponse,
+        AzureAuthType, AzureProviderConfig, BedrockProviderConfig, ConfigDiffResponse,
+        ConfigResourceDiffDto, ConfigSnapshotDiffDto, ConfigSnapshotResponse, CreateApiKeyRequest,
         CreateModelDefinitionRequest, CreatePipelineRequestDto, CreateProviderRequest,
-        ModelDefinitionResponse, ModelRouterConfigDto, ModelRouterModelEntryDto,
-        ModelRouterStrategyDto, OpenAIProviderConfig, PipelinePluginConfigDto, PipelineResponseDto,
-        PluginType, ProviderConfig, ProviderResponse, ProviderType, UpdateModelDefinitionRequest,
-        UpdatePipelineRequestDto, UpdateProviderRequest, VertexAIProviderConfig,
+        DegradedMode, DegradedOverrides, DependentModelDefinitionDto, DependentPipelineDto,
+        FieldChangeDto, MaintenanceWindow, MockMode, MockProviderConfig, ModelDefinitionResponse,
+        ModelRouterConfigDto, ModelRouterModelEntryDto, ModelRouterStrategyDto,
+        OpenAIProviderConfig, PassthroughHeaders, PatchPipelinePluginRequestDto,
+        PipelinePluginConfigDto, PipelineResponseDto, PluginType, PromotePipelineRequestDto,
+        ProviderConfig, ProviderDependentsResponse, ProviderResponse, ProviderTlsConfig,
+        ProviderType, RaceRouting, ResourceChangeDto, ResourceDiffDto, ToolLimits,
+        UpdateModelDefinitionRequest, UpdatePipelineRequestDto, UpdateProviderRequest,
+        VertexAIProviderConfig,
     },
     errors::ApiError,
 };
@@ -31,6 +43,7 @@ use crate::management::{
         health_handler,
         metrics_handler,
         chat_completions_handler,
+        batch_inference_handler,
         completions_handler,
         embeddings_handler,
         // Management API endpoints (available in database mode only)
@@ -39,6 +52,8 @@ use crate::management::{
         get_provider_handler,
         update_provider_handler,
         delete_provider_handler,
+        restore_provider_handler,
+        get_provider_dependents_handler,
         create_model_definition_handler,
         list_model_definitions_handler,
         get_model_definition_handler,
@@ -51,6 +66,16 @@ use crate::management::{
         get_pipeline_by_name_handler,
         update_pipeline_handler,
         delete_pipeline_handler,
+        restore_pipeline_handler,
+        promote_pipeline_handler,
+        patch_pipeline_plugin_handler,
+        create_api_key_handler,
+        list_api_keys_handler,
+        rotate_api_key_handler,
+        revoke_api_key_handler,
+        list_config_snapshots_handler,
+        rollback_config_handler,
+        diff_config_handler,
     ),
     components(
         schemas(
@@ -64,16 +89,26 @@ use crate::management::{
             EmbeddingsResponse,
             // Management API models
             ApiError,
+            ValidationError,
+            Severity,
             ProviderType,
             ProviderConfig,
             OpenAIProviderConfig,
             AnthropicProviderConfig,
             AzureProviderConfig,
+            AzureAuthType,
             BedrockProviderConfig,
             VertexAIProviderConfig,
+            MockProviderConfig,
+            MockMode,
+            ProviderTlsConfig,
+            MaintenanceWindow,
             CreateProviderRequest,
             UpdateProviderRequest,
             ProviderResponse,
+            ProviderDependentsResponse,
+            DependentModelDefinitionDto,
+            DependentPipelineDto,
             CreateModelDefinitionRequest,
             UpdateModelDefinitionRequest,
             ModelDefinitionResponse,
@@ -81,10 +116,29 @@ use crate::management::{
             UpdatePipelineRequestDto,
             PipelineResponseDto,
             PipelinePluginConfigDto,
+            PatchPipelinePluginRequestDto,
+            PromotePipelineRequestDto,
             PluginType,
             ModelRouterConfigDto,
             ModelRouterModelEntryDto,
             ModelRouterStrategyDto,
+            AdaptiveRouting,
+            RaceRouting,
+            DegradedMode,
+            DegradedOverrides,
+            PassthroughHeaders,
+            ToolLimits,
+            ApiKeyRole,
+            CreateApiKeyRequest,
+            ApiKeyResponse,
+            ApiKeySecretResponse,
+            ConfigSnapshotResponse,
+            ConfigSnapshotDiffDto,
+            ResourceDiffDto,
+            ConfigDiffResponse,
+            ConfigResourceDiffDto,
+            ResourceChangeDto,
+            FieldChangeDto,
         )
     ),
     tags(
@@ -95,6 +149,8 @@ use crate::management::{
         (name = "Providers", description = "Provider management endpoints (database mode only)"),
         (name = "Model Definitions", description = "Model definition management endpoints (database mode only)"),
         (name = "Pipelines", description = "Pipeline management endpoints (database mode only)"),
+        (name = "Config", description = "Config snapshot, rollback and diff endpoints (database mode only)"),
+        (name = "API Keys", description = "Management API key endpoints (database mode only, admin keys only)"),
     ),
     info(
         title = "Traceloop Hub LLM Gateway API",
@@ -129,12 +185,12 @@ pub fn get_openapi_spec() -> utoipa::openapi::OpenApi {
     get,
     path = "/health",
     responses(
-        (status = 200, description = "Service is healthy", body = String),
+        (status = 200, description = "Service is up", body = String),
     ),
     tag = "Health"
 )]
 pub async fn health_handler() -> &'static str {
-    "Working!"
+    "ok"
 }
 
 #[utoipa::path(
@@ -160,6 +216,27 @@ pub async fn metrics_handler() -> &'static str {
 )]
 pub async fn chat_completions_handler() {}
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/batch-inference",
+    params(
+        ("pipeline" = Option<String>, Query, description = "Pipeline serving the batch"),
+        ("concurrency" = Option<usize>, Query, description = "Lines sent at once, up to `max_concurrency`"),
+        ("ordered" = Option<bool>, Query, description = "Return results in the order of the lines"),
+    ),
+    request_body(
+        content = String,
+        content_type = "application/x-ndjson",
+        description = "A chat completion request per line, each with an optional `custom_id`"
+    ),
+    responses(
+        (status = 200, description = "A result per line, as each completes", body = String, content_type = "application/x-ndjson"),
+        (status = 413, description = "The batch has too many lines or bytes"),
+    ),
+    tag = "Chat"
+)]
+pub async fn batch_inference_handler() {}
+
 #[utoipa::path(
     post,
     path = "/api/v1/completions",
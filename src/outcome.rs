@@ -0,0 +1,384 @@
+use crate::metrics::counter;
+use crate::pipelines::pipeline::PipelineScope;
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const REQUESTS_METRIC: &str = "hub_requests_total";
+/// How far back the rollup reported by `/health` reaches.
+const ROLLUP_WINDOW: Duration = Duration::from_secs(300);
+const ROLLUP_BUCKET: Duration = Duration::from_secs(60);
+
+/// How a pipeline request ended, used as the `status_class` of its metrics and logs.
+///
+/// Code that produces a response knows why it failed and tags it with `tagged`. Responses
+/// without a tag are classified by status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    /// The request was invalid, unauthorized or over a limit of the hub.
+    ClientError,
+    /// The provider failed or rejected the hub's own credentials or rate.
+    ProviderError,
+    /// A safety filter or pipeline policy blocked the request or its response.
+    GuardrailBlock,
+    Timeout,
+    /// The client went away before a response was produced.
+    Cancelled,
+}
+
+impl Outcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::ClientError => "client_error",
+            Outcome::ProviderError => "provider_error",
+            Outcome::GuardrailBlock => "guardrail_block",
+            Outcome::Timeout => "timeout",
+            Outcome::Cancelled => "cancelled",
+        }
+    }
+
+    /// Class of an untagged response.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => Outcome::Timeout,
+            status if status.is_client_error() => Outcome::ClientError,
+            status if status.is_server_error() => Outcome::ProviderError,
+            _ => Outcome::Success,
+        }
+    }
+
+    /// Class of a provider call that failed with `status`. Upstreams reject bad request
+    /// bodies with 400, 413 or 422; anything else is on the provider's side, including
+    /// rejected hub credentials and throttling.
+    pub fn from_upstream_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => Outcome::Timeout,
+            StatusCode::BAD_REQUEST
+            | StatusCode::PAYLOAD_TOO_LARGE
+            | StatusCode::UNPROCESSABLE_ENTITY => Outcome::ClientError,
+            _ => Outcome::ProviderError,
+        }
+    }
+
+    /// The class `response` was tagged with, or the one its status implies.
+    pub fn of(response: &Response) -> Self {
+        response
+            .extensions()
+            .get::<Outcome>()
+            .copied()
+            .unwrap_or_else(|| Outcome::from_status(response.status()))
+    }
+
+    /// Tags `response` with this class.
+    pub fn tagged(self, mut response: Response) -> Response {
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// The response to a provider call that failed with `status`, tagged with its class.
+pub fn provider_failure(status: StatusCode) -> Response {
+    Outcome::from_upstream_status(status).tagged(status.into_response())
+}
+
+/// Requests of one pipeline per outcome.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct OutcomeCounts {
+    pub success: u64,
+    pub client_error: u64,
+    pub provider_error: u64,
+    pub guardrail_block: u64,
+    pub timeout: u64,
+    pub cancelled: u64,
+}
+
+impl OutcomeCounts {
+    fn add(&mut self, outcome: Outcome) {
+        let field = match outcome {
+            Outcome::Success => &mut self.success,
+            Outcome::ClientError => &mut self.client_error,
+            Outcome::ProviderError => &mut self.provider_error,
+            Outcome::GuardrailBlock => &mut self.guardrail_block,
+            Outcome::Timeout => &mut self.timeout,
+            Outcome::Cancelled => &mut self.cancelled,
+        };
+        *field += 1;
+    }
+
+    fn merge(&mut self, other: &OutcomeCounts) {
+        self.success += other.success;
+        self.client_error += other.client_error;
+        self.provider_error += other.provider_error;
+        self.guardrail_block += other.guardrail_block;
+        self.timeout += other.timeout;
+        self.cancelled += other.cancelled;
+    }
+}
+
+/// Per-pipeline outcome counts over the last five minutes, kept in one-minute buckets.
+#[derive(Debug)]
+pub struct OutcomeRollup {
+    started: Instant,
+    pipelines: Mutex<HashMap<String, VecDeque<(u64, OutcomeCounts)>>>,
+}
+
+impl Default for OutcomeRollup {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl OutcomeRollup {
+    fn new(started: Instant) -> Self {
+        Self {
+            started,
+            pipelines: Mutex::default(),
+        }
+    }
+
+    fn bucket(&self, now: Instant) -> u64 {
+        now.duration_since(self.started).as_secs() / ROLLUP_BUCKET.as_secs()
+    }
+
+    /// The oldest bucket still inside the window ending in `bucket`.
+    fn oldest_kept(bucket: u64) -> u64 {
+        (bucket + 1).saturating_sub(ROLLUP_WINDOW.as_secs() / ROLLUP_BUCKET.as_secs())
+    }
+
+    pub fn record(&self, pipeline: &str, outcome: Outcome) {
+        self.record_at(Instant::now(), pipeline, outcome);
+    }
+
+    fn record_at(&self, now: Instant, pipeline: &str, outcome: Outcome) {
+        let bucket = self.bucket(now);
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let buckets = pipelines.entry(pipeline.to_string()).or_default();
+        while buckets
+            .front()
+            .is_some_and(|(oldest, _)| *oldest < Self::oldest_kept(bucket))
+        {
+            buckets.pop_front();
+        }
+        match buckets.back_mut() {
+            Some((latest, counts)) if *latest == bucket => counts.add(outcome),
+            _ => {
+                let mut counts = OutcomeCounts::default();
+                counts.add(outcome);
+                buckets.push_back((bucket, counts));
+            }
+        }
+    }
+
+    /// Counts of each pipeline that had requests in the window.
+    pub fn snapshot(&self) -> BTreeMap<String, OutcomeCounts> {
+        self.snapshot_at(Instant::now())
+    }
+
+    fn snapshot_at(&self, now: Instant) -> BTreeMap<String, OutcomeCounts> {
+        let oldest_kept = Self::oldest_kept(self.bucket(now));
+        let pipelines = self.pipelines.lock().unwrap();
+        pipelines
+            .iter()
+            .filter_map(|(pipeline, buckets)| {
+                let mut total = None::<OutcomeCounts>;
+                for (_, counts) in buckets.iter().filter(|(bucket, _)| *bucket >= oldest_kept) {
+                    total.get_or_insert_default().merge(counts);
+                }
+                Some((pipeline.clone(), total?))
+            })
+            .collect()
+    }
+}
+
+/// Counts an outcome of `pipeline` in `hub_requests_total` and in `rollup`.
+pub fn record(rollup: &OutcomeRollup, pipeline: &str, outcome: Outcome) {
+    counter!(
+        REQUESTS_METRIC,
+        "pipeline" => pipeline.to_string(),
+        "status_class" => outcome.as_str()
+    )
+    .increment(1);
+    rollup.record(pipeline, outcome);
+}
+
+/// Counts a request as cancelled unless a response is produced before it's dropped.
+struct CancellationGuard(Option<Arc<PipelineScope>>);
+
+impl Drop for CancellationGuard {
+    fn drop(&mut self) {
+        if let Some(scope) = self.0.take() {
+            record(&scope.services.outcomes, &scope.name, Outcome::Cancelled);
+        }
+    }
+}
+
+/// Middleware counting each pipeline response in `hub_requests_total` and the rollup by
+/// its outcome. A stream counts once its response starts.
+pub async fn classify_outcomes(
+    State(scope): State<Arc<PipelineScope>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut guard = CancellationGuard(Some(scope.clone()));
+    let response = next.run(request).await;
+    guard.0 = None;
+    record(
+        &scope.services.outcomes,
+        &scope.name,
+        Outcome::of(&response),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::HubServices;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::middleware;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_untagged_responses_are_classified_by_status() {
+        let cases = [
+            (StatusCode::OK, Outcome::Success),
+            (StatusCode::NOT_MODIFIED, Outcome::Success),
+            (StatusCode::BAD_REQUEST, Outcome::ClientError),
+            (StatusCode::UNAUTHORIZED, Outcome::ClientError),
+            (StatusCode::NOT_FOUND, Outcome::ClientError),
+            (StatusCode::TOO_MANY_REQUESTS, Outcome::ClientError),
+            (StatusCode::REQUEST_TIMEOUT, Outcome::Timeout),
+            (StatusCode::GATEWAY_TIMEOUT, Outcome::Timeout),
+            (StatusCode::INTERNAL_SERVER_ERROR, Outcome::ProviderError),
+            (StatusCode::BAD_GATEWAY, Outcome::ProviderError),
+            (StatusCode::SERVICE_UNAVAILABLE, Outcome::ProviderError),
+        ];
+        for (status, expected) in cases {
+            assert_eq!(Outcome::of(&status.into_response()), expected, "{status}");
+        }
+    }
+
+    #[test]
+    fn test_upstream_failures_are_classified_explicitly() {
+        let cases = [
+            (StatusCode::BAD_REQUEST, Outcome::ClientError),
+            (StatusCode::UNPROCESSABLE_ENTITY, Outcome::ClientError),
+            (StatusCode::PAYLOAD_TOO_LARGE, Outcome::ClientError),
+            // The hub's own key or rate, not the client's.
+            (StatusCode::UNAUTHORIZED, Outcome::ProviderError),
+            (StatusCode::FORBIDDEN, Outcome::ProviderError),
+            (StatusCode::NOT_FOUND, Outcome::ProviderError),
+            (StatusCode::TOO_MANY_REQUESTS, Outcome::ProviderError),
+            (StatusCode::INTERNAL_SERVER_ERROR, Outcome::ProviderError),
+            (StatusCode::SERVICE_UNAVAILABLE, Outcome::ProviderError),
+            (StatusCode::REQUEST_TIMEOUT, Outcome::Timeout),
+            (StatusCode::GATEWAY_TIMEOUT, Outcome::Timeout),
+        ];
+        for (status, expected) in cases {
+            let response = provider_failure(status);
+            assert_eq!(response.status(), status);
+            assert_eq!(Outcome::of(&response), expected, "{status}");
+        }
+    }
+
+    #[test]
+    fn test_tag_overrides_status() {
+        let blocked = Outcome::GuardrailBlock.tagged(StatusCode::BAD_REQUEST.into_response());
+        assert_eq!(Outcome::of(&blocked), Outcome::GuardrailBlock);
+        let throttled =
+            Outcome::ProviderError.tagged(StatusCode::TOO_MANY_REQUESTS.into_response());
+        assert_eq!(Outcome::of(&throttled), Outcome::ProviderError);
+    }
+
+    #[test]
+    fn test_rollup_keeps_the_last_five_minutes() {
+        let started = Instant::now();
+        let rollup = OutcomeRollup::new(started);
+        let at = |secs| started + Duration::from_secs(secs);
+        rollup.record_at(at(0), "default", Outcome::ProviderError);
+        rollup.record_at(at(30), "default", Outcome::Success);
+        rollup.record_at(at(130), "default", Outcome::ClientError);
+        rollup.record_at(at(130), "other", Outcome::Timeout);
+
+        let snapshot = rollup.snapshot_at(at(200));
+        assert_eq!(
+            snapshot["default"],
+            OutcomeCounts {
+                success: 1,
+                client_error: 1,
+                provider_error: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(snapshot["other"].timeout, 1);
+
+        // The first minute has left the window.
+        let snapshot = rollup.snapshot_at(at(300));
+        assert_eq!(snapshot["default"].provider_error, 0);
+        assert_eq!(snapshot["default"].client_error, 1);
+
+        // Pipelines without recent requests are left out.
+        assert!(rollup.snapshot_at(at(500)).is_empty());
+        rollup.record_at(at(500), "default", Outcome::Cancelled);
+        assert_eq!(
+            rollup.snapshot_at(at(500))["default"],
+            OutcomeCounts {
+                cancelled: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    async fn blocked() -> Response {
+        Outcome::GuardrailBlock.tagged(StatusCode::BAD_REQUEST.into_response())
+    }
+
+    async fn hangs() -> Response {
+        std::future::pending().await
+    }
+
+    #[tokio::test]
+    async fn test_middleware_records_tagged_and_cancelled_requests() {
+        let pipeline = "outcome-middleware-test";
+        let scope = Arc::new(PipelineScope::new(
+            pipeline,
+            Arc::default(),
+            HubServices::default(),
+        ));
+        let app = Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route("/blocked", get(blocked))
+            .route("/hangs", get(hangs))
+            .layer(middleware::from_fn_with_state(
+                scope.clone(),
+                classify_outcomes,
+            ));
+        let request = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        app.clone().oneshot(request("/ok")).await.unwrap();
+        app.clone().oneshot(request("/blocked")).await.unwrap();
+        let abandoned =
+            tokio::time::timeout(Duration::from_millis(20), app.oneshot(request("/hangs"))).await;
+        assert!(abandoned.is_err());
+
+        assert_eq!(
+            scope.services.outcomes.snapshot()[pipeline],
+            OutcomeCounts {
+                success: 1,
+                guardrail_block: 1,
+                cancelled: 1,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,392 @@
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::ai_models::instance::ModelInstance;
+use crate::ai_models::registry::ModelRegistry;
+use crate::logging::LogSampler;
+use crate::types::AdaptiveRouting;
+
+/// Why the adaptive or race router sent a request to the model in `x-hub-model-key`.
+pub const HEADER_ROUTING_DECISION: HeaderName = HeaderName::from_static("x-hub-routing-decision");
+
+/// Samples older than this are dropped, whatever the router's window.
+pub const MAX_WINDOW_SECONDS: u64 = 3600;
+/// Bounds memory per model under heavy traffic; older samples are dropped first.
+const MAX_SAMPLES_PER_MODEL: usize = 2000;
+
+/// How the adaptive or race router picked a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingDecision {
+    /// The candidate with the best score.
+    Best,
+    /// Another candidate, picked so its score stays current.
+    Explore,
+    /// No candidate has samples in the window yet, so the first one is used.
+    Default,
+    /// The model answered first when the request was raced.
+    Race,
+}
+
+impl RoutingDecision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RoutingDecision::Best => "best",
+            RoutingDecision::Explore => "explore",
+            RoutingDecision::Default => "default",
+            RoutingDecision::Race => "race",
+        }
+    }
+}
+
+/// Adds `x-hub-routing-decision` to a response.
+pub fn inject_routing_decision_header(
+    response: &mut axum::response::Response,
+    decision: Option<RoutingDecision>,
+) {
+    if let Some(decision) = decision {
+        response.headers_mut().insert(
+            HEADER_ROUTING_DECISION,
+            HeaderValue::from_static(decision.as_str()),
+        );
+    }
+}
+
+/// Whether a failed upstream call counts against the model's error rate. Client errors
+/// are the caller's fault, except rate limiting.
+pub fn counts_as_error(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+struct Sample {
+    at: Instant,
+    /// `None` for failed requests.
+    latency: Option<Duration>,
+}
+
+/// Latency and error rate of a model over a window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelStats {
+    pub requests: usize,
+    /// `None` when every request in the window failed.
+    pub p95_latency: Option<Duration>,
+    pub error_rate: f64,
+}
+
+/// Recent request outcomes per model key. Shared by every pipeline of a hub, so routers
+/// keep their history across config reloads.
+#[derive(Default)]
+pub struct ModelStatsTracker {
+    samples: Mutex<HashMap<String, VecDeque<Sample>>>,
+}
+
+impl ModelStatsTracker {
+    /// Records a finished request: its latency, or `None` if it failed.
+    pub fn record_at(&self, now: Instant, model_key: &str, latency: Option<Duration>) {
+        let mut samples = self.samples.lock().unwrap();
+        let model_samples = samples.entry(model_key.to_string()).or_default();
+        model_samples.push_back(Sample { at: now, latency });
+        let max_age = Duration::from_secs(MAX_WINDOW_SECONDS);
+        while model_samples.len() > MAX_SAMPLES_PER_MODEL
+            || model_samples
+                .front()
+                .is_some_and(|sample| now.saturating_duration_since(sample.at) > max_age)
+        {
+            model_samples.pop_front();
+        }
+    }
+
+    /// Stats over the requests of the last `window`, or `None` if there were none.
+    pub fn stats_at(&self, now: Instant, model_key: &str, window: Duration) -> Option<ModelStats> {
+        let samples = self.samples.lock().unwrap();
+        let recent: Vec<Option<Duration>> = samples
+            .get(model_key)?
+            .iter()
+            .filter(|sample| now.saturating_duration_since(sample.at) <= window)
+            .map(|sample| sample.latency)
+            .collect();
+        if recent.is_empty() {
+            return None;
+        }
+        let mut latencies: Vec<Duration> = recent.iter().flatten().copied().collect();
+        latencies.sort();
+        let p95_latency = (!latencies.is_empty()).then(|| {
+            let rank = (latencies.len() as f64 * 0.95).ceil() as usize;
+            latencies[rank.clamp(1, latencies.len()) - 1]
+        });
+        Some(ModelStats {
+            requests: recent.len(),
+            p95_latency,
+            error_rate: (recent.len() - latencies.len()) as f64 / recent.len() as f64,
+        })
+    }
+}
+
+/// Picks among the models serving a request by their recent p95 latency and error rate.
+/// A share of requests goes to the other candidates so their scores stay current.
+pub struct AdaptiveRouter {
+    settings: AdaptiveRouting,
+    tracker: Arc<ModelStatsTracker>,
+    explorer: LogSampler,
+    explore_cursor: AtomicUsize,
+}
+
+impl AdaptiveRouter {
+    pub fn new(settings: AdaptiveRouting, tracker: Arc<ModelStatsTracker>) -> Self {
+        Self::with_explorer(
+            settings,
+            tracker,
+            LogSampler::new(settings.exploration_percent as f64 / 100.0),
+        )
+    }
+
+    fn with_explorer(
+        settings: AdaptiveRouting,
+        tracker: Arc<ModelStatsTracker>,
+        explorer: LogSampler,
+    ) -> Self {
+        Self {
+            settings,
+            tracker,
+            explorer,
+            explore_cursor: AtomicUsize::new(0),
+        }
+    }
+
+    /// Index into `candidates` of the model to route to.
+    pub fn pick(&self, candidates: &[&str]) -> (usize, RoutingDecision) {
+        self.pick_at(Instant::now(), candidates)
+    }
+
+    /// Picks among the configured models serving `requested`. Returns `None` when none
+    /// does, leaving the request to the regular routing rules.
+    pub fn route(
+        &self,
+        registry: &ModelRegistry,
+        requested: &str,
+        model_keys: &[String],
+    ) -> Option<(Arc<ModelInstance>, RoutingDecision)> {
+        let candidates = registry.candidates(requested, model_keys);
+        if candidates.is_empty() {
+            return None;
+        }
+        let keys: Vec<&str> = candidates.iter().map(|model| model.name.as_str()).collect();
+        let (index, decision) = self.pick(&keys);
+        Some((candidates[index].clone(), decision))
+    }
+
+    pub fn record(&self, model_key: &str, latency: Option<Duration>) {
+        self.tracker.record_at(Instant::now(), model_key, latency);
+    }
+
+    fn pick_at(&self, now: Instant, candidates: &[&str]) -> (usize, RoutingDecision) {
+        let window = Duration::from_secs(self.settings.window_seconds);
+        let best = candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, key)| {
+                let stats = self.tracker.stats_at(now, key, window)?;
+                Some((index, self.score(&stats)))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index);
+
+        if candidates.len() > 1 && self.explorer.sample() {
+            // Round-robin over the others, so each gets explored equally often.
+            let offset =
+                self.explore_cursor.fetch_add(1, Ordering::Relaxed) % (candidates.len() - 1);
+            let skipped = best.unwrap_or(0);
+            let index = (skipped + 1 + offset) % candidates.len();
+            return (index, RoutingDecision::Explore);
+        }
+        match best {
+            Some(index) => (index, RoutingDecision::Best),
+            None => (0, RoutingDecision::Default),
+        }
+    }
+
+    /// Lower is better. A model whose requests all failed scores as if it had no latency,
+    /// so only its error rate counts.
+    fn score(&self, stats: &ModelStats) -> f64 {
+        let latency = stats.p95_latency.unwrap_or_default().as_secs_f64();
+        self.settings.latency_weight * latency + self.settings.error_weight * stats.error_rate
+    }
+}
+
+/// Checks that the adaptive strategy's settings are usable.
+pub fn validate_adaptive_routing(settings: &AdaptiveRouting) -> Result<(), String> {
+    if !(1..=MAX_WINDOW_SECONDS).contains(&settings.window_seconds) {
+        return Err(format!(
+            "window_seconds must be between 1 and {MAX_WINDOW_SECONDS}"
+        ));
+    }
+    if settings.exploration_percent > 50 {
+        return Err("exploration_percent must be at most 50".to_string());
+    }
+    for (name, weight) in [
+        ("latency_weight", settings.latency_weight),
+        ("error_weight", settings.error_weight),
+    ] {
+        if !(weight.is_finite() && weight >= 0.0) {
+            return Err(format!("{name} must be a non-negative number"));
+        }
+    }
+    if settings.latency_weight == 0.0 && settings.error_weight == 0.0 {
+        return Err("latency_weight and error_weight can't both be 0".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    const STEP: Duration = Duration::from_millis(100);
+
+    fn router(tracker: &Arc<ModelStatsTracker>, settings: AdaptiveRouting) -> AdaptiveRouter {
+        let explorer = LogSampler::with_seed(settings.exploration_percent as f64 / 100.0, 7);
+        AdaptiveRouter::with_explorer(settings, tracker.clone(), explorer)
+    }
+
+    /// Sends `requests` requests, one every `STEP`, to two mock providers answering with
+    /// the given latencies or failing. Returns how many went to the first one.
+    fn simulate(
+        router: &AdaptiveRouter,
+        now: &mut Instant,
+        requests: usize,
+        outcome: impl Fn(&str) -> Option<Duration>,
+    ) -> usize {
+        let candidates = ["provider-a", "provider-b"];
+        let mut first = 0;
+        for _ in 0..requests {
+            let (index, _) = router.pick_at(*now, &candidates);
+            if index == 0 {
+                first += 1;
+            }
+            let key = candidates[index];
+            router.tracker.record_at(*now, key, outcome(key));
+            *now += STEP;
+        }
+        first
+    }
+
+    #[test]
+    fn test_traffic_follows_latency_changes() {
+        let tracker = Arc::new(ModelStatsTracker::default());
+        let settings = AdaptiveRouting {
+            window_seconds: 30,
+            exploration_percent: 10,
+            ..AdaptiveRouting::default()
+        };
+        let router = router(&tracker, settings);
+        let mut now = Instant::now();
+
+        // Provider A is fast, so it gets the traffic beyond exploration.
+        let to_a = simulate(&router, &mut now, 1000, |key| match key {
+            "provider-a" => Some(Duration::from_millis(200)),
+            _ => Some(Duration::from_millis(900)),
+        });
+        assert!(to_a > 800, "provider A got {to_a} of 1000 requests");
+
+        // A slows down. Exploration notices B is faster now and traffic moves over once
+        // A's slow requests dominate its window.
+        simulate(&router, &mut now, 300, |key| match key {
+            "provider-a" => Some(Duration::from_millis(1500)),
+            _ => Some(Duration::from_millis(300)),
+        });
+        let to_a = simulate(&router, &mut now, 1000, |key| match key {
+            "provider-a" => Some(Duration::from_millis(1500)),
+            _ => Some(Duration::from_millis(300)),
+        });
+        assert!(to_a < 200, "provider A got {to_a} of 1000 requests");
+    }
+
+    #[test]
+    fn test_traffic_moves_away_from_failing_provider() {
+        let tracker = Arc::new(ModelStatsTracker::default());
+        let router = router(&tracker, AdaptiveRouting::default());
+        let mut now = Instant::now();
+
+        simulate(&router, &mut now, 200, |_| Some(Duration::from_millis(500)));
+        // A is faster but fails every other request.
+        let a_requests = Cell::new(0);
+        let to_a = simulate(&router, &mut now, 3000, |key| match key {
+            "provider-a" => {
+                a_requests.set(a_requests.get() + 1);
+                (a_requests.get() % 2 == 0).then_some(Duration::from_millis(100))
+            }
+            _ => Some(Duration::from_millis(500)),
+        });
+        assert!(to_a < 600, "provider A got {to_a} of 3000 requests");
+    }
+
+    #[test]
+    fn test_cold_candidates_are_explored() {
+        let tracker = Arc::new(ModelStatsTracker::default());
+        let router = router(&tracker, AdaptiveRouting::default());
+        let candidates = ["warm", "cold"];
+        let now = Instant::now();
+
+        assert_eq!(router.pick_at(now, &candidates).1, RoutingDecision::Default);
+        tracker.record_at(now, "warm", Some(Duration::from_millis(100)));
+
+        let decisions: Vec<(usize, RoutingDecision)> =
+            (0..200).map(|_| router.pick_at(now, &candidates)).collect();
+        assert!(decisions.contains(&(0, RoutingDecision::Best)));
+        assert!(decisions.contains(&(1, RoutingDecision::Explore)));
+        assert!(!decisions.contains(&(1, RoutingDecision::Best)));
+    }
+
+    #[test]
+    fn test_stats_only_cover_the_window() {
+        let tracker = ModelStatsTracker::default();
+        let start = Instant::now();
+        for millis in 1..=100 {
+            tracker.record_at(start, "model", Some(Duration::from_millis(millis)));
+        }
+        tracker.record_at(start, "model", None);
+
+        let stats = tracker
+            .stats_at(start, "model", Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(stats.requests, 101);
+        assert_eq!(stats.p95_latency, Some(Duration::from_millis(95)));
+        assert!((stats.error_rate - 1.0 / 101.0).abs() < 1e-9);
+
+        let later = start + Duration::from_secs(11);
+        assert!(
+            tracker
+                .stats_at(later, "model", Duration::from_secs(10))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_validate_adaptive_routing() {
+        assert!(validate_adaptive_routing(&AdaptiveRouting::default()).is_ok());
+        for invalid in [
+            AdaptiveRouting {
+                window_seconds: 0,
+                ..AdaptiveRouting::default()
+            },
+            AdaptiveRouting {
+                exploration_percent: 80,
+                ..AdaptiveRouting::default()
+            },
+            AdaptiveRouting {
+                latency_weight: -1.0,
+                ..AdaptiveRouting::default()
+            },
+            AdaptiveRouting {
+                latency_weight: 0.0,
+                error_weight: 0.0,
+                ..AdaptiveRouting::default()
+            },
+        ] {
+            assert!(validate_adaptive_routing(&invalid).is_err(), "{invalid:?}");
+        }
+    }
+}
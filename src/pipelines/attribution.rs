@@ -0,0 +1,73 @@
+//! Which provider and model served a response, reported when `general.attribution_headers`
+//! is on, for clients comparing models per response.
+
+use crate::ai_models::instance::ModelInstance;
+use crate::pipelines::pipeline::HEADER_MODEL_KEY;
+use axum::http::{HeaderName, HeaderValue};
+use axum::response::Response;
+use serde::Serialize;
+use serde_json::Value;
+
+pub const HEADER_ATTRIBUTION_PROVIDER: HeaderName = HeaderName::from_static("x-hub-provider");
+pub const HEADER_MODEL_TYPE: HeaderName = HeaderName::from_static("x-hub-model-type");
+/// Field of the streamed chunk carrying usage that repeats the headers, for clients that
+/// can't read them.
+pub const ATTRIBUTION_FIELD: &str = "hub_attribution";
+
+/// The provider and model that answered, after failover, races and degraded-mode
+/// substitution.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Attribution {
+    /// Key of the provider, or of the failover group member that served the request.
+    pub provider: String,
+    pub model_key: String,
+    pub model_type: String,
+}
+
+impl Attribution {
+    pub fn new(model: &ModelInstance, served_by: Option<&str>) -> Self {
+        Self {
+            provider: served_by.map_or_else(|| model.provider.key(), str::to_string),
+            model_key: model.name.clone(),
+            model_type: model.model_type.clone(),
+        }
+    }
+
+    /// [`Attribution::new`], when attribution headers are `enabled`.
+    pub fn when_enabled(
+        enabled: bool,
+        model: &ModelInstance,
+        served_by: Option<&str>,
+    ) -> Option<Self> {
+        enabled.then(|| Self::new(model, served_by))
+    }
+
+    /// Adds `x-hub-provider`, `x-hub-model-key` and `x-hub-model-type` to `response`.
+    pub fn inject_headers(&self, response: &mut Response) {
+        for (name, value) in [
+            (HEADER_ATTRIBUTION_PROVIDER, &self.provider),
+            (HEADER_MODEL_KEY, &self.model_key),
+            (HEADER_MODEL_TYPE, &self.model_type),
+        ] {
+            if let Ok(value) = HeaderValue::from_str(value) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+    }
+
+    /// `chunk` with the attribution added as its `hub_attribution` field.
+    pub fn extend_chunk<T: Serialize>(&self, chunk: &T) -> serde_json::Result<Value> {
+        let mut chunk = serde_json::to_value(chunk)?;
+        if let Some(fields) = chunk.as_object_mut() {
+            fields.insert(ATTRIBUTION_FIELD.to_string(), serde_json::to_value(self)?);
+        }
+        Ok(chunk)
+    }
+}
+
+/// Adds `attribution`'s headers to `response`, when there is one.
+pub fn inject_attribution_headers(response: &mut Response, attribution: Option<&Attribution>) {
+    if let Some(attribution) = attribution {
+        attribution.inject_headers(response);
+    }
+}
@@ -0,0 +1,402 @@
+use crate::metrics::{counter, gauge};
+use crate::notifications::{Notification, NotificationBus};
+use crate::pipelines::explain::ExplainStep;
+use crate::state_store::StateStore;
+use crate::types::{BudgetWindow, NotificationEventType, UsdAmount};
+use axum::Json;
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+const STORE_PREFIX: &str = "budget:";
+
+/// Spend accumulated by one pipeline in its current window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct WindowSpend {
+    window_start: DateTime<Utc>,
+    spent_usd: f64,
+    warned: bool,
+    exceeded: bool,
+}
+
+/// Spend ledger keyed by pipeline name, kept in the `StateStore`.
+///
+/// Spend lives in the store rather than in the ledger, so config reloads and new ledgers
+/// over the same store don't reset it.
+pub struct BudgetLedger {
+    store: Arc<StateStore>,
+    /// Where budget warnings and overruns are published.
+    notifications: Option<Arc<NotificationBus>>,
+    /// Serializes updates, which read an entry and write it back.
+    updates: Mutex<()>,
+}
+
+impl std::fmt::Debug for BudgetLedger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BudgetLedger").finish_non_exhaustive()
+    }
+}
+
+impl Default for BudgetLedger {
+    fn default() -> Self {
+        Self::new(Arc::new(StateStore::default()))
+    }
+}
+
+impl BudgetLedger {
+    pub fn new(store: Arc<StateStore>) -> Self {
+        Self {
+            store,
+            notifications: None,
+            updates: Mutex::new(()),
+        }
+    }
+
+    pub fn with_notifications(mut self, notifications: Arc<NotificationBus>) -> Self {
+        self.notifications = Some(notifications);
+        self
+    }
+
+    fn get(&self, pipeline: &str) -> Option<WindowSpend> {
+        self.store
+            .get(&format!("{STORE_PREFIX}{pipeline}"))
+            .and_then(|value| serde_json::from_value(value).ok())
+    }
+
+    fn set(&self, pipeline: &str, spend: &WindowSpend) {
+        if let Ok(value) = serde_json::to_value(spend) {
+            self.store
+                .set(&format!("{STORE_PREFIX}{pipeline}"), value, None);
+        }
+    }
+}
+
+/// Returned when a pipeline has spent its whole budget for the current window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetExceeded {
+    pub pipeline: String,
+    pub limit_usd: f64,
+    pub spent_usd: f64,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl IntoResponse for BudgetExceeded {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "error": {
+                "type": "budget_exceeded",
+                "message": format!(
+                    "Pipeline '{}' has exceeded its budget of ${:.2}",
+                    self.pipeline, self.limit_usd
+                ),
+                "resets_at": self.resets_at.to_rfc3339(),
+            }
+        });
+        (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response()
+    }
+}
+
+/// Spend cap for a single pipeline, configured through the `budget` plugin.
+#[derive(Debug, Clone)]
+pub struct PipelineBudget {
+    pipeline: String,
+    limit_usd: f64,
+    window: BudgetWindow,
+    warn_at_percent: u8,
+    ledger: Arc<BudgetLedger>,
+}
+
+impl PipelineBudget {
+    pub fn new(
+        pipeline: &str,
+        limit_usd: UsdAmount,
+        window: BudgetWindow,
+        warn_at_percent: u8,
+        ledger: Arc<BudgetLedger>,
+    ) -> Self {
+        Self {
+            pipeline: pipeline.to_string(),
+            limit_usd: limit_usd.0,
+            window,
+            warn_at_percent,
+            ledger,
+        }
+    }
+
+    /// Fails if the budget for the current window is already spent.
+    pub fn check(&self) -> Result<(), BudgetExceeded> {
+        self.check_at(Utc::now())
+    }
+
+    /// Adds the cost of a completed request to the current window.
+    pub fn record(&self, cost_usd: f64) {
+        self.record_at(Utc::now(), cost_usd);
+    }
+
+    fn check_at(&self, now: DateTime<Utc>) -> Result<(), BudgetExceeded> {
+        let spent_usd = self.spent_at(now);
+        if spent_usd >= self.limit_usd {
+            return Err(BudgetExceeded {
+                pipeline: self.pipeline.clone(),
+                limit_usd: self.limit_usd,
+                spent_usd,
+                resets_at: window_end(self.window, now),
+            });
+        }
+        Ok(())
+    }
+
+    fn spent_at(&self, now: DateTime<Utc>) -> f64 {
+        let window_start = window_start(self.window, now);
+        self.ledger
+            .get(&self.pipeline)
+            .filter(|entry| entry.window_start == window_start)
+            .map_or(0.0, |entry| entry.spent_usd)
+    }
+
+    fn record_at(&self, now: DateTime<Utc>, cost_usd: f64) {
+        if cost_usd <= 0.0 {
+            return;
+        }
+
+        let window_start = window_start(self.window, now);
+        let _update = self.ledger.updates.lock().unwrap();
+        let mut entry = self
+            .ledger
+            .get(&self.pipeline)
+            .filter(|entry| entry.window_start == window_start)
+            .unwrap_or(WindowSpend {
+                window_start,
+                spent_usd: 0.0,
+                warned: false,
+                exceeded: false,
+            });
+        entry.spent_usd += cost_usd;
+
+        gauge!("budget_spend_usd", "pipeline" => self.pipeline.clone()).set(entry.spent_usd);
+
+        let warn_at_usd = self.limit_usd * f64::from(self.warn_at_percent) / 100.0;
+        if !entry.warned && entry.spent_usd >= warn_at_usd {
+            entry.warned = true;
+            warn!(
+                "Pipeline '{}' has spent ${:.4} of its ${:.2} {:?} budget ({}% warning threshold)",
+                self.pipeline, entry.spent_usd, self.limit_usd, self.window, self.warn_at_percent
+            );
+            counter!("budget_warning_total", "pipeline" => self.pipeline.clone()).increment(1);
+            self.notify(NotificationEventType::BudgetWarning, entry.spent_usd);
+        }
+
+        if !entry.exceeded && entry.spent_usd >= self.limit_usd {
+            entry.exceeded = true;
+            self.notify(NotificationEventType::BudgetExceeded, entry.spent_usd);
+        }
+        self.ledger.set(&self.pipeline, &entry);
+    }
+
+    fn notify(&self, event: NotificationEventType, spent_usd: f64) {
+        let Some(notifications) = &self.ledger.notifications else {
+            return;
+        };
+        let message = match event {
+            NotificationEventType::BudgetExceeded => format!(
+                "Pipeline '{}' has exceeded its ${:.2} {:?} budget",
+                self.pipeline, self.limit_usd, self.window
+            ),
+            _ => format!(
+                "Pipeline '{}' has spent ${:.4} of its ${:.2} {:?} budget",
+                self.pipeline, spent_usd, self.limit_usd, self.window
+            ),
+        };
+        let details = json!({
+            "spent_usd": spent_usd,
+            "limit_usd": self.limit_usd,
+            "window": self.window,
+        });
+        let notification = Notification::new(event, &self.pipeline, message, details);
+        notifications.publish(notification);
+    }
+}
+
+/// Middleware rejecting requests with 429 once the pipeline's budget is spent.
+pub async fn enforce_budget(
+    State(budget): State<Arc<PipelineBudget>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match budget.check() {
+        Ok(()) => {
+            ExplainStep::new("budget", "passed").record();
+            next.run(request).await
+        }
+        Err(exceeded) => {
+            ExplainStep::new("budget", "blocked")
+                .details(json!({
+                    "limit_usd": exceeded.limit_usd,
+                    "spent_usd": exceeded.spent_usd,
+                }))
+                .record();
+            exceeded.into_response()
+        }
+    }
+}
+
+fn window_start(window: BudgetWindow, now: DateTime<Utc>) -> DateTime<Utc> {
+    let date = match window {
+        BudgetWindow::Daily => now.date_naive(),
+        BudgetWindow::Monthly => NaiveDate::from_ymd_opt(now.year(), now.month(), 1).unwrap(),
+    };
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+}
+
+fn window_end(window: BudgetWindow, now: DateTime<Utc>) -> DateTime<Utc> {
+    let start = window_start(window, now);
+    match window {
+        BudgetWindow::Daily => start + Duration::days(1),
+        BudgetWindow::Monthly => {
+            let (year, month) = if start.month() == 12 {
+                (start.year() + 1, 1)
+            } else {
+                (start.year(), start.month() + 1)
+            };
+            Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).unwrap()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(limit_usd: f64, window: BudgetWindow) -> PipelineBudget {
+        PipelineBudget::new(
+            "default",
+            UsdAmount(limit_usd),
+            window,
+            80,
+            Arc::new(BudgetLedger::default()),
+        )
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap()
+    }
+
+    fn warned(budget: &PipelineBudget) -> bool {
+        budget.ledger.get("default").unwrap().warned
+    }
+
+    #[test]
+    fn test_accumulates_until_limit_then_rejects() {
+        let budget = budget(1.0, BudgetWindow::Daily);
+        let now = at(2025, 6, 10, 9);
+
+        budget.record_at(now, 0.5);
+        assert!(budget.check_at(now).is_ok());
+        assert!(!warned(&budget));
+
+        budget.record_at(now, 0.3);
+        assert!(budget.check_at(now).is_ok());
+        assert!(warned(&budget));
+        assert!(!budget.ledger.get("default").unwrap().exceeded);
+
+        budget.record_at(now, 0.25);
+        let exceeded = budget.check_at(now).unwrap_err();
+        assert_eq!(exceeded.resets_at, at(2025, 6, 11, 0));
+        assert!((exceeded.spent_usd - 1.05).abs() < 1e-9);
+        assert!(budget.ledger.get("default").unwrap().exceeded);
+    }
+
+    #[test]
+    fn test_daily_window_resets() {
+        let budget = budget(1.0, BudgetWindow::Daily);
+        budget.record_at(at(2025, 6, 10, 23), 2.0);
+        assert!(budget.check_at(at(2025, 6, 10, 23)).is_err());
+        assert!(budget.check_at(at(2025, 6, 11, 0)).is_ok());
+
+        budget.record_at(at(2025, 6, 11, 1), 0.1);
+        assert!(!warned(&budget));
+    }
+
+    #[test]
+    fn test_monthly_window_resets_at_month_boundary() {
+        let budget = budget(10.0, BudgetWindow::Monthly);
+        budget.record_at(at(2025, 12, 3, 12), 10.0);
+
+        let exceeded = budget.check_at(at(2025, 12, 31, 12)).unwrap_err();
+        assert_eq!(exceeded.resets_at, at(2026, 1, 1, 0));
+        assert!(budget.check_at(at(2026, 1, 1, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_budgets_are_tracked_per_pipeline() {
+        let ledger = Arc::new(BudgetLedger::default());
+        let chat = PipelineBudget::new(
+            "chat",
+            UsdAmount(1.0),
+            BudgetWindow::Daily,
+            80,
+            ledger.clone(),
+        );
+        let embeddings = PipelineBudget::new(
+            "embeddings",
+            UsdAmount(1.0),
+            BudgetWindow::Daily,
+            80,
+            ledger,
+        );
+        let now = at(2025, 6, 10, 9);
+
+        chat.record_at(now, 1.5);
+        assert!(chat.check_at(now).is_err());
+        assert!(embeddings.check_at(now).is_ok());
+    }
+
+    #[test]
+    fn test_spend_survives_a_new_ledger_over_the_same_store() {
+        let store = Arc::new(StateStore::default());
+        let pipeline_budget = |ledger| {
+            PipelineBudget::new("default", UsdAmount(1.0), BudgetWindow::Daily, 80, ledger)
+        };
+        let now = at(2025, 6, 10, 9);
+
+        let first = pipeline_budget(Arc::new(BudgetLedger::new(store.clone())));
+        first.record_at(now, 0.9);
+        assert!(warned(&first));
+
+        let second = pipeline_budget(Arc::new(BudgetLedger::new(store)));
+        assert!((second.spent_at(now) - 0.9).abs() < 1e-9);
+        second.record_at(now, 0.2);
+        assert!(second.check_at(now).is_err());
+    }
+
+    #[test]
+    fn test_spend_survives_a_restart_with_a_file_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let pipeline_budget = |store| {
+            PipelineBudget::new(
+                "default",
+                UsdAmount(1.0),
+                BudgetWindow::Daily,
+                80,
+                Arc::new(BudgetLedger::new(Arc::new(store))),
+            )
+        };
+        let now = at(2025, 6, 10, 9);
+
+        let before = pipeline_budget(StateStore::open(&path).unwrap());
+        before.record_at(now, 0.9);
+        drop(before);
+
+        let after = pipeline_budget(StateStore::open(&path).unwrap());
+        assert!((after.spent_at(now) - 0.9).abs() < 1e-9);
+        after.record_at(now, 0.2);
+        assert!(after.check_at(now).is_err());
+    }
+}
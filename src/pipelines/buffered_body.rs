@@ -0,0 +1,68 @@
+use crate::pipelines::request_validation::RequestValidationError;
+use axum::body::{Body, Bytes};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+
+/// Reads a request body whole for middleware that inspects it, answering bodies larger
+/// than `max_bytes` with 413 as soon as they get there.
+pub async fn read_request_body(body: Body, max_bytes: usize) -> Result<Bytes, Response> {
+    let mut data = body.into_data_stream();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = data.next().await {
+        let Ok(chunk) = chunk else {
+            return Err(StatusCode::BAD_REQUEST.into_response());
+        };
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(RequestValidationError::body_too_large(max_bytes).into_response());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(bytes))
+}
+
+/// Reads a response body whole for middleware that keeps it. A body larger than
+/// `max_bytes` comes back unbuffered instead, as what was read followed by the rest.
+pub async fn buffer_response_body(body: Body, max_bytes: usize) -> Result<Bytes, Body> {
+    let mut data = body.into_data_stream();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = data.next().await {
+        match chunk {
+            Ok(chunk) if bytes.len() + chunk.len() <= max_bytes => {
+                bytes.extend_from_slice(&chunk);
+            }
+            chunk => {
+                let read = futures::stream::iter([Ok(Bytes::from(bytes)), chunk]);
+                return Err(Body::from_stream(read.chain(data)));
+            }
+        }
+    }
+    Ok(Bytes::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_bodies_over_the_cap_are_rejected() {
+        let bytes = read_request_body(Body::from("0123456789"), 10)
+            .await
+            .unwrap();
+        assert_eq!(bytes, "0123456789");
+
+        let response = read_request_body(Body::from("0123456789"), 9)
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_response_bodies_over_the_cap_pass_through_whole() {
+        let chunks = ["01234", "56789", "abcde"].map(Ok::<_, std::io::Error>);
+        let body = Body::from_stream(futures::stream::iter(chunks));
+        let body = buffer_response_body(body, 8).await.unwrap_err();
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(bytes, "0123456789abcde");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::config::models::ModelConfig;
+use crate::models::usage::Usage;
+
+/// Model param with the USD price of 1000 prompt tokens.
+pub const INPUT_COST_PARAM: &str = "input_cost_per_1k_tokens";
+/// Model param with the USD price of 1000 completion tokens, reasoning tokens included.
+pub const OUTPUT_COST_PARAM: &str = "output_cost_per_1k_tokens";
+/// Model param with the USD price of 1000 prompt tokens read from the prompt cache.
+/// Defaults to the input price.
+pub const CACHED_INPUT_COST_PARAM: &str = "cached_input_cost_per_1k_tokens";
+/// Model param with the USD price of 1000 prompt tokens written to the prompt cache.
+/// Defaults to the input price.
+pub const CACHE_WRITE_COST_PARAM: &str = "cache_write_cost_per_1k_tokens";
+/// Every price param.
+pub const COST_PARAMS: [&str; 4] = [
+    INPUT_COST_PARAM,
+    OUTPUT_COST_PARAM,
+    CACHED_INPUT_COST_PARAM,
+    CACHE_WRITE_COST_PARAM,
+];
+
+/// Parses a per-1k-token price param, rejecting negative or non-numeric values.
+pub fn parse_price(value: &str) -> Result<f64, String> {
+    match value.trim().parse::<f64>() {
+        Ok(price) if price.is_finite() && price >= 0.0 => Ok(price),
+        _ => Err(format!("'{value}' is not a non-negative number")),
+    }
+}
+
+fn price(model_config: &ModelConfig, param: &str) -> Option<f64> {
+    model_config
+        .params
+        .get(param)
+        .and_then(|value| parse_price(value).ok())
+}
+
+/// USD cost of a request priced from the model's cost params. Prompt tokens read from or
+/// written to the prompt cache use the cache prices when the model sets them.
+/// Models without prices are treated as free.
+pub fn usage_cost_usd(model_config: &ModelConfig, usage: &Usage) -> f64 {
+    let input_price = price(model_config, INPUT_COST_PARAM).unwrap_or(0.0);
+    let cached_price = price(model_config, CACHED_INPUT_COST_PARAM).unwrap_or(input_price);
+    let write_price = price(model_config, CACHE_WRITE_COST_PARAM).unwrap_or(input_price);
+    let output_price = price(model_config, OUTPUT_COST_PARAM).unwrap_or(0.0);
+
+    let cached = usage.cached_tokens().min(usage.prompt_tokens);
+    let written = usage
+        .cache_creation_tokens()
+        .min(usage.prompt_tokens - cached);
+    let uncached = usage.prompt_tokens - cached - written;
+    let cost = input_price * f64::from(uncached)
+        + cached_price * f64::from(cached)
+        + write_price * f64::from(written)
+        + output_price * f64::from(usage.completion_tokens);
+    cost / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::usage::PromptTokensDetails;
+    use std::collections::HashMap;
+
+    fn model(params: HashMap<String, String>) -> ModelConfig {
+        ModelConfig {
+            key: "gpt-4o".to_string(),
+            r#type: "gpt-4o".to_string(),
+            provider: "openai".to_string(),
+            params,
+            enabled: true,
+            deprecation: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_usage_cost_usd() {
+        let priced = model(HashMap::from([
+            (INPUT_COST_PARAM.to_string(), "0.005".to_string()),
+            (OUTPUT_COST_PARAM.to_string(), "0.015".to_string()),
+        ]));
+        let cost = usage_cost_usd(&priced, &Usage::new(2000, 1000));
+        assert!((cost - 0.025).abs() < 1e-9);
+
+        assert_eq!(
+            usage_cost_usd(&model(HashMap::new()), &Usage::new(2000, 1000)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_cached_prompt_tokens_use_cache_prices() {
+        let usage = Usage {
+            prompt_tokens_details: Some(PromptTokensDetails {
+                cached_tokens: Some(1000),
+                cache_creation_tokens: Some(500),
+                ..Default::default()
+            }),
+            ..Usage::new(2000, 1000)
+        };
+        let priced = model(HashMap::from([
+            (INPUT_COST_PARAM.to_string(), "0.004".to_string()),
+            (OUTPUT_COST_PARAM.to_string(), "0.016".to_string()),
+            (CACHED_INPUT_COST_PARAM.to_string(), "0.001".to_string()),
+            (CACHE_WRITE_COST_PARAM.to_string(), "0.005".to_string()),
+        ]));
+        // 500 uncached, 1000 cached and 500 written prompt tokens, then the output.
+        let cost = usage_cost_usd(&priced, &usage);
+        assert!((cost - (0.002 + 0.001 + 0.0025 + 0.016)).abs() < 1e-9);
+
+        // Without cache prices, every prompt token costs the input price.
+        let uncached_prices = model(HashMap::from([
+            (INPUT_COST_PARAM.to_string(), "0.004".to_string()),
+            (OUTPUT_COST_PARAM.to_string(), "0.016".to_string()),
+        ]));
+        let cost = usage_cost_usd(&uncached_prices, &usage);
+        assert!((cost - (0.008 + 0.016)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_price() {
+        assert_eq!(parse_price("0.5"), Ok(0.5));
+        assert!(parse_price("-1").is_err());
+        assert!(parse_price("cheap").is_err());
+    }
+}
@@ -0,0 +1,482 @@
+use crate::metrics::{counter, gauge};
+use axum::http::{HeaderName, HeaderValue};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::models::{Pipeline, PluginConfig};
+use crate::logging::LogSampler;
+use crate::models::chat::ChatCompletionRequest;
+use crate::pipelines::adaptive_routing::{MAX_WINDOW_SECONDS, ModelStats, ModelStatsTracker};
+use crate::types::DegradedMode;
+
+/// Set to `true` on responses served by the substitute model of a degraded pipeline.
+pub const HEADER_DEGRADED: HeaderName = HeaderName::from_static("x-hub-degraded");
+
+/// 1 while a pipeline is degraded, 0 otherwise.
+pub const DEGRADED_METRIC: &str = "hub_pipeline_degraded";
+pub const TRANSITIONS_METRIC: &str = "hub_pipeline_degraded_transitions_total";
+
+/// Adds `x-hub-degraded` to a response served by the substitute model.
+pub fn inject_degraded_header(response: &mut axum::response::Response, degraded: bool) {
+    if degraded {
+        response
+            .headers_mut()
+            .insert(HEADER_DEGRADED, HeaderValue::from_static("true"));
+    }
+}
+
+/// The `degraded-mode` settings of a pipeline, if it has any.
+pub fn degraded_mode_settings(pipeline: &Pipeline) -> Option<&DegradedMode> {
+    pipeline.plugins.iter().find_map(|plugin| match plugin {
+        PluginConfig::DegradedMode(settings) => Some(settings),
+        _ => None,
+    })
+}
+
+/// Degraded mode state keyed by pipeline name.
+///
+/// The state outlives pipeline routers, so a config reload keeps it as long as the
+/// pipeline's `degraded-mode` settings are unchanged. Changed settings start afresh.
+#[derive(Default)]
+pub struct DegradedModes {
+    pipelines: Mutex<HashMap<String, Arc<PipelineDegradation>>>,
+}
+
+impl DegradedModes {
+    /// The state of `pipeline`, reset if it was tracked with other settings.
+    pub fn pipeline(&self, pipeline: &str, settings: &DegradedMode) -> Arc<PipelineDegradation> {
+        let mut pipelines = self.pipelines.lock().unwrap();
+        if let Some(existing) = pipelines.get(pipeline) {
+            if existing.settings == *settings {
+                return existing.clone();
+            }
+        }
+        let degradation = Arc::new(PipelineDegradation::new(pipeline, settings.clone()));
+        publish_state(pipeline, false);
+        pipelines.insert(pipeline.to_string(), degradation.clone());
+        degradation
+    }
+
+    /// Forgets pipelines that are gone from `pipelines` or no longer use degraded mode.
+    pub fn retain(&self, pipelines: &[Pipeline]) {
+        self.pipelines.lock().unwrap().retain(|name, _| {
+            let kept = pipelines.iter().any(|pipeline| {
+                pipeline.name == *name && degraded_mode_settings(pipeline).is_some()
+            });
+            if !kept {
+                publish_state(name, false);
+            }
+            kept
+        });
+    }
+}
+
+fn publish_state(pipeline: &str, degraded: bool) {
+    let value = if degraded { 1.0 } else { 0.0 };
+    gauge!(DEGRADED_METRIC, "pipeline" => pipeline.to_string()).set(value);
+}
+
+/// Degraded mode of one pipeline, configured through the `degraded-mode` plugin.
+///
+/// Only requests taking the pipeline's normal route are sampled. The pipeline enters
+/// degraded mode once a window of at least `min_requests` crosses a threshold, and leaves
+/// it after `min_degraded_seconds` once the window is below `recovery_percent` of each
+/// threshold. A window without samples counts as recovered, so without probes the normal
+/// route is retried after `min_degraded_seconds`.
+pub struct PipelineDegradation {
+    pipeline: String,
+    settings: DegradedMode,
+    outcomes: ModelStatsTracker,
+    prober: LogSampler,
+    degraded_since: Mutex<Option<Instant>>,
+}
+
+impl PipelineDegradation {
+    pub fn new(pipeline: &str, settings: DegradedMode) -> Self {
+        let prober = LogSampler::new(settings.probe_percent as f64 / 100.0);
+        Self::with_prober(pipeline, settings, prober)
+    }
+
+    pub fn with_prober(pipeline: &str, settings: DegradedMode, prober: LogSampler) -> Self {
+        Self {
+            pipeline: pipeline.to_string(),
+            settings,
+            outcomes: ModelStatsTracker::default(),
+            prober,
+            degraded_since: Mutex::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> &DegradedMode {
+        &self.settings
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded_since.lock().unwrap().is_some()
+    }
+
+    /// Whether the next request should go to the substitute model.
+    pub fn divert(&self) -> bool {
+        self.divert_at(Instant::now())
+    }
+
+    pub fn divert_at(&self, now: Instant) -> bool {
+        self.update_at(now) && !self.prober.sample()
+    }
+
+    /// Records a request that took the normal route: its latency, or `None` if it failed.
+    pub fn record(&self, latency: Option<Duration>) {
+        self.record_at(Instant::now(), latency);
+    }
+
+    pub fn record_at(&self, now: Instant, latency: Option<Duration>) {
+        self.outcomes.record_at(now, &self.pipeline, latency);
+        self.update_at(now);
+    }
+
+    /// Caps and overrides request parameters as configured for degraded mode.
+    pub fn apply_overrides(&self, payload: &mut ChatCompletionRequest) {
+        let overrides = &self.settings.overrides;
+        if let Some(cap) = overrides.max_tokens {
+            match payload.max_completion_tokens {
+                Some(max) => payload.max_completion_tokens = Some(max.min(cap)),
+                None => {
+                    payload.max_tokens = Some(payload.max_tokens.map_or(cap, |max| max.min(cap)))
+                }
+            }
+        }
+        if let Some(temperature) = overrides.temperature {
+            payload.temperature = Some(temperature);
+        }
+    }
+
+    /// Enters or leaves degraded mode as the window requires. Returns whether the pipeline
+    /// is degraded.
+    fn update_at(&self, now: Instant) -> bool {
+        let window = Duration::from_secs(self.settings.window_seconds);
+        let stats = self.outcomes.stats_at(now, &self.pipeline, window);
+        let mut degraded_since = self.degraded_since.lock().unwrap();
+        match *degraded_since {
+            None => {
+                let breach = stats
+                    .filter(|stats| stats.requests >= self.settings.min_requests as usize)
+                    .and_then(|stats| self.breach(&stats, 100));
+                if let Some(reason) = breach {
+                    warn!(
+                        pipeline = %self.pipeline,
+                        model = %self.settings.model,
+                        "Pipeline entered degraded mode: {reason}"
+                    );
+                    self.transition("enter", true);
+                    *degraded_since = Some(now);
+                }
+            }
+            Some(since) => {
+                let min_degraded = Duration::from_secs(self.settings.min_degraded_seconds);
+                let recovered = stats.is_none_or(|stats| {
+                    self.breach(&stats, self.settings.recovery_percent)
+                        .is_none()
+                });
+                if now.saturating_duration_since(since) >= min_degraded && recovered {
+                    info!(
+                        pipeline = %self.pipeline,
+                        degraded_seconds = now.saturating_duration_since(since).as_secs(),
+                        "Pipeline left degraded mode"
+                    );
+                    self.transition("exit", false);
+                    *degraded_since = None;
+                }
+            }
+        }
+        degraded_since.is_some()
+    }
+
+    /// Describes the first threshold `stats` exceeds, with thresholds scaled to `percent`.
+    fn breach(&self, stats: &ModelStats, percent: u8) -> Option<String> {
+        let scale = percent as f64 / 100.0;
+        if let Some(max_percent) = self.settings.max_error_rate_percent {
+            let max_rate = max_percent as f64 / 100.0 * scale;
+            if stats.error_rate > max_rate {
+                return Some(format!(
+                    "error rate {:.1}% is above {:.1}%",
+                    stats.error_rate * 100.0,
+                    max_rate * 100.0
+                ));
+            }
+        }
+        if let (Some(max_ms), Some(p95)) = (self.settings.max_p95_latency_ms, stats.p95_latency) {
+            let max_latency = Duration::from_secs_f64(max_ms as f64 / 1000.0 * scale);
+            if p95 > max_latency {
+                return Some(format!(
+                    "p95 latency {}ms is above {}ms",
+                    p95.as_millis(),
+                    max_latency.as_millis()
+                ));
+            }
+        }
+        None
+    }
+
+    fn transition(&self, transition: &'static str, degraded: bool) {
+        publish_state(&self.pipeline, degraded);
+        counter!(
+            TRANSITIONS_METRIC,
+            "pipeline" => self.pipeline.clone(),
+            "transition" => transition
+        )
+        .increment(1);
+    }
+}
+
+/// Checks that the `degraded-mode` settings are usable.
+pub fn validate_degraded_mode(settings: &DegradedMode) -> Result<(), String> {
+    if settings.model.trim().is_empty() {
+        return Err("model must not be empty".to_string());
+    }
+    if !(1..=MAX_WINDOW_SECONDS).contains(&settings.window_seconds) {
+        return Err(format!(
+            "window_seconds must be between 1 and {MAX_WINDOW_SECONDS}"
+        ));
+    }
+    if settings.min_requests == 0 {
+        return Err("min_requests must be greater than 0".to_string());
+    }
+    match (settings.max_error_rate_percent, settings.max_p95_latency_ms) {
+        (None, None) => {
+            return Err("set max_error_rate_percent, max_p95_latency_ms or both".to_string());
+        }
+        (Some(percent), _) if !(1..100).contains(&percent) => {
+            return Err("max_error_rate_percent must be between 1 and 99".to_string());
+        }
+        (_, Some(0)) => return Err("max_p95_latency_ms must be greater than 0".to_string()),
+        _ => {}
+    }
+    if !(1..=100).contains(&settings.recovery_percent) {
+        return Err("recovery_percent must be between 1 and 100".to_string());
+    }
+    if settings.probe_percent > 50 {
+        return Err("probe_percent must be at most 50".to_string());
+    }
+    if settings.overrides.max_tokens == Some(0) {
+        return Err("overrides.max_tokens must be greater than 0".to_string());
+    }
+    if let Some(temperature) = settings.overrides.temperature {
+        if !(temperature.is_finite() && temperature >= 0.0) {
+            return Err("overrides.temperature must be a non-negative number".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DegradedOverrides, PipelineType};
+
+    const STEP: Duration = Duration::from_millis(100);
+
+    fn settings() -> DegradedMode {
+        DegradedMode {
+            model: "gpt-4o-mini".to_string(),
+            window_seconds: 10,
+            min_requests: 10,
+            max_error_rate_percent: Some(50),
+            max_p95_latency_ms: None,
+            recovery_percent: 50,
+            min_degraded_seconds: 30,
+            probe_percent: 0,
+            overrides: DegradedOverrides::default(),
+        }
+    }
+
+    fn degradation(settings: DegradedMode) -> PipelineDegradation {
+        let prober = LogSampler::with_seed(settings.probe_percent as f64 / 100.0, 7);
+        PipelineDegradation::with_prober("default", settings, prober)
+    }
+
+    /// Records `requests` normal-route requests, one every `STEP`, failing every
+    /// `fail_every`th one.
+    fn simulate(
+        degradation: &PipelineDegradation,
+        now: &mut Instant,
+        requests: usize,
+        fail_every: Option<usize>,
+    ) {
+        for i in 1..=requests {
+            let failed = fail_every.is_some_and(|every| i % every == 0);
+            let latency = (!failed).then_some(Duration::from_millis(200));
+            degradation.record_at(*now, latency);
+            *now += STEP;
+        }
+    }
+
+    #[test]
+    fn test_enters_once_window_has_enough_requests() {
+        let degradation = degradation(settings());
+        let mut now = Instant::now();
+
+        simulate(&degradation, &mut now, 9, Some(1));
+        assert!(!degradation.divert_at(now));
+        simulate(&degradation, &mut now, 1, Some(1));
+        assert!(degradation.divert_at(now));
+    }
+
+    #[test]
+    fn test_enters_on_p95_latency() {
+        let degradation = degradation(DegradedMode {
+            max_error_rate_percent: None,
+            max_p95_latency_ms: Some(100),
+            ..settings()
+        });
+        let mut now = Instant::now();
+
+        simulate(&degradation, &mut now, 20, None);
+        assert!(degradation.is_degraded());
+    }
+
+    #[test]
+    fn test_recovery_has_hysteresis() {
+        let degradation = degradation(settings());
+        let mut now = Instant::now();
+        simulate(&degradation, &mut now, 20, Some(1));
+        assert!(degradation.is_degraded());
+
+        // 33% errors is below the entry threshold but above the recovery one, so the
+        // pipeline stays degraded past min_degraded_seconds.
+        simulate(&degradation, &mut now, 400, Some(3));
+        assert!(degradation.divert_at(now));
+
+        // Once the window only holds successes the pipeline recovers.
+        simulate(&degradation, &mut now, 110, None);
+        assert!(!degradation.divert_at(now));
+    }
+
+    #[test]
+    fn test_stays_degraded_for_min_degraded_seconds() {
+        let degradation = degradation(settings());
+        let mut now = Instant::now();
+        simulate(&degradation, &mut now, 20, Some(1));
+
+        simulate(&degradation, &mut now, 150, None);
+        assert!(degradation.divert_at(now));
+        // Nothing took the normal route since, which counts as recovered.
+        now += Duration::from_secs(30);
+        assert!(!degradation.divert_at(now));
+    }
+
+    #[test]
+    fn test_probes_take_the_normal_route() {
+        let degradation = degradation(DegradedMode {
+            probe_percent: 20,
+            ..settings()
+        });
+        let mut now = Instant::now();
+        simulate(&degradation, &mut now, 20, Some(1));
+
+        let diverted = (0..1000).filter(|_| degradation.divert_at(now)).count();
+        assert!(
+            (700..900).contains(&diverted),
+            "{diverted} of 1000 diverted"
+        );
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let degradation = degradation(DegradedMode {
+            overrides: DegradedOverrides {
+                max_tokens: Some(256),
+                temperature: Some(0.0),
+            },
+            ..settings()
+        });
+        let mut payload: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hi"}],
+            "temperature": 0.9
+        }))
+        .unwrap();
+
+        degradation.apply_overrides(&mut payload);
+        assert_eq!(payload.max_tokens, Some(256));
+        assert_eq!(payload.temperature, Some(0.0));
+
+        payload.max_tokens = None;
+        payload.max_completion_tokens = Some(100);
+        degradation.apply_overrides(&mut payload);
+        assert_eq!(payload.max_tokens, None);
+        assert_eq!(payload.max_completion_tokens, Some(100));
+    }
+
+    #[test]
+    fn test_state_resets_when_settings_change() {
+        let modes = DegradedModes::default();
+        let mut now = Instant::now();
+        simulate(
+            &modes.pipeline("resets", &settings()),
+            &mut now,
+            20,
+            Some(1),
+        );
+        assert!(modes.pipeline("resets", &settings()).is_degraded());
+
+        let changed = DegradedMode {
+            min_requests: 5,
+            ..settings()
+        };
+        assert!(!modes.pipeline("resets", &changed).is_degraded());
+
+        let pipeline = Pipeline {
+            name: "resets".to_string(),
+            r#type: PipelineType::Chat,
+            plugins: vec![],
+            store_artifacts: false,
+        };
+        let tracked = modes.pipeline("resets", &changed);
+        simulate(&tracked, &mut now, 20, Some(1));
+        assert!(tracked.is_degraded());
+        modes.retain(&[pipeline]);
+        assert!(!modes.pipeline("resets", &changed).is_degraded());
+    }
+
+    #[test]
+    fn test_validate_degraded_mode() {
+        assert!(validate_degraded_mode(&settings()).is_ok());
+        for invalid in [
+            DegradedMode {
+                window_seconds: 0,
+                ..settings()
+            },
+            DegradedMode {
+                max_error_rate_percent: None,
+                ..settings()
+            },
+            DegradedMode {
+                max_error_rate_percent: Some(100),
+                ..settings()
+            },
+            DegradedMode {
+                max_p95_latency_ms: Some(0),
+                ..settings()
+            },
+            DegradedMode {
+                recovery_percent: 0,
+                ..settings()
+            },
+            DegradedMode {
+                probe_percent: 60,
+                ..settings()
+            },
+            DegradedMode {
+                overrides: DegradedOverrides {
+                    max_tokens: None,
+                    temperature: Some(-1.0),
+                },
+                ..settings()
+            },
+        ] {
+            assert!(validate_degraded_mode(&invalid).is_err(), "{invalid:?}");
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use crate::ai_models::registry::ModelRegistry;
+use crate::config::models::ModelConfig;
+use crate::config::names::lookup_matches;
+use crate::pipelines::buffered_body::read_request_body;
+use crate::pipelines::explain::ExplainStep;
+use crate::pipelines::request_validation::RequestValidationError;
+use crate::types::ModelDeprecation;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::NaiveDate;
+use serde_json::{Map, Value, json};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Set to `true` on responses to requests for a deprecated model.
+pub const DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+/// The HTTP-date from which the deprecated model is no longer served.
+pub const SUNSET_HEADER: HeaderName = HeaderName::from_static("sunset");
+
+const SUNSET_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone)]
+struct DeprecatedModel {
+    model_type: String,
+    /// The model name clients should send instead.
+    replacement: Option<String>,
+    auto_replace: bool,
+    sunset: Option<HeaderValue>,
+}
+
+impl DeprecatedModel {
+    fn mark(&self, response: &mut Response) {
+        let headers = response.headers_mut();
+        headers.insert(DEPRECATION_HEADER, HeaderValue::from_static("true"));
+        if let Some(sunset) = &self.sunset {
+            headers.insert(SUNSET_HEADER, sunset.clone());
+        }
+    }
+}
+
+/// The deprecated models a pipeline routes, matched on the model name clients send.
+#[derive(Debug, Clone)]
+pub struct DeprecatedModels {
+    models: Vec<DeprecatedModel>,
+    case_insensitive_lookups: bool,
+}
+
+impl DeprecatedModels {
+    /// Collects the deprecated models among `model_keys`, or `None` if there are none.
+    pub fn new(model_keys: &[String], model_registry: &ModelRegistry) -> Option<Self> {
+        let mut seen = HashSet::new();
+        let models: Vec<_> = model_keys
+            .iter()
+            .filter_map(|key| model_registry.get(key))
+            // Handlers serve the first model with a matching name, so only that one counts.
+            .filter(|model| seen.insert(model.model_type.clone()))
+            .filter(|model| model.config.deprecation.deprecated)
+            .map(|model| {
+                let deprecation = &model.config.deprecation;
+                DeprecatedModel {
+                    model_type: model.model_type.clone(),
+                    replacement: deprecation
+                        .replacement
+                        .as_ref()
+                        .and_then(|key| model_registry.get(key))
+                        .map(|replacement| replacement.model_type.clone()),
+                    auto_replace: deprecation.auto_replace,
+                    sunset: deprecation.sunset.as_deref().and_then(sunset_header_value),
+                }
+            })
+            .collect();
+        (!models.is_empty()).then_some(Self {
+            models,
+            case_insensitive_lookups: model_registry.case_insensitive_lookups(),
+        })
+    }
+
+    fn get(&self, model_type: &str) -> Option<&DeprecatedModel> {
+        self.models.iter().find(|model| {
+            lookup_matches(&model.model_type, model_type, self.case_insensitive_lookups)
+        })
+    }
+
+    /// Looks up the model the request body `fields` asks for. A deprecated model is swapped
+    /// for its replacement when it's replaced automatically, and the request is rejected
+    /// otherwise. Returns the deprecated model, whose headers the response carries either
+    /// way, or `None` if the model isn't deprecated.
+    fn apply(
+        &self,
+        fields: &mut Map<String, Value>,
+    ) -> Option<(&DeprecatedModel, Result<(), RequestValidationError>)> {
+        let model = fields
+            .get("model")
+            .and_then(Value::as_str)
+            .and_then(|name| self.get(name))?;
+        let applied = match (&model.replacement, model.auto_replace) {
+            (Some(replacement), true) => {
+                ExplainStep::new("deprecation", "replaced")
+                    .details(json!({ "model": model.model_type, "replacement": replacement }))
+                    .record();
+                fields.insert("model".to_string(), Value::String(replacement.clone()));
+                Ok(())
+            }
+            (replacement, _) => {
+                ExplainStep::new("deprecation", "rejected")
+                    .details(json!({ "model": model.model_type, "replacement": replacement }))
+                    .record();
+                Err(RequestValidationError::deprecated_model(
+                    &model.model_type,
+                    replacement.as_deref(),
+                ))
+            }
+        };
+        Some((model, applied))
+    }
+
+    /// [`DeprecatedModels::apply`] for requests sent in process, whose responses carry no
+    /// headers.
+    pub(crate) fn check(
+        &self,
+        fields: &mut Map<String, Value>,
+    ) -> Result<(), RequestValidationError> {
+        self.apply(fields).map_or(Ok(()), |(_, applied)| applied)
+    }
+}
+
+/// Renders a `YYYY-MM-DD` sunset date as the HTTP-date the `Sunset` header carries.
+fn sunset_header_value(sunset: &str) -> Option<HeaderValue> {
+    let date = NaiveDate::parse_from_str(sunset, SUNSET_FORMAT).ok()?;
+    let http_date = date
+        .and_hms_opt(0, 0, 0)?
+        .and_utc()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string();
+    HeaderValue::from_str(&http_date).ok()
+}
+
+/// Checks a model's deprecation settings against the other configured models.
+pub fn validate_model_deprecation(
+    model: &ModelConfig,
+    models: &[ModelConfig],
+) -> Result<(), String> {
+    let ModelDeprecation {
+        replacement,
+        auto_replace,
+        sunset,
+        ..
+    } = &model.deprecation;
+    if let Some(sunset) = sunset {
+        if NaiveDate::parse_from_str(sunset, SUNSET_FORMAT).is_err() {
+            return Err(format!("sunset '{sunset}' must be a YYYY-MM-DD date"));
+        }
+    }
+    let Some(replacement) = replacement else {
+        if *auto_replace {
+            return Err("auto_replace needs a replacement".to_string());
+        }
+        return Ok(());
+    };
+    if *replacement == model.key {
+        return Err("can't be its own replacement".to_string());
+    }
+    match models.iter().find(|other| other.key == *replacement) {
+        None => Err(format!("replacement '{replacement}' does not exist")),
+        Some(other) if !other.enabled => Err(format!("replacement '{replacement}' is disabled")),
+        Some(other) if other.deprecation.deprecated => {
+            Err(format!("replacement '{replacement}' is deprecated too"))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Middleware rejecting requests for deprecated models, or sending them to the replacement
+/// when the model is configured with `auto_replace`. Request bodies are read up to
+/// `max_body_bytes`.
+pub async fn handle_deprecated_models(
+    State((deprecated, max_body_bytes)): State<(Arc<DeprecatedModels>, usize)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = request.into_parts();
+    let bytes = match read_request_body(body, max_body_bytes).await {
+        Ok(bytes) => bytes,
+        Err(rejection) => return rejection,
+    };
+
+    // Bodies that aren't JSON objects are left for the handler to reject.
+    let Ok(Value::Object(mut fields)) = serde_json::from_slice::<Value>(&bytes) else {
+        return next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await;
+    };
+    let Some((model, applied)) = deprecated.apply(&mut fields) else {
+        return next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await;
+    };
+
+    let mut response = match applied {
+        Ok(()) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            let body = Body::from(Value::Object(fields).to_string());
+            next.run(Request::from_parts(parts, body)).await
+        }
+        Err(rejection) => rejection.into_response(),
+    };
+    model.mark(&mut response);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(key: &str, deprecation: ModelDeprecation) -> ModelConfig {
+        ModelConfig {
+            key: key.to_string(),
+            r#type: key.to_string(),
+            provider: "openai".to_string(),
+            deprecation,
+            params: Default::default(),
+            enabled: true,
+        }
+    }
+
+    fn deprecated(replacement: Option<&str>, auto_replace: bool) -> ModelDeprecation {
+        ModelDeprecation {
+            deprecated: true,
+            replacement: replacement.map(str::to_string),
+            auto_replace,
+            sunset: Some("2025-06-30".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_sunset_header_is_an_http_date() {
+        assert_eq!(
+            sunset_header_value("2025-06-30").unwrap(),
+            "Mon, 30 Jun 2025 00:00:00 GMT"
+        );
+        assert!(sunset_header_value("30/06/2025").is_none());
+    }
+
+    #[test]
+    fn test_validate_model_deprecation() {
+        let models = [
+            model("gpt-4o", ModelDeprecation::default()),
+            model("gpt-4-0314", deprecated(Some("gpt-4o"), true)),
+            model("gpt-3.5-turbo-0301", deprecated(Some("gpt-4-0314"), false)),
+        ];
+        let check = |model: &ModelConfig| validate_model_deprecation(model, &models);
+
+        assert!(check(&models[1]).is_ok());
+        assert!(check(&models[2]).unwrap_err().contains("deprecated too"));
+        assert!(check(&model("m", deprecated(None, true))).is_err());
+        assert!(check(&model("m", deprecated(Some("m"), false))).is_err());
+        assert!(check(&model("m", deprecated(Some("missing"), false))).is_err());
+
+        let mut bad_sunset = deprecated(None, false);
+        bad_sunset.sunset = Some("June 2025".to_string());
+        assert!(
+            check(&model("m", bad_sunset))
+                .unwrap_err()
+                .contains("YYYY-MM-DD")
+        );
+    }
+}
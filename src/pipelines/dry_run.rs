@@ -0,0 +1,92 @@
+use crate::ai_models::instance::ModelInstance;
+use crate::pipelines::request_validation::RequestValidationError;
+use crate::providers::upstream::UpstreamRequest;
+use axum::http::{HeaderMap, StatusCode};
+use serde_json::{Value, json};
+
+/// Returns the translated upstream request instead of sending it.
+pub const DRY_RUN_HEADER: &str = "x-hub-dry-run";
+
+/// Reads `x-hub-dry-run`. Only honoured when debug headers are `allowed`, as by
+/// `general.allow_debug_headers`.
+pub fn parse_dry_run(headers: &HeaderMap, allowed: bool) -> Result<bool, RequestValidationError> {
+    parse_debug_header(headers, DRY_RUN_HEADER, allowed)
+}
+
+/// Reads a `true`/`false` debug header, rejecting `true` unless debug headers are `allowed`.
+pub(crate) fn parse_debug_header(
+    headers: &HeaderMap,
+    name: &str,
+    allowed: bool,
+) -> Result<bool, RequestValidationError> {
+    let Some(value) = headers.get(name) else {
+        return Ok(false);
+    };
+    let value = value.to_str().unwrap_or_default().to_ascii_lowercase();
+    let enabled = match value.as_str() {
+        "true" => true,
+        "false" => false,
+        _ => {
+            return Err(RequestValidationError {
+                status: StatusCode::BAD_REQUEST,
+                message: format!("{name} must be 'true' or 'false'"),
+                param: None,
+            });
+        }
+    };
+    if enabled && !allowed {
+        return Err(RequestValidationError {
+            status: StatusCode::FORBIDDEN,
+            message: format!("{name} requires 'allow_debug_headers' in the general config"),
+            param: None,
+        });
+    }
+    Ok(enabled)
+}
+
+/// Describes the request `model` would have sent upstream, with credentials masked.
+pub fn dry_run_body(model_key: &str, model: &ModelInstance, upstream: &UpstreamRequest) -> Value {
+    json!({
+        "dry_run": true,
+        "model": model_key,
+        "upstream_model": model.model_type,
+        "provider": {
+            "key": model.provider.key(),
+            "type": model.provider.r#type().to_string(),
+        },
+        "request": upstream.to_masked_json(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(DRY_RUN_HEADER, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_missing_header_is_not_a_dry_run() {
+        assert_eq!(parse_dry_run(&HeaderMap::new(), false), Ok(false));
+        assert_eq!(parse_dry_run(&HeaderMap::new(), true), Ok(false));
+    }
+
+    #[test]
+    fn test_dry_run_requires_debug_headers() {
+        assert_eq!(parse_dry_run(&headers("true"), true), Ok(true));
+        assert_eq!(parse_dry_run(&headers("TRUE"), true), Ok(true));
+        let rejection = parse_dry_run(&headers("true"), false).unwrap_err();
+        assert_eq!(rejection.status, StatusCode::FORBIDDEN);
+        assert_eq!(parse_dry_run(&headers("false"), false), Ok(false));
+    }
+
+    #[test]
+    fn test_invalid_header_value_is_rejected() {
+        let rejection = parse_dry_run(&headers("yes"), true).unwrap_err();
+        assert_eq!(rejection.status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,291 @@
+//! Explain mode: with `x-hub-explain: true`, the pipeline records what each stage decided
+//! about a request and returns the trace in a `hub_explain` field of the JSON response, or
+//! in a trailing event of a streamed one. Only honoured when `general.allow_debug_headers`
+//! is enabled.
+
+use crate::ai_models::registry::ModelRegistry;
+use crate::pipelines::adaptive_routing::RoutingDecision;
+use crate::pipelines::buffered_body::buffer_response_body;
+use crate::pipelines::dry_run::parse_debug_header;
+use crate::pipelines::pipeline::{HEADER_MODEL_KEY, HEADER_PROVIDER, PipelineScope};
+use crate::providers::failover::SERVED_BY_HEADER;
+use crate::state::PIPELINE_HEADER;
+use crate::trace_content::redact;
+use crate::types::ContentRule;
+use async_stream::stream;
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const EXPLAIN_HEADER: &str = "x-hub-explain";
+/// Field of the response carrying the trace.
+pub const EXPLAIN_FIELD: &str = "hub_explain";
+
+tokio::task_local! {
+    static EXPLAIN: Arc<ExplainTrace>;
+}
+
+/// One decision taken about the request.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExplainStep {
+    /// The stage or plugin that decided, e.g. `routing` or `parameter_policy`.
+    pub stage: &'static str,
+    /// What it decided, e.g. `passed`, `blocked` or `failed`.
+    pub decision: String,
+    /// Time the stage took, for stages doing work of their own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub details: Value,
+}
+
+impl ExplainStep {
+    pub fn new(stage: &'static str, decision: impl Into<String>) -> Self {
+        Self {
+            stage,
+            decision: decision.into(),
+            duration_ms: None,
+            details: Value::Null,
+        }
+    }
+
+    pub fn took(mut self, duration: Duration) -> Self {
+        self.duration_ms = Some(duration.as_secs_f64() * 1000.0);
+        self
+    }
+
+    pub fn details(mut self, details: Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// Adds the step to the trace of the current request, when it's being explained.
+    pub fn record(self) {
+        let _ = EXPLAIN.try_with(|trace| trace.push(self));
+    }
+}
+
+/// Whether the current request is being explained, for stages whose details take work to
+/// gather.
+pub fn is_explaining() -> bool {
+    EXPLAIN.try_with(|_| ()).is_ok()
+}
+
+/// A guardrail's verdict: `passed`, or `blocked` with the reason it gave.
+pub fn guardrail_step(stage: &'static str, rejection: Option<&str>) -> ExplainStep {
+    match rejection {
+        None => ExplainStep::new(stage, "passed"),
+        Some(reason) => ExplainStep::new(stage, "blocked").details(json!({ "reason": reason })),
+    }
+}
+
+/// The routing step: the model `requested` was routed to, if any, how it was picked, and
+/// the candidates considered with the reasons any were skipped.
+pub fn routing_step(
+    model_registry: &ModelRegistry,
+    requested: &str,
+    model_keys: &[String],
+    model_key: Option<&str>,
+    decision: Option<RoutingDecision>,
+    degraded: bool,
+) -> ExplainStep {
+    let candidates: Vec<Value> = model_registry
+        .route_candidates(requested, model_keys)
+        .into_iter()
+        .map(|(key, skipped)| json!({ "model_key": key, "skipped": skipped }))
+        .collect();
+    ExplainStep::new("routing", model_key.unwrap_or("none")).details(json!({
+        "requested": requested,
+        "decision": decision.map(RoutingDecision::as_str),
+        "degraded": degraded,
+        "candidates": candidates,
+    }))
+}
+
+/// Response content quoted in a step, redacted by the `trace_content` rules. `None` when
+/// the rules exclude it, or when the request isn't being explained.
+pub fn explained_content(text: &str) -> Option<String> {
+    EXPLAIN
+        .try_with(|trace| redact(trace.response_content, text))
+        .ok()
+        .flatten()
+}
+
+/// The steps recorded for a request, in order.
+#[derive(Debug)]
+pub struct ExplainTrace {
+    started: Instant,
+    /// How response content quoted in steps is redacted.
+    response_content: ContentRule,
+    steps: Mutex<Vec<ExplainStep>>,
+}
+
+impl ExplainTrace {
+    fn new(response_content: ContentRule) -> Self {
+        Self {
+            started: Instant::now(),
+            response_content,
+            steps: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn push(&self, step: ExplainStep) {
+        self.steps.lock().unwrap().push(step);
+    }
+
+    fn to_value(&self) -> Value {
+        json!({
+            "steps": *self.steps.lock().unwrap(),
+            "total_ms": self.started.elapsed().as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/event-stream"))
+}
+
+/// The step naming the status of the response and the model and provider that served it.
+fn response_step(status: StatusCode, headers: &HeaderMap) -> ExplainStep {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    ExplainStep::new("response", status.as_u16().to_string()).details(json!({
+        "model_key": header(HEADER_MODEL_KEY.as_str()),
+        "provider": header(HEADER_PROVIDER.as_str()),
+        "served_by": header(SERVED_BY_HEADER),
+    }))
+}
+
+/// `body` with the trace added as its `hub_explain` field. Bodies that aren't JSON objects
+/// are returned unchanged.
+fn with_trace(body: Bytes, trace: &ExplainTrace) -> Bytes {
+    match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Object(mut fields)) => {
+            fields.insert(EXPLAIN_FIELD.to_string(), trace.to_value());
+            Bytes::from(Value::Object(fields).to_string())
+        }
+        _ => body,
+    }
+}
+
+/// Pipeline middleware collecting the trace of requests sent with `x-hub-explain: true`.
+pub async fn explain_requests(
+    State(scope): State<Arc<PipelineScope>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let settings = &scope.settings;
+    match parse_debug_header(
+        request.headers(),
+        EXPLAIN_HEADER,
+        settings.allow_debug_headers,
+    ) {
+        Ok(true) => {}
+        Ok(false) => return next.run(request).await,
+        Err(rejection) => return rejection.into_response(),
+    }
+
+    let response_content = settings.trace_content_policy().response_content;
+    let trace = Arc::new(ExplainTrace::new(response_content));
+    let requested = request
+        .headers()
+        .get(PIPELINE_HEADER)
+        .and_then(|value| value.to_str().ok());
+    trace.push(
+        ExplainStep::new("pipeline", scope.name.to_string()).details(json!({
+            "requested": requested,
+        })),
+    );
+    let response = EXPLAIN.scope(trace.clone(), next.run(request)).await;
+
+    let (mut parts, body) = response.into_parts();
+    trace.push(response_step(parts.status, &parts.headers));
+    if !is_event_stream(&parts.headers) {
+        // Responses too large to buffer go out without the trace.
+        let bytes = match buffer_response_body(body, settings.max_buffered_body_bytes).await {
+            Ok(bytes) => bytes,
+            Err(body) => return Response::from_parts(parts, body),
+        };
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::from(with_trace(bytes, &trace)));
+    }
+
+    // Sent once the stream ends, so the trace covers the whole of it.
+    let mut data = body.into_data_stream();
+    let body = Body::from_stream(stream! {
+        while let Some(chunk) = data.next().await {
+            yield chunk;
+        }
+        let event = json!({ EXPLAIN_FIELD: trace.to_value() });
+        yield Ok(Bytes::from(format!("data: {event}\n\n")));
+    });
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_outside_an_explained_request_are_dropped() {
+        assert!(!is_explaining());
+        ExplainStep::new("routing", "gpt-4o").record();
+    }
+
+    #[tokio::test]
+    async fn test_steps_are_recorded_in_order() {
+        let trace = Arc::new(ExplainTrace::new(ContentRule::Include));
+        EXPLAIN
+            .scope(trace.clone(), async {
+                assert!(is_explaining());
+                ExplainStep::new("budget", "passed")
+                    .took(Duration::from_secs(1))
+                    .record();
+                ExplainStep::new("routing", "gpt-4o")
+                    .details(json!({"decision": "best"}))
+                    .record();
+            })
+            .await;
+
+        let trace = trace.to_value();
+        assert_eq!(
+            trace["steps"],
+            json!([
+                {"stage": "budget", "decision": "passed", "duration_ms": 1000.0},
+                {"stage": "routing", "decision": "gpt-4o", "details": {"decision": "best"}},
+            ])
+        );
+        assert!(trace["total_ms"].is_f64());
+    }
+
+    #[test]
+    fn test_trace_is_added_to_json_objects_only() {
+        let trace = ExplainTrace::new(ContentRule::Include);
+        let body = with_trace(Bytes::from(r#"{"id":"chatcmpl-1"}"#), &trace);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["id"], "chatcmpl-1");
+        assert_eq!(body[EXPLAIN_FIELD]["steps"], json!([]));
+
+        let list = Bytes::from(r#"[{"id":"gpt-4o"}]"#);
+        assert_eq!(with_trace(list.clone(), &trace), list);
+    }
+
+    #[test]
+    fn test_explain_requires_debug_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(EXPLAIN_HEADER, "true".parse().unwrap());
+        assert_eq!(parse_debug_header(&headers, EXPLAIN_HEADER, true), Ok(true));
+        let rejection = parse_debug_header(&headers, EXPLAIN_HEADER, false).unwrap_err();
+        assert_eq!(rejection.status, StatusCode::FORBIDDEN);
+        assert!(rejection.message.starts_with(EXPLAIN_HEADER));
+    }
+}
@@ -0,0 +1,359 @@
+use crate::pipelines::buffered_body::{buffer_response_body, read_request_body};
+use crate::pipelines::pipeline::PipelineScope;
+use crate::pipelines::request_validation::RequestValidationError;
+use crate::state_store::StateStore;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue, Method, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Client-chosen key identifying retries of the same request.
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+/// Set to `true` on responses replayed for a duplicate request.
+pub const REPLAY_HEADER: HeaderName = HeaderName::from_static("x-hub-idempotent-replay");
+const MAX_KEY_LENGTH: usize = 255;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl CachedResponse {
+    fn replay(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() =
+            StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::try_from(name.as_str()),
+                HeaderValue::from_str(value),
+            ) {
+                response.headers_mut().append(name, value);
+            }
+        }
+        response
+            .headers_mut()
+            .insert(REPLAY_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// What the `StateStore` remembers about an idempotency key.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Stored {
+    /// A completed request whose response is replayed to duplicates.
+    Response {
+        fingerprint: String,
+        response: CachedResponse,
+    },
+    /// A streaming request. Streams aren't replayed, so duplicates are rejected.
+    Stream { fingerprint: String },
+}
+
+enum Claim {
+    /// First request with the key: run it, then complete the guard.
+    Run(InFlightGuard),
+    /// First streaming request with the key: run it as is.
+    Stream,
+    /// The original is in flight; its response arrives on the channel.
+    Wait(watch::Receiver<Option<Arc<CachedResponse>>>),
+    Replay(CachedResponse),
+    Reject(RequestValidationError),
+}
+
+struct InFlightRequest {
+    fingerprint: String,
+    response: watch::Receiver<Option<Arc<CachedResponse>>>,
+}
+
+/// Requests running under an idempotency key, so duplicates can wait for them, and the
+/// `StateStore` completed ones are kept in.
+pub(crate) struct InFlight {
+    store: Arc<StateStore>,
+    requests: Mutex<HashMap<String, InFlightRequest>>,
+}
+
+impl InFlight {
+    pub(crate) fn new(store: Arc<StateStore>) -> Self {
+        Self {
+            store,
+            requests: Mutex::default(),
+        }
+    }
+
+    /// Decides what a request with `key` does. The in-flight lock is held while the store
+    /// is read, so a response is always either stored or still in flight for a duplicate.
+    /// Whatever is stored is kept for `ttl`.
+    fn claim(
+        self: &Arc<Self>,
+        key: &str,
+        fingerprint: &str,
+        streaming: bool,
+        ttl: Duration,
+    ) -> Claim {
+        let mut requests = self.requests.lock().unwrap();
+        let store = &self.store;
+        let stored = store
+            .get(key)
+            .and_then(|value| serde_json::from_value::<Stored>(value).ok());
+        match stored {
+            Some(Stored::Response {
+                fingerprint: stored_fingerprint,
+                response,
+            }) => {
+                return if stored_fingerprint == fingerprint {
+                    Claim::Replay(response)
+                } else {
+                    Claim::Reject(key_reused())
+                };
+            }
+            Some(Stored::Stream {
+                fingerprint: stored_fingerprint,
+            }) => {
+                return Claim::Reject(if stored_fingerprint == fingerprint {
+                    stream_in_use()
+                } else {
+                    key_reused()
+                });
+            }
+            None => {}
+        }
+        if let Some(in_flight) = requests.get(key) {
+            return if in_flight.fingerprint == fingerprint {
+                Claim::Wait(in_flight.response.clone())
+            } else {
+                Claim::Reject(key_reused())
+            };
+        }
+
+        if streaming {
+            let stored = Stored::Stream {
+                fingerprint: fingerprint.to_string(),
+            };
+            if let Ok(value) = serde_json::to_value(stored) {
+                store.set(key, value, Some(ttl));
+            }
+            return Claim::Stream;
+        }
+        let (sender, receiver) = watch::channel(None);
+        requests.insert(
+            key.to_string(),
+            InFlightRequest {
+                fingerprint: fingerprint.to_string(),
+                response: receiver,
+            },
+        );
+        Claim::Run(InFlightGuard {
+            in_flight: self.clone(),
+            ttl,
+            key: key.to_string(),
+            fingerprint: fingerprint.to_string(),
+            sender,
+            completed: false,
+        })
+    }
+}
+
+/// Held by the original request. Dropping it without completing, e.g. when the client
+/// disconnects, releases the key so waiting duplicates run themselves.
+struct InFlightGuard {
+    in_flight: Arc<InFlight>,
+    ttl: Duration,
+    key: String,
+    fingerprint: String,
+    sender: watch::Sender<Option<Arc<CachedResponse>>>,
+    completed: bool,
+}
+
+impl InFlightGuard {
+    /// Hands the response to waiting duplicates and, when it succeeded, stores it for
+    /// later ones. Responses over `max_body_bytes` aren't kept; dropping the guard lets the
+    /// duplicates run themselves.
+    async fn complete(mut self, response: Response, max_body_bytes: usize) -> Response {
+        let (parts, body) = response.into_parts();
+        let bytes = match buffer_response_body(body, max_body_bytes).await {
+            Ok(bytes) => bytes,
+            Err(body) => return Response::from_parts(parts, body),
+        };
+        let Ok(text) = std::str::from_utf8(&bytes) else {
+            return Response::from_parts(parts, Body::from(bytes));
+        };
+        let cached = CachedResponse {
+            status: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .filter(|(name, _)| *name != header::CONTENT_LENGTH)
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: text.to_string(),
+        };
+
+        let mut requests = self.in_flight.requests.lock().unwrap();
+        // Failures aren't remembered, so a later retry runs again.
+        if parts.status.is_success() {
+            let stored = Stored::Response {
+                fingerprint: self.fingerprint.clone(),
+                response: cached.clone(),
+            };
+            if let Ok(value) = serde_json::to_value(stored) {
+                self.in_flight.store.set(&self.key, value, Some(self.ttl));
+            }
+        }
+        requests.remove(&self.key);
+        drop(requests);
+        self.sender.send_replace(Some(Arc::new(cached)));
+        self.completed = true;
+        Response::from_parts(parts, Body::from(bytes))
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.in_flight.requests.lock().unwrap().remove(&self.key);
+        }
+    }
+}
+
+fn key_reused() -> RequestValidationError {
+    RequestValidationError {
+        status: StatusCode::UNPROCESSABLE_ENTITY,
+        message: "Idempotency-Key was already used for a different request".to_string(),
+        param: None,
+    }
+}
+
+fn stream_in_use() -> RequestValidationError {
+    RequestValidationError {
+        status: StatusCode::CONFLICT,
+        message: "Idempotency-Key was already used for a streaming request; streams can't be \
+                  replayed"
+            .to_string(),
+        param: None,
+    }
+}
+
+pub(crate) fn is_stream_request(body: &[u8]) -> bool {
+    serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|body| body.get("stream")?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Identifies a request by its path and body, so a key reused for another request is caught.
+pub(crate) fn request_fingerprint(path: &str, body: &[u8]) -> String {
+    hex::encode(
+        Sha256::new()
+            .chain_update(path)
+            .chain_update(b"\0")
+            .chain_update(body)
+            .finalize(),
+    )
+}
+
+/// Deduplicates POST requests carrying an `Idempotency-Key`, per pipeline. A duplicate of a
+/// request still in flight waits for its response; a duplicate of a completed one gets the
+/// stored response with `x-hub-idempotent-replay: true`. Streaming requests are never
+/// replayed: duplicates get 409.
+pub async fn deduplicate_requests(
+    State(scope): State<Arc<PipelineScope>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+        _ => {
+            return RequestValidationError {
+                status: StatusCode::BAD_REQUEST,
+                message: format!(
+                    "Idempotency-Key must be 1 to {MAX_KEY_LENGTH} visible ASCII characters"
+                ),
+                param: None,
+            }
+            .into_response();
+        }
+    };
+
+    let max_body_bytes = scope.settings.max_buffered_body_bytes;
+    let (parts, body) = request.into_parts();
+    let body = match read_request_body(body, max_body_bytes).await {
+        Ok(body) => body,
+        Err(rejection) => return rejection,
+    };
+    let fingerprint = request_fingerprint(parts.uri.path(), &body);
+    let streaming = is_stream_request(&body);
+    let state_key = format!("idempotency:{}:{key}", scope.name);
+    let request = Request::from_parts(parts, Body::from(body));
+    let in_flight = &scope.services.in_flight;
+    let ttl = scope.settings.idempotency_ttl;
+
+    loop {
+        match in_flight.claim(&state_key, &fingerprint, streaming, ttl) {
+            Claim::Run(guard) => {
+                return guard
+                    .complete(next.run(request).await, max_body_bytes)
+                    .await;
+            }
+            Claim::Stream => return next.run(request).await,
+            Claim::Replay(response) => return response.replay(),
+            Claim::Reject(rejection) => return rejection.into_response(),
+            Claim::Wait(mut receiver) => {
+                let response = receiver
+                    .wait_for(Option::is_some)
+                    .await
+                    .ok()
+                    .and_then(|response| response.clone());
+                if let Some(response) = response {
+                    return response.replay();
+                }
+                // The original was abandoned; claim the key again.
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_stream_request() {
+        assert!(is_stream_request(br#"{"model": "m", "stream": true}"#));
+        assert!(!is_stream_request(br#"{"model": "m", "stream": false}"#));
+        assert!(!is_stream_request(br#"{"model": "m"}"#));
+        assert!(!is_stream_request(b"not json"));
+    }
+
+    #[test]
+    fn test_replay_restores_status_and_headers() {
+        let cached = CachedResponse {
+            status: 201,
+            headers: vec![("x-hub-model-key".to_string(), "gpt-4o".to_string())],
+            body: "{}".to_string(),
+        };
+        let response = cached.replay();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["x-hub-model-key"], "gpt-4o");
+        assert_eq!(response.headers()[REPLAY_HEADER], "true");
+    }
+}
@@ -0,0 +1,583 @@
+use crate::ai_models::params::repairs_json;
+use crate::metrics::counter;
+use crate::models::chat::{ChatCompletion, ChatCompletionRequest};
+use crate::models::content::ChatMessageContent;
+use crate::models::streaming::ChatCompletionChunk;
+use axum::Json;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// JSON-mode responses the gateway had to repair, by model and outcome.
+pub const JSON_REPAIRS_METRIC: &str = "hub_json_repairs_total";
+
+/// Repairs the content of JSON-mode chat responses for models with `json_repair`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonRepair {
+    /// The `json_schema` response format's schema, which repaired content must match.
+    schema: Option<Value>,
+}
+
+/// Content that isn't JSON even after repair, or doesn't match the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonRepairFailed {
+    pub model_key: String,
+    pub reason: String,
+    /// The content as the provider sent it.
+    pub raw_content: String,
+}
+
+impl fmt::Display for JsonRepairFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Model '{}' returned invalid JSON: {}",
+            self.model_key, self.reason
+        )
+    }
+}
+
+impl IntoResponse for JsonRepairFailed {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "error": {
+                "type": "upstream_error",
+                "message": self.to_string(),
+                "param": null,
+                "code": "invalid_json_response",
+                "raw_content": self.raw_content,
+            }
+        });
+        (StatusCode::BAD_GATEWAY, Json(body)).into_response()
+    }
+}
+
+impl JsonRepair {
+    /// The repair for responses to `request`, when the model opts in and the request asks
+    /// for `json_object` or `json_schema` output.
+    pub fn for_request(
+        params: &HashMap<String, String>,
+        request: &ChatCompletionRequest,
+    ) -> Option<Self> {
+        if !repairs_json(params) {
+            return None;
+        }
+        let response_format = request.response_format.as_ref()?;
+        match response_format.r#type.as_str() {
+            "json_object" => Some(Self { schema: None }),
+            "json_schema" => Some(Self {
+                schema: response_format
+                    .json_schema
+                    .as_ref()
+                    .and_then(|json_schema| json_schema.schema.clone()),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The JSON in `content`, or `None` when it's valid as is.
+    pub fn repair(&self, content: &str) -> Result<Option<String>, String> {
+        let (repaired, value) = match serde_json::from_str::<Value>(content) {
+            Ok(value) => (None, value),
+            Err(_) => {
+                let (json, value) = extract_json(content)
+                    .ok_or_else(|| "no JSON object or array found".to_string())?;
+                (Some(json.to_string()), value)
+            }
+        };
+        if let Some(schema) = &self.schema {
+            validate(schema, schema, &value, "$")?;
+        }
+        Ok(repaired)
+    }
+
+    /// Repairs the text content of each choice.
+    pub fn repair_completion(
+        &self,
+        model_key: &str,
+        completion: &mut ChatCompletion,
+    ) -> Result<(), JsonRepairFailed> {
+        for choice in &mut completion.choices {
+            let Some(content) = &mut choice.message.content else {
+                continue;
+            };
+            let text = content.text_parts().concat();
+            if text.is_empty() {
+                continue;
+            }
+            if let Some(repaired) = self.check(model_key, &text)? {
+                *content = ChatMessageContent::String(repaired);
+            }
+        }
+        Ok(())
+    }
+
+    /// Repairs the content of each choice of a buffered stream. Repaired content is sent in
+    /// the choice's first content delta, and its later content deltas are emptied.
+    pub fn repair_chunks(
+        &self,
+        model_key: &str,
+        chunks: &mut [ChatCompletionChunk],
+    ) -> Result<(), JsonRepairFailed> {
+        let mut contents: BTreeMap<u32, String> = BTreeMap::new();
+        for choice in chunks.iter().flat_map(|chunk| &chunk.choices) {
+            if let Some(content) = &choice.delta.content {
+                contents.entry(choice.index).or_default().push_str(content);
+            }
+        }
+        for (index, text) in contents {
+            if text.is_empty() {
+                continue;
+            }
+            let Some(repaired) = self.check(model_key, &text)? else {
+                continue;
+            };
+            let mut repaired = Some(repaired);
+            let deltas = chunks
+                .iter_mut()
+                .flat_map(|chunk| &mut chunk.choices)
+                .filter(|choice| choice.index == index)
+                .filter_map(|choice| choice.delta.content.as_mut());
+            for content in deltas {
+                *content = repaired.take().unwrap_or_default();
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self, model_key: &str, content: &str) -> Result<Option<String>, JsonRepairFailed> {
+        let outcome = self.repair(content);
+        let label = match &outcome {
+            Ok(None) => return Ok(None),
+            Ok(Some(_)) => "repaired",
+            Err(_) => "failed",
+        };
+        counter!(
+            JSON_REPAIRS_METRIC,
+            "model" => model_key.to_string(),
+            "outcome" => label
+        )
+        .increment(1);
+        outcome.map_err(|reason| JsonRepairFailed {
+            model_key: model_key.to_string(),
+            reason,
+            raw_content: content.to_string(),
+        })
+    }
+}
+
+/// The first JSON object or array in `content` that parses, preferring the body of a
+/// markdown code fence.
+fn extract_json(content: &str) -> Option<(&str, Value)> {
+    let fenced = fence_body(content);
+    fenced.into_iter().chain([content]).find_map(|text| {
+        let text = text.trim();
+        if let Ok(value) = serde_json::from_str(text) {
+            return Some((text, value));
+        }
+        text.char_indices()
+            .filter(|(_, c)| matches!(c, '{' | '['))
+            .find_map(|(start, _)| {
+                let json = balanced_value(&text[start..])?;
+                let value = serde_json::from_str(json).ok()?;
+                Some((json, value))
+            })
+    })
+}
+
+/// The text between the first ``` fence, minus its language tag, and the next one.
+fn fence_body(content: &str) -> Option<&str> {
+    let start = content.find("```")? + 3;
+    let body = &content[start..];
+    // Skip the info string, e.g. `json`.
+    let body = &body[body.find('\n').map_or(0, |newline| newline + 1)..];
+    let end = body.find("```").unwrap_or(body.len());
+    Some(&body[..end])
+}
+
+/// The object or array `text` starts with, up to its matching bracket.
+fn balanced_value(text: &str) -> Option<&str> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(&text[..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Checks `value` against `schema`. Supports the keywords structured outputs use: `type`,
+/// `enum`, `const`, `properties`, `required`, `additionalProperties`, `items`, `anyOf`,
+/// `oneOf`, `allOf` and local `$ref`s. Other keywords are ignored.
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        // `true`, or a schema we can't read, accepts anything; `false` accepts nothing.
+        return match schema {
+            Value::Bool(false) => Err(format!("{path}: no value is allowed here")),
+            _ => Ok(()),
+        };
+    };
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let target = reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .ok_or_else(|| format!("{path}: unresolvable $ref '{reference}'"))?;
+        validate(root, target, value, path)?;
+    }
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+            return Err(format!(
+                "{path}: expected {}, got {}",
+                types.join(" or "),
+                type_name(value)
+            ));
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Err(format!(
+                "{path}: {value} is not one of {}",
+                Value::from(allowed.clone())
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(format!("{path}: expected {expected}"));
+        }
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(options) = schema.get(keyword).and_then(Value::as_array) {
+            if !options
+                .iter()
+                .any(|option| validate(root, option, value, path).is_ok())
+            {
+                return Err(format!("{path}: matches none of the {keyword} schemas"));
+            }
+        }
+    }
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for option in all {
+            validate(root, option, value, path)?;
+        }
+    }
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(name) {
+                return Err(format!("{path}: missing required property '{name}'"));
+            }
+        }
+        for (name, property) in object {
+            let property_path = format!("{path}.{name}");
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property_schema) => validate(root, property_schema, property, &property_path)?,
+                None => {
+                    if let Some(additional) = schema.get("additionalProperties") {
+                        validate(root, additional, property, &property_path)?;
+                    }
+                }
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate(root, item_schema, item, &format!("{path}[{i}]"))?;
+        }
+    }
+    Ok(())
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Bool(_) => "boolean",
+        Value::Null => "null",
+        Value::Number(_) => "number",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai_models::params::JSON_REPAIR_PARAM;
+    use crate::models::streaming::{Choice, ChoiceDelta};
+
+    fn json_object() -> JsonRepair {
+        JsonRepair { schema: None }
+    }
+
+    fn person_schema() -> JsonRepair {
+        JsonRepair {
+            schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "age": {"type": "integer"},
+                    "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}}
+                },
+                "required": ["name", "age"],
+                "additionalProperties": false,
+                "$defs": {"tag": {"type": "string", "enum": ["admin", "user"]}}
+            })),
+        }
+    }
+
+    /// Malformed outputs collected from Gemini and Claude models in JSON mode, and the JSON
+    /// each should be repaired to.
+    const CORPUS: &[(&str, &str)] = &[
+        ("```json\n{\"a\": 1}\n```", r#"{"a": 1}"#),
+        ("```\n{\"a\": 1}\n```", r#"{"a": 1}"#),
+        ("```JSON\n[1, 2]\n```\n", "[1, 2]"),
+        (
+            "Here is the JSON you asked for:\n\n```json\n{\"a\": 1}\n```\n\nLet me know if you need anything else!",
+            r#"{"a": 1}"#,
+        ),
+        (
+            "{\"a\": 1}\n\nThis object contains the requested field.",
+            r#"{"a": 1}"#,
+        ),
+        (
+            "Sure! {\"a\": {\"b\": [1, 2]}} Hope that helps.",
+            r#"{"a": {"b": [1, 2]}}"#,
+        ),
+        (
+            "{\"text\": \"braces } and ] in \\\"strings\\\" {\"} trailing",
+            r#"{"text": "braces } and ] in \"strings\" {"}"#,
+        ),
+        ("\n\n  {\"a\": 1}  \n", r#"{"a": 1}"#),
+        // A truncated fence still holds the JSON.
+        ("```json\n{\"a\": 1}\n", r#"{"a": 1}"#),
+        // Prose with braces before the JSON itself.
+        ("Use {curly} quotes: {\"a\": 1}", r#"{"a": 1}"#),
+        ("{\"a\": 1}{\"b\": 2}", r#"{"a": 1}"#),
+        (
+            "[{\"a\": 1}, {\"a\": 2}] — two items.",
+            r#"[{"a": 1}, {"a": 2}]"#,
+        ),
+    ];
+
+    #[test]
+    fn test_repair_corpus() {
+        for (content, expected) in CORPUS {
+            let repaired = json_object().repair(content).unwrap();
+            assert_eq!(
+                repaired.as_deref(),
+                Some(*expected),
+                "repairing {content:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_valid_json_is_left_alone() {
+        for content in [r#"{"a": 1}"#, "[1, 2]", "  {\"a\": 1}\n"] {
+            assert_eq!(json_object().repair(content), Ok(None));
+        }
+    }
+
+    #[test]
+    fn test_unrepairable_content() {
+        for content in [
+            "I can't help with that.",
+            "```json\n{\"a\": 1,\n```",
+            "{\"a\": 1",
+            "{a: 1}",
+            "",
+            "]{",
+        ] {
+            assert!(
+                json_object().repair(content).is_err(),
+                "repairing {content:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_schema_validation() {
+        let repair = person_schema();
+        assert_eq!(
+            repair.repair("```json\n{\"name\": \"Ada\", \"age\": 36, \"tags\": [\"admin\"]}\n```"),
+            Ok(Some(
+                r#"{"name": "Ada", "age": 36, "tags": ["admin"]}"#.to_string()
+            ))
+        );
+        assert_eq!(repair.repair(r#"{"name": "Ada", "age": 36.0}"#), Ok(None));
+
+        for (content, error) in [
+            (r#"{"name": "Ada"}"#, "$: missing required property 'age'"),
+            (
+                r#"{"name": "Ada", "age": "36"}"#,
+                "$.age: expected integer, got string",
+            ),
+            (
+                r#"{"name": "Ada", "age": 36, "x": 1}"#,
+                "$.x: no value is allowed here",
+            ),
+            (
+                r#"{"name": "Ada", "age": 36, "tags": ["root"]}"#,
+                r#"$.tags[0]: "root" is not one of ["admin","user"]"#,
+            ),
+            (
+                r#"[{"name": "Ada", "age": 36}]"#,
+                "$: expected object, got array",
+            ),
+        ] {
+            assert_eq!(repair.repair(content), Err(error.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_any_of_and_nullable_types() {
+        let repair = JsonRepair {
+            schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "result": {"anyOf": [{"type": "integer"}, {"type": "string", "const": "none"}]},
+                    "note": {"type": ["string", "null"]}
+                }
+            })),
+        };
+        assert!(repair.repair(r#"{"result": 3, "note": null}"#).is_ok());
+        assert!(repair.repair(r#"{"result": "none", "note": "x"}"#).is_ok());
+        assert!(repair.repair(r#"{"result": "some"}"#).is_err());
+        assert!(repair.repair(r#"{"note": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_only_json_modes_with_the_param_are_repaired() {
+        let request = |response_format: Value| -> ChatCompletionRequest {
+            serde_json::from_value(json!({
+                "model": "gpt-4o",
+                "messages": [{"role": "user", "content": "hi"}],
+                "response_format": response_format
+            }))
+            .unwrap()
+        };
+        let on = HashMap::from([(JSON_REPAIR_PARAM.to_string(), "true".to_string())]);
+        let schema = json!({"type": "object"});
+
+        assert_eq!(
+            JsonRepair::for_request(&on, &request(json!({"type": "json_object"}))),
+            Some(json_object())
+        );
+        assert_eq!(
+            JsonRepair::for_request(
+                &on,
+                &request(
+                    json!({"type": "json_schema", "json_schema": {"name": "x", "schema": schema.clone()}})
+                )
+            ),
+            Some(JsonRepair {
+                schema: Some(schema)
+            })
+        );
+        assert_eq!(
+            JsonRepair::for_request(&on, &request(json!({"type": "text"}))),
+            None
+        );
+        assert_eq!(JsonRepair::for_request(&on, &request(Value::Null)), None);
+        assert_eq!(
+            JsonRepair::for_request(&HashMap::new(), &request(json!({"type": "json_object"}))),
+            None
+        );
+    }
+
+    fn chunk(index: u32, content: Option<&str>) -> ChatCompletionChunk {
+        ChatCompletionChunk {
+            id: "chunk".to_string(),
+            choices: vec![Choice {
+                delta: ChoiceDelta {
+                    content: content.map(str::to_string),
+                    role: None,
+                    tool_calls: None,
+                    reasoning: None,
+                    annotations: None,
+                },
+                finish_reason: None,
+                stop_sequence: None,
+                index,
+                logprobs: None,
+            }],
+            created: 0,
+            model: "model".to_string(),
+            service_tier: None,
+            system_fingerprint: None,
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn test_repair_chunks() {
+        let mut chunks = vec![
+            chunk(0, Some("```json\n{\"a\":")),
+            chunk(1, Some("{\"b\": 2}")),
+            chunk(0, Some(" 1}\n```")),
+            chunk(0, None),
+        ];
+        json_object().repair_chunks("model", &mut chunks).unwrap();
+        let contents: Vec<_> = chunks
+            .iter()
+            .map(|chunk| chunk.choices[0].delta.content.as_deref())
+            .collect();
+        assert_eq!(
+            contents,
+            vec![Some(r#"{"a": 1}"#), Some(r#"{"b": 2}"#), Some(""), None]
+        );
+
+        let mut chunks = vec![chunk(0, Some("no")), chunk(0, Some(" JSON"))];
+        let failed = json_object()
+            .repair_chunks("model", &mut chunks)
+            .unwrap_err();
+        assert_eq!(failed.raw_content, "no JSON");
+    }
+}
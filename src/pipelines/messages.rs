@@ -0,0 +1,344 @@
+use crate::models::chat::ChatCompletionRequest;
+use crate::models::messages::{
+    MessagesRequest, MessagesResponse, STOP_SEQUENCE_REASON, stop_reason,
+};
+use crate::models::streaming::ChatCompletionChunk;
+use crate::pipelines::adaptive_routing::inject_routing_decision_header;
+use crate::pipelines::attribution::inject_attribution_headers;
+use crate::pipelines::degraded_mode::inject_degraded_header;
+use crate::pipelines::pipeline::{
+    ChatOutcome, PipelineRunner, apply_timing, inject_model_key_header, inject_provider_header,
+};
+use crate::pipelines::request_validation::{ValidateRequest, ValidatedJson};
+use crate::providers::failover::inject_served_by_header;
+use async_stream::stream;
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive};
+use axum::response::{IntoResponse, Response, Sse};
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use reqwest_streams::error::StreamBodyError;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+/// Anthropic-compatible `POST /messages`. The request runs through the chat pipeline, so any
+/// provider can serve it, and the answer is converted back to Anthropic's message format.
+pub async fn messages(
+    State(runner): State<Arc<PipelineRunner>>,
+    headers: HeaderMap,
+    ValidatedJson(request): ValidatedJson<MessagesRequest>,
+) -> Result<Response, StatusCode> {
+    let payload = ChatCompletionRequest::from(request);
+    if let Err(rejection) = payload.validate() {
+        return Ok(rejection.into_response());
+    }
+
+    let outcome = runner.run_chat(&headers, payload).await?;
+
+    Ok(match outcome {
+        ChatOutcome::Response(response) => response,
+        ChatOutcome::Completion {
+            completion,
+            provider_type,
+            model_key,
+            routing_decision,
+            served_by,
+            degraded,
+            attribution,
+            timing,
+        } => {
+            let mut resp = Json(MessagesResponse::from(completion)).into_response();
+            inject_provider_header(&mut resp, &provider_type);
+            inject_model_key_header(&mut resp, &model_key);
+            inject_routing_decision_header(&mut resp, routing_decision);
+            inject_served_by_header(&mut resp, served_by.as_deref());
+            inject_degraded_header(&mut resp, degraded);
+            inject_attribution_headers(&mut resp, attribution.as_ref());
+            apply_timing(
+                &timing,
+                &mut resp,
+                &provider_type,
+                runner.settings().timing_headers,
+            );
+            resp
+        }
+        ChatOutcome::Stream {
+            chunks,
+            provider_type,
+            model_key,
+            routing_decision,
+            served_by,
+            degraded,
+            attribution,
+        } => {
+            let mut resp = Sse::new(message_events(chunks))
+                .keep_alive(KeepAlive::default())
+                .into_response();
+            inject_provider_header(&mut resp, &provider_type);
+            inject_model_key_header(&mut resp, &model_key);
+            inject_routing_decision_header(&mut resp, routing_decision);
+            inject_served_by_header(&mut resp, served_by.as_deref());
+            inject_degraded_header(&mut resp, degraded);
+            inject_attribution_headers(&mut resp, attribution.as_ref());
+            resp
+        }
+    })
+}
+
+fn message_events(
+    chunks: BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>>,
+) -> impl Stream<Item = Result<Event, axum::Error>> {
+    stream! {
+        let mut chunks = chunks;
+        let mut converter = MessageStream::default();
+        while let Some(chunk) = chunks.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    for event in converter.on_chunk(&chunk) {
+                        yield sse_event(event);
+                    }
+                }
+                Err(e) => {
+                    yield sse_event(json!({
+                        "type": "error",
+                        "error": {"type": "api_error", "message": e.to_string()}
+                    }));
+                    return;
+                }
+            }
+        }
+        for event in converter.finish() {
+            yield sse_event(event);
+        }
+    }
+}
+
+/// Anthropic names each SSE event after its `type`.
+fn sse_event(event: Value) -> Result<Event, axum::Error> {
+    let name = event["type"].as_str().unwrap_or_default().to_string();
+    Event::default().event(name).json_data(event)
+}
+
+enum OpenBlock {
+    Text,
+    ToolUse { id: String },
+}
+
+/// Turns OpenAI chunks into Anthropic's `message_start`, `content_block_*`,
+/// `message_delta` and `message_stop` events.
+#[derive(Default)]
+struct MessageStream {
+    started: bool,
+    open_block: Option<(usize, OpenBlock)>,
+    next_index: usize,
+    stop_reason: Option<&'static str>,
+    stop_sequence: Option<String>,
+    input_tokens: u32,
+    output_tokens: u32,
+}
+
+impl MessageStream {
+    fn on_chunk(&mut self, chunk: &ChatCompletionChunk) -> Vec<Value> {
+        let mut events = Vec::new();
+        if !self.started {
+            self.started = true;
+            events.push(json!({
+                "type": "message_start",
+                "message": {
+                    "id": chunk.id,
+                    "type": "message",
+                    "role": "assistant",
+                    "model": chunk.model,
+                    "content": [],
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {"input_tokens": 0, "output_tokens": 0}
+                }
+            }));
+        }
+        if let Some(usage) = &chunk.usage {
+            self.input_tokens = usage.prompt_tokens;
+            self.output_tokens = usage.completion_tokens;
+        }
+
+        for choice in chunk.choices.iter().filter(|choice| choice.index == 0) {
+            if let Some(text) = choice
+                .delta
+                .content
+                .as_deref()
+                .filter(|text| !text.is_empty())
+            {
+                if !matches!(self.open_block, Some((_, OpenBlock::Text))) {
+                    let block = json!({"type": "text", "text": ""});
+                    self.start_block(&mut events, OpenBlock::Text, block);
+                }
+                events.push(self.delta(json!({"type": "text_delta", "text": text})));
+            }
+            for tool_call in choice.delta.tool_calls.iter().flatten() {
+                let call_id = tool_call.id.as_deref().unwrap_or_default();
+                let function = tool_call.function.clone().unwrap_or_default();
+                // Continuation deltas carry no id of their own.
+                let continues_open_call = match &self.open_block {
+                    Some((_, OpenBlock::ToolUse { id })) => call_id.is_empty() || id == call_id,
+                    _ => false,
+                };
+                if !continues_open_call {
+                    let block = json!({
+                        "type": "tool_use",
+                        "id": call_id,
+                        "name": function.name.unwrap_or_default(),
+                        "input": {}
+                    });
+                    let id = call_id.to_string();
+                    self.start_block(&mut events, OpenBlock::ToolUse { id }, block);
+                }
+                let arguments = function.arguments.unwrap_or_default();
+                if !arguments.is_empty() {
+                    let delta = json!({"type": "input_json_delta", "partial_json": arguments});
+                    events.push(self.delta(delta));
+                }
+            }
+            if let Some(reason) = &choice.finish_reason {
+                self.stop_reason = Some(stop_reason(reason));
+            }
+            if let Some(sequence) = &choice.stop_sequence {
+                self.stop_reason = Some(STOP_SEQUENCE_REASON);
+                self.stop_sequence = Some(sequence.clone());
+            }
+        }
+        events
+    }
+
+    fn finish(&mut self) -> Vec<Value> {
+        let mut events = Vec::new();
+        self.close_block(&mut events);
+        events.push(json!({
+            "type": "message_delta",
+            "delta": {
+                "stop_reason": self.stop_reason.unwrap_or("end_turn"),
+                "stop_sequence": self.stop_sequence
+            },
+            "usage": {"input_tokens": self.input_tokens, "output_tokens": self.output_tokens}
+        }));
+        events.push(json!({"type": "message_stop"}));
+        events
+    }
+
+    fn delta(&self, delta: Value) -> Value {
+        let index = self.open_block.as_ref().map_or(0, |(index, _)| *index);
+        json!({"type": "content_block_delta", "index": index, "delta": delta})
+    }
+
+    fn start_block(&mut self, events: &mut Vec<Value>, kind: OpenBlock, content_block: Value) {
+        self.close_block(events);
+        let index = self.next_index;
+        self.next_index += 1;
+        self.open_block = Some((index, kind));
+        events.push(json!({
+            "type": "content_block_start",
+            "index": index,
+            "content_block": content_block
+        }));
+    }
+
+    fn close_block(&mut self, events: &mut Vec<Value>) {
+        if let Some((index, _)) = self.open_block.take() {
+            events.push(json!({"type": "content_block_stop", "index": index}));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(delta: Value, finish_reason: Option<&str>) -> ChatCompletionChunk {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        }))
+        .unwrap()
+    }
+
+    fn types(events: &[Value]) -> Vec<&str> {
+        events
+            .iter()
+            .map(|event| event["type"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_text_deltas_share_one_block() {
+        let mut stream = MessageStream::default();
+        let first = chunk(json!({"role": "assistant", "content": "Hel"}), None);
+        let mut events = stream.on_chunk(&first);
+        events.extend(stream.on_chunk(&chunk(json!({"content": "lo"}), Some("stop"))));
+        events.extend(stream.finish());
+
+        assert_eq!(
+            types(&events),
+            [
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop"
+            ]
+        );
+        assert_eq!(events[0]["message"]["id"], "chatcmpl-1");
+        assert_eq!(events[3]["delta"]["text"], "lo");
+        assert_eq!(events[5]["delta"]["stop_reason"], "end_turn");
+    }
+
+    #[test]
+    fn test_tool_calls_open_tool_use_blocks() {
+        let mut stream = MessageStream::default();
+        let mut events = stream.on_chunk(&chunk(json!({"content": "Checking."}), None));
+        let tool_call = json!({
+            "id": "call_1",
+            "type": "function",
+            "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+        });
+        let delta = json!({"tool_calls": [tool_call]});
+        events.extend(stream.on_chunk(&chunk(delta, Some("tool_calls"))));
+        events.extend(stream.finish());
+
+        assert_eq!(
+            types(&events),
+            [
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop"
+            ]
+        );
+        assert_eq!(events[4]["index"], 1);
+        assert_eq!(events[4]["content_block"]["type"], "tool_use");
+        assert_eq!(events[4]["content_block"]["name"], "get_weather");
+        assert_eq!(events[5]["delta"]["partial_json"], "{\"city\":\"Paris\"}");
+        assert_eq!(events[7]["delta"]["stop_reason"], "tool_use");
+    }
+
+    #[test]
+    fn test_matched_stop_sequence_is_reported() {
+        let mut stream = MessageStream::default();
+        let mut last = chunk(json!({"content": "Hi"}), Some("stop"));
+        last.choices[0].stop_sequence = Some("\n\nHuman:".to_string());
+        stream.on_chunk(&last);
+
+        let events = stream.finish();
+        assert_eq!(events[1]["delta"]["stop_reason"], "stop_sequence");
+        assert_eq!(events[1]["delta"]["stop_sequence"], "\n\nHuman:");
+    }
+}
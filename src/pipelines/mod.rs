@@ -1,2 +1,28 @@
+pub mod adaptive_routing;
+pub mod attribution;
+pub mod budget;
+pub mod buffered_body;
+pub mod cost;
+pub mod degraded_mode;
+pub mod deprecation;
+pub mod dry_run;
+pub mod explain;
+pub mod idempotency;
+pub mod json_repair;
+pub mod messages;
+pub mod normalization;
 mod otel;
+pub mod parameter_policy;
 pub mod pipeline;
+pub mod race;
+pub mod realtime;
+pub mod request_logging;
+pub mod request_validation;
+pub mod resumable_streams;
+pub mod stream_buffer;
+pub mod stream_cadence;
+pub mod system_prompt;
+pub mod token_count;
+pub mod tool_call_aggregation;
+pub mod tool_limits;
+pub mod usage;
@@ -0,0 +1,213 @@
+use crate::models::chat::ChatCompletion;
+use crate::models::streaming::ChatCompletionChunk;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Groups a choice's provider-specific fields when a pipeline passes them through.
+pub const PROVIDER_METADATA_KEY: &str = "provider_metadata";
+
+/// A response body: either the OpenAI schema as is, or with `provider_metadata` added.
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Normalized<T> {
+    OpenAi(T),
+    WithMetadata(Value),
+}
+
+/// Keeps provider-specific fields out of chat responses, configured by the
+/// `response-normalization` plugin.
+///
+/// Fields outside the OpenAI schema are already dropped when provider responses are
+/// deserialized, except for the few extensions the hub models itself (Gemini safety
+/// ratings, reasoning deltas from OpenAI-compatible upstreams, the matched stop sequence).
+/// Those are stripped by default, or nested under `provider_metadata` on their choice with
+/// `passthrough_extra_fields`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResponseNormalizer {
+    passthrough_extra_fields: bool,
+}
+
+impl ResponseNormalizer {
+    pub fn new(passthrough_extra_fields: bool) -> Self {
+        Self {
+            passthrough_extra_fields,
+        }
+    }
+
+    pub fn completion(&self, mut completion: ChatCompletion) -> Normalized<ChatCompletion> {
+        let extras = completion
+            .choices
+            .iter_mut()
+            .map(|choice| {
+                let stop_sequence = choice.stop_sequence.take().map(Value::String);
+                extra_fields([
+                    ("safety_ratings", choice.safety_ratings.take()),
+                    ("stop_sequence", stop_sequence),
+                ])
+            })
+            .collect();
+        self.finish(completion, extras)
+    }
+
+    pub fn chunk(&self, mut chunk: ChatCompletionChunk) -> Normalized<ChatCompletionChunk> {
+        let extras = chunk
+            .choices
+            .iter_mut()
+            .map(|choice| {
+                let reasoning = choice.delta.reasoning.take().map(Value::String);
+                let stop_sequence = choice.stop_sequence.take().map(Value::String);
+                extra_fields([("reasoning", reasoning), ("stop_sequence", stop_sequence)])
+            })
+            .collect();
+        self.finish(chunk, extras)
+    }
+
+    /// Only re-encodes the response when there are extras to nest, so the common case
+    /// costs no more than serializing the typed response.
+    fn finish<T: Serialize>(&self, response: T, extras: Vec<Map<String, Value>>) -> Normalized<T> {
+        if !self.passthrough_extra_fields || extras.iter().all(Map::is_empty) {
+            return Normalized::OpenAi(response);
+        }
+        let Ok(mut value) = serde_json::to_value(&response) else {
+            return Normalized::OpenAi(response);
+        };
+        if let Some(choices) = value.get_mut("choices").and_then(Value::as_array_mut) {
+            for (choice, extra) in choices.iter_mut().zip(extras) {
+                if !extra.is_empty() {
+                    choice[PROVIDER_METADATA_KEY] = Value::Object(extra);
+                }
+            }
+        }
+        Normalized::WithMetadata(value)
+    }
+}
+
+fn extra_fields<const N: usize>(fields: [(&str, Option<Value>); N]) -> Map<String, Value> {
+    fields
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::vertexai::models::{GeminiChatResponse, VertexAIStreamChunk};
+    use std::collections::BTreeSet;
+
+    const CHUNK_FIELDS: [&str; 8] = [
+        "id",
+        "object",
+        "created",
+        "model",
+        "choices",
+        "service_tier",
+        "system_fingerprint",
+        "usage",
+    ];
+    const CHUNK_CHOICE_FIELDS: [&str; 4] = ["index", "delta", "logprobs", "finish_reason"];
+    const DELTA_FIELDS: [&str; 5] = ["role", "content", "refusal", "tool_calls", "function_call"];
+
+    fn keys(value: &Value) -> BTreeSet<&str> {
+        value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn assert_only(value: &Value, allowed: &[&str]) {
+        let unknown: Vec<_> = keys(value)
+            .into_iter()
+            .filter(|key| !allowed.contains(key))
+            .collect();
+        assert!(
+            unknown.is_empty(),
+            "unexpected fields {unknown:?} in {value}"
+        );
+    }
+
+    fn fixture_chunks(provider: &str) -> Vec<ChatCompletionChunk> {
+        let fixture = std::fs::read_to_string("tests/fixtures/provider_stream_chunks.json")
+            .expect("Failed to read stream chunk fixture");
+        let fixtures: Value = serde_json::from_str(&fixture).unwrap();
+        let chunks = fixtures[provider].as_array().unwrap().iter().cloned();
+        if provider == "vertexai" {
+            chunks
+                .map(|chunk| {
+                    serde_json::from_value::<VertexAIStreamChunk>(chunk)
+                        .unwrap()
+                        .into()
+                })
+                .collect()
+        } else {
+            chunks
+                .map(|chunk| serde_json::from_value(chunk).unwrap())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_chunks_from_every_provider_only_carry_openai_fields() {
+        let normalizer = ResponseNormalizer::default();
+        for provider in ["openai", "azure", "openai_compatible", "vertexai"] {
+            for chunk in fixture_chunks(provider) {
+                let value = serde_json::to_value(normalizer.chunk(chunk)).unwrap();
+                assert_only(&value, &CHUNK_FIELDS);
+                for choice in value["choices"].as_array().unwrap() {
+                    assert_only(choice, &CHUNK_CHOICE_FIELDS);
+                    assert_only(&choice["delta"], &DELTA_FIELDS);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_passthrough_nests_reasoning_under_provider_metadata() {
+        let normalizer = ResponseNormalizer::new(true);
+        let chunk = fixture_chunks("openai_compatible").remove(0);
+
+        let value = serde_json::to_value(normalizer.chunk(chunk)).unwrap();
+        let choice = &value["choices"][0];
+        assert!(choice["delta"].get("reasoning").is_none());
+        assert_eq!(
+            choice[PROVIDER_METADATA_KEY]["reasoning"],
+            "The user greets me."
+        );
+    }
+
+    #[test]
+    fn test_passthrough_leaves_chunks_without_extras_typed() {
+        let normalizer = ResponseNormalizer::new(true);
+        for chunk in fixture_chunks("openai") {
+            assert!(matches!(normalizer.chunk(chunk), Normalized::OpenAi(_)));
+        }
+    }
+
+    #[test]
+    fn test_safety_ratings_are_stripped_or_nested() {
+        let completion = || {
+            let fixture = std::fs::read_to_string("tests/fixtures/vertexai_response_blocked.json")
+                .expect("Failed to read blocked response fixture");
+            serde_json::from_str::<GeminiChatResponse>(&fixture)
+                .unwrap()
+                .to_openai("gemini-1.5-pro".to_string())
+        };
+
+        let stripped = ResponseNormalizer::default().completion(completion());
+        let stripped = serde_json::to_value(stripped).unwrap();
+        assert!(stripped["choices"][0].get("safety_ratings").is_none());
+        assert!(stripped["choices"][0].get(PROVIDER_METADATA_KEY).is_none());
+
+        let nested = ResponseNormalizer::new(true).completion(completion());
+        let nested = serde_json::to_value(nested).unwrap();
+        let choice = &nested["choices"][0];
+        assert!(choice.get("safety_ratings").is_none());
+        assert_eq!(
+            choice[PROVIDER_METADATA_KEY]["safety_ratings"][1]["blocked"],
+            true
+        );
+        assert_eq!(choice["finish_reason"], "content_filter");
+    }
+}
@@ -1,26 +1,32 @@
-use crate::config::lib::get_trace_content_enabled;
 use crate::models::chat::{ChatCompletion, ChatCompletionChoice, ChatCompletionRequest};
 use crate::models::completion::{CompletionRequest, CompletionResponse};
 use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
 use crate::models::embeddings::{EmbeddingsInput, EmbeddingsRequest, EmbeddingsResponse};
 use crate::models::streaming::ChatCompletionChunk;
 use crate::models::usage::{EmbeddingUsage, Usage};
+use crate::trace_content::redact;
+use crate::trace_context::record_trace_id;
+use crate::traceloop_headers::record_traceloop_attributes;
+use crate::types::TraceContentPolicy;
 use opentelemetry::global::{BoxedSpan, ObjectSafeSpan};
-use opentelemetry::trace::{SpanKind, Status, Tracer};
-use opentelemetry::{KeyValue, global};
+use opentelemetry::trace::{FutureExt, SpanKind, Status, TraceContextExt, Tracer, WithContext};
+use opentelemetry::{Context, KeyValue, global};
 use opentelemetry_otlp::{SpanExporter, WithExportConfig, WithHttpConfig};
 use opentelemetry_sdk::propagation::TraceContextPropagator;
 use opentelemetry_sdk::trace::TracerProvider;
 use opentelemetry_semantic_conventions::attribute::GEN_AI_REQUEST_MODEL;
 use opentelemetry_semantic_conventions::trace::*;
 use std::collections::HashMap;
+use std::future::Future;
 
+/// Records a request or response on a span, with content redacted by `policy`.
 pub trait RecordSpan {
-    fn record_span(&self, span: &mut BoxedSpan);
+    fn record_span(&self, span: &mut BoxedSpan, policy: &TraceContentPolicy);
 }
 
 pub struct OtelTracer {
     span: BoxedSpan,
+    policy: TraceContentPolicy,
     accumulated_completion: Option<ChatCompletion>,
 }
 
@@ -87,21 +93,34 @@ impl OtelTracer {
         }
     }
 
-    pub fn start<T: RecordSpan>(operation: &str, request: &T) -> Self {
+    /// Starts the span of a request, as a child of the caller's trace context if it sent one.
+    /// Content is recorded as `policy` says.
+    pub fn start<T: RecordSpan>(operation: &str, request: &T, policy: TraceContentPolicy) -> Self {
         let tracer = global::tracer("traceloop_hub");
         let mut span = tracer
             .span_builder(format!("traceloop_hub.{operation}"))
             .with_kind(SpanKind::Client)
-            .start(&tracer);
+            .start_with_context(&tracer, &Context::current());
+        record_trace_id(span.span_context());
 
-        request.record_span(&mut span);
+        request.record_span(&mut span, &policy);
+        record_traceloop_attributes(&mut span);
 
         Self {
             span,
+            policy,
             accumulated_completion: None,
         }
     }
 
+    /// Runs `future`, e.g. a provider call, with this span as the current one, so upstream
+    /// requests carry it in `traceparent`.
+    pub fn in_span<F: Future>(&self, future: F) -> WithContext<F> {
+        future.with_context(
+            Context::current().with_remote_span_context(self.span.span_context().clone()),
+        )
+    }
+
     pub fn log_chunk(&mut self, chunk: &ChatCompletionChunk) {
         if self.accumulated_completion.is_none() {
             self.accumulated_completion = Some(ChatCompletion {
@@ -112,10 +131,14 @@ impl OtelTracer {
                 choices: vec![],
                 usage: Usage::default(),
                 system_fingerprint: chunk.system_fingerprint.clone(),
+                service_tier: chunk.service_tier.clone(),
             });
         }
 
         if let Some(completion) = &mut self.accumulated_completion {
+            if completion.service_tier.is_none() {
+                completion.service_tier = chunk.service_tier.clone();
+            }
             for chunk_choice in &chunk.choices {
                 if let Some(existing_choice) =
                     completion.choices.get_mut(chunk_choice.index as usize)
@@ -130,10 +153,20 @@ impl OtelTracer {
                     if chunk_choice.finish_reason.is_some() {
                         existing_choice.finish_reason = chunk_choice.finish_reason.clone();
                     }
-                    if let Some(tool_calls) = &chunk_choice.delta.tool_calls {
-                        existing_choice.message.tool_calls = Some(tool_calls.clone());
+                    if let Some(fragments) = &chunk_choice.delta.tool_calls {
+                        let tool_calls = existing_choice.message.tool_calls.get_or_insert_default();
+                        for fragment in fragments {
+                            fragment.merge_into_calls(tool_calls);
+                        }
                     }
                 } else {
+                    let tool_calls = chunk_choice.delta.tool_calls.as_ref().map(|fragments| {
+                        let mut tool_calls = Vec::new();
+                        for fragment in fragments {
+                            fragment.merge_into_calls(&mut tool_calls);
+                        }
+                        tool_calls
+                    });
                     completion.choices.push(ChatCompletionChoice {
                         index: chunk_choice.index,
                         message: ChatCompletionMessage {
@@ -146,12 +179,15 @@ impl OtelTracer {
                             content: Some(ChatMessageContent::String(
                                 chunk_choice.delta.content.clone().unwrap_or_default(),
                             )),
-                            tool_calls: chunk_choice.delta.tool_calls.clone(),
+                            tool_calls,
                             tool_call_id: None,
                             refusal: None,
+                            annotations: None,
                         },
                         finish_reason: chunk_choice.finish_reason.clone(),
                         logprobs: None,
+                        safety_ratings: None,
+                        stop_sequence: None,
                     });
                 }
             }
@@ -160,13 +196,13 @@ impl OtelTracer {
 
     pub fn streaming_end(&mut self) {
         if let Some(completion) = self.accumulated_completion.take() {
-            completion.record_span(&mut self.span);
+            completion.record_span(&mut self.span, &self.policy);
             self.span.set_status(Status::Ok);
         }
     }
 
     pub fn log_success<T: RecordSpan>(&mut self, response: &T) {
-        response.record_span(&mut self.span);
+        response.record_span(&mut self.span, &self.policy);
         self.span.set_status(Status::Ok);
     }
 
@@ -180,8 +216,16 @@ impl OtelTracer {
     }
 }
 
+/// Text content as is, other content as its JSON.
+fn content_text(content: &ChatMessageContent) -> String {
+    match content {
+        ChatMessageContent::String(content) => content.clone(),
+        ChatMessageContent::Array(content) => serde_json::to_string(content).unwrap_or_default(),
+    }
+}
+
 impl RecordSpan for ChatCompletionRequest {
-    fn record_span(&self, span: &mut BoxedSpan) {
+    fn record_span(&self, span: &mut BoxedSpan, policy: &TraceContentPolicy) {
         span.set_attribute(KeyValue::new("llm.request.type", "chat"));
         span.set_attribute(KeyValue::new(GEN_AI_REQUEST_MODEL, self.model.clone()));
 
@@ -203,67 +247,73 @@ impl RecordSpan for ChatCompletionRequest {
         if let Some(temp) = self.temperature {
             span.set_attribute(KeyValue::new(GEN_AI_REQUEST_TEMPERATURE, temp as f64));
         }
+        if let Some(priority) = self.priority {
+            span.set_attribute(KeyValue::new(
+                "gen_ai.request.priority",
+                priority.to_string(),
+            ));
+        }
 
-        if get_trace_content_enabled() {
-            for (i, message) in self.messages.iter().enumerate() {
-                if let Some(content) = &message.content {
-                    span.set_attribute(KeyValue::new(
-                        format!("gen_ai.prompt.{i}.role"),
-                        message.role.clone(),
-                    ));
-                    span.set_attribute(KeyValue::new(
-                        format!("gen_ai.prompt.{i}.content"),
-                        match &content {
-                            ChatMessageContent::String(content) => content.clone(),
-                            ChatMessageContent::Array(content) => {
-                                serde_json::to_string(content).unwrap_or_default()
-                            }
-                        },
-                    ));
-                }
+        for (i, message) in self.messages.iter().enumerate() {
+            let content = message.content.as_ref().and_then(|content| {
+                redact(policy.message_rule(&message.role), &content_text(content))
+            });
+            if let Some(content) = content {
+                span.set_attribute(KeyValue::new(
+                    format!("gen_ai.prompt.{i}.role"),
+                    message.role.clone(),
+                ));
+                span.set_attribute(KeyValue::new(format!("gen_ai.prompt.{i}.content"), content));
             }
         }
     }
 }
 
 impl RecordSpan for ChatCompletion {
-    fn record_span(&self, span: &mut BoxedSpan) {
+    fn record_span(&self, span: &mut BoxedSpan, policy: &TraceContentPolicy) {
         span.set_attribute(KeyValue::new(GEN_AI_RESPONSE_MODEL, self.model.clone()));
         span.set_attribute(KeyValue::new(GEN_AI_RESPONSE_ID, self.id.clone()));
+        if let Some(service_tier) = &self.service_tier {
+            span.set_attribute(KeyValue::new(
+                "gen_ai.response.service_tier",
+                service_tier.clone(),
+            ));
+        }
 
-        self.usage.record_span(span);
+        self.usage.record_span(span, policy);
 
-        if get_trace_content_enabled() {
-            for choice in &self.choices {
-                if let Some(content) = &choice.message.content {
-                    span.set_attribute(KeyValue::new(
-                        format!("gen_ai.completion.{}.role", choice.index),
-                        choice.message.role.clone(),
-                    ));
-                    span.set_attribute(KeyValue::new(
-                        format!("gen_ai.completion.{}.content", choice.index),
-                        match &content {
-                            ChatMessageContent::String(content) => content.clone(),
-                            ChatMessageContent::Array(content) => {
-                                serde_json::to_string(content).unwrap_or_default()
-                            }
-                        },
-                    ));
-                }
+        let rule = policy.response_content;
+        for choice in &self.choices {
+            let content = choice
+                .message
+                .content
+                .as_ref()
+                .and_then(|content| redact(rule, &content_text(content)));
+            if let Some(content) = content {
+                span.set_attribute(KeyValue::new(
+                    format!("gen_ai.completion.{}.role", choice.index),
+                    choice.message.role.clone(),
+                ));
                 span.set_attribute(KeyValue::new(
-                    format!("gen_ai.completion.{}.finish_reason", choice.index),
-                    choice.finish_reason.clone().unwrap_or_default(),
+                    format!("gen_ai.completion.{}.content", choice.index),
+                    content,
                 ));
             }
+            span.set_attribute(KeyValue::new(
+                format!("gen_ai.completion.{}.finish_reason", choice.index),
+                choice.finish_reason.clone().unwrap_or_default(),
+            ));
         }
     }
 }
 
 impl RecordSpan for CompletionRequest {
-    fn record_span(&self, span: &mut BoxedSpan) {
+    fn record_span(&self, span: &mut BoxedSpan, policy: &TraceContentPolicy) {
         span.set_attribute(KeyValue::new("llm.request.type", "completion"));
         span.set_attribute(KeyValue::new(GEN_AI_REQUEST_MODEL, self.model.clone()));
-        span.set_attribute(KeyValue::new("gen_ai.prompt", self.prompt.clone()));
+        if let Some(prompt) = redact(policy.messages.user, &self.prompt) {
+            span.set_attribute(KeyValue::new("gen_ai.prompt", prompt));
+        }
 
         if let Some(freq_penalty) = self.frequency_penalty {
             span.set_attribute(KeyValue::new(
@@ -287,21 +337,24 @@ impl RecordSpan for CompletionRequest {
 }
 
 impl RecordSpan for CompletionResponse {
-    fn record_span(&self, span: &mut BoxedSpan) {
+    fn record_span(&self, span: &mut BoxedSpan, policy: &TraceContentPolicy) {
         span.set_attribute(KeyValue::new(GEN_AI_RESPONSE_MODEL, self.model.clone()));
         span.set_attribute(KeyValue::new(GEN_AI_RESPONSE_ID, self.id.clone()));
 
-        self.usage.record_span(span);
+        self.usage.record_span(span, policy);
 
+        let rule = policy.response_content;
         for choice in &self.choices {
-            span.set_attribute(KeyValue::new(
-                format!("gen_ai.completion.{}.role", choice.index),
-                "assistant".to_string(),
-            ));
-            span.set_attribute(KeyValue::new(
-                format!("gen_ai.completion.{}.content", choice.index),
-                choice.text.clone(),
-            ));
+            if let Some(content) = redact(rule, &choice.text) {
+                span.set_attribute(KeyValue::new(
+                    format!("gen_ai.completion.{}.role", choice.index),
+                    "assistant".to_string(),
+                ));
+                span.set_attribute(KeyValue::new(
+                    format!("gen_ai.completion.{}.content", choice.index),
+                    content,
+                ));
+            }
             span.set_attribute(KeyValue::new(
                 format!("gen_ai.completion.{}.finish_reason", choice.index),
                 choice.finish_reason.clone().unwrap_or_default(),
@@ -311,59 +364,59 @@ impl RecordSpan for CompletionResponse {
 }
 
 impl RecordSpan for EmbeddingsRequest {
-    fn record_span(&self, span: &mut BoxedSpan) {
+    fn record_span(&self, span: &mut BoxedSpan, policy: &TraceContentPolicy) {
         span.set_attribute(KeyValue::new("llm.request.type", "embeddings"));
         span.set_attribute(KeyValue::new(GEN_AI_REQUEST_MODEL, self.model.clone()));
 
-        if get_trace_content_enabled() {
-            match &self.input {
-                EmbeddingsInput::Single(text) => {
-                    span.set_attribute(KeyValue::new("llm.prompt.0.content", text.clone()));
+        let rule = policy.messages.user;
+        match &self.input {
+            EmbeddingsInput::Single(text) => {
+                if let Some(text) = redact(rule, text) {
+                    span.set_attribute(KeyValue::new("llm.prompt.0.content", text));
                 }
-                EmbeddingsInput::Multiple(texts) => {
-                    for (i, text) in texts.iter().enumerate() {
-                        span.set_attribute(KeyValue::new(
-                            format!("llm.prompt.{i}.role"),
-                            "user".to_string(),
-                        ));
-                        span.set_attribute(KeyValue::new(
-                            format!("llm.prompt.{i}.content"),
-                            text.clone(),
-                        ));
-                    }
-                }
-                EmbeddingsInput::SingleTokenIds(token_ids) => {
+            }
+            EmbeddingsInput::Multiple(texts) => {
+                for (i, text) in texts.iter().enumerate() {
+                    let Some(text) = redact(rule, text) else {
+                        continue;
+                    };
                     span.set_attribute(KeyValue::new(
-                        "llm.prompt.0.content",
-                        format!("{token_ids:?}"),
+                        format!("llm.prompt.{i}.role"),
+                        "user".to_string(),
                     ));
+                    span.set_attribute(KeyValue::new(format!("llm.prompt.{i}.content"), text));
                 }
-                EmbeddingsInput::MultipleTokenIds(token_ids) => {
-                    for (i, token_ids) in token_ids.iter().enumerate() {
-                        span.set_attribute(KeyValue::new(
-                            format!("llm.prompt.{i}.role"),
-                            "user".to_string(),
-                        ));
-                        span.set_attribute(KeyValue::new(
-                            format!("llm.prompt.{i}.content"),
-                            format!("{token_ids:?}"),
-                        ));
-                    }
+            }
+            EmbeddingsInput::SingleTokenIds(token_ids) => {
+                if let Some(token_ids) = redact(rule, &format!("{token_ids:?}")) {
+                    span.set_attribute(KeyValue::new("llm.prompt.0.content", token_ids));
+                }
+            }
+            EmbeddingsInput::MultipleTokenIds(token_ids) => {
+                for (i, token_ids) in token_ids.iter().enumerate() {
+                    let Some(token_ids) = redact(rule, &format!("{token_ids:?}")) else {
+                        continue;
+                    };
+                    span.set_attribute(KeyValue::new(
+                        format!("llm.prompt.{i}.role"),
+                        "user".to_string(),
+                    ));
+                    span.set_attribute(KeyValue::new(format!("llm.prompt.{i}.content"), token_ids));
                 }
             }
         }
     }
 }
 impl RecordSpan for EmbeddingsResponse {
-    fn record_span(&self, span: &mut BoxedSpan) {
+    fn record_span(&self, span: &mut BoxedSpan, policy: &TraceContentPolicy) {
         span.set_attribute(KeyValue::new(GEN_AI_RESPONSE_MODEL, self.model.clone()));
 
-        self.usage.record_span(span);
+        self.usage.record_span(span, policy);
     }
 }
 
 impl RecordSpan for Usage {
-    fn record_span(&self, span: &mut BoxedSpan) {
+    fn record_span(&self, span: &mut BoxedSpan, _policy: &TraceContentPolicy) {
         span.set_attribute(KeyValue::new(
             "gen_ai.usage.prompt_tokens",
             self.prompt_tokens as i64,
@@ -376,11 +429,35 @@ impl RecordSpan for Usage {
             "gen_ai.usage.total_tokens",
             self.total_tokens as i64,
         ));
+        if let Some(details) = &self.prompt_tokens_details {
+            if let Some(cached) = details.cached_tokens {
+                span.set_attribute(KeyValue::new(
+                    "gen_ai.usage.cache_read_input_tokens",
+                    cached as i64,
+                ));
+            }
+            if let Some(written) = details.cache_creation_tokens {
+                span.set_attribute(KeyValue::new(
+                    "gen_ai.usage.cache_creation_input_tokens",
+                    written as i64,
+                ));
+            }
+        }
+        if let Some(reasoning) = self
+            .completion_tokens_details
+            .as_ref()
+            .and_then(|details| details.reasoning_tokens)
+        {
+            span.set_attribute(KeyValue::new(
+                "gen_ai.usage.reasoning_tokens",
+                reasoning as i64,
+            ));
+        }
     }
 }
 
 impl RecordSpan for EmbeddingUsage {
-    fn record_span(&self, span: &mut BoxedSpan) {
+    fn record_span(&self, span: &mut BoxedSpan, _policy: &TraceContentPolicy) {
         span.set_attribute(KeyValue::new(
             "gen_ai.usage.prompt_tokens",
             self.prompt_tokens.unwrap_or(0) as i64,
@@ -0,0 +1,540 @@
+use crate::outcome::Outcome;
+use crate::pipelines::buffered_body::read_request_body;
+use crate::pipelines::explain::{ExplainStep, guardrail_step};
+use crate::pipelines::request_validation::RequestValidationError;
+use crate::types::{ParameterPolicyMode, ParameterRule};
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde_json::{Map, Number, Value, json};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Lists the request fields the policy stripped or clamped, comma separated.
+pub const SANITIZED_HEADER: HeaderName = HeaderName::from_static("x-hub-sanitized-params");
+
+/// Fields the policy can't deny, since routing depends on them.
+const UNDENIABLE_FIELDS: &[&str] = &["model"];
+
+/// Request field carrying provider-specific parameters to merge into the upstream body.
+pub const EXTRA_BODY_FIELD: &str = "extra_body";
+
+/// Fields of the unified chat, completion and embeddings requests. The hub translates them
+/// for each provider, so `extra_body` can't set them.
+pub const HUB_MANAGED_FIELDS: &[&str] = &[
+    "model",
+    "messages",
+    "prompt",
+    "input",
+    "suffix",
+    "temperature",
+    "top_p",
+    "n",
+    "stream",
+    "stream_options",
+    "stop",
+    "max_tokens",
+    "max_completion_tokens",
+    "parallel_tool_calls",
+    "presence_penalty",
+    "frequency_penalty",
+    "logit_bias",
+    "logprobs",
+    "top_logprobs",
+    "echo",
+    "best_of",
+    "tool_choice",
+    "tools",
+    "user",
+    "response_format",
+    "reasoning",
+    "reasoning_effort",
+    "store",
+    "metadata",
+    "prediction",
+    "web_search_options",
+    "encoding_format",
+    "dimensions",
+    EXTRA_BODY_FIELD,
+];
+
+/// Request field rules for a single pipeline, configured through the `parameter-policy` plugin.
+/// Pipelines without the plugin get the default policy, which has no rules and rejects
+/// `extra_body`.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterPolicy {
+    mode: ParameterPolicyMode,
+    rules: BTreeMap<String, ParameterRule>,
+    allow_extra_body: bool,
+    extra_body_keys: Vec<String>,
+}
+
+impl ParameterPolicy {
+    pub fn new(mode: ParameterPolicyMode, rules: BTreeMap<String, ParameterRule>) -> Self {
+        Self {
+            mode,
+            rules,
+            ..Default::default()
+        }
+    }
+
+    /// Accepts `extra_body`, limited to `keys` unless they're empty.
+    pub fn with_extra_body(mut self, keys: Vec<String>) -> Self {
+        self.allow_extra_body = true;
+        self.extra_body_keys = keys;
+        self
+    }
+
+    /// Checks `extra_body` against the pipeline's settings. Keys colliding with hub-managed
+    /// fields, or with fields the rules cover, are rejected in both modes; anything else
+    /// that isn't allowed is stripped when sanitizing.
+    fn apply_extra_body(
+        &self,
+        body: &mut Map<String, Value>,
+        sanitized: &mut Vec<String>,
+    ) -> Result<(), RequestValidationError> {
+        let Some(extra_body) = body.get_mut(EXTRA_BODY_FIELD) else {
+            return Ok(());
+        };
+        if extra_body.is_null() {
+            return Ok(());
+        }
+        if !self.allow_extra_body {
+            if self.mode == ParameterPolicyMode::Strict {
+                return Err(RequestValidationError::policy_violation(
+                    EXTRA_BODY_FIELD,
+                    format!("'{EXTRA_BODY_FIELD}' is not allowed by this pipeline"),
+                ));
+            }
+            body.remove(EXTRA_BODY_FIELD);
+            sanitized.push(EXTRA_BODY_FIELD.to_string());
+            return Ok(());
+        }
+        let Some(extra_body) = extra_body.as_object_mut() else {
+            return Err(RequestValidationError::policy_violation(
+                EXTRA_BODY_FIELD,
+                format!("'{EXTRA_BODY_FIELD}' must be an object"),
+            ));
+        };
+        if let Some(key) = extra_body
+            .keys()
+            .find(|key| HUB_MANAGED_FIELDS.contains(&key.as_str()) || self.rules.contains_key(*key))
+        {
+            return Err(RequestValidationError::policy_violation(
+                EXTRA_BODY_FIELD,
+                format!("'{EXTRA_BODY_FIELD}.{key}' collides with a field the hub manages"),
+            ));
+        }
+        if self.extra_body_keys.is_empty() {
+            return Ok(());
+        }
+        let unlisted: Vec<String> = extra_body
+            .keys()
+            .filter(|key| !self.extra_body_keys.contains(key))
+            .cloned()
+            .collect();
+        if let Some(key) = unlisted.first() {
+            if self.mode == ParameterPolicyMode::Strict {
+                return Err(RequestValidationError::policy_violation(
+                    EXTRA_BODY_FIELD,
+                    format!("'{EXTRA_BODY_FIELD}.{key}' is not allowed by this pipeline"),
+                ));
+            }
+        }
+        for key in unlisted {
+            extra_body.remove(&key);
+            sanitized.push(format!("{EXTRA_BODY_FIELD}.{key}"));
+        }
+        Ok(())
+    }
+
+    /// Applies every rule to a request body, returning the fields that were sanitized.
+    pub fn apply(
+        &self,
+        body: &mut Map<String, Value>,
+    ) -> Result<Vec<String>, RequestValidationError> {
+        let mut sanitized = Vec::new();
+        for (field, rule) in &self.rules {
+            let value = body.get(field).filter(|value| !value.is_null());
+            match (rule, value) {
+                (ParameterRule::Require, None) => {
+                    return Err(RequestValidationError::policy_violation(
+                        field,
+                        format!("'{field}' is required by this pipeline"),
+                    ));
+                }
+                (ParameterRule::Deny, Some(_)) => {
+                    if self.mode == ParameterPolicyMode::Strict {
+                        return Err(RequestValidationError::policy_violation(
+                            field,
+                            format!("'{field}' is not allowed by this pipeline"),
+                        ));
+                    }
+                    body.remove(field);
+                    sanitized.push(field.clone());
+                }
+                (ParameterRule::Clamp { min, max }, Some(value)) => {
+                    let Some(number) = value.as_f64() else {
+                        return Err(RequestValidationError::policy_violation(
+                            field,
+                            format!("'{field}' must be a number"),
+                        ));
+                    };
+                    let bounded = max
+                        .map_or(number, |max| number.min(max))
+                        .max(min.unwrap_or(f64::NEG_INFINITY));
+                    if bounded == number {
+                        continue;
+                    }
+                    if self.mode == ParameterPolicyMode::Strict {
+                        let bounds = describe_bounds(*min, *max);
+                        return Err(RequestValidationError::policy_violation(
+                            field,
+                            format!("'{field}' must be {bounds}, got {number}"),
+                        ));
+                    }
+                    let integer = value.is_i64() || value.is_u64();
+                    body.insert(field.clone(), number_value(bounded, integer));
+                    sanitized.push(field.clone());
+                }
+                _ => {}
+            }
+        }
+        self.apply_extra_body(body, &mut sanitized)?;
+        Ok(sanitized)
+    }
+}
+
+fn describe_bounds(min: Option<f64>, max: Option<f64>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!("between {min} and {max}"),
+        (Some(min), None) => format!("at least {min}"),
+        (None, Some(max)) => format!("at most {max}"),
+        (None, None) => "a number".to_string(),
+    }
+}
+
+/// Keeps integer fields such as `max_tokens` integers after clamping.
+fn number_value(value: f64, integer: bool) -> Value {
+    if integer && value.fract() == 0.0 {
+        Value::from(value as i64)
+    } else {
+        Number::from_f64(value).map_or(Value::Null, Value::Number)
+    }
+}
+
+/// Checks a policy's rules and `extra_body` settings, shared by config validation and the
+/// management API.
+pub fn validate_parameter_policy(
+    rules: &BTreeMap<String, ParameterRule>,
+    allow_extra_body: bool,
+    extra_body_keys: &[String],
+) -> Result<(), String> {
+    if rules.is_empty() && !allow_extra_body {
+        return Err("parameter-policy needs at least one rule or allow_extra_body".to_string());
+    }
+    if !allow_extra_body && !extra_body_keys.is_empty() {
+        return Err("parameter-policy extra_body_keys need allow_extra_body".to_string());
+    }
+    for key in extra_body_keys {
+        if key.trim().is_empty() {
+            return Err("parameter-policy extra_body_keys must not be empty".to_string());
+        }
+        if HUB_MANAGED_FIELDS.contains(&key.as_str()) || rules.contains_key(key) {
+            return Err(format!(
+                "parameter-policy can't allow '{key}' in extra_body, the hub manages it"
+            ));
+        }
+    }
+    for (field, rule) in rules {
+        if field.trim().is_empty() {
+            return Err("parameter-policy rule names must not be empty".to_string());
+        }
+        match rule {
+            ParameterRule::Deny if UNDENIABLE_FIELDS.contains(&field.as_str()) => {
+                return Err(format!("parameter-policy can't deny '{field}'"));
+            }
+            ParameterRule::Clamp { min, max } => {
+                if min.is_none() && max.is_none() {
+                    return Err(format!(
+                        "parameter-policy clamp for '{field}' needs min or max"
+                    ));
+                }
+                if [min, max]
+                    .into_iter()
+                    .flatten()
+                    .any(|bound| !bound.is_finite())
+                {
+                    return Err(format!(
+                        "parameter-policy clamp for '{field}' must be finite"
+                    ));
+                }
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        return Err(format!(
+                            "parameter-policy clamp for '{field}' has min {min} above max {max}"
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Middleware applying the pipeline's parameter policy to JSON request bodies, which are
+/// read up to `max_body_bytes`.
+pub async fn enforce_parameter_policy(
+    State((policy, max_body_bytes)): State<(Arc<ParameterPolicy>, usize)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = request.into_parts();
+    let bytes = match read_request_body(body, max_body_bytes).await {
+        Ok(bytes) => bytes,
+        Err(rejection) => return rejection,
+    };
+
+    // Bodies that aren't JSON objects are left for the handler to reject.
+    let Ok(Value::Object(mut fields)) = serde_json::from_slice::<Value>(&bytes) else {
+        return next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await;
+    };
+    let started = Instant::now();
+    let sanitized = match policy.apply(&mut fields) {
+        Ok(sanitized) => sanitized,
+        Err(rejection) => {
+            guardrail_step("parameter_policy", Some(&rejection.message))
+                .took(started.elapsed())
+                .record();
+            return Outcome::GuardrailBlock.tagged(rejection.into_response());
+        }
+    };
+    let step = if sanitized.is_empty() {
+        ExplainStep::new("parameter_policy", "passed")
+    } else {
+        ExplainStep::new("parameter_policy", "sanitized").details(json!({ "fields": sanitized }))
+    };
+    step.took(started.elapsed()).record();
+    if sanitized.is_empty() {
+        return next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await;
+    }
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let body = Body::from(Value::Object(fields).to_string());
+    let mut response = next.run(Request::from_parts(parts, body)).await;
+    if let Ok(value) = HeaderValue::from_str(&sanitized.join(",")) {
+        response.headers_mut().insert(SANITIZED_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy(mode: ParameterPolicyMode, rules: &[(&str, ParameterRule)]) -> ParameterPolicy {
+        ParameterPolicy::new(
+            mode,
+            rules
+                .iter()
+                .map(|(field, rule)| (field.to_string(), rule.clone()))
+                .collect(),
+        )
+    }
+
+    fn body(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    const MAX_TEMPERATURE: ParameterRule = ParameterRule::Clamp {
+        min: None,
+        max: Some(1.0),
+    };
+
+    #[test]
+    fn test_sanitize_clamps_temperature() {
+        let policy = policy(
+            ParameterPolicyMode::Sanitize,
+            &[("temperature", MAX_TEMPERATURE)],
+        );
+        let mut fields = body(json!({"model": "gpt-4o", "temperature": 1.7}));
+
+        let sanitized = policy.apply(&mut fields).unwrap();
+        assert_eq!(sanitized, vec!["temperature"]);
+        assert_eq!(fields["temperature"], json!(1.0));
+    }
+
+    #[test]
+    fn test_strict_rejects_out_of_range_temperature() {
+        let policy = policy(
+            ParameterPolicyMode::Strict,
+            &[("temperature", MAX_TEMPERATURE)],
+        );
+        let mut fields = body(json!({"model": "gpt-4o", "temperature": 1.7}));
+
+        let rejection = policy.apply(&mut fields).unwrap_err();
+        assert_eq!(rejection.param.as_deref(), Some("temperature"));
+        assert!(rejection.message.contains("at most 1"));
+
+        let mut fields = body(json!({"model": "gpt-4o", "temperature": 0.3}));
+        assert!(policy.apply(&mut fields).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_deny_strips_or_rejects_user() {
+        let rules = [("user", ParameterRule::Deny)];
+        let mut fields = body(json!({"model": "gpt-4o", "user": "alice@example.com"}));
+        let sanitized = policy(ParameterPolicyMode::Sanitize, &rules)
+            .apply(&mut fields)
+            .unwrap();
+        assert_eq!(sanitized, vec!["user"]);
+        assert!(!fields.contains_key("user"));
+
+        let mut fields = body(json!({"model": "gpt-4o", "user": "alice@example.com"}));
+        let rejection = policy(ParameterPolicyMode::Strict, &rules)
+            .apply(&mut fields)
+            .unwrap_err();
+        assert_eq!(rejection.param.as_deref(), Some("user"));
+    }
+
+    #[test]
+    fn test_require_rejects_in_both_modes() {
+        for mode in [ParameterPolicyMode::Strict, ParameterPolicyMode::Sanitize] {
+            let policy = policy(mode, &[("max_tokens", ParameterRule::Require)]);
+            let mut fields = body(json!({"model": "gpt-4o"}));
+            let rejection = policy.apply(&mut fields).unwrap_err();
+            assert_eq!(rejection.param.as_deref(), Some("max_tokens"));
+
+            let mut fields = body(json!({"model": "gpt-4o", "max_tokens": 256}));
+            assert!(policy.apply(&mut fields).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_clamped_integers_stay_integers() {
+        let policy = policy(
+            ParameterPolicyMode::Sanitize,
+            &[(
+                "max_tokens",
+                ParameterRule::Clamp {
+                    min: Some(1.0),
+                    max: Some(1024.0),
+                },
+            )],
+        );
+        let mut fields = body(json!({"max_tokens": 4096}));
+        policy.apply(&mut fields).unwrap();
+        assert_eq!(fields["max_tokens"], json!(1024));
+        assert!(fields["max_tokens"].is_u64());
+    }
+
+    #[test]
+    fn test_validate_parameter_policy() {
+        let rules = |rules: &[(&str, ParameterRule)]| {
+            rules
+                .iter()
+                .map(|(field, rule)| (field.to_string(), rule.clone()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let validate =
+            |r: &[(&str, ParameterRule)]| validate_parameter_policy(&rules(r), false, &[]);
+        assert!(validate(&[("temperature", MAX_TEMPERATURE)]).is_ok());
+        assert!(validate(&[]).is_err());
+        assert!(validate(&[("model", ParameterRule::Deny)]).is_err());
+        assert!(
+            validate(&[(
+                "temperature",
+                ParameterRule::Clamp {
+                    min: None,
+                    max: None
+                }
+            )])
+            .is_err()
+        );
+        assert!(
+            validate(&[(
+                "temperature",
+                ParameterRule::Clamp {
+                    min: Some(2.0),
+                    max: Some(1.0)
+                }
+            )])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_extra_body_keys() {
+        let validate = |rules: &BTreeMap<String, ParameterRule>, allow: bool, keys: &[&str]| {
+            let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+            validate_parameter_policy(rules, allow, &keys)
+        };
+        let no_rules = BTreeMap::new();
+        let deny_user = BTreeMap::from([("user".to_string(), ParameterRule::Deny)]);
+        assert!(validate(&no_rules, true, &[]).is_ok());
+        assert!(validate(&no_rules, true, &["safety_settings"]).is_ok());
+        assert!(validate(&no_rules, false, &["safety_settings"]).is_err());
+        assert!(validate(&no_rules, true, &["temperature"]).is_err());
+        assert!(validate(&deny_user, true, &["user"]).is_err());
+    }
+
+    #[test]
+    fn test_extra_body_is_rejected_unless_allowed() {
+        let mut fields = body(json!({"model": "gpt-4o", "extra_body": {"top_k": 5}}));
+        let rejection = ParameterPolicy::default().apply(&mut fields).unwrap_err();
+        assert_eq!(rejection.param.as_deref(), Some("extra_body"));
+
+        let sanitize = ParameterPolicy::new(ParameterPolicyMode::Sanitize, BTreeMap::new());
+        assert_eq!(sanitize.apply(&mut fields).unwrap(), vec!["extra_body"]);
+        assert!(!fields.contains_key("extra_body"));
+
+        let allowed = ParameterPolicy::default().with_extra_body(vec![]);
+        let mut fields = body(json!({"model": "gpt-4o", "extra_body": {"top_k": 5}}));
+        assert!(allowed.apply(&mut fields).unwrap().is_empty());
+        assert_eq!(fields["extra_body"], json!({"top_k": 5}));
+    }
+
+    #[test]
+    fn test_extra_body_rejects_hub_managed_keys_in_both_modes() {
+        let rules = BTreeMap::from([("safety_settings".to_string(), ParameterRule::Deny)]);
+        for mode in [ParameterPolicyMode::Strict, ParameterPolicyMode::Sanitize] {
+            let policy = ParameterPolicy::new(mode, rules.clone()).with_extra_body(vec![]);
+            for extra_body in [
+                json!({"temperature": 2.0}),
+                json!({"safety_settings": []}),
+                json!("top_k=5"),
+            ] {
+                let mut fields = body(json!({"model": "gpt-4o", "extra_body": extra_body}));
+                let rejection = policy.apply(&mut fields).unwrap_err();
+                assert_eq!(rejection.param.as_deref(), Some("extra_body"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_extra_body_allowlist() {
+        let keys = vec!["top_k".to_string()];
+        let mut fields = body(json!({"extra_body": {"top_k": 5, "safe_prompt": true}}));
+        let rejection = ParameterPolicy::new(ParameterPolicyMode::Strict, BTreeMap::new())
+            .with_extra_body(keys.clone())
+            .apply(&mut fields)
+            .unwrap_err();
+        assert!(rejection.message.contains("'extra_body.safe_prompt'"));
+
+        let sanitized = ParameterPolicy::new(ParameterPolicyMode::Sanitize, BTreeMap::new())
+            .with_extra_body(keys)
+            .apply(&mut fields)
+            .unwrap();
+        assert_eq!(sanitized, vec!["extra_body.safe_prompt"]);
+        assert_eq!(fields["extra_body"], json!({"top_k": 5}));
+    }
+}
@@ -1,34 +1,94 @@
-use crate::config::models::PipelineType;
-use crate::models::chat::ChatCompletionResponse;
-use crate::models::completion::CompletionRequest;
-use crate::models::embeddings::EmbeddingsRequest;
+use crate::artifacts::record_artifacts;
+use crate::config::lib::RuntimeSettings;
+use crate::config::models::{ModelConfig, PipelineType};
+use crate::models::chat::{
+    ChatCompletion, ChatCompletionResponse, PRIORITY_HEADER, validate_metadata,
+};
+use crate::models::completion::{CompletionRequest, CompletionResponse};
+use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
+use crate::models::responses::ModelListQuery;
 use crate::models::streaming::ChatCompletionChunk;
+use crate::models::usage::Usage;
+use crate::notifications::track_errors;
+use crate::outcome::{self, Outcome, classify_outcomes, provider_failure};
+use crate::pipelines::adaptive_routing::{
+    AdaptiveRouter, RoutingDecision, counts_as_error, inject_routing_decision_header,
+};
+use crate::pipelines::attribution::{Attribution, inject_attribution_headers};
+use crate::pipelines::budget::{PipelineBudget, enforce_budget};
+use crate::pipelines::cost::usage_cost_usd;
+use crate::pipelines::degraded_mode::{PipelineDegradation, inject_degraded_header};
+use crate::pipelines::deprecation::{DeprecatedModels, handle_deprecated_models};
+use crate::pipelines::dry_run::{dry_run_body, parse_dry_run};
+use crate::pipelines::explain::{
+    ExplainStep, explain_requests, explained_content, guardrail_step, is_explaining, routing_step,
+};
+use crate::pipelines::idempotency::deduplicate_requests;
+use crate::pipelines::json_repair::JsonRepair;
+use crate::pipelines::messages::messages;
+use crate::pipelines::normalization::ResponseNormalizer;
 use crate::pipelines::otel::OtelTracer;
+use crate::pipelines::parameter_policy::{ParameterPolicy, enforce_parameter_policy};
+use crate::pipelines::race::RaceRouter;
+use crate::pipelines::realtime::realtime;
+use crate::pipelines::request_logging::{RequestLogger, log_requests};
+use crate::pipelines::request_validation::{
+    ModelNotFound, RequestValidationError, ValidateRequest, ValidatedJson,
+};
+use crate::pipelines::resumable_streams::resume_streams;
+use crate::pipelines::stream_buffer::{buffer_stream, collect_bounded};
+use crate::pipelines::stream_cadence::{SlowStreamThreshold, observe_cadence};
+use crate::pipelines::system_prompt::SystemPromptRenderer;
+use crate::pipelines::token_count::{check_context_window, count_tokens};
+use crate::pipelines::tool_call_aggregation::{
+    aggregate_tool_call_stream, aggregate_tool_calls_requested,
+};
+use crate::pipelines::tool_limits::apply_tool_limits;
+use crate::pipelines::usage::PipelineUsage;
+use crate::providers::failover::{inject_served_by_header, track_served_by};
+use crate::providers::maintenance::InMaintenance;
 use crate::providers::provider::get_vendor_name;
-use crate::types::ProviderType;
+use crate::providers::upstream::UpstreamRequest;
+use crate::services::HubServices;
+use crate::timing::RequestTiming;
+use crate::trace_context::propagate_trace_context;
+use crate::traceloop_headers::capture_traceloop_headers;
+use crate::types::{ProviderType, RequestPriority, SafetyBlockBehavior, ToolLimits};
+use crate::upstream_headers::{HeaderPassthrough, passthrough_upstream_headers};
 use crate::{
+    ai_models::instance::ModelInstance,
     ai_models::registry::ModelRegistry,
     config::models::{Pipeline, PluginConfig},
     models::chat::ChatCompletionRequest,
 };
 use async_stream::stream;
-use axum::http::{HeaderName, HeaderValue};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
 use axum::response::sse::{Event, KeepAlive};
 use axum::response::{IntoResponse, Sse};
 use axum::{
     Json, Router,
-    extract::State,
+    extract::{Query, State},
     http::StatusCode,
-    routing::{get, post},
+    middleware,
+    routing::{MethodRouter, get, post},
 };
+use futures::StreamExt;
 use futures::stream::BoxStream;
-use futures::{Stream, StreamExt};
 use reqwest_streams::error::StreamBodyError;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Instant;
 
-const HEADER_PROVIDER: HeaderName = HeaderName::from_static("x-genai-provider-name");
+pub const HEADER_PROVIDER: HeaderName = HeaderName::from_static("x-genai-provider-name");
+pub const HEADER_MODEL_KEY: HeaderName = HeaderName::from_static("x-hub-model-key");
 
-fn inject_provider_header(response: &mut axum::response::Response, provider_type: &ProviderType) {
+pub(crate) fn inject_provider_header(
+    response: &mut axum::response::Response,
+    provider_type: &ProviderType,
+) {
     if let Ok(value) = HeaderValue::from_str(&provider_type.to_string()) {
         response
             .headers_mut()
@@ -36,183 +96,1300 @@ fn inject_provider_header(response: &mut axum::response::Response, provider_type
     }
 }
 
-pub fn create_pipeline(pipeline: &Pipeline, model_registry: &ModelRegistry) -> Router {
-    let mut router = Router::new();
+/// Reports the model a chat request was routed to, including implicit `provider/model` ones.
+pub(crate) fn inject_model_key_header(response: &mut axum::response::Response, model_key: &str) {
+    if let Ok(value) = HeaderValue::from_str(model_key) {
+        response.headers_mut().insert(HEADER_MODEL_KEY, value);
+    }
+}
 
-    let available_models: Vec<String> = pipeline
-        .plugins
+fn dry_run_response(
+    model_key: &str,
+    model: &ModelInstance,
+    upstream: &UpstreamRequest,
+) -> axum::response::Response {
+    let mut resp = Json(dry_run_body(model_key, model, upstream)).into_response();
+    inject_provider_header(&mut resp, &model.provider.r#type());
+    resp
+}
+
+/// The refusal of a safety-blocked completion, when blocks are configured to surface as errors.
+fn content_filter_refusal(
+    completion: &ChatCompletion,
+    behavior: SafetyBlockBehavior,
+) -> Option<String> {
+    if behavior != SafetyBlockBehavior::Error {
+        return None;
+    }
+    completion
+        .choices
         .iter()
-        .find_map(|plugin| {
-            if let PluginConfig::ModelRouter { models } = plugin {
-                Some(models.clone())
+        .find(|choice| choice.finish_reason.as_deref() == Some("content_filter"))
+        .map(|choice| {
+            choice.message.refusal.clone().unwrap_or_else(|| {
+                "The response was blocked by the provider's content filter.".to_string()
+            })
+        })
+}
+
+fn content_filter_response(message: String) -> axum::response::Response {
+    let body = serde_json::json!({
+        "error": {
+            "type": "content_filter",
+            "message": message,
+            "param": null,
+            "code": "content_filter",
+        }
+    });
+    Outcome::GuardrailBlock.tagged((StatusCode::BAD_REQUEST, Json(body)).into_response())
+}
+
+fn with_budget<S>(route: MethodRouter<S>, budget: &Option<Arc<PipelineBudget>>) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    match budget {
+        Some(budget) => route.route_layer(middleware::from_fn_with_state(
+            budget.clone(),
+            enforce_budget,
+        )),
+        None => route,
+    }
+}
+
+fn with_deprecated_models<S>(
+    route: MethodRouter<S>,
+    deprecated_models: &Option<Arc<DeprecatedModels>>,
+    max_body_bytes: usize,
+) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    match deprecated_models {
+        Some(deprecated_models) => route.route_layer(middleware::from_fn_with_state(
+            (deprecated_models.clone(), max_body_bytes),
+            handle_deprecated_models,
+        )),
+        None => route,
+    }
+}
+
+/// Every pipeline gets a policy, since the default one rejects `extra_body`.
+fn with_parameter_policy<S>(
+    route: MethodRouter<S>,
+    policy: &Arc<ParameterPolicy>,
+    max_body_bytes: usize,
+) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    route.route_layer(middleware::from_fn_with_state(
+        (policy.clone(), max_body_bytes),
+        enforce_parameter_policy,
+    ))
+}
+
+/// What the middleware of a pipeline shares: the pipeline's name, and the settings and
+/// services of the hub serving it.
+#[derive(Clone)]
+pub struct PipelineScope {
+    pub name: Arc<str>,
+    pub settings: Arc<RuntimeSettings>,
+    pub services: HubServices,
+}
+
+impl PipelineScope {
+    pub fn new(name: &str, settings: Arc<RuntimeSettings>, services: HubServices) -> Self {
+        Self {
+            name: Arc::from(name),
+            settings,
+            services,
+        }
+    }
+}
+
+/// A pipeline's plugins, set up once per config. Its routes run requests through it, and
+/// so does the in-process [`Gateway`](crate::gateway::Gateway).
+pub struct PipelineRunner {
+    scope: Arc<PipelineScope>,
+    pub(crate) model_registry: Arc<ModelRegistry>,
+    r#type: PipelineType,
+    /// The `model-router` models, listed by `/models`.
+    pub(crate) available_models: Vec<String>,
+    /// Whether the pipeline has a `model-router`, without which it serves no completions.
+    routes_models: bool,
+    /// `provider/model` names only reach unconfigured models when prefix routing is on.
+    pub(crate) allow_dynamic_models: bool,
+    adaptive: Option<Arc<AdaptiveRouter>>,
+    race: Option<Arc<RaceRouter>>,
+    degradation: Option<Arc<PipelineDegradation>>,
+    pub(crate) budget: Option<Arc<PipelineBudget>>,
+    pub(crate) usage: PipelineUsage,
+    metadata: BTreeMap<String, String>,
+    default_priority: Option<RequestPriority>,
+    tool_limits: Option<ToolLimits>,
+    system_prompt: Option<SystemPromptRenderer>,
+    normalizer: ResponseNormalizer,
+    aggregate_tool_calls: bool,
+    parameter_policy: Arc<ParameterPolicy>,
+    deprecated_models: Option<Arc<DeprecatedModels>>,
+}
+
+impl PipelineRunner {
+    /// Sets up `pipeline`, resolving its `system-prompt` template from the config's
+    /// `prompt_templates`. Budgets, usage and degraded mode state live in the services of
+    /// `scope`, so a runner built for a reloaded config carries on where the last one was.
+    pub fn new(
+        pipeline: &Pipeline,
+        model_registry: &ModelRegistry,
+        prompt_templates: &BTreeMap<String, String>,
+        scope: PipelineScope,
+    ) -> Self {
+        let settings = &scope.settings;
+        let services = &scope.services;
+        let model_registry = Arc::new(
+            model_registry
+                .clone()
+                .with_case_insensitive_lookups(settings.case_insensitive_lookups),
+        );
+
+        let model_router = pipeline.plugins.iter().find_map(|plugin| {
+            if let PluginConfig::ModelRouter {
+                models,
+                allow_dynamic_models,
+                adaptive,
+                race,
+            } = plugin
+            {
+                Some((models, *allow_dynamic_models, adaptive, race))
             } else {
                 None
             }
-        })
-        .unwrap_or_default();
+        });
+        let available_models = model_router
+            .map(|(models, ..)| models.clone())
+            .unwrap_or_default();
+        let allow_dynamic_models = model_router
+            .is_some_and(|(_, allow_dynamic_models, ..)| allow_dynamic_models)
+            && settings.prefix_routing;
+        let adaptive = model_router
+            .and_then(|(_, _, adaptive, _)| *adaptive)
+            .map(|settings| Arc::new(AdaptiveRouter::new(settings, services.model_stats.clone())));
+        let race = model_router
+            .and_then(|(.., race)| race.clone())
+            .map(|settings| Arc::new(RaceRouter::new(settings)));
+
+        let budget = pipeline.plugins.iter().find_map(|plugin| {
+            if let PluginConfig::Budget {
+                limit_usd,
+                window,
+                warn_at_percent,
+            } = plugin
+            {
+                Some(Arc::new(PipelineBudget::new(
+                    &pipeline.name,
+                    *limit_usd,
+                    *window,
+                    *warn_at_percent,
+                    services.budgets.clone(),
+                )))
+            } else {
+                None
+            }
+        });
+
+        let usage = PipelineUsage::new(&pipeline.name, services.usage.clone());
+
+        let default_priority = pipeline.plugins.iter().find_map(|plugin| {
+            if let PluginConfig::Priority { default } = plugin {
+                Some(*default)
+            } else {
+                None
+            }
+        });
+
+        let parameter_policy = pipeline
+            .plugins
+            .iter()
+            .find_map(|plugin| {
+                if let PluginConfig::ParameterPolicy {
+                    mode,
+                    rules,
+                    allow_extra_body,
+                    extra_body_keys,
+                } = plugin
+                {
+                    let policy = ParameterPolicy::new(*mode, rules.clone());
+                    Some(if *allow_extra_body {
+                        policy.with_extra_body(extra_body_keys.clone())
+                    } else {
+                        policy
+                    })
+                } else {
+                    None
+                }
+            })
+            .map(Arc::new)
+            .unwrap_or_default();
 
-    router = router.route(
+        let deprecated_models =
+            DeprecatedModels::new(&available_models, &model_registry).map(Arc::new);
+
+        let degradation = pipeline.plugins.iter().find_map(|plugin| {
+            if let PluginConfig::DegradedMode(settings) = plugin {
+                Some(services.degraded_modes.pipeline(&pipeline.name, settings))
+            } else {
+                None
+            }
+        });
+
+        let normalizer = pipeline
+            .plugins
+            .iter()
+            .find_map(|plugin| {
+                if let PluginConfig::ResponseNormalization {
+                    passthrough_extra_fields,
+                } = plugin
+                {
+                    Some(ResponseNormalizer::new(*passthrough_extra_fields))
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        let aggregate_tool_calls = pipeline.plugins.iter().any(|plugin| {
+            matches!(
+                plugin,
+                PluginConfig::StreamOptions {
+                    aggregate_tool_calls: true
+                }
+            )
+        });
+
+        let tool_limits = pipeline.plugins.iter().find_map(|plugin| {
+            if let PluginConfig::ToolLimits(settings) = plugin {
+                Some(settings.clone())
+            } else {
+                None
+            }
+        });
+
+        // Templates are validated with the config, so this only fails for configs that
+        // skipped validation.
+        let system_prompt = pipeline.plugins.iter().find_map(|plugin| {
+            if let PluginConfig::SystemPrompt(settings) = plugin {
+                SystemPromptRenderer::new(settings, prompt_templates)
+                    .inspect_err(|e| {
+                        tracing::error!("Pipeline {} has no system prompt: {e}", pipeline.name)
+                    })
+                    .ok()
+            } else {
+                None
+            }
+        });
+
+        let metadata = pipeline
+            .plugins
+            .iter()
+            .find_map(|plugin| {
+                if let PluginConfig::Metadata { values } = plugin {
+                    Some(values.clone())
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            model_registry,
+            r#type: pipeline.r#type.clone(),
+            available_models,
+            routes_models: model_router.is_some(),
+            allow_dynamic_models,
+            adaptive,
+            race,
+            degradation,
+            budget,
+            usage,
+            metadata,
+            default_priority,
+            tool_limits,
+            system_prompt,
+            normalizer,
+            aggregate_tool_calls,
+            parameter_policy,
+            deprecated_models,
+            scope: Arc::new(scope),
+        }
+    }
+
+    pub fn scope(&self) -> &Arc<PipelineScope> {
+        &self.scope
+    }
+
+    /// Whether the pipeline serves the completions of pipelines of `r#type`.
+    pub(crate) fn serves(&self, r#type: PipelineType) -> bool {
+        self.routes_models && self.r#type == r#type
+    }
+
+    /// Applies the route middleware that checks a request before its handler runs: the
+    /// budget, deprecated models and the parameter policy. For requests sent in process,
+    /// which skip the routes.
+    pub(crate) fn admit<T>(&self, request: T) -> Result<T, axum::response::Response>
+    where
+        T: Serialize + DeserializeOwned + ValidateRequest,
+    {
+        if let Some(budget) = &self.budget {
+            budget.check().map_err(IntoResponse::into_response)?;
+        }
+        let invalid = |e: serde_json::Error| {
+            RequestValidationError {
+                status: StatusCode::UNPROCESSABLE_ENTITY,
+                message: format!("Invalid request body: {e}"),
+                param: None,
+            }
+            .into_response()
+        };
+        let mut value = serde_json::to_value(request).map_err(invalid)?;
+        if let Value::Object(fields) = &mut value {
+            if let Some(deprecated_models) = &self.deprecated_models {
+                deprecated_models
+                    .check(fields)
+                    .map_err(IntoResponse::into_response)?;
+            }
+            self.parameter_policy
+                .apply(fields)
+                .map_err(|rejection| Outcome::GuardrailBlock.tagged(rejection.into_response()))?;
+        }
+        let request: T = serde_json::from_value(value).map_err(invalid)?;
+        request.validate().map_err(IntoResponse::into_response)?;
+        Ok(request)
+    }
+
+    /// Counts a request sent in process towards the pipeline's outcomes and error rate,
+    /// as the pipeline middleware does for requests over HTTP.
+    pub(crate) fn record_outcome(&self, outcome: Outcome, status: StatusCode) {
+        let scope = &self.scope;
+        outcome::record(&scope.services.outcomes, &scope.name, outcome);
+        scope
+            .services
+            .notifications
+            .record_outcome(&scope.name, status.is_server_error());
+    }
+}
+
+pub fn create_pipeline(pipeline: &Pipeline, model_registry: &ModelRegistry) -> Router {
+    let settings = Arc::new(RuntimeSettings::from_general(None));
+    let scope = PipelineScope::new(&pipeline.name, settings, HubServices::default());
+    let runner = PipelineRunner::new(pipeline, model_registry, &BTreeMap::new(), scope);
+    create_pipeline_router(pipeline, Arc::new(runner))
+}
+
+/// The routes of `pipeline`, served by `runner`, wrapped in the pipeline middleware.
+pub fn create_pipeline_router(pipeline: &Pipeline, runner: Arc<PipelineRunner>) -> Router {
+    let scope = runner.scope.clone();
+    let max_body_bytes = scope.settings.max_buffered_body_bytes;
+    let mut router = Router::new().route(
         "/models",
         get(
-            move |State(model_registry): State<Arc<ModelRegistry>>| async move {
-                let model_info = model_registry.get_filtered_model_info(&available_models);
+            |State(runner): State<Arc<PipelineRunner>>,
+             Query(query): Query<ModelListQuery>| async move {
+                let model_info = runner
+                    .model_registry
+                    .get_filtered_model_info(&runner.available_models, query.include_capabilities);
                 Json(model_info)
             },
         ),
     );
 
-    for plugin in pipeline.plugins.clone() {
-        router = match plugin {
-            PluginConfig::Tracing { endpoint, api_key } => {
-                tracing::info!("Initializing OtelTracer for pipeline {}", pipeline.name);
-                OtelTracer::init(endpoint, api_key);
-                router
-            }
-            PluginConfig::ModelRouter { models } => match pipeline.r#type {
-                PipelineType::Chat => router.route(
-                    "/chat/completions",
-                    post(move |state, payload| chat_completions(state, payload, models)),
-                ),
-                PipelineType::Completion => router.route(
-                    "/completions",
-                    post(move |state, payload| completions(state, payload, models)),
-                ),
-                PipelineType::Embeddings => router.route(
-                    "/embeddings",
-                    post(move |state, payload| embeddings(state, payload, models)),
-                ),
-            },
-            _ => router,
+    for plugin in &pipeline.plugins {
+        if let PluginConfig::Tracing { endpoint, api_key } = plugin {
+            tracing::info!("Initializing OtelTracer for pipeline {}", pipeline.name);
+            OtelTracer::init(endpoint.clone(), api_key.clone());
+        }
+    }
+
+    // The middleware checking requests before their handler runs, as `admit` does for
+    // requests sent in process.
+    let checked = |route: MethodRouter<Arc<PipelineRunner>>| {
+        with_budget(
+            with_parameter_policy(
+                with_deprecated_models(route, &runner.deprecated_models, max_body_bytes),
+                &runner.parameter_policy,
+                max_body_bytes,
+            ),
+            &runner.budget,
+        )
+    };
+    if runner.routes_models {
+        router = match runner.r#type {
+            PipelineType::Chat => router
+                .route("/messages", checked(post(messages)))
+                .route("/messages/count_tokens", post(count_tokens))
+                .route("/chat/completions", checked(post(chat_completions)))
+                .route("/realtime", with_budget(get(realtime), &runner.budget)),
+            PipelineType::Completion => router.route("/completions", checked(post(completions))),
+            PipelineType::Embeddings => router.route("/embeddings", checked(post(embeddings))),
         };
     }
 
-    router.with_state(Arc::new(model_registry.clone()))
+    // Applied after the routes are registered so every pipeline route is covered.
+    let passthrough = pipeline
+        .plugins
+        .iter()
+        .find_map(|plugin| {
+            if let PluginConfig::PassthroughHeaders(settings) = plugin {
+                Some(HeaderPassthrough::new(settings))
+            } else {
+                None
+            }
+        })
+        .unwrap_or_else(|| HeaderPassthrough::new(&scope.settings.passthrough_response_headers));
+    if !passthrough.is_empty() {
+        router = router.layer(middleware::from_fn_with_state(
+            Arc::new(passthrough),
+            passthrough_upstream_headers,
+        ));
+    }
+    router = router.layer(middleware::from_fn_with_state(
+        scope.clone(),
+        deduplicate_requests,
+    ));
+    // Outside deduplication, so reconnects to a stream aren't rejected as duplicates.
+    router = router.layer(middleware::from_fn_with_state(
+        scope.clone(),
+        resume_streams,
+    ));
+    // Inside the artifact store, which records the trace id.
+    router = router.layer(middleware::from_fn(propagate_trace_context));
+    router = router.layer(middleware::from_fn_with_state(
+        scope.settings.forward_traceloop_headers,
+        capture_traceloop_headers,
+    ));
+    // Around every route, so the plugins' middleware can add their decisions to the trace.
+    router = router.layer(middleware::from_fn_with_state(
+        scope.clone(),
+        explain_requests,
+    ));
+    if pipeline.store_artifacts {
+        router = router.layer(middleware::from_fn_with_state(
+            scope.clone(),
+            record_artifacts,
+        ));
+    }
+    let request_logger = pipeline.plugins.iter().find_map(|plugin| {
+        if let PluginConfig::Logging { level, sample_rate } = plugin {
+            Some(Arc::new(RequestLogger::new(
+                &pipeline.name,
+                level,
+                *sample_rate,
+            )))
+        } else {
+            None
+        }
+    });
+    if let Some(request_logger) = request_logger {
+        router = router.layer(middleware::from_fn_with_state(request_logger, log_requests));
+    }
+    router = router.layer(middleware::from_fn_with_state(scope.clone(), track_errors));
+    router = router.layer(middleware::from_fn_with_state(scope, classify_outcomes));
+
+    router.with_state(runner)
+}
+
+/// Records the latency breakdown and, when `timing_headers` is on, exposes it as response
+/// headers.
+pub(crate) fn apply_timing(
+    timing: &RequestTiming,
+    response: &mut axum::response::Response,
+    provider_type: &ProviderType,
+    timing_headers: bool,
+) {
+    if let Some(breakdown) = timing.finish() {
+        breakdown.record_metrics(&provider_type.to_string());
+        if timing_headers {
+            breakdown.inject_headers(response);
+        }
+        // Picked up by the artifact store.
+        response.extensions_mut().insert(breakdown);
+    }
 }
 
-fn trace_and_stream(
+/// Records traces, spend, usage and timing as the chunks of a streamed completion pass
+/// through.
+fn trace_stream(
     mut tracer: OtelTracer,
     stream: BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>>,
-) -> impl Stream<Item = Result<Event, axum::Error>> {
-    stream! {
+    budget: Option<Arc<PipelineBudget>>,
+    usage: PipelineUsage,
+    model_config: ModelConfig,
+    timing: Arc<RequestTiming>,
+    provider_type: ProviderType,
+) -> BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>> {
+    Box::pin(stream! {
         let mut stream = stream;
+        let mut tokens = Usage::default();
+        let mut failed = false;
         while let Some(result) = stream.next().await {
             yield match result {
                 Ok(chunk) => {
+                    let has_content = chunk.choices.iter().any(|choice| {
+                        choice.delta.content.as_deref().is_some_and(|content| !content.is_empty())
+                    });
+                    if has_content {
+                        timing.mark_first_content();
+                    }
                     tracer.log_chunk(&chunk);
-                    Event::default().json_data(chunk)
+                    if let Some(chunk_usage) = &chunk.usage {
+                        tokens = chunk_usage.clone();
+                        if let Some(budget) = &budget {
+                            budget.record(usage_cost_usd(&model_config, &tokens));
+                        }
+                    }
+                    Ok(chunk)
                 }
                 Err(e) => {
                     eprintln!("Error in stream: {e:?}");
                     tracer.log_error(e.to_string());
-                    Err(axum::Error::new(e))
+                    failed = true;
+                    Err(e)
                 }
             };
         }
+        // Usage arrives with the last chunk, so the request is counted once the stream ends.
+        if failed {
+            usage.record_error(&model_config.key);
+        } else {
+            usage.record(&model_config, &tokens);
+        }
         tracer.streaming_end();
+        timing.mark_upstream_done();
+        if let Some(breakdown) = timing.finish() {
+            breakdown.record_metrics(&provider_type.to_string());
+        }
+    })
+}
+
+/// Reads `x-hub-priority`, falling back to the pipeline's configured default.
+fn request_priority(
+    headers: &HeaderMap,
+    default_priority: Option<RequestPriority>,
+) -> Result<Option<RequestPriority>, String> {
+    match headers.get(PRIORITY_HEADER) {
+        Some(value) => value
+            .to_str()
+            .map_err(|_| format!("{PRIORITY_HEADER} is not valid UTF-8"))?
+            .parse()
+            .map(Some),
+        None => Ok(default_priority),
     }
 }
 
-pub async fn chat_completions(
-    State(model_registry): State<Arc<ModelRegistry>>,
-    Json(payload): Json<ChatCompletionRequest>,
-    model_keys: Vec<String>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let mut tracer = OtelTracer::start("chat", &payload);
+/// A chat request after the pipeline has routed it to a model.
+pub(crate) enum ChatOutcome {
+    /// Rejections and dry runs, answered without a completion.
+    Response(axum::response::Response),
+    Completion {
+        completion: ChatCompletion,
+        provider_type: ProviderType,
+        /// Key of the model that served the request, reported in `x-hub-model-key`.
+        model_key: String,
+        /// How an adaptive or race router picked the model, reported in `x-hub-routing-decision`.
+        routing_decision: Option<RoutingDecision>,
+        /// Failover group member that served the request, reported in `x-hub-served-by`.
+        served_by: Option<String>,
+        /// Served by a degraded pipeline's substitute model, reported in `x-hub-degraded`.
+        degraded: bool,
+        /// Set when attribution headers are on.
+        attribution: Option<Attribution>,
+        timing: Arc<RequestTiming>,
+    },
+    Stream {
+        chunks: BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>>,
+        provider_type: ProviderType,
+        model_key: String,
+        routing_decision: Option<RoutingDecision>,
+        served_by: Option<String>,
+        degraded: bool,
+        attribution: Option<Attribution>,
+    },
+}
 
-    for model_key in model_keys {
-        let model = model_registry.get(&model_key).unwrap();
+/// A completion or embeddings response, with what its headers report.
+pub(crate) struct Served<T> {
+    pub(crate) body: T,
+    provider_type: ProviderType,
+    served_by: Option<String>,
+    attribution: Option<Attribution>,
+    timing: Arc<RequestTiming>,
+}
 
-        if payload.model == model.model_type {
-            // Set vendor now that we know which model/provider we're using
-            tracer.set_vendor(&get_vendor_name(&model.provider.r#type()));
+impl<T: Serialize> Served<T> {
+    fn into_response(self, timing_headers: bool) -> axum::response::Response {
+        let mut resp = Json(self.body).into_response();
+        inject_provider_header(&mut resp, &self.provider_type);
+        inject_served_by_header(&mut resp, self.served_by.as_deref());
+        inject_attribution_headers(&mut resp, self.attribution.as_ref());
+        apply_timing(&self.timing, &mut resp, &self.provider_type, timing_headers);
+        resp
+    }
 
-            let response = model
-                .chat_completions(payload.clone())
-                .await
-                .inspect_err(|e| {
-                    eprintln!("Chat completion error for model {model_key}: {e:?}");
-                })?;
+    /// The response alone, for callers without headers to report it in.
+    pub(crate) fn into_body(self) -> T {
+        if let Some(breakdown) = self.timing.finish() {
+            breakdown.record_metrics(&self.provider_type.to_string());
+        }
+        self.body
+    }
+}
+
+impl PipelineRunner {
+    pub(crate) fn settings(&self) -> &RuntimeSettings {
+        &self.scope.settings
+    }
 
-            let provider_type = model.provider.r#type();
+    pub(crate) fn normalizer(&self) -> ResponseNormalizer {
+        self.normalizer
+    }
 
-            if let ChatCompletionResponse::NonStream(completion) = response {
-                tracer.log_success(&completion);
-                let mut resp = Json(completion).into_response();
-                inject_provider_header(&mut resp, &provider_type);
-                return Ok(resp);
+    pub(crate) fn aggregates_tool_calls(&self) -> bool {
+        self.aggregate_tool_calls
+    }
+
+    /// Runs a chat request through the pipeline: priority, metadata, tool limits, system
+    /// prompt, model routing, capability checks and dry runs, then calls the model while
+    /// recording traces and spend.
+    pub(crate) async fn run_chat(
+        &self,
+        headers: &HeaderMap,
+        mut payload: ChatCompletionRequest,
+    ) -> Result<ChatOutcome, StatusCode> {
+        let settings = self.settings();
+        let model_registry = self.model_registry.as_ref();
+        let model_keys = &self.available_models;
+        payload.priority = request_priority(headers, self.default_priority).map_err(|e| {
+            tracing::error!("Invalid priority: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+        let dry_run = match parse_dry_run(headers, settings.allow_debug_headers) {
+            Ok(dry_run) => dry_run,
+            Err(rejection) => return Ok(ChatOutcome::Response(rejection.into_response())),
+        };
+
+        if !self.metadata.is_empty() {
+            payload.metadata.get_or_insert_with(HashMap::new).extend(
+                self.metadata
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone())),
+            );
+        }
+        if let Some(metadata) = &payload.metadata {
+            if let Err(e) = validate_metadata(metadata.iter()) {
+                tracing::error!("Invalid metadata: {}", e);
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        }
+        if let Some(tool_limits) = &self.tool_limits {
+            let started = Instant::now();
+            let limited = apply_tool_limits(tool_limits, &mut payload);
+            let rejection = limited
+                .as_ref()
+                .err()
+                .map(|rejection| rejection.message.as_str());
+            guardrail_step("tool_limits", rejection)
+                .took(started.elapsed())
+                .record();
+            if let Err(rejection) = limited {
+                let response = Outcome::GuardrailBlock.tagged(rejection.into_response());
+                return Ok(ChatOutcome::Response(response));
+            }
+        }
+        if let Some(system_prompt) = &self.system_prompt {
+            let started = Instant::now();
+            let applied = system_prompt.apply(headers, &mut payload);
+            let decision = if applied.is_ok() {
+                "applied"
+            } else {
+                "rejected"
+            };
+            ExplainStep::new("system_prompt", decision)
+                .took(started.elapsed())
+                .record();
+            if let Err(rejection) = applied {
+                return Ok(ChatOutcome::Response(rejection.into_response()));
             }
+        }
 
-            if let ChatCompletionResponse::Stream(stream) = response {
-                let mut resp = Sse::new(trace_and_stream(tracer, stream))
-                    .keep_alive(KeepAlive::default())
-                    .into_response();
-                inject_provider_header(&mut resp, &provider_type);
-                return Ok(resp);
+        let mut tracer = OtelTracer::start("chat", &payload, settings.trace_content_policy());
+
+        // A degraded pipeline sends everything but its probes to the substitute model.
+        let substitute = self
+            .degradation
+            .as_ref()
+            .filter(|degradation| degradation.divert())
+            .and_then(|degradation| {
+                let model = model_registry.get(&degradation.settings().model)?;
+                degradation.apply_overrides(&mut payload);
+                Some(model)
+            });
+        let degraded = substitute.is_some();
+        // Raced requests are checked against the first contender.
+        let contenders = self
+            .race
+            .as_ref()
+            .filter(|_| !degraded)
+            .and_then(|race| race.contenders(model_registry, &payload.model, model_keys));
+        let route = match substitute {
+            Some(model) => Some((model, None)),
+            None => match &contenders {
+                Some(contenders) => Some((contenders[0].clone(), Some(RoutingDecision::Race))),
+                None => self
+                    .adaptive
+                    .as_ref()
+                    .and_then(|adaptive| adaptive.route(model_registry, &payload.model, model_keys))
+                    .map(|(model, decision)| (model, Some(decision))),
+            },
+        };
+        let explain_routing = |model_key: Option<&str>, decision: Option<RoutingDecision>| {
+            if is_explaining() {
+                routing_step(
+                    model_registry,
+                    &payload.model,
+                    model_keys,
+                    model_key,
+                    decision,
+                    degraded,
+                )
+                .record();
+            }
+        };
+        let (model, routing_decision) = match route {
+            Some(route) => route,
+            None => {
+                let Some(model) =
+                    model_registry.route(&payload.model, model_keys, self.allow_dynamic_models)
+                else {
+                    explain_routing(None, None);
+                    if let Some(maintenance) = model_registry.maintenance(
+                        &payload.model,
+                        model_keys,
+                        self.allow_dynamic_models,
+                    ) {
+                        tracer.log_error(maintenance.to_string());
+                        return Ok(ChatOutcome::Response(maintenance.into_response()));
+                    }
+                    let not_found = ModelNotFound::new(
+                        &payload.model,
+                        model_registry,
+                        model_keys,
+                        settings.expose_available_models,
+                    );
+                    tracer.log_error(not_found.to_string());
+                    return Ok(ChatOutcome::Response(not_found.into_response()));
+                };
+                (model, None)
+            }
+        };
+        let model_key = model.name.clone();
+        explain_routing(Some(&model_key), routing_decision);
+
+        // Set vendor now that we know which model/provider we're using
+        tracer.set_vendor(&get_vendor_name(&model.provider.r#type()));
+
+        let unsupported = model.unsupported_chat_params(&payload);
+        if !unsupported.is_empty() {
+            let rejection = RequestValidationError::unsupported_params(&model_key, &unsupported);
+            tracer.log_error(rejection.message.clone());
+            return Ok(ChatOutcome::Response(rejection.into_response()));
+        }
+        let builtin_tools = model.unsupported_builtin_tools(&payload);
+        if !builtin_tools.is_empty() {
+            let rejection =
+                RequestValidationError::unsupported_builtin_tools(&model_key, &builtin_tools);
+            tracer.log_error(rejection.message.clone());
+            return Ok(ChatOutcome::Response(rejection.into_response()));
+        }
+        if let Some(rejection) = check_context_window(&model, &payload).await {
+            tracer.log_error(rejection.message.clone());
+            return Ok(ChatOutcome::Response(rejection.into_response()));
+        }
+
+        if dry_run {
+            let upstream = model.build_chat_request(payload.clone()).await?;
+            let response = dry_run_response(&model_key, &model, &upstream);
+            return Ok(ChatOutcome::Response(response));
+        }
+
+        let timing = RequestTiming::start();
+        let started = Instant::now();
+        let (model, response, served_by) = match (&self.race, &contenders) {
+            (Some(race), Some(contenders)) => {
+                match timing
+                    .scope(tracer.in_span(race.run(contenders, &payload)))
+                    .await
+                {
+                    Ok(winner) => {
+                        tracer.set_vendor(&get_vendor_name(&winner.model.provider.r#type()));
+                        (winner.model, Ok(winner.response), winner.served_by)
+                    }
+                    Err(status) => (model, Err(status), None),
+                }
+            }
+            _ => {
+                let (response, served_by) = track_served_by(
+                    timing.scope(tracer.in_span(model.chat_completions(payload.clone()))),
+                )
+                .await;
+                (model, response, served_by)
+            }
+        };
+        // Another contender may have won the race.
+        let model_key = model.name.clone();
+        let decision = match &response {
+            Ok(ChatCompletionResponse::NonStream(_)) => "completed".to_string(),
+            Ok(ChatCompletionResponse::Stream(_)) => "streaming".to_string(),
+            Err(status) => format!("failed with {}", status.as_u16()),
+        };
+        ExplainStep::new("provider", decision)
+            .took(started.elapsed())
+            .details(serde_json::json!({
+                "model_key": model_key,
+                "provider": served_by.clone().unwrap_or_else(|| model.provider.key()),
+            }))
+            .record();
+        let attribution =
+            Attribution::when_enabled(settings.attribution_headers, &model, served_by.as_deref());
+        let sample = match &response {
+            Ok(_) => Some(Some(started.elapsed())),
+            Err(status) if counts_as_error(*status) => Some(None),
+            Err(_) => None,
+        };
+        if let Some(latency) = sample {
+            if let (Some(adaptive), Some(_)) = (&self.adaptive, routing_decision) {
+                adaptive.record(&model_key, latency);
+            }
+            if let (Some(degradation), false) = (&self.degradation, degraded) {
+                degradation.record(latency);
             }
         }
+        let response = match response {
+            Ok(response) => response,
+            Err(status) => {
+                eprintln!("Chat completion error for model {model_key}: {status:?}");
+                self.usage.record_error(&model.config.key);
+                return Ok(ChatOutcome::Response(provider_failure(status)));
+            }
+        };
+
+        let provider_type = model.provider.r#type();
+        let json_repair = JsonRepair::for_request(&model.config.params, &payload);
+
+        Ok(match response {
+            ChatCompletionResponse::NonStream(mut completion) => {
+                tracer.log_success(&completion);
+                if let Some(budget) = &self.budget {
+                    budget.record(usage_cost_usd(&model.config, &completion.usage));
+                }
+                self.usage.record(&model.config, &completion.usage);
+                let refusal = content_filter_refusal(&completion, settings.safety_block_behavior);
+                match &refusal {
+                    Some(refusal) => ExplainStep::new("content_filter", "blocked")
+                        .details(serde_json::json!({ "message": explained_content(refusal) })),
+                    None => ExplainStep::new("content_filter", "passed"),
+                }
+                .record();
+                if let Some(refusal) = refusal {
+                    tracer.log_error(refusal.clone());
+                    let mut response = content_filter_response(refusal);
+                    inject_provider_header(&mut response, &provider_type);
+                    return Ok(ChatOutcome::Response(response));
+                }
+                if let Some(json_repair) = &json_repair {
+                    let repaired = json_repair.repair_completion(&model_key, &mut completion);
+                    // The reason can quote the response, so it's redacted like the content.
+                    match &repaired {
+                        Ok(_) => ExplainStep::new("json_repair", "passed"),
+                        Err(failed) => {
+                            let reason = explained_content(&failed.reason);
+                            ExplainStep::new("json_repair", "blocked")
+                                .details(serde_json::json!({ "reason": reason }))
+                        }
+                    }
+                    .record();
+                    if let Err(failed) = repaired {
+                        tracer.log_error(failed.to_string());
+                        let mut response = failed.into_response();
+                        inject_provider_header(&mut response, &provider_type);
+                        return Ok(ChatOutcome::Response(response));
+                    }
+                }
+                ChatOutcome::Completion {
+                    completion,
+                    provider_type,
+                    model_key,
+                    routing_decision,
+                    served_by,
+                    degraded,
+                    attribution,
+                    timing,
+                }
+            }
+            ChatCompletionResponse::Stream(stream) => {
+                let stream = observe_cadence(
+                    stream,
+                    provider_type.to_string(),
+                    model_key.clone(),
+                    settings.slow_stream.as_ref().map(SlowStreamThreshold::from),
+                );
+                let mut chunks = trace_stream(
+                    tracer,
+                    buffer_stream(
+                        stream,
+                        settings.stream_buffer_chunks,
+                        settings.stream_buffer_max_bytes,
+                    ),
+                    self.budget.clone(),
+                    self.usage.clone(),
+                    model.config.clone(),
+                    timing,
+                    provider_type,
+                );
+                if let Some(json_repair) = &json_repair {
+                    // The content can only be repaired once all of it has arrived.
+                    let buffered = collect_bounded(chunks, settings.stream_buffer_max_bytes).await;
+                    chunks = if buffered.iter().all(Result::is_ok) {
+                        let mut buffered: Vec<_> = buffered.into_iter().flatten().collect();
+                        if let Err(failed) = json_repair.repair_chunks(&model_key, &mut buffered) {
+                            let mut response = failed.into_response();
+                            inject_provider_header(&mut response, &provider_type);
+                            return Ok(ChatOutcome::Response(response));
+                        }
+                        futures::stream::iter(buffered.into_iter().map(Ok)).boxed()
+                    } else {
+                        futures::stream::iter(buffered).boxed()
+                    };
+                }
+                ChatOutcome::Stream {
+                    chunks,
+                    provider_type,
+                    model_key,
+                    routing_decision,
+                    served_by,
+                    degraded,
+                    attribution,
+                }
+            }
+        })
     }
 
-    tracer.log_error("No matching model found".to_string());
-    eprintln!("No matching model found for: {}", payload.model);
-    Err(StatusCode::NOT_FOUND)
-}
+    /// Routes a completion request to the first of the pipeline's models serving it. Fails
+    /// with the response to send instead: a rejection, or the upstream request of a dry run.
+    pub(crate) async fn run_completion(
+        &self,
+        headers: &HeaderMap,
+        payload: CompletionRequest,
+    ) -> Result<Served<CompletionResponse>, axum::response::Response> {
+        let settings = self.settings();
+        let dry_run = parse_dry_run(headers, settings.allow_debug_headers)
+            .map_err(IntoResponse::into_response)?;
+        let mut tracer = OtelTracer::start("completion", &payload, settings.trace_content_policy());
+        let mut maintenance: Option<InMaintenance> = None;
+
+        for model_key in &self.available_models {
+            let Some(model) = self.model_registry.get(model_key) else {
+                continue;
+            };
 
-pub async fn completions(
-    State(model_registry): State<Arc<ModelRegistry>>,
-    Json(payload): Json<CompletionRequest>,
-    model_keys: Vec<String>,
-) -> impl IntoResponse {
-    let mut tracer = OtelTracer::start("completion", &payload);
+            if self
+                .model_registry
+                .lookup_matches(&model.model_type, &payload.model)
+            {
+                if let Some(window) = self.model_registry.in_maintenance(&model) {
+                    if maintenance.as_ref().is_none_or(|m| window.until < m.until) {
+                        maintenance = Some(window);
+                    }
+                    continue;
+                }
+                // Set vendor now that we know which model/provider we're using
+                tracer.set_vendor(&get_vendor_name(&model.provider.r#type()));
+
+                let unsupported = model.unsupported_completion_params(&payload);
+                if !unsupported.is_empty() {
+                    let rejection =
+                        RequestValidationError::unsupported_params(model_key, &unsupported);
+                    tracer.log_error(rejection.message.clone());
+                    return Err(rejection.into_response());
+                }
 
-    for model_key in model_keys {
-        let model = model_registry.get(&model_key).unwrap();
+                if dry_run {
+                    let upstream = model
+                        .build_completion_request(payload.clone())
+                        .await
+                        .map_err(IntoResponse::into_response)?;
+                    return Err(dry_run_response(model_key, &model, &upstream));
+                }
 
-        if payload.model == model.model_type {
-            // Set vendor now that we know which model/provider we're using
-            tracer.set_vendor(&get_vendor_name(&model.provider.r#type()));
+                let timing = RequestTiming::start();
+                let (response, served_by) = track_served_by(
+                    timing.scope(tracer.in_span(model.completions(payload.clone()))),
+                )
+                .await;
+                let response = match response {
+                    Ok(response) => response,
+                    Err(status) => {
+                        eprintln!("Completion error for model {model_key}: {status:?}");
+                        self.usage.record_error(&model.config.key);
+                        return Err(provider_failure(status));
+                    }
+                };
+                tracer.log_success(&response);
+                if let Some(budget) = &self.budget {
+                    budget.record(usage_cost_usd(&model.config, &response.usage));
+                }
+                self.usage.record(&model.config, &response.usage);
+                let attribution = Attribution::when_enabled(
+                    settings.attribution_headers,
+                    &model,
+                    served_by.as_deref(),
+                );
+                return Ok(Served {
+                    body: response,
+                    provider_type: model.provider.r#type(),
+                    served_by,
+                    attribution,
+                    timing,
+                });
+            }
+        }
 
-            let response = model.completions(payload.clone()).await.inspect_err(|e| {
-                eprintln!("Completion error for model {model_key}: {e:?}");
-            })?;
-            tracer.log_success(&response);
-            let mut resp = Json(response).into_response();
-            inject_provider_header(&mut resp, &model.provider.r#type());
-            return Ok(resp);
+        if let Some(maintenance) = maintenance {
+            tracer.log_error(maintenance.to_string());
+            return Err(maintenance.into_response());
         }
+        let not_found = ModelNotFound::new(
+            &payload.model,
+            &self.model_registry,
+            &self.available_models,
+            settings.expose_available_models,
+        );
+        tracer.log_error(not_found.to_string());
+        Err(not_found.into_response())
     }
 
-    tracer.log_error("No matching model found".to_string());
-    eprintln!("No matching model found for: {}", payload.model);
-    Err(StatusCode::NOT_FOUND)
-}
+    /// Routes an embeddings request like [`run_completion`](Self::run_completion).
+    pub(crate) async fn run_embeddings(
+        &self,
+        headers: &HeaderMap,
+        payload: EmbeddingsRequest,
+    ) -> Result<Served<EmbeddingsResponse>, axum::response::Response> {
+        let settings = self.settings();
+        let dry_run = parse_dry_run(headers, settings.allow_debug_headers)
+            .map_err(IntoResponse::into_response)?;
+        let mut tracer = OtelTracer::start("embeddings", &payload, settings.trace_content_policy());
+        let mut maintenance: Option<InMaintenance> = None;
+
+        for model_key in &self.available_models {
+            let Some(model) = self.model_registry.get(model_key) else {
+                continue;
+            };
 
-pub async fn embeddings(
-    State(model_registry): State<Arc<ModelRegistry>>,
-    Json(payload): Json<EmbeddingsRequest>,
-    model_keys: Vec<String>,
-) -> impl IntoResponse {
-    let mut tracer = OtelTracer::start("embeddings", &payload);
+            if self
+                .model_registry
+                .lookup_matches(&model.model_type, &payload.model)
+            {
+                if let Some(window) = self.model_registry.in_maintenance(&model) {
+                    if maintenance.as_ref().is_none_or(|m| window.until < m.until) {
+                        maintenance = Some(window);
+                    }
+                    continue;
+                }
+                // Set vendor now that we know which model/provider we're using
+                tracer.set_vendor(&get_vendor_name(&model.provider.r#type()));
+
+                if dry_run {
+                    let upstream = model
+                        .build_embeddings_request(payload.clone())
+                        .await
+                        .map_err(IntoResponse::into_response)?;
+                    return Err(dry_run_response(model_key, &model, &upstream));
+                }
+
+                let timing = RequestTiming::start();
+                let (response, served_by) = track_served_by(
+                    timing.scope(tracer.in_span(model.embeddings(payload.clone()))),
+                )
+                .await;
+                let response = match response {
+                    Ok(response) => response,
+                    Err(status) => {
+                        eprintln!("Embeddings error for model {model_key}: {status:?}");
+                        self.usage.record_error(&model.config.key);
+                        return Err(provider_failure(status));
+                    }
+                };
+                tracer.log_success(&response);
+                let prompt_tokens = response
+                    .usage
+                    .prompt_tokens
+                    .or(response.usage.total_tokens)
+                    .unwrap_or(0);
+                let tokens = Usage::new(prompt_tokens, 0);
+                if let Some(budget) = &self.budget {
+                    budget.record(usage_cost_usd(&model.config, &tokens));
+                }
+                self.usage.record(&model.config, &tokens);
+                let attribution = Attribution::when_enabled(
+                    settings.attribution_headers,
+                    &model,
+                    served_by.as_deref(),
+                );
+                return Ok(Served {
+                    body: response,
+                    provider_type: model.provider.r#type(),
+                    served_by,
+                    attribution,
+                    timing,
+                });
+            }
+        }
 
-    for model_key in model_keys {
-        let model = model_registry.get(&model_key).unwrap();
+        if let Some(maintenance) = maintenance {
+            tracer.log_error(maintenance.to_string());
+            return Err(maintenance.into_response());
+        }
+        let not_found = ModelNotFound::new(
+            &payload.model,
+            &self.model_registry,
+            &self.available_models,
+            settings.expose_available_models,
+        );
+        tracer.log_error(not_found.to_string());
+        Err(not_found.into_response())
+    }
+}
 
-        if payload.model == model.model_type {
-            // Set vendor now that we know which model/provider we're using
-            tracer.set_vendor(&get_vendor_name(&model.provider.r#type()));
+pub async fn chat_completions(
+    State(runner): State<Arc<PipelineRunner>>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<ChatCompletionRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let aggregate_tool_calls =
+        match aggregate_tool_calls_requested(&headers, runner.aggregate_tool_calls) {
+            Ok(aggregate_tool_calls) => aggregate_tool_calls,
+            Err(rejection) => return Ok(rejection.into_response()),
+        };
+    let outcome = runner.run_chat(&headers, payload).await?;
 
-            let response = model.embeddings(payload.clone()).await.inspect_err(|e| {
-                eprintln!("Embeddings error for model {model_key}: {e:?}");
-            })?;
-            tracer.log_success(&response);
-            let mut resp = Json(response).into_response();
-            inject_provider_header(&mut resp, &model.provider.r#type());
-            return Ok(resp);
+    Ok(match outcome {
+        ChatOutcome::Response(response) => response,
+        ChatOutcome::Completion {
+            completion,
+            provider_type,
+            model_key,
+            routing_decision,
+            served_by,
+            degraded,
+            attribution,
+            timing,
+        } => {
+            let mut resp = Json(runner.normalizer.completion(completion)).into_response();
+            inject_provider_header(&mut resp, &provider_type);
+            inject_model_key_header(&mut resp, &model_key);
+            inject_routing_decision_header(&mut resp, routing_decision);
+            inject_served_by_header(&mut resp, served_by.as_deref());
+            inject_degraded_header(&mut resp, degraded);
+            inject_attribution_headers(&mut resp, attribution.as_ref());
+            apply_timing(
+                &timing,
+                &mut resp,
+                &provider_type,
+                runner.settings().timing_headers,
+            );
+            resp
+        }
+        ChatOutcome::Stream {
+            chunks,
+            provider_type,
+            model_key,
+            routing_decision,
+            served_by,
+            degraded,
+            attribution,
+        } => {
+            let chunks = if aggregate_tool_calls {
+                aggregate_tool_call_stream(chunks)
+            } else {
+                chunks
+            };
+            let mut resp = Sse::new(chat_events(chunks, runner.normalizer, attribution.clone()))
+                .keep_alive(KeepAlive::default())
+                .into_response();
+            inject_provider_header(&mut resp, &provider_type);
+            inject_model_key_header(&mut resp, &model_key);
+            inject_routing_decision_header(&mut resp, routing_decision);
+            inject_served_by_header(&mut resp, served_by.as_deref());
+            inject_degraded_header(&mut resp, degraded);
+            inject_attribution_headers(&mut resp, attribution.as_ref());
+            resp
+        }
+    })
+}
+
+/// The SSE events of a streamed chat completion. A failed stream ends with an error event,
+/// so clients see why it stopped rather than a dropped connection. The chunk carrying
+/// usage also carries `attribution`, if any.
+fn chat_events(
+    chunks: BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>>,
+    normalizer: ResponseNormalizer,
+    attribution: Option<Attribution>,
+) -> impl futures::Stream<Item = Result<Event, axum::Error>> {
+    stream! {
+        let mut chunks = chunks;
+        while let Some(chunk) = chunks.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    let attribution = attribution.as_ref().filter(|_| chunk.usage.is_some());
+                    let chunk = normalizer.chunk(chunk);
+                    yield match attribution {
+                        Some(attribution) => attribution
+                            .extend_chunk(&chunk)
+                            .map_err(axum::Error::new)
+                            .and_then(|chunk| Event::default().json_data(chunk)),
+                        None => Event::default().json_data(chunk),
+                    };
+                }
+                Err(e) => {
+                    yield Event::default().json_data(serde_json::json!({
+                        "error": {"type": "api_error", "message": e.to_string()}
+                    }));
+                    return;
+                }
+            }
         }
     }
+}
 
-    tracer.log_error("No matching model found".to_string());
-    eprintln!("No matching model found for: {}", payload.model);
-    Err(StatusCode::NOT_FOUND)
+pub async fn completions(
+    State(runner): State<Arc<PipelineRunner>>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<CompletionRequest>,
+) -> axum::response::Response {
+    match runner.run_completion(&headers, payload).await {
+        Ok(served) => served.into_response(runner.settings().timing_headers),
+        Err(response) => response,
+    }
+}
+
+pub async fn embeddings(
+    State(runner): State<Arc<PipelineRunner>>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<EmbeddingsRequest>,
+) -> axum::response::Response {
+    match runner.run_embeddings(&headers, payload).await {
+        Ok(served) => served.into_response(runner.settings().timing_headers),
+        Err(response) => response,
+    }
 }
 
 #[cfg(test)]
@@ -289,6 +1466,7 @@ mod tests {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: String::new(),
+            maintenance_windows: vec![],
             params: HashMap::new(),
         };
         Arc::new(ProviderRegistry::new(&[provider_config]).unwrap())
@@ -303,6 +1481,8 @@ mod tests {
                 r#type: "test".to_string(),
                 provider: "test-provider".to_string(),
                 params: HashMap::new(),
+                enabled: true,
+                deprecation: Default::default(),
             })
             .collect()
     }
@@ -314,7 +1494,11 @@ mod tests {
             r#type: PipelineType::Chat,
             plugins: vec![PluginConfig::ModelRouter {
                 models: model_keys.into_iter().map(|s| s.to_string()).collect(),
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: None,
             }],
+            store_artifacts: false,
         }
     }
 
@@ -364,12 +1548,14 @@ mod tests {
             key: "test-provider-1".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: String::new(),
+            maintenance_windows: vec![],
             params: HashMap::new(),
         };
         let provider_config_2 = ProviderConfig {
             key: "test-provider-2".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: String::new(),
+            maintenance_windows: vec![],
             params: HashMap::new(),
         };
         let provider_registry =
@@ -381,12 +1567,16 @@ mod tests {
                 r#type: "test".to_string(),
                 provider: "test-provider-1".to_string(),
                 params: HashMap::new(),
+                enabled: true,
+                deprecation: Default::default(),
             },
             ModelConfig {
                 key: "test-model-2".to_string(),
                 r#type: "test".to_string(),
                 provider: "test-provider-2".to_string(),
                 params: HashMap::new(),
+                enabled: true,
+                deprecation: Default::default(),
             },
         ];
 
@@ -449,6 +1639,123 @@ mod tests {
         assert!(!ids.contains(&"test-model-2"));
     }
 
+    #[tokio::test]
+    async fn test_models_endpoint_omits_disabled_models() {
+        let provider_registry = create_test_provider_registry();
+        let mut model_configs = create_model_configs(vec!["test-model-1", "test-model-2"]);
+        model_configs[1].enabled = false;
+        let model_registry =
+            Arc::new(ModelRegistry::new(&model_configs, provider_registry).unwrap());
+        let pipeline = create_test_pipeline(vec!["test-model-1", "test-model-2"]);
+        let app = create_pipeline(&pipeline, &model_registry);
+
+        let response = get_models_response(app).await;
+
+        let data = response["data"].as_array().unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0]["id"], "test-model-1");
+    }
+
+    fn anthropic_model_registry(params: HashMap<String, String>) -> ModelRegistry {
+        let provider_config = ProviderConfig {
+            key: "anthropic".to_string(),
+            r#type: ProviderType::Anthropic,
+            api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
+            params: HashMap::new(),
+        };
+        let provider_registry = Arc::new(ProviderRegistry::new(&[provider_config]).unwrap());
+        let model_configs = vec![ModelConfig {
+            key: "claude".to_string(),
+            r#type: "claude-3-5-sonnet".to_string(),
+            provider: "anthropic".to_string(),
+            params,
+            enabled: true,
+            deprecation: Default::default(),
+        }];
+        ModelRegistry::new(&model_configs, provider_registry).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_models_endpoint_includes_capabilities_on_request() {
+        let model_registry = anthropic_model_registry(HashMap::new());
+        let app = create_pipeline(&create_test_pipeline(vec!["claude"]), &model_registry);
+
+        let response = get_models_response(app.clone()).await;
+        assert!(response["data"][0].get("capabilities").is_none());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/models?include_capabilities=true")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let capabilities = &body["data"][0]["capabilities"];
+        assert_eq!(capabilities["supports_tools"], true);
+        assert_eq!(capabilities["supports_streaming"], false);
+        assert!(capabilities["max_stop_sequences"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_chat_rejects_params_unsupported_by_provider() {
+        let model_registry = anthropic_model_registry(HashMap::new());
+        let app = create_pipeline(&create_test_pipeline(vec!["claude"]), &model_registry);
+        let body = serde_json::json!({
+            "model": "claude-3-5-sonnet",
+            "messages": [{"role": "user", "content": "hello"}],
+            "n": 2,
+            "logprobs": true
+        });
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/chat/completions")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+        assert_eq!(body["error"]["param"], "logprobs");
+        let message = body["error"]["message"].as_str().unwrap();
+        assert!(
+            message.contains("logprobs, n"),
+            "unexpected message: {message}"
+        );
+    }
+
+    #[test]
+    fn test_ignore_unsupported_params_skips_capability_check() {
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-5-sonnet",
+            "messages": [{"role": "user", "content": "hello"}],
+            "n": 2
+        }))
+        .unwrap();
+
+        let registry = anthropic_model_registry(HashMap::new());
+        let model = registry.get("claude").unwrap();
+        assert_eq!(model.unsupported_chat_params(&request), vec!["n"]);
+
+        let params = HashMap::from([("ignore_unsupported_params".to_string(), "true".to_string())]);
+        let registry = anthropic_model_registry(params);
+        let model = registry.get("claude").unwrap();
+        assert!(model.unsupported_chat_params(&request).is_empty());
+    }
+
     #[test]
     fn test_vendor_mapping_integration() {
         assert_eq!(get_vendor_name(&ProviderType::OpenAI), "openai");
@@ -482,7 +1789,7 @@ mod tests {
 
         async fn chat_completions(
             &self,
-            _payload: crate::models::chat::ChatCompletionRequest,
+            payload: crate::models::chat::ChatCompletionRequest,
             _model_config: &ModelConfig,
         ) -> Result<crate::models::chat::ChatCompletionResponse, StatusCode> {
             Ok(crate::models::chat::ChatCompletionResponse::NonStream(
@@ -494,6 +1801,8 @@ mod tests {
                     choices: vec![],
                     usage: crate::models::usage::Usage::default(),
                     system_fingerprint: None,
+                    // Echo the priority so tests can see what reached the provider.
+                    service_tier: payload.priority.map(|priority| priority.to_string()),
                 },
             ))
         }
@@ -532,6 +1841,16 @@ mod tests {
         provider_type: ProviderType,
         model: &str,
         pipeline_type: PipelineType,
+    ) -> Router {
+        build_mock_pipeline_with_plugins(provider_type, model, pipeline_type, vec![])
+    }
+
+    /// Like `build_mock_pipeline`, with extra plugins ahead of the model router.
+    fn build_mock_pipeline_with_plugins(
+        provider_type: ProviderType,
+        model: &str,
+        pipeline_type: PipelineType,
+        mut plugins: Vec<PluginConfig>,
     ) -> Router {
         let provider = Arc::new(ConfigurableMockProvider {
             key: "mock-provider".to_string(),
@@ -545,17 +1864,24 @@ mod tests {
             r#type: model.to_string(),
             provider: "mock-provider".to_string(),
             params: HashMap::new(),
+            enabled: true,
+            deprecation: Default::default(),
         }];
 
         let model_registry =
             ModelRegistry::new(&model_configs, Arc::new(provider_registry)).unwrap();
 
+        plugins.push(PluginConfig::ModelRouter {
+            models: vec!["mock-model".to_string()],
+            allow_dynamic_models: false,
+            adaptive: None,
+            race: None,
+        });
         let pipeline = Pipeline {
             name: "test".to_string(),
             r#type: pipeline_type,
-            plugins: vec![PluginConfig::ModelRouter {
-                models: vec!["mock-model".to_string()],
-            }],
+            plugins,
+            store_artifacts: false,
         };
 
         create_pipeline(&pipeline, &model_registry)
@@ -813,4 +2139,210 @@ mod tests {
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
         assert!(response.headers().get("x-genai-provider-name").is_none());
     }
+
+    #[tokio::test]
+    async fn test_chat_disabled_model_is_skipped() {
+        let provider = Arc::new(ConfigurableMockProvider {
+            key: "mock-provider".to_string(),
+            provider_type: ProviderType::OpenAI,
+        }) as Arc<dyn Provider>;
+        let provider_registry = ProviderRegistry::from_mock("mock-provider".to_string(), provider);
+        let model_configs = vec![ModelConfig {
+            key: "mock-model".to_string(),
+            r#type: "gpt-4o".to_string(),
+            provider: "mock-provider".to_string(),
+            params: HashMap::new(),
+            enabled: false,
+            deprecation: Default::default(),
+        }];
+        let model_registry =
+            ModelRegistry::new(&model_configs, Arc::new(provider_registry)).unwrap();
+        let app = create_pipeline(&create_test_pipeline(vec!["mock-model"]), &model_registry);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/chat/completions")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .body(Body::from(chat_request_body("gpt-4o")))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_budget_exceeded_returns_429() {
+        use crate::pipelines::budget::PipelineBudget;
+        use crate::types::{BudgetWindow, UsdAmount};
+
+        let provider = Arc::new(ConfigurableMockProvider {
+            key: "mock-provider".to_string(),
+            provider_type: ProviderType::OpenAI,
+        }) as Arc<dyn Provider>;
+        let provider_registry = ProviderRegistry::from_mock("mock-provider".to_string(), provider);
+        let model_configs = vec![ModelConfig {
+            key: "mock-model".to_string(),
+            r#type: "gpt-4o".to_string(),
+            provider: "mock-provider".to_string(),
+            params: HashMap::new(),
+            enabled: true,
+            deprecation: Default::default(),
+        }];
+        let model_registry =
+            ModelRegistry::new(&model_configs, Arc::new(provider_registry)).unwrap();
+
+        let pipeline = Pipeline {
+            name: "budget-exhausted".to_string(),
+            r#type: PipelineType::Chat,
+            plugins: vec![
+                PluginConfig::Budget {
+                    limit_usd: UsdAmount(1.0),
+                    window: BudgetWindow::Daily,
+                    warn_at_percent: 80,
+                },
+                PluginConfig::ModelRouter {
+                    models: vec!["mock-model".to_string()],
+                    allow_dynamic_models: false,
+                    adaptive: None,
+                    race: None,
+                },
+            ],
+            store_artifacts: false,
+        };
+        let services = HubServices::default();
+        let scope = PipelineScope::new(
+            &pipeline.name,
+            Arc::new(RuntimeSettings::from_general(None)),
+            services.clone(),
+        );
+        let runner = PipelineRunner::new(&pipeline, &model_registry, &BTreeMap::new(), scope);
+        let app = create_pipeline_router(&pipeline, Arc::new(runner));
+
+        PipelineBudget::new(
+            "budget-exhausted",
+            UsdAmount(1.0),
+            BudgetWindow::Daily,
+            80,
+            services.budgets.clone(),
+        )
+        .record(1.5);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/chat/completions")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .body(Body::from(chat_request_body("gpt-4o")))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["type"], "budget_exceeded");
+        assert!(body["error"]["resets_at"].is_string());
+    }
+
+    // ── Priority header ──────────────────────────────────────────────────
+
+    async fn chat_service_tier(app: Router, priority: Option<&str>) -> (StatusCode, String) {
+        let mut request = Request::builder()
+            .uri("/chat/completions")
+            .method("POST")
+            .header("content-type", "application/json");
+        if let Some(priority) = priority {
+            request = request.header(PRIORITY_HEADER, priority);
+        }
+        let response = app
+            .oneshot(
+                request
+                    .body(Body::from(chat_request_body("gpt-4o")))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+        let service_tier = body["service_tier"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        (status, service_tier)
+    }
+
+    #[tokio::test]
+    async fn test_priority_header_reaches_provider() {
+        let app = build_mock_pipeline(ProviderType::OpenAI, "gpt-4o", PipelineType::Chat);
+        let (status, service_tier) = chat_service_tier(app, Some("high")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(service_tier, "high");
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_default_priority_applies_without_header() {
+        let plugins = vec![PluginConfig::Priority {
+            default: RequestPriority::Low,
+        }];
+        let app = build_mock_pipeline_with_plugins(
+            ProviderType::OpenAI,
+            "gpt-4o",
+            PipelineType::Chat,
+            plugins.clone(),
+        );
+        let (_, service_tier) = chat_service_tier(app, None).await;
+        assert_eq!(service_tier, "low");
+
+        // An explicit header overrides the pipeline default.
+        let app = build_mock_pipeline_with_plugins(
+            ProviderType::OpenAI,
+            "gpt-4o",
+            PipelineType::Chat,
+            plugins,
+        );
+        let (_, service_tier) = chat_service_tier(app, Some("high")).await;
+        assert_eq!(service_tier, "high");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_priority_header_is_rejected() {
+        let app = build_mock_pipeline(ProviderType::OpenAI, "gpt-4o", PipelineType::Chat);
+        let (status, _) = chat_service_tier(app, Some("urgent")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_rejected_without_debug_headers() {
+        let app = build_mock_pipeline(ProviderType::OpenAI, "gpt-4o", PipelineType::Chat);
+        let body = serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hello"}]
+        });
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/chat/completions")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .header("x-hub-dry-run", "true")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+    }
 }
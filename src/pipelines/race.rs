@@ -0,0 +1,415 @@
+use crate::metrics::counter;
+use axum::http::StatusCode;
+use futures::stream::{self, FuturesUnordered};
+use futures::{FutureExt, StreamExt};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ai_models::instance::ModelInstance;
+use crate::ai_models::registry::ModelRegistry;
+use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::streaming::ChatCompletionChunk;
+use crate::providers::failover::track_served_by;
+use crate::types::RaceRouting;
+
+/// Counts the attempts of raced requests, labelled by model, `winner` (`true` for the
+/// attempt whose answer was used) and `outcome`: `won`, `failed` or `cancelled`.
+pub const RACE_ATTEMPTS_METRIC: &str = "hub_race_attempts_total";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttemptOutcome {
+    Won,
+    Failed,
+    /// Aborted because another attempt won.
+    Cancelled,
+}
+
+impl AttemptOutcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            AttemptOutcome::Won => "won",
+            AttemptOutcome::Failed => "failed",
+            AttemptOutcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+fn record_attempt(model_key: &str, outcome: AttemptOutcome) {
+    counter!(
+        RACE_ATTEMPTS_METRIC,
+        "model" => model_key.to_string(),
+        "winner" => (outcome == AttemptOutcome::Won).to_string(),
+        "outcome" => outcome.as_str()
+    )
+    .increment(1);
+}
+
+/// The attempt whose answer a race used.
+pub struct RaceWinner {
+    pub model: Arc<ModelInstance>,
+    pub response: ChatCompletionResponse,
+    /// Failover group member that served the winning attempt.
+    pub served_by: Option<String>,
+}
+
+/// Sends a request to several models serving its name and answers with the first response,
+/// cutting latency at the cost of the extra upstream requests. `stagger_ms` limits that
+/// cost to requests the first candidates are slow to answer.
+pub struct RaceRouter {
+    settings: RaceRouting,
+}
+
+impl RaceRouter {
+    pub fn new(settings: RaceRouting) -> Self {
+        Self { settings }
+    }
+
+    /// The models to race for `requested`, in the order requests are sent to them. Returns
+    /// `None` when fewer than two serve it, leaving the request to the regular routing rules.
+    pub fn contenders(
+        &self,
+        registry: &ModelRegistry,
+        requested: &str,
+        model_keys: &[String],
+    ) -> Option<Vec<Arc<ModelInstance>>> {
+        let keys = if self.settings.candidates.is_empty() {
+            model_keys
+        } else {
+            &self.settings.candidates
+        };
+        let contenders = registry.candidates(requested, keys);
+        (contenders.len() > 1).then_some(contenders)
+    }
+
+    /// Races `payload` across `contenders`. A stream counts as an answer once it produces
+    /// content. Fails with the last error when every contender fails.
+    pub async fn run(
+        &self,
+        contenders: &[Arc<ModelInstance>],
+        payload: &ChatCompletionRequest,
+    ) -> Result<RaceWinner, StatusCode> {
+        let keys: Vec<&str> = contenders.iter().map(|model| model.name.as_str()).collect();
+        let stagger = Duration::from_millis(self.settings.stagger_ms);
+        let (index, (response, served_by)) = race(&keys, stagger, |index| {
+            let model = contenders[index].clone();
+            let payload = payload.clone();
+            async move {
+                let (response, served_by) = track_served_by(async {
+                    let response = model.chat_completions(payload).await?;
+                    first_content(response).await
+                })
+                .await;
+                response.map(|response| (response, served_by))
+            }
+        })
+        .await?;
+        Ok(RaceWinner {
+            model: contenders[index].clone(),
+            response,
+            served_by,
+        })
+    }
+}
+
+/// Starts `attempt` for the first key, then for the next one whenever `stagger` passes
+/// without an answer or an attempt fails. Returns the index and answer of the first attempt
+/// to succeed. The attempts still running are dropped, which aborts their upstream requests.
+async fn race<T, F, Fut>(
+    keys: &[&str],
+    stagger: Duration,
+    attempt: F,
+) -> Result<(usize, T), StatusCode>
+where
+    F: Fn(usize) -> Fut,
+    Fut: Future<Output = Result<T, StatusCode>>,
+{
+    let launch = |index: usize| attempt(index).map(move |result| (index, result));
+    let mut running = FuturesUnordered::new();
+    let mut pending = HashSet::new();
+    let mut started = 0;
+    let mut last_error = StatusCode::SERVICE_UNAVAILABLE;
+    loop {
+        if running.is_empty() {
+            if started == keys.len() {
+                return Err(last_error);
+            }
+            running.push(launch(started));
+            pending.insert(started);
+            started += 1;
+        }
+        tokio::select! {
+            Some((index, result)) = running.next() => {
+                pending.remove(&index);
+                match result {
+                    Ok(answer) => {
+                        record_attempt(keys[index], AttemptOutcome::Won);
+                        for loser in pending {
+                            record_attempt(keys[loser], AttemptOutcome::Cancelled);
+                        }
+                        return Ok((index, answer));
+                    }
+                    Err(status) => {
+                        record_attempt(keys[index], AttemptOutcome::Failed);
+                        last_error = status;
+                        if started < keys.len() && !running.is_empty() {
+                            running.push(launch(started));
+                            pending.insert(started);
+                            started += 1;
+                        }
+                    }
+                }
+            }
+            _ = tokio::time::sleep(stagger), if started < keys.len() => {
+                running.push(launch(started));
+                pending.insert(started);
+                started += 1;
+            }
+        }
+    }
+}
+
+/// Waits for the first content of a streamed answer, so a stream that is slow to start
+/// loses the race. The chunks read so far are replayed ahead of the rest.
+async fn first_content(
+    response: ChatCompletionResponse,
+) -> Result<ChatCompletionResponse, StatusCode> {
+    let mut chunks = match response {
+        ChatCompletionResponse::Stream(chunks) => chunks,
+        completion => return Ok(completion),
+    };
+    let mut read = Vec::new();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| {
+            tracing::warn!("Raced stream failed before its first content: {e}");
+            StatusCode::BAD_GATEWAY
+        })?;
+        let answered = has_content(&chunk);
+        read.push(Ok(chunk));
+        if answered {
+            break;
+        }
+    }
+    Ok(ChatCompletionResponse::Stream(Box::pin(
+        stream::iter(read).chain(chunks),
+    )))
+}
+
+fn has_content(chunk: &ChatCompletionChunk) -> bool {
+    chunk.choices.iter().any(|choice| {
+        let delta = &choice.delta;
+        delta
+            .content
+            .as_deref()
+            .is_some_and(|content| !content.is_empty())
+            || delta
+                .reasoning
+                .as_deref()
+                .is_some_and(|reasoning| !reasoning.is_empty())
+            || delta.tool_calls.is_some()
+            || delta.annotations.is_some()
+            || choice.finish_reason.is_some()
+    })
+}
+
+/// Checks that the race strategy's candidates are models of the router.
+pub fn validate_race_routing(settings: &RaceRouting, models: &[String]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for candidate in &settings.candidates {
+        if !models.contains(candidate) {
+            return Err(format!(
+                "candidate '{candidate}' is not one of the router's models"
+            ));
+        }
+        if !seen.insert(candidate) {
+            return Err(format!("candidate '{candidate}' is listed twice"));
+        }
+    }
+    if settings.candidates.len() == 1 {
+        return Err("a race needs at least two candidates".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::streaming::{Choice, ChoiceDelta};
+    use reqwest_streams::error::StreamBodyError;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    const KEYS: [&str; 2] = ["slow", "fast"];
+
+    /// Marks an attempt as aborted when it's dropped before finishing.
+    struct Attempt {
+        finished: bool,
+        aborted: Arc<AtomicBool>,
+    }
+
+    impl Drop for Attempt {
+        fn drop(&mut self) {
+            if !self.finished {
+                self.aborted.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// An attempt answering `answer` after `latency`.
+    async fn answer_after(
+        latency: Duration,
+        answer: Result<&'static str, StatusCode>,
+        aborted: Arc<AtomicBool>,
+    ) -> Result<&'static str, StatusCode> {
+        let mut attempt = Attempt {
+            finished: false,
+            aborted,
+        };
+        tokio::time::sleep(latency).await;
+        attempt.finished = true;
+        answer
+    }
+
+    #[tokio::test]
+    async fn test_first_answer_wins_and_loser_is_aborted() {
+        let aborted = [
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicBool::new(false)),
+        ];
+        let started = Instant::now();
+        let winner = race(&KEYS, Duration::ZERO, |index| {
+            let latency = [Duration::from_millis(500), Duration::from_millis(20)][index];
+            answer_after(latency, Ok(KEYS[index]), aborted[index].clone())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(winner, (1, "fast"));
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert!(aborted[0].load(Ordering::SeqCst));
+        assert!(!aborted[1].load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_stagger_holds_back_later_candidates() {
+        let launched = AtomicUsize::new(0);
+        let winner = race(&KEYS, Duration::from_millis(300), |index| {
+            launched.fetch_add(1, Ordering::SeqCst);
+            let latency = [Duration::from_millis(50), Duration::from_millis(10)][index];
+            answer_after(latency, Ok(KEYS[index]), Default::default())
+        })
+        .await
+        .unwrap();
+        // The first candidate answered within the stagger, so the second was never sent.
+        assert_eq!(winner, (0, "slow"));
+        assert_eq!(launched.load(Ordering::SeqCst), 1);
+
+        let started = Instant::now();
+        let winner = race(&KEYS, Duration::from_millis(50), |index| {
+            let latency = [Duration::from_millis(500), Duration::from_millis(10)][index];
+            answer_after(latency, Ok(KEYS[index]), Default::default())
+        })
+        .await
+        .unwrap();
+        let elapsed = started.elapsed();
+        assert_eq!(winner, (1, "fast"));
+        assert!(elapsed >= Duration::from_millis(60), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(400), "{elapsed:?}");
+    }
+
+    #[tokio::test]
+    async fn test_failure_sends_to_next_candidate_at_once() {
+        let started = Instant::now();
+        let winner = race(&KEYS, Duration::from_secs(5), |index| {
+            let answer = [Err(StatusCode::SERVICE_UNAVAILABLE), Ok("fast")][index];
+            answer_after(Duration::from_millis(10), answer, Default::default())
+        })
+        .await
+        .unwrap();
+        assert_eq!(winner, (1, "fast"));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let failed = race(&KEYS, Duration::ZERO, |index| {
+            let answer = [
+                Err(StatusCode::TOO_MANY_REQUESTS),
+                Err(StatusCode::BAD_GATEWAY),
+            ][index];
+            answer_after(
+                Duration::from_millis(10 + 20 * index as u64),
+                answer,
+                Default::default(),
+            )
+        })
+        .await;
+        assert_eq!(failed, Err(StatusCode::BAD_GATEWAY));
+    }
+
+    fn chunk(delta: ChoiceDelta) -> ChatCompletionChunk {
+        ChatCompletionChunk {
+            id: "chatcmpl-1".to_string(),
+            choices: vec![Choice {
+                delta,
+                finish_reason: None,
+                stop_sequence: None,
+                index: 0,
+                logprobs: None,
+            }],
+            created: 1,
+            model: "gpt-4o".to_string(),
+            service_tier: None,
+            system_fingerprint: None,
+            usage: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streams_answer_with_their_first_content() {
+        let role = chunk(ChoiceDelta {
+            content: None,
+            role: Some("assistant".to_string()),
+            tool_calls: None,
+            reasoning: None,
+            annotations: None,
+        });
+        let content = chunk(ChoiceDelta {
+            content: Some("Hello".to_string()),
+            role: None,
+            tool_calls: None,
+            reasoning: None,
+            annotations: None,
+        });
+        assert!(!has_content(&role));
+        assert!(has_content(&content));
+
+        // Content arrives after the role chunk; both are replayed.
+        let chunks =
+            stream::iter([Ok::<_, StreamBodyError>(role), Ok(content)]).then(|chunk| async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                chunk
+            });
+        let response = first_content(ChatCompletionResponse::Stream(Box::pin(chunks)))
+            .await
+            .unwrap();
+        let ChatCompletionResponse::Stream(chunks) = response else {
+            panic!("expected a stream");
+        };
+        let chunks: Vec<_> = chunks.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].choices[0].delta.content.as_deref(), Some("Hello"));
+    }
+
+    #[test]
+    fn test_validate_race_routing() {
+        let models = ["a".to_string(), "b".to_string()];
+        let settings = |candidates: &[&str]| RaceRouting {
+            candidates: candidates.iter().map(|key| key.to_string()).collect(),
+            stagger_ms: 100,
+        };
+        assert!(validate_race_routing(&settings(&[]), &models).is_ok());
+        assert!(validate_race_routing(&settings(&["b", "a"]), &models).is_ok());
+        assert!(validate_race_routing(&settings(&["a", "c"]), &models).is_err());
+        assert!(validate_race_routing(&settings(&["a", "a"]), &models).is_err());
+        assert!(validate_race_routing(&settings(&["a"]), &models).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::ai_models::params::realtime_max_session;
+use crate::config::models::ModelConfig;
+use crate::logging::error_rate_limited;
+use crate::models::usage::Usage;
+use crate::pipelines::budget::PipelineBudget;
+use crate::pipelines::cost::usage_cost_usd;
+use crate::pipelines::pipeline::PipelineRunner;
+use crate::pipelines::usage::PipelineUsage;
+use axum::extract::ws::{self, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+const SESSION_LIMIT_REASON: &str = "session duration limit reached";
+
+#[derive(Debug, Deserialize)]
+pub struct RealtimeQuery {
+    pub model: String,
+}
+
+/// Token usage reported by a realtime session's `response.done` events.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RealtimeUsage {
+    pub responses: u32,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl RealtimeUsage {
+    /// Adds the usage of a `response.done` server event and returns its
+    /// `(input_tokens, output_tokens)`. Other events are ignored.
+    pub fn record_event(&mut self, event: &str) -> Option<(u32, u32)> {
+        // Audio deltas are large; skip parsing anything that can't be a response.done.
+        if !event.contains("response.done") {
+            return None;
+        }
+        let event: Value = serde_json::from_str(event).ok()?;
+        if event["type"] != "response.done" {
+            return None;
+        }
+        let usage = &event["response"]["usage"];
+        let tokens = |key: &str| usage[key].as_u64().unwrap_or(0) as u32;
+        let (input_tokens, output_tokens) = (tokens("input_tokens"), tokens("output_tokens"));
+        self.responses += 1;
+        self.input_tokens += input_tokens;
+        self.output_tokens += output_tokens;
+        Some((input_tokens, output_tokens))
+    }
+}
+
+/// Upgrades to a websocket proxied to the provider's realtime API.
+pub async fn realtime(
+    State(runner): State<Arc<PipelineRunner>>,
+    Query(query): Query<RealtimeQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let model_registry = &runner.model_registry;
+    let Some((model_key, model)) = runner.available_models.iter().find_map(|model_key| {
+        let model = model_registry.get(model_key)?;
+        model_registry
+            .lookup_matches(&model.model_type, &query.model)
+            .then(|| (model_key.clone(), model))
+    }) else {
+        tracing::warn!("No matching realtime model found for: {}", query.model);
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let upstream_request = match model
+        .provider
+        .build_realtime_request(&model.model_type, &model.config)
+    {
+        Ok(request) => request,
+        Err(status) => {
+            tracing::error!("Model '{}' does not support realtime sessions", model_key);
+            return status.into_response();
+        }
+    };
+
+    let session = RealtimeSession {
+        model_key,
+        model_config: model.config.clone(),
+        max_duration: realtime_max_session(&model.config.params),
+        budget: runner.budget.clone(),
+        usage: runner.usage.clone(),
+    };
+    upgrade.on_upgrade(move |socket| session.proxy(socket, upstream_request))
+}
+
+struct RealtimeSession {
+    model_key: String,
+    model_config: ModelConfig,
+    max_duration: Duration,
+    budget: Option<Arc<PipelineBudget>>,
+    usage: PipelineUsage,
+}
+
+impl RealtimeSession {
+    async fn proxy(self, mut client: WebSocket, upstream_request: Request<()>) {
+        let started = Instant::now();
+        let upstream = match tokio_tungstenite::connect_async(upstream_request).await {
+            Ok((upstream, _)) => upstream,
+            Err(e) => {
+                error_rate_limited(
+                    "realtime.connect",
+                    format!("Realtime upstream connection failed: {e}"),
+                );
+                self.usage.record_error(&self.model_key);
+                let close = close_frame(ws::close_code::ERROR, "upstream connection failed");
+                let _ = client.send(close).await;
+                return;
+            }
+        };
+        tracing::info!("Realtime session opened for model {}", self.model_key);
+
+        let (mut client_tx, mut client_rx) = client.split();
+        let (mut upstream_tx, mut upstream_rx) = upstream.split();
+        let mut usage = RealtimeUsage::default();
+
+        let client_to_upstream = async {
+            while let Some(Ok(message)) = client_rx.next().await {
+                let Some(message) = to_upstream_message(message) else {
+                    continue;
+                };
+                let closing = message.is_close();
+                if upstream_tx.send(message).await.is_err() || closing {
+                    break;
+                }
+            }
+        };
+        let upstream_to_client = async {
+            while let Some(Ok(message)) = upstream_rx.next().await {
+                if let tungstenite::Message::Text(event) = &message {
+                    // Each response counts as a request in the usage summary.
+                    if let Some((input, output)) = usage.record_event(event.as_str()) {
+                        let tokens = Usage::new(input, output);
+                        if let Some(budget) = &self.budget {
+                            budget.record(usage_cost_usd(&self.model_config, &tokens));
+                        }
+                        self.usage.record(&self.model_config, &tokens);
+                    }
+                }
+                let Some(message) = to_client_message(message) else {
+                    continue;
+                };
+                let closing = matches!(message, ws::Message::Close(_));
+                if client_tx.send(message).await.is_err() || closing {
+                    break;
+                }
+            }
+        };
+
+        let reason = tokio::select! {
+            _ = client_to_upstream => "client closed",
+            _ = upstream_to_client => "upstream closed",
+            _ = tokio::time::sleep(self.max_duration) => SESSION_LIMIT_REASON,
+        };
+        if reason == SESSION_LIMIT_REASON {
+            let close = close_frame(ws::close_code::POLICY, SESSION_LIMIT_REASON);
+            let _ = client_tx.send(close).await;
+        }
+        let _ = upstream_tx.close().await;
+        let _ = client_tx.close().await;
+
+        tracing::info!(
+            "Realtime session for model {} ended ({}) after {:?}: {} responses, {} input / {} output tokens",
+            self.model_key,
+            reason,
+            started.elapsed(),
+            usage.responses,
+            usage.input_tokens,
+            usage.output_tokens
+        );
+    }
+}
+
+fn close_frame(code: u16, reason: &str) -> ws::Message {
+    ws::Message::Close(Some(ws::CloseFrame {
+        code,
+        reason: reason.into(),
+    }))
+}
+
+/// Each hop answers its own pings, so only data and close frames are forwarded.
+fn to_upstream_message(message: ws::Message) -> Option<tungstenite::Message> {
+    match message {
+        ws::Message::Text(text) => Some(tungstenite::Message::text(text.as_str())),
+        ws::Message::Binary(data) => Some(tungstenite::Message::binary(data)),
+        ws::Message::Close(frame) => {
+            Some(tungstenite::Message::Close(frame.map(|frame| CloseFrame {
+                code: frame.code.into(),
+                reason: frame.reason.as_str().into(),
+            })))
+        }
+        ws::Message::Ping(_) | ws::Message::Pong(_) => None,
+    }
+}
+
+fn to_client_message(message: tungstenite::Message) -> Option<ws::Message> {
+    match message {
+        tungstenite::Message::Text(text) => Some(ws::Message::Text(text.as_str().into())),
+        tungstenite::Message::Binary(data) => Some(ws::Message::Binary(data)),
+        tungstenite::Message::Close(frame) => {
+            Some(ws::Message::Close(frame.map(|frame| ws::CloseFrame {
+                code: frame.code.into(),
+                reason: frame.reason.as_str().into(),
+            })))
+        }
+        tungstenite::Message::Ping(_)
+        | tungstenite::Message::Pong(_)
+        | tungstenite::Message::Frame(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_is_read_from_response_done_events() {
+        let mut usage = RealtimeUsage::default();
+        let done = serde_json::json!({
+            "type": "response.done",
+            "response": {"usage": {"total_tokens": 30, "input_tokens": 10, "output_tokens": 20}}
+        })
+        .to_string();
+        assert_eq!(usage.record_event(&done), Some((10, 20)));
+        assert_eq!(usage.record_event(&done), Some((10, 20)));
+        assert_eq!(
+            usage.record_event(r#"{"type":"response.audio.delta","delta":"AAAA"}"#),
+            None
+        );
+        assert_eq!(usage.record_event("not json response.done"), None);
+        assert_eq!(
+            usage,
+            RealtimeUsage {
+                responses: 2,
+                input_tokens: 20,
+                output_tokens: 40,
+            }
+        );
+    }
+
+    #[test]
+    fn test_close_frames_keep_code_and_reason() {
+        let message = to_upstream_message(close_frame(1008, "limit")).unwrap();
+        let tungstenite::Message::Close(Some(frame)) = &message else {
+            panic!("expected a close frame");
+        };
+        assert_eq!(u16::from(frame.code), 1008);
+        assert_eq!(frame.reason.as_str(), "limit");
+
+        let message = to_client_message(message).unwrap();
+        let ws::Message::Close(Some(frame)) = message else {
+            panic!("expected a close frame");
+        };
+        assert_eq!(frame.code, 1008);
+        assert_eq!(frame.reason.as_str(), "limit");
+    }
+
+    #[test]
+    fn test_pings_are_not_forwarded() {
+        assert!(to_upstream_message(ws::Message::Ping(vec![1].into())).is_none());
+        let text = to_client_message(tungstenite::Message::text("hi")).unwrap();
+        assert_eq!(text, ws::Message::Text("hi".into()));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::logging::LogSampler;
+use crate::outcome::Outcome;
+use crate::types::SampleRate;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::Level;
+
+/// Per-request summary logging configured through the `logging` plugin.
+#[derive(Debug)]
+pub struct RequestLogger {
+    pipeline: String,
+    level: Level,
+    sampler: LogSampler,
+}
+
+impl RequestLogger {
+    pub fn new(pipeline: &str, level: &str, sample_rate: SampleRate) -> Self {
+        Self::with_sampler(pipeline, level, LogSampler::new(sample_rate.0))
+    }
+
+    fn with_sampler(pipeline: &str, level: &str, sampler: LogSampler) -> Self {
+        Self {
+            pipeline: pipeline.to_string(),
+            level: parse_level(level),
+            sampler,
+        }
+    }
+
+    /// Errors are always logged; successful requests are subject to sampling.
+    fn should_log(&self, status: u16) -> bool {
+        status >= 400 || self.sampler.sample()
+    }
+}
+
+/// Maps the plugin's level names onto tracing levels, defaulting to warn.
+fn parse_level(level: &str) -> Level {
+    match level.to_lowercase().as_str() {
+        "trace" => Level::TRACE,
+        "debug" => Level::DEBUG,
+        "info" => Level::INFO,
+        "error" => Level::ERROR,
+        _ => Level::WARN,
+    }
+}
+
+/// Middleware logging method, path, status, outcome and latency of pipeline requests.
+pub async fn log_requests(
+    State(logger): State<Arc<RequestLogger>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16();
+    if logger.should_log(status) {
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let pipeline = &logger.pipeline;
+        let status_class = Outcome::of(&response).as_str();
+        match logger.level {
+            Level::TRACE => tracing::trace!(
+                %pipeline, %method, %path, status, status_class, latency_ms, "pipeline request"
+            ),
+            Level::DEBUG => tracing::debug!(
+                %pipeline, %method, %path, status, status_class, latency_ms, "pipeline request"
+            ),
+            Level::INFO => tracing::info!(
+                %pipeline, %method, %path, status, status_class, latency_ms, "pipeline request"
+            ),
+            Level::ERROR => tracing::error!(
+                %pipeline, %method, %path, status, status_class, latency_ms, "pipeline request"
+            ),
+            _ => tracing::warn!(
+                %pipeline, %method, %path, status, status_class, latency_ms, "pipeline request"
+            ),
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_bypass_sampling() {
+        let logger = RequestLogger::with_sampler("default", "info", LogSampler::with_seed(0.0, 3));
+        assert!(!logger.should_log(200));
+        assert!(logger.should_log(429));
+        assert!(logger.should_log(502));
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug"), Level::DEBUG);
+        assert_eq!(parse_level("INFO"), Level::INFO);
+        assert_eq!(parse_level("warning"), Level::WARN);
+        assert_eq!(parse_level("nonsense"), Level::WARN);
+    }
+}
@@ -0,0 +1,474 @@
+use crate::ai_models::registry::ModelRegistry;
+use crate::models::chat::ChatCompletionRequest;
+use crate::models::completion::CompletionRequest;
+use crate::models::embeddings::EmbeddingsRequest;
+use crate::models::messages::{CountTokensRequest, MessagesRequest};
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Request};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::fmt;
+
+const JSON_SCHEMA_TYPES: &[&str] = &[
+    "object", "array", "string", "number", "integer", "boolean", "null",
+];
+
+/// A rejected request body, rendered as an OpenAI-style `invalid_request_error`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestValidationError {
+    pub status: StatusCode,
+    pub message: String,
+    pub param: Option<String>,
+}
+
+impl RequestValidationError {
+    fn invalid(param: &str, message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            message: message.into(),
+            param: Some(param.to_string()),
+        }
+    }
+
+    /// Rejects requests that break the pipeline's `parameter-policy`.
+    pub fn policy_violation(param: &str, message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+            param: Some(param.to_string()),
+        }
+    }
+
+    /// Rejects requests whose tools break the pipeline's `tool-limits`.
+    pub fn tool_limit(param: &str, message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+            param: Some(param.to_string()),
+        }
+    }
+
+    /// Rejects requests for a model that has been deprecated, naming its replacement.
+    pub fn deprecated_model(model: &str, replacement: Option<&str>) -> Self {
+        let message = match replacement {
+            Some(replacement) => {
+                format!("Model '{model}' is deprecated, use '{replacement}' instead")
+            }
+            None => format!("Model '{model}' is deprecated"),
+        };
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message,
+            param: Some("model".to_string()),
+        }
+    }
+
+    /// Rejects request fields the selected model's provider can't honour.
+    pub fn unsupported_params(model: &str, params: &[&str]) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: format!(
+                "Model '{model}' does not support: {}. Remove them or set \
+                 'ignore_unsupported_params' on the model to send them anyway",
+                params.join(", ")
+            ),
+            param: params.first().map(ToString::to_string),
+        }
+    }
+
+    /// Rejects requests with built-in tools, such as `web_search_preview`, that the model's
+    /// provider can't run.
+    pub fn unsupported_builtin_tools(model: &str, tool_types: &[&str]) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: format!(
+                "Model '{model}' does not support the built-in tools: {}. Remove them or set \
+                 'ignore_unsupported_params' on the model to leave them out",
+                tool_types.join(", ")
+            ),
+            param: Some("tools".to_string()),
+        }
+    }
+
+    /// Rejects request bodies larger than `max_buffered_body_bytes`, which middleware reads
+    /// whole.
+    pub fn body_too_large(max_bytes: usize) -> Self {
+        Self {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            message: format!("Request bodies are limited to {max_bytes} bytes"),
+            param: None,
+        }
+    }
+
+    /// Rejects requests whose prompt and requested output can't fit in the model's context
+    /// window.
+    pub fn context_window_exceeded(
+        model: &str,
+        input_tokens: u32,
+        max_tokens: u32,
+        context_window: u32,
+    ) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: format!(
+                "Model '{model}' has a context window of {context_window} tokens, but the \
+                 request has {input_tokens} input tokens and asks for {max_tokens} more"
+            ),
+            param: Some("messages".to_string()),
+        }
+    }
+}
+
+impl IntoResponse for RequestValidationError {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "error": {
+                "type": "invalid_request_error",
+                "message": self.message,
+                "param": self.param,
+                "code": null,
+            }
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// A request for a model the pipeline doesn't serve, rendered as OpenAI's `model_not_found`
+/// error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelNotFound {
+    pub model: String,
+    /// The models the pipeline serves, listed when `general.expose_available_models` is on.
+    pub available_models: Option<Vec<String>>,
+}
+
+impl ModelNotFound {
+    /// Lists the models `model_keys` serve when `expose_available_models` is set.
+    pub fn new(
+        model: &str,
+        model_registry: &ModelRegistry,
+        model_keys: &[String],
+        expose_available_models: bool,
+    ) -> Self {
+        Self {
+            model: model.to_string(),
+            available_models: expose_available_models
+                .then(|| model_registry.served_models(model_keys)),
+        }
+    }
+}
+
+impl fmt::Display for ModelNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Model '{}' is not served by this pipeline", self.model)?;
+        match self.available_models.as_deref() {
+            Some([]) => write!(f, ", which has no models available"),
+            Some(models) => write!(f, ". Available models: {}", models.join(", ")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl IntoResponse for ModelNotFound {
+    fn into_response(self) -> Response {
+        let mut error = json!({
+            "type": "invalid_request_error",
+            "message": self.to_string(),
+            "param": "model",
+            "code": "model_not_found",
+        });
+        if let Some(models) = self.available_models {
+            error["available_models"] = json!(models);
+        }
+        (StatusCode::NOT_FOUND, Json(json!({ "error": error }))).into_response()
+    }
+}
+
+/// Semantic checks run after a request body has been parsed.
+pub trait ValidateRequest {
+    fn validate(&self) -> Result<(), RequestValidationError> {
+        Ok(())
+    }
+}
+
+fn check_range(
+    param: &str,
+    value: Option<f32>,
+    min: f32,
+    max: f32,
+) -> Result<(), RequestValidationError> {
+    match value {
+        Some(value) if !(min..=max).contains(&value) => Err(RequestValidationError::invalid(
+            param,
+            format!("'{param}' must be between {min} and {max}, got {value}"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_positive(param: &str, value: Option<u32>) -> Result<(), RequestValidationError> {
+    match value {
+        Some(0) => Err(RequestValidationError::invalid(
+            param,
+            format!("'{param}' must be a positive integer"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the first `type` in a JSON schema that is not a JSON Schema type name.
+fn find_invalid_schema_type(schema: &Value) -> Option<String> {
+    match schema {
+        Value::Object(map) => map.iter().find_map(|(key, value)| match key.as_str() {
+            "type" => match value {
+                Value::String(name) if !JSON_SCHEMA_TYPES.contains(&name.as_str()) => {
+                    Some(name.clone())
+                }
+                Value::Array(names) => names.iter().find_map(|name| match name.as_str() {
+                    Some(name) if JSON_SCHEMA_TYPES.contains(&name) => None,
+                    _ => Some(name.to_string()),
+                }),
+                Value::String(_) => None,
+                other => Some(other.to_string()),
+            },
+            // Keys of these maps are property names, not keywords.
+            "properties" | "$defs" | "definitions" => value
+                .as_object()
+                .and_then(|entries| entries.values().find_map(find_invalid_schema_type)),
+            _ => find_invalid_schema_type(value),
+        }),
+        Value::Array(items) => items.iter().find_map(find_invalid_schema_type),
+        _ => None,
+    }
+}
+
+impl ValidateRequest for ChatCompletionRequest {
+    fn validate(&self) -> Result<(), RequestValidationError> {
+        if self.messages.is_empty() {
+            return Err(RequestValidationError::invalid(
+                "messages",
+                "'messages' must contain at least one message",
+            ));
+        }
+        check_range("temperature", self.temperature, 0.0, 2.0)?;
+        check_range("top_p", self.top_p, 0.0, 1.0)?;
+        check_positive("max_tokens", self.max_tokens)?;
+
+        for (index, tool) in self.tools.iter().flatten().enumerate() {
+            let Some(parameters) = tool.function().and_then(|f| f.parameters.as_ref()) else {
+                continue;
+            };
+            let schema = Value::Object(parameters.clone().into_iter().collect());
+            if let Some(invalid) = find_invalid_schema_type(&schema) {
+                let param = format!("tools[{index}].function.parameters");
+                return Err(RequestValidationError::invalid(
+                    &param,
+                    format!("'{param}' uses unknown JSON schema type {invalid}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ValidateRequest for CompletionRequest {
+    fn validate(&self) -> Result<(), RequestValidationError> {
+        check_range("temperature", self.temperature, 0.0, 2.0)?;
+        check_range("top_p", self.top_p, 0.0, 1.0)?;
+        check_positive("max_tokens", self.max_tokens)
+    }
+}
+
+impl ValidateRequest for EmbeddingsRequest {}
+
+/// Checked once converted to a `ChatCompletionRequest`.
+impl ValidateRequest for MessagesRequest {}
+
+/// Checked once converted to a `ChatCompletionRequest`.
+impl ValidateRequest for CountTokensRequest {}
+
+/// JSON extractor for inference routes. Unlike `axum::Json`, deserialization failures name
+/// the offending field path and are returned as 422 OpenAI-style errors.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + ValidateRequest,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_json = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| {
+                let mime = value.split(';').next().unwrap_or_default().trim();
+                mime == "application/json" || mime.ends_with("+json")
+            });
+        if !is_json {
+            return Err(RequestValidationError {
+                status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                message: "Expected request with `Content-Type: application/json`".to_string(),
+                param: None,
+            }
+            .into_response());
+        }
+
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let deserializer = &mut serde_json::Deserializer::from_slice(&bytes);
+        let payload: T = serde_path_to_error::deserialize(deserializer).map_err(|e| {
+            let path = e.path().to_string();
+            let (message, param) = if path == "." {
+                (format!("Invalid request body: {}", e.inner()), None)
+            } else {
+                (
+                    format!("Invalid value for '{path}': {}", e.inner()),
+                    Some(path),
+                )
+            };
+            RequestValidationError {
+                status: StatusCode::UNPROCESSABLE_ENTITY,
+                message,
+                param,
+            }
+            .into_response()
+        })?;
+
+        payload.validate().map_err(IntoResponse::into_response)?;
+        Ok(Self(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::{Body, to_bytes};
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    async fn post_chat(body: Value) -> (StatusCode, Value) {
+        post_raw("application/json", body.to_string()).await
+    }
+
+    async fn post_raw(content_type: &str, body: String) -> (StatusCode, Value) {
+        let app = Router::new().route(
+            "/chat/completions",
+            post(|ValidatedJson(_): ValidatedJson<ChatCompletionRequest>| async { StatusCode::OK }),
+        );
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/chat/completions")
+                    .method("POST")
+                    .header("content-type", content_type)
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn chat_body(overrides: Value) -> Value {
+        let mut body = json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hello"}]
+        });
+        for (key, value) in overrides.as_object().unwrap() {
+            body[key] = value.clone();
+        }
+        body
+    }
+
+    async fn assert_rejected(body: Value, param: &str) {
+        let (status, error) = post_chat(body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error["error"]["type"], "invalid_request_error");
+        assert_eq!(error["error"]["param"], param);
+        let message = error["error"]["message"].as_str().unwrap();
+        assert!(
+            message.contains(param),
+            "message '{message}' should name '{param}'"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_valid_request_is_accepted() {
+        let (status, _) = post_chat(chat_body(json!({"temperature": 0.7}))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_fields_name_their_path() {
+        let cases = [
+            (json!({"temperature": "hot"}), "temperature"),
+            (json!({"top_p": "high"}), "top_p"),
+            (json!({"max_tokens": -5}), "max_tokens"),
+            (json!({"n": "two"}), "n"),
+            (json!({"stream": "yes"}), "stream"),
+            (json!({"messages": "hello"}), "messages"),
+            (
+                json!({"messages": [{"role": 5, "content": "hi"}]}),
+                "messages[0].role",
+            ),
+            (json!({"logit_bias": {"50256": "ban"}}), "logit_bias.50256"),
+            (json!({"metadata": {"team": 7}}), "metadata.team"),
+            (
+                json!({"tools": [{"type": "function", "function": {"name": 1}}]}),
+                "tools[0].function.name",
+            ),
+        ];
+        for (overrides, param) in cases {
+            assert_rejected(chat_body(overrides), param).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_semantic_constraints() {
+        let cases = [
+            (json!({"temperature": 2.5}), "temperature"),
+            (json!({"top_p": 1.5}), "top_p"),
+            (json!({"max_tokens": 0}), "max_tokens"),
+            (json!({"messages": []}), "messages"),
+            (
+                json!({"tools": [{"type": "function", "function": {
+                    "name": "lookup",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {"type": {"type": "strng"}}
+                    }
+                }}]}),
+                "tools[0].function.parameters",
+            ),
+        ];
+        for (overrides, param) in cases {
+            assert_rejected(chat_body(overrides), param).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_field_and_content_type() {
+        let (status, error) = post_chat(json!({"messages": []})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(error["error"]["param"].is_null());
+        assert!(
+            error["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("missing field `model`")
+        );
+
+        let (status, _) = post_raw("text/plain", chat_body(json!({})).to_string()).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
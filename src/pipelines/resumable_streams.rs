@@ -0,0 +1,470 @@
+use crate::pipelines::buffered_body::read_request_body;
+use crate::pipelines::idempotency::{is_stream_request, request_fingerprint};
+use crate::pipelines::pipeline::PipelineScope;
+use crate::pipelines::request_validation::RequestValidationError;
+use crate::state_store::StateStore;
+use async_stream::stream;
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Client-chosen id under which a streamed response is buffered for reconnects.
+pub const STREAM_ID_HEADER: HeaderName = HeaderName::from_static("x-hub-stream-id");
+/// Set to `true` on responses that resume a stream.
+pub const RESUMED_HEADER: HeaderName = HeaderName::from_static("x-hub-stream-resumed");
+const LAST_EVENT_ID_HEADER: HeaderName = HeaderName::from_static("last-event-id");
+const MAX_STREAM_ID_LENGTH: usize = 255;
+/// Most bytes of events buffered per stream. Older events are evicted beyond it, so very
+/// long streams can only be resumed from their recent events.
+pub const MAX_BUFFERED_BYTES: usize = 1024 * 1024;
+
+/// What the `StateStore` remembers about a resumable stream. Each event is stored under
+/// its own key, so appending one doesn't rewrite the others.
+#[derive(Serialize, Deserialize)]
+struct StreamRecord {
+    fingerprint: String,
+    headers: Vec<(String, String)>,
+    /// Oldest event still buffered. Events are numbered from 1.
+    first_event_id: u64,
+    /// Newest event, 0 before the first one.
+    last_event_id: u64,
+    complete: bool,
+}
+
+fn event_key(key: &str, id: u64) -> String {
+    format!("{key}:{id}")
+}
+
+fn read_record(store: &StateStore, key: &str) -> Option<StreamRecord> {
+    store
+        .get(key)
+        .and_then(|value| serde_json::from_value(value).ok())
+}
+
+enum Claim {
+    /// No stream has the id yet: run the request and buffer its events.
+    Start(StreamWriter),
+    /// Send the events after `after`, then live ones while the stream runs.
+    Resume {
+        record: StreamRecord,
+        after: u64,
+        progress: Option<watch::Receiver<u64>>,
+    },
+    Reject(RequestValidationError),
+}
+
+/// Streams whose upstream response is still being read, so followers can wait for their
+/// next event, and the `StateStore` the events of all streams are buffered in.
+pub(crate) struct LiveStreams {
+    store: Arc<StateStore>,
+    streams: Mutex<HashMap<String, watch::Receiver<u64>>>,
+}
+
+impl LiveStreams {
+    pub(crate) fn new(store: Arc<StateStore>) -> Self {
+        Self {
+            store,
+            streams: Mutex::default(),
+        }
+    }
+
+    /// Decides what a request for the stream under `key` does. The lock is held while the
+    /// store is read, so a stream is never started twice. A stream started now is kept for
+    /// `ttl` once it ends.
+    fn claim(
+        self: &Arc<Self>,
+        key: &str,
+        fingerprint: &str,
+        last_event_id: Option<u64>,
+        ttl: Duration,
+    ) -> Claim {
+        let mut streams = self.streams.lock().unwrap();
+        match read_record(&self.store, key) {
+            Some(record) if record.fingerprint != fingerprint => Claim::Reject(id_reused()),
+            Some(record) => {
+                let after = last_event_id.unwrap_or(0);
+                if after + 1 < record.first_event_id {
+                    return Claim::Reject(events_evicted(after));
+                }
+                let progress = streams.get(key).cloned();
+                Claim::Resume {
+                    record,
+                    after,
+                    progress,
+                }
+            }
+            // Started, but the upstream hasn't answered yet.
+            None if streams.contains_key(key) => Claim::Reject(stream_starting()),
+            None if last_event_id.is_some() => Claim::Reject(unknown_stream()),
+            None => {
+                let (progress, receiver) = watch::channel(0);
+                streams.insert(key.to_string(), receiver);
+                Claim::Start(StreamWriter {
+                    live: self.clone(),
+                    ttl,
+                    key: key.to_string(),
+                    record: None,
+                    sizes: VecDeque::new(),
+                    buffered_bytes: 0,
+                    progress,
+                })
+            }
+        }
+    }
+}
+
+/// Copies the events of an upstream stream into the `StateStore`, numbering them. Events
+/// are kept until the stream ends and for `resumable_stream_ttl_seconds` after that.
+/// Dropping the writer, whether the stream ended or never started, releases the id.
+struct StreamWriter {
+    live: Arc<LiveStreams>,
+    ttl: Duration,
+    key: String,
+    /// `None` until the upstream response has started.
+    record: Option<StreamRecord>,
+    /// Sizes of the buffered events, oldest first.
+    sizes: VecDeque<usize>,
+    buffered_bytes: usize,
+    progress: watch::Sender<u64>,
+}
+
+impl StreamWriter {
+    fn start(&mut self, fingerprint: String, headers: &HeaderMap) {
+        let headers = headers
+            .iter()
+            .filter(|(name, _)| *name != header::CONTENT_LENGTH)
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        self.record = Some(StreamRecord {
+            fingerprint,
+            headers,
+            first_event_id: 1,
+            last_event_id: 0,
+            complete: false,
+        });
+        self.save(None);
+    }
+
+    /// Reads `body` to the end, whether or not anyone is still following the stream.
+    async fn tee(mut self, body: Body) {
+        let mut body = body.into_data_stream();
+        let mut pending = Vec::new();
+        while let Some(Ok(chunk)) = body.next().await {
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.windows(2).position(|window| window == b"\n\n") {
+                let frame: Vec<u8> = pending.drain(..end + 2).collect();
+                self.push(&String::from_utf8_lossy(&frame));
+            }
+        }
+    }
+
+    fn push(&mut self, frame: &str) {
+        // Keep-alive comments carry nothing worth replaying.
+        if frame
+            .lines()
+            .all(|line| line.is_empty() || line.starts_with(':'))
+        {
+            return;
+        }
+        let Some(record) = self.record.as_mut() else {
+            return;
+        };
+        let store = &self.live.store;
+        let id = record.last_event_id + 1;
+        let event = format!("id: {id}\n{frame}");
+        self.sizes.push_back(event.len());
+        self.buffered_bytes += event.len();
+        store.set(&event_key(&self.key, id), Value::String(event), None);
+        record.last_event_id = id;
+        while self.buffered_bytes > MAX_BUFFERED_BYTES && self.sizes.len() > 1 {
+            if let Some(size) = self.sizes.pop_front() {
+                self.buffered_bytes -= size;
+            }
+            store.remove(&event_key(&self.key, record.first_event_id));
+            record.first_event_id += 1;
+        }
+        self.save(None);
+        self.progress.send_replace(id);
+    }
+
+    fn save(&self, ttl: Option<Duration>) {
+        let Some(record) = &self.record else {
+            return;
+        };
+        if let Ok(value) = serde_json::to_value(record) {
+            self.live.store.set(&self.key, value, ttl);
+        }
+    }
+}
+
+impl Drop for StreamWriter {
+    fn drop(&mut self) {
+        if let Some(record) = self.record.as_mut() {
+            record.complete = true;
+            let ttl = Some(self.ttl);
+            let store = &self.live.store;
+            for id in record.first_event_id..=record.last_event_id {
+                store.set_ttl(&event_key(&self.key, id), ttl);
+            }
+            self.save(ttl);
+        }
+        self.live.streams.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// The buffered events after `after`, then live ones until the stream ends. Ends early if
+/// the follower falls so far behind that its next event was evicted.
+fn follow(
+    store: Arc<StateStore>,
+    key: String,
+    after: u64,
+    mut progress: Option<watch::Receiver<u64>>,
+) -> Body {
+    let events = stream! {
+        let mut next = after + 1;
+        'follow: loop {
+            if let Some(progress) = progress.as_mut() {
+                progress.mark_unchanged();
+            }
+            let Some(record) = read_record(&store, &key) else {
+                break;
+            };
+            while next <= record.last_event_id {
+                let Some(Value::String(event)) = store.get(&event_key(&key, next)) else {
+                    break 'follow;
+                };
+                yield Ok::<_, Infallible>(Bytes::from(event));
+                next += 1;
+            }
+            if record.complete {
+                break;
+            }
+            match progress.as_mut() {
+                // The writer is gone; the final record tells whether anything is left.
+                Some(receiver) => {
+                    if receiver.changed().await.is_err() {
+                        progress = None;
+                    }
+                }
+                None => break,
+            }
+        }
+    };
+    Body::from_stream(events)
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"))
+}
+
+fn invalid_header(message: String) -> Response {
+    RequestValidationError {
+        status: StatusCode::BAD_REQUEST,
+        message,
+        param: None,
+    }
+    .into_response()
+}
+
+fn id_reused() -> RequestValidationError {
+    RequestValidationError {
+        status: StatusCode::UNPROCESSABLE_ENTITY,
+        message: "x-hub-stream-id was already used for a different request".to_string(),
+        param: None,
+    }
+}
+
+fn stream_starting() -> RequestValidationError {
+    RequestValidationError {
+        status: StatusCode::CONFLICT,
+        message: "The stream for this x-hub-stream-id is still starting; retry shortly".to_string(),
+        param: None,
+    }
+}
+
+fn unknown_stream() -> RequestValidationError {
+    RequestValidationError {
+        status: StatusCode::NOT_FOUND,
+        message: "No resumable stream has this x-hub-stream-id; it may have expired".to_string(),
+        param: None,
+    }
+}
+
+fn events_evicted(after: u64) -> RequestValidationError {
+    RequestValidationError {
+        status: StatusCode::GONE,
+        message: format!("The events after {after} are no longer buffered"),
+        param: None,
+    }
+}
+
+/// Makes streamed responses resumable for POST requests carrying `x-hub-stream-id`, per
+/// pipeline. The upstream stream is read to the end even if the client disconnects, and
+/// its events are numbered with SSE `id`s and buffered. A repeat of the request with the
+/// same id gets the events after its `Last-Event-ID`, or all of them without one, followed
+/// by live events while the stream is still running.
+pub async fn resume_streams(
+    State(scope): State<Arc<PipelineScope>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(stream_id) = request.headers().get(STREAM_ID_HEADER) else {
+        return next.run(request).await;
+    };
+    let stream_id = match stream_id.to_str() {
+        Ok(id) if !id.is_empty() && id.len() <= MAX_STREAM_ID_LENGTH => id.to_string(),
+        _ => {
+            return invalid_header(format!(
+                "x-hub-stream-id must be 1 to {MAX_STREAM_ID_LENGTH} visible ASCII characters"
+            ));
+        }
+    };
+    let last_event_id = match request.headers().get(LAST_EVENT_ID_HEADER) {
+        None => None,
+        Some(value) => match value.to_str().ok().and_then(|id| id.trim().parse().ok()) {
+            Some(id) => Some(id),
+            None => return invalid_header("Last-Event-ID must be an event id".to_string()),
+        },
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = match read_request_body(body, scope.settings.max_buffered_body_bytes).await {
+        Ok(body) => body,
+        Err(rejection) => return rejection,
+    };
+    if !is_stream_request(&body) {
+        return next.run(Request::from_parts(parts, Body::from(body))).await;
+    }
+    let fingerprint = request_fingerprint(parts.uri.path(), &body);
+    let key = format!("resumable:{}:{stream_id}", scope.name);
+    let live = &scope.services.live_streams;
+    let store = live.store.clone();
+
+    match live.claim(
+        &key,
+        &fingerprint,
+        last_event_id,
+        scope.settings.resumable_stream_ttl,
+    ) {
+        Claim::Reject(rejection) => rejection.into_response(),
+        Claim::Resume {
+            record,
+            after,
+            progress,
+        } => {
+            let mut response = Response::new(follow(store, key, after, progress));
+            for (name, value) in &record.headers {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::try_from(name.as_str()),
+                    HeaderValue::from_str(value),
+                ) {
+                    response.headers_mut().append(name, value);
+                }
+            }
+            response
+                .headers_mut()
+                .insert(RESUMED_HEADER, HeaderValue::from_static("true"));
+            response
+        }
+        Claim::Start(mut writer) => {
+            let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+            if !response.status().is_success() || !is_event_stream(response.headers()) {
+                return response;
+            }
+            let (parts, body) = response.into_parts();
+            writer.start(fingerprint, &parts.headers);
+            let progress = writer.progress.subscribe();
+            tokio::spawn(writer.tee(body));
+            Response::from_parts(parts, follow(store, key, 0, Some(progress)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(300);
+
+    fn live_streams() -> Arc<LiveStreams> {
+        Arc::new(LiveStreams::new(Arc::new(StateStore::default())))
+    }
+
+    fn writer(live: &Arc<LiveStreams>, key: &str) -> StreamWriter {
+        let Claim::Start(mut writer) = live.claim(key, "fingerprint", None, TTL) else {
+            panic!("stream {key} already exists");
+        };
+        writer.start("fingerprint".to_string(), &HeaderMap::new());
+        writer
+    }
+
+    #[test]
+    fn test_events_are_numbered_and_comments_skipped() {
+        let live = live_streams();
+        let mut writer = writer(&live, "resumable:test:numbered");
+        writer.push("data: {\"a\":1}\n\n");
+        writer.push(":\n\n");
+        writer.push("event: message_stop\ndata: {}\n\n");
+        drop(writer);
+
+        let store = &live.store;
+        let record = read_record(store, "resumable:test:numbered").unwrap();
+        assert!(record.complete);
+        assert_eq!((record.first_event_id, record.last_event_id), (1, 2));
+        assert_eq!(
+            store.get("resumable:test:numbered:2"),
+            Some(Value::String(
+                "id: 2\nevent: message_stop\ndata: {}\n\n".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_old_events_are_evicted_past_the_size_limit() {
+        let live = live_streams();
+        let mut writer = writer(&live, "resumable:test:evicted");
+        let frame = format!("data: {}\n\n", "x".repeat(MAX_BUFFERED_BYTES / 4));
+        for _ in 0..4 {
+            writer.push(&frame);
+        }
+        let record = writer.record.as_ref().unwrap();
+        assert_eq!((record.first_event_id, record.last_event_id), (2, 4));
+        assert!(writer.buffered_bytes <= MAX_BUFFERED_BYTES);
+        assert_eq!(live.store.get("resumable:test:evicted:1"), None);
+        drop(writer);
+
+        let Claim::Reject(rejection) =
+            live.claim("resumable:test:evicted", "fingerprint", Some(0), TTL)
+        else {
+            panic!("resuming before the oldest buffered event should fail");
+        };
+        assert_eq!(rejection.status, StatusCode::GONE);
+    }
+
+    #[test]
+    fn test_a_stream_id_is_tied_to_its_request() {
+        let live = live_streams();
+        drop(writer(&live, "resumable:test:reused"));
+        let Claim::Reject(rejection) = live.claim("resumable:test:reused", "other", None, TTL)
+        else {
+            panic!("a different request shouldn't resume the stream");
+        };
+        assert_eq!(rejection.status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
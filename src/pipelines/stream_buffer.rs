@@ -0,0 +1,99 @@
+//! Bounded read-ahead between a provider's stream and the client. A slow client slows the
+//! upstream read down instead of growing the gateway's memory.
+
+use crate::metrics::counter;
+use crate::models::streaming::ChatCompletionChunk;
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use reqwest_streams::error::{StreamBodyError, StreamBodyKind};
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::mpsc;
+
+/// Counts streamed responses ended because their buffered chunks outgrew
+/// `stream_buffer_max_bytes`.
+pub const STREAM_BUFFER_OVERFLOWS_METRIC: &str = "hub_stream_buffer_overflows_total";
+
+pub type ChunkStream = BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>>;
+
+/// Reads `chunks` on a task of its own into a channel holding up to `capacity` of them.
+/// While the channel is full the upstream isn't read, so backpressure from the client
+/// reaches the provider's connection. If the waiting chunks would take more than
+/// `max_bytes` as JSON, the stream ends with an error instead. The task stops reading when
+/// the returned stream is dropped, e.g. because the client went away.
+pub fn buffer_stream(chunks: ChunkStream, capacity: usize, max_bytes: usize) -> ChunkStream {
+    let (sender, mut receiver) = mpsc::channel(capacity.max(1));
+    let buffered = Arc::new(AtomicUsize::new(0));
+    let read_ahead = buffered.clone();
+    tokio::spawn(async move {
+        let mut chunks = chunks;
+        while let Some(item) = chunks.next().await {
+            let size = item.as_ref().map_or(0, json_size);
+            if read_ahead.fetch_add(size, Ordering::AcqRel) + size > max_bytes {
+                counter!(STREAM_BUFFER_OVERFLOWS_METRIC).increment(1);
+                let _ = sender.send((0, Err(overflow_error(max_bytes)))).await;
+                return;
+            }
+            if sender.send((size, item)).await.is_err() {
+                return;
+            }
+        }
+    });
+    Box::pin(async_stream::stream! {
+        while let Some((size, item)) = receiver.recv().await {
+            buffered.fetch_sub(size, Ordering::AcqRel);
+            yield item;
+        }
+    })
+}
+
+/// Collects all of `chunks`, for features that need the whole response such as JSON
+/// repair. Stops with an error once they take more than `max_bytes` as JSON.
+pub async fn collect_bounded(
+    mut chunks: ChunkStream,
+    max_bytes: usize,
+) -> Vec<Result<ChatCompletionChunk, StreamBodyError>> {
+    let mut collected = Vec::new();
+    let mut total = 0;
+    while let Some(item) = chunks.next().await {
+        total += item.as_ref().map_or(0, json_size);
+        if total > max_bytes {
+            counter!(STREAM_BUFFER_OVERFLOWS_METRIC).increment(1);
+            collected.push(Err(overflow_error(max_bytes)));
+            break;
+        }
+        collected.push(item);
+    }
+    collected
+}
+
+fn overflow_error(max_bytes: usize) -> StreamBodyError {
+    StreamBodyError::new(
+        StreamBodyKind::MaxLenReachedError,
+        None,
+        Some(format!(
+            "Streamed response exceeded the gateway's buffer of {max_bytes} bytes"
+        )),
+    )
+}
+
+/// The size of `chunk` serialized, without allocating it.
+fn json_size(chunk: &ChatCompletionChunk) -> usize {
+    let mut counter = ByteCounter(0);
+    let _ = serde_json::to_writer(&mut counter, chunk);
+    counter.0
+}
+
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
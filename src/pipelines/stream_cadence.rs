@@ -0,0 +1,318 @@
+//! How fast streamed responses arrive after their first chunk. A provider can send its first
+//! token quickly and then trickle, which the TTFB and total duration metrics hide.
+
+use crate::metrics::{counter, histogram};
+use crate::models::streaming::ChatCompletionChunk;
+use crate::pipelines::token_count::CHARS_PER_TOKEN;
+use crate::types::SlowStreamConfig;
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use reqwest_streams::error::StreamBodyError;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Time between consecutive chunks of a stream, by provider and model.
+pub const INTERCHUNK_METRIC: &str = "hub_stream_interchunk_seconds";
+/// Output tokens per second of each completed stream, by provider and model.
+pub const TOKENS_PER_SECOND_METRIC: &str = "hub_stream_tokens_per_second";
+/// Streams whose output slowed below `general.slow_stream`, by provider and model.
+pub const SLOW_STREAMS_METRIC: &str = "hub_slow_streams_total";
+
+type ChunkStream = BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>>;
+
+/// When a stream counts as slow: once its output over the last `window` stays below
+/// `min_tokens_per_second`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlowStreamThreshold {
+    pub min_tokens_per_second: f64,
+    pub window: Duration,
+}
+
+impl From<&SlowStreamConfig> for SlowStreamThreshold {
+    fn from(config: &SlowStreamConfig) -> Self {
+        Self {
+            min_tokens_per_second: config.min_tokens_per_second,
+            window: Duration::from_secs(config.window_seconds),
+        }
+    }
+}
+
+/// Rough output tokens of a chunk, for the rate mid-stream; providers only report usage
+/// with the last chunk.
+fn estimated_tokens(chunk: &ChatCompletionChunk) -> usize {
+    let chars: usize = chunk
+        .choices
+        .iter()
+        .map(|choice| {
+            let delta = &choice.delta;
+            let arguments: usize = delta
+                .tool_calls
+                .iter()
+                .flatten()
+                .filter_map(|call| call.function.as_ref()?.arguments.as_ref())
+                .map(String::len)
+                .sum();
+            delta.content.as_ref().map_or(0, String::len)
+                + delta.reasoning.as_ref().map_or(0, String::len)
+                + arguments
+        })
+        .sum();
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+/// The arrival of a stream's chunks.
+#[derive(Debug)]
+struct Cadence {
+    threshold: Option<SlowStreamThreshold>,
+    last_chunk: Option<Instant>,
+    first_output: Option<Instant>,
+    last_output: Option<Instant>,
+    estimated_tokens: usize,
+    /// Chunks with output received within the last `window`, with their tokens.
+    recent: VecDeque<(Instant, usize)>,
+    slow: bool,
+}
+
+impl Cadence {
+    fn new(threshold: Option<SlowStreamThreshold>) -> Self {
+        Self {
+            threshold,
+            last_chunk: None,
+            first_output: None,
+            last_output: None,
+            estimated_tokens: 0,
+            recent: VecDeque::new(),
+            slow: false,
+        }
+    }
+
+    /// Records a chunk of `tokens` output tokens received at `now`. Returns the time since
+    /// the previous chunk, and the rolling rate when the stream has just become slow.
+    fn record(&mut self, now: Instant, tokens: usize) -> (Option<Duration>, Option<f64>) {
+        let gap = self
+            .last_chunk
+            .replace(now)
+            .map(|last| now.saturating_duration_since(last));
+        if tokens > 0 {
+            self.first_output.get_or_insert(now);
+            self.last_output = Some(now);
+            self.estimated_tokens += tokens;
+            self.recent.push_back((now, tokens));
+        }
+        (gap, self.check_slow(now))
+    }
+
+    /// The rolling rate, once it fell under the threshold over a whole window of output.
+    /// Reported once per stream.
+    fn check_slow(&mut self, now: Instant) -> Option<f64> {
+        let threshold = self.threshold?;
+        let first_output = self.first_output?;
+        if self.slow || now.saturating_duration_since(first_output) < threshold.window {
+            return None;
+        }
+        while self
+            .recent
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) >= threshold.window)
+        {
+            self.recent.pop_front();
+        }
+        let tokens: usize = self.recent.iter().map(|(_, tokens)| tokens).sum();
+        let rate = tokens as f64 / threshold.window.as_secs_f64();
+        if rate >= threshold.min_tokens_per_second {
+            return None;
+        }
+        self.slow = true;
+        Some(rate)
+    }
+
+    /// Output tokens per second from the first output to the last, counting the tokens the
+    /// provider reported when it did.
+    fn tokens_per_second(&self, reported_tokens: Option<u32>) -> Option<f64> {
+        let duration = self
+            .last_output?
+            .saturating_duration_since(self.first_output?)
+            .as_secs_f64();
+        if duration <= 0.0 {
+            return None;
+        }
+        let tokens = reported_tokens.map_or(self.estimated_tokens as f64, f64::from);
+        Some(tokens / duration)
+    }
+}
+
+/// Records the inter-chunk latency of `chunks` and, once they end, their output tokens per
+/// second. With a `threshold`, a stream slowing below it mid-stream is logged and counted.
+/// Rates are checked as chunks arrive, so a stalled stream is caught by its next chunk.
+pub fn observe_cadence(
+    mut chunks: ChunkStream,
+    provider: String,
+    model: String,
+    threshold: Option<SlowStreamThreshold>,
+) -> ChunkStream {
+    Box::pin(async_stream::stream! {
+        let mut cadence = Cadence::new(threshold);
+        let mut reported_tokens = None;
+        while let Some(item) = chunks.next().await {
+            if let Ok(chunk) = &item {
+                if let Some(usage) = &chunk.usage {
+                    reported_tokens = Some(usage.completion_tokens);
+                }
+                let (gap, slow_rate) = cadence.record(Instant::now(), estimated_tokens(chunk));
+                if let Some(gap) = gap {
+                    histogram!(
+                        INTERCHUNK_METRIC,
+                        "provider" => provider.clone(),
+                        "model" => model.clone()
+                    )
+                    .record(gap.as_secs_f64());
+                }
+                if let (Some(rate), Some(threshold)) = (slow_rate, threshold) {
+                    tracing::warn!(
+                        "Stream of model '{model}' from {provider} slowed to {rate:.1} tokens/s \
+                         over the last {}s, below {} tokens/s",
+                        threshold.window.as_secs_f64(),
+                        threshold.min_tokens_per_second
+                    );
+                    counter!(
+                        SLOW_STREAMS_METRIC,
+                        "provider" => provider.clone(),
+                        "model" => model.clone()
+                    )
+                    .increment(1);
+                }
+            }
+            yield item;
+        }
+        if let Some(rate) = cadence.tokens_per_second(reported_tokens) {
+            histogram!(TOKENS_PER_SECOND_METRIC, "provider" => provider, "model" => model)
+                .record(rate);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chunk(content: &str) -> ChatCompletionChunk {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{"index": 0, "delta": {"content": content}}]
+        }))
+        .unwrap()
+    }
+
+    fn threshold(min_tokens_per_second: f64, window_ms: u64) -> SlowStreamThreshold {
+        SlowStreamThreshold {
+            min_tokens_per_second,
+            window: Duration::from_millis(window_ms),
+        }
+    }
+
+    #[test]
+    fn test_tokens_are_estimated_from_every_kind_of_output() {
+        let mut chunk: ChatCompletionChunk = serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{"index": 0, "delta": {
+                "content": "12345678",
+                "reasoning": "1234",
+                "tool_calls": [{"index": 0, "function": {"arguments": "{\"a\":1}"}}]
+            }}]
+        }))
+        .unwrap();
+        assert_eq!(estimated_tokens(&chunk), 5);
+
+        chunk.choices.clear();
+        assert_eq!(estimated_tokens(&chunk), 0);
+    }
+
+    #[test]
+    fn test_stream_becomes_slow_once_a_whole_window_is_under_the_threshold() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        // 10 tokens/s needed over any second.
+        let mut cadence = Cadence::new(Some(threshold(10.0, 1000)));
+
+        // 20 tokens/s for two seconds, then one token every 200ms (5 tokens/s).
+        let mut slow_at = None;
+        let mut times: Vec<u64> = (0..40).map(|i| i * 50).collect();
+        times.extend((1..=10).map(|i| 2000 + i * 200));
+        for ms in times {
+            let (_, slow_rate) = cadence.record(at(ms), 1);
+            if let Some(rate) = slow_rate {
+                assert!(slow_at.is_none(), "reported twice");
+                assert!(rate < 10.0);
+                slow_at = Some(ms);
+            }
+        }
+        // Fast chunks still in the window keep the rate up until 2.8s.
+        assert_eq!(slow_at, Some(2800));
+    }
+
+    #[test]
+    fn test_gaps_and_tokens_per_second() {
+        let start = Instant::now();
+        let mut cadence = Cadence::new(None);
+        assert_eq!(cadence.record(start, 0), (None, None));
+        let (gap, _) = cadence.record(start + Duration::from_millis(100), 10);
+        assert_eq!(gap, Some(Duration::from_millis(100)));
+        cadence.record(start + Duration::from_millis(600), 10);
+        // The role-only first chunk doesn't count towards the generation time.
+        assert_eq!(cadence.tokens_per_second(None), Some(40.0));
+        assert_eq!(cadence.tokens_per_second(Some(25)), Some(50.0));
+
+        let mut cadence = Cadence::new(None);
+        cadence.record(start, 10);
+        assert_eq!(cadence.tokens_per_second(None), None);
+    }
+
+    /// Streams `count` one-token chunks, the first `fast` of them 10ms apart and the others
+    /// 60ms apart, through `observe_cadence`.
+    async fn stream_chunks(model: &str, count: usize, fast: usize) {
+        let delays = (0..count).map(move |i| if i < fast { 10 } else { 60 });
+        let chunks = futures::stream::iter(delays)
+            .then(|delay| async move {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                Ok(chunk("word"))
+            })
+            .boxed();
+        let stream = observe_cadence(
+            chunks,
+            "openai".to_string(),
+            model.to_string(),
+            Some(threshold(30.0, 150)),
+        );
+        assert_eq!(stream.count().await, count);
+    }
+
+    #[tokio::test]
+    async fn test_stream_slowing_down_halfway_is_detected() {
+        let metrics = crate::metrics::install();
+        // 100 tokens/s, then about 17.
+        stream_chunks("cadence-slowing", 20, 10).await;
+        stream_chunks("cadence-steady", 20, 20).await;
+
+        let rendered = metrics.render();
+        assert!(
+            rendered.contains(
+                "hub_slow_streams_total{provider=\"openai\",model=\"cadence-slowing\"} 1"
+            ),
+            "{rendered}"
+        );
+        assert!(
+            !rendered
+                .contains("hub_slow_streams_total{provider=\"openai\",model=\"cadence-steady\"}")
+        );
+        for model in ["cadence-slowing", "cadence-steady"] {
+            let labels = format!("{{provider=\"openai\",model=\"{model}\"}}");
+            assert!(rendered.contains(&format!("hub_stream_interchunk_seconds_count{labels} 19")));
+            assert!(rendered.contains(&format!("hub_stream_tokens_per_second_count{labels} 1")));
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use axum::http::{HeaderMap, HeaderName, StatusCode};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::models::chat::ChatCompletionRequest;
+use crate::models::content::{ChatCompletionMessage, ChatMessageContent, is_instruction_role};
+use crate::pipelines::request_validation::RequestValidationError;
+use crate::types::SystemPrompt;
+
+/// JSON object of template variable values, read when the pipeline allows it.
+pub const TEMPLATE_VARS_HEADER: HeaderName = HeaderName::from_static("x-hub-template-vars");
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Variable(String),
+}
+
+/// A prompt template from `prompt_templates`, split into text and `{{variable}}`
+/// placeholders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    segments: Vec<Segment>,
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+impl PromptTemplate {
+    /// Parses `template`. Whitespace inside the braces is ignored, so `{{ brand }}` and
+    /// `{{brand}}` are the same variable.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            let placeholder = &rest[start + 2..];
+            let Some(end) = placeholder.find("}}") else {
+                return Err(format!(
+                    "'{{{{' at byte {} is never closed",
+                    template.len() - rest.len() + start
+                ));
+            };
+            let name = placeholder[..end].trim();
+            if !is_variable_name(name) {
+                return Err(format!(
+                    "'{{{{{}}}}}' is not a valid variable; names use letters, digits, '_', \
+                     '-' and '.'",
+                    &placeholder[..end]
+                ));
+            }
+            segments.push(Segment::Variable(name.to_string()));
+            rest = &placeholder[end + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+        Ok(Self { segments })
+    }
+
+    /// The template's variables, in order of first use.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables: Vec<&str> = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Variable(name) = segment {
+                if !variables.contains(&name.as_str()) {
+                    variables.push(name);
+                }
+            }
+        }
+        variables
+    }
+
+    /// Fills in every placeholder, failing with the name of the first variable `value`
+    /// has nothing for.
+    pub fn render<'a>(&self, value: impl Fn(&str) -> Option<&'a str>) -> Result<String, String> {
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Variable(name) => {
+                    rendered.push_str(value(name).ok_or_else(|| name.clone())?);
+                }
+            }
+        }
+        Ok(rendered)
+    }
+}
+
+/// A pipeline's `system-prompt` plugin with its template parsed.
+#[derive(Debug)]
+pub struct SystemPromptRenderer {
+    name: String,
+    template: PromptTemplate,
+    variables: BTreeMap<String, String>,
+    allow_header_variables: bool,
+}
+
+impl SystemPromptRenderer {
+    pub fn new(
+        settings: &SystemPrompt,
+        prompt_templates: &BTreeMap<String, String>,
+    ) -> Result<Self, String> {
+        let name = &settings.system_prompt_template;
+        let template = prompt_templates
+            .get(name)
+            .ok_or_else(|| format!("prompt template '{name}' is not defined"))?;
+        Ok(Self {
+            name: name.clone(),
+            template: PromptTemplate::parse(template)
+                .map_err(|e| format!("prompt template '{name}' is invalid: {e}"))?,
+            variables: settings.variables.clone(),
+            allow_header_variables: settings.allow_header_variables,
+        })
+    }
+
+    /// Renders the prompt for a request and prepends it to the request's messages. The
+    /// pipeline's values take precedence over the header's.
+    pub fn apply(
+        &self,
+        headers: &HeaderMap,
+        payload: &mut ChatCompletionRequest,
+    ) -> Result<(), RequestValidationError> {
+        let header_variables = header_variables(headers, self.allow_header_variables)?;
+        let prompt = self
+            .template
+            .render(|name| {
+                self.variables
+                    .get(name)
+                    .or_else(|| header_variables.get(name))
+                    .map(String::as_str)
+            })
+            .map_err(|variable| {
+                bad_request(format!(
+                    "System prompt template '{}' needs a value for variable '{variable}'; \
+                     send it in {TEMPLATE_VARS_HEADER}",
+                    self.name
+                ))
+            })?;
+
+        // Requests written for newer OpenAI models give instructions as `developer`.
+        let role = payload
+            .messages
+            .iter()
+            .map(|message| message.role.as_str())
+            .find(|role| is_instruction_role(role))
+            .unwrap_or("system")
+            .to_string();
+        payload.messages.insert(
+            0,
+            ChatCompletionMessage {
+                role,
+                content: Some(ChatMessageContent::String(prompt)),
+                name: None,
+                tool_calls: None,
+                tool_call_id: None,
+                refusal: None,
+                annotations: None,
+            },
+        );
+        Ok(())
+    }
+}
+
+fn bad_request(message: String) -> RequestValidationError {
+    RequestValidationError {
+        status: StatusCode::BAD_REQUEST,
+        message,
+        param: None,
+    }
+}
+
+/// Variable values sent in `x-hub-template-vars`: strings, numbers or booleans.
+fn header_variables(
+    headers: &HeaderMap,
+    allowed: bool,
+) -> Result<HashMap<String, String>, RequestValidationError> {
+    let Some(value) = headers.get(TEMPLATE_VARS_HEADER) else {
+        return Ok(HashMap::new());
+    };
+    if !allowed {
+        return Err(RequestValidationError {
+            status: StatusCode::FORBIDDEN,
+            message: format!(
+                "{TEMPLATE_VARS_HEADER} requires 'allow_header_variables' on the pipeline's \
+                 system-prompt plugin"
+            ),
+            param: None,
+        });
+    }
+    let object = match serde_json::from_slice(value.as_bytes()) {
+        Ok(Value::Object(object)) => object,
+        _ => {
+            return Err(bad_request(format!(
+                "{TEMPLATE_VARS_HEADER} must be a JSON object"
+            )));
+        }
+    };
+    object
+        .into_iter()
+        .map(|(name, value)| match value {
+            Value::String(text) => Ok((name, text)),
+            Value::Number(_) | Value::Bool(_) => Ok((name, value.to_string())),
+            _ => Err(bad_request(format!(
+                "{TEMPLATE_VARS_HEADER} value of '{name}' must be a string, number or boolean"
+            ))),
+        })
+        .collect()
+}
+
+/// Checks a `system-prompt` plugin against the configured templates. Unless requests can
+/// send values, the pipeline must set every variable of its template.
+pub fn validate_system_prompt(
+    settings: &SystemPrompt,
+    prompt_templates: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let renderer = SystemPromptRenderer::new(settings, prompt_templates)?;
+    if renderer.allow_header_variables {
+        return Ok(());
+    }
+    let missing: Vec<&str> = renderer
+        .template
+        .variables()
+        .into_iter()
+        .filter(|variable| !settings.variables.contains_key(*variable))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "variables has no value for {}, and allow_header_variables is off",
+            missing.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    fn templates() -> BTreeMap<String, String> {
+        BTreeMap::from([(
+            "support".to_string(),
+            "You are {{brand}}'s assistant. {{ policy }} Ask {{brand}} staff if unsure."
+                .to_string(),
+        )])
+    }
+
+    fn renderer(variables: &[(&str, &str)], allow_header_variables: bool) -> SystemPromptRenderer {
+        let settings = SystemPrompt {
+            system_prompt_template: "support".to_string(),
+            variables: variables
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            allow_header_variables,
+        };
+        SystemPromptRenderer::new(&settings, &templates()).unwrap()
+    }
+
+    fn request(messages: Value) -> ChatCompletionRequest {
+        serde_json::from_value(json!({"model": "gpt-4o", "messages": messages})).unwrap()
+    }
+
+    fn header(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(TEMPLATE_VARS_HEADER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    fn first_message(payload: &ChatCompletionRequest) -> (&str, String) {
+        let message = &payload.messages[0];
+        let Some(ChatMessageContent::String(text)) = &message.content else {
+            panic!("expected text content");
+        };
+        (message.role.as_str(), text.clone())
+    }
+
+    #[test]
+    fn test_parse() {
+        let template = PromptTemplate::parse("Hi {{name}}, {{ name }} and {{team.lead}}!").unwrap();
+        assert_eq!(template.variables(), ["name", "team.lead"]);
+        assert_eq!(
+            PromptTemplate::parse("no placeholders")
+                .unwrap()
+                .variables(),
+            Vec::<&str>::new()
+        );
+        // A lone closing brace pair is plain text.
+        assert!(PromptTemplate::parse("JSON like {\"a\": {}}").is_ok());
+
+        let unclosed = PromptTemplate::parse("Hi {{name").unwrap_err();
+        assert!(unclosed.contains("never closed"), "{unclosed}");
+        let invalid = PromptTemplate::parse("Hi {{first name}}").unwrap_err();
+        assert!(invalid.contains("'{{first name}}'"), "{invalid}");
+        assert!(PromptTemplate::parse("Hi {{}}").is_err());
+    }
+
+    #[test]
+    fn test_renders_and_prepends_system_message() {
+        let renderer = renderer(&[("brand", "Acme"), ("policy", "Be brief.")], false);
+        let mut payload = request(json!([{"role": "user", "content": "hi"}]));
+        renderer.apply(&HeaderMap::new(), &mut payload).unwrap();
+
+        assert_eq!(payload.messages.len(), 2);
+        assert_eq!(
+            first_message(&payload),
+            (
+                "system",
+                "You are Acme's assistant. Be brief. Ask Acme staff if unsure.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_follows_the_request_instruction_role() {
+        let renderer = renderer(&[("brand", "Acme"), ("policy", "")], false);
+        let mut payload = request(json!([
+            {"role": "developer", "content": "Answer in French."},
+            {"role": "user", "content": "hi"}
+        ]));
+        renderer.apply(&HeaderMap::new(), &mut payload).unwrap();
+
+        assert_eq!(payload.messages.len(), 3);
+        assert_eq!(first_message(&payload).0, "developer");
+        assert_eq!(payload.messages[1].role, "developer");
+    }
+
+    #[test]
+    fn test_missing_variable_is_named() {
+        let renderer = renderer(&[("brand", "Acme")], false);
+        let mut payload = request(json!([{"role": "user", "content": "hi"}]));
+        let rejection = renderer.apply(&HeaderMap::new(), &mut payload).unwrap_err();
+
+        assert_eq!(rejection.status, StatusCode::BAD_REQUEST);
+        assert!(
+            rejection.message.contains("variable 'policy'"),
+            "{}",
+            rejection.message
+        );
+        assert_eq!(payload.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_header_fills_variables_the_pipeline_leaves_out() {
+        let renderer = renderer(&[("brand", "Acme")], true);
+        let mut payload = request(json!([{"role": "user", "content": "hi"}]));
+        let headers = header(r#"{"brand": "Other", "policy": "Max 3 sentences."}"#);
+        renderer.apply(&headers, &mut payload).unwrap();
+
+        assert_eq!(
+            first_message(&payload).1,
+            "You are Acme's assistant. Max 3 sentences. Ask Acme staff if unsure."
+        );
+    }
+
+    #[test]
+    fn test_header_values() {
+        let renderer = renderer(&[("brand", "Acme")], true);
+        let mut payload = request(json!([{"role": "user", "content": "hi"}]));
+        renderer
+            .apply(&header(r#"{"policy": 42}"#), &mut payload)
+            .unwrap();
+        assert!(first_message(&payload).1.contains(" 42 "));
+
+        for invalid in [r#"["policy"]"#, "policy=short", r#"{"policy": {"a": 1}}"#] {
+            let mut payload = request(json!([{"role": "user", "content": "hi"}]));
+            let rejection = renderer.apply(&header(invalid), &mut payload).unwrap_err();
+            assert_eq!(rejection.status, StatusCode::BAD_REQUEST, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_header_needs_the_pipeline_to_allow_it() {
+        let renderer = renderer(&[("brand", "Acme"), ("policy", "")], false);
+        let mut payload = request(json!([{"role": "user", "content": "hi"}]));
+        let rejection = renderer
+            .apply(&header(r#"{"policy": "Be brief."}"#), &mut payload)
+            .unwrap_err();
+        assert_eq!(rejection.status, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_validate_system_prompt() {
+        let settings = |template: &str, variables: &[&str], allow_header_variables| SystemPrompt {
+            system_prompt_template: template.to_string(),
+            variables: variables
+                .iter()
+                .map(|name| (name.to_string(), "value".to_string()))
+                .collect(),
+            allow_header_variables,
+        };
+        let templates = templates();
+
+        assert!(
+            validate_system_prompt(
+                &settings("support", &["brand", "policy"], false),
+                &templates
+            )
+            .is_ok()
+        );
+        assert!(validate_system_prompt(&settings("support", &[], true), &templates).is_ok());
+
+        let missing = validate_system_prompt(&settings("support", &["brand"], false), &templates)
+            .unwrap_err();
+        assert!(missing.contains("policy"), "{missing}");
+        let unknown =
+            validate_system_prompt(&settings("sales", &[], true), &templates).unwrap_err();
+        assert!(unknown.contains("'sales' is not defined"), "{unknown}");
+
+        let broken = BTreeMap::from([("support".to_string(), "Hi {{brand".to_string())]);
+        assert!(validate_system_prompt(&settings("support", &[], true), &broken).is_err());
+    }
+}
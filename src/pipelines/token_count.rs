@@ -0,0 +1,203 @@
+use crate::ai_models::instance::ModelInstance;
+use crate::ai_models::params::{apply_chat_defaults, checked_context_window};
+use crate::models::chat::ChatCompletionRequest;
+use crate::models::messages::CountTokensRequest;
+use crate::pipelines::pipeline::{PipelineRunner, inject_model_key_header};
+use crate::pipelines::request_validation::{
+    RequestValidationError, ValidateRequest, ValidatedJson,
+};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Longest a provider count may take before the estimate is used instead.
+const COUNT_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a provider count is reused for an identical prompt.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+const CACHE_CAPACITY: usize = 1024;
+/// Rough characters per token of English text and JSON across the major tokenizers.
+pub(crate) const CHARS_PER_TOKEN: usize = 4;
+
+/// Rough input token count for providers without a counting endpoint. Counts the serialized
+/// prompt, so it errs high rather than low.
+pub fn estimate_tokens(payload: &ChatCompletionRequest) -> u32 {
+    let prompt = serde_json::to_string(&(&payload.messages, &payload.tools, &payload.tool_choice))
+        .unwrap_or_default();
+    u32::try_from(prompt.len().div_ceil(CHARS_PER_TOKEN)).unwrap_or(u32::MAX)
+}
+
+/// Counts prompt tokens with the provider's tokenizer when it has one, falling back to
+/// `estimate_tokens` when it doesn't, fails or is slow. Provider counts are cached briefly
+/// so retries and pre-flight checks of the same prompt don't call upstream again.
+#[derive(Default)]
+pub struct TokenCounter {
+    cache: Mutex<HashMap<[u8; 32], (u32, Instant)>>,
+}
+
+impl TokenCounter {
+    /// Counter shared by every pipeline in the process.
+    pub fn global() -> &'static TokenCounter {
+        static COUNTER: OnceLock<TokenCounter> = OnceLock::new();
+        COUNTER.get_or_init(Default::default)
+    }
+
+    pub async fn count(&self, model: &ModelInstance, payload: &ChatCompletionRequest) -> u32 {
+        let key = cache_key(&model.name, payload);
+        if let Some(tokens) = self.cached(&key) {
+            return tokens;
+        }
+        match tokio::time::timeout(COUNT_TIMEOUT, model.count_tokens(payload.clone())).await {
+            Ok(Ok(tokens)) => {
+                self.insert(key, tokens);
+                tokens
+            }
+            Ok(Err(StatusCode::NOT_IMPLEMENTED)) => estimate_tokens(payload),
+            Ok(Err(status)) => {
+                tracing::warn!(
+                    "Token count for model '{}' failed with {status}, using an estimate",
+                    model.name
+                );
+                estimate_tokens(payload)
+            }
+            Err(_) => {
+                tracing::warn!(
+                    "Token count for model '{}' timed out, using an estimate",
+                    model.name
+                );
+                estimate_tokens(payload)
+            }
+        }
+    }
+
+    fn cached(&self, key: &[u8; 32]) -> Option<u32> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(key)
+            .filter(|(_, at)| at.elapsed() < CACHE_TTL)
+            .map(|(tokens, _)| *tokens)
+    }
+
+    fn insert(&self, key: [u8; 32], tokens: u32) {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= CACHE_CAPACITY {
+            cache.retain(|_, (_, at)| at.elapsed() < CACHE_TTL);
+            if cache.len() >= CACHE_CAPACITY {
+                cache.clear();
+            }
+        }
+        cache.insert(key, (tokens, Instant::now()));
+    }
+}
+
+/// Identifies a prompt for a model. Sampling fields don't change the count, so they're left out.
+fn cache_key(model_key: &str, payload: &ChatCompletionRequest) -> [u8; 32] {
+    let prompt = serde_json::to_vec(&(&payload.messages, &payload.tools, &payload.tool_choice))
+        .unwrap_or_default();
+    Sha256::new()
+        .chain_update(model_key.as_bytes())
+        .chain_update([0])
+        .chain_update(prompt)
+        .finalize()
+        .into()
+}
+
+/// Pre-flight check for models with `context_window_check` set: rejects requests whose
+/// prompt plus `max_tokens` can't fit in the model's `context_window`.
+pub async fn check_context_window(
+    model: &ModelInstance,
+    payload: &ChatCompletionRequest,
+) -> Option<RequestValidationError> {
+    let context_window = checked_context_window(&model.config.params)?;
+    let mut request = payload.clone();
+    apply_chat_defaults(&model.config.params, &mut request);
+    let max_tokens = request
+        .max_completion_tokens
+        .or(request.max_tokens)
+        .unwrap_or(0);
+    let input_tokens = TokenCounter::global().count(model, payload).await;
+    (input_tokens.saturating_add(max_tokens) > context_window).then(|| {
+        RequestValidationError::context_window_exceeded(
+            &model.name,
+            input_tokens,
+            max_tokens,
+            context_window,
+        )
+    })
+}
+
+/// Anthropic-compatible `POST /messages/count_tokens`. Answers with the selected model's
+/// provider count, or an estimate when the provider can't count.
+pub async fn count_tokens(
+    State(runner): State<Arc<PipelineRunner>>,
+    ValidatedJson(request): ValidatedJson<CountTokensRequest>,
+) -> Result<Response, StatusCode> {
+    let payload = ChatCompletionRequest::from(request);
+    if let Err(rejection) = payload.validate() {
+        return Ok(rejection.into_response());
+    }
+
+    let model_registry = &runner.model_registry;
+    let model_keys = &runner.available_models;
+    let allow_dynamic_models = runner.allow_dynamic_models;
+    let Some(model) = model_registry.route(&payload.model, model_keys, allow_dynamic_models) else {
+        if let Some(maintenance) =
+            model_registry.maintenance(&payload.model, model_keys, allow_dynamic_models)
+        {
+            return Ok(maintenance.into_response());
+        }
+        tracing::error!("No matching model found for: {}", payload.model);
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    let input_tokens = TokenCounter::global().count(&model, &payload).await;
+    let mut response = Json(json!({ "input_tokens": input_tokens })).into_response();
+    inject_model_key_header(&mut response, &model.name);
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(content: &str) -> ChatCompletionRequest {
+        serde_json::from_value(json!({
+            "model": "claude",
+            "messages": [{"role": "user", "content": content}]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_estimate_grows_with_the_prompt() {
+        let short = estimate_tokens(&request("hi"));
+        let long = estimate_tokens(&request(&"hello world ".repeat(100)));
+        assert!(short > 0);
+        assert!(long >= 300, "{long}");
+        assert!(long > short);
+    }
+
+    #[test]
+    fn test_cache_key_ignores_sampling_fields() {
+        let mut sampled = request("hi");
+        sampled.temperature = Some(0.5);
+        sampled.max_tokens = Some(10);
+        assert_eq!(
+            cache_key("claude", &request("hi")),
+            cache_key("claude", &sampled)
+        );
+        assert_ne!(
+            cache_key("claude", &request("hi")),
+            cache_key("other", &request("hi"))
+        );
+        assert_ne!(
+            cache_key("claude", &request("hi")),
+            cache_key("claude", &request("bye"))
+        );
+    }
+}
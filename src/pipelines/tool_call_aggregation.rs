@@ -0,0 +1,378 @@
+use crate::models::streaming::{ChatCompletionChunk, Choice, ChoiceDelta};
+use crate::models::tool_calls::{ChatMessageToolCall, ChoiceDeltaToolCall, FunctionCall};
+use crate::pipelines::request_validation::RequestValidationError;
+use async_stream::stream;
+use axum::http::{HeaderMap, StatusCode};
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use reqwest_streams::error::StreamBodyError;
+use serde_json::de::IgnoredAny;
+use std::collections::BTreeMap;
+
+/// Emits each streamed tool call as a single chunk instead of argument fragments.
+pub const AGGREGATE_TOOL_CALLS_HEADER: &str = "x-hub-aggregate-tool-calls";
+
+/// Reads `x-hub-aggregate-tool-calls`, falling back to the pipeline's `stream-options`.
+pub fn aggregate_tool_calls_requested(
+    headers: &HeaderMap,
+    pipeline_default: bool,
+) -> Result<bool, RequestValidationError> {
+    let Some(value) = headers.get(AGGREGATE_TOOL_CALLS_HEADER) else {
+        return Ok(pipeline_default);
+    };
+    match value
+        .to_str()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(RequestValidationError {
+            status: StatusCode::BAD_REQUEST,
+            message: format!("{AGGREGATE_TOOL_CALLS_HEADER} must be 'true' or 'false'"),
+            param: None,
+        }),
+    }
+}
+
+/// Buffers tool call fragments while text and other deltas pass through.
+///
+/// A call is emitted once the next call of its choice starts and its arguments parse as
+/// JSON, or when the choice finishes, whichever comes first. Calls still open when the
+/// stream ends are emitted then.
+#[derive(Debug, Default)]
+struct ToolCallAggregator {
+    /// Calls still receiving fragments, keyed by choice index and tool call index.
+    pending: BTreeMap<(u32, u32), ChatMessageToolCall>,
+    /// The last chunk's id, model and timestamps, reused for the chunks of flushed calls.
+    template: Option<ChatCompletionChunk>,
+}
+
+impl ToolCallAggregator {
+    fn push(&mut self, mut chunk: ChatCompletionChunk) -> Vec<ChatCompletionChunk> {
+        let template = ChatCompletionChunk {
+            id: chunk.id.clone(),
+            choices: Vec::new(),
+            created: chunk.created,
+            model: chunk.model.clone(),
+            service_tier: chunk.service_tier.clone(),
+            system_fingerprint: chunk.system_fingerprint.clone(),
+            usage: None,
+        };
+        let mut emitted = Vec::new();
+        for choice in &mut chunk.choices {
+            for fragment in choice.delta.tool_calls.take().into_iter().flatten() {
+                let key = (choice.index, fragment.index);
+                if !self.pending.contains_key(&key) {
+                    let started = fragment.index;
+                    emitted.extend(self.flush(&template, |(index, call_index), call| {
+                        index == choice.index && call_index < started && has_json_arguments(call)
+                    }));
+                }
+                let call = self.pending.entry(key).or_insert_with(empty_tool_call);
+                fragment.merge_into(call);
+            }
+            if choice.finish_reason.is_some() {
+                emitted.extend(self.flush(&template, |(index, _), _| index == choice.index));
+            }
+        }
+        self.template = Some(template);
+        if has_output(&chunk) {
+            emitted.push(chunk);
+        }
+        emitted
+    }
+
+    /// Emits every call still open.
+    fn finish(&mut self) -> Vec<ChatCompletionChunk> {
+        match self.template.take() {
+            Some(template) => self.flush(&template, |_, _| true),
+            None => Vec::new(),
+        }
+    }
+
+    fn flush(
+        &mut self,
+        template: &ChatCompletionChunk,
+        is_done: impl Fn((u32, u32), &ChatMessageToolCall) -> bool,
+    ) -> Vec<ChatCompletionChunk> {
+        let done: Vec<(u32, u32)> = self
+            .pending
+            .iter()
+            .filter(|(key, call)| is_done(**key, *call))
+            .map(|(key, _)| *key)
+            .collect();
+        done.into_iter()
+            .filter_map(|key| {
+                let call = self.pending.remove(&key)?;
+                let (index, call_index) = key;
+                Some(ChatCompletionChunk {
+                    choices: vec![Choice {
+                        delta: ChoiceDelta {
+                            content: None,
+                            role: None,
+                            tool_calls: Some(vec![ChoiceDeltaToolCall::complete(call_index, call)]),
+                            reasoning: None,
+                            annotations: None,
+                        },
+                        finish_reason: None,
+                        stop_sequence: None,
+                        index,
+                        logprobs: None,
+                    }],
+                    ..template.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+fn empty_tool_call() -> ChatMessageToolCall {
+    ChatMessageToolCall {
+        id: String::new(),
+        function: FunctionCall {
+            arguments: String::new(),
+            name: String::new(),
+        },
+        r#type: "function".to_string(),
+    }
+}
+
+fn has_json_arguments(call: &ChatMessageToolCall) -> bool {
+    serde_json::from_str::<IgnoredAny>(&call.function.arguments).is_ok()
+}
+
+/// Whether a chunk still carries anything once its tool call fragments are removed.
+fn has_output(chunk: &ChatCompletionChunk) -> bool {
+    chunk.usage.is_some()
+        || chunk.choices.is_empty()
+        || chunk.choices.iter().any(|choice| {
+            choice.delta.role.is_some()
+                || choice.delta.content.is_some()
+                || choice.delta.reasoning.is_some()
+                || choice.delta.annotations.is_some()
+                || choice.finish_reason.is_some()
+                || choice.logprobs.is_some()
+        })
+}
+
+/// Wraps a chat stream so that each tool call arrives as one complete chunk.
+pub fn aggregate_tool_call_stream(
+    stream: BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>>,
+) -> BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>> {
+    Box::pin(stream! {
+        let mut stream = stream;
+        let mut aggregator = ToolCallAggregator::default();
+        while let Some(result) = stream.next().await {
+            match result {
+                Ok(chunk) => {
+                    for chunk in aggregator.push(chunk) {
+                        yield Ok(chunk);
+                    }
+                }
+                Err(e) => {
+                    // Calls cut off by the error would be incomplete; don't pass them on.
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+        for chunk in aggregator.finish() {
+            yield Ok(chunk);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use serde_json::{Value, json};
+
+    fn chunk(delta: Value, finish_reason: Option<&str>) -> ChatCompletionChunk {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        }))
+        .unwrap()
+    }
+
+    fn fragment(index: u32, id: Option<&str>, name: Option<&str>, arguments: &str) -> Value {
+        let mut fragment = json!({"index": index, "function": {"arguments": arguments}});
+        if let Some(id) = id {
+            fragment["id"] = json!(id);
+            fragment["type"] = json!("function");
+        }
+        if let Some(name) = name {
+            fragment["function"]["name"] = json!(name);
+        }
+        fragment
+    }
+
+    /// Two tool calls split over several fragments, with text before, between and after.
+    fn interleaved_stream() -> Vec<ChatCompletionChunk> {
+        vec![
+            chunk(
+                json!({"role": "assistant", "content": "Let me check"}),
+                None,
+            ),
+            chunk(json!({"content": " both cities."}), None),
+            chunk(
+                json!({"tool_calls": [fragment(0, Some("call_1"), Some("get_weather"), "")]}),
+                None,
+            ),
+            chunk(
+                json!({"tool_calls": [fragment(0, None, None, "{\"city\":")]}),
+                None,
+            ),
+            chunk(json!({"content": "…"}), None),
+            chunk(
+                json!({"tool_calls": [fragment(0, None, None, "\"Paris\"}")]}),
+                None,
+            ),
+            chunk(
+                json!({"tool_calls": [fragment(1, Some("call_2"), Some("get_time"), "{\"tz\"")]}),
+                None,
+            ),
+            chunk(
+                json!({"tool_calls": [fragment(1, None, None, ": \"CET\"}")]}),
+                None,
+            ),
+            chunk(json!({}), Some("tool_calls")),
+        ]
+    }
+
+    fn aggregate(chunks: Vec<ChatCompletionChunk>) -> Vec<Value> {
+        let mut aggregator = ToolCallAggregator::default();
+        let mut output: Vec<ChatCompletionChunk> = Vec::new();
+        for chunk in chunks {
+            output.extend(aggregator.push(chunk));
+        }
+        output.extend(aggregator.finish());
+        output
+            .into_iter()
+            .map(|chunk| serde_json::to_value(chunk).unwrap())
+            .collect()
+    }
+
+    fn describe(chunk: &Value) -> String {
+        let choice = &chunk["choices"][0];
+        if let Some(content) = choice["delta"]["content"].as_str() {
+            format!("text:{content}")
+        } else if let Some(calls) = choice["delta"]["tool_calls"].as_array() {
+            format!("call:{}", calls[0]["function"]["name"].as_str().unwrap())
+        } else if let Some(reason) = choice["finish_reason"].as_str() {
+            format!("finish:{reason}")
+        } else {
+            "other".to_string()
+        }
+    }
+
+    #[test]
+    fn test_text_streams_while_tool_calls_are_buffered() {
+        let output = aggregate(interleaved_stream());
+
+        let order: Vec<String> = output.iter().map(describe).collect();
+        assert_eq!(
+            order,
+            [
+                "text:Let me check",
+                "text: both cities.",
+                "text:…",
+                "call:get_weather",
+                "call:get_time",
+                "finish:tool_calls"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_each_tool_call_is_one_complete_chunk() {
+        let output = aggregate(interleaved_stream());
+
+        let calls: Vec<&Value> = output
+            .iter()
+            .filter_map(|chunk| chunk["choices"][0]["delta"]["tool_calls"].as_array())
+            .map(|calls| {
+                assert_eq!(calls.len(), 1);
+                &calls[0]
+            })
+            .collect();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0]["index"], 0);
+        assert_eq!(calls[0]["id"], "call_1");
+        assert_eq!(calls[0]["type"], "function");
+        assert_eq!(calls[1]["index"], 1);
+        assert_eq!(calls[1]["id"], "call_2");
+        let arguments: Vec<Value> = calls
+            .iter()
+            .map(|call| {
+                serde_json::from_str(call["function"]["arguments"].as_str().unwrap()).unwrap()
+            })
+            .collect();
+        assert_eq!(arguments, [json!({"city": "Paris"}), json!({"tz": "CET"})]);
+        assert!(output.iter().all(|chunk| chunk["id"] == "chatcmpl-1"));
+    }
+
+    #[test]
+    fn test_call_with_incomplete_arguments_waits_for_finish() {
+        // The second call starts before the first one's arguments parse, as some
+        // providers interleave fragments of parallel calls.
+        let output = aggregate(vec![
+            chunk(
+                json!({"tool_calls": [fragment(0, Some("call_1"), Some("a"), "{\"x\":")]}),
+                None,
+            ),
+            chunk(
+                json!({"tool_calls": [fragment(1, Some("call_2"), Some("b"), "{}")]}),
+                None,
+            ),
+            chunk(json!({"tool_calls": [fragment(0, None, None, "1}")]}), None),
+            chunk(json!({}), Some("tool_calls")),
+        ]);
+
+        let order: Vec<String> = output.iter().map(describe).collect();
+        assert_eq!(order, ["call:a", "call:b", "finish:tool_calls"]);
+        assert_eq!(
+            output[0]["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"],
+            "{\"x\":1}"
+        );
+    }
+
+    #[test]
+    fn test_open_calls_are_emitted_when_the_stream_ends() {
+        let call = fragment(0, Some("call_1"), Some("a"), "{}");
+        let output = aggregate(vec![chunk(
+            json!({"role": "assistant", "tool_calls": [call]}),
+            None,
+        )]);
+
+        let order: Vec<String> = output.iter().map(describe).collect();
+        assert_eq!(order, ["other", "call:a"]);
+        assert_eq!(output[0]["choices"][0]["delta"]["role"], "assistant");
+    }
+
+    #[test]
+    fn test_header_overrides_pipeline_default() {
+        let mut headers = HeaderMap::new();
+        assert!(aggregate_tool_calls_requested(&headers, true).unwrap());
+        assert!(!aggregate_tool_calls_requested(&headers, false).unwrap());
+
+        headers.insert(
+            AGGREGATE_TOOL_CALLS_HEADER,
+            HeaderValue::from_static("TRUE"),
+        );
+        assert!(aggregate_tool_calls_requested(&headers, false).unwrap());
+        headers.insert(
+            AGGREGATE_TOOL_CALLS_HEADER,
+            HeaderValue::from_static("false"),
+        );
+        assert!(!aggregate_tool_calls_requested(&headers, true).unwrap());
+        headers.insert(AGGREGATE_TOOL_CALLS_HEADER, HeaderValue::from_static("yes"));
+        let rejection = aggregate_tool_calls_requested(&headers, false).unwrap_err();
+        assert_eq!(rejection.status, StatusCode::BAD_REQUEST);
+    }
+}
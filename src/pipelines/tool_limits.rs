@@ -0,0 +1,345 @@
+use serde_json::Value;
+
+use crate::models::chat::ChatCompletionRequest;
+use crate::models::tool_choice::{SimpleToolChoice, ToolChoice};
+use crate::models::tool_definition::ToolDefinition;
+use crate::pipelines::request_validation::RequestValidationError;
+use crate::types::ToolLimits;
+
+/// Name a tool is filtered and reported by: the function name, or the type of a built-in
+/// tool.
+fn tool_name(tool: &ToolDefinition) -> &str {
+    match tool.function() {
+        Some(function) => &function.name,
+        None => tool.tool_type(),
+    }
+}
+
+fn allowed(filter: &[String], name: &str) -> bool {
+    filter
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => pattern == name,
+        })
+}
+
+fn schema_depth(schema: &Value) -> u32 {
+    match schema {
+        Value::Object(schema) => object_schema_depth(schema),
+        _ => 0,
+    }
+}
+
+fn deepest<'a>(schemas: impl Iterator<Item = &'a Value>) -> u32 {
+    schemas.map(schema_depth).max().unwrap_or_default()
+}
+
+/// Levels of nesting in a JSON schema: 1, plus one for each level of properties or items.
+/// `anyOf`, `oneOf` and `allOf` branches don't add a level of their own.
+fn object_schema_depth<'a>(schema: impl IntoIterator<Item = (&'a String, &'a Value)>) -> u32 {
+    let nested = schema
+        .into_iter()
+        .map(|(keyword, value)| match (keyword.as_str(), value) {
+            ("properties" | "patternProperties" | "$defs" | "definitions", Value::Object(map)) => {
+                deepest(map.values())
+            }
+            ("items" | "prefixItems", Value::Array(items)) => deepest(items.iter()),
+            ("items" | "additionalProperties", value) => schema_depth(value),
+            ("anyOf" | "oneOf" | "allOf", Value::Array(branches)) => {
+                deepest(branches.iter()).saturating_sub(1)
+            }
+            _ => 0,
+        })
+        .max()
+        .unwrap_or_default();
+    1 + nested
+}
+
+/// Drops the tools the pipeline's `tool_filter` doesn't allow, then checks the remaining
+/// ones against its limits. A `tool_choice` that can no longer be honoured is rejected.
+pub fn apply_tool_limits(
+    limits: &ToolLimits,
+    payload: &mut ChatCompletionRequest,
+) -> Result<(), RequestValidationError> {
+    let Some(tools) = payload.tools.as_mut() else {
+        return Ok(());
+    };
+
+    if let Some(filter) = &limits.tool_filter {
+        let before = tools.len();
+        tools.retain(|tool| allowed(filter, tool_name(tool)));
+        if tools.len() < before {
+            tracing::debug!(
+                "Dropped {} tools outside the tool filter",
+                before - tools.len()
+            );
+        }
+        if let Some(ToolChoice::Named(choice)) = &payload.tool_choice {
+            if !tools
+                .iter()
+                .any(|tool| tool_name(tool) == choice.function.name)
+            {
+                return Err(RequestValidationError::tool_limit(
+                    "tool_choice",
+                    format!(
+                        "tool_choice names tool '{}', which this pipeline doesn't allow",
+                        choice.function.name
+                    ),
+                ));
+            }
+        }
+        if tools.is_empty() {
+            if matches!(
+                payload.tool_choice,
+                Some(ToolChoice::Simple(SimpleToolChoice::Required))
+            ) {
+                return Err(RequestValidationError::tool_limit(
+                    "tool_choice",
+                    "tool_choice 'required' needs a tool, but this pipeline allows none of the \
+                     request's tools",
+                ));
+            }
+            payload.tools = None;
+            payload.tool_choice = None;
+            payload.parallel_tool_calls = None;
+            return Ok(());
+        }
+    }
+
+    if let Some(max_tools) = limits.max_tools {
+        if let Some(extra) = tools.get(max_tools as usize) {
+            return Err(RequestValidationError::tool_limit(
+                "tools",
+                format!(
+                    "Request defines {} tools, more than the {max_tools} this pipeline allows; \
+                     '{}' is the first over the limit",
+                    tools.len(),
+                    tool_name(extra)
+                ),
+            ));
+        }
+    }
+
+    if let Some(max_depth) = limits.max_tool_schema_depth {
+        for tool in tools.iter() {
+            let Some(function) = tool.function() else {
+                continue;
+            };
+            let depth = function.parameters.as_ref().map_or(0, object_schema_depth);
+            if depth > max_depth {
+                return Err(RequestValidationError::tool_limit(
+                    "tools",
+                    format!(
+                        "Tool '{}' has a parameter schema nested {depth} levels deep, more \
+                         than the {max_depth} this pipeline allows",
+                        function.name
+                    ),
+                ));
+            }
+        }
+    }
+
+    if let Some(max_bytes) = limits.max_tools_bytes {
+        let sizes: Vec<u64> = tools
+            .iter()
+            .map(|tool| serde_json::to_vec(tool).map_or(0, |json| json.len() as u64))
+            .collect();
+        let total: u64 = sizes.iter().sum();
+        if total > max_bytes {
+            let mut running = 0;
+            let first_over = sizes
+                .iter()
+                .position(|size| {
+                    running += size;
+                    running > max_bytes
+                })
+                .unwrap_or_default();
+            return Err(RequestValidationError::tool_limit(
+                "tools",
+                format!(
+                    "Tool definitions take {total} bytes, more than the {max_bytes} this \
+                     pipeline allows; '{}' is the first over the limit",
+                    tool_name(&tools[first_over])
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the `tool-limits` settings are usable.
+pub fn validate_tool_limits(settings: &ToolLimits) -> Result<(), String> {
+    if settings == &ToolLimits::default() {
+        return Err(
+            "set max_tools, max_tools_bytes, max_tool_schema_depth or tool_filter".to_string(),
+        );
+    }
+    if settings.max_tools == Some(0) {
+        return Err("max_tools must be greater than 0".to_string());
+    }
+    if settings.max_tools_bytes == Some(0) {
+        return Err("max_tools_bytes must be greater than 0".to_string());
+    }
+    if settings.max_tool_schema_depth == Some(0) {
+        return Err("max_tool_schema_depth must be greater than 0".to_string());
+    }
+    if let Some(filter) = &settings.tool_filter {
+        if filter.iter().any(|pattern| pattern.trim().is_empty()) {
+            return Err("tool_filter entries must not be empty".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(tools: Value, tool_choice: Option<Value>) -> ChatCompletionRequest {
+        let mut request = json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hi"}],
+            "tools": tools,
+            "parallel_tool_calls": false,
+        });
+        if let Some(tool_choice) = tool_choice {
+            request["tool_choice"] = tool_choice;
+        }
+        serde_json::from_value(request).unwrap()
+    }
+
+    fn function(name: &str, parameters: Value) -> Value {
+        json!({"type": "function", "function": {"name": name, "parameters": parameters}})
+    }
+
+    fn names(request: &ChatCompletionRequest) -> Vec<&str> {
+        request.tools.iter().flatten().map(tool_name).collect()
+    }
+
+    #[test]
+    fn test_schema_depth() {
+        assert_eq!(schema_depth(&json!({"type": "string"})), 1);
+        let flat = json!({"type": "object", "properties": {"city": {"type": "string"}}});
+        assert_eq!(schema_depth(&flat), 2);
+        let nested = json!({
+            "type": "object",
+            "properties": {
+                "stops": {
+                    "type": "array",
+                    "items": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }
+            }
+        });
+        assert_eq!(schema_depth(&nested), 4);
+        let branches = json!({
+            "anyOf": [{"type": "string"}, {"type": "array", "items": {"type": "string"}}]
+        });
+        assert_eq!(schema_depth(&branches), 2);
+    }
+
+    #[test]
+    fn test_filter_keeps_named_and_prefixed_tools() {
+        let limits = ToolLimits {
+            tool_filter: Some(vec!["get_weather".to_string(), "docs_*".to_string()]),
+            ..Default::default()
+        };
+        let mut payload = request(
+            json!([
+                function("get_weather", json!({})),
+                function("docs_search", json!({})),
+                function("delete_user", json!({})),
+                {"type": "web_search_preview"}
+            ]),
+            None,
+        );
+        apply_tool_limits(&limits, &mut payload).unwrap();
+        assert_eq!(names(&payload), ["get_weather", "docs_search"]);
+        assert_eq!(payload.parallel_tool_calls, Some(false));
+    }
+
+    #[test]
+    fn test_filter_dropping_every_tool_drops_tool_choice() {
+        let limits = ToolLimits {
+            tool_filter: Some(vec!["get_weather".to_string()]),
+            ..Default::default()
+        };
+        let mut payload = request(
+            json!([function("delete_user", json!({}))]),
+            Some(json!("auto")),
+        );
+        apply_tool_limits(&limits, &mut payload).unwrap();
+        assert!(payload.tools.is_none());
+        assert!(payload.tool_choice.is_none());
+        assert!(payload.parallel_tool_calls.is_none());
+
+        let mut payload = request(
+            json!([function("delete_user", json!({}))]),
+            Some(json!("required")),
+        );
+        let rejection = apply_tool_limits(&limits, &mut payload).unwrap_err();
+        assert_eq!(rejection.param.as_deref(), Some("tool_choice"));
+    }
+
+    #[test]
+    fn test_limits_are_checked_after_filtering() {
+        let limits = ToolLimits {
+            max_tools: Some(1),
+            tool_filter: Some(vec!["get_weather".to_string()]),
+            ..Default::default()
+        };
+        let mut payload = request(
+            json!([
+                function("delete_user", json!({})),
+                function("get_weather", json!({}))
+            ]),
+            None,
+        );
+        assert!(apply_tool_limits(&limits, &mut payload).is_ok());
+    }
+
+    #[test]
+    fn test_max_tools_bytes_names_first_tool_over_limit() {
+        let small = function("small", json!({}));
+        let limits = ToolLimits {
+            max_tools_bytes: Some(small.to_string().len() as u64 + 10),
+            ..Default::default()
+        };
+        let mut payload = request(json!([small, function("large", json!({}))]), None);
+        let rejection = apply_tool_limits(&limits, &mut payload).unwrap_err();
+        assert_eq!(rejection.param.as_deref(), Some("tools"));
+        assert!(
+            rejection.message.contains("'large'"),
+            "{}",
+            rejection.message
+        );
+    }
+
+    #[test]
+    fn test_validate_tool_limits() {
+        assert!(validate_tool_limits(&ToolLimits::default()).is_err());
+        assert!(
+            validate_tool_limits(&ToolLimits {
+                max_tools: Some(0),
+                ..Default::default()
+            })
+            .is_err()
+        );
+        assert!(
+            validate_tool_limits(&ToolLimits {
+                tool_filter: Some(vec![" ".to_string()]),
+                ..Default::default()
+            })
+            .is_err()
+        );
+        assert!(
+            validate_tool_limits(&ToolLimits {
+                tool_filter: Some(vec![]),
+                ..Default::default()
+            })
+            .is_ok()
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use crate::config::models::ModelConfig;
+use crate::models::usage::Usage;
+use crate::pipelines::cost::usage_cost_usd;
+use crate::state_store::StateStore;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// How often counters are flushed to the `StateStore`.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+const STORE_PREFIX: &str = "usage:";
+/// Costs are counted in millionths of a dollar so they fit an atomic integer.
+const MICRO_USD: f64 = 1_000_000.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct UsageKey {
+    pipeline: Arc<str>,
+    model: String,
+    date: NaiveDate,
+}
+
+/// Usage recorded since the last flush.
+#[derive(Debug, Default)]
+struct Counters {
+    requests: AtomicU64,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+    cached_tokens: AtomicU64,
+    reasoning_tokens: AtomicU64,
+    errors: AtomicU64,
+    cost_micro_usd: AtomicU64,
+}
+
+/// Usage of one model in one pipeline on one UTC day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub pipeline: String,
+    pub model: String,
+    pub date: NaiveDate,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Part of `prompt_tokens` read from the provider's prompt cache.
+    #[serde(default)]
+    pub cached_tokens: u64,
+    /// Part of `completion_tokens` spent on reasoning.
+    #[serde(default)]
+    pub reasoning_tokens: u64,
+    pub errors: u64,
+    /// Estimated from the model's configured prices.
+    pub cost_usd: f64,
+}
+
+impl UsageRecord {
+    fn empty(key: &UsageKey) -> Self {
+        Self {
+            pipeline: key.pipeline.to_string(),
+            model: key.model.clone(),
+            date: key.date,
+            requests: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            cached_tokens: 0,
+            reasoning_tokens: 0,
+            errors: 0,
+            cost_usd: 0.0,
+        }
+    }
+}
+
+/// Sums of the usage records in a summary.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cached_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub errors: u64,
+    pub cost_usd: f64,
+}
+
+/// Usage between two days, as returned by `GET /admin/usage`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<String>,
+    pub totals: UsageTotals,
+    pub usage: Vec<UsageRecord>,
+}
+
+/// Aggregates requests, tokens, errors and cost per pipeline × model × day.
+///
+/// Requests only bump atomic counters; `flush` moves them into the `StateStore`, which
+/// is what summaries are read from. Like the budget ledger, usage survives config reloads
+/// but not process restarts.
+pub struct UsageAggregator {
+    counters: RwLock<HashMap<UsageKey, Arc<Counters>>>,
+    store: Arc<StateStore>,
+}
+
+impl UsageAggregator {
+    pub fn new(store: Arc<StateStore>) -> Self {
+        Self {
+            counters: RwLock::default(),
+            store,
+        }
+    }
+
+    /// Flushes the counters every `FLUSH_INTERVAL` for as long as the runtime runs.
+    pub fn spawn_flush_task(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                self.flush();
+            }
+        })
+    }
+
+    fn counters(&self, pipeline: &Arc<str>, model: &str) -> Arc<Counters> {
+        let key = UsageKey {
+            pipeline: pipeline.clone(),
+            model: model.to_string(),
+            date: Utc::now().date_naive(),
+        };
+        if let Some(counters) = self.counters.read().unwrap().get(&key) {
+            return counters.clone();
+        }
+        self.counters
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .clone()
+    }
+
+    /// Adds the counters to the stored totals and resets them.
+    pub fn flush(&self) {
+        let counters = self.counters.read().unwrap();
+        for (key, counters) in counters.iter() {
+            let requests = counters.requests.swap(0, Ordering::Relaxed);
+            let prompt_tokens = counters.prompt_tokens.swap(0, Ordering::Relaxed);
+            let completion_tokens = counters.completion_tokens.swap(0, Ordering::Relaxed);
+            let cached_tokens = counters.cached_tokens.swap(0, Ordering::Relaxed);
+            let reasoning_tokens = counters.reasoning_tokens.swap(0, Ordering::Relaxed);
+            let errors = counters.errors.swap(0, Ordering::Relaxed);
+            let cost_micro_usd = counters.cost_micro_usd.swap(0, Ordering::Relaxed);
+            if requests == 0 && prompt_tokens == 0 && completion_tokens == 0 {
+                continue;
+            }
+
+            let store_key = format!("{STORE_PREFIX}{}:{}:{}", key.date, key.pipeline, key.model);
+            let mut record = self
+                .store
+                .get(&store_key)
+                .and_then(|value| serde_json::from_value(value).ok())
+                .unwrap_or_else(|| UsageRecord::empty(key));
+            record.requests += requests;
+            record.prompt_tokens += prompt_tokens;
+            record.completion_tokens += completion_tokens;
+            record.cached_tokens += cached_tokens;
+            record.reasoning_tokens += reasoning_tokens;
+            record.errors += errors;
+            record.cost_usd += cost_micro_usd as f64 / MICRO_USD;
+            if let Ok(value) = serde_json::to_value(&record) {
+                self.store.set(&store_key, value, None);
+            }
+        }
+    }
+
+    /// Flushed usage between `from` and `to`, inclusive, optionally for one pipeline.
+    pub fn records(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        pipeline: Option<&str>,
+    ) -> Vec<UsageRecord> {
+        let mut records: Vec<UsageRecord> = self
+            .store
+            .scan_prefix(STORE_PREFIX)
+            .into_iter()
+            .filter_map(|(_, value)| serde_json::from_value::<UsageRecord>(value).ok())
+            .filter(|record| (from..=to).contains(&record.date))
+            .filter(|record| pipeline.is_none_or(|pipeline| record.pipeline == pipeline))
+            .collect();
+        records
+            .sort_by(|a, b| (a.date, &a.pipeline, &a.model).cmp(&(b.date, &b.pipeline, &b.model)));
+        records
+    }
+
+    /// Flushes pending counters, then summarizes usage between `from` and `to`.
+    pub fn summary(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        pipeline: Option<String>,
+    ) -> UsageSummary {
+        self.flush();
+        let usage = self.records(from, to, pipeline.as_deref());
+        let totals = usage
+            .iter()
+            .fold(UsageTotals::default(), |mut totals, record| {
+                totals.requests += record.requests;
+                totals.prompt_tokens += record.prompt_tokens;
+                totals.completion_tokens += record.completion_tokens;
+                totals.cached_tokens += record.cached_tokens;
+                totals.reasoning_tokens += record.reasoning_tokens;
+                totals.errors += record.errors;
+                totals.cost_usd += record.cost_usd;
+                totals
+            });
+        UsageSummary {
+            from,
+            to,
+            pipeline,
+            totals,
+            usage,
+        }
+    }
+}
+
+/// Usage recording for a single pipeline.
+#[derive(Clone)]
+pub struct PipelineUsage {
+    pipeline: Arc<str>,
+    aggregator: Arc<UsageAggregator>,
+}
+
+impl PipelineUsage {
+    pub fn new(pipeline: &str, aggregator: Arc<UsageAggregator>) -> Self {
+        Self {
+            pipeline: Arc::from(pipeline),
+            aggregator,
+        }
+    }
+
+    /// Counts a request answered by `model`, with the tokens it used.
+    pub fn record(&self, model: &ModelConfig, usage: &Usage) {
+        let counters = self.aggregator.counters(&self.pipeline, &model.key);
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        counters
+            .prompt_tokens
+            .fetch_add(usage.prompt_tokens.into(), Ordering::Relaxed);
+        counters
+            .completion_tokens
+            .fetch_add(usage.completion_tokens.into(), Ordering::Relaxed);
+        counters
+            .cached_tokens
+            .fetch_add(usage.cached_tokens().into(), Ordering::Relaxed);
+        counters
+            .reasoning_tokens
+            .fetch_add(usage.reasoning_tokens().into(), Ordering::Relaxed);
+        let cost = usage_cost_usd(model, usage);
+        counters
+            .cost_micro_usd
+            .fetch_add((cost * MICRO_USD).round() as u64, Ordering::Relaxed);
+    }
+
+    /// Counts a request that `model` failed to answer.
+    pub fn record_error(&self, model_key: &str) {
+        let counters = self.aggregator.counters(&self.pipeline, model_key);
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        counters.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::usage::{CompletionTokensDetails, PromptTokensDetails};
+    use crate::pipelines::cost::{INPUT_COST_PARAM, OUTPUT_COST_PARAM};
+
+    fn model(key: &str) -> ModelConfig {
+        ModelConfig {
+            key: key.to_string(),
+            r#type: key.to_string(),
+            provider: "openai".to_string(),
+            params: HashMap::from([
+                (INPUT_COST_PARAM.to_string(), "0.01".to_string()),
+                (OUTPUT_COST_PARAM.to_string(), "0.03".to_string()),
+            ]),
+            enabled: true,
+            deprecation: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_flush_accumulates_into_store() {
+        let aggregator = Arc::new(UsageAggregator::new(Arc::new(StateStore::default())));
+        let usage = PipelineUsage::new("default", aggregator.clone());
+        let today = Utc::now().date_naive();
+
+        usage.record(&model("gpt-4o"), &Usage::new(1000, 500));
+        usage.record_error("gpt-4o");
+        // Nothing is visible before a flush.
+        assert!(aggregator.records(today, today, None).is_empty());
+        aggregator.flush();
+        usage.record(
+            &model("gpt-4o"),
+            &Usage {
+                prompt_tokens_details: Some(PromptTokensDetails {
+                    cached_tokens: Some(800),
+                    ..Default::default()
+                }),
+                completion_tokens_details: Some(CompletionTokensDetails {
+                    reasoning_tokens: Some(0),
+                    ..Default::default()
+                }),
+                ..Usage::new(1000, 0)
+            },
+        );
+        aggregator.flush();
+
+        let records = aggregator.records(today, today, Some("default"));
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        // Errors count as requests too.
+        assert_eq!((record.requests, record.errors), (3, 1));
+        assert_eq!(
+            (record.prompt_tokens, record.completion_tokens),
+            (2000, 500)
+        );
+        assert_eq!((record.cached_tokens, record.reasoning_tokens), (800, 0));
+        assert!((record.cost_usd - 0.035).abs() < 1e-9);
+        assert!(aggregator.records(today, today, Some("other")).is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+//! Model types a provider accepts, so a model pointed at the wrong provider, e.g. `gpt-4o`
+//! at an Anthropic provider, fails validation instead of its requests.
+
+use crate::types::{Provider, ProviderType};
+
+/// Comma-separated prefixes of the model types a provider accepts, replacing the defaults
+/// of its type.
+pub const ALLOWED_MODEL_PREFIXES_PARAM: &str = "allowed_model_prefixes";
+/// Allows any model type when listed in `allowed_model_prefixes`.
+pub const ANY_MODEL_PREFIX: &str = "*";
+
+const OPENAI_MODEL_PREFIXES: &[&str] = &[
+    "gpt-",
+    "chatgpt-",
+    "o1",
+    "o3",
+    "o4",
+    "text-embedding-",
+    "omni-moderation-",
+    "text-moderation-",
+    "davinci-",
+    "babbage-",
+    "codex-",
+    "ft:",
+];
+const ANTHROPIC_MODEL_PREFIXES: &[&str] = &["claude-"];
+
+/// The prefixes a provider of `provider_type` accepts unless configured otherwise. Types
+/// serving models under names of the user's choosing, like Azure deployments, have none,
+/// and neither have OpenAI providers with a `base_url`, which are usually
+/// OpenAI-compatible servers of other models.
+pub fn default_model_prefixes(
+    provider_type: &ProviderType,
+    has_base_url: bool,
+) -> &'static [&'static str] {
+    match provider_type {
+        ProviderType::OpenAI if !has_base_url => OPENAI_MODEL_PREFIXES,
+        ProviderType::Anthropic => ANTHROPIC_MODEL_PREFIXES,
+        _ => &[],
+    }
+}
+
+/// The prefixes listed in an `allowed_model_prefixes` param.
+pub fn parse_model_prefixes(param: &str) -> Vec<String> {
+    param
+        .split(',')
+        .map(str::trim)
+        .filter(|prefix| !prefix.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The prefixes a provider accepts: `configured` if set, the defaults of its type
+/// otherwise. `None` accepts any model type, as does an empty list.
+pub fn allowed_model_prefixes(
+    provider_type: &ProviderType,
+    configured: Option<&[String]>,
+    has_base_url: bool,
+) -> Option<Vec<String>> {
+    let prefixes = match configured {
+        Some(prefixes) => prefixes.to_vec(),
+        None => default_model_prefixes(provider_type, has_base_url)
+            .iter()
+            .map(|prefix| prefix.to_string())
+            .collect(),
+    };
+    if prefixes.is_empty() || prefixes.iter().any(|prefix| prefix == ANY_MODEL_PREFIX) {
+        return None;
+    }
+    Some(prefixes)
+}
+
+/// The prefixes `provider` accepts, from its params.
+pub fn provider_model_prefixes(provider: &Provider) -> Option<Vec<String>> {
+    let configured = provider
+        .params
+        .get(ALLOWED_MODEL_PREFIXES_PARAM)
+        .map(|param| parse_model_prefixes(param));
+    allowed_model_prefixes(
+        &provider.r#type,
+        configured.as_deref(),
+        provider.params.contains_key("base_url"),
+    )
+}
+
+/// Checks that the model `model_key`, of type `model_type`, may use the provider
+/// `provider_key`, which accepts the model types starting with one of `prefixes`.
+pub fn check_model_type(
+    model_key: &str,
+    model_type: &str,
+    provider_key: &str,
+    prefixes: Option<&[String]>,
+) -> Result<(), String> {
+    let Some(prefixes) = prefixes else {
+        return Ok(());
+    };
+    if prefixes
+        .iter()
+        .any(|prefix| model_type.starts_with(prefix.as_str()))
+    {
+        return Ok(());
+    }
+    let allowed = prefixes
+        .iter()
+        .map(|prefix| format!("'{prefix}'"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(format!(
+        "Model '{model_key}' has type '{model_type}', which provider '{provider_key}' doesn't \
+         allow: its model types start with {allowed}. Set the provider's \
+         {ALLOWED_MODEL_PREFIXES_PARAM} to allow others, or to '{ANY_MODEL_PREFIX}' to allow any"
+    ))
+}
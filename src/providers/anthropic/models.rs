@@ -1,14 +1,21 @@
+use crate::ai_models::params::model_default_max_tokens;
 use crate::config::constants::default_max_tokens;
 use crate::models::chat::{ChatCompletion, ChatCompletionChoice, ChatCompletionRequest};
-use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
+use crate::models::content::{ChatCompletionMessage, ChatMessageContent, is_instruction_role};
+use crate::models::messages::{InputContent, InputContentBlock, InputMessage, tool_input};
+use crate::models::response_format::ResponseFormat;
 use crate::models::tool_calls::{ChatMessageToolCall, FunctionCall};
+use crate::models::tool_definition::ToolDefinition;
+use crate::models::usage::PromptTokensDetails;
+use crate::types::RequestPriority;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct AnthropicChatCompletionRequest {
     pub max_tokens: u32,
     pub model: String,
-    pub messages: Vec<ChatCompletionMessage>,
+    pub messages: Vec<InputMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -20,6 +27,189 @@ pub struct AnthropicChatCompletionRequest {
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
+}
+
+/// Body of `POST /v1/messages/count_tokens`: the prompt fields of a translated chat request.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AnthropicCountTokensRequest {
+    pub model: String,
+    pub messages: Vec<InputMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    pub tools: Vec<ToolParam>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+}
+
+impl From<AnthropicChatCompletionRequest> for AnthropicCountTokensRequest {
+    fn from(request: AnthropicChatCompletionRequest) -> Self {
+        Self {
+            model: request.model,
+            messages: request.messages,
+            tool_choice: request.tool_choice,
+            tools: request.tools,
+            system: request.system,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AnthropicCountTokensResponse {
+    pub input_tokens: u32,
+}
+
+/// Maps a gateway priority onto Anthropic's `service_tier` request field.
+/// `auto` lets Anthropic use Priority Tier capacity; `standard_only` opts out of it.
+pub fn anthropic_service_tier(priority: RequestPriority) -> Option<&'static str> {
+    match priority {
+        RequestPriority::Low => Some("standard_only"),
+        RequestPriority::Default => None,
+        RequestPriority::High => Some("auto"),
+    }
+}
+
+/// Most output tokens each Claude model family can produce, by model name prefix. More
+/// specific prefixes come first.
+const MAX_OUTPUT_TOKENS: &[(&str, u32)] = &[
+    ("claude-opus-4-5", 64_000),
+    ("claude-opus-4", 32_000),
+    ("claude-sonnet-4", 64_000),
+    ("claude-haiku-4", 64_000),
+    ("claude-3-7-sonnet", 64_000),
+    ("claude-3-5-sonnet", 8_192),
+    ("claude-3-5-haiku", 8_192),
+    ("claude-3-opus", 4_096),
+    ("claude-3-haiku", 4_096),
+];
+
+/// The most output tokens `model` can produce, when it's a known Claude model.
+pub fn max_output_tokens(model: &str) -> Option<u32> {
+    MAX_OUTPUT_TOKENS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, max)| *max)
+}
+
+/// The `max_tokens` Anthropic requires on every request: the request's
+/// `max_completion_tokens`, then its `max_tokens`, then the model's `default_max_tokens`
+/// param, then the model's maximum output. Values above that maximum are clamped to it.
+pub fn anthropic_max_tokens(
+    request: &ChatCompletionRequest,
+    params: &HashMap<String, String>,
+) -> u32 {
+    let model_max = max_output_tokens(&request.model);
+    let max_tokens = request
+        .max_completion_tokens
+        .filter(|max_tokens| *max_tokens > 0)
+        .or(request.max_tokens)
+        .or_else(|| model_default_max_tokens(params))
+        .or(model_max)
+        .unwrap_or_else(default_max_tokens);
+    match model_max {
+        Some(model_max) if max_tokens > model_max => {
+            tracing::warn!(
+                "max_tokens {} exceeds the {} maximum output of {}; clamping",
+                max_tokens,
+                request.model,
+                model_max
+            );
+            model_max
+        }
+        _ => max_tokens,
+    }
+}
+
+/// Appended to the system prompt for `json_object` responses.
+const JSON_OBJECT_INSTRUCTION: &str =
+    "Respond only with a single valid JSON object. Do not include any other text.";
+
+/// Schema keywords that cannot be enforced when a `json_schema` response is strict.
+const UNSUPPORTED_STRICT_KEYWORDS: &[&str] = &[
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+    "minLength",
+    "maxLength",
+    "patternProperties",
+    "if",
+    "then",
+    "else",
+    "not",
+    "$dynamicRef",
+];
+
+fn find_unsupported_keyword(schema: &serde_json::Value) -> Option<&'static str> {
+    match schema {
+        serde_json::Value::Object(map) => map.iter().find_map(|(key, value)| {
+            if let Some(keyword) = UNSUPPORTED_STRICT_KEYWORDS
+                .iter()
+                .find(|keyword| **keyword == key.as_str())
+            {
+                return Some(*keyword);
+            }
+            // Keys of these maps are property names, not keywords.
+            if matches!(key.as_str(), "properties" | "$defs" | "definitions") {
+                value
+                    .as_object()
+                    .and_then(|entries| entries.values().find_map(find_unsupported_keyword))
+            } else {
+                find_unsupported_keyword(value)
+            }
+        }),
+        serde_json::Value::Array(items) => items.iter().find_map(find_unsupported_keyword),
+        _ => None,
+    }
+}
+
+impl AnthropicChatCompletionRequest {
+    /// Applies an OpenAI `response_format`. A `json_schema` becomes a single forced tool whose
+    /// name is returned so the response can be unwrapped with `into_structured_completion`.
+    pub fn apply_response_format(
+        &mut self,
+        response_format: &ResponseFormat,
+    ) -> Result<Option<String>, String> {
+        let json_schema = match response_format.r#type.as_str() {
+            "json_schema" => response_format.json_schema.as_ref(),
+            "json_object" => None,
+            _ => return Ok(None),
+        };
+
+        let Some((json_schema, schema)) =
+            json_schema.and_then(|json_schema| Some((json_schema, json_schema.schema.as_ref()?)))
+        else {
+            self.system = Some(match self.system.take() {
+                Some(existing) => format!("{existing}\n\n{JSON_OBJECT_INSTRUCTION}"),
+                None => JSON_OBJECT_INSTRUCTION.to_string(),
+            });
+            return Ok(None);
+        };
+
+        if json_schema.strict == Some(true) {
+            if let Some(keyword) = find_unsupported_keyword(schema) {
+                return Err(format!(
+                    "strict schema '{}' uses unsupported keyword '{keyword}'",
+                    json_schema.name
+                ));
+            }
+        }
+
+        self.tools.push(ToolParam {
+            input_schema: schema.clone(),
+            name: json_schema.name.clone(),
+            description: json_schema.description.clone(),
+        });
+        self.tool_choice = Some(ToolChoice::Tool {
+            name: json_schema.name.clone(),
+            disable_parallel_tool_use: true,
+        });
+        Ok(Some(json_schema.name.clone()))
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -28,6 +218,11 @@ pub struct AnthropicChatCompletionResponse {
     pub model: String,
     pub content: Vec<ContentBlock>,
     pub usage: Usage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    /// The stop sequence generation ended on, when `stop_reason` is `stop_sequence`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequence: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -45,8 +240,36 @@ pub enum ContentBlock {
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Usage {
+    /// Prompt tokens that were neither read from nor written to the prompt cache.
     pub input_tokens: u32,
     pub output_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
+}
+
+impl From<&Usage> for crate::models::usage::Usage {
+    /// OpenAI counts cached tokens in `prompt_tokens`, while Anthropic leaves them out of
+    /// `input_tokens`.
+    fn from(usage: &Usage) -> Self {
+        let cache_read = usage.cache_read_input_tokens.unwrap_or(0);
+        let cache_creation = usage.cache_creation_input_tokens.unwrap_or(0);
+        let prompt_tokens = usage.input_tokens + cache_read + cache_creation;
+        let prompt_tokens_details = (usage.cache_read_input_tokens.is_some()
+            || usage.cache_creation_input_tokens.is_some())
+        .then(|| PromptTokensDetails {
+            cached_tokens: Some(cache_read),
+            cache_creation_tokens: Some(cache_creation),
+            ..Default::default()
+        });
+        Self {
+            prompt_tokens_details,
+            ..Self::new(prompt_tokens, usage.output_tokens)
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -71,16 +294,177 @@ pub struct ToolParam {
 #[serde(tag = "type")]
 pub enum ToolChoice {
     #[serde(rename = "auto")]
-    Auto { disable_parallel_tool_use: bool },
+    Auto {
+        #[serde(default)]
+        disable_parallel_tool_use: bool,
+    },
     #[serde(rename = "any")]
-    Any { disable_parallel_tool_use: bool },
+    Any {
+        #[serde(default)]
+        disable_parallel_tool_use: bool,
+    },
     #[serde(rename = "tool")]
     Tool {
         name: String,
+        #[serde(default)]
         disable_parallel_tool_use: bool,
     },
 }
 
+/// Converts chat messages to Anthropic's format. Tool calls become `tool_use` blocks, tool
+/// results become `tool_result` blocks in a user turn, and consecutive turns from the same
+/// role are merged, since Anthropic requires roles to alternate.
+fn anthropic_messages(
+    messages: impl IntoIterator<Item = ChatCompletionMessage>,
+) -> Vec<InputMessage> {
+    let mut turns: Vec<(String, Vec<InputContentBlock>)> = Vec::new();
+    for message in messages {
+        let (role, blocks) = anthropic_blocks(message);
+        if blocks.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    pair_tool_results(turns)
+        .into_iter()
+        .map(|(role, mut blocks)| {
+            // Plain single-text turns keep Anthropic's shorthand string content.
+            let content = match blocks.as_mut_slice() {
+                [InputContentBlock::Text { text }] => InputContent::Text(std::mem::take(text)),
+                _ => InputContent::Blocks(blocks),
+            };
+            InputMessage { role, content }
+        })
+        .collect()
+}
+
+/// Anthropic requires every `tool_use` to be answered by a `tool_result` at the start of the
+/// very next user turn, and rejects results for calls it never saw. Agent transcripts often
+/// break this, so results are moved into place in call order, unanswered calls get an error
+/// result and orphaned results become text, instead of the request failing with a 400.
+fn pair_tool_results(
+    turns: Vec<(String, Vec<InputContentBlock>)>,
+) -> Vec<(String, Vec<InputContentBlock>)> {
+    let mut paired = Vec::with_capacity(turns.len());
+    // Calls made by the previous assistant turn, in order.
+    let mut pending: Vec<String> = Vec::new();
+    for (role, blocks) in turns {
+        match role.as_str() {
+            "assistant" => {
+                if !pending.is_empty() {
+                    paired.push(("user".to_string(), answer_tool_calls(&pending, Vec::new())));
+                }
+                pending = blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        InputContentBlock::ToolUse { id, .. } => Some(id.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                paired.push((role, blocks));
+            }
+            "user" => {
+                let blocks = answer_tool_calls(&pending, blocks);
+                pending.clear();
+                paired.push((role, blocks));
+            }
+            _ => paired.push((role, blocks)),
+        }
+    }
+    if !pending.is_empty() {
+        paired.push(("user".to_string(), answer_tool_calls(&pending, Vec::new())));
+    }
+    paired
+}
+
+/// Puts a result for each of `calls` at the front of a user turn's `blocks`.
+fn answer_tool_calls(calls: &[String], blocks: Vec<InputContentBlock>) -> Vec<InputContentBlock> {
+    let mut results = Vec::new();
+    let mut rest = Vec::new();
+    for block in blocks {
+        match block {
+            InputContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => results.push((tool_use_id, content, is_error)),
+            block => rest.push(block),
+        }
+    }
+
+    let mut answered = Vec::with_capacity(calls.len() + results.len() + rest.len());
+    for call in calls {
+        let (content, is_error) = match results.iter().position(|(id, ..)| id == call) {
+            Some(position) => {
+                let (_, content, is_error) = results.remove(position);
+                (content, is_error)
+            }
+            None => {
+                tracing::warn!(
+                    "Tool call '{call}' has no result; sending an error result to Anthropic"
+                );
+                let content = "No result was provided for this tool call.".to_string();
+                (Some(ChatMessageContent::String(content)), Some(true))
+            }
+        };
+        answered.push(InputContentBlock::ToolResult {
+            tool_use_id: call.clone(),
+            content,
+            is_error,
+        });
+    }
+
+    // Results that answer no call of the previous turn can't be sent as results.
+    for (id, content, _) in results {
+        tracing::warn!(
+            "Tool result for '{id}' answers no preceding tool call; sending it to Anthropic as text"
+        );
+        let output = content
+            .as_ref()
+            .map(|content| content.text_parts().join("\n"))
+            .unwrap_or_default();
+        answered.push(InputContentBlock::Text {
+            text: format!("Result of tool call '{id}': {output}"),
+        });
+    }
+    answered.extend(rest);
+    answered
+}
+
+fn anthropic_blocks(message: ChatCompletionMessage) -> (String, Vec<InputContentBlock>) {
+    if message.role == "tool" {
+        let result = InputContentBlock::ToolResult {
+            tool_use_id: message.tool_call_id.unwrap_or_default(),
+            content: message.content,
+            is_error: None,
+        };
+        return ("user".to_string(), vec![result]);
+    }
+
+    let mut blocks: Vec<InputContentBlock> = message
+        .content
+        .iter()
+        .flat_map(ChatMessageContent::text_parts)
+        // Anthropic rejects empty text blocks.
+        .filter(|text| !text.is_empty())
+        .map(|text| InputContentBlock::Text {
+            text: text.to_string(),
+        })
+        .collect();
+    blocks.extend(message.tool_calls.into_iter().flatten().map(|call| {
+        InputContentBlock::ToolUse {
+            input: tool_input(&call.function.arguments),
+            id: call.id,
+            name: call.function.name,
+        }
+    }));
+    (message.role, blocks)
+}
+
 impl From<ChatCompletionRequest> for AnthropicChatCompletionRequest {
     fn from(request: ChatCompletionRequest) -> Self {
         let should_include_tools = !matches!(
@@ -90,18 +474,14 @@ impl From<ChatCompletionRequest> for AnthropicChatCompletionRequest {
             ))
         );
 
-        let mut system = request
+        let instructions: Vec<&str> = request
             .messages
             .iter()
-            .find(|msg| msg.role == "system")
-            .and_then(|message| match &message.content {
-                Some(ChatMessageContent::String(text)) => Some(text.clone()),
-                Some(ChatMessageContent::Array(parts)) => parts
-                    .iter()
-                    .find(|part| part.r#type == "text")
-                    .map(|part| part.text.clone()),
-                _ => None,
-            });
+            .filter(|msg| is_instruction_role(&msg.role))
+            .filter_map(|msg| msg.content.as_ref())
+            .flat_map(ChatMessageContent::text_parts)
+            .collect();
+        let mut system = (!instructions.is_empty()).then(|| instructions.join("\n\n"));
 
         // Add reasoning prompt if reasoning is requested
         if let Some(reasoning_config) = &request.reasoning {
@@ -113,11 +493,12 @@ impl From<ChatCompletionRequest> for AnthropicChatCompletionRequest {
             }
         }
 
-        let messages: Vec<ChatCompletionMessage> = request
-            .messages
-            .into_iter()
-            .filter(|msg| msg.role != "system")
-            .collect();
+        let messages = anthropic_messages(
+            request
+                .messages
+                .into_iter()
+                .filter(|msg| !is_instruction_role(&msg.role)),
+        );
 
         let max_tokens = match request.max_completion_tokens {
             Some(val) if val > 0 => val,
@@ -139,6 +520,7 @@ impl From<ChatCompletionRequest> for AnthropicChatCompletionRequest {
             },
             stream: request.stream,
             system,
+            stop_sequences: request.stop.filter(|stop| !stop.is_empty()),
             tool_choice: request.tool_choice.map(|choice| match choice {
                 crate::models::tool_choice::ToolChoice::Simple(simple) => match simple {
                     crate::models::tool_choice::SimpleToolChoice::None
@@ -159,16 +541,17 @@ impl From<ChatCompletionRequest> for AnthropicChatCompletionRequest {
                     .tools
                     .unwrap_or_default()
                     .into_iter()
-                    .map(|tool| ToolParam {
-                        name: tool.function.name,
-                        description: tool.function.description,
-                        input_schema: serde_json::to_value(tool.function.parameters)
-                            .unwrap_or_default(),
+                    .filter_map(ToolDefinition::into_function)
+                    .map(|function| ToolParam {
+                        name: function.name,
+                        description: function.description,
+                        input_schema: serde_json::to_value(function.parameters).unwrap_or_default(),
                     })
                     .collect()
             } else {
                 Vec::new()
             },
+            service_tier: None,
         }
     }
 }
@@ -211,12 +594,44 @@ impl From<Vec<ContentBlock>> for ChatCompletionMessage {
                 Some(tool_calls)
             },
             tool_call_id: None,
+            annotations: None,
         }
     }
 }
 
+impl AnthropicChatCompletionResponse {
+    /// Converts a structured-output response, returning the forced tool's input as content.
+    pub fn into_structured_completion(mut self, tool_name: &str) -> ChatCompletion {
+        self.content = self
+            .content
+            .into_iter()
+            .map(|block| match block {
+                ContentBlock::ToolUse { name, input, .. } if name == tool_name => {
+                    ContentBlock::Text {
+                        text: input.to_string(),
+                    }
+                }
+                other => other,
+            })
+            .collect();
+        self.into()
+    }
+}
+
 impl From<AnthropicChatCompletionResponse> for ChatCompletion {
     fn from(response: AnthropicChatCompletionResponse) -> Self {
+        let mut message: ChatCompletionMessage = response.content.into();
+        let finish_reason = if response.stop_reason.as_deref() == Some("refusal") {
+            // Surface refusals the way OpenAI does: text in `refusal`, no content.
+            message.refusal = match message.content.take() {
+                Some(ChatMessageContent::String(text)) if !text.is_empty() => Some(text),
+                _ => Some("The request was declined by the provider's safety filters.".to_string()),
+            };
+            "content_filter"
+        } else {
+            "stop"
+        };
+
         ChatCompletion {
             id: response.id,
             object: None,
@@ -224,18 +639,15 @@ impl From<AnthropicChatCompletionResponse> for ChatCompletion {
             model: response.model,
             choices: vec![ChatCompletionChoice {
                 index: 0,
-                message: response.content.into(),
-                finish_reason: Some("stop".to_string()),
+                message,
+                finish_reason: Some(finish_reason.to_string()),
                 logprobs: None,
+                safety_ratings: None,
+                stop_sequence: response.stop_sequence,
             }],
-            usage: crate::models::usage::Usage {
-                prompt_tokens: response.usage.input_tokens,
-                completion_tokens: response.usage.output_tokens,
-                total_tokens: response.usage.input_tokens + response.usage.output_tokens,
-                completion_tokens_details: None,
-                prompt_tokens_details: None,
-            },
+            usage: (&response.usage).into(),
             system_fingerprint: None,
+            service_tier: response.usage.service_tier,
         }
     }
 }
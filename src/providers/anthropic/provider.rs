@@ -1,45 +1,38 @@
 use async_trait::async_trait;
 use axum::http::StatusCode;
-use reqwest::Client;
+use serde::Serialize;
 use tracing::info;
 
-use super::models::{AnthropicChatCompletionRequest, AnthropicChatCompletionResponse};
+use super::models::{
+    AnthropicChatCompletionRequest, AnthropicChatCompletionResponse, AnthropicCountTokensRequest,
+    AnthropicCountTokensResponse, anthropic_max_tokens, anthropic_service_tier,
+};
 use crate::config::models::{ModelConfig, Provider as ProviderConfig};
 use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
 use crate::models::completion::{CompletionRequest, CompletionResponse};
 use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
-use crate::providers::provider::Provider;
+use crate::providers::capabilities::Capabilities;
+use crate::providers::provider::{Provider, base_url};
+use crate::providers::transport::{Auth, Transport, parse_json};
+use crate::providers::upstream::UpstreamRequest;
 use crate::types::ProviderType;
 
 pub struct AnthropicProvider {
-    api_key: String,
     config: ProviderConfig,
-    http_client: Client,
+    transport: Transport,
 }
 
-#[async_trait]
-impl Provider for AnthropicProvider {
-    fn new(config: &ProviderConfig) -> Self {
-        Self {
-            api_key: config.api_key.clone(),
-            config: config.clone(),
-            http_client: Client::new(),
-        }
+impl AnthropicProvider {
+    fn base_url(&self) -> String {
+        base_url(&self.config, "https://api.anthropic.com")
     }
 
-    fn key(&self) -> String {
-        self.config.key.clone()
-    }
-
-    fn r#type(&self) -> ProviderType {
-        ProviderType::Anthropic
-    }
-
-    async fn chat_completions(
-        &self,
+    /// Translates the request, returning the forced tool name when `response_format`
+    /// asks for structured output.
+    fn anthropic_request(
         payload: ChatCompletionRequest,
-        _model_config: &ModelConfig,
-    ) -> Result<ChatCompletionResponse, StatusCode> {
+        model_config: &ModelConfig,
+    ) -> Result<(AnthropicChatCompletionRequest, Option<String>), StatusCode> {
         // Validate reasoning config if present
         if let Some(reasoning) = &payload.reasoning {
             if let Err(e) = reasoning.validate() {
@@ -67,40 +60,112 @@ impl Provider for AnthropicProvider {
             }
         }
 
-        let request = AnthropicChatCompletionRequest::from(payload);
-        let response = self
-            .http_client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| {
-                eprintln!("Anthropic API request error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-        let status = response.status();
-        if status.is_success() {
-            if request.stream.unwrap_or(false) {
-                unimplemented!()
-            } else {
-                let anthropic_response: AnthropicChatCompletionResponse = response
-                    .json()
-                    .await
-                    .expect("Failed to parse Anthropic response");
-                Ok(ChatCompletionResponse::NonStream(anthropic_response.into()))
+        let priority = payload.priority;
+        let response_format = payload.response_format.clone();
+        let max_tokens = anthropic_max_tokens(&payload, &model_config.params);
+        let mut request = AnthropicChatCompletionRequest::from(payload);
+        request.max_tokens = max_tokens;
+        request.service_tier = priority
+            .and_then(anthropic_service_tier)
+            .map(str::to_string);
+        let structured_tool = match &response_format {
+            Some(response_format) => {
+                request
+                    .apply_response_format(response_format)
+                    .map_err(|e| {
+                        tracing::error!("Invalid response_format for Anthropic: {}", e);
+                        StatusCode::BAD_REQUEST
+                    })?
             }
-        } else {
-            eprintln!(
-                "Anthropic API request error: {}",
-                response.text().await.unwrap()
-            );
-            Err(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+            None => None,
+        };
+        Ok((request, structured_tool))
+    }
+
+    /// The request posting `request` to `path`, without credentials.
+    fn upstream_request(
+        &self,
+        path: &str,
+        request: &impl Serialize,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let upstream = UpstreamRequest::post(format!("{}{path}", self.base_url()), request)?;
+        Ok(upstream.header("anthropic-version", "2023-06-01"))
+    }
+
+    /// Maps a Messages API response body back to a chat completion.
+    fn parse_response(
+        body: &[u8],
+        structured_tool: Option<&str>,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        let response: AnthropicChatCompletionResponse =
+            parse_json(body, "anthropic.chat_completions")?;
+        Ok(ChatCompletionResponse::NonStream(match structured_tool {
+            Some(tool_name) => response.into_structured_completion(tool_name),
+            None => response.into(),
+        }))
+    }
+}
+
+#[async_trait]
+impl Provider for AnthropicProvider {
+    fn new(config: &ProviderConfig) -> Self {
+        Self {
+            config: config.clone(),
+            transport: Transport::new(config, "Anthropic", Auth::Header("x-api-key"))
+                .expect("Invalid HTTP client configuration for provider"),
         }
     }
 
+    fn key(&self) -> String {
+        self.config.key.clone()
+    }
+
+    fn r#type(&self) -> ProviderType {
+        ProviderType::Anthropic
+    }
+
+    fn unhealthy_reason(&self) -> Option<String> {
+        self.transport.unhealthy_reason()
+    }
+
+    fn capabilities(&self, _model_config: &ModelConfig) -> Capabilities {
+        Capabilities {
+            supports_streaming: false,
+            supports_tools: true,
+            supports_vision: false,
+            supports_completions: false,
+            supports_embeddings: false,
+            supports_n: false,
+            supports_logprobs: false,
+            supports_penalties: false,
+            supports_logit_bias: false,
+            supports_prediction: false,
+            supports_builtin_tools: false,
+            max_stop_sequences: None,
+        }
+    }
+
+    async fn chat_completions(
+        &self,
+        payload: ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        let extra_body = payload.extra_body.clone();
+        let (request, structured_tool) = Self::anthropic_request(payload, model_config)?;
+        if request.stream.unwrap_or(false) {
+            unimplemented!()
+        }
+        let upstream = self
+            .upstream_request("/v1/messages", &request)?
+            .with_extra_body(extra_body.as_ref())?;
+        let upstream = self.transport.authorize(upstream)?;
+        let body = self
+            .transport
+            .send_for_body(upstream, "anthropic.chat_completions")
+            .await?;
+        Self::parse_response(&body, structured_tool.as_deref())
+    }
+
     async fn completions(
         &self,
         _payload: CompletionRequest,
@@ -116,4 +181,37 @@ impl Provider for AnthropicProvider {
     ) -> Result<EmbeddingsResponse, StatusCode> {
         unimplemented!()
     }
+
+    async fn build_chat_request(
+        &self,
+        payload: &ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let (request, _) = Self::anthropic_request(payload.clone(), model_config)?;
+        let upstream = self
+            .upstream_request("/v1/messages", &request)?
+            .with_extra_body(payload.extra_body.as_ref())?;
+        self.transport.authorize(upstream)
+    }
+
+    async fn count_tokens(
+        &self,
+        payload: &ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<u32, StatusCode> {
+        let (request, _) = Self::anthropic_request(payload.clone(), model_config)?;
+        let request = AnthropicCountTokensRequest::from(request);
+        let upstream = self
+            .transport
+            .authorize(self.upstream_request("/v1/messages/count_tokens", &request)?)?;
+        let body = self
+            .transport
+            .send_for_body(upstream, "anthropic.count_tokens")
+            .await?;
+        let counted: AnthropicCountTokensResponse = serde_json::from_slice(&body).map_err(|e| {
+            tracing::error!("Failed to parse Anthropic count_tokens response: {e}");
+            StatusCode::BAD_GATEWAY
+        })?;
+        Ok(counted.input_tokens)
+    }
 }
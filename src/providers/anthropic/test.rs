@@ -1,13 +1,22 @@
 use super::models::{
     AnthropicChatCompletionRequest, AnthropicChatCompletionResponse, ContentBlock,
+    anthropic_max_tokens, max_output_tokens,
 };
 use super::provider::AnthropicProvider;
 use crate::config::models::{ModelConfig, Provider as ProviderConfig};
 use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
 use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
+use crate::models::messages::MessagesResponse;
+use crate::models::response_format::{JsonSchema, ResponseFormat};
 use crate::models::tool_choice::{SimpleToolChoice, ToolChoice};
-use crate::models::tool_definition::{FunctionDefinition, ToolDefinition};
+use crate::models::tool_definition::{FunctionDefinition, FunctionTool, ToolDefinition};
+use crate::providers::contract_tests::{
+    COMPLETION_TOKENS, ContractTemplates, MODEL, PROMPT_TOKENS, STOP_SEQUENCE, TOOL_NAME,
+    provider_contract_tests,
+};
 use crate::providers::provider::Provider;
+use crate::types::ProviderType;
+use axum::http::StatusCode;
 use serde_json::{Value, json};
 use std::collections::HashMap;
 use std::fs;
@@ -44,6 +53,7 @@ fn create_test_provider() -> AnthropicProvider {
         key: "anthropic".to_string(),
         r#type: crate::types::ProviderType::Anthropic,
         api_key,
+        maintenance_windows: vec![],
         params: HashMap::new(),
     })
 }
@@ -54,11 +64,13 @@ fn create_model_config() -> ModelConfig {
         r#type: "claude-sonnet-4-20250514".to_string(),
         provider: "anthropic".to_string(),
         params: HashMap::new(),
+        enabled: true,
+        deprecation: Default::default(),
     }
 }
 
 fn create_weather_tool_definition() -> ToolDefinition {
-    ToolDefinition {
+    ToolDefinition::Function(FunctionTool {
         tool_type: "function".to_string(),
         function: FunctionDefinition {
             name: "get_weather".to_string(),
@@ -83,7 +95,7 @@ fn create_weather_tool_definition() -> ToolDefinition {
             ),
             strict: None,
         },
-    }
+    })
 }
 
 #[tokio::test]
@@ -154,6 +166,7 @@ async fn test_chat_completions_basic() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(0.0),
         top_p: None,
@@ -174,6 +187,12 @@ async fn test_chat_completions_basic() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let response = provider
@@ -283,6 +302,7 @@ async fn test_chat_completions_with_tool_calls() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(0.0),
         top_p: None,
@@ -303,6 +323,12 @@ async fn test_chat_completions_with_tool_calls() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let response = provider
@@ -419,7 +445,12 @@ fn test_anthropic_response_to_chat_completion() {
         usage: super::models::Usage {
             input_tokens: 10,
             output_tokens: 5,
+            cache_creation_input_tokens: None,
+            cache_read_input_tokens: None,
+            service_tier: None,
         },
+        stop_reason: None,
+        stop_sequence: None,
     };
 
     let completion: crate::models::chat::ChatCompletion = response.into();
@@ -444,6 +475,7 @@ fn test_request_drops_top_p_when_both_temperature_and_top_p_set() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(0.7),
         top_p: Some(0.9),
@@ -464,6 +496,12 @@ fn test_request_drops_top_p_when_both_temperature_and_top_p_set() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let anthropic_request = AnthropicChatCompletionRequest::from(request);
@@ -485,6 +523,7 @@ fn test_request_preserves_top_p_when_temperature_absent() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: None,
         top_p: Some(0.9),
@@ -505,6 +544,12 @@ fn test_request_preserves_top_p_when_temperature_absent() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let anthropic_request = AnthropicChatCompletionRequest::from(request);
@@ -515,3 +560,578 @@ fn test_request_preserves_top_p_when_temperature_absent() {
         "top_p should be preserved when temperature is absent"
     );
 }
+
+#[test]
+fn test_priority_maps_to_service_tier() {
+    use super::models::anthropic_service_tier;
+    use crate::types::RequestPriority;
+
+    assert_eq!(
+        anthropic_service_tier(RequestPriority::Low),
+        Some("standard_only")
+    );
+    assert_eq!(anthropic_service_tier(RequestPriority::Default), None);
+    assert_eq!(anthropic_service_tier(RequestPriority::High), Some("auto"));
+}
+
+#[test]
+fn test_response_service_tier_is_reported() {
+    let response: AnthropicChatCompletionResponse = serde_json::from_value(json!({
+        "id": "msg_123",
+        "model": "claude-sonnet-4-20250514",
+        "content": [{"type": "text", "text": "Hello!"}],
+        "usage": {"input_tokens": 10, "output_tokens": 5, "service_tier": "priority"}
+    }))
+    .unwrap();
+
+    let completion: crate::models::chat::ChatCompletion = response.into();
+    assert_eq!(completion.service_tier.as_deref(), Some("priority"));
+}
+
+fn structured_output_request() -> AnthropicChatCompletionRequest {
+    AnthropicChatCompletionRequest::from(ChatCompletionRequest {
+        model: "claude-sonnet-4-20250514".to_string(),
+        messages: vec![ChatCompletionMessage {
+            role: "user".to_string(),
+            content: Some(ChatMessageContent::String("Describe Paris".to_string())),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+            refusal: None,
+            annotations: None,
+        }],
+        temperature: None,
+        top_p: None,
+        n: None,
+        stream: None,
+        stop: None,
+        max_tokens: Some(100),
+        presence_penalty: None,
+        frequency_penalty: None,
+        logit_bias: None,
+        user: None,
+        tools: None,
+        tool_choice: None,
+        parallel_tool_calls: None,
+        max_completion_tokens: None,
+        logprobs: None,
+        top_logprobs: None,
+        response_format: None,
+        reasoning: None,
+        reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
+    })
+}
+
+fn city_response_format(strict: Option<bool>, schema: Value) -> ResponseFormat {
+    ResponseFormat {
+        r#type: "json_schema".to_string(),
+        json_schema: Some(JsonSchema {
+            name: "city".to_string(),
+            description: Some("A city".to_string()),
+            schema: Some(schema),
+            strict,
+        }),
+    }
+}
+
+#[test]
+fn test_request_conversion_with_json_schema() {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "name": {"type": "string"},
+            "population": {"type": "integer"}
+        },
+        "required": ["name"]
+    });
+    let mut request = structured_output_request();
+
+    let tool_name = request
+        .apply_response_format(&city_response_format(Some(true), schema.clone()))
+        .unwrap();
+
+    assert_eq!(tool_name.as_deref(), Some("city"));
+    assert_eq!(request.tools.len(), 1);
+    assert_eq!(request.tools[0].name, "city");
+    assert_eq!(request.tools[0].input_schema, schema);
+    let serialized = serde_json::to_value(&request).unwrap();
+    assert_eq!(
+        serialized["tool_choice"],
+        json!({"type": "tool", "name": "city", "disable_parallel_tool_use": true})
+    );
+    assert!(request.system.is_none());
+}
+
+#[test]
+fn test_request_conversion_with_json_object() {
+    let mut request = structured_output_request();
+    request.system = Some("You are helpful.".to_string());
+
+    let tool_name = request
+        .apply_response_format(&ResponseFormat {
+            r#type: "json_object".to_string(),
+            json_schema: None,
+        })
+        .unwrap();
+
+    assert!(tool_name.is_none());
+    assert!(request.tools.is_empty());
+    assert!(request.tool_choice.is_none());
+    let system = request.system.unwrap();
+    assert!(system.starts_with("You are helpful.\n\n"));
+    assert!(system.contains("valid JSON object"));
+}
+
+#[test]
+fn test_strict_schema_with_unsupported_keyword_is_rejected() {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "minimum": {"type": "string"},
+            "population": {"type": "integer", "minimum": 0}
+        }
+    });
+
+    let error = structured_output_request()
+        .apply_response_format(&city_response_format(Some(true), schema.clone()))
+        .unwrap_err();
+    assert!(error.contains("'minimum'"));
+
+    // Non-strict schemas are passed through as-is.
+    assert!(
+        structured_output_request()
+            .apply_response_format(&city_response_format(None, schema))
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_structured_response_unwraps_tool_input() {
+    let response: AnthropicChatCompletionResponse = serde_json::from_value(json!({
+        "id": "msg_123",
+        "model": "claude-sonnet-4-20250514",
+        "content": [{
+            "type": "tool_use",
+            "id": "toolu_1",
+            "name": "city",
+            "input": {"name": "Paris", "population": 2102650}
+        }],
+        "usage": {"input_tokens": 10, "output_tokens": 5}
+    }))
+    .unwrap();
+
+    let completion = response.into_structured_completion("city");
+    let message = &completion.choices[0].message;
+    assert!(message.tool_calls.is_none());
+    match &message.content {
+        Some(ChatMessageContent::String(text)) => {
+            let parsed: Value = serde_json::from_str(text).unwrap();
+            assert_eq!(parsed, json!({"name": "Paris", "population": 2102650}));
+        }
+        _ => panic!("expected JSON string content"),
+    }
+}
+
+#[test]
+fn test_anthropic_refusal_maps_to_content_filter() {
+    let fixture = fs::read_to_string("tests/fixtures/anthropic_refusal.json")
+        .expect("Failed to read refusal fixture");
+    let response: AnthropicChatCompletionResponse =
+        serde_json::from_str(&fixture).expect("Failed to parse refusal fixture");
+
+    let completion: crate::models::chat::ChatCompletion = response.into();
+    let choice = &completion.choices[0];
+    assert_eq!(choice.finish_reason.as_deref(), Some("content_filter"));
+    assert!(choice.message.content.is_none());
+    assert_eq!(
+        choice.message.refusal.as_deref(),
+        Some("I can't help with that request.")
+    );
+}
+
+#[test]
+fn test_stop_sequences_are_sent_and_the_matched_one_reported() {
+    let request: ChatCompletionRequest = serde_json::from_value(json!({
+        "model": "claude-sonnet-4-20250514",
+        "messages": [{"role": "user", "content": "Say hi"}],
+        "stop": ["\n\nHuman:"]
+    }))
+    .unwrap();
+    let request = AnthropicChatCompletionRequest::from(request);
+    assert_eq!(request.stop_sequences, Some(vec!["\n\nHuman:".to_string()]));
+
+    let mut message = anthropic_message(json!([{"type": "text", "text": "Hi"}]), "stop_sequence");
+    message["stop_sequence"] = json!("\n\nHuman:");
+    let response: AnthropicChatCompletionResponse = serde_json::from_value(message).unwrap();
+    let completion: crate::models::chat::ChatCompletion = response.into();
+    let choice = &completion.choices[0];
+    assert_eq!(choice.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(choice.stop_sequence.as_deref(), Some("\n\nHuman:"));
+
+    // The Messages API reports it as Anthropic does.
+    let messages = MessagesResponse::from(completion);
+    assert_eq!(messages.stop_reason.as_deref(), Some("stop_sequence"));
+    assert_eq!(messages.stop_sequence.as_deref(), Some("\n\nHuman:"));
+}
+
+fn multi_turn_request() -> ChatCompletionRequest {
+    let fixture = fs::read_to_string("tests/fixtures/chat_multi_turn_tools.json")
+        .expect("Failed to read multi-turn fixture");
+    serde_json::from_str(&fixture).expect("Failed to parse multi-turn fixture")
+}
+
+#[test]
+fn test_multi_turn_conversion_with_tool_results() {
+    let anthropic_request = AnthropicChatCompletionRequest::from(multi_turn_request());
+    let body = serde_json::to_value(&anthropic_request).unwrap();
+
+    assert_eq!(
+        body["system"],
+        "You are a travel assistant.\n\nAnswer in metric units.\n\nKeep replies short."
+    );
+    assert_eq!(
+        body["messages"],
+        json!([
+            {"role": "user", "content": "What's the weather in Paris and Rome?"},
+            {"role": "assistant", "content": [
+                {"type": "text", "text": "Let me check both."},
+                {"type": "tool_use", "id": "call_paris", "name": "get_weather",
+                 "input": {"city": "Paris"}},
+                {"type": "tool_use", "id": "call_rome", "name": "get_weather",
+                 "input": {"city": "Rome"}}
+            ]},
+            // Both tool results and the follow-up merge into one user turn.
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "call_paris", "content": "{\"temp_c\":18}"},
+                {"type": "tool_result", "tool_use_id": "call_rome", "content": "sunny, 24C"},
+                {"type": "text", "text": "Thanks!"},
+                {"type": "text", "text": "Which is warmer?"}
+            ]}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_message_names_are_inlined_unless_disabled() {
+    use crate::ai_models::instance::ModelInstance;
+    use std::sync::Arc;
+
+    let model = |params: HashMap<String, String>| ModelInstance {
+        name: "claude".to_string(),
+        model_type: "claude-sonnet-4-20250514".to_string(),
+        provider: Arc::new(create_test_provider()),
+        config: ModelConfig {
+            key: "claude".to_string(),
+            r#type: "claude-sonnet-4-20250514".to_string(),
+            provider: "anthropic".to_string(),
+            params,
+            enabled: true,
+            deprecation: Default::default(),
+        },
+    };
+
+    let inlined = model(HashMap::new())
+        .build_chat_request(multi_turn_request())
+        .await
+        .unwrap();
+    assert_eq!(
+        inlined.body_json()["messages"][0]["content"],
+        "alice: What's the weather in Paris and Rome?"
+    );
+
+    let params = HashMap::from([("inline_message_names".to_string(), "false".to_string())]);
+    let dropped = model(params)
+        .build_chat_request(multi_turn_request())
+        .await
+        .unwrap();
+    assert_eq!(
+        dropped.body_json()["messages"][0]["content"],
+        "What's the weather in Paris and Rome?"
+    );
+}
+
+fn max_tokens_request(model: &str, fields: Value) -> ChatCompletionRequest {
+    let mut request = json!({"model": model, "messages": [{"role": "user", "content": "Hi"}]});
+    request
+        .as_object_mut()
+        .unwrap()
+        .extend(fields.as_object().unwrap().clone());
+    serde_json::from_value(request).unwrap()
+}
+
+#[test]
+fn test_max_tokens_precedence() {
+    let model = "claude-sonnet-4-20250514";
+    let default_param = HashMap::from([("default_max_tokens".to_string(), "2048".to_string())]);
+    let max_tokens = |fields: Value, params: &HashMap<String, String>| {
+        anthropic_max_tokens(&max_tokens_request(model, fields), params)
+    };
+
+    let both = json!({"max_completion_tokens": 300, "max_tokens": 200});
+    assert_eq!(max_tokens(both.clone(), &default_param), 300);
+    assert_eq!(max_tokens(both, &HashMap::new()), 300);
+    let only_max_tokens = json!({"max_tokens": 200});
+    assert_eq!(max_tokens(only_max_tokens.clone(), &default_param), 200);
+    assert_eq!(max_tokens(only_max_tokens, &HashMap::new()), 200);
+    // A zero `max_completion_tokens` counts as unset.
+    let zero = json!({"max_completion_tokens": 0, "max_tokens": 200});
+    assert_eq!(max_tokens(zero, &HashMap::new()), 200);
+    assert_eq!(max_tokens(json!({}), &default_param), 2048);
+    assert_eq!(max_tokens(json!({}), &HashMap::new()), 64_000);
+    assert_eq!(
+        anthropic_max_tokens(
+            &max_tokens_request("claude-3-5-haiku-latest", json!({})),
+            &HashMap::new()
+        ),
+        8_192
+    );
+}
+
+#[test]
+fn test_max_tokens_above_model_maximum_is_clamped() {
+    let request = max_tokens_request("claude-3-haiku-20240307", json!({"max_tokens": 100_000}));
+    assert_eq!(anthropic_max_tokens(&request, &HashMap::new()), 4_096);
+    let request = max_tokens_request(
+        "claude-opus-4-1-20250805",
+        json!({"max_completion_tokens": 50_000}),
+    );
+    assert_eq!(anthropic_max_tokens(&request, &HashMap::new()), 32_000);
+    let params = HashMap::from([("default_max_tokens".to_string(), "10000".to_string())]);
+    let request = max_tokens_request("claude-3-5-sonnet-20241022", json!({}));
+    assert_eq!(anthropic_max_tokens(&request, &params), 8_192);
+
+    // Models without a known maximum are sent what was asked for.
+    let request = max_tokens_request("claude-next", json!({"max_tokens": 100_000}));
+    assert_eq!(anthropic_max_tokens(&request, &HashMap::new()), 100_000);
+    assert_eq!(max_output_tokens("claude-next"), None);
+}
+
+#[tokio::test]
+async fn test_upstream_request_always_sets_max_tokens() {
+    let provider = create_test_provider();
+    let model_config = ModelConfig {
+        key: "claude".to_string(),
+        r#type: "claude-3-5-sonnet-20241022".to_string(),
+        provider: "anthropic".to_string(),
+        params: HashMap::from([("default_max_tokens".to_string(), "1024".to_string())]),
+        enabled: true,
+        deprecation: Default::default(),
+    };
+
+    for (fields, expected) in [
+        (json!({}), 1024),
+        (json!({"max_completion_tokens": 512}), 512),
+        (json!({"max_tokens": 20_000}), 8_192),
+    ] {
+        let request = max_tokens_request("claude-3-5-sonnet-20241022", fields);
+        let upstream = provider
+            .build_chat_request(&request, &model_config)
+            .await
+            .unwrap();
+        assert_eq!(upstream.body_json()["max_tokens"], expected);
+    }
+}
+
+/// The messages of an agent trace fixture, converted to Anthropic's format.
+fn converted_trace(name: &str) -> Value {
+    let fixture = fs::read_to_string(format!("tests/fixtures/{name}.json"))
+        .expect("Failed to read agent trace fixture");
+    let request: ChatCompletionRequest =
+        serde_json::from_str(&fixture).expect("Failed to parse agent trace fixture");
+    serde_json::to_value(AnthropicChatCompletionRequest::from(request)).unwrap()["messages"].clone()
+}
+
+#[test]
+fn test_agent_trace_with_sequential_tool_calls() {
+    assert_eq!(
+        converted_trace("agent_trace_multi_tool"),
+        json!([
+            {"role": "user", "content": "Summarize the latest Rust release notes."},
+            {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "toolu_search", "name": "web_search",
+                 "input": {"query": "rust release notes"}}
+            ]},
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_search",
+                 "content": "https://blog.rust-lang.org/releases/latest"}
+            ]},
+            {"role": "assistant", "content": [
+                {"type": "text", "text": "Fetching the announcement."},
+                {"type": "tool_use", "id": "toolu_fetch", "name": "fetch_url",
+                 "input": {"url": "https://blog.rust-lang.org/releases/latest"}}
+            ]},
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_fetch",
+                 "content": "Rust 1.88 stabilizes let chains."}
+            ]},
+            // Consecutive assistant messages merge into one turn.
+            {"role": "assistant", "content": [
+                {"type": "text", "text": "Rust 1.88 stabilizes let chains."},
+                {"type": "text", "text": "Anything else?"}
+            ]},
+            {"role": "user", "content": "No, thanks."}
+        ])
+    );
+}
+
+#[test]
+fn test_agent_trace_with_parallel_calls_orders_results() {
+    assert_eq!(
+        converted_trace("agent_trace_parallel_calls"),
+        json!([
+            {"role": "user", "content": "Compare the weather in Oslo, Lima and Pune."},
+            {"role": "assistant", "content": [
+                {"type": "text", "text": "Checking all three."},
+                {"type": "tool_use", "id": "toolu_oslo", "name": "get_weather",
+                 "input": {"city": "Oslo"}},
+                {"type": "tool_use", "id": "toolu_lima", "name": "get_weather",
+                 "input": {"city": "Lima"}},
+                {"type": "tool_use", "id": "toolu_pune", "name": "get_weather",
+                 "input": {"city": "Pune"}}
+            ]},
+            // Results come first, in call order, even the one sent after the user's text.
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_oslo", "content": "4C"},
+                {"type": "tool_result", "tool_use_id": "toolu_lima", "content": "19C"},
+                {"type": "tool_result", "tool_use_id": "toolu_pune", "content": "31C"},
+                {"type": "text", "text": "Lima is taking a while."}
+            ]}
+        ])
+    );
+}
+
+#[test]
+fn test_agent_trace_with_missing_results_is_repaired() {
+    let missing = |id: &str| {
+        json!({"type": "tool_result", "tool_use_id": id,
+               "content": "No result was provided for this tool call.", "is_error": true})
+    };
+    assert_eq!(
+        converted_trace("agent_trace_missing_results"),
+        json!([
+            {"role": "user", "content": "Book me the cheapest flight to Lisbon."},
+            {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "toolu_flights", "name": "search_flights",
+                 "input": {"to": "LIS"}},
+                {"type": "tool_use", "id": "toolu_prices", "name": "get_prices",
+                 "input": {"to": "LIS"}}
+            ]},
+            {"role": "user", "content": [
+                missing("toolu_flights"),
+                {"type": "tool_result", "tool_use_id": "toolu_prices",
+                 "content": "TP1234: 89 EUR"},
+                // A result for a call that was never made is kept as text.
+                {"type": "text", "text": "Result of tool call 'toolu_stale': cache hit"},
+                {"type": "text", "text": "Go ahead."}
+            ]},
+            {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "toolu_book", "name": "book_flight",
+                 "input": {"flight": "TP1234"}}
+            ]},
+            // A trailing unanswered call gets its own user turn.
+            {"role": "user", "content": [missing("toolu_book")]}
+        ])
+    );
+}
+
+#[test]
+fn test_usage_counts_prompt_cache_tokens() {
+    let fixture = fs::read_to_string("tests/fixtures/provider_usage.json")
+        .expect("Failed to read usage fixture");
+    let recorded = serde_json::from_str::<Value>(&fixture).unwrap()["anthropic"].clone();
+    let response: AnthropicChatCompletionResponse =
+        serde_json::from_value(recorded.clone()).unwrap();
+
+    let completion: crate::models::chat::ChatCompletion = response.into();
+    let usage = &completion.usage;
+    // OpenAI's prompt tokens include the cached ones.
+    assert_eq!(usage.prompt_tokens, 12 + 248 + 1800);
+    assert_eq!(usage.cached_tokens(), 1800);
+    assert_eq!(usage.cache_creation_tokens(), 248);
+    assert_eq!(
+        usage.prompt_tokens + usage.completion_tokens,
+        usage.total_tokens
+    );
+    // Cache writes have no OpenAI field.
+    assert_eq!(
+        serde_json::to_value(usage).unwrap(),
+        json!({
+            "prompt_tokens": 2060,
+            "completion_tokens": 40,
+            "total_tokens": 2100,
+            "prompt_tokens_details": {"cached_tokens": 1800}
+        })
+    );
+
+    // The Messages API reports Anthropic's own counts again.
+    let messages = MessagesResponse::from(completion);
+    assert_eq!(
+        serde_json::to_value(messages.usage).unwrap(),
+        recorded["usage"]
+    );
+}
+
+struct AnthropicTemplates;
+
+impl ContractTemplates for AnthropicTemplates {
+    type Provider = AnthropicProvider;
+
+    const PROVIDER_TYPE: ProviderType = ProviderType::Anthropic;
+
+    fn chat_path(_stream: bool) -> String {
+        "/v1/messages".to_string()
+    }
+
+    fn text_response(text: &str) -> Value {
+        anthropic_message(json!([{"type": "text", "text": text}]), "end_turn")
+    }
+
+    fn empty_response() -> Value {
+        anthropic_message(json!([]), "end_turn")
+    }
+
+    fn tool_call_response(arguments: &Value) -> Value {
+        let content = json!([{
+            "type": "tool_use",
+            "id": "toolu_contract",
+            "name": TOOL_NAME,
+            "input": arguments
+        }]);
+        anthropic_message(content, "tool_use")
+    }
+
+    fn stop_sequence_response(text: &str) -> Value {
+        let mut message =
+            anthropic_message(json!([{"type": "text", "text": text}]), "stop_sequence");
+        message["stop_sequence"] = json!(STOP_SEQUENCE);
+        message
+    }
+
+    fn error_response(status: StatusCode) -> Value {
+        json!({
+            "type": "error",
+            "error": {"type": "api_error", "message": status.canonical_reason()}
+        })
+    }
+}
+
+fn anthropic_message(content: Value, stop_reason: &str) -> Value {
+    json!({
+        "id": "msg_contract",
+        "type": "message",
+        "role": "assistant",
+        "model": MODEL,
+        "content": content,
+        "stop_reason": stop_reason,
+        "stop_sequence": null,
+        "usage": {"input_tokens": PROMPT_TOKENS, "output_tokens": COMPLETION_TOKENS}
+    })
+}
+
+provider_contract_tests!(AnthropicTemplates);
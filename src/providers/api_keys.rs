@@ -0,0 +1,310 @@
+use reqwest::StatusCode;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::info;
+
+use crate::config::models::Provider as ProviderConfig;
+use crate::logging::error_rate_limited;
+use crate::management::dto::SecretObject;
+use crate::management::services::secret_resolver::SecretResolver;
+
+/// Provider param holding a second API key, used while the primary is being rotated.
+pub const API_KEY_SECONDARY_PARAM: &str = "api_key_secondary";
+/// Provider param with the path of a file holding the API key, such as a mounted
+/// Kubernetes secret. Used instead of `api_key`.
+pub const API_KEY_FILE_PARAM: &str = "api_key_file";
+/// Provider param with how often, in seconds, `api_key_file` is checked for changes.
+pub const API_KEY_FILE_REFRESH_PARAM: &str = "api_key_file_refresh_seconds";
+const DEFAULT_API_KEY_FILE_REFRESH: Duration = Duration::from_secs(10);
+/// Provider param with a secret reference, as `SecretObject` JSON, that the API key is
+/// resolved from on first use rather than when the config is built. Used instead of
+/// `api_key`.
+pub const API_KEY_SECRET_PARAM: &str = "api_key_secret";
+/// Provider param with how long, in seconds, a failure to resolve `api_key_secret` is
+/// cached before requests try again.
+pub const API_KEY_SECRET_RETRY_PARAM: &str = "api_key_secret_retry_seconds";
+const DEFAULT_API_KEY_SECRET_RETRY: Duration = Duration::from_secs(30);
+/// The background retry of an unresolved secret never runs more often than this.
+const MIN_BACKGROUND_RETRY: Duration = Duration::from_secs(1);
+/// Provider param listing the provider's secrets that couldn't be resolved when the
+/// config was built. Such a provider answers 503 until a later config resolves them.
+pub const UNRESOLVED_SECRETS_PARAM: &str = "unresolved_secrets";
+/// Counts requests whose primary key the upstream rejected, labelled by provider.
+pub const SUSPECT_KEY_METRIC: &str = "hub_provider_key_suspect_total";
+
+/// How often `api_key_file` is checked for changes, if the param is valid.
+pub fn api_key_file_refresh(config: &ProviderConfig) -> Result<Duration, String> {
+    match config.params.get(API_KEY_FILE_REFRESH_PARAM) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map(Duration::from_secs)
+            .map_err(|_| format!("'{value}' is not a whole number of seconds")),
+        None => Ok(DEFAULT_API_KEY_FILE_REFRESH),
+    }
+}
+
+/// How long a failure to resolve `api_key_secret` is cached, if the param is valid.
+pub fn api_key_secret_retry(config: &ProviderConfig) -> Result<Duration, String> {
+    match config.params.get(API_KEY_SECRET_RETRY_PARAM) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map(Duration::from_secs)
+            .map_err(|_| format!("'{value}' is not a whole number of seconds")),
+        None => Ok(DEFAULT_API_KEY_SECRET_RETRY),
+    }
+}
+
+/// The secret reference in `api_key_secret`, if the param is set and valid.
+pub fn api_key_secret(config: &ProviderConfig) -> Option<Result<SecretObject, String>> {
+    let secret = config.params.get(API_KEY_SECRET_PARAM)?;
+    Some(serde_json::from_str(secret).map_err(|e| e.to_string()))
+}
+
+/// A provider's primary API key: the configured `api_key`, the contents of
+/// `api_key_file`, or the value `api_key_secret` refers to.
+pub enum ApiKey {
+    Static(String),
+    File(ApiKeyFile),
+    Secret(ApiKeySecret),
+}
+
+impl ApiKey {
+    pub fn from_config(config: &ProviderConfig) -> Self {
+        if let Some(path) = config.params.get(API_KEY_FILE_PARAM) {
+            return ApiKey::File(ApiKeyFile {
+                provider_key: config.key.clone(),
+                path: PathBuf::from(path),
+                refresh: api_key_file_refresh(config).unwrap_or(DEFAULT_API_KEY_FILE_REFRESH),
+                state: Mutex::default(),
+            });
+        }
+        match api_key_secret(config) {
+            Some(secret) => ApiKey::Secret(ApiKeySecret {
+                provider_key: config.key.clone(),
+                secret,
+                retry: api_key_secret_retry(config).unwrap_or(DEFAULT_API_KEY_SECRET_RETRY),
+                state: Arc::default(),
+            }),
+            None => ApiKey::Static(config.api_key.clone()),
+        }
+    }
+
+    /// Whether a key is configured at all, as opposed to another way of authenticating.
+    pub fn is_configured(&self) -> bool {
+        match self {
+            ApiKey::Static(key) => !key.is_empty(),
+            ApiKey::File(_) | ApiKey::Secret(_) => true,
+        }
+    }
+
+    /// The current key. Fails with 503 while the key file can't be read or the secret
+    /// can't be resolved.
+    pub fn get(&self) -> Result<String, StatusCode> {
+        match self {
+            ApiKey::Static(key) => Ok(key.clone()),
+            ApiKey::File(file) => file.key(),
+            ApiKey::Secret(secret) => secret.key(),
+        }
+    }
+
+    /// Why the key can't be read, reported by `/health`. A key file or secret that hasn't
+    /// been needed yet is read now, so a bad one shows up before the first request.
+    pub fn unhealthy_reason(&self) -> Option<String> {
+        match self {
+            ApiKey::Static(_) => None,
+            ApiKey::File(file) => {
+                let _ = file.key();
+                file.state.lock().unwrap().last_error.clone()
+            }
+            ApiKey::Secret(secret) => {
+                let _ = secret.key();
+                secret.state.lock().unwrap().last_error.clone()
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct KeyFileState {
+    key: Option<String>,
+    /// Modification time and length of the file the key was read from.
+    version: Option<(SystemTime, u64)>,
+    checked_at: Option<Instant>,
+    last_error: Option<String>,
+}
+
+/// An API key read lazily from a file and re-read when the file changes, so rotated
+/// secrets are picked up without a config reload. The file is checked at most once per
+/// refresh interval.
+pub struct ApiKeyFile {
+    provider_key: String,
+    path: PathBuf,
+    refresh: Duration,
+    state: Mutex<KeyFileState>,
+}
+
+impl ApiKeyFile {
+    fn key(&self) -> Result<String, StatusCode> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let fresh = state
+            .checked_at
+            .is_some_and(|checked_at| now.duration_since(checked_at) < self.refresh);
+        if !fresh {
+            state.checked_at = Some(now);
+            if let Err(e) = self.reload(&mut state) {
+                error_rate_limited(
+                    "provider.api_key_file",
+                    format!(
+                        "Failed to read the API key of provider '{}' from '{}': {e}",
+                        self.provider_key,
+                        self.path.display()
+                    ),
+                );
+                state.key = None;
+                state.version = None;
+                state.last_error = Some(format!("API key file '{}': {e}", self.path.display()));
+            }
+        }
+        state.key.clone().ok_or(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    /// Reads the file again if it changed since it was last read.
+    fn reload(&self, state: &mut KeyFileState) -> Result<(), String> {
+        let metadata = std::fs::metadata(&self.path).map_err(|e| e.to_string())?;
+        let version = (
+            metadata.modified().map_err(|e| e.to_string())?,
+            metadata.len(),
+        );
+        if state.key.is_some() && state.version == Some(version) {
+            return Ok(());
+        }
+        let contents = std::fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        let key = contents.trim();
+        if key.is_empty() {
+            return Err("the file is empty".to_string());
+        }
+        state.key = Some(key.to_string());
+        state.version = Some(version);
+        state.last_error = None;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct KeySecretState {
+    key: Option<String>,
+    failed_at: Option<Instant>,
+    last_error: Option<String>,
+    /// Whether a background task is retrying the secret.
+    retrying: bool,
+}
+
+/// An API key resolved from a secret reference on first use, so a secret that can't be
+/// resolved fails this provider's requests instead of the config it came with. A failure
+/// is cached for the retry interval, while a background task keeps retrying until the
+/// secret resolves or the provider is dropped.
+pub struct ApiKeySecret {
+    provider_key: String,
+    /// The reference, or why `api_key_secret` couldn't be parsed.
+    secret: Result<SecretObject, String>,
+    retry: Duration,
+    state: Arc<Mutex<KeySecretState>>,
+}
+
+impl ApiKeySecret {
+    fn key(&self) -> Result<String, StatusCode> {
+        let mut state = self.state.lock().unwrap();
+        let due = state
+            .failed_at
+            .is_none_or(|failed_at| failed_at.elapsed() >= self.retry);
+        if state.key.is_none() && due {
+            let resolved = resolve_secret(&self.secret);
+            record_resolution(&self.provider_key, &mut state, resolved);
+            if state.key.is_none() && !state.retrying {
+                state.retrying = self.spawn_retry();
+            }
+        }
+        state.key.clone().ok_or(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    /// Retries the secret in the background. Returns whether a task was started, which
+    /// needs a Tokio runtime.
+    fn spawn_retry(&self) -> bool {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return false;
+        };
+        let provider_key = self.provider_key.clone();
+        let secret = self.secret.clone();
+        let interval = self.retry.max(MIN_BACKGROUND_RETRY);
+        let state: Weak<Mutex<KeySecretState>> = Arc::downgrade(&self.state);
+        runtime.spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(shared) = state.upgrade() else {
+                    return;
+                };
+                let mut state = shared.lock().unwrap();
+                if state.key.is_none() {
+                    let resolved = resolve_secret(&secret);
+                    record_resolution(&provider_key, &mut state, resolved);
+                }
+                if state.key.is_some() {
+                    state.retrying = false;
+                    return;
+                }
+            }
+        });
+        true
+    }
+}
+
+fn resolve_secret(secret: &Result<SecretObject, String>) -> Result<String, String> {
+    let secret = secret
+        .as_ref()
+        .map_err(|e| format!("invalid {API_KEY_SECRET_PARAM}: {e}"))?;
+    let key = SecretResolver::new()
+        .resolve(secret)
+        .map_err(|e| e.to_string())?;
+    if key.is_empty() {
+        return Err("the secret is empty".to_string());
+    }
+    Ok(key)
+}
+
+fn record_resolution(
+    provider_key: &str,
+    state: &mut KeySecretState,
+    resolved: Result<String, String>,
+) {
+    match resolved {
+        Ok(key) => {
+            if state.last_error.is_some() {
+                info!("Resolved the API key secret of provider '{provider_key}'");
+            }
+            state.key = Some(key);
+            state.failed_at = None;
+            state.last_error = None;
+        }
+        Err(e) => {
+            error_rate_limited(
+                "provider.api_key_secret",
+                format!("Failed to resolve the API key secret of provider '{provider_key}': {e}"),
+            );
+            state.failed_at = Some(Instant::now());
+            state.last_error = Some(format!("API key secret: {e}"));
+        }
+    }
+}
+
+/// The provider's secondary API key, if one is configured.
+pub fn secondary_api_key(config: &ProviderConfig) -> Option<&str> {
+    config
+        .params
+        .get(API_KEY_SECONDARY_PARAM)
+        .map(String::as_str)
+        .filter(|key| !key.is_empty())
+}
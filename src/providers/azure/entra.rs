@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use axum::http::StatusCode;
+use reqwest::Client;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::logging::error_rate_limited;
+
+/// Provider param selecting how requests are authorized: `api_key` (default) or `entra_id`.
+pub const AUTH_TYPE_PARAM: &str = "auth_type";
+/// Directory (tenant) ID of the app registration used for client-credential tokens.
+pub const TENANT_ID_PARAM: &str = "tenant_id";
+/// Application ID of the app registration, or of a user-assigned managed identity.
+pub const CLIENT_ID_PARAM: &str = "client_id";
+/// Client secret of the app registration.
+pub const CLIENT_SECRET_PARAM: &str = "client_secret";
+/// Overrides `https://login.microsoftonline.com`, e.g. for sovereign clouds.
+pub const AUTHORITY_HOST_PARAM: &str = "authority_host";
+/// Overrides the instance metadata (IMDS) token endpoint used for managed identities.
+pub const IMDS_ENDPOINT_PARAM: &str = "imds_endpoint";
+
+const DEFAULT_AUTHORITY_HOST: &str = "https://login.microsoftonline.com";
+const DEFAULT_IMDS_ENDPOINT: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+const COGNITIVE_SERVICES_RESOURCE: &str = "https://cognitiveservices.azure.com";
+const IMDS_TIMEOUT: Duration = Duration::from_secs(10);
+/// Tokens are refreshed this long before they expire, or halfway through shorter lifetimes.
+const MAX_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+/// After a failed token request, requests fail fast for this long before retrying.
+const FAILURE_BACKOFF: Duration = Duration::from_secs(10);
+
+/// How the Azure provider authorizes upstream requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthType {
+    ApiKey,
+    EntraId,
+}
+
+impl AuthType {
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, String> {
+        match params.get(AUTH_TYPE_PARAM).map(String::as_str) {
+            None | Some("api_key") => Ok(AuthType::ApiKey),
+            Some("entra_id") => Ok(AuthType::EntraId),
+            Some(other) => Err(format!(
+                "unknown auth_type '{other}', expected 'api_key' or 'entra_id'"
+            )),
+        }
+    }
+}
+
+/// Checks the auth params of an Azure provider without building it.
+pub fn validate_auth_params(params: &HashMap<String, String>) -> Result<(), String> {
+    match AuthType::from_params(params)? {
+        AuthType::ApiKey => Ok(()),
+        AuthType::EntraId => TokenSource::from_params(params).map(|_| ()),
+    }
+}
+
+enum TokenSource {
+    /// An app registration's client secret, exchanged at the tenant's token endpoint.
+    ClientCredentials {
+        token_url: String,
+        client_id: String,
+        client_secret: String,
+    },
+    /// The managed identity of the VM or container, from the instance metadata service.
+    ManagedIdentity {
+        endpoint: String,
+        client_id: Option<String>,
+    },
+}
+
+impl TokenSource {
+    fn from_params(params: &HashMap<String, String>) -> Result<Self, String> {
+        let param = |name: &str| params.get(name).filter(|v| !v.is_empty()).cloned();
+        match (
+            param(TENANT_ID_PARAM),
+            param(CLIENT_ID_PARAM),
+            param(CLIENT_SECRET_PARAM),
+        ) {
+            (Some(tenant_id), Some(client_id), Some(client_secret)) => {
+                let authority_host =
+                    param(AUTHORITY_HOST_PARAM).unwrap_or_else(|| DEFAULT_AUTHORITY_HOST.into());
+                Ok(TokenSource::ClientCredentials {
+                    token_url: format!(
+                        "{}/{tenant_id}/oauth2/v2.0/token",
+                        authority_host.trim_end_matches('/')
+                    ),
+                    client_id,
+                    client_secret,
+                })
+            }
+            (None, client_id, None) => Ok(TokenSource::ManagedIdentity {
+                endpoint: param(IMDS_ENDPOINT_PARAM)
+                    .unwrap_or_else(|| DEFAULT_IMDS_ENDPOINT.into()),
+                client_id,
+            }),
+            _ => Err(
+                "entra_id auth needs tenant_id, client_id and client_secret, \
+                 or no tenant_id and client_secret to use a managed identity"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Seconds,
+}
+
+/// The token endpoint sends `expires_in` as a number, IMDS as a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Seconds {
+    Number(u64),
+    Text(String),
+}
+
+impl Seconds {
+    fn to_duration(&self) -> Result<Duration> {
+        match self {
+            Seconds::Number(secs) => Ok(Duration::from_secs(*secs)),
+            Seconds::Text(text) => text
+                .parse()
+                .map(Duration::from_secs)
+                .map_err(|_| anyhow!("invalid expires_in '{text}'")),
+        }
+    }
+}
+
+struct CachedToken {
+    access_token: String,
+    refresh_at: Instant,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct TokenState {
+    token: Option<CachedToken>,
+    last_error: Option<String>,
+    retry_at: Option<Instant>,
+}
+
+/// Fetches and caches Entra ID access tokens for Azure OpenAI. While no valid token can
+/// be obtained, requests fail with 503 and the provider reports itself unhealthy.
+pub struct EntraTokenProvider {
+    provider_key: String,
+    source: TokenSource,
+    http_client: Client,
+    state: Mutex<TokenState>,
+    refresh_lock: tokio::sync::Mutex<()>,
+}
+
+impl EntraTokenProvider {
+    /// `http_client` carries the provider's proxy and TLS settings and is used for the
+    /// token endpoint. Managed identities always talk to IMDS directly.
+    pub fn from_params(
+        provider_key: &str,
+        params: &HashMap<String, String>,
+        http_client: Client,
+    ) -> Result<Self, String> {
+        let source = TokenSource::from_params(params)?;
+        let http_client = match source {
+            TokenSource::ClientCredentials { .. } => http_client,
+            TokenSource::ManagedIdentity { .. } => Client::builder()
+                .no_proxy()
+                .timeout(IMDS_TIMEOUT)
+                .build()
+                .map_err(|e| format!("failed to build IMDS client: {e}"))?,
+        };
+        Ok(Self {
+            provider_key: provider_key.to_string(),
+            source,
+            http_client,
+            state: Mutex::new(TokenState::default()),
+            refresh_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Returns a cached token, fetching a new one when it is due for refresh. If the
+    /// refresh fails, a token that hasn't expired yet is still returned.
+    pub async fn token(&self) -> Result<String, StatusCode> {
+        if let Some(token) = self.cached_token(|t| t.refresh_at) {
+            return Ok(token);
+        }
+        let _refresh = self.refresh_lock.lock().await;
+        // Another request may have refreshed the token while this one waited.
+        if let Some(token) = self.cached_token(|t| t.refresh_at) {
+            return Ok(token);
+        }
+        let backing_off = self
+            .state
+            .lock()
+            .unwrap()
+            .retry_at
+            .is_some_and(|retry_at| Instant::now() < retry_at);
+        if backing_off {
+            return self.unexpired_token();
+        }
+
+        match self.fetch_token().await {
+            Ok(token) => {
+                let access_token = token.access_token.clone();
+                let mut state = self.state.lock().unwrap();
+                state.token = Some(token);
+                state.last_error = None;
+                state.retry_at = None;
+                Ok(access_token)
+            }
+            Err(e) => {
+                error_rate_limited(
+                    "azure.entra_id.token",
+                    format!(
+                        "Entra ID token request for provider '{}' failed: {e:#}",
+                        self.provider_key
+                    ),
+                );
+                {
+                    let mut state = self.state.lock().unwrap();
+                    state.last_error = Some(format!("{e:#}"));
+                    state.retry_at = Some(Instant::now() + FAILURE_BACKOFF);
+                }
+                self.unexpired_token()
+            }
+        }
+    }
+
+    /// Why no token is available, if the last token request failed and no unexpired
+    /// token is cached.
+    pub fn unhealthy_reason(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        let error = state.last_error.as_ref()?;
+        let has_valid_token = state
+            .token
+            .as_ref()
+            .is_some_and(|t| Instant::now() < t.expires_at);
+        (!has_valid_token).then(|| format!("Entra ID token request failed: {error}"))
+    }
+
+    fn cached_token(&self, valid_until: impl Fn(&CachedToken) -> Instant) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state
+            .token
+            .as_ref()
+            .filter(|t| Instant::now() < valid_until(t))
+            .map(|t| t.access_token.clone())
+    }
+
+    fn unexpired_token(&self) -> Result<String, StatusCode> {
+        self.cached_token(|t| t.expires_at)
+            .ok_or(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    async fn fetch_token(&self) -> Result<CachedToken> {
+        let request = match &self.source {
+            TokenSource::ClientCredentials {
+                token_url,
+                client_id,
+                client_secret,
+            } => self.http_client.post(token_url).form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("scope", &format!("{COGNITIVE_SERVICES_RESOURCE}/.default")),
+            ]),
+            TokenSource::ManagedIdentity {
+                endpoint,
+                client_id,
+            } => {
+                let mut query = vec![
+                    ("api-version", "2018-02-01"),
+                    ("resource", COGNITIVE_SERVICES_RESOURCE),
+                ];
+                if let Some(client_id) = client_id {
+                    query.push(("client_id", client_id.as_str()));
+                }
+                self.http_client
+                    .get(endpoint)
+                    .header("Metadata", "true")
+                    .query(&query)
+            }
+        };
+
+        let requested_at = Instant::now();
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow!("token endpoint returned {status}"));
+        }
+        let token: TokenResponse = response.json().await?;
+        let lifetime = token.expires_in.to_duration()?;
+        debug!(
+            provider = %self.provider_key,
+            expires_in = lifetime.as_secs(),
+            "Fetched Entra ID token"
+        );
+        Ok(CachedToken {
+            access_token: token.access_token,
+            refresh_at: requested_at + lifetime - MAX_REFRESH_MARGIN.min(lifetime / 2),
+            expires_at: requested_at + lifetime,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client_credential_params(server: &MockServer) -> HashMap<String, String> {
+        HashMap::from([
+            (AUTH_TYPE_PARAM.to_string(), "entra_id".to_string()),
+            (TENANT_ID_PARAM.to_string(), "tenant".to_string()),
+            (CLIENT_ID_PARAM.to_string(), "client".to_string()),
+            (CLIENT_SECRET_PARAM.to_string(), "secret".to_string()),
+            (AUTHORITY_HOST_PARAM.to_string(), server.uri()),
+        ])
+    }
+
+    fn token_response(token: &str, expires_in: serde_json::Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "token_type": "Bearer",
+            "access_token": token,
+            "expires_in": expires_in,
+        }))
+    }
+
+    fn expire_cached_token(provider: &EntraTokenProvider) {
+        let mut state = provider.state.lock().unwrap();
+        let token = state.token.as_mut().unwrap();
+        token.refresh_at = Instant::now();
+        token.expires_at = Instant::now();
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials_token_is_cached_and_refreshed_on_expiry() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/tenant/oauth2/v2.0/token"))
+            .and(body_string_contains("grant_type=client_credentials"))
+            .and(body_string_contains("client_secret=secret"))
+            .and(body_string_contains(
+                "scope=https%3A%2F%2Fcognitiveservices.azure.com%2F.default",
+            ))
+            .respond_with(token_response("first", json!(3600)))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/tenant/oauth2/v2.0/token"))
+            .respond_with(token_response("second", json!(3600)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = EntraTokenProvider::from_params(
+            "azure",
+            &client_credential_params(&server),
+            Client::new(),
+        )
+        .unwrap();
+        assert_eq!(provider.token().await.unwrap(), "first");
+        assert_eq!(provider.token().await.unwrap(), "first");
+
+        expire_cached_token(&provider);
+        assert_eq!(provider.token().await.unwrap(), "second");
+        assert!(provider.unhealthy_reason().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_managed_identity_token_with_string_expiry() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/metadata/identity/oauth2/token"))
+            .and(header("Metadata", "true"))
+            .and(query_param("resource", COGNITIVE_SERVICES_RESOURCE))
+            .and(query_param("client_id", "user-assigned"))
+            .respond_with(token_response("imds-token", json!("86399")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let params = HashMap::from([
+            (AUTH_TYPE_PARAM.to_string(), "entra_id".to_string()),
+            (CLIENT_ID_PARAM.to_string(), "user-assigned".to_string()),
+            (
+                IMDS_ENDPOINT_PARAM.to_string(),
+                format!("{}/metadata/identity/oauth2/token", server.uri()),
+            ),
+        ]);
+        let provider = EntraTokenProvider::from_params("azure", &params, Client::new()).unwrap();
+        assert_eq!(provider.token().await.unwrap(), "imds-token");
+    }
+
+    #[tokio::test]
+    async fn test_token_failure_marks_provider_unhealthy_and_backs_off() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = EntraTokenProvider::from_params(
+            "azure",
+            &client_credential_params(&server),
+            Client::new(),
+        )
+        .unwrap();
+        assert_eq!(provider.token().await, Err(StatusCode::SERVICE_UNAVAILABLE));
+        // Within the backoff the token endpoint isn't called again.
+        assert_eq!(provider.token().await, Err(StatusCode::SERVICE_UNAVAILABLE));
+        let reason = provider.unhealthy_reason().unwrap();
+        assert!(reason.contains("401"), "{reason}");
+        assert!(!reason.contains("secret"), "{reason}");
+    }
+
+    #[tokio::test]
+    async fn test_unexpired_token_is_served_when_refresh_fails() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(token_response("still-valid", json!(3600)))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let provider = EntraTokenProvider::from_params(
+            "azure",
+            &client_credential_params(&server),
+            Client::new(),
+        )
+        .unwrap();
+        assert_eq!(provider.token().await.unwrap(), "still-valid");
+
+        provider
+            .state
+            .lock()
+            .unwrap()
+            .token
+            .as_mut()
+            .unwrap()
+            .refresh_at = Instant::now();
+        assert_eq!(provider.token().await.unwrap(), "still-valid");
+        assert!(provider.unhealthy_reason().is_none());
+
+        expire_cached_token(&provider);
+        provider.state.lock().unwrap().retry_at = None;
+        assert_eq!(provider.token().await, Err(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(provider.unhealthy_reason().is_some());
+    }
+
+    #[test]
+    fn test_validate_auth_params() {
+        let params = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert!(validate_auth_params(&params(&[])).is_ok());
+        assert!(validate_auth_params(&params(&[(AUTH_TYPE_PARAM, "entra_id")])).is_ok());
+        assert!(validate_auth_params(&params(&[(AUTH_TYPE_PARAM, "oauth")])).is_err());
+        assert!(
+            validate_auth_params(&params(&[
+                (AUTH_TYPE_PARAM, "entra_id"),
+                (TENANT_ID_PARAM, "tenant"),
+                (CLIENT_ID_PARAM, "client"),
+            ]))
+            .is_err()
+        );
+    }
+}
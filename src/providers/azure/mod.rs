@@ -1,3 +1,4 @@
+pub mod entra;
 mod provider;
 
-pub use provider::AzureProvider;
+pub use provider::{AzureProvider, DEPLOYMENT_PARAM};
@@ -5,15 +5,22 @@ use serde::{Deserialize, Serialize};
 
 use crate::config::constants::stream_buffer_size_bytes;
 use crate::config::models::{ModelConfig, Provider as ProviderConfig};
-use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::chat::{ChatCompletion, ChatCompletionRequest, ChatCompletionResponse};
 use crate::models::completion::{CompletionRequest, CompletionResponse};
 use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
 use crate::models::streaming::ChatCompletionChunk;
+use crate::providers::azure::entra::{AuthType, EntraTokenProvider};
+use crate::providers::capabilities::Capabilities;
 use crate::providers::provider::Provider;
+use crate::providers::stop_sequences::{trim_completion, trim_stream};
+use crate::providers::transport::{Auth, Transport, parse_json};
+use crate::providers::upstream::UpstreamRequest;
 use crate::types::ProviderType;
-use reqwest::Client;
 use tracing::info;
 
+/// Model param naming the Azure deployment that serves the model.
+pub const DEPLOYMENT_PARAM: &str = "deployment";
+
 #[derive(Serialize, Deserialize, Clone)]
 struct AzureChatCompletionRequest {
     #[serde(flatten)]
@@ -41,7 +48,9 @@ impl From<ChatCompletionRequest> for AzureChatCompletionRequest {
 
 pub struct AzureProvider {
     config: ProviderConfig,
-    http_client: Client,
+    transport: Transport,
+    /// Set when the provider authorizes with Entra ID tokens instead of an API key.
+    entra: Option<EntraTokenProvider>,
 }
 
 impl AzureProvider {
@@ -55,33 +64,28 @@ impl AzureProvider {
             )
         }
     }
-    fn api_version(&self) -> String {
-        self.config.params.get("api_version").unwrap().clone()
-    }
-}
 
-#[async_trait]
-impl Provider for AzureProvider {
-    fn new(config: &ProviderConfig) -> Self {
-        Self {
-            config: config.clone(),
-            http_client: Client::new(),
-        }
-    }
-
-    fn key(&self) -> String {
-        self.config.key.clone()
+    fn deployment_url(&self, model_config: &ModelConfig, path: &str) -> String {
+        let deployment = model_config.params.get(DEPLOYMENT_PARAM).unwrap();
+        format!(
+            "{}/{}/{}?api-version={}",
+            self.endpoint(),
+            deployment,
+            path,
+            self.api_version()
+        )
     }
 
-    fn r#type(&self) -> ProviderType {
-        ProviderType::Azure
+    fn api_version(&self) -> String {
+        self.config.params.get("api_version").unwrap().clone()
     }
 
-    async fn chat_completions(
+    /// Translates a chat request, without credentials.
+    fn chat_request(
         &self,
-        payload: ChatCompletionRequest,
+        payload: &ChatCompletionRequest,
         model_config: &ModelConfig,
-    ) -> Result<ChatCompletionResponse, StatusCode> {
+    ) -> Result<UpstreamRequest, StatusCode> {
         // Validate legacy `reasoning` only when top-level `reasoning_effort` isn't set,
         // mirroring the construction precedence in AzureChatCompletionRequest::from.
         if payload.reasoning_effort.is_none() {
@@ -111,52 +115,86 @@ impl Provider for AzureProvider {
             }
         }
 
-        let deployment = model_config.params.get("deployment").unwrap();
-        let api_version = self.api_version();
-        let url = format!(
-            "{}/{}/chat/completions?api-version={}",
-            self.endpoint(),
-            deployment,
-            api_version
-        );
+        let url = self.deployment_url(model_config, "chat/completions");
 
         // Convert to Azure-specific request format
         let azure_request = AzureChatCompletionRequest::from(payload.clone());
+        UpstreamRequest::post(url, &azure_request)?.with_extra_body(payload.extra_body.as_ref())
+    }
 
-        let response = self
-            .http_client
-            .post(&url)
-            .header("api-key", &self.config.api_key)
-            .json(&azure_request)
-            .send()
-            .await
-            .map_err(|e| {
-                eprintln!("Azure OpenAI API request error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-        let status = response.status();
-        if status.is_success() {
-            if payload.stream.unwrap_or(false) {
-                let stream =
-                    response.json_array_stream::<ChatCompletionChunk>(stream_buffer_size_bytes());
-                Ok(ChatCompletionResponse::Stream(stream))
-            } else {
-                response
-                    .json()
-                    .await
-                    .map(ChatCompletionResponse::NonStream)
-                    .map_err(|e| {
-                        eprintln!("Azure OpenAI API response error: {e}");
-                        StatusCode::INTERNAL_SERVER_ERROR
-                    })
-            }
+    async fn authorize(&self, request: UpstreamRequest) -> Result<UpstreamRequest, StatusCode> {
+        match &self.entra {
+            Some(entra) => Ok(request.bearer_auth(&entra.token().await?)),
+            None => self.transport.authorize(request),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for AzureProvider {
+    fn new(config: &ProviderConfig) -> Self {
+        let transport = Transport::new(config, "Azure OpenAI", Auth::Header("api-key"))
+            .expect("Invalid HTTP client configuration for provider");
+        let auth_type = AuthType::from_params(&config.params)
+            .expect("Invalid auth configuration for Azure provider");
+        let entra = (auth_type == AuthType::EntraId).then(|| {
+            EntraTokenProvider::from_params(&config.key, &config.params, transport.client().clone())
+                .expect("Invalid Entra ID configuration for Azure provider")
+        });
+        Self {
+            config: config.clone(),
+            transport,
+            entra,
+        }
+    }
+
+    fn key(&self) -> String {
+        self.config.key.clone()
+    }
+
+    fn r#type(&self) -> ProviderType {
+        ProviderType::Azure
+    }
+
+    fn capabilities(&self, _model_config: &ModelConfig) -> Capabilities {
+        Capabilities {
+            max_stop_sequences: Some(4),
+            ..Capabilities::ALL
+        }
+    }
+
+    fn unhealthy_reason(&self) -> Option<String> {
+        match &self.entra {
+            Some(entra) => entra.unhealthy_reason(),
+            None => self.transport.unhealthy_reason(),
+        }
+    }
+
+    async fn chat_completions(
+        &self,
+        payload: ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        let request = self.build_chat_request(&payload, model_config).await?;
+        if payload.stream.unwrap_or(false) {
+            let response = self
+                .transport
+                .send(request, "azure.chat_completions")
+                .await?;
+            let stream =
+                response.json_array_stream::<ChatCompletionChunk>(stream_buffer_size_bytes());
+            Ok(ChatCompletionResponse::Stream(trim_stream(
+                stream,
+                payload.stop.as_deref(),
+            )))
         } else {
-            info!(
-                "Azure OpenAI API request error: {}",
-                response.text().await.unwrap()
-            );
-            Err(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+            let body = self
+                .transport
+                .send_for_body(request, "azure.chat_completions")
+                .await?;
+            let mut completion: ChatCompletion = parse_json(&body, "azure.chat_completions")?;
+            trim_completion(&mut completion, payload.stop.as_deref());
+            Ok(ChatCompletionResponse::NonStream(completion))
         }
     }
 
@@ -165,40 +203,14 @@ impl Provider for AzureProvider {
         payload: CompletionRequest,
         model_config: &ModelConfig,
     ) -> Result<CompletionResponse, StatusCode> {
-        let deployment = model_config.params.get("deployment").unwrap();
-        let api_version = self.api_version();
-        let url = format!(
-            "{}/{}/completions?api-version={}",
-            self.endpoint(),
-            deployment,
-            api_version
-        );
-
-        let response = self
-            .http_client
-            .post(&url)
-            .header("api-key", &self.config.api_key)
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| {
-                eprintln!("Azure OpenAI API request error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-        let status = response.status();
-        if status.is_success() {
-            response.json().await.map_err(|e| {
-                eprintln!("Azure OpenAI API response error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })
-        } else {
-            eprintln!(
-                "Azure OpenAI API request error: {}",
-                response.text().await.unwrap()
-            );
-            Err(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
-        }
+        let request = self
+            .build_completion_request(&payload, model_config)
+            .await?;
+        let body = self
+            .transport
+            .send_for_body(request, "azure.completions")
+            .await?;
+        parse_json(&body, "azure.completions")
     }
 
     async fn embeddings(
@@ -206,41 +218,45 @@ impl Provider for AzureProvider {
         payload: EmbeddingsRequest,
         model_config: &ModelConfig,
     ) -> Result<EmbeddingsResponse, StatusCode> {
-        let deployment = model_config.params.get("deployment").unwrap();
-        let api_version = self.api_version();
+        let request = self
+            .build_embeddings_request(&payload, model_config)
+            .await?;
+        let body = self
+            .transport
+            .send_for_body(request, "azure.embeddings")
+            .await?;
+        parse_json(&body, "azure.embeddings")
+    }
 
-        let url = format!(
-            "{}/{}/embeddings?api-version={}",
-            self.endpoint(),
-            deployment,
-            api_version
-        );
-
-        let response = self
-            .http_client
-            .post(&url)
-            .header("api-key", &self.config.api_key)
-            .json(&payload)
-            .send()
+    async fn build_chat_request(
+        &self,
+        payload: &ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        self.authorize(self.chat_request(payload, model_config)?)
             .await
-            .map_err(|e| {
-                eprintln!("Azure OpenAI API request error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-        let status = response.status();
-        if status.is_success() {
-            response.json().await.map_err(|e| {
-                eprintln!("Azure OpenAI Embeddings API response error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })
-        } else {
-            eprintln!(
-                "Azure OpenAI Embeddings API request error: {}",
-                response.text().await.unwrap()
-            );
-            Err(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
-        }
+    }
+
+    async fn build_completion_request(
+        &self,
+        payload: &CompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let url = self.deployment_url(model_config, "completions");
+        let request =
+            UpstreamRequest::post(url, payload)?.with_extra_body(payload.extra_body.as_ref())?;
+        self.authorize(request).await
+    }
+
+    async fn build_embeddings_request(
+        &self,
+        payload: &EmbeddingsRequest,
+        model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let url = self.deployment_url(model_config, "embeddings");
+        let request =
+            UpstreamRequest::post(url, payload)?.with_extra_body(payload.extra_body.as_ref())?;
+        self.authorize(request).await
     }
 }
 
@@ -260,6 +276,7 @@ mod reasoning_effort_precedence_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -280,6 +297,12 @@ mod reasoning_effort_precedence_tests {
             response_format: None,
             reasoning: None,
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         }
     }
 
@@ -0,0 +1,66 @@
+//! AWS credentials for Bedrock providers. Explicit keys in the provider config are used
+//! when set. Otherwise the SDK's default credential provider chain finds them: environment
+//! variables, shared profiles, a web identity token (EKS IRSA), ECS container credentials
+//! or EC2 instance metadata. The SDK caches the credentials, loads new ones ahead of their
+//! expiry and signs every request with SigV4.
+
+use std::collections::HashMap;
+
+use aws_config::{BehaviorVersion, ConfigLoader, Region};
+use aws_credential_types::Credentials;
+
+pub const REGION_PARAM: &str = "region";
+pub const ACCESS_KEY_ID_PARAM: &str = "AWS_ACCESS_KEY_ID";
+pub const SECRET_ACCESS_KEY_PARAM: &str = "AWS_SECRET_ACCESS_KEY";
+pub const SESSION_TOKEN_PARAM: &str = "AWS_SESSION_TOKEN";
+/// Provider param forcing the default credential chain even when keys are set.
+pub const USE_IAM_ROLE_PARAM: &str = "use_iam_role";
+
+/// Where a Bedrock provider's credentials come from.
+#[derive(Debug, Clone)]
+pub enum CredentialSource {
+    /// `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`, with `AWS_SESSION_TOKEN` when set.
+    Keys(Credentials),
+    /// The SDK's default credential provider chain.
+    DefaultChain,
+}
+
+impl CredentialSource {
+    /// Keys are used when both are set and `use_iam_role` isn't `true`. Setting only one of
+    /// them is an error, rather than silently falling back to the chain.
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, String> {
+        let use_iam_role = params
+            .get(USE_IAM_ROLE_PARAM)
+            .is_some_and(|value| value.parse::<bool>().unwrap_or(false));
+        if use_iam_role {
+            return Ok(CredentialSource::DefaultChain);
+        }
+        match (
+            params.get(ACCESS_KEY_ID_PARAM),
+            params.get(SECRET_ACCESS_KEY_PARAM),
+        ) {
+            (Some(access_key_id), Some(secret_access_key)) => {
+                Ok(CredentialSource::Keys(Credentials::from_keys(
+                    access_key_id,
+                    secret_access_key,
+                    params.get(SESSION_TOKEN_PARAM).cloned(),
+                )))
+            }
+            (None, None) => Ok(CredentialSource::DefaultChain),
+            _ => Err(format!(
+                "{ACCESS_KEY_ID_PARAM} and {SECRET_ACCESS_KEY_PARAM} must be set together"
+            )),
+        }
+    }
+}
+
+/// Loader for the SDK config of a Bedrock provider in `region`, signing with credentials
+/// from `source`.
+pub fn config_loader(region: &str, source: CredentialSource) -> ConfigLoader {
+    let loader =
+        aws_config::defaults(BehaviorVersion::latest()).region(Region::new(region.to_string()));
+    match source {
+        CredentialSource::Keys(credentials) => loader.credentials_provider(credentials),
+        CredentialSource::DefaultChain => loader,
+    }
+}
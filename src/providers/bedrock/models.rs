@@ -5,7 +5,7 @@ use crate::models::chat::{ChatCompletion, ChatCompletionChoice, ChatCompletionRe
 use crate::models::completion::{
     CompletionChoice, CompletionRequest, CompletionResponse, LogProbs,
 };
-use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
+use crate::models::content::{ChatCompletionMessage, ChatMessageContent, is_instruction_role};
 use crate::models::embeddings::{
     Embedding, Embeddings, EmbeddingsInput, EmbeddingsRequest, EmbeddingsResponse,
 };
@@ -68,20 +68,24 @@ impl From<ChatCompletionRequest> for TitanChatCompletionRequest {
             .messages
             .into_iter()
             .map(|msg| {
-                let content_text = match msg.content {
-                    Some(ChatMessageContent::String(text)) => text,
-                    Some(ChatMessageContent::Array(parts)) => parts
-                        .into_iter()
-                        .filter(|part| part.r#type == "text")
-                        .map(|part| part.text)
-                        .collect::<Vec<String>>()
-                        .join(" "),
-                    None => String::new(),
-                };
+                // Titan takes content as a list, so part boundaries are kept.
+                let mut content: Vec<TitanMessageContent> = msg
+                    .content
+                    .iter()
+                    .flat_map(ChatMessageContent::text_parts)
+                    .map(|text| TitanMessageContent {
+                        text: text.to_string(),
+                    })
+                    .collect();
+                if content.is_empty() {
+                    content.push(TitanMessageContent {
+                        text: String::new(),
+                    });
+                }
 
                 TitanMessage {
                     role: msg.role,
-                    content: vec![TitanMessageContent { text: content_text }],
+                    content,
                 }
             })
             .collect();
@@ -113,6 +117,7 @@ impl From<TitanChatCompletionResponse> for ChatCompletion {
             tool_calls: None,
             tool_call_id: None,
             refusal: None, //this is not returned titan as at 1/04/2025
+            annotations: None,
         };
 
         ChatCompletion {
@@ -125,6 +130,8 @@ impl From<TitanChatCompletionResponse> for ChatCompletion {
                 message,
                 finish_reason: Some(response.stop_reason),
                 logprobs: None,
+                safety_ratings: None,
+                stop_sequence: None,
             }],
             usage: Usage {
                 prompt_tokens: response.usage.input_tokens,
@@ -134,6 +141,7 @@ impl From<TitanChatCompletionResponse> for ChatCompletion {
                 prompt_tokens_details: None,
             },
             system_fingerprint: None,
+            service_tier: None,
         }
     }
 }
@@ -160,6 +168,14 @@ pub struct EmbeddingsByType {
     pub float: Vec<f32>,
 }
 
+/// Output sizes accepted by Titan Text Embeddings V2; V1 has a fixed size.
+const TITAN_V2_DIMENSIONS: [u32; 3] = [256, 512, 1024];
+
+/// Returns true if a Titan embedding model can produce vectors of the requested size.
+pub fn titan_supports_dimensions(model: &str, dimensions: u32) -> bool {
+    model.contains("titan-embed-text-v2") && TITAN_V2_DIMENSIONS.contains(&dimensions)
+}
+
 impl From<EmbeddingsRequest> for TitanEmbeddingRequest {
     fn from(request: EmbeddingsRequest) -> Self {
         let input_text = match request.input {
@@ -186,7 +202,9 @@ impl From<EmbeddingsRequest> for TitanEmbeddingRequest {
 
         TitanEmbeddingRequest {
             input_text,
-            dimensions: default_embedding_dimension(),
+            dimensions: request
+                .dimensions
+                .unwrap_or_else(default_embedding_dimension),
             normalize: default_embedding_normalize(),
         }
     }
@@ -277,7 +295,11 @@ impl From<ChatCompletionRequest> for Ai21ChatCompletionRequest {
                 };
 
                 Ai21Message {
-                    role: msg.role,
+                    role: if is_instruction_role(&msg.role) {
+                        "system".to_string()
+                    } else {
+                        msg.role
+                    },
                     content,
                 }
             })
@@ -311,9 +333,12 @@ impl From<Ai21ChatCompletionResponse> for ChatCompletion {
                         tool_calls: None,
                         tool_call_id: None,
                         refusal: None, //Ai21 does not return this as at 1/04/2025
+                        annotations: None,
                     },
                     finish_reason: Some(choice.finish_reason),
                     logprobs: None,
+                    safety_ratings: None,
+                    stop_sequence: None,
                 })
                 .collect(),
             usage: Usage {
@@ -324,6 +349,7 @@ impl From<Ai21ChatCompletionResponse> for ChatCompletion {
                 prompt_tokens_details: None,
             },
             system_fingerprint: None,
+            service_tier: None,
         }
     }
 }
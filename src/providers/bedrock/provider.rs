@@ -3,12 +3,17 @@ use axum::http::StatusCode;
 use std::error::Error;
 
 use aws_sdk_bedrockruntime::Client as BedrockRuntimeClient;
+use tokio::sync::OnceCell;
 
 use crate::config::models::{ModelConfig, Provider as ProviderConfig};
+use crate::logging::error_rate_limited;
 use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
 use crate::models::completion::{CompletionRequest, CompletionResponse};
 use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
+use crate::providers::capabilities::Capabilities;
 use crate::providers::provider::Provider;
+use crate::providers::upstream::{UpstreamRequest, merge_extra_body};
+use crate::timing;
 use crate::types::ProviderType;
 
 use crate::providers::anthropic::{
@@ -17,9 +22,10 @@ use crate::providers::anthropic::{
 use crate::providers::bedrock::models::{
     Ai21ChatCompletionRequest, Ai21ChatCompletionResponse, Ai21CompletionsRequest,
     Ai21CompletionsResponse, TitanChatCompletionRequest, TitanChatCompletionResponse,
-    TitanEmbeddingRequest, TitanEmbeddingResponse,
+    TitanEmbeddingRequest, TitanEmbeddingResponse, titan_supports_dimensions,
 };
 use aws_sdk_bedrockruntime::primitives::Blob;
+use reqwest::Url;
 
 struct AI21Implementation;
 struct TitanImplementation;
@@ -27,6 +33,9 @@ struct AnthropicImplementation;
 
 pub struct BedrockProvider {
     pub(crate) config: ProviderConfig,
+    /// Built on first use and shared by the provider's requests, so the credentials the
+    /// SDK caches outlive a single request.
+    client: OnceCell<BedrockRuntimeClient>,
 }
 
 pub trait ClientProvider {
@@ -36,47 +45,38 @@ pub trait ClientProvider {
 #[cfg(not(test))]
 impl ClientProvider for BedrockProvider {
     async fn create_client(&self) -> Result<BedrockRuntimeClient, String> {
-        use aws_config::BehaviorVersion;
-        use aws_config::Region;
-        use aws_credential_types::Credentials;
+        use crate::providers::bedrock::credentials::{
+            CredentialSource, REGION_PARAM, config_loader,
+        };
+        use crate::providers::http_client::PROXY_URL_PARAM;
 
-        let region = self.config.params.get("region").unwrap().clone();
-        let use_iam_role = self
+        if self.config.params.contains_key(PROXY_URL_PARAM) {
+            tracing::warn!(
+                "Provider '{}': proxy_url is not supported by the Bedrock SDK client, connecting directly",
+                self.config.key
+            );
+        }
+
+        let region = self
             .config
             .params
-            .get("use_iam_role")
-            .map_or("false", |s| &**s);
-
-        let sdk_config = if use_iam_role.parse::<bool>().unwrap_or(false) {
-            aws_config::defaults(BehaviorVersion::latest())
-                .region(Region::new(region))
-                .load()
-                .await
-        } else {
-            let access_key_id = self.config.params.get("AWS_ACCESS_KEY_ID").unwrap().clone();
-            let secret_access_key = self
-                .config
-                .params
-                .get("AWS_SECRET_ACCESS_KEY")
-                .unwrap()
-                .clone();
-            let session_token = self.config.params.get("AWS_SESSION_TOKEN").cloned();
-
-            let credentials =
-                Credentials::from_keys(access_key_id, secret_access_key, session_token);
-
-            aws_config::defaults(BehaviorVersion::latest())
-                .region(Region::new(region))
-                .credentials_provider(credentials)
-                .load()
-                .await
-        };
-
+            .get(REGION_PARAM)
+            .ok_or_else(|| format!("{REGION_PARAM} is not set"))?;
+        let source = CredentialSource::from_params(&self.config.params)?;
+        let sdk_config = config_loader(region, source).load().await;
         Ok(BedrockRuntimeClient::new(&sdk_config))
     }
 }
 
 impl BedrockProvider {
+    /// The provider's client, created on first use.
+    async fn client(&self) -> Result<BedrockRuntimeClient, String> {
+        self.client
+            .get_or_try_init(|| self.create_client())
+            .await
+            .cloned()
+    }
+
     fn get_provider_implementation(
         &self,
         model_config: &ModelConfig,
@@ -94,6 +94,18 @@ impl BedrockProvider {
         provider_implementation
     }
 
+    /// The InvokeModel endpoint. The SDK signs requests to it when sending, so dry runs
+    /// show it unsigned.
+    fn invoke_url(&self, model_id: &str) -> String {
+        let region = self.config.params.get("region").unwrap();
+        let mut url = Url::parse(&format!("https://bedrock-runtime.{region}.amazonaws.com"))
+            .expect("Invalid Bedrock region");
+        url.path_segments_mut()
+            .expect("Bedrock endpoint is a base URL")
+            .extend(["model", model_id, "invoke"]);
+        url.to_string()
+    }
+
     fn transform_model_identifier(&self, model: String, model_config: &ModelConfig) -> String {
         // Check if the model is already an ARN or inference profile ID
         if model.starts_with("arn:aws:bedrock:") || model.contains("inference-profile") {
@@ -122,6 +134,7 @@ impl Provider for BedrockProvider {
     fn new(config: &ProviderConfig) -> Self {
         Self {
             config: config.clone(),
+            client: OnceCell::new(),
         }
     }
 
@@ -133,13 +146,40 @@ impl Provider for BedrockProvider {
         ProviderType::Bedrock
     }
 
+    fn capabilities(&self, model_config: &ModelConfig) -> Capabilities {
+        let model_provider = model_config.params.get("model_provider");
+        let family = model_provider.map(String::as_str).unwrap_or_default();
+        Capabilities {
+            supports_streaming: false,
+            supports_tools: family == "anthropic",
+            supports_vision: false,
+            supports_completions: family == "ai21",
+            supports_embeddings: family == "titan",
+            supports_n: false,
+            supports_logprobs: false,
+            // AI21 completions map penalties and stop sequences; Anthropic chat models map
+            // stop sequences; other chat models drop them.
+            supports_penalties: family == "ai21",
+            supports_logit_bias: false,
+            supports_prediction: false,
+            supports_builtin_tools: false,
+            max_stop_sequences: match family {
+                "ai21" | "anthropic" => None,
+                _ => Some(0),
+            },
+        }
+    }
+
     async fn chat_completions(
         &self,
         payload: ChatCompletionRequest,
         model_config: &ModelConfig,
     ) -> Result<ChatCompletionResponse, StatusCode> {
-        let client = self.create_client().await.map_err(|e| {
-            eprintln!("Failed to create Bedrock client: {e}");
+        let client = self.client().await.map_err(|e| {
+            error_rate_limited(
+                "bedrock.create_client",
+                format!("Failed to create Bedrock client: {e}"),
+            );
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
@@ -158,8 +198,11 @@ impl Provider for BedrockProvider {
         payload: CompletionRequest,
         model_config: &ModelConfig,
     ) -> Result<CompletionResponse, StatusCode> {
-        let client = self.create_client().await.map_err(|e| {
-            eprintln!("Failed to create Bedrock client: {e}");
+        let client = self.client().await.map_err(|e| {
+            error_rate_limited(
+                "bedrock.create_client",
+                format!("Failed to create Bedrock client: {e}"),
+            );
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
@@ -178,8 +221,11 @@ impl Provider for BedrockProvider {
         payload: EmbeddingsRequest,
         model_config: &ModelConfig,
     ) -> Result<EmbeddingsResponse, StatusCode> {
-        let client = self.create_client().await.map_err(|e| {
-            eprintln!("Failed to create Bedrock client: {e}");
+        let client = self.client().await.map_err(|e| {
+            error_rate_limited(
+                "bedrock.create_client",
+                format!("Failed to create Bedrock client: {e}"),
+            );
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
@@ -192,6 +238,24 @@ impl Provider for BedrockProvider {
             .embedding(&client, transformed_payload)
             .await
     }
+
+    async fn build_chat_request(
+        &self,
+        payload: &ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let mut transformed_payload = payload.clone();
+        transformed_payload.model =
+            self.transform_model_identifier(transformed_payload.model, model_config);
+
+        let body = self
+            .get_provider_implementation(model_config)
+            .chat_request_body(&transformed_payload)?;
+        Ok(UpstreamRequest::post_json_bytes(
+            self.invoke_url(&transformed_payload.model),
+            body,
+        ))
+    }
 }
 
 /**
@@ -200,6 +264,9 @@ impl Provider for BedrockProvider {
 
 #[async_trait]
 trait BedrockModelImplementation: Send + Sync {
+    /// The InvokeModel body `chat_completion` sends for this request.
+    fn chat_request_body(&self, payload: &ChatCompletionRequest) -> Result<Vec<u8>, StatusCode>;
+
     async fn chat_completion(
         &self,
         client: &BedrockRuntimeClient,
@@ -229,6 +296,7 @@ trait BedrockRequestHandler {
         client: &BedrockRuntimeClient,
         model_id: &str,
         request: T,
+        extra_body: Option<&serde_json::Value>,
         error_context: &str,
     ) -> Result<U, StatusCode>
     where
@@ -240,23 +308,43 @@ trait BedrockRequestHandler {
             eprintln!("Failed to serialize {error_context}: {e}");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+        let request_json = merge_extra_body(request_json, extra_body)?;
 
-        // Make API call
-        let response = client
-            .invoke_model()
-            .body(Blob::new(request_json))
-            .model_id(model_id)
-            .send()
+        self.send_bedrock_request(client, model_id, request_json, error_context)
             .await
-            .map_err(|e| {
-                eprintln!("Bedrock API error for {error_context}: {e:?}");
-                eprintln!(
-                    "Error details - Source: {}, Raw error: {:?}",
+    }
+
+    async fn send_bedrock_request<U>(
+        &self,
+        client: &BedrockRuntimeClient,
+        model_id: &str,
+        request_json: Vec<u8>,
+        error_context: &str,
+    ) -> Result<U, StatusCode>
+    where
+        U: for<'de> serde::Deserialize<'de>,
+    {
+        // Make API call
+        let response = timing::upstream(
+            client
+                .invoke_model()
+                .body(Blob::new(request_json))
+                .model_id(model_id)
+                .send(),
+        )
+        .await
+        .map_err(|e| {
+            error_rate_limited(
+                &format!("bedrock.invoke_model.{error_context}"),
+                format!(
+                    "Bedrock API error for {error_context}: {e:?}. Source: {}, Raw error: {:?}",
                     e.source().unwrap_or(&e),
                     e.raw_response()
-                );
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
+                ),
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        timing::mark_upstream_done();
 
         // Deserialize response
         serde_json::from_slice(&response.body.into_inner()).map_err(|e| {
@@ -266,6 +354,18 @@ trait BedrockRequestHandler {
     }
 }
 
+/// Serializes a chat request body with the request's `extra_body` merged in.
+fn serialize_request_body<T: serde::Serialize>(
+    request: &T,
+    extra_body: Option<&serde_json::Value>,
+) -> Result<Vec<u8>, StatusCode> {
+    let body = serde_json::to_vec(request).map_err(|e| {
+        eprintln!("Failed to serialize Bedrock request: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    merge_extra_body(body, extra_body)
+}
+
 impl BedrockRequestHandler for AI21Implementation {}
 impl BedrockRequestHandler for TitanImplementation {}
 impl BedrockRequestHandler for AnthropicImplementation {}
@@ -276,14 +376,21 @@ impl BedrockRequestHandler for AnthropicImplementation {}
 
 #[async_trait]
 impl BedrockModelImplementation for AI21Implementation {
+    fn chat_request_body(&self, payload: &ChatCompletionRequest) -> Result<Vec<u8>, StatusCode> {
+        serialize_request_body(
+            &Ai21ChatCompletionRequest::from(payload.clone()),
+            payload.extra_body.as_ref(),
+        )
+    }
+
     async fn chat_completion(
         &self,
         client: &BedrockRuntimeClient,
         payload: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse, StatusCode> {
-        let ai21_request = Ai21ChatCompletionRequest::from(payload.clone());
+        let request_json = self.chat_request_body(&payload)?;
         let ai21_response: Ai21ChatCompletionResponse = self
-            .handle_bedrock_request(client, &payload.model, ai21_request, "AI21 chat completion")
+            .send_bedrock_request(client, &payload.model, request_json, "AI21 chat completion")
             .await?;
 
         Ok(ChatCompletionResponse::NonStream(ai21_response.into()))
@@ -297,7 +404,13 @@ impl BedrockModelImplementation for AI21Implementation {
         // Bedrock AI21 supports completions in legacy models similar to openai
         let ai21_request = Ai21CompletionsRequest::from(payload.clone());
         let ai21_response: Ai21CompletionsResponse = self
-            .handle_bedrock_request(client, &payload.model, ai21_request, "AI21 completion")
+            .handle_bedrock_request(
+                client,
+                &payload.model,
+                ai21_request,
+                payload.extra_body.as_ref(),
+                "AI21 completion",
+            )
             .await?;
 
         Ok(CompletionResponse::from(ai21_response))
@@ -310,17 +423,24 @@ impl BedrockModelImplementation for AI21Implementation {
 
 #[async_trait]
 impl BedrockModelImplementation for TitanImplementation {
+    fn chat_request_body(&self, payload: &ChatCompletionRequest) -> Result<Vec<u8>, StatusCode> {
+        serialize_request_body(
+            &TitanChatCompletionRequest::from(payload.clone()),
+            payload.extra_body.as_ref(),
+        )
+    }
+
     async fn chat_completion(
         &self,
         client: &BedrockRuntimeClient,
         payload: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse, StatusCode> {
-        let titan_request = TitanChatCompletionRequest::from(payload.clone());
+        let request_json = self.chat_request_body(&payload)?;
         let titan_response: TitanChatCompletionResponse = self
-            .handle_bedrock_request(
+            .send_bedrock_request(
                 client,
                 &payload.model,
-                titan_request,
+                request_json,
                 "Titan chat completion",
             )
             .await?;
@@ -333,9 +453,25 @@ impl BedrockModelImplementation for TitanImplementation {
         client: &BedrockRuntimeClient,
         payload: EmbeddingsRequest,
     ) -> Result<EmbeddingsResponse, StatusCode> {
+        if let Some(dimensions) = payload.dimensions {
+            if !titan_supports_dimensions(&payload.model, dimensions) {
+                tracing::error!(
+                    "Model '{}' does not support dimensions={dimensions}",
+                    payload.model
+                );
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        }
+
         let titan_request = TitanEmbeddingRequest::from(payload.clone());
         let titan_response: TitanEmbeddingResponse = self
-            .handle_bedrock_request(client, &payload.model, titan_request, "Titan embedding")
+            .handle_bedrock_request(
+                client,
+                &payload.model,
+                titan_request,
+                payload.extra_body.as_ref(),
+                "Titan embedding",
+            )
             .await?;
 
         Ok(EmbeddingsResponse::from(titan_response))
@@ -348,11 +484,7 @@ impl BedrockModelImplementation for TitanImplementation {
 
 #[async_trait]
 impl BedrockModelImplementation for AnthropicImplementation {
-    async fn chat_completion(
-        &self,
-        client: &BedrockRuntimeClient,
-        payload: ChatCompletionRequest,
-    ) -> Result<ChatCompletionResponse, StatusCode> {
+    fn chat_request_body(&self, payload: &ChatCompletionRequest) -> Result<Vec<u8>, StatusCode> {
         let anthropic_request = AnthropicChatCompletionRequest::from(payload.clone());
 
         // Convert to Value for Bedrock-specific modifications
@@ -369,11 +501,20 @@ impl BedrockModelImplementation for AnthropicImplementation {
             );
         }
 
+        serialize_request_body(&request_value, payload.extra_body.as_ref())
+    }
+
+    async fn chat_completion(
+        &self,
+        client: &BedrockRuntimeClient,
+        payload: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        let request_json = self.chat_request_body(&payload)?;
         let anthropic_response: AnthropicChatCompletionResponse = self
-            .handle_bedrock_request(
+            .send_bedrock_request(
                 client,
                 &payload.model,
-                request_value,
+                request_json,
                 "Anthropic chat completion",
             )
             .await?;
@@ -49,6 +49,8 @@ fn get_test_provider_config(
         key: "test_key".to_string(),
         r#type: crate::types::ProviderType::Bedrock,
         api_key: "".to_string(),
+        maintenance_windows: vec![],
+        maintenance_windows: vec![],
         params,
     }
 }
@@ -67,6 +69,8 @@ fn get_test_model_config(
         r#type: model_type.to_string(),
         provider: "bedrock".to_string(),
         params,
+        enabled: true,
+        deprecation: Default::default(),
     }
 }
 
@@ -74,6 +78,7 @@ fn get_test_model_config(
 mod antropic_tests {
     use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
     use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
+    use crate::providers::anthropic::AnthropicChatCompletionResponse;
     use crate::providers::bedrock::BedrockProvider;
     use crate::providers::bedrock::test::{get_test_model_config, get_test_provider_config};
     use crate::providers::provider::Provider;
@@ -106,6 +111,7 @@ mod antropic_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -126,6 +132,12 @@ mod antropic_tests {
             response_format: None,
             reasoning: None,
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         let result = provider.chat_completions(payload, &model_config).await;
@@ -150,6 +162,25 @@ mod antropic_tests {
             );
         }
     }
+
+    #[test]
+    fn test_anthropic_usage_counts_prompt_cache_tokens() {
+        // Bedrock returns Anthropic's response body unchanged.
+        let fixture = std::fs::read_to_string("tests/fixtures/provider_usage.json")
+            .expect("Failed to read usage fixture");
+        let fixtures: serde_json::Value = serde_json::from_str(&fixture).unwrap();
+        let response: AnthropicChatCompletionResponse =
+            serde_json::from_value(fixtures["anthropic"].clone()).unwrap();
+
+        let usage = crate::models::chat::ChatCompletion::from(response).usage;
+        assert_eq!(usage.prompt_tokens, 12 + 248 + 1800);
+        assert_eq!(usage.cached_tokens(), 1800);
+        assert_eq!(usage.cache_creation_tokens(), 248);
+        assert_eq!(
+            usage.total_tokens,
+            usage.prompt_tokens + usage.completion_tokens
+        );
+    }
 }
 
 #[cfg(test)]
@@ -162,6 +193,21 @@ mod titan_tests {
     use crate::providers::bedrock::test::{get_test_model_config, get_test_provider_config};
     use crate::providers::provider::Provider;
 
+    #[test]
+    fn test_titan_request_keeps_part_boundaries() {
+        use crate::providers::bedrock::models::TitanChatCompletionRequest;
+
+        let fixture = std::fs::read_to_string("tests/fixtures/chat_multi_turn_tools.json")
+            .expect("Failed to read multi-turn fixture");
+        let payload: ChatCompletionRequest =
+            serde_json::from_str(&fixture).expect("Failed to parse multi-turn fixture");
+
+        let request = TitanChatCompletionRequest::from(payload);
+        let last = request.messages.last().unwrap();
+        let texts: Vec<&str> = last.content.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["Thanks!", "Which is warmer?"]);
+    }
+
     #[test]
     fn test_titan_provider_new() {
         let config = get_test_provider_config("us-east-2", "");
@@ -182,6 +228,8 @@ mod titan_tests {
             user: None,
             input: Single("this is where you place your input text".to_string()),
             encoding_format: None,
+            dimensions: None,
+            extra_body: None,
         };
 
         let result = provider.embeddings(payload, &model_config).await;
@@ -223,6 +271,7 @@ mod titan_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -243,6 +292,12 @@ mod titan_tests {
             response_format: None,
             reasoning: None,
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         let result = provider.chat_completions(payload, &model_config).await;
@@ -266,6 +321,53 @@ mod titan_tests {
             );
         }
     }
+
+    #[test]
+    fn test_embeddings_dimensions_mapping() {
+        use crate::providers::bedrock::models::{TitanEmbeddingRequest, titan_supports_dimensions};
+
+        let payload = EmbeddingsRequest {
+            model: "amazon.titan-embed-text-v2:0".to_string(),
+            user: None,
+            input: Single("hello".to_string()),
+            encoding_format: None,
+            dimensions: Some(256),
+            extra_body: None,
+        };
+        assert_eq!(TitanEmbeddingRequest::from(payload).dimensions, 256);
+
+        assert!(titan_supports_dimensions(
+            "amazon.titan-embed-text-v2:0",
+            1024
+        ));
+        assert!(!titan_supports_dimensions(
+            "amazon.titan-embed-text-v2:0",
+            300
+        ));
+        assert!(!titan_supports_dimensions(
+            "amazon.titan-embed-text-v1",
+            256
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_unsupported_dimensions_is_bad_request() {
+        let config = get_test_provider_config("us-east-2", "titan_embedding");
+        let provider = BedrockProvider::new(&config);
+        let model_config = get_test_model_config("amazon.titan-embed-text-v1", "titan");
+
+        let payload = EmbeddingsRequest {
+            model: "amazon.titan-embed-text-v1".to_string(),
+            user: None,
+            input: Single("hello".to_string()),
+            encoding_format: None,
+            dimensions: Some(256),
+            extra_body: None,
+        };
+
+        let result = provider.embeddings(payload, &model_config).await;
+        assert_eq!(result.err(), Some(axum::http::StatusCode::BAD_REQUEST));
+    }
 }
 
 #[cfg(test)]
@@ -277,6 +379,31 @@ mod ai21_tests {
     use crate::providers::bedrock::test::{get_test_model_config, get_test_provider_config};
     use crate::providers::provider::Provider;
 
+    #[test]
+    fn test_ai21_request_maps_developer_to_system() {
+        use crate::providers::bedrock::models::Ai21ChatCompletionRequest;
+
+        let fixture = std::fs::read_to_string("tests/fixtures/chat_multi_turn_tools.json")
+            .expect("Failed to read multi-turn fixture");
+        let payload: ChatCompletionRequest =
+            serde_json::from_str(&fixture).expect("Failed to parse multi-turn fixture");
+
+        let request = Ai21ChatCompletionRequest::from(payload);
+        let roles: Vec<&str> = request.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(
+            roles,
+            vec![
+                "system",
+                "system",
+                "user",
+                "assistant",
+                "tool",
+                "tool",
+                "user"
+            ]
+        );
+    }
+
     #[test]
     fn test_ai21_provider_new() {
         let config = get_test_provider_config("us-east-1", "");
@@ -308,6 +435,7 @@ mod ai21_tests {
             presence_penalty: None,
             frequency_penalty: None,
             best_of: None,
+            extra_body: None,
             logit_bias: None,
             user: None,
         };
@@ -351,6 +479,7 @@ mod ai21_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: Some(0.8),
             top_p: Some(0.8),
@@ -371,6 +500,12 @@ mod ai21_tests {
             response_format: None,
             reasoning: None,
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         let result = provider.chat_completions(payload, &model_config).await;
@@ -428,6 +563,7 @@ mod arn_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -448,6 +584,12 @@ mod arn_tests {
             response_format: None,
             reasoning: None,
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         // The test here is that we don't get a transformation error
@@ -482,6 +624,7 @@ mod arn_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -502,6 +645,12 @@ mod arn_tests {
             response_format: None,
             reasoning: None,
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         let result = provider.chat_completions(payload, &model_config).await;
@@ -532,6 +681,7 @@ mod arn_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -556,6 +706,12 @@ mod arn_tests {
                 exclude: None,
             }),
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         let result = provider.chat_completions(payload, &model_config).await;
@@ -584,6 +740,7 @@ mod arn_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -608,6 +765,12 @@ mod arn_tests {
                 exclude: None,
             }),
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         let result = provider.chat_completions(payload, &model_config).await;
@@ -636,6 +799,7 @@ mod arn_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -660,6 +824,12 @@ mod arn_tests {
                 exclude: None,
             }),
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         let result = provider.chat_completions(payload, &model_config).await;
@@ -769,6 +939,7 @@ mod arn_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -793,6 +964,12 @@ mod arn_tests {
                 exclude: None,
             }),
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         // Transform the request to Anthropic format
@@ -828,6 +1005,7 @@ mod arn_tests {
                     tool_calls: None,
                     tool_call_id: None,
                     refusal: None,
+                    annotations: None,
                 },
                 ChatCompletionMessage {
                     role: "user".to_string(),
@@ -836,6 +1014,7 @@ mod arn_tests {
                     tool_calls: None,
                     tool_call_id: None,
                     refusal: None,
+                    annotations: None,
                 },
             ],
             temperature: None,
@@ -861,6 +1040,12 @@ mod arn_tests {
                 exclude: None,
             }),
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         };
 
         let anthropic_request = AnthropicChatCompletionRequest::from(payload);
@@ -884,6 +1069,174 @@ mod arn_tests {
     }
 }
 
+#[cfg(test)]
+mod credentials_tests {
+    use crate::providers::bedrock::credentials::{CredentialSource, config_loader};
+    use aws_credential_types::Credentials;
+    use aws_credential_types::provider::{ProvideCredentials, future};
+    use aws_sdk_bedrockruntime::Client;
+    use aws_sdk_bedrockruntime::primitives::Blob;
+    use aws_smithy_runtime::client::http::test_util::{ReplayEvent, StaticReplayClient};
+    use aws_smithy_types::body::SdkBody;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, SystemTime};
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// An HTTP client answering `requests` InvokeModel calls.
+    fn bedrock_http(requests: usize) -> StaticReplayClient {
+        StaticReplayClient::new(
+            (0..requests)
+                .map(|_| {
+                    ReplayEvent::new(
+                        http::Request::builder()
+                            .method("POST")
+                            .uri("https://bedrock-runtime.us-east-1.amazonaws.com/model/amazon.titan-embed-text-v2:0/invoke")
+                            .body(SdkBody::empty())
+                            .unwrap(),
+                        http::Response::builder()
+                            .status(200)
+                            .body(SdkBody::from("{}"))
+                            .unwrap(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    async fn invoke_twice(client: &Client) {
+        for _ in 0..2 {
+            client
+                .invoke_model()
+                .model_id("amazon.titan-embed-text-v2:0")
+                .body(Blob::new("{}"))
+                .send()
+                .await
+                .unwrap();
+        }
+    }
+
+    /// The access key each request was signed with.
+    fn signing_keys(http: &StaticReplayClient) -> Vec<String> {
+        http.actual_requests()
+            .map(|request| {
+                let authorization = request.headers().get("authorization").unwrap();
+                assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential="));
+                assert!(authorization.contains("/us-east-1/bedrock/aws4_request"));
+                let credential = authorization.split("Credential=").nth(1).unwrap();
+                credential.split('/').next().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_credential_source_from_params() {
+        let keys = params(&[
+            ("AWS_ACCESS_KEY_ID", "AKIDKEYS"),
+            ("AWS_SECRET_ACCESS_KEY", "secret"),
+        ]);
+        let source = CredentialSource::from_params(&keys).unwrap();
+        assert!(
+            matches!(source, CredentialSource::Keys(credentials) if credentials.access_key_id() == "AKIDKEYS")
+        );
+
+        let mut iam_role = keys.clone();
+        iam_role.insert("use_iam_role".to_string(), "true".to_string());
+        for params in [iam_role, params(&[("region", "us-east-1")])] {
+            let source = CredentialSource::from_params(&params).unwrap();
+            assert!(matches!(source, CredentialSource::DefaultChain));
+        }
+
+        let half = params(&[("AWS_ACCESS_KEY_ID", "AKIDKEYS")]);
+        assert_eq!(
+            CredentialSource::from_params(&half).unwrap_err(),
+            "AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set together"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_explicit_keys_sign_requests() {
+        let source = CredentialSource::from_params(&params(&[
+            ("AWS_ACCESS_KEY_ID", "AKIDKEYS"),
+            ("AWS_SECRET_ACCESS_KEY", "secret"),
+            ("AWS_SESSION_TOKEN", "session"),
+        ]))
+        .unwrap();
+        let http = bedrock_http(2);
+        let sdk_config = config_loader("us-east-1", source)
+            .http_client(http.clone())
+            .load()
+            .await;
+        invoke_twice(&Client::new(&sdk_config)).await;
+
+        assert_eq!(signing_keys(&http), ["AKIDKEYS", "AKIDKEYS"]);
+        for request in http.actual_requests() {
+            assert_eq!(
+                request.headers().get("x-amz-security-token"),
+                Some("session")
+            );
+        }
+    }
+
+    /// Hands out new keys on every load, each expiring after `lifetime`.
+    #[derive(Debug)]
+    struct RotatingCredentials {
+        lifetime: Duration,
+        loads: Arc<AtomicUsize>,
+    }
+
+    impl ProvideCredentials for RotatingCredentials {
+        fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+        where
+            Self: 'a,
+        {
+            let load = self.loads.fetch_add(1, Ordering::SeqCst) + 1;
+            future::ProvideCredentials::ready(Ok(Credentials::new(
+                format!("AKIDROTATED{load}"),
+                "secret",
+                Some("session".to_string()),
+                Some(SystemTime::now() + self.lifetime),
+                "rotating",
+            )))
+        }
+    }
+
+    /// The keys two requests were signed with, and how often credentials were loaded.
+    async fn sign_with_rotating_credentials(lifetime: Duration) -> (Vec<String>, usize) {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let http = bedrock_http(2);
+        let sdk_config = config_loader("us-east-1", CredentialSource::DefaultChain)
+            .credentials_provider(RotatingCredentials {
+                lifetime,
+                loads: loads.clone(),
+            })
+            .http_client(http.clone())
+            .load()
+            .await;
+        invoke_twice(&Client::new(&sdk_config)).await;
+        (signing_keys(&http), loads.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_credentials_are_cached_until_they_expire() {
+        let (keys, loads) = sign_with_rotating_credentials(Duration::from_secs(3600)).await;
+        assert_eq!(keys, ["AKIDROTATED1", "AKIDROTATED1"]);
+        assert_eq!(loads, 1);
+
+        // Credentials this close to expiry are replaced before the next request.
+        let (keys, loads) = sign_with_rotating_credentials(Duration::from_secs(1)).await;
+        assert_eq!(keys, ["AKIDROTATED1", "AKIDROTATED2"]);
+        assert_eq!(loads, 2);
+    }
+}
+
 /**
 
 Helper functions for creating test clients and mock responses
@@ -0,0 +1,405 @@
+use serde::Serialize;
+
+use crate::models::chat::ChatCompletionRequest;
+use crate::models::completion::CompletionRequest;
+use crate::models::content::ChatMessageContent;
+use crate::models::tool_definition::ToolDefinition;
+
+/// Request features a provider can honour. Requests using anything else are rejected
+/// before dispatch instead of being silently dropped by the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    pub supports_streaming: bool,
+    pub supports_tools: bool,
+    pub supports_vision: bool,
+    pub supports_completions: bool,
+    pub supports_embeddings: bool,
+    /// More than one choice per request (`n` > 1).
+    pub supports_n: bool,
+    pub supports_logprobs: bool,
+    /// `presence_penalty` and `frequency_penalty`.
+    pub supports_penalties: bool,
+    pub supports_logit_bias: bool,
+    /// Predicted outputs (`prediction`).
+    pub supports_prediction: bool,
+    /// Tools the provider runs itself, such as `web_search_preview`, and
+    /// `web_search_options`.
+    pub supports_builtin_tools: bool,
+    /// Most `stop` sequences accepted, `None` when the gateway enforces no limit.
+    pub max_stop_sequences: Option<usize>,
+}
+
+impl Capabilities {
+    /// Everything the OpenAI API accepts.
+    pub const ALL: Capabilities = Capabilities {
+        supports_streaming: true,
+        supports_tools: true,
+        supports_vision: true,
+        supports_completions: true,
+        supports_embeddings: true,
+        supports_n: true,
+        supports_logprobs: true,
+        supports_penalties: true,
+        supports_logit_bias: true,
+        supports_prediction: true,
+        supports_builtin_tools: true,
+        max_stop_sequences: None,
+    };
+
+    /// Names the chat request fields this provider can't honour.
+    pub fn unsupported_chat_params(&self, request: &ChatCompletionRequest) -> Vec<&'static str> {
+        let mut unsupported = Vec::new();
+        if !self.supports_streaming && request.stream == Some(true) {
+            unsupported.push("stream");
+        }
+        if !self.supports_tools {
+            if request
+                .tools
+                .as_ref()
+                .is_some_and(|tools| !tools.is_empty())
+            {
+                unsupported.push("tools");
+            }
+            if request.tool_choice.is_some() {
+                unsupported.push("tool_choice");
+            }
+        }
+        let has_non_text_parts = request.messages.iter().any(|message| {
+            matches!(&message.content, Some(ChatMessageContent::Array(parts))
+                if parts.iter().any(|part| part.r#type != "text"))
+        });
+        if !self.supports_vision && has_non_text_parts {
+            unsupported.push("messages");
+        }
+        if !self.supports_logprobs {
+            if request.logprobs == Some(true) {
+                unsupported.push("logprobs");
+            }
+            if request.top_logprobs.is_some() {
+                unsupported.push("top_logprobs");
+            }
+        }
+        if !self.supports_prediction && request.prediction.is_some() {
+            unsupported.push("prediction");
+        }
+        if !self.supports_builtin_tools && request.web_search_options.is_some() {
+            unsupported.push("web_search_options");
+        }
+        self.check_sampling_params(
+            &mut unsupported,
+            request.n,
+            request.presence_penalty,
+            request.frequency_penalty,
+            request.logit_bias.is_some(),
+            request.stop.as_deref(),
+        );
+        unsupported
+    }
+
+    /// Types of the request's built-in tools this provider can't run.
+    pub fn unsupported_builtin_tools<'a>(
+        &self,
+        request: &'a ChatCompletionRequest,
+    ) -> Vec<&'a str> {
+        if self.supports_builtin_tools {
+            return Vec::new();
+        }
+        request
+            .tools
+            .iter()
+            .flatten()
+            .filter(|tool| tool.function().is_none())
+            .map(ToolDefinition::tool_type)
+            .collect()
+    }
+
+    /// Names the completion request fields this provider can't honour.
+    pub fn unsupported_completion_params(&self, request: &CompletionRequest) -> Vec<&'static str> {
+        let mut unsupported = Vec::new();
+        if !self.supports_streaming && request.stream == Some(true) {
+            unsupported.push("stream");
+        }
+        if !self.supports_logprobs && request.logprobs.is_some() {
+            unsupported.push("logprobs");
+        }
+        self.check_sampling_params(
+            &mut unsupported,
+            request.n,
+            request.presence_penalty,
+            request.frequency_penalty,
+            request.logit_bias.is_some(),
+            request.stop.as_deref(),
+        );
+        unsupported
+    }
+
+    fn check_sampling_params(
+        &self,
+        unsupported: &mut Vec<&'static str>,
+        n: Option<u32>,
+        presence_penalty: Option<f32>,
+        frequency_penalty: Option<f32>,
+        has_logit_bias: bool,
+        stop: Option<&[String]>,
+    ) {
+        if !self.supports_n && n.is_some_and(|n| n > 1) {
+            unsupported.push("n");
+        }
+        if !self.supports_penalties {
+            if presence_penalty.is_some() {
+                unsupported.push("presence_penalty");
+            }
+            if frequency_penalty.is_some() {
+                unsupported.push("frequency_penalty");
+            }
+        }
+        if !self.supports_logit_bias && has_logit_bias {
+            unsupported.push("logit_bias");
+        }
+        let stop_count = stop.map_or(0, <[String]>::len);
+        if self.max_stop_sequences.is_some_and(|max| stop_count > max) {
+            unsupported.push("stop");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::models::{ModelConfig, Provider as ProviderConfig};
+    use crate::providers::anthropic::AnthropicProvider;
+    use crate::providers::azure::AzureProvider;
+    use crate::providers::bedrock::BedrockProvider;
+    use crate::providers::openai::OpenAIProvider;
+    use crate::providers::provider::Provider;
+    use crate::providers::vertexai::VertexAIProvider;
+    use crate::types::ProviderType;
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+
+    const TEXT_ONLY: Capabilities = Capabilities {
+        supports_streaming: false,
+        supports_tools: false,
+        supports_vision: false,
+        supports_completions: false,
+        supports_embeddings: false,
+        supports_n: false,
+        supports_logprobs: false,
+        supports_penalties: false,
+        supports_logit_bias: false,
+        supports_prediction: false,
+        supports_builtin_tools: false,
+        max_stop_sequences: Some(0),
+    };
+
+    fn chat_request(overrides: Value) -> ChatCompletionRequest {
+        let mut body = json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hello"}]
+        });
+        for (key, value) in overrides.as_object().unwrap() {
+            body[key] = value.clone();
+        }
+        serde_json::from_value(body).unwrap()
+    }
+
+    fn completion_request(overrides: Value) -> CompletionRequest {
+        let mut body = json!({"model": "gpt-3.5-turbo-instruct", "prompt": "hello"});
+        for (key, value) in overrides.as_object().unwrap() {
+            body[key] = value.clone();
+        }
+        serde_json::from_value(body).unwrap()
+    }
+
+    fn full_chat_request() -> ChatCompletionRequest {
+        chat_request(json!({
+            "stream": true,
+            "n": 2,
+            "logprobs": true,
+            "top_logprobs": 3,
+            "presence_penalty": 0.5,
+            "frequency_penalty": 0.5,
+            "logit_bias": {"50256": -100},
+            "stop": ["END"],
+            "tools": [{"type": "function", "function": {"name": "lookup"}}],
+            "tool_choice": "auto",
+            "prediction": {"type": "content", "content": "fn main() {}"},
+            "web_search_options": {"search_context_size": "low"},
+            "messages": [{
+                "role": "user",
+                "content": [{"type": "image_url", "text": "https://example.com/cat.png"}]
+            }]
+        }))
+    }
+
+    #[test]
+    fn test_plain_request_is_always_supported() {
+        let request = chat_request(json!({"temperature": 0.2, "max_tokens": 10}));
+        assert!(TEXT_ONLY.unsupported_chat_params(&request).is_empty());
+        let request = completion_request(json!({"n": 1, "stream": false}));
+        assert!(TEXT_ONLY.unsupported_completion_params(&request).is_empty());
+    }
+
+    #[test]
+    fn test_all_capabilities_accept_every_chat_field() {
+        let unsupported = Capabilities::ALL.unsupported_chat_params(&full_chat_request());
+        assert!(unsupported.is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_chat_fields_are_listed() {
+        let unsupported = TEXT_ONLY.unsupported_chat_params(&full_chat_request());
+        assert_eq!(
+            unsupported,
+            vec![
+                "stream",
+                "tools",
+                "tool_choice",
+                "messages",
+                "logprobs",
+                "top_logprobs",
+                "prediction",
+                "web_search_options",
+                "n",
+                "presence_penalty",
+                "frequency_penalty",
+                "logit_bias",
+                "stop",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_completion_fields_are_listed() {
+        let request = completion_request(json!({
+            "stream": true,
+            "logprobs": 2,
+            "n": 3,
+            "presence_penalty": 1.0,
+            "stop": ["\n"]
+        }));
+        let unsupported = TEXT_ONLY.unsupported_completion_params(&request);
+        assert_eq!(
+            unsupported,
+            vec!["stream", "logprobs", "n", "presence_penalty", "stop"]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_builtin_tools_are_listed() {
+        let request = chat_request(json!({"tools": [
+            {"type": "function", "function": {"name": "lookup"}},
+            {"type": "web_search_preview", "search_context_size": "low"},
+            {"type": "file_search", "vector_store_ids": ["vs_123"]}
+        ]}));
+        assert_eq!(
+            TEXT_ONLY.unsupported_builtin_tools(&request),
+            vec!["web_search_preview", "file_search"]
+        );
+        assert!(
+            Capabilities::ALL
+                .unsupported_builtin_tools(&request)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_stop_sequences_over_limit_are_rejected() {
+        let capabilities = Capabilities {
+            max_stop_sequences: Some(2),
+            ..Capabilities::ALL
+        };
+        let request = chat_request(json!({"stop": ["a", "b"]}));
+        assert!(capabilities.unsupported_chat_params(&request).is_empty());
+        let request = chat_request(json!({"stop": ["a", "b", "c"]}));
+        assert_eq!(capabilities.unsupported_chat_params(&request), vec!["stop"]);
+    }
+
+    #[test]
+    fn test_text_content_parts_do_not_need_vision() {
+        let request = chat_request(json!({
+            "messages": [{"role": "user", "content": [{"type": "text", "text": "hi"}]}]
+        }));
+        assert!(TEXT_ONLY.unsupported_chat_params(&request).is_empty());
+    }
+
+    #[test]
+    fn test_empty_tools_and_single_choice_are_allowed() {
+        let request = chat_request(json!({"tools": [], "n": 1, "logprobs": false}));
+        assert!(TEXT_ONLY.unsupported_chat_params(&request).is_empty());
+    }
+
+    fn provider_config(r#type: ProviderType) -> ProviderConfig {
+        ProviderConfig {
+            key: "test-provider".to_string(),
+            r#type,
+            api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
+            params: HashMap::new(),
+        }
+    }
+
+    fn model_config(params: &[(&str, &str)]) -> ModelConfig {
+        ModelConfig {
+            key: "test-model".to_string(),
+            r#type: "test".to_string(),
+            provider: "test-provider".to_string(),
+            params: params
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            enabled: true,
+            deprecation: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_provider_capability_matrix() {
+        let model = model_config(&[]);
+        let openai = OpenAIProvider::new(&provider_config(ProviderType::OpenAI));
+        let azure = AzureProvider::new(&provider_config(ProviderType::Azure));
+        for capabilities in [openai.capabilities(&model), azure.capabilities(&model)] {
+            assert!(capabilities.supports_streaming && capabilities.supports_n);
+            assert!(capabilities.supports_logprobs && capabilities.supports_embeddings);
+            assert!(capabilities.supports_prediction && capabilities.supports_builtin_tools);
+            assert_eq!(capabilities.max_stop_sequences, Some(4));
+        }
+
+        let anthropic = AnthropicProvider::new(&provider_config(ProviderType::Anthropic));
+        let capabilities = anthropic.capabilities(&model);
+        assert!(capabilities.supports_tools);
+        assert!(!capabilities.supports_streaming);
+        assert!(!capabilities.supports_n && !capabilities.supports_logprobs);
+        assert!(!capabilities.supports_completions && !capabilities.supports_embeddings);
+        assert!(!capabilities.supports_prediction);
+        assert_eq!(capabilities.max_stop_sequences, None);
+
+        let vertexai = VertexAIProvider::new(&provider_config(ProviderType::VertexAI));
+        let capabilities = vertexai.capabilities(&model);
+        assert!(capabilities.supports_streaming && capabilities.supports_tools);
+        assert!(capabilities.supports_embeddings && capabilities.supports_logprobs);
+        assert!(!capabilities.supports_completions && !capabilities.supports_n);
+        assert!(!capabilities.supports_prediction);
+        assert_eq!(capabilities.max_stop_sequences, Some(5));
+    }
+
+    #[test]
+    fn test_bedrock_capabilities_follow_model_provider() {
+        let bedrock = BedrockProvider::new(&provider_config(ProviderType::Bedrock));
+
+        let anthropic = bedrock.capabilities(&model_config(&[("model_provider", "anthropic")]));
+        assert!(anthropic.supports_tools);
+        assert!(!anthropic.supports_streaming && !anthropic.supports_embeddings);
+        assert_eq!(anthropic.max_stop_sequences, None);
+
+        let titan = bedrock.capabilities(&model_config(&[("model_provider", "titan")]));
+        assert!(titan.supports_embeddings);
+        assert!(!titan.supports_tools && !titan.supports_completions);
+        assert_eq!(titan.max_stop_sequences, Some(0));
+
+        let ai21 = bedrock.capabilities(&model_config(&[("model_provider", "ai21")]));
+        assert!(ai21.supports_completions && ai21.supports_penalties);
+        assert_eq!(ai21.max_stop_sequences, None);
+        assert!(!ai21.supports_embeddings);
+    }
+}
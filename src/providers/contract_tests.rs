@@ -0,0 +1,497 @@
+//! Provider contract tests: one matrix of chat scenarios, played against a provider backed
+//! by wiremock, asserting the OpenAI-shaped output every provider must produce. A provider
+//! gets the whole matrix by describing its wire format in a [`ContractTemplates`] and
+//! invoking [`provider_contract_tests!`].
+
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use futures::StreamExt;
+use serde_json::{Value, json};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::config::models::{ModelConfig, Provider as ProviderConfig};
+use crate::models::chat::{ChatCompletion, ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
+use crate::models::tool_definition::{FunctionDefinition, FunctionTool, ToolDefinition};
+use crate::providers::provider::Provider;
+use crate::types::ProviderType;
+
+pub const MODEL: &str = "contract-model";
+pub const PROMPT_TOKENS: u32 = 12;
+pub const COMPLETION_TOKENS: u32 = 5;
+pub const TOOL_NAME: &str = "get_weather";
+pub const STOP_SEQUENCE: &str = "<|end|>";
+
+/// How a provider speaks on the wire. Responses report [`PROMPT_TOKENS`] and
+/// [`COMPLETION_TOKENS`] as their usage, in the provider's own format.
+pub trait ContractTemplates {
+    type Provider: Provider;
+
+    const PROVIDER_TYPE: ProviderType;
+
+    /// Params the provider needs besides `base_url`, which points at the mock server.
+    fn params() -> Vec<(&'static str, &'static str)> {
+        Vec::new()
+    }
+
+    /// The path chat requests for [`MODEL`] are posted to.
+    fn chat_path(stream: bool) -> String;
+
+    /// A response answering with `text`.
+    fn text_response(text: &str) -> Value;
+
+    /// A response without any content.
+    fn empty_response() -> Value;
+
+    /// A response calling [`TOOL_NAME`] with `arguments`.
+    fn tool_call_response(arguments: &Value) -> Value;
+
+    /// A response answering with `text` that ended on [`STOP_SEQUENCE`], reported the way
+    /// the provider reports it.
+    fn stop_sequence_response(text: &str) -> Value;
+
+    /// A streamed response delivering `parts` in order, for providers that stream.
+    fn stream_response(_parts: &[&str]) -> Option<ResponseTemplate> {
+        None
+    }
+
+    /// The body the provider's API sends along with an error `status`.
+    fn error_response(status: StatusCode) -> Value;
+}
+
+/// Generates a test per scenario of the contract for the given [`ContractTemplates`].
+macro_rules! provider_contract_tests {
+    ($templates:ty) => {
+        mod contract {
+            use super::*;
+            use $crate::providers::contract_tests;
+
+            #[tokio::test]
+            async fn simple_chat() {
+                contract_tests::simple_chat::<$templates>().await;
+            }
+
+            #[tokio::test]
+            async fn system_prompt() {
+                contract_tests::system_prompt::<$templates>().await;
+            }
+
+            #[tokio::test]
+            async fn multi_turn() {
+                contract_tests::multi_turn::<$templates>().await;
+            }
+
+            #[tokio::test]
+            async fn tools() {
+                contract_tests::tools::<$templates>().await;
+            }
+
+            #[tokio::test]
+            async fn streaming() {
+                contract_tests::streaming::<$templates>().await;
+            }
+
+            #[tokio::test]
+            async fn stop_sequence() {
+                contract_tests::stop_sequence::<$templates>().await;
+            }
+
+            #[tokio::test]
+            async fn streamed_stop_sequence() {
+                contract_tests::streamed_stop_sequence::<$templates>().await;
+            }
+
+            #[tokio::test]
+            async fn empty_content() {
+                contract_tests::empty_content::<$templates>().await;
+            }
+
+            #[tokio::test]
+            async fn error_mapping() {
+                contract_tests::error_mapping::<$templates>().await;
+            }
+        }
+    };
+}
+pub(crate) use provider_contract_tests;
+
+struct Harness<T: ContractTemplates> {
+    server: MockServer,
+    provider: T::Provider,
+    model_config: ModelConfig,
+}
+
+impl<T: ContractTemplates> Harness<T> {
+    async fn new() -> Self {
+        let server = MockServer::start().await;
+        let mut params: HashMap<String, String> = T::params()
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        params.insert("base_url".to_string(), server.uri());
+        let config = ProviderConfig {
+            key: "contract".to_string(),
+            r#type: T::PROVIDER_TYPE,
+            api_key: "contract-key".to_string(),
+            maintenance_windows: vec![],
+            params,
+        };
+        Self {
+            server,
+            provider: T::Provider::new(&config),
+            model_config: ModelConfig {
+                key: MODEL.to_string(),
+                r#type: MODEL.to_string(),
+                provider: "contract".to_string(),
+                params: HashMap::new(),
+                enabled: true,
+                deprecation: Default::default(),
+            },
+        }
+    }
+
+    async fn respond_with(&self, stream: bool, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(path(T::chat_path(stream)))
+            .respond_with(response)
+            .expect(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    async fn chat(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        self.provider
+            .chat_completions(request, &self.model_config)
+            .await
+    }
+
+    /// Sends `request`, expecting a complete response.
+    async fn completion(&self, request: ChatCompletionRequest) -> ChatCompletion {
+        match self.chat(request).await {
+            Ok(ChatCompletionResponse::NonStream(completion)) => completion,
+            Ok(ChatCompletionResponse::Stream(_)) => panic!("expected a complete response"),
+            Err(status) => panic!("chat failed with {status}"),
+        }
+    }
+
+    /// The body of the only request the upstream received.
+    async fn upstream_body(&self) -> String {
+        let requests = self.server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        String::from_utf8(requests[0].body.clone()).unwrap()
+    }
+}
+
+fn message(role: &str, text: &str) -> ChatCompletionMessage {
+    ChatCompletionMessage {
+        role: role.to_string(),
+        content: Some(ChatMessageContent::String(text.to_string())),
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+        refusal: None,
+        annotations: None,
+    }
+}
+
+fn request(messages: Vec<ChatCompletionMessage>) -> ChatCompletionRequest {
+    let mut request: ChatCompletionRequest =
+        serde_json::from_value(json!({"model": MODEL, "messages": []})).unwrap();
+    request.messages = messages;
+    request
+}
+
+fn text(message: &ChatCompletionMessage) -> String {
+    message
+        .content
+        .as_ref()
+        .map(|content| content.text_parts().concat())
+        .unwrap_or_default()
+}
+
+/// The invariants of every complete response: one assistant choice with a finish reason,
+/// and the usage the upstream reported.
+fn assert_normalized(completion: &ChatCompletion) {
+    assert_eq!(completion.choices.len(), 1);
+    let choice = &completion.choices[0];
+    assert_eq!(choice.index, 0);
+    assert_eq!(choice.message.role, "assistant");
+    assert!(
+        choice
+            .finish_reason
+            .as_deref()
+            .is_some_and(|reason| !reason.is_empty()),
+        "missing finish_reason"
+    );
+    let usage = &completion.usage;
+    assert_eq!(usage.prompt_tokens, PROMPT_TOKENS);
+    assert_eq!(usage.completion_tokens, COMPLETION_TOKENS);
+    assert_eq!(usage.total_tokens, PROMPT_TOKENS + COMPLETION_TOKENS);
+}
+
+/// Asserts that `parts` occur in `body` in the given order.
+fn assert_in_order(body: &str, parts: &[&str]) {
+    let mut position = 0;
+    for part in parts {
+        let found = body[position..]
+            .find(part)
+            .unwrap_or_else(|| panic!("{part:?} missing from the upstream request: {body}"));
+        position += found + part.len();
+    }
+}
+
+pub async fn simple_chat<T: ContractTemplates>() {
+    let harness = Harness::<T>::new().await;
+    harness
+        .respond_with(
+            false,
+            ResponseTemplate::new(200).set_body_json(T::text_response("Paris.")),
+        )
+        .await;
+
+    let completion = harness
+        .completion(request(vec![message("user", "Capital of France?")]))
+        .await;
+    assert_normalized(&completion);
+    assert_eq!(text(&completion.choices[0].message), "Paris.");
+    assert!(completion.choices[0].message.tool_calls.is_none());
+    assert_in_order(&harness.upstream_body().await, &["Capital of France?"]);
+}
+
+pub async fn system_prompt<T: ContractTemplates>() {
+    let harness = Harness::<T>::new().await;
+    harness
+        .respond_with(
+            false,
+            ResponseTemplate::new(200).set_body_json(T::text_response("Bonjour.")),
+        )
+        .await;
+
+    let completion = harness
+        .completion(request(vec![
+            message("system", "Answer in French."),
+            message("user", "Say hello."),
+        ]))
+        .await;
+    assert_normalized(&completion);
+    assert_eq!(text(&completion.choices[0].message), "Bonjour.");
+    let body = harness.upstream_body().await;
+    assert_in_order(&body, &["Answer in French."]);
+    assert_in_order(&body, &["Say hello."]);
+}
+
+pub async fn multi_turn<T: ContractTemplates>() {
+    let harness = Harness::<T>::new().await;
+    harness
+        .respond_with(
+            false,
+            ResponseTemplate::new(200).set_body_json(T::text_response("Lyon.")),
+        )
+        .await;
+
+    let completion = harness
+        .completion(request(vec![
+            message("user", "Capital of France?"),
+            message("assistant", "Paris."),
+            message("user", "And its second city?"),
+        ]))
+        .await;
+    assert_normalized(&completion);
+    assert_eq!(text(&completion.choices[0].message), "Lyon.");
+    assert_in_order(
+        &harness.upstream_body().await,
+        &["Capital of France?", "Paris.", "And its second city?"],
+    );
+}
+
+pub async fn tools<T: ContractTemplates>() {
+    let harness = Harness::<T>::new().await;
+    let arguments = json!({"location": "Paris", "unit": "celsius"});
+    harness
+        .respond_with(
+            false,
+            ResponseTemplate::new(200).set_body_json(T::tool_call_response(&arguments)),
+        )
+        .await;
+
+    let parameters = json!({
+        "type": "object",
+        "properties": {
+            "location": {"type": "string"},
+            "unit": {"type": "string", "enum": ["celsius", "fahrenheit"]}
+        },
+        "required": ["location"]
+    });
+    let mut chat = request(vec![message("user", "Weather in Paris?")]);
+    chat.tools = Some(vec![ToolDefinition::Function(FunctionTool {
+        function: FunctionDefinition {
+            name: TOOL_NAME.to_string(),
+            description: Some("Current weather for a location".to_string()),
+            parameters: serde_json::from_value(parameters).unwrap(),
+            strict: None,
+        },
+        tool_type: "function".to_string(),
+    })]);
+
+    let completion = harness.completion(chat).await;
+    assert_normalized(&completion);
+    let tool_calls = completion.choices[0].message.tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls.len(), 1);
+    assert!(!tool_calls[0].id.is_empty());
+    assert_eq!(tool_calls[0].r#type, "function");
+    assert_eq!(tool_calls[0].function.name, TOOL_NAME);
+    let sent: Value = serde_json::from_str(&tool_calls[0].function.arguments).unwrap();
+    assert_eq!(sent, arguments);
+    assert_in_order(
+        &harness.upstream_body().await,
+        &[TOOL_NAME, "Current weather for a location"],
+    );
+}
+
+pub async fn streaming<T: ContractTemplates>() {
+    let harness = Harness::<T>::new().await;
+    let parts = ["The capital ", "of France ", "is Paris."];
+    let supports_streaming = harness
+        .provider
+        .capabilities(&harness.model_config)
+        .supports_streaming;
+    let Some(response) = T::stream_response(&parts) else {
+        assert!(
+            !supports_streaming,
+            "no stream template for a streaming provider"
+        );
+        return;
+    };
+    assert!(
+        supports_streaming,
+        "stream template for a provider that doesn't stream"
+    );
+    harness.respond_with(true, response).await;
+
+    let mut chat = request(vec![message("user", "Capital of France?")]);
+    chat.stream = Some(true);
+    let Ok(ChatCompletionResponse::Stream(stream)) = harness.chat(chat).await else {
+        panic!("expected a stream");
+    };
+    let chunks: Vec<_> = stream.map(|chunk| chunk.unwrap()).collect().await;
+
+    let content: String = chunks
+        .iter()
+        .flat_map(|chunk| &chunk.choices)
+        .filter_map(|choice| choice.delta.content.as_deref())
+        .collect();
+    assert_eq!(content, parts.concat());
+    assert!(chunks.iter().all(|chunk| !chunk.model.is_empty()));
+    let last = chunks
+        .iter()
+        .rev()
+        .find_map(|chunk| chunk.choices.first())
+        .expect("stream without choices");
+    assert!(
+        last.finish_reason.is_some(),
+        "last choice without finish_reason"
+    );
+}
+
+pub async fn stop_sequence<T: ContractTemplates>() {
+    let harness = Harness::<T>::new().await;
+    harness
+        .respond_with(
+            false,
+            ResponseTemplate::new(200).set_body_json(T::stop_sequence_response("Paris.")),
+        )
+        .await;
+
+    let mut chat = request(vec![message("user", "Capital of France?")]);
+    chat.stop = Some(vec![STOP_SEQUENCE.to_string()]);
+    let completion = harness.completion(chat).await;
+    assert_normalized(&completion);
+    let choice = &completion.choices[0];
+    assert_eq!(text(&choice.message), "Paris.");
+    assert_eq!(choice.finish_reason.as_deref(), Some("stop"));
+    // Providers that don't say which sequence matched leave it out.
+    assert!(
+        choice
+            .stop_sequence
+            .as_deref()
+            .is_none_or(|sequence| sequence == STOP_SEQUENCE)
+    );
+    assert_in_order(&harness.upstream_body().await, &[STOP_SEQUENCE]);
+}
+
+pub async fn streamed_stop_sequence<T: ContractTemplates>() {
+    let harness = Harness::<T>::new().await;
+    // The sequence is split across chunks, and followed by text the upstream shouldn't send.
+    let parts = ["The capital is Paris.", "<|e", "nd|>", " Next question"];
+    let Some(response) = T::stream_response(&parts) else {
+        return;
+    };
+    harness.respond_with(true, response).await;
+
+    let mut chat = request(vec![message("user", "Capital of France?")]);
+    chat.stream = Some(true);
+    chat.stop = Some(vec![STOP_SEQUENCE.to_string()]);
+    let Ok(ChatCompletionResponse::Stream(stream)) = harness.chat(chat).await else {
+        panic!("expected a stream");
+    };
+    let chunks: Vec<_> = stream.map(|chunk| chunk.unwrap()).collect().await;
+
+    let content: String = chunks
+        .iter()
+        .flat_map(|chunk| &chunk.choices)
+        .filter_map(|choice| choice.delta.content.as_deref())
+        .collect();
+    assert_eq!(content, "The capital is Paris.");
+    let last = chunks
+        .iter()
+        .rev()
+        .find_map(|chunk| chunk.choices.first())
+        .expect("stream without choices");
+    assert_eq!(last.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(last.stop_sequence.as_deref(), Some(STOP_SEQUENCE));
+}
+
+pub async fn empty_content<T: ContractTemplates>() {
+    let harness = Harness::<T>::new().await;
+    harness
+        .respond_with(
+            false,
+            ResponseTemplate::new(200).set_body_json(T::empty_response()),
+        )
+        .await;
+
+    let completion = harness
+        .completion(request(vec![message("user", "Say nothing.")]))
+        .await;
+    assert_normalized(&completion);
+    assert_eq!(text(&completion.choices[0].message), "");
+    assert!(completion.choices[0].message.tool_calls.is_none());
+}
+
+pub async fn error_mapping<T: ContractTemplates>() {
+    for status in [
+        StatusCode::BAD_REQUEST,
+        StatusCode::UNAUTHORIZED,
+        StatusCode::TOO_MANY_REQUESTS,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        StatusCode::SERVICE_UNAVAILABLE,
+    ] {
+        let harness = Harness::<T>::new().await;
+        harness
+            .respond_with(
+                false,
+                ResponseTemplate::new(status.as_u16()).set_body_json(T::error_response(status)),
+            )
+            .await;
+
+        let result = harness
+            .chat(request(vec![message("user", "Capital of France?")]))
+            .await;
+        assert_eq!(result.err(), Some(status));
+    }
+}
@@ -0,0 +1,470 @@
+use crate::metrics::counter;
+use crate::pipelines::explain::ExplainStep;
+use async_trait::async_trait;
+use axum::http::{HeaderValue, Request, StatusCode};
+use axum::response::Response;
+use chrono::Utc;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::config::models::{ModelConfig, Provider as ProviderConfig};
+use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::completion::{CompletionRequest, CompletionResponse};
+use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
+use crate::providers::capabilities::Capabilities;
+use crate::providers::maintenance::{MaintenanceSchedule, record_maintenance_skip};
+use crate::providers::provider::Provider;
+use crate::providers::registry::build_provider;
+use crate::providers::upstream::UpstreamRequest;
+use crate::types::ProviderType;
+
+/// Provider param naming the failover group the provider belongs to. Models can reference
+/// the group name instead of a provider key.
+pub const GROUP_PARAM: &str = "group";
+/// Provider param ordering members of a failover group; lower values are tried first.
+pub const GROUP_PRIORITY_PARAM: &str = "group_priority";
+/// Response header naming the group member that served the request.
+pub const SERVED_BY_HEADER: &str = "x-hub-served-by";
+/// Counts requests served by a failover group, labelled by group and serving provider.
+pub const SERVED_METRIC: &str = "hub_failover_group_requests_total";
+/// Counts failed attempts that moved a request on to the next group member.
+pub const FAILOVER_METRIC: &str = "hub_failover_total";
+
+/// Consecutive failures that open a member's circuit.
+const FAILURE_THRESHOLD: u32 = 3;
+/// How long an open circuit keeps a member out of rotation.
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+tokio::task_local! {
+    static SERVED_BY: Arc<Mutex<Option<String>>>;
+}
+
+/// Runs a model call, returning which failover group member served it. `None` when the
+/// model's provider isn't a group.
+pub async fn track_served_by<F: Future>(call: F) -> (F::Output, Option<String>) {
+    let served_by = Arc::new(Mutex::new(None));
+    let output = SERVED_BY.scope(served_by.clone(), call).await;
+    let served_by = served_by.lock().unwrap().take();
+    (output, served_by)
+}
+
+/// Adds `x-hub-served-by` to a response served by a failover group.
+pub fn inject_served_by_header(response: &mut Response, served_by: Option<&str>) {
+    if let Some(value) = served_by.and_then(|key| HeaderValue::from_str(key).ok()) {
+        response.headers_mut().insert(SERVED_BY_HEADER, value);
+    }
+}
+
+/// Failover group name of a provider, if it belongs to one.
+pub fn provider_group(config: &ProviderConfig) -> Option<&str> {
+    config
+        .params
+        .get(GROUP_PARAM)
+        .map(String::as_str)
+        .filter(|group| !group.is_empty())
+}
+
+/// Priority of a provider within its group. Unset priorities sort as 0.
+pub fn group_priority(config: &ProviderConfig) -> Result<i32, String> {
+    config
+        .params
+        .get(GROUP_PRIORITY_PARAM)
+        .map_or(Ok(0), |value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| format!("{GROUP_PRIORITY_PARAM} must be an integer"))
+        })
+}
+
+/// Checks that each failover group's members share a provider type and parse their
+/// priority, and that no group is named like a provider.
+pub fn validate_failover_groups(providers: &[ProviderConfig]) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut group_types: BTreeMap<&str, ProviderType> = BTreeMap::new();
+    for provider in providers {
+        let Some(group) = provider_group(provider) else {
+            continue;
+        };
+        if let Err(e) = group_priority(provider) {
+            errors.push(format!("Provider '{}': {e}.", provider.key));
+        }
+        match group_types.get(group) {
+            Some(group_type) if *group_type != provider.r#type => errors.push(format!(
+                "Provider '{}' is of type {} but failover group '{group}' holds {} providers.",
+                provider.key, provider.r#type, group_type
+            )),
+            Some(_) => {}
+            None => {
+                group_types.insert(group, provider.r#type);
+            }
+        }
+    }
+    for group in group_types.keys() {
+        if providers.iter().any(|provider| provider.key == *group) {
+            errors.push(format!(
+                "Failover group '{group}' has the same name as a provider."
+            ));
+        }
+    }
+    errors
+}
+
+/// Errors another region may not have: upstream outages, throttling and timeouts.
+/// Client errors would fail the same way everywhere.
+fn is_regional_failure(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+/// Consecutive failures of a group member. Opens after `FAILURE_THRESHOLD` failures and
+/// lets a request through again once `OPEN_DURATION` has passed.
+#[derive(Default)]
+struct Circuit {
+    state: Mutex<CircuitState>,
+}
+
+#[derive(Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Circuit {
+    fn is_open(&self, now: Instant) -> bool {
+        let state = self.state.lock().unwrap();
+        state.open_until.is_some_and(|until| now < until)
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = CircuitState::default();
+    }
+
+    fn record_failure(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= FAILURE_THRESHOLD {
+            state.open_until = Some(now + OPEN_DURATION);
+        }
+    }
+}
+
+struct Member {
+    key: String,
+    provider: Arc<dyn Provider>,
+    circuit: Circuit,
+    maintenance: MaintenanceSchedule,
+}
+
+/// Providers of one type deployed in several regions, served as one provider. Requests go
+/// to the members in priority order, skipping members in a maintenance window or whose
+/// circuit is open, and move on to the next member when one fails with an error another
+/// region may not have.
+pub struct FailoverProvider {
+    group: String,
+    members: Vec<Member>,
+}
+
+impl FailoverProvider {
+    /// Groups `members`, ordered by their `group_priority` and then by config order.
+    pub fn group(group: &str, members: Vec<(&ProviderConfig, Arc<dyn Provider>)>) -> Self {
+        let mut members: Vec<(i32, Member)> = members
+            .into_iter()
+            .map(|(config, provider)| {
+                let member = Member {
+                    key: config.key.clone(),
+                    provider,
+                    circuit: Circuit::default(),
+                    maintenance: MaintenanceSchedule::new(&config.maintenance_windows),
+                };
+                (group_priority(config).unwrap_or(0), member)
+            })
+            .collect();
+        members.sort_by_key(|(priority, _)| *priority);
+        Self {
+            group: group.to_string(),
+            members: members.into_iter().map(|(_, member)| member).collect(),
+        }
+    }
+
+    /// Members in the order to try them. Members in a maintenance window are left out, and
+    /// so are members with open circuits, unless every remaining circuit is open, in which
+    /// case all remaining members are tried.
+    fn attempt_order(&self, now: Instant) -> Vec<&Member> {
+        let wall_clock = Utc::now();
+        let mut available: Vec<&Member> = self
+            .members
+            .iter()
+            .filter(|member| member.maintenance.active_at(wall_clock).is_none())
+            .collect();
+        // The router doesn't pick a group whose members are all in maintenance.
+        if available.is_empty() {
+            available = self.members.iter().collect();
+        }
+        let closed: Vec<&Member> = available
+            .iter()
+            .copied()
+            .filter(|member| !member.circuit.is_open(now))
+            .collect();
+        if closed.is_empty() { available } else { closed }
+    }
+
+    /// The member building requests and answering capability queries.
+    fn primary(&self) -> &Member {
+        self.attempt_order(Instant::now())[0]
+    }
+
+    async fn dispatch<T, F, Fut>(&self, call: F) -> Result<T, StatusCode>
+    where
+        F: Fn(Arc<dyn Provider>) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T, StatusCode>> + Send,
+        T: Send,
+    {
+        let wall_clock = Utc::now();
+        for member in &self.members {
+            if member.maintenance.active_at(wall_clock).is_some() {
+                record_maintenance_skip(&member.key);
+            }
+        }
+        let attempt_order = self.attempt_order(Instant::now());
+        for member in &self.members {
+            if !attempt_order
+                .iter()
+                .any(|attempt| attempt.key == member.key)
+            {
+                let reason = if member.maintenance.active_at(wall_clock).is_some() {
+                    "maintenance"
+                } else {
+                    "circuit_open"
+                };
+                self.member_step(member, "skipped", json!({ "reason": reason }))
+                    .record();
+            }
+        }
+        let mut last_error = StatusCode::SERVICE_UNAVAILABLE;
+        for member in attempt_order {
+            let started = Instant::now();
+            let result = call(member.provider.clone()).await;
+            let elapsed = started.elapsed();
+            match result {
+                Ok(response) => {
+                    self.member_step(member, "served", Value::Null)
+                        .took(elapsed)
+                        .record();
+                    member.circuit.record_success();
+                    counter!(
+                        SERVED_METRIC,
+                        "group" => self.group.clone(),
+                        "provider" => member.key.clone()
+                    )
+                    .increment(1);
+                    let _ = SERVED_BY.try_with(|served_by| {
+                        *served_by.lock().unwrap() = Some(member.key.clone());
+                    });
+                    return Ok(response);
+                }
+                Err(status) if is_regional_failure(status) => {
+                    self.member_step(member, "failed", json!({ "status": status.as_u16() }))
+                        .took(elapsed)
+                        .record();
+                    member.circuit.record_failure(Instant::now());
+                    counter!(
+                        FAILOVER_METRIC,
+                        "group" => self.group.clone(),
+                        "provider" => member.key.clone()
+                    )
+                    .increment(1);
+                    warn!(
+                        group = %self.group,
+                        provider = %member.key,
+                        status = status.as_u16(),
+                        "Failover group member failed, trying the next one"
+                    );
+                    last_error = status;
+                }
+                Err(status) => {
+                    self.member_step(member, "failed", json!({ "status": status.as_u16() }))
+                        .took(elapsed)
+                        .record();
+                    return Err(status);
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// The explain step of what happened to `member`, with `details` added to its fields.
+    fn member_step(&self, member: &Member, decision: &str, details: Value) -> ExplainStep {
+        let mut fields = json!({ "group": self.group, "provider": member.key });
+        if let (Some(fields), Value::Object(details)) = (fields.as_object_mut(), details) {
+            fields.extend(details);
+        }
+        ExplainStep::new("failover", decision).details(fields)
+    }
+}
+
+#[async_trait]
+impl Provider for FailoverProvider {
+    /// A group with `config` as its only member. Panics if the member can't be built, which
+    /// the registry checks before building its groups.
+    fn new(config: &ProviderConfig) -> Self {
+        let provider = build_provider(config).expect("failover member should build");
+        Self::group(
+            provider_group(config).unwrap_or(&config.key),
+            vec![(config, provider)],
+        )
+    }
+
+    fn key(&self) -> String {
+        self.group.clone()
+    }
+
+    fn r#type(&self) -> ProviderType {
+        self.members[0].provider.r#type()
+    }
+
+    fn capabilities(&self, model_config: &ModelConfig) -> Capabilities {
+        self.primary().provider.capabilities(model_config)
+    }
+
+    /// Unhealthy only when no member can serve requests.
+    fn unhealthy_reason(&self) -> Option<String> {
+        let reasons: BTreeMap<String, String> = self
+            .members
+            .iter()
+            .map(|member| Some((member.key.clone(), member.provider.unhealthy_reason()?)))
+            .collect::<Option<_>>()?;
+        Some(
+            reasons
+                .into_iter()
+                .map(|(key, reason)| format!("{key}: {reason}"))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    async fn chat_completions(
+        &self,
+        payload: ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        self.dispatch(|provider| {
+            let payload = payload.clone();
+            async move { provider.chat_completions(payload, model_config).await }
+        })
+        .await
+    }
+
+    async fn completions(
+        &self,
+        payload: CompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<CompletionResponse, StatusCode> {
+        self.dispatch(|provider| {
+            let payload = payload.clone();
+            async move { provider.completions(payload, model_config).await }
+        })
+        .await
+    }
+
+    async fn embeddings(
+        &self,
+        payload: EmbeddingsRequest,
+        model_config: &ModelConfig,
+    ) -> Result<EmbeddingsResponse, StatusCode> {
+        self.dispatch(|provider| {
+            let payload = payload.clone();
+            async move { provider.embeddings(payload, model_config).await }
+        })
+        .await
+    }
+
+    async fn build_chat_request(
+        &self,
+        payload: &ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        self.primary()
+            .provider
+            .build_chat_request(payload, model_config)
+            .await
+    }
+
+    async fn count_tokens(
+        &self,
+        payload: &ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<u32, StatusCode> {
+        self.primary()
+            .provider
+            .count_tokens(payload, model_config)
+            .await
+    }
+
+    async fn build_completion_request(
+        &self,
+        payload: &CompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        self.primary()
+            .provider
+            .build_completion_request(payload, model_config)
+            .await
+    }
+
+    async fn build_embeddings_request(
+        &self,
+        payload: &EmbeddingsRequest,
+        model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        self.primary()
+            .provider
+            .build_embeddings_request(payload, model_config)
+            .await
+    }
+
+    fn build_realtime_request(
+        &self,
+        model: &str,
+        model_config: &ModelConfig,
+    ) -> Result<Request<()>, StatusCode> {
+        self.primary()
+            .provider
+            .build_realtime_request(model, model_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_opens_after_consecutive_failures() {
+        let circuit = Circuit::default();
+        let now = Instant::now();
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            circuit.record_failure(now);
+        }
+        assert!(!circuit.is_open(now));
+        circuit.record_failure(now);
+        assert!(circuit.is_open(now));
+        assert!(!circuit.is_open(now + OPEN_DURATION));
+
+        circuit.record_success();
+        circuit.record_failure(now);
+        assert!(!circuit.is_open(now));
+    }
+
+    #[test]
+    fn test_regional_failures() {
+        assert!(is_regional_failure(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_regional_failure(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_regional_failure(StatusCode::BAD_REQUEST));
+        assert!(!is_regional_failure(StatusCode::UNAUTHORIZED));
+    }
+}
@@ -0,0 +1,418 @@
+use crate::metrics::counter;
+use axum::Json;
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Datelike, FixedOffset, SecondsFormat, TimeDelta, Timelike, Utc};
+use serde_json::json;
+
+use crate::types::MaintenanceWindow;
+
+/// Counts routing candidates skipped because their provider couldn't serve, labelled by
+/// provider and reason.
+pub const SKIPPED_METRIC: &str = "hub_router_candidates_skipped_total";
+/// Longest recurring window, which bounds how far back a schedule is searched.
+const MAX_DURATION_MINUTES: u32 = 7 * 24 * 60;
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Counts a routing candidate skipped because its provider is in a maintenance window.
+pub fn record_maintenance_skip(provider: &str) {
+    counter!(
+        SKIPPED_METRIC,
+        "provider" => provider.to_string(),
+        "reason" => "maintenance"
+    )
+    .increment(1);
+}
+
+/// Values allowed in each field of a five-field cron expression, as bit sets.
+#[derive(Debug)]
+struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// When both day fields are restricted, a time matching either one matches, as in cron.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Cron {
+    fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let &[minutes, hours, days, months, weekdays] = fields.as_slice() else {
+            return Err(format!(
+                "schedule '{expression}' must have five fields: minute hour day-of-month \
+                 month day-of-week"
+            ));
+        };
+        let mut weekday_bits = parse_field(weekdays, 0, 7, &WEEKDAY_NAMES)?;
+        // Both 0 and 7 are Sunday.
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits = (weekday_bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59, &[])?,
+            hours: parse_field(hours, 0, 23, &[])?,
+            days: parse_field(days, 1, 31, &[])?,
+            months: parse_field(months, 1, 12, &MONTH_NAMES)?,
+            weekdays: weekday_bits,
+            days_restricted: !days.starts_with('*'),
+            weekdays_restricted: !weekdays.starts_with('*'),
+        })
+    }
+
+    fn matches(&self, time: &DateTime<FixedOffset>) -> bool {
+        let has = |bits: u64, value: u32| bits & (1 << value) != 0;
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+        has(self.minutes, time.minute())
+            && has(self.hours, time.hour())
+            && has(self.months, time.month())
+            && day_matches
+    }
+}
+
+/// Parses a comma-separated list of `*`, values and `a-b` ranges, each with an optional
+/// `/step`. `names` spell out the values from `min` on, e.g. `mon` for a weekday.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let value = match names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(text))
+        {
+            Some(index) => index as u32 + min,
+            None => text
+                .parse()
+                .map_err(|_| format!("'{text}' is not a valid value in '{field}'"))?,
+        };
+        if !(min..=max).contains(&value) {
+            return Err(format!("{value} is out of range {min}-{max} in '{field}'"));
+        }
+        Ok(value)
+    };
+
+    let mut bits = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("'{step}' is not a valid step in '{field}'")),
+            },
+            None => (item, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if start > end {
+            return Err(format!("range '{range}' is reversed in '{field}'"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// Parses a UTC offset such as `+02:00`, `-0530` or `UTC`.
+fn parse_offset(timezone: &str) -> Result<FixedOffset, String> {
+    let invalid = || format!("timezone '{timezone}' must be UTC or an offset such as +02:00");
+    if timezone.eq_ignore_ascii_case("utc") || timezone == "Z" {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+    let (sign, offset) = match timezone.split_at_checked(1) {
+        Some(("+", offset)) => (1, offset),
+        Some(("-", offset)) => (-1, offset),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = match offset.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if offset.len() == 4 => offset.split_at(2),
+        None => (offset, "0"),
+    };
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes > 59 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+#[derive(Debug)]
+enum Window {
+    OneOff {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+    Recurring {
+        cron: Cron,
+        duration: TimeDelta,
+        offset: FixedOffset,
+    },
+}
+
+impl Window {
+    fn parse(window: &MaintenanceWindow) -> Result<Self, String> {
+        let timestamp = |value: &str| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|time| time.to_utc())
+                .map_err(|_| format!("'{value}' is not an RFC 3339 timestamp"))
+        };
+        match window {
+            MaintenanceWindow {
+                start: Some(start),
+                end: Some(end),
+                schedule: None,
+                duration_minutes: None,
+                timezone: None,
+            } => {
+                let (start, end) = (timestamp(start.as_str())?, timestamp(end.as_str())?);
+                if end <= start {
+                    return Err("end must be after start".to_string());
+                }
+                Ok(Window::OneOff { start, end })
+            }
+            MaintenanceWindow {
+                start: None,
+                end: None,
+                schedule: Some(schedule),
+                duration_minutes: Some(duration_minutes),
+                timezone,
+            } => {
+                if !(1..=MAX_DURATION_MINUTES).contains(duration_minutes) {
+                    return Err(format!(
+                        "duration_minutes must be between 1 and {MAX_DURATION_MINUTES}"
+                    ));
+                }
+                Ok(Window::Recurring {
+                    cron: Cron::parse(schedule)?,
+                    duration: TimeDelta::minutes((*duration_minutes).into()),
+                    offset: parse_offset(timezone.as_deref().unwrap_or("UTC"))?,
+                })
+            }
+            _ => Err(
+                "set either start and end, or schedule and duration_minutes with an optional \
+                 timezone"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// End of the occurrence of the window that covers `now`, if any.
+    fn active_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Window::OneOff { start, end } => (*start <= now && now < *end).then_some(*end),
+            Window::Recurring {
+                cron,
+                duration,
+                offset,
+            } => {
+                // The latest start at or before `now` is the one ending last.
+                let minute = now
+                    .with_timezone(offset)
+                    .with_second(0)?
+                    .with_nanosecond(0)?;
+                (0..duration.num_minutes())
+                    .map(|back| minute - TimeDelta::minutes(back))
+                    .find(|start| cron.matches(start))
+                    .map(|start| (start + *duration).to_utc())
+            }
+        }
+    }
+}
+
+/// Checks that a maintenance window is either a valid one-off or a valid recurring window.
+pub fn validate_maintenance_window(window: &MaintenanceWindow) -> Result<(), String> {
+    Window::parse(window).map(|_| ())
+}
+
+/// A provider's parsed maintenance windows.
+#[derive(Debug, Default)]
+pub struct MaintenanceSchedule {
+    windows: Vec<Window>,
+}
+
+impl MaintenanceSchedule {
+    /// Parses `windows`, leaving out invalid ones, which config validation reports.
+    pub fn new(windows: &[MaintenanceWindow]) -> Self {
+        Self {
+            windows: windows
+                .iter()
+                .filter_map(|window| Window::parse(window).ok())
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// End of the maintenance window covering `now`, or the latest end when windows overlap.
+    pub fn active_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.windows
+            .iter()
+            .filter_map(|window| window.active_at(now))
+            .max()
+    }
+}
+
+/// No routing candidate could serve a request because their providers are in maintenance.
+/// Reports the provider that comes back first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InMaintenance {
+    pub provider: String,
+    pub until: DateTime<Utc>,
+}
+
+impl std::fmt::Display for InMaintenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Provider '{}' is in a scheduled maintenance window until {}",
+            self.provider,
+            self.until.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )
+    }
+}
+
+impl IntoResponse for InMaintenance {
+    fn into_response(self) -> Response {
+        let retry_after = (self.until - Utc::now()).num_seconds().max(1);
+        let body = json!({
+            "error": {
+                "type": "provider_maintenance",
+                "message": self.to_string(),
+            }
+        });
+        let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    fn recurring(
+        schedule: &str,
+        duration_minutes: u32,
+        timezone: Option<&str>,
+    ) -> MaintenanceWindow {
+        MaintenanceWindow {
+            schedule: Some(schedule.to_string()),
+            duration_minutes: Some(duration_minutes),
+            timezone: timezone.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_one_off_window_boundaries() {
+        let schedule = MaintenanceSchedule::new(&[MaintenanceWindow {
+            start: Some("2025-03-01T22:00:00+01:00".to_string()),
+            end: Some("2025-03-02T02:00:00+01:00".to_string()),
+            ..Default::default()
+        }]);
+        let end = at("2025-03-02T01:00:00Z");
+        assert_eq!(schedule.active_at(at("2025-03-01T20:59:59Z")), None);
+        assert_eq!(schedule.active_at(at("2025-03-01T21:00:00Z")), Some(end));
+        assert_eq!(schedule.active_at(at("2025-03-02T00:59:59Z")), Some(end));
+        // The end is exclusive.
+        assert_eq!(schedule.active_at(at("2025-03-02T01:00:00Z")), None);
+    }
+
+    #[test]
+    fn test_recurring_window_in_timezone() {
+        // Saturdays 23:00-01:00 at UTC+02:00, i.e. 21:00-23:00 UTC.
+        let schedule = MaintenanceSchedule::new(&[recurring("0 23 * * SAT", 120, Some("+02:00"))]);
+        // 2025-03-01 is a Saturday.
+        let end = at("2025-03-01T23:00:00Z");
+        assert_eq!(schedule.active_at(at("2025-03-01T20:59:59Z")), None);
+        assert_eq!(schedule.active_at(at("2025-03-01T21:00:00Z")), Some(end));
+        // Past midnight locally, the window started on Saturday still covers Sunday.
+        assert_eq!(schedule.active_at(at("2025-03-01T22:59:59Z")), Some(end));
+        assert_eq!(schedule.active_at(at("2025-03-01T23:00:00Z")), None);
+        // 23:30 UTC is 01:30 on Sunday locally, past the window.
+        assert_eq!(schedule.active_at(at("2025-03-01T23:30:00Z")), None);
+        assert_eq!(
+            schedule.active_at(at("2025-03-08T21:30:00Z")),
+            Some(at("2025-03-08T23:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_recurring_window_defaults_to_utc() {
+        let schedule = MaintenanceSchedule::new(&[recurring("30 2 1 * *", 30, None)]);
+        assert_eq!(
+            schedule.active_at(at("2025-04-01T02:45:00Z")),
+            Some(at("2025-04-01T03:00:00Z"))
+        );
+        assert_eq!(schedule.active_at(at("2025-04-02T02:45:00Z")), None);
+    }
+
+    #[test]
+    fn test_cron_fields() {
+        let cron = Cron::parse("*/15 9-17 * jan,JUL 1-5").unwrap();
+        let time = |time: &str| DateTime::parse_from_rfc3339(time).unwrap();
+        // 2025-07-01 is a Tuesday.
+        assert!(cron.matches(&time("2025-07-01T09:45:00Z")));
+        assert!(!cron.matches(&time("2025-07-01T09:50:00Z")));
+        assert!(!cron.matches(&time("2025-07-01T18:00:00Z")));
+        assert!(!cron.matches(&time("2025-08-01T09:00:00Z")));
+        assert!(!cron.matches(&time("2025-07-05T09:00:00Z")));
+
+        // Restricted day-of-month and day-of-week match either, as in cron; 7 is Sunday.
+        let cron = Cron::parse("0 0 13 * 7").unwrap();
+        assert!(cron.matches(&time("2025-06-13T00:00:00Z")));
+        assert!(cron.matches(&time("2025-06-15T00:00:00Z")));
+        assert!(!cron.matches(&time("2025-06-14T00:00:00Z")));
+    }
+
+    #[test]
+    fn test_invalid_windows() {
+        let invalid = [
+            recurring("0 23 * *", 60, None),
+            recurring("0 24 * * *", 60, None),
+            recurring("0 5-3 * * *", 60, None),
+            recurring("0 23 * * *", 0, None),
+            recurring("0 23 * * *", 60, Some("Europe/Berlin")),
+            MaintenanceWindow {
+                start: Some("2025-03-02T00:00:00Z".to_string()),
+                end: Some("2025-03-01T00:00:00Z".to_string()),
+                ..Default::default()
+            },
+            MaintenanceWindow {
+                start: Some("2025-03-01".to_string()),
+                end: Some("2025-03-02".to_string()),
+                ..Default::default()
+            },
+            MaintenanceWindow {
+                start: Some("2025-03-01T00:00:00Z".to_string()),
+                ..Default::default()
+            },
+        ];
+        for window in &invalid {
+            assert!(validate_maintenance_window(window).is_err(), "{window:?}");
+        }
+        assert!(validate_maintenance_window(&recurring("0 23 * * *", 60, Some("-05:30"))).is_ok());
+    }
+}
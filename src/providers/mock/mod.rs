@@ -0,0 +1,10 @@
+mod provider;
+
+#[cfg(test)]
+mod test;
+
+pub use provider::{
+    CHUNK_DELAY_MS_PARAM, EMBEDDING_DIMENSIONS_PARAM, FAIL_EVERY_PARAM, FAIL_STATUS_PARAM,
+    LATENCY_MS_PARAM, MODE_PARAM, MockProvider, RESPONSE_PARAM, TOOL_ARGUMENTS_PARAM,
+    validate_mock_params,
+};
@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use futures::stream::{self, StreamExt};
+use reqwest_streams::error::StreamBodyError;
+
+use crate::config::models::{ModelConfig, Provider as ProviderConfig};
+use crate::models::chat::{
+    ChatCompletion, ChatCompletionChoice, ChatCompletionRequest, ChatCompletionResponse,
+};
+use crate::models::completion::{CompletionChoice, CompletionRequest, CompletionResponse};
+use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
+use crate::models::embeddings::{
+    Embedding, Embeddings, EmbeddingsInput, EmbeddingsRequest, EmbeddingsResponse,
+};
+use crate::models::streaming::{ChatCompletionChunk, Choice, ChoiceDelta};
+use crate::models::tool_calls::{ChatMessageToolCall, ChoiceDeltaToolCall, FunctionCall};
+use crate::models::tool_choice::{SimpleToolChoice, ToolChoice};
+use crate::models::tool_definition::ToolDefinition;
+use crate::models::usage::{EmbeddingUsage, Usage};
+use crate::providers::capabilities::Capabilities;
+use crate::providers::provider::Provider;
+use crate::types::ProviderType;
+
+/// Provider param selecting how replies are made: `echo` (default) returns the last user
+/// message, `fixed` returns `response`.
+pub const MODE_PARAM: &str = "mode";
+/// Canned reply of `fixed` mode. A model param of the same name overrides it per model.
+pub const RESPONSE_PARAM: &str = "response";
+/// Arguments of the canned tool call, `{}` by default. Can be overridden per model.
+pub const TOOL_ARGUMENTS_PARAM: &str = "tool_arguments";
+/// Every Nth request fails with `fail_status`. Unset or 0 never fails.
+pub const FAIL_EVERY_PARAM: &str = "fail_every";
+/// Status of scripted failures, 500 by default.
+pub const FAIL_STATUS_PARAM: &str = "fail_status";
+/// Delay before each response, or before the first chunk of a stream.
+pub const LATENCY_MS_PARAM: &str = "latency_ms";
+/// Delay between the chunks of a stream.
+pub const CHUNK_DELAY_MS_PARAM: &str = "chunk_delay_ms";
+/// Size of the embedding vectors when the request doesn't ask for `dimensions`.
+pub const EMBEDDING_DIMENSIONS_PARAM: &str = "embedding_dimensions";
+
+const DEFAULT_EMBEDDING_DIMENSIONS: usize = 8;
+const DEFAULT_TOOL_ARGUMENTS: &str = "{}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Echo,
+    Fixed,
+}
+
+#[derive(Debug, Clone)]
+struct MockSettings {
+    mode: Mode,
+    fail_every: u64,
+    fail_status: StatusCode,
+    latency: Duration,
+    chunk_delay: Duration,
+    embedding_dimensions: usize,
+}
+
+impl MockSettings {
+    fn from_params(params: &HashMap<String, String>) -> Result<Self, String> {
+        fn number<T: std::str::FromStr>(
+            params: &HashMap<String, String>,
+            key: &str,
+        ) -> Result<Option<T>, String> {
+            params
+                .get(key)
+                .map(|value| {
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("{key} must be a non-negative integer"))
+                })
+                .transpose()
+        }
+
+        let mode = match params.get(MODE_PARAM).map(|mode| mode.trim()) {
+            None | Some("echo") => Mode::Echo,
+            Some("fixed") => Mode::Fixed,
+            Some(other) => {
+                return Err(format!(
+                    "unknown mode '{other}', expected 'echo' or 'fixed'"
+                ));
+            }
+        };
+        let fail_status = match number::<u16>(params, FAIL_STATUS_PARAM)? {
+            Some(status) if (400..=599).contains(&status) => {
+                StatusCode::from_u16(status).map_err(|e| e.to_string())?
+            }
+            Some(_) => return Err(format!("{FAIL_STATUS_PARAM} must be a 4xx or 5xx status")),
+            None => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let embedding_dimensions = number::<usize>(params, EMBEDDING_DIMENSIONS_PARAM)?
+            .unwrap_or(DEFAULT_EMBEDDING_DIMENSIONS);
+        if embedding_dimensions == 0 {
+            return Err(format!("{EMBEDDING_DIMENSIONS_PARAM} must be positive"));
+        }
+        Ok(Self {
+            mode,
+            fail_every: number(params, FAIL_EVERY_PARAM)?.unwrap_or(0),
+            fail_status,
+            latency: Duration::from_millis(number(params, LATENCY_MS_PARAM)?.unwrap_or(0)),
+            chunk_delay: Duration::from_millis(number(params, CHUNK_DELAY_MS_PARAM)?.unwrap_or(0)),
+            embedding_dimensions,
+        })
+    }
+}
+
+/// Checks the params of a mock provider without building it.
+pub fn validate_mock_params(params: &HashMap<String, String>) -> Result<(), String> {
+    MockSettings::from_params(params).map(|_| ())
+}
+
+/// A provider answering locally with deterministic responses, for development and
+/// hermetic tests. It never makes network calls.
+pub struct MockProvider {
+    config: ProviderConfig,
+    settings: Result<MockSettings, String>,
+    /// Requests served so far, counting failed ones, for `fail_every`.
+    requests: AtomicU64,
+}
+
+impl MockProvider {
+    fn param<'a>(&'a self, model_config: &'a ModelConfig, key: &str) -> Option<&'a str> {
+        model_config
+            .params
+            .get(key)
+            .or_else(|| self.config.params.get(key))
+            .map(String::as_str)
+    }
+
+    /// Counts the request, waits out the configured latency and fails the request when
+    /// its turn in `fail_every` has come.
+    async fn begin(&self) -> Result<&MockSettings, StatusCode> {
+        let settings = self
+            .settings
+            .as_ref()
+            .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+        let request = self.requests.fetch_add(1, Ordering::SeqCst) + 1;
+        if !settings.latency.is_zero() {
+            tokio::time::sleep(settings.latency).await;
+        }
+        if settings.fail_every > 0 && request % settings.fail_every == 0 {
+            return Err(settings.fail_status);
+        }
+        Ok(settings)
+    }
+
+    fn reply(&self, settings: &MockSettings, model_config: &ModelConfig, prompt: String) -> String {
+        match settings.mode {
+            Mode::Echo => prompt,
+            Mode::Fixed => self
+                .param(model_config, RESPONSE_PARAM)
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    /// The canned call of the requested tool, or of the first one. Requests answering a
+    /// tool call get text instead, so agent loops end.
+    fn tool_call(
+        &self,
+        payload: &ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Option<ChatMessageToolCall> {
+        let tools = payload.tools.as_ref()?;
+        if payload.messages.last().is_some_and(|m| m.role == "tool") {
+            return None;
+        }
+        let name = match &payload.tool_choice {
+            Some(ToolChoice::Simple(SimpleToolChoice::None)) => return None,
+            Some(ToolChoice::Named(named)) => named.function.name.clone(),
+            _ => tools
+                .iter()
+                .find_map(ToolDefinition::function)?
+                .name
+                .clone(),
+        };
+        let arguments = self
+            .param(model_config, TOOL_ARGUMENTS_PARAM)
+            .unwrap_or(DEFAULT_TOOL_ARGUMENTS);
+        Some(ChatMessageToolCall {
+            id: "call_mock_0".to_string(),
+            function: FunctionCall {
+                arguments: arguments.to_string(),
+                name,
+            },
+            r#type: "function".to_string(),
+        })
+    }
+}
+
+/// Whitespace-separated words, standing in for tokens.
+fn count_words(text: &str) -> u32 {
+    text.split_whitespace().count() as u32
+}
+
+fn message_text(message: &ChatCompletionMessage) -> String {
+    message
+        .content
+        .as_ref()
+        .map(|content| content.text_parts().join("\n"))
+        .unwrap_or_default()
+}
+
+fn prompt_words(messages: &[ChatCompletionMessage]) -> u32 {
+    messages
+        .iter()
+        .map(|message| count_words(&message_text(message)))
+        .sum()
+}
+
+fn usage(prompt_tokens: u32, completion_tokens: u32) -> Usage {
+    Usage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+        completion_tokens_details: None,
+        prompt_tokens_details: None,
+    }
+}
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
+
+fn chunk(model: &str, delta: ChoiceDelta, finish_reason: Option<&str>) -> ChatCompletionChunk {
+    ChatCompletionChunk {
+        id: "chatcmpl-mock".to_string(),
+        choices: vec![Choice {
+            delta,
+            finish_reason: finish_reason.map(str::to_string),
+            stop_sequence: None,
+            index: 0,
+            logprobs: None,
+        }],
+        created: now() as i64,
+        model: model.to_string(),
+        service_tier: None,
+        system_fingerprint: None,
+        usage: None,
+    }
+}
+
+/// The chunks streaming `content` word by word, or `tool_call` at once, followed by the
+/// chunk with the finish reason.
+fn chunks(
+    model: &str,
+    content: &str,
+    tool_call: Option<ChatMessageToolCall>,
+) -> Vec<ChatCompletionChunk> {
+    let delta = |content: Option<&str>, tool_calls: Option<Vec<ChoiceDeltaToolCall>>| ChoiceDelta {
+        content: content.map(str::to_string),
+        role: None,
+        tool_calls,
+        reasoning: None,
+        annotations: None,
+    };
+    let (mut deltas, finish_reason) = match tool_call {
+        Some(tool_call) => (
+            vec![delta(
+                None,
+                Some(vec![ChoiceDeltaToolCall::complete(0, tool_call)]),
+            )],
+            "tool_calls",
+        ),
+        None if content.is_empty() => (vec![delta(Some(""), None)], "stop"),
+        None => (
+            content
+                .split_inclusive(char::is_whitespace)
+                .map(|word| delta(Some(word), None))
+                .collect(),
+            "stop",
+        ),
+    };
+    deltas[0].role = Some("assistant".to_string());
+    let mut chunks: Vec<ChatCompletionChunk> = deltas
+        .into_iter()
+        .map(|delta| chunk(model, delta, None))
+        .collect();
+    chunks.push(chunk(model, delta(None, None), Some(finish_reason)));
+    chunks
+}
+
+/// A unit vector derived from `input` alone, so equal inputs always embed alike.
+pub(super) fn hash_embedding(input: &str, dimensions: usize) -> Vec<f32> {
+    // FNV-1a seeds a splitmix64 sequence; both are stable across platforms and releases.
+    let mut state = input.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    let vector: Vec<f32> = (0..dimensions)
+        .map(|_| {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            (z >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+        })
+        .collect();
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector;
+    }
+    vector.into_iter().map(|value| value / norm).collect()
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    fn new(config: &ProviderConfig) -> Self {
+        Self {
+            config: config.clone(),
+            settings: MockSettings::from_params(&config.params),
+            requests: AtomicU64::new(0),
+        }
+    }
+
+    fn key(&self) -> String {
+        self.config.key.clone()
+    }
+
+    fn r#type(&self) -> ProviderType {
+        ProviderType::Mock
+    }
+
+    fn unhealthy_reason(&self) -> Option<String> {
+        self.settings
+            .as_ref()
+            .err()
+            .map(|e| format!("invalid mock settings: {e}"))
+    }
+
+    fn capabilities(&self, _model_config: &ModelConfig) -> Capabilities {
+        Capabilities {
+            supports_n: false,
+            supports_logprobs: false,
+            ..Capabilities::ALL
+        }
+    }
+
+    async fn chat_completions(
+        &self,
+        payload: ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        let settings = self.begin().await?;
+        let tool_call = self.tool_call(&payload, model_config);
+        let prompt = payload
+            .messages
+            .iter()
+            .rev()
+            .find(|message| message.role == "user")
+            .map(message_text)
+            .unwrap_or_default();
+        let content = if tool_call.is_some() {
+            String::new()
+        } else {
+            self.reply(settings, model_config, prompt)
+        };
+
+        if payload.stream.unwrap_or(false) {
+            let delay = settings.chunk_delay;
+            let chunks = chunks(&payload.model, &content, tool_call);
+            let stream = stream::iter(chunks.into_iter().enumerate()).then(
+                move |(index, chunk)| async move {
+                    if index > 0 && !delay.is_zero() {
+                        tokio::time::sleep(delay).await;
+                    }
+                    Ok::<_, StreamBodyError>(chunk)
+                },
+            );
+            return Ok(ChatCompletionResponse::Stream(Box::pin(stream)));
+        }
+
+        let finish_reason = if tool_call.is_some() {
+            "tool_calls"
+        } else {
+            "stop"
+        };
+        let completion_tokens = count_words(&content);
+        Ok(ChatCompletionResponse::NonStream(ChatCompletion {
+            id: "chatcmpl-mock".to_string(),
+            object: Some("chat.completion".to_string()),
+            created: Some(now()),
+            model: payload.model,
+            choices: vec![ChatCompletionChoice {
+                index: 0,
+                message: ChatCompletionMessage {
+                    role: "assistant".to_string(),
+                    content: Some(ChatMessageContent::String(content)),
+                    name: None,
+                    tool_calls: tool_call.map(|tool_call| vec![tool_call]),
+                    tool_call_id: None,
+                    refusal: None,
+                    annotations: None,
+                },
+                finish_reason: Some(finish_reason.to_string()),
+                logprobs: None,
+                safety_ratings: None,
+                stop_sequence: None,
+            }],
+            usage: usage(prompt_words(&payload.messages), completion_tokens),
+            system_fingerprint: None,
+            service_tier: None,
+        }))
+    }
+
+    async fn completions(
+        &self,
+        payload: CompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<CompletionResponse, StatusCode> {
+        let settings = self.begin().await?;
+        let prompt_tokens = count_words(&payload.prompt);
+        let text = self.reply(settings, model_config, payload.prompt);
+        let completion_tokens = count_words(&text);
+        Ok(CompletionResponse {
+            id: "cmpl-mock".to_string(),
+            object: "text_completion".to_string(),
+            created: now(),
+            model: payload.model,
+            choices: vec![CompletionChoice {
+                text,
+                index: 0,
+                logprobs: None,
+                finish_reason: Some("stop".to_string()),
+            }],
+            usage: usage(prompt_tokens, completion_tokens),
+        })
+    }
+
+    async fn embeddings(
+        &self,
+        payload: EmbeddingsRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<EmbeddingsResponse, StatusCode> {
+        let settings = self.begin().await?;
+        let token_ids = |ids: &[i32]| ids.iter().map(i32::to_string).collect::<Vec<_>>().join(" ");
+        let inputs = match &payload.input {
+            EmbeddingsInput::Single(input) => vec![input.clone()],
+            EmbeddingsInput::Multiple(inputs) => inputs.clone(),
+            EmbeddingsInput::SingleTokenIds(ids) => vec![token_ids(ids)],
+            EmbeddingsInput::MultipleTokenIds(inputs) => {
+                inputs.iter().map(|ids| token_ids(ids)).collect()
+            }
+        };
+        let dimensions = payload
+            .dimensions
+            .map(|dimensions| dimensions as usize)
+            .unwrap_or(settings.embedding_dimensions);
+        let tokens = inputs.iter().map(|input| count_words(input)).sum();
+        let mut response = EmbeddingsResponse {
+            object: "list".to_string(),
+            data: inputs
+                .iter()
+                .enumerate()
+                .map(|(index, input)| Embeddings {
+                    object: "embedding".to_string(),
+                    embedding: Embedding::Float(hash_embedding(input, dimensions)),
+                    index,
+                })
+                .collect(),
+            model: payload.model.clone(),
+            usage: EmbeddingUsage {
+                prompt_tokens: Some(tokens),
+                total_tokens: Some(tokens),
+            },
+        };
+        if payload.wants_base64() {
+            response.encode_base64();
+        }
+        Ok(response)
+    }
+
+    async fn count_tokens(
+        &self,
+        payload: &ChatCompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<u32, StatusCode> {
+        Ok(prompt_words(&payload.messages))
+    }
+}
@@ -0,0 +1,288 @@
+use super::provider::{MockProvider, hash_embedding};
+use super::validate_mock_params;
+use crate::config::models::{ModelConfig, Provider as ProviderConfig};
+use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::content::ChatMessageContent;
+use crate::models::embeddings::{Embedding, EmbeddingsRequest};
+use crate::providers::provider::Provider;
+use crate::types::ProviderType;
+use axum::http::StatusCode;
+use futures::StreamExt;
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn mock_provider(pairs: &[(&str, &str)]) -> MockProvider {
+    MockProvider::new(&ProviderConfig {
+        key: "mock".to_string(),
+        r#type: ProviderType::Mock,
+        api_key: String::new(),
+        maintenance_windows: vec![],
+        params: params(pairs),
+    })
+}
+
+fn model_config(pairs: &[(&str, &str)]) -> ModelConfig {
+    ModelConfig {
+        key: "mock-model".to_string(),
+        r#type: "mock-model".to_string(),
+        provider: "mock".to_string(),
+        params: params(pairs),
+        enabled: true,
+        deprecation: Default::default(),
+    }
+}
+
+fn chat_request(extra: serde_json::Value) -> ChatCompletionRequest {
+    let mut request = json!({
+        "model": "mock-model",
+        "messages": [
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "first question"},
+            {"role": "assistant", "content": "first answer"},
+            {"role": "user", "content": "what is the weather"}
+        ]
+    });
+    request
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    serde_json::from_value(request).unwrap()
+}
+
+async fn chat_text(
+    provider: &MockProvider,
+    model: &ModelConfig,
+    request: ChatCompletionRequest,
+) -> Result<String, StatusCode> {
+    match provider.chat_completions(request, model).await? {
+        ChatCompletionResponse::NonStream(completion) => {
+            match &completion.choices[0].message.content {
+                Some(ChatMessageContent::String(text)) => Ok(text.clone()),
+                _ => panic!("expected text content"),
+            }
+        }
+        ChatCompletionResponse::Stream(_) => panic!("expected non-streaming response"),
+    }
+}
+
+#[tokio::test]
+async fn test_echo_mode_returns_last_user_message() {
+    let provider = mock_provider(&[]);
+    let response = provider
+        .chat_completions(chat_request(json!({})), &model_config(&[]))
+        .await
+        .unwrap();
+
+    let ChatCompletionResponse::NonStream(completion) = response else {
+        panic!("expected non-streaming response");
+    };
+    let choice = &completion.choices[0];
+    assert!(matches!(
+        &choice.message.content,
+        Some(ChatMessageContent::String(text)) if text == "what is the weather"
+    ));
+    assert_eq!(choice.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(completion.usage.prompt_tokens, 10);
+    assert_eq!(completion.usage.completion_tokens, 4);
+}
+
+#[tokio::test]
+async fn test_fixed_mode_prefers_model_response() {
+    let provider = mock_provider(&[("mode", "fixed"), ("response", "provider reply")]);
+
+    let text = chat_text(&provider, &model_config(&[]), chat_request(json!({})))
+        .await
+        .unwrap();
+    assert_eq!(text, "provider reply");
+
+    let model = model_config(&[("response", r#"{"answer": 42}"#)]);
+    let text = chat_text(&provider, &model, chat_request(json!({})))
+        .await
+        .unwrap();
+    assert_eq!(text, r#"{"answer": 42}"#);
+}
+
+#[tokio::test]
+async fn test_fail_every_nth_request() {
+    let provider = mock_provider(&[("fail_every", "3"), ("fail_status", "429")]);
+    let model = model_config(&[]);
+
+    let mut statuses = Vec::new();
+    for _ in 0..6 {
+        let status = match chat_text(&provider, &model, chat_request(json!({}))).await {
+            Ok(_) => StatusCode::OK,
+            Err(status) => status,
+        };
+        statuses.push(status);
+    }
+    assert_eq!(
+        statuses,
+        [
+            StatusCode::OK,
+            StatusCode::OK,
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::OK,
+            StatusCode::OK,
+            StatusCode::TOO_MANY_REQUESTS,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_latency_and_chunk_cadence() {
+    let provider = mock_provider(&[("latency_ms", "50"), ("chunk_delay_ms", "20")]);
+    let started = Instant::now();
+    let response = provider
+        .chat_completions(chat_request(json!({"stream": true})), &model_config(&[]))
+        .await
+        .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(50));
+
+    let ChatCompletionResponse::Stream(stream) = response else {
+        panic!("expected streaming response");
+    };
+    let started = Instant::now();
+    let chunks: Vec<_> = stream.map(|chunk| chunk.unwrap()).collect().await;
+    // One chunk per word, then the finish chunk, each after the chunk delay.
+    assert_eq!(chunks.len(), 5);
+    assert!(started.elapsed() >= Duration::from_millis(80));
+
+    assert_eq!(
+        chunks[0].choices[0].delta.role.as_deref(),
+        Some("assistant")
+    );
+    let text: String = chunks
+        .iter()
+        .filter_map(|chunk| chunk.choices[0].delta.content.clone())
+        .collect();
+    assert_eq!(text, "what is the weather");
+    assert_eq!(chunks[4].choices[0].finish_reason.as_deref(), Some("stop"));
+}
+
+#[tokio::test]
+async fn test_tools_get_a_canned_tool_call() {
+    let provider = mock_provider(&[]);
+    let model = model_config(&[("tool_arguments", r#"{"city": "Paris"}"#)]);
+    let tools = json!({
+        "tools": [
+            {"type": "function", "function": {"name": "get_time"}},
+            {"type": "function", "function": {"name": "get_weather"}}
+        ],
+        "tool_choice": {"type": "function", "function": {"name": "get_weather"}}
+    });
+
+    let response = provider
+        .chat_completions(chat_request(tools.clone()), &model)
+        .await
+        .unwrap();
+    let ChatCompletionResponse::NonStream(completion) = response else {
+        panic!("expected non-streaming response");
+    };
+    let choice = &completion.choices[0];
+    let tool_calls = choice.message.tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls[0].function.name, "get_weather");
+    assert_eq!(tool_calls[0].function.arguments, r#"{"city": "Paris"}"#);
+    assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+
+    let mut streamed = tools.clone();
+    streamed["stream"] = json!(true);
+    let ChatCompletionResponse::Stream(stream) = provider
+        .chat_completions(chat_request(streamed), &model)
+        .await
+        .unwrap()
+    else {
+        panic!("expected streaming response");
+    };
+    let chunks: Vec<_> = stream.map(|chunk| chunk.unwrap()).collect().await;
+    let delta = chunks[0].choices[0].delta.tool_calls.as_ref().unwrap();
+    assert_eq!(
+        delta[0].function.as_ref().unwrap().name.as_deref(),
+        Some("get_weather")
+    );
+    assert_eq!(
+        chunks.last().unwrap().choices[0].finish_reason.as_deref(),
+        Some("tool_calls")
+    );
+
+    // Once the tool result is in, the conversation ends with text.
+    let mut answered = chat_request(tools);
+    answered.messages.push(
+        serde_json::from_value(
+            json!({"role": "tool", "tool_call_id": "call_mock_0", "content": "sunny"}),
+        )
+        .unwrap(),
+    );
+    let response = provider.chat_completions(answered, &model).await.unwrap();
+    let ChatCompletionResponse::NonStream(completion) = response else {
+        panic!("expected non-streaming response");
+    };
+    assert!(completion.choices[0].message.tool_calls.is_none());
+}
+
+#[tokio::test]
+async fn test_embeddings_are_deterministic_unit_vectors() {
+    let provider = mock_provider(&[("embedding_dimensions", "16")]);
+    let request: EmbeddingsRequest = serde_json::from_value(json!({
+        "model": "mock-embedding",
+        "input": ["hello", "world", "hello"]
+    }))
+    .unwrap();
+
+    let response = provider
+        .embeddings(request, &model_config(&[]))
+        .await
+        .unwrap();
+    let vectors: Vec<Vec<f32>> = response
+        .data
+        .iter()
+        .map(|item| match &item.embedding {
+            Embedding::Float(vector) => vector.clone(),
+            _ => panic!("expected float vector"),
+        })
+        .collect();
+    assert_eq!(vectors[0].len(), 16);
+    assert_eq!(vectors[0], vectors[2]);
+    assert_ne!(vectors[0], vectors[1]);
+    let norm: f32 = vectors[1]
+        .iter()
+        .map(|value| value * value)
+        .sum::<f32>()
+        .sqrt();
+    assert!((norm - 1.0).abs() < 1e-5);
+
+    let request: EmbeddingsRequest = serde_json::from_value(json!({
+        "model": "mock-embedding",
+        "input": "hello",
+        "dimensions": 4
+    }))
+    .unwrap();
+    let response = provider
+        .embeddings(request, &model_config(&[]))
+        .await
+        .unwrap();
+    assert!(matches!(
+        &response.data[0].embedding,
+        Embedding::Float(vector) if *vector == hash_embedding("hello", 4)
+    ));
+}
+
+#[test]
+fn test_validate_mock_params() {
+    assert!(validate_mock_params(&params(&[])).is_ok());
+    assert!(validate_mock_params(&params(&[("mode", "fixed"), ("fail_every", "2")])).is_ok());
+    assert!(validate_mock_params(&params(&[("mode", "random")])).is_err());
+    assert!(validate_mock_params(&params(&[("fail_status", "200")])).is_err());
+    assert!(validate_mock_params(&params(&[("latency_ms", "-1")])).is_err());
+    assert!(validate_mock_params(&params(&[("embedding_dimensions", "0")])).is_err());
+
+    let provider = mock_provider(&[("mode", "random")]);
+    assert!(provider.unhealthy_reason().is_some());
+}
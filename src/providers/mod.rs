@@ -1,7 +1,20 @@
+pub mod allowed_models;
 pub mod anthropic;
+pub mod api_keys;
 pub mod azure;
 pub mod bedrock;
+pub mod capabilities;
+#[cfg(test)]
+mod contract_tests;
+pub mod failover;
+pub mod http_client;
+pub mod maintenance;
+pub mod mock;
 pub mod openai;
 pub mod provider;
 pub mod registry;
+pub mod stop_sequences;
+pub mod transport;
+pub mod unresolved;
+pub mod upstream;
 pub mod vertexai;
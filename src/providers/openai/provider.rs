@@ -1,17 +1,20 @@
 use crate::config::constants::stream_buffer_size_bytes;
 use crate::config::models::{ModelConfig, Provider as ProviderConfig};
-use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::chat::{ChatCompletion, ChatCompletionRequest, ChatCompletionResponse};
 use crate::models::completion::{CompletionRequest, CompletionResponse};
 use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
 use crate::models::streaming::ChatCompletionChunk;
-use crate::providers::provider::Provider;
-use crate::types::ProviderType;
+use crate::providers::capabilities::Capabilities;
+use crate::providers::provider::{Provider, base_url};
+use crate::providers::stop_sequences::{trim_completion, trim_stream};
+use crate::providers::transport::{Auth, Transport, parse_json};
+use crate::providers::upstream::UpstreamRequest;
+use crate::types::{ProviderType, RequestPriority};
 use async_trait::async_trait;
-use axum::http::StatusCode;
-use reqwest::Client;
+use axum::http::{HeaderValue, Request, StatusCode, header};
 use reqwest_streams::*;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 
 #[derive(Serialize, Deserialize, Clone)]
 struct OpenAIChatCompletionRequest {
@@ -19,6 +22,17 @@ struct OpenAIChatCompletionRequest {
     base: ChatCompletionRequest,
     #[serde(skip_serializing_if = "Option::is_none")]
     reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service_tier: Option<String>,
+}
+
+/// Maps a gateway priority onto OpenAI's `service_tier`.
+fn openai_service_tier(priority: RequestPriority) -> &'static str {
+    match priority {
+        RequestPriority::Low => "flex",
+        RequestPriority::Default => "auto",
+        RequestPriority::High => "priority",
+    }
 }
 
 impl From<ChatCompletionRequest> for OpenAIChatCompletionRequest {
@@ -41,25 +55,64 @@ impl From<ChatCompletionRequest> for OpenAIChatCompletionRequest {
         // Remove reasoning field from base request since OpenAI uses reasoning_effort
         base.reasoning = None;
 
+        let service_tier = base
+            .priority
+            .map(|priority| openai_service_tier(priority).to_string());
+
         Self {
             base,
             reasoning_effort,
+            service_tier,
         }
     }
 }
 
 pub struct OpenAIProvider {
     config: ProviderConfig,
-    http_client: Client,
+    transport: Transport,
 }
 
 impl OpenAIProvider {
     fn base_url(&self) -> String {
-        self.config
-            .params
-            .get("base_url")
-            .unwrap_or(&String::from("https://api.openai.com/v1"))
-            .to_string()
+        base_url(&self.config, "https://api.openai.com/v1")
+    }
+
+    /// Translates a chat request, without credentials.
+    fn chat_request(&self, payload: &ChatCompletionRequest) -> Result<UpstreamRequest, StatusCode> {
+        // Validate legacy `reasoning` only when top-level `reasoning_effort` isn't set,
+        // mirroring the construction precedence in OpenAIChatCompletionRequest::from.
+        if payload.reasoning_effort.is_none() {
+            if let Some(reasoning) = &payload.reasoning {
+                if let Err(e) = reasoning.validate() {
+                    tracing::error!("Invalid reasoning config: {}", e);
+                    return Err(StatusCode::BAD_REQUEST);
+                }
+            }
+        }
+
+        // Convert to OpenAI-specific request format
+        let openai_request = OpenAIChatCompletionRequest::from(payload.clone());
+        UpstreamRequest::post(
+            format!("{}/chat/completions", self.base_url()),
+            &openai_request,
+        )?
+        .with_extra_body(payload.extra_body.as_ref())
+    }
+
+    fn completion_request(
+        &self,
+        payload: &CompletionRequest,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        UpstreamRequest::post(format!("{}/completions", self.base_url()), payload)?
+            .with_extra_body(payload.extra_body.as_ref())
+    }
+
+    fn embeddings_request(
+        &self,
+        payload: &EmbeddingsRequest,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        UpstreamRequest::post(format!("{}/embeddings", self.base_url()), payload)?
+            .with_extra_body(payload.extra_body.as_ref())
     }
 }
 
@@ -68,7 +121,9 @@ impl Provider for OpenAIProvider {
     fn new(config: &ProviderConfig) -> Self {
         Self {
             config: config.clone(),
-            http_client: Client::new(),
+            transport: Transport::new(config, "OpenAI", Auth::Bearer)
+                .expect("Invalid HTTP client configuration for provider")
+                .with_key_fallback(config),
         }
     }
 
@@ -80,124 +135,124 @@ impl Provider for OpenAIProvider {
         ProviderType::OpenAI
     }
 
+    fn unhealthy_reason(&self) -> Option<String> {
+        self.transport.unhealthy_reason()
+    }
+
+    fn capabilities(&self, _model_config: &ModelConfig) -> Capabilities {
+        Capabilities {
+            max_stop_sequences: Some(4),
+            ..Capabilities::ALL
+        }
+    }
+
     async fn chat_completions(
         &self,
         payload: ChatCompletionRequest,
-        _model_config: &ModelConfig,
+        model_config: &ModelConfig,
     ) -> Result<ChatCompletionResponse, StatusCode> {
-        // Validate legacy `reasoning` only when top-level `reasoning_effort` isn't set,
-        // mirroring the construction precedence in OpenAIChatCompletionRequest::from.
-        if payload.reasoning_effort.is_none() {
-            if let Some(reasoning) = &payload.reasoning {
-                if let Err(e) = reasoning.validate() {
-                    tracing::error!("Invalid reasoning config: {}", e);
-                    return Err(StatusCode::BAD_REQUEST);
-                }
-            }
-        }
-
-        // Convert to OpenAI-specific request format
-        let openai_request = OpenAIChatCompletionRequest::from(payload.clone());
-
-        let response = self
-            .http_client
-            .post(format!("{}/chat/completions", self.base_url()))
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .json(&openai_request)
-            .send()
-            .await
-            .map_err(|e| {
-                eprintln!("OpenAI API request error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-        let status = response.status();
-        if status.is_success() {
-            if payload.stream.unwrap_or(false) {
-                let stream =
-                    response.json_array_stream::<ChatCompletionChunk>(stream_buffer_size_bytes());
-                Ok(ChatCompletionResponse::Stream(stream))
-            } else {
-                response
-                    .json()
-                    .await
-                    .map(ChatCompletionResponse::NonStream)
-                    .map_err(|e| {
-                        eprintln!("OpenAI API response error: {e}");
-                        StatusCode::INTERNAL_SERVER_ERROR
-                    })
-            }
+        let request = self.build_chat_request(&payload, model_config).await?;
+        if payload.stream.unwrap_or(false) {
+            let response = self
+                .transport
+                .send(request, "openai.chat_completions")
+                .await?;
+            let stream =
+                response.json_array_stream::<ChatCompletionChunk>(stream_buffer_size_bytes());
+            Ok(ChatCompletionResponse::Stream(trim_stream(
+                stream,
+                payload.stop.as_deref(),
+            )))
         } else {
-            info!(
-                "OpenAI API request error: {}",
-                response.text().await.unwrap()
-            );
-            Err(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+            let body = self
+                .transport
+                .send_for_body(request, "openai.chat_completions")
+                .await?;
+            let mut completion: ChatCompletion = parse_json(&body, "openai.chat_completions")?;
+            trim_completion(&mut completion, payload.stop.as_deref());
+            Ok(ChatCompletionResponse::NonStream(completion))
         }
     }
 
     async fn completions(
         &self,
         payload: CompletionRequest,
-        _model_config: &ModelConfig,
+        model_config: &ModelConfig,
     ) -> Result<CompletionResponse, StatusCode> {
-        let response = self
-            .http_client
-            .post(format!("{}/completions", self.base_url()))
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| {
-                eprintln!("OpenAI API request error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-        let status = response.status();
-        if status.is_success() {
-            response.json().await.map_err(|e| {
-                eprintln!("OpenAI API response error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })
-        } else {
-            eprintln!(
-                "OpenAI API request error: {}",
-                response.text().await.unwrap()
-            );
-            Err(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
-        }
+        let request = self
+            .build_completion_request(&payload, model_config)
+            .await?;
+        let body = self
+            .transport
+            .send_for_body(request, "openai.completions")
+            .await?;
+        parse_json(&body, "openai.completions")
     }
 
     async fn embeddings(
         &self,
         payload: EmbeddingsRequest,
-        _model_config: &ModelConfig,
+        model_config: &ModelConfig,
     ) -> Result<EmbeddingsResponse, StatusCode> {
-        let response = self
-            .http_client
-            .post(format!("{}/embeddings", self.base_url()))
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| {
-                eprintln!("OpenAI API request error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-        let status = response.status();
-        if status.is_success() {
-            response.json().await.map_err(|e| {
-                eprintln!("OpenAI API response error: {e}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })
+        let request = self
+            .build_embeddings_request(&payload, model_config)
+            .await?;
+        let body = self
+            .transport
+            .send_for_body(request, "openai.embeddings")
+            .await?;
+        parse_json(&body, "openai.embeddings")
+    }
+
+    async fn build_chat_request(
+        &self,
+        payload: &ChatCompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        self.transport.authorize(self.chat_request(payload)?)
+    }
+
+    async fn build_completion_request(
+        &self,
+        payload: &CompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        self.transport.authorize(self.completion_request(payload)?)
+    }
+
+    async fn build_embeddings_request(
+        &self,
+        payload: &EmbeddingsRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        self.transport.authorize(self.embeddings_request(payload)?)
+    }
+
+    fn build_realtime_request(
+        &self,
+        model: &str,
+        _model_config: &ModelConfig,
+    ) -> Result<Request<()>, StatusCode> {
+        let base_url = self.base_url();
+        let ws_base_url = if let Some(host) = base_url.strip_prefix("https://") {
+            format!("wss://{host}")
+        } else if let Some(host) = base_url.strip_prefix("http://") {
+            format!("ws://{host}")
         } else {
-            eprintln!(
-                "OpenAI API request error: {}",
-                response.text().await.unwrap()
-            );
-            Err(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
-        }
+            base_url
+        };
+        let url = format!("{ws_base_url}/realtime?model={model}");
+        let mut request = url.into_client_request().map_err(|e| {
+            tracing::error!("Invalid OpenAI realtime URL: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        let authorization =
+            HeaderValue::from_str(&format!("Bearer {}", self.transport.api_key().get()?))
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let headers = request.headers_mut();
+        headers.insert(header::AUTHORIZATION, authorization);
+        headers.insert("openai-beta", HeaderValue::from_static("realtime=v1"));
+        Ok(request)
     }
 }
 
@@ -206,6 +261,7 @@ mod reasoning_effort_precedence_tests {
     use super::*;
     use crate::models::chat::ReasoningConfig;
     use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
+    use std::collections::HashMap;
 
     fn base_request() -> ChatCompletionRequest {
         ChatCompletionRequest {
@@ -217,6 +273,7 @@ mod reasoning_effort_precedence_tests {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             }],
             temperature: None,
             top_p: None,
@@ -237,6 +294,12 @@ mod reasoning_effort_precedence_tests {
             response_format: None,
             reasoning: None,
             reasoning_effort: None,
+            store: None,
+            metadata: None,
+            prediction: None,
+            web_search_options: None,
+            extra_body: None,
+            priority: None,
         }
     }
 
@@ -284,4 +347,93 @@ mod reasoning_effort_precedence_tests {
         let converted = OpenAIChatCompletionRequest::from(base_request());
         assert_eq!(converted.reasoning_effort, None);
     }
+
+    #[test]
+    fn passes_store_and_metadata_through() {
+        let mut req = base_request();
+        req.store = Some(true);
+        req.metadata = Some(HashMap::from([("team".to_string(), "search".to_string())]));
+
+        let converted = OpenAIChatCompletionRequest::from(req);
+        let json = serde_json::to_value(&converted).unwrap();
+        assert_eq!(json["store"], true);
+        assert_eq!(json["metadata"]["team"], "search");
+
+        let round_trip: ChatCompletionRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip.store, Some(true));
+        assert_eq!(round_trip.metadata, converted.base.metadata);
+    }
+
+    #[test]
+    fn passes_prediction_through() {
+        let prediction = serde_json::json!({
+            "type": "content",
+            "content": [{"type": "text", "text": "fn main() {}"}]
+        });
+        let mut req = base_request();
+        req.prediction = Some(serde_json::from_value(prediction.clone()).unwrap());
+
+        let json = serde_json::to_value(OpenAIChatCompletionRequest::from(req)).unwrap();
+        assert_eq!(json["prediction"], prediction);
+
+        let round_trip: ChatCompletionRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            serde_json::to_value(round_trip.prediction).unwrap(),
+            prediction
+        );
+    }
+
+    #[test]
+    fn maps_priority_to_service_tier() {
+        for (priority, tier) in [
+            (RequestPriority::Low, "flex"),
+            (RequestPriority::Default, "auto"),
+            (RequestPriority::High, "priority"),
+        ] {
+            let mut req = base_request();
+            req.priority = Some(priority);
+
+            let json = serde_json::to_value(OpenAIChatCompletionRequest::from(req)).unwrap();
+            assert_eq!(json["service_tier"], tier);
+            assert!(json.get("priority").is_none());
+        }
+
+        let json = serde_json::to_value(OpenAIChatCompletionRequest::from(base_request())).unwrap();
+        assert!(json.get("service_tier").is_none());
+    }
+
+    #[test]
+    fn omits_store_and_metadata_when_unset() {
+        let converted = OpenAIChatCompletionRequest::from(base_request());
+        let json = serde_json::to_value(&converted).unwrap();
+        assert!(json.get("store").is_none());
+        assert!(json.get("metadata").is_none());
+    }
+
+    #[test]
+    fn passes_logprobs_through() {
+        let mut req = base_request();
+        req.logprobs = Some(true);
+        req.top_logprobs = Some(3);
+
+        let json = serde_json::to_value(OpenAIChatCompletionRequest::from(req)).unwrap();
+        assert_eq!(json["logprobs"], true);
+        assert_eq!(json["top_logprobs"], 3);
+    }
+
+    #[test]
+    fn passes_web_search_and_builtin_tools_through() {
+        let fixture = std::fs::read_to_string("tests/fixtures/openai_web_search.json").unwrap();
+        let recorded =
+            serde_json::from_str::<serde_json::Value>(&fixture).unwrap()["request"].clone();
+        let req: ChatCompletionRequest = serde_json::from_value(recorded.clone()).unwrap();
+        let tools = req.tools.as_ref().unwrap();
+        assert_eq!(tools[0].function().unwrap().name, "get_weather");
+        assert_eq!(tools[1].tool_type(), "web_search_preview");
+        assert!(tools[2].function().is_none());
+
+        let json = serde_json::to_value(OpenAIChatCompletionRequest::from(req)).unwrap();
+        assert_eq!(json["tools"], recorded["tools"]);
+        assert_eq!(json["web_search_options"], recorded["web_search_options"]);
+    }
 }
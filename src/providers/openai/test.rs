@@ -1,16 +1,23 @@
+use axum::http::StatusCode;
 use serde_json::{Value, json};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use tracing::debug;
+use wiremock::ResponseTemplate;
 
 use super::provider::OpenAIProvider;
 use crate::config::models::{ModelConfig, Provider as ProviderConfig};
-use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::chat::{ChatCompletion, ChatCompletionRequest, ChatCompletionResponse};
 use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
+use crate::models::streaming::ChatCompletionChunk;
 use crate::models::tool_choice::{SimpleToolChoice, ToolChoice};
-use crate::models::tool_definition::{FunctionDefinition, ToolDefinition};
+use crate::models::tool_definition::{FunctionDefinition, FunctionTool, ToolDefinition};
+use crate::providers::contract_tests::{
+    COMPLETION_TOKENS, ContractTemplates, MODEL, PROMPT_TOKENS, TOOL_NAME, provider_contract_tests,
+};
 use crate::providers::provider::Provider;
+use crate::types::ProviderType;
 
 async fn save_to_cassette(test_name: &str, response: &Value) {
     let cassettes_dir = PathBuf::from("tests/cassettes/openai");
@@ -48,6 +55,7 @@ fn create_test_provider() -> OpenAIProvider {
         key: "openai".to_string(),
         r#type: crate::types::ProviderType::OpenAI,
         api_key,
+        maintenance_windows: vec![],
         params: HashMap::new(),
     })
 }
@@ -58,11 +66,13 @@ fn create_model_config() -> ModelConfig {
         r#type: "gpt-5.1".to_string(),
         provider: "openai".to_string(),
         params: HashMap::new(),
+        enabled: true,
+        deprecation: Default::default(),
     }
 }
 
 fn create_weather_tool_definition() -> ToolDefinition {
-    ToolDefinition {
+    ToolDefinition::Function(FunctionTool {
         tool_type: "function".to_string(),
         function: FunctionDefinition {
             name: "get_weather".to_string(),
@@ -87,7 +97,7 @@ fn create_weather_tool_definition() -> ToolDefinition {
             ),
             strict: None,
         },
-    }
+    })
 }
 
 #[tokio::test]
@@ -194,6 +204,7 @@ async fn test_chat_completions_with_tool_calls() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(0.7),
         top_p: None,
@@ -214,6 +225,12 @@ async fn test_chat_completions_with_tool_calls() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let response_1 = provider
@@ -268,6 +285,7 @@ async fn test_chat_completions_with_tool_calls() {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             },
             ChatCompletionMessage {
                 role: "assistant".to_string(),
@@ -276,6 +294,7 @@ async fn test_chat_completions_with_tool_calls() {
                 tool_calls: Some(tool_calls.clone()),
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             },
             ChatCompletionMessage {
                 role: "tool".to_string(),
@@ -287,6 +306,7 @@ async fn test_chat_completions_with_tool_calls() {
                 tool_calls: None,
                 tool_call_id: Some(tool_calls[0].id.clone()), // CRITICAL: Must match the id from tool_calls
                 refusal: None,
+                annotations: None,
             },
         ],
         temperature: Some(0.7),
@@ -308,6 +328,12 @@ async fn test_chat_completions_with_tool_calls() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let response_2 = provider
@@ -338,3 +364,198 @@ async fn test_chat_completions_with_tool_calls() {
         }
     }
 }
+
+fn load_cassette(name: &str) -> Value {
+    let path = PathBuf::from("tests/cassettes/openai").join(format!("{name}.json"));
+    let content = fs::read_to_string(path).expect("Failed to read cassette file");
+    serde_json::from_str(&content).expect("Failed to parse cassette JSON")
+}
+
+#[test]
+fn test_chat_completion_logprobs_round_trip() {
+    let recorded = load_cassette("chat_completion_logprobs");
+    let completion: ChatCompletion = serde_json::from_value(recorded.clone()).unwrap();
+
+    let logprobs = completion.choices[0].logprobs.as_ref().unwrap();
+    let content = logprobs.content.as_ref().unwrap();
+    assert_eq!(content.len(), 2);
+    assert_eq!(content[0].token, "Hello");
+    assert_eq!(content[0].bytes, Some(vec![72, 101, 108, 108, 111]));
+    assert_eq!(content[0].top_logprobs[1].token, "Hi");
+
+    let serialized = serde_json::to_value(&completion).unwrap();
+    assert_eq!(
+        serialized["choices"][0]["logprobs"]["content"],
+        recorded["choices"][0]["logprobs"]["content"]
+    );
+}
+
+#[test]
+fn test_chat_completion_chunk_logprobs_round_trip() {
+    let recorded = load_cassette("chat_completion_logprobs_stream");
+    let chunks: Vec<ChatCompletionChunk> = serde_json::from_value(recorded.clone()).unwrap();
+
+    let tokens: Vec<String> = chunks
+        .iter()
+        .filter_map(|chunk| chunk.choices[0].logprobs.as_ref()?.content.clone())
+        .flatten()
+        .map(|logprob| logprob.token)
+        .collect();
+    assert_eq!(tokens, vec!["Hello", "!"]);
+    assert!(chunks[3].choices[0].logprobs.is_none());
+
+    for (chunk, recorded) in chunks.iter().zip(recorded.as_array().unwrap()) {
+        let serialized = serde_json::to_value(chunk).unwrap();
+        assert_eq!(
+            serialized["choices"][0]["logprobs"]["content"],
+            recorded["choices"][0]["logprobs"]["content"]
+        );
+    }
+}
+
+fn usage_fixture(name: &str) -> Value {
+    let fixture = fs::read_to_string("tests/fixtures/provider_usage.json")
+        .expect("Failed to read usage fixture");
+    serde_json::from_str::<Value>(&fixture).unwrap()[name].clone()
+}
+
+#[test]
+fn test_usage_details_round_trip() {
+    let recorded = usage_fixture("openai");
+    let completion: ChatCompletion = serde_json::from_value(recorded.clone()).unwrap();
+    let usage = &completion.usage;
+    assert_eq!(usage.cached_tokens(), 1920);
+    assert_eq!(usage.reasoning_tokens(), 256);
+    assert_eq!(
+        usage.prompt_tokens + usage.completion_tokens,
+        usage.total_tokens
+    );
+    assert_eq!(serde_json::to_value(usage).unwrap(), recorded["usage"]);
+
+    let recorded = usage_fixture("openai_stream");
+    let chunk: ChatCompletionChunk = serde_json::from_value(recorded.clone()).unwrap();
+    let usage = chunk.usage.as_ref().unwrap();
+    assert_eq!(usage.cached_tokens(), 1920);
+    assert_eq!(usage.reasoning_tokens(), 256);
+    assert_eq!(serde_json::to_value(usage).unwrap(), recorded["usage"]);
+}
+
+fn web_search_fixture(name: &str) -> Value {
+    let fixture = fs::read_to_string("tests/fixtures/openai_web_search.json")
+        .expect("Failed to read web search fixture");
+    serde_json::from_str::<Value>(&fixture).unwrap()[name].clone()
+}
+
+#[test]
+fn test_web_search_annotations_round_trip() {
+    let recorded = web_search_fixture("response");
+    let completion: ChatCompletion = serde_json::from_value(recorded.clone()).unwrap();
+    let annotations = completion.choices[0].message.annotations.as_ref().unwrap();
+    let citation = annotations[0].url_citation.as_ref().unwrap();
+    assert_eq!(citation.url, "https://www.bbc.co.uk/news/garden");
+    assert_eq!((citation.start_index, citation.end_index), (67, 113));
+    assert_eq!(annotations[1].r#type, "file_citation");
+
+    let serialized = serde_json::to_value(&completion).unwrap();
+    assert_eq!(
+        serialized["choices"][0]["message"]["annotations"],
+        recorded["choices"][0]["message"]["annotations"]
+    );
+
+    let recorded = web_search_fixture("stream");
+    let chunks: Vec<ChatCompletionChunk> = serde_json::from_value(recorded.clone()).unwrap();
+    assert!(chunks[0].choices[0].delta.annotations.is_none());
+    for (chunk, recorded) in chunks.iter().zip(recorded.as_array().unwrap()) {
+        let serialized = serde_json::to_value(chunk).unwrap();
+        assert_eq!(
+            serialized["choices"][0]["delta"]["annotations"],
+            recorded["choices"][0]["delta"]["annotations"]
+        );
+    }
+}
+
+struct OpenAITemplates;
+
+impl ContractTemplates for OpenAITemplates {
+    type Provider = OpenAIProvider;
+
+    const PROVIDER_TYPE: ProviderType = ProviderType::OpenAI;
+
+    fn chat_path(_stream: bool) -> String {
+        "/chat/completions".to_string()
+    }
+
+    fn text_response(text: &str) -> Value {
+        openai_completion(json!({"role": "assistant", "content": text}), "stop")
+    }
+
+    fn empty_response() -> Value {
+        openai_completion(json!({"role": "assistant", "content": ""}), "stop")
+    }
+
+    fn tool_call_response(arguments: &Value) -> Value {
+        let message = json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_contract",
+                "type": "function",
+                "function": {"name": TOOL_NAME, "arguments": arguments.to_string()}
+            }]
+        });
+        openai_completion(message, "tool_calls")
+    }
+
+    /// OpenAI leaves the sequence out without naming it.
+    fn stop_sequence_response(text: &str) -> Value {
+        openai_completion(json!({"role": "assistant", "content": text}), "stop")
+    }
+
+    fn stream_response(parts: &[&str]) -> Option<ResponseTemplate> {
+        let chunk = |delta: Value, finish_reason: Option<&str>| {
+            json!({
+                "id": "chatcmpl-contract",
+                "object": "chat.completion.chunk",
+                "created": 1,
+                "model": MODEL,
+                "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+            })
+        };
+        let mut chunks = vec![chunk(json!({"role": "assistant", "content": ""}), None)];
+        chunks.extend(
+            parts
+                .iter()
+                .map(|part| chunk(json!({"content": part}), None)),
+        );
+        chunks.push(chunk(json!({}), Some("stop")));
+        Some(ResponseTemplate::new(200).set_body_json(chunks))
+    }
+
+    fn error_response(status: StatusCode) -> Value {
+        json!({
+            "error": {
+                "message": status.canonical_reason(),
+                "type": "invalid_request_error",
+                "param": null,
+                "code": null
+            }
+        })
+    }
+}
+
+fn openai_completion(message: Value, finish_reason: &str) -> Value {
+    json!({
+        "id": "chatcmpl-contract",
+        "object": "chat.completion",
+        "created": 1,
+        "model": MODEL,
+        "choices": [{"index": 0, "message": message, "finish_reason": finish_reason}],
+        "usage": {
+            "prompt_tokens": PROMPT_TOKENS,
+            "completion_tokens": COMPLETION_TOKENS,
+            "total_tokens": PROMPT_TOKENS + COMPLETION_TOKENS
+        }
+    })
+}
+
+provider_contract_tests!(OpenAITemplates);
@@ -1,11 +1,13 @@
 use async_trait::async_trait;
-use axum::http::StatusCode;
+use axum::http::{Request, StatusCode};
 use std::borrow::Cow;
 
 use crate::config::models::{ModelConfig, Provider as ProviderConfig};
 use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
 use crate::models::completion::{CompletionRequest, CompletionResponse};
 use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
+use crate::providers::capabilities::Capabilities;
+use crate::providers::upstream::UpstreamRequest;
 use crate::types::ProviderType;
 
 #[async_trait]
@@ -16,6 +18,17 @@ pub trait Provider: Send + Sync {
     fn key(&self) -> String;
     fn r#type(&self) -> ProviderType;
 
+    /// Request features this provider honours for the given model.
+    fn capabilities(&self, _model_config: &ModelConfig) -> Capabilities {
+        Capabilities::ALL
+    }
+
+    /// Why the provider can't serve requests right now, e.g. missing credentials.
+    /// Reported by `/health`.
+    fn unhealthy_reason(&self) -> Option<String> {
+        None
+    }
+
     async fn chat_completions(
         &self,
         payload: ChatCompletionRequest,
@@ -33,6 +46,62 @@ pub trait Provider: Send + Sync {
         payload: EmbeddingsRequest,
         model_config: &ModelConfig,
     ) -> Result<EmbeddingsResponse, StatusCode>;
+
+    /// Builds the upstream request `chat_completions` would send, without sending it.
+    async fn build_chat_request(
+        &self,
+        _payload: &ChatCompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+
+    /// Counts the input tokens `chat_completions` would send for `payload`, using the
+    /// provider's own tokenizer.
+    async fn count_tokens(
+        &self,
+        _payload: &ChatCompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<u32, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+
+    /// Builds the upstream request `completions` would send, without sending it.
+    async fn build_completion_request(
+        &self,
+        _payload: &CompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+
+    /// Builds the upstream request `embeddings` would send, without sending it.
+    async fn build_embeddings_request(
+        &self,
+        _payload: &EmbeddingsRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+
+    /// Builds the websocket handshake opening a realtime session with `model`.
+    fn build_realtime_request(
+        &self,
+        _model: &str,
+        _model_config: &ModelConfig,
+    ) -> Result<Request<()>, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+}
+
+/// The provider's `base_url` param without a trailing slash, or `default` when unset.
+pub fn base_url(config: &ProviderConfig, default: &str) -> String {
+    config
+        .params
+        .get("base_url")
+        .map_or(default, String::as_str)
+        .trim_end_matches('/')
+        .to_string()
 }
 
 /// Maps provider type enum to standardized vendor names for OTEL reporting
@@ -43,5 +112,7 @@ pub fn get_vendor_name(provider_type: &ProviderType) -> Cow<'static, str> {
         ProviderType::Anthropic => Cow::Borrowed("Anthropic"),
         ProviderType::Bedrock => Cow::Borrowed("AWS"),
         ProviderType::VertexAI => Cow::Borrowed("Google"),
+        ProviderType::Mock => Cow::Borrowed("mock"),
+        ProviderType::Custom(name) => Cow::Borrowed(*name),
     }
 }
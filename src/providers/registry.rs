@@ -1,44 +1,181 @@
-use anyhow::Result;
-use std::collections::HashMap;
-use std::sync::Arc;
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, LazyLock, RwLock};
 
 use crate::config::models::Provider as ProviderConfig;
 use crate::providers::{
-    anthropic::AnthropicProvider, azure::AzureProvider, bedrock::BedrockProvider,
-    openai::OpenAIProvider, provider::Provider, vertexai::VertexAIProvider,
+    anthropic::AnthropicProvider,
+    api_keys::UNRESOLVED_SECRETS_PARAM,
+    azure::AzureProvider,
+    bedrock::BedrockProvider,
+    failover::{FailoverProvider, provider_group},
+    maintenance::MaintenanceSchedule,
+    mock::MockProvider,
+    openai::OpenAIProvider,
+    provider::Provider,
+    unresolved::UnresolvedSecretsProvider,
+    vertexai::VertexAIProvider,
 };
 use crate::types::ProviderType;
 
+/// Builds the provider of a config of the type it is registered for.
+pub type ProviderFactory = dyn Fn(&ProviderConfig) -> Result<Arc<dyn Provider>> + Send + Sync;
+
+/// The factory of each provider type, the built-in ones included.
+static FACTORIES: LazyLock<RwLock<HashMap<ProviderType, Arc<ProviderFactory>>>> =
+    LazyLock::new(|| RwLock::new(built_in_factories()));
+
+/// A factory building `P` from its config.
+fn factory_of<P: Provider + 'static>() -> Arc<ProviderFactory> {
+    Arc::new(|config: &ProviderConfig| -> Result<Arc<dyn Provider>> {
+        Ok(Arc::new(P::new(config)))
+    })
+}
+
+fn built_in_factories() -> HashMap<ProviderType, Arc<ProviderFactory>> {
+    HashMap::from([
+        (ProviderType::OpenAI, factory_of::<OpenAIProvider>()),
+        (ProviderType::Anthropic, factory_of::<AnthropicProvider>()),
+        (ProviderType::Azure, factory_of::<AzureProvider>()),
+        (ProviderType::Bedrock, factory_of::<BedrockProvider>()),
+        (ProviderType::VertexAI, factory_of::<VertexAIProvider>()),
+        (ProviderType::Mock, factory_of::<MockProvider>()),
+    ])
+}
+
+/// Whether providers of `provider_type` can be built.
+pub fn has_factory(provider_type: ProviderType) -> bool {
+    FACTORIES.read().unwrap().contains_key(&provider_type)
+}
+
+pub fn build_provider(config: &ProviderConfig) -> Result<Arc<dyn Provider>> {
+    if config.params.contains_key(UNRESOLVED_SECRETS_PARAM) {
+        return Ok(Arc::new(UnresolvedSecretsProvider::new(config)));
+    }
+    let factory = FACTORIES
+        .read()
+        .unwrap()
+        .get(&config.r#type)
+        .cloned()
+        .ok_or_else(|| {
+            anyhow!(
+                "No factory is registered for type '{}' of provider '{}'",
+                config.r#type,
+                config.key
+            )
+        })?;
+    factory(config).with_context(|| format!("Failed to build provider '{}'", config.key))
+}
+
 pub struct ProviderRegistry {
     providers: HashMap<String, Arc<dyn Provider>>,
+    /// Failover groups, keyed by group name.
+    groups: HashMap<String, Arc<dyn Provider>>,
+    /// Keys of each failover group's members.
+    group_members: HashMap<String, Vec<String>>,
+    /// Schedules of the providers with maintenance windows.
+    maintenance: HashMap<String, MaintenanceSchedule>,
 }
 
 impl ProviderRegistry {
+    /// Serves the providers of type `type_name` with the ones `factory` builds, from then on.
+    /// Embedders register their own provider types before building an
+    /// [`AppState`](crate::state::AppState) or [`Gateway`](crate::gateway::Gateway), so
+    /// configs can use them; registering a built-in type's name replaces it.
+    pub fn register_factory<F>(type_name: &str, factory: F)
+    where
+        F: Fn(&ProviderConfig) -> Result<Arc<dyn Provider>> + Send + Sync + 'static,
+    {
+        FACTORIES
+            .write()
+            .unwrap()
+            .insert(ProviderType::from_name(type_name), Arc::new(factory));
+    }
+
     pub fn new(provider_configs: &[ProviderConfig]) -> Result<Self> {
         let mut providers = HashMap::new();
+        let mut group_members: BTreeMap<&str, Vec<(&ProviderConfig, Arc<dyn Provider>)>> =
+            BTreeMap::new();
+
+        let mut maintenance = HashMap::new();
 
         for config in provider_configs {
-            let provider: Arc<dyn Provider> = match config.r#type {
-                ProviderType::OpenAI => Arc::new(OpenAIProvider::new(config)),
-                ProviderType::Anthropic => Arc::new(AnthropicProvider::new(config)),
-                ProviderType::Azure => Arc::new(AzureProvider::new(config)),
-                ProviderType::Bedrock => Arc::new(BedrockProvider::new(config)),
-                ProviderType::VertexAI => Arc::new(VertexAIProvider::new(config)),
-            };
+            let provider = build_provider(config)?;
+            let schedule = MaintenanceSchedule::new(&config.maintenance_windows);
+            if !schedule.is_empty() {
+                maintenance.insert(config.key.clone(), schedule);
+            }
+            if let Some(group) = provider_group(config) {
+                group_members
+                    .entry(group)
+                    .or_default()
+                    .push((config, provider.clone()));
+            }
             providers.insert(config.key.clone(), provider);
         }
 
-        Ok(Self { providers })
+        let mut groups = HashMap::new();
+        let mut member_keys = HashMap::new();
+        for (group, members) in group_members {
+            let keys = members
+                .iter()
+                .map(|(config, _)| config.key.clone())
+                .collect();
+            member_keys.insert(group.to_string(), keys);
+            let provider: Arc<dyn Provider> = Arc::new(FailoverProvider::group(group, members));
+            groups.insert(group.to_string(), provider);
+        }
+
+        Ok(Self {
+            providers,
+            groups,
+            group_members: member_keys,
+            maintenance,
+        })
     }
 
+    /// The provider with key `name`, or the failover group called `name`.
     pub fn get(&self, name: &str) -> Option<Arc<dyn Provider>> {
-        self.providers.get(name).cloned()
+        self.providers
+            .get(name)
+            .or_else(|| self.groups.get(name))
+            .cloned()
+    }
+
+    /// End of the maintenance window the provider, or failover group, called `name` is in at
+    /// `now`. A group is in maintenance while all of its members are, until the first of
+    /// them comes back.
+    pub fn maintenance_until(&self, name: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.providers.contains_key(name) {
+            return self.maintenance.get(name)?.active_at(now);
+        }
+        self.group_members
+            .get(name)?
+            .iter()
+            .map(|key| self.maintenance.get(key)?.active_at(now))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .min()
+    }
+
+    /// Providers that can't serve requests right now, keyed by provider with the reason.
+    pub fn unhealthy_providers(&self) -> BTreeMap<String, String> {
+        self.providers
+            .iter()
+            .filter_map(|(key, provider)| Some((key.clone(), provider.unhealthy_reason()?)))
+            .collect()
     }
 
     #[cfg(test)]
     pub fn from_mock(key: String, provider: Arc<dyn Provider>) -> Self {
         let mut providers = HashMap::new();
         providers.insert(key, provider);
-        Self { providers }
+        Self {
+            providers,
+            groups: HashMap::new(),
+            group_members: HashMap::new(),
+            maintenance: HashMap::new(),
+        }
     }
 }
@@ -0,0 +1,278 @@
+//! Stop sequences handled the same way whichever provider serves the request, following
+//! OpenAI: the content never includes the sequence that ended it, the choice names the
+//! sequence in its `stop_sequence` extension field when it's known, and `finish_reason` is
+//! `stop`. Providers differ on all three: OpenAI leaves the sequence out without naming it,
+//! Anthropic names it, and Gemini sometimes echoes it at the end of the text.
+
+use crate::models::chat::ChatCompletion;
+use crate::models::content::ChatMessageContent;
+use crate::models::streaming::{ChatCompletionChunk, Choice, ChoiceDelta};
+use async_stream::stream;
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use reqwest_streams::error::StreamBodyError;
+use std::collections::BTreeMap;
+
+pub const STOP_FINISH_REASON: &str = "stop";
+
+type ChunkStream = BoxStream<'static, Result<ChatCompletionChunk, StreamBodyError>>;
+
+/// The non-empty sequences of a request's `stop`.
+fn stop_sequences(stop: Option<&[String]>) -> Vec<String> {
+    stop.unwrap_or_default()
+        .iter()
+        .filter(|sequence| !sequence.is_empty())
+        .cloned()
+        .collect()
+}
+
+/// Cuts `text` at the first stop sequence in it, returning the sequence.
+pub fn trim_stop_sequence(text: &mut String, stop: &[String]) -> Option<String> {
+    let (at, sequence) = stop
+        .iter()
+        .filter(|sequence| !sequence.is_empty())
+        .filter_map(|sequence| Some((text.find(sequence.as_str())?, sequence)))
+        .min_by_key(|(at, _)| *at)?;
+    text.truncate(at);
+    Some(sequence.clone())
+}
+
+/// Trims the stop sequences of `stop` from the text of each choice.
+pub fn trim_completion(completion: &mut ChatCompletion, stop: Option<&[String]>) {
+    let stop = stop_sequences(stop);
+    if stop.is_empty() {
+        return;
+    }
+    for choice in &mut completion.choices {
+        let Some(ChatMessageContent::String(text)) = &mut choice.message.content else {
+            continue;
+        };
+        if let Some(sequence) = trim_stop_sequence(text, &stop) {
+            choice.stop_sequence = Some(sequence);
+            choice.finish_reason = Some(STOP_FINISH_REASON.to_string());
+        }
+    }
+}
+
+/// Trims stop sequences from the streamed content of one choice. A sequence may be split
+/// across chunks, so text that could be the start of one is held back until the next chunk
+/// settles it.
+#[derive(Debug)]
+struct StreamTrimmer {
+    stop: Vec<String>,
+    held: String,
+    matched: Option<String>,
+}
+
+impl StreamTrimmer {
+    fn new(stop: Vec<String>) -> Self {
+        Self {
+            stop,
+            held: String::new(),
+            matched: None,
+        }
+    }
+
+    /// The content that can be sent after receiving `text`. Nothing is sent once a stop
+    /// sequence was seen.
+    fn push(&mut self, text: &str) -> String {
+        if self.matched.is_some() {
+            return String::new();
+        }
+        self.held.push_str(text);
+        if let Some(sequence) = trim_stop_sequence(&mut self.held, &self.stop) {
+            self.matched = Some(sequence);
+            return std::mem::take(&mut self.held);
+        }
+        let held_back = self
+            .held
+            .split_off(self.held.len() - self.partial_match_len());
+        std::mem::replace(&mut self.held, held_back)
+    }
+
+    /// Length of the longest end of the held text that a stop sequence starts with.
+    fn partial_match_len(&self) -> usize {
+        self.stop
+            .iter()
+            .filter_map(|sequence| {
+                (1..sequence.len())
+                    .rev()
+                    .filter(|&len| sequence.is_char_boundary(len))
+                    .find(|&len| self.held.ends_with(&sequence[..len]))
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The content held back, sent when the choice finishes.
+    fn flush(&mut self) -> String {
+        std::mem::take(&mut self.held)
+    }
+}
+
+/// Trims the stop sequences of `stop` from a streamed chat completion. The chunk finishing
+/// a choice that hit one has `finish_reason` `stop` and names the sequence.
+pub fn trim_stream(mut chunks: ChunkStream, stop: Option<&[String]>) -> ChunkStream {
+    let stop = stop_sequences(stop);
+    if stop.is_empty() {
+        return chunks;
+    }
+    Box::pin(stream! {
+        let mut trimmers: BTreeMap<u32, StreamTrimmer> = BTreeMap::new();
+        let mut last_chunk = None;
+        while let Some(chunk) = chunks.next().await {
+            let mut chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    yield Err(e);
+                    continue;
+                }
+            };
+            for choice in &mut chunk.choices {
+                let trimmer = trimmers
+                    .entry(choice.index)
+                    .or_insert_with(|| StreamTrimmer::new(stop.clone()));
+                let mut content = choice.delta.content.as_deref().map(|text| trimmer.push(text));
+                if choice.finish_reason.is_some() {
+                    let held = trimmer.flush();
+                    if !held.is_empty() {
+                        content.get_or_insert_default().push_str(&held);
+                    }
+                    if let Some(sequence) = trimmer.matched.take() {
+                        choice.stop_sequence = Some(sequence);
+                        choice.finish_reason = Some(STOP_FINISH_REASON.to_string());
+                    }
+                }
+                choice.delta.content = content;
+            }
+            last_chunk = Some(chunk.clone());
+            yield Ok(chunk);
+        }
+
+        // Upstreams that end without a finishing chunk still get the held content out.
+        let Some(mut chunk) = last_chunk else {
+            return;
+        };
+        chunk.usage = None;
+        chunk.choices = trimmers
+            .into_iter()
+            .filter_map(|(index, mut trimmer)| {
+                let held = trimmer.flush();
+                let stop_sequence = trimmer.matched;
+                if held.is_empty() && stop_sequence.is_none() {
+                    return None;
+                }
+                Some(Choice {
+                    delta: ChoiceDelta {
+                        content: Some(held),
+                        role: None,
+                        tool_calls: None,
+                        reasoning: None,
+                        annotations: None,
+                    },
+                    finish_reason: stop_sequence
+                        .is_some()
+                        .then(|| STOP_FINISH_REASON.to_string()),
+                    stop_sequence,
+                    index,
+                    logprobs: None,
+                })
+            })
+            .collect();
+        if !chunk.choices.is_empty() {
+            yield Ok(chunk);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use serde_json::json;
+
+    fn stop(sequences: &[&str]) -> Vec<String> {
+        sequences
+            .iter()
+            .map(|sequence| sequence.to_string())
+            .collect()
+    }
+
+    fn chunk(content: &str, finish_reason: Option<&str>) -> ChatCompletionChunk {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{"index": 0, "delta": {"content": content}, "finish_reason": finish_reason}]
+        }))
+        .unwrap()
+    }
+
+    /// The streamed content, and the finish reason and stop sequence of the last chunk.
+    async fn trimmed(
+        chunks: Vec<ChatCompletionChunk>,
+        sequences: &[&str],
+    ) -> (String, Option<String>, Option<String>) {
+        let chunks: Vec<_> = trim_stream(
+            Box::pin(stream::iter(chunks.into_iter().map(Ok))),
+            Some(&stop(sequences)),
+        )
+        .map(Result::unwrap)
+        .collect()
+        .await;
+        let content = chunks
+            .iter()
+            .filter_map(|chunk| chunk.choices[0].delta.content.clone())
+            .collect();
+        let last = chunks.last().unwrap().choices[0].clone();
+        (content, last.finish_reason, last.stop_sequence)
+    }
+
+    #[test]
+    fn test_text_is_cut_at_the_first_stop_sequence() {
+        let mut text = "one, two\nEND three END".to_string();
+        let sequence = trim_stop_sequence(&mut text, &stop(&["END", "\n"]));
+        assert_eq!(text, "one, two");
+        assert_eq!(sequence.as_deref(), Some("\n"));
+
+        let mut text = "no match".to_string();
+        assert_eq!(trim_stop_sequence(&mut text, &stop(&["END", ""])), None);
+        assert_eq!(text, "no match");
+    }
+
+    #[tokio::test]
+    async fn test_stop_sequence_split_across_chunks_is_trimmed() {
+        let chunks = vec![
+            chunk("Hello wor", None),
+            chunk("ld<|e", None),
+            chunk("nd|> and more", None),
+            chunk("", Some("STOP")),
+        ];
+        let (content, finish_reason, stop_sequence) = trimmed(chunks, &["<|end|>"]).await;
+        assert_eq!(content, "Hello world");
+        assert_eq!(finish_reason.as_deref(), Some("stop"));
+        assert_eq!(stop_sequence.as_deref(), Some("<|end|>"));
+    }
+
+    #[tokio::test]
+    async fn test_held_back_text_is_sent_when_no_sequence_follows() {
+        let chunks = vec![chunk("Hello <|e", None), chunk("nvelope", Some("stop"))];
+        let (content, finish_reason, stop_sequence) = trimmed(chunks, &["<|end|>"]).await;
+        assert_eq!(content, "Hello <|envelope");
+        assert_eq!(finish_reason.as_deref(), Some("stop"));
+        assert_eq!(stop_sequence, None);
+
+        let chunks = vec![chunk("Hello", None), chunk(" <|en", Some("length"))];
+        let (content, finish_reason, _) = trimmed(chunks, &["<|end|>"]).await;
+        assert_eq!(content, "Hello <|en");
+        assert_eq!(finish_reason.as_deref(), Some("length"));
+    }
+
+    #[tokio::test]
+    async fn test_held_back_text_is_sent_when_the_stream_ends_unfinished() {
+        let chunks = vec![chunk("Hello", None), chunk(" <|en", None)];
+        let (content, finish_reason, _) = trimmed(chunks, &["<|end|>"]).await;
+        assert_eq!(content, "Hello <|en");
+        assert_eq!(finish_reason, None);
+    }
+}
@@ -0,0 +1,292 @@
+use crate::metrics::counter;
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use reqwest::{Client, Response};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use tracing::warn;
+
+use crate::config::models::Provider as ProviderConfig;
+use crate::logging::error_rate_limited;
+use crate::pipelines::explain::ExplainStep;
+use crate::providers::api_keys::{ApiKey, SUSPECT_KEY_METRIC, secondary_api_key};
+use crate::providers::http_client::build_http_client;
+use crate::providers::upstream::UpstreamRequest;
+use crate::timing::{self, TimedSend};
+use crate::trace_context::inject_trace_context;
+use crate::traceloop_headers::inject_traceloop_headers;
+use crate::upstream_headers::record_upstream_headers;
+
+/// Where a provider puts its API key on outbound requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Auth {
+    /// `Authorization: Bearer <key>`.
+    Bearer,
+    /// The bare key in the named header, e.g. Anthropic's `x-api-key`.
+    Header(&'static str),
+}
+
+impl Auth {
+    pub fn apply(self, request: UpstreamRequest, key: &str) -> UpstreamRequest {
+        match self {
+            Auth::Bearer => request.bearer_auth(key),
+            Auth::Header(name) => request.header(name, key),
+        }
+    }
+}
+
+/// The HTTP side of a provider: the client built from its proxy and TLS params, API key
+/// injection, the secondary key retry and upstream timing.
+///
+/// Providers only translate requests into `UpstreamRequest`s and parse response bodies.
+/// Everything goes over the wire through `send`, so a dry run of `build_chat_request`
+/// shows exactly the bytes that would be sent.
+pub struct Transport {
+    provider_key: String,
+    /// Name of the upstream API in logs, e.g. "OpenAI".
+    upstream: &'static str,
+    client: Client,
+    api_key: ApiKey,
+    auth: Auth,
+    /// Set when a request rejected with 401 or 403 is retried with `api_key_secondary`.
+    secondary_key: Option<String>,
+}
+
+impl Transport {
+    pub fn new(config: &ProviderConfig, upstream: &'static str, auth: Auth) -> Result<Self> {
+        Ok(Self::with_client(
+            config,
+            upstream,
+            auth,
+            build_http_client(config)?,
+        ))
+    }
+
+    pub fn with_client(
+        config: &ProviderConfig,
+        upstream: &'static str,
+        auth: Auth,
+        client: Client,
+    ) -> Self {
+        Self {
+            provider_key: config.key.clone(),
+            upstream,
+            client,
+            api_key: ApiKey::from_config(config),
+            auth,
+            secondary_key: None,
+        }
+    }
+
+    /// Retries requests whose primary key the upstream rejects with the provider's
+    /// `api_key_secondary`, if one is configured.
+    pub fn with_key_fallback(mut self, config: &ProviderConfig) -> Self {
+        self.secondary_key = secondary_api_key(config).map(str::to_string);
+        self
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn api_key(&self) -> &ApiKey {
+        &self.api_key
+    }
+
+    pub fn unhealthy_reason(&self) -> Option<String> {
+        self.api_key.unhealthy_reason()
+    }
+
+    /// Adds the current API key to `request`.
+    pub fn authorize(&self, request: UpstreamRequest) -> Result<UpstreamRequest, StatusCode> {
+        Ok(self.auth.apply(request, &self.api_key.get()?))
+    }
+
+    /// Sends `request`, returning the response if the upstream accepted it. Failures are
+    /// logged under `signature`, and an error status is passed through. The response
+    /// headers are recorded for passthrough either way. The current trace context goes
+    /// along in `traceparent`, as do the request's `x-traceloop-*` headers when
+    /// `forward_traceloop_headers` is on.
+    ///
+    /// When the upstream rejects the primary key with 401 or 403 and key fallback is on,
+    /// the request is re-authorized with the secondary key and sent once more. The status
+    /// arrives before any body bytes, so a stream that has started is never retried.
+    pub async fn send(
+        &self,
+        request: UpstreamRequest,
+        signature: &str,
+    ) -> Result<Response, StatusCode> {
+        let mut response = self.send_once(&request, signature).await?;
+        let status = response.status();
+        let rejected = status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN;
+        if let Some(secondary) = self.secondary_key.as_deref().filter(|_| rejected) {
+            warn!(
+                provider = %self.provider_key,
+                status = status.as_u16(),
+                "Primary API key rejected, retrying with the secondary key"
+            );
+            counter!(SUSPECT_KEY_METRIC, "provider" => self.provider_key.clone()).increment(1);
+            ExplainStep::new("retry", "secondary_key")
+                .details(json!({ "provider": self.provider_key, "status": status.as_u16() }))
+                .record();
+            let request = self.auth.apply(request, secondary);
+            response = self.send_once(&request, signature).await?;
+        }
+        record_upstream_headers(&self.provider_key, response.headers());
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        error_rate_limited(
+            &format!("{signature}.upstream"),
+            format!(
+                "{} API request failed with status {status}: {}",
+                self.upstream,
+                response.text().await.unwrap_or_default()
+            ),
+        );
+        Err(status)
+    }
+
+    /// Sends `request` and reads the whole response body, marking the end of the
+    /// upstream call for the request's timing breakdown.
+    pub async fn send_for_body(
+        &self,
+        request: UpstreamRequest,
+        signature: &str,
+    ) -> Result<Bytes, StatusCode> {
+        let body = self
+            .send(request, signature)
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| {
+                error_rate_limited(
+                    &format!("{signature}.response"),
+                    format!("{} API response error: {e}", self.upstream),
+                );
+                request_error_status(&e)
+            })?;
+        timing::mark_upstream_done();
+        Ok(body)
+    }
+
+    async fn send_once(
+        &self,
+        request: &UpstreamRequest,
+        signature: &str,
+    ) -> Result<Response, StatusCode> {
+        let builder = inject_trace_context(request.to_request_builder(&self.client));
+        inject_traceloop_headers(builder)
+            .send_timed()
+            .await
+            .map_err(|e| {
+                error_rate_limited(
+                    &format!("{signature}.request"),
+                    format!("{} API request error: {e}", self.upstream),
+                );
+                request_error_status(&e)
+            })
+    }
+}
+
+/// Status for a request that got no response: 504 when it timed out, 500 otherwise.
+fn request_error_status(error: &reqwest::Error) -> StatusCode {
+    if error.is_timeout() {
+        StatusCode::GATEWAY_TIMEOUT
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// Parses an upstream JSON response body, logging failures under `signature`.
+pub fn parse_json<T: DeserializeOwned>(body: &[u8], signature: &str) -> Result<T, StatusCode> {
+    serde_json::from_slice(body).map_err(|e| {
+        error_rate_limited(
+            &format!("{signature}.response"),
+            format!(
+                "Failed to parse upstream response: {e}. Raw response: {}",
+                String::from_utf8_lossy(body)
+            ),
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::api_keys::API_KEY_SECONDARY_PARAM;
+    use crate::types::ProviderType;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use wiremock::matchers::{header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config(params: &[(&str, &str)]) -> ProviderConfig {
+        ProviderConfig {
+            key: "openai".to_string(),
+            r#type: ProviderType::OpenAI,
+            api_key: "sk-primary".to_string(),
+            maintenance_windows: vec![],
+            params: params
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_authorize_uses_provider_auth_scheme() {
+        let request = || UpstreamRequest::post("https://example.com", &json!({})).unwrap();
+
+        let bearer = Transport::new(&config(&[]), "OpenAI", Auth::Bearer).unwrap();
+        let authorized = bearer.authorize(request()).unwrap();
+        assert_eq!(authorized.headers["authorization"], "Bearer sk-primary");
+
+        let header = Transport::new(&config(&[]), "Anthropic", Auth::Header("X-Api-Key")).unwrap();
+        let authorized = header.authorize(request()).unwrap();
+        assert_eq!(authorized.headers["x-api-key"], "sk-primary");
+        assert!(!authorized.headers.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_key_falls_back_to_secondary_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer sk-secondary"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": true})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let config = config(&[(API_KEY_SECONDARY_PARAM, "sk-secondary")]);
+        let request = UpstreamRequest::post(server.uri(), &json!({})).unwrap();
+
+        let transport = Transport::new(&config, "OpenAI", Auth::Bearer)
+            .unwrap()
+            .with_key_fallback(&config);
+        let body = transport
+            .send_for_body(transport.authorize(request.clone()).unwrap(), "test")
+            .await
+            .unwrap();
+        assert_eq!(
+            parse_json::<serde_json::Value>(&body, "test").unwrap()["ok"],
+            true
+        );
+
+        // Without key fallback the rejection is passed through.
+        let transport = Transport::new(&config, "OpenAI", Auth::Bearer).unwrap();
+        let status = transport
+            .send(transport.authorize(request).unwrap(), "test")
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
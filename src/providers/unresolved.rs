@@ -0,0 +1,70 @@
+use async_trait::async_trait;
+use axum::http::StatusCode;
+
+use crate::config::models::{ModelConfig, Provider as ProviderConfig};
+use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::completion::{CompletionRequest, CompletionResponse};
+use crate::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
+use crate::providers::api_keys::UNRESOLVED_SECRETS_PARAM;
+use crate::providers::provider::Provider;
+use crate::types::ProviderType;
+
+/// Stands in for a provider whose secrets couldn't be resolved when the config was built,
+/// answering 503 so the rest of the config still applies. The config poller resolves the
+/// secrets again on every poll and replaces this provider once they resolve.
+pub struct UnresolvedSecretsProvider {
+    key: String,
+    r#type: ProviderType,
+    reason: String,
+}
+
+#[async_trait]
+impl Provider for UnresolvedSecretsProvider {
+    fn new(config: &ProviderConfig) -> Self {
+        Self {
+            key: config.key.clone(),
+            r#type: config.r#type,
+            reason: config
+                .params
+                .get(UNRESOLVED_SECRETS_PARAM)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    fn key(&self) -> String {
+        self.key.clone()
+    }
+
+    fn r#type(&self) -> ProviderType {
+        self.r#type
+    }
+
+    fn unhealthy_reason(&self) -> Option<String> {
+        Some(format!("unresolved secrets: {}", self.reason))
+    }
+
+    async fn chat_completions(
+        &self,
+        _payload: ChatCompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        Err(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    async fn completions(
+        &self,
+        _payload: CompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<CompletionResponse, StatusCode> {
+        Err(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    async fn embeddings(
+        &self,
+        _payload: EmbeddingsRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<EmbeddingsResponse, StatusCode> {
+        Err(StatusCode::SERVICE_UNAVAILABLE)
+    }
+}
@@ -0,0 +1,254 @@
+use axum::http::StatusCode;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+/// Headers carrying credentials. Their values never leave the gateway.
+const SECRET_HEADERS: &[&str] = &["authorization", "api-key", "x-api-key", "x-goog-api-key"];
+/// Query parameters carrying credentials.
+const SECRET_QUERY_PARAMS: &[&str] = &["key", "api-key", "api_key"];
+const MASK: &str = "***";
+
+/// A provider's upstream HTTP request, built but not yet sent. Providers send exactly this
+/// request, so dry runs can show what would go over the wire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamRequest {
+    pub url: String,
+    /// Header names are lowercase.
+    pub headers: BTreeMap<String, String>,
+    /// The serialized JSON body.
+    pub body: Vec<u8>,
+}
+
+impl UpstreamRequest {
+    /// A JSON POST of `body` to `url`.
+    pub fn post(url: impl Into<String>, body: &impl Serialize) -> Result<Self, StatusCode> {
+        let body = serde_json::to_vec(body).map_err(|e| {
+            tracing::error!("Failed to serialize upstream request body: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        Ok(Self::post_json_bytes(url, body))
+    }
+
+    /// A POST of an already serialized JSON body to `url`.
+    pub fn post_json_bytes(url: impl Into<String>, body: Vec<u8>) -> Self {
+        Self {
+            url: url.into(),
+            headers: BTreeMap::from([(CONTENT_TYPE.to_string(), "application/json".to_string())]),
+            body,
+        }
+    }
+
+    /// Merges a request's `extra_body` into the translated body. See [`merge_extra_body`].
+    pub fn with_extra_body(mut self, extra_body: Option<&Value>) -> Result<Self, StatusCode> {
+        self.body = merge_extra_body(self.body, extra_body)?;
+        Ok(self)
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.insert(name.to_ascii_lowercase(), value.into());
+        self
+    }
+
+    pub fn bearer_auth(self, token: &str) -> Self {
+        self.header("authorization", format!("Bearer {token}"))
+    }
+
+    /// The reqwest request that sends this upstream.
+    pub fn to_request_builder(&self, client: &Client) -> RequestBuilder {
+        let mut builder = client.post(&self.url).body(self.body.clone());
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        builder
+    }
+
+    /// The body as JSON, for display.
+    pub fn body_json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    /// A JSON description of the request with credentials masked.
+    pub fn to_masked_json(&self) -> Value {
+        let headers: BTreeMap<&str, &str> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                    MASK
+                } else {
+                    value.as_str()
+                };
+                (name.as_str(), value)
+            })
+            .collect();
+        json!({
+            "method": "POST",
+            "url": mask_url(&self.url),
+            "headers": headers,
+            "body": self.body_json(),
+        })
+    }
+}
+
+/// Merges `extra_body` into a serialized JSON object body, descending into objects present
+/// in both, e.g. Gemini's `generationConfig`. A key the translation already set to anything
+/// but an object is hub-managed, so colliding with it is a bad request rather than an
+/// override.
+pub fn merge_extra_body(body: Vec<u8>, extra_body: Option<&Value>) -> Result<Vec<u8>, StatusCode> {
+    let Some(extra_body) = extra_body else {
+        return Ok(body);
+    };
+    let Some(extra) = extra_body.as_object() else {
+        tracing::debug!("extra_body must be a JSON object");
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    let Ok(Value::Object(mut translated)) = serde_json::from_slice::<Value>(&body) else {
+        tracing::error!("Upstream request body isn't a JSON object, can't merge extra_body");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+    if let Err(path) = merge_object(&mut translated, extra, "") {
+        tracing::debug!(
+            "extra_body key '{}' collides with a hub-managed field",
+            path
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    serde_json::to_vec(&translated).map_err(|e| {
+        tracing::error!("Failed to serialize upstream request body: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Adds `extra` to `target`, failing with the dotted path of the first colliding key.
+fn merge_object(
+    target: &mut Map<String, Value>,
+    extra: &Map<String, Value>,
+    prefix: &str,
+) -> Result<(), String> {
+    for (key, value) in extra {
+        let path = format!("{prefix}{key}");
+        match (target.get_mut(key), value) {
+            (None, _) => {
+                target.insert(key.clone(), value.clone());
+            }
+            (Some(Value::Object(existing)), Value::Object(nested)) => {
+                merge_object(existing, nested, &format!("{path}."))?;
+            }
+            (Some(_), _) => return Err(path),
+        }
+    }
+    Ok(())
+}
+
+fn mask_url(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    if parsed.query().is_none() {
+        return url.to_string();
+    }
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(name, value)| {
+            let value = if SECRET_QUERY_PARAMS.contains(&name.as_ref()) {
+                MASK.to_string()
+            } else {
+                value.into_owned()
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masked_json_hides_credentials() {
+        let request = UpstreamRequest::post(
+            "https://example.com/v1/chat?api-version=2024-10-21&key=secret",
+            &json!({"model": "gpt-4o", "temperature": 0.7}),
+        )
+        .unwrap()
+        .bearer_auth("sk-secret")
+        .header("X-Api-Key", "secret")
+        .header("anthropic-version", "2023-06-01");
+
+        let masked = request.to_masked_json();
+        assert_eq!(masked["method"], "POST");
+        assert_eq!(
+            masked["url"],
+            "https://example.com/v1/chat?api-version=2024-10-21&key=***"
+        );
+        assert_eq!(masked["headers"]["authorization"], "***");
+        assert_eq!(masked["headers"]["x-api-key"], "***");
+        assert_eq!(masked["headers"]["anthropic-version"], "2023-06-01");
+        assert_eq!(masked["headers"]["content-type"], "application/json");
+        assert_eq!(
+            masked["body"],
+            json!({"model": "gpt-4o", "temperature": 0.7})
+        );
+        assert!(!masked.to_string().contains("secret"));
+    }
+
+    #[test]
+    fn test_extra_body_merges_into_nested_objects() {
+        let request = UpstreamRequest::post(
+            "https://example.com/v1/chat",
+            &json!({"model": "gemini-2.5-pro", "generationConfig": {"temperature": 0.2}}),
+        )
+        .unwrap();
+
+        let merged = request
+            .clone()
+            .with_extra_body(Some(&json!({
+                "generationConfig": {"thinkingConfig": {"thinkingBudget": 1024}},
+                "safetySettings": [],
+            })))
+            .unwrap();
+        assert_eq!(
+            merged.body_json(),
+            json!({
+                "model": "gemini-2.5-pro",
+                "generationConfig": {
+                    "temperature": 0.2,
+                    "thinkingConfig": {"thinkingBudget": 1024},
+                },
+                "safetySettings": [],
+            })
+        );
+        assert_eq!(request.clone().with_extra_body(None).unwrap(), request);
+
+        for extra_body in [
+            json!({"model": "gpt-4o"}),
+            json!({"generationConfig": {"temperature": 1.0}}),
+            json!({"generationConfig": 1}),
+            json!(["not", "an", "object"]),
+        ] {
+            assert_eq!(
+                request.clone().with_extra_body(Some(&extra_body)),
+                Err(StatusCode::BAD_REQUEST)
+            );
+        }
+    }
+
+    #[test]
+    fn test_request_builder_sends_headers_and_body() {
+        let request = UpstreamRequest::post("https://example.com/v1/chat", &json!({"n": 1}))
+            .unwrap()
+            .bearer_auth("sk-test");
+
+        let built = request.to_request_builder(&Client::new()).build().unwrap();
+        assert_eq!(built.url().as_str(), "https://example.com/v1/chat");
+        assert_eq!(built.headers()["authorization"], "Bearer sk-test");
+        assert_eq!(built.headers()["content-type"], "application/json");
+        let body = built.body().and_then(|body| body.as_bytes()).unwrap();
+        assert_eq!(body, br#"{"n":1}"#);
+    }
+}
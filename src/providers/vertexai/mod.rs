@@ -1,5 +1,6 @@
 pub mod models;
 pub mod provider;
+pub mod service_account;
 #[cfg(test)]
 mod tests;
 
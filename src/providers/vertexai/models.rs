@@ -1,12 +1,16 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 
 use crate::models::chat::{ChatCompletion, ChatCompletionChoice, ChatCompletionRequest};
-use crate::models::content::{ChatCompletionMessage, ChatMessageContent};
+use crate::models::content::{ChatCompletionMessage, ChatMessageContent, is_instruction_role};
+use crate::models::logprob::{ChatCompletionTokenLogprob, ChoiceLogprobs, TopLogprob};
+use crate::models::messages::tool_input;
 use crate::models::streaming::{ChatCompletionChunk, Choice, ChoiceDelta};
-use crate::models::tool_calls::{ChatMessageToolCall, FunctionCall};
+use crate::models::tool_calls::{ChatMessageToolCall, ChoiceDeltaToolCall, FunctionCall};
 use crate::models::tool_choice::{SimpleToolChoice, ToolChoice};
-use crate::models::usage::Usage;
+use crate::models::tool_definition::ToolDefinition;
+use crate::models::usage::{CompletionTokensDetails, PromptTokensDetails, Usage};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeminiChatRequest {
@@ -58,7 +62,7 @@ pub struct GeminiFunctionChoice {
     pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct GeminiContent {
     pub role: String,
     pub parts: Vec<ContentPart>,
@@ -70,6 +74,8 @@ pub struct ContentPart {
     pub text: Option<String>,
     #[serde(rename = "functionCall", skip_serializing_if = "Option::is_none")]
     pub function_call: Option<GeminiFunctionCall>,
+    #[serde(rename = "functionResponse", skip_serializing_if = "Option::is_none")]
+    pub function_response: Option<GeminiFunctionResponse>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -96,6 +102,11 @@ pub struct GenerationConfig {
     pub response_schema: Option<GeminiSchema>,
     #[serde(rename = "thinkingConfig", skip_serializing_if = "Option::is_none")]
     pub thinking_config: Option<ThinkingConfig>,
+    #[serde(rename = "responseLogprobs", skip_serializing_if = "Option::is_none")]
+    pub response_logprobs: Option<bool>,
+    /// Alternatives returned per token; only honoured with `response_logprobs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -144,16 +155,104 @@ pub struct SafetySetting {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeminiChatResponse {
+    #[serde(default)]
     pub candidates: Vec<GeminiCandidate>,
+    #[serde(alias = "usageMetadata")]
     pub usage_metadata: Option<UsageMetadata>,
+    #[serde(
+        alias = "promptFeedback",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub prompt_feedback: Option<PromptFeedback>,
+}
+
+/// Set when Gemini refuses the prompt itself; no candidates are returned in that case.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptFeedback {
+    #[serde(alias = "blockReason", default)]
+    pub block_reason: Option<String>,
+    #[serde(alias = "blockReasonMessage", default)]
+    pub block_reason_message: Option<String>,
+    #[serde(alias = "safetyRatings", default)]
+    pub safety_ratings: Option<Vec<SafetyRating>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeminiCandidate {
+    /// Omitted by Gemini when the candidate was blocked.
+    #[serde(default)]
     pub content: GeminiContent,
+    #[serde(alias = "finishReason")]
     pub finish_reason: Option<String>,
+    #[serde(alias = "safetyRatings")]
     pub safety_ratings: Option<Vec<SafetyRating>>,
     pub tool_calls: Option<Vec<GeminiToolCall>>,
+    #[serde(
+        rename = "logprobsResult",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub logprobs_result: Option<GeminiLogprobsResult>,
+}
+
+/// Token log probabilities, returned when `responseLogprobs` is enabled.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiLogprobsResult {
+    /// The most likely tokens at each step, in step order.
+    #[serde(default)]
+    pub top_candidates: Vec<GeminiTopCandidates>,
+    /// The sampled token at each step.
+    #[serde(default)]
+    pub chosen_candidates: Vec<GeminiLogprobsCandidate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GeminiTopCandidates {
+    #[serde(default)]
+    pub candidates: Vec<GeminiLogprobsCandidate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiLogprobsCandidate {
+    pub token: String,
+    pub log_probability: f64,
+}
+
+fn token_bytes(token: &str) -> Option<Vec<i32>> {
+    Some(token.bytes().map(i32::from).collect())
+}
+
+impl From<GeminiLogprobsResult> for ChoiceLogprobs {
+    fn from(result: GeminiLogprobsResult) -> Self {
+        let mut top_candidates = result.top_candidates.into_iter();
+        let content = result
+            .chosen_candidates
+            .into_iter()
+            .map(|chosen| ChatCompletionTokenLogprob {
+                bytes: token_bytes(&chosen.token),
+                token: chosen.token,
+                logprob: chosen.log_probability,
+                top_logprobs: top_candidates
+                    .next()
+                    .unwrap_or_default()
+                    .candidates
+                    .into_iter()
+                    .map(|top| TopLogprob {
+                        bytes: token_bytes(&top.token),
+                        token: top.token,
+                        logprob: top.log_probability,
+                    })
+                    .collect(),
+            })
+            .collect();
+        ChoiceLogprobs {
+            content: Some(content),
+            refusal: None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -167,23 +266,121 @@ pub struct GeminiFunctionCall {
     pub args: Value,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A tool result sent back to Gemini.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GeminiFunctionResponse {
+    pub name: String,
+    pub response: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SafetyRating {
     pub category: String,
     pub probability: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Gemini finish reasons that mean the candidate was withheld by a safety filter.
+const SAFETY_FINISH_REASONS: [&str; 5] = [
+    "SAFETY",
+    "PROHIBITED_CONTENT",
+    "BLOCKLIST",
+    "SPII",
+    "IMAGE_SAFETY",
+];
+
+fn is_safety_block(finish_reason: Option<&str>) -> bool {
+    finish_reason.is_some_and(|reason| SAFETY_FINISH_REASONS.contains(&reason))
+}
+
+fn safety_refusal(subject: &str, reason: &str) -> String {
+    format!("The {subject} was blocked by the provider's safety filters ({reason}).")
+}
+
+fn safety_ratings_value(ratings: Option<Vec<SafetyRating>>) -> Option<Value> {
+    ratings.and_then(|ratings| serde_json::to_value(ratings).ok())
+}
+
+fn blocked_prompt_choice(feedback: PromptFeedback) -> Option<ChatCompletionChoice> {
+    let reason = feedback.block_reason?;
+    let refusal = feedback
+        .block_reason_message
+        .unwrap_or_else(|| safety_refusal("prompt", &reason));
+    Some(ChatCompletionChoice {
+        index: 0,
+        message: ChatCompletionMessage {
+            role: "assistant".to_string(),
+            content: None,
+            tool_calls: None,
+            name: None,
+            tool_call_id: None,
+            refusal: Some(refusal),
+            annotations: None,
+        },
+        finish_reason: Some("content_filter".to_string()),
+        logprobs: None,
+        safety_ratings: safety_ratings_value(feedback.safety_ratings),
+        stop_sequence: None,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct UsageMetadata {
+    #[serde(alias = "promptTokenCount", default)]
     pub prompt_token_count: i32,
+    /// Output tokens, not counting thoughts.
+    #[serde(alias = "candidatesTokenCount", default)]
     pub candidates_token_count: i32,
+    #[serde(alias = "totalTokenCount", default)]
     pub total_token_count: i32,
+    #[serde(
+        alias = "thoughtsTokenCount",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub thoughts_token_count: Option<i32>,
+    /// Part of the prompt served from a context cache.
+    #[serde(
+        alias = "cachedContentTokenCount",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cached_content_token_count: Option<i32>,
+}
+
+impl From<UsageMetadata> for Usage {
+    /// OpenAI counts reasoning tokens in `completion_tokens`, while Gemini reports thoughts
+    /// apart from the candidates.
+    fn from(meta: UsageMetadata) -> Self {
+        let count = |tokens: i32| tokens.max(0) as u32;
+        let thoughts = meta.thoughts_token_count.map(count);
+        Usage {
+            prompt_tokens: count(meta.prompt_token_count),
+            completion_tokens: count(meta.candidates_token_count) + thoughts.unwrap_or(0),
+            total_tokens: count(meta.total_token_count),
+            completion_tokens_details: thoughts.map(|reasoning_tokens| CompletionTokensDetails {
+                reasoning_tokens: Some(reasoning_tokens),
+                ..Default::default()
+            }),
+            prompt_tokens_details: meta.cached_content_token_count.map(|cached| {
+                PromptTokensDetails {
+                    cached_tokens: Some(count(cached)),
+                    ..Default::default()
+                }
+            }),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct VertexAIStreamChunk {
+    #[serde(default)]
     pub candidates: Vec<GeminiCandidate>,
+    #[serde(alias = "usageMetadata")]
     pub usage_metadata: Option<UsageMetadata>,
+    #[serde(alias = "promptFeedback", default)]
+    pub prompt_feedback: Option<PromptFeedback>,
 }
 
 impl GeminiSchema {
@@ -331,58 +528,113 @@ impl GeminiSchema {
     }
 }
 
+/// Converts chat messages to Gemini contents, one part per text part. Tool calls become
+/// `functionCall` parts and tool results `functionResponse` parts, named after the call
+/// they answer.
+fn gemini_contents<'a>(
+    messages: impl IntoIterator<Item = &'a ChatCompletionMessage>,
+) -> Vec<GeminiContent> {
+    let mut call_names: HashMap<&str, &str> = HashMap::new();
+    let mut contents = Vec::new();
+    for message in messages {
+        if message.role == "tool" {
+            let name = message
+                .tool_call_id
+                .as_deref()
+                .and_then(|id| call_names.get(id).copied())
+                .or(message.name.as_deref())
+                .unwrap_or_default();
+            let output = message
+                .content
+                .as_ref()
+                .map(|content| content.text_parts().concat())
+                .unwrap_or_default();
+            contents.push(GeminiContent {
+                role: "user".to_string(),
+                parts: vec![ContentPart {
+                    text: None,
+                    function_call: None,
+                    function_response: Some(GeminiFunctionResponse {
+                        name: name.to_string(),
+                        response: function_response_value(output),
+                    }),
+                }],
+            });
+            continue;
+        }
+
+        let mut parts: Vec<ContentPart> = message
+            .content
+            .iter()
+            .flat_map(ChatMessageContent::text_parts)
+            .map(|text| ContentPart {
+                text: Some(text.to_string()),
+                function_call: None,
+                function_response: None,
+            })
+            .collect();
+        for call in message.tool_calls.iter().flatten() {
+            call_names.insert(&call.id, &call.function.name);
+            parts.push(ContentPart {
+                text: None,
+                function_call: Some(GeminiFunctionCall {
+                    name: call.function.name.clone(),
+                    args: tool_input(&call.function.arguments),
+                }),
+                function_response: None,
+            });
+        }
+        if parts.is_empty() {
+            parts.push(ContentPart {
+                text: None,
+                function_call: None,
+                function_response: None,
+            });
+        }
+        contents.push(GeminiContent {
+            role: match message.role.as_str() {
+                "assistant" => "model".to_string(),
+                role => role.to_string(),
+            },
+            parts,
+        });
+    }
+    contents
+}
+
+/// Gemini expects a function response to be an object; other tool output is wrapped.
+fn function_response_value(output: String) -> Value {
+    match serde_json::from_str(&output) {
+        Ok(Value::Object(object)) => Value::Object(object),
+        _ => serde_json::json!({ "content": output }),
+    }
+}
+
 impl From<ChatCompletionRequest> for GeminiChatRequest {
     fn from(req: ChatCompletionRequest) -> Self {
         tracing::debug!(
             "🔄 Converting ChatCompletionRequest to GeminiChatRequest, reasoning: {:?}",
             req.reasoning
         );
-        let system_instruction = req
+        let instructions: Vec<GeminiSystemPart> = req
             .messages
             .iter()
-            .find(|msg| msg.role == "system")
-            .and_then(|message| match &message.content {
-                Some(ChatMessageContent::String(text)) => Some(GeminiSystemInstruction {
-                    parts: vec![GeminiSystemPart { text: text.clone() }],
-                }),
-                Some(ChatMessageContent::Array(parts)) => parts
-                    .iter()
-                    .find(|part| part.r#type == "text")
-                    .map(|part| GeminiSystemInstruction {
-                        parts: vec![GeminiSystemPart {
-                            text: part.text.clone(),
-                        }],
-                    }),
-                _ => None,
-            });
-
-        let contents = req
-            .messages
-            .into_iter()
-            .filter(|msg| msg.role != "system")
-            .map(|msg| GeminiContent {
-                role: match msg.role.as_str() {
-                    "assistant" => "model".to_string(),
-                    role => role.to_string(),
-                },
-                parts: vec![ContentPart {
-                    text: match msg.content {
-                        Some(content) => match content {
-                            ChatMessageContent::String(text) => Some(text),
-                            ChatMessageContent::Array(parts) => Some(
-                                parts
-                                    .into_iter()
-                                    .map(|p| p.text)
-                                    .collect::<Vec<_>>()
-                                    .join(" "),
-                            ),
-                        },
-                        None => None,
-                    },
-                    function_call: None,
-                }],
+            .filter(|msg| is_instruction_role(&msg.role))
+            .filter_map(|msg| msg.content.as_ref())
+            .flat_map(ChatMessageContent::text_parts)
+            .map(|text| GeminiSystemPart {
+                text: text.to_string(),
             })
             .collect();
+        let system_instruction = (!instructions.is_empty()).then_some(GeminiSystemInstruction {
+            parts: instructions,
+        });
+
+        let contents = gemini_contents(
+            req.messages
+                .iter()
+                .filter(|msg| !is_instruction_role(&msg.role)),
+        );
 
         let (response_mime_type, response_schema) =
             if let Some(response_format) = &req.response_format {
@@ -436,17 +688,21 @@ impl From<ChatCompletionRequest> for GeminiChatRequest {
             response_mime_type: response_mime_type.clone(),
             response_schema: response_schema.clone(),
             thinking_config,
+            response_logprobs: req.logprobs.filter(|&enabled| enabled),
+            logprobs: req
+                .top_logprobs
+                .filter(|&top| top > 0 && req.logprobs == Some(true)),
         });
 
         let tools = req.tools.map(|tools| {
             vec![GeminiTool {
                 function_declarations: tools
                     .into_iter()
-                    .map(|tool| GeminiFunctionDeclaration {
-                        name: tool.function.name,
-                        description: tool.function.description,
-                        parameters: serde_json::to_value(tool.function.parameters)
-                            .unwrap_or_default(),
+                    .filter_map(ToolDefinition::into_function)
+                    .map(|function| GeminiFunctionDeclaration {
+                        name: function.name,
+                        description: function.description,
+                        parameters: serde_json::to_value(function.parameters).unwrap_or_default(),
                     })
                     .collect(),
             }]
@@ -488,7 +744,7 @@ impl GeminiChatResponse {
         model: String,
         is_structured_output: bool,
     ) -> ChatCompletion {
-        let choices = self
+        let mut choices: Vec<ChatCompletionChoice> = self
             .candidates
             .into_iter()
             .enumerate()
@@ -533,7 +789,7 @@ impl GeminiChatResponse {
                     }
                 }
 
-                ChatCompletionChoice {
+                let mut choice = ChatCompletionChoice {
                     index: i as u32,
                     message: ChatCompletionMessage {
                         role: "assistant".to_string(),
@@ -550,29 +806,29 @@ impl GeminiChatResponse {
                         name: None,
                         tool_call_id: None,
                         refusal: None,
+                        annotations: None,
                     },
                     finish_reason: candidate.finish_reason,
-                    logprobs: None,
+                    logprobs: candidate.logprobs_result.map(ChoiceLogprobs::from),
+                    safety_ratings: safety_ratings_value(candidate.safety_ratings),
+                    stop_sequence: None,
+                };
+                if is_safety_block(choice.finish_reason.as_deref()) {
+                    let reason = choice
+                        .finish_reason
+                        .replace("content_filter".to_string())
+                        .unwrap_or_default();
+                    choice.message.refusal = Some(safety_refusal("response", &reason));
                 }
+                choice
             })
             .collect();
 
-        let usage = self.usage_metadata.map_or_else(
-            || Usage {
-                prompt_tokens: 0,
-                completion_tokens: 0,
-                total_tokens: 0,
-                completion_tokens_details: None,
-                prompt_tokens_details: None,
-            },
-            |meta| Usage {
-                prompt_tokens: meta.prompt_token_count as u32,
-                completion_tokens: meta.candidates_token_count as u32,
-                total_tokens: meta.total_token_count as u32,
-                completion_tokens_details: None,
-                prompt_tokens_details: None,
-            },
-        );
+        if let Some(choice) = self.prompt_feedback.and_then(blocked_prompt_choice) {
+            choices = vec![choice];
+        }
+
+        let usage = self.usage_metadata.map(Usage::from).unwrap_or_default();
 
         ChatCompletion {
             id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
@@ -582,6 +838,7 @@ impl GeminiChatResponse {
             choices,
             usage,
             system_fingerprint: None,
+            service_tier: None,
         }
     }
 }
@@ -589,6 +846,21 @@ impl GeminiChatResponse {
 impl From<VertexAIStreamChunk> for ChatCompletionChunk {
     fn from(chunk: VertexAIStreamChunk) -> Self {
         let first_candidate = chunk.candidates.first();
+        let prompt_blocked = chunk
+            .prompt_feedback
+            .as_ref()
+            .is_some_and(|feedback| feedback.block_reason.is_some());
+        let finish_reason = first_candidate.and_then(|c| c.finish_reason.clone());
+        let finish_reason = if prompt_blocked || is_safety_block(finish_reason.as_deref()) {
+            Some("content_filter".to_string())
+        } else {
+            finish_reason
+        };
+        // Every chunk carries the running counts; only the last chunk's are final.
+        let usage = chunk
+            .usage_metadata
+            .filter(|_| finish_reason.is_some())
+            .map(Usage::from);
 
         Self {
             id: uuid::Uuid::new_v4().to_string(),
@@ -598,7 +870,9 @@ impl From<VertexAIStreamChunk> for ChatCompletionChunk {
             model: String::new(),
             choices: vec![Choice {
                 index: 0,
-                logprobs: None,
+                logprobs: first_candidate
+                    .and_then(|c| c.logprobs_result.clone())
+                    .map(ChoiceLogprobs::from),
                 delta: ChoiceDelta {
                     role: None,
                     content: first_candidate
@@ -609,22 +883,28 @@ impl From<VertexAIStreamChunk> for ChatCompletionChunk {
                         .map(|calls| {
                             calls
                                 .into_iter()
-                                .map(|call| ChatMessageToolCall {
-                                    id: format!("call_{}", uuid::Uuid::new_v4()),
-                                    r#type: "function".to_string(),
-                                    function: FunctionCall {
-                                        name: call.function.name,
-                                        arguments: serde_json::to_string(&call.function.args)
-                                            .unwrap_or_else(|_| "{}".to_string()),
-                                    },
+                                .enumerate()
+                                .map(|(index, call)| {
+                                    let tool_call = ChatMessageToolCall {
+                                        id: format!("call_{}", uuid::Uuid::new_v4()),
+                                        r#type: "function".to_string(),
+                                        function: FunctionCall {
+                                            name: call.function.name,
+                                            arguments: serde_json::to_string(&call.function.args)
+                                                .unwrap_or_else(|_| "{}".to_string()),
+                                        },
+                                    };
+                                    ChoiceDeltaToolCall::complete(index as u32, tool_call)
                                 })
                                 .collect()
                         }),
                     reasoning: None,
+                    annotations: None,
                 },
-                finish_reason: first_candidate.and_then(|c| c.finish_reason.clone()),
+                finish_reason,
+                stop_sequence: None,
             }],
-            usage: None,
+            usage,
         }
     }
 }
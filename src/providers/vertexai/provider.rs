@@ -1,4 +1,5 @@
 use super::models::{GeminiChatRequest, GeminiChatResponse, VertexAIStreamChunk};
+use super::service_account::ServiceAccountTokens;
 use crate::config::models::{ModelConfig, Provider as ProviderConfig};
 use crate::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
 use crate::models::completion::{CompletionRequest, CompletionResponse};
@@ -7,23 +8,27 @@ use crate::models::embeddings::{
 };
 use crate::models::streaming::ChatCompletionChunk;
 use crate::models::usage::EmbeddingUsage;
-use crate::providers::provider::Provider;
+use crate::providers::api_keys::ApiKey;
+use crate::providers::capabilities::Capabilities;
+use crate::providers::provider::{Provider, base_url};
+use crate::providers::stop_sequences::{trim_completion, trim_stream};
+use crate::providers::transport::{Auth, Transport};
+use crate::providers::upstream::UpstreamRequest;
 use crate::types::ProviderType;
 use async_trait::async_trait;
 use axum::http::StatusCode;
 use futures::StreamExt;
-use reqwest::Client;
 use reqwest_streams::JsonStreamResponse;
 use reqwest_streams::error::{StreamBodyError, StreamBodyKind};
 use serde_json::json;
 use tracing::{debug, error};
-use yup_oauth2::{ServiceAccountAuthenticator, ServiceAccountKey};
 
 const STREAM_BUFFER_SIZE: usize = 8192;
 
 pub struct VertexAIProvider {
     config: ProviderConfig,
-    http_client: Client,
+    transport: Transport,
+    tokens: ServiceAccountTokens,
     project_id: String,
     location: String,
 }
@@ -31,57 +36,258 @@ pub struct VertexAIProvider {
 impl VertexAIProvider {
     // API key → Gemini Developer API, Service account → Vertex AI
     fn uses_api_key(&self) -> bool {
-        !self.config.api_key.is_empty()
+        self.transport.api_key().is_configured()
     }
 
-    async fn get_oauth_token(&self) -> Result<String, StatusCode> {
-        debug!("Getting OAuth token for service account...");
-
-        // Special case for tests - return dummy token when in test mode
-        if self
-            .config
+    fn is_test_mode(&self) -> bool {
+        self.config
             .params
             .get("use_test_auth")
             .is_some_and(|v| v == "true")
-        {
-            debug!("Using test auth mode, returning dummy token");
-            return Ok("test-token-for-vertex-ai".to_string());
+    }
+
+    /// Builds the generateContent request without credentials, also reporting whether it
+    /// asks for structured output.
+    fn chat_request(
+        &self,
+        payload: &ChatCompletionRequest,
+    ) -> Result<(UpstreamRequest, bool), StatusCode> {
+        // Validate reasoning config if present
+        if let Some(reasoning) = &payload.reasoning {
+            tracing::debug!("🧠 VertexAI processing reasoning config: {:?}", reasoning);
+
+            if let Err(e) = reasoning.validate() {
+                tracing::error!("❌ VertexAI reasoning validation failed: {}", e);
+                return Err(StatusCode::BAD_REQUEST);
+            }
+
+            if let Some(thinking_budget) = reasoning.to_gemini_thinking_budget() {
+                tracing::info!(
+                    "✅ VertexAI reasoning enabled with thinking_budget: {} tokens",
+                    thinking_budget
+                );
+            } else {
+                tracing::debug!(
+                    "ℹ️ VertexAI reasoning config present but no valid parameters (effort: {:?}, max_tokens: {:?})",
+                    reasoning.effort,
+                    reasoning.max_tokens
+                );
+            }
+        } else {
+            tracing::debug!("ℹ️ VertexAI no reasoning config provided");
         }
 
-        let key_path = self
-            .config
-            .params
-            .get("credentials_path")
-            .map(|p| p.to_string())
-            .or_else(|| std::env::var("GOOGLE_APPLICATION_CREDENTIALS").ok())
-            .expect("Either api_key, credentials_path in config, or GOOGLE_APPLICATION_CREDENTIALS environment variable must be set");
-
-        debug!("Reading service account key from: {}", key_path);
-        let key_json =
-            std::fs::read_to_string(key_path).expect("Failed to read service account key file");
-
-        debug!(
-            "Service account key file content length: {}",
-            key_json.len()
-        );
-        let sa_key: ServiceAccountKey =
-            serde_json::from_str(&key_json).expect("Failed to parse service account key");
-
-        debug!("Successfully parsed service account key");
-        let auth = ServiceAccountAuthenticator::builder(sa_key)
-            .build()
-            .await
-            .expect("Failed to create authenticator");
-
-        debug!("Created authenticator, requesting token...");
-        let scopes = &["https://www.googleapis.com/auth/cloud-platform"];
-        let token = auth.token(scopes).await.map_err(|e| {
-            error!("Failed to get access token: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-        debug!("Successfully obtained token");
-        Ok(token.token().unwrap_or_default().to_string())
+        let endpoint_suffix = if payload.stream.unwrap_or(false) {
+            "streamGenerateContent"
+        } else {
+            "generateContent"
+        };
+
+        let request_body = GeminiChatRequest::from(payload.clone());
+        let has_structured_output = request_body
+            .generation_config
+            .as_ref()
+            .map(|config| config.response_schema.is_some())
+            .unwrap_or(false);
+
+        let endpoint = self.endpoint(&payload.model, endpoint_suffix);
+        let request = UpstreamRequest::post(endpoint, &request_body)?
+            .with_extra_body(payload.extra_body.as_ref())?;
+        Ok((request, has_structured_output))
+    }
+
+    /// Builds the embeddings request without credentials.
+    fn embeddings_request(
+        &self,
+        payload: &EmbeddingsRequest,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let request = if self.uses_api_key() && !self.is_test_mode() {
+            let endpoint = self.endpoint(&payload.model, "embedContent");
+            UpstreamRequest::post(endpoint, &gemini_embeddings_body(payload))?
+        } else {
+            let endpoint = self.endpoint(&payload.model, "predict");
+            UpstreamRequest::post(endpoint, &vertex_embeddings_body(payload))?
+        };
+        request.with_extra_body(payload.extra_body.as_ref())
+    }
+
+    /// The URL of `method` on `model`, which depends on the auth mode. A `base_url` param
+    /// replaces the scheme and host of the Google API.
+    fn endpoint(&self, model: &str, method: &str) -> String {
+        if self.is_test_mode() {
+            let test_endpoint = std::env::var("VERTEXAI_TEST_ENDPOINT")
+                .unwrap_or_else(|_| "http://localhost:8080".to_string());
+            debug!("Using test endpoint: {}", test_endpoint);
+            test_endpoint
+        } else if self.uses_api_key() {
+            // API key mode → Gemini Developer API
+            let base_url = base_url(&self.config, "https://generativelanguage.googleapis.com");
+            let endpoint = format!("{base_url}/v1beta/models/{model}:{method}");
+            tracing::debug!("🌐 Using Gemini Developer API: {}", endpoint);
+            endpoint
+        } else {
+            // Service account mode → Vertex AI
+            let default_base_url = format!("https://{}-aiplatform.googleapis.com", self.location);
+            let base_url = base_url(&self.config, &default_base_url);
+            let full_model_path = format!(
+                "projects/{}/locations/{}/publishers/google/models/{model}",
+                self.project_id, self.location
+            );
+            let endpoint = format!("{base_url}/v1/{full_model_path}:{method}");
+            tracing::debug!("🌐 Using Vertex AI: {}", endpoint);
+            endpoint
+        }
+    }
+
+    async fn authorize(&self, request: UpstreamRequest) -> Result<UpstreamRequest, StatusCode> {
+        if self.is_test_mode() {
+            Ok(request.bearer_auth("test-token-for-vertex-ai"))
+        } else if self.uses_api_key() {
+            self.transport.authorize(request)
+        } else {
+            Ok(request.bearer_auth(&self.tokens.token().await?))
+        }
+    }
+
+    /// Maps a generateContent response body back to a chat completion.
+    fn parse_chat_response(
+        &self,
+        body: &[u8],
+        model: String,
+        has_structured_output: bool,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        let response_text = String::from_utf8_lossy(body);
+        debug!("Raw VertexAI Response Body: {}", response_text);
+
+        // In test mode, we may be getting an array directly from the mock server
+        // since we saved multiple interactions in a single array
+        if self.is_test_mode() && response_text.trim().starts_with('[') {
+            debug!("Test mode detected array response, extracting first item");
+            let array: Vec<serde_json::Value> =
+                serde_json::from_str(&response_text).map_err(|e| {
+                    error!("Failed to parse test response as array: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+
+            if let Some(first_item) = array.first() {
+                // Convert the first item back to JSON string
+                let item_str = serde_json::to_string(first_item).unwrap_or_default();
+                debug!("Using first item from array: {}", item_str);
+
+                // Parse as GeminiChatResponse
+                let gemini_response: GeminiChatResponse =
+                    serde_json::from_str(&item_str).map_err(|e| {
+                        error!("Failed to parse test item as GeminiChatResponse: {}", e);
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    })?;
+
+                return Ok(ChatCompletionResponse::NonStream(
+                    gemini_response.to_openai_with_structured_output(model, has_structured_output),
+                ));
+            }
+        }
+
+        // Regular parsing for normal API responses
+        let gemini_response: GeminiChatResponse =
+            serde_json::from_str(&response_text).map_err(|e| {
+                error!(
+                    "Failed to parse response as GeminiChatResponse. Error: {}, Raw Response: {}",
+                    e, response_text
+                );
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        Ok(ChatCompletionResponse::NonStream(
+            gemini_response.to_openai_with_structured_output(model, has_structured_output),
+        ))
+    }
+
+    /// Maps an embeddings response body back to OpenAI's format.
+    fn parse_embeddings_response(
+        &self,
+        body: &[u8],
+        model: String,
+    ) -> Result<EmbeddingsResponse, StatusCode> {
+        let response_text = String::from_utf8_lossy(body);
+        debug!("Embeddings response body: {}", response_text);
+
+        // In test mode, we may be getting an array directly from the mock server
+        // since we saved multiple interactions in a single array
+        if self.is_test_mode() && response_text.trim().starts_with('[') {
+            debug!("Test mode detected array response for embeddings, extracting first item");
+            let array: Vec<serde_json::Value> =
+                serde_json::from_str(&response_text).map_err(|e| {
+                    error!("Failed to parse test response as array: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+
+            if let Some(first_item) = array.first() {
+                // Use the first item from the array as the response
+                return Ok(EmbeddingsResponse {
+                    object: "list".to_string(),
+                    data: first_item["data"]
+                        .as_array()
+                        .unwrap_or(&vec![])
+                        .iter()
+                        .enumerate()
+                        .map(|(i, emb)| Embeddings {
+                            object: "embedding".to_string(),
+                            embedding: Embedding::Float(
+                                emb["embedding"]
+                                    .as_array()
+                                    .unwrap_or(&vec![])
+                                    .iter()
+                                    .filter_map(|v| v.as_f64().map(|f| f as f32))
+                                    .collect::<Vec<f32>>(),
+                            ),
+                            index: i,
+                        })
+                        .collect(),
+                    model,
+                    usage: EmbeddingUsage {
+                        prompt_tokens: Some(0),
+                        total_tokens: Some(0),
+                    },
+                });
+            }
+        }
+
+        // Normal processing for regular API responses
+        let gemini_response: serde_json::Value =
+            serde_json::from_str(&response_text).map_err(|e| {
+                error!("Failed to parse response as JSON: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        // Extract embeddings from updated response format
+        let embeddings = gemini_response["predictions"]
+            .as_array()
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+            .iter()
+            .enumerate()
+            .map(|(i, pred)| Embeddings {
+                object: "embedding".to_string(),
+                embedding: Embedding::Float(
+                    pred["embeddings"]["values"]
+                        .as_array()
+                        .unwrap_or(&vec![])
+                        .iter()
+                        .filter_map(|v| v.as_f64().map(|f| f as f32))
+                        .collect::<Vec<f32>>(),
+                ),
+                index: i,
+            })
+            .collect();
+
+        Ok(EmbeddingsResponse {
+            object: "list".to_string(),
+            data: embeddings,
+            model,
+            usage: EmbeddingUsage {
+                prompt_tokens: Some(0),
+                total_tokens: Some(0),
+            },
+        })
     }
 
     pub fn validate_location(location: &str) -> Result<String, String> {
@@ -103,7 +309,7 @@ impl VertexAIProvider {
 #[async_trait]
 impl Provider for VertexAIProvider {
     fn new(config: &ProviderConfig) -> Self {
-        let has_api_key = !config.api_key.is_empty();
+        let has_api_key = ApiKey::from_config(config).is_configured();
 
         let project_id = config.params.get("project_id").cloned().unwrap_or_default();
         let location_str = config.params.get("location").cloned().unwrap_or_default();
@@ -122,7 +328,9 @@ impl Provider for VertexAIProvider {
 
         Self {
             config: config.clone(),
-            http_client: Client::new(),
+            transport: Transport::new(config, "VertexAI", Auth::Header("x-goog-api-key"))
+                .expect("Invalid HTTP client configuration for provider"),
+            tokens: ServiceAccountTokens::from_params(&config.key, &config.params),
             project_id,
             location,
         }
@@ -136,6 +344,31 @@ impl Provider for VertexAIProvider {
         ProviderType::VertexAI
     }
 
+    fn unhealthy_reason(&self) -> Option<String> {
+        if self.uses_api_key() {
+            self.transport.unhealthy_reason()
+        } else {
+            self.tokens.unhealthy_reason()
+        }
+    }
+
+    fn capabilities(&self, _model_config: &ModelConfig) -> Capabilities {
+        Capabilities {
+            supports_streaming: true,
+            supports_tools: true,
+            supports_vision: false,
+            supports_completions: false,
+            supports_embeddings: true,
+            supports_n: false,
+            supports_logprobs: true,
+            supports_penalties: false,
+            supports_logit_bias: false,
+            supports_prediction: false,
+            supports_builtin_tools: false,
+            max_stop_sequences: Some(5),
+        }
+    }
+
     async fn chat_completions(
         &self,
         payload: ChatCompletionRequest,
@@ -146,188 +379,47 @@ impl Provider for VertexAIProvider {
             payload.model
         );
 
-        // Validate reasoning config if present
-        if let Some(reasoning) = &payload.reasoning {
-            tracing::debug!("🧠 VertexAI processing reasoning config: {:?}", reasoning);
-
-            if let Err(e) = reasoning.validate() {
-                tracing::error!("❌ VertexAI reasoning validation failed: {}", e);
-                return Err(StatusCode::BAD_REQUEST);
-            }
-
-            if let Some(thinking_budget) = reasoning.to_gemini_thinking_budget() {
-                tracing::info!(
-                    "✅ VertexAI reasoning enabled with thinking_budget: {} tokens",
-                    thinking_budget
-                );
-            } else {
-                tracing::debug!(
-                    "ℹ️ VertexAI reasoning config present but no valid parameters (effort: {:?}, max_tokens: {:?})",
-                    reasoning.effort,
-                    reasoning.max_tokens
-                );
-            }
-        } else {
-            tracing::debug!("ℹ️ VertexAI no reasoning config provided");
-        }
-
-        let endpoint_suffix = if payload.stream.unwrap_or(false) {
-            "streamGenerateContent"
-        } else {
-            "generateContent"
-        };
-
-        // Determine if we're in test mode
-        let is_test_mode = self
-            .config
-            .params
-            .get("use_test_auth")
-            .map_or(false, |v| v == "true");
-
-        let request_body = GeminiChatRequest::from(payload.clone());
-        let has_structured_output = request_body
-            .generation_config
-            .as_ref()
-            .map(|config| config.response_schema.is_some())
-            .unwrap_or(false);
-
-        // Build endpoint and request based on auth mode
-        let response_result = if is_test_mode {
-            let test_endpoint = std::env::var("VERTEXAI_TEST_ENDPOINT")
-                .unwrap_or_else(|_| "http://localhost:8080".to_string());
-            debug!("Using test endpoint: {}", test_endpoint);
-            self.http_client
-                .post(&test_endpoint)
-                .bearer_auth("test-token-for-vertex-ai")
-                .json(&request_body)
-                .send()
-                .await
-        } else if self.uses_api_key() {
-            // API key mode → Gemini Developer API
-            let endpoint = format!(
-                "https://generativelanguage.googleapis.com/v1beta/models/{}:{}",
-                payload.model, endpoint_suffix
-            );
-            tracing::debug!("🌐 Using Gemini Developer API: {}", endpoint);
-            self.http_client
-                .post(&endpoint)
-                .header("x-goog-api-key", &self.config.api_key)
-                .json(&request_body)
-                .send()
-                .await
-        } else {
-            // Service account mode → Vertex AI
-            let auth_token = self.get_oauth_token().await?;
-            let service_endpoint = format!("{}-aiplatform.googleapis.com", self.location);
-            let full_model_path = format!(
-                "projects/{}/locations/{}/publishers/google/models/{}",
-                self.project_id, self.location, payload.model
-            );
-            let endpoint =
-                format!("https://{service_endpoint}/v1/{full_model_path}:{endpoint_suffix}");
-            tracing::debug!("🌐 Using Vertex AI: {}", endpoint);
-            self.http_client
-                .post(&endpoint)
-                .bearer_auth(auth_token)
-                .json(&request_body)
-                .send()
-                .await
-        };
-
-        let response = match response_result {
-            Ok(resp) => resp,
-            Err(e) => {
-                error!("VertexAI API request failed before getting response: {}", e);
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
-            }
-        };
-
-        let status = response.status();
-        debug!("Response status: {}", status);
-
-        if status.is_success() {
-            if payload.stream.unwrap_or(false) {
-                let model = payload.model.clone();
-                let stream = response
-                    .json_array_stream::<VertexAIStreamChunk>(STREAM_BUFFER_SIZE)
-                    .map(move |result| {
-                        result
-                            .map(|chunk| {
-                                let mut completion_chunk: ChatCompletionChunk = chunk.into();
-                                completion_chunk.model = model.clone();
-                                completion_chunk
-                            })
-                            .map_err(|e| {
-                                StreamBodyError::new(
-                                    StreamBodyKind::CodecError,
-                                    Some(Box::new(e)),
-                                    None,
-                                )
-                            })
-                    });
-
-                Ok(ChatCompletionResponse::Stream(Box::pin(stream)))
-            } else {
-                let response_text = response.text().await.map_err(|e| {
-                    error!("Failed to get response text: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?;
-                debug!("Raw VertexAI Response Body: {}", response_text);
-
-                // In test mode, we may be getting an array directly from the mock server
-                // since we saved multiple interactions in a single array
-                if is_test_mode && response_text.trim().starts_with('[') {
-                    debug!("Test mode detected array response, extracting first item");
-                    let array: Vec<serde_json::Value> = serde_json::from_str(&response_text)
+        let (request, has_structured_output) = self.chat_request(&payload)?;
+        let request = self.authorize(request).await?;
+        if payload.stream.unwrap_or(false) {
+            let response = self
+                .transport
+                .send(request, "vertexai.chat_completions")
+                .await?;
+            let model = payload.model.clone();
+            let stream = response
+                .json_array_stream::<VertexAIStreamChunk>(STREAM_BUFFER_SIZE)
+                .map(move |result| {
+                    result
+                        .map(|chunk| {
+                            let mut completion_chunk: ChatCompletionChunk = chunk.into();
+                            completion_chunk.model = model.clone();
+                            completion_chunk
+                        })
                         .map_err(|e| {
-                            error!("Failed to parse test response as array: {}", e);
-                            StatusCode::INTERNAL_SERVER_ERROR
-                        })?;
-
-                    if let Some(first_item) = array.first() {
-                        // Convert the first item back to JSON string
-                        let item_str = serde_json::to_string(first_item).unwrap_or_default();
-                        debug!("Using first item from array: {}", item_str);
-
-                        // Parse as GeminiChatResponse
-                        let gemini_response: GeminiChatResponse = serde_json::from_str(&item_str)
-                            .map_err(|e| {
-                            error!("Failed to parse test item as GeminiChatResponse: {}", e);
-                            StatusCode::INTERNAL_SERVER_ERROR
-                        })?;
-
-                        return Ok(ChatCompletionResponse::NonStream(
-                            gemini_response.to_openai_with_structured_output(
-                                payload.model,
-                                has_structured_output,
-                            ),
-                        ));
-                    }
-                }
-
-                // Regular parsing for normal API responses
-                let gemini_response: GeminiChatResponse = serde_json::from_str(&response_text)
-                    .map_err(|e| {
-                        error!(
-                            "Failed to parse response as GeminiChatResponse. Error: {}, Raw Response: {}",
-                            e,
-                            response_text
-                        );
-                        StatusCode::INTERNAL_SERVER_ERROR
-                    })?;
-
-                Ok(ChatCompletionResponse::NonStream(
-                    gemini_response
-                        .to_openai_with_structured_output(payload.model, has_structured_output),
-                ))
-            }
+                            StreamBodyError::new(
+                                StreamBodyKind::CodecError,
+                                Some(Box::new(e)),
+                                None,
+                            )
+                        })
+                });
+
+            Ok(ChatCompletionResponse::Stream(trim_stream(
+                Box::pin(stream),
+                payload.stop.as_deref(),
+            )))
         } else {
-            let error_text = response.text().await.unwrap_or_default();
-            error!(
-                "VertexAI API request failed with status {}. Error body: {}",
-                status, error_text
-            );
-            Err(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+            let body = self
+                .transport
+                .send_for_body(request, "vertexai.chat_completions")
+                .await?;
+            let mut response =
+                self.parse_chat_response(&body, payload.model, has_structured_output)?;
+            if let ChatCompletionResponse::NonStream(completion) = &mut response {
+                trim_completion(completion, payload.stop.as_deref());
+            }
+            Ok(response)
         }
     }
 
@@ -344,194 +436,89 @@ impl Provider for VertexAIProvider {
     async fn embeddings(
         &self,
         payload: EmbeddingsRequest,
-        _model_config: &ModelConfig,
+        model_config: &ModelConfig,
     ) -> Result<EmbeddingsResponse, StatusCode> {
-        // Determine if we're in test mode
-        let is_test_mode = self
-            .config
-            .params
-            .get("use_test_auth")
-            .map_or(false, |v| v == "true");
-
-        // Vertex AI format: {"instances": [{"content": "..."}], "parameters": {...}}
-        let vertex_request_body = json!({
-            "instances": match &payload.input {
-                EmbeddingsInput::Single(text) => vec![json!({"content": text})],
-                EmbeddingsInput::Multiple(texts) => texts.iter()
-                    .map(|text| json!({"content": text}))
-                    .collect::<Vec<_>>(),
-                EmbeddingsInput::SingleTokenIds(tokens) => vec![json!({"content": tokens.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(" ")})],
-                EmbeddingsInput::MultipleTokenIds(token_arrays) => token_arrays.iter()
-                    .map(|tokens| json!({"content": tokens.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(" ")}))
-                    .collect::<Vec<_>>(),
-            },
-            "parameters": {
-                "autoTruncate": true
-            }
-        });
-
-        // Gemini API format: {"content": {"parts": [{"text": "..."}]}}
-        let text_for_gemini = match &payload.input {
-            EmbeddingsInput::Single(text) => text.clone(),
-            EmbeddingsInput::Multiple(texts) => texts.first().cloned().unwrap_or_default(),
-            EmbeddingsInput::SingleTokenIds(tokens) => tokens
-                .iter()
-                .map(|t| t.to_string())
-                .collect::<Vec<_>>()
-                .join(" "),
-            EmbeddingsInput::MultipleTokenIds(token_arrays) => token_arrays
-                .first()
-                .map(|tokens| {
-                    tokens
-                        .iter()
-                        .map(|t| t.to_string())
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                })
-                .unwrap_or_default(),
-        };
-        let gemini_request_body = json!({
-            "content": {
-                "parts": [{"text": text_for_gemini}]
-            }
-        });
-
-        let response = if is_test_mode {
-            let test_endpoint = std::env::var("VERTEXAI_TEST_ENDPOINT")
-                .unwrap_or_else(|_| "http://localhost:8080".to_string());
-            debug!("Using test endpoint for embeddings: {}", test_endpoint);
-            self.http_client
-                .post(&test_endpoint)
-                .bearer_auth("test-token-for-vertex-ai")
-                .json(&vertex_request_body)
-                .send()
-                .await
-        } else if self.uses_api_key() {
-            // API key mode → Gemini Developer API
-            let endpoint = format!(
-                "https://generativelanguage.googleapis.com/v1beta/models/{}:embedContent",
-                payload.model
-            );
-            debug!("Using Gemini Developer API for embeddings: {}", endpoint);
-            self.http_client
-                .post(&endpoint)
-                .header("x-goog-api-key", &self.config.api_key)
-                .json(&gemini_request_body)
-                .send()
-                .await
-        } else {
-            // Service account mode → Vertex AI
-            let auth_token = self.get_oauth_token().await?;
-            let endpoint = format!(
-                "https://{}-aiplatform.googleapis.com/v1/projects/{}/locations/{}/publishers/google/models/{}:predict",
-                self.location, self.project_id, self.location, payload.model
-            );
-            debug!("Using Vertex AI for embeddings: {}", endpoint);
-            self.http_client
-                .post(&endpoint)
-                .bearer_auth(auth_token)
-                .json(&vertex_request_body)
-                .send()
-                .await
-        }
-        .map_err(|e| {
-            error!("VertexAI API request error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-        let status = response.status();
-        debug!("Embeddings response status: {}", status);
+        let request = self
+            .build_embeddings_request(&payload, model_config)
+            .await?;
+        let body = self
+            .transport
+            .send_for_body(request, "vertexai.embeddings")
+            .await?;
+        self.parse_embeddings_response(&body, payload.model)
+    }
 
-        if status.is_success() {
-            let response_text = response.text().await.map_err(|e| {
-                error!("Failed to get response text: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-            debug!("Embeddings response body: {}", response_text);
-
-            // In test mode, we may be getting an array directly from the mock server
-            // since we saved multiple interactions in a single array
-            if is_test_mode && response_text.trim().starts_with('[') {
-                debug!("Test mode detected array response for embeddings, extracting first item");
-                let array: Vec<serde_json::Value> =
-                    serde_json::from_str(&response_text).map_err(|e| {
-                        error!("Failed to parse test response as array: {}", e);
-                        StatusCode::INTERNAL_SERVER_ERROR
-                    })?;
+    async fn build_chat_request(
+        &self,
+        payload: &ChatCompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        let (request, _) = self.chat_request(payload)?;
+        self.authorize(request).await
+    }
 
-                if let Some(first_item) = array.first() {
-                    // Use the first item from the array as the response
-                    return Ok(EmbeddingsResponse {
-                        object: "list".to_string(),
-                        data: first_item["data"]
-                            .as_array()
-                            .unwrap_or(&vec![])
-                            .iter()
-                            .enumerate()
-                            .map(|(i, emb)| Embeddings {
-                                object: "embedding".to_string(),
-                                embedding: Embedding::Float(
-                                    emb["embedding"]
-                                        .as_array()
-                                        .unwrap_or(&vec![])
-                                        .iter()
-                                        .filter_map(|v| v.as_f64().map(|f| f as f32))
-                                        .collect::<Vec<f32>>(),
-                                ),
-                                index: i,
-                            })
-                            .collect(),
-                        model: payload.model,
-                        usage: EmbeddingUsage {
-                            prompt_tokens: Some(0),
-                            total_tokens: Some(0),
-                        },
-                    });
-                }
-            }
+    async fn build_embeddings_request(
+        &self,
+        payload: &EmbeddingsRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<UpstreamRequest, StatusCode> {
+        self.authorize(self.embeddings_request(payload)?).await
+    }
+}
 
-            // Normal processing for regular API responses
-            let gemini_response: serde_json::Value =
-                serde_json::from_str(&response_text).map_err(|e| {
-                    error!("Failed to parse response as JSON: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?;
+/// Vertex AI `:predict` embeddings body: {"instances": [{"content": "..."}], "parameters": {...}}
+pub(crate) fn vertex_embeddings_body(payload: &EmbeddingsRequest) -> serde_json::Value {
+    let mut body = json!({
+        "instances": match &payload.input {
+            EmbeddingsInput::Single(text) => vec![json!({"content": text})],
+            EmbeddingsInput::Multiple(texts) => texts.iter()
+                .map(|text| json!({"content": text}))
+                .collect::<Vec<_>>(),
+            EmbeddingsInput::SingleTokenIds(tokens) => vec![json!({"content": tokens.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(" ")})],
+            EmbeddingsInput::MultipleTokenIds(token_arrays) => token_arrays.iter()
+                .map(|tokens| json!({"content": tokens.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(" ")}))
+                .collect::<Vec<_>>(),
+        },
+        "parameters": {
+            "autoTruncate": true
+        }
+    });
+    if let Some(dimensions) = payload.dimensions {
+        body["parameters"]["outputDimensionality"] = json!(dimensions);
+    }
+    body
+}
 
-            // Extract embeddings from updated response format
-            let embeddings = gemini_response["predictions"]
-                .as_array()
-                .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
-                .iter()
-                .enumerate()
-                .map(|(i, pred)| Embeddings {
-                    object: "embedding".to_string(),
-                    embedding: Embedding::Float(
-                        pred["embeddings"]["values"]
-                            .as_array()
-                            .unwrap_or(&vec![])
-                            .iter()
-                            .filter_map(|v| v.as_f64().map(|f| f as f32))
-                            .collect::<Vec<f32>>(),
-                    ),
-                    index: i,
-                })
-                .collect();
-
-            Ok(EmbeddingsResponse {
-                object: "list".to_string(),
-                data: embeddings,
-                model: payload.model,
-                usage: EmbeddingUsage {
-                    prompt_tokens: Some(0),
-                    total_tokens: Some(0),
-                },
+/// Gemini API `:embedContent` body: {"content": {"parts": [{"text": "..."}]}}
+pub(crate) fn gemini_embeddings_body(payload: &EmbeddingsRequest) -> serde_json::Value {
+    let text_for_gemini = match &payload.input {
+        EmbeddingsInput::Single(text) => text.clone(),
+        EmbeddingsInput::Multiple(texts) => texts.first().cloned().unwrap_or_default(),
+        EmbeddingsInput::SingleTokenIds(tokens) => tokens
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+        EmbeddingsInput::MultipleTokenIds(token_arrays) => token_arrays
+            .first()
+            .map(|tokens| {
+                tokens
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
             })
-        } else {
-            let error_text = response.text().await.unwrap_or_default();
-            error!("VertexAI API request error: {}", error_text);
-            Err(StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+            .unwrap_or_default(),
+    };
+    let mut body = json!({
+        "content": {
+            "parts": [{"text": text_for_gemini}]
         }
+    });
+    if let Some(dimensions) = payload.dimensions {
+        body["outputDimensionality"] = json!(dimensions);
     }
+    body
 }
 
 #[cfg(test)]
@@ -554,7 +541,13 @@ impl VertexAIProvider {
 
         Self {
             config: config.clone(),
-            http_client: client,
+            transport: Transport::with_client(
+                config,
+                "VertexAI",
+                Auth::Header("x-goog-api-key"),
+                client,
+            ),
+            tokens: ServiceAccountTokens::from_params(&config.key, &config.params),
             project_id,
             location,
         }
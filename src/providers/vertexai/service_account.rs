@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, anyhow};
+use axum::http::StatusCode;
+use tracing::debug;
+use yup_oauth2::ServiceAccountAuthenticator;
+
+use crate::logging::error_rate_limited;
+
+/// Provider param pointing at the service account key file. Defaults to
+/// `GOOGLE_APPLICATION_CREDENTIALS`.
+pub const CREDENTIALS_PATH_PARAM: &str = "credentials_path";
+
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+/// Lifetime assumed when the token endpoint doesn't say. Google's tokens last an hour.
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+/// Tokens are refreshed this long before they expire, or halfway through shorter lifetimes.
+const MAX_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+/// After a failed token request, refreshes are retried after this long, doubling with
+/// each further failure up to `MAX_FAILURE_BACKOFF`.
+const MIN_FAILURE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_FAILURE_BACKOFF: Duration = Duration::from_secs(60);
+
+struct CachedToken {
+    access_token: String,
+    refresh_at: Instant,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct TokenState {
+    token: Option<CachedToken>,
+    last_error: Option<String>,
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+/// Fetches and caches OAuth access tokens for a Vertex AI service account, so requests
+/// don't each mint a token. One request refreshes the token ahead of its expiry while
+/// the others keep using the current one.
+pub struct ServiceAccountTokens {
+    provider_key: String,
+    credentials_path: Option<String>,
+    state: Mutex<TokenState>,
+    refresh_lock: tokio::sync::Mutex<()>,
+}
+
+impl ServiceAccountTokens {
+    pub fn from_params(provider_key: &str, params: &HashMap<String, String>) -> Self {
+        Self {
+            provider_key: provider_key.to_string(),
+            credentials_path: params.get(CREDENTIALS_PATH_PARAM).cloned(),
+            state: Mutex::new(TokenState::default()),
+            refresh_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Returns a cached token, fetching a new one when it is due for refresh. If the
+    /// refresh fails, a token that hasn't expired yet is still returned.
+    pub async fn token(&self) -> Result<String, StatusCode> {
+        if let Some(token) = self.cached_token(|t| t.refresh_at) {
+            return Ok(token);
+        }
+        let _refresh = match self.refresh_lock.try_lock() {
+            Ok(refresh) => refresh,
+            // Another request is refreshing. The current token is fine until it expires.
+            Err(_) => match self.cached_token(|t| t.expires_at) {
+                Some(token) => return Ok(token),
+                None => self.refresh_lock.lock().await,
+            },
+        };
+        // Another request may have refreshed the token while this one waited.
+        if let Some(token) = self.cached_token(|t| t.refresh_at) {
+            return Ok(token);
+        }
+        let backing_off = self
+            .state
+            .lock()
+            .unwrap()
+            .retry_at
+            .is_some_and(|retry_at| Instant::now() < retry_at);
+        if backing_off {
+            return self.unexpired_token();
+        }
+
+        match self.fetch_token().await {
+            Ok(token) => {
+                let access_token = token.access_token.clone();
+                *self.state.lock().unwrap() = TokenState {
+                    token: Some(token),
+                    ..Default::default()
+                };
+                Ok(access_token)
+            }
+            Err(e) => {
+                error_rate_limited(
+                    "vertexai.service_account.token",
+                    format!(
+                        "Service account token request for provider '{}' failed: {e:#}",
+                        self.provider_key
+                    ),
+                );
+                {
+                    let mut state = self.state.lock().unwrap();
+                    let backoff = MIN_FAILURE_BACKOFF
+                        .saturating_mul(1 << state.failures.min(6))
+                        .min(MAX_FAILURE_BACKOFF);
+                    state.last_error = Some(format!("{e:#}"));
+                    state.failures += 1;
+                    state.retry_at = Some(Instant::now() + backoff);
+                }
+                self.unexpired_token()
+            }
+        }
+    }
+
+    /// Why no token is available, if the last token request failed and no unexpired
+    /// token is cached.
+    pub fn unhealthy_reason(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        let error = state.last_error.as_ref()?;
+        let has_valid_token = state
+            .token
+            .as_ref()
+            .is_some_and(|t| Instant::now() < t.expires_at);
+        (!has_valid_token).then(|| format!("Service account token request failed: {error}"))
+    }
+
+    fn cached_token(&self, valid_until: impl Fn(&CachedToken) -> Instant) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state
+            .token
+            .as_ref()
+            .filter(|t| Instant::now() < valid_until(t))
+            .map(|t| t.access_token.clone())
+    }
+
+    fn unexpired_token(&self) -> Result<String, StatusCode> {
+        self.cached_token(|t| t.expires_at)
+            .ok_or(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    async fn fetch_token(&self) -> Result<CachedToken> {
+        let key_path = self
+            .credentials_path
+            .clone()
+            .or_else(|| std::env::var("GOOGLE_APPLICATION_CREDENTIALS").ok())
+            .ok_or_else(|| {
+                anyhow!(
+                    "either api_key, credentials_path or GOOGLE_APPLICATION_CREDENTIALS must be set"
+                )
+            })?;
+        // Read on every refresh, so a rotated key file is picked up.
+        let key = yup_oauth2::read_service_account_key(&key_path)
+            .await
+            .with_context(|| format!("failed to read service account key '{key_path}'"))?;
+        let auth = ServiceAccountAuthenticator::builder(key)
+            .build()
+            .await
+            .context("failed to create service account authenticator")?;
+
+        let requested_at = Instant::now();
+        let token = auth.token(&[CLOUD_PLATFORM_SCOPE]).await?;
+        let access_token = token
+            .token()
+            .ok_or_else(|| anyhow!("token endpoint returned no access token"))?
+            .to_string();
+        let lifetime = token
+            .expiration_time()
+            .map(|expires| {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs() as i64;
+                Duration::from_secs((expires.unix_timestamp() - now).max(0) as u64)
+            })
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
+        debug!(
+            provider = %self.provider_key,
+            expires_in = lifetime.as_secs(),
+            "Fetched service account token"
+        );
+        Ok(CachedToken {
+            access_token,
+            refresh_at: requested_at + lifetime - MAX_REFRESH_MARGIN.min(lifetime / 2),
+            expires_at: requested_at + lifetime,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::models::{ModelConfig, Provider as ProviderConfig};
+    use crate::models::chat::ChatCompletionRequest;
+    use crate::providers::provider::Provider;
+    use crate::providers::vertexai::VertexAIProvider;
+    use crate::types::ProviderType;
+    use futures::future::join_all;
+    use serde_json::{Value, json};
+    use tempfile::NamedTempFile;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// A service account key whose token endpoint is `server`.
+    fn key_file(server: &MockServer) -> NamedTempFile {
+        let mut key: Value = serde_json::from_str(include_str!(
+            "../../../tests/fixtures/vertexai_service_account.json"
+        ))
+        .unwrap();
+        key["token_uri"] = json!(format!("{}/token", server.uri()));
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), key.to_string()).unwrap();
+        file
+    }
+
+    fn token_response(token: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": token,
+            "expires_in": 3600,
+            "token_type": "Bearer",
+        }))
+    }
+
+    fn tokens(key: &NamedTempFile) -> ServiceAccountTokens {
+        let params = HashMap::from([(
+            CREDENTIALS_PATH_PARAM.to_string(),
+            key.path().to_string_lossy().to_string(),
+        )]);
+        ServiceAccountTokens::from_params("vertexai", &params)
+    }
+
+    fn due_for_refresh(tokens: &ServiceAccountTokens) {
+        tokens
+            .state
+            .lock()
+            .unwrap()
+            .token
+            .as_mut()
+            .unwrap()
+            .refresh_at = Instant::now();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_chat_requests_share_one_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("jwt-bearer"))
+            // Slow enough that every request arrives while the first token is in flight.
+            .respond_with(token_response("ya29.cached").set_delay(Duration::from_millis(200)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let key = key_file(&server);
+        let config = ProviderConfig {
+            key: "vertexai".to_string(),
+            r#type: ProviderType::VertexAI,
+            api_key: "".to_string(),
+            maintenance_windows: vec![],
+            params: HashMap::from([
+                ("project_id".to_string(), "test-project".to_string()),
+                ("location".to_string(), "us-central1".to_string()),
+                (
+                    CREDENTIALS_PATH_PARAM.to_string(),
+                    key.path().to_string_lossy().to_string(),
+                ),
+            ]),
+        };
+        let provider = VertexAIProvider::new(&config);
+        let model_config = ModelConfig {
+            key: "gemini".to_string(),
+            r#type: "gemini-1.5-flash".to_string(),
+            provider: "vertexai".to_string(),
+            params: HashMap::new(),
+            enabled: true,
+            deprecation: Default::default(),
+        };
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gemini-1.5-flash",
+            "messages": [{"role": "user", "content": "hello"}]
+        }))
+        .unwrap();
+
+        let built =
+            join_all((0..100).map(|_| provider.build_chat_request(&request, &model_config))).await;
+        for request in built {
+            assert_eq!(
+                request.unwrap().headers["authorization"],
+                "Bearer ya29.cached"
+            );
+        }
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_token_is_refreshed_ahead_of_expiry() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(token_response("first"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(token_response("second"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let key = key_file(&server);
+        let tokens = tokens(&key);
+
+        assert_eq!(tokens.token().await.unwrap(), "first");
+        {
+            let state = tokens.state.lock().unwrap();
+            let token = state.token.as_ref().unwrap();
+            assert_eq!(token.expires_at - token.refresh_at, MAX_REFRESH_MARGIN);
+        }
+        assert_eq!(tokens.token().await.unwrap(), "first");
+
+        due_for_refresh(&tokens);
+        assert_eq!(tokens.token().await.unwrap(), "second");
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_keeps_token_and_backs_off() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(token_response("still-valid"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+        let key = key_file(&server);
+        let tokens = tokens(&key);
+        assert_eq!(tokens.token().await.unwrap(), "still-valid");
+
+        due_for_refresh(&tokens);
+        assert_eq!(tokens.token().await.unwrap(), "still-valid");
+        assert!(tokens.unhealthy_reason().is_none());
+        let first_retry_at = tokens.state.lock().unwrap().retry_at.unwrap();
+        // Within the backoff the token endpoint isn't called again.
+        assert_eq!(tokens.token().await.unwrap(), "still-valid");
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        tokens.state.lock().unwrap().retry_at = None;
+        assert_eq!(tokens.token().await.unwrap(), "still-valid");
+        let second_retry_at = tokens.state.lock().unwrap().retry_at.unwrap();
+        assert!(second_retry_at - first_retry_at >= MIN_FAILURE_BACKOFF);
+
+        let mut state = tokens.state.lock().unwrap();
+        state.token.as_mut().unwrap().expires_at = Instant::now();
+        state.retry_at = None;
+        drop(state);
+        assert_eq!(tokens.token().await, Err(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(tokens.unhealthy_reason().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_missing_credentials_fail_with_service_unavailable() {
+        let params = HashMap::from([(
+            CREDENTIALS_PATH_PARAM.to_string(),
+            "/nonexistent/key.json".to_string(),
+        )]);
+        let tokens = ServiceAccountTokens::from_params("vertexai", &params);
+        assert_eq!(tokens.token().await, Err(StatusCode::SERVICE_UNAVAILABLE));
+        let reason = tokens.unhealthy_reason().unwrap();
+        assert!(reason.contains("/nonexistent/key.json"), "{reason}");
+    }
+}
@@ -1,3 +1,4 @@
+use axum::http::StatusCode;
 use serde_json::Value;
 use serde_json::json;
 use std::collections::HashMap;
@@ -16,7 +17,11 @@ use crate::models::embeddings::{EmbeddingsInput, EmbeddingsRequest};
 use crate::models::response_format::{JsonSchema, ResponseFormat};
 use crate::models::tool_choice::SimpleToolChoice;
 use crate::models::tool_choice::ToolChoice;
-use crate::models::tool_definition::{FunctionDefinition, ToolDefinition};
+use crate::models::tool_definition::{FunctionDefinition, FunctionTool, ToolDefinition};
+use crate::providers::contract_tests::{
+    COMPLETION_TOKENS, ContractTemplates, MODEL, PROMPT_TOKENS, STOP_SEQUENCE, TOOL_NAME,
+    provider_contract_tests,
+};
 use crate::providers::provider::Provider;
 use crate::providers::vertexai::models::ContentPart;
 use crate::providers::vertexai::models::GeminiCandidate;
@@ -26,6 +31,7 @@ use crate::providers::vertexai::models::GeminiContent;
 use crate::providers::vertexai::models::GeminiFunctionCall;
 use crate::providers::vertexai::models::GeminiToolChoice;
 use crate::providers::vertexai::models::UsageMetadata;
+use crate::types::ProviderType;
 
 // Test constants
 const TEST_PROJECT_ID: &str = "heavenya";
@@ -154,6 +160,7 @@ fn create_test_provider(client: reqwest::Client) -> VertexAIProvider {
             key: "vertexai".to_string(),
             r#type: crate::types::ProviderType::VertexAI,
             api_key: "".to_string(), // Empty API key to force service account auth
+            maintenance_windows: vec![],
             params,
         },
         client,
@@ -172,6 +179,7 @@ fn create_test_provider_with_api_key(client: reqwest::Client, api_key: String) -
             key: "vertexai".to_string(),
             r#type: crate::types::ProviderType::VertexAI,
             api_key,
+            maintenance_windows: vec![],
             params,
         },
         client,
@@ -265,6 +273,7 @@ async fn test_chat_completions() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(0.7),
         top_p: Some(0.9),
@@ -285,6 +294,12 @@ async fn test_chat_completions() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let model_config = ModelConfig {
@@ -292,6 +307,8 @@ async fn test_chat_completions() {
         r#type: "gemini-2.0-flash-exp".to_string(),
         provider: "vertexai".to_string(),
         params: HashMap::new(),
+        enabled: true,
+        deprecation: Default::default(),
     };
 
     let result = run_test_with_quota_retry(|| async {
@@ -382,6 +399,8 @@ async fn test_embeddings() {
         input: EmbeddingsInput::Single("This is a test sentence.".to_string()),
         user: None,
         encoding_format: None,
+        dimensions: None,
+        extra_body: None,
     };
 
     let model_config = ModelConfig {
@@ -389,6 +408,8 @@ async fn test_embeddings() {
         r#type: "text-embedding-005".to_string(),
         provider: "vertexai".to_string(),
         params: HashMap::new(),
+        enabled: true,
+        deprecation: Default::default(),
     };
 
     let result = run_test_with_quota_retry(|| async {
@@ -495,6 +516,7 @@ async fn test_chat_completions_with_tools() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(0.7),
         top_p: Some(0.9),
@@ -506,7 +528,7 @@ async fn test_chat_completions_with_tools() {
         frequency_penalty: None,
         logit_bias: None,
         user: None,
-        tools: Some(vec![ToolDefinition {
+        tools: Some(vec![ToolDefinition::Function(FunctionTool {
             tool_type: "function".to_string(),
             function: FunctionDefinition {
                 name: "get_weather".to_string(),
@@ -526,7 +548,7 @@ async fn test_chat_completions_with_tools() {
                 ),
                 strict: None,
             },
-        }]),
+        })]),
         tool_choice: None,
         parallel_tool_calls: None,
         max_completion_tokens: None,
@@ -535,6 +557,12 @@ async fn test_chat_completions_with_tools() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let model_config = ModelConfig {
@@ -542,6 +570,8 @@ async fn test_chat_completions_with_tools() {
         r#type: "gemini-2.0-flash-exp".to_string(),
         provider: "vertexai".to_string(),
         params: HashMap::new(),
+        enabled: true,
+        deprecation: Default::default(),
     };
 
     let result = run_test_with_quota_retry(|| async {
@@ -617,6 +647,7 @@ async fn test_completions() {
         presence_penalty: None,
         frequency_penalty: None,
         best_of: None,
+        extra_body: None,
         logit_bias: None,
         user: None,
     };
@@ -626,6 +657,8 @@ async fn test_completions() {
         r#type: "gemini-2.0-flash-exp".to_string(),
         provider: "vertexai".to_string(),
         params: HashMap::new(),
+        enabled: true,
+        deprecation: Default::default(),
     };
 
     let result = run_test_with_quota_retry(|| async {
@@ -658,6 +691,7 @@ async fn test_chat_completions_with_api_key() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(0.7),
         top_p: Some(0.9),
@@ -678,6 +712,12 @@ async fn test_chat_completions_with_api_key() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let model_config = ModelConfig {
@@ -685,6 +725,8 @@ async fn test_chat_completions_with_api_key() {
         r#type: "gemini-2.0-flash-exp".to_string(),
         provider: "vertexai".to_string(),
         params: HashMap::new(),
+        enabled: true,
+        deprecation: Default::default(),
     };
 
     let result = run_test_with_quota_retry(|| async {
@@ -723,6 +765,7 @@ fn test_invalid_location_format() {
         key: "test-vertexai".to_string(),
         r#type: crate::types::ProviderType::VertexAI,
         api_key: "".to_string(),
+        maintenance_windows: vec![],
         params,
     };
 
@@ -753,6 +796,7 @@ fn test_auth_config_precedence() {
         key: "test-vertexai".to_string(),
         r#type: crate::types::ProviderType::VertexAI,
         api_key: "test-api-key".to_string(),
+        maintenance_windows: vec![],
         params,
     };
 
@@ -770,6 +814,7 @@ fn test_auth_config_credentials_only() {
         key: "test-vertexai".to_string(),
         r#type: crate::types::ProviderType::VertexAI,
         api_key: "".to_string(),
+        maintenance_windows: vec![],
         params,
     };
 
@@ -787,6 +832,7 @@ fn test_empty_message_handling() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: None,
         top_p: None,
@@ -807,6 +853,12 @@ fn test_empty_message_handling() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -824,9 +876,10 @@ fn test_tool_choice_none() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         tool_choice: Some(ToolChoice::Simple(SimpleToolChoice::None)),
-        tools: Some(vec![ToolDefinition {
+        tools: Some(vec![ToolDefinition::Function(FunctionTool {
             tool_type: "function".to_string(),
             function: FunctionDefinition {
                 name: "test_function".to_string(),
@@ -844,7 +897,7 @@ fn test_tool_choice_none() {
                 ),
                 strict: None,
             },
-        }]),
+        })]),
         temperature: None,
         top_p: None,
         n: None,
@@ -861,7 +914,13 @@ fn test_tool_choice_none() {
         top_logprobs: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
         response_format: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -882,6 +941,7 @@ fn test_generation_config_limits() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(2.0),
         top_p: Some(1.5),
@@ -902,6 +962,12 @@ fn test_generation_config_limits() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -915,6 +981,7 @@ fn test_response_error_mapping() {
     let gemini_response = GeminiChatResponse {
         candidates: vec![],
         usage_metadata: None,
+        prompt_feedback: None,
     };
 
     let model = "gemini-2.0-flash-exp".to_string();
@@ -925,6 +992,58 @@ fn test_response_error_mapping() {
     assert_eq!(openai_response.usage.total_tokens, 0);
 }
 
+#[test]
+fn test_prompt_blocked_maps_to_content_filter() {
+    let fixture = fs::read_to_string("tests/fixtures/vertexai_prompt_blocked.json")
+        .expect("Failed to read blocked prompt fixture");
+    let gemini_response: GeminiChatResponse =
+        serde_json::from_str(&fixture).expect("Failed to parse blocked prompt fixture");
+
+    let openai_response = gemini_response.to_openai("gemini-1.5-pro".to_string());
+    assert_eq!(openai_response.choices.len(), 1);
+    let choice = &openai_response.choices[0];
+    assert_eq!(choice.finish_reason.as_deref(), Some("content_filter"));
+    assert!(choice.message.content.is_none());
+    assert!(
+        choice
+            .message
+            .refusal
+            .as_deref()
+            .unwrap()
+            .contains("SAFETY")
+    );
+
+    let ratings = choice.safety_ratings.as_ref().expect("safety ratings");
+    assert_eq!(ratings.as_array().unwrap().len(), 4);
+    assert_eq!(ratings[1]["category"], "HARM_CATEGORY_DANGEROUS_CONTENT");
+    assert_eq!(ratings[1]["blocked"], true);
+}
+
+#[test]
+fn test_response_blocked_maps_to_content_filter() {
+    let fixture = fs::read_to_string("tests/fixtures/vertexai_response_blocked.json")
+        .expect("Failed to read blocked response fixture");
+    let gemini_response: GeminiChatResponse =
+        serde_json::from_str(&fixture).expect("Failed to parse blocked response fixture");
+
+    let openai_response = gemini_response.to_openai("gemini-1.5-pro".to_string());
+    assert_eq!(openai_response.choices.len(), 1);
+    let choice = &openai_response.choices[0];
+    assert_eq!(choice.finish_reason.as_deref(), Some("content_filter"));
+    assert!(choice.message.content.is_none());
+    assert!(choice.message.refusal.is_some());
+    assert_eq!(
+        choice.safety_ratings.as_ref().unwrap()[1]["probability"],
+        "MEDIUM"
+    );
+
+    let serialized = serde_json::to_value(&openai_response).unwrap();
+    assert_eq!(
+        serialized["choices"][0]["safety_ratings"][1]["blocked"],
+        true
+    );
+}
+
 #[test]
 fn test_provider_new() {
     let mut params = HashMap::new();
@@ -935,6 +1054,7 @@ fn test_provider_new() {
         key: "test-vertexai".to_string(),
         r#type: crate::types::ProviderType::VertexAI,
         api_key: "".to_string(),
+        maintenance_windows: vec![],
         params,
     };
 
@@ -950,6 +1070,7 @@ fn test_provider_new_missing_project_id() {
         key: "test-vertexai".to_string(),
         r#type: crate::types::ProviderType::VertexAI,
         api_key: "".to_string(),
+        maintenance_windows: vec![],
         params: HashMap::new(),
     };
 
@@ -967,6 +1088,7 @@ fn test_gemini_request_conversion() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(0.7),
         top_p: Some(0.9),
@@ -987,6 +1109,12 @@ fn test_gemini_request_conversion() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -1019,17 +1147,22 @@ fn test_gemini_response_conversion() {
                 parts: vec![ContentPart {
                     text: Some("Hello there!".to_string()),
                     function_call: None,
+                    function_response: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
             safety_ratings: None,
             tool_calls: None,
+            logprobs_result: None,
         }],
         usage_metadata: Some(UsageMetadata {
             prompt_token_count: 10,
             candidates_token_count: 20,
             total_token_count: 30,
+            thoughts_token_count: None,
+            cached_content_token_count: None,
         }),
+        prompt_feedback: None,
     };
 
     let model = "gemini-2.0-flash-exp".to_string();
@@ -1060,9 +1193,10 @@ fn test_gemini_request_with_tools() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: Some(0.7),
-        tools: Some(vec![ToolDefinition {
+        tools: Some(vec![ToolDefinition::Function(FunctionTool {
             tool_type: "function".to_string(),
             function: FunctionDefinition {
                 name: "test_function".to_string(),
@@ -1080,7 +1214,7 @@ fn test_gemini_request_with_tools() {
                 ),
                 strict: None,
             },
-        }]),
+        })]),
         tool_choice: Some(ToolChoice::Simple(SimpleToolChoice::Auto)),
         top_p: None,
         n: None,
@@ -1098,6 +1232,12 @@ fn test_gemini_request_with_tools() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -1121,17 +1261,22 @@ fn test_gemini_response_with_tool_calls() {
                             "location": "San Francisco"
                         }),
                     }),
+                    function_response: None,
                 }],
             },
             finish_reason: Some("TOOL_CODE".to_string()),
             safety_ratings: None,
             tool_calls: None,
+            logprobs_result: None,
         }],
         usage_metadata: Some(UsageMetadata {
             prompt_token_count: 10,
             candidates_token_count: 20,
             total_token_count: 30,
+            thoughts_token_count: None,
+            cached_content_token_count: None,
         }),
+        prompt_feedback: None,
     };
 
     let model = "gemini-2.0-flash-exp".to_string();
@@ -1164,6 +1309,7 @@ fn test_gemini_request_with_system_message() {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             },
             ChatCompletionMessage {
                 role: "user".to_string(),
@@ -1172,6 +1318,7 @@ fn test_gemini_request_with_system_message() {
                 tool_calls: None,
                 tool_call_id: None,
                 refusal: None,
+                annotations: None,
             },
         ],
         temperature: None,
@@ -1193,6 +1340,12 @@ fn test_gemini_request_with_system_message() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -1225,6 +1378,7 @@ fn test_gemini_request_with_array_content() {
             tool_calls: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: None,
         top_p: None,
@@ -1245,14 +1399,20 @@ fn test_gemini_request_with_array_content() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
 
-    assert_eq!(
-        gemini_request.contents[0].parts[0].text,
-        Some("Part 1 Part 2".to_string())
-    );
+    let parts = &gemini_request.contents[0].parts;
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].text.as_deref(), Some("Part 1"));
+    assert_eq!(parts[1].text.as_deref(), Some("Part 2"));
 }
 
 #[test]
@@ -1446,6 +1606,7 @@ fn test_gemini_request_conversion_with_response_format() {
             name: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: None,
         top_p: None,
@@ -1466,6 +1627,12 @@ fn test_gemini_request_conversion_with_response_format() {
         response_format: Some(response_format),
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -1507,6 +1674,7 @@ fn test_gemini_request_conversion_without_response_format() {
             name: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: None,
         top_p: None,
@@ -1527,6 +1695,12 @@ fn test_gemini_request_conversion_without_response_format() {
         response_format: None,
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -1555,6 +1729,7 @@ fn test_gemini_request_conversion_with_json_object() {
             name: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: None,
         top_p: None,
@@ -1575,6 +1750,12 @@ fn test_gemini_request_conversion_with_json_object() {
         response_format: Some(response_format),
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -1691,6 +1872,7 @@ fn test_gemini_request_conversion_with_exact_user_format() {
             name: None,
             tool_call_id: None,
             refusal: None,
+            annotations: None,
         }],
         temperature: None,
         top_p: None,
@@ -1711,6 +1893,12 @@ fn test_gemini_request_conversion_with_exact_user_format() {
         response_format: Some(response_format),
         reasoning: None,
         reasoning_effort: None,
+        store: None,
+        metadata: None,
+        prediction: None,
+        web_search_options: None,
+        extra_body: None,
+        priority: None,
     };
 
     let gemini_request = GeminiChatRequest::from(chat_request);
@@ -1745,3 +1933,304 @@ fn test_gemini_request_conversion_with_exact_user_format() {
     assert!(required_fields.contains(&json!("age")));
     assert!(required_fields.contains(&json!("isAlive")));
 }
+
+#[test]
+fn test_embeddings_dimensions_map_to_output_dimensionality() {
+    use super::provider::{gemini_embeddings_body, vertex_embeddings_body};
+
+    let mut request = EmbeddingsRequest {
+        model: "text-embedding-005".to_string(),
+        input: EmbeddingsInput::Single("hello".to_string()),
+        user: None,
+        encoding_format: None,
+        dimensions: Some(256),
+        extra_body: None,
+    };
+
+    let vertex_body = vertex_embeddings_body(&request);
+    assert_eq!(vertex_body["parameters"]["outputDimensionality"], 256);
+    assert_eq!(vertex_body["parameters"]["autoTruncate"], true);
+    assert_eq!(
+        gemini_embeddings_body(&request)["outputDimensionality"],
+        256
+    );
+
+    request.dimensions = None;
+    assert!(
+        vertex_embeddings_body(&request)["parameters"]
+            .get("outputDimensionality")
+            .is_none()
+    );
+    assert!(
+        gemini_embeddings_body(&request)
+            .get("outputDimensionality")
+            .is_none()
+    );
+}
+
+#[test]
+fn test_logprobs_request_enables_response_logprobs() {
+    let chat_request: ChatCompletionRequest = serde_json::from_value(json!({
+        "model": "gemini-2.0-flash",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "logprobs": true,
+        "top_logprobs": 2
+    }))
+    .unwrap();
+
+    let body = serde_json::to_value(GeminiChatRequest::from(chat_request)).unwrap();
+    assert_eq!(body["generation_config"]["responseLogprobs"], true);
+    assert_eq!(body["generation_config"]["logprobs"], 2);
+
+    let chat_request: ChatCompletionRequest = serde_json::from_value(json!({
+        "model": "gemini-2.0-flash",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "top_logprobs": 2
+    }))
+    .unwrap();
+    let body = serde_json::to_value(GeminiChatRequest::from(chat_request)).unwrap();
+    assert!(body["generation_config"].get("responseLogprobs").is_none());
+    assert!(body["generation_config"].get("logprobs").is_none());
+}
+
+#[test]
+fn test_logprobs_result_maps_to_openai_logprobs() {
+    let gemini_response: GeminiChatResponse = serde_json::from_value(json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": [{"text": "Hi!"}]},
+            "finish_reason": "STOP",
+            "logprobsResult": {
+                "topCandidates": [
+                    {"candidates": [
+                        {"token": "Hi", "logProbability": -0.01},
+                        {"token": "Hello", "logProbability": -4.6}
+                    ]},
+                    {"candidates": [{"token": "!", "logProbability": -0.2}]}
+                ],
+                "chosenCandidates": [
+                    {"token": "Hi", "logProbability": -0.01},
+                    {"token": "!", "logProbability": -0.2}
+                ]
+            }
+        }],
+        "usage_metadata": null
+    }))
+    .unwrap();
+
+    let openai_response = gemini_response.to_openai("gemini-2.0-flash".to_string());
+    let logprobs = serde_json::to_value(&openai_response.choices[0].logprobs).unwrap();
+    assert_eq!(
+        logprobs,
+        json!({
+            "content": [
+                {
+                    "token": "Hi",
+                    "bytes": [72, 105],
+                    "logprob": -0.01,
+                    "top_logprobs": [
+                        {"token": "Hi", "bytes": [72, 105], "logprob": -0.01},
+                        {"token": "Hello", "bytes": [72, 101, 108, 108, 111], "logprob": -4.6}
+                    ]
+                },
+                {
+                    "token": "!",
+                    "bytes": [33],
+                    "logprob": -0.2,
+                    "top_logprobs": [{"token": "!", "bytes": [33], "logprob": -0.2}]
+                }
+            ]
+        })
+    );
+}
+
+#[test]
+fn test_stream_chunk_carries_logprobs_delta() {
+    use crate::models::streaming::ChatCompletionChunk;
+    use crate::providers::vertexai::models::VertexAIStreamChunk;
+
+    let chunk: VertexAIStreamChunk = serde_json::from_value(json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": [{"text": "Hi"}]},
+            "finish_reason": null,
+            "logprobsResult": {
+                "topCandidates": [{"candidates": [{"token": "Hi", "logProbability": -0.5}]}],
+                "chosenCandidates": [{"token": "Hi", "logProbability": -0.5}]
+            }
+        }],
+        "usage_metadata": null
+    }))
+    .unwrap();
+
+    let chunk = ChatCompletionChunk::from(chunk);
+    let logprobs = chunk.choices[0].logprobs.clone().unwrap();
+    let content = logprobs.content.unwrap();
+    assert_eq!(content.len(), 1);
+    assert_eq!(content[0].token, "Hi");
+    assert_eq!(content[0].logprob, -0.5);
+    assert_eq!(content[0].top_logprobs.len(), 1);
+}
+
+#[test]
+fn test_multi_turn_conversion_with_tool_results() {
+    let fixture = fs::read_to_string("tests/fixtures/chat_multi_turn_tools.json")
+        .expect("Failed to read multi-turn fixture");
+    let chat_request: ChatCompletionRequest =
+        serde_json::from_str(&fixture).expect("Failed to parse multi-turn fixture");
+
+    let gemini_request = GeminiChatRequest::from(chat_request);
+    let body = serde_json::to_value(&gemini_request).unwrap();
+
+    assert_eq!(
+        body["system_instruction"]["parts"],
+        json!([
+            {"text": "You are a travel assistant."},
+            {"text": "Answer in metric units."},
+            {"text": "Keep replies short."}
+        ])
+    );
+    assert_eq!(
+        body["contents"],
+        json!([
+            {"role": "user", "parts": [{"text": "What's the weather in Paris and Rome?"}]},
+            {"role": "model", "parts": [
+                {"text": "Let me check both."},
+                {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}},
+                {"functionCall": {"name": "get_weather", "args": {"city": "Rome"}}}
+            ]},
+            {"role": "user", "parts": [
+                {"functionResponse": {"name": "get_weather", "response": {"temp_c": 18}}}
+            ]},
+            {"role": "user", "parts": [
+                {"functionResponse": {
+                    "name": "get_weather",
+                    "response": {"content": "sunny, 24C"}
+                }}
+            ]},
+            {"role": "user", "parts": [{"text": "Thanks!"}, {"text": "Which is warmer?"}]}
+        ])
+    );
+}
+
+#[test]
+fn test_usage_counts_thoughts_and_cached_tokens() {
+    use crate::models::streaming::ChatCompletionChunk;
+    use crate::providers::vertexai::models::VertexAIStreamChunk;
+
+    let fixture = fs::read_to_string("tests/fixtures/provider_usage.json")
+        .expect("Failed to read usage fixture");
+    let fixtures: Value = serde_json::from_str(&fixture).unwrap();
+
+    let gemini_response: GeminiChatResponse =
+        serde_json::from_value(fixtures["vertexai"].clone()).unwrap();
+    let usage = gemini_response
+        .to_openai("gemini-2.5-flash".to_string())
+        .usage;
+    // Thoughts count as completion tokens, as reasoning tokens do for OpenAI.
+    assert_eq!(
+        (
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.total_tokens
+        ),
+        (2006, 44 + 256, 2306)
+    );
+    assert_eq!(usage.reasoning_tokens(), 256);
+    assert_eq!(usage.cached_tokens(), 1920);
+
+    let chunks: Vec<ChatCompletionChunk> = fixtures["vertexai_stream"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|chunk| {
+            serde_json::from_value::<VertexAIStreamChunk>(chunk.clone())
+                .unwrap()
+                .into()
+        })
+        .collect();
+    // Only the final counts are reported, so they're counted once.
+    assert!(chunks[0].usage.is_none());
+    let streamed = chunks[1].usage.as_ref().unwrap();
+    assert_eq!(
+        serde_json::to_value(streamed).unwrap(),
+        serde_json::to_value(&usage).unwrap()
+    );
+}
+
+struct VertexAITemplates;
+
+impl ContractTemplates for VertexAITemplates {
+    type Provider = VertexAIProvider;
+
+    const PROVIDER_TYPE: ProviderType = ProviderType::VertexAI;
+
+    /// With an API key the provider talks to the Gemini Developer API.
+    fn chat_path(stream: bool) -> String {
+        let method = if stream {
+            "streamGenerateContent"
+        } else {
+            "generateContent"
+        };
+        format!("/v1beta/models/{MODEL}:{method}")
+    }
+
+    fn text_response(text: &str) -> Value {
+        gemini_response(json!([{"text": text}]))
+    }
+
+    fn empty_response() -> Value {
+        gemini_response(json!([]))
+    }
+
+    fn tool_call_response(arguments: &Value) -> Value {
+        gemini_response(json!([{"functionCall": {"name": TOOL_NAME, "args": arguments}}]))
+    }
+
+    /// Gemini sometimes ends the text with the sequence it stopped on.
+    fn stop_sequence_response(text: &str) -> Value {
+        gemini_response(json!([{"text": format!("{text}{STOP_SEQUENCE}")}]))
+    }
+
+    /// Only the last chunk carries the finish reason and the final usage.
+    fn stream_response(parts: &[&str]) -> Option<ResponseTemplate> {
+        let chunks: Vec<Value> = parts
+            .iter()
+            .enumerate()
+            .map(|(index, part)| {
+                let text = json!([{"text": part}]);
+                if index + 1 == parts.len() {
+                    gemini_response(text)
+                } else {
+                    json!({"candidates": [{"content": {"role": "model", "parts": text}}]})
+                }
+            })
+            .collect();
+        Some(ResponseTemplate::new(200).set_body_json(chunks))
+    }
+
+    fn error_response(status: StatusCode) -> Value {
+        json!({
+            "error": {
+                "code": status.as_u16(),
+                "message": status.canonical_reason(),
+                "status": "INVALID_ARGUMENT"
+            }
+        })
+    }
+}
+
+fn gemini_response(parts: Value) -> Value {
+    json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": parts},
+            "finishReason": "STOP"
+        }],
+        "usageMetadata": {
+            "promptTokenCount": PROMPT_TOKENS,
+            "candidatesTokenCount": COMPLETION_TOKENS,
+            "totalTokenCount": PROMPT_TOKENS + COMPLETION_TOKENS
+        },
+        "modelVersion": MODEL
+    })
+}
+
+provider_contract_tests!(VertexAITemplates);
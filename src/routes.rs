@@ -1,29 +1,73 @@
-use crate::state::AppState;
+use crate::admission::{Admission, admit};
+use crate::azure_compat;
+use crate::batch::batch_inference;
+use crate::compression::{compression_layer, request_decompression_layer};
+use crate::cors::cors_layer;
+use crate::gateway::Gateway;
+use crate::management::api::auth::require_admin;
+use crate::pipelines::usage::UsageSummary;
+use crate::state::{AppState, ConfigSummary, ConfigVersion};
+use crate::stream_limits::limit_streams;
 use axum::{
-    Json, Router, body::Body, extract::Request, http::StatusCode, response::Response, routing::get,
+    Json, Router,
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::StatusCode,
+    middleware,
+    response::Response,
+    routing::get,
     routing::post,
 };
-use axum_prometheus::PrometheusMetricLayerBuilder;
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use tower::{Service, ServiceExt};
+use tower::Service;
 use tracing::{debug, warn};
+use uuid::Uuid;
 
 pub fn create_router(state: Arc<AppState>) -> Router {
-    let (prometheus_layer, metric_handle) = PrometheusMetricLayerBuilder::new()
-        .with_ignore_patterns(&["/metrics", "/health"])
-        .with_prefix("traceloop_hub")
-        .with_default_metrics()
-        .build_pair();
+    let (prometheus_layer, metric_handle) = crate::metrics::prometheus_layer();
 
     // Create a dynamic service that forwards to the current pipeline router
     let dynamic_service = DynamicPipelineService::new(state.clone());
+    let cors = state.cors_config().as_ref().map(cors_layer);
+    let compression = state.compression_config();
 
-    Router::new()
-        .nest_service("/api/v1", dynamic_service)
-        .route("/health", get(|| async { "Working!" }))
+    // Admission control only covers the API, so health checks and scrapes stay responsive
+    let api = Router::new()
+        .route("/api/v1/batch-inference", post(batch_inference))
+        .route(
+            "/openai/deployments/{deployment}/chat/completions",
+            post(azure_compat::chat_completions),
+        )
+        .route(
+            "/openai/deployments/{deployment}/completions",
+            post(azure_compat::completions),
+        )
+        .route(
+            "/openai/deployments/{deployment}/embeddings",
+            post(azure_compat::embeddings),
+        )
+        .nest_service("/api/v1", dynamic_service);
+    let api = match state.admission_controller() {
+        Some(controller) => api.layer(middleware::from_fn_with_state(
+            Admission {
+                controller,
+                state: state.clone(),
+            },
+            admit,
+        )),
+        None => api,
+    };
+    // Outside admission control, so streams over a cap are rejected without queueing
+    let api = api.layer(middleware::from_fn_with_state(state.clone(), limit_streams));
+
+    let router = Router::new()
+        .merge(api)
+        .route("/health", get(health_handler))
         .route("/metrics", get(|| async move { metric_handle.render() }))
         // Add OpenAPI documentation endpoints
         .route(
@@ -31,18 +75,128 @@ pub fn create_router(state: Arc<AppState>) -> Router {
             get(|| async { Json(crate::openapi::get_openapi_spec()) }),
         )
         .layer(prometheus_layer)
+        .with_state(state);
+
+    let router = match compression {
+        Some(compression) => router
+            .layer(compression_layer(&compression))
+            .layer(request_decompression_layer(&compression)),
+        None => router,
+    };
+
+    // CORS wraps everything so preflights are answered before routing
+    match cors {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
+}
+
+/// Routes for operators, served by the management server behind its API keys rather than
+/// on the gateway's port.
+pub fn admin_routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/admin/config", get(admin_config_handler))
+        .route("/admin/config/version", get(admin_config_version_handler))
+        .route("/admin/health", get(admin_health_handler))
+        .route("/admin/usage", get(admin_usage_handler))
+        // Artifacts hold whole request and response bodies
+        .route(
+            "/admin/artifacts/{request_id}",
+            get(admin_artifact_handler).route_layer(middleware::from_fn(require_admin)),
+        )
         .with_state(state)
 }
 
-/// A service that dynamically forwards requests to the current pipeline router
+/// Reports liveness only. It is served to anyone who can reach the gateway, so the details
+/// are left to `/admin/health`.
+async fn health_handler() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Reports the hash of the live configuration and each pipeline's recent request outcomes.
+/// Providers that can't serve requests turn the status to `degraded`.
+async fn admin_health_handler(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    let config_hash = state.config_version().config_hash;
+    let recent_outcomes = state.services().outcomes.snapshot();
+    let unhealthy_providers = state.unhealthy_providers();
+    if unhealthy_providers.is_empty() {
+        return Json(serde_json::json!({
+            "status": "ok",
+            "config_hash": config_hash,
+            "recent_outcomes": recent_outcomes,
+        }));
+    }
+    Json(serde_json::json!({
+        "status": "degraded",
+        "config_hash": config_hash,
+        "unhealthy_providers": unhealthy_providers,
+        "recent_outcomes": recent_outcomes,
+    }))
+}
+
+/// Returns the live configuration with secrets masked, plus where it came from
+async fn admin_config_handler(State(state): State<Arc<AppState>>) -> Json<ConfigSummary> {
+    Json(state.config_summary())
+}
+
+/// Returns the hash of the live configuration and when it was applied
+async fn admin_config_version_handler(State(state): State<Arc<AppState>>) -> Json<ConfigVersion> {
+    Json(state.config_version())
+}
+
+/// Returns the artifact stored for a request, by the id sent in `x-hub-request-id`
+async fn admin_artifact_handler(
+    State(state): State<Arc<AppState>>,
+    Path(request_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match state.services().artifacts.get(request_id).await {
+        Ok(Some(artifact)) => Ok(Json(artifact)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            warn!("Failed to read artifact {request_id}: {e:#}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageQuery {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    pipeline: Option<String>,
+}
+
+/// Returns token, request, error and cost totals per pipeline, model and UTC day between
+/// `from` and `to` (inclusive, both defaulting to today)
+async fn admin_usage_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageSummary>, (StatusCode, String)> {
+    let today = Utc::now().date_naive();
+    let from = query.from.unwrap_or(today);
+    let to = query.to.unwrap_or(today);
+    if from > to {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("from ({from}) is after to ({to})"),
+        ));
+    }
+    let summary = state.services().usage.summary(from, to, query.pipeline);
+    Ok(Json(summary))
+}
+
+/// A service that dynamically forwards requests to the current pipeline router, through
+/// the same [`Gateway`] the hub offers to services embedding it
 #[derive(Clone)]
 pub struct DynamicPipelineService {
-    state: Arc<AppState>,
+    gateway: Gateway,
 }
 
 impl DynamicPipelineService {
     pub fn new(state: Arc<AppState>) -> Self {
-        Self { state }
+        Self {
+            gateway: Gateway::from_state(state),
+        }
     }
 }
 
@@ -56,25 +210,9 @@ impl Service<Request<Body>> for DynamicPipelineService {
     }
 
     fn call(&mut self, request: Request<Body>) -> Self::Future {
-        let state = self.state.clone();
-
-        Box::pin(async move {
-            // Get the current dynamic router
-            let current_router = create_dynamic_pipeline_router(state);
-
-            // Forward the request to the current router
-            match current_router.oneshot(request).await {
-                Ok(response) => Ok(response),
-                Err(_) => {
-                    // Create a 500 error response
-                    let response = Response::builder()
-                        .status(StatusCode::INTERNAL_SERVER_ERROR)
-                        .body(Body::empty())
-                        .unwrap();
-                    Ok(response)
-                }
-            }
-        })
+        let gateway = self.gateway.clone();
+
+        Box::pin(async move { Ok(gateway.handle(request).await) })
     }
 }
 
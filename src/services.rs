@@ -0,0 +1,72 @@
+use crate::artifacts::ArtifactStore;
+use crate::notifications::NotificationBus;
+use crate::outcome::OutcomeRollup;
+use crate::pipelines::adaptive_routing::ModelStatsTracker;
+use crate::pipelines::budget::BudgetLedger;
+use crate::pipelines::degraded_mode::DegradedModes;
+use crate::pipelines::idempotency::InFlight;
+use crate::pipelines::resumable_streams::LiveStreams;
+use crate::pipelines::usage::UsageAggregator;
+use crate::state_store::StateStore;
+use crate::types::GatewayConfig;
+use anyhow::Result;
+use std::sync::Arc;
+
+/// State shared by the pipelines of one hub that outlives their routers, so config
+/// reloads keep it. Two hubs in a process, such as two `Gateway`s, don't share any of it.
+#[derive(Clone)]
+pub struct HubServices {
+    pub state_store: Arc<StateStore>,
+    pub budgets: Arc<BudgetLedger>,
+    pub usage: Arc<UsageAggregator>,
+    pub model_stats: Arc<ModelStatsTracker>,
+    pub degraded_modes: Arc<DegradedModes>,
+    pub notifications: Arc<NotificationBus>,
+    pub artifacts: Arc<ArtifactStore>,
+    pub outcomes: Arc<OutcomeRollup>,
+    pub(crate) in_flight: Arc<InFlight>,
+    pub(crate) live_streams: Arc<LiveStreams>,
+}
+
+impl Default for HubServices {
+    fn default() -> Self {
+        Self::new(Arc::new(StateStore::default()))
+    }
+}
+
+impl HubServices {
+    /// Services keeping their budgets and usage in `state_store`. Idempotent responses and
+    /// resumable streams change with every request and chunk, so they are kept in memory
+    /// even when `state_store` is backed by a file.
+    pub fn new(state_store: Arc<StateStore>) -> Self {
+        let notifications = Arc::new(NotificationBus::default());
+        let transient_store = Arc::new(StateStore::default());
+        Self {
+            budgets: Arc::new(
+                BudgetLedger::new(state_store.clone()).with_notifications(notifications.clone()),
+            ),
+            usage: Arc::new(UsageAggregator::new(state_store.clone())),
+            model_stats: Arc::default(),
+            degraded_modes: Arc::default(),
+            notifications,
+            artifacts: Arc::default(),
+            outcomes: Arc::default(),
+            in_flight: Arc::new(InFlight::new(transient_store.clone())),
+            live_streams: Arc::new(LiveStreams::new(transient_store)),
+            state_store,
+        }
+    }
+
+    /// Services for a hub starting with `config`, restoring budgets and usage from
+    /// `general.state_store_path` when it is set.
+    pub fn for_config(config: &GatewayConfig) -> Result<Self> {
+        let path = config
+            .general
+            .as_ref()
+            .and_then(|general| general.state_store_path.as_ref());
+        Ok(match path {
+            Some(path) => Self::new(Arc::new(StateStore::open(path)?)),
+            None => Self::default(),
+        })
+    }
+}
@@ -0,0 +1,147 @@
+//! Startup steps shared by the gateway server and `hub validate`: finding where the
+//! configuration comes from, reading it and checking it.
+
+use crate::config::lib::{EnvSubstitution, load_config_with};
+use crate::config::validation::{InvalidConfig, Severity, ValidationError, check_gateway_config};
+use crate::management::{DbPools, config_provider_service};
+use crate::providers::registry::ProviderRegistry;
+use crate::state::effective_providers;
+use crate::types::GatewayConfig;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+pub const DEFAULT_CONFIG_PATH: &str = "config.yaml";
+const DEFAULT_DB_STATEMENT_TIMEOUT_SECONDS: u64 = 30;
+
+#[derive(Debug, Clone)]
+pub enum ConfigMode {
+    Yaml { path: String },
+    Database { pools: DbPools },
+}
+
+/// Picks the configuration source from `HUB_MODE`, connecting to the database in database
+/// mode. YAML mode reads `CONFIG_FILE_PATH`, or `config.yaml` when it isn't set.
+pub async fn determine_config_mode() -> anyhow::Result<ConfigMode> {
+    match std::env::var("HUB_MODE").as_deref() {
+        Ok("database") => {
+            debug!("HUB_MODE=database detected. Initializing database mode.");
+            Ok(ConfigMode::Database {
+                pools: connect_database().await?,
+            })
+        }
+        Ok("yaml") => {
+            debug!("HUB_MODE=yaml detected. Using YAML configuration mode.");
+            Ok(ConfigMode::Yaml {
+                path: config_file_path(),
+            })
+        }
+        Ok(invalid_mode) => {
+            error!(
+                "Invalid HUB_MODE '{}'. Valid options: 'yaml', 'database'",
+                invalid_mode
+            );
+            Err(anyhow::anyhow!("Invalid HUB_MODE: {invalid_mode}"))
+        }
+        Err(_) => {
+            // HUB_MODE not set, fallback to yaml mode
+            debug!("HUB_MODE not set. Defaulting to YAML configuration mode.");
+            Ok(ConfigMode::Yaml {
+                path: config_file_path(),
+            })
+        }
+    }
+}
+
+fn config_file_path() -> String {
+    std::env::var("CONFIG_FILE_PATH").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string())
+}
+
+/// Connects to `DATABASE_URL`, reading from `DATABASE_READ_URL` when it's set.
+pub async fn connect_database() -> anyhow::Result<DbPools> {
+    let database_url = std::env::var("DATABASE_URL")
+        .map_err(|e| anyhow::anyhow!("DATABASE_URL not set for database mode: {e}"))?;
+
+    let read_url = std::env::var("DATABASE_READ_URL").ok();
+    // 0 turns the timeout off
+    let statement_timeout = std::env::var("DB_STATEMENT_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_DB_STATEMENT_TIMEOUT_SECONDS);
+    let statement_timeout =
+        (statement_timeout > 0).then_some(Duration::from_secs(statement_timeout));
+
+    debug!("Connecting to database: {}", database_url);
+    if let Some(read_url) = &read_url {
+        debug!("Using read replica: {}", read_url);
+    }
+
+    let pools = DbPools::connect(&database_url, read_url.as_deref(), statement_timeout)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to connect to database at {database_url}: {e}"))?;
+
+    info!("Database connection established successfully.");
+    Ok(pools)
+}
+
+/// Loads the YAML configuration at `path`. A file that can't be read or parsed is reported
+/// as an `unreadable_config` finding.
+pub fn read_yaml_config(path: &str, env: EnvSubstitution) -> Result<GatewayConfig, InvalidConfig> {
+    debug!("Loading configuration from YAML file: {}", path);
+    load_config_with(path, env).map_err(|e| {
+        InvalidConfig::unreadable(
+            path,
+            format!("Failed to load YAML configuration from {path}: {e}"),
+        )
+    })
+}
+
+/// Everything wrong with the configuration `mode` points at, warnings included, as the
+/// gateway would find it on startup. A database config is fetched as the poller fetches it.
+/// With `resolve_secrets`, a config without errors also has its providers built and their
+/// API key files and secrets read, reporting any that can't be as `provider_unavailable`.
+pub async fn validate_config(
+    mode: ConfigMode,
+    env: EnvSubstitution,
+    resolve_secrets: bool,
+) -> anyhow::Result<Vec<ValidationError>> {
+    let config = match mode {
+        ConfigMode::Yaml { path } => match read_yaml_config(&path, env) {
+            Ok(config) => config,
+            Err(InvalidConfig(findings)) => return Ok(findings),
+        },
+        ConfigMode::Database { pools } => config_provider_service(pools)
+            .fetch_live_config()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch configuration from database: {e}"))?,
+    };
+
+    let mut findings = check_gateway_config(&config);
+    let has_errors = findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error);
+    if resolve_secrets && !has_errors {
+        findings.extend(provider_findings(&config));
+    }
+    Ok(findings)
+}
+
+/// The providers of `config` that can't be built or can't read their API key.
+fn provider_findings(config: &GatewayConfig) -> Vec<ValidationError> {
+    let registry = match ProviderRegistry::new(&effective_providers(config)) {
+        Ok(registry) => registry,
+        Err(e) => {
+            return vec![ValidationError::error(
+                "provider_unavailable",
+                "providers",
+                format!("Failed to create providers: {e}"),
+            )];
+        }
+    };
+    registry
+        .unhealthy_providers()
+        .into_iter()
+        .map(|(key, reason)| {
+            ValidationError::error("provider_unavailable", format!("providers[{key}]"), reason)
+        })
+        .collect()
+}
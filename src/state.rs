@@ -1,20 +1,42 @@
+use crate::admission::AdmissionController;
 use crate::ai_models::registry::ModelRegistry;
-use crate::config::hash::calculate_config_hash;
-use crate::config::models::GatewayConfig;
+use crate::config::hash::{calculate_config_hash, format_config_hash};
+use crate::config::lib::RuntimeSettings;
+use crate::config::models::{
+    CompressionConfig, CorsConfig, GatewayConfig, NotificationsConfig, Provider,
+};
+use crate::config::names::lookup_matches;
+use crate::config::redaction::RedactedGatewayConfig;
+use crate::error_detail::redact_errors;
+use crate::metrics::{OtlpMetrics, gauge};
+use crate::pipelines::pipeline::{PipelineRunner, PipelineScope, create_pipeline_router};
+use crate::providers::azure::DEPLOYMENT_PARAM;
+use crate::providers::http_client::apply_default_proxy;
 use crate::providers::registry::ProviderRegistry;
+use crate::services::HubServices;
+use crate::stream_limits::{StreamLimiter, StreamLimits};
+use crate::types::{
+    ArtifactStoreConfig, BatchInferenceConfig, OtlpMetricsConfig, PluginConfig, RequestPriority,
+};
 use anyhow::{Context, Result};
-use axum::{Router, body::Body, extract::Request};
-use std::collections::HashMap;
+use axum::{Router, body::Body, extract::Request, http::HeaderMap, middleware};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::sync::{Arc, RwLock};
 use tower::ServiceExt;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 
-const PIPELINE_HEADER: &str = "x-traceloop-pipeline";
+/// Names the pipeline a request goes to; the default pipeline serves requests without it.
+pub const PIPELINE_HEADER: &str = "x-traceloop-pipeline";
 
 const DEFAULT_PIPELINE_NAME: &str = "default";
 
 const FALLBACK_PIPELINE_NAME: &str = "fallback";
 
+const CONFIG_HASH_METRIC: &str = "hub_config_hash_info";
+
 /// A snapshot of configuration state at a point in time
 /// This reduces lock contention by capturing all needed data in one operation
 /// NOTE: This struct is only used in tests and should not be used in production code
@@ -25,27 +47,90 @@ pub struct ConfigSnapshot {
     pub model_registry: Arc<ModelRegistry>,
 }
 
+/// Where the running configuration was loaded from.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ConfigSource {
+    Yaml { path: String },
+    Database,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Yaml { path } => write!(f, "yaml:{path}"),
+            ConfigSource::Database => write!(f, "database"),
+        }
+    }
+}
+
+/// Identifies the live configuration, so replicas can be checked for drift.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ConfigVersion {
+    pub config_hash: String,
+    pub last_applied_at: DateTime<Utc>,
+}
+
+/// Debug view of the live configuration, with all secrets masked.
+#[derive(Serialize, Debug, Clone)]
+pub struct ConfigSummary {
+    pub source: Option<ConfigSource>,
+    pub last_applied_at: DateTime<Utc>,
+    pub config_hash: String,
+    pub config: RedactedGatewayConfig,
+}
+
+/// Providers as they should be instantiated, with `general` defaults applied.
+pub(crate) fn effective_providers(config: &GatewayConfig) -> Vec<Provider> {
+    let default_proxy_url = config
+        .general
+        .as_ref()
+        .and_then(|g| g.default_proxy_url.as_deref());
+    apply_default_proxy(&config.providers, default_proxy_url)
+}
+
+fn notifications_config(config: &GatewayConfig) -> Option<&NotificationsConfig> {
+    config.general.as_ref()?.notifications.as_ref()
+}
+
+fn artifact_store_config(config: &GatewayConfig) -> Option<&ArtifactStoreConfig> {
+    config.general.as_ref()?.artifact_store.as_ref()
+}
+
+fn otlp_metrics_config(config: &GatewayConfig) -> Option<&OtlpMetricsConfig> {
+    config.general.as_ref()?.otlp_metrics.as_ref()
+}
+
 // Inner state that holds the frequently updated parts
 struct InnerAppState {
     config: GatewayConfig,
     config_hash: u64,
+    last_applied_at: DateTime<Utc>,
     provider_registry: Arc<ProviderRegistry>,
     model_registry: Arc<ModelRegistry>,
+    settings: Arc<RuntimeSettings>,
+    /// The pipelines of the configuration, the default one first.
+    runners: Vec<Arc<PipelineRunner>>,
 }
 
 impl InnerAppState {
     fn new(initial_config: GatewayConfig) -> Result<Self> {
         let config_hash = calculate_config_hash(&initial_config);
-        let provider_registry_arc = Arc::new(ProviderRegistry::new(&initial_config.providers)?);
+        let provider_registry_arc = Arc::new(ProviderRegistry::new(&effective_providers(
+            &initial_config,
+        ))?);
         let model_registry_arc = Arc::new(ModelRegistry::new(
             &initial_config.models,
             provider_registry_arc.clone(),
         )?);
         Ok(Self {
+            settings: Arc::new(initial_config.settings()),
             config: initial_config,
             config_hash,
+            last_applied_at: Utc::now(),
             provider_registry: provider_registry_arc,
             model_registry: model_registry_arc,
+            runners: Vec::new(),
         })
     }
 }
@@ -54,26 +139,69 @@ impl InnerAppState {
 pub struct AppState {
     inner: Arc<RwLock<InnerAppState>>,
     current_router: Arc<RwLock<Arc<Router>>>,
+    config_source: Option<ConfigSource>,
+    stream_limiter: Arc<StreamLimiter>,
+    services: HubServices,
+    /// Whether this hub exports the process's metrics, see [`Self::with_metrics_export`].
+    export_metrics: bool,
 }
 
 impl AppState {
+    /// State for `initial_config`, restoring budgets and usage from
+    /// `general.state_store_path` when it is set.
     pub fn new(initial_config: GatewayConfig) -> Result<Self> {
-        let inner_app_state =
+        let services = HubServices::for_config(&initial_config)?;
+        Self::with_services(initial_config, services)
+    }
+
+    /// State keeping its budgets, usage and the like in `services`, e.g. to share them with
+    /// another hub or to keep them in a durable store.
+    pub fn with_services(initial_config: GatewayConfig, services: HubServices) -> Result<Self> {
+        let mut inner_app_state =
             InnerAppState::new(initial_config).context("Failed to create initial InnerAppState")?;
 
         // Build initial router
-        let initial_router = Self::build_router_for_config(
+        let (initial_router, runners) = Self::build_router_for_config(
             &inner_app_state.config,
-            &inner_app_state.provider_registry,
             &inner_app_state.model_registry,
+            &inner_app_state.settings,
+            &services,
         );
+        inner_app_state.runners = runners;
+        services
+            .notifications
+            .configure(notifications_config(&inner_app_state.config));
+        services
+            .artifacts
+            .configure(artifact_store_config(&inner_app_state.config));
 
         Ok(Self {
             inner: Arc::new(RwLock::new(inner_app_state)),
             current_router: Arc::new(RwLock::new(Arc::new(initial_router))),
+            config_source: None,
+            stream_limiter: StreamLimiter::new(),
+            services,
+            export_metrics: false,
         })
     }
 
+    /// Exports metrics to the OTLP endpoint of `general.otlp_metrics`, following config
+    /// updates. The exporter serves the whole process, so only the hub serving the HTTP API
+    /// sets this.
+    pub fn with_metrics_export(mut self) -> Self {
+        self.export_metrics = true;
+        OtlpMetrics::global().configure(otlp_metrics_config(&self.inner.read().unwrap().config));
+        self
+    }
+
+    /// Record where the configuration comes from, for the admin config summary.
+    /// Announces the initial configuration, now that its source is known.
+    pub fn with_config_source(mut self, source: ConfigSource) -> Self {
+        self.config_source = Some(source);
+        self.record_config_applied(None);
+        self
+    }
+
     // Public getters to access inner state fields safely
     // Note: These methods use unwrap() for lock acquisition, which is acceptable
     // since lock poisoning is extremely rare in practice
@@ -96,6 +224,184 @@ impl AppState {
         }
     }
 
+    /// Get the CORS policy of the live configuration
+    pub fn cors_config(&self) -> Option<CorsConfig> {
+        let guard = self.inner.read().unwrap();
+        guard.config.general.as_ref()?.cors.clone()
+    }
+
+    /// Get the compression settings of the live configuration
+    pub fn compression_config(&self) -> Option<CompressionConfig> {
+        let guard = self.inner.read().unwrap();
+        guard.config.general.as_ref()?.compression.clone()
+    }
+
+    /// Build the admission controller for the live configuration, if a limit is set
+    pub fn admission_controller(&self) -> Option<Arc<AdmissionController>> {
+        let guard = self.inner.read().unwrap();
+        AdmissionController::from_general(guard.config.general.as_ref()?)
+    }
+
+    /// Get the runtime settings of the live configuration
+    pub fn settings(&self) -> Arc<RuntimeSettings> {
+        self.inner.read().unwrap().settings.clone()
+    }
+
+    /// Get the services shared by this hub's pipelines across configuration updates
+    pub fn services(&self) -> &HubServices {
+        &self.services
+    }
+
+    /// Get the pipeline a request naming `pipeline` goes to, as the HTTP API steers it:
+    /// the pipeline of that name, or the default one. `None` when there are no pipelines.
+    pub fn pipeline_runner(&self, pipeline: Option<&str>) -> Option<Arc<PipelineRunner>> {
+        let guard = self.inner.read().unwrap();
+        let case_insensitive = guard.settings.case_insensitive_lookups;
+        let runners = &guard.runners;
+        pipeline
+            .and_then(|name| {
+                runners
+                    .iter()
+                    .find(|runner| &*runner.scope().name == name)
+                    .or_else(|| {
+                        runners.iter().find(|runner| {
+                            lookup_matches(&runner.scope().name, name, case_insensitive)
+                        })
+                    })
+            })
+            .or_else(|| runners.first())
+            .cloned()
+    }
+
+    /// Get the open stream counts, shared across configuration updates
+    pub fn stream_limiter(&self) -> Arc<StreamLimiter> {
+        self.stream_limiter.clone()
+    }
+
+    /// Get the stream caps of the live configuration
+    pub fn stream_limits(&self) -> StreamLimits {
+        let guard = self.inner.read().unwrap();
+        guard
+            .config
+            .general
+            .as_ref()
+            .map(StreamLimits::from_general)
+            .unwrap_or_default()
+    }
+
+    /// Get the batch inference caps of the live configuration
+    pub fn batch_inference_config(&self) -> BatchInferenceConfig {
+        let guard = self.inner.read().unwrap();
+        guard
+            .config
+            .general
+            .as_ref()
+            .and_then(|general| general.batch_inference)
+            .unwrap_or_default()
+    }
+
+    /// Whether the live configuration serves the Azure OpenAI route aliases
+    pub fn azure_compat_routes(&self) -> bool {
+        let guard = self.inner.read().unwrap();
+        guard
+            .config
+            .general
+            .as_ref()
+            .is_some_and(|general| general.azure_compat_routes)
+    }
+
+    /// Get the key of the model serving the Azure deployment `deployment`: the model whose
+    /// `deployment` param names it or, without one, the model keyed by it
+    pub fn model_for_deployment(&self, deployment: &str) -> Option<String> {
+        let guard = self.inner.read().unwrap();
+        let case_insensitive = guard.settings.case_insensitive_lookups;
+        let models = &guard.config.models;
+        models
+            .iter()
+            .find(|model| {
+                model
+                    .params
+                    .get(DEPLOYMENT_PARAM)
+                    .is_some_and(|name| lookup_matches(name, deployment, case_insensitive))
+            })
+            .or_else(|| {
+                models
+                    .iter()
+                    .find(|model| lookup_matches(&model.key, deployment, case_insensitive))
+            })
+            .map(|model| model.key.clone())
+    }
+
+    /// Get the `priority` plugin default of the pipeline a request will be routed to
+    pub fn pipeline_default_priority(&self, headers: &HeaderMap) -> Option<RequestPriority> {
+        let guard = self.inner.read().unwrap();
+        let case_insensitive = guard.settings.case_insensitive_lookups;
+        let pipelines = &guard.config.pipelines;
+        let requested = headers
+            .get(PIPELINE_HEADER)
+            .and_then(|header| header.to_str().ok());
+        let pipeline = requested
+            .and_then(|name| {
+                pipelines
+                    .iter()
+                    .find(|p| lookup_matches(&p.name, name, case_insensitive))
+            })
+            .or_else(|| pipelines.iter().find(|p| p.name == DEFAULT_PIPELINE_NAME))
+            .or_else(|| pipelines.first())?;
+        pipeline.plugins.iter().find_map(|plugin| match plugin {
+            PluginConfig::Priority { default } => Some(*default),
+            _ => None,
+        })
+    }
+
+    /// Get a redacted summary of the live configuration for debugging endpoints
+    pub fn config_summary(&self) -> ConfigSummary {
+        let guard = self.inner.read().unwrap();
+        ConfigSummary {
+            source: self.config_source.clone(),
+            last_applied_at: guard.last_applied_at,
+            config_hash: format_config_hash(guard.config_hash),
+            config: RedactedGatewayConfig::from(&guard.config),
+        }
+    }
+
+    /// Get the hash and apply time of the live configuration
+    pub fn config_version(&self) -> ConfigVersion {
+        let guard = self.inner.read().unwrap();
+        ConfigVersion {
+            config_hash: format_config_hash(guard.config_hash),
+            last_applied_at: guard.last_applied_at,
+        }
+    }
+
+    /// Emits the `config_applied` event and points the hash info metric at the live config.
+    fn record_config_applied(&self, previous_hash: Option<u64>) {
+        let guard = self.inner.read().unwrap();
+        let hash = format_config_hash(guard.config_hash);
+        if let Some(previous_hash) = previous_hash {
+            gauge!(CONFIG_HASH_METRIC, "hash" => format_config_hash(previous_hash)).set(0.0);
+        }
+        gauge!(CONFIG_HASH_METRIC, "hash" => hash.clone()).set(1.0);
+        let source = self
+            .config_source
+            .as_ref()
+            .map_or_else(|| "unknown".to_string(), ToString::to_string);
+        info!(
+            hash = %hash,
+            providers = guard.config.providers.len(),
+            models = guard.config.models.len(),
+            pipelines = guard.config.pipelines.len(),
+            source = %source,
+            "config_applied"
+        );
+    }
+
+    /// Get the providers of the live configuration that can't serve requests right now
+    pub fn unhealthy_providers(&self) -> BTreeMap<String, String> {
+        let guard = self.inner.read().unwrap();
+        guard.provider_registry.unhealthy_providers()
+    }
+
     pub fn get_current_router(&self) -> Arc<Router> {
         let guard = self.current_router.read().unwrap();
         Arc::clone(&guard)
@@ -130,39 +436,77 @@ impl AppState {
         );
 
         if let Err(val_errors) = crate::config::validation::validate_gateway_config(&new_config) {
-            return Err(anyhow::anyhow!("Invalid configuration: {val_errors:?}"));
+            return Err(crate::config::validation::InvalidConfig(val_errors).into());
         }
 
-        let new_provider_registry = Arc::new(ProviderRegistry::new(&new_config.providers)?);
+        let new_provider_registry =
+            Arc::new(ProviderRegistry::new(&effective_providers(&new_config))?);
         let new_model_registry = Arc::new(ModelRegistry::new(
             &new_config.models,
             new_provider_registry.clone(),
         )?);
 
-        let new_router =
-            Self::build_router_for_config(&new_config, &new_provider_registry, &new_model_registry);
+        let new_settings = Arc::new(new_config.settings());
+        let (new_router, new_runners) = Self::build_router_for_config(
+            &new_config,
+            &new_model_registry,
+            &new_settings,
+            &self.services,
+        );
+
+        let notifications_changed = {
+            let guard = self.inner.read().unwrap();
+            notifications_config(&guard.config) != notifications_config(&new_config)
+        };
+        if notifications_changed {
+            self.services
+                .notifications
+                .configure(notifications_config(&new_config));
+        }
+        let artifact_store_changed = {
+            let guard = self.inner.read().unwrap();
+            artifact_store_config(&guard.config) != artifact_store_config(&new_config)
+        };
+        if artifact_store_changed {
+            self.services
+                .artifacts
+                .configure(artifact_store_config(&new_config));
+        }
+        let otlp_metrics_changed = {
+            let guard = self.inner.read().unwrap();
+            otlp_metrics_config(&guard.config) != otlp_metrics_config(&new_config)
+        };
+        if self.export_metrics && otlp_metrics_changed {
+            OtlpMetrics::global().configure(otlp_metrics_config(&new_config));
+        }
+        // The new router has picked up the degraded mode state it keeps.
+        self.services.degraded_modes.retain(&new_config.pipelines);
 
         {
             let mut inner_guard = self.inner.write().unwrap();
             inner_guard.config = new_config;
             inner_guard.config_hash = new_hash;
+            inner_guard.last_applied_at = Utc::now();
             inner_guard.provider_registry = new_provider_registry;
             inner_guard.model_registry = new_model_registry;
+            inner_guard.settings = new_settings;
+            inner_guard.runners = new_runners;
         }
 
         self.set_current_router(new_router);
+        self.record_config_applied(Some(current_hash));
 
         debug!("Configuration and router updated successfully");
         Ok(())
     }
 
+    /// The router serving `config`, and the runners of its pipelines, the default one first.
     fn build_router_for_config(
         config: &GatewayConfig,
-        _provider_registry: &Arc<ProviderRegistry>,
         model_registry: &Arc<ModelRegistry>,
-    ) -> axum::Router {
-        use crate::pipelines::pipeline::create_pipeline;
-
+        settings: &Arc<RuntimeSettings>,
+        services: &HubServices,
+    ) -> (axum::Router, Vec<Arc<PipelineRunner>>) {
         debug!("Building router with {} pipelines", config.pipelines.len());
 
         let (default_pipeline, other_pipelines): (Vec<_>, Vec<_>) = config
@@ -172,25 +516,23 @@ impl AppState {
 
         let mut pipeline_routers = Vec::with_capacity(config.pipelines.len());
         let mut pipeline_names = Vec::with_capacity(config.pipelines.len());
+        let mut runners = Vec::with_capacity(config.pipelines.len());
         let default_pipeline_idx = 0; // Default is always first
 
-        if let Some(default_pipeline) = default_pipeline.first() {
-            debug!(
-                "Adding default pipeline '{}' to router at index 0",
-                default_pipeline.name
-            );
-            let pipeline_router = create_pipeline(default_pipeline, model_registry);
-            pipeline_routers.push(pipeline_router);
-            pipeline_names.push(default_pipeline.name.clone());
-        }
-
-        for (idx, pipeline) in other_pipelines.iter().enumerate() {
+        for (idx, pipeline) in default_pipeline.iter().chain(&other_pipelines).enumerate() {
             let name = &pipeline.name;
-            debug!("Adding pipeline '{}' to router at index {}", name, idx + 1);
-
-            let pipeline_router = create_pipeline(pipeline, model_registry);
-            pipeline_routers.push(pipeline_router);
+            debug!("Adding pipeline '{}' to router at index {}", name, idx);
+
+            let scope = PipelineScope::new(name, settings.clone(), services.clone());
+            let runner = Arc::new(PipelineRunner::new(
+                pipeline,
+                model_registry,
+                &config.prompt_templates,
+                scope,
+            ));
+            pipeline_routers.push(create_pipeline_router(pipeline, runner.clone()));
             pipeline_names.push(name.clone());
+            runners.push(runner);
         }
 
         if pipeline_routers.is_empty() {
@@ -207,11 +549,21 @@ impl AppState {
             pipeline_names[default_pipeline_idx]
         );
 
-        Self::create_pipeline_steering_router(
+        let router = Self::create_pipeline_steering_router(
             pipeline_routers,
             pipeline_names,
             default_pipeline_idx,
-        )
+            settings.case_insensitive_lookups,
+        );
+        // Around the steering, so every pipeline's errors, and those of requests steered to
+        // none, are returned alike.
+        let error_detail = config
+            .general
+            .as_ref()
+            .map(|general| general.error_detail)
+            .unwrap_or_default();
+        let router = router.layer(middleware::from_fn_with_state(error_detail, redact_errors));
+        (router, runners)
     }
 
     fn create_no_config_router_static() -> axum::Router {
@@ -222,6 +574,7 @@ impl AppState {
         pipeline_routers: Vec<Router>,
         pipeline_names: Vec<String>,
         default_pipeline_idx: usize,
+        case_insensitive_lookups: bool,
     ) -> Router {
         if pipeline_routers.len() == 1 {
             return pipeline_routers
@@ -241,7 +594,8 @@ impl AppState {
             .map(|(name, router)| (name, Arc::new(router)))
             .collect();
 
-        let steering_service = PipelineSteeringService::new(pipeline_map, default_pipeline_name);
+        let steering_service = PipelineSteeringService::new(pipeline_map, default_pipeline_name)
+            .with_case_insensitive_lookups(case_insensitive_lookups);
 
         Router::new().fallback_service(steering_service)
     }
@@ -251,6 +605,7 @@ impl AppState {
 pub struct PipelineSteeringService {
     pipeline_routers: HashMap<String, Arc<Router>>,
     default_pipeline: Arc<Router>,
+    case_insensitive_lookups: bool,
 }
 
 impl PipelineSteeringService {
@@ -266,9 +621,16 @@ impl PipelineSteeringService {
         Self {
             pipeline_routers,
             default_pipeline,
+            case_insensitive_lookups: false,
         }
     }
 
+    /// Matches pipeline names ignoring case, as `general.case_insensitive_lookups` asks.
+    pub fn with_case_insensitive_lookups(mut self, case_insensitive_lookups: bool) -> Self {
+        self.case_insensitive_lookups = case_insensitive_lookups;
+        self
+    }
+
     /// Extract pipeline name from header without unnecessary string allocations
     fn get_pipeline_name_from_header<'a>(&self, request: &'a Request<Body>) -> Option<&'a str> {
         request
@@ -300,6 +662,14 @@ impl tower::Service<Request<Body>> for PipelineSteeringService {
 
             self.pipeline_routers
                 .get(name)
+                .or_else(|| {
+                    self.pipeline_routers
+                        .iter()
+                        .find(|(pipeline, _)| {
+                            lookup_matches(pipeline, name, self.case_insensitive_lookups)
+                        })
+                        .map(|(_, router)| router)
+                })
                 .unwrap_or_else(|| {
                     debug!(
                         "Pipeline '{}' not found, falling back to default pipeline",
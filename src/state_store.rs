@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often expired entries are swept out, at most.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Entry {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_live(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+}
+
+/// An entry as kept in a store's file, expiring at a wall-clock time since `Instant`s
+/// don't outlive the process.
+#[derive(Serialize, Deserialize)]
+struct StoredEntry {
+    value: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+struct Entries {
+    map: HashMap<String, Entry>,
+    last_sweep: Instant,
+}
+
+/// Key-value state that outlives single requests, such as idempotent responses. Entries
+/// can expire; expired entries read as absent and are swept out as new ones are written.
+///
+/// The store lives in memory and is shared by every pipeline of a hub, so config reloads
+/// keep it. A store opened on a file also writes every change through to it, so a process
+/// restart picks up where the last one stopped; otherwise it starts empty.
+pub struct StateStore {
+    entries: Mutex<Entries>,
+    path: Option<PathBuf>,
+}
+
+impl Default for StateStore {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                map: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+            path: None,
+        }
+    }
+}
+
+impl StateStore {
+    /// A store kept in the JSON file at `path`, starting with the live entries it holds.
+    /// The file is created on the first write if it doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let stored: HashMap<String, StoredEntry> = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .with_context(|| format!("Invalid state store file {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read state store {}", path.display()));
+            }
+        };
+
+        let now = Instant::now();
+        let wall_now = Utc::now();
+        let map = stored
+            .into_iter()
+            .filter_map(|(key, entry)| {
+                // Entries that expired while the hub was down are dropped.
+                let expires_at = match entry.expires_at {
+                    None => None,
+                    Some(expires_at) => now.checked_add((expires_at - wall_now).to_std().ok()?),
+                };
+                Some((
+                    key,
+                    Entry {
+                        value: entry.value,
+                        expires_at,
+                    },
+                ))
+            })
+            .collect();
+        Ok(Self {
+            entries: Mutex::new(Entries {
+                map,
+                last_sweep: now,
+            }),
+            path: Some(path),
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.map.get(key)?;
+        entry.is_live(Instant::now()).then(|| entry.value.clone())
+    }
+
+    /// Stores `value` under `key`, replacing any previous value. `None` keeps it until it is
+    /// removed.
+    pub fn set(&self, key: &str, value: Value, ttl: Option<Duration>) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        Self::sweep(&mut entries, now);
+        entries.map.insert(
+            key.to_string(),
+            Entry {
+                value,
+                expires_at: ttl.map(|ttl| now + ttl),
+            },
+        );
+        self.persist(&entries, now);
+    }
+
+    /// Stores `value` unless `key` already holds a live value, which is returned instead.
+    pub fn set_if_absent(&self, key: &str, value: Value, ttl: Option<Duration>) -> Option<Value> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(existing) = entries.map.get(key).filter(|entry| entry.is_live(now)) {
+            return Some(existing.value.clone());
+        }
+        Self::sweep(&mut entries, now);
+        entries.map.insert(
+            key.to_string(),
+            Entry {
+                value,
+                expires_at: ttl.map(|ttl| now + ttl),
+            },
+        );
+        self.persist(&entries, now);
+        None
+    }
+
+    /// Restarts the expiry of a live entry, or keeps it indefinitely with `None`.
+    pub fn set_ttl(&self, key: &str, ttl: Option<Duration>) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.map.get_mut(key).filter(|entry| entry.is_live(now)) {
+            entry.expires_at = ttl.map(|ttl| now + ttl);
+            self.persist(&entries, now);
+        }
+    }
+
+    pub fn remove(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.map.remove(key).is_some() {
+            self.persist(&entries, Instant::now());
+        }
+    }
+
+    /// Live entries whose key starts with `prefix`.
+    pub fn scan_prefix(&self, prefix: &str) -> Vec<(String, Value)> {
+        let now = Instant::now();
+        let entries = self.entries.lock().unwrap();
+        entries
+            .map
+            .iter()
+            .filter(|(key, entry)| key.starts_with(prefix) && entry.is_live(now))
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect()
+    }
+
+    fn sweep(entries: &mut Entries, now: Instant) {
+        if now.saturating_duration_since(entries.last_sweep) < SWEEP_INTERVAL {
+            return;
+        }
+        entries.map.retain(|_, entry| entry.is_live(now));
+        entries.last_sweep = now;
+    }
+
+    /// Writes the live entries to the store's file, if it has one. The file is replaced
+    /// whole, so a crash mid-write leaves the previous version. Failures are logged and the
+    /// store carries on in memory.
+    fn persist(&self, entries: &Entries, now: Instant) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let wall_now = Utc::now();
+        let stored: HashMap<&String, StoredEntry> = entries
+            .map
+            .iter()
+            .filter(|(_, entry)| entry.is_live(now))
+            .map(|(key, entry)| {
+                let expires_at = entry.expires_at.and_then(|expires_at| {
+                    let ttl = chrono::Duration::from_std(expires_at.saturating_duration_since(now))
+                        .ok()?;
+                    wall_now.checked_add_signed(ttl)
+                });
+                (
+                    key,
+                    StoredEntry {
+                        value: entry.value.clone(),
+                        expires_at,
+                    },
+                )
+            })
+            .collect();
+
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let result = serde_json::to_vec(&stored)
+            .map_err(std::io::Error::from)
+            .and_then(|contents| std::fs::write(&temp_path, contents))
+            .and_then(|()| std::fs::rename(&temp_path, path));
+        if let Err(e) = result {
+            tracing::warn!("Failed to write state store {}: {e}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_expired_entries_read_as_absent() {
+        let store = StateStore::default();
+        store.set("kept", json!(1), None);
+        store.set("expired", json!(2), Some(Duration::ZERO));
+        assert_eq!(store.get("kept"), Some(json!(1)));
+        assert_eq!(store.get("expired"), None);
+        assert_eq!(store.scan_prefix(""), vec![("kept".to_string(), json!(1))]);
+
+        // An expired entry doesn't block a new one.
+        assert_eq!(store.set_if_absent("expired", json!(3), None), None);
+        assert_eq!(
+            store.set_if_absent("expired", json!(4), None),
+            Some(json!(3))
+        );
+        store.remove("expired");
+        assert_eq!(store.get("expired"), None);
+
+        store.set_ttl("kept", Some(Duration::ZERO));
+        assert_eq!(store.get("kept"), None);
+    }
+
+    #[test]
+    fn test_file_store_keeps_live_entries_across_opens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let store = StateStore::open(&path).unwrap();
+        store.set("kept", json!(1), None);
+        store.set("expiring", json!(2), Some(Duration::from_secs(3600)));
+        store.set("expired", json!(3), Some(Duration::ZERO));
+        store.set("removed", json!(4), None);
+        store.remove("removed");
+        drop(store);
+
+        let reopened = StateStore::open(&path).unwrap();
+        let mut entries = reopened.scan_prefix("");
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            entries,
+            vec![
+                ("expiring".to_string(), json!(2)),
+                ("kept".to_string(), json!(1)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use crate::metrics::{counter, gauge};
+use crate::pipelines::buffered_body::read_request_body;
+use crate::pipelines::idempotency::is_stream_request;
+use crate::state::AppState;
+use crate::types::General;
+use axum::Json;
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+pub const ACTIVE_STREAMS_METRIC: &str = "hub_streams_active";
+pub const ACTIVE_STREAM_CLIENTS_METRIC: &str = "hub_stream_clients_active";
+pub const REJECTED_STREAMS_METRIC: &str = "hub_streams_rejected_total";
+pub const TOO_MANY_STREAMS_CODE: &str = "too_many_streams";
+
+/// The stream caps of the live configuration. `None` leaves a cap off.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StreamLimits {
+    pub global: Option<usize>,
+    pub per_key: Option<usize>,
+}
+
+impl StreamLimits {
+    pub fn from_general(general: &General) -> Self {
+        Self {
+            global: general
+                .max_concurrent_streams_global
+                .map(|limit| limit as usize),
+            per_key: general
+                .max_concurrent_streams_per_key
+                .map(|limit| limit as usize),
+        }
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.global.is_none() && self.per_key.is_none()
+    }
+}
+
+/// Returned when a streaming request would go over one of the caps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TooManyStreams {
+    Global,
+    PerKey,
+}
+
+impl TooManyStreams {
+    fn label(self) -> &'static str {
+        match self {
+            TooManyStreams::Global => "global",
+            TooManyStreams::PerKey => "per_key",
+        }
+    }
+}
+
+impl IntoResponse for TooManyStreams {
+    fn into_response(self) -> Response {
+        let message = match self {
+            TooManyStreams::Global => "The gateway has too many open streams, retry later",
+            TooManyStreams::PerKey => "This client has too many open streams, close one first",
+        };
+        let body = json!({
+            "error": {
+                "type": "rate_limit_error",
+                "message": message,
+                "param": null,
+                "code": TOO_MANY_STREAMS_CODE,
+            }
+        });
+        (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    total: usize,
+    per_key: HashMap<String, usize>,
+}
+
+/// Open streaming responses, in total and per client. Shared by every config the gateway
+/// serves, so reloads don't forget streams that are still open.
+#[derive(Debug, Default)]
+pub struct StreamLimiter {
+    counts: Mutex<Counts>,
+}
+
+/// An open stream's slot, freed when dropped: when the stream ends, fails or its client
+/// disconnects.
+#[derive(Debug)]
+pub struct StreamSlot {
+    limiter: Arc<StreamLimiter>,
+    key: String,
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        self.limiter.release(&self.key);
+    }
+}
+
+impl StreamLimiter {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Takes a slot for a stream of the client `key`, or fails if either cap is reached.
+    pub fn acquire(
+        self: &Arc<Self>,
+        key: &str,
+        limits: StreamLimits,
+    ) -> Result<StreamSlot, TooManyStreams> {
+        let mut counts = self.counts.lock().unwrap();
+        let rejection = if limits.global.is_some_and(|limit| counts.total >= limit) {
+            Some(TooManyStreams::Global)
+        } else if limits
+            .per_key
+            .is_some_and(|limit| counts.per_key.get(key).copied().unwrap_or(0) >= limit)
+        {
+            Some(TooManyStreams::PerKey)
+        } else {
+            None
+        };
+        if let Some(rejection) = rejection {
+            counter!(REJECTED_STREAMS_METRIC, "limit" => rejection.label()).increment(1);
+            return Err(rejection);
+        }
+        counts.total += 1;
+        *counts.per_key.entry(key.to_string()).or_default() += 1;
+        record(&counts);
+        Ok(StreamSlot {
+            limiter: self.clone(),
+            key: key.to_string(),
+        })
+    }
+
+    /// Takes a slot for a stream of the client `key` regardless of the caps.
+    pub fn track(self: &Arc<Self>, key: &str) -> StreamSlot {
+        self.acquire(key, StreamLimits::default())
+            .expect("streams without caps are never rejected")
+    }
+
+    /// Streams open in total, and for the client `key`.
+    pub fn active(&self, key: &str) -> (usize, usize) {
+        let counts = self.counts.lock().unwrap();
+        (counts.total, counts.per_key.get(key).copied().unwrap_or(0))
+    }
+
+    fn release(&self, key: &str) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.total -= 1;
+        if let Some(count) = counts.per_key.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                counts.per_key.remove(key);
+            }
+        }
+        record(&counts);
+    }
+}
+
+fn record(counts: &Counts) {
+    gauge!(ACTIVE_STREAMS_METRIC).set(counts.total as f64);
+    gauge!(ACTIVE_STREAM_CLIENTS_METRIC).set(counts.per_key.len() as f64);
+}
+
+/// Who a stream counts against: the client's IP address. Gateway requests carry no
+/// verified identity, so headers such as the API key aren't used; a client could pick a
+/// new one for every stream.
+pub fn client_key(request: &Request) -> String {
+    match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(address)) => format!("ip:{}", address.ip()),
+        None => "unknown".to_string(),
+    }
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"text/event-stream"))
+}
+
+/// Middleware counting open streaming responses and, when a cap is set, rejecting
+/// streaming requests over it with 429 before they reach a provider.
+pub async fn limit_streams(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limiter = state.stream_limiter();
+    let limits = state.stream_limits();
+    let key = client_key(&request);
+
+    let (request, slot) = if limits.is_unlimited() {
+        (request, None)
+    } else {
+        let (parts, body) = request.into_parts();
+        let body = match read_request_body(body, state.settings().max_buffered_body_bytes).await {
+            Ok(body) => body,
+            Err(response) => return response,
+        };
+        let slot = if is_stream_request(&body) {
+            match limiter.acquire(&key, limits) {
+                Ok(slot) => Some(slot),
+                Err(rejection) => return rejection.into_response(),
+            }
+        } else {
+            None
+        };
+        (Request::from_parts(parts, Body::from(body)), slot)
+    };
+
+    let response = next.run(request).await;
+    // Requests that failed before streaming give their slot back here.
+    if !is_event_stream(response.headers()) {
+        return response;
+    }
+    let slot = match slot {
+        Some(slot) => slot,
+        // Counted without a cap, so the gauges cover every stream.
+        None => limiter.track(&key),
+    };
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _slot = &slot;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_count_against_both_caps() {
+        let limiter = StreamLimiter::new();
+        let limits = StreamLimits {
+            global: Some(3),
+            per_key: Some(2),
+        };
+        let first = limiter.acquire("a", limits).unwrap();
+        let _second = limiter.acquire("a", limits).unwrap();
+        assert_eq!(
+            limiter.acquire("a", limits).err(),
+            Some(TooManyStreams::PerKey)
+        );
+        let _third = limiter.acquire("b", limits).unwrap();
+        assert_eq!(
+            limiter.acquire("c", limits).err(),
+            Some(TooManyStreams::Global)
+        );
+        assert_eq!(limiter.active("a"), (3, 2));
+
+        drop(first);
+        assert_eq!(limiter.active("a"), (2, 1));
+        limiter.acquire("a", limits).unwrap();
+    }
+
+    #[test]
+    fn test_released_keys_are_forgotten() {
+        let limiter = StreamLimiter::new();
+        drop(limiter.track("a"));
+        assert!(limiter.counts.lock().unwrap().per_key.is_empty());
+        assert_eq!(limiter.active("a"), (0, 0));
+    }
+}
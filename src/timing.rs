@@ -0,0 +1,180 @@
+use crate::metrics::histogram;
+use axum::http::{HeaderName, HeaderValue};
+use axum::response::Response;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const UPSTREAM_TTFB_HEADER: HeaderName = HeaderName::from_static("x-hub-upstream-ttfb-ms");
+pub const OVERHEAD_HEADER: HeaderName = HeaderName::from_static("x-hub-overhead-ms");
+
+tokio::task_local! {
+    static CURRENT: Arc<RequestTiming>;
+}
+
+#[derive(Debug, Default)]
+struct Marks {
+    upstream_start: Option<Instant>,
+    first_byte: Option<Instant>,
+    first_content: Option<Instant>,
+    upstream_end: Option<Instant>,
+}
+
+/// Tracks where a single request spends its time between the hub and the upstream provider.
+#[derive(Debug)]
+pub struct RequestTiming {
+    started: Instant,
+    marks: Mutex<Marks>,
+}
+
+/// Latency breakdown of a finished request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimingBreakdown {
+    pub request_translation: Duration,
+    pub upstream_ttfb: Duration,
+    pub upstream_total: Duration,
+    pub response_translation: Duration,
+    pub total: Duration,
+}
+
+impl TimingBreakdown {
+    /// Time spent in the hub rather than waiting on the upstream.
+    pub fn overhead(&self) -> Duration {
+        self.total.saturating_sub(self.upstream_total)
+    }
+
+    pub fn record_metrics(&self, provider: &str) {
+        for (name, value) in [
+            ("hub_request_translation_seconds", self.request_translation),
+            ("hub_upstream_ttfb_seconds", self.upstream_ttfb),
+            ("hub_upstream_duration_seconds", self.upstream_total),
+            (
+                "hub_response_translation_seconds",
+                self.response_translation,
+            ),
+            ("hub_overhead_seconds", self.overhead()),
+        ] {
+            histogram!(name, "provider" => provider.to_string()).record(value.as_secs_f64());
+        }
+    }
+
+    /// Adds `x-hub-upstream-ttfb-ms` and `x-hub-overhead-ms` to a response.
+    pub fn inject_headers(&self, response: &mut Response) {
+        for (name, value) in [
+            (UPSTREAM_TTFB_HEADER, self.upstream_ttfb),
+            (OVERHEAD_HEADER, self.overhead()),
+        ] {
+            let millis = format!("{:.3}", value.as_secs_f64() * 1000.0);
+            if let Ok(value) = HeaderValue::from_str(&millis) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+    }
+}
+
+impl RequestTiming {
+    pub fn start() -> Arc<Self> {
+        Arc::new(Self {
+            started: Instant::now(),
+            marks: Mutex::new(Marks::default()),
+        })
+    }
+
+    /// Runs a provider call with this timing as the current request's timing.
+    pub async fn scope<F: Future>(self: &Arc<Self>, future: F) -> F::Output {
+        CURRENT.scope(self.clone(), future).await
+    }
+
+    fn mark(&self, update: impl FnOnce(&mut Marks, Instant)) {
+        let now = Instant::now();
+        if let Ok(mut marks) = self.marks.lock() {
+            update(&mut marks, now);
+        }
+    }
+
+    /// Marks the first streamed content delta, which is the TTFB for streaming responses.
+    pub fn mark_first_content(&self) {
+        self.mark(|marks, now| {
+            marks.first_content.get_or_insert(now);
+        });
+    }
+
+    pub fn mark_upstream_done(&self) {
+        self.mark(|marks, now| {
+            marks.upstream_end.get_or_insert(now);
+        });
+    }
+
+    /// Returns the breakdown, or `None` if no upstream call was made.
+    pub fn finish(&self) -> Option<TimingBreakdown> {
+        let finished = Instant::now();
+        let marks = self.marks.lock().ok()?;
+        let upstream_start = marks.upstream_start?;
+        let first_byte = marks.first_content.or(marks.first_byte).unwrap_or(finished);
+        let upstream_end = marks.upstream_end.unwrap_or(finished);
+        Some(TimingBreakdown {
+            request_translation: upstream_start.saturating_duration_since(self.started),
+            upstream_ttfb: first_byte.saturating_duration_since(upstream_start),
+            upstream_total: upstream_end.saturating_duration_since(upstream_start),
+            response_translation: finished.saturating_duration_since(upstream_end),
+            total: finished.saturating_duration_since(self.started),
+        })
+    }
+}
+
+fn with_current(update: impl FnOnce(&RequestTiming)) {
+    let _ = CURRENT.try_with(|timing| update(timing));
+}
+
+/// Awaits an upstream call, recording when it was sent and when its response headers arrived.
+pub async fn upstream<F: Future>(call: F) -> F::Output {
+    with_current(|timing| timing.mark(|marks, now| marks.upstream_start = Some(now)));
+    let output = call.await;
+    with_current(|timing| timing.mark(|marks, now| marks.first_byte = Some(now)));
+    output
+}
+
+/// `RequestBuilder::send` that records upstream timing for the current request.
+pub trait TimedSend {
+    fn send_timed(self) -> impl Future<Output = reqwest::Result<reqwest::Response>> + Send;
+}
+
+impl TimedSend for reqwest::RequestBuilder {
+    fn send_timed(self) -> impl Future<Output = reqwest::Result<reqwest::Response>> + Send {
+        upstream(self.send())
+    }
+}
+
+/// Marks the upstream response body as fully received; the rest is response translation.
+pub fn mark_upstream_done() {
+    with_current(RequestTiming::mark_upstream_done);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_breakdown_covers_upstream_call() {
+        let timing = RequestTiming::start();
+        timing
+            .scope(async {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                upstream(tokio::time::sleep(Duration::from_millis(20))).await;
+                mark_upstream_done();
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            })
+            .await;
+
+        let breakdown = timing.finish().unwrap();
+        assert!(breakdown.request_translation >= Duration::from_millis(5));
+        assert!(breakdown.upstream_ttfb >= Duration::from_millis(20));
+        assert!(breakdown.response_translation >= Duration::from_millis(5));
+        assert!(breakdown.overhead() < breakdown.total);
+    }
+
+    #[test]
+    fn test_no_upstream_call_has_no_breakdown() {
+        assert!(RequestTiming::start().finish().is_none());
+    }
+}
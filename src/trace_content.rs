@@ -0,0 +1,311 @@
+use crate::types::{ContentRule, MessageContentRules, TraceContentPolicy};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Applies `rule` to `text`, or `None` when the text is excluded. Traces and artifacts both
+/// redact through here, so a hashed value matches across them.
+pub fn redact(rule: ContentRule, text: &str) -> Option<String> {
+    match rule {
+        ContentRule::Include => Some(text.to_string()),
+        ContentRule::Exclude => None,
+        ContentRule::Hash => Some(format!(
+            "sha256:{}",
+            hex::encode(Sha256::digest(text.as_bytes()))
+        )),
+        ContentRule::Truncate(chars) => Some(text.chars().take(chars).collect()),
+    }
+}
+
+impl TraceContentPolicy {
+    /// What `trace_content_enabled: false` stands for.
+    pub const EXCLUDE_ALL: Self = Self {
+        messages: MessageContentRules {
+            user: ContentRule::Exclude,
+            system: ContentRule::Exclude,
+        },
+        tool_results: ContentRule::Exclude,
+        response_content: ContentRule::Exclude,
+    };
+
+    /// The rule for a request message sent with `role`.
+    pub fn message_rule(&self, role: &str) -> ContentRule {
+        match role {
+            "system" | "developer" => self.messages.system,
+            "assistant" => self.response_content,
+            "tool" | "function" => self.tool_results,
+            _ => self.messages.user,
+        }
+    }
+
+    /// Redacts a chat, completion, embeddings or Anthropic messages request body in place.
+    pub fn redact_request(&self, body: &mut Value) {
+        let Some(body) = body.as_object_mut() else {
+            return;
+        };
+        redact_field(body, "system", self.messages.system);
+        redact_field(body, "prompt", self.messages.user);
+        redact_field(body, "input", self.messages.user);
+
+        let Some(Value::Array(messages)) = body.get_mut("messages") else {
+            return;
+        };
+        for message in messages.iter_mut().filter_map(Value::as_object_mut) {
+            let role = message
+                .get("role")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let rule = self.message_rule(role);
+            let Some(Value::Array(blocks)) = message.get_mut("content") else {
+                redact_field(message, "content", rule);
+                continue;
+            };
+            // Anthropic sends tool results as blocks of user messages.
+            blocks.retain_mut(|block| {
+                let rule = if block["type"] == "tool_result" {
+                    self.tool_results
+                } else {
+                    rule
+                };
+                if rule == ContentRule::Exclude {
+                    return false;
+                }
+                redact_value(rule, block);
+                true
+            });
+        }
+    }
+
+    /// Redacts a response body in place. An array is taken for the `data` payloads of a
+    /// streamed response.
+    pub fn redact_response(&self, body: &mut Value) {
+        let rule = self.response_content;
+        match body {
+            Value::Array(payloads) => {
+                for payload in payloads {
+                    self.redact_response(payload);
+                }
+            }
+            Value::Object(body) => {
+                if let Some(Value::Array(choices)) = body.get_mut("choices") {
+                    for choice in choices.iter_mut().filter_map(Value::as_object_mut) {
+                        redact_field(choice, "text", rule);
+                        for key in ["message", "delta"] {
+                            if let Some(Value::Object(message)) = choice.get_mut(key) {
+                                redact_field(message, "content", rule);
+                            }
+                        }
+                    }
+                }
+                // Anthropic messages and their stream events.
+                redact_field(body, "content", rule);
+                if let Some(Value::Object(delta)) = body.get_mut("delta") {
+                    redact_field(delta, "text", rule);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn redact_field(object: &mut Map<String, Value>, key: &str, rule: ContentRule) {
+    if rule == ContentRule::Exclude {
+        object.remove(key);
+    } else if let Some(value) = object.get_mut(key) {
+        redact_value(rule, value);
+    }
+}
+
+/// Redacts strings, the text of content blocks and the items of input lists. Anything else,
+/// such as token ids or image blocks, is redacted as its JSON.
+fn redact_value(rule: ContentRule, value: &mut Value) {
+    if rule == ContentRule::Include {
+        return;
+    }
+    match value {
+        Value::Null => {}
+        Value::String(text) => *text = redact(rule, text).unwrap_or_default(),
+        Value::Array(items)
+            if items
+                .iter()
+                .any(|item| item.is_string() || item.is_object()) =>
+        {
+            for item in items {
+                redact_value(rule, item);
+            }
+        }
+        Value::Object(block) if block.contains_key("text") || block.contains_key("content") => {
+            for key in ["text", "content"] {
+                if let Some(field) = block.get_mut(key) {
+                    redact_value(rule, field);
+                }
+            }
+        }
+        other => *other = Value::String(redact(rule, &other.to_string()).unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const HELLO_SHA256: &str =
+        "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    fn policy(user: &str, system: &str, tool_results: &str, response: &str) -> TraceContentPolicy {
+        TraceContentPolicy {
+            messages: MessageContentRules {
+                user: user.parse().unwrap(),
+                system: system.parse().unwrap(),
+            },
+            tool_results: tool_results.parse().unwrap(),
+            response_content: response.parse().unwrap(),
+        }
+    }
+
+    fn request() -> Value {
+        json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "system", "content": "hello"},
+                {"role": "user", "content": [{"type": "text", "text": "hello"}]},
+                {"role": "assistant", "content": null, "tool_calls": [{"id": "call_1"}]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "hello"}
+            ]
+        })
+    }
+
+    fn response() -> Value {
+        json!({
+            "id": "chatcmpl-1",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "hello"},
+                "finish_reason": "stop"
+            }]
+        })
+    }
+
+    fn redacted(policy: TraceContentPolicy) -> (Value, Value) {
+        let (mut request, mut response) = (request(), response());
+        policy.redact_request(&mut request);
+        policy.redact_response(&mut response);
+        (request, response)
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact(ContentRule::Include, "hello").unwrap(), "hello");
+        assert_eq!(redact(ContentRule::Exclude, "hello"), None);
+        assert_eq!(redact(ContentRule::Hash, "hello").unwrap(), HELLO_SHA256);
+        assert_eq!(redact(ContentRule::Truncate(2), "héllo").unwrap(), "hé");
+        assert_eq!(redact(ContentRule::Truncate(10), "hello").unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_parse_content_rules() {
+        assert_eq!("Hash".parse::<ContentRule>(), Ok(ContentRule::Hash));
+        assert_eq!(
+            "truncate:200".parse::<ContentRule>(),
+            Ok(ContentRule::Truncate(200))
+        );
+        assert!("truncate:0".parse::<ContentRule>().is_err());
+        assert!("truncate".parse::<ContentRule>().is_err());
+        assert!("mask".parse::<ContentRule>().is_err());
+
+        let policy: TraceContentPolicy =
+            serde_yaml::from_str("messages:\n  user: hash\ntool_results: truncate:100\n").unwrap();
+        assert_eq!(policy, policy("hash", "include", "truncate:100", "include"));
+        assert_eq!(
+            serde_json::to_value(policy).unwrap()["tool_results"],
+            "truncate:100"
+        );
+        assert!(serde_yaml::from_str::<TraceContentPolicy>("prompts: exclude\n").is_err());
+    }
+
+    #[test]
+    fn test_include_keeps_bodies() {
+        let (request_body, response_body) = redacted(TraceContentPolicy::default());
+        assert_eq!(request_body, request());
+        assert_eq!(response_body, response());
+    }
+
+    #[test]
+    fn test_exclude_removes_content() {
+        let (request, response) = redacted(TraceContentPolicy::EXCLUDE_ALL);
+        for message in request["messages"].as_array().unwrap() {
+            assert!(message.get("content").is_none());
+        }
+        assert_eq!(request["messages"][2]["tool_calls"][0]["id"], "call_1");
+        assert!(response["choices"][0]["message"].get("content").is_none());
+        assert_eq!(response["choices"][0]["finish_reason"], "stop");
+    }
+
+    #[test]
+    fn test_hash_is_stable_across_fields() {
+        let (request, response) = redacted(policy("hash", "hash", "hash", "hash"));
+        assert_eq!(request["messages"][0]["content"], HELLO_SHA256);
+        assert_eq!(request["messages"][1]["content"][0]["text"], HELLO_SHA256);
+        assert_eq!(request["messages"][1]["content"][0]["type"], "text");
+        assert_eq!(request["messages"][3]["content"], HELLO_SHA256);
+        assert_eq!(response["choices"][0]["message"]["content"], HELLO_SHA256);
+    }
+
+    #[test]
+    fn test_truncate_cuts_content() {
+        let (request, response) =
+            redacted(policy("truncate:3", "include", "truncate:1", "truncate:4"));
+        assert_eq!(request["messages"][0]["content"], "hello");
+        assert_eq!(request["messages"][1]["content"][0]["text"], "hel");
+        assert_eq!(request["messages"][3]["content"], "h");
+        assert_eq!(response["choices"][0]["message"]["content"], "hell");
+    }
+
+    #[test]
+    fn test_rules_apply_per_field() {
+        let (request, response) = redacted(policy("include", "exclude", "hash", "truncate:2"));
+        assert!(request["messages"][0].get("content").is_none());
+        assert_eq!(request["messages"][1]["content"][0]["text"], "hello");
+        assert_eq!(request["messages"][3]["content"], HELLO_SHA256);
+        assert_eq!(response["choices"][0]["message"]["content"], "he");
+    }
+
+    #[test]
+    fn test_other_request_and_response_shapes() {
+        let policy = policy("hash", "exclude", "exclude", "truncate:2");
+
+        let mut completion = json!({"prompt": "hello", "model": "gpt-3.5-turbo-instruct"});
+        policy.redact_request(&mut completion);
+        assert_eq!(completion["prompt"], HELLO_SHA256);
+
+        let mut embeddings = json!({"input": ["hello", "hello"]});
+        policy.redact_request(&mut embeddings);
+        assert_eq!(embeddings["input"], json!([HELLO_SHA256, HELLO_SHA256]));
+
+        let mut messages = json!({
+            "system": "be brief",
+            "messages": [{"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": "sunny"},
+                {"type": "text", "text": "hello"}
+            ]}]
+        });
+        policy.redact_request(&mut messages);
+        assert!(messages.get("system").is_none());
+        assert_eq!(
+            messages["messages"][0]["content"],
+            json!([{"type": "text", "text": HELLO_SHA256}])
+        );
+
+        let mut streamed = json!([
+            {"choices": [{"index": 0, "delta": {"role": "assistant", "content": "hello"}}]},
+            {"choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}]}
+        ]);
+        policy.redact_response(&mut streamed);
+        assert_eq!(streamed[0]["choices"][0]["delta"]["content"], "he");
+        assert_eq!(streamed[1]["choices"][0]["finish_reason"], "stop");
+
+        let mut text_completion = json!({"choices": [{"index": 0, "text": "hello"}]});
+        policy.redact_response(&mut text_completion);
+        assert_eq!(text_completion["choices"][0]["text"], "he");
+    }
+}
@@ -0,0 +1,151 @@
+use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use opentelemetry::Context;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{FutureExt, SpanContext, TraceContextExt, TraceId};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use reqwest::RequestBuilder;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Trace id of the hub's span for a request, to find it in the tracing backend.
+pub const HEADER_TRACE_ID: HeaderName = HeaderName::from_static("x-hub-trace-id");
+
+tokio::task_local! {
+    static TRACE_ID: Arc<Mutex<Option<TraceId>>>;
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+/// The W3C trace context sent in `headers`, or an empty context when there is none or
+/// it's malformed.
+pub fn extract_trace_context(headers: &HeaderMap) -> Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+/// Pipeline middleware making the caller's `traceparent` the parent of the spans the hub
+/// starts for the request. Without one they start a new trace. The trace id is returned in
+/// `x-hub-trace-id`.
+pub async fn propagate_trace_context(request: Request, next: Next) -> Response {
+    let parent = extract_trace_context(request.headers());
+    let parent_trace_id = parent.span().span_context().trace_id();
+    let trace_id = Arc::new(Mutex::new(
+        Some(parent_trace_id).filter(|id| *id != TraceId::INVALID),
+    ));
+    let mut response = TRACE_ID
+        .scope(trace_id.clone(), next.run(request).with_context(parent))
+        .await;
+    let trace_id = trace_id.lock().ok().and_then(|trace_id| *trace_id);
+    if let Some(trace_id) = trace_id {
+        if let Ok(value) = HeaderValue::from_str(&trace_id.to_string()) {
+            response.headers_mut().insert(HEADER_TRACE_ID, value);
+        }
+    }
+    response
+}
+
+/// Notes the trace of a span started for the current request, for `x-hub-trace-id`.
+/// Spans without a valid context, such as those of a disabled tracer outside of a trace,
+/// are ignored.
+pub fn record_trace_id(span_context: &SpanContext) {
+    if !span_context.is_valid() {
+        return;
+    }
+    let _ = TRACE_ID.try_with(|trace_id| {
+        if let Ok(mut trace_id) = trace_id.lock() {
+            *trace_id = Some(span_context.trace_id());
+        }
+    });
+}
+
+/// Adds `traceparent` and `tracestate` for the current span to an upstream request, so
+/// the provider's spans join the trace. Nothing is added outside of a trace.
+pub fn inject_trace_context(builder: RequestBuilder) -> RequestBuilder {
+    let mut headers = HashMap::new();
+    TraceContextPropagator::new().inject_context(&Context::current(), &mut headers);
+    headers.into_iter().fold(builder, |builder, (name, value)| {
+        builder.header(name, value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanId, TraceFlags, TraceState};
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_trace_context() {
+        let context = extract_trace_context(&headers(&[
+            ("traceparent", TRACEPARENT),
+            ("tracestate", "vendor=value"),
+        ]));
+        let span_context = context.span().span_context().clone();
+        assert!(span_context.is_remote());
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(span_context.span_id().to_string(), "00f067aa0ba902b7");
+        assert!(span_context.is_sampled());
+        assert_eq!(span_context.trace_state().get("vendor"), Some("value"));
+
+        for traceparent in [
+            "",
+            "garbage",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        ] {
+            let context = extract_trace_context(&headers(&[("traceparent", traceparent)]));
+            assert!(!context.span().span_context().is_valid());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inject_trace_context() {
+        let client = reqwest::Client::new();
+        let request = inject_trace_context(client.post("http://localhost/v1"))
+            .build()
+            .unwrap();
+        assert!(!request.headers().contains_key("traceparent"));
+
+        let span_context = SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::default(),
+        );
+        let context = Context::current().with_remote_span_context(span_context);
+        let request = async { inject_trace_context(client.post("http://localhost/v1")) }
+            .with_context(context)
+            .await
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["traceparent"], TRACEPARENT);
+    }
+}
@@ -0,0 +1,224 @@
+//! The `x-traceloop-*` attribute headers Traceloop SDKs send, so the hub's spans join the
+//! caller's workflows.
+
+use axum::extract::{Request, State};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use opentelemetry::KeyValue;
+use opentelemetry::global::{BoxedSpan, ObjectSafeSpan};
+use reqwest::RequestBuilder;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::Instrument;
+
+pub const HEADER_WORKFLOW_NAME: &str = "x-traceloop-workflow-name";
+pub const HEADER_ENTITY_NAME: &str = "x-traceloop-entity-name";
+/// Followed by the property's key, e.g. `x-traceloop-association-property-user_id`.
+pub const HEADER_ASSOCIATION_PROPERTY_PREFIX: &str = "x-traceloop-association-property-";
+
+/// At most this many headers are taken from a request; the rest are ignored.
+pub const MAX_TRACELOOP_HEADERS: usize = 16;
+/// Association property keys longer than this are ignored.
+pub const MAX_PROPERTY_KEY_BYTES: usize = 64;
+/// Values longer than this are truncated.
+pub const MAX_VALUE_BYTES: usize = 256;
+
+tokio::task_local! {
+    static TRACELOOP_HEADERS: Arc<CapturedHeaders>;
+}
+
+/// The attribute headers of the current request, and whether upstream requests carry them.
+struct CapturedHeaders {
+    headers: TraceloopHeaders,
+    forward: bool,
+}
+
+/// The attribute headers of a request, in the order they were sent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceloopHeaders {
+    headers: Vec<(String, String)>,
+}
+
+impl TraceloopHeaders {
+    /// The documented attribute headers in `headers`, capped by [`MAX_TRACELOOP_HEADERS`],
+    /// [`MAX_PROPERTY_KEY_BYTES`] and [`MAX_VALUE_BYTES`]. Values that aren't visible
+    /// ASCII are ignored.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let headers = headers
+            .iter()
+            .filter(|(name, _)| attribute_name(name.as_str()).is_some())
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?;
+                Some((name.as_str().to_string(), truncate(value).to_string()))
+            })
+            .take(MAX_TRACELOOP_HEADERS)
+            .collect();
+        Self { headers }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    /// Span attributes as Traceloop's SDKs name them, e.g. `traceloop.workflow.name`.
+    pub fn attributes(&self) -> impl Iterator<Item = (String, &str)> {
+        self.headers.iter().filter_map(|(name, value)| {
+            attribute_name(name).map(|attribute| (attribute, value.as_str()))
+        })
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn association_properties(&self) -> BTreeMap<&str, &str> {
+        self.headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let key = name.strip_prefix(HEADER_ASSOCIATION_PROPERTY_PREFIX)?;
+                Some((key, value.as_str()))
+            })
+            .collect()
+    }
+}
+
+/// The span attribute a header is recorded as, if it's one of the documented ones.
+fn attribute_name(header: &str) -> Option<String> {
+    match header {
+        HEADER_WORKFLOW_NAME => Some("traceloop.workflow.name".to_string()),
+        HEADER_ENTITY_NAME => Some("traceloop.entity.name".to_string()),
+        _ => header
+            .strip_prefix(HEADER_ASSOCIATION_PROPERTY_PREFIX)
+            .filter(|key| !key.is_empty() && key.len() <= MAX_PROPERTY_KEY_BYTES)
+            .map(|key| format!("traceloop.association.properties.{key}")),
+    }
+}
+
+fn truncate(value: &str) -> &str {
+    // Header values that pass `to_str` are ASCII, so any index is a char boundary.
+    &value[..value.len().min(MAX_VALUE_BYTES)]
+}
+
+/// Pipeline middleware taking the attribute headers of a request, for the spans the hub
+/// starts and, when `forward` is set by `forward_traceloop_headers`, the upstream requests
+/// it sends. Logs within the request carry them as fields of a `traceloop` span.
+pub async fn capture_traceloop_headers(
+    State(forward): State<bool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = TraceloopHeaders::from_headers(request.headers());
+    if headers.is_empty() {
+        return next.run(request).await;
+    }
+    let span = tracing::info_span!(
+        "traceloop",
+        workflow_name = headers.get(HEADER_WORKFLOW_NAME),
+        entity_name = headers.get(HEADER_ENTITY_NAME),
+        association_properties = ?headers.association_properties(),
+    );
+    let captured = Arc::new(CapturedHeaders { headers, forward });
+    TRACELOOP_HEADERS
+        .scope(captured, next.run(request).instrument(span))
+        .await
+}
+
+/// Sets the current request's attribute headers as attributes of `span`.
+pub fn record_traceloop_attributes(span: &mut BoxedSpan) {
+    let _ = TRACELOOP_HEADERS.try_with(|captured| {
+        for (attribute, value) in captured.headers.attributes() {
+            span.set_attribute(KeyValue::new(attribute, value.to_string()));
+        }
+    });
+}
+
+/// Adds the current request's attribute headers to an upstream request when
+/// `forward_traceloop_headers` is on.
+pub fn inject_traceloop_headers(builder: RequestBuilder) -> RequestBuilder {
+    match TRACELOOP_HEADERS.try_with(Arc::clone) {
+        Ok(captured) if captured.forward => captured
+            .headers
+            .headers
+            .iter()
+            .fold(builder, |builder, (name, value)| {
+                builder.header(name, value)
+            }),
+        _ => builder,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderName, HeaderValue};
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.parse::<HeaderName>().unwrap(),
+                    HeaderValue::from_str(value).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_attributes_of_documented_headers() {
+        let headers = TraceloopHeaders::from_headers(&headers(&[
+            ("x-traceloop-workflow-name", "checkout"),
+            ("x-traceloop-entity-name", "summarize"),
+            ("x-traceloop-association-property-user_id", "u-1"),
+            ("x-traceloop-pipeline", "default"),
+            ("x-traceloop-association-property-", "no key"),
+            ("authorization", "Bearer sk"),
+        ]));
+        let attributes: Vec<_> = headers.attributes().collect();
+        assert_eq!(
+            attributes,
+            vec![
+                ("traceloop.workflow.name".to_string(), "checkout"),
+                ("traceloop.entity.name".to_string(), "summarize"),
+                (
+                    "traceloop.association.properties.user_id".to_string(),
+                    "u-1"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_caps() {
+        let long_value = "v".repeat(MAX_VALUE_BYTES + 10);
+        let long_key = format!("x-traceloop-association-property-{}", "k".repeat(65));
+        let mut pairs: Vec<(String, String)> = vec![
+            ("x-traceloop-workflow-name".to_string(), long_value),
+            (long_key, "ignored".to_string()),
+        ];
+        pairs.extend((0..MAX_TRACELOOP_HEADERS * 2).map(|i| {
+            (
+                format!("x-traceloop-association-property-key{i}"),
+                i.to_string(),
+            )
+        }));
+        let pairs: Vec<_> = pairs
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+
+        let headers = TraceloopHeaders::from_headers(&headers(&pairs));
+        let attributes: Vec<_> = headers.attributes().collect();
+        assert_eq!(attributes.len(), MAX_TRACELOOP_HEADERS);
+        let workflow = attributes
+            .iter()
+            .find(|(attribute, _)| attribute == "traceloop.workflow.name")
+            .unwrap();
+        assert_eq!(workflow.1.len(), MAX_VALUE_BYTES);
+        assert!(attributes.iter().all(|(_, value)| *value != "ignored"));
+    }
+}
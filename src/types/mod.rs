@@ -1,7 +1,9 @@
+use crate::management::dto::SecretObject;
 use serde::{Deserialize, Serialize};
 // use serde_json::Value as JsonValue; // Removed
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
+use std::sync::{LazyLock, Mutex};
 use utoipa::ToSchema;
 
 fn default_trace_content_enabled() -> bool {
@@ -16,49 +18,118 @@ fn default_log_level_core() -> String {
     "warning".to_string()
 }
 
+fn default_budget_warn_at_percent() -> u8 {
+    80
+}
+
 /// Enum representing the type of LLM provider.
-#[derive(Serialize, Deserialize, Debug, ToSchema, Clone, Copy, PartialEq, Eq, Hash)]
-#[serde(rename_all = "snake_case")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ProviderType {
-    #[serde(rename = "azure")]
     Azure,
-    #[serde(rename = "openai")]
     OpenAI,
-    #[serde(rename = "anthropic")]
     Anthropic,
-    #[serde(rename = "bedrock")]
     Bedrock,
-    #[serde(rename = "vertexai")]
     VertexAI,
+    /// Answers locally with canned responses, for development and tests.
+    Mock,
+    /// Any other type, served by the factory registered for it with
+    /// `ProviderRegistry::register_factory`.
+    Custom(&'static str),
 }
 
-impl std::fmt::Display for ProviderType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl ProviderType {
+    /// The types the hub implements itself.
+    pub const BUILT_IN: [ProviderType; 6] = [
+        ProviderType::Azure,
+        ProviderType::OpenAI,
+        ProviderType::Anthropic,
+        ProviderType::Bedrock,
+        ProviderType::VertexAI,
+        ProviderType::Mock,
+    ];
+
+    /// The type called `name` in configs: a built-in type, or a custom one.
+    pub fn from_name(name: &str) -> Self {
+        Self::BUILT_IN
+            .into_iter()
+            .find(|provider_type| provider_type.name() == name)
+            .unwrap_or_else(|| ProviderType::Custom(intern_type_name(name)))
+    }
+
+    pub fn name(&self) -> &'static str {
         match self {
-            ProviderType::Azure => write!(f, "azure"),
-            ProviderType::OpenAI => write!(f, "openai"),
-            ProviderType::Anthropic => write!(f, "anthropic"),
-            ProviderType::Bedrock => write!(f, "bedrock"),
-            ProviderType::VertexAI => write!(f, "vertexai"),
+            ProviderType::Azure => "azure",
+            ProviderType::OpenAI => "openai",
+            ProviderType::Anthropic => "anthropic",
+            ProviderType::Bedrock => "bedrock",
+            ProviderType::VertexAI => "vertexai",
+            ProviderType::Mock => "mock",
+            ProviderType::Custom(name) => *name,
         }
     }
 }
 
+/// Keeps custom type names for the life of the process, so the type stays `Copy`. Configs
+/// only name a handful of types, however often they are reloaded.
+fn intern_type_name(name: &str) -> &'static str {
+    static NAMES: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+    let mut names = NAMES.lock().unwrap();
+    if let Some(&interned) = names.get(name) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(name.to_string().into_boxed_str());
+    names.insert(interned);
+    interned
+}
+
+impl std::fmt::Display for ProviderType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses the built-in types only, ignoring case, as the management API can't store custom
+/// ones.
 impl std::str::FromStr for ProviderType {
     type Err = String; // Or a custom error type
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "azure" => Ok(ProviderType::Azure),
-            "openai" => Ok(ProviderType::OpenAI),
-            "anthropic" => Ok(ProviderType::Anthropic),
-            "bedrock" => Ok(ProviderType::Bedrock),
-            "vertexai" => Ok(ProviderType::VertexAI),
-            _ => Err(format!("Unknown provider type: {s}")),
-        }
+        let name = s.to_lowercase();
+        Self::BUILT_IN
+            .into_iter()
+            .find(|provider_type| provider_type.name() == name)
+            .ok_or_else(|| format!("Unknown provider type: {s}"))
     }
 }
 
+impl Serialize for ProviderType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for ProviderType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(ProviderType::from_name(&name))
+    }
+}
+
+/// Documented as the built-in types, the only ones the management API accepts.
+impl utoipa::PartialSchema for ProviderType {
+    fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
+        let names = ProviderType::BUILT_IN.map(|provider_type| provider_type.name());
+        utoipa::openapi::RefOr::T(utoipa::openapi::schema::Schema::Object(
+            utoipa::openapi::schema::ObjectBuilder::new()
+                .schema_type(utoipa::openapi::schema::Type::String)
+                .enum_values(Some(names))
+                .build(),
+        ))
+    }
+}
+
+impl ToSchema for ProviderType {}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Provider {
     pub key: String,
@@ -67,15 +138,41 @@ pub struct Provider {
     #[serde(default = "no_api_key")]
     pub api_key: String,
 
+    /// Periods during which routers treat the provider as unavailable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+
     #[serde(flatten, default, skip_serializing_if = "HashMap::is_empty")]
     pub params: HashMap<String, String>,
 }
 
+/// A scheduled maintenance period of a provider: either a one-off window from `start` to
+/// `end`, or a recurring one starting on `schedule` and lasting `duration_minutes`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash, ToSchema)]
+pub struct MaintenanceWindow {
+    /// RFC 3339 start of a one-off window, e.g. `2025-03-01T22:00:00+01:00`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    /// RFC 3339 end of a one-off window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    /// Cron expression (`minute hour day-of-month month day-of-week`) of when a recurring
+    /// window starts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
+    /// UTC offset `schedule` is evaluated in, such as `+02:00`. Defaults to UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
 impl Hash for Provider {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.key.hash(state);
         self.r#type.hash(state);
         self.api_key.hash(state);
+        self.maintenance_windows.hash(state);
         // Hash the params by sorting keys and hashing key-value pairs
         let mut params_vec: Vec<_> = self.params.iter().collect();
         params_vec.sort_by_key(|(k, _)| *k);
@@ -93,10 +190,47 @@ pub struct ModelConfig {
     pub r#type: String,   // Actual model name, e.g., "gpt-4o"
     pub provider: String, // Key of the Provider struct
 
+    /// Must come before `params`, which would otherwise collect these keys.
+    #[serde(flatten)]
+    pub deprecation: ModelDeprecation,
     #[serde(flatten, default, skip_serializing_if = "HashMap::is_empty")]
     pub params: HashMap<String, String>,
     // ee_id: Option<Uuid>, // Removed
-    // enabled: bool, // Removed
+    /// Disabled models stay in the config but are skipped by routers and `/models`.
+    #[serde(
+        default = "default_model_enabled",
+        skip_serializing_if = "is_model_enabled"
+    )]
+    pub enabled: bool,
+}
+
+/// Turndown settings for a model its provider is retiring.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ModelDeprecation {
+    /// Requests for a deprecated model are rejected, or rerouted with `auto_replace`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deprecated: bool,
+    /// Key of the model clients should move to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    /// Serve requests with `replacement` instead of rejecting them.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub auto_replace: bool,
+    /// Date the provider stops serving the model, as `YYYY-MM-DD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<String>,
+}
+
+fn default_model_enabled() -> bool {
+    true
+}
+
+fn is_model_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl Hash for ModelConfig {
@@ -104,6 +238,8 @@ impl Hash for ModelConfig {
         self.key.hash(state);
         self.r#type.hash(state);
         self.provider.hash(state);
+        self.enabled.hash(state);
+        self.deprecation.hash(state);
         // Hash the params by sorting keys and hashing key-value pairs
         let mut params_vec: Vec<_> = self.params.iter().collect();
         params_vec.sort_by_key(|(k, _)| *k);
@@ -130,6 +266,9 @@ pub enum PluginConfig {
     Logging {
         #[serde(default = "default_log_level_core")] // Renamed default fn to avoid conflict
         level: String,
+        /// Fraction of successful requests to log; errors are always logged.
+        #[serde(default)]
+        sample_rate: SampleRate,
     },
     Tracing {
         endpoint: String,
@@ -137,7 +276,365 @@ pub enum PluginConfig {
     },
     ModelRouter {
         models: Vec<String>,
+        /// Serve `provider/model` requests without a configured model when
+        /// `general.prefix_routing` is on. Limited to the providers of `models`.
+        #[serde(default)]
+        allow_dynamic_models: bool,
+        /// Route each request to the model serving its name with the best recent latency
+        /// and error rate, instead of the first one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        adaptive: Option<AdaptiveRouting>,
+        /// Send each request to several models serving its name and answer with the first
+        /// response. Can't be combined with `adaptive`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        race: Option<RaceRouting>,
+    },
+    Metadata {
+        values: BTreeMap<String, String>,
+    },
+    Budget {
+        limit_usd: UsdAmount,
+        #[serde(default)]
+        window: BudgetWindow,
+        #[serde(default = "default_budget_warn_at_percent")]
+        warn_at_percent: u8,
     },
+    Priority {
+        /// Priority used when a request doesn't send `x-hub-priority`.
+        default: RequestPriority,
+    },
+    ParameterPolicy {
+        #[serde(default)]
+        mode: ParameterPolicyMode,
+        /// Rules keyed by top-level request field, e.g. `temperature` or `user`.
+        #[serde(default)]
+        rules: BTreeMap<String, ParameterRule>,
+        /// Accept a request's `extra_body` and merge it into the provider's request body.
+        #[serde(default)]
+        allow_extra_body: bool,
+        /// The only `extra_body` keys accepted. Empty accepts any key the hub doesn't manage.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        extra_body_keys: Vec<String>,
+    },
+    ResponseNormalization {
+        /// Nest provider-specific response fields under `provider_metadata` instead of
+        /// dropping them.
+        #[serde(default)]
+        passthrough_extra_fields: bool,
+    },
+    StreamOptions {
+        /// Buffer streamed tool call fragments and emit each call as one chunk. Requests can
+        /// override this with `x-hub-aggregate-tool-calls`.
+        #[serde(default)]
+        aggregate_tool_calls: bool,
+    },
+    DegradedMode(DegradedMode),
+    /// Overrides `general.passthrough_response_headers` for the pipeline.
+    PassthroughHeaders(PassthroughHeaders),
+    ToolLimits(ToolLimits),
+    SystemPrompt(SystemPrompt),
+}
+
+/// Settings of the model router's adaptive strategy. A model scores
+/// `latency_weight * p95 seconds + error_weight * error rate`; lowest wins.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveRouting {
+    /// How far back latencies and errors count.
+    pub window_seconds: u64,
+    /// Share of requests sent to the other models, so their scores stay current.
+    pub exploration_percent: u8,
+    pub latency_weight: f64,
+    pub error_weight: f64,
+}
+
+impl Default for AdaptiveRouting {
+    fn default() -> Self {
+        Self {
+            window_seconds: 300,
+            exploration_percent: 5,
+            latency_weight: 1.0,
+            // A 10% error rate weighs as much as a second of p95 latency.
+            error_weight: 10.0,
+        }
+    }
+}
+
+impl Hash for AdaptiveRouting {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.window_seconds.hash(state);
+        self.exploration_percent.hash(state);
+        self.latency_weight.to_bits().hash(state);
+        self.error_weight.to_bits().hash(state);
+    }
+}
+
+/// Settings of the model router's race strategy. A request is sent to the candidates
+/// serving its name, one more every `stagger_ms` until one answers, and the first answer
+/// wins. The other attempts are aborted.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RaceRouting {
+    /// Keys of the models to race, in the order requests are sent to them. Defaults to the
+    /// router's `models`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<String>,
+    /// How long an attempt may go without an answer before the next candidate is sent the
+    /// request too. 0 sends it to every candidate at once.
+    pub stagger_ms: u64,
+}
+
+/// Settings of the `degraded-mode` plugin. When the pipeline's recent error rate or p95
+/// latency crosses a threshold, chat requests go to a substitute model until it recovers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DegradedMode {
+    /// Key of the model serving requests while the pipeline is degraded.
+    pub model: String,
+    /// How far back errors and latencies count.
+    #[serde(default = "default_degraded_window_seconds")]
+    pub window_seconds: u64,
+    /// Requests a window needs before degraded mode can be entered.
+    #[serde(default = "default_degraded_min_requests")]
+    pub min_requests: u32,
+    /// Percentage of failed requests in the window that enters degraded mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_error_rate_percent: Option<u8>,
+    /// p95 latency in the window that enters degraded mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_p95_latency_ms: Option<u64>,
+    /// Degraded mode is left once the error rate and p95 latency are below this percentage
+    /// of their thresholds.
+    #[serde(default = "default_degraded_recovery_percent")]
+    pub recovery_percent: u8,
+    /// Shortest time spent in degraded mode, so the pipeline doesn't flap.
+    #[serde(default = "default_min_degraded_seconds")]
+    pub min_degraded_seconds: u64,
+    /// Share of requests still taking the normal route while degraded, so recovery is noticed.
+    #[serde(default = "default_degraded_probe_percent")]
+    pub probe_percent: u8,
+    #[serde(default, skip_serializing_if = "DegradedOverrides::is_empty")]
+    pub overrides: DegradedOverrides,
+}
+
+fn default_degraded_window_seconds() -> u64 {
+    60
+}
+
+fn default_degraded_min_requests() -> u32 {
+    20
+}
+
+fn default_degraded_recovery_percent() -> u8 {
+    50
+}
+
+fn default_min_degraded_seconds() -> u64 {
+    60
+}
+
+fn default_degraded_probe_percent() -> u8 {
+    5
+}
+
+/// Upstream response headers copied onto gateway responses, e.g. for client-side pacing
+/// on the provider's rate limits.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct PassthroughHeaders {
+    /// Upstream header names to copy. Empty copies nothing.
+    pub headers: Vec<String>,
+    /// Send the headers as `x-upstream-<name>`, so they can't collide with the hub's own.
+    pub prefix: bool,
+}
+
+impl Default for PassthroughHeaders {
+    /// OpenAI's rate-limit headers, unprefixed.
+    fn default() -> Self {
+        Self {
+            headers: [
+                "x-ratelimit-remaining-requests",
+                "x-ratelimit-remaining-tokens",
+                "x-ratelimit-reset-requests",
+                "x-ratelimit-reset-tokens",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+            prefix: false,
+        }
+    }
+}
+
+/// Settings of the `tool-limits` plugin, checked on chat requests before they are routed.
+/// Requests over a limit are rejected rather than trimmed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolLimits {
+    /// Most tools a request may define.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tools: Option<u32>,
+    /// Most bytes the request's tool definitions may take, serialized as JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tools_bytes: Option<u64>,
+    /// Deepest nesting of a function's parameter schema. A schema without properties or
+    /// items has depth 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tool_schema_depth: Option<u32>,
+    /// Tools requests may define; the others are dropped before the limits are checked. A
+    /// name ending in `*` allows every tool starting with the rest, and built-in tools are
+    /// matched by their type, e.g. `web_search_preview`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_filter: Option<Vec<String>>,
+}
+
+/// Settings of the `system-prompt` plugin, which renders a template from `prompt_templates`
+/// and prepends it to chat requests as their system message.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct SystemPrompt {
+    /// Name of the template in `prompt_templates`.
+    pub system_prompt_template: String,
+    /// Values of the template's variables.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    /// Lets requests set the variables `variables` leaves out through the
+    /// `x-hub-template-vars` header.
+    #[serde(default)]
+    pub allow_header_variables: bool,
+}
+
+/// Request parameters overridden while a pipeline is degraded.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DegradedOverrides {
+    /// Caps `max_tokens`, or `max_completion_tokens` when the request sends that instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+impl DegradedOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.max_tokens.is_none() && self.temperature.is_none()
+    }
+}
+
+impl Hash for DegradedOverrides {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.max_tokens.hash(state);
+        self.temperature.map(f32::to_bits).hash(state);
+    }
+}
+
+/// What the `parameter-policy` plugin does with a request that breaks a rule.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterPolicyMode {
+    /// Reject the request with 400.
+    #[default]
+    Strict,
+    /// Strip denied fields and clamp out-of-range values, then forward the request.
+    Sanitize,
+}
+
+/// A single `parameter-policy` rule for one request field.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterRule {
+    /// The field must not be sent upstream.
+    Deny,
+    /// The field must be a number within the given bounds.
+    Clamp {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+    },
+    /// The field must be present. Can't be sanitized, so always rejects.
+    Require,
+}
+
+impl Hash for ParameterRule {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        if let ParameterRule::Clamp { min, max } = self {
+            min.map(f64::to_bits).hash(state);
+            max.map(f64::to_bits).hash(state);
+        }
+    }
+}
+
+/// A spend amount in US dollars.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd, ToSchema)]
+#[serde(transparent)]
+pub struct UsdAmount(pub f64);
+
+impl Hash for UsdAmount {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+/// Fraction of requests to log, between 0.0 and 1.0.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd, ToSchema)]
+#[serde(transparent)]
+pub struct SampleRate(pub f64);
+
+impl Default for SampleRate {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl Hash for SampleRate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+/// Provider-neutral request priority, mapped onto each provider's service tiers.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestPriority {
+    Low,
+    #[default]
+    Default,
+    High,
+}
+
+impl std::fmt::Display for RequestPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestPriority::Low => write!(f, "low"),
+            RequestPriority::Default => write!(f, "default"),
+            RequestPriority::High => write!(f, "high"),
+        }
+    }
+}
+
+impl std::str::FromStr for RequestPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(RequestPriority::Low),
+            "default" => Ok(RequestPriority::Default),
+            "high" => Ok(RequestPriority::High),
+            _ => Err(format!(
+                "Unknown priority '{s}', expected low, default or high"
+            )),
+        }
+    }
+}
+
+/// Calendar window (UTC) over which a budget accumulates spend.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetWindow {
+    #[default]
+    Daily,
+    Monthly,
 }
 
 // Renamed from SharedPipelineConfig
@@ -151,12 +648,534 @@ pub struct Pipeline {
     pub plugins: Vec<PluginConfig>,
     // ee_id: Option<Uuid>, // Removed
     // enabled: bool, // Removed
+    /// Write each request's artifact to `general.artifact_store`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub store_artifacts: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Hash)]
 pub struct General {
+    /// Shorthand for including (`true`) or excluding (`false`) all content; `false` also
+    /// overrides `trace_content`.
     #[serde(default = "default_trace_content_enabled")]
     pub trace_content_enabled: bool,
+    /// Per-field rules for the content kept by traces and artifacts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_content: Option<TraceContentPolicy>,
+    /// Proxy applied to providers that don't set their own `proxy_url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_proxy_url: Option<String>,
+    /// Adds `x-hub-upstream-ttfb-ms` and `x-hub-overhead-ms` to non-streaming responses.
+    #[serde(default)]
+    pub timing_headers: bool,
+    /// Honours debug request headers such as `x-hub-dry-run`.
+    #[serde(default)]
+    pub allow_debug_headers: bool,
+    /// Browser CORS policy. Without it no CORS headers are sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+    /// Response compression and compressed request bodies. Off when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionConfig>,
+    /// How responses blocked by provider safety filters are returned.
+    #[serde(default)]
+    pub safety_block_behavior: SafetyBlockBehavior,
+    /// How much of an error's message is returned to clients. Logs keep the full error.
+    #[serde(default)]
+    pub error_detail: ErrorDetail,
+    /// Caps concurrently served API requests; further requests wait in a queue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_in_flight_requests: Option<u32>,
+    /// Requests allowed to wait for a slot before new ones get 503.
+    /// Defaults to `max_in_flight_requests`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_queued_requests: Option<u32>,
+    /// Caps streaming responses open at once across all clients; further streaming requests
+    /// get 429.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_streams_global: Option<u32>,
+    /// Caps streaming responses open at once per client, identified by its IP address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_streams_per_key: Option<u32>,
+    /// Webhook alerts for budget and error-rate events. Off when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<NotificationsConfig>,
+    /// Routes `provider/model` names, e.g. `anthropic/claude-sonnet-4`, to the provider with
+    /// that key or type when no configured model matches.
+    #[serde(default)]
+    pub prefix_routing: bool,
+    /// Lists the models a pipeline serves in its `model_not_found` errors.
+    #[serde(default)]
+    pub expose_available_models: bool,
+    /// Matches the `x-traceloop-pipeline` header and request `model` to pipelines and models
+    /// ignoring case, so `GPT-4o` is served by `gpt-4o`.
+    #[serde(default)]
+    pub case_insensitive_lookups: bool,
+    /// Binds the gateway and management ports with `SO_REUSEPORT`, so a new instance can
+    /// listen alongside the old one during a rolling restart.
+    #[serde(default)]
+    pub reuse_port: bool,
+    /// Sends the `x-traceloop-*` attribute headers of a request on to its provider, besides
+    /// recording them on the request's span.
+    #[serde(default)]
+    pub forward_traceloop_headers: bool,
+    /// Reports the provider, model key and model type that served each response in
+    /// `x-hub-provider`, `x-hub-model-key` and `x-hub-model-type`.
+    #[serde(default)]
+    pub attribution_headers: bool,
+    /// Where pipelines with `store_artifacts` write request/response artifacts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_store: Option<ArtifactStoreConfig>,
+    /// How long responses to requests with an `Idempotency-Key` are replayed. Defaults to an
+    /// hour.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_ttl_seconds: Option<u64>,
+    /// How long streams started with `x-hub-stream-id` can be resumed after their last
+    /// chunk. Defaults to five minutes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resumable_stream_ttl_seconds: Option<u64>,
+    /// JSON file pipeline budgets and usage are kept in, so a restart doesn't reset them.
+    /// Read at startup. Without it they are kept in memory only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_store_path: Option<String>,
+    /// Chunks of a streamed response read from the provider ahead of the client. Once that
+    /// many are waiting, the provider isn't read until the client catches up. Defaults to 64.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_buffer_chunks: Option<usize>,
+    /// Most bytes of chunks waiting for a streaming client. A response that would exceed it
+    /// ends with an error event. Defaults to 4 MiB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_buffer_max_bytes: Option<usize>,
+    /// Most bytes of a body read whole by middleware such as the parameter policy. Larger
+    /// requests get 413; larger responses pass through without being kept. Defaults to
+    /// 32 MiB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_buffered_body_bytes: Option<usize>,
+    /// Upstream response headers copied onto gateway responses. Defaults to OpenAI's
+    /// rate-limit headers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passthrough_response_headers: Option<PassthroughHeaders>,
+    /// Warns about and counts streams whose output slows down mid-stream. Off when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_stream: Option<SlowStreamConfig>,
+    /// Serves chat completions, completions and embeddings on Azure OpenAI's
+    /// `/openai/deployments/{deployment}/...` paths, for clients written against Azure.
+    #[serde(default)]
+    pub azure_compat_routes: bool,
+    /// Caps of the batches sent to `/api/v1/batch-inference`. Defaults apply when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_inference: Option<BatchInferenceConfig>,
+    /// Pushes the metrics served on `/metrics` to an OTLP collector as well. Off when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otlp_metrics: Option<OtlpMetricsConfig>,
+    /// Access logs and request spans of the gateway and management servers, read at
+    /// startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<LoggingConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Hash)]
+pub struct LoggingConfig {
+    #[serde(default)]
+    pub gateway: ServerLoggingConfig,
+    #[serde(default)]
+    pub management: ServerLoggingConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash)]
+pub struct ServerLoggingConfig {
+    /// Most verbose access log level: `info` logs every response, `debug` also each request
+    /// as it starts. Defaults to the `RUST_LOG` level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<LogLevel>,
+    /// Records the request headers on each request's span.
+    #[serde(default = "default_include_headers")]
+    pub include_headers: bool,
+    /// Leaves `/health` requests out of the access log and their spans.
+    #[serde(default)]
+    pub exclude_health_checks: bool,
+}
+
+impl Default for ServerLoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: None,
+            include_headers: default_include_headers(),
+            exclude_health_checks: false,
+        }
+    }
+}
+
+fn default_include_headers() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// A stream is slow once its output over the last `window_seconds` falls below
+/// `min_tokens_per_second`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SlowStreamConfig {
+    pub min_tokens_per_second: f64,
+    #[serde(default = "default_slow_stream_window_seconds")]
+    pub window_seconds: u64,
+}
+
+fn default_slow_stream_window_seconds() -> u64 {
+    10
+}
+
+impl Hash for SlowStreamConfig {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.min_tokens_per_second.to_bits().hash(state);
+        self.window_seconds.hash(state);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BatchInferenceConfig {
+    /// Most requests in a batch. Defaults to 1000.
+    #[serde(default = "default_batch_max_lines")]
+    pub max_lines: usize,
+    /// Largest batch body, in bytes. Defaults to 10 MiB.
+    #[serde(default = "default_batch_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Most requests of a batch sent at once, and the default `concurrency` of a batch.
+    /// Defaults to 8.
+    #[serde(default = "default_batch_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+impl Default for BatchInferenceConfig {
+    fn default() -> Self {
+        Self {
+            max_lines: default_batch_max_lines(),
+            max_body_bytes: default_batch_max_body_bytes(),
+            max_concurrency: default_batch_max_concurrency(),
+        }
+    }
+}
+
+fn default_batch_max_lines() -> usize {
+    1000
+}
+
+fn default_batch_max_body_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_batch_max_concurrency() -> usize {
+    8
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash)]
+pub struct OtlpMetricsConfig {
+    /// OTLP/HTTP metrics URL, e.g. `https://collector:4318/v1/metrics`.
+    pub endpoint: String,
+    /// Sent with every export, e.g. `authorization`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, SecretObject>,
+    #[serde(default = "default_otlp_export_interval_seconds")]
+    pub export_interval_seconds: u64,
+    /// The `service.name` resource attribute.
+    #[serde(default = "default_otlp_service_name")]
+    pub service_name: String,
+    /// The `deployment.environment` resource attribute, left out when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+}
+
+fn default_otlp_export_interval_seconds() -> u64 {
+    60
+}
+
+fn default_otlp_service_name() -> String {
+    "traceloop-hub".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash)]
+pub struct ArtifactStoreConfig {
+    #[serde(flatten)]
+    pub backend: ArtifactBackend,
+    /// Artifacts older than this are deleted.
+    #[serde(default = "default_artifact_retention_hours")]
+    pub retention_hours: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactBackend {
+    /// One `<request_id>.json` file per artifact in `path`.
+    Filesystem { path: String },
+    /// An S3-compatible bucket, addressed path-style: `<endpoint>/<bucket>/<prefix><id>.json`.
+    S3 {
+        endpoint: String,
+        bucket: String,
+        #[serde(default = "default_s3_region")]
+        region: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        prefix: String,
+        access_key_id: SecretObject,
+        secret_access_key: SecretObject,
+    },
+}
+
+fn default_artifact_retention_hours() -> u64 {
+    7 * 24
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// What traces and artifacts keep of one kind of content: `include`, `exclude`, `hash` or
+/// `truncate:<n>`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub enum ContentRule {
+    #[default]
+    Include,
+    Exclude,
+    /// Replaced by `sha256:<hex digest>`, so equal contents can still be matched up.
+    Hash,
+    /// Cut to the first n characters.
+    Truncate(usize),
+}
+
+impl std::fmt::Display for ContentRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentRule::Include => write!(f, "include"),
+            ContentRule::Exclude => write!(f, "exclude"),
+            ContentRule::Hash => write!(f, "hash"),
+            ContentRule::Truncate(chars) => write!(f, "truncate:{chars}"),
+        }
+    }
+}
+
+impl std::str::FromStr for ContentRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = s.trim().to_lowercase();
+        if let Some(chars) = rule.strip_prefix("truncate:") {
+            return match chars.trim().parse::<usize>() {
+                Ok(chars) if chars > 0 => Ok(ContentRule::Truncate(chars)),
+                _ => Err(format!(
+                    "Invalid content rule '{s}', truncate needs a positive length"
+                )),
+            };
+        }
+        match rule.as_str() {
+            "include" => Ok(ContentRule::Include),
+            "exclude" => Ok(ContentRule::Exclude),
+            "hash" => Ok(ContentRule::Hash),
+            _ => Err(format!(
+                "Unknown content rule '{s}', expected include, exclude, hash or truncate:<n>"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for ContentRule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ContentRule> for String {
+    fn from(rule: ContentRule) -> Self {
+        rule.to_string()
+    }
+}
+
+/// Per-field rules for the request and response content kept by traces and artifacts.
+/// Fields left out include their content.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(default, deny_unknown_fields)]
+pub struct TraceContentPolicy {
+    pub messages: MessageContentRules,
+    /// Results of tool calls, i.e. `tool` messages.
+    pub tool_results: ContentRule,
+    /// Generated text, including the assistant turns replayed in requests.
+    pub response_content: ContentRule,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(default, deny_unknown_fields)]
+pub struct MessageContentRules {
+    /// User messages, completion prompts and embedding inputs.
+    pub user: ContentRule,
+    /// System and developer messages.
+    pub system: ContentRule,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyBlockBehavior {
+    /// 200 with `finish_reason: "content_filter"` and the reason in `message.refusal`.
+    #[default]
+    FinishReason,
+    /// 400 with error type `content_filter`.
+    Error,
+}
+
+impl std::str::FromStr for SafetyBlockBehavior {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "finish_reason" => Ok(Self::FinishReason),
+            "error" => Ok(Self::Error),
+            other => Err(format!(
+                "unknown safety_block_behavior '{other}', expected 'finish_reason' or 'error'"
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorDetail {
+    /// Errors as the provider or pipeline reported them.
+    #[default]
+    Full,
+    /// Messages with URLs, IP addresses and resource identifiers redacted.
+    Sanitized,
+    /// Only the error type, a generic message and the request id.
+    Minimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    Gzip,
+    Br,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash)]
+pub struct CompressionConfig {
+    #[serde(default = "default_compression_algorithms")]
+    pub algorithms: Vec<CompressionAlgorithm>,
+    /// Responses smaller than this are sent uncompressed.
+    #[serde(default = "default_compression_min_size_bytes")]
+    pub min_size_bytes: u16,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            algorithms: default_compression_algorithms(),
+            min_size_bytes: default_compression_min_size_bytes(),
+        }
+    }
+}
+
+fn default_compression_algorithms() -> Vec<CompressionAlgorithm> {
+    vec![CompressionAlgorithm::Gzip, CompressionAlgorithm::Br]
+}
+
+fn default_compression_min_size_bytes() -> u16 {
+    1024
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Hash)]
+pub struct CorsConfig {
+    /// Exact origins, `*` for any origin, or subdomain wildcards like `https://*.example.com`.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Request headers browsers may send; empty allows whatever the preflight asks for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_headers: Vec<String>,
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
+}
+
+/// Kinds of events the hub can send to notification webhooks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEventType {
+    /// A pipeline crossed the `warn_at_percent` threshold of its budget.
+    BudgetWarning,
+    /// A pipeline spent its whole budget for the current window.
+    BudgetExceeded,
+    /// A pipeline's share of failed requests crossed `error_rate.threshold_percent`.
+    ErrorRate,
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationSeverity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash)]
+pub struct WebhookConfig {
+    pub url: SecretObject,
+    /// Event types delivered to this webhook; empty sends every type.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<NotificationEventType>,
+    #[serde(default)]
+    pub min_severity: NotificationSeverity,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash)]
+pub struct ErrorRateAlert {
+    /// Percentage of failed (5xx) requests in a window that triggers an alert.
+    pub threshold_percent: u8,
+    #[serde(default = "default_error_rate_window_seconds")]
+    pub window_seconds: u64,
+    /// Requests a window needs before its error rate is judged.
+    #[serde(default = "default_error_rate_min_requests")]
+    pub min_requests: u32,
+}
+
+fn default_error_rate_window_seconds() -> u64 {
+    60
+}
+
+fn default_error_rate_min_requests() -> u32 {
+    20
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Hash)]
+pub struct NotificationsConfig {
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_rate: Option<ErrorRateAlert>,
+    /// How long events are collected before they are posted together.
+    #[serde(default = "default_notification_batch_interval_ms")]
+    pub batch_interval_ms: u64,
+    /// Extra delivery attempts after a failed POST.
+    #[serde(default = "default_notification_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_notification_batch_interval_ms() -> u64 {
+    1000
+}
+
+fn default_notification_max_retries() -> u32 {
+    3
 }
 
 // GatewayConfig name remains the same
@@ -169,4 +1188,8 @@ pub struct GatewayConfig {
     pub models: Vec<ModelConfig>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub pipelines: Vec<Pipeline>,
+    /// System prompt templates by name, with `{{variable}}` placeholders. Pipelines use
+    /// them through the `system-prompt` plugin.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prompt_templates: BTreeMap<String, String>,
 }
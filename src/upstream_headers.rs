@@ -0,0 +1,185 @@
+use crate::metrics::gauge;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderName};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::{Arc, Mutex};
+
+use crate::types::PassthroughHeaders;
+
+/// Prepended to passed-through header names when `prefix` is set.
+pub const UPSTREAM_HEADER_PREFIX: &str = "x-upstream-";
+/// Tokens left in a provider's rate-limit window, as of its latest response.
+pub const REMAINING_TOKENS_METRIC: &str = "hub_upstream_ratelimit_remaining_tokens";
+const REMAINING_TOKENS_HEADER: &str = "x-ratelimit-remaining-tokens";
+
+/// Headers that describe the response body or the connection, which the hub sets itself.
+const RESERVED_HEADERS: [&str; 5] = [
+    "connection",
+    "content-encoding",
+    "content-length",
+    "content-type",
+    "transfer-encoding",
+];
+
+tokio::task_local! {
+    static CAPTURE: Arc<Capture>;
+}
+
+struct Capture {
+    passthrough: Arc<HeaderPassthrough>,
+    headers: Mutex<HeaderMap>,
+}
+
+/// The upstream response headers a pipeline copies onto its responses.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderPassthrough {
+    /// Upstream header names, each with the name it gets on the gateway response.
+    names: Vec<(HeaderName, HeaderName)>,
+}
+
+impl HeaderPassthrough {
+    /// Names that aren't valid header names are skipped; validation reports them.
+    pub fn new(settings: &PassthroughHeaders) -> Self {
+        let names = settings
+            .headers
+            .iter()
+            .filter_map(|name| {
+                let upstream = HeaderName::try_from(name.trim()).ok()?;
+                let target = if settings.prefix {
+                    HeaderName::try_from(format!("{UPSTREAM_HEADER_PREFIX}{upstream}")).ok()?
+                } else {
+                    upstream.clone()
+                };
+                Some((upstream, target))
+            })
+            .collect();
+        Self { names }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Copies the passed-through headers in `upstream` to `response`. Headers the hub
+    /// already set win.
+    fn copy(&self, upstream: &HeaderMap, response: &mut HeaderMap) {
+        for (name, target) in &self.names {
+            if response.contains_key(target) {
+                continue;
+            }
+            for value in upstream.get_all(name) {
+                response.append(target.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Notes the headers of an upstream response: the provider's remaining tokens go to a
+/// gauge, and passed-through headers are kept for the current request. A later attempt,
+/// e.g. by a failover group, replaces the headers of an earlier one.
+pub fn record_upstream_headers(provider_key: &str, headers: &HeaderMap) {
+    let remaining_tokens = headers
+        .get(REMAINING_TOKENS_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<f64>().ok());
+    if let Some(remaining_tokens) = remaining_tokens {
+        gauge!(REMAINING_TOKENS_METRIC, "provider" => provider_key.to_string())
+            .set(remaining_tokens);
+    }
+
+    let _ = CAPTURE.try_with(|capture| {
+        if let Ok(mut captured) = capture.headers.lock() {
+            captured.clear();
+            for (name, _) in &capture.passthrough.names {
+                for value in headers.get_all(name) {
+                    captured.append(name.clone(), value.clone());
+                }
+            }
+        }
+    });
+}
+
+/// Pipeline middleware copying the configured upstream response headers onto the
+/// response. Streams are covered too, since upstream headers arrive before the body.
+pub async fn passthrough_upstream_headers(
+    State(passthrough): State<Arc<HeaderPassthrough>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let capture = Arc::new(Capture {
+        passthrough: passthrough.clone(),
+        headers: Mutex::new(HeaderMap::new()),
+    });
+    let mut response = CAPTURE.scope(capture.clone(), next.run(request)).await;
+    if let Ok(captured) = capture.headers.lock() {
+        passthrough.copy(&captured, response.headers_mut());
+    }
+    response
+}
+
+pub fn validate_passthrough_headers(settings: &PassthroughHeaders) -> Result<(), String> {
+    for name in &settings.headers {
+        let header = HeaderName::try_from(name.trim())
+            .map_err(|_| format!("'{name}' is not a valid header name"))?;
+        if settings.prefix {
+            continue;
+        }
+        if RESERVED_HEADERS.contains(&header.as_str()) || header.as_str().starts_with("x-hub-") {
+            return Err(format!(
+                "'{name}' can't be passed through without prefix, it would replace a header the hub sets"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn settings(headers: &[&str], prefix: bool) -> PassthroughHeaders {
+        PassthroughHeaders {
+            headers: headers.iter().map(|name| name.to_string()).collect(),
+            prefix,
+        }
+    }
+
+    #[test]
+    fn test_copy_renames_and_keeps_hub_headers() {
+        let mut upstream = HeaderMap::new();
+        upstream.insert(
+            "x-ratelimit-remaining-tokens",
+            HeaderValue::from_static("900"),
+        );
+        upstream.insert("x-request-id", HeaderValue::from_static("upstream-id"));
+        upstream.insert("server", HeaderValue::from_static("nginx"));
+
+        let passthrough = HeaderPassthrough::new(&settings(
+            &["X-Ratelimit-Remaining-Tokens", "x-request-id"],
+            false,
+        ));
+        let mut response = HeaderMap::new();
+        response.insert("x-request-id", HeaderValue::from_static("hub-id"));
+        passthrough.copy(&upstream, &mut response);
+        assert_eq!(response["x-ratelimit-remaining-tokens"], "900");
+        assert_eq!(response["x-request-id"], "hub-id");
+        assert!(!response.contains_key("server"));
+
+        let prefixed = HeaderPassthrough::new(&settings(&["x-ratelimit-remaining-tokens"], true));
+        let mut response = HeaderMap::new();
+        prefixed.copy(&upstream, &mut response);
+        assert_eq!(response["x-upstream-x-ratelimit-remaining-tokens"], "900");
+        assert_eq!(response.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_passthrough_headers() {
+        assert!(validate_passthrough_headers(&PassthroughHeaders::default()).is_ok());
+        assert!(validate_passthrough_headers(&settings(&["bad header"], true)).is_err());
+        assert!(validate_passthrough_headers(&settings(&["content-length"], false)).is_err());
+        assert!(validate_passthrough_headers(&settings(&["x-hub-provider"], false)).is_err());
+        assert!(validate_passthrough_headers(&settings(&["content-length"], true)).is_ok());
+    }
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use common::{model, model_router, params, pipeline, provider};
+use hub_lib::management::dto::ApiKeyRole;
+use hub_lib::management::services::api_key_service::{ApiKeyService, StaticApiKey};
+use hub_lib::state::{AppState, ConfigSource};
+use hub_lib::types::{GatewayConfig, PluginConfig, Provider, ProviderType};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const SECRETS: [&str; 4] = [
+    "sk-openai-secret",
+    "aws-secret-value",
+    "aws-session-value",
+    "tracing-secret-key",
+];
+
+const ADMIN_KEY: &str = "admin-key";
+
+/// The admin routes as the management server serves them, accepting `ADMIN_KEY`.
+fn admin_router(app_state: Arc<AppState>) -> axum::Router {
+    let api_key_service =
+        ApiKeyService::with_static_keys(vec![StaticApiKey::new(ADMIN_KEY, ApiKeyRole::Admin)]);
+    hub_lib::management::admin_router(
+        hub_lib::routes::admin_routes(app_state),
+        Arc::new(api_key_service),
+    )
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn config_with_secrets() -> GatewayConfig {
+    GatewayConfig {
+        providers: vec![
+            Provider {
+                api_key: SECRETS[0].to_string(),
+                ..provider("openai", ProviderType::OpenAI)
+            },
+            Provider {
+                api_key: String::new(),
+                params: params(&[
+                    ("region", "us-east-1"),
+                    ("AWS_SECRET_ACCESS_KEY", SECRETS[1]),
+                    ("AWS_SESSION_TOKEN", SECRETS[2]),
+                ]),
+                ..provider("bedrock", ProviderType::Bedrock)
+            },
+        ],
+        models: vec![model("gpt-4", "openai")],
+        pipelines: vec![pipeline(
+            "default",
+            vec![
+                PluginConfig::Tracing {
+                    endpoint: "https://api.traceloop.com/v1/traces".to_string(),
+                    api_key: SECRETS[3].to_string(),
+                },
+                model_router(&["gpt-4"]),
+            ],
+        )],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_admin_config_endpoint_masks_secrets() {
+    let app_state = Arc::new(
+        AppState::new(config_with_secrets())
+            .expect("Failed to create app state")
+            .with_config_source(ConfigSource::Yaml {
+                path: "config.yaml".to_string(),
+            }),
+    );
+    let router = admin_router(app_state);
+
+    let response = router.oneshot(get("/admin/config")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+    let body_text = String::from_utf8(body.to_vec()).unwrap();
+    for secret in SECRETS {
+        assert!(
+            !body_text.contains(secret),
+            "secret '{secret}' leaked in /admin/config response"
+        );
+    }
+
+    let summary: serde_json::Value = serde_json::from_str(&body_text).unwrap();
+    assert_eq!(summary["source"]["type"], "yaml");
+    assert_eq!(summary["source"]["path"], "config.yaml");
+    assert!(summary["last_applied_at"].is_string());
+    assert_eq!(summary["config_hash"].as_str().unwrap().len(), 16);
+    assert_eq!(summary["config"]["providers"][0]["api_key"], "***");
+    assert_eq!(summary["config"]["providers"][1]["region"], "us-east-1");
+}
+
+#[tokio::test]
+async fn test_admin_config_endpoints_require_an_api_key() {
+    let app_state = Arc::new(AppState::new(config_with_secrets()).unwrap());
+    let router = admin_router(app_state.clone());
+
+    for uri in ["/admin/config", "/admin/config/version", "/admin/health"] {
+        let response = router
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+    }
+
+    // The gateway's own port doesn't serve them at all
+    let gateway = hub_lib::routes::create_router(app_state);
+    let response = gateway.oneshot(get("/admin/config")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+async fn get_json(router: &axum::Router, uri: &str) -> serde_json::Value {
+    let response = router.clone().oneshot(get(uri)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_config_version_changes_only_when_config_changes() {
+    let app_state = Arc::new(AppState::new(config_with_secrets()).unwrap());
+    let admin = admin_router(app_state.clone());
+
+    let initial = app_state.config_version();
+    let health = get_json(&admin, "/admin/health").await;
+    assert_eq!(health["status"], "ok");
+    assert_eq!(health["config_hash"], initial.config_hash);
+    let version = get_json(&admin, "/admin/config/version").await;
+    assert_eq!(version["config_hash"], initial.config_hash);
+    assert!(version["last_applied_at"].is_string());
+
+    // Re-applying the same configuration is not a new version
+    app_state.update_config(config_with_secrets()).unwrap();
+    assert_eq!(app_state.config_version(), initial);
+
+    let mut changed = config_with_secrets();
+    changed.models[0].r#type = "gpt-4o".to_string();
+    app_state.update_config(changed.clone()).unwrap();
+    let updated = app_state.config_version();
+    assert_ne!(updated.config_hash, initial.config_hash);
+    assert!(updated.last_applied_at >= initial.last_applied_at);
+    let health = get_json(&admin, "/admin/health").await;
+    assert_eq!(health["config_hash"], updated.config_hash);
+
+    app_state.update_config(changed).unwrap();
+    assert_eq!(app_state.config_version(), updated);
+}
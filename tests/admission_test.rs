@@ -0,0 +1,119 @@
+mod common;
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use common::{chat, completion, openai_config};
+use hub_lib::types::{GatewayConfig, General};
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const UPSTREAM_DELAY: Duration = Duration::from_millis(300);
+
+async fn slow_upstream() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(completion("hi"))
+                .set_delay(UPSTREAM_DELAY),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+fn hub(server: &MockServer, max_in_flight: u32, max_queued: u32) -> Router {
+    common::hub(GatewayConfig {
+        general: Some(General {
+            max_in_flight_requests: Some(max_in_flight),
+            max_queued_requests: Some(max_queued),
+            ..Default::default()
+        }),
+        ..openai_config(server)
+    })
+}
+
+fn spawn_chat(
+    app: &Router,
+    priority: Option<&str>,
+) -> tokio::task::JoinHandle<(Response, Instant)> {
+    let mut request = Request::builder()
+        .uri("/api/v1/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json");
+    if let Some(priority) = priority {
+        request = request.header("x-hub-priority", priority);
+    }
+    let request = request.body(Body::from(chat("hello").to_string())).unwrap();
+    let app = app.clone();
+    tokio::spawn(async move {
+        let response = app.oneshot(request).await.unwrap();
+        (response, Instant::now())
+    })
+}
+
+async fn pause() {
+    tokio::time::sleep(Duration::from_millis(50)).await;
+}
+
+#[tokio::test]
+async fn test_requests_beyond_the_queue_are_rejected_fast() {
+    let server = slow_upstream().await;
+    let app = hub(&server, 1, 1);
+
+    let running = spawn_chat(&app, None);
+    pause().await;
+    let queued = spawn_chat(&app, None);
+    pause().await;
+
+    let started = Instant::now();
+    let (rejected, _) = spawn_chat(&app, None).await.unwrap();
+    assert!(started.elapsed() < UPSTREAM_DELAY);
+    assert_eq!(rejected.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(rejected.headers()[header::RETRY_AFTER], "1");
+
+    assert_eq!(running.await.unwrap().0.status(), StatusCode::OK);
+    assert_eq!(queued.await.unwrap().0.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_high_priority_request_jumps_the_queue() {
+    let server = slow_upstream().await;
+    let app = hub(&server, 1, 4);
+
+    let running = spawn_chat(&app, None);
+    pause().await;
+    let normal = spawn_chat(&app, None);
+    pause().await;
+    let high = spawn_chat(&app, Some("high"));
+
+    let (high, high_done) = high.await.unwrap();
+    let (normal, normal_done) = normal.await.unwrap();
+    assert_eq!(high.status(), StatusCode::OK);
+    assert_eq!(normal.status(), StatusCode::OK);
+    assert!(high_done < normal_done);
+    assert_eq!(running.await.unwrap().0.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_health_is_not_queued() {
+    let server = slow_upstream().await;
+    let app = hub(&server, 1, 1);
+
+    let running = spawn_chat(&app, None);
+    pause().await;
+    let _queued = spawn_chat(&app, None);
+    pause().await;
+
+    let response = app
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(running.await.unwrap().0.status(), StatusCode::OK);
+}
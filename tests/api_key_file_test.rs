@@ -0,0 +1,98 @@
+mod common;
+
+use common::{completion, model, model_router, openai, params, pipeline};
+use hub_lib::ai_models::registry::ModelRegistry;
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::pipelines::pipeline::create_pipeline;
+use hub_lib::providers::registry::ProviderRegistry;
+use hub_lib::types::Provider;
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// An upstream that only accepts `key`.
+async fn expect_key(server: &MockServer, key: &str) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("authorization", format!("Bearer {key}").as_str()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+/// A hub whose OpenAI provider reads its key from `key_file`, checking it on every request.
+fn hub(server: &MockServer, key_file: &Path) -> (Router, Arc<ProviderRegistry>) {
+    let mut provider = Provider {
+        api_key: String::new(),
+        ..openai("openai", server)
+    };
+    provider.params.extend(params(&[
+        ("api_key_file", &key_file.to_string_lossy()),
+        ("api_key_file_refresh_seconds", "0"),
+    ]));
+    let provider_registry = Arc::new(ProviderRegistry::new(&[provider]).unwrap());
+    let model_registry =
+        ModelRegistry::new(&[model("gpt-4o", "openai")], provider_registry.clone()).unwrap();
+    let app = create_pipeline(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &model_registry,
+    );
+    (app, provider_registry)
+}
+
+async fn chat(app: &Router) -> StatusCode {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(common::chat("hello").to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    status
+}
+
+#[tokio::test]
+async fn test_rotated_key_file_is_picked_up() {
+    let server = MockServer::start().await;
+    expect_key(&server, "key-one").await;
+    expect_key(&server, "key-two-rotated").await;
+    let dir = tempfile::tempdir().unwrap();
+    let key_file = dir.path().join("api-key");
+    std::fs::write(&key_file, "key-one\n").unwrap();
+    let (app, providers) = hub(&server, &key_file);
+
+    assert_eq!(chat(&app).await, StatusCode::OK);
+    std::fs::write(&key_file, "key-two-rotated\n").unwrap();
+    assert_eq!(chat(&app).await, StatusCode::OK);
+    assert!(providers.unhealthy_providers().is_empty());
+}
+
+#[tokio::test]
+async fn test_missing_key_file_marks_provider_unhealthy() {
+    let server = MockServer::start().await;
+    expect_key(&server, "key-one").await;
+    let dir = tempfile::tempdir().unwrap();
+    let key_file = dir.path().join("api-key");
+    let (app, providers) = hub(&server, &key_file);
+
+    assert_eq!(chat(&app).await, StatusCode::SERVICE_UNAVAILABLE);
+    let unhealthy = providers.unhealthy_providers();
+    assert!(unhealthy["openai"].contains("api-key"), "{unhealthy:?}");
+
+    // The provider recovers once the secret is mounted.
+    std::fs::write(&key_file, "key-one").unwrap();
+    assert_eq!(chat(&app).await, StatusCode::OK);
+    assert!(providers.unhealthy_providers().is_empty());
+}
@@ -0,0 +1,231 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use common::{completion, model_router, openai_config, pipeline, post_json};
+use hub_lib::artifacts::{Artifact, HEADER_REQUEST_ID};
+use hub_lib::management::dto::ApiKeyRole;
+use hub_lib::management::services::api_key_service::{ApiKeyService, StaticApiKey};
+use hub_lib::state::AppState;
+use hub_lib::types::{ArtifactBackend, ArtifactStoreConfig, GatewayConfig, General, Pipeline};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn upstream() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "id": "chatcmpl-2",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{"index": 0, "delta": {"content": "streamed"}, "finish_reason": "stop"}]
+        }])))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .mount(&server)
+        .await;
+    server
+}
+
+const ADMIN_KEY: &str = "admin-key";
+const READ_ONLY_KEY: &str = "read-only-key";
+
+/// The gateway, and the admin routes as the management server serves them.
+fn hub(server: &MockServer, artifact_dir: &std::path::Path) -> (Router, Router) {
+    hub_with_body_cap(server, artifact_dir, None)
+}
+
+fn hub_with_body_cap(
+    server: &MockServer,
+    artifact_dir: &std::path::Path,
+    max_buffered_body_bytes: Option<usize>,
+) -> (Router, Router) {
+    let config = GatewayConfig {
+        general: Some(General {
+            artifact_store: Some(ArtifactStoreConfig {
+                backend: ArtifactBackend::Filesystem {
+                    path: artifact_dir.to_string_lossy().into_owned(),
+                },
+                retention_hours: 24,
+            }),
+            max_buffered_body_bytes,
+            ..Default::default()
+        }),
+        pipelines: vec![Pipeline {
+            store_artifacts: true,
+            ..pipeline("default", vec![model_router(&["gpt-4o"])])
+        }],
+        ..openai_config(server)
+    };
+    let state = Arc::new(AppState::new(config).unwrap());
+    let api_key_service = ApiKeyService::with_static_keys(vec![
+        StaticApiKey::new(ADMIN_KEY, ApiKeyRole::Admin),
+        StaticApiKey::new(READ_ONLY_KEY, ApiKeyRole::ReadOnly),
+    ]);
+    let admin = hub_lib::management::admin_router(
+        hub_lib::routes::admin_routes(state.clone()),
+        Arc::new(api_key_service),
+    );
+    (hub_lib::routes::create_router(state), admin)
+}
+
+async fn get_artifact(admin: &Router, request_id: &str, key: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().uri(format!("/admin/artifacts/{request_id}"));
+    if let Some(key) = key {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {key}"));
+    }
+    let response = admin
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    response.status()
+}
+
+/// Sends a chat request and returns its request id after reading the whole response.
+async fn chat(app: &Router, stream: bool) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "stream": stream
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let request_id = response.headers()[HEADER_REQUEST_ID]
+        .to_str()
+        .unwrap()
+        .to_string();
+    to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    request_id
+}
+
+/// Artifacts are written in the background, so give the write a moment to land.
+async fn fetch_artifact(admin: &Router, request_id: &str) -> Artifact {
+    for _ in 0..50 {
+        let response = admin
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/admin/artifacts/{request_id}"))
+                    .header(header::AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        if response.status() == StatusCode::OK {
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            return serde_json::from_slice(&body).unwrap();
+        }
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("artifact {request_id} was never stored");
+}
+
+#[tokio::test]
+async fn test_artifacts_are_stored_and_served_by_request_id() {
+    let server = upstream().await;
+    let artifact_dir = tempfile::tempdir().unwrap();
+    let (app, admin) = hub(&server, artifact_dir.path());
+
+    let request_id = chat(&app, false).await;
+    let artifact = fetch_artifact(&admin, &request_id).await;
+    assert_eq!(artifact.request_id.to_string(), request_id);
+    assert_eq!(artifact.pipeline, "default");
+    assert_eq!(artifact.status, 200);
+    assert_eq!(artifact.request.unwrap()["messages"][0]["content"], "hello");
+    assert_eq!(
+        artifact.response.unwrap()["choices"][0]["message"]["content"],
+        "hi"
+    );
+    assert_eq!(artifact.routing.model_key.as_deref(), Some("gpt-4o"));
+    assert_eq!(artifact.routing.provider.as_deref(), Some("openai"));
+    assert!(artifact.timings.upstream_total_ms.is_some());
+    assert!(
+        artifact_dir
+            .path()
+            .join(format!("{request_id}.json"))
+            .exists()
+    );
+
+    let request_id = chat(&app, true).await;
+    let artifact = fetch_artifact(&admin, &request_id).await;
+    let events: Vec<Value> = serde_json::from_value(artifact.response.unwrap()).unwrap();
+    assert_eq!(events[0]["choices"][0]["delta"]["content"], "streamed");
+
+    let unknown = uuid::Uuid::new_v4().to_string();
+    assert_eq!(
+        get_artifact(&admin, &unknown, Some(ADMIN_KEY)).await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_artifacts_require_an_admin_api_key() {
+    let server = upstream().await;
+    let artifact_dir = tempfile::tempdir().unwrap();
+    let (app, admin) = hub(&server, artifact_dir.path());
+
+    let request_id = chat(&app, false).await;
+    fetch_artifact(&admin, &request_id).await;
+    assert_eq!(
+        get_artifact(&admin, &request_id, None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        get_artifact(&admin, &request_id, Some(READ_ONLY_KEY)).await,
+        StatusCode::FORBIDDEN
+    );
+
+    // The gateway's own port doesn't serve them at all
+    assert_eq!(
+        get_artifact(&app, &request_id, Some(ADMIN_KEY)).await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_requests_over_the_body_cap_are_served_without_an_artifact() {
+    let server = upstream().await;
+    let artifact_dir = tempfile::tempdir().unwrap();
+    let (app, _admin) = hub_with_body_cap(&server, artifact_dir.path(), Some(16));
+
+    let response = app
+        .oneshot(post_json(
+            "/api/v1/chat/completions",
+            &common::chat("hello"),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(HEADER_REQUEST_ID));
+    let body: Value =
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(body["choices"][0]["message"]["content"], "hi");
+    assert_eq!(std::fs::read_dir(artifact_dir.path()).unwrap().count(), 0);
+}
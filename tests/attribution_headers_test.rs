@@ -0,0 +1,152 @@
+mod common;
+
+use common::{azure_member, completion, model, model_router, params, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{HeaderMap, Request, StatusCode};
+use hub_lib::types::ModelConfig;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A region that answers chat requests, streamed or not.
+async fn healthy_region() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/gpt-4o/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "id": "chatcmpl-2",
+                "object": "chat.completion.chunk",
+                "created": 1,
+                "model": "gpt-4o",
+                "choices": [{"index": 0, "delta": {"content": "hi"}, "finish_reason": null}]
+            },
+            {
+                "id": "chatcmpl-2",
+                "object": "chat.completion.chunk",
+                "created": 1,
+                "model": "gpt-4o",
+                "choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}],
+                "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+            }
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/gpt-4o/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn failing_region() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/gpt-4o/chat/completions"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// A chat pipeline whose `gpt-4o-canary` model, of type `gpt-4o`, is served by the
+/// `azure-prod` group. East US is tried first, then West Europe.
+fn hub(eastus: &MockServer, westeu: &MockServer) -> Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o-canary"])]),
+        &[
+            azure_member("azure-eastus", eastus, "0"),
+            azure_member("azure-westeu", westeu, "1"),
+        ],
+        &[ModelConfig {
+            r#type: "gpt-4o".to_string(),
+            params: params(&[("deployment", "gpt-4o")]),
+            ..model("gpt-4o-canary", "azure-prod")
+        }],
+    )
+}
+
+async fn chat(app: &Router, stream: bool) -> (HeaderMap, String) {
+    let body = json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hello"}],
+        "stream": stream
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers().clone();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (headers, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn attribution_headers(headers: &HeaderMap) -> [Option<&str>; 3] {
+    ["x-hub-provider", "x-hub-model-key", "x-hub-model-type"]
+        .map(|name| headers.get(name).map(|value| value.to_str().unwrap()))
+}
+
+fn stream_chunks(body: &str) -> Vec<Value> {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect()
+}
+
+// Every case shares a test, since the setting is read from the environment.
+#[tokio::test]
+async fn test_attribution_reports_the_model_that_answered() {
+    let eastus = failing_region().await;
+    let westeu = healthy_region().await;
+    let served = [Some("azure-westeu"), Some("gpt-4o-canary"), Some("gpt-4o")];
+
+    unsafe {
+        std::env::set_var("ATTRIBUTION_HEADERS", "false");
+    }
+    let app = hub(&eastus, &westeu);
+    let (headers, body) = chat(&app, false).await;
+    assert_eq!(headers.get("x-hub-provider"), None);
+    assert_eq!(headers.get("x-hub-model-type"), None);
+    assert!(!body.contains("hub_attribution"));
+    let (_, body) = chat(&app, true).await;
+    assert!(!body.contains("hub_attribution"));
+
+    unsafe {
+        std::env::set_var("ATTRIBUTION_HEADERS", "true");
+    }
+    let app = hub(&eastus, &westeu);
+    // East US fails, so West Europe answers.
+    let (headers, _) = chat(&app, false).await;
+    assert_eq!(attribution_headers(&headers), served);
+
+    let (headers, body) = chat(&app, true).await;
+    assert_eq!(attribution_headers(&headers), served);
+    let chunks = stream_chunks(&body);
+    assert_eq!(chunks.len(), 2);
+    assert!(chunks[0].get("hub_attribution").is_none());
+    assert_eq!(
+        chunks[1]["hub_attribution"],
+        json!({
+            "provider": "azure-westeu",
+            "model_key": "gpt-4o-canary",
+            "model_type": "gpt-4o"
+        })
+    );
+
+    unsafe {
+        std::env::remove_var("ATTRIBUTION_HEADERS");
+    }
+}
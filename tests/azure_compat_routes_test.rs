@@ -0,0 +1,173 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use common::{model, model_router, params, pipeline, provider};
+use hub_lib::types::{GatewayConfig, General, ModelConfig, Pipeline, PipelineType, ProviderType};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+/// A model of type `echo` on the mock provider.
+fn echo_model(key: &str) -> ModelConfig {
+    ModelConfig {
+        r#type: "echo".to_string(),
+        ..model(key, "mock")
+    }
+}
+
+/// A hub serving the model `chat` as the deployment `prod-gpt-4o` and `embed` under its own
+/// key; `unrouted` isn't in any pipeline.
+fn hub(azure_compat_routes: bool) -> Router {
+    hub_with_general(General {
+        azure_compat_routes,
+        ..Default::default()
+    })
+}
+
+fn hub_with_general(general: General) -> Router {
+    common::hub(GatewayConfig {
+        general: Some(general),
+        providers: vec![provider("mock", ProviderType::Mock)],
+        models: vec![
+            ModelConfig {
+                params: params(&[("deployment", "prod-gpt-4o")]),
+                ..echo_model("chat")
+            },
+            echo_model("embed"),
+            echo_model("unrouted"),
+        ],
+        pipelines: vec![
+            pipeline("default", vec![model_router(&["chat"])]),
+            Pipeline {
+                r#type: PipelineType::Embeddings,
+                ..pipeline("embeddings", vec![model_router(&["embed"])])
+            },
+        ],
+        ..Default::default()
+    })
+}
+
+async fn post(app: &Router, uri: &str, pipeline: Option<&str>, body: Value) -> Response {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("api-key", "azure-style-key");
+    if let Some(pipeline) = pipeline {
+        request = request.header("x-traceloop-pipeline", pipeline);
+    }
+    let request = request.body(Body::from(body.to_string())).unwrap();
+    app.clone().oneshot(request).await.unwrap()
+}
+
+async fn json_body(response: Response) -> Value {
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+fn chat_body() -> Value {
+    json!({"messages": [{"role": "user", "content": "hello from azure"}]})
+}
+
+#[tokio::test]
+async fn test_chat_completions_through_a_deployment() {
+    let app = hub(true);
+    let response = post(
+        &app,
+        "/openai/deployments/prod-gpt-4o/chat/completions?api-version=2024-02-01",
+        None,
+        chat_body(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["choices"][0]["message"]["content"], "hello from azure");
+}
+
+#[tokio::test]
+async fn test_embeddings_through_a_deployment_named_after_the_model() {
+    let app = hub(true);
+    let response = post(
+        &app,
+        "/openai/deployments/embed/embeddings?api-version=2024-05-01-preview",
+        Some("embeddings"),
+        json!({"input": ["one", "two"]}),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_errors_use_the_azure_envelope() {
+    let app = hub(true);
+    let chat = |deployment: &str, api_version: &str| {
+        format!("/openai/deployments/{deployment}/chat/completions{api_version}")
+    };
+
+    let response = post(&app, &chat("prod-gpt-4o", ""), None, chat_body()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(response).await;
+    assert_eq!(body["error"]["code"], "MissingApiVersionParameter");
+
+    let response = post(
+        &app,
+        &chat("prod-gpt-4o", "?api-version=latest"),
+        None,
+        chat_body(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json_body(response).await["error"]["code"],
+        "UnsupportedApiVersion"
+    );
+
+    for deployment in ["missing", "unrouted"] {
+        let uri = chat(deployment, "?api-version=2024-02-01");
+        let response = post(&app, &uri, None, chat_body()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{deployment}");
+        let body = json_body(response).await;
+        assert_eq!(body["error"]["code"], "DeploymentNotFound");
+        assert!(body["error"]["message"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn test_routes_are_off_by_default() {
+    let app = hub(false);
+    let response = post(
+        &app,
+        "/openai/deployments/prod-gpt-4o/chat/completions?api-version=2024-02-01",
+        None,
+        chat_body(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_bodies_over_the_cap_get_413_in_the_azure_envelope() {
+    let app = hub_with_general(General {
+        azure_compat_routes: true,
+        max_buffered_body_bytes: Some(16),
+        ..Default::default()
+    });
+    let response = post(
+        &app,
+        "/openai/deployments/prod-gpt-4o/chat/completions?api-version=2024-02-01",
+        None,
+        chat_body(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let body = json_body(response).await;
+    assert_eq!(body["error"]["code"], "413");
+    assert_eq!(
+        body["error"]["message"],
+        "Request bodies are limited to 16 bytes"
+    );
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use common::{model, model_router, params, pipeline, provider};
+use hub_lib::batch::{BATCH_TOO_LARGE_CODE, NDJSON_CONTENT_TYPE};
+use hub_lib::types::{
+    BatchInferenceConfig, GatewayConfig, General, ModelConfig, Provider, ProviderType,
+};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+fn mock_provider(key: &str, latency_ms: u64) -> Provider {
+    Provider {
+        api_key: String::new(),
+        params: params(&[("latency_ms", &latency_ms.to_string())]),
+        ..provider(key, ProviderType::Mock)
+    }
+}
+
+fn echo_model(key: &str, provider: &str) -> ModelConfig {
+    ModelConfig {
+        r#type: "echo".to_string(),
+        ..model(key, provider)
+    }
+}
+
+/// A hub whose `slow` model answers after 300ms and whose `fast` model answers at once.
+fn hub(batch_inference: BatchInferenceConfig) -> Router {
+    hub_with_general(General {
+        batch_inference: Some(batch_inference),
+        ..Default::default()
+    })
+}
+
+fn hub_with_general(general: General) -> Router {
+    common::hub(GatewayConfig {
+        general: Some(general),
+        providers: vec![mock_provider("slow", 300), mock_provider("fast", 0)],
+        models: vec![echo_model("slow", "slow"), echo_model("fast", "fast")],
+        pipelines: vec![pipeline("default", vec![model_router(&["slow", "fast"])])],
+        ..Default::default()
+    })
+}
+
+fn line(custom_id: &str, model: &str, content: &str) -> String {
+    json!({
+        "custom_id": custom_id,
+        "model": model,
+        "messages": [{"role": "user", "content": content}]
+    })
+    .to_string()
+}
+
+async fn send_batch(app: &Router, query: &str, lines: &[String]) -> Response {
+    let request = Request::builder()
+        .method("POST")
+        .uri(format!("/api/v1/batch-inference{query}"))
+        .header(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)
+        .body(Body::from(lines.join("\n")))
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap()
+}
+
+async fn results(response: Response) -> Vec<Value> {
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        NDJSON_CONTENT_TYPE
+    );
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn custom_ids(results: &[Value]) -> Vec<&str> {
+    results
+        .iter()
+        .map(|result| result["custom_id"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_failed_lines_do_not_abort_the_batch() {
+    let app = hub(BatchInferenceConfig::default());
+    let lines = [
+        line("ok", "fast", "hello there"),
+        line("unknown-model", "gpt-nonexistent", "hello"),
+        "{not json".to_string(),
+        json!({"custom_id": "streamed", "model": "fast", "stream": true}).to_string(),
+        line("also-ok", "fast", "bye"),
+    ];
+    let results = results(send_batch(&app, "?ordered=true", &lines).await).await;
+    assert_eq!(results.len(), 5);
+
+    assert_eq!(results[0]["custom_id"], "ok");
+    assert_eq!(results[0]["status"], 200);
+    assert_eq!(
+        results[0]["response"]["choices"][0]["message"]["content"],
+        "hello there"
+    );
+    assert!(results[0].get("error").is_none());
+
+    assert_eq!(results[1]["custom_id"], "unknown-model");
+    assert_eq!(results[1]["status"], 404);
+    assert!(results[1]["error"].is_object());
+
+    assert_eq!(results[2]["line"], 3);
+    assert_eq!(results[2]["custom_id"], Value::Null);
+    assert_eq!(results[2]["status"], 400);
+
+    assert_eq!(results[3]["custom_id"], "streamed");
+    assert_eq!(results[3]["status"], 400);
+
+    assert_eq!(results[4]["custom_id"], "also-ok");
+    assert_eq!(results[4]["status"], 200);
+}
+
+#[tokio::test]
+async fn test_results_follow_completion_unless_ordered() {
+    let app = hub(BatchInferenceConfig::default());
+    let lines = [line("first", "slow", "one"), line("second", "fast", "two")];
+
+    let unordered = results(send_batch(&app, "", &lines).await).await;
+    assert_eq!(custom_ids(&unordered), ["second", "first"]);
+
+    let ordered = results(send_batch(&app, "?ordered=true", &lines).await).await;
+    assert_eq!(custom_ids(&ordered), ["first", "second"]);
+
+    // One line at a time completes in order either way.
+    let sequential = results(send_batch(&app, "?concurrency=1", &lines).await).await;
+    assert_eq!(custom_ids(&sequential), ["first", "second"]);
+}
+
+#[tokio::test]
+async fn test_batches_over_the_caps_are_rejected() {
+    let app = hub(BatchInferenceConfig {
+        max_lines: 2,
+        max_body_bytes: 512,
+        max_concurrency: 4,
+    });
+    let lines = [
+        line("a", "fast", "a"),
+        line("b", "fast", "b"),
+        line("c", "fast", "c"),
+    ];
+    let response = send_batch(&app, "", &lines).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["code"], BATCH_TOO_LARGE_CODE);
+
+    let long = line("long", "fast", &"word ".repeat(200));
+    let response = send_batch(&app, "", &[long]).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let response = send_batch(&app, "?concurrency=5", &lines[..2]).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Blank lines don't count towards the cap.
+    let with_blank = [lines[0].clone(), String::new(), lines[1].clone()];
+    let results = results(send_batch(&app, "", &with_blank).await).await;
+    assert_eq!(results.len(), 2);
+}
+
+#[tokio::test]
+async fn test_responses_over_the_body_cap_fail_their_line() {
+    let app = hub_with_general(General {
+        max_buffered_body_bytes: Some(1000),
+        ..Default::default()
+    });
+    let lines = [
+        line("short", "fast", "hi"),
+        line("long", "fast", &"word ".repeat(400)),
+    ];
+    let results = results(send_batch(&app, "?ordered=true", &lines).await).await;
+
+    assert_eq!(results[0]["custom_id"], "short");
+    assert_eq!(results[0]["status"], 200);
+    assert_eq!(results[1]["custom_id"], "long");
+    assert_eq!(results[1]["status"], 502);
+    assert_eq!(
+        results[1]["error"]["message"],
+        "The response failed or was larger than 1000 bytes"
+    );
+}
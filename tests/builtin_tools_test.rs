@@ -0,0 +1,152 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use common::{model, model_router, openai, params, pipeline, provider};
+use hub_lib::types::{GatewayConfig, ModelConfig, Provider, ProviderType};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn web_search() -> Value {
+    let fixture = std::fs::read_to_string("tests/fixtures/openai_web_search.json").unwrap();
+    serde_json::from_str::<Value>(&fixture).unwrap()
+}
+
+fn hub(openai_server: &MockServer, anthropic: &MockServer) -> Router {
+    common::hub(GatewayConfig {
+        providers: vec![
+            openai("openai", openai_server),
+            Provider {
+                params: params(&[("base_url", &anthropic.uri())]),
+                ..provider("anthropic", ProviderType::Anthropic)
+            },
+        ],
+        models: vec![
+            model("gpt-4o", "openai"),
+            model("claude-sonnet-4", "anthropic"),
+            ModelConfig {
+                params: params(&[("ignore_unsupported_params", "true")]),
+                ..model("claude-lenient", "anthropic")
+            },
+        ],
+        pipelines: vec![pipeline(
+            "default",
+            vec![model_router(&[
+                "gpt-4o",
+                "claude-sonnet-4",
+                "claude-lenient",
+            ])],
+        )],
+        ..Default::default()
+    })
+}
+
+async fn chat(app: &Router, model: &str) -> (StatusCode, Value) {
+    let mut request = web_search()["request"].clone();
+    request["model"] = json!(model);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(request.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_builtin_tools_reach_openai_and_annotations_come_back() {
+    let fixture = web_search();
+    let openai = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({
+            "tools": fixture["request"]["tools"],
+            "web_search_options": fixture["request"]["web_search_options"]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture["response"].clone()))
+        .expect(1)
+        .mount(&openai)
+        .await;
+    let anthropic = MockServer::start().await;
+
+    let (status, body) = chat(&hub(&openai, &anthropic), "gpt-4o").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["choices"][0]["message"]["annotations"],
+        fixture["response"]["choices"][0]["message"]["annotations"]
+    );
+}
+
+#[tokio::test]
+async fn test_builtin_tools_are_rejected_for_anthropic_unless_lenient() {
+    let openai = MockServer::start().await;
+    let anthropic = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_1",
+            "model": "claude-sonnet-4",
+            "content": [{"type": "text", "text": "Nothing new today."}],
+            "usage": {"input_tokens": 20, "output_tokens": 12}
+        })))
+        .expect(1)
+        .mount(&anthropic)
+        .await;
+    let app = hub(&openai, &anthropic);
+
+    let (status, body) = chat(&app, "claude-sonnet-4").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["param"], "web_search_options");
+
+    // Without `web_search_options` the built-in tools themselves are listed.
+    let mut request = web_search()["request"].clone();
+    request["model"] = json!("claude-sonnet-4");
+    request
+        .as_object_mut()
+        .unwrap()
+        .remove("web_search_options");
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(request.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: Value =
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(body["error"]["param"], "tools");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("web_search_preview, file_search"),
+        "{body}"
+    );
+
+    // With `ignore_unsupported_params` they are dropped instead, keeping the function tool.
+    let (status, _) = chat(&app, "claude-lenient").await;
+    assert_eq!(status, StatusCode::OK);
+    let sent: Value =
+        serde_json::from_slice(&anthropic.received_requests().await.unwrap()[0].body).unwrap();
+    assert!(sent.get("web_search_options").is_none());
+    let tools = sent["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["name"], "get_weather");
+}
@@ -0,0 +1,166 @@
+//! Config and response fixtures shared by the integration tests. Builders return what most
+//! tests need; a test sets the rest with struct update syntax, e.g.
+//! `ModelConfig { r#type: "echo".to_string(), ..model("chat", "mock") }`.
+
+// Each test crate uses only some of the fixtures.
+#![allow(dead_code)]
+
+use hub_lib::ai_models::registry::ModelRegistry;
+use hub_lib::axum::Router;
+use hub_lib::axum::body::Body;
+use hub_lib::axum::http::{Request, header};
+use hub_lib::pipelines::pipeline::create_pipeline;
+use hub_lib::providers::registry::ProviderRegistry;
+use hub_lib::state::AppState;
+use hub_lib::types::{
+    GatewayConfig, ModelConfig, Pipeline, PipelineType, PluginConfig, Provider, ProviderType,
+};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+pub fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// A provider with a test API key and no params.
+pub fn provider(key: &str, r#type: ProviderType) -> Provider {
+    Provider {
+        key: key.to_string(),
+        r#type,
+        api_key: "sk-test".to_string(),
+        maintenance_windows: vec![],
+        params: HashMap::new(),
+    }
+}
+
+/// An OpenAI provider sending its requests to `server`.
+pub fn openai(key: &str, server: &MockServer) -> Provider {
+    Provider {
+        params: params(&[("base_url", &format!("{}/v1", server.uri()))]),
+        ..provider(key, ProviderType::OpenAI)
+    }
+}
+
+/// An Azure provider at `server` in the failover group `azure-prod`, tried in `priority`
+/// order.
+pub fn azure_member(key: &str, server: &MockServer, priority: &str) -> Provider {
+    Provider {
+        api_key: "azure-key".to_string(),
+        params: params(&[
+            ("base_url", &server.uri()),
+            ("api_version", "2024-10-21"),
+            ("group", "azure-prod"),
+            ("group_priority", priority),
+        ]),
+        ..provider(key, ProviderType::Azure)
+    }
+}
+
+/// An enabled model of type `key` served by `provider`.
+pub fn model(key: &str, provider: &str) -> ModelConfig {
+    ModelConfig {
+        key: key.to_string(),
+        r#type: key.to_string(),
+        provider: provider.to_string(),
+        params: HashMap::new(),
+        enabled: true,
+        deprecation: Default::default(),
+    }
+}
+
+/// A `model-router` plugin trying `models` in order.
+pub fn model_router(models: &[&str]) -> PluginConfig {
+    PluginConfig::ModelRouter {
+        models: models.iter().map(|model| model.to_string()).collect(),
+        allow_dynamic_models: false,
+        adaptive: None,
+        race: None,
+    }
+}
+
+/// A chat pipeline running `plugins`.
+pub fn pipeline(name: &str, plugins: Vec<PluginConfig>) -> Pipeline {
+    Pipeline {
+        name: name.to_string(),
+        r#type: PipelineType::Chat,
+        plugins,
+        store_artifacts: false,
+    }
+}
+
+/// `gpt-4o` on the OpenAI provider `openai` at `server`, served by the `default` pipeline.
+pub fn openai_config(server: &MockServer) -> GatewayConfig {
+    GatewayConfig {
+        providers: vec![openai("openai", server)],
+        models: vec![model("gpt-4o", "openai")],
+        pipelines: vec![pipeline("default", vec![model_router(&["gpt-4o"])])],
+        ..Default::default()
+    }
+}
+
+/// The gateway routes of a hub serving `config`, under `/api/v1`.
+pub fn hub(config: GatewayConfig) -> Router {
+    hub_lib::routes::create_router(Arc::new(AppState::new(config).unwrap()))
+}
+
+/// The routes of `pipeline` alone, such as `/chat/completions`, over `providers` and `models`.
+pub fn pipeline_router(
+    pipeline: &Pipeline,
+    providers: &[Provider],
+    models: &[ModelConfig],
+) -> Router {
+    let provider_registry = Arc::new(ProviderRegistry::new(providers).unwrap());
+    let model_registry = ModelRegistry::new(models, provider_registry).unwrap();
+    create_pipeline(pipeline, &model_registry)
+}
+
+/// An OpenAI chat completion answering `content`.
+pub fn completion(content: &str) -> Value {
+    json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+    })
+}
+
+/// An OpenAI upstream answering every chat request with `completion(content)`.
+pub async fn openai_upstream(content: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion(content)))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// A chat request body sending `content` to `gpt-4o`.
+pub fn chat(content: &str) -> Value {
+    json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": content}]
+    })
+}
+
+/// A JSON POST of `body` to `uri`.
+pub fn post_json(uri: &str, body: &Value) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
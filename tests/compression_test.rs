@@ -0,0 +1,199 @@
+mod common;
+
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use common::{model, model_router, openai, pipeline};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use hub_lib::types::{CompressionConfig, GatewayConfig, General, Pipeline, PipelineType};
+use serde_json::{Value, json};
+use std::io::{Read, Write};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const DIMENSIONS: usize = 1536;
+
+fn large_embeddings_response() -> Value {
+    let data: Vec<Value> = (0..20)
+        .map(|index| {
+            let vector: Vec<f64> = (0..DIMENSIONS).map(|i| i as f64 / 7.0).collect();
+            json!({"object": "embedding", "embedding": vector, "index": index})
+        })
+        .collect();
+    json!({
+        "object": "list",
+        "data": data,
+        "model": "text-embedding-3-small",
+        "usage": {"prompt_tokens": 20, "total_tokens": 20}
+    })
+}
+
+fn stream_chunks() -> Value {
+    json!([{
+        "id": "chatcmpl-1",
+        "created": 1,
+        "model": "gpt-4o",
+        "choices": [{"index": 0, "delta": {"role": "assistant", "content": "hi"}}]
+    }])
+}
+
+fn hub_router(
+    server: &MockServer,
+    r#type: PipelineType,
+    model_key: &str,
+    compression: Option<CompressionConfig>,
+) -> axum::Router {
+    common::hub(GatewayConfig {
+        general: Some(General {
+            compression,
+            ..Default::default()
+        }),
+        providers: vec![openai("openai", server)],
+        models: vec![model(model_key, "openai")],
+        pipelines: vec![Pipeline {
+            r#type,
+            ..pipeline("default", vec![model_router(&[model_key])])
+        }],
+        ..Default::default()
+    })
+}
+
+async fn embeddings_router(compression: Option<CompressionConfig>) -> (MockServer, axum::Router) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(large_embeddings_response()))
+        .mount(&server)
+        .await;
+    let router = hub_router(
+        &server,
+        PipelineType::Embeddings,
+        "text-embedding-3-small",
+        compression,
+    );
+    (server, router)
+}
+
+fn embeddings_body() -> String {
+    json!({"model": "text-embedding-3-small", "input": "hello"}).to_string()
+}
+
+fn gunzip(bytes: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut decoded).unwrap();
+    decoded
+}
+
+#[tokio::test]
+async fn test_large_embeddings_response_is_gzipped() {
+    let (_server, router) = embeddings_router(Some(CompressionConfig::default())).await;
+    let response = router
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/embeddings")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("accept-encoding", "gzip")
+                .body(Body::from(embeddings_body()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    let compressed = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let decoded = gunzip(&compressed);
+    assert!(compressed.len() < decoded.len() / 2);
+    let body: Value = serde_json::from_slice(&decoded).unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 20);
+    assert_eq!(
+        body["data"][0]["embedding"].as_array().unwrap().len(),
+        DIMENSIONS
+    );
+}
+
+#[tokio::test]
+async fn test_responses_are_not_compressed_without_config() {
+    let (_server, router) = embeddings_router(None).await;
+    let response = router
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/embeddings")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("accept-encoding", "gzip")
+                .body(Body::from(embeddings_body()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-encoding").is_none());
+}
+
+#[tokio::test]
+async fn test_gzipped_request_body_is_accepted() {
+    let (server, router) = embeddings_router(Some(CompressionConfig::default())).await;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(embeddings_body().as_bytes()).unwrap();
+    let response = router
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/embeddings")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("content-encoding", "gzip")
+                .body(Body::from(encoder.finish().unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let received = server.received_requests().await.unwrap();
+    let upstream_body: Value = serde_json::from_slice(&received[0].body).unwrap();
+    assert_eq!(upstream_body["input"], "hello");
+}
+
+#[tokio::test]
+async fn test_streaming_chat_responses_are_not_compressed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(stream_chunks()))
+        .mount(&server)
+        .await;
+    let compression = CompressionConfig {
+        min_size_bytes: 0,
+        ..Default::default()
+    };
+    let router = hub_router(&server, PipelineType::Chat, "gpt-4o", Some(compression));
+
+    let response = router
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("accept-encoding", "gzip, br")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "stream": true
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    assert!(response.headers().get("content-encoding").is_none());
+}
@@ -0,0 +1,87 @@
+use hub_lib::config::validation::CONFIG_ERROR_EXIT_CODE;
+use serde_json::{Value, json};
+use std::io::Write;
+use std::process::{Command, Output};
+
+/// Starts the gateway binary on `config` and waits for it to exit.
+fn run_hub(config: &str) -> Output {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(config.as_bytes()).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_hub"))
+        .env("HUB_MODE", "yaml")
+        .env("CONFIG_FILE_PATH", file.path())
+        .env("PORT", "0")
+        .output()
+        .unwrap()
+}
+
+fn stderr_json(output: &Output) -> Value {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    serde_json::from_str(stderr.trim())
+        .unwrap_or_else(|e| panic!("stderr is not JSON ({e}): {stderr}"))
+}
+
+#[test]
+fn test_invalid_config_exits_with_errors_as_json() {
+    let output = run_hub(
+        r#"
+general:
+  max_in_flight_requests: 0
+providers:
+  - key: openai
+    type: openai
+    api_key: sk-test
+models:
+  - key: gpt-4o
+    type: gpt-4o
+    provider: azure
+pipelines:
+  - name: default
+    type: chat
+    plugins:
+      - model-router:
+          models:
+            - gpt-4o
+"#,
+    );
+
+    assert_eq!(output.status.code(), Some(CONFIG_ERROR_EXIT_CODE));
+    assert_eq!(
+        stderr_json(&output),
+        json!([
+            {
+                "code": "unknown_provider",
+                "path": "models[gpt-4o].provider",
+                "message": "Model 'gpt-4o' references non-existent provider 'azure'.",
+                "severity": "error"
+            },
+            {
+                "code": "invalid_admission_limit",
+                "path": "general.max_in_flight_requests",
+                "message": "general.max_in_flight_requests must be greater than 0.",
+                "severity": "error"
+            }
+        ])
+    );
+}
+
+#[test]
+fn test_unparsable_config_exits_with_config_error() {
+    let output = run_hub("providers: [");
+
+    assert_eq!(output.status.code(), Some(CONFIG_ERROR_EXIT_CODE));
+    let errors = stderr_json(&output);
+    assert_eq!(errors.as_array().unwrap().len(), 1);
+    assert_eq!(errors[0]["code"], "unreadable_config");
+    assert_eq!(errors[0]["severity"], "error");
+}
+
+#[test]
+fn test_other_startup_failures_use_a_different_exit_code() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hub"))
+        .env("HUB_MODE", "neither")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+}
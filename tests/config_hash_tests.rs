@@ -1,5 +1,5 @@
 use hub_lib::config::hash::{calculate_config_hash, configs_are_equal};
-use hub_lib::types::{GatewayConfig, Provider, ProviderType};
+use hub_lib::types::{GatewayConfig, ModelConfig, Provider, ProviderType};
 use std::collections::HashMap;
 
 #[test]
@@ -10,10 +10,12 @@ fn test_identical_configs_have_same_hash() {
             key: "test".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![],
         pipelines: vec![],
+        prompt_templates: Default::default(),
     };
 
     let config2 = config1.clone();
@@ -33,10 +35,12 @@ fn test_different_configs_have_different_hashes() {
             key: "test1".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![],
         pipelines: vec![],
+        prompt_templates: Default::default(),
     };
 
     let config2 = GatewayConfig {
@@ -45,10 +49,12 @@ fn test_different_configs_have_different_hashes() {
             key: "test2".to_string(), // Different key
             r#type: ProviderType::OpenAI,
             api_key: "key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![],
         pipelines: vec![],
+        prompt_templates: Default::default(),
     };
 
     assert!(!configs_are_equal(&config1, &config2));
@@ -74,10 +80,12 @@ fn test_params_order_independence() {
             key: "test".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "key".to_string(),
+            maintenance_windows: vec![],
             params: params1,
         }],
         models: vec![],
         pipelines: vec![],
+        prompt_templates: Default::default(),
     };
 
     let config2 = GatewayConfig {
@@ -86,12 +94,41 @@ fn test_params_order_independence() {
             key: "test".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "key".to_string(),
+            maintenance_windows: vec![],
             params: params2,
         }],
         models: vec![],
         pipelines: vec![],
+        prompt_templates: Default::default(),
     };
 
     // Should be equal despite different insertion order
     assert!(configs_are_equal(&config1, &config2));
 }
+
+#[test]
+fn test_model_enabled_flag_changes_hash() {
+    let config1 = GatewayConfig {
+        general: None,
+        providers: vec![],
+        models: vec![ModelConfig {
+            key: "model".to_string(),
+            r#type: "gpt-4o".to_string(),
+            provider: "test".to_string(),
+            params: HashMap::new(),
+            enabled: true,
+            deprecation: Default::default(),
+        }],
+        pipelines: vec![],
+        prompt_templates: Default::default(),
+    };
+
+    let mut config2 = config1.clone();
+    config2.models[0].enabled = false;
+
+    assert!(!configs_are_equal(&config1, &config2));
+    assert_ne!(
+        calculate_config_hash(&config1),
+        calculate_config_hash(&config2)
+    );
+}
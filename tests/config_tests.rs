@@ -146,11 +146,13 @@ fn test_config_with_missing_environment_variable() {
         env::remove_var("MISSING_TEST_VAR");
     }
 
+    // Only a whole API key is resolved later; any other use of an unset variable fails
     let config_content = r#"
 providers:
   - key: openai
     type: openai
     api_key: "${MISSING_TEST_VAR}"
+    base_url: "https://${MISSING_TEST_VAR}/v1"
 
 models:
   - key: gpt-4o-openai
@@ -181,6 +183,57 @@ pipelines:
     assert!(error_message.contains("Environment variable 'MISSING_TEST_VAR' not found"));
 }
 
+#[test]
+fn test_config_with_missing_api_key_variable_resolves_it_later() {
+    unsafe {
+        env::remove_var("MISSING_API_KEY_TEST_VAR");
+    }
+
+    let config_content = r#"
+providers:
+  - key: openai
+    type: openai
+    api_key: "${MISSING_API_KEY_TEST_VAR}"
+  - key: anthropic
+    type: anthropic
+    api_key: "sk-ant-inline"
+
+models:
+  - key: gpt-4o-openai
+    type: gpt-4o
+    provider: openai
+
+pipelines:
+  - name: default
+    type: chat
+    plugins:
+      - model-router:
+          models:
+            - gpt-4o-openai
+"#;
+
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    temp_file
+        .write_all(config_content.as_bytes())
+        .expect("Failed to write to temp file");
+
+    let gateway_config = config::load_config(temp_file.path().to_str().unwrap())
+        .expect("A missing API key variable should not fail the load");
+    let openai = &gateway_config.providers[0];
+    assert!(openai.api_key.is_empty());
+    let secret: serde_json::Value = serde_json::from_str(&openai.params["api_key_secret"]).unwrap();
+    assert_eq!(
+        secret,
+        serde_json::json!({"type": "environment", "variable_name": "MISSING_API_KEY_TEST_VAR"})
+    );
+    assert_eq!(gateway_config.providers[1].api_key, "sk-ant-inline");
+    assert!(
+        !gateway_config.providers[1]
+            .params
+            .contains_key("api_key_secret")
+    );
+}
+
 #[test]
 fn test_config_without_environment_variables() {
     let config_content = r#"
@@ -222,3 +275,312 @@ pipelines:
         .expect("OpenAI provider not found");
     assert_eq!(openai_provider.api_key, "sk-static-key-123".to_string());
 }
+
+fn write_config_file(dir: &std::path::Path, name: &str, contents: &str) -> std::path::PathBuf {
+    let path = dir.join(name);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).expect("Failed to create config dir");
+    }
+    std::fs::write(&path, contents).expect("Failed to write config file");
+    path
+}
+
+#[test]
+fn test_config_comma_separated_files_are_merged() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let providers = write_config_file(
+        dir.path(),
+        "providers.yaml",
+        r#"
+providers:
+  - key: openai
+    type: openai
+    api_key: "sk-static-key-123"
+models:
+  - key: gpt-4o-openai
+    type: gpt-4o
+    provider: openai
+"#,
+    );
+    let pipelines = write_config_file(
+        dir.path(),
+        "pipelines.yaml",
+        r#"
+pipelines:
+  - name: default
+    type: chat
+    plugins:
+      - model-router:
+          models:
+            - gpt-4o-openai
+"#,
+    );
+
+    let path = format!("{}, {}", providers.display(), pipelines.display());
+    let gateway_config = config::load_config(&path).expect("Config loading failed");
+
+    assert_eq!(gateway_config.providers.len(), 1);
+    assert_eq!(gateway_config.models.len(), 1);
+    assert_eq!(gateway_config.pipelines.len(), 1);
+    assert_eq!(gateway_config.pipelines[0].name, "default");
+}
+
+#[test]
+fn test_config_include_directive_with_glob_is_ordered() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    write_config_file(
+        dir.path(),
+        "pipelines/b.yaml",
+        r#"
+pipelines:
+  - name: second
+    type: chat
+    plugins:
+      - model-router:
+          models:
+            - gpt-4o-openai
+"#,
+    );
+    write_config_file(
+        dir.path(),
+        "pipelines/a.yaml",
+        r#"
+pipelines:
+  - name: first
+    type: chat
+    plugins:
+      - model-router:
+          models:
+            - gpt-4o-openai
+"#,
+    );
+    write_config_file(dir.path(), "pipelines/notes.txt", "not yaml");
+    let main = write_config_file(
+        dir.path(),
+        "config.yaml",
+        r#"
+include:
+  - pipelines/*.yaml
+providers:
+  - key: openai
+    type: openai
+    api_key: "sk-static-key-123"
+models:
+  - key: gpt-4o-openai
+    type: gpt-4o
+    provider: openai
+"#,
+    );
+
+    let gateway_config =
+        config::load_config(main.to_str().unwrap()).expect("Config loading failed");
+
+    let names: Vec<&str> = gateway_config
+        .pipelines
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["first", "second"]);
+}
+
+#[test]
+fn test_config_include_substitutes_env_vars_per_file() {
+    unsafe {
+        env::set_var("TEST_INCLUDED_API_KEY", "sk-included-key");
+    }
+
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    write_config_file(
+        dir.path(),
+        "providers.yaml",
+        r#"
+providers:
+  - key: openai
+    type: openai
+    api_key: "${TEST_INCLUDED_API_KEY}"
+"#,
+    );
+    let main = write_config_file(dir.path(), "config.yaml", "include:\n  - providers.yaml\n");
+
+    let gateway_config =
+        config::load_config(main.to_str().unwrap()).expect("Config loading failed");
+    assert_eq!(gateway_config.providers[0].api_key, "sk-included-key");
+
+    unsafe {
+        env::remove_var("TEST_INCLUDED_API_KEY");
+    }
+}
+
+#[test]
+fn test_config_duplicate_keys_across_files_name_both_sources() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let provider = r#"
+providers:
+  - key: openai
+    type: openai
+    api_key: "sk-static-key-123"
+"#;
+    let first = write_config_file(dir.path(), "first.yaml", provider);
+    let second = write_config_file(dir.path(), "second.yaml", provider);
+
+    let path = format!("{},{}", first.display(), second.display());
+    let error_message = config::load_config(&path).unwrap_err().to_string();
+
+    assert!(error_message.contains("Duplicate provider key 'openai'"));
+    assert!(error_message.contains("first.yaml"));
+    assert!(error_message.contains("second.yaml"));
+}
+
+#[test]
+fn test_config_general_in_two_files_names_both_sources() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let first = write_config_file(
+        dir.path(),
+        "first.yaml",
+        "general:\n  timing_headers: true\n",
+    );
+    let second = write_config_file(dir.path(), "second.yaml", "general:\n  reuse_port: true\n");
+
+    let path = format!("{},{}", first.display(), second.display());
+    let error_message = config::load_config(&path).unwrap_err().to_string();
+
+    assert!(error_message.contains("Duplicate general section"));
+    assert!(error_message.contains("first.yaml"));
+    assert!(error_message.contains("second.yaml"));
+}
+
+#[test]
+fn test_config_model_enabled_flag() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = write_config_file(
+        dir.path(),
+        "config.yaml",
+        r#"
+providers:
+  - key: openai
+    type: openai
+    api_key: "sk-static-key-123"
+models:
+  - key: gpt-4o
+    type: gpt-4o
+    provider: openai
+  - key: gpt-4o-mini
+    type: gpt-4o-mini
+    provider: openai
+    enabled: false
+pipelines:
+  - name: default
+    type: chat
+    plugins:
+      - model-router:
+          models:
+            - gpt-4o
+            - gpt-4o-mini
+"#,
+    );
+
+    let gateway_config =
+        config::load_config(path.to_str().unwrap()).expect("Config loading failed");
+    assert!(gateway_config.models[0].enabled);
+    assert!(!gateway_config.models[1].enabled);
+    assert!(!gateway_config.models[1].params.contains_key("enabled"));
+}
+
+#[test]
+fn test_config_model_deprecation() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = write_config_file(
+        dir.path(),
+        "config.yaml",
+        r#"
+providers:
+  - key: openai
+    type: openai
+    api_key: "sk-static-key-123"
+models:
+  - key: gpt-4o
+    type: gpt-4o
+    provider: openai
+  - key: gpt-3.5-turbo-0301
+    type: gpt-3.5-turbo-0301
+    provider: openai
+    deprecated: true
+    replacement: gpt-4o
+    auto_replace: true
+    sunset: 2025-06-30
+pipelines:
+  - name: default
+    type: chat
+    plugins:
+      - model-router:
+          models:
+            - gpt-3.5-turbo-0301
+            - gpt-4o
+"#,
+    );
+
+    let gateway_config =
+        config::load_config(path.to_str().unwrap()).expect("Config loading failed");
+    assert!(!gateway_config.models[0].deprecation.deprecated);
+    let deprecation = &gateway_config.models[1].deprecation;
+    assert!(deprecation.deprecated);
+    assert!(deprecation.auto_replace);
+    assert_eq!(deprecation.replacement.as_deref(), Some("gpt-4o"));
+    assert_eq!(deprecation.sunset.as_deref(), Some("2025-06-30"));
+    assert!(gateway_config.models[1].params.is_empty());
+}
+
+#[test]
+fn test_config_prompt_templates() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    write_config_file(
+        dir.path(),
+        "prompts.yaml",
+        r#"
+prompt_templates:
+  support: "You are {{brand}}'s assistant."
+"#,
+    );
+    let main = write_config_file(
+        dir.path(),
+        "config.yaml",
+        r#"
+include:
+  - prompts.yaml
+pipelines:
+  - name: default
+    type: chat
+    plugins:
+      - system-prompt:
+          system_prompt_template: support
+          variables:
+            brand: Acme
+          allow_header_variables: true
+"#,
+    );
+
+    let gateway_config =
+        config::load_config(main.to_str().unwrap()).expect("Config loading failed");
+    assert_eq!(
+        gateway_config.prompt_templates["support"],
+        "You are {{brand}}'s assistant."
+    );
+    let hub_lib::types::PluginConfig::SystemPrompt(system_prompt) =
+        &gateway_config.pipelines[0].plugins[0]
+    else {
+        panic!("expected a system-prompt plugin");
+    };
+    assert_eq!(system_prompt.system_prompt_template, "support");
+    assert_eq!(system_prompt.variables["brand"], "Acme");
+    assert!(system_prompt.allow_header_variables);
+
+    // The same template name in two files is an error.
+    let duplicate = write_config_file(
+        dir.path(),
+        "duplicate.yaml",
+        "prompt_templates:\n  support: \"Hi\"\n",
+    );
+    let path = format!("{},{}", main.display(), duplicate.display());
+    let error_message = config::load_config(&path).unwrap_err().to_string();
+    assert!(error_message.contains("Duplicate prompt template 'support'"));
+}
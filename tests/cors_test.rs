@@ -0,0 +1,117 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{HeaderMap, Method, Request, StatusCode};
+use common::{hub, model, model_router, pipeline, provider};
+use hub_lib::types::{CorsConfig, GatewayConfig, General, ProviderType};
+use tower::ServiceExt;
+
+fn config(cors: Option<CorsConfig>) -> GatewayConfig {
+    GatewayConfig {
+        general: Some(General {
+            cors,
+            ..Default::default()
+        }),
+        providers: vec![provider("openai", ProviderType::OpenAI)],
+        models: vec![model("gpt-4o", "openai")],
+        pipelines: vec![pipeline("default", vec![model_router(&["gpt-4o"])])],
+        ..Default::default()
+    }
+}
+
+fn browser_cors() -> CorsConfig {
+    CorsConfig {
+        allowed_origins: vec![
+            "https://app.example.com".to_string(),
+            "https://*.preview.example.com".to_string(),
+        ],
+        allowed_headers: vec!["authorization".to_string(), "content-type".to_string()],
+        allow_credentials: true,
+        max_age: Some(600),
+    }
+}
+
+async fn preflight(cors: Option<CorsConfig>, origin: &str) -> (StatusCode, HeaderMap) {
+    let response = hub(config(cors))
+        .oneshot(
+            Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/api/v1/chat/completions")
+                .header("origin", origin)
+                .header("access-control-request-method", "POST")
+                .header(
+                    "access-control-request-headers",
+                    "content-type,authorization",
+                )
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    (response.status(), response.headers().clone())
+}
+
+#[tokio::test]
+async fn test_preflight_for_chat_completions_is_answered() {
+    let (status, headers) = preflight(Some(browser_cors()), "https://app.example.com").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+    assert_eq!(headers["access-control-allow-credentials"], "true");
+    assert_eq!(headers["access-control-max-age"], "600");
+    let methods = headers["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.contains("POST"));
+    let allowed = headers["access-control-allow-headers"].to_str().unwrap();
+    assert!(allowed.contains("authorization") && allowed.contains("content-type"));
+}
+
+#[tokio::test]
+async fn test_preflight_matches_wildcard_subdomains_only() {
+    let (_, headers) = preflight(Some(browser_cors()), "https://pr-12.preview.example.com").await;
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "https://pr-12.preview.example.com"
+    );
+
+    let (_, headers) = preflight(Some(browser_cors()), "https://evil.com").await;
+    assert!(headers.get("access-control-allow-origin").is_none());
+}
+
+#[tokio::test]
+async fn test_no_cors_headers_without_config() {
+    let (_, headers) = preflight(None, "https://app.example.com").await;
+    assert!(headers.get("access-control-allow-origin").is_none());
+}
+
+#[tokio::test]
+async fn test_hub_response_headers_are_exposed() {
+    let cors = CorsConfig {
+        allowed_origins: vec!["*".to_string()],
+        ..Default::default()
+    };
+    let app_state = Arc::new(AppState::new(config(Some(cors))).unwrap());
+    let router = hub_lib::routes::create_router(app_state);
+    let response = router
+        .oneshot(
+            Request::builder()
+                .uri("/health")
+                .header("origin", "https://anywhere.dev")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let headers = response.headers();
+    assert_eq!(headers["access-control-allow-origin"], "*");
+    let exposed = headers["access-control-expose-headers"].to_str().unwrap();
+    for header in [
+        "x-genai-provider-name",
+        "x-hub-upstream-ttfb-ms",
+        "x-hub-overhead-ms",
+    ] {
+        assert!(exposed.contains(header), "{header} not exposed: {exposed}");
+    }
+}
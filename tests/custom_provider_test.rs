@@ -0,0 +1,182 @@
+use async_trait::async_trait;
+use hub_lib::axum::http::StatusCode;
+use hub_lib::gateway::Gateway;
+use hub_lib::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use hub_lib::models::completion::{CompletionRequest, CompletionResponse};
+use hub_lib::models::content::ChatMessageContent;
+use hub_lib::models::embeddings::{
+    Embedding, EmbeddingsInput, EmbeddingsRequest, EmbeddingsResponse,
+};
+use hub_lib::providers::provider::Provider;
+use hub_lib::providers::registry::ProviderRegistry;
+use hub_lib::types::{GatewayConfig, ModelConfig, Provider as ProviderConfig, ProviderType};
+use serde_json::json;
+use std::sync::Arc;
+
+/// A provider for a proprietary inference service, answering from its `region` param.
+struct AcmeProvider {
+    key: String,
+    region: String,
+}
+
+#[async_trait]
+impl Provider for AcmeProvider {
+    fn new(config: &ProviderConfig) -> Self {
+        Self {
+            key: config.key.clone(),
+            region: config.params.get("region").cloned().unwrap_or_default(),
+        }
+    }
+
+    fn key(&self) -> String {
+        self.key.clone()
+    }
+
+    fn r#type(&self) -> ProviderType {
+        ProviderType::from_name("acme")
+    }
+
+    async fn chat_completions(
+        &self,
+        payload: ChatCompletionRequest,
+        model_config: &ModelConfig,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        let content = format!(
+            "{} messages served in {}",
+            payload.messages.len(),
+            self.region
+        );
+        let completion = serde_json::from_value(json!({
+            "id": "acme-1",
+            "model": model_config.r#type,
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": content
+                },
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 5, "total_tokens": 8},
+            "system_fingerprint": null
+        }))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        Ok(ChatCompletionResponse::NonStream(completion))
+    }
+
+    async fn completions(
+        &self,
+        _payload: CompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<CompletionResponse, StatusCode> {
+        Err(StatusCode::NOT_IMPLEMENTED)
+    }
+
+    async fn embeddings(
+        &self,
+        payload: EmbeddingsRequest,
+        model_config: &ModelConfig,
+    ) -> Result<EmbeddingsResponse, StatusCode> {
+        let inputs = match payload.input {
+            EmbeddingsInput::Single(_) => 1,
+            EmbeddingsInput::Multiple(inputs) => inputs.len(),
+            _ => return Err(StatusCode::BAD_REQUEST),
+        };
+        let data: Vec<_> = (0..inputs)
+            .map(|index| {
+                json!({"object": "embedding", "embedding": [index as f32, 1.0], "index": index})
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "object": "list",
+            "data": data,
+            "model": model_config.r#type,
+            "usage": {"prompt_tokens": 2, "total_tokens": 2}
+        }))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+fn config(provider_type: &str) -> GatewayConfig {
+    serde_yaml::from_str(&format!(
+        r#"
+providers:
+  - key: acme
+    type: {provider_type}
+    region: eu-west
+models:
+  - key: acme-chat
+    type: acme-large
+    provider: acme
+  - key: acme-embed
+    type: acme-embed-v2
+    provider: acme
+pipelines:
+  - name: default
+    type: chat
+    plugins:
+      - model-router:
+          models: [acme-chat]
+  - name: embeddings
+    type: embeddings
+    plugins:
+      - model-router:
+          models: [acme-embed]
+"#
+    ))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_registered_provider_serves_chat_and_embeddings() {
+    ProviderRegistry::register_factory("acme", |config| {
+        Ok(Arc::new(AcmeProvider::new(config)) as Arc<dyn Provider>)
+    });
+    let gateway = Gateway::new(config("acme")).unwrap();
+
+    let request = serde_json::from_value(json!({
+        "model": "acme-chat",
+        "messages": [{"role": "user", "content": "hello"}]
+    }))
+    .unwrap();
+    let ChatCompletionResponse::NonStream(completion) =
+        gateway.chat_completions("default", request).await.unwrap()
+    else {
+        panic!("expected a completion");
+    };
+    assert_eq!(completion.model, "acme-large");
+    assert!(matches!(
+        &completion.choices[0].message.content,
+        Some(ChatMessageContent::String(text)) if text == "1 messages served in eu-west"
+    ));
+
+    let request =
+        serde_json::from_value(json!({"model": "acme-embed", "input": ["one", "two"]})).unwrap();
+    let embeddings = gateway.embeddings("embeddings", request).await.unwrap();
+    assert_eq!(embeddings.model, "acme-embed-v2");
+    assert_eq!(embeddings.data.len(), 2);
+    assert!(
+        matches!(&embeddings.data[1].embedding, Embedding::Float(vector) if vector == &[1.0, 1.0])
+    );
+}
+
+#[tokio::test]
+async fn test_unregistered_provider_type_fails_validation() {
+    let error = Gateway::new(config("acme-unregistered")).err().unwrap();
+    assert!(
+        error.to_string().contains("unknown_provider_type"),
+        "{error:#}"
+    );
+}
+
+#[tokio::test]
+async fn test_factory_errors_fail_the_config() {
+    ProviderRegistry::register_factory("acme-broken", |config| {
+        anyhow::bail!("no credentials for {}", config.key)
+    });
+    let error = Gateway::new(config("acme-broken")).err().unwrap();
+    assert!(
+        format!("{error:#}").contains("no credentials for acme"),
+        "{error:#}"
+    );
+}
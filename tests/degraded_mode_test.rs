@@ -0,0 +1,149 @@
+mod common;
+
+use common::{completion, model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::types::{DegradedMode, ModelConfig, PluginConfig};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, Respond, ResponseTemplate};
+
+/// An upstream that fails with 500 while `failing` is set.
+struct FlakyUpstream {
+    failing: Arc<AtomicBool>,
+}
+
+impl Respond for FlakyUpstream {
+    fn respond(&self, _: &wiremock::Request) -> ResponseTemplate {
+        if self.failing.load(Ordering::SeqCst) {
+            ResponseTemplate::new(500)
+        } else {
+            ResponseTemplate::new(200).set_body_json(completion("primary"))
+        }
+    }
+}
+
+/// A model of type `r#type` on the provider of the same key.
+fn typed_model(key: &str, r#type: &str) -> ModelConfig {
+    ModelConfig {
+        r#type: r#type.to_string(),
+        ..model(key, key)
+    }
+}
+
+fn hub(primary: &MockServer, substitute: &MockServer) -> Router {
+    let providers = [openai("primary", primary), openai("substitute", substitute)];
+    let models = [
+        typed_model("primary", "gpt-4o"),
+        typed_model("substitute", "gpt-4o-mini"),
+    ];
+    let degraded_mode: DegradedMode = serde_json::from_value(json!({
+        "model": "substitute",
+        "window_seconds": 1,
+        "min_requests": 3,
+        "max_error_rate_percent": 50,
+        "min_degraded_seconds": 1,
+        "probe_percent": 0,
+        "overrides": {"max_tokens": 64}
+    }))
+    .unwrap();
+    pipeline_router(
+        &pipeline(
+            "degraded-mode-test",
+            vec![
+                model_router(&["primary"]),
+                PluginConfig::DegradedMode(degraded_mode),
+            ],
+        ),
+        &providers,
+        &models,
+    )
+}
+
+async fn chat(app: &Router) -> (StatusCode, Option<String>, Option<String>) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "max_tokens": 1000
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let degraded = response
+        .headers()
+        .get("x-hub-degraded")
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let content = serde_json::from_slice::<Value>(&body)
+        .ok()
+        .and_then(|body| {
+            body["choices"][0]["message"]["content"]
+                .as_str()
+                .map(str::to_string)
+        });
+    (status, degraded, content)
+}
+
+#[tokio::test]
+async fn test_pipeline_degrades_and_recovers() {
+    let failing = Arc::new(AtomicBool::new(true));
+    let primary = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(FlakyUpstream {
+            failing: failing.clone(),
+        })
+        .expect(4)
+        .mount(&primary)
+        .await;
+    let substitute = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(
+            json!({"model": "gpt-4o-mini", "max_tokens": 64}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("substitute")))
+        .expect(1)
+        .mount(&substitute)
+        .await;
+    let app = hub(&primary, &substitute);
+
+    // Failures below min_requests don't degrade the pipeline yet.
+    for _ in 0..3 {
+        let (status, degraded, _) = chat(&app).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(degraded, None);
+    }
+
+    // Degraded: served by the substitute model with max_tokens capped.
+    let (status, degraded, content) = chat(&app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(degraded.as_deref(), Some("true"));
+    assert_eq!(content.as_deref(), Some("substitute"));
+
+    // Once the failures leave the window and min_degraded_seconds passed, the normal
+    // route is back.
+    failing.store(false, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let (status, degraded, content) = chat(&app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(degraded, None);
+    assert_eq!(content.as_deref(), Some("primary"));
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::{model, model_router, openai, openai_upstream, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::axum::response::Response;
+use hub_lib::pipelines::deprecation::{DEPRECATION_HEADER, SUNSET_HEADER};
+use hub_lib::types::{ModelConfig, ModelDeprecation};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::MockServer;
+
+fn hub(server: &MockServer, auto_replace: bool) -> Router {
+    pipeline_router(
+        &pipeline(
+            "default",
+            vec![model_router(&["gpt-3.5-turbo-0301", "gpt-4o"])],
+        ),
+        &[openai("openai", server)],
+        &[
+            model("gpt-4o", "openai"),
+            ModelConfig {
+                deprecation: ModelDeprecation {
+                    deprecated: true,
+                    replacement: Some("gpt-4o".to_string()),
+                    auto_replace,
+                    sunset: Some("2025-06-30".to_string()),
+                },
+                ..model("gpt-3.5-turbo-0301", "openai")
+            },
+        ],
+    )
+}
+
+async fn post_chat(app: Router, model: &str) -> Response {
+    app.oneshot(
+        Request::builder()
+            .uri("/chat/completions")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "model": model,
+                    "messages": [{"role": "user", "content": "hello"}]
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+async fn json_body(response: Response) -> Value {
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_deprecated_model_is_rejected_with_replacement() {
+    let server = openai_upstream("hi").await;
+
+    let response = post_chat(hub(&server, false), "gpt-3.5-turbo-0301").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.headers()[DEPRECATION_HEADER], "true");
+    let body = json_body(response).await;
+    assert_eq!(body["error"]["param"], "model");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("use 'gpt-4o' instead")
+    );
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_auto_replace_routes_to_replacement() {
+    let server = openai_upstream("hi").await;
+
+    let response = post_chat(hub(&server, true), "gpt-3.5-turbo-0301").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[DEPRECATION_HEADER], "true");
+    assert_eq!(
+        response.headers()[SUNSET_HEADER],
+        "Mon, 30 Jun 2025 00:00:00 GMT"
+    );
+    let received = server.received_requests().await.unwrap();
+    let upstream: Value = serde_json::from_slice(&received[0].body).unwrap();
+    assert_eq!(upstream["model"], "gpt-4o");
+}
+
+#[tokio::test]
+async fn test_current_model_has_no_deprecation_headers() {
+    let server = openai_upstream("hi").await;
+
+    let response = post_chat(hub(&server, false), "gpt-4o").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(DEPRECATION_HEADER).is_none());
+}
+
+#[tokio::test]
+async fn test_models_list_includes_deprecation() {
+    let server = openai_upstream("hi").await;
+
+    let response = hub(&server, true)
+        .oneshot(Request::get("/models").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let body = json_body(response).await;
+    let models = body["data"].as_array().unwrap();
+    let deprecated = models
+        .iter()
+        .find(|model| model["id"] == "gpt-3.5-turbo-0301")
+        .unwrap();
+    assert_eq!(deprecated["deprecated"], true);
+    assert_eq!(deprecated["replacement"], "gpt-4o");
+    assert_eq!(deprecated["auto_replace"], true);
+    assert_eq!(deprecated["sunset"], "2025-06-30");
+    let current = models.iter().find(|model| model["id"] == "gpt-4o").unwrap();
+    assert!(current.get("deprecated").is_none());
+}
@@ -0,0 +1,278 @@
+mod common;
+
+use common::{completion, model, model_router, params, pipeline, pipeline_router, provider};
+use hub_lib::ai_models::instance::ModelInstance;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::models::chat::ChatCompletionRequest;
+use hub_lib::models::completion::CompletionRequest;
+use hub_lib::models::embeddings::EmbeddingsRequest;
+use hub_lib::providers::anthropic::AnthropicProvider;
+use hub_lib::providers::azure::AzureProvider;
+use hub_lib::providers::bedrock::BedrockProvider;
+use hub_lib::providers::openai::OpenAIProvider;
+use hub_lib::providers::provider::Provider as _;
+use hub_lib::providers::upstream::UpstreamRequest;
+use hub_lib::providers::vertexai::VertexAIProvider;
+use hub_lib::types::{ModelConfig, Provider, ProviderType};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn provider_config(r#type: ProviderType, provider_params: &[(&str, &str)]) -> Provider {
+    Provider {
+        api_key: "sk-secret".to_string(),
+        params: params(provider_params),
+        ..provider("upstream", r#type)
+    }
+}
+
+fn instance(
+    provider: Arc<dyn hub_lib::providers::provider::Provider>,
+    model_type: &str,
+    model_params: &[(&str, &str)],
+) -> ModelInstance {
+    ModelInstance {
+        name: "model".to_string(),
+        model_type: model_type.to_string(),
+        provider,
+        config: ModelConfig {
+            r#type: model_type.to_string(),
+            params: params(model_params),
+            ..model("model", "upstream")
+        },
+    }
+}
+
+fn chat_body(model: &str) -> Value {
+    json!({
+        "model": model,
+        "messages": [
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "hello"}
+        ],
+        "temperature": 0.7,
+        "max_tokens": 16
+    })
+}
+
+fn chat_request(model: &str) -> ChatCompletionRequest {
+    serde_json::from_value(chat_body(model)).unwrap()
+}
+
+/// An upstream that only answers requests carrying the expected credential header.
+async fn mock_upstream(auth_header: &'static str, auth_value: &'static str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(header(auth_header, auth_value))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Asserts the mock server received exactly the URL and body that were built.
+async fn assert_sent(server: &MockServer, built: &UpstreamRequest) {
+    let received = server.received_requests().await.unwrap();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].url.as_str(), built.url);
+    assert_eq!(received[0].body, built.body);
+}
+
+#[tokio::test]
+async fn test_openai_build_matches_sent_requests() {
+    let server = mock_upstream("authorization", "Bearer sk-secret").await;
+    let base_url = format!("{}/v1", server.uri());
+    let config = provider_config(ProviderType::OpenAI, &[("base_url", &base_url)]);
+    let model = instance(Arc::new(OpenAIProvider::new(&config)), "gpt-4o", &[]);
+
+    let built = model
+        .build_chat_request(chat_request("gpt-4o"))
+        .await
+        .unwrap();
+    model
+        .chat_completions(chat_request("gpt-4o"))
+        .await
+        .unwrap();
+    assert_sent(&server, &built).await;
+    assert_eq!(built.url, format!("{base_url}/chat/completions"));
+    assert_eq!(built.headers["authorization"], "Bearer sk-secret");
+    assert_eq!(built.body_json()["model"], "gpt-4o");
+}
+
+#[tokio::test]
+async fn test_openai_completion_and_embeddings_requests() {
+    let config = provider_config(
+        ProviderType::OpenAI,
+        &[("base_url", "https://example.com/v1")],
+    );
+    let provider = Arc::new(OpenAIProvider::new(&config));
+
+    let completion = json!({"model": "gpt-3.5-turbo-instruct", "prompt": "hi"});
+    let completion: CompletionRequest = serde_json::from_value(completion).unwrap();
+    let model = instance(provider.clone(), "gpt-3.5-turbo-instruct", &[]);
+    let built = model.build_completion_request(completion).await.unwrap();
+    assert_eq!(built.url, "https://example.com/v1/completions");
+    assert_eq!(built.body_json()["prompt"], "hi");
+
+    let embeddings = json!({"model": "text-embedding-3-small", "input": "hi"});
+    let embeddings: EmbeddingsRequest = serde_json::from_value(embeddings).unwrap();
+    let model = instance(provider, "text-embedding-3-small", &[]);
+    let built = model.build_embeddings_request(embeddings).await.unwrap();
+    assert_eq!(built.url, "https://example.com/v1/embeddings");
+    assert_eq!(built.body_json()["input"], "hi");
+}
+
+#[tokio::test]
+async fn test_azure_build_matches_sent_request() {
+    let server = mock_upstream("api-key", "sk-secret").await;
+    let config = provider_config(
+        ProviderType::Azure,
+        &[("base_url", &server.uri()), ("api_version", "2024-10-21")],
+    );
+    let model = instance(
+        Arc::new(AzureProvider::new(&config)),
+        "gpt-4o",
+        &[("deployment", "chat-deployment")],
+    );
+
+    let built = model
+        .build_chat_request(chat_request("gpt-4o"))
+        .await
+        .unwrap();
+    model
+        .chat_completions(chat_request("gpt-4o"))
+        .await
+        .unwrap();
+    assert_sent(&server, &built).await;
+    assert_eq!(
+        built.url,
+        format!(
+            "{}/chat-deployment/chat/completions?api-version=2024-10-21",
+            server.uri()
+        )
+    );
+    assert_eq!(built.to_masked_json()["headers"]["api-key"], "***");
+}
+
+#[tokio::test]
+async fn test_anthropic_build_chat_request() {
+    let config = provider_config(ProviderType::Anthropic, &[]);
+    let model = instance(
+        Arc::new(AnthropicProvider::new(&config)),
+        "claude-3-5-sonnet",
+        &[],
+    );
+
+    let built = model
+        .build_chat_request(chat_request("claude-3-5-sonnet"))
+        .await
+        .unwrap();
+    assert_eq!(built.url, "https://api.anthropic.com/v1/messages");
+    assert_eq!(built.headers["x-api-key"], "sk-secret");
+    assert_eq!(built.headers["anthropic-version"], "2023-06-01");
+    let body = built.body_json();
+    assert_eq!(body["model"], "claude-3-5-sonnet");
+    assert_eq!(body["system"], "Be brief.");
+    assert_eq!(body["max_tokens"], 16);
+}
+
+#[tokio::test]
+async fn test_vertexai_build_chat_request_with_api_key() {
+    let config = provider_config(ProviderType::VertexAI, &[]);
+    let model = instance(
+        Arc::new(VertexAIProvider::new(&config)),
+        "gemini-1.5-flash",
+        &[],
+    );
+
+    let built = model
+        .build_chat_request(chat_request("gemini-1.5-flash"))
+        .await
+        .unwrap();
+    assert_eq!(
+        built.url,
+        "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-flash:generateContent"
+    );
+    assert_eq!(built.headers["x-goog-api-key"], "sk-secret");
+    let body = built.body_json();
+    assert_eq!(body["contents"][0]["parts"][0]["text"], "hello");
+    assert_eq!(body["generation_config"]["max_output_tokens"], 16);
+}
+
+#[tokio::test]
+async fn test_bedrock_build_chat_request() {
+    let config = provider_config(ProviderType::Bedrock, &[("region", "us-east-1")]);
+    let model = instance(
+        Arc::new(BedrockProvider::new(&config)),
+        "claude-3-haiku-20240307",
+        &[("model_provider", "anthropic")],
+    );
+
+    let built = model
+        .build_chat_request(chat_request("claude-3-haiku-20240307"))
+        .await
+        .unwrap();
+    assert_eq!(
+        built.url,
+        "https://bedrock-runtime.us-east-1.amazonaws.com/model/\
+         anthropic.claude-3-haiku-20240307-v1:0/invoke"
+    );
+    let body = built.body_json();
+    assert_eq!(body["anthropic_version"], "bedrock-2023-05-31");
+    assert!(body.get("model").is_none());
+}
+
+#[tokio::test]
+async fn test_dry_run_header_returns_request_without_sending() {
+    unsafe {
+        std::env::set_var("ALLOW_DEBUG_HEADERS", "true");
+    }
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let base_url = format!("{}/v1", server.uri());
+    let app = pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &[Provider {
+            key: "openai".to_string(),
+            ..provider_config(ProviderType::OpenAI, &[("base_url", &base_url)])
+        }],
+        &[model("gpt-4o", "openai")],
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-hub-dry-run", "true")
+                .body(Body::from(chat_body("gpt-4o").to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-genai-provider-name"], "openai");
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["dry_run"], true);
+    assert_eq!(body["model"], "gpt-4o");
+    assert_eq!(body["provider"]["key"], "openai");
+    assert_eq!(body["provider"]["type"], "openai");
+    let request = &body["request"];
+    assert_eq!(request["url"], format!("{base_url}/chat/completions"));
+    assert_eq!(request["headers"]["authorization"], "***");
+    assert_eq!(request["body"]["temperature"], 0.7);
+    assert!(!body.to_string().contains("sk-secret"));
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use common::{model, params, provider};
+use hub_lib::ai_models::instance::ModelInstance;
+use hub_lib::axum::http::StatusCode;
+use hub_lib::models::embeddings::{Embedding, EmbeddingsInput, EmbeddingsRequest};
+use hub_lib::providers::azure::AzureProvider;
+use hub_lib::providers::openai::OpenAIProvider;
+use hub_lib::providers::provider::Provider as _;
+use hub_lib::types::{ModelConfig, Provider, ProviderType};
+use serde_json::json;
+use std::sync::Arc;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn float_response() -> serde_json::Value {
+    json!({
+        "object": "list",
+        "data": [{"object": "embedding", "embedding": [1.0, -2.0], "index": 0}],
+        "model": "text-embedding-3-small",
+        "usage": {"prompt_tokens": 1, "total_tokens": 1}
+    })
+}
+
+fn request(encoding_format: Option<&str>, dimensions: Option<u32>) -> EmbeddingsRequest {
+    EmbeddingsRequest {
+        model: "text-embedding-3-small".to_string(),
+        input: EmbeddingsInput::Single("hello".to_string()),
+        user: None,
+        encoding_format: encoding_format.map(str::to_string),
+        dimensions,
+        extra_body: None,
+    }
+}
+
+fn provider_config(r#type: ProviderType, provider_params: &[(&str, &str)]) -> Provider {
+    Provider {
+        api_key: "test-key".to_string(),
+        params: params(provider_params),
+        ..provider("upstream", r#type)
+    }
+}
+
+fn embedding_model() -> ModelConfig {
+    ModelConfig {
+        r#type: "text-embedding-3-small".to_string(),
+        ..model("embedding", "upstream")
+    }
+}
+
+fn openai_instance(server: &MockServer) -> ModelInstance {
+    let config = provider_config(
+        ProviderType::OpenAI,
+        &[("base_url", &format!("{}/v1", server.uri()))],
+    );
+    ModelInstance {
+        name: "embedding".to_string(),
+        model_type: "text-embedding-3-small".to_string(),
+        provider: Arc::new(OpenAIProvider::new(&config)),
+        config: embedding_model(),
+    }
+}
+
+#[tokio::test]
+async fn test_openai_forwards_dimensions() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .and(body_partial_json(json!({"dimensions": 256})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(float_response()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let response = openai_instance(&server)
+        .embeddings(request(None, Some(256)))
+        .await
+        .expect("dimensions should be forwarded to OpenAI");
+    assert!(matches!(response.data[0].embedding, Embedding::Float(_)));
+}
+
+#[tokio::test]
+async fn test_azure_forwards_dimensions() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/embedding-deployment/embeddings"))
+        .and(body_partial_json(json!({"dimensions": 512})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(float_response()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let provider = AzureProvider::new(&provider_config(
+        ProviderType::Azure,
+        &[("base_url", &server.uri()), ("api_version", "2024-02-01")],
+    ));
+    let model_config = ModelConfig {
+        params: params(&[("deployment", "embedding-deployment")]),
+        ..embedding_model()
+    };
+
+    provider
+        .embeddings(request(None, Some(512)), &model_config)
+        .await
+        .expect("dimensions should be forwarded to Azure");
+}
+
+#[tokio::test]
+async fn test_base64_requested_but_upstream_returns_floats() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(float_response()))
+        .mount(&server)
+        .await;
+
+    let response = openai_instance(&server)
+        .embeddings(request(Some("base64"), None))
+        .await
+        .unwrap();
+    match &response.data[0].embedding {
+        // [1.0, -2.0] as little-endian f32 bytes.
+        Embedding::String(encoded) => assert_eq!(encoded, "AACAPwAAAMA="),
+        _ => panic!("expected a base64 string embedding"),
+    }
+}
+
+#[tokio::test]
+async fn test_zero_dimensions_is_rejected() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(float_response()))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let result = openai_instance(&server)
+        .embeddings(request(None, Some(0)))
+        .await;
+    assert_eq!(result.err(), Some(StatusCode::BAD_REQUEST));
+}
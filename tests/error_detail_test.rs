@@ -0,0 +1,169 @@
+use async_trait::async_trait;
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use futures::StreamExt;
+use hub_lib::artifacts::HEADER_REQUEST_ID;
+use hub_lib::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use hub_lib::models::completion::{CompletionRequest, CompletionResponse};
+use hub_lib::models::embeddings::{EmbeddingsRequest, EmbeddingsResponse};
+use hub_lib::providers::provider::Provider;
+use hub_lib::providers::registry::ProviderRegistry;
+use hub_lib::state::AppState;
+use hub_lib::types::{GatewayConfig, ModelConfig, Provider as ProviderConfig, ProviderType};
+use reqwest_streams::error::{StreamBodyError, StreamBodyKind};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const UPSTREAM_HOST: &str = "eastus.inference.acme.example";
+const UPSTREAM_IP: &str = "10.1.2.3";
+
+/// A provider whose streams fail with an error naming its upstream, and whose other
+/// requests fail with a bare 502.
+struct LeakyProvider {
+    key: String,
+}
+
+#[async_trait]
+impl Provider for LeakyProvider {
+    fn new(config: &ProviderConfig) -> Self {
+        Self {
+            key: config.key.clone(),
+        }
+    }
+
+    fn key(&self) -> String {
+        self.key.clone()
+    }
+
+    fn r#type(&self) -> ProviderType {
+        ProviderType::from_name("leaky")
+    }
+
+    async fn chat_completions(
+        &self,
+        payload: ChatCompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<ChatCompletionResponse, StatusCode> {
+        if payload.stream != Some(true) {
+            return Err(StatusCode::BAD_GATEWAY);
+        }
+        let error = StreamBodyError::new(
+            StreamBodyKind::InputOutputError,
+            None,
+            Some(format!(
+                "connection to https://{UPSTREAM_HOST}/v1/chat ({UPSTREAM_IP}) was reset"
+            )),
+        );
+        Ok(ChatCompletionResponse::Stream(
+            futures::stream::iter([Err(error)]).boxed(),
+        ))
+    }
+
+    async fn completions(
+        &self,
+        _payload: CompletionRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<CompletionResponse, StatusCode> {
+        Err(StatusCode::BAD_GATEWAY)
+    }
+
+    async fn embeddings(
+        &self,
+        _payload: EmbeddingsRequest,
+        _model_config: &ModelConfig,
+    ) -> Result<EmbeddingsResponse, StatusCode> {
+        Err(StatusCode::BAD_GATEWAY)
+    }
+}
+
+fn hub(error_detail: &str) -> Router {
+    ProviderRegistry::register_factory("leaky", |config| {
+        Ok(Arc::new(LeakyProvider::new(config)) as Arc<dyn Provider>)
+    });
+    let config: GatewayConfig = serde_yaml::from_str(&format!(
+        r#"
+general:
+  error_detail: {error_detail}
+providers:
+  - key: leaky
+    type: leaky
+models:
+  - key: leaky-chat
+    type: leaky-large
+    provider: leaky
+pipelines:
+  - name: default
+    type: chat
+    plugins:
+      - model-router:
+          models: [leaky-chat]
+"#
+    ))
+    .unwrap();
+    let state = Arc::new(AppState::new(config).unwrap());
+    hub_lib::routes::create_router(state)
+}
+
+async fn chat(app: &Router, stream: bool) -> Response {
+    let body = json!({
+        "model": "leaky-chat",
+        "messages": [{"role": "user", "content": "hello"}],
+        "stream": stream
+    });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/chat/completions")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap()
+}
+
+async fn text(response: Response) -> String {
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_full_keeps_the_upstream_error() {
+    let app = hub("full");
+    let events = text(chat(&app, true).await).await;
+    assert!(events.contains(UPSTREAM_HOST), "{events}");
+    assert!(events.contains(UPSTREAM_IP), "{events}");
+}
+
+#[tokio::test]
+async fn test_sanitized_redacts_the_upstream_error() {
+    let app = hub("sanitized");
+    let response = chat(&app, true).await;
+    assert!(response.headers().contains_key(HEADER_REQUEST_ID));
+    let events = text(response).await;
+    assert!(!events.contains(UPSTREAM_HOST), "{events}");
+    assert!(!events.contains(UPSTREAM_IP), "{events}");
+    assert!(events.contains("was reset"), "{events}");
+}
+
+#[tokio::test]
+async fn test_minimal_returns_the_type_and_request_id() {
+    let app = hub("minimal");
+
+    let response = chat(&app, false).await;
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    let request_id = response.headers()[HEADER_REQUEST_ID]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body: Value = serde_json::from_str(&text(response).await).unwrap();
+    let error = body["error"].as_object().unwrap();
+    assert_eq!(error.len(), 3, "{body}");
+    assert_eq!(error["type"], "api_error");
+    assert_eq!(error["request_id"], request_id.as_str());
+
+    let events = text(chat(&app, true).await).await;
+    assert!(!events.contains(UPSTREAM_HOST), "{events}");
+    assert!(!events.contains("was reset"), "{events}");
+    assert!(events.contains("request_id"), "{events}");
+}
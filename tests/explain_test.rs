@@ -0,0 +1,215 @@
+mod common;
+
+use common::{azure_member, completion, model, model_router, params, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::types::{ModelConfig, ParameterPolicyMode, ParameterRule, PluginConfig};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A region that answers chat requests, streamed or not.
+async fn healthy_region() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/gpt-4o/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "id": "chatcmpl-2",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{"index": 0, "delta": {"content": "hi"}, "finish_reason": "stop"}]
+        }])))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/gpt-4o/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn failing_region() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/gpt-4o/chat/completions"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn group_model(key: &str, enabled: bool) -> ModelConfig {
+    ModelConfig {
+        r#type: "gpt-4o".to_string(),
+        params: params(&[("deployment", "gpt-4o")]),
+        enabled,
+        ..model(key, "azure-prod")
+    }
+}
+
+/// A chat pipeline with a parameter policy capping `temperature`, whose `gpt-4o-canary`
+/// model is served by the `azure-prod` group: East US first, then West Europe. The disabled
+/// `gpt-4o-legacy` model is listed first.
+fn hub(eastus: &MockServer, westeu: &MockServer) -> Router {
+    pipeline_router(
+        &pipeline(
+            "default",
+            vec![
+                PluginConfig::ParameterPolicy {
+                    mode: ParameterPolicyMode::Strict,
+                    rules: BTreeMap::from([(
+                        "temperature".to_string(),
+                        ParameterRule::Clamp {
+                            min: None,
+                            max: Some(1.0),
+                        },
+                    )]),
+                    allow_extra_body: false,
+                    extra_body_keys: vec![],
+                },
+                model_router(&["gpt-4o-legacy", "gpt-4o-canary"]),
+            ],
+        ),
+        &[
+            azure_member("azure-eastus", eastus, "0"),
+            azure_member("azure-westeu", westeu, "1"),
+        ],
+        &[
+            group_model("gpt-4o-legacy", false),
+            group_model("gpt-4o-canary", true),
+        ],
+    )
+}
+
+async fn explained_chat(app: Router, stream: bool) -> String {
+    let body = json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hello"}],
+        "temperature": 0.5,
+        "stream": stream
+    });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-hub-explain", "true")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+/// The stage and decision of each step, dropping durations and details.
+fn stages(explain: &Value) -> Vec<(&str, &str)> {
+    explain["steps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|step| {
+            (
+                step["stage"].as_str().unwrap(),
+                step["decision"].as_str().unwrap(),
+            )
+        })
+        .collect()
+}
+
+fn step<'a>(explain: &'a Value, stage: &str, decision: &str) -> &'a Value {
+    explain["steps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|step| step["stage"] == stage && step["decision"] == decision)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_explain_traces_a_failover_and_a_guardrail_pass() {
+    unsafe {
+        std::env::set_var("ALLOW_DEBUG_HEADERS", "true");
+    }
+    let eastus = failing_region().await;
+    let westeu = healthy_region().await;
+
+    let body: Value =
+        serde_json::from_str(&explained_chat(hub(&eastus, &westeu), false).await).unwrap();
+    assert_eq!(body["choices"][0]["message"]["content"], "hi");
+    let explain = &body["hub_explain"];
+    assert_eq!(
+        stages(explain),
+        vec![
+            ("pipeline", "default"),
+            ("parameter_policy", "passed"),
+            ("routing", "gpt-4o-canary"),
+            ("failover", "failed"),
+            ("failover", "served"),
+            ("provider", "completed"),
+            ("content_filter", "passed"),
+            ("response", "200"),
+        ]
+    );
+    assert!(step(explain, "parameter_policy", "passed")["duration_ms"].is_f64());
+    assert_eq!(
+        step(explain, "routing", "gpt-4o-canary")["details"]["candidates"],
+        json!([
+            {"model_key": "gpt-4o-legacy", "skipped": "disabled"},
+            {"model_key": "gpt-4o-canary", "skipped": null},
+        ])
+    );
+    assert_eq!(
+        step(explain, "failover", "failed")["details"],
+        json!({"group": "azure-prod", "provider": "azure-eastus", "status": 503})
+    );
+    assert_eq!(
+        step(explain, "failover", "served")["details"],
+        json!({"group": "azure-prod", "provider": "azure-westeu"})
+    );
+    assert_eq!(
+        step(explain, "response", "200")["details"],
+        json!({"model_key": "gpt-4o-canary", "provider": "azure", "served_by": "azure-westeu"})
+    );
+    assert!(explain["total_ms"].is_f64());
+}
+
+#[tokio::test]
+async fn test_streamed_explain_arrives_in_a_trailing_event() {
+    unsafe {
+        std::env::set_var("ALLOW_DEBUG_HEADERS", "true");
+    }
+    let eastus = failing_region().await;
+    let westeu = healthy_region().await;
+
+    let body = explained_chat(hub(&eastus, &westeu), true).await;
+    let events: Vec<Value> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["choices"][0]["delta"]["content"], "hi");
+    let explain = &events[1]["hub_explain"];
+    assert_eq!(
+        stages(explain),
+        vec![
+            ("pipeline", "default"),
+            ("parameter_policy", "passed"),
+            ("routing", "gpt-4o-canary"),
+            ("failover", "failed"),
+            ("failover", "served"),
+            ("provider", "streaming"),
+            ("response", "200"),
+        ]
+    );
+}
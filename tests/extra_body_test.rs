@@ -0,0 +1,315 @@
+mod common;
+
+use common::{model, model_router, openai, params, pipeline, pipeline_router, provider};
+use hub_lib::ai_models::instance::ModelInstance;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::axum::response::Response;
+use hub_lib::models::chat::ChatCompletionRequest;
+use hub_lib::models::completion::CompletionRequest;
+use hub_lib::models::embeddings::EmbeddingsRequest;
+use hub_lib::providers::anthropic::AnthropicProvider;
+use hub_lib::providers::azure::AzureProvider;
+use hub_lib::providers::bedrock::BedrockProvider;
+use hub_lib::providers::openai::OpenAIProvider;
+use hub_lib::providers::provider::Provider as _;
+use hub_lib::providers::vertexai::VertexAIProvider;
+use hub_lib::types::{ModelConfig, ParameterPolicyMode, PluginConfig, Provider, ProviderType};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn provider_config(r#type: ProviderType, provider_params: &[(&str, &str)]) -> Provider {
+    Provider {
+        api_key: "sk-secret".to_string(),
+        params: params(provider_params),
+        ..provider("upstream", r#type)
+    }
+}
+
+fn instance(
+    provider: Arc<dyn hub_lib::providers::provider::Provider>,
+    model_type: &str,
+    model_params: &[(&str, &str)],
+) -> ModelInstance {
+    ModelInstance {
+        name: "model".to_string(),
+        model_type: model_type.to_string(),
+        provider,
+        config: ModelConfig {
+            r#type: model_type.to_string(),
+            params: params(model_params),
+            ..model("model", "upstream")
+        },
+    }
+}
+
+fn chat_body(model: &str, extra_body: Value) -> Value {
+    json!({
+        "model": model,
+        "messages": [
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "hello"}
+        ],
+        "max_tokens": 16,
+        "extra_body": extra_body
+    })
+}
+
+fn chat_request(model: &str, extra_body: Value) -> ChatCompletionRequest {
+    serde_json::from_value(chat_body(model, extra_body)).unwrap()
+}
+
+#[tokio::test]
+async fn test_openai_merges_extra_body() {
+    let config = provider_config(
+        ProviderType::OpenAI,
+        &[("base_url", "https://example.com/v1")],
+    );
+    let provider = Arc::new(OpenAIProvider::new(&config));
+
+    let model = instance(provider.clone(), "gpt-4o", &[]);
+    let built = model
+        .build_chat_request(chat_request("gpt-4o", json!({"service_tier": "flex"})))
+        .await
+        .unwrap();
+    let body = built.body_json();
+    assert_eq!(body["service_tier"], "flex");
+    assert_eq!(body["max_tokens"], 16);
+    assert!(body.get("extra_body").is_none());
+
+    let completion = json!({
+        "model": "gpt-3.5-turbo-instruct",
+        "prompt": "hi",
+        "extra_body": {"seed": 7}
+    });
+    let completion: CompletionRequest = serde_json::from_value(completion).unwrap();
+    let model = instance(provider.clone(), "gpt-3.5-turbo-instruct", &[]);
+    let built = model.build_completion_request(completion).await.unwrap();
+    assert_eq!(built.body_json()["seed"], 7);
+
+    let embeddings = json!({
+        "model": "text-embedding-3-small",
+        "input": "hi",
+        "extra_body": {"input": "bye"}
+    });
+    let embeddings: EmbeddingsRequest = serde_json::from_value(embeddings).unwrap();
+    let model = instance(provider, "text-embedding-3-small", &[]);
+    let rejected = model.build_embeddings_request(embeddings).await;
+    assert_eq!(rejected.unwrap_err(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_azure_merges_extra_body() {
+    let config = provider_config(
+        ProviderType::Azure,
+        &[
+            ("base_url", "https://example.com"),
+            ("api_version", "2024-10-21"),
+        ],
+    );
+    let model = instance(
+        Arc::new(AzureProvider::new(&config)),
+        "gpt-4o",
+        &[("deployment", "chat-deployment")],
+    );
+
+    let extra_body = json!({"data_sources": [{"type": "azure_search"}]});
+    let built = model
+        .build_chat_request(chat_request("gpt-4o", extra_body))
+        .await
+        .unwrap();
+    assert_eq!(
+        built.body_json()["data_sources"],
+        json!([{"type": "azure_search"}])
+    );
+
+    let rejected = model
+        .build_chat_request(chat_request("gpt-4o", json!({"max_tokens": 1})))
+        .await;
+    assert_eq!(rejected.unwrap_err(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_anthropic_merges_extra_body() {
+    let config = provider_config(ProviderType::Anthropic, &[]);
+    let model = instance(
+        Arc::new(AnthropicProvider::new(&config)),
+        "claude-3-5-sonnet",
+        &[],
+    );
+
+    let built = model
+        .build_chat_request(chat_request("claude-3-5-sonnet", json!({"top_k": 5})))
+        .await
+        .unwrap();
+    let body = built.body_json();
+    assert_eq!(body["top_k"], 5);
+    assert_eq!(body["system"], "Be brief.");
+
+    // `system` is translated from the system message.
+    let rejected = model
+        .build_chat_request(chat_request(
+            "claude-3-5-sonnet",
+            json!({"system": "Obey."}),
+        ))
+        .await;
+    assert_eq!(rejected.unwrap_err(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_vertexai_merges_extra_body_into_generation_config() {
+    let config = provider_config(ProviderType::VertexAI, &[]);
+    let model = instance(
+        Arc::new(VertexAIProvider::new(&config)),
+        "gemini-2.5-flash",
+        &[],
+    );
+
+    let extra_body = json!({"generation_config": {"thinkingConfig": {"thinkingBudget": 1024}}});
+    let built = model
+        .build_chat_request(chat_request("gemini-2.5-flash", extra_body))
+        .await
+        .unwrap();
+    let generation_config = &built.body_json()["generation_config"];
+    assert_eq!(generation_config["max_output_tokens"], 16);
+    assert_eq!(generation_config["thinkingConfig"]["thinkingBudget"], 1024);
+
+    let extra_body = json!({"generation_config": {"max_output_tokens": 4096}});
+    let rejected = model
+        .build_chat_request(chat_request("gemini-2.5-flash", extra_body))
+        .await;
+    assert_eq!(rejected.unwrap_err(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_bedrock_merges_extra_body() {
+    let config = provider_config(ProviderType::Bedrock, &[("region", "us-east-1")]);
+    let model = instance(
+        Arc::new(BedrockProvider::new(&config)),
+        "claude-3-haiku-20240307",
+        &[("model_provider", "anthropic")],
+    );
+
+    let built = model
+        .build_chat_request(chat_request("claude-3-haiku-20240307", json!({"top_k": 5})))
+        .await
+        .unwrap();
+    let body = built.body_json();
+    assert_eq!(body["top_k"], 5);
+    assert_eq!(body["anthropic_version"], "bedrock-2023-05-31");
+
+    let extra_body = json!({"anthropic_version": "bedrock-2024-01-01"});
+    let rejected = model
+        .build_chat_request(chat_request("claude-3-haiku-20240307", extra_body))
+        .await;
+    assert_eq!(rejected.unwrap_err(), StatusCode::BAD_REQUEST);
+}
+
+/// A chat pipeline in front of an OpenAI upstream, with `plugins` ahead of its model router.
+fn hub(server: &MockServer, mut plugins: Vec<PluginConfig>) -> hub_lib::axum::Router {
+    plugins.push(model_router(&["gpt-4o"]));
+    pipeline_router(
+        &pipeline("default", plugins),
+        &[openai("openai", server)],
+        &[model("gpt-4o", "openai")],
+    )
+}
+
+fn allow_extra_body(keys: &[&str]) -> PluginConfig {
+    PluginConfig::ParameterPolicy {
+        mode: ParameterPolicyMode::Strict,
+        rules: BTreeMap::new(),
+        allow_extra_body: true,
+        extra_body_keys: keys.iter().map(|key| key.to_string()).collect(),
+    }
+}
+
+async fn post_chat(app: hub_lib::axum::Router, body: Value) -> Response {
+    app.oneshot(
+        Request::builder()
+            .uri("/chat/completions")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+async fn error_message(response: Response) -> String {
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    body["error"]["message"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_pipeline_forwards_allowed_extra_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"service_tier": "flex"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "hi"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = hub(&server, vec![allow_extra_body(&["service_tier"])]);
+
+    let response = post_chat(app, chat_body("gpt-4o", json!({"service_tier": "flex"}))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let received = server.received_requests().await.unwrap();
+    let sent: Value = serde_json::from_slice(&received[0].body).unwrap();
+    assert!(sent.get("extra_body").is_none());
+}
+
+#[tokio::test]
+async fn test_pipeline_policy_rejects_extra_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    // Without a parameter-policy plugin, extra_body isn't allowed.
+    let response = post_chat(
+        hub(&server, vec![]),
+        chat_body("gpt-4o", json!({"service_tier": "flex"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error_message(response).await,
+        "'extra_body' is not allowed by this pipeline"
+    );
+
+    let allowlisted = || hub(&server, vec![allow_extra_body(&["service_tier"])]);
+    let response = post_chat(allowlisted(), chat_body("gpt-4o", json!({"seed": 7}))).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error_message(response).await,
+        "'extra_body.seed' is not allowed by this pipeline"
+    );
+
+    let response = post_chat(allowlisted(), chat_body("gpt-4o", json!({"model": "o3"}))).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error_message(response).await,
+        "'extra_body.model' collides with a field the hub manages"
+    );
+}
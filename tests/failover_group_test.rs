@@ -0,0 +1,136 @@
+mod common;
+
+use common::{azure_member, completion, model, model_router, params, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::providers::failover::SERVED_BY_HEADER;
+use hub_lib::types::ModelConfig;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn region(response: ResponseTemplate, expected_calls: u64) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/gpt-4o/chat/completions"))
+        .respond_with(response)
+        .expect(expected_calls)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// A chat pipeline whose only model is served by the `azure-prod` group. West Europe is
+/// listed first but has the lower priority.
+fn hub(eastus: &MockServer, westeu: &MockServer) -> Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &[
+            azure_member("azure-westeu", westeu, "1"),
+            azure_member("azure-eastus", eastus, "0"),
+        ],
+        &[ModelConfig {
+            params: params(&[("deployment", "gpt-4o")]),
+            ..model("gpt-4o", "azure-prod")
+        }],
+    )
+}
+
+async fn chat(app: &Router) -> (StatusCode, Option<String>, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let served_by = response
+        .headers()
+        .get(SERVED_BY_HEADER)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, served_by, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_primary_region_serves_when_healthy() {
+    let eastus = region(
+        ResponseTemplate::new(200).set_body_json(completion("hi")),
+        1,
+    )
+    .await;
+    let westeu = region(
+        ResponseTemplate::new(200).set_body_json(completion("hi")),
+        0,
+    )
+    .await;
+
+    let (status, served_by, _) = chat(&hub(&eastus, &westeu)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(served_by.as_deref(), Some("azure-eastus"));
+}
+
+#[tokio::test]
+async fn test_failed_region_fails_over_transparently() {
+    let eastus = region(ResponseTemplate::new(503), 3).await;
+    let westeu = region(
+        ResponseTemplate::new(200).set_body_json(completion("hi")),
+        4,
+    )
+    .await;
+    let app = hub(&eastus, &westeu);
+
+    for _ in 0..3 {
+        let (status, served_by, body) = chat(&app).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(served_by.as_deref(), Some("azure-westeu"));
+        assert_eq!(body["choices"][0]["message"]["content"], "hi");
+    }
+    // Three failures in a row open East US's circuit, so it is skipped.
+    let (status, served_by, _) = chat(&app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(served_by.as_deref(), Some("azure-westeu"));
+}
+
+#[tokio::test]
+async fn test_client_errors_do_not_fail_over() {
+    let eastus = region(ResponseTemplate::new(400), 1).await;
+    let westeu = region(
+        ResponseTemplate::new(200).set_body_json(completion("hi")),
+        0,
+    )
+    .await;
+
+    let response = hub(&eastus, &westeu)
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
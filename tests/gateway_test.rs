@@ -0,0 +1,170 @@
+mod common;
+
+use common::{model, model_router, params, pipeline, provider};
+use futures::StreamExt;
+use hub_lib::gateway::{Gateway, GatewayError};
+use hub_lib::models::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use hub_lib::models::content::ChatMessageContent;
+use hub_lib::models::embeddings::Embedding;
+use hub_lib::types::{
+    GatewayConfig, ParameterPolicyMode, ParameterRule, Pipeline, PipelineType, PluginConfig,
+    Provider, ProviderType,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+fn mock_provider(key: &str, provider_params: &[(&str, &str)]) -> Provider {
+    Provider {
+        api_key: String::new(),
+        params: params(provider_params),
+        ..provider(key, ProviderType::Mock)
+    }
+}
+
+/// A pipeline of `r#type` running `plugins` ahead of a router to `model_key`.
+fn routed_pipeline(
+    name: &str,
+    r#type: PipelineType,
+    model_key: &str,
+    mut plugins: Vec<PluginConfig>,
+) -> Pipeline {
+    plugins.push(model_router(&[model_key]));
+    Pipeline {
+        r#type,
+        ..pipeline(name, plugins)
+    }
+}
+
+/// Chat pipelines `default`, echoing the request and denying `user`, and `canned`,
+/// answering `pong`; plus `completions` and `embeddings` pipelines.
+fn gateway() -> Gateway {
+    let deny_user = PluginConfig::ParameterPolicy {
+        mode: ParameterPolicyMode::Strict,
+        rules: BTreeMap::from([("user".to_string(), ParameterRule::Deny)]),
+        allow_extra_body: false,
+        extra_body_keys: vec![],
+    };
+    Gateway::new(GatewayConfig {
+        providers: vec![
+            mock_provider("mock", &[]),
+            mock_provider("mock-fixed", &[("mode", "fixed"), ("response", "pong")]),
+        ],
+        models: vec![model("echo", "mock"), model("pong", "mock-fixed")],
+        pipelines: vec![
+            routed_pipeline("default", PipelineType::Chat, "echo", vec![deny_user]),
+            routed_pipeline("canned", PipelineType::Chat, "pong", vec![]),
+            routed_pipeline("completions", PipelineType::Completion, "echo", vec![]),
+            routed_pipeline("embeddings", PipelineType::Embeddings, "echo", vec![]),
+        ],
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn chat(model: &str, stream: bool) -> ChatCompletionRequest {
+    serde_json::from_value(json!({
+        "model": model,
+        "messages": [{"role": "user", "content": "ping from the library"}],
+        "stream": stream
+    }))
+    .unwrap()
+}
+
+fn content(response: ChatCompletionResponse) -> String {
+    let ChatCompletionResponse::NonStream(completion) = response else {
+        panic!("expected a completion, got a stream");
+    };
+    match &completion.choices[0].message.content {
+        Some(ChatMessageContent::String(text)) => text.clone(),
+        _ => panic!("expected text content"),
+    }
+}
+
+#[tokio::test]
+async fn test_chat_completions_go_to_the_named_pipeline() {
+    let gateway = gateway();
+
+    let response = gateway
+        .chat_completions("default", chat("echo", false))
+        .await
+        .unwrap();
+    assert_eq!(content(response), "ping from the library");
+
+    let response = gateway
+        .chat_completions("canned", chat("pong", false))
+        .await
+        .unwrap();
+    assert_eq!(content(response), "pong");
+}
+
+#[tokio::test]
+async fn test_streamed_chat_completion_yields_chunks() {
+    let response = gateway()
+        .chat_completions("default", chat("echo", true))
+        .await
+        .unwrap();
+    let ChatCompletionResponse::Stream(chunks) = response else {
+        panic!("expected a stream");
+    };
+    let chunks: Vec<_> = chunks.collect().await;
+    assert!(chunks.len() > 1);
+    let text: String = chunks
+        .into_iter()
+        .map(Result::unwrap)
+        .filter_map(|chunk| chunk.choices.first()?.delta.content.clone())
+        .collect();
+    assert_eq!(text, "ping from the library");
+}
+
+#[tokio::test]
+async fn test_completions_and_embeddings() {
+    let gateway = gateway();
+
+    let request =
+        serde_json::from_value(json!({"model": "echo", "prompt": "say this back"})).unwrap();
+    let completion = gateway.completions("completions", request).await.unwrap();
+    assert_eq!(completion.choices[0].text, "say this back");
+
+    let request =
+        serde_json::from_value(json!({"model": "echo", "input": ["one", "two"]})).unwrap();
+    let embeddings = gateway.embeddings("embeddings", request).await.unwrap();
+    assert_eq!(embeddings.data.len(), 2);
+    assert!(matches!(&embeddings.data[0].embedding, Embedding::Float(vector) if vector.len() == 8));
+}
+
+#[tokio::test]
+async fn test_pipeline_plugins_reject_requests() {
+    let mut request = chat("echo", false);
+    request.user = Some("someone".to_string());
+    let error = gateway()
+        .chat_completions("default", request)
+        .await
+        .err()
+        .unwrap();
+    let GatewayError::Status { status, body } = error else {
+        panic!("expected an error status, got {error}");
+    };
+    assert_eq!(status.as_u16(), 400);
+    assert!(body.contains("user"));
+}
+
+#[tokio::test]
+async fn test_gateways_keep_their_own_state() {
+    let first = gateway();
+    let second = gateway();
+
+    first
+        .chat_completions("default", chat("echo", false))
+        .await
+        .unwrap();
+
+    let today = chrono::Utc::now().date_naive();
+    let requests = |gateway: &Gateway| {
+        let services = gateway.state().services();
+        let usage = services.usage.summary(today, today, None).totals.requests;
+        let outcomes = services.outcomes.snapshot();
+        (usage, outcomes.get("default").map(|counts| counts.success))
+    };
+    assert_eq!(requests(&first), (1, Some(1)));
+    assert_eq!(requests(&second), (0, None));
+}
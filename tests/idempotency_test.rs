@@ -0,0 +1,141 @@
+mod common;
+
+use common::{completion, model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::pipelines::idempotency::REPLAY_HEADER;
+use serde_json::{Value, json};
+use std::time::Duration;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// An upstream that answers chat requests slowly, so duplicates overlap the original.
+async fn upstream(expected_calls: u64) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(completion("hi"))
+                .set_delay(Duration::from_millis(300)),
+        )
+        .expect(expected_calls)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn hub(server: &MockServer) -> Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &[openai("openai", server)],
+        &[model("gpt-4o", "openai")],
+    )
+}
+
+/// Sends a chat request and returns its status, whether it was a replay, and its body.
+async fn chat(app: &Router, key: &str, body: Value) -> (StatusCode, bool, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("idempotency-key", key)
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let replayed = response.headers().get(REPLAY_HEADER).is_some();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, replayed, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_concurrent_duplicates_share_one_upstream_call() {
+    let server = upstream(1).await;
+    let app = hub(&server);
+
+    let (first, second) = tokio::join!(
+        chat(&app, "concurrent-key", common::chat("hello")),
+        chat(&app, "concurrent-key", common::chat("hello")),
+    );
+    assert_eq!(first.0, StatusCode::OK);
+    assert_eq!(second.0, StatusCode::OK);
+    assert_eq!(first.2, second.2);
+    // Exactly one of them ran; the other waited for its response.
+    assert!(first.1 ^ second.1);
+}
+
+#[tokio::test]
+async fn test_completed_request_is_replayed() {
+    let server = upstream(1).await;
+    let app = hub(&server);
+
+    let (status, replayed, original) = chat(&app, "replay-key", common::chat("hello")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!replayed);
+
+    let (status, replayed, replay) = chat(&app, "replay-key", common::chat("hello")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(replayed);
+    assert_eq!(replay, original);
+}
+
+#[tokio::test]
+async fn test_key_reused_for_different_request_is_rejected() {
+    let server = upstream(1).await;
+    let app = hub(&server);
+
+    chat(&app, "reused-key", common::chat("hello")).await;
+    let (status, _, body) = chat(&app, "reused-key", common::chat("goodbye")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+}
+
+#[tokio::test]
+async fn test_streaming_duplicate_gets_conflict() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("data: [DONE]\n\n"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let app = hub(&server);
+    let body = json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hello"}],
+        "stream": true
+    });
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("idempotency-key", "stream-key")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    let (status, replayed, body) = chat(&app, "stream-key", body).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(!replayed);
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("streaming")
+    );
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use common::{model, model_router, params, pipeline, pipeline_router, provider};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::types::{ModelConfig, Provider, ProviderType};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+const FENCED_REPLY: &str =
+    "Here you go:\n```json\n{\"city\": \"Paris\", \"population\": 2102650}\n```\nAnything else?";
+const REPAIRED: &str = r#"{"city": "Paris", "population": 2102650}"#;
+
+/// A pipeline whose mock model always answers with `reply`.
+fn hub(reply: &str, json_repair: bool) -> Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["mock"])]),
+        &[Provider {
+            api_key: String::new(),
+            params: params(&[("mode", "fixed")]),
+            ..provider("mock", ProviderType::Mock)
+        }],
+        &[ModelConfig {
+            r#type: "mock-model".to_string(),
+            params: params(&[
+                ("response", reply),
+                ("json_repair", &json_repair.to_string()),
+            ]),
+            ..model("mock", "mock")
+        }],
+    )
+}
+
+async fn chat(app: &Router, response_format: Value, stream: bool) -> (StatusCode, String) {
+    let body = json!({
+        "model": "mock-model",
+        "messages": [{"role": "user", "content": "Describe Paris as JSON"}],
+        "response_format": response_format,
+        "stream": stream
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&body).to_string())
+}
+
+fn content(body: &str) -> Value {
+    serde_json::from_str::<Value>(body).unwrap()["choices"][0]["message"]["content"].clone()
+}
+
+fn streamed_content(body: &str) -> String {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+        .filter_map(|chunk| {
+            chunk["choices"][0]["delta"]["content"]
+                .as_str()
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+fn city_schema(required: &[&str]) -> Value {
+    json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": "city",
+            "schema": {
+                "type": "object",
+                "properties": {
+                    "city": {"type": "string"},
+                    "population": {"type": "integer"},
+                    "country": {"type": "string"}
+                },
+                "required": required
+            }
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_json_mode_responses_are_repaired() {
+    let app = hub(FENCED_REPLY, true);
+
+    let (status, body) = chat(&app, json!({"type": "json_object"}), false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content(&body), REPAIRED);
+
+    let (status, body) = chat(&app, city_schema(&["city", "population"]), false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content(&body), REPAIRED);
+
+    let (status, body) = chat(&app, json!({"type": "json_object"}), true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(streamed_content(&body), REPAIRED);
+}
+
+#[tokio::test]
+async fn test_unrepairable_response_is_a_bad_gateway() {
+    let app = hub(FENCED_REPLY, true);
+
+    for stream in [false, true] {
+        let (status, body) = chat(&app, city_schema(&["city", "country"]), stream).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        let error = &serde_json::from_str::<Value>(&body).unwrap()["error"];
+        assert_eq!(error["code"], "invalid_json_response");
+        assert_eq!(
+            error["message"],
+            "Model 'mock' returned invalid JSON: $: missing required property 'country'"
+        );
+        assert_eq!(error["raw_content"], FENCED_REPLY);
+    }
+
+    let app = hub("I'd rather not.", true);
+    let (status, body) = chat(&app, json!({"type": "json_object"}), false).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    let error = &serde_json::from_str::<Value>(&body).unwrap()["error"];
+    assert_eq!(error["raw_content"], "I'd rather not.");
+}
+
+#[tokio::test]
+async fn test_repair_only_applies_when_enabled_in_json_mode() {
+    // Text mode is never repaired.
+    let app = hub(FENCED_REPLY, true);
+    let (status, body) = chat(&app, json!({"type": "text"}), false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content(&body), FENCED_REPLY);
+    let (_, body) = chat(&app, json!({"type": "text"}), true).await;
+    assert_eq!(streamed_content(&body), FENCED_REPLY);
+
+    // Off by default.
+    let app = hub(FENCED_REPLY, false);
+    let (status, body) = chat(&app, json!({"type": "json_object"}), false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content(&body), FENCED_REPLY);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use common::{completion, model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::Body;
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::providers::api_keys::API_KEY_SECONDARY_PARAM;
+use hub_lib::types::Provider;
+use serde_json::json;
+use tower::ServiceExt;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const OLD_KEY: &str = "sk-old";
+const NEW_KEY: &str = "sk-new";
+
+fn hub(server: &MockServer, secondary: Option<&str>) -> Router {
+    let mut provider = Provider {
+        api_key: OLD_KEY.to_string(),
+        ..openai("openai", server)
+    };
+    if let Some(secondary) = secondary {
+        provider
+            .params
+            .insert(API_KEY_SECONDARY_PARAM.to_string(), secondary.to_string());
+    }
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &[provider],
+        &[model("gpt-4o", "openai")],
+    )
+}
+
+async fn post_chat(app: Router) -> StatusCode {
+    app.oneshot(
+        Request::builder()
+            .uri("/chat/completions")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "model": "gpt-4o",
+                    "messages": [{"role": "user", "content": "hello"}]
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+    .status()
+}
+
+async fn rotated_upstream(old_key_status: u16) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header(
+            "authorization",
+            format!("Bearer {OLD_KEY}").as_str(),
+        ))
+        .respond_with(ResponseTemplate::new(old_key_status).set_body_json(json!({
+            "error": {"message": "Incorrect API key provided", "type": "invalid_request_error"}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn test_rejected_primary_key_retries_with_secondary() {
+    let server = rotated_upstream(401).await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header(
+            "authorization",
+            format!("Bearer {NEW_KEY}").as_str(),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert_eq!(post_chat(hub(&server, Some(NEW_KEY))).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_forbidden_primary_key_retries_with_secondary() {
+    let server = rotated_upstream(403).await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header(
+            "authorization",
+            format!("Bearer {NEW_KEY}").as_str(),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert_eq!(post_chat(hub(&server, Some(NEW_KEY))).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_without_secondary_key_rejection_is_returned() {
+    let server = rotated_upstream(401).await;
+
+    assert_eq!(
+        post_chat(hub(&server, None)).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_other_errors_are_not_retried() {
+    let server = rotated_upstream(500).await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header(
+            "authorization",
+            format!("Bearer {NEW_KEY}").as_str(),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    assert_eq!(
+        post_chat(hub(&server, Some(NEW_KEY))).await,
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use common::{completion, model, model_router, openai, params, pipeline};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode, header};
+use hub_lib::management::dto::ApiKeyRole;
+use hub_lib::management::services::api_key_service::{ApiKeyService, StaticApiKey};
+use hub_lib::state::AppState;
+use hub_lib::types::{GatewayConfig, PluginConfig, Provider};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const KEY_VAR: &str = "LATE_BINDING_TEST_OPENAI_KEY";
+
+async fn upstream() -> MockServer {
+    let server = MockServer::start().await;
+    for key in ["sk-healthy", "sk-late"] {
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", format!("Bearer {key}").as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion(key)))
+            .mount(&server)
+            .await;
+    }
+    server
+}
+
+/// An OpenAI provider at `server` without an API key, with `extra_params`.
+fn keyless_provider(key: &str, server: &MockServer, extra_params: &[(&str, &str)]) -> Provider {
+    let mut provider = Provider {
+        api_key: String::new(),
+        ..openai(key, server)
+    };
+    provider.params.extend(params(extra_params));
+    provider
+}
+
+/// A healthy provider next to one whose API key secret resolves on first use and one
+/// whose proxy secret didn't resolve when the config was built.
+fn config(server: &MockServer) -> GatewayConfig {
+    let mut healthy = keyless_provider("healthy", server, &[]);
+    healthy.api_key = "sk-healthy".to_string();
+    let secret = json!({"type": "environment", "variable_name": KEY_VAR}).to_string();
+    let late = keyless_provider(
+        "late",
+        server,
+        &[
+            ("api_key_secret", &secret),
+            ("api_key_secret_retry_seconds", "0"),
+        ],
+    );
+    let unresolved = keyless_provider(
+        "unresolved",
+        server,
+        &[(
+            "unresolved_secrets",
+            "proxy_url: Kubernetes secret resolution not yet implemented",
+        )],
+    );
+    GatewayConfig {
+        providers: vec![healthy, late, unresolved],
+        models: vec![
+            model("healthy", "healthy"),
+            model("late", "late"),
+            model("unresolved", "unresolved"),
+        ],
+        pipelines: vec![pipeline(
+            "default",
+            vec![model_router(&["healthy", "late", "unresolved"])],
+        )],
+        ..Default::default()
+    }
+}
+
+async fn chat(router: &Router, model: &str) -> (StatusCode, Value) {
+    let response = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": model,
+                        "messages": [{"role": "user", "content": "hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+const ADMIN_KEY: &str = "admin-key";
+
+/// The admin routes as the management server serves them.
+fn admin(app_state: Arc<AppState>) -> Router {
+    hub_lib::management::admin_router(
+        hub_lib::routes::admin_routes(app_state),
+        Arc::new(ApiKeyService::with_static_keys(vec![StaticApiKey::new(
+            ADMIN_KEY,
+            ApiKeyRole::Admin,
+        )])),
+    )
+}
+
+async fn get_health(admin: &Router) -> Value {
+    let response = admin
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/admin/health")
+                .header(header::AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_unresolvable_secrets_only_fail_their_provider() {
+    unsafe {
+        std::env::remove_var(KEY_VAR);
+    }
+    let server = upstream().await;
+    let app_state = Arc::new(AppState::new(config(&server)).unwrap());
+    let router = hub_lib::routes::create_router(app_state.clone());
+    let admin = admin(app_state.clone());
+
+    let (status, body) = chat(&router, "healthy").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["choices"][0]["message"]["content"], "sk-healthy");
+    assert_eq!(
+        chat(&router, "late").await.0,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(
+        chat(&router, "unresolved").await.0,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    let health = get_health(&admin).await;
+    assert_eq!(health["status"], "degraded");
+    let unhealthy = &health["unhealthy_providers"];
+    assert!(
+        unhealthy["late"].as_str().unwrap().contains(KEY_VAR),
+        "{health}"
+    );
+    assert!(
+        unhealthy["unresolved"]
+            .as_str()
+            .unwrap()
+            .contains("proxy_url"),
+        "{health}"
+    );
+    assert!(unhealthy.get("healthy").is_none(), "{health}");
+
+    // Config updates keep applying while the secrets are unresolved.
+    let mut updated = config(&server);
+    updated.models.push(model("healthy-mini", "healthy"));
+    if let PluginConfig::ModelRouter { models, .. } = &mut updated.pipelines[0].plugins[0] {
+        models.push("healthy-mini".to_string());
+    }
+    app_state.update_config(updated).unwrap();
+    assert_eq!(chat(&router, "healthy-mini").await.0, StatusCode::OK);
+
+    // The key resolves once the secret becomes available.
+    unsafe {
+        std::env::set_var(KEY_VAR, "sk-late");
+    }
+    let (status, body) = chat(&router, "late").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["choices"][0]["message"]["content"], "sk-late");
+    let health = get_health(&admin).await;
+    assert!(
+        health["unhealthy_providers"].get("late").is_none(),
+        "{health}"
+    );
+}
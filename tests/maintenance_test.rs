@@ -0,0 +1,135 @@
+mod common;
+
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use common::{completion, model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::types::{MaintenanceWindow, ModelConfig, Provider};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn upstream(expected_calls: u64) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("hi")))
+        .expect(expected_calls)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// A one-off window that started an hour ago and ends at `end`.
+fn window_until(end: DateTime<Utc>) -> MaintenanceWindow {
+    MaintenanceWindow {
+        start: Some((Utc::now() - TimeDelta::hours(1)).to_rfc3339()),
+        end: Some(end.to_rfc3339()),
+        ..Default::default()
+    }
+}
+
+fn provider(key: &str, server: &MockServer, windows: Vec<MaintenanceWindow>) -> Provider {
+    Provider {
+        maintenance_windows: windows,
+        ..openai(key, server)
+    }
+}
+
+/// A pipeline routing `gpt-4o` to a model on each of `providers`, in order.
+fn hub(providers: Vec<Provider>) -> Router {
+    let models: Vec<ModelConfig> = providers
+        .iter()
+        .map(|provider| ModelConfig {
+            r#type: "gpt-4o".to_string(),
+            ..model(&format!("gpt-4o-{}", provider.key), &provider.key)
+        })
+        .collect();
+    let model_keys: Vec<&str> = models.iter().map(|model| model.key.as_str()).collect();
+    pipeline_router(
+        &pipeline("default", vec![model_router(&model_keys)]),
+        &providers,
+        &models,
+    )
+}
+
+async fn chat(app: &Router) -> (StatusCode, Option<String>, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get("retry-after")
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, retry_after, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_provider_in_maintenance_is_skipped() {
+    let drained = upstream(0).await;
+    let fallback = upstream(1).await;
+    let end = Utc::now() + TimeDelta::hours(1);
+    let app = hub(vec![
+        provider("azure-eastus", &drained, vec![window_until(end)]),
+        provider("azure-westeu", &fallback, vec![]),
+    ]);
+
+    let (status, _, body) = chat(&app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["choices"][0]["message"]["content"], "hi");
+}
+
+#[tokio::test]
+async fn test_no_alternative_fails_fast_naming_window_end() {
+    let drained = upstream(0).await;
+    let end = Utc::now() + TimeDelta::minutes(30);
+    let app = hub(vec![provider(
+        "azure-eastus",
+        &drained,
+        vec![window_until(end)],
+    )]);
+
+    let (status, retry_after, body) = chat(&app).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"]["type"], "provider_maintenance");
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.contains("'azure-eastus'"), "{message}");
+    assert!(
+        message.contains(&end.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        "{message}"
+    );
+    let retry_after: i64 = retry_after.unwrap().parse().unwrap();
+    assert!((1..=30 * 60).contains(&retry_after));
+}
+
+#[tokio::test]
+async fn test_past_window_no_longer_applies() {
+    let server = upstream(1).await;
+    let app = hub(vec![provider(
+        "azure-eastus",
+        &server,
+        vec![window_until(Utc::now() - TimeDelta::minutes(1))],
+    )]);
+
+    let (status, _, _) = chat(&app).await;
+    assert_eq!(status, StatusCode::OK);
+}
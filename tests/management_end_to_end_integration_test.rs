@@ -1026,3 +1026,99 @@ async fn test_pipeline_header_routing_e2e() {
     println!("✓ Database state verified");
     println!("🎉 Pipeline header routing E2E test completed successfully!");
 }
+
+#[tokio::test]
+async fn test_model_config_details_reach_upstream() {
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let env = TestEnvironment::setup()
+        .await
+        .expect("Failed to setup test environment");
+
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(
+            json!({"temperature": 0.5, "max_tokens": 64}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi!"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        })))
+        .expect(1)
+        .mount(&upstream)
+        .await;
+
+    let provider: Value = env
+        .client
+        .post(format!("{}/providers", env.management_api_base_url))
+        .json(&json!({
+            "name": "mock-openai",
+            "provider_type": "openai",
+            "config": {
+                "api_key": {"type": "literal", "value": "test-key"},
+                "organization_id": null,
+                "base_url": format!("{}/v1", upstream.uri())
+            }
+        }))
+        .send()
+        .await
+        .expect("Provider request failed")
+        .json()
+        .await
+        .expect("Failed to parse provider response");
+    let provider_id = provider["id"].as_str().unwrap();
+
+    // Nested config_details cannot be flattened into params and are rejected at write time.
+    let nested = env
+        .client
+        .post(format!("{}/model-definitions", env.management_api_base_url))
+        .json(&json!({
+            "key": "nested-model",
+            "provider_id": provider_id,
+            "model_type": "gpt-4o",
+            "config_details": {"routing": {"region": "us"}}
+        }))
+        .send()
+        .await
+        .expect("Model request failed");
+    assert_eq!(nested.status(), 400);
+
+    let model = env
+        .client
+        .post(format!("{}/model-definitions", env.management_api_base_url))
+        .json(&json!({
+            "key": "gpt-4o",
+            "provider_id": provider_id,
+            "model_type": "gpt-4o",
+            "config_details": {"temperature": 0.5, "max_tokens": 64}
+        }))
+        .send()
+        .await
+        .expect("Model request failed");
+    assert_eq!(model.status(), 201);
+
+    env.create_pipeline("default", vec!["gpt-4o".to_string()])
+        .await
+        .expect("Failed to create pipeline");
+    sleep(Duration::from_secs(3)).await;
+
+    let response = env
+        .make_chat_request("gpt-4o")
+        .await
+        .expect("Chat request failed");
+    assert_eq!(
+        response.status(),
+        200,
+        "Upstream should receive the model's default temperature and max_tokens"
+    );
+}
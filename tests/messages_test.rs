@@ -0,0 +1,171 @@
+mod common;
+
+use common::{model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REQUEST_FIXTURE: &str = include_str!("fixtures/anthropic_messages_request.json");
+
+fn fixture_request(stream: bool) -> Value {
+    let mut request: Value = serde_json::from_str(REQUEST_FIXTURE).unwrap();
+    request["stream"] = json!(stream);
+    request
+}
+
+async fn openai_upstream(response: Value) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response))
+        .expect(1)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn hub(server: &MockServer) -> Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &[openai("openai", server)],
+        &[model("gpt-4o", "openai")],
+    )
+}
+
+async fn post_messages(app: Router, body: Value) -> (StatusCode, String) {
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/messages")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+async fn upstream_request(server: &MockServer) -> Value {
+    let received = server.received_requests().await.unwrap();
+    serde_json::from_slice(&received[0].body).unwrap()
+}
+
+#[tokio::test]
+async fn test_messages_request_round_trips_tool_use() {
+    let server = openai_upstream(json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_2",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Rome\"}"}
+                }]
+            },
+            "finish_reason": "tool_calls"
+        }],
+        "usage": {"prompt_tokens": 40, "completion_tokens": 12, "total_tokens": 52}
+    }))
+    .await;
+
+    let (status, body) = post_messages(hub(&server), fixture_request(false)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let sent = upstream_request(&server).await;
+    assert_eq!(sent["max_tokens"], 256);
+    assert_eq!(sent["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(sent["tool_choice"], "auto");
+    let messages = sent["messages"].as_array().unwrap();
+    let roles: Vec<&str> = messages
+        .iter()
+        .map(|message| message["role"].as_str().unwrap())
+        .collect();
+    assert_eq!(roles, ["system", "user", "assistant", "tool", "user"]);
+    assert_eq!(messages[0]["content"], "You are a weather assistant.");
+    let tool_call = &messages[2]["tool_calls"][0];
+    assert_eq!(tool_call["id"], "toolu_01");
+    let arguments: Value =
+        serde_json::from_str(tool_call["function"]["arguments"].as_str().unwrap()).unwrap();
+    assert_eq!(arguments, json!({"city": "Paris"}));
+    assert_eq!(messages[3]["tool_call_id"], "toolu_01");
+    assert_eq!(messages[3]["content"], "18°C and sunny");
+    assert_eq!(messages[4]["content"], "And in Rome?");
+
+    let message: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(message["type"], "message");
+    assert_eq!(message["role"], "assistant");
+    assert_eq!(message["stop_reason"], "tool_use");
+    assert_eq!(message["content"][0]["type"], "tool_use");
+    assert_eq!(message["content"][0]["id"], "call_2");
+    assert_eq!(message["content"][0]["input"], json!({"city": "Rome"}));
+    assert_eq!(message["usage"]["input_tokens"], 40);
+    assert_eq!(message["usage"]["output_tokens"], 12);
+}
+
+#[tokio::test]
+async fn test_streamed_messages_use_anthropic_events() {
+    let chunk = |delta: Value, finish_reason: Value| {
+        json!({
+            "id": "chatcmpl-2",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        })
+    };
+    let server = openai_upstream(json!([
+        chunk(
+            json!({"role": "assistant", "content": "Sunny"}),
+            Value::Null
+        ),
+        chunk(json!({"content": " in Rome."}), json!("stop")),
+    ]))
+    .await;
+
+    let (status, body) = post_messages(hub(&server), fixture_request(true)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(upstream_request(&server).await["stream"], true);
+
+    let events: Vec<&str> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("event: "))
+        .collect();
+    assert_eq!(
+        events,
+        [
+            "message_start",
+            "content_block_start",
+            "content_block_delta",
+            "content_block_delta",
+            "content_block_stop",
+            "message_delta",
+            "message_stop"
+        ]
+    );
+    assert!(body.contains(r#""text":" in Rome.""#));
+    assert!(body.contains(r#""stop_reason":"end_turn""#));
+}
+
+#[tokio::test]
+async fn test_messages_request_is_validated() {
+    let server = MockServer::start().await;
+    let mut request = fixture_request(false);
+    request["temperature"] = json!(3.0);
+
+    let (status, body) = post_messages(hub(&server), request).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.contains("temperature"));
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use common::{model, model_router, params, pipeline, pipeline_router, provider};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::types::{ModelConfig, Provider, ProviderType};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+fn hub(provider_params: &[(&str, &str)], model_params: &[(&str, &str)]) -> Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["mock-model"])]),
+        &[Provider {
+            api_key: String::new(),
+            params: params(provider_params),
+            ..provider("mock", ProviderType::Mock)
+        }],
+        &[ModelConfig {
+            params: params(model_params),
+            ..model("mock-model", "mock")
+        }],
+    )
+}
+
+async fn post(app: &Router, uri: &str, body: Value) -> (StatusCode, String) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&body).to_string())
+}
+
+fn chat(stream: bool) -> Value {
+    json!({
+        "model": "mock-model",
+        "messages": [{"role": "user", "content": "ping from CI"}],
+        "stream": stream
+    })
+}
+
+#[tokio::test]
+async fn test_echo_through_the_gateway() {
+    let app = hub(&[], &[]);
+
+    let (status, body) = post(&app, "/chat/completions", chat(false)).await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["choices"][0]["message"]["content"], "ping from CI");
+
+    let (status, body) = post(&app, "/chat/completions", chat(true)).await;
+    assert_eq!(status, StatusCode::OK);
+    let text: String = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+        .filter_map(|chunk| {
+            chunk["choices"][0]["delta"]["content"]
+                .as_str()
+                .map(str::to_string)
+        })
+        .collect();
+    assert_eq!(text, "ping from CI");
+}
+
+#[tokio::test]
+async fn test_fixed_response_and_scripted_failures_through_the_gateway() {
+    let app = hub(
+        &[
+            ("mode", "fixed"),
+            ("fail_every", "2"),
+            ("fail_status", "503"),
+        ],
+        &[("response", "pong")],
+    );
+
+    let (status, body) = post(&app, "/chat/completions", chat(false)).await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["choices"][0]["message"]["content"], "pong");
+
+    let (status, _) = post(&app, "/chat/completions", chat(false)).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
@@ -11,12 +11,12 @@ use hub_lib::management::{
     api::routes::model_definition_routes, // Assuming this is the entry point for model definition routes
     db::models::{ModelDefinition, Provider as DbProvider}, // For direct DB checks if needed
     dto::{
-        self, AzureProviderConfig, CreateModelDefinitionRequest, CreateProviderRequest,
-        ModelDefinitionResponse, OpenAIProviderConfig, ProviderConfig, ProviderResponse,
-        ProviderType, SecretObject, UpdateModelDefinitionRequest,
+        self, AnthropicProviderConfig, AzureProviderConfig, CreateModelDefinitionRequest,
+        CreateProviderRequest, ModelDefinitionResponse, OpenAIProviderConfig, ProviderConfig,
+        ProviderResponse, ProviderType, SecretObject, UpdateModelDefinitionRequest,
     },
-    errors::ApiError,      // Assuming ApiError is serializable for error responses
-    management_api_bundle, // Main router function from lib.rs
+    errors::ApiError, // Assuming ApiError is serializable for error responses
+    management_api_bundle_without_auth, // Main router function from lib.rs
 };
 use serde_json::json;
 use sqlx::postgres::PgPoolOptions;
@@ -59,7 +59,7 @@ async fn setup_test_environment() -> (TestServer, PgPool, impl Drop) {
         .await
         .expect("Failed to run migrations on test DB");
 
-    let (router, _config_provider) = management_api_bundle(pool.clone());
+    let (router, _config_provider) = management_api_bundle_without_auth(pool.clone());
     let client = TestServer::new(router).expect("Failed to create TestServer");
 
     (client, pool, container)
@@ -73,15 +73,40 @@ async fn create_test_provider(
 ) -> ProviderResponse {
     let config = match provider_type {
         ProviderType::Azure => ProviderConfig::Azure(AzureProviderConfig {
-            api_key: SecretObject::literal("test_azure_key".to_string()),
+            api_key: Some(SecretObject::literal("test_azure_key".to_string())),
             resource_name: "test_resource".to_string(),
             api_version: "2023-05-15".to_string(),
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            auth_type: None,
+            tenant_id: None,
+            client_id: None,
+            client_secret: None,
+            authority_host: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         ProviderType::OpenAI => ProviderConfig::OpenAI(OpenAIProviderConfig {
             api_key: SecretObject::literal("test_openai_key".to_string()),
+            api_key_secondary: None,
             organization_id: None,
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            // The tests name their model types freely.
+            allowed_model_prefixes: Some(vec!["*".to_string()]),
+        }),
+        ProviderType::Anthropic => ProviderConfig::Anthropic(AnthropicProviderConfig {
+            api_key: SecretObject::literal("test_anthropic_key".to_string()),
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         _ => panic!("Unsupported provider type for test helper"),
     };
@@ -144,7 +169,7 @@ async fn test_create_model_definition_success() {
     // Verify in DB
     let db_md = sqlx::query_as!(
         ModelDefinition,
-        "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at FROM hub_llmgateway_model_definitions WHERE id = $1",
+        "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at, version FROM hub_llmgateway_model_definitions WHERE id = $1",
         md_response.id
     )
     .fetch_one(&pool)
@@ -417,6 +442,7 @@ async fn test_update_model_definition_success() {
         provider_id: None, // Not changing provider
         config_details: Some(json!({ "new_detail": "cool"})),
         enabled: Some(false),
+        expected_version: Some(created_md.version),
     };
 
     let update_response = client
@@ -450,6 +476,7 @@ async fn test_update_model_definition_not_found() {
         provider_id: None,
         config_details: None,
         enabled: None,
+        expected_version: Some(1),
     };
     let response = client
         .put(&format!(
@@ -502,6 +529,7 @@ async fn test_update_model_definition_duplicate_key_conflict() {
         provider_id: None,
         config_details: None,
         enabled: None,
+        expected_version: Some(md2_created.version),
     };
     let update_response = client
         .put(&format!(
@@ -539,6 +567,7 @@ async fn test_update_model_definition_invalid_provider_id() {
         model_type: None,
         config_details: None,
         enabled: None,
+        expected_version: Some(created_md.version),
     };
     let update_response = client
         .put(&format!(
@@ -550,6 +579,51 @@ async fn test_update_model_definition_invalid_provider_id() {
     assert_eq!(update_response.status_code(), StatusCode::BAD_REQUEST);
 }
 
+#[tokio::test]
+async fn test_model_type_must_be_allowed_by_provider() {
+    let (client, _pool, _container) = setup_test_environment().await;
+    let provider = create_test_provider(&client, "Prov-Allowed-MD", ProviderType::Anthropic).await;
+    let create = |key: &str, model_type: &str| CreateModelDefinitionRequest {
+        key: key.to_string(),
+        model_type: model_type.to_string(),
+        provider_id: provider.id,
+        config_details: None,
+        enabled: Some(true),
+    };
+
+    let response = client
+        .post("/api/v1/management/model-definitions")
+        .json(&create("gpt-4o-on-anthropic", "gpt-4o"))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let message = response.text();
+    assert!(message.contains("gpt-4o-on-anthropic"), "{message}");
+    assert!(message.contains("Prov-Allowed-MD"), "{message}");
+
+    let response = client
+        .post("/api/v1/management/model-definitions")
+        .json(&create("claude-sonnet", "claude-sonnet-4-20250514"))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let created: ModelDefinitionResponse = response.json();
+
+    let update_response = client
+        .put(&format!(
+            "/api/v1/management/model-definitions/{}",
+            created.id
+        ))
+        .json(&UpdateModelDefinitionRequest {
+            provider_id: None,
+            key: None,
+            model_type: Some("gpt-4o".to_string()),
+            config_details: None,
+            enabled: None,
+            expected_version: Some(created.version),
+        })
+        .await;
+    assert_eq!(update_response.status_code(), StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn test_delete_model_definition_success() {
     let (client, pool, _container) = setup_test_environment().await;
@@ -583,7 +657,7 @@ async fn test_delete_model_definition_success() {
     // Verify it's gone from DB
     let db_model_after_delete = sqlx::query_as!(
         ModelDefinition,
-        "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at FROM hub_llmgateway_model_definitions WHERE id = $1",
+        "SELECT id, key, model_type, provider_id, config_details, enabled, created_at, updated_at, version FROM hub_llmgateway_model_definitions WHERE id = $1",
         created_md.id
     )
     .fetch_optional(&pool)
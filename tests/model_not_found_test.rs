@@ -0,0 +1,109 @@
+mod common;
+
+use common::{model, model_router, pipeline, pipeline_router, provider};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::types::{ModelConfig, Pipeline, PipelineType, ProviderType};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+/// A model of type `r#type` on the mock provider.
+fn mock_model(key: &str, r#type: &str) -> ModelConfig {
+    ModelConfig {
+        r#type: r#type.to_string(),
+        ..model(key, "mock")
+    }
+}
+
+/// A pipeline of `r#type` routing two `gpt-4o-mini` models on the mock provider.
+fn hub(r#type: PipelineType) -> Router {
+    pipeline_router(
+        &Pipeline {
+            r#type,
+            ..pipeline("default", vec![model_router(&["mini", "mini-backup"])])
+        },
+        &[provider("mock", ProviderType::Mock)],
+        &[
+            mock_model("mini", "gpt-4o-mini"),
+            mock_model("mini-backup", "gpt-4o-mini"),
+            mock_model("gpt-4o", "gpt-4o"),
+        ],
+    )
+}
+
+async fn post(r#type: PipelineType, model: &str) -> (StatusCode, Value) {
+    let (uri, body) = match r#type {
+        PipelineType::Chat => (
+            "/chat/completions",
+            json!({"model": model, "messages": [{"role": "user", "content": "Hi"}]}),
+        ),
+        PipelineType::Completion => ("/completions", json!({"model": model, "prompt": "Hi"})),
+        PipelineType::Embeddings => ("/embeddings", json!({"model": model, "input": "Hi"})),
+    };
+    let response = hub(r#type)
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+const PIPELINE_TYPES: [PipelineType; 3] = [
+    PipelineType::Chat,
+    PipelineType::Completion,
+    PipelineType::Embeddings,
+];
+
+#[tokio::test]
+async fn test_known_model_is_served() {
+    for r#type in PIPELINE_TYPES {
+        let (status, _) = post(r#type, "gpt-4o-mini").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
+
+// Both cases share a test, since the setting is read from the environment.
+#[tokio::test]
+async fn test_unknown_model_is_rejected() {
+    unsafe {
+        std::env::set_var("EXPOSE_AVAILABLE_MODELS", "true");
+    }
+    for r#type in PIPELINE_TYPES {
+        // A model the hub knows, but the pipeline doesn't route.
+        let (status, body) = post(r#type, "gpt-4o").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let error = &body["error"];
+        assert_eq!(error["type"], "invalid_request_error");
+        assert_eq!(error["code"], "model_not_found");
+        assert_eq!(error["param"], "model");
+        assert_eq!(error["available_models"], json!(["gpt-4o-mini"]));
+        assert_eq!(
+            error["message"],
+            "Model 'gpt-4o' is not served by this pipeline. Available models: gpt-4o-mini"
+        );
+    }
+
+    unsafe {
+        std::env::set_var("EXPOSE_AVAILABLE_MODELS", "false");
+    }
+    for r#type in PIPELINE_TYPES {
+        let (status, body) = post(r#type, "gpt-5").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let error = &body["error"];
+        assert_eq!(error["code"], "model_not_found");
+        assert!(error.get("available_models").is_none());
+        assert_eq!(
+            error["message"],
+            "Model 'gpt-5' is not served by this pipeline"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{model, model_router, pipeline, provider};
+use hub_lib::types::{GatewayConfig, ProviderType};
+use serde_json::json;
+use tower::ServiceExt;
+
+/// A `default` pipeline serving `gpt-4o` and a `mini` pipeline serving `gpt-4o-mini`.
+fn hub() -> axum::Router {
+    common::hub(GatewayConfig {
+        providers: vec![provider("mock", ProviderType::Mock)],
+        models: vec![model("gpt-4o", "mock"), model("gpt-4o-mini", "mock")],
+        pipelines: vec![
+            pipeline("default", vec![model_router(&["gpt-4o"])]),
+            pipeline("mini", vec![model_router(&["gpt-4o-mini"])]),
+        ],
+        ..Default::default()
+    })
+}
+
+async fn post(pipeline: &str, model: &str) -> StatusCode {
+    let body = json!({"model": model, "messages": [{"role": "user", "content": "Hi"}]});
+    hub()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-traceloop-pipeline", pipeline)
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+// Both cases share a test, since the setting is read from the environment.
+#[tokio::test]
+async fn test_lookups_ignore_case_only_when_enabled() {
+    unsafe {
+        std::env::set_var("CASE_INSENSITIVE_LOOKUPS", "false");
+    }
+    assert_eq!(post("mini", "gpt-4o-mini").await, StatusCode::OK);
+    assert_eq!(post("mini", " gpt-4o-mini ").await, StatusCode::OK);
+    assert_eq!(post("mini", "GPT-4o-Mini").await, StatusCode::NOT_FOUND);
+    // An unknown pipeline falls back to `default`, which doesn't serve the model.
+    assert_eq!(post("Mini", "gpt-4o-mini").await, StatusCode::NOT_FOUND);
+
+    unsafe {
+        std::env::set_var("CASE_INSENSITIVE_LOOKUPS", "true");
+    }
+    assert_eq!(post("mini", "GPT-4o-Mini").await, StatusCode::OK);
+    assert_eq!(post("Mini", "gpt-4o-mini").await, StatusCode::OK);
+    assert_eq!(post("MINI", " GPT-4O-MINI ").await, StatusCode::OK);
+
+    unsafe {
+        std::env::set_var("CASE_INSENSITIVE_LOOKUPS", "false");
+    }
+}
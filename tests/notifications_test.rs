@@ -0,0 +1,235 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{hub, openai_config};
+use hub_lib::management::dto::SecretObject;
+use hub_lib::notifications::{Notification, Notifier};
+use hub_lib::types::{
+    ErrorRateAlert, GatewayConfig, General, NotificationEventType, NotificationSeverity,
+    NotificationsConfig, WebhookConfig,
+};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BATCH_INTERVAL_MS: u64 = 100;
+
+async fn webhook_receiver() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn webhook(server: &MockServer, hook: &str) -> WebhookConfig {
+    WebhookConfig {
+        url: SecretObject::literal(format!("{}/{hook}", server.uri())),
+        events: vec![],
+        min_severity: NotificationSeverity::Info,
+    }
+}
+
+fn notifications(webhooks: Vec<WebhookConfig>) -> NotificationsConfig {
+    NotificationsConfig {
+        webhooks,
+        error_rate: None,
+        batch_interval_ms: BATCH_INTERVAL_MS,
+        max_retries: 2,
+    }
+}
+
+fn budget_event(event: NotificationEventType) -> Notification {
+    Notification::new(
+        event,
+        "default",
+        "Pipeline 'default' budget".to_string(),
+        json!({"spent_usd": 8.5, "limit_usd": 10.0, "window": "daily"}),
+    )
+}
+
+async fn settle() {
+    tokio::time::sleep(Duration::from_millis(BATCH_INTERVAL_MS * 4)).await;
+}
+
+async fn received(server: &MockServer) -> Vec<(String, Value)> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|request| {
+            let body = serde_json::from_slice(&request.body).unwrap();
+            (request.url.path().to_string(), body)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_events_are_batched_into_one_post() {
+    let server = webhook_receiver().await;
+    let notifier = Notifier::start(&notifications(vec![webhook(&server, "alerts")]));
+
+    notifier.publish(budget_event(NotificationEventType::BudgetWarning));
+    notifier.publish(budget_event(NotificationEventType::BudgetExceeded));
+    settle().await;
+
+    let received = received(&server).await;
+    assert_eq!(received.len(), 1);
+    let (path, body) = &received[0];
+    assert_eq!(path, "/alerts");
+    assert_eq!(
+        body["text"],
+        "Pipeline 'default' budget\nPipeline 'default' budget"
+    );
+    let events = body["notifications"].as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "budget_warning");
+    assert_eq!(events[0]["severity"], "warning");
+    assert_eq!(events[0]["pipeline"], "default");
+    assert_eq!(events[0]["details"]["limit_usd"], 10.0);
+    assert!(events[0]["timestamp"].is_string());
+    assert_eq!(events[1]["event"], "budget_exceeded");
+    assert_eq!(events[1]["severity"], "critical");
+}
+
+#[tokio::test]
+async fn test_webhooks_filter_by_event_and_severity() {
+    let server = webhook_receiver().await;
+    let critical = WebhookConfig {
+        min_severity: NotificationSeverity::Critical,
+        ..webhook(&server, "critical")
+    };
+    let warnings = WebhookConfig {
+        events: vec![NotificationEventType::BudgetWarning],
+        ..webhook(&server, "warnings")
+    };
+    let notifier = Notifier::start(&notifications(vec![critical, warnings]));
+
+    notifier.publish(budget_event(NotificationEventType::BudgetWarning));
+    notifier.publish(budget_event(NotificationEventType::BudgetExceeded));
+    settle().await;
+
+    let received: HashMap<_, _> = received(&server).await.into_iter().collect();
+    assert_eq!(received.len(), 2);
+    assert_eq!(
+        received["/critical"]["notifications"][0]["event"],
+        "budget_exceeded"
+    );
+    assert_eq!(
+        received["/warnings"]["notifications"][0]["event"],
+        "budget_warning"
+    );
+    assert_eq!(
+        received["/critical"]["notifications"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        received["/warnings"]["notifications"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_failed_delivery_is_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let notifier = Notifier::start(&notifications(vec![webhook(&server, "alerts")]));
+
+    notifier.publish(budget_event(NotificationEventType::BudgetExceeded));
+    settle().await;
+
+    let received = received(&server).await;
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0].1, received[1].1);
+}
+
+#[tokio::test]
+async fn test_publishing_never_waits_for_delivery() {
+    let notifier = Notifier::start(&NotificationsConfig {
+        webhooks: vec![WebhookConfig {
+            url: SecretObject::literal("http://127.0.0.1:9/alerts".to_string()),
+            events: vec![],
+            min_severity: NotificationSeverity::Info,
+        }],
+        ..notifications(vec![])
+    });
+
+    // Far more events than the queue holds; the overflow is dropped, not waited on.
+    let started = Instant::now();
+    for _ in 0..2000 {
+        notifier.publish(budget_event(NotificationEventType::BudgetWarning));
+    }
+    assert!(started.elapsed() < Duration::from_millis(BATCH_INTERVAL_MS));
+}
+
+#[tokio::test]
+async fn test_pipeline_errors_trigger_error_rate_alert() {
+    let receiver = webhook_receiver().await;
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&upstream)
+        .await;
+
+    let config = GatewayConfig {
+        general: Some(General {
+            notifications: Some(NotificationsConfig {
+                error_rate: Some(ErrorRateAlert {
+                    threshold_percent: 50,
+                    window_seconds: 60,
+                    min_requests: 2,
+                }),
+                ..notifications(vec![webhook(&receiver, "alerts")])
+            }),
+            ..Default::default()
+        }),
+        ..openai_config(&upstream)
+    };
+    let app = hub(config);
+
+    for _ in 0..2 {
+        let request = Request::builder()
+            .uri("/api/v1/chat/completions")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "model": "gpt-4o",
+                    "messages": [{"role": "user", "content": "hello"}]
+                })
+                .to_string(),
+            ))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    settle().await;
+
+    let received = received(&receiver).await;
+    assert_eq!(received.len(), 1);
+    let alert = &received[0].1["notifications"][0];
+    assert_eq!(alert["event"], "error_rate");
+    assert_eq!(alert["pipeline"], "default");
+    assert_eq!(alert["details"]["errors"], 2);
+}
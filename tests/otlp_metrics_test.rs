@@ -0,0 +1,116 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{model, model_router, pipeline, provider};
+use hub_lib::metrics::{OtlpMetrics, counter, gauge, histogram};
+use hub_lib::types::{GatewayConfig, ProviderType};
+use opentelemetry_sdk::metrics::data::{Gauge, Histogram, Metric, ResourceMetrics, Sum};
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::runtime;
+use opentelemetry_sdk::testing::metrics::InMemoryMetricExporter;
+use tower::ServiceExt;
+
+fn hub() -> axum::Router {
+    common::hub(GatewayConfig {
+        providers: vec![provider("openai", ProviderType::OpenAI)],
+        models: vec![model("gpt-4o", "openai")],
+        pipelines: vec![pipeline("default", vec![model_router(&["gpt-4o"])])],
+        ..Default::default()
+    })
+}
+
+/// The last export of metric `name`.
+fn exported<'a>(exports: &'a [ResourceMetrics], name: &str) -> &'a Metric {
+    exports
+        .iter()
+        .flat_map(|export| &export.scope_metrics)
+        .flat_map(|scope| &scope.metrics)
+        .rfind(|metric| metric.name == name)
+        .unwrap_or_else(|| panic!("{name} was not exported"))
+}
+
+// The meter provider's flush blocks on its export task, which needs another worker thread.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_metrics_reach_both_exporters() {
+    let exporter = InMemoryMetricExporter::default();
+    let provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(exporter.clone(), runtime::Tokio).build())
+        .build();
+    let app = hub();
+    OtlpMetrics::global().install(provider.clone());
+
+    counter!("test_requests_total", "pipeline" => "default").increment(2);
+    counter!("test_requests_total", "pipeline" => "default").increment(1);
+    gauge!("test_in_flight").increment(3.0);
+    gauge!("test_in_flight").decrement(1.0);
+    histogram!("test_latency_seconds").record(0.25);
+    histogram!("test_latency_seconds").record(0.75);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"model": "gpt-5", "messages": [{"role": "user", "content": "hi"}]}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    provider.force_flush().unwrap();
+    let exports = exporter.get_finished_metrics().unwrap();
+
+    let requests = exported(&exports, "test_requests_total");
+    let requests = requests.data.as_any().downcast_ref::<Sum<u64>>().unwrap();
+    assert_eq!(requests.data_points[0].value, 3);
+    let attributes = &requests.data_points[0].attributes;
+    assert_eq!(attributes.len(), 1);
+    assert_eq!(attributes[0].key.as_str(), "pipeline");
+    assert_eq!(attributes[0].value.as_str(), "default");
+
+    let in_flight = exported(&exports, "test_in_flight");
+    let in_flight = in_flight
+        .data
+        .as_any()
+        .downcast_ref::<Gauge<f64>>()
+        .unwrap();
+    assert_eq!(in_flight.data_points[0].value, 2.0);
+
+    let latency = exported(&exports, "test_latency_seconds");
+    let latency = latency
+        .data
+        .as_any()
+        .downcast_ref::<Histogram<f64>>()
+        .unwrap();
+    assert_eq!(latency.data_points[0].count, 2);
+    assert_eq!(latency.data_points[0].sum, 1.0);
+
+    // The HTTP layer's metrics are pushed too, while /metrics keeps serving them.
+    let http_requests = exported(&exports, "traceloop_hub_http_requests_total");
+    let http_requests = http_requests
+        .data
+        .as_any()
+        .downcast_ref::<Sum<u64>>()
+        .unwrap();
+    assert!(http_requests.data_points.iter().any(|point| {
+        point
+            .attributes
+            .iter()
+            .any(|kv| kv.key.as_str() == "status" && kv.value.as_str() == "404")
+    }));
+    let rendered = app
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let rendered = axum::body::to_bytes(rendered.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let rendered = String::from_utf8(rendered.to_vec()).unwrap();
+    assert!(rendered.contains("test_requests_total{pipeline=\"default\"} 3"));
+    assert!(rendered.contains("traceloop_hub_http_requests_total"));
+}
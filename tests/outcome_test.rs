@@ -0,0 +1,173 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use common::{completion, model_router, openai_config, pipeline};
+use hub_lib::management::dto::ApiKeyRole;
+use hub_lib::management::services::api_key_service::{ApiKeyService, StaticApiKey};
+use hub_lib::outcome::Outcome;
+use hub_lib::state::AppState;
+use hub_lib::types::{GatewayConfig, PluginConfig, ToolLimits};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Answers with the status named in the request's `user` field.
+async fn upstream() -> MockServer {
+    let server = MockServer::start().await;
+    for status in [400, 429, 503] {
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(body_string_contains(format!("status-{status}")))
+            .respond_with(ResponseTemplate::new(status).set_body_json(json!({
+                "error": {"message": "upstream failure", "type": "upstream"}
+            })))
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("Hi")))
+        .mount(&server)
+        .await;
+    server
+}
+
+const READ_ONLY_KEY: &str = "read-only-key";
+
+/// The gateway, and the admin routes as the management server serves them.
+fn hub(server: &MockServer) -> (Router, Router) {
+    let config = GatewayConfig {
+        pipelines: vec![pipeline(
+            "default",
+            vec![
+                PluginConfig::ToolLimits(ToolLimits {
+                    max_tools: Some(1),
+                    ..Default::default()
+                }),
+                model_router(&["gpt-4o"]),
+            ],
+        )],
+        ..openai_config(server)
+    };
+    let state = Arc::new(AppState::new(config).unwrap());
+    let admin = hub_lib::management::admin_router(
+        hub_lib::routes::admin_routes(state.clone()),
+        Arc::new(ApiKeyService::with_static_keys(vec![StaticApiKey::new(
+            READ_ONLY_KEY,
+            ApiKeyRole::ReadOnly,
+        )])),
+    );
+    (hub_lib::routes::create_router(state), admin)
+}
+
+fn tool(name: &str) -> Value {
+    json!({"type": "function", "function": {"name": name, "parameters": {"type": "object"}}})
+}
+
+async fn chat(app: &Router, body: Value) -> (StatusCode, Outcome) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    (response.status(), Outcome::of(&response))
+}
+
+fn request(user: &str) -> Value {
+    json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "hello"}],
+        "user": user
+    })
+}
+
+#[tokio::test]
+async fn test_outcomes_are_classified_and_rolled_up() {
+    let server = upstream().await;
+    let (app, admin) = hub(&server);
+
+    let mut too_many_tools = request("ok");
+    too_many_tools["tools"] = json!([tool("get_weather"), tool("get_forecast")]);
+    let mut unknown_model = request("ok");
+    unknown_model["model"] = json!("gpt-5");
+    let cases = [
+        (request("ok"), StatusCode::OK, Outcome::Success),
+        // The provider rejected what the client sent.
+        (
+            request("status-400"),
+            StatusCode::BAD_REQUEST,
+            Outcome::ClientError,
+        ),
+        // Throttling of the hub's key is the provider's, not the client's.
+        (
+            request("status-429"),
+            StatusCode::TOO_MANY_REQUESTS,
+            Outcome::ProviderError,
+        ),
+        (
+            request("status-503"),
+            StatusCode::SERVICE_UNAVAILABLE,
+            Outcome::ProviderError,
+        ),
+        (
+            too_many_tools,
+            StatusCode::BAD_REQUEST,
+            Outcome::GuardrailBlock,
+        ),
+        (unknown_model, StatusCode::NOT_FOUND, Outcome::ClientError),
+    ];
+    for (body, status, outcome) in cases {
+        let user = body["user"].clone();
+        assert_eq!(chat(&app, body).await, (status, outcome), "{user}");
+    }
+
+    // The gateway's health check only reports liveness.
+    let health = app
+        .oneshot(
+            Request::builder()
+                .uri("/health")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = to_bytes(health.into_body(), usize::MAX).await.unwrap();
+    let health: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(health, json!({"status": "ok"}));
+
+    let health = admin
+        .oneshot(
+            Request::builder()
+                .uri("/admin/health")
+                .header(header::AUTHORIZATION, format!("Bearer {READ_ONLY_KEY}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(health.status(), StatusCode::OK);
+    let body = to_bytes(health.into_body(), usize::MAX).await.unwrap();
+    let health: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        health["recent_outcomes"]["default"],
+        json!({
+            "success": 1,
+            "client_error": 2,
+            "provider_error": 2,
+            "guardrail_block": 1,
+            "timeout": 0,
+            "cancelled": 0
+        })
+    );
+}
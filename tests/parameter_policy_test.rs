@@ -0,0 +1,148 @@
+mod common;
+
+use common::{model, model_router, openai, openai_upstream, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::axum::response::Response;
+use hub_lib::pipelines::parameter_policy::SANITIZED_HEADER;
+use hub_lib::types::{ParameterPolicyMode, ParameterRule, PluginConfig};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use tower::ServiceExt;
+use wiremock::MockServer;
+
+fn hub(server: &MockServer, mode: ParameterPolicyMode) -> Router {
+    pipeline_router(
+        &pipeline(
+            "compliance",
+            vec![
+                PluginConfig::ParameterPolicy {
+                    mode,
+                    rules: BTreeMap::from([
+                        ("user".to_string(), ParameterRule::Deny),
+                        (
+                            "temperature".to_string(),
+                            ParameterRule::Clamp {
+                                min: None,
+                                max: Some(1.0),
+                            },
+                        ),
+                        ("max_tokens".to_string(), ParameterRule::Require),
+                    ]),
+                    allow_extra_body: false,
+                    extra_body_keys: vec![],
+                },
+                model_router(&["gpt-4o"]),
+            ],
+        ),
+        &[openai("openai", server)],
+        &[model("gpt-4o", "openai")],
+    )
+}
+
+async fn post_chat(app: Router, body: Value) -> Response {
+    app.oneshot(
+        Request::builder()
+            .uri("/chat/completions")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+async fn error_param(response: Response) -> Value {
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    body["error"]["param"].clone()
+}
+
+#[tokio::test]
+async fn test_sanitize_clamps_temperature_and_strips_user() {
+    let server = openai_upstream("hi").await;
+    let app = hub(&server, ParameterPolicyMode::Sanitize);
+
+    let response = post_chat(
+        app,
+        json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hello"}],
+            "temperature": 1.8,
+            "max_tokens": 64,
+            "user": "alice@example.com"
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[SANITIZED_HEADER], "temperature,user");
+
+    let received = server.received_requests().await.unwrap();
+    let upstream: Value = serde_json::from_slice(&received[0].body).unwrap();
+    assert_eq!(upstream["temperature"], json!(1.0));
+    assert!(upstream.get("user").is_none());
+    assert_eq!(upstream["max_completion_tokens"], json!(64));
+}
+
+#[tokio::test]
+async fn test_strict_rejects_denied_user() {
+    let server = openai_upstream("hi").await;
+    let app = hub(&server, ParameterPolicyMode::Strict);
+
+    let response = post_chat(
+        app,
+        json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hello"}],
+            "max_tokens": 64,
+            "user": "alice@example.com"
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(error_param(response).await, "user");
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_missing_max_tokens_is_rejected() {
+    let server = openai_upstream("hi").await;
+    let app = hub(&server, ParameterPolicyMode::Sanitize);
+
+    let response = post_chat(
+        app,
+        json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hello"}]
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(error_param(response).await, "max_tokens");
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_compliant_request_is_untouched() {
+    let server = openai_upstream("hi").await;
+    let app = hub(&server, ParameterPolicyMode::Strict);
+
+    let response = post_chat(
+        app,
+        json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hello"}],
+            "temperature": 0.4,
+            "max_tokens": 64
+        }),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(SANITIZED_HEADER).is_none());
+}
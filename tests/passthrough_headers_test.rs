@@ -0,0 +1,157 @@
+mod common;
+
+use common::{completion, model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, Response, StatusCode};
+use hub_lib::types::{PassthroughHeaders, PluginConfig};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn chunks() -> Value {
+    json!([{
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 1,
+        "model": "gpt-4o",
+        "choices": [{"index": 0, "delta": {"content": "Hello"}, "finish_reason": "stop"}]
+    }])
+}
+
+/// An OpenAI upstream that reports its rate limits alongside `body`.
+async fn upstream(body: Value) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-ratelimit-remaining-requests", "499")
+                .insert_header("x-ratelimit-remaining-tokens", "29000")
+                .insert_header("x-ratelimit-reset-tokens", "2ms")
+                .insert_header("openai-processing-ms", "120")
+                .set_body_json(body),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+fn hub(server: &MockServer, mut plugins: Vec<PluginConfig>) -> Router {
+    plugins.push(model_router(&["gpt-4o"]));
+    pipeline_router(
+        &pipeline("default", plugins),
+        &[openai("openai", server)],
+        &[model("gpt-4o", "openai")],
+    )
+}
+
+async fn chat(app: Router, stream: bool) -> Response<Body> {
+    app.oneshot(
+        Request::builder()
+            .uri("/chat/completions")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "model": "gpt-4o",
+                    "messages": [{"role": "user", "content": "Hi"}],
+                    "stream": stream
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+fn header<'a>(response: &'a Response<Body>, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .map(|value| value.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn test_rate_limit_headers_are_passed_through_by_default() {
+    let server = upstream(completion("Hello")).await;
+
+    let response = chat(hub(&server, vec![]), false).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        header(&response, "x-ratelimit-remaining-requests"),
+        Some("499")
+    );
+    assert_eq!(
+        header(&response, "x-ratelimit-remaining-tokens"),
+        Some("29000")
+    );
+    assert_eq!(header(&response, "x-ratelimit-reset-tokens"), Some("2ms"));
+    assert_eq!(header(&response, "openai-processing-ms"), None);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["choices"][0]["message"]["content"], "Hello");
+}
+
+#[tokio::test]
+async fn test_streaming_responses_carry_passed_through_headers() {
+    let server = upstream(chunks()).await;
+
+    let response = chat(hub(&server, vec![]), true).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        header(&response, "x-ratelimit-remaining-tokens"),
+        Some("29000")
+    );
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("Hello"));
+}
+
+#[tokio::test]
+async fn test_pipeline_override_prefixes_headers() {
+    let server = upstream(completion("Hello")).await;
+    let passthrough = PluginConfig::PassthroughHeaders(PassthroughHeaders {
+        headers: vec![
+            "openai-processing-ms".to_string(),
+            "x-ratelimit-remaining-tokens".to_string(),
+        ],
+        prefix: true,
+    });
+
+    for stream in [false, true] {
+        let response = chat(hub(&server, vec![passthrough.clone()]), stream).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            header(&response, "x-upstream-openai-processing-ms"),
+            Some("120")
+        );
+        assert_eq!(
+            header(&response, "x-upstream-x-ratelimit-remaining-tokens"),
+            Some("29000")
+        );
+        assert_eq!(header(&response, "x-ratelimit-remaining-tokens"), None);
+        assert_eq!(
+            header(&response, "x-upstream-x-ratelimit-remaining-requests"),
+            None
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_empty_override_disables_passthrough() {
+    let server = upstream(completion("Hello")).await;
+    let passthrough = PluginConfig::PassthroughHeaders(PassthroughHeaders {
+        headers: vec![],
+        prefix: false,
+    });
+
+    let response = chat(hub(&server, vec![passthrough]), false).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(header(&response, "x-ratelimit-remaining-tokens"), None);
+}
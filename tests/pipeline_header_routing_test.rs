@@ -9,6 +9,7 @@ fn create_test_config_with_multiple_pipelines() -> GatewayConfig {
         key: "test-provider".to_string(),
         r#type: ProviderType::OpenAI,
         api_key: "test-key".to_string(),
+        maintenance_windows: vec![],
         params: Default::default(),
     };
 
@@ -17,6 +18,8 @@ fn create_test_config_with_multiple_pipelines() -> GatewayConfig {
         r#type: "gpt-4".to_string(),
         provider: "test-provider".to_string(),
         params: Default::default(),
+        enabled: true,
+        deprecation: Default::default(),
     };
 
     let pipeline1 = Pipeline {
@@ -24,7 +27,11 @@ fn create_test_config_with_multiple_pipelines() -> GatewayConfig {
         r#type: PipelineType::Chat,
         plugins: vec![PluginConfig::ModelRouter {
             models: vec!["test-model".to_string()],
+            allow_dynamic_models: false,
+            adaptive: None,
+            race: None,
         }],
+        store_artifacts: false,
     };
 
     let pipeline2 = Pipeline {
@@ -32,7 +39,11 @@ fn create_test_config_with_multiple_pipelines() -> GatewayConfig {
         r#type: PipelineType::Chat,
         plugins: vec![PluginConfig::ModelRouter {
             models: vec!["test-model".to_string()],
+            allow_dynamic_models: false,
+            adaptive: None,
+            race: None,
         }],
+        store_artifacts: false,
     };
 
     GatewayConfig {
@@ -40,6 +51,7 @@ fn create_test_config_with_multiple_pipelines() -> GatewayConfig {
         providers: vec![provider],
         models: vec![model],
         pipelines: vec![pipeline1, pipeline2],
+        prompt_templates: Default::default(),
     }
 }
 
@@ -74,7 +86,11 @@ async fn test_pipeline_header_routing_configuration_updates() {
         r#type: PipelineType::Chat,
         plugins: vec![PluginConfig::ModelRouter {
             models: vec!["test-model".to_string()],
+            allow_dynamic_models: false,
+            adaptive: None,
+            race: None,
         }],
+        store_artifacts: false,
     };
     updated_config.pipelines.push(pipeline3);
 
@@ -11,16 +11,26 @@ use hub_lib::management::{
     AppState,
     api::routes::{model_definition_routes, pipeline_routes, provider_routes},
     db::models::{ModelDefinition, Pipeline, Provider},
+    db::repositories::{
+        model_definition_repository::ModelDefinitionRepository,
+        pipeline_repository::PipelineRepository,
+    },
     dto::{
         AnthropicProviderConfig, AzureProviderConfig, BedrockProviderConfig,
         CreateModelDefinitionRequest, CreatePipelineRequestDto, CreateProviderRequest,
-        LoggingConfigDto, ModelDefinitionResponse, ModelRouterConfigDto, ModelRouterModelEntryDto,
-        ModelRouterStrategyDto, OpenAIProviderConfig, PipelinePluginConfigDto, PipelineResponseDto,
-        PluginType, ProviderConfig, ProviderResponse, ProviderType, SecretObject, TracingConfigDto,
-        UpdatePipelineRequestDto, VertexAIProviderConfig,
+        LoggingConfigDto, MockProviderConfig, ModelDefinitionResponse, ModelRouterConfigDto,
+        ModelRouterModelEntryDto, ModelRouterStrategyDto, OpenAIProviderConfig,
+        PipelinePluginConfigDto, PipelineResponseDto, PluginType, ProviderConfig, ProviderResponse,
+        ProviderType, SecretObject, TracingConfigDto, UpdatePipelineRequestDto,
+        VertexAIProviderConfig,
     },
     errors::ApiError,
-    management_api_bundle,
+    management_api_bundle_without_auth,
+    services::{
+        config_provider_service::ConfigProviderService,
+        model_definition_service::ModelDefinitionService, pipeline_service::PipelineService,
+        provider_service::ProviderService,
+    },
 };
 use serde_json::json;
 use sqlx::{PgPool, migrate::Migrator, postgres::PgPoolOptions, types::Uuid};
@@ -66,7 +76,7 @@ async fn setup_test_environment() -> (TestServer, PgPool, impl Drop) {
         .await
         .expect("Failed to run migrations on test DB");
 
-    let (router, _config_provider) = management_api_bundle(pool.clone());
+    let (router, _config_provider) = management_api_bundle_without_auth(pool.clone());
     let client = TestServer::new(router).expect("Failed to create TestServer");
 
     (client, pool, container) // Return Arc<PgPool> and container
@@ -91,21 +101,42 @@ async fn create_test_provider(
     key_suffix: &str,
     provider_type_enum: ProviderType,
 ) -> ProviderResponse {
-    let name = format!("Test Provider {}", key_suffix);
+    let name = format!("Test-Provider-{}", key_suffix);
     let provider_config = match provider_type_enum {
         ProviderType::OpenAI => ProviderConfig::OpenAI(OpenAIProviderConfig {
             api_key: SecretObject::literal(format!("openai_key_{}", key_suffix)),
+            api_key_secondary: None,
             organization_id: Some(format!("openai_org_{}", key_suffix)),
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         ProviderType::Azure => ProviderConfig::Azure(AzureProviderConfig {
-            api_key: SecretObject::literal(format!("azure_key_{}", key_suffix)),
+            api_key: Some(SecretObject::literal(format!("azure_key_{}", key_suffix))),
             api_version: "2023-05-15".to_string(),
             resource_name: format!("azure_res_{}", key_suffix),
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            auth_type: None,
+            tenant_id: None,
+            client_id: None,
+            client_secret: None,
+            authority_host: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         ProviderType::Anthropic => ProviderConfig::Anthropic(AnthropicProviderConfig {
             api_key: SecretObject::literal(format!("anthropic_key_{}", key_suffix)),
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         ProviderType::Bedrock => ProviderConfig::Bedrock(BedrockProviderConfig {
             region: "us-east-1".to_string(),
@@ -120,12 +151,26 @@ async fn create_test_provider(
             aws_session_token: None,
             use_iam_role: Some(false),
             inference_profile_id: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         ProviderType::VertexAI => ProviderConfig::VertexAI(VertexAIProviderConfig {
             project_id: Some(format!("vertexai_project_{}", key_suffix)),
             location: Some("us-central1".to_string()),
             credentials_path: Some(format!("/path/to/vertexai_creds_{}.json", key_suffix)),
             api_key: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
+        }),
+        ProviderType::Mock => ProviderConfig::Mock(MockProviderConfig {
+            response: Some(format!("mock_response_{}", key_suffix)),
+            ..Default::default()
         }),
     };
 
@@ -171,11 +216,12 @@ async fn create_test_model_definition(
 async fn test_create_pipeline_success_simple() {
     let (server, _pool, _container) = setup_test_environment().await;
     let pipeline_req = CreatePipelineRequestDto {
-        name: "Test Simple Pipeline".to_string(),
+        name: "Test-Simple-Pipeline".to_string(),
         pipeline_type: "chat".to_string(),
         description: Some("A simple test pipeline".to_string()),
         plugins: vec![],
         enabled: true,
+        environment: None,
     };
     let response = server
         .post("/api/v1/management/pipelines")
@@ -199,13 +245,14 @@ async fn test_create_pipeline_success_simple() {
 #[tokio::test]
 async fn test_create_pipeline_name_conflict() {
     let (server, _pool, _container) = setup_test_environment().await;
-    let pipeline_name = format!("Conflict Pipeline {}", Uuid::new_v4());
+    let pipeline_name = format!("Conflict-Pipeline-{}", Uuid::new_v4());
     let pipeline_req = CreatePipelineRequestDto {
         name: pipeline_name.clone(),
         pipeline_type: "chat".to_string(),
         description: None,
         plugins: vec![],
         enabled: true,
+        environment: None,
     };
     let creation_response = server
         .post("/api/v1/management/pipelines")
@@ -241,7 +288,7 @@ async fn test_create_pipeline_with_valid_model_router() {
     .await;
     let model_def_key = "gpt-4-for-pipeline-valid-mr";
     let model_def = create_test_model_definition(&server, provider.id, model_def_key).await;
-    let pipeline_name = format!("Pipeline ValidMR {}", Uuid::new_v4());
+    let pipeline_name = format!("Pipeline-ValidMR-{}", Uuid::new_v4());
     let plugin_config_data = json!({
         "strategy": "simple",
         "models": [
@@ -253,6 +300,7 @@ async fn test_create_pipeline_with_valid_model_router() {
         config_data: plugin_config_data,
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
     let pipeline_req = CreatePipelineRequestDto {
         name: pipeline_name.clone(),
@@ -260,6 +308,7 @@ async fn test_create_pipeline_with_valid_model_router() {
         description: Some("Test pipeline with a valid model router plugin".to_string()),
         plugins: vec![pipeline_plugin],
         enabled: true,
+        environment: None,
     };
     let response = server
         .post("/api/v1/management/pipelines")
@@ -282,7 +331,7 @@ async fn test_create_pipeline_with_valid_model_router() {
 async fn test_create_pipeline_with_invalid_model_router_key() {
     let (server, _pool, _container) = setup_test_environment().await;
     let non_existent_model_key = format!("non-existent-model-{}", Uuid::new_v4());
-    let pipeline_name = format!("Pipeline InvalidMRKey {}", Uuid::new_v4());
+    let pipeline_name = format!("Pipeline-InvalidMRKey-{}", Uuid::new_v4());
     let plugin_config_data = json!({
         "strategy": "simple",
         "models": [
@@ -294,6 +343,7 @@ async fn test_create_pipeline_with_invalid_model_router_key() {
         config_data: plugin_config_data,
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
     let pipeline_req = CreatePipelineRequestDto {
         name: pipeline_name.clone(),
@@ -301,6 +351,7 @@ async fn test_create_pipeline_with_invalid_model_router_key() {
         description: Some("Test pipeline with an invalid model router key".to_string()),
         plugins: vec![pipeline_plugin],
         enabled: true,
+        environment: None,
     };
     let response = server
         .post("/api/v1/management/pipelines")
@@ -325,13 +376,14 @@ async fn test_create_pipeline_with_invalid_model_router_key() {
 #[tokio::test]
 async fn test_list_pipelines() {
     let (server, _pool, _container) = setup_test_environment().await;
-    let pipeline_name1 = format!("Listable Pipeline 1 {}", Uuid::new_v4());
+    let pipeline_name1 = format!("Listable-Pipeline-1-{}", Uuid::new_v4());
     let pipeline1_req = CreatePipelineRequestDto {
         name: pipeline_name1.clone(),
         pipeline_type: "chat".to_string(),
         description: None,
         plugins: vec![],
         enabled: true,
+        environment: None,
     };
     let response1 = server
         .post("/api/v1/management/pipelines")
@@ -340,13 +392,14 @@ async fn test_list_pipelines() {
     response1.assert_status(StatusCode::CREATED);
     let created_pipeline1: PipelineResponseDto = response1.json();
 
-    let pipeline_name2 = format!("Listable Pipeline 2 {}", Uuid::new_v4());
+    let pipeline_name2 = format!("Listable-Pipeline-2-{}", Uuid::new_v4());
     let pipeline2_req = CreatePipelineRequestDto {
         name: pipeline_name2.clone(),
         pipeline_type: "embeddings".to_string(),
         description: Some("Another listable".to_string()),
         plugins: vec![],
         enabled: false,
+        environment: None,
     };
     let response2 = server
         .post("/api/v1/management/pipelines")
@@ -380,6 +433,7 @@ async fn test_get_pipeline_by_name() {
         description: Some("Fetch by name".to_string()),
         plugins: vec![],
         enabled: true,
+        environment: None,
     };
     let creation_response = server
         .post("/api/v1/management/pipelines")
@@ -417,7 +471,7 @@ async fn test_update_pipeline_name_and_plugins() {
     let model_def1 = create_test_model_definition(&server, provider.id, model_def1_key).await;
     let model_def2_key = "gpt-4-for-update-pipe";
     let model_def2 = create_test_model_definition(&server, provider.id, model_def2_key).await;
-    let initial_pipeline_name = format!("Update Target Pipeline {}", Uuid::new_v4());
+    let initial_pipeline_name = format!("Update-Target-Pipeline-{}", Uuid::new_v4());
     let initial_plugin_config_data = json!({
         "strategy": "simple",
         "models": [{"key": model_def1.key.clone(), "priority": 1, "weight": 100}]
@@ -427,6 +481,7 @@ async fn test_update_pipeline_name_and_plugins() {
         config_data: initial_plugin_config_data,
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
     let create_req = CreatePipelineRequestDto {
         name: initial_pipeline_name.clone(),
@@ -434,6 +489,7 @@ async fn test_update_pipeline_name_and_plugins() {
         description: Some("Initial version".to_string()),
         plugins: vec![initial_pipeline_plugin],
         enabled: true,
+        environment: None,
     };
     let creation_response = server
         .post("/api/v1/management/pipelines")
@@ -442,7 +498,7 @@ async fn test_update_pipeline_name_and_plugins() {
     creation_response.assert_status(StatusCode::CREATED);
     let created_pipeline: PipelineResponseDto = creation_response.json();
 
-    let updated_pipeline_name = format!("Updated Pipeline Name {}", Uuid::new_v4());
+    let updated_pipeline_name = format!("Updated-Pipeline-Name-{}", Uuid::new_v4());
     let updated_plugin_config_data = json!({
         "strategy": "ordered_fallback",
         "models": [
@@ -455,12 +511,14 @@ async fn test_update_pipeline_name_and_plugins() {
         config_data: updated_plugin_config_data,
         enabled: false,
         order_in_pipeline: 1,
+        id: None,
     };
     let new_simple_plugin = PipelinePluginConfigDto {
         plugin_type: PluginType::Logging,
         config_data: json!({ "level": "strict"}),
         enabled: true,
         order_in_pipeline: 2,
+        id: None,
     };
     let update_req = UpdatePipelineRequestDto {
         name: Some(updated_pipeline_name.clone()),
@@ -468,6 +526,7 @@ async fn test_update_pipeline_name_and_plugins() {
         description: Some("Updated version".to_string()),
         plugins: Some(vec![updated_model_router_plugin, new_simple_plugin]),
         enabled: Some(false),
+        expected_version: Some(created_pipeline.version),
     };
     let update_response = server
         .put(&format!(
@@ -478,6 +537,7 @@ async fn test_update_pipeline_name_and_plugins() {
         .await;
     update_response.assert_status_ok();
     let updated_pipeline: PipelineResponseDto = update_response.json();
+    assert_eq!(updated_pipeline.version, created_pipeline.version + 1);
     assert_eq!(updated_pipeline.id, created_pipeline.id);
     assert_eq!(updated_pipeline.name, updated_pipeline_name);
     assert_eq!(updated_pipeline.description, update_req.description);
@@ -505,16 +565,58 @@ async fn test_update_pipeline_name_and_plugins() {
     );
 }
 
+#[tokio::test]
+async fn test_update_pipeline_rejects_stale_version() {
+    let (server, _pool, _container) = setup_test_environment().await;
+    let pipeline_req = CreatePipelineRequestDto {
+        name: format!("Versioned-Pipeline-{}", Uuid::new_v4()),
+        pipeline_type: "chat".to_string(),
+        description: None,
+        plugins: vec![],
+        enabled: true,
+        environment: None,
+    };
+    let created_pipeline: PipelineResponseDto = server
+        .post("/api/v1/management/pipelines")
+        .json(&pipeline_req)
+        .await
+        .json();
+    let pipeline_url = format!("/api/v1/management/pipelines/{}", created_pipeline.id);
+    let describe = |description: &str| UpdatePipelineRequestDto {
+        name: None,
+        pipeline_type: None,
+        description: Some(description.to_string()),
+        plugins: None,
+        enabled: None,
+        expected_version: Some(created_pipeline.version),
+    };
+
+    let first_write = server.put(&pipeline_url).json(&describe("first")).await;
+    first_write.assert_status_ok();
+    let updated_pipeline: PipelineResponseDto = first_write.json();
+    assert_eq!(updated_pipeline.version, created_pipeline.version + 1);
+
+    let stale_write = server.put(&pipeline_url).json(&describe("second")).await;
+    stale_write.assert_status(StatusCode::CONFLICT);
+    let error: serde_json::Value = stale_write.json();
+    assert_eq!(error["current_version"], updated_pipeline.version);
+
+    let fetched_pipeline: PipelineResponseDto = server.get(&pipeline_url).await.json();
+    assert_eq!(fetched_pipeline.description.as_deref(), Some("first"));
+    assert_eq!(fetched_pipeline.version, updated_pipeline.version);
+}
+
 #[tokio::test]
 async fn test_delete_pipeline() {
     let (server, _pool, _container) = setup_test_environment().await;
-    let pipeline_name = format!("Delete Target Pipeline {}", Uuid::new_v4());
+    let pipeline_name = format!("Delete-Target-Pipeline-{}", Uuid::new_v4());
     let pipeline_req = CreatePipelineRequestDto {
         name: pipeline_name.clone(),
         pipeline_type: "temporary".to_string(),
         description: Some("To be deleted".to_string()),
         plugins: vec![],
         enabled: true,
+        environment: None,
     };
     let creation_response = server
         .post("/api/v1/management/pipelines")
@@ -544,6 +646,117 @@ async fn test_delete_pipeline() {
         .assert_status_not_found();
 }
 
+#[tokio::test]
+async fn test_restore_pipeline() {
+    let (server, _pool, _container) = setup_test_environment().await;
+    let pipeline_req = CreatePipelineRequestDto {
+        name: format!("Restore-Target-Pipeline-{}", Uuid::new_v4()),
+        pipeline_type: "chat".to_string(),
+        description: None,
+        plugins: vec![],
+        enabled: true,
+        environment: None,
+    };
+    let created_pipeline: PipelineResponseDto = server
+        .post("/api/v1/management/pipelines")
+        .json(&pipeline_req)
+        .await
+        .json();
+    let pipeline_url = format!("/api/v1/management/pipelines/{}", created_pipeline.id);
+
+    server
+        .delete(&pipeline_url)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    let pipelines: Vec<PipelineResponseDto> =
+        server.get("/api/v1/management/pipelines").await.json();
+    assert!(pipelines.iter().all(|p| p.id != created_pipeline.id));
+    server
+        .get(&format!(
+            "/api/v1/management/pipelines/name/{}",
+            pipeline_req.name
+        ))
+        .await
+        .assert_status_not_found();
+
+    let restore_response = server.post(&format!("{pipeline_url}/restore")).await;
+    restore_response.assert_status_ok();
+    let restored_pipeline: PipelineResponseDto = restore_response.json();
+    assert_eq!(restored_pipeline.name, pipeline_req.name);
+    server.get(&pipeline_url).await.assert_status_ok();
+
+    server
+        .delete(&format!("{pipeline_url}?hard=true"))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    server
+        .post(&format!("{pipeline_url}/restore"))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_soft_deleted_provider_leaves_gateway_config() {
+    let (server, pool, _container) = setup_test_environment().await;
+    let (_router, config_provider) = management_api_bundle_without_auth(pool.clone());
+    let provider = create_test_provider(&server, "soft-delete", ProviderType::OpenAI).await;
+    let model_def = create_test_model_definition(&server, provider.id, "gpt-4o-soft-delete").await;
+    let pipeline_req = CreatePipelineRequestDto {
+        name: format!("Soft-Delete-Pipeline-{}", Uuid::new_v4()),
+        pipeline_type: "chat".to_string(),
+        description: None,
+        plugins: vec![PipelinePluginConfigDto {
+            plugin_type: PluginType::ModelRouter,
+            config_data: json!({"models": [{"key": model_def.key, "priority": 0}]}),
+            enabled: true,
+            order_in_pipeline: 1,
+            id: None,
+        }],
+        enabled: true,
+        environment: None,
+    };
+    let pipeline: PipelineResponseDto = server
+        .post("/api/v1/management/pipelines")
+        .json(&pipeline_req)
+        .await
+        .json();
+    let provider_url = format!("/api/v1/management/providers/{}", provider.id);
+
+    // A live pipeline routes to the provider's model, so it can't go away yet.
+    server
+        .delete(&provider_url)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    server
+        .delete(&format!("/api/v1/management/pipelines/{}", pipeline.id))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    let config = config_provider.fetch_live_config().await.unwrap();
+    assert!(config.providers.iter().any(|p| p.key == provider.name));
+    assert!(config.pipelines.is_empty());
+
+    server
+        .delete(&provider_url)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    let config = config_provider.fetch_live_config().await.unwrap();
+    assert!(config.providers.is_empty());
+    assert!(config.models.is_empty());
+    server
+        .get("/api/v1/management/model-definitions")
+        .await
+        .assert_status_ok();
+
+    server
+        .post(&format!("{provider_url}/restore"))
+        .await
+        .assert_status_ok();
+    let config = config_provider.fetch_live_config().await.unwrap();
+    assert!(config.providers.iter().any(|p| p.key == provider.name));
+    assert!(config.models.iter().any(|m| m.key == model_def.key));
+}
+
 #[tokio::test]
 async fn test_create_pipeline_with_logging_plugin() {
     let (server, _pool, _container) = setup_test_environment().await;
@@ -553,14 +766,16 @@ async fn test_create_pipeline_with_logging_plugin() {
         config_data: json!({"level": "debug"}),
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
 
     let pipeline_req = CreatePipelineRequestDto {
-        name: format!("Logging Pipeline {}", Uuid::new_v4()),
+        name: format!("Logging-Pipeline-{}", Uuid::new_v4()),
         pipeline_type: "chat".to_string(),
         description: Some("Pipeline with logging plugin".to_string()),
         plugins: vec![logging_plugin],
         enabled: true,
+        environment: None,
     };
 
     let response = server
@@ -599,14 +814,16 @@ async fn test_create_pipeline_with_tracing_plugin() {
         }),
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
 
     let pipeline_req = CreatePipelineRequestDto {
-        name: format!("Tracing Pipeline {}", Uuid::new_v4()),
+        name: format!("Tracing-Pipeline-{}", Uuid::new_v4()),
         pipeline_type: "chat".to_string(),
         description: Some("Pipeline with tracing plugin".to_string()),
         plugins: vec![tracing_plugin],
         enabled: true,
+        environment: None,
     };
 
     let response = server
@@ -652,14 +869,16 @@ async fn test_create_pipeline_with_tracing_environment_secret() {
         }),
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
 
     let pipeline_req = CreatePipelineRequestDto {
-        name: format!("Tracing Env Pipeline {}", Uuid::new_v4()),
+        name: format!("Tracing-Env-Pipeline-{}", Uuid::new_v4()),
         pipeline_type: "chat".to_string(),
         description: Some("Pipeline with tracing plugin using environment variable".to_string()),
         plugins: vec![tracing_plugin],
         enabled: true,
+        environment: None,
     };
 
     let response = server
@@ -695,14 +914,16 @@ async fn test_create_pipeline_with_tracing_kubernetes_secret() {
         }),
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
 
     let pipeline_req = CreatePipelineRequestDto {
-        name: format!("Tracing K8s Pipeline {}", Uuid::new_v4()),
+        name: format!("Tracing-K8s-Pipeline-{}", Uuid::new_v4()),
         pipeline_type: "chat".to_string(),
         description: Some("Pipeline with tracing plugin using Kubernetes secret".to_string()),
         plugins: vec![tracing_plugin],
         enabled: true,
+        environment: None,
     };
 
     let response = server
@@ -737,6 +958,7 @@ async fn test_create_pipeline_with_multiple_plugins() {
         config_data: json!({"level": "info"}),
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
 
     let tracing_plugin = PipelinePluginConfigDto {
@@ -750,6 +972,7 @@ async fn test_create_pipeline_with_multiple_plugins() {
         }),
         enabled: true,
         order_in_pipeline: 2,
+        id: None,
     };
 
     let model_router_plugin = PipelinePluginConfigDto {
@@ -760,14 +983,16 @@ async fn test_create_pipeline_with_multiple_plugins() {
         }),
         enabled: true,
         order_in_pipeline: 3,
+        id: None,
     };
 
     let pipeline_req = CreatePipelineRequestDto {
-        name: format!("Multi Plugin Pipeline {}", Uuid::new_v4()),
+        name: format!("Multi-Plugin-Pipeline-{}", Uuid::new_v4()),
         pipeline_type: "chat".to_string(),
         description: Some("Pipeline with multiple plugin types".to_string()),
         plugins: vec![logging_plugin, tracing_plugin, model_router_plugin],
         enabled: true,
+        environment: None,
     };
 
     let response = server
@@ -798,14 +1023,16 @@ async fn test_create_pipeline_with_invalid_logging_config() {
         config_data: json!({"invalid_field": "value"}), // Missing required 'level' field
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
 
     let pipeline_req = CreatePipelineRequestDto {
-        name: format!("Invalid Logging Pipeline {}", Uuid::new_v4()),
+        name: format!("Invalid-Logging-Pipeline-{}", Uuid::new_v4()),
         pipeline_type: "chat".to_string(),
         description: Some("Pipeline with invalid logging config".to_string()),
         plugins: vec![invalid_logging_plugin],
         enabled: true,
+        environment: None,
     };
 
     let response = server
@@ -824,14 +1051,16 @@ async fn test_create_pipeline_with_invalid_tracing_config() {
         config_data: json!({"endpoint": "http://trace.example.com"}), // Missing required 'api_key' field
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
 
     let pipeline_req = CreatePipelineRequestDto {
-        name: format!("Invalid Tracing Pipeline {}", Uuid::new_v4()),
+        name: format!("Invalid-Tracing-Pipeline-{}", Uuid::new_v4()),
         pipeline_type: "chat".to_string(),
         description: Some("Pipeline with invalid tracing config".to_string()),
         plugins: vec![invalid_tracing_plugin],
         enabled: true,
+        environment: None,
     };
 
     let response = server
@@ -847,11 +1076,12 @@ async fn test_update_pipeline_with_logging_and_tracing() {
 
     // Create a simple pipeline first
     let pipeline_req = CreatePipelineRequestDto {
-        name: format!("Update Target Pipeline {}", Uuid::new_v4()),
+        name: format!("Update-Target-Pipeline-{}", Uuid::new_v4()),
         pipeline_type: "chat".to_string(),
         description: Some("Initial pipeline".to_string()),
         plugins: vec![],
         enabled: true,
+        environment: None,
     };
 
     let response = server
@@ -867,6 +1097,7 @@ async fn test_update_pipeline_with_logging_and_tracing() {
         config_data: json!({"level": "warn"}),
         enabled: true,
         order_in_pipeline: 1,
+        id: None,
     };
 
     let tracing_plugin = PipelinePluginConfigDto {
@@ -880,6 +1111,7 @@ async fn test_update_pipeline_with_logging_and_tracing() {
         }),
         enabled: true,
         order_in_pipeline: 2,
+        id: None,
     };
 
     let update_req = UpdatePipelineRequestDto {
@@ -888,6 +1120,7 @@ async fn test_update_pipeline_with_logging_and_tracing() {
         description: Some("Updated with logging and tracing".to_string()),
         plugins: Some(vec![logging_plugin, tracing_plugin]),
         enabled: None,
+        expected_version: Some(created_pipeline.version),
     };
 
     let update_response = server
@@ -930,6 +1163,367 @@ async fn test_update_pipeline_with_logging_and_tracing() {
     );
 }
 
+#[tokio::test]
+async fn test_patch_tracing_plugin_api_key_only() {
+    let (server, pool, _container) = setup_test_environment().await;
+    let provider =
+        create_test_provider(&server, "openai-for-plugin-patch", ProviderType::OpenAI).await;
+    let model_def =
+        create_test_model_definition(&server, provider.id, "gpt-4o-for-plugin-patch").await;
+
+    let pipeline_req = CreatePipelineRequestDto {
+        name: format!("Plugin-Patch-Pipeline-{}", Uuid::new_v4()),
+        pipeline_type: "chat".to_string(),
+        description: None,
+        plugins: vec![
+            PipelinePluginConfigDto {
+                plugin_type: PluginType::ModelRouter,
+                config_data: json!({
+                    "strategy": "simple",
+                    "models": [{ "key": model_def.key.clone(), "priority": 0 }]
+                }),
+                enabled: true,
+                order_in_pipeline: 1,
+                id: None,
+            },
+            PipelinePluginConfigDto {
+                plugin_type: PluginType::Tracing,
+                config_data: json!({
+                    "endpoint": "http://trace.example.com/v1/traces",
+                    "api_key": { "type": "literal", "value": "old-trace-key" }
+                }),
+                enabled: true,
+                order_in_pipeline: 2,
+                id: None,
+            },
+        ],
+        enabled: true,
+        environment: None,
+    };
+    let response = server
+        .post("/api/v1/management/pipelines")
+        .json(&pipeline_req)
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let created_pipeline: PipelineResponseDto = response.json();
+    let plugin = |pipeline: &PipelineResponseDto, plugin_type: PluginType| {
+        pipeline
+            .plugins
+            .iter()
+            .find(|p| p.plugin_type == plugin_type)
+            .cloned()
+            .unwrap()
+    };
+    let tracing_plugin = plugin(&created_pipeline, PluginType::Tracing);
+    let plugin_url = format!(
+        "/api/v1/management/pipelines/{}/plugins/{}",
+        created_pipeline.id,
+        tracing_plugin.id.unwrap()
+    );
+
+    let response = server
+        .patch(&plugin_url)
+        .json(&json!({
+            "config_data": { "api_key": { "type": "literal", "value": "rotated-trace-key" } },
+            "expected_version": created_pipeline.version
+        }))
+        .await;
+    response.assert_status_ok();
+    let updated_pipeline: PipelineResponseDto = response.json();
+
+    assert_eq!(updated_pipeline.version, created_pipeline.version + 1);
+    assert!(updated_pipeline.updated_at > created_pipeline.updated_at);
+    assert_eq!(
+        plugin(&updated_pipeline, PluginType::ModelRouter),
+        plugin(&created_pipeline, PluginType::ModelRouter)
+    );
+    let tracing_plugin = plugin(&updated_pipeline, PluginType::Tracing);
+    let tracing_config: TracingConfigDto =
+        serde_json::from_value(tracing_plugin.config_data).unwrap();
+    assert_eq!(
+        tracing_config.endpoint,
+        "http://trace.example.com/v1/traces"
+    );
+    assert_eq!(
+        tracing_config.api_key,
+        SecretObject::literal("rotated-trace-key".to_string())
+    );
+
+    // The gateway config built on the next poll carries the rotated key.
+    let (_router, config_provider) = management_api_bundle_without_auth(pool.clone());
+    let live_config = config_provider.fetch_live_config().await.unwrap();
+    let live_pipeline = live_config
+        .pipelines
+        .iter()
+        .find(|p| p.name == created_pipeline.name)
+        .unwrap();
+    assert!(live_pipeline.plugins.iter().any(|p| matches!(
+        p,
+        hub_lib::types::PluginConfig::Tracing { api_key, .. } if api_key == "rotated-trace-key"
+    )));
+
+    // A stale version is rejected.
+    let response = server
+        .patch(&plugin_url)
+        .json(&json!({ "enabled": false, "expected_version": created_pipeline.version }))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_patch_plugin_validates_result() {
+    let (server, _pool, _container) = setup_test_environment().await;
+    let provider =
+        create_test_provider(&server, "openai-for-plugin-check", ProviderType::OpenAI).await;
+    let model_def =
+        create_test_model_definition(&server, provider.id, "gpt-4o-for-plugin-check").await;
+
+    let pipeline_req = CreatePipelineRequestDto {
+        name: format!("Plugin-Validation-Pipeline-{}", Uuid::new_v4()),
+        pipeline_type: "chat".to_string(),
+        description: None,
+        plugins: vec![PipelinePluginConfigDto {
+            plugin_type: PluginType::ModelRouter,
+            config_data: json!({
+                "strategy": "simple",
+                "models": [{ "key": model_def.key.clone(), "priority": 0 }]
+            }),
+            enabled: true,
+            order_in_pipeline: 1,
+            id: None,
+        }],
+        enabled: true,
+        environment: None,
+    };
+    let created_pipeline: PipelineResponseDto = server
+        .post("/api/v1/management/pipelines")
+        .json(&pipeline_req)
+        .await
+        .json();
+    let router_plugin_id = created_pipeline.plugins[0].id.unwrap();
+
+    let response = server
+        .patch(&format!(
+            "/api/v1/management/pipelines/{}/plugins/{}",
+            created_pipeline.id, router_plugin_id
+        ))
+        .json(&json!({
+            "config_data": { "models": [{ "key": "no-such-model", "priority": 0 }] },
+            "expected_version": created_pipeline.version
+        }))
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = server
+        .patch(&format!(
+            "/api/v1/management/pipelines/{}/plugins/{}",
+            created_pipeline.id,
+            Uuid::new_v4()
+        ))
+        .json(&json!({ "enabled": false, "expected_version": created_pipeline.version }))
+        .await;
+    response.assert_status_not_found();
+
+    // Neither failed request changed the pipeline.
+    let pipeline: PipelineResponseDto = server
+        .get(&format!(
+            "/api/v1/management/pipelines/{}",
+            created_pipeline.id
+        ))
+        .await
+        .json();
+    assert_eq!(pipeline, created_pipeline);
+}
+
+async fn create_test_pipeline(
+    server: &TestServer,
+    name: &str,
+    environment: Option<&str>,
+    plugins: Vec<PipelinePluginConfigDto>,
+) -> PipelineResponseDto {
+    let pipeline_req = CreatePipelineRequestDto {
+        name: name.to_string(),
+        pipeline_type: "chat".to_string(),
+        description: environment.map(|e| format!("{e} pipeline")),
+        plugins,
+        enabled: true,
+        environment: environment.map(str::to_string),
+    };
+    let response = server
+        .post("/api/v1/management/pipelines")
+        .json(&pipeline_req)
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    response.json()
+}
+
+#[tokio::test]
+async fn test_gateway_config_filters_pipelines_by_environment() {
+    let (server, pool, _container) = setup_test_environment().await;
+    create_test_pipeline(&server, "default", None, vec![]).await;
+    create_test_pipeline(&server, "default", Some("prod"), vec![]).await;
+    create_test_pipeline(&server, "canary", Some("staging"), vec![]).await;
+    create_test_pipeline(&server, "shared", None, vec![]).await;
+    // The same name can only exist once per environment.
+    server
+        .post("/api/v1/management/pipelines")
+        .json(&json!({"name": "default", "pipeline_type": "chat", "environment": "prod"}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    let config_provider = |environment: Option<&str>| {
+        ConfigProviderService::new(
+            Arc::new(ProviderService::new(pool.clone())),
+            Arc::new(ModelDefinitionService::new(pool.clone())),
+            Arc::new(PipelineService::new(
+                Arc::new(PipelineRepository::new(pool.clone())),
+                Arc::new(ModelDefinitionRepository::new(pool.clone())),
+            )),
+        )
+        .with_environment(environment.map(str::to_string))
+    };
+    let mut loaded = Vec::new();
+    for environment in [Some("prod"), Some("staging"), None] {
+        let config = config_provider(environment)
+            .fetch_live_config()
+            .await
+            .unwrap();
+        let mut names: Vec<String> = config.pipelines.into_iter().map(|p| p.name).collect();
+        names.sort();
+        loaded.push(names);
+    }
+
+    assert_eq!(loaded[0], ["default", "shared"]);
+    assert_eq!(loaded[1], ["canary", "default", "shared"]);
+    assert_eq!(loaded[2], ["default", "shared"]);
+
+    let prod_default: PipelineResponseDto = server
+        .get("/api/v1/management/pipelines/name/default?environment=prod")
+        .await
+        .json();
+    assert_eq!(prod_default.environment.as_deref(), Some("prod"));
+    let untagged_default: PipelineResponseDto = server
+        .get("/api/v1/management/pipelines/name/default")
+        .await
+        .json();
+    assert_eq!(untagged_default.environment, None);
+}
+
+#[tokio::test]
+async fn test_promote_pipeline_overwrites_target_plugins() {
+    let (server, _pool, _container) = setup_test_environment().await;
+    let provider = create_test_provider(&server, "promote", ProviderType::OpenAI).await;
+    let model_def = create_test_model_definition(&server, provider.id, "gpt-4o-promote").await;
+    let staging = create_test_pipeline(
+        &server,
+        "checkout",
+        Some("staging"),
+        vec![
+            PipelinePluginConfigDto {
+                plugin_type: PluginType::ModelRouter,
+                config_data: json!({"models": [{"key": model_def.key, "priority": 0}]}),
+                enabled: true,
+                order_in_pipeline: 1,
+                id: None,
+            },
+            PipelinePluginConfigDto {
+                plugin_type: PluginType::Logging,
+                config_data: json!({"level": "debug"}),
+                enabled: false,
+                order_in_pipeline: 2,
+                id: None,
+            },
+        ],
+    )
+    .await;
+    let prod = create_test_pipeline(
+        &server,
+        "checkout",
+        Some("prod"),
+        vec![PipelinePluginConfigDto {
+            plugin_type: PluginType::Tracing,
+            config_data: json!({
+                "endpoint": "http://trace.example.com/v1/traces",
+                "api_key": {"type": "literal", "value": "prod-key"}
+            }),
+            enabled: true,
+            order_in_pipeline: 1,
+            id: None,
+        }],
+    )
+    .await;
+    let promote_url = format!("/api/v1/management/pipelines/{}/promote", staging.id);
+
+    let response = server
+        .post(&promote_url)
+        .json(&json!({"target_environment": "prod"}))
+        .await;
+    response.assert_status_ok();
+    let promoted: PipelineResponseDto = response.json();
+    assert_eq!(promoted.id, prod.id);
+    assert_eq!(promoted.environment.as_deref(), Some("prod"));
+    assert_eq!(promoted.description, staging.description);
+    assert_eq!(promoted.version, prod.version + 1);
+    let plugins = |pipeline: &PipelineResponseDto| {
+        pipeline
+            .plugins
+            .iter()
+            .map(|p| {
+                let config_data = p.config_data.clone();
+                (
+                    p.plugin_type.clone(),
+                    config_data,
+                    p.enabled,
+                    p.order_in_pipeline,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(plugins(&promoted), plugins(&staging));
+    let fetched: PipelineResponseDto = server
+        .get(&format!("/api/v1/management/pipelines/{}", prod.id))
+        .await
+        .json();
+    assert_eq!(fetched, promoted);
+    // The source pipeline is left untouched.
+    let source: PipelineResponseDto = server
+        .get(&format!("/api/v1/management/pipelines/{}", staging.id))
+        .await
+        .json();
+    assert_eq!(source, staging);
+
+    // Promoting to an environment without the pipeline creates it there.
+    let response = server
+        .post(&promote_url)
+        .json(&json!({"target_environment": "eu-prod"}))
+        .await;
+    response.assert_status_ok();
+    let created: PipelineResponseDto = response.json();
+    assert!(created.id != staging.id && created.id != prod.id);
+    assert_eq!(created.name, "checkout");
+    assert_eq!(created.environment.as_deref(), Some("eu-prod"));
+    assert_eq!(plugins(&created), plugins(&staging));
+
+    server
+        .post(&promote_url)
+        .json(&json!({"target_environment": "staging"}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .post(&promote_url)
+        .json(&json!({"target_environment": "not an env"}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .post(&format!(
+            "/api/v1/management/pipelines/{}/promote",
+            Uuid::new_v4()
+        ))
+        .json(&json!({"target_environment": "prod"}))
+        .await
+        .assert_status_not_found();
+}
+
 /*
 Further considerations for tests:
 - Test with different plugin types if more are added.
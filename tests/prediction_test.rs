@@ -0,0 +1,139 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use common::{model, model_router, openai, params, pipeline, provider};
+use hub_lib::types::{GatewayConfig, ModelConfig, Provider, ProviderType};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn prediction() -> Value {
+    json!({
+        "type": "content",
+        "content": "fn main() {\n    println!(\"hello\");\n}\n"
+    })
+}
+
+fn hub(openai_server: &MockServer, anthropic: &MockServer) -> Router {
+    common::hub(GatewayConfig {
+        providers: vec![
+            openai("openai", openai_server),
+            Provider {
+                params: params(&[("base_url", &anthropic.uri())]),
+                ..provider("anthropic", ProviderType::Anthropic)
+            },
+        ],
+        models: vec![
+            model("gpt-4o", "openai"),
+            model("claude-sonnet-4", "anthropic"),
+            ModelConfig {
+                params: params(&[("ignore_unsupported_params", "true")]),
+                ..model("claude-lenient", "anthropic")
+            },
+        ],
+        pipelines: vec![pipeline(
+            "default",
+            vec![model_router(&[
+                "gpt-4o",
+                "claude-sonnet-4",
+                "claude-lenient",
+            ])],
+        )],
+        ..Default::default()
+    })
+}
+
+async fn chat(app: &Router, model: &str) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": model,
+                        "messages": [{"role": "user", "content": "Rename the greeting."}],
+                        "prediction": prediction()
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_prediction_reaches_openai_and_usage_details_come_back() {
+    let openai = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"prediction": prediction()})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "fn main() {}"},
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": 20,
+                "completion_tokens": 12,
+                "total_tokens": 32,
+                "completion_tokens_details": {
+                    "accepted_prediction_tokens": 9,
+                    "rejected_prediction_tokens": 2
+                }
+            }
+        })))
+        .expect(1)
+        .mount(&openai)
+        .await;
+    let anthropic = MockServer::start().await;
+
+    let (status, body) = chat(&hub(&openai, &anthropic), "gpt-4o").await;
+    assert_eq!(status, StatusCode::OK);
+    let details = &body["usage"]["completion_tokens_details"];
+    assert_eq!(details["accepted_prediction_tokens"], 9);
+    assert_eq!(details["rejected_prediction_tokens"], 2);
+}
+
+#[tokio::test]
+async fn test_prediction_is_rejected_for_anthropic_unless_lenient() {
+    let openai = MockServer::start().await;
+    let anthropic = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_1",
+            "model": "claude-sonnet-4",
+            "content": [{"type": "text", "text": "fn main() {}"}],
+            "usage": {"input_tokens": 20, "output_tokens": 12}
+        })))
+        .expect(1)
+        .mount(&anthropic)
+        .await;
+    let app = hub(&openai, &anthropic);
+
+    let (status, body) = chat(&app, "claude-sonnet-4").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["param"], "prediction");
+
+    // With `ignore_unsupported_params` the field is dropped instead.
+    let (status, _) = chat(&app, "claude-lenient").await;
+    assert_eq!(status, StatusCode::OK);
+    let sent: Value =
+        serde_json::from_slice(&anthropic.received_requests().await.unwrap()[0].body).unwrap();
+    assert!(sent.get("prediction").is_none());
+}
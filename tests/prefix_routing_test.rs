@@ -0,0 +1,176 @@
+mod common;
+
+use common::{model, openai, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::Body;
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::pipelines::pipeline::HEADER_MODEL_KEY;
+use hub_lib::types::{ModelConfig, PluginConfig};
+use serde_json::json;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// An OpenAI-compatible upstream that only answers requests for `model`.
+async fn upstream(model: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"model": model})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "hi"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// A `gpt-4o` model on `provider`.
+fn gpt_4o(key: &str, provider: &str) -> ModelConfig {
+    ModelConfig {
+        r#type: "gpt-4o".to_string(),
+        ..model(key, provider)
+    }
+}
+
+/// Providers `primary` and `groq` behind the router, plus `other` which no router model uses.
+fn hub(
+    primary: &MockServer,
+    groq: &MockServer,
+    other: &MockServer,
+    allow_dynamic_models: bool,
+) -> Router {
+    unsafe {
+        std::env::set_var("PREFIX_ROUTING", "true");
+    }
+    pipeline_router(
+        &pipeline(
+            "default",
+            vec![PluginConfig::ModelRouter {
+                models: vec!["gpt-4o".to_string(), "groq-gpt-4o".to_string()],
+                allow_dynamic_models,
+                adaptive: None,
+                race: None,
+            }],
+        ),
+        &[
+            openai("primary", primary),
+            openai("groq", groq),
+            openai("other", other),
+        ],
+        &[
+            gpt_4o("gpt-4o", "primary"),
+            gpt_4o("groq-gpt-4o", "groq"),
+            gpt_4o("other-gpt-4o", "other"),
+        ],
+    )
+}
+
+async fn chat(app: Router, model: &str) -> hub_lib::axum::response::Response {
+    app.oneshot(
+        Request::builder()
+            .uri("/chat/completions")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "model": model,
+                    "messages": [{"role": "user", "content": "hello"}]
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+}
+
+fn model_key(response: &hub_lib::axum::response::Response) -> &str {
+    response
+        .headers()
+        .get(HEADER_MODEL_KEY)
+        .unwrap()
+        .to_str()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_prefix_matching_provider_key_creates_implicit_model() {
+    let primary = MockServer::start().await;
+    let groq = upstream("llama-3.3-70b").await;
+    let other = MockServer::start().await;
+
+    let response = chat(hub(&primary, &groq, &other, true), "groq/llama-3.3-70b").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(model_key(&response), "groq/llama-3.3-70b");
+}
+
+#[tokio::test]
+async fn test_prefix_matching_provider_type_uses_first_router_provider() {
+    let primary = upstream("gpt-4o-mini").await;
+    let groq = MockServer::start().await;
+    let other = MockServer::start().await;
+
+    let response = chat(hub(&primary, &groq, &other, true), "openai/gpt-4o-mini").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(model_key(&response), "primary/gpt-4o-mini");
+}
+
+#[tokio::test]
+async fn test_configured_models_take_precedence() {
+    let primary = upstream("gpt-4o").await;
+    let groq = MockServer::start().await;
+    let other = MockServer::start().await;
+
+    let response = chat(hub(&primary, &groq, &other, true), "gpt-4o").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(model_key(&response), "gpt-4o");
+}
+
+#[tokio::test]
+async fn test_providers_outside_the_router_are_not_reachable() {
+    let primary = MockServer::start().await;
+    let groq = MockServer::start().await;
+    let other = MockServer::start().await;
+
+    let response = chat(hub(&primary, &groq, &other, true), "other/gpt-4o-mini").await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_pipeline_must_allow_dynamic_models() {
+    let primary = MockServer::start().await;
+    let groq = MockServer::start().await;
+    let other = MockServer::start().await;
+
+    let response = chat(hub(&primary, &groq, &other, false), "groq/llama-3.3-70b").await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_malformed_prefixed_names_are_not_routed() {
+    let primary = MockServer::start().await;
+    let groq = MockServer::start().await;
+    let other = MockServer::start().await;
+    let app = hub(&primary, &groq, &other, true);
+
+    for name in ["groq/", "/llama-3.3-70b", "unknown/llama-3.3-70b"] {
+        let response = chat(app.clone(), name).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{name}");
+    }
+}
@@ -12,12 +12,13 @@ use hub_lib::management::{
     api::routes::provider_routes,
     db::models::Provider,
     dto::{
-        AnthropicProviderConfig, AzureProviderConfig, BedrockProviderConfig, CreateProviderRequest,
-        OpenAIProviderConfig, ProviderConfig, ProviderResponse, ProviderType, SecretObject,
+        AnthropicProviderConfig, AzureAuthType, AzureProviderConfig, BedrockProviderConfig,
+        CreateProviderRequest, MockMode, MockProviderConfig, OpenAIProviderConfig, ProviderConfig,
+        ProviderDependentsResponse, ProviderResponse, ProviderType, SecretObject,
         UpdateProviderRequest, VertexAIProviderConfig,
     },
     errors::ApiError,
-    management_api_bundle,
+    management_api_bundle_without_auth,
 };
 use serde_json::json;
 use sqlx::postgres::PgPoolOptions;
@@ -40,47 +41,89 @@ struct ProviderTestData {
 fn get_all_provider_test_data() -> Vec<ProviderTestData> {
     vec![
         ProviderTestData {
-            name: "Test OpenAI Provider".to_string(),
+            name: "Test-OpenAI-Provider".to_string(),
             provider_type: ProviderType::OpenAI,
             config: ProviderConfig::OpenAI(OpenAIProviderConfig {
                 api_key: SecretObject::literal("test_openai_key".to_string()),
+                api_key_secondary: None,
                 organization_id: Some("test_org".to_string()),
                 base_url: None,
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
             }),
             updated_config: ProviderConfig::OpenAI(OpenAIProviderConfig {
                 api_key: SecretObject::literal("updated_openai_key".to_string()),
+                api_key_secondary: None,
                 organization_id: Some("updated_org".to_string()),
                 base_url: None,
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
             }),
         },
         ProviderTestData {
-            name: "Test Azure Provider".to_string(),
+            name: "Test-Azure-Provider".to_string(),
             provider_type: ProviderType::Azure,
             config: ProviderConfig::Azure(AzureProviderConfig {
-                api_key: SecretObject::literal("test_azure_key".to_string()),
+                api_key: Some(SecretObject::literal("test_azure_key".to_string())),
                 resource_name: "test_resource".to_string(),
                 api_version: "2023-05-15".to_string(),
                 base_url: None,
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                auth_type: None,
+                tenant_id: None,
+                client_id: None,
+                client_secret: None,
+                authority_host: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
             }),
             updated_config: ProviderConfig::Azure(AzureProviderConfig {
-                api_key: SecretObject::literal("updated_azure_key".to_string()),
+                api_key: Some(SecretObject::literal("updated_azure_key".to_string())),
                 resource_name: "updated_resource".to_string(),
                 api_version: "2024-02-01".to_string(),
                 base_url: None,
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                auth_type: None,
+                tenant_id: None,
+                client_id: None,
+                client_secret: None,
+                authority_host: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
             }),
         },
         ProviderTestData {
-            name: "Test Anthropic Provider".to_string(),
+            name: "Test-Anthropic-Provider".to_string(),
             provider_type: ProviderType::Anthropic,
             config: ProviderConfig::Anthropic(AnthropicProviderConfig {
                 api_key: SecretObject::literal("test_anthropic_key".to_string()),
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
             }),
             updated_config: ProviderConfig::Anthropic(AnthropicProviderConfig {
                 api_key: SecretObject::literal("updated_anthropic_key".to_string()),
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
             }),
         },
         ProviderTestData {
-            name: "Test Bedrock Provider".to_string(),
+            name: "Test-Bedrock-Provider".to_string(),
             provider_type: ProviderType::Bedrock,
             config: ProviderConfig::Bedrock(BedrockProviderConfig {
                 aws_access_key_id: Some(SecretObject::literal("test_access_key".to_string())),
@@ -89,6 +132,11 @@ fn get_all_provider_test_data() -> Vec<ProviderTestData> {
                 region: "us-east-1".to_string(),
                 use_iam_role: Some(false),
                 inference_profile_id: None,
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
             }),
             updated_config: ProviderConfig::Bedrock(BedrockProviderConfig {
                 aws_access_key_id: Some(SecretObject::literal("updated_access_key".to_string())),
@@ -99,22 +147,48 @@ fn get_all_provider_test_data() -> Vec<ProviderTestData> {
                 region: "us-west-2".to_string(),
                 use_iam_role: Some(false),
                 inference_profile_id: None,
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
             }),
         },
         ProviderTestData {
-            name: "Test VertexAI Provider".to_string(),
+            name: "Test-VertexAI-Provider".to_string(),
             provider_type: ProviderType::VertexAI,
             config: ProviderConfig::VertexAI(VertexAIProviderConfig {
                 project_id: Some("test-project-123".to_string()),
                 location: Some("us-central1".to_string()),
                 credentials_path: Some("/path/to/service-account.json".to_string()),
                 api_key: None,
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
             }),
             updated_config: ProviderConfig::VertexAI(VertexAIProviderConfig {
                 project_id: Some("updated-project-456".to_string()),
                 location: Some("europe-west1".to_string()),
                 credentials_path: None,
                 api_key: Some(SecretObject::literal("updated_vertex_api_key".to_string())),
+                proxy_url: None,
+                no_proxy: None,
+                tls: None,
+                maintenance_windows: vec![],
+                allowed_model_prefixes: None,
+            }),
+        },
+        ProviderTestData {
+            name: "Test-Mock-Provider".to_string(),
+            provider_type: ProviderType::Mock,
+            config: ProviderConfig::Mock(MockProviderConfig::default()),
+            updated_config: ProviderConfig::Mock(MockProviderConfig {
+                mode: Some(MockMode::Fixed),
+                response: Some("canned reply".to_string()),
+                fail_every: Some(3),
+                ..Default::default()
             }),
         },
     ]
@@ -153,7 +227,7 @@ async fn setup_test_environment() -> (TestServer, PgPool, impl Drop) {
         .await
         .expect("Failed to run migrations on test DB");
 
-    let (router, _config_provider) = management_api_bundle(pool.clone());
+    let (router, _config_provider) = management_api_bundle_without_auth(pool.clone());
     let client = TestServer::new(router).expect("Failed to create TestServer");
 
     (client, pool, container)
@@ -164,12 +238,18 @@ async fn test_create_provider_success() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let request_payload = CreateProviderRequest {
-        name: "Test OpenAI Provider".to_string(),
+        name: "Test-OpenAI-Provider".to_string(),
         provider_type: ProviderType::OpenAI,
         config: ProviderConfig::OpenAI(OpenAIProviderConfig {
             api_key: SecretObject::literal("test_openai_key".to_string()),
+            api_key_secondary: None,
             organization_id: Some("test_org".to_string()),
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -207,13 +287,18 @@ async fn test_create_vertexai_provider_success() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let request_payload = CreateProviderRequest {
-        name: "Test VertexAI Provider".to_string(),
+        name: "Test-VertexAI-Provider".to_string(),
         provider_type: ProviderType::VertexAI,
         config: ProviderConfig::VertexAI(VertexAIProviderConfig {
             project_id: Some("test-project-123".to_string()),
             location: Some("us-central1".to_string()),
             credentials_path: Some("/path/to/service-account.json".to_string()),
             api_key: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -251,13 +336,18 @@ async fn test_create_vertexai_provider_with_api_key() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let request_payload = CreateProviderRequest {
-        name: "Test VertexAI Provider with API Key".to_string(),
+        name: "Test-VertexAI-Provider-with-API-Key".to_string(),
         provider_type: ProviderType::VertexAI,
         config: ProviderConfig::VertexAI(VertexAIProviderConfig {
             project_id: Some("test-project-456".to_string()),
             location: Some("europe-west1".to_string()),
             credentials_path: None,
             api_key: Some(SecretObject::literal("test-vertex-api-key".to_string())),
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(false),
     };
@@ -285,12 +375,18 @@ async fn test_create_provider_duplicate_name() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let initial_payload = CreateProviderRequest {
-        name: "Unique Name Provider".to_string(),
+        name: "Unique-Name-Provider".to_string(),
         provider_type: ProviderType::OpenAI,
         config: ProviderConfig::OpenAI(OpenAIProviderConfig {
             api_key: SecretObject::literal("openai_key_1".to_string()),
+            api_key_secondary: None,
             organization_id: None,
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -306,13 +402,23 @@ async fn test_create_provider_duplicate_name() {
     );
 
     let duplicate_payload = CreateProviderRequest {
-        name: "Unique Name Provider".to_string(),
+        name: "Unique-Name-Provider".to_string(),
         provider_type: ProviderType::Azure,
         config: ProviderConfig::Azure(AzureProviderConfig {
-            api_key: SecretObject::literal("azure_key_2".to_string()),
+            api_key: Some(SecretObject::literal("azure_key_2".to_string())),
             resource_name: "res2".to_string(),
             api_version: "v2".to_string(),
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            auth_type: None,
+            tenant_id: None,
+            client_id: None,
+            client_secret: None,
+            authority_host: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(false),
     };
@@ -327,7 +433,31 @@ async fn test_create_provider_duplicate_name() {
     let error_response: serde_json::Value = response2.json::<serde_json::Value>();
     assert_eq!(
         error_response["error"],
-        "Provider with name 'Unique Name Provider' already exists."
+        "Provider with name 'Unique-Name-Provider' already exists."
+    );
+
+    // Names are unique ignoring case and surrounding whitespace.
+    let mut payload = duplicate_payload;
+    payload.name = " unique-name-provider ".to_string();
+    let response3 = client
+        .post("/api/v1/management/providers")
+        .json(&payload)
+        .await;
+    assert_eq!(response3.status_code(), axum::http::StatusCode::CONFLICT);
+    assert_eq!(
+        response3.json::<serde_json::Value>()["error"],
+        "Provider with name 'Unique-Name-Provider' already exists."
+    );
+
+    payload.name = "Another Provider".to_string();
+    let response4 = client
+        .post("/api/v1/management/providers")
+        .json(&payload)
+        .await;
+    assert_eq!(response4.status_code(), axum::http::StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response4.json::<serde_json::Value>()["error"],
+        "Provider name 'Another Provider' contains ' '; use letters, digits, '.', '_' or '-'"
     );
 }
 
@@ -336,7 +466,7 @@ async fn test_get_provider_success() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let request_payload = CreateProviderRequest {
-        name: "Provider to GET".to_string(),
+        name: "Provider-to-GET".to_string(),
         provider_type: ProviderType::Bedrock,
         config: ProviderConfig::Bedrock(BedrockProviderConfig {
             aws_access_key_id: Some(SecretObject::literal("bedrock_access_key".to_string())),
@@ -345,6 +475,11 @@ async fn test_get_provider_success() {
             region: "us-east-1".to_string(),
             use_iam_role: Some(false),
             inference_profile_id: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -422,12 +557,18 @@ async fn test_list_providers_multiple() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let provider1_payload = CreateProviderRequest {
-        name: "List Provider B - OpenAI".to_string(),
+        name: "List-Provider-B-OpenAI".to_string(),
         provider_type: ProviderType::OpenAI,
         config: ProviderConfig::OpenAI(OpenAIProviderConfig {
             api_key: SecretObject::literal("key1".to_string()),
+            api_key_secondary: None,
             organization_id: None,
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -443,13 +584,23 @@ async fn test_list_providers_multiple() {
     let provider1_resp: ProviderResponse = res1.json::<ProviderResponse>();
 
     let provider2_payload = CreateProviderRequest {
-        name: "List Provider A - Azure".to_string(),
+        name: "List-Provider-A-Azure".to_string(),
         provider_type: ProviderType::Azure,
         config: ProviderConfig::Azure(AzureProviderConfig {
-            api_key: SecretObject::literal("key2".to_string()),
+            api_key: Some(SecretObject::literal("key2".to_string())),
             resource_name: "res2".to_string(),
             api_version: "v2".to_string(),
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            auth_type: None,
+            tenant_id: None,
+            client_id: None,
+            client_secret: None,
+            authority_host: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(false),
     };
@@ -491,12 +642,18 @@ async fn test_update_provider_success() {
     let (client, pool, _container) = setup_test_environment().await;
 
     let initial_payload = CreateProviderRequest {
-        name: "Initial Provider Name".to_string(),
+        name: "Initial-Provider-Name".to_string(),
         provider_type: ProviderType::OpenAI,
         config: ProviderConfig::OpenAI(OpenAIProviderConfig {
             api_key: SecretObject::literal("initial_openai_key".to_string()),
+            api_key_secondary: None,
             organization_id: Some("org_initial".to_string()),
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -511,11 +668,17 @@ async fn test_update_provider_success() {
     );
     let created_provider: ProviderResponse = create_response.json::<ProviderResponse>();
 
-    let updated_name = "Updated Provider Name".to_string();
+    let updated_name = "Updated-Provider-Name".to_string();
     let updated_config = ProviderConfig::OpenAI(OpenAIProviderConfig {
         api_key: SecretObject::literal("updated_openai_key".to_string()),
+        api_key_secondary: None,
         organization_id: Some("org_updated".to_string()),
         base_url: None,
+        proxy_url: None,
+        no_proxy: None,
+        tls: None,
+        maintenance_windows: vec![],
+        allowed_model_prefixes: None,
     });
     let updated_enabled = false;
 
@@ -523,6 +686,7 @@ async fn test_update_provider_success() {
         name: Some(updated_name.clone()),
         config: Some(updated_config.clone()),
         enabled: Some(updated_enabled),
+        expected_version: Some(created_provider.version),
     };
 
     let update_response = client
@@ -548,6 +712,10 @@ async fn test_update_provider_success() {
     );
     assert_eq!(updated_provider_response.config, updated_config);
     assert_eq!(updated_provider_response.enabled, updated_enabled);
+    assert_eq!(
+        updated_provider_response.version,
+        created_provider.version + 1
+    );
 
     assert_eq!(
         updated_provider_response.created_at,
@@ -564,7 +732,7 @@ async fn test_update_provider_success() {
     let db_provider = sqlx::query_as!(
         Provider,
         r#"
-            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at
+            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at, version
             FROM hub_llmgateway_providers
             WHERE id = $1
             "#,
@@ -606,19 +774,105 @@ async fn test_update_provider_success() {
                 .expect("Failed to deserialize VertexAI config from DB");
             ProviderConfig::VertexAI(config)
         }
+        ProviderType::Mock => {
+            let config: MockProviderConfig = serde_json::from_value(db_provider.config_details)
+                .expect("Failed to deserialize mock config from DB");
+            ProviderConfig::Mock(config)
+        }
     };
     assert_eq!(db_config, updated_config);
 }
 
+#[tokio::test]
+async fn test_update_provider_rejects_stale_version() {
+    let (client, _pool, _container) = setup_test_environment().await;
+
+    let provider_payload = CreateProviderRequest {
+        name: "Versioned-Provider".to_string(),
+        provider_type: ProviderType::Anthropic,
+        config: ProviderConfig::Anthropic(AnthropicProviderConfig {
+            api_key: SecretObject::literal("versioned_key".to_string()),
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
+        }),
+        enabled: Some(true),
+    };
+    let created_provider: ProviderResponse = client
+        .post("/api/v1/management/providers")
+        .json(&provider_payload)
+        .await
+        .json::<ProviderResponse>();
+    assert_eq!(created_provider.version, 1);
+    let provider_url = format!("/api/v1/management/providers/{}", created_provider.id);
+    let rename = |name: &str| UpdateProviderRequest {
+        name: Some(name.to_string()),
+        config: None,
+        enabled: None,
+        expected_version: None,
+    };
+
+    let first_write = client
+        .put(&provider_url)
+        .add_header(
+            axum::http::header::IF_MATCH,
+            axum::http::HeaderValue::from(created_provider.version),
+        )
+        .json(&rename("Renamed-By-Operator-A"))
+        .await;
+    assert_eq!(first_write.status_code(), axum::http::StatusCode::OK);
+    assert_eq!(first_write.json::<ProviderResponse>().version, 2);
+
+    // A second operator still holding version 1 must not overwrite the first write.
+    let stale_write = client
+        .put(&provider_url)
+        .add_header(
+            axum::http::header::IF_MATCH,
+            axum::http::HeaderValue::from(created_provider.version),
+        )
+        .json(&rename("Renamed-By-Operator-B"))
+        .await;
+    assert_eq!(stale_write.status_code(), axum::http::StatusCode::CONFLICT);
+    let error_response: serde_json::Value = stale_write.json::<serde_json::Value>();
+    assert_eq!(error_response["current_version"], 2);
+
+    let unversioned_write = client
+        .put(&provider_url)
+        .json(&rename("Renamed-Blindly"))
+        .await;
+    assert_eq!(
+        unversioned_write.status_code(),
+        axum::http::StatusCode::PRECONDITION_REQUIRED
+    );
+
+    let body_versioned_write = client
+        .put(&provider_url)
+        .json(&UpdateProviderRequest {
+            expected_version: Some(2),
+            ..rename("Renamed-By-Operator-B")
+        })
+        .await;
+    assert_eq!(
+        body_versioned_write.status_code(),
+        axum::http::StatusCode::OK
+    );
+    let provider_response: ProviderResponse = body_versioned_write.json::<ProviderResponse>();
+    assert_eq!(provider_response.name, "Renamed-By-Operator-B");
+    assert_eq!(provider_response.version, 3);
+}
+
 #[tokio::test]
 async fn test_update_provider_not_found() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let non_existent_uuid = Uuid::new_v4();
     let update_payload = UpdateProviderRequest {
-        name: Some("New Name for NonExistent".to_string()),
+        name: Some("New-Name-for-NonExistent".to_string()),
         config: None,
         enabled: Some(true),
+        expected_version: Some(1),
     };
 
     let response = client
@@ -641,14 +895,20 @@ async fn test_update_provider_not_found() {
 async fn test_update_provider_duplicate_name_conflict() {
     let (client, _pool, _container) = setup_test_environment().await;
 
-    let provider1_name = "Name A - Original".to_string();
+    let provider1_name = "Name-A-Original".to_string();
     let provider1_payload = CreateProviderRequest {
         name: provider1_name.clone(),
         provider_type: ProviderType::OpenAI,
         config: ProviderConfig::OpenAI(OpenAIProviderConfig {
             api_key: SecretObject::literal("key_A".to_string()),
+            api_key_secondary: None,
             organization_id: None,
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -663,13 +923,23 @@ async fn test_update_provider_duplicate_name_conflict() {
     );
 
     let provider2_payload = CreateProviderRequest {
-        name: "Name B - To Be Updated".to_string(),
+        name: "Name-B-To-Be-Updated".to_string(),
         provider_type: ProviderType::Azure,
         config: ProviderConfig::Azure(AzureProviderConfig {
-            api_key: SecretObject::literal("key_B".to_string()),
+            api_key: Some(SecretObject::literal("key_B".to_string())),
             resource_name: "resB".to_string(),
             api_version: "vB".to_string(),
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            auth_type: None,
+            tenant_id: None,
+            client_id: None,
+            client_secret: None,
+            authority_host: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -688,6 +958,7 @@ async fn test_update_provider_duplicate_name_conflict() {
         name: Some(provider1_name.clone()),
         config: None,
         enabled: None,
+        expected_version: Some(provider2_created.version),
     };
 
     let update_conflict_response = client
@@ -714,13 +985,23 @@ async fn test_delete_provider_success() {
     let (client, pool, _container) = setup_test_environment().await;
 
     let provider_payload = CreateProviderRequest {
-        name: "Provider To Delete".to_string(),
+        name: "Provider-To-Delete".to_string(),
         provider_type: ProviderType::Azure,
         config: ProviderConfig::Azure(AzureProviderConfig {
-            api_key: SecretObject::literal("delete_key".to_string()),
+            api_key: Some(SecretObject::literal("delete_key".to_string())),
             resource_name: "del_res".to_string(),
             api_version: "del_v".to_string(),
             base_url: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            auth_type: None,
+            tenant_id: None,
+            client_id: None,
+            client_secret: None,
+            authority_host: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -757,10 +1038,30 @@ async fn test_delete_provider_success() {
         axum::http::StatusCode::NOT_FOUND
     );
 
+    let soft_deleted: bool = sqlx::query_scalar(
+        "SELECT deleted_at IS NOT NULL FROM hub_llmgateway_providers WHERE id = $1",
+    )
+    .bind(created_provider.id)
+    .fetch_one(&pool)
+    .await
+    .expect("Soft-deleted provider should remain in DB");
+    assert!(soft_deleted, "Provider should be marked as deleted");
+
+    let purge_response = client
+        .delete(&format!(
+            "/api/v1/management/providers/{}?hard=true",
+            created_provider.id
+        ))
+        .await;
+    assert_eq!(
+        purge_response.status_code(),
+        axum::http::StatusCode::NO_CONTENT
+    );
+
     let db_provider_after_delete = sqlx::query_as!(
         Provider,
         r#"
-            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at
+            SELECT id, name, provider_type, config_details, enabled, created_at, updated_at, version
             FROM hub_llmgateway_providers
             WHERE id = $1
             "#,
@@ -771,7 +1072,7 @@ async fn test_delete_provider_success() {
     .expect("DB query failed after delete");
     assert!(
         db_provider_after_delete.is_none(),
-        "Provider should not exist in DB after delete"
+        "Provider should not exist in DB after hard delete"
     );
 }
 
@@ -797,18 +1098,97 @@ async fn test_delete_provider_not_found() {
     )));
 }
 
+#[tokio::test]
+async fn test_restore_provider() {
+    let (client, _pool, _container) = setup_test_environment().await;
+
+    let provider_payload = CreateProviderRequest {
+        name: "Provider-To-Restore".to_string(),
+        provider_type: ProviderType::Anthropic,
+        config: ProviderConfig::Anthropic(AnthropicProviderConfig {
+            api_key: SecretObject::literal("restore_key".to_string()),
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
+        }),
+        enabled: Some(true),
+    };
+    let created_provider: ProviderResponse = client
+        .post("/api/v1/management/providers")
+        .json(&provider_payload)
+        .await
+        .json::<ProviderResponse>();
+    let provider_url = format!("/api/v1/management/providers/{}", created_provider.id);
+    let restore_url = format!("{provider_url}/restore");
+
+    let restore_live = client.post(&restore_url).await;
+    assert_eq!(
+        restore_live.status_code(),
+        axum::http::StatusCode::NOT_FOUND
+    );
+
+    let delete_response = client.delete(&provider_url).await;
+    assert_eq!(
+        delete_response.status_code(),
+        axum::http::StatusCode::NO_CONTENT
+    );
+    let providers: Vec<ProviderResponse> = client
+        .get("/api/v1/management/providers")
+        .await
+        .json::<Vec<ProviderResponse>>();
+    assert!(providers.iter().all(|p| p.id != created_provider.id));
+
+    // The name is free while the provider is deleted, so restoring must not clash with a reuse.
+    let reuse_response = client
+        .post("/api/v1/management/providers")
+        .json(&provider_payload)
+        .await;
+    assert_eq!(
+        reuse_response.status_code(),
+        axum::http::StatusCode::CREATED
+    );
+    let reused_provider: ProviderResponse = reuse_response.json::<ProviderResponse>();
+    let conflict_response = client.post(&restore_url).await;
+    assert_eq!(
+        conflict_response.status_code(),
+        axum::http::StatusCode::CONFLICT
+    );
+
+    client
+        .delete(&format!(
+            "/api/v1/management/providers/{}?hard=true",
+            reused_provider.id
+        ))
+        .await;
+    let restore_response = client.post(&restore_url).await;
+    assert_eq!(restore_response.status_code(), axum::http::StatusCode::OK);
+    let restored_provider: ProviderResponse = restore_response.json::<ProviderResponse>();
+    assert_eq!(restored_provider.id, created_provider.id);
+    assert_eq!(restored_provider.config, provider_payload.config);
+
+    let get_response = client.get(&provider_url).await;
+    assert_eq!(get_response.status_code(), axum::http::StatusCode::OK);
+}
+
 #[tokio::test]
 async fn test_vertexai_provider_config_transformation() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let request_payload = CreateProviderRequest {
-        name: "Test VertexAI Config Transform".to_string(),
+        name: "Test-VertexAI-Config-Transform".to_string(),
         provider_type: ProviderType::VertexAI,
         config: ProviderConfig::VertexAI(VertexAIProviderConfig {
             project_id: Some("test-project-transform".to_string()),
             location: Some("us-central1".to_string()),
             credentials_path: Some("/path/to/credentials.json".to_string()),
             api_key: None,
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -846,10 +1226,15 @@ async fn test_create_anthropic_provider_success() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let request_payload = CreateProviderRequest {
-        name: "Test Anthropic Provider".to_string(),
+        name: "Test-Anthropic-Provider".to_string(),
         provider_type: ProviderType::Anthropic,
         config: ProviderConfig::Anthropic(AnthropicProviderConfig {
             api_key: SecretObject::literal("test_anthropic_key".to_string()),
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -886,12 +1271,18 @@ async fn test_create_openai_provider_with_base_url() {
     let (client, _pool, _container) = setup_test_environment().await;
 
     let request_payload = CreateProviderRequest {
-        name: "OpenAI with Base URL".to_string(),
+        name: "OpenAI-with-Base-URL".to_string(),
         provider_type: ProviderType::OpenAI,
         config: ProviderConfig::OpenAI(OpenAIProviderConfig {
             api_key: SecretObject::literal("test_openai_key".to_string()),
+            api_key_secondary: None,
             organization_id: Some("test_org".to_string()),
             base_url: Some("https://eu.api.openai.com/v1".to_string()),
+            proxy_url: None,
+            no_proxy: None,
+            tls: None,
+            maintenance_windows: vec![],
+            allowed_model_prefixes: None,
         }),
         enabled: Some(true),
     };
@@ -917,3 +1308,228 @@ async fn test_create_openai_provider_with_base_url() {
     let fetched: ProviderResponse = get_response.json::<ProviderResponse>();
     assert_eq!(fetched.config, request_payload.config);
 }
+
+#[tokio::test]
+async fn test_create_azure_entra_id_provider() {
+    let (client, _pool, _container) = setup_test_environment().await;
+
+    let response = client
+        .post("/api/v1/management/providers")
+        .json(&json!({
+            "name": "Azure-Entra-ID",
+            "provider_type": "azure",
+            "config": {
+                "resource_name": "my-resource",
+                "api_version": "2024-02-01",
+                "auth_type": "entra_id",
+                "tenant_id": {"type": "literal", "value": "tenant"},
+                "client_id": {"type": "literal", "value": "client"},
+                "client_secret": {"type": "environment", "variable_name": "AZURE_CLIENT_SECRET"}
+            }
+        }))
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::CREATED);
+    let provider_response: ProviderResponse = response.json::<ProviderResponse>();
+    let ProviderConfig::Azure(config) = provider_response.config else {
+        panic!("Expected Azure config");
+    };
+    assert_eq!(config.auth_type, Some(AzureAuthType::EntraId));
+    assert!(config.api_key.is_none());
+
+    // A client secret without a tenant is neither client credentials nor a managed identity.
+    let response = client
+        .post("/api/v1/management/providers")
+        .json(&json!({
+            "name": "Azure-Partial-Credentials",
+            "provider_type": "azure",
+            "config": {
+                "resource_name": "my-resource",
+                "api_version": "2024-02-01",
+                "auth_type": "entra_id",
+                "client_secret": {"type": "literal", "value": "secret"}
+            }
+        }))
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::BAD_REQUEST);
+
+    // API-key auth still needs a key.
+    let response = client
+        .post("/api/v1/management/providers")
+        .json(&json!({
+            "name": "Azure-Without-Key",
+            "provider_type": "azure",
+            "config": {"resource_name": "my-resource", "api_version": "2024-02-01"}
+        }))
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::BAD_REQUEST);
+}
+
+async fn create_mock_provider(client: &TestServer, name: &str) -> ProviderResponse {
+    let response = client
+        .post("/api/v1/management/providers")
+        .json(&json!({"name": name, "provider_type": "mock", "config": {}}))
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::CREATED);
+    response.json::<ProviderResponse>()
+}
+
+async fn create_model(client: &TestServer, provider_id: Uuid, key: &str) {
+    let response = client
+        .post("/api/v1/management/model-definitions")
+        .json(&json!({"key": key, "model_type": "mock-model", "provider_id": provider_id}))
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::CREATED);
+}
+
+async fn create_routing_pipeline(client: &TestServer, name: &str, models: &[&str], enabled: bool) {
+    let models: Vec<_> = models
+        .iter()
+        .enumerate()
+        .map(|(priority, key)| json!({"key": key, "priority": priority}))
+        .collect();
+    let response = client
+        .post("/api/v1/management/pipelines")
+        .json(&json!({
+            "name": name,
+            "pipeline_type": "chat",
+            "plugins": [{"plugin_type": "model-router", "config_data": {"models": models}}],
+            "enabled": enabled
+        }))
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::CREATED);
+}
+
+/// Names and enabled flags of the pipelines routing to each model, by model key.
+fn dependents_tree(dependents: &ProviderDependentsResponse) -> Vec<(String, Vec<(String, bool)>)> {
+    dependents
+        .model_definitions
+        .iter()
+        .map(|model| {
+            let pipelines = model
+                .pipelines
+                .iter()
+                .map(|pipeline| (pipeline.name.clone(), pipeline.enabled))
+                .collect();
+            (model.key.clone(), pipelines)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_provider_dependents() {
+    let (client, _pool, _container) = setup_test_environment().await;
+
+    let primary = create_mock_provider(&client, "Primary").await;
+    let other = create_mock_provider(&client, "Other").await;
+    let spare = create_mock_provider(&client, "Spare").await;
+    create_model(&client, primary.id, "primary-a").await;
+    create_model(&client, primary.id, "primary-b").await;
+    create_model(&client, other.id, "other-a").await;
+    create_model(&client, spare.id, "spare-a").await;
+    create_routing_pipeline(&client, "chat", &["primary-a", "other-a"], true).await;
+    create_routing_pipeline(&client, "batch", &["primary-a", "spare-a"], false).await;
+    create_routing_pipeline(&client, "fallback", &["other-a"], true).await;
+
+    let response = client
+        .get(&format!(
+            "/api/v1/management/providers/{}/dependents",
+            primary.id
+        ))
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::OK);
+    let dependents = response.json::<ProviderDependentsResponse>();
+    assert_eq!(dependents.provider_id, primary.id);
+    assert_eq!(
+        dependents_tree(&dependents),
+        vec![
+            (
+                "primary-a".to_string(),
+                vec![("batch".to_string(), false), ("chat".to_string(), true)]
+            ),
+            ("primary-b".to_string(), vec![]),
+        ]
+    );
+
+    let response = client
+        .get(&format!(
+            "/api/v1/management/providers/{}/dependents",
+            Uuid::new_v4()
+        ))
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::NOT_FOUND);
+
+    // Only the disabled `batch` pipeline routes to the spare provider.
+    let response = client
+        .delete(&format!("/api/v1/management/providers/{}", spare.id))
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_provider_with_enabled_dependents_is_protected() {
+    let (client, _pool, _container) = setup_test_environment().await;
+
+    let primary = create_mock_provider(&client, "Primary").await;
+    create_model(&client, primary.id, "primary-a").await;
+    create_routing_pipeline(&client, "chat", &["primary-a"], true).await;
+    create_routing_pipeline(&client, "batch", &["primary-a"], false).await;
+    let provider_url = format!("/api/v1/management/providers/{}", primary.id);
+
+    let response = client.delete(&provider_url).await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::CONFLICT);
+    let body = response.json::<serde_json::Value>();
+    assert!(
+        body["error"]
+            .as_str()
+            .unwrap()
+            .contains("enabled pipelines: chat.")
+    );
+    let dependents: ProviderDependentsResponse =
+        serde_json::from_value(body["dependents"].clone()).unwrap();
+    assert_eq!(
+        dependents_tree(&dependents),
+        vec![(
+            "primary-a".to_string(),
+            vec![("batch".to_string(), false), ("chat".to_string(), true)]
+        )]
+    );
+
+    let disable = UpdateProviderRequest {
+        name: None,
+        config: None,
+        enabled: Some(false),
+        expected_version: Some(primary.version),
+    };
+    let response = client.put(&provider_url).json(&disable).await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::CONFLICT);
+    assert!(response.json::<serde_json::Value>()["dependents"].is_object());
+    let provider = client.get(&provider_url).await.json::<ProviderResponse>();
+    assert!(provider.enabled);
+
+    // Changes that keep the provider enabled aren't blocked.
+    let rename = UpdateProviderRequest {
+        name: Some("Renamed".to_string()),
+        config: None,
+        enabled: None,
+        expected_version: Some(primary.version),
+    };
+    let response = client.put(&provider_url).json(&rename).await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::OK);
+    let renamed = response.json::<ProviderResponse>();
+
+    let disable = UpdateProviderRequest {
+        expected_version: Some(renamed.version),
+        ..disable
+    };
+    let response = client
+        .put(&format!("{provider_url}?force=true"))
+        .json(&disable)
+        .await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::OK);
+    assert!(!response.json::<ProviderResponse>().enabled);
+
+    let response = client.delete(&format!("{provider_url}?force=true")).await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::NO_CONTENT);
+    let response = client.get(&provider_url).await;
+    assert_eq!(response.status_code(), axum::http::StatusCode::NOT_FOUND);
+}
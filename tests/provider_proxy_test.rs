@@ -0,0 +1,84 @@
+mod common;
+
+use common::{model, provider};
+use hub_lib::models::embeddings::{EmbeddingsInput, EmbeddingsRequest};
+use hub_lib::providers::openai::OpenAIProvider;
+use hub_lib::providers::provider::Provider as _;
+use hub_lib::types::{ModelConfig, Provider, ProviderType};
+use std::collections::HashMap;
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const UPSTREAM_HOST: &str = "upstream.invalid";
+
+fn openai_provider(mut params: HashMap<String, String>) -> OpenAIProvider {
+    params.insert("base_url".to_string(), format!("http://{UPSTREAM_HOST}/v1"));
+    OpenAIProvider::new(&Provider {
+        api_key: "test-key".to_string(),
+        params,
+        ..provider("openai", ProviderType::OpenAI)
+    })
+}
+
+fn model_config() -> ModelConfig {
+    ModelConfig {
+        r#type: "text-embedding-3-small".to_string(),
+        ..model("embedding", "openai")
+    }
+}
+
+fn embeddings_request() -> EmbeddingsRequest {
+    EmbeddingsRequest {
+        model: "text-embedding-3-small".to_string(),
+        input: EmbeddingsInput::Single("hello".to_string()),
+        user: None,
+        encoding_format: None,
+        dimensions: None,
+        extra_body: None,
+    }
+}
+
+#[tokio::test]
+async fn test_provider_requests_go_through_proxy() {
+    let proxy = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(header("host", UPSTREAM_HOST))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "object": "list",
+            "data": [{"object": "embedding", "embedding": [0.1, 0.2], "index": 0}],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 1, "total_tokens": 1}
+        })))
+        .expect(1)
+        .mount(&proxy)
+        .await;
+
+    let provider = openai_provider(HashMap::from([("proxy_url".to_string(), proxy.uri())]));
+
+    let response = provider
+        .embeddings(embeddings_request(), &model_config())
+        .await
+        .expect("request should be served by the proxy");
+    assert_eq!(response.data.len(), 1);
+}
+
+#[tokio::test]
+async fn test_no_proxy_hosts_bypass_proxy() {
+    let proxy = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&proxy)
+        .await;
+
+    let provider = openai_provider(HashMap::from([
+        ("proxy_url".to_string(), proxy.uri()),
+        ("no_proxy".to_string(), UPSTREAM_HOST.to_string()),
+    ]));
+
+    // The upstream host does not resolve, so a direct connection must fail.
+    let result = provider
+        .embeddings(embeddings_request(), &model_config())
+        .await;
+    assert!(result.is_err());
+}
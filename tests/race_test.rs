@@ -0,0 +1,157 @@
+mod common;
+
+use common::{model, params, pipeline, pipeline_router, provider};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{HeaderMap, Request, StatusCode};
+use hub_lib::types::{ModelConfig, PluginConfig, Provider, ProviderType, RaceRouting};
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+
+/// A mock provider and a `mock-model` model on it answering with its own key.
+fn contender(key: &str, provider_params: &[(&str, &str)]) -> (Provider, ModelConfig) {
+    let mut provider = Provider {
+        api_key: String::new(),
+        params: params(provider_params),
+        ..provider(key, ProviderType::Mock)
+    };
+    provider
+        .params
+        .insert("mode".to_string(), "fixed".to_string());
+    let model = ModelConfig {
+        r#type: "mock-model".to_string(),
+        params: params(&[("response", &format!("answer from {key}"))]),
+        ..model(key, key)
+    };
+    (provider, model)
+}
+
+/// Races a slow and a fast mock model, in that order.
+fn hub(slow: &[(&str, &str)], fast: &[(&str, &str)], stagger_ms: u64) -> Router {
+    let (slow_provider, slow_model) = contender("slow", slow);
+    let (fast_provider, fast_model) = contender("fast", fast);
+    pipeline_router(
+        &pipeline(
+            "default",
+            vec![PluginConfig::ModelRouter {
+                models: vec!["slow".to_string(), "fast".to_string()],
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: Some(RaceRouting {
+                    candidates: vec![],
+                    stagger_ms,
+                }),
+            }],
+        ),
+        &[slow_provider, fast_provider],
+        &[slow_model, fast_model],
+    )
+}
+
+async fn chat(app: &Router, stream: bool) -> (StatusCode, HeaderMap, String) {
+    let body = json!({
+        "model": "mock-model",
+        "messages": [{"role": "user", "content": "who is faster?"}],
+        "stream": stream
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, headers, String::from_utf8_lossy(&body).to_string())
+}
+
+fn content(body: &str) -> Value {
+    serde_json::from_str::<Value>(body).unwrap()["choices"][0]["message"]["content"].clone()
+}
+
+#[tokio::test]
+async fn test_fastest_contender_wins() {
+    let app = hub(&[("latency_ms", "500")], &[("latency_ms", "20")], 0);
+
+    let started = Instant::now();
+    let (status, headers, body) = chat(&app, false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content(&body), "answer from fast");
+    assert_eq!(headers["x-hub-model-key"], "fast");
+    assert_eq!(headers["x-hub-routing-decision"], "race");
+    // The slow attempt was cancelled rather than waited for.
+    assert!(started.elapsed() < Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn test_stagger_gives_the_first_candidate_a_head_start() {
+    let app = hub(&[("latency_ms", "100")], &[("latency_ms", "20")], 300);
+
+    let started = Instant::now();
+    let (status, headers, body) = chat(&app, false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content(&body), "answer from slow");
+    assert_eq!(headers["x-hub-model-key"], "slow");
+    assert!(started.elapsed() < Duration::from_millis(300));
+
+    // Past the stagger, the fast contender joins and overtakes.
+    let app = hub(&[("latency_ms", "500")], &[("latency_ms", "20")], 50);
+    let (_, headers, _) = chat(&app, false).await;
+    assert_eq!(headers["x-hub-model-key"], "fast");
+}
+
+#[tokio::test]
+async fn test_failed_contender_loses() {
+    // A failure starts the next contender without waiting out the stagger.
+    let app = hub(
+        &[("fail_every", "1"), ("fail_status", "503")],
+        &[("latency_ms", "20")],
+        300,
+    );
+    let started = Instant::now();
+    let (status, headers, body) = chat(&app, false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content(&body), "answer from fast");
+    assert_eq!(headers["x-hub-model-key"], "fast");
+    assert!(started.elapsed() < Duration::from_millis(300));
+
+    let app = hub(
+        &[("fail_every", "1"), ("fail_status", "429")],
+        &[("fail_every", "1"), ("fail_status", "503")],
+        0,
+    );
+    // Every contender failed, so the last error is returned.
+    let (status, _, _) = chat(&app, false).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_stream_commits_on_first_content() {
+    // The slow contender opens its stream at once but takes its time to say anything.
+    let app = hub(&[("chunk_delay_ms", "500")], &[("latency_ms", "50")], 0);
+
+    let started = Instant::now();
+    let (status, headers, body) = chat(&app, true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["x-hub-model-key"], "fast");
+    let text: String = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+        .filter_map(|chunk| {
+            chunk["choices"][0]["delta"]["content"]
+                .as_str()
+                .map(str::to_string)
+        })
+        .collect();
+    assert_eq!(text, "answer from fast");
+    assert!(started.elapsed() < Duration::from_millis(500));
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::{model, model_router, params, pipeline, provider};
+use futures::{SinkExt, StreamExt};
+use hub_lib::ai_models::registry::ModelRegistry;
+use hub_lib::config::lib::RuntimeSettings;
+use hub_lib::pipelines::pipeline::{PipelineRunner, PipelineScope, create_pipeline_router};
+use hub_lib::providers::registry::ProviderRegistry;
+use hub_lib::services::HubServices;
+use hub_lib::types::{ModelConfig, Provider, ProviderType};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+const MODEL: &str = "gpt-4o-realtime-preview";
+
+/// The handshake URI and authorization header the fake upstream received.
+type SeenHandshake = Arc<Mutex<Option<(String, String)>>>;
+
+/// Stands in for OpenAI: echoes text frames and answers `done` with a usage event.
+async fn start_echo_upstream() -> (String, SeenHandshake) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let seen: SeenHandshake = Arc::default();
+    let handshake = seen.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let handshake = handshake.clone();
+            tokio::spawn(async move {
+                let callback = |request: &Request, response: Response| {
+                    let authorization = request.headers()["authorization"].to_str().unwrap();
+                    *handshake.lock().unwrap() =
+                        Some((request.uri().to_string(), authorization.to_string()));
+                    Ok::<_, ErrorResponse>(response)
+                };
+                let mut socket = tokio_tungstenite::accept_hdr_async(stream, callback)
+                    .await
+                    .unwrap();
+                while let Some(Ok(message)) = socket.next().await {
+                    let reply = match message {
+                        Message::Text(text) if text.as_str() == "done" => json!({
+                            "type": "response.done",
+                            "response": {"usage": {"input_tokens": 3, "output_tokens": 5}}
+                        })
+                        .to_string(),
+                        Message::Text(text) => text.as_str().to_string(),
+                        _ => continue,
+                    };
+                    if socket.send(Message::text(reply)).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    (format!("http://{address}/v1"), seen)
+}
+
+/// Serves a pipeline routing to `base_url`, returning its URL and the services it records
+/// usage in.
+async fn start_hub(base_url: &str, model_params: HashMap<String, String>) -> (String, HubServices) {
+    let provider_registry = ProviderRegistry::new(&[Provider {
+        api_key: "sk-realtime".to_string(),
+        params: params(&[("base_url", base_url)]),
+        ..provider("openai", ProviderType::OpenAI)
+    }])
+    .unwrap();
+    let model_registry = ModelRegistry::new(
+        &[ModelConfig {
+            r#type: MODEL.to_string(),
+            params: model_params,
+            ..model("realtime", "openai")
+        }],
+        Arc::new(provider_registry),
+    )
+    .unwrap();
+    let pipeline = pipeline("default", vec![model_router(&["realtime"])]);
+    let services = HubServices::default();
+    let scope = PipelineScope::new(
+        &pipeline.name,
+        Arc::new(RuntimeSettings::default()),
+        services.clone(),
+    );
+    let runner = PipelineRunner::new(&pipeline, &model_registry, &BTreeMap::new(), scope);
+    let app = create_pipeline_router(&pipeline, Arc::new(runner));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        hub_lib::axum::serve(listener, app).await.unwrap();
+    });
+    (format!("ws://{address}/realtime?model={MODEL}"), services)
+}
+
+#[tokio::test]
+async fn test_realtime_frames_are_proxied_both_ways() {
+    let (base_url, seen) = start_echo_upstream().await;
+    let (hub_url, services) = start_hub(&base_url, HashMap::new()).await;
+
+    let (mut client, _) = tokio_tungstenite::connect_async(hub_url).await.unwrap();
+    client.send(Message::text("hello")).await.unwrap();
+    let echoed = client.next().await.unwrap().unwrap();
+    assert_eq!(echoed, Message::text("hello"));
+
+    client.send(Message::text("done")).await.unwrap();
+    let event = client.next().await.unwrap().unwrap();
+    let event: serde_json::Value = serde_json::from_str(event.to_text().unwrap()).unwrap();
+    assert_eq!(event["type"], "response.done");
+
+    let (uri, authorization) = seen.lock().unwrap().clone().unwrap();
+    assert_eq!(uri, format!("/v1/realtime?model={MODEL}"));
+    assert_eq!(authorization, "Bearer sk-realtime");
+    client.close(None).await.unwrap();
+
+    // Recorded before the event is forwarded, so it's counted by now.
+    let today = chrono::Utc::now().date_naive();
+    let summary = services
+        .usage
+        .summary(today, today, Some("default".to_string()));
+    assert_eq!(summary.totals.requests, 1);
+    assert_eq!(summary.totals.prompt_tokens, 3);
+    assert_eq!(summary.totals.completion_tokens, 5);
+}
+
+#[tokio::test]
+async fn test_realtime_session_is_closed_after_max_duration() {
+    let (base_url, _) = start_echo_upstream().await;
+    let params = HashMap::from([("realtime_max_session_seconds".to_string(), "1".to_string())]);
+    let (hub_url, _) = start_hub(&base_url, params).await;
+
+    let (mut client, _) = tokio_tungstenite::connect_async(hub_url).await.unwrap();
+    let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+        .await
+        .expect("session should be closed by the hub")
+        .unwrap()
+        .unwrap();
+    let Message::Close(Some(frame)) = &message else {
+        panic!("expected a close frame, got {message:?}");
+    };
+    assert_eq!(frame.code, CloseCode::Policy);
+}
+
+#[tokio::test]
+async fn test_realtime_unknown_model_is_rejected() {
+    let (base_url, _) = start_echo_upstream().await;
+    let (hub_url, _) = start_hub(&base_url, HashMap::new()).await;
+    let hub_url = hub_url.replace(MODEL, "gpt-unknown");
+
+    let error = tokio_tungstenite::connect_async(hub_url).await.unwrap_err();
+    let tokio_tungstenite::tungstenite::Error::Http(response) = &error else {
+        panic!("expected an HTTP error, got {error:?}");
+    };
+    assert_eq!(response.status(), 404);
+}
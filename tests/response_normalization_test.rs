@@ -0,0 +1,95 @@
+mod common;
+
+use common::{model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::types::PluginConfig;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn openai_compatible_upstream() -> MockServer {
+    let fixture = std::fs::read_to_string("tests/fixtures/provider_stream_chunks.json").unwrap();
+    let fixtures: Value = serde_json::from_str(&fixture).unwrap();
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&fixtures["openai_compatible"]))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn hub(server: &MockServer, mut plugins: Vec<PluginConfig>) -> Router {
+    plugins.push(model_router(&["deepseek-r1"]));
+    pipeline_router(
+        &pipeline("default", plugins),
+        &[openai("openai", server)],
+        &[model("deepseek-r1", "openai")],
+    )
+}
+
+async fn streamed_chunks(app: Router) -> Vec<Value> {
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "deepseek-r1",
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "stream": true
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec())
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_streamed_chunks_drop_provider_fields_by_default() {
+    let server = openai_compatible_upstream().await;
+
+    let chunks = streamed_chunks(hub(&server, vec![])).await;
+
+    assert_eq!(chunks.len(), 1);
+    let chunk = &chunks[0];
+    assert!(chunk.get("provider").is_none());
+    let choice = &chunk["choices"][0];
+    assert!(choice.get("native_finish_reason").is_none());
+    assert!(choice.get("provider_metadata").is_none());
+    assert!(choice["delta"].get("reasoning").is_none());
+    assert!(choice["delta"].get("reasoning_details").is_none());
+    assert_eq!(choice["delta"]["role"], "assistant");
+}
+
+#[tokio::test]
+async fn test_passthrough_nests_provider_fields() {
+    let server = openai_compatible_upstream().await;
+    let normalization = PluginConfig::ResponseNormalization {
+        passthrough_extra_fields: true,
+    };
+
+    let chunks = streamed_chunks(hub(&server, vec![normalization])).await;
+
+    let choice = &chunks[0]["choices"][0];
+    assert!(choice["delta"].get("reasoning").is_none());
+    assert_eq!(
+        choice["provider_metadata"]["reasoning"],
+        "The user greets me."
+    );
+}
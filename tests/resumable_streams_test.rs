@@ -0,0 +1,141 @@
+mod common;
+
+use common::{model, model_router, params, pipeline, pipeline_router, provider};
+use futures::StreamExt;
+use hub_lib::axum::Router;
+use hub_lib::axum::body::Body;
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::axum::response::Response;
+use hub_lib::types::{ModelConfig, Provider, ProviderType};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+const REPLY: &str = "the quick brown fox jumps over the lazy dog";
+/// One event per word, then one with the finish reason.
+const EVENTS: u64 = 10;
+
+fn hub() -> Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["mock-model"])]),
+        &[Provider {
+            api_key: String::new(),
+            params: params(&[("mode", "fixed"), ("chunk_delay_ms", "30")]),
+            ..provider("mock", ProviderType::Mock)
+        }],
+        &[ModelConfig {
+            params: params(&[("response", REPLY)]),
+            ..model("mock-model", "mock")
+        }],
+    )
+}
+
+async fn send(
+    app: &Router,
+    stream_id: &str,
+    last_event_id: Option<&str>,
+    prompt: &str,
+) -> Response {
+    let mut request = Request::builder()
+        .uri("/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("x-hub-stream-id", stream_id);
+    if let Some(last_event_id) = last_event_id {
+        request = request.header("last-event-id", last_event_id);
+    }
+    let body = json!({
+        "model": "mock-model",
+        "messages": [{"role": "user", "content": prompt}],
+        "stream": true
+    });
+    app.clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap()
+}
+
+/// Reads SSE events as `(id, data)` pairs, stopping after `limit` of them. Dropping the
+/// body afterwards is what a disconnecting client does.
+async fn read_events(response: Response, limit: Option<usize>) -> Vec<(u64, Value)> {
+    let mut body = response.into_body().into_data_stream();
+    let mut text = String::new();
+    let mut events = Vec::new();
+    while limit.is_none_or(|limit| events.len() < limit) {
+        let Some(chunk) = body.next().await else {
+            break;
+        };
+        text.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+        while let Some(end) = text.find("\n\n") {
+            let frame: String = text.drain(..end + 2).collect();
+            let mut id = None;
+            let mut data = None;
+            for line in frame.lines() {
+                if let Some(value) = line.strip_prefix("id: ") {
+                    id = Some(value.parse().unwrap());
+                } else if let Some(value) = line.strip_prefix("data: ") {
+                    data = Some(serde_json::from_str(value).unwrap());
+                }
+            }
+            if let (Some(id), Some(data)) = (id, data) {
+                events.push((id, data));
+            }
+        }
+    }
+    events
+}
+
+fn ids(events: &[(u64, Value)]) -> Vec<u64> {
+    events.iter().map(|(id, _)| *id).collect()
+}
+
+fn content(events: &[(u64, Value)]) -> String {
+    events
+        .iter()
+        .filter_map(|(_, data)| data["choices"][0]["delta"]["content"].as_str())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_reconnect_gets_missed_events_without_duplicates() {
+    let app = hub();
+
+    let first = send(&app, "resume-1", None, "hello").await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert!(first.headers().get("x-hub-stream-resumed").is_none());
+    let received = read_events(first, Some(3)).await;
+    assert_eq!(ids(&received), [1, 2, 3]);
+
+    // The connection dropped after event 3; generation carries on without it.
+    let resumed = send(&app, "resume-1", Some("3"), "hello").await;
+    assert_eq!(resumed.status(), StatusCode::OK);
+    assert_eq!(resumed.headers()["x-hub-stream-resumed"], "true");
+    assert_eq!(resumed.headers()["content-type"], "text/event-stream");
+    let rest = read_events(resumed, None).await;
+    assert_eq!(ids(&rest), (4..=EVENTS).collect::<Vec<_>>());
+
+    let all: Vec<_> = received.into_iter().chain(rest).collect();
+    assert_eq!(content(&all), REPLY);
+    assert_eq!(all.last().unwrap().1["choices"][0]["finish_reason"], "stop");
+
+    // Once finished, the whole stream can still be replayed.
+    let replay = read_events(send(&app, "resume-1", None, "hello").await, None).await;
+    assert_eq!(ids(&replay), (1..=EVENTS).collect::<Vec<_>>());
+    assert_eq!(content(&replay), REPLY);
+}
+
+#[tokio::test]
+async fn test_stream_id_misuse_is_rejected() {
+    let app = hub();
+
+    let started = read_events(send(&app, "resume-2", None, "hello").await, None).await;
+    assert_eq!(started.len() as u64, EVENTS);
+
+    let other_request = send(&app, "resume-2", Some("1"), "something else").await;
+    assert_eq!(other_request.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let unknown = send(&app, "resume-unknown", Some("4"), "hello").await;
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+
+    let invalid = send(&app, "resume-2", Some("latest"), "hello").await;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+}
@@ -13,6 +13,7 @@ async fn test_router_always_available() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -20,14 +21,21 @@ async fn test_router_always_available() {
             r#type: "gpt-4".to_string(),
             provider: "test-provider".to_string(),
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![Pipeline {
             name: "default".to_string(),
             r#type: PipelineType::Chat,
             plugins: vec![PluginConfig::ModelRouter {
                 models: vec!["gpt-4".to_string()],
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: None,
             }],
+            store_artifacts: false,
         }],
+        prompt_templates: Default::default(),
     };
 
     let app_state = Arc::new(AppState::new(config).expect("Failed to create app state"));
@@ -47,6 +55,7 @@ async fn test_configuration_change_detection() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -54,14 +63,21 @@ async fn test_configuration_change_detection() {
             r#type: "gpt-4".to_string(),
             provider: "test-provider".to_string(),
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![Pipeline {
             name: "default".to_string(),
             r#type: PipelineType::Chat,
             plugins: vec![PluginConfig::ModelRouter {
                 models: vec!["gpt-4".to_string()],
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: None,
             }],
+            store_artifacts: false,
         }],
+        prompt_templates: Default::default(),
     };
 
     let app_state =
@@ -91,10 +107,12 @@ async fn test_invalid_configuration_rejected() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![],
         pipelines: vec![],
+        prompt_templates: Default::default(),
     };
 
     let app_state = Arc::new(AppState::new(initial_config).expect("Failed to create app state"));
@@ -106,6 +124,7 @@ async fn test_invalid_configuration_rejected() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -113,8 +132,11 @@ async fn test_invalid_configuration_rejected() {
             r#type: "gpt-4".to_string(),
             provider: "non-existent-provider".to_string(), // Invalid reference
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![],
+        prompt_templates: Default::default(),
     };
 
     // Invalid configuration should be rejected
@@ -134,6 +156,7 @@ async fn test_concurrent_router_access() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -141,14 +164,21 @@ async fn test_concurrent_router_access() {
             r#type: "gpt-4".to_string(),
             provider: "test-provider".to_string(),
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![Pipeline {
             name: "default".to_string(),
             r#type: PipelineType::Chat,
             plugins: vec![PluginConfig::ModelRouter {
                 models: vec!["gpt-4".to_string()],
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: None,
             }],
+            store_artifacts: false,
         }],
+        prompt_templates: Default::default(),
     };
 
     let app_state = Arc::new(AppState::new(config).expect("Failed to create app state"));
@@ -181,6 +211,7 @@ async fn test_empty_configuration_fallback() {
         providers: vec![],
         models: vec![],
         pipelines: vec![],
+        prompt_templates: Default::default(),
     };
 
     let app_state = Arc::new(AppState::new(empty_config).expect("Failed to create app state"));
@@ -199,6 +230,7 @@ async fn test_pipeline_with_failing_tracing_endpoint() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -206,6 +238,8 @@ async fn test_pipeline_with_failing_tracing_endpoint() {
             r#type: "gpt-4".to_string(),
             provider: "test-provider".to_string(),
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![Pipeline {
             name: "traced-pipeline".to_string(),
@@ -217,9 +251,14 @@ async fn test_pipeline_with_failing_tracing_endpoint() {
                 },
                 PluginConfig::ModelRouter {
                     models: vec!["gpt-4".to_string()],
+                    allow_dynamic_models: false,
+                    adaptive: None,
+                    race: None,
                 },
             ],
+            store_artifacts: false,
         }],
+        prompt_templates: Default::default(),
     };
 
     // This should not hang or panic even with an invalid tracing endpoint
@@ -254,6 +293,7 @@ async fn test_tracing_isolation_between_pipelines() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -261,6 +301,8 @@ async fn test_tracing_isolation_between_pipelines() {
             r#type: "gpt-4".to_string(),
             provider: "test-provider".to_string(),
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![
             // Pipeline with tracing
@@ -274,8 +316,12 @@ async fn test_tracing_isolation_between_pipelines() {
                     },
                     PluginConfig::ModelRouter {
                         models: vec!["gpt-4".to_string()],
+                        allow_dynamic_models: false,
+                        adaptive: None,
+                        race: None,
                     },
                 ],
+                store_artifacts: false,
             },
             // Pipeline without tracing
             Pipeline {
@@ -283,9 +329,14 @@ async fn test_tracing_isolation_between_pipelines() {
                 r#type: PipelineType::Chat,
                 plugins: vec![PluginConfig::ModelRouter {
                     models: vec!["gpt-4".to_string()],
+                    allow_dynamic_models: false,
+                    adaptive: None,
+                    race: None,
                 }],
+                store_artifacts: false,
             },
         ],
+        prompt_templates: Default::default(),
     };
 
     // Create app state with the configuration
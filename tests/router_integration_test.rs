@@ -12,6 +12,7 @@ async fn test_router_integration_flow() {
         providers: vec![],
         models: vec![],
         pipelines: vec![],
+        prompt_templates: Default::default(),
     };
 
     let app_state = Arc::new(AppState::new(empty_config).expect("Failed to create app state"));
@@ -26,6 +27,7 @@ async fn test_router_integration_flow() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -33,14 +35,21 @@ async fn test_router_integration_flow() {
             r#type: "gpt-4".to_string(),
             provider: "test-provider".to_string(),
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![Pipeline {
             name: "default".to_string(),
             r#type: PipelineType::Chat,
             plugins: vec![PluginConfig::ModelRouter {
                 models: vec!["gpt-4".to_string()],
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: None,
             }],
+            store_artifacts: false,
         }],
+        prompt_templates: Default::default(),
     };
 
     // Test 3: Configuration update
@@ -67,6 +76,7 @@ async fn test_router_integration_flow() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -74,14 +84,21 @@ async fn test_router_integration_flow() {
             r#type: "gpt-4".to_string(),
             provider: "nonexistent-provider".to_string(), // Invalid provider reference
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![Pipeline {
             name: "default".to_string(),
             r#type: PipelineType::Chat,
             plugins: vec![PluginConfig::ModelRouter {
                 models: vec!["gpt-4".to_string()],
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: None,
             }],
+            store_artifacts: false,
         }],
+        prompt_templates: Default::default(),
     };
 
     // Validation should fail
@@ -90,7 +107,11 @@ async fn test_router_integration_flow() {
 
     let errors = validation_result.unwrap_err();
     assert!(!errors.is_empty());
-    assert!(errors[0].contains("references non-existent provider"));
+    assert!(
+        errors[0]
+            .message
+            .contains("references non-existent provider")
+    );
 
     // Test 7: Multiple pipeline configuration
     let multi_pipeline_config = GatewayConfig {
@@ -99,6 +120,7 @@ async fn test_router_integration_flow() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![
@@ -107,12 +129,16 @@ async fn test_router_integration_flow() {
                 r#type: "gpt-4".to_string(),
                 provider: "test-provider".to_string(),
                 params: Default::default(),
+                enabled: true,
+                deprecation: Default::default(),
             },
             ModelConfig {
                 key: "gpt-3.5-turbo".to_string(),
                 r#type: "gpt-3.5-turbo".to_string(),
                 provider: "test-provider".to_string(),
                 params: Default::default(),
+                enabled: true,
+                deprecation: Default::default(),
             },
         ],
         pipelines: vec![
@@ -121,16 +147,25 @@ async fn test_router_integration_flow() {
                 r#type: PipelineType::Chat,
                 plugins: vec![PluginConfig::ModelRouter {
                     models: vec!["gpt-4".to_string()],
+                    allow_dynamic_models: false,
+                    adaptive: None,
+                    race: None,
                 }],
+                store_artifacts: false,
             },
             Pipeline {
                 name: "fast".to_string(),
                 r#type: PipelineType::Chat,
                 plugins: vec![PluginConfig::ModelRouter {
                     models: vec!["gpt-3.5-turbo".to_string()],
+                    allow_dynamic_models: false,
+                    adaptive: None,
+                    race: None,
                 }],
+                store_artifacts: false,
             },
         ],
+        prompt_templates: Default::default(),
     };
 
     let multi_update_result = app_state.update_config(multi_pipeline_config);
@@ -162,6 +197,7 @@ async fn test_concurrent_configuration_updates() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -169,14 +205,21 @@ async fn test_concurrent_configuration_updates() {
             r#type: "gpt-4".to_string(),
             provider: "test-provider".to_string(),
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![Pipeline {
             name: "default".to_string(),
             r#type: PipelineType::Chat,
             plugins: vec![PluginConfig::ModelRouter {
                 models: vec!["gpt-4".to_string()],
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: None,
             }],
+            store_artifacts: false,
         }],
+        prompt_templates: Default::default(),
     };
 
     let app_state = Arc::new(AppState::new(initial_config).expect("Failed to create app state"));
@@ -193,6 +236,7 @@ async fn test_concurrent_configuration_updates() {
                     key: format!("provider-{}", i),
                     r#type: ProviderType::OpenAI,
                     api_key: "test-key".to_string(),
+                    maintenance_windows: vec![],
                     params: Default::default(),
                 }],
                 models: vec![ModelConfig {
@@ -200,14 +244,21 @@ async fn test_concurrent_configuration_updates() {
                     r#type: "gpt-4".to_string(),
                     provider: format!("provider-{}", i),
                     params: Default::default(),
+                    enabled: true,
+                    deprecation: Default::default(),
                 }],
                 pipelines: vec![Pipeline {
                     name: format!("pipeline-{}", i),
                     r#type: PipelineType::Chat,
                     plugins: vec![PluginConfig::ModelRouter {
                         models: vec![format!("model-{}", i)],
+                        allow_dynamic_models: false,
+                        adaptive: None,
+                        race: None,
                     }],
+                    store_artifacts: false,
                 }],
+                prompt_templates: Default::default(),
             };
 
             // Some tasks update configuration, others access router
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_safety_block_returns_content_filter_error() {
+    unsafe {
+        std::env::set_var("SAFETY_BLOCK_BEHAVIOR", "error");
+    }
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "refusal": "I can't help with that request."
+                },
+                "finish_reason": "content_filter"
+            }],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 0, "total_tokens": 12}
+        })))
+        .mount(&server)
+        .await;
+
+    let app = pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &[openai("openai", &server)],
+        &[model("gpt-4o", "openai")],
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["type"], "content_filter");
+    assert_eq!(body["error"]["code"], "content_filter");
+    assert_eq!(body["error"]["message"], "I can't help with that request.");
+}
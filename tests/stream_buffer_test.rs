@@ -0,0 +1,84 @@
+use futures::StreamExt;
+use futures::stream;
+use hub_lib::models::streaming::ChatCompletionChunk;
+use hub_lib::pipelines::stream_buffer::{ChunkStream, buffer_stream, collect_bounded};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+fn chunk(id: usize) -> ChatCompletionChunk {
+    ChatCompletionChunk {
+        id: format!("chunk-{id}"),
+        choices: vec![],
+        created: 0,
+        model: "gpt-4o".to_string(),
+        service_tier: None,
+        system_fingerprint: None,
+        usage: None,
+    }
+}
+
+/// An upstream that has `total` chunks ready at once, counting how many were read.
+fn fast_upstream(total: usize, pulled: Arc<AtomicUsize>) -> ChunkStream {
+    stream::iter(0..total)
+        .map(move |id| {
+            pulled.fetch_add(1, Ordering::SeqCst);
+            Ok(chunk(id))
+        })
+        .boxed()
+}
+
+#[tokio::test]
+async fn test_slow_consumer_holds_back_upstream() {
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let mut chunks = buffer_stream(fast_upstream(100, pulled.clone()), 4, usize::MAX);
+
+    let mut consumed = 0;
+    while let Some(chunk) = chunks.next().await {
+        assert_eq!(chunk.unwrap().id, format!("chunk-{consumed}"));
+        consumed += 1;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        // The channel's chunks plus the one the reader holds while waiting for room.
+        assert!(pulled.load(Ordering::SeqCst) <= consumed + 4 + 1);
+    }
+    assert_eq!(consumed, 100);
+}
+
+#[tokio::test]
+async fn test_outgrowing_max_bytes_ends_the_stream_with_an_error() {
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let size = serde_json::to_vec(&chunk(0)).unwrap().len();
+    let chunks = buffer_stream(fast_upstream(100, pulled.clone()), 64, size * 3);
+
+    // The reader runs ahead of the idle consumer until it hits the cap.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let items: Vec<_> = chunks.collect().await;
+    assert_eq!(items.len(), 4);
+    assert!(items[..3].iter().all(Result::is_ok));
+    assert!(items[3].is_err());
+    assert_eq!(pulled.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn test_dropping_the_stream_stops_reading_upstream() {
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let mut chunks = buffer_stream(fast_upstream(1000, pulled.clone()), 4, usize::MAX);
+    chunks.next().await.unwrap().unwrap();
+    drop(chunks);
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(pulled.load(Ordering::SeqCst) < 1000);
+}
+
+#[tokio::test]
+async fn test_collect_bounded_stops_at_max_bytes() {
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let size = serde_json::to_vec(&chunk(0)).unwrap().len();
+    let collected = collect_bounded(fast_upstream(10, pulled.clone()), size * 2).await;
+    assert_eq!(collected.len(), 3);
+    assert!(collected[2].is_err());
+
+    let collected = collect_bounded(fast_upstream(10, pulled), usize::MAX).await;
+    assert!(collected.iter().all(Result::is_ok));
+    assert_eq!(collected.len(), 10);
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use common::{model, model_router, params, pipeline, provider};
+use hub_lib::state::AppState;
+use hub_lib::stream_limits::TOO_MANY_STREAMS_CODE;
+use hub_lib::types::{GatewayConfig, General, Provider, ProviderType};
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn hub(global: Option<u32>, per_key: Option<u32>) -> (Router, Arc<AppState>) {
+    let config = GatewayConfig {
+        general: Some(General {
+            max_concurrent_streams_global: global,
+            max_concurrent_streams_per_key: per_key,
+            ..Default::default()
+        }),
+        providers: vec![Provider {
+            api_key: String::new(),
+            params: params(&[("chunk_delay_ms", "20")]),
+            ..provider("mock", ProviderType::Mock)
+        }],
+        models: vec![model("echo", "mock")],
+        pipelines: vec![pipeline("default", vec![model_router(&["echo"])])],
+        ..Default::default()
+    };
+    let state = Arc::new(AppState::new(config).unwrap());
+    (hub_lib::routes::create_router(state.clone()), state)
+}
+
+/// Sends a chat request from the client at `10.0.0.<client>`; streaming responses come back
+/// unread.
+async fn chat(app: &Router, client: u8, stream: bool) -> Response {
+    chat_with_key(app, client, "sk-test", stream).await
+}
+
+async fn chat_with_key(app: &Router, client: u8, key: &str, stream: bool) -> Response {
+    let body = json!({
+        "model": "echo",
+        "messages": [{"role": "user", "content": "one two three four"}],
+        "stream": stream
+    });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/chat/completions")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {key}"))
+        .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, client], 40000))))
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap()
+}
+
+async fn json_body(response: Response) -> Value {
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+fn open_streams(state: &AppState) -> usize {
+    state.stream_limiter().active("").0
+}
+
+#[tokio::test]
+async fn test_streams_over_the_per_key_cap_are_rejected() {
+    let (app, state) = hub(None, Some(2));
+    let first = chat(&app, 1, true).await;
+    let second = chat(&app, 1, true).await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(second.status(), StatusCode::OK);
+
+    let rejected = chat(&app, 1, true).await;
+    assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+    let body = json_body(rejected).await;
+    assert_eq!(body["error"]["code"], TOO_MANY_STREAMS_CODE);
+
+    // Other clients and non-streaming requests aren't affected.
+    assert_eq!(chat(&app, 2, true).await.status(), StatusCode::OK);
+    assert_eq!(chat(&app, 1, false).await.status(), StatusCode::OK);
+
+    // A client disconnecting frees its slot.
+    drop(first);
+    assert_eq!(chat(&app, 1, true).await.status(), StatusCode::OK);
+    drop(second);
+    assert_eq!(open_streams(&state), 0);
+}
+
+#[tokio::test]
+async fn test_streams_over_the_global_cap_are_rejected_until_one_completes() {
+    let (app, state) = hub(Some(2), None);
+    let first = chat(&app, 1, true).await;
+    let _second = chat(&app, 2, true).await;
+    assert_eq!(open_streams(&state), 2);
+
+    let rejected = chat(&app, 3, true).await;
+    assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Reading a stream to the end frees its slot.
+    to_bytes(first.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(open_streams(&state), 1);
+    assert_eq!(chat(&app, 3, true).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_streams_are_counted_without_caps() {
+    let (app, state) = hub(None, None);
+    let stream = chat(&app, 1, true).await;
+    assert_eq!(open_streams(&state), 1);
+
+    to_bytes(stream.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(open_streams(&state), 0);
+}
+
+#[tokio::test]
+async fn test_changing_the_api_key_doesnt_escape_the_per_client_cap() {
+    let (app, _state) = hub(None, Some(1));
+    let _first = chat_with_key(&app, 1, "sk-a", true).await;
+
+    let rejected = chat_with_key(&app, 1, "sk-b", true).await;
+    assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+    let rejected = chat_with_key(&app, 1, "", true).await;
+    assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+}
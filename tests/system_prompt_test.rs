@@ -0,0 +1,155 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use common::{model_router, openai_config, pipeline};
+use hub_lib::types::{GatewayConfig, PluginConfig, SystemPrompt};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn upstream(expected_calls: u64) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 2, "total_tokens": 12}
+        })))
+        .expect(expected_calls)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn hub(server: &MockServer) -> Router {
+    common::hub(GatewayConfig {
+        pipelines: vec![pipeline(
+            "default",
+            vec![
+                PluginConfig::SystemPrompt(SystemPrompt {
+                    system_prompt_template: "support".to_string(),
+                    variables: BTreeMap::from([("brand".to_string(), "Acme".to_string())]),
+                    allow_header_variables: true,
+                }),
+                model_router(&["gpt-4o"]),
+            ],
+        )],
+        prompt_templates: BTreeMap::from([(
+            "support".to_string(),
+            "You are {{brand}}'s support assistant. Answer in {{language}}.".to_string(),
+        )]),
+        ..openai_config(server)
+    })
+}
+
+async fn post(
+    app: &Router,
+    uri: &str,
+    template_vars: Option<&str>,
+    body: Value,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("content-type", "application/json");
+    if let Some(template_vars) = template_vars {
+        request = request.header("x-hub-template-vars", template_vars);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn sent_messages(server: &MockServer) -> Value {
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    body["messages"].clone()
+}
+
+#[tokio::test]
+async fn test_rendered_prompt_is_prepended() {
+    let server = upstream(1).await;
+    let (status, body) = post(
+        &hub(&server),
+        "/api/v1/chat/completions",
+        Some(r#"{"language": "French"}"#),
+        json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "My order is late"}]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    assert_eq!(
+        sent_messages(&server).await,
+        json!([
+            {
+                "role": "system",
+                "content": "You are Acme's support assistant. Answer in French."
+            },
+            {"role": "user", "content": "My order is late"}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_missing_variable_fails_the_request() {
+    let server = upstream(0).await;
+    let (status, body) = post(
+        &hub(&server),
+        "/api/v1/chat/completions",
+        None,
+        json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "My order is late"}]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.contains("variable 'language'"), "{message}");
+}
+
+#[tokio::test]
+async fn test_messages_endpoint_gets_the_prompt_too() {
+    let server = upstream(1).await;
+    let (status, body) = post(
+        &hub(&server),
+        "/api/v1/messages",
+        Some(r#"{"language": "German"}"#),
+        json!({
+            "model": "gpt-4o",
+            "max_tokens": 100,
+            "system": "Keep it short.",
+            "messages": [{"role": "user", "content": "My order is late"}]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let messages = sent_messages(&server).await;
+    assert_eq!(
+        messages[0]["content"],
+        "You are Acme's support assistant. Answer in German."
+    );
+    assert_eq!(messages[1]["content"], "Keep it short.");
+}
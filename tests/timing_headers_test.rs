@@ -0,0 +1,76 @@
+mod common;
+
+use common::{completion, model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::body::Body;
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::timing::{OVERHEAD_HEADER, UPSTREAM_TTFB_HEADER};
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const UPSTREAM_DELAY: Duration = Duration::from_millis(200);
+
+fn header_millis(response: &hub_lib::axum::response::Response, name: &str) -> f64 {
+    response
+        .headers()
+        .get(name)
+        .unwrap_or_else(|| panic!("missing {name} header"))
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_timing_headers_with_delayed_upstream() {
+    unsafe {
+        std::env::set_var("TIMING_HEADERS_ENABLED", "true");
+    }
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_delay(UPSTREAM_DELAY)
+                .set_body_json(completion("hi")),
+        )
+        .mount(&server)
+        .await;
+
+    let app = pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &[openai("openai", &server)],
+        &[model("gpt-4o", "openai")],
+    );
+
+    let started = Instant::now();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "hello"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let total_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let ttfb_ms = header_millis(&response, UPSTREAM_TTFB_HEADER.as_str());
+    let overhead_ms = header_millis(&response, OVERHEAD_HEADER.as_str());
+    assert!(ttfb_ms >= UPSTREAM_DELAY.as_millis() as f64);
+    assert!(ttfb_ms <= total_ms);
+    assert!(overhead_ms >= 0.0);
+    assert!(overhead_ms < total_ms);
+}
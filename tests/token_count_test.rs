@@ -0,0 +1,217 @@
+mod common;
+
+use common::{model, model_router, params, pipeline, pipeline_router, provider};
+use hub_lib::ai_models::instance::ModelInstance;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::models::chat::ChatCompletionRequest;
+use hub_lib::pipelines::token_count::{TokenCounter, estimate_tokens};
+use hub_lib::providers::anthropic::AnthropicProvider;
+use hub_lib::providers::provider::Provider as _;
+use hub_lib::types::{ModelConfig, Provider, ProviderType};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn anthropic_provider(server: &MockServer) -> Provider {
+    Provider {
+        api_key: "sk-ant-test".to_string(),
+        params: params(&[("base_url", &server.uri())]),
+        ..provider("anthropic", ProviderType::Anthropic)
+    }
+}
+
+fn model_config(key: &str, model_params: &[(&str, &str)]) -> ModelConfig {
+    ModelConfig {
+        r#type: "claude-sonnet-4-20250514".to_string(),
+        params: params(model_params),
+        ..model(key, "anthropic")
+    }
+}
+
+fn instance(server: &MockServer, key: &str) -> ModelInstance {
+    ModelInstance {
+        name: key.to_string(),
+        model_type: "claude-sonnet-4-20250514".to_string(),
+        provider: Arc::new(AnthropicProvider::new(&anthropic_provider(server))),
+        config: model_config(key, &[]),
+    }
+}
+
+fn chat_request(content: &str) -> ChatCompletionRequest {
+    serde_json::from_value(json!({
+        "model": "claude",
+        "messages": [
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": content}
+        ],
+        "tools": [{
+            "type": "function",
+            "function": {
+                "name": "get_weather",
+                "description": "Weather for a city",
+                "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+            }
+        }],
+        "temperature": 0.5,
+        "max_tokens": 64
+    }))
+    .unwrap()
+}
+
+async fn mock_count(server: &MockServer, response: ResponseTemplate, expected_calls: u64) {
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .and(header("x-api-key", "sk-ant-test"))
+        .and(header("anthropic-version", "2023-06-01"))
+        .respond_with(response)
+        .expect(expected_calls)
+        .mount(server)
+        .await;
+}
+
+fn chat_pipeline(server: &MockServer, model_params: &[(&str, &str)]) -> hub_lib::axum::Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["claude"])]),
+        &[anthropic_provider(server)],
+        &[model_config("claude", model_params)],
+    )
+}
+
+async fn post(app: hub_lib::axum::Router, uri: &str, body: Value) -> (StatusCode, Value) {
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_count_body_matches_chat_translation() {
+    let server = MockServer::start().await;
+    mock_count(
+        &server,
+        ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 42})),
+        1,
+    )
+    .await;
+    let model = instance(&server, "claude-translation");
+    let payload = chat_request("What's the weather in Paris?");
+
+    assert_eq!(TokenCounter::global().count(&model, &payload).await, 42);
+    // Identical prompts are answered from the cache.
+    assert_eq!(TokenCounter::global().count(&model, &payload).await, 42);
+
+    let received = server.received_requests().await.unwrap();
+    let counted: Value = serde_json::from_slice(&received[0].body).unwrap();
+    let mut expected = model.build_chat_request(payload).await.unwrap().body_json();
+    let expected = expected.as_object_mut().unwrap();
+    for sampling_field in [
+        "max_tokens",
+        "temperature",
+        "top_p",
+        "stream",
+        "service_tier",
+    ] {
+        expected.remove(sampling_field);
+    }
+    assert_eq!(counted, Value::Object(expected.clone()));
+    assert_eq!(counted["system"], "Be brief.");
+    assert_eq!(counted["tools"][0]["name"], "get_weather");
+}
+
+#[tokio::test]
+async fn test_failed_count_falls_back_to_estimate() {
+    let server = MockServer::start().await;
+    mock_count(&server, ResponseTemplate::new(529), 1).await;
+    let model = instance(&server, "claude-fallback");
+    let payload = chat_request("hello");
+
+    let estimate = estimate_tokens(&payload);
+    assert!(estimate > 0);
+    assert_eq!(
+        TokenCounter::global().count(&model, &payload).await,
+        estimate
+    );
+}
+
+#[tokio::test]
+async fn test_count_tokens_route() {
+    let server = MockServer::start().await;
+    mock_count(
+        &server,
+        ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 17})),
+        1,
+    )
+    .await;
+
+    let (status, body) = post(
+        chat_pipeline(&server, &[]),
+        "/messages/count_tokens",
+        json!({
+            "model": "claude",
+            "system": "Be brief.",
+            "messages": [{"role": "user", "content": "route test"}]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({"input_tokens": 17}));
+
+    let received = server.received_requests().await.unwrap();
+    let counted: Value = serde_json::from_slice(&received[0].body).unwrap();
+    assert_eq!(counted["model"], "claude-sonnet-4-20250514");
+    assert_eq!(counted["messages"][0]["content"], "route test");
+    assert!(counted.get("max_tokens").is_none());
+}
+
+#[tokio::test]
+async fn test_context_window_check_rejects_oversized_requests() {
+    let server = MockServer::start().await;
+    mock_count(
+        &server,
+        ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 900})),
+        1,
+    )
+    .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let (status, body) = post(
+        chat_pipeline(
+            &server,
+            &[("context_window", "1000"), ("context_window_check", "true")],
+        ),
+        "/chat/completions",
+        json!({
+            "model": "claude",
+            "messages": [{"role": "user", "content": "too long for the window"}],
+            "max_tokens": 200
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    assert_eq!(body["error"]["param"], "messages");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("context window of 1000 tokens")
+    );
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use common::{model, model_router, openai, pipeline, pipeline_router};
+use hub_lib::axum::Router;
+use hub_lib::axum::body::{Body, to_bytes};
+use hub_lib::axum::http::{Request, StatusCode};
+use hub_lib::types::PluginConfig;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn chunk(delta: Value, finish_reason: Option<&str>) -> Value {
+    json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 1,
+        "model": "gpt-4o",
+        "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+    })
+}
+
+/// Text around two tool calls whose arguments arrive in several fragments.
+async fn fragmented_tool_call_upstream() -> MockServer {
+    let server = MockServer::start().await;
+    let chunks = json!([
+        chunk(json!({"role": "assistant", "content": "Checking"}), None),
+        chunk(
+            json!({"tool_calls": [{"index": 0, "id": "call_1", "type": "function",
+                "function": {"name": "get_weather", "arguments": ""}}]}),
+            None,
+        ),
+        chunk(
+            json!({"tool_calls": [{"index": 0, "function": {"arguments": "{\"city\""}}]}),
+            None
+        ),
+        chunk(json!({"content": " now."}), None),
+        chunk(
+            json!({"tool_calls": [{"index": 0, "function": {"arguments": ":\"Paris\"}"}}]}),
+            None,
+        ),
+        chunk(
+            json!({"tool_calls": [{"index": 1, "id": "call_2", "type": "function",
+                "function": {"name": "get_time", "arguments": "{\"tz\":"}}]}),
+            None,
+        ),
+        chunk(
+            json!({"tool_calls": [{"index": 1, "function": {"arguments": "\"CET\"}"}}]}),
+            None
+        ),
+        chunk(json!({}), Some("tool_calls")),
+    ]);
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chunks))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn hub(server: &MockServer, mut plugins: Vec<PluginConfig>) -> Router {
+    plugins.push(model_router(&["gpt-4o"]));
+    pipeline_router(
+        &pipeline("default", plugins),
+        &[openai("openai", server)],
+        &[model("gpt-4o", "openai")],
+    )
+}
+
+async fn stream(app: Router, aggregate_header: Option<&str>) -> (StatusCode, Vec<Value>) {
+    let mut request = Request::builder()
+        .uri("/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json");
+    if let Some(value) = aggregate_header {
+        request = request.header("x-hub-aggregate-tool-calls", value);
+    }
+    let response = app
+        .oneshot(
+            request
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "messages": [{"role": "user", "content": "Weather and time in Paris?"}],
+                        "stream": true
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let chunks = String::from_utf8(body.to_vec())
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    (status, chunks)
+}
+
+fn tool_call_deltas(chunks: &[Value]) -> Vec<&Value> {
+    chunks
+        .iter()
+        .filter_map(|chunk| chunk["choices"][0]["delta"]["tool_calls"].as_array())
+        .flatten()
+        .collect()
+}
+
+#[tokio::test]
+async fn test_tool_call_fragments_are_forwarded_by_default() {
+    let server = fragmented_tool_call_upstream().await;
+
+    let (status, chunks) = stream(hub(&server, vec![]), None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(chunks.len(), 8);
+    assert_eq!(tool_call_deltas(&chunks).len(), 5);
+}
+
+#[tokio::test]
+async fn test_header_aggregates_tool_calls() {
+    let server = fragmented_tool_call_upstream().await;
+
+    let (status, chunks) = stream(hub(&server, vec![]), Some("true")).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let text: Vec<&str> = chunks
+        .iter()
+        .filter_map(|chunk| chunk["choices"][0]["delta"]["content"].as_str())
+        .collect();
+    assert_eq!(text, ["Checking", " now."]);
+    let calls = tool_call_deltas(&chunks);
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0]["index"], 0);
+    assert_eq!(calls[0]["id"], "call_1");
+    assert_eq!(calls[0]["function"]["name"], "get_weather");
+    assert_eq!(calls[1]["index"], 1);
+    assert_eq!(calls[1]["id"], "call_2");
+    assert_eq!(calls[1]["function"]["name"], "get_time");
+    let arguments: Vec<Value> = calls
+        .iter()
+        .map(|call| serde_json::from_str(call["function"]["arguments"].as_str().unwrap()).unwrap())
+        .collect();
+    assert_eq!(arguments, [json!({"city": "Paris"}), json!({"tz": "CET"})]);
+    let last = chunks.last().unwrap();
+    assert_eq!(last["choices"][0]["finish_reason"], "tool_calls");
+}
+
+#[tokio::test]
+async fn test_pipeline_config_aggregates_and_header_opts_out() {
+    let server = fragmented_tool_call_upstream().await;
+    let stream_options = PluginConfig::StreamOptions {
+        aggregate_tool_calls: true,
+    };
+    let app = hub(&server, vec![stream_options]);
+
+    let (_, aggregated) = stream(app.clone(), None).await;
+    let (_, fragments) = stream(app, Some("false")).await;
+
+    assert_eq!(tool_call_deltas(&aggregated).len(), 2);
+    assert_eq!(tool_call_deltas(&fragments).len(), 5);
+}
+
+#[tokio::test]
+async fn test_invalid_header_is_rejected() {
+    let server = fragmented_tool_call_upstream().await;
+
+    let (status, _) = stream(hub(&server, vec![]), Some("sometimes")).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
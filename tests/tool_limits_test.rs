@@ -0,0 +1,237 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode};
+use common::{model_router, openai_config, pipeline};
+use hub_lib::types::{GatewayConfig, PluginConfig, ToolLimits};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn upstream(expected_calls: u64) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "It's sunny."},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 3, "total_tokens": 13}
+        })))
+        .expect(expected_calls)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn hub(server: &MockServer) -> Router {
+    let tool_limits = ToolLimits {
+        max_tools: Some(2),
+        max_tools_bytes: Some(1024),
+        max_tool_schema_depth: Some(3),
+        tool_filter: Some(vec!["get_*".to_string(), "lookup_order".to_string()]),
+    };
+    common::hub(GatewayConfig {
+        pipelines: vec![pipeline(
+            "default",
+            vec![
+                PluginConfig::ToolLimits(tool_limits),
+                model_router(&["gpt-4o"]),
+            ],
+        )],
+        ..openai_config(server)
+    })
+}
+
+fn tool(name: &str, parameters: Value) -> Value {
+    json!({"type": "function", "function": {"name": name, "parameters": parameters}})
+}
+
+fn flat_tool(name: &str) -> Value {
+    tool(
+        name,
+        json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+    )
+}
+
+async fn post(app: &Router, uri: &str, body: Value) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn chat(app: &Router, tools: Value, tool_choice: Value) -> (StatusCode, Value) {
+    post(
+        app,
+        "/api/v1/chat/completions",
+        json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "What's the weather in Paris?"}],
+            "tools": tools,
+            "tool_choice": tool_choice
+        }),
+    )
+    .await
+}
+
+async fn sent_tools(server: &MockServer) -> Vec<String> {
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    body["tools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|tool| tool["function"]["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_tool_filter_drops_tools_before_dispatch() {
+    let server = upstream(1).await;
+    let tools = json!([
+        flat_tool("get_weather"),
+        flat_tool("delete_user"),
+        flat_tool("lookup_order"),
+        flat_tool("run_shell")
+    ]);
+
+    // Four tools are over `max_tools`, but only two survive the filter.
+    let (status, body) = chat(&hub(&server), tools, json!("auto")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(sent_tools(&server).await, ["get_weather", "lookup_order"]);
+}
+
+#[tokio::test]
+async fn test_filtering_out_every_tool_drops_tool_choice() {
+    let server = upstream(1).await;
+
+    let (status, body) = chat(
+        &hub(&server),
+        json!([flat_tool("run_shell")]),
+        json!("auto"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let requests = server.received_requests().await.unwrap();
+    let sent: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert!(sent.get("tools").is_none(), "{sent}");
+    assert!(sent.get("tool_choice").is_none(), "{sent}");
+}
+
+#[tokio::test]
+async fn test_tool_choice_naming_a_filtered_out_tool_is_rejected() {
+    let server = upstream(0).await;
+    let app = hub(&server);
+    let tool_choice = json!({"type": "function", "function": {"name": "delete_user"}});
+
+    let tools = json!([flat_tool("get_weather"), flat_tool("delete_user")]);
+    let (status, body) = chat(&app, tools, tool_choice).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["param"], "tool_choice");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("'delete_user'"),
+        "{body}"
+    );
+
+    let (status, body) = chat(&app, json!([flat_tool("run_shell")]), json!("required")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["param"], "tool_choice");
+
+    // The Anthropic-compatible endpoint goes through the same checks.
+    let (status, body) = post(
+        &app,
+        "/api/v1/messages",
+        json!({
+            "model": "gpt-4o",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Delete user 42"}],
+            "tools": [{"name": "delete_user", "input_schema": {"type": "object"}}],
+            "tool_choice": {"type": "tool", "name": "delete_user"}
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["param"], "tool_choice");
+}
+
+#[tokio::test]
+async fn test_max_tools_names_the_first_tool_over_the_limit() {
+    let server = upstream(0).await;
+    let tools = json!([
+        flat_tool("get_weather"),
+        flat_tool("get_forecast"),
+        flat_tool("get_alerts")
+    ]);
+
+    let (status, body) = chat(&hub(&server), tools, json!("auto")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["param"], "tools");
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.contains("defines 3 tools"), "{message}");
+    assert!(message.contains("'get_alerts'"), "{message}");
+}
+
+#[tokio::test]
+async fn test_max_tools_bytes_names_the_first_tool_over_the_limit() {
+    let server = upstream(0).await;
+    let mut verbose = flat_tool("get_history");
+    verbose["function"]["description"] = json!("Weather history. ".repeat(64));
+
+    let tools = json!([flat_tool("get_weather"), verbose]);
+    let (status, body) = chat(&hub(&server), tools, json!("auto")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["param"], "tools");
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.contains("more than the 1024"), "{message}");
+    assert!(message.contains("'get_history'"), "{message}");
+}
+
+#[tokio::test]
+async fn test_max_tool_schema_depth_names_the_deep_tool() {
+    let server = upstream(0).await;
+    let deep = tool(
+        "get_route",
+        json!({
+            "type": "object",
+            "properties": {
+                "stops": {
+                    "type": "array",
+                    "items": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }
+            }
+        }),
+    );
+
+    let tools = json!([flat_tool("get_weather"), deep]);
+    let (status, body) = chat(&hub(&server), tools, json!("auto")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["param"], "tools");
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(
+        message.contains("Tool 'get_route' has a parameter schema nested 4 levels deep"),
+        "{message}"
+    );
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use common::{model, model_router, openai, openai_upstream, pipeline, pipeline_router};
+use futures::future::BoxFuture;
+use hub_lib::axum::Router;
+use hub_lib::axum::body::Body;
+use hub_lib::axum::http::{Request, StatusCode};
+use opentelemetry::global;
+use opentelemetry::trace::SpanId;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry_sdk::trace::TracerProvider;
+use serde_json::json;
+use std::sync::{Arc, Mutex, OnceLock};
+use tower::ServiceExt;
+use wiremock::MockServer;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const CALLER_SPAN_ID: &str = "00f067aa0ba902b7";
+
+/// Keeps finished spans in memory.
+#[derive(Debug, Clone, Default)]
+struct InMemoryExporter(Arc<Mutex<Vec<SpanData>>>);
+
+impl SpanExporter for InMemoryExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        self.0.lock().unwrap().extend(batch);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Spans finished by the hub. The tracer provider is process-wide, so tests tell their
+/// spans apart by trace id.
+fn finished_spans() -> Vec<SpanData> {
+    static EXPORTER: OnceLock<InMemoryExporter> = OnceLock::new();
+    let exporter = EXPORTER.get_or_init(|| {
+        let exporter = InMemoryExporter::default();
+        global::set_tracer_provider(
+            TracerProvider::builder()
+                .with_simple_exporter(exporter.clone())
+                .build(),
+        );
+        exporter
+    });
+    exporter.0.lock().unwrap().clone()
+}
+
+fn hub_span(trace_id: &str) -> SpanData {
+    finished_spans()
+        .into_iter()
+        .find(|span| span.span_context.trace_id().to_string() == trace_id)
+        .expect("the hub should have recorded a span in the trace")
+}
+
+fn hub(server: &MockServer) -> Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &[openai("openai", server)],
+        &[model("gpt-4o", "openai")],
+    )
+}
+
+/// Sends a chat request, returning the `x-hub-trace-id` of the response.
+async fn chat(app: Router, traceparent: Option<&str>) -> String {
+    // Installs the tracer provider before the hub starts its span.
+    finished_spans();
+    let mut request = Request::builder()
+        .uri("/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json");
+    if let Some(traceparent) = traceparent {
+        request = request
+            .header("traceparent", traceparent)
+            .header("tracestate", "caller=1");
+    }
+    let body = json!({"model": "gpt-4o", "messages": [{"role": "user", "content": "Hi"}]});
+    let response = app
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.headers()["x-hub-trace-id"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+/// The `traceparent` the upstream received.
+async fn upstream_traceparent(server: &MockServer) -> String {
+    let received = server.received_requests().await.unwrap();
+    received[0]
+        .headers
+        .iter()
+        .find(|(name, _)| name.as_str() == "traceparent")
+        .map(|(_, values)| values.last().as_str().to_string())
+        .expect("the upstream request should carry a traceparent")
+}
+
+#[tokio::test]
+async fn test_incoming_trace_context_is_continued() {
+    let server = openai_upstream("Hello").await;
+    let traceparent = format!("00-{TRACE_ID}-{CALLER_SPAN_ID}-01");
+
+    let trace_id = chat(hub(&server), Some(&traceparent)).await;
+    assert_eq!(trace_id, TRACE_ID);
+
+    let span = hub_span(TRACE_ID);
+    assert_eq!(span.name, "traceloop_hub.chat");
+    assert_eq!(span.parent_span_id.to_string(), CALLER_SPAN_ID);
+    assert_eq!(span.span_context.trace_state().get("caller"), Some("1"));
+
+    // The provider sees the hub's span as its parent.
+    let hub_span_id = span.span_context.span_id();
+    assert_eq!(
+        upstream_traceparent(&server).await,
+        format!("00-{TRACE_ID}-{hub_span_id}-01")
+    );
+}
+
+#[tokio::test]
+async fn test_new_trace_without_trace_context() {
+    let server = openai_upstream("Hello").await;
+
+    let trace_id = chat(hub(&server), None).await;
+    assert_ne!(trace_id, TRACE_ID);
+
+    let span = hub_span(&trace_id);
+    assert_eq!(span.parent_span_id, SpanId::INVALID);
+    let hub_span_id = span.span_context.span_id();
+    assert_eq!(
+        upstream_traceparent(&server).await,
+        format!("00-{trace_id}-{hub_span_id}-01")
+    );
+}
+
+#[tokio::test]
+async fn test_malformed_traceparent_starts_a_new_trace() {
+    let server = openai_upstream("Hello").await;
+
+    let trace_id = chat(hub(&server), Some("00-not-a-trace-01")).await;
+    let span = hub_span(&trace_id);
+    assert_eq!(span.parent_span_id, SpanId::INVALID);
+}
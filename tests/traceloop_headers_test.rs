@@ -0,0 +1,158 @@
+mod common;
+
+use common::{model, model_router, openai, openai_upstream, pipeline, pipeline_router};
+use futures::future::BoxFuture;
+use hub_lib::axum::Router;
+use hub_lib::axum::body::Body;
+use hub_lib::axum::http::{Request, StatusCode};
+use opentelemetry::global;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry_sdk::trace::TracerProvider;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tower::ServiceExt;
+use wiremock::MockServer;
+
+/// Keeps finished spans in memory.
+#[derive(Debug, Clone, Default)]
+struct InMemoryExporter(Arc<Mutex<Vec<SpanData>>>);
+
+impl SpanExporter for InMemoryExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        self.0.lock().unwrap().extend(batch);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Spans finished by the hub. The tracer provider is process-wide, so tests tell their
+/// spans apart by trace id.
+fn finished_spans() -> Vec<SpanData> {
+    static EXPORTER: OnceLock<InMemoryExporter> = OnceLock::new();
+    let exporter = EXPORTER.get_or_init(|| {
+        let exporter = InMemoryExporter::default();
+        global::set_tracer_provider(
+            TracerProvider::builder()
+                .with_simple_exporter(exporter.clone())
+                .build(),
+        );
+        exporter
+    });
+    exporter.0.lock().unwrap().clone()
+}
+
+/// The attributes of the hub's span in `trace_id` whose key starts with `traceloop.`.
+fn traceloop_attributes(trace_id: &str) -> HashMap<String, String> {
+    let span = finished_spans()
+        .into_iter()
+        .find(|span| span.span_context.trace_id().to_string() == trace_id)
+        .expect("the hub should have recorded a span in the trace");
+    span.attributes
+        .iter()
+        .filter(|attribute| attribute.key.as_str().starts_with("traceloop."))
+        .map(|attribute| (attribute.key.to_string(), attribute.value.to_string()))
+        .collect()
+}
+
+fn hub(server: &MockServer) -> Router {
+    pipeline_router(
+        &pipeline("default", vec![model_router(&["gpt-4o"])]),
+        &[openai("openai", server)],
+        &[model("gpt-4o", "openai")],
+    )
+}
+
+/// Sends a chat request with `headers`, returning the `x-hub-trace-id` of the response.
+async fn chat(app: Router, headers: &[(&str, &str)]) -> String {
+    // Installs the tracer provider before the hub starts its span.
+    finished_spans();
+    let mut request = Request::builder()
+        .uri("/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let body = json!({"model": "gpt-4o", "messages": [{"role": "user", "content": "Hi"}]});
+    let response = app
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.headers()["x-hub-trace-id"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+/// The `x-traceloop-*` headers the upstream received.
+async fn upstream_traceloop_headers(server: &MockServer) -> Vec<String> {
+    let received = server.received_requests().await.unwrap();
+    received
+        .last()
+        .unwrap()
+        .headers
+        .iter()
+        .map(|(name, _)| name.as_str().to_string())
+        .filter(|name| name.starts_with("x-traceloop-"))
+        .collect()
+}
+
+const HEADERS: &[(&str, &str)] = &[
+    ("x-traceloop-workflow-name", "checkout"),
+    ("x-traceloop-entity-name", "summarize"),
+    ("x-traceloop-association-property-user_id", "u-1"),
+];
+
+#[tokio::test]
+async fn test_headers_become_span_attributes() {
+    let server = openai_upstream("Hello").await;
+    let trace_id = chat(hub(&server), HEADERS).await;
+
+    let attributes = traceloop_attributes(&trace_id);
+    assert_eq!(attributes.len(), 3);
+    assert_eq!(attributes["traceloop.workflow.name"], "checkout");
+    assert_eq!(attributes["traceloop.entity.name"], "summarize");
+    assert_eq!(
+        attributes["traceloop.association.properties.user_id"],
+        "u-1"
+    );
+}
+
+#[tokio::test]
+async fn test_no_attributes_without_headers() {
+    let server = openai_upstream("Hello").await;
+    let trace_id = chat(hub(&server), &[]).await;
+
+    assert!(traceloop_attributes(&trace_id).is_empty());
+}
+
+// Both cases share a test, since the setting is read from the environment.
+#[tokio::test]
+async fn test_headers_are_forwarded_only_when_enabled() {
+    let server = openai_upstream("Hello").await;
+    unsafe {
+        std::env::set_var("FORWARD_TRACELOOP_HEADERS", "false");
+    }
+    chat(hub(&server), HEADERS).await;
+    assert!(upstream_traceloop_headers(&server).await.is_empty());
+
+    unsafe {
+        std::env::set_var("FORWARD_TRACELOOP_HEADERS", "true");
+    }
+    chat(hub(&server), HEADERS).await;
+    let mut forwarded = upstream_traceloop_headers(&server).await;
+    forwarded.sort();
+    assert_eq!(
+        forwarded,
+        vec![
+            "x-traceloop-association-property-user_id",
+            "x-traceloop-entity-name",
+            "x-traceloop-workflow-name",
+        ]
+    );
+
+    unsafe {
+        std::env::remove_var("FORWARD_TRACELOOP_HEADERS");
+    }
+}
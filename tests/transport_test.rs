@@ -0,0 +1,191 @@
+mod common;
+
+use common::{model, params, provider};
+use hub_lib::models::chat::ChatCompletionRequest;
+use hub_lib::providers::anthropic::AnthropicProvider;
+use hub_lib::providers::azure::AzureProvider;
+use hub_lib::providers::openai::OpenAIProvider;
+use hub_lib::providers::provider::Provider as ProviderTrait;
+use hub_lib::providers::vertexai::VertexAIProvider;
+use hub_lib::reqwest::Url;
+use hub_lib::types::{ModelConfig, Provider, ProviderType};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use wiremock::matchers::{header, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Chat requests every provider is checked against.
+fn fixtures() -> Vec<ChatCompletionRequest> {
+    let mut fixtures: Vec<Value> =
+        serde_json::from_str(include_str!("fixtures/transport_chat_requests.json")).unwrap();
+    fixtures
+        .push(serde_json::from_str(include_str!("fixtures/chat_multi_turn_tools.json")).unwrap());
+    fixtures
+        .into_iter()
+        .map(|fixture| serde_json::from_value(fixture).unwrap())
+        .collect()
+}
+
+fn provider_config(r#type: ProviderType, api_key: &str, pairs: &[(&str, &str)]) -> Provider {
+    Provider {
+        api_key: api_key.to_string(),
+        params: params(pairs),
+        ..provider("upstream", r#type)
+    }
+}
+
+fn model_config(pairs: &[(&str, &str)]) -> ModelConfig {
+    ModelConfig {
+        r#type: "test-model".to_string(),
+        params: params(pairs),
+        ..model("model", "upstream")
+    }
+}
+
+/// The request a provider is expected to send for a fixture: the URL relative to the mock
+/// server, the headers it must carry and the JSON body.
+#[derive(Deserialize)]
+struct Golden {
+    url: String,
+    headers: HashMap<String, String>,
+    body: Value,
+}
+
+/// Asserts that, for every fixture, the provider sends the request recorded for it in
+/// `golden`, and that its dry run through `build_chat_request` shows that same request.
+/// Bodies are compared as JSON, as maps like tool parameters have no fixed key order.
+async fn assert_sends_golden_requests(
+    provider: impl Fn(&MockServer) -> Box<dyn ProviderTrait>,
+    model_config: ModelConfig,
+    golden: &str,
+) {
+    let goldens: Vec<Golden> = serde_json::from_str(golden).unwrap();
+    let fixtures = fixtures();
+    assert_eq!(goldens.len(), fixtures.len());
+
+    for (fixture, golden) in fixtures.into_iter().zip(goldens) {
+        let server = MockServer::start().await;
+        let provider = provider(&server);
+
+        let built = provider
+            .build_chat_request(&fixture, &model_config)
+            .await
+            .unwrap();
+        let built_url = Url::parse(&built.url).unwrap();
+        assert_eq!(relative_url(&built_url), golden.url);
+        for (name, value) in &golden.headers {
+            assert_eq!(built.headers.get(name), Some(value), "header {name}");
+        }
+        assert_eq!(
+            serde_json::from_slice::<Value>(&built.body).unwrap(),
+            golden.body
+        );
+
+        let mut mock = Mock::given(method("POST"));
+        for (name, value) in &golden.headers {
+            mock = mock.and(header(name.as_str(), value.as_str()));
+        }
+        // Only the outbound request matters here, not how the response is parsed.
+        mock.respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let _ = provider.chat_completions(fixture, &model_config).await;
+
+        let received = server.received_requests().await.unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(relative_url(&received[0].url), golden.url);
+        assert_eq!(
+            serde_json::from_slice::<Value>(&received[0].body).unwrap(),
+            golden.body
+        );
+        server.verify().await;
+    }
+}
+
+/// The path and query of `url`.
+fn relative_url(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_openai_sends_golden_requests() {
+    assert_sends_golden_requests(
+        |server| {
+            let base_url = format!("{}/v1", server.uri());
+            let config = provider_config(
+                ProviderType::OpenAI,
+                "sk-openai",
+                &[("base_url", &base_url)],
+            );
+            Box::new(OpenAIProvider::new(&config))
+        },
+        model_config(&[]),
+        include_str!("fixtures/transport_golden/openai.json"),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_azure_sends_golden_requests() {
+    assert_sends_golden_requests(
+        |server| {
+            let config = provider_config(
+                ProviderType::Azure,
+                "azure-key",
+                &[("base_url", &server.uri()), ("api_version", "2024-10-21")],
+            );
+            Box::new(AzureProvider::new(&config))
+        },
+        model_config(&[("deployment", "chat-deployment")]),
+        include_str!("fixtures/transport_golden/azure.json"),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_anthropic_sends_golden_requests() {
+    assert_sends_golden_requests(
+        |server| {
+            let config = provider_config(
+                ProviderType::Anthropic,
+                "sk-ant",
+                &[("base_url", &server.uri())],
+            );
+            Box::new(AnthropicProvider::new(&config))
+        },
+        model_config(&[]),
+        include_str!("fixtures/transport_golden/anthropic.json"),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_vertexai_sends_golden_requests() {
+    assert_sends_golden_requests(
+        |server| {
+            // Test auth sends every request to VERTEXAI_TEST_ENDPOINT. No other test in
+            // this file reads it.
+            unsafe {
+                std::env::set_var("VERTEXAI_TEST_ENDPOINT", server.uri());
+            }
+            let config = provider_config(
+                ProviderType::VertexAI,
+                "",
+                &[
+                    ("project_id", "test-project"),
+                    ("location", "us-central1"),
+                    ("use_test_auth", "true"),
+                ],
+            );
+            Box::new(VertexAIProvider::new(&config))
+        },
+        model_config(&[]),
+        include_str!("fixtures/transport_golden/vertexai.json"),
+    )
+    .await;
+}
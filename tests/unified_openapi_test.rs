@@ -129,6 +129,7 @@ async fn test_router_creation_no_conflicts() {
             key: "test-provider".to_string(),
             r#type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            maintenance_windows: vec![],
             params: Default::default(),
         }],
         models: vec![ModelConfig {
@@ -136,14 +137,21 @@ async fn test_router_creation_no_conflicts() {
             r#type: "gpt-4".to_string(),
             provider: "test-provider".to_string(),
             params: Default::default(),
+            enabled: true,
+            deprecation: Default::default(),
         }],
         pipelines: vec![Pipeline {
             name: "default".to_string(),
             r#type: PipelineType::Chat,
             plugins: vec![PluginConfig::ModelRouter {
                 models: vec!["gpt-4".to_string()],
+                allow_dynamic_models: false,
+                adaptive: None,
+                race: None,
             }],
+            store_artifacts: false,
         }],
+        prompt_templates: Default::default(),
     };
 
     let app_state = Arc::new(AppState::new(config).expect("Failed to create app state"));
@@ -0,0 +1,213 @@
+mod common;
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header};
+use chrono::Utc;
+use common::{model, model_router, openai_config, params, pipeline};
+use hub_lib::management::dto::ApiKeyRole;
+use hub_lib::management::services::api_key_service::{ApiKeyService, StaticApiKey};
+use hub_lib::state::AppState;
+use hub_lib::types::{GatewayConfig, ModelConfig};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// `gpt-4o` answers with usage, streamed or not; `gpt-4o-mini` always fails.
+async fn upstream() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"model": "gpt-4o-mini"})))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "id": "chatcmpl-2",
+                "object": "chat.completion.chunk",
+                "created": 1,
+                "model": "gpt-4o",
+                "choices": [{"index": 0, "delta": {"content": "streamed"}, "finish_reason": null}]
+            },
+            {
+                "id": "chatcmpl-2",
+                "object": "chat.completion.chunk",
+                "created": 1,
+                "model": "gpt-4o",
+                "choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}],
+                "usage": {"prompt_tokens": 7, "completion_tokens": 3, "total_tokens": 10}
+            }
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "hi"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// `key` on the OpenAI provider, with token prices.
+fn priced_model(key: &str) -> ModelConfig {
+    ModelConfig {
+        params: params(&[
+            ("input_cost_per_1k_tokens", "0.01"),
+            ("output_cost_per_1k_tokens", "0.03"),
+        ]),
+        ..model(key, "openai")
+    }
+}
+
+const API_KEY: &str = "read-only-key";
+
+/// The gateway, and the admin routes as the management server serves them.
+fn hub(server: &MockServer) -> (Router, Router) {
+    let config = GatewayConfig {
+        models: vec![priced_model("gpt-4o"), priced_model("gpt-4o-mini")],
+        pipelines: vec![pipeline(
+            "default",
+            vec![model_router(&["gpt-4o", "gpt-4o-mini"])],
+        )],
+        ..openai_config(server)
+    };
+    let state = Arc::new(AppState::new(config).unwrap());
+    let api_key_service =
+        ApiKeyService::with_static_keys(vec![StaticApiKey::new(API_KEY, ApiKeyRole::ReadOnly)]);
+    let admin = hub_lib::management::admin_router(
+        hub_lib::routes::admin_routes(state.clone()),
+        Arc::new(api_key_service),
+    );
+    (hub_lib::routes::create_router(state), admin)
+}
+
+/// Sends a chat request and reads the whole response, so streamed usage is recorded.
+async fn chat(app: &Router, model: &str, stream: bool) -> StatusCode {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/chat/completions")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": model,
+                        "messages": [{"role": "user", "content": "hello"}],
+                        "stream": stream
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    status
+}
+
+async fn usage(admin: &Router, query: &str) -> (StatusCode, Value) {
+    let response = admin
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/admin/usage{query}"))
+                .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_usage_summary_counts_requests_tokens_errors_and_cost() {
+    let server = upstream().await;
+    let (app, admin) = hub(&server);
+
+    for _ in 0..3 {
+        assert_eq!(chat(&app, "gpt-4o", false).await, StatusCode::OK);
+    }
+    assert_eq!(chat(&app, "gpt-4o", true).await, StatusCode::OK);
+    assert!(chat(&app, "gpt-4o-mini", false).await.is_server_error());
+
+    let today = Utc::now().date_naive();
+    let (status, summary) = usage(
+        &admin,
+        &format!("?from={today}&to={today}&pipeline=default"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary["pipeline"], "default");
+    assert_eq!(summary["totals"]["requests"], 5);
+    assert_eq!(summary["totals"]["errors"], 1);
+
+    let records = summary["usage"].as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    let gpt_4o = &records[0];
+    assert_eq!(gpt_4o["model"], "gpt-4o");
+    assert_eq!(gpt_4o["date"], today.to_string());
+    assert_eq!(gpt_4o["requests"], 4);
+    // Three non-streamed requests plus the usage of the streamed one, sent at its end.
+    assert_eq!(gpt_4o["prompt_tokens"], 3 * 10 + 7);
+    assert_eq!(gpt_4o["completion_tokens"], 3 * 5 + 3);
+    assert_eq!(gpt_4o["errors"], 0);
+    let cost = gpt_4o["cost_usd"].as_f64().unwrap();
+    assert!((cost - (37.0 * 0.01 + 18.0 * 0.03) / 1000.0).abs() < 1e-6);
+
+    let gpt_4o_mini = &records[1];
+    assert_eq!(gpt_4o_mini["model"], "gpt-4o-mini");
+    assert_eq!(gpt_4o_mini["requests"], 1);
+    assert_eq!(gpt_4o_mini["errors"], 1);
+
+    let (_, other) = usage(&admin, "?pipeline=other").await;
+    assert_eq!(other["totals"]["requests"], 0);
+    assert_eq!(other["usage"], json!([]));
+}
+
+#[tokio::test]
+async fn test_usage_rejects_inverted_range() {
+    let server = MockServer::start().await;
+    let (_, admin) = hub(&server);
+    let (status, _) = usage(&admin, "?from=2025-02-01&to=2025-01-01").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_usage_requires_an_api_key() {
+    let server = MockServer::start().await;
+    let (app, admin) = hub(&server);
+    let request = || {
+        Request::builder()
+            .uri("/admin/usage")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = admin.oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // The gateway's own port doesn't serve it at all
+    let response = app.oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,134 @@
+use hub_lib::config::validation::CONFIG_ERROR_EXIT_CODE;
+use serde_json::{Value, json};
+use std::io::Write;
+use std::process::{Command, Output};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/validate");
+
+/// Runs `hub validate` with `args`, without the variable the valid fixture references.
+fn validate(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hub"))
+        .arg("validate")
+        .args(args)
+        .env_remove("HUB_VALIDATE_TEST_ORGANIZATION")
+        .output()
+        .unwrap()
+}
+
+fn fixture(name: &str) -> String {
+    format!("{FIXTURES}/{name}")
+}
+
+fn stdout_json(output: &Output) -> Value {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    serde_json::from_str(stdout.trim())
+        .unwrap_or_else(|e| panic!("stdout is not JSON ({e}): {stdout}"))
+}
+
+fn codes(findings: &Value) -> Vec<&str> {
+    findings
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|finding| finding["code"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_valid_config_passes() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hub"))
+        .args(["validate", "--config", &fixture("valid.yaml")])
+        .env("HUB_VALIDATE_TEST_ORGANIZATION", "org-1")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout_json(&output), json!([]));
+}
+
+#[test]
+fn test_invalid_config_exits_with_findings_as_json() {
+    let output = validate(&["--config", &fixture("invalid.yaml")]);
+
+    assert_eq!(output.status.code(), Some(CONFIG_ERROR_EXIT_CODE));
+    assert_eq!(
+        stdout_json(&output),
+        json!([
+            {
+                "code": "unknown_provider",
+                "path": "models[gpt-4o].provider",
+                "message": "Model 'gpt-4o' references non-existent provider 'azure'.",
+                "severity": "error"
+            },
+            {
+                "code": "invalid_admission_limit",
+                "path": "general.max_in_flight_requests",
+                "message": "general.max_in_flight_requests must be greater than 0.",
+                "severity": "error"
+            }
+        ])
+    );
+}
+
+#[test]
+fn test_unset_variables_fail_unless_no_env() {
+    let output = validate(&["--config", &fixture("valid.yaml")]);
+    assert_eq!(output.status.code(), Some(CONFIG_ERROR_EXIT_CODE));
+    let findings = stdout_json(&output);
+    assert_eq!(codes(&findings), vec!["unreadable_config"]);
+    assert!(
+        findings[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("HUB_VALIDATE_TEST_ORGANIZATION")
+    );
+
+    let output = validate(&["--config", &fixture("valid.yaml"), "--no-env"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_no_env_rejects_malformed_references() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let config = std::fs::read_to_string(fixture("valid.yaml"))
+        .unwrap()
+        .replace("${HUB_VALIDATE_TEST_ORGANIZATION}", "${HUB_VALIDATE_TEST");
+    file.write_all(config.as_bytes()).unwrap();
+
+    let output = validate(&["--config", file.path().to_str().unwrap(), "--no-env"]);
+
+    assert_eq!(output.status.code(), Some(CONFIG_ERROR_EXIT_CODE));
+    assert_eq!(codes(&stdout_json(&output)), vec!["unreadable_config"]);
+}
+
+#[test]
+fn test_resolve_secrets_reports_unreadable_api_key_files() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let config = std::fs::read_to_string(fixture("valid.yaml"))
+        .unwrap()
+        .replace(
+            "api_key: sk-test",
+            "api_key_file: /nonexistent/openai-api-key",
+        );
+    file.write_all(config.as_bytes()).unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let output = validate(&["--config", path, "--no-env"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hub"))
+        .args(["validate", "--config", path, "--resolve-secrets"])
+        .env("HUB_VALIDATE_TEST_ORGANIZATION", "org-1")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(CONFIG_ERROR_EXIT_CODE));
+    let findings = stdout_json(&output);
+    assert_eq!(codes(&findings), vec!["provider_unavailable"]);
+    assert_eq!(findings[0]["path"], "providers[openai]");
+    assert!(
+        findings[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("/nonexistent/openai-api-key")
+    );
+}